@@ -104,6 +104,16 @@ impl<T> Sender<T> {
         self.high_priority_tx.send(msg)?;
         Ok(())
     }
+
+    /// Returns the capacity of the low priority queue, `None` if it is unbounded.
+    pub fn low_priority_capacity(&self) -> Option<usize> {
+        self.low_priority_tx.capacity()
+    }
+
+    /// Returns the number of messages waiting in the low priority queue.
+    pub fn num_low_priority_messages(&self) -> usize {
+        self.low_priority_tx.len()
+    }
 }
 
 pub struct Receiver<T> {
@@ -332,4 +342,20 @@ mod tests {
         assert_eq!(rx.try_recv(), Ok(1));
         assert_eq!(rx.try_recv(), Err(RecvError::NoMessageAvailable));
     }
+
+    #[tokio::test]
+    async fn test_low_priority_capacity_and_num_messages() {
+        let (tx, mut rx) = super::channel::<usize>(QueueCapacity::Bounded(3));
+        assert_eq!(tx.low_priority_capacity(), Some(3));
+        tx.send_low_priority(1).await.unwrap();
+        tx.send_low_priority(2).await.unwrap();
+        tx.send_high_priority(3).unwrap();
+        assert_eq!(tx.num_low_priority_messages(), 2);
+        assert_eq!(rx.try_recv(), Ok(3));
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(tx.num_low_priority_messages(), 1);
+
+        let (unbounded_tx, _unbounded_rx) = super::channel::<usize>(QueueCapacity::Unbounded);
+        assert_eq!(unbounded_tx.low_priority_capacity(), None);
+    }
 }
@@ -135,6 +135,20 @@ impl<A: Actor> Mailbox<A> {
         &self.inner.instance_id
     }
 
+    /// Returns the capacity of the queue of the regular messages of the actor.
+    pub fn queue_capacity(&self) -> QueueCapacity {
+        match self.inner.tx.low_priority_capacity() {
+            Some(capacity) => QueueCapacity::Bounded(capacity),
+            None => QueueCapacity::Unbounded,
+        }
+    }
+
+    /// Returns the number of regular messages waiting to be processed by the actor. Commands are
+    /// not counted.
+    pub fn num_pending_messages(&self) -> usize {
+        self.inner.tx.num_low_priority_messages()
+    }
+
     /// Sends a message to the actor owning the associated inbox.
     ///
     /// From an actor context, use the `ActorContext::send_message` method instead.
@@ -238,6 +252,15 @@ impl<A: Actor> Inbox<A> {
     /// Warning this iterator might never be exhausted if there is a living
     /// mailbox associated to it.
     pub fn drain_for_test_typed<M: 'static>(&self) -> Vec<M> {
+        self.drain_typed()
+    }
+
+    /// Returns the pending messages of type `M` in the low priority channel. Pending messages of
+    /// other types are dropped.
+    ///
+    /// This makes it possible to collect the messages sent to a mailbox without running the
+    /// actor, for instance to intercept the messages of an actor meant for another one.
+    pub fn drain_typed<M: 'static>(&self) -> Vec<M> {
         self.rx
             .drain_low_priority()
             .into_iter()
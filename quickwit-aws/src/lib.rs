@@ -17,9 +17,12 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
 use std::time::Duration;
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use async_trait::async_trait;
 use hyper_rustls::HttpsConnectorBuilder;
 use once_cell::sync::OnceCell;
@@ -28,6 +31,7 @@ use rusoto_core::credential::{
 };
 use rusoto_core::{HttpClient, HttpConfig};
 use rusoto_sts::WebIdentityProvider;
+use rustls::{Certificate, ClientConfig, RootCertStore};
 
 pub mod error;
 pub mod region;
@@ -40,12 +44,16 @@ const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
 /// providers).
 const CREDENTIALS_FETCH_TIMEOUT: Duration = Duration::from_secs(5);
 
-/// Returns a hyper http client.
-pub fn get_http_client() -> HttpClient {
+fn get_http_config() -> HttpConfig {
     let mut http_config: HttpConfig = HttpConfig::default();
     // We experience an issue similar to https://github.com/hyperium/hyper/issues/2312.
     // It seems like the setting below solved it.
     http_config.pool_idle_timeout(POOL_IDLE_TIMEOUT);
+    http_config
+}
+
+/// Returns a hyper http client.
+pub fn get_http_client() -> HttpClient {
     let builder = HttpsConnectorBuilder::new();
     let builder = builder.with_native_roots();
     let connector = builder
@@ -58,7 +66,55 @@ pub fn get_http_client() -> HttpClient {
         // multiplexing anyway.)
         .enable_http1()
         .build();
-    HttpClient::from_connector_with_config(connector, http_config)
+    HttpClient::from_connector_with_config(connector, get_http_config())
+}
+
+/// Returns a hyper http client trusting the native root certificates as well as the
+/// certificate authorities listed in the PEM file located at `ca_cert_path`.
+///
+/// This is typically used to reach an on-premise S3-compatible object storage (MinIO, Ceph
+/// RGW, ...) served over TLS with a self-signed certificate.
+pub fn get_http_client_with_ca_cert(ca_cert_path: &Path) -> anyhow::Result<HttpClient> {
+    let mut root_cert_store = RootCertStore::empty();
+    let native_certs = rustls_native_certs::load_native_certs()
+        .context("Failed to load native root certificates.")?;
+    for native_cert in native_certs {
+        // Like `hyper-rustls`, we silently ignore the native certificates we fail to parse.
+        let _ = root_cert_store.add(&Certificate(native_cert.0));
+    }
+    let ca_cert_file = File::open(ca_cert_path).with_context(|| {
+        format!(
+            "Failed to open CA certificate file `{}`.",
+            ca_cert_path.display()
+        )
+    })?;
+    let ca_certs = rustls_pemfile::certs(&mut BufReader::new(ca_cert_file)).with_context(|| {
+        format!(
+            "Failed to parse CA certificate file `{}`.",
+            ca_cert_path.display()
+        )
+    })?;
+    let (num_added_certs, _num_ignored_certs) =
+        root_cert_store.add_parsable_certificates(&ca_certs);
+    if num_added_certs == 0 {
+        bail!(
+            "Failed to find a valid certificate in CA certificate file `{}`.",
+            ca_cert_path.display()
+        );
+    }
+    let tls_config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(root_cert_store)
+        .with_no_client_auth();
+    let connector = HttpsConnectorBuilder::new()
+        .with_tls_config(tls_config)
+        .https_or_http()
+        .enable_http1()
+        .build();
+    Ok(HttpClient::from_connector_with_config(
+        connector,
+        get_http_config(),
+    ))
 }
 
 /// Returns a singleton credentials provider.
@@ -108,3 +164,29 @@ impl ProvideAwsCredentials for ExtendedChainProvider {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_get_http_client_with_ca_cert_errors() {
+        let error = get_http_client_with_ca_cert(Path::new("/does/not/exist.pem"))
+            .err()
+            .unwrap();
+        assert!(error
+            .to_string()
+            .contains("Failed to open CA certificate file"));
+
+        let mut ca_cert_file = tempfile::NamedTempFile::new().unwrap();
+        ca_cert_file.write_all(b"not a certificate").unwrap();
+        let error = get_http_client_with_ca_cert(ca_cert_file.path())
+            .err()
+            .unwrap();
+        assert!(error
+            .to_string()
+            .contains("Failed to find a valid certificate"));
+    }
+}
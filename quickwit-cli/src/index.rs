@@ -32,15 +32,19 @@ use quickwit_actors::{ActorHandle, ObservationType, Universe};
 use quickwit_common::uri::Uri;
 use quickwit_common::GREEN_COLOR;
 use quickwit_config::{
-    IndexConfig, IndexerConfig, SourceConfig, SourceParams, CLI_INGEST_SOURCE_ID,
+    validate_feature_flag, IndexConfig, IndexerConfig, SourceConfig, SourceParams,
+    CLI_INGEST_SOURCE_ID,
 };
 use quickwit_core::{
-    clear_cache_directory, remove_indexing_directory, validate_storage_uri, IndexService,
+    clear_cache_directory, remove_indexing_directory, validate_storage_uri, ImportIndexOptions,
+    IndexService,
 };
+use quickwit_doc_mapper::DocIdConfig;
 use quickwit_indexing::actors::{IndexingPipeline, IndexingService};
 use quickwit_indexing::models::{
-    DetachPipeline, IndexingStatistics, SpawnMergePipeline, SpawnPipeline,
+    DetachPipeline, ForceMergeIndex, IndexingStatistics, SpawnMergePipeline, SpawnPipeline,
 };
+use quickwit_indexing::MergeSettingsOverrides;
 use quickwit_metastore::{quickwit_metastore_uri_resolver, IndexMetadata, SplitState};
 use quickwit_proto::{SearchRequest, SearchResponse};
 use quickwit_search::{single_node_search, SearchResponseRest};
@@ -65,7 +69,9 @@ pub fn build_index_command<'a>() -> Command<'a> {
                 .alias("ls")
                 .args(&[
                     arg!(--"metastore-uri" <METASTORE_URI> "Metastore URI. Override the `metastore_uri` parameter defined in the config file. Defaults to file-backed, but could be Amazon S3 or PostgreSQL.")
-                        .required(false)
+                        .required(false),
+                    arg!(--namespace <NAMESPACE> "Only lists the indexes of this namespace, including the indexes of its nested namespaces.")
+                        .required(false),
                 ])
             )
         .subcommand(
@@ -94,6 +100,8 @@ pub fn build_index_command<'a>() -> Command<'a> {
                         .required(false),
                     arg!(--"keep-cache" "Does not clear local cache directory upon completion.")
                         .required(false),
+                    arg!(--"no-merge" "Does not spawn the merge pipeline. The new splits are merged later by `quickwit index merge` or by the indexers of the index.")
+                        .required(false),
                 ])
             )
         .subcommand(
@@ -142,6 +150,8 @@ pub fn build_index_command<'a>() -> Command<'a> {
                 .about("Merges an index.")
                 .args(&[
                     arg!(--index <INDEX> "ID of the target index"),
+                    arg!(--"target-num-splits" <TARGET_NUM_SPLITS> "Merges all the published splits down to this number of splits, regardless of the maturity rules of the merge policy.")
+                        .required(false),
                     arg!(--"data-dir" <DATA_DIR> "Where data is persisted. Override data-dir defined in config file, default is `./qwdata`.")
                         .env("QW_DATA_DIR")
                         .required(false),
@@ -162,6 +172,114 @@ pub fn build_index_command<'a>() -> Command<'a> {
                         .required(false),
                 ])
             )
+        .subcommand(
+            Command::new("retag")
+                .about("Recomputes the tags of the published splits of an index from its current doc mapping, for instance after adding a tag field, and amends the tags of the splits without rewriting them.")
+                .args(&[
+                    arg!(--index <INDEX> "ID of the target index"),
+                    arg!(--reason <REASON> "Why the tags are recomputed. Recorded along with the amended tags of each split.")
+                        .default_value("Recompute split tags.")
+                        .required(false),
+                    arg!(--"data-dir" <DATA_DIR> "Where data is persisted. Override data-dir defined in config file, default is `./qwdata`.")
+                        .env("QW_DATA_DIR")
+                        .required(false),
+                    arg!(--"dry-run" "Executes the command in dry run mode and only displays the tags changes of the splits.")
+                        .required(false),
+                ])
+            )
+        .subcommand(
+            Command::new("repair")
+                .about("Restores the splits of an index whose files are in the storage of the index but missing from the metastore, for instance after losing the metastore. Create the index again with the same index URI first. The metadata of the splits is rebuilt from their files, but the source checkpoints are not restored.")
+                .args(&[
+                    arg!(--index <INDEX> "ID of the target index"),
+                    arg!(--"data-dir" <DATA_DIR> "Where data is persisted. Override data-dir defined in config file, default is `./qwdata`.")
+                        .env("QW_DATA_DIR")
+                        .required(false),
+                    arg!(--"dry-run" "Executes the command in dry run mode and only displays the splits to restore.")
+                        .required(false),
+                ])
+            )
+        .subcommand(
+            Command::new("preview-merges")
+                .about("Runs the merge policy of an index against its published splits without executing any merge, and displays the planned merges along with their estimated write amplification. The merge settings of the index can be overridden to preview the effect of a change.")
+                .args(&[
+                    arg!(--index <INDEX> "ID of the target index"),
+                    arg!(--"merge-policy" <MERGE_POLICY> "Type of the merge policy to preview instead of the merge policy of the index.")
+                        .required(false),
+                    arg!(--"merge-factor" <MERGE_FACTOR> "Merge factor to preview instead of the merge factor of the index.")
+                        .required(false),
+                    arg!(--"max-merge-factor" <MAX_MERGE_FACTOR> "Max merge factor to preview instead of the max merge factor of the index.")
+                        .required(false),
+                    arg!(--"split-num-docs-target" <SPLIT_NUM_DOCS_TARGET> "Target number of docs per split to preview instead of the target of the index.")
+                        .required(false),
+                    arg!(--"data-dir" <DATA_DIR> "Where data is persisted. Override data-dir defined in config file, default is `./qwdata`.")
+                        .env("QW_DATA_DIR")
+                        .required(false),
+                ])
+            )
+        .subcommand(
+            Command::new("maintenance")
+                .about("Enables or disables the maintenance mode of an index. While in maintenance mode, the index is read-only: its splits cannot be staged, published, or deleted, and the indexing pipelines pause their sources.")
+                .args(&[
+                    arg!(--index <INDEX> "ID of the target index"),
+                    arg!(--disable "Disables the maintenance mode instead of enabling it.")
+                        .required(false),
+                    arg!(--"data-dir" <DATA_DIR> "Where data is persisted. Override data-dir defined in config file, default is `./qwdata`.")
+                        .env("QW_DATA_DIR")
+                        .required(false),
+                ])
+            )
+        .subcommand(
+            Command::new("feature-flag")
+                .about("Enables or disables a feature flag of an index. Feature flags gate new indexing behaviors per index: the indexing pipelines of the index pick up the change without being restarted.")
+                .args(&[
+                    arg!(--index <INDEX> "ID of the target index"),
+                    arg!(--flag <FLAG> "Name of the feature flag, for instance `shadow_indexing`."),
+                    arg!(--disable "Disables the feature flag instead of enabling it.")
+                        .required(false),
+                    arg!(--"data-dir" <DATA_DIR> "Where data is persisted. Override data-dir defined in config file, default is `./qwdata`.")
+                        .env("QW_DATA_DIR")
+                        .required(false),
+                ])
+            )
+        .subcommand(
+            Command::new("export")
+                .about("Exports the definition of an index (doc mapping, indexing and search settings, sources, retention policy) as an index config, which can be imported with `quickwit index import` or passed to `quickwit index create`.")
+                .args(&[
+                    arg!(--index <INDEX> "ID of the target index"),
+                    arg!(--output <OUTPUT_PATH> "Location of the file the index config is written to. Defaults to stdout.")
+                        .required(false),
+                    arg!(--"data-dir" <DATA_DIR> "Where data is persisted. Override data-dir defined in config file, default is `./qwdata`.")
+                        .env("QW_DATA_DIR")
+                        .required(false),
+                ])
+            )
+        .subcommand(
+            Command::new("import")
+                .about("Creates an index from an index config exported with `quickwit index export`, possibly from another cluster.")
+                .args(&[
+                    arg!(--"index-export" <INDEX_EXPORT> "Location of the exported index config file."),
+                    arg!(--"index-id" <INDEX_ID> "ID given to the imported index. Defaults to the ID of the exported index.")
+                        .required(false),
+                    arg!(--"keep-index-uri" "Keeps the URI of the exported index instead of storing the imported index under the default index root URI.")
+                        .required(false),
+                    arg!(--"data-dir" <DATA_DIR> "Where data is persisted. Override data-dir defined in config file, default is `./qwdata`.")
+                        .env("QW_DATA_DIR")
+                        .required(false),
+                    arg!(--overwrite "Overwrites pre-existing index.")
+                        .required(false),
+                ])
+            )
+        .subcommand(
+            Command::new("update")
+                .about("Updates the doc mapping and the search settings of an index from an index config, for instance edited from the output of `quickwit index export`. Only field mappings can be added or removed: the splits indexed before the update are searched as if the added fields were empty.")
+                .args(&[
+                    arg!(--"index-config" <INDEX_CONFIG> "Location of the index config file."),
+                    arg!(--"data-dir" <DATA_DIR> "Where data is persisted. Override data-dir defined in config file, default is `./qwdata`.")
+                        .env("QW_DATA_DIR")
+                        .required(false),
+                ])
+            )
         .subcommand(
             Command::new("clear")
                 .alias("clr")
@@ -217,6 +335,7 @@ pub struct IngestDocsArgs {
     pub data_dir: Option<PathBuf>,
     pub overwrite: bool,
     pub clear_cache: bool,
+    pub merge_pipeline_enabled: bool,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -256,6 +375,74 @@ pub struct GarbageCollectIndexArgs {
 pub struct MergeArgs {
     pub config_uri: Uri,
     pub index_id: String,
+    pub target_num_splits: Option<usize>,
+    pub data_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct MaintenanceIndexArgs {
+    pub config_uri: Uri,
+    pub index_id: String,
+    pub enabled: bool,
+    pub data_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct FeatureFlagIndexArgs {
+    pub config_uri: Uri,
+    pub index_id: String,
+    pub flag: String,
+    pub enabled: bool,
+    pub data_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct RetagIndexArgs {
+    pub config_uri: Uri,
+    pub index_id: String,
+    pub reason: String,
+    pub dry_run: bool,
+    pub data_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct RepairIndexArgs {
+    pub config_uri: Uri,
+    pub index_id: String,
+    pub dry_run: bool,
+    pub data_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct PreviewMergesArgs {
+    pub config_uri: Uri,
+    pub index_id: String,
+    pub overrides: MergeSettingsOverrides,
+    pub data_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct ExportIndexArgs {
+    pub config_uri: Uri,
+    pub index_id: String,
+    pub output_path_opt: Option<PathBuf>,
+    pub data_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct ImportIndexArgs {
+    pub config_uri: Uri,
+    pub index_export_uri: Uri,
+    pub index_id_opt: Option<String>,
+    pub keep_index_uri: bool,
+    pub data_dir: Option<PathBuf>,
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct UpdateIndexArgs {
+    pub config_uri: Uri,
+    pub index_config_uri: Uri,
     pub data_dir: Option<PathBuf>,
 }
 
@@ -263,6 +450,7 @@ pub struct MergeArgs {
 pub struct ListIndexesArgs {
     pub config_uri: Uri,
     pub metastore_uri: Option<Uri>,
+    pub namespace: Option<String>,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -271,17 +459,25 @@ pub enum IndexCliCommand {
     Create(CreateIndexArgs),
     Delete(DeleteIndexArgs),
     Describe(DescribeIndexArgs),
+    Export(ExportIndexArgs),
+    FeatureFlag(FeatureFlagIndexArgs),
     GarbageCollect(GarbageCollectIndexArgs),
+    Import(ImportIndexArgs),
     Ingest(IngestDocsArgs),
     List(ListIndexesArgs),
+    Maintenance(MaintenanceIndexArgs),
     Merge(MergeArgs),
+    PreviewMerges(PreviewMergesArgs),
+    Repair(RepairIndexArgs),
+    Retag(RetagIndexArgs),
     Search(SearchIndexArgs),
+    Update(UpdateIndexArgs),
 }
 
 impl IndexCliCommand {
     pub fn default_log_level(&self) -> Level {
         match self {
-            Self::Search(_) => Level::ERROR,
+            Self::Export(_) | Self::Search(_) => Level::ERROR,
             _ => Level::INFO,
         }
     }
@@ -295,11 +491,19 @@ impl IndexCliCommand {
             "create" => Self::parse_create_args(submatches),
             "delete" => Self::parse_delete_args(submatches),
             "describe" => Self::parse_describe_args(submatches),
+            "export" => Self::parse_export_args(submatches),
+            "feature-flag" => Self::parse_feature_flag_args(submatches),
             "gc" => Self::parse_garbage_collect_args(submatches),
+            "import" => Self::parse_import_args(submatches),
             "ingest" => Self::parse_ingest_args(submatches),
             "list" => Self::parse_list_args(submatches),
+            "maintenance" => Self::parse_maintenance_args(submatches),
             "merge" => Self::parse_merge_args(submatches),
+            "preview-merges" => Self::parse_preview_merges_args(submatches),
+            "repair" => Self::parse_repair_args(submatches),
+            "retag" => Self::parse_retag_args(submatches),
             "search" => Self::parse_search_args(submatches),
+            "update" => Self::parse_update_args(submatches),
             _ => bail!("Index subcommand `{}` is not implemented.", subcommand),
         }
     }
@@ -368,10 +572,12 @@ impl IndexCliCommand {
             .value_of("metastore-uri")
             .map(Uri::try_new)
             .transpose()?;
+        let namespace = matches.value_of("namespace").map(str::to_string);
 
         Ok(Self::List(ListIndexesArgs {
             config_uri,
             metastore_uri,
+            namespace,
         }))
     }
 
@@ -394,6 +600,7 @@ impl IndexCliCommand {
         let data_dir = matches.value_of("data-dir").map(PathBuf::from);
         let overwrite = matches.is_present("overwrite");
         let clear_cache = !matches.is_present("keep-cache");
+        let merge_pipeline_enabled = !matches.is_present("no-merge");
 
         Ok(Self::Ingest(IngestDocsArgs {
             index_id,
@@ -402,6 +609,7 @@ impl IndexCliCommand {
             config_uri,
             data_dir,
             clear_cache,
+            merge_pipeline_enabled,
         }))
     }
 
@@ -465,10 +673,16 @@ impl IndexCliCommand {
             .value_of("config")
             .map(Uri::try_new)
             .expect("`config` is a required arg.")?;
+        let target_num_splits = matches
+            .value_of("target-num-splits")
+            .map(|target_num_splits| target_num_splits.parse::<usize>())
+            .transpose()
+            .context("'target-num-splits' must be a positive integer.")?;
         let data_dir = matches.value_of("data-dir").map(PathBuf::from);
         Ok(Self::Merge(MergeArgs {
             index_id,
             config_uri,
+            target_num_splits,
             data_dir,
         }))
     }
@@ -497,6 +711,183 @@ impl IndexCliCommand {
         }))
     }
 
+    fn parse_maintenance_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let index_id = matches
+            .value_of("index")
+            .expect("`index` is a required arg.")
+            .to_string();
+        let enabled = !matches.is_present("disable");
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::try_new)
+            .expect("`config` is a required arg.")?;
+        let data_dir = matches.value_of("data-dir").map(PathBuf::from);
+        Ok(Self::Maintenance(MaintenanceIndexArgs {
+            index_id,
+            enabled,
+            config_uri,
+            data_dir,
+        }))
+    }
+
+    fn parse_feature_flag_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let index_id = matches
+            .value_of("index")
+            .expect("`index` is a required arg.")
+            .to_string();
+        let flag = matches
+            .value_of("flag")
+            .expect("`flag` is a required arg.")
+            .to_string();
+        validate_feature_flag(&flag)?;
+        let enabled = !matches.is_present("disable");
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::try_new)
+            .expect("`config` is a required arg.")?;
+        let data_dir = matches.value_of("data-dir").map(PathBuf::from);
+        Ok(Self::FeatureFlag(FeatureFlagIndexArgs {
+            index_id,
+            flag,
+            enabled,
+            config_uri,
+            data_dir,
+        }))
+    }
+
+    fn parse_retag_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let index_id = matches
+            .value_of("index")
+            .expect("`index` is a required arg.")
+            .to_string();
+        let reason = matches
+            .value_of("reason")
+            .expect("`reason` should have a default value.")
+            .to_string();
+        let dry_run = matches.is_present("dry-run");
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::try_new)
+            .expect("`config` is a required arg.")?;
+        let data_dir = matches.value_of("data-dir").map(PathBuf::from);
+        Ok(Self::Retag(RetagIndexArgs {
+            index_id,
+            reason,
+            dry_run,
+            config_uri,
+            data_dir,
+        }))
+    }
+
+    fn parse_repair_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let index_id = matches
+            .value_of("index")
+            .expect("`index` is a required arg.")
+            .to_string();
+        let dry_run = matches.is_present("dry-run");
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::try_new)
+            .expect("`config` is a required arg.")?;
+        let data_dir = matches.value_of("data-dir").map(PathBuf::from);
+        Ok(Self::Repair(RepairIndexArgs {
+            index_id,
+            dry_run,
+            config_uri,
+            data_dir,
+        }))
+    }
+
+    fn parse_preview_merges_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let index_id = matches
+            .value_of("index")
+            .expect("`index` is a required arg.")
+            .to_string();
+        let parse_usize_arg = |name: &str| -> anyhow::Result<Option<usize>> {
+            matches
+                .value_of(name)
+                .map(|value| value.parse::<usize>())
+                .transpose()
+                .with_context(|| format!("'{}' must be a positive integer.", name))
+        };
+        let overrides = MergeSettingsOverrides {
+            merge_policy_type: matches.value_of("merge-policy").map(str::to_string),
+            merge_factor: parse_usize_arg("merge-factor")?,
+            max_merge_factor: parse_usize_arg("max-merge-factor")?,
+            split_num_docs_target: parse_usize_arg("split-num-docs-target")?,
+        };
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::try_new)
+            .expect("`config` is a required arg.")?;
+        let data_dir = matches.value_of("data-dir").map(PathBuf::from);
+        Ok(Self::PreviewMerges(PreviewMergesArgs {
+            index_id,
+            overrides,
+            config_uri,
+            data_dir,
+        }))
+    }
+
+    fn parse_export_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let index_id = matches
+            .value_of("index")
+            .expect("`index` is a required arg.")
+            .to_string();
+        let output_path_opt = matches.value_of("output").map(PathBuf::from);
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::try_new)
+            .expect("`config` is a required arg.")?;
+        let data_dir = matches.value_of("data-dir").map(PathBuf::from);
+        Ok(Self::Export(ExportIndexArgs {
+            config_uri,
+            index_id,
+            output_path_opt,
+            data_dir,
+        }))
+    }
+
+    fn parse_import_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::try_new)
+            .expect("`config` is a required arg.")?;
+        let index_export_uri = matches
+            .value_of("index-export")
+            .map(Uri::try_new)
+            .expect("`index-export` is a required arg.")?;
+        let index_id_opt = matches.value_of("index-id").map(String::from);
+        let keep_index_uri = matches.is_present("keep-index-uri");
+        let data_dir = matches.value_of("data-dir").map(PathBuf::from);
+        let overwrite = matches.is_present("overwrite");
+        Ok(Self::Import(ImportIndexArgs {
+            config_uri,
+            index_export_uri,
+            index_id_opt,
+            keep_index_uri,
+            data_dir,
+            overwrite,
+        }))
+    }
+
+    fn parse_update_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::try_new)
+            .expect("`config` is a required arg.")?;
+        let index_config_uri = matches
+            .value_of("index-config")
+            .map(Uri::try_new)
+            .expect("`index-config` is a required arg.")?;
+        let data_dir = matches.value_of("data-dir").map(PathBuf::from);
+        Ok(Self::Update(UpdateIndexArgs {
+            config_uri,
+            index_config_uri,
+            data_dir,
+        }))
+    }
+
     fn parse_delete_args(matches: &ArgMatches) -> anyhow::Result<Self> {
         let index_id = matches
             .value_of("index")
@@ -522,11 +913,19 @@ impl IndexCliCommand {
             Self::Create(args) => create_index_cli(args).await,
             Self::Delete(args) => delete_index_cli(args).await,
             Self::Describe(args) => describe_index_cli(args).await,
+            Self::Export(args) => export_index_cli(args).await,
             Self::GarbageCollect(args) => garbage_collect_index_cli(args).await,
+            Self::Import(args) => import_index_cli(args).await,
             Self::Ingest(args) => ingest_docs_cli(args).await,
             Self::List(args) => list_index_cli(args).await,
+            Self::FeatureFlag(args) => feature_flag_index_cli(args).await,
+            Self::Maintenance(args) => maintenance_index_cli(args).await,
             Self::Merge(args) => merge_cli(args, true).await,
+            Self::PreviewMerges(args) => preview_merges_cli(args).await,
+            Self::Repair(args) => repair_index_cli(args).await,
+            Self::Retag(args) => retag_index_cli(args).await,
             Self::Search(args) => search_index_cli(args).await,
+            Self::Update(args) => update_index_cli(args).await,
         }
     }
 }
@@ -569,7 +968,8 @@ pub async fn create_index_cli(args: CreateIndexArgs) -> anyhow::Result<()> {
         metastore,
         quickwit_storage_uri_resolver().clone(),
         quickwit_config.default_index_root_uri,
-    );
+    )
+    .with_namespace_configs(quickwit_config.namespaces);
     index_service
         .create_index(index_config, args.overwrite)
         .await?;
@@ -577,13 +977,87 @@ pub async fn create_index_cli(args: CreateIndexArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+pub async fn export_index_cli(args: ExportIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "export-index");
+    let quickwit_config = load_quickwit_config(&args.config_uri, args.data_dir).await?;
+    let index_service = IndexService::from_config(quickwit_config).await?;
+    let index_config = index_service.export_index(&args.index_id).await?;
+    let index_config_json = serde_json::to_string_pretty(&index_config)?;
+    if let Some(output_path) = args.output_path_opt {
+        tokio::fs::write(&output_path, index_config_json)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to write index config to `{}`.",
+                    output_path.display()
+                )
+            })?;
+        println!(
+            "Index `{}` successfully exported to `{}`.",
+            args.index_id,
+            output_path.display()
+        );
+    } else {
+        println!("{}", index_config_json);
+    }
+    Ok(())
+}
+
+pub async fn import_index_cli(args: ImportIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "import-index");
+    let quickwit_config = load_quickwit_config(&args.config_uri, args.data_dir).await?;
+    let file_content = load_file(&args.index_export_uri).await?;
+    let index_config = IndexConfig::load(&args.index_export_uri, file_content.as_slice()).await?;
+    let index_service = IndexService::from_config(quickwit_config).await?;
+    let import_options = ImportIndexOptions {
+        index_id_opt: args.index_id_opt,
+        keep_index_uri: args.keep_index_uri,
+        overwrite: args.overwrite,
+    };
+    let index_metadata = index_service
+        .import_index(index_config, import_options)
+        .await?;
+    println!(
+        "Index `{}` successfully imported to `{}`.",
+        index_metadata.index_id, index_metadata.index_uri
+    );
+    Ok(())
+}
+
+pub async fn update_index_cli(args: UpdateIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "update-index");
+    let quickwit_config = load_quickwit_config(&args.config_uri, args.data_dir).await?;
+    let file_content = load_file(&args.index_config_uri).await?;
+    let index_config = IndexConfig::load(&args.index_config_uri, file_content.as_slice()).await?;
+    let index_service = IndexService::from_config(quickwit_config).await?;
+    let index_metadata = index_service
+        .update_index(
+            &index_config.index_id,
+            index_config.doc_mapping,
+            index_config.search_settings,
+        )
+        .await?;
+    println!(
+        "Index `{}` successfully updated to doc mapping version {}. Restart its indexing \
+         pipelines to index new documents with the new doc mapping.",
+        index_metadata.index_id, index_metadata.doc_mapping_version
+    );
+    Ok(())
+}
+
 pub async fn list_index_cli(args: ListIndexesArgs) -> anyhow::Result<()> {
     debug!(args=?args, "list");
     let metastore_uri_resolver = quickwit_metastore_uri_resolver();
     let quickwit_config = load_quickwit_config(&args.config_uri, None).await?;
     let metastore_uri = args.metastore_uri.unwrap_or(quickwit_config.metastore_uri);
     let metastore = metastore_uri_resolver.resolve(&metastore_uri).await?;
-    let indexes = metastore.list_indexes_metadatas().await?;
+    let indexes = if let Some(namespace) = &args.namespace {
+        metastore
+            .list_indexes_metadatas_in_namespace(namespace)
+            .await?
+    } else {
+        metastore.list_indexes_metadatas().await?
+    };
     let index_table = make_list_indexes_table(indexes);
 
     println!();
@@ -650,6 +1124,28 @@ pub async fn describe_index_cli(args: DescribeIndexArgs) -> anyhow::Result<()> {
         "Index URI:".color(GREEN_COLOR),
         index_metadata.index_uri
     );
+    if index_metadata.maintenance_mode {
+        println!(
+            "{:<35} {}",
+            "Maintenance mode:".color(GREEN_COLOR),
+            "enabled (read-only)"
+        );
+    }
+    if !index_metadata.feature_flags.is_empty() {
+        let feature_flags = index_metadata
+            .feature_flags
+            .iter()
+            .map(|(flag, enabled)| {
+                let state = if *enabled { "enabled" } else { "disabled" };
+                format!("{flag} ({state})")
+            })
+            .join(", ");
+        println!(
+            "{:<35} {}",
+            "Feature flags:".color(GREEN_COLOR),
+            feature_flags
+        );
+    }
     println!(
         "{:<35} {}",
         "Number of published splits:".color(GREEN_COLOR),
@@ -665,6 +1161,19 @@ pub async fn describe_index_cli(args: DescribeIndexArgs) -> anyhow::Result<()> {
         "Size of published splits:".color(GREEN_COLOR),
         total_bytes
     );
+    if let Some(doc_id_config) = &index_metadata.doc_mapping.doc_id {
+        let doc_id_source = match doc_id_config {
+            DocIdConfig::Field { field } => format!("extracted from field `{}`", field),
+            DocIdConfig::Ulid => "generated (ulid)".to_string(),
+            DocIdConfig::Uuid => "generated (uuid)".to_string(),
+            DocIdConfig::ContentHash => "generated (content hash)".to_string(),
+        };
+        println!(
+            "{:<35} {}",
+            "Document ID:".color(GREEN_COLOR),
+            doc_id_source
+        );
+    }
     if let Some(timestamp_field_name) = &index_metadata.indexing_settings.timestamp_field {
         println!(
             "{:<35} {}",
@@ -751,6 +1260,10 @@ pub async fn ingest_docs_cli(args: IngestDocsArgs) -> anyhow::Result<()> {
     let source_config = SourceConfig {
         source_id: CLI_INGEST_SOURCE_ID.to_string(),
         num_pipelines: 1,
+        profile: None,
+        rate_limit: None,
+        transforms: Vec::new(),
+        strict_ordering: false,
         source_params,
     };
     run_index_checklist(&config.metastore_uri, &args.index_id, Some(&source_config)).await?;
@@ -786,6 +1299,7 @@ pub async fn ingest_docs_cli(args: IngestDocsArgs) -> anyhow::Result<()> {
             index_id: args.index_id.clone(),
             source_config,
             pipeline_ord: 0,
+            merge_pipeline_enabled: args.merge_pipeline_enabled,
         })
         .await?;
     let pipeline_handle = indexing_server_mailbox
@@ -849,6 +1363,10 @@ pub async fn search_index(args: SearchIndexArgs) -> anyhow::Result<SearchRespons
         sort_order: None,
         sort_by_field: args.sort_by_score.then_some("_score".to_string()),
         aggregation_request: args.aggregation,
+        search_after: None,
+        snippet_pre_tag: None,
+        snippet_post_tag: None,
+        snippet_fragment_size: None,
     };
     let search_response: SearchResponse =
         single_node_search(&search_request, &*metastore, storage_uri_resolver.clone()).await?;
@@ -892,6 +1410,14 @@ pub async fn merge_cli(args: MergeArgs, merge_enabled: bool) -> anyhow::Result<(
             merge_enabled,
         })
         .await?;
+    if let Some(target_num_splits) = args.target_num_splits {
+        indexing_server_mailbox
+            .ask_for_res(ForceMergeIndex {
+                index_id: args.index_id.clone(),
+                target_num_splits,
+            })
+            .await?;
+    }
     let pipeline_handle = indexing_server_mailbox
         .ask_for_res(DetachPipeline { pipeline_id })
         .await?;
@@ -941,6 +1467,43 @@ pub async fn delete_index_cli(args: DeleteIndexArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+pub async fn maintenance_index_cli(args: MaintenanceIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "maintenance-index");
+    let quickwit_config = load_quickwit_config(&args.config_uri, args.data_dir).await?;
+    let metastore = quickwit_metastore_uri_resolver()
+        .resolve(&quickwit_config.metastore_uri)
+        .await?;
+    metastore
+        .set_index_maintenance_mode(&args.index_id, args.enabled)
+        .await?;
+    if args.enabled {
+        println!("Index `{}` is now in maintenance mode.", args.index_id);
+    } else {
+        println!(
+            "Index `{}` is no longer in maintenance mode.",
+            args.index_id
+        );
+    }
+    Ok(())
+}
+
+pub async fn feature_flag_index_cli(args: FeatureFlagIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "feature-flag-index");
+    let quickwit_config = load_quickwit_config(&args.config_uri, args.data_dir).await?;
+    let metastore = quickwit_metastore_uri_resolver()
+        .resolve(&quickwit_config.metastore_uri)
+        .await?;
+    metastore
+        .set_index_feature_flag(&args.index_id, &args.flag, args.enabled)
+        .await?;
+    let state = if args.enabled { "enabled" } else { "disabled" };
+    println!(
+        "Feature flag `{}` is now {} for index `{}`.",
+        args.flag, state, args.index_id
+    );
+    Ok(())
+}
+
 pub async fn garbage_collect_index_cli(args: GarbageCollectIndexArgs) -> anyhow::Result<()> {
     debug!(args=?args, "garbage-collect-index");
     quickwit_telemetry::send_telemetry_event(TelemetryEvent::GarbageCollect).await;
@@ -982,6 +1545,130 @@ pub async fn garbage_collect_index_cli(args: GarbageCollectIndexArgs) -> anyhow:
     Ok(())
 }
 
+pub async fn retag_index_cli(args: RetagIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "retag-index");
+    let quickwit_config = load_quickwit_config(&args.config_uri, args.data_dir).await?;
+    let metastore = quickwit_metastore_uri_resolver()
+        .resolve(&quickwit_config.metastore_uri)
+        .await?;
+    let index_service = IndexService::new(
+        metastore,
+        quickwit_storage_uri_resolver().clone(),
+        quickwit_config.default_index_root_uri,
+    );
+    let split_retaggings = index_service
+        .retag_splits(&args.index_id, &args.reason, args.dry_run)
+        .await?;
+    if split_retaggings.is_empty() {
+        println!("The tags of the splits are up to date.");
+        return Ok(());
+    }
+    if args.dry_run {
+        println!("The tags of the following splits will be amended.");
+    }
+    for split_retagging in &split_retaggings {
+        println!(
+            " - {}: added {:?}, removed {:?}",
+            split_retagging.split_id, split_retagging.added_tags, split_retagging.removed_tags
+        );
+    }
+    if !args.dry_run {
+        println!(
+            "Tags of {} split(s) of index `{}` successfully amended.",
+            split_retaggings.len(),
+            args.index_id
+        );
+    }
+    Ok(())
+}
+
+pub async fn repair_index_cli(args: RepairIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "repair-index");
+    let quickwit_config = load_quickwit_config(&args.config_uri, args.data_dir).await?;
+    let metastore = quickwit_metastore_uri_resolver()
+        .resolve(&quickwit_config.metastore_uri)
+        .await?;
+    let index_service = IndexService::new(
+        metastore,
+        quickwit_storage_uri_resolver().clone(),
+        quickwit_config.default_index_root_uri,
+    );
+    let index_repair = index_service
+        .repair_index(&args.index_id, args.dry_run)
+        .await?;
+    for split_file in &index_repair.unreadable_split_files {
+        println!(
+            "Split file `{}` could not be read and was skipped.",
+            split_file
+        );
+    }
+    if index_repair.restored_splits.is_empty() {
+        println!("The metastore knows of all the splits in the storage of the index.");
+        return Ok(());
+    }
+    if args.dry_run {
+        println!("The following splits will be restored.");
+    }
+    for restored_split in &index_repair.restored_splits {
+        println!(
+            " - {}: {} docs, time range {:?}",
+            restored_split.split_id, restored_split.num_docs, restored_split.time_range
+        );
+    }
+    if !args.dry_run {
+        println!(
+            "{} split(s) of index `{}` successfully restored.",
+            index_repair.restored_splits.len(),
+            args.index_id
+        );
+    }
+    Ok(())
+}
+
+pub async fn preview_merges_cli(args: PreviewMergesArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "preview-merges");
+    let quickwit_config = load_quickwit_config(&args.config_uri, args.data_dir).await?;
+    let metastore = quickwit_metastore_uri_resolver()
+        .resolve(&quickwit_config.metastore_uri)
+        .await?;
+    let index_service = IndexService::new(
+        metastore,
+        quickwit_storage_uri_resolver().clone(),
+        quickwit_config.default_index_root_uri,
+    );
+    let preview = index_service
+        .preview_merges(&args.index_id, &args.overrides)
+        .await?;
+    if preview.operations.is_empty() {
+        println!("The merge policy does not plan any merge.");
+    } else {
+        println!("The merge policy plans the following operations.");
+    }
+    for operation in &preview.operations {
+        println!(
+            " - round {}, partition {}: {:?} of {} split(s) into `{}` ({} docs, {}MB)",
+            operation.round,
+            operation.partition_id,
+            operation.operation_type,
+            operation.split_ids.len(),
+            operation.merge_split_id,
+            operation.num_docs.separate_with_commas(),
+            operation.num_bytes / 1_000_000
+        );
+    }
+    println!(
+        "Number of splits: {} -> {}",
+        preview.num_splits_before, preview.num_splits_after
+    );
+    println!(
+        "Estimated bytes written: {}MB for {}MB of splits (write amplification: {:.2})",
+        preview.num_bytes_merged / 1_000_000,
+        preview.num_bytes / 1_000_000,
+        preview.write_amplification
+    );
+    Ok(())
+}
+
 /// Starts a tokio task that displays the indexing statistics
 /// every once in awhile.
 pub async fn start_statistics_reporting_loop(
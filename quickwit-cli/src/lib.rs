@@ -37,6 +37,7 @@ use tracing::info;
 
 pub mod cli;
 pub mod index;
+pub mod metastore;
 pub mod service;
 pub mod source;
 pub mod split;
@@ -131,7 +131,7 @@ fn runtime_configuration_for_cmd(command: &CliCommand) -> Option<RuntimesConfigu
             }
         }
         CliCommand::Index(_) => Some(RuntimesConfiguration::default()),
-        CliCommand::Split(_) | CliCommand::Source(_) => None,
+        CliCommand::Split(_) | CliCommand::Source(_) | CliCommand::Metastore(_) => None,
     }
 }
 
@@ -212,11 +212,14 @@ mod tests {
 
     use quickwit_cli::cli::{build_cli, CliCommand};
     use quickwit_cli::index::{
-        ClearIndexArgs, CreateIndexArgs, DeleteIndexArgs, DescribeIndexArgs,
-        GarbageCollectIndexArgs, IndexCliCommand, IngestDocsArgs, MergeArgs, SearchIndexArgs,
+        ClearIndexArgs, CreateIndexArgs, DeleteIndexArgs, DescribeIndexArgs, ExportIndexArgs,
+        FeatureFlagIndexArgs, GarbageCollectIndexArgs, ImportIndexArgs, IndexCliCommand,
+        IngestDocsArgs, MaintenanceIndexArgs, MergeArgs, PreviewMergesArgs, RepairIndexArgs,
+        RetagIndexArgs, SearchIndexArgs, UpdateIndexArgs,
     };
     use quickwit_cli::split::{DescribeSplitArgs, ExtractSplitArgs, SplitCliCommand};
     use quickwit_common::uri::Uri;
+    use quickwit_indexing::MergeSettingsOverrides;
 
     #[test]
     fn test_parse_clear_args() {
@@ -334,6 +337,7 @@ mod tests {
                     overwrite: false,
                     data_dir: None,
                     clear_cache: true,
+                    merge_pipeline_enabled: true,
                 })) if &index_id == "wikipedia"
                        && config_uri == Uri::try_new("file:///config.yaml").unwrap()
         ));
@@ -348,6 +352,7 @@ mod tests {
             "/config.yaml",
             "--keep-cache",
             "--overwrite",
+            "--no-merge",
         ])?;
         let command = CliCommand::parse_cli_args(&matches)?;
         assert!(matches!(
@@ -359,7 +364,8 @@ mod tests {
                     input_path_opt: None,
                     overwrite: true,
                     data_dir: None,
-                    clear_cache: false
+                    clear_cache: false,
+                    merge_pipeline_enabled: false,
                 })) if &index_id == "wikipedia"
                         && config_uri == Uri::try_new("file:///config.yaml").unwrap()
         ));
@@ -488,6 +494,183 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_maintenance_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(&[
+            "index",
+            "maintenance",
+            "--index",
+            "wikipedia",
+            "--config",
+            "/config.yaml",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        assert!(matches!(
+            command,
+            CliCommand::Index(IndexCliCommand::Maintenance(MaintenanceIndexArgs {
+                index_id,
+                enabled: true,
+                ..
+            })) if &index_id == "wikipedia"
+        ));
+
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(&[
+            "index",
+            "maintenance",
+            "--index",
+            "wikipedia",
+            "--disable",
+            "--config",
+            "/config.yaml",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        assert!(matches!(
+            command,
+            CliCommand::Index(IndexCliCommand::Maintenance(MaintenanceIndexArgs {
+                index_id,
+                enabled: false,
+                ..
+            })) if &index_id == "wikipedia"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_feature_flag_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(&[
+            "index",
+            "feature-flag",
+            "--index",
+            "wikipedia",
+            "--flag",
+            "shadow_indexing",
+            "--disable",
+            "--config",
+            "/config.yaml",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        assert!(matches!(
+            command,
+            CliCommand::Index(IndexCliCommand::FeatureFlag(FeatureFlagIndexArgs {
+                index_id,
+                flag,
+                enabled: false,
+                ..
+            })) if &index_id == "wikipedia" && &flag == "shadow_indexing"
+        ));
+
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(&[
+            "index",
+            "feature-flag",
+            "--index",
+            "wikipedia",
+            "--flag",
+            "shadow indexing",
+            "--config",
+            "/config.yaml",
+        ])?;
+        assert!(CliCommand::parse_cli_args(&matches).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_export_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(&[
+            "index",
+            "export",
+            "--index",
+            "wikipedia",
+            "--output",
+            "/wikipedia.json",
+            "--config",
+            "/config.yaml",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        assert!(matches!(
+            command,
+            CliCommand::Index(IndexCliCommand::Export(ExportIndexArgs {
+                index_id,
+                output_path_opt: Some(output_path),
+                ..
+            })) if &index_id == "wikipedia" && output_path == PathBuf::from("/wikipedia.json")
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_import_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(&[
+            "index",
+            "import",
+            "--index-export",
+            "/wikipedia.json",
+            "--config",
+            "/config.yaml",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_command = CliCommand::Index(IndexCliCommand::Import(ImportIndexArgs {
+            config_uri: Uri::try_new("file:///config.yaml").unwrap(),
+            index_export_uri: Uri::try_new("file:///wikipedia.json").unwrap(),
+            index_id_opt: None,
+            keep_index_uri: false,
+            data_dir: None,
+            overwrite: false,
+        }));
+        assert_eq!(command, expected_command);
+
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(&[
+            "index",
+            "import",
+            "--index-export",
+            "/wikipedia.json",
+            "--index-id",
+            "wikipedia-staging",
+            "--keep-index-uri",
+            "--overwrite",
+            "--config",
+            "/config.yaml",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        assert!(matches!(
+            command,
+            CliCommand::Index(IndexCliCommand::Import(ImportIndexArgs {
+                index_id_opt: Some(index_id),
+                keep_index_uri: true,
+                overwrite: true,
+                ..
+            })) if &index_id == "wikipedia-staging"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_update_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(&[
+            "index",
+            "update",
+            "--index-config",
+            "/wikipedia.yaml",
+            "--config",
+            "/config.yaml",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_command = CliCommand::Index(IndexCliCommand::Update(UpdateIndexArgs {
+            config_uri: Uri::try_new("file:///config.yaml").unwrap(),
+            index_config_uri: Uri::try_new("file:///wikipedia.yaml").unwrap(),
+            data_dir: None,
+        }));
+        assert_eq!(command, expected_command);
+        Ok(())
+    }
+
     #[test]
     fn test_parse_garbage_collect_args() -> anyhow::Result<()> {
         let app = build_cli().no_binary_name(true);
@@ -537,6 +720,143 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_retag_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(&[
+            "index",
+            "retag",
+            "--index",
+            "wikipedia",
+            "--config",
+            "/config.yaml",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        assert!(matches!(
+            command,
+            CliCommand::Index(IndexCliCommand::Retag(RetagIndexArgs {
+                index_id,
+                reason,
+                dry_run: false,
+                ..
+            })) if &index_id == "wikipedia" && reason == "Recompute split tags."
+        ));
+
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(&[
+            "index",
+            "retag",
+            "--index",
+            "wikipedia",
+            "--reason",
+            "Add tenant tag field.",
+            "--config",
+            "/config.yaml",
+            "--dry-run",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        assert!(matches!(
+            command,
+            CliCommand::Index(IndexCliCommand::Retag(RetagIndexArgs {
+                index_id,
+                reason,
+                dry_run: true,
+                data_dir: None,
+                ..
+            })) if &index_id == "wikipedia" && reason == "Add tenant tag field."
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_repair_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(&[
+            "index",
+            "repair",
+            "--index",
+            "wikipedia",
+            "--config",
+            "/config.yaml",
+            "--dry-run",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        assert!(matches!(
+            command,
+            CliCommand::Index(IndexCliCommand::Repair(RepairIndexArgs {
+                index_id,
+                dry_run: true,
+                data_dir: None,
+                ..
+            })) if &index_id == "wikipedia"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_preview_merges_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(&[
+            "index",
+            "preview-merges",
+            "--index",
+            "wikipedia",
+            "--config",
+            "/config.yaml",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        assert!(matches!(
+            command,
+            CliCommand::Index(IndexCliCommand::PreviewMerges(PreviewMergesArgs {
+                index_id,
+                overrides,
+                ..
+            })) if &index_id == "wikipedia" && overrides == MergeSettingsOverrides::default()
+        ));
+
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(&[
+            "index",
+            "preview-merges",
+            "--index",
+            "wikipedia",
+            "--merge-factor",
+            "4",
+            "--split-num-docs-target",
+            "1000000",
+            "--config",
+            "/config.yaml",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_overrides = MergeSettingsOverrides {
+            merge_factor: Some(4),
+            split_num_docs_target: Some(1_000_000),
+            ..Default::default()
+        };
+        assert!(matches!(
+            command,
+            CliCommand::Index(IndexCliCommand::PreviewMerges(PreviewMergesArgs {
+                overrides,
+                data_dir: None,
+                ..
+            })) if overrides == expected_overrides
+        ));
+
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(&[
+            "index",
+            "preview-merges",
+            "--index",
+            "wikipedia",
+            "--merge-factor",
+            "many",
+            "--config",
+            "/config.yaml",
+        ])?;
+        assert!(CliCommand::parse_cli_args(&matches).is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_parse_merge_args() -> anyhow::Result<()> {
         let app = build_cli().no_binary_name(true);
@@ -553,9 +873,30 @@ mod tests {
             command,
             CliCommand::Index(IndexCliCommand::Merge(MergeArgs {
                 index_id,
+                target_num_splits: None,
                 ..
             })) if &index_id == "wikipedia"
         ));
+
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(&[
+            "index",
+            "merge",
+            "--index",
+            "wikipedia",
+            "--target-num-splits",
+            "3",
+            "--config",
+            "/config.yaml",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        assert!(matches!(
+            command,
+            CliCommand::Index(IndexCliCommand::Merge(MergeArgs {
+                target_num_splits: Some(3),
+                ..
+            }))
+        ));
         Ok(())
     }
 
@@ -0,0 +1,152 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use anyhow::bail;
+use clap::{arg, ArgMatches, Command};
+use quickwit_common::uri::Uri;
+use quickwit_metastore::{migrate_metastore, quickwit_metastore_uri_resolver};
+use tabled::Tabled;
+use tracing::debug;
+
+use crate::make_table;
+
+pub fn build_metastore_command<'a>() -> Command<'a> {
+    Command::new("metastore")
+        .about("Manages metastores.")
+        .subcommand(
+            Command::new("migrate")
+                .about("Copies the indexes, sources, checkpoints, delete tasks, and splits of a metastore to another metastore.")
+                .args(&[
+                    arg!(--from <FROM_METASTORE_URI> "URI of the metastore to copy, for instance `file:///qwdata/indexes` or `postgres://...`."),
+                    arg!(--to <TO_METASTORE_URI> "URI of the metastore to copy to. It must not hold any of the indexes of the metastore to copy."),
+                ])
+            )
+        .arg_required_else_help(true)
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct MigrateMetastoreArgs {
+    pub from_metastore_uri: Uri,
+    pub to_metastore_uri: Uri,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum MetastoreCliCommand {
+    Migrate(MigrateMetastoreArgs),
+}
+
+impl MetastoreCliCommand {
+    pub async fn execute(self) -> anyhow::Result<()> {
+        match self {
+            Self::Migrate(args) => migrate_metastore_cli(args).await,
+        }
+    }
+
+    pub fn parse_cli_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let (subcommand, submatches) = matches
+            .subcommand()
+            .ok_or_else(|| anyhow::anyhow!("Failed to parse metastore subcommand arguments."))?;
+        match subcommand {
+            "migrate" => Self::parse_migrate_args(submatches).map(Self::Migrate),
+            _ => bail!("Metastore subcommand `{}` is not implemented.", subcommand),
+        }
+    }
+
+    fn parse_migrate_args(matches: &ArgMatches) -> anyhow::Result<MigrateMetastoreArgs> {
+        let from_metastore_uri = matches
+            .value_of("from")
+            .map(Uri::try_new)
+            .expect("`from` is a required arg.")?;
+        let to_metastore_uri = matches
+            .value_of("to")
+            .map(Uri::try_new)
+            .expect("`to` is a required arg.")?;
+        Ok(MigrateMetastoreArgs {
+            from_metastore_uri,
+            to_metastore_uri,
+        })
+    }
+}
+
+async fn migrate_metastore_cli(args: MigrateMetastoreArgs) -> anyhow::Result<()> {
+    debug!(args = ?args, "migrate-metastore");
+
+    if args.from_metastore_uri == args.to_metastore_uri {
+        bail!("The metastore to copy and the metastore to copy to must be different.");
+    }
+    let metastore_uri_resolver = quickwit_metastore_uri_resolver();
+    let from_metastore = metastore_uri_resolver
+        .resolve(&args.from_metastore_uri)
+        .await?;
+    let to_metastore = metastore_uri_resolver
+        .resolve(&args.to_metastore_uri)
+        .await?;
+    let migrated_indexes = migrate_metastore(&*from_metastore, &*to_metastore).await?;
+
+    let rows = migrated_indexes
+        .into_iter()
+        .map(|migrated_index| MigratedIndexRow {
+            index_id: migrated_index.index_id,
+            num_splits: migrated_index.num_splits,
+            num_delete_tasks: migrated_index.num_delete_tasks,
+        });
+    println!("{}", make_table("Migrated indexes", rows, false));
+    println!(
+        "The indexes were copied from `{}` to `{}` and verified.",
+        args.from_metastore_uri, args.to_metastore_uri
+    );
+    Ok(())
+}
+
+#[derive(Tabled)]
+struct MigratedIndexRow {
+    #[tabled(rename = "Index ID")]
+    index_id: String,
+    #[tabled(rename = "Num splits")]
+    num_splits: usize,
+    #[tabled(rename = "Num delete tasks")]
+    num_delete_tasks: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{build_cli, CliCommand};
+
+    #[test]
+    fn test_parse_migrate_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(vec![
+            "metastore",
+            "migrate",
+            "--from",
+            "file:///qwdata/indexes",
+            "--to",
+            "postgres://localhost/metastore",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_command =
+            CliCommand::Metastore(MetastoreCliCommand::Migrate(MigrateMetastoreArgs {
+                from_metastore_uri: Uri::try_new("file:///qwdata/indexes")?,
+                to_metastore_uri: Uri::try_new("postgres://localhost/metastore")?,
+            }));
+        assert_eq!(command, expected_command);
+        Ok(())
+    }
+}
@@ -571,6 +571,10 @@ mod tests {
         let sources = vec![SourceConfig {
             source_id: "foo-source".to_string(),
             num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
             source_params: SourceParams::file("path/to/file"),
         }];
         let expected_source = vec![SourceRow {
@@ -634,11 +638,19 @@ mod tests {
             SourceConfig {
                 source_id: "foo-source".to_string(),
                 num_pipelines: 1,
+                profile: None,
+                rate_limit: None,
+                transforms: Vec::new(),
+                strict_ordering: false,
                 source_params: SourceParams::stdin(),
             },
             SourceConfig {
                 source_id: "bar-source".to_string(),
                 num_pipelines: 1,
+                profile: None,
+                rate_limit: None,
+                transforms: Vec::new(),
+                strict_ordering: false,
                 source_params: SourceParams::stdin(),
             },
         ];
@@ -28,7 +28,7 @@ use quickwit_directories::{
     get_hotcache_from_split, read_split_footer, BundleDirectory, HotDirectory,
 };
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
-use quickwit_metastore::{quickwit_metastore_uri_resolver, Split, SplitState};
+use quickwit_metastore::{quickwit_metastore_uri_resolver, ListSplitsQuery, Split, SplitState};
 use quickwit_storage::{quickwit_storage_uri_resolver, BundleStorage, Storage};
 use tabled::{Table, Tabled};
 use time::{format_description, Date, OffsetDateTime, PrimitiveDateTime};
@@ -63,9 +63,25 @@ pub fn build_split_command<'a>() -> Command<'a> {
                         .display_order(6)
                         .required(false)
                         .use_value_delimiter(true),
+                    arg!(--source <SOURCE_ID> "Selects the splits produced by this source.")
+                        .display_order(7)
+                        .required(false),
+                    arg!(--node <NODE_ID> "Selects the splits produced by this node.")
+                        .display_order(8)
+                        .required(false),
+                    arg!(--"pipeline-ord" <PIPELINE_ORD> "Selects the splits produced by the indexing pipeline with this ordinal.")
+                        .display_order(9)
+                        .required(false),
+                    arg!(--"pipeline-generation" <PIPELINE_GENERATION> "Selects the splits produced by this generation of their indexing pipeline.")
+                        .display_order(10)
+                        .required(false),
+                    Arg::new("outdated-config")
+                        .display_order(11)
+                        .long("outdated-config")
+                        .help("Selects the splits built with a doc mapping or indexing settings other than the current ones of the index."),
                     Arg::new("mark-for-deletion")
                         .alias("mark")
-                        .display_order(7)
+                        .display_order(12)
                         .long("mark-for-deletion")
                         .help("Marks the selected splits for deletion.")
                 ])
@@ -120,6 +136,11 @@ pub struct ListSplitArgs {
     pub start_date: Option<OffsetDateTime>,
     pub end_date: Option<OffsetDateTime>,
     pub tags: Option<TagFilterAst>,
+    pub source_id: Option<String>,
+    pub node_id: Option<String>,
+    pub pipeline_ord: Option<usize>,
+    pub pipeline_generation: Option<usize>,
+    pub outdated_config: bool,
     pub mark_for_deletion: bool,
 }
 
@@ -211,6 +232,19 @@ impl SplitCliCommand {
                     .collect(),
             )
         });
+        let source_id = matches.value_of("source").map(String::from);
+        let node_id = matches.value_of("node").map(String::from);
+        let pipeline_ord = matches
+            .value_of("pipeline-ord")
+            .map(|arg| arg.parse::<usize>())
+            .transpose()
+            .context("Failed to parse --pipeline-ord option parameter.")?;
+        let pipeline_generation = matches
+            .value_of("pipeline-generation")
+            .map(|arg| arg.parse::<usize>())
+            .transpose()
+            .context("Failed to parse --pipeline-generation option parameter.")?;
+        let outdated_config = matches.is_present("outdated-config");
         let mark_for_deletion = matches.is_present("mark-for-deletion");
 
         Ok(Self::List(ListSplitArgs {
@@ -221,6 +255,11 @@ impl SplitCliCommand {
             end_date,
             create_date,
             tags,
+            source_id,
+            node_id,
+            pipeline_ord,
+            pipeline_generation,
+            outdated_config,
             mark_for_deletion,
         }))
     }
@@ -318,7 +357,22 @@ async fn list_split_cli(args: ListSplitArgs) -> anyhow::Result<()> {
     let metastore = metastore_uri_resolver
         .resolve(&quickwit_config.metastore_uri)
         .await?;
-    let splits = metastore.list_all_splits(&args.index_id).await?;
+    let current_config_fingerprint_opt = if args.outdated_config {
+        let index_metadata = metastore.index_metadata(&args.index_id).await?;
+        Some(index_metadata.config_fingerprint())
+    } else {
+        None
+    };
+    // The producer and config filters are evaluated by the metastore.
+    let query = ListSplitsQuery {
+        source_id_opt: args.source_id,
+        node_id_opt: args.node_id,
+        pipeline_ord_opt: args.pipeline_ord,
+        pipeline_generation_opt: args.pipeline_generation,
+        current_config_fingerprint_opt,
+        ..Default::default()
+    };
+    let splits = metastore.list_splits_page(&args.index_id, query).await?;
 
     let filtered_splits = filter_splits(
         splits,
@@ -538,6 +592,14 @@ fn make_split_table(splits: &[Split], title: &str) -> Table {
             let updated_at = OffsetDateTime::from_unix_timestamp(split.update_timestamp)
                 .expect("Failed to create `OffsetDateTime` from split update timestamp.");
 
+            let producer = format!(
+                "{}@{}/{}#{}",
+                split.split_metadata.source_id,
+                split.split_metadata.node_id,
+                split.split_metadata.pipeline_ord,
+                split.split_metadata.pipeline_generation
+            );
+
             SplitRow {
                 split_id: split.split_metadata.split_id.clone(),
                 split_state: split.split_state,
@@ -546,6 +608,7 @@ fn make_split_table(splits: &[Split], title: &str) -> Table {
                 created_at,
                 updated_at,
                 time_range,
+                producer,
             }
         })
         .sorted_by(|left, right| left.created_at.cmp(&right.created_at));
@@ -607,6 +670,8 @@ struct SplitRow {
     updated_at: OffsetDateTime,
     #[tabled(rename = "Time range")]
     time_range: String,
+    #[tabled(rename = "Producer")]
+    producer: String,
 }
 
 #[cfg(test)]
@@ -641,6 +706,15 @@ mod tests {
             "2020-12-25T12:42",
             "--tags",
             "tenant:a,service:zk",
+            "--source",
+            "kafka-source",
+            "--node",
+            "indexer-1",
+            "--pipeline-ord",
+            "2",
+            "--pipeline-generation",
+            "3",
+            "--outdated-config",
             "--mark",
         ])?;
         let command = CliCommand::parse_cli_args(&matches)?;
@@ -668,6 +742,11 @@ mod tests {
                 start_date,
                 end_date,
                 tags,
+                source_id,
+                node_id,
+                pipeline_ord,
+                pipeline_generation,
+                outdated_config,
                 mark_for_deletion,
                 ..
             })) if index_id == "hdfs"
@@ -676,6 +755,11 @@ mod tests {
                    && start_date == expected_start_date
                    && end_date == expected_end_date
                    && tags == expected_tags
+                   && source_id.as_deref() == Some("kafka-source")
+                   && node_id.as_deref() == Some("indexer-1")
+                   && pipeline_ord == Some(2)
+                   && pipeline_generation == Some(3)
+                   && outdated_config
                    && mark_for_deletion
         ));
         Ok(())
@@ -275,6 +275,23 @@ impl Cluster {
         chitchat_guard.self_node_state().set(key, value);
     }
 
+    /// Returns the value associated with `key` in the state of each node of the cluster, self
+    /// included, as `(node ID, value)` pairs.
+    pub async fn node_key_values(&self, key: &str) -> Vec<(String, String)> {
+        let chitchat = self.chitchat_handle.chitchat();
+        let chitchat_guard = chitchat.lock().await;
+        chitchat_guard
+            .state_snapshot()
+            .node_states
+            .iter()
+            .filter_map(|(node_id, node_state)| {
+                node_state
+                    .get(key)
+                    .map(|value| (node_id.id.clone(), value.to_string()))
+            })
+            .collect()
+    }
+
     pub async fn state(&self) -> ClusterState {
         let chitchat = self.chitchat_handle.chitchat();
         let chitchat_guard = chitchat.lock().await;
@@ -17,7 +17,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::path::Path;
+//! File system helpers hiding the differences between platforms.
+//!
+//! The platform-specific rules are implemented by private functions that do not depend on the
+//! platform they run on, so that the rules of every platform are tested on any platform.
+
+use std::io;
+use std::path::{Path, MAIN_SEPARATOR};
 
 use tokio;
 
@@ -34,6 +40,100 @@ pub async fn empty_dir<P: AsRef<Path>>(path: P) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Flushes the entries of a directory to disk, so that the files created, renamed, or deleted in
+/// the directory are not lost on a crash.
+///
+/// On Windows, a directory cannot be opened to be synced, and NTFS journals the updates of the
+/// directory entries, so this is a no-op.
+pub async fn sync_dir<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    if cfg!(windows) {
+        return Ok(());
+    }
+    tokio::fs::File::open(path).await?.sync_all().await
+}
+
+/// Renames the directory `from` to `to`, replacing `to` if it exists.
+///
+/// `rename` replaces an empty destination directory on Unix, fails on a non-empty one, and always
+/// fails on Windows, so the destination is deleted first on every platform.
+pub async fn replace_dir<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> io::Result<()> {
+    match tokio::fs::remove_dir_all(to.as_ref()).await {
+        Ok(()) => {}
+        Err(io_error) if io_error.kind() == io::ErrorKind::NotFound => {}
+        Err(io_error) => return Err(io_error),
+    }
+    tokio::fs::rename(from, to).await
+}
+
+/// Returns the path as a string using `/` as separator whatever the platform, as expected in the
+/// keys of object storages.
+pub fn to_slash_path(path: &Path) -> String {
+    replace_separator(&path.to_string_lossy(), MAIN_SEPARATOR)
+}
+
+fn replace_separator(path_str: &str, separator: char) -> String {
+    if separator == '/' {
+        return path_str.to_string();
+    }
+    path_str.replace(separator, "/")
+}
+
+/// Returns whether an I/O error was caused by a file still opened, or mapped in memory, by this
+/// process or another one.
+///
+/// Windows refuses to delete or rename such a file, whereas Unix unlinks it and lets its readers
+/// carry on. The callers can then retry the operation later.
+pub fn is_file_in_use_error(io_error: &io::Error) -> bool {
+    cfg!(windows) && is_windows_sharing_violation(io_error.raw_os_error())
+}
+
+fn is_windows_sharing_violation(raw_os_error_opt: Option<i32>) -> bool {
+    // `ERROR_SHARING_VIOLATION` and `ERROR_LOCK_VIOLATION`.
+    matches!(raw_os_error_opt, Some(32) | Some(33))
+}
+
+/// Checks that `file_name` can be used as a file or directory name on this platform. Returns an
+/// error of kind [`io::ErrorKind::Unsupported`] explaining why otherwise.
+///
+/// Index and source IDs are used as directory names in the data directory: some of the IDs that
+/// are valid on Unix, such as `aux` or `nul`, are reserved names on Windows.
+pub fn check_file_name_supported(file_name: &str) -> io::Result<()> {
+    if cfg!(windows) {
+        if let Some(reason) = windows_unsupported_file_name_reason(file_name) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("File name `{file_name}` is not supported on Windows: {reason}."),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn windows_unsupported_file_name_reason(file_name: &str) -> Option<&'static str> {
+    const RESERVED_NAMES: [&str; 22] = [
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+    // Reserved names are reserved with any extension as well, e.g. `nul.txt`.
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved_name| stem.eq_ignore_ascii_case(reserved_name))
+    {
+        return Some("it is a reserved device name");
+    }
+    if file_name.ends_with('.') || file_name.ends_with(' ') {
+        return Some("it ends with a dot or a space");
+    }
+    if file_name
+        .chars()
+        .any(|c| matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*') || c < ' ')
+    {
+        return Some("it contains a reserved character");
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use tempfile;
@@ -61,4 +161,62 @@ mod tests {
             .is_none());
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_replace_dir() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+
+        let from_path = tempdir.path().join("from");
+        tokio::fs::create_dir(&from_path).await?;
+        tokio::fs::write(from_path.join("new-file"), b"new").await?;
+
+        let to_path = tempdir.path().join("to");
+        tokio::fs::create_dir(&to_path).await?;
+        tokio::fs::write(to_path.join("old-file"), b"old").await?;
+
+        replace_dir(&from_path, &to_path).await?;
+        assert!(!from_path.exists());
+        assert!(to_path.join("new-file").exists());
+        assert!(!to_path.join("old-file").exists());
+
+        // The destination does not exist.
+        replace_dir(&to_path, &from_path).await?;
+        assert!(from_path.join("new-file").exists());
+        assert!(!to_path.exists());
+
+        sync_dir(tempdir.path()).await?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_slash_path() {
+        assert_eq!(
+            to_slash_path(&Path::new("indexes").join("split")),
+            "indexes/split"
+        );
+        assert_eq!(replace_separator("a/b", '/'), "a/b");
+        assert_eq!(replace_separator("a\\b\\c", '\\'), "a/b/c");
+    }
+
+    #[test]
+    fn test_is_windows_sharing_violation() {
+        assert!(is_windows_sharing_violation(Some(32)));
+        assert!(is_windows_sharing_violation(Some(33)));
+        assert!(!is_windows_sharing_violation(Some(2)));
+        assert!(!is_windows_sharing_violation(None));
+        assert!(!is_file_in_use_error(&io::Error::from(
+            io::ErrorKind::NotFound
+        )));
+    }
+
+    #[test]
+    fn test_windows_unsupported_file_name_reason() {
+        assert!(windows_unsupported_file_name_reason("my-index").is_none());
+        assert!(windows_unsupported_file_name_reason("console").is_none());
+        assert!(windows_unsupported_file_name_reason("nul").is_some());
+        assert!(windows_unsupported_file_name_reason("Com1.log").is_some());
+        assert!(windows_unsupported_file_name_reason("index.").is_some());
+        assert!(windows_unsupported_file_name_reason("index:1").is_some());
+        assert!(check_file_name_supported("my-index").is_ok());
+    }
 }
@@ -18,7 +18,9 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use prometheus::{Encoder, HistogramOpts, Opts, TextEncoder};
-pub use prometheus::{Histogram, HistogramTimer, IntCounter, IntGauge};
+pub use prometheus::{
+    Histogram, HistogramTimer, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
+};
 
 pub fn new_counter(name: &str, description: &str, namespace: &str) -> IntCounter {
     let counter_opts = Opts::new(name, description).namespace(namespace);
@@ -41,6 +43,45 @@ pub fn new_gauge(name: &str, description: &str, namespace: &str) -> IntGauge {
     gauge
 }
 
+pub fn new_counter_vec(
+    name: &str,
+    description: &str,
+    namespace: &str,
+    label_names: &[&str],
+) -> IntCounterVec {
+    let counter_opts = Opts::new(name, description).namespace(namespace);
+    let counter_vec =
+        IntCounterVec::new(counter_opts, label_names).expect("Failed to create counter vec");
+    prometheus::register(Box::new(counter_vec.clone())).expect("Failed to register counter vec");
+    counter_vec
+}
+
+pub fn new_histogram_vec(
+    name: &str,
+    description: &str,
+    namespace: &str,
+    label_names: &[&str],
+) -> HistogramVec {
+    let histogram_opts = HistogramOpts::new(name, description).namespace(namespace);
+    let histogram_vec =
+        HistogramVec::new(histogram_opts, label_names).expect("Failed to create histogram vec");
+    prometheus::register(Box::new(histogram_vec.clone()))
+        .expect("Failed to register histogram vec");
+    histogram_vec
+}
+
+pub fn new_gauge_vec(
+    name: &str,
+    description: &str,
+    namespace: &str,
+    label_names: &[&str],
+) -> IntGaugeVec {
+    let gauge_opts = Opts::new(name, description).namespace(namespace);
+    let gauge_vec = IntGaugeVec::new(gauge_opts, label_names).expect("Failed to create gauge vec");
+    prometheus::register(Box::new(gauge_vec.clone())).expect("Failed to register gauge vec");
+    gauge_vec
+}
+
 pub fn metrics_handler() -> impl warp::Reply {
     let metric_families = prometheus::gather();
     let mut buffer = Vec::new();
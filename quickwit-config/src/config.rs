@@ -31,8 +31,9 @@ use quickwit_common::uri::{Extension, Uri};
 use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
 
+use crate::index_config::deser_and_validate_uri;
 use crate::templating::render_config;
-use crate::validate_identifier;
+use crate::{is_index_in_namespace, validate_identifier, validate_index_id, RetentionPolicy};
 
 pub const DEFAULT_QW_CONFIG_PATH: &str = "./config/quickwit.yaml";
 
@@ -84,6 +85,38 @@ pub struct IndexerConfig {
     pub split_store_max_num_bytes: Byte,
     #[serde(default = "IndexerConfig::default_split_store_max_num_splits")]
     pub split_store_max_num_splits: usize,
+    /// Number of orphan scratch directories left by previous runs that are kept for debugging
+    /// when an indexing pipeline starts. Older ones are deleted.
+    #[serde(default)]
+    pub max_num_retained_orphan_scratch_dirs: usize,
+    /// Maximum number of merges executed concurrently by all the pipelines of the node. Merges
+    /// beyond this limit are queued. Unlimited when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_merges: Option<usize>,
+    /// Maximum total size of the splits downloaded and merged concurrently by all the pipelines
+    /// of the node. Merges beyond this limit are queued, but a merge larger than the limit runs
+    /// alone. Unlimited when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_merge_bytes: Option<Byte>,
+    /// Maximum number of document batches indexed concurrently by the indexers of the node. When
+    /// set, the batches beyond this limit are queued and handed out to the index that used the
+    /// least indexing time relative to its scheduling weight. Unlimited when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_indexing_batches: Option<usize>,
+    /// Index receiving the log records sent to the OTLP logs gRPC endpoint of the node. The
+    /// endpoint is not mounted when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub otlp_logs_index_id: Option<String>,
+    /// Index receiving the spans sent to the OTLP traces gRPC endpoint of the node, created from
+    /// the `otel-traces` index template if it does not exist. The endpoint is not mounted when
+    /// unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub otlp_traces_index_id: Option<String>,
+    /// Whether the write-ahead log of the ingest API queues is synced before the documents are
+    /// acknowledged. Disabling it trades the durability of the acknowledged documents on a
+    /// machine crash for ingest throughput.
+    #[serde(default = "IndexerConfig::default_sync_ingest_writes")]
+    pub sync_ingest_writes: bool,
 }
 
 impl IndexerConfig {
@@ -95,11 +128,22 @@ impl IndexerConfig {
         1_000
     }
 
+    fn default_sync_ingest_writes() -> bool {
+        true
+    }
+
     #[cfg(any(test, feature = "testsuite"))]
     pub fn for_test() -> anyhow::Result<Self> {
         let indexer_config = IndexerConfig {
             split_store_max_num_bytes: Byte::from_bytes(1_000_000),
             split_store_max_num_splits: 3,
+            max_num_retained_orphan_scratch_dirs: 0,
+            max_concurrent_merges: None,
+            max_concurrent_merge_bytes: None,
+            max_concurrent_indexing_batches: None,
+            otlp_logs_index_id: None,
+            otlp_traces_index_id: None,
+            sync_ingest_writes: true,
         };
         Ok(indexer_config)
     }
@@ -110,6 +154,13 @@ impl Default for IndexerConfig {
         Self {
             split_store_max_num_bytes: Self::default_split_store_max_num_bytes(),
             split_store_max_num_splits: Self::default_split_store_max_num_splits(),
+            max_num_retained_orphan_scratch_dirs: 0,
+            max_concurrent_merges: None,
+            max_concurrent_merge_bytes: None,
+            max_concurrent_indexing_batches: None,
+            otlp_logs_index_id: None,
+            otlp_traces_index_id: None,
+            sync_ingest_writes: Self::default_sync_ingest_writes(),
         }
     }
 }
@@ -156,6 +207,46 @@ impl Default for SearcherConfig {
     }
 }
 
+/// Defaults applied to the indexes created in a namespace, such as `team-a` for the index
+/// `team-a/logs`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NamespaceConfig {
+    pub namespace: String,
+    /// Root URI of the indexes of the namespace that do not specify an `index_uri`.
+    #[serde(default)]
+    #[serde(deserialize_with = "deser_and_validate_uri")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_index_root_uri: Option<Uri>,
+    /// Retention policy of the indexes of the namespace that do not specify one.
+    #[serde(rename = "retention")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_retention_policy: Option<RetentionPolicy>,
+}
+
+impl NamespaceConfig {
+    /// Returns the config of the innermost namespace containing the index `index_id`.
+    pub fn find<'a>(
+        namespace_configs: &'a [NamespaceConfig],
+        index_id: &str,
+    ) -> Option<&'a NamespaceConfig> {
+        namespace_configs
+            .iter()
+            .filter(|namespace_config| is_index_in_namespace(index_id, &namespace_config.namespace))
+            .max_by_key(|namespace_config| namespace_config.namespace.len())
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        validate_index_id(&self.namespace)
+            .with_context(|| format!("Namespace `{}` is invalid.", self.namespace))?;
+        if let Some(retention_policy) = &self.default_retention_policy {
+            retention_policy.validate()?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Derivative)]
 #[derivative(Debug)]
 #[derive(Clone, Serialize, Deserialize, PartialEq)]
@@ -190,6 +281,8 @@ struct QuickwitConfigBuilder {
     #[serde(rename = "searcher")]
     #[serde(default)]
     searcher_config: SearcherConfig,
+    #[serde(default)]
+    namespaces: Vec<NamespaceConfig>,
 }
 
 impl QuickwitConfigBuilder {
@@ -356,6 +449,7 @@ impl QuickwitConfigBuilder {
             peer_seeds: self.peer_seeds,
             indexer_config: self.indexer_config,
             searcher_config: self.searcher_config,
+            namespaces: self.namespaces,
         })
     }
 }
@@ -392,6 +486,7 @@ pub struct QuickwitConfig {
     pub data_dir_path: PathBuf,
     pub indexer_config: IndexerConfig,
     pub searcher_config: SearcherConfig,
+    pub namespaces: Vec<NamespaceConfig>,
 }
 
 impl QuickwitConfig {
@@ -440,6 +535,39 @@ impl QuickwitConfig {
                 self.data_dir_path.display()
             );
         }
+        if self.indexer_config.max_concurrent_merges == Some(0) {
+            bail!("Indexer config `max_concurrent_merges` must be strictly positive.")
+        }
+        if self.indexer_config.max_concurrent_merge_bytes == Some(Byte::from_bytes(0)) {
+            bail!("Indexer config `max_concurrent_merge_bytes` must be strictly positive.")
+        }
+        if self.indexer_config.max_concurrent_indexing_batches == Some(0) {
+            bail!("Indexer config `max_concurrent_indexing_batches` must be strictly positive.")
+        }
+        if let Some(otlp_logs_index_id) = &self.indexer_config.otlp_logs_index_id {
+            validate_index_id(otlp_logs_index_id).with_context(|| {
+                format!("Indexer config `otlp_logs_index_id` `{otlp_logs_index_id}` is invalid.")
+            })?;
+        }
+        if let Some(otlp_traces_index_id) = &self.indexer_config.otlp_traces_index_id {
+            validate_index_id(otlp_traces_index_id).with_context(|| {
+                format!(
+                    "Indexer config `otlp_traces_index_id` `{otlp_traces_index_id}` is invalid."
+                )
+            })?;
+        }
+        for (idx, namespace_config) in self.namespaces.iter().enumerate() {
+            namespace_config.validate()?;
+            if self.namespaces[..idx]
+                .iter()
+                .any(|other| other.namespace == namespace_config.namespace)
+            {
+                bail!(
+                    "Namespace `{}` is configured more than once.",
+                    namespace_config.namespace
+                );
+            }
+        }
         Ok(())
     }
 
@@ -509,6 +637,7 @@ impl QuickwitConfig {
             data_dir_path,
             indexer_config: IndexerConfig::default(),
             searcher_config: SearcherConfig::default(),
+            namespaces: Vec::new(),
         }
     }
 }
@@ -537,6 +666,7 @@ mod tests {
                 data_dir_path: PathBuf::from(DEFAULT_DATA_DIR_PATH),
                 indexer_config: IndexerConfig::default(),
                 searcher_config: SearcherConfig::default(),
+                namespaces: Vec::new(),
             }
         }
     }
@@ -584,6 +714,13 @@ mod tests {
                     IndexerConfig {
                         split_store_max_num_bytes: Byte::from_str("1T").unwrap(),
                         split_store_max_num_splits: 10_000,
+                        max_num_retained_orphan_scratch_dirs: 0,
+                        max_concurrent_merges: None,
+                        max_concurrent_merge_bytes: None,
+                        max_concurrent_indexing_batches: None,
+                        otlp_logs_index_id: None,
+                        otlp_traces_index_id: None,
+                        sync_ingest_writes: true,
                     }
                 );
 
@@ -624,6 +761,66 @@ mod tests {
         assert_eq!(indexer_config, IndexerConfig::default());
     }
 
+    #[test]
+    fn test_indexer_config_merge_budget() {
+        let indexer_config_yaml = r#"
+            max_concurrent_merges: 2
+            max_concurrent_merge_bytes: 10G
+            max_concurrent_indexing_batches: 6
+        "#;
+        let indexer_config = serde_yaml::from_str::<IndexerConfig>(indexer_config_yaml).unwrap();
+        assert_eq!(indexer_config.max_concurrent_merges, Some(2));
+        assert_eq!(
+            indexer_config.max_concurrent_merge_bytes,
+            Some(Byte::from_str("10G").unwrap())
+        );
+        assert_eq!(indexer_config.max_concurrent_indexing_batches, Some(6));
+    }
+
+    #[test]
+    fn test_indexer_config_sync_ingest_writes() {
+        assert!(IndexerConfig::default().sync_ingest_writes);
+        let indexer_config =
+            serde_yaml::from_str::<IndexerConfig>("sync_ingest_writes: false").unwrap();
+        assert!(!indexer_config.sync_ingest_writes);
+    }
+
+    #[test]
+    fn test_indexer_config_otlp_logs_index_id() {
+        let indexer_config =
+            serde_yaml::from_str::<IndexerConfig>("otlp_logs_index_id: otel-logs").unwrap();
+        assert_eq!(
+            indexer_config.otlp_logs_index_id.as_deref(),
+            Some("otel-logs")
+        );
+
+        let mut config = QuickwitConfig::for_test();
+        config.data_dir_path = env::temp_dir();
+        config.indexer_config.otlp_logs_index_id = Some("otel logs".to_string());
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "Indexer config `otlp_logs_index_id` `otel logs` is invalid."
+        );
+    }
+
+    #[test]
+    fn test_indexer_config_otlp_traces_index_id() {
+        let indexer_config =
+            serde_yaml::from_str::<IndexerConfig>("otlp_traces_index_id: otel-traces").unwrap();
+        assert_eq!(
+            indexer_config.otlp_traces_index_id.as_deref(),
+            Some("otel-traces")
+        );
+
+        let mut config = QuickwitConfig::for_test();
+        config.data_dir_path = env::temp_dir();
+        config.indexer_config.otlp_traces_index_id = Some("otel traces".to_string());
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "Indexer config `otlp_traces_index_id` `otel traces` is invalid."
+        );
+    }
+
     #[test]
     fn test_searcher_config_default_values() {
         let searcher_config = serde_yaml::from_str::<SearcherConfig>("{}").unwrap();
@@ -689,11 +886,18 @@ mod tests {
         let config_uri = Uri::try_new(&config_filepath).unwrap();
         let file_content = std::fs::read_to_string(&config_filepath).unwrap();
         let data_dir_path = env::current_dir().unwrap();
-        let config =
+        let mut config =
             QuickwitConfig::load(&config_uri, file_content.as_bytes(), Some(data_dir_path))
                 .await
                 .unwrap();
         assert!(config.validate().is_ok());
+
+        config.indexer_config.max_concurrent_merges = Some(0);
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("Indexer config `max_concurrent_merges` must be strictly positive."));
     }
 
     #[tokio::test]
@@ -852,4 +1056,64 @@ mod tests {
             config_builder.build().await.unwrap_err();
         }
     }
+
+    #[tokio::test]
+    async fn test_config_namespaces() {
+        let config_yaml = r#"
+            version: 0
+            namespaces:
+              - namespace: team-a
+                default_index_root_uri: s3://team-a-bucket/indexes
+                retention:
+                  period: 30 days
+                  cutoff_reference: split_timestamp_field
+              - namespace: team-a/prod
+                default_index_root_uri: s3://team-a-prod-bucket/indexes
+        "#;
+        let config_builder = serde_yaml::from_str::<QuickwitConfigBuilder>(config_yaml).unwrap();
+        let config = config_builder.build().await.unwrap();
+        assert_eq!(config.namespaces.len(), 2);
+
+        let namespace_config = NamespaceConfig::find(&config.namespaces, "team-a/logs").unwrap();
+        assert_eq!(namespace_config.namespace, "team-a");
+        assert_eq!(
+            namespace_config.default_index_root_uri.clone().unwrap(),
+            "s3://team-a-bucket/indexes"
+        );
+        assert!(namespace_config.default_retention_policy.is_some());
+
+        let namespace_config =
+            NamespaceConfig::find(&config.namespaces, "team-a/prod/logs").unwrap();
+        assert_eq!(namespace_config.namespace, "team-a/prod");
+
+        assert!(NamespaceConfig::find(&config.namespaces, "team-b/logs").is_none());
+        assert!(NamespaceConfig::find(&config.namespaces, "logs").is_none());
+    }
+
+    #[test]
+    fn test_config_validates_namespaces() {
+        let mut config = QuickwitConfig::for_test();
+        config.data_dir_path = env::temp_dir();
+        config.namespaces = vec![
+            NamespaceConfig {
+                namespace: "team-a".to_string(),
+                default_index_root_uri: None,
+                default_retention_policy: None,
+            };
+            2
+        ];
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "Namespace `team-a` is configured more than once."
+        );
+        config.namespaces = vec![NamespaceConfig {
+            namespace: "team-a/".to_string(),
+            default_index_root_uri: None,
+            default_retention_policy: None,
+        }];
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "Namespace `team-a/` is invalid."
+        );
+    }
 }
@@ -17,7 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -29,14 +29,15 @@ use humantime::parse_duration;
 use json_comments::StripComments;
 use quickwit_common::uri::{Extension, Uri};
 use quickwit_doc_mapper::{
-    DefaultDocMapperBuilder, DocMapper, FieldMappingEntry, ModeType, QuickwitJsonOptions, SortBy,
-    SortByConfig, SortOrder,
+    DefaultDocMapperBuilder, DocIdConfig, DocMapper, FieldMappingEntry, ModeType,
+    QuickwitJsonOptions, SortBy, SortByConfig, SortOrder,
 };
 use serde::de::{Error, IgnoredAny};
 use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value as JsonValue;
 
 use crate::source_config::SourceConfig;
-use crate::validate_identifier;
+use crate::{is_false, validate_index_id, IndexingProfile};
 
 // Note(fmassot): `DocMapping` is a struct only used for
 // serialization/deserialization of `DocMapper` parameters.
@@ -58,6 +59,16 @@ pub struct DocMapping {
     pub dynamic_mapping: Option<QuickwitJsonOptions>,
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub partition_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc_id: Option<DocIdConfig>,
+    /// Field holding the expiry of the documents. Expired documents are dropped when their split
+    /// is rewritten.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expire_timestamp_field: Option<String>,
+    /// Field holding the sequence number of the documents. Merges only keep the document with
+    /// the highest sequence number among the documents sharing the same ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence_field: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -67,11 +78,26 @@ pub struct IndexingResources {
     pub __num_threads_deprecated: IgnoredAny, // DEPRECATED
     #[serde(default = "IndexingResources::default_heap_size")]
     pub heap_size: Byte,
+    /// Estimated memory held by the index writers of all the partitions of the indexer workbench
+    /// above which the workbench is committed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workbench_memory_limit: Option<Byte>,
+    /// Capacity of the buffer of the writes to the files of the splits being built or merged,
+    /// such as the doc store. Defaults to 8KB.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub split_write_buffer_size: Option<Byte>,
+    /// Number of bytes written to a file of a split being built or merged after which the file
+    /// is synced to disk. By default, files are only synced once complete.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub split_sync_interval: Option<Byte>,
 }
 
 impl PartialEq for IndexingResources {
     fn eq(&self, other: &Self) -> bool {
         self.heap_size == other.heap_size
+            && self.workbench_memory_limit == other.workbench_memory_limit
+            && self.split_write_buffer_size == other.split_write_buffer_size
+            && self.split_sync_interval == other.split_sync_interval
     }
 }
 
@@ -85,6 +111,9 @@ impl IndexingResources {
         Self {
             __num_threads_deprecated: IgnoredAny,
             heap_size: Byte::from_bytes(20_000_000), // 20MB
+            workbench_memory_limit: None,
+            split_write_buffer_size: None,
+            split_sync_interval: None,
         }
     }
 }
@@ -94,6 +123,9 @@ impl Default for IndexingResources {
         Self {
             __num_threads_deprecated: IgnoredAny,
             heap_size: Self::default_heap_size(),
+            workbench_memory_limit: None,
+            split_write_buffer_size: None,
+            split_sync_interval: None,
         }
     }
 }
@@ -101,23 +133,47 @@ impl Default for IndexingResources {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct MergePolicy {
+    /// Type of the merge policy, resolved against the merge policy factories registered by the
+    /// indexer.
+    #[serde(
+        rename = "type",
+        default = "MergePolicy::default_merge_policy_type",
+        skip_serializing_if = "MergePolicy::is_default_merge_policy_type"
+    )]
+    pub merge_policy_type: String,
     #[serde(default, rename = "demux_factor", skip_serializing)]
     pub __demux_factor_deprecated: IgnoredAny, // DEPRECATED
     #[serde(default = "MergePolicy::default_merge_factor")]
     pub merge_factor: usize,
     #[serde(default = "MergePolicy::default_max_merge_factor")]
     pub max_merge_factor: usize,
+    /// Parameters specific to the merge policy type.
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub params: serde_json::Map<String, JsonValue>,
 }
 
 impl PartialEq for MergePolicy {
     fn eq(&self, other: &Self) -> bool {
-        self.merge_factor == other.merge_factor && self.max_merge_factor == other.max_merge_factor
+        self.merge_policy_type == other.merge_policy_type
+            && self.merge_factor == other.merge_factor
+            && self.max_merge_factor == other.max_merge_factor
+            && self.params == other.params
     }
 }
 
 impl Eq for MergePolicy {}
 
 impl MergePolicy {
+    pub const STABLE_MULTITENANT_TYPE: &'static str = "stable_multitenant";
+
+    fn default_merge_policy_type() -> String {
+        Self::STABLE_MULTITENANT_TYPE.to_string()
+    }
+
+    fn is_default_merge_policy_type(merge_policy_type: &str) -> bool {
+        merge_policy_type == Self::STABLE_MULTITENANT_TYPE
+    }
+
     fn default_merge_factor() -> usize {
         10
     }
@@ -130,9 +186,434 @@ impl MergePolicy {
 impl Default for MergePolicy {
     fn default() -> Self {
         Self {
+            merge_policy_type: Self::default_merge_policy_type(),
             __demux_factor_deprecated: serde::de::IgnoredAny,
             merge_factor: Self::default_merge_factor(),
             max_merge_factor: Self::default_max_merge_factor(),
+            params: serde_json::Map::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeduplicationSettings {
+    /// Field holding the ID of the documents. When unset, the whole raw document is used as ID.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc_id_field: Option<String>,
+    /// Documents whose ID was already indexed within this window are dropped.
+    #[serde(default = "DeduplicationSettings::default_window_secs")]
+    pub window_secs: u64,
+}
+
+impl DeduplicationSettings {
+    fn default_window_secs() -> u64 {
+        3_600
+    }
+
+    pub fn window(&self) -> Duration {
+        Duration::from_secs(self.window_secs)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnrichmentSettings {
+    /// Key under which the pipeline context fields are added to the documents.
+    #[serde(default = "EnrichmentSettings::default_namespace")]
+    pub namespace: String,
+}
+
+impl EnrichmentSettings {
+    fn default_namespace() -> String {
+        "_quickwit".to_string()
+    }
+}
+
+impl Default for EnrichmentSettings {
+    fn default() -> Self {
+        Self {
+            namespace: Self::default_namespace(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SplitCarryOverSettings {
+    /// Partitions whose split holds fewer documents than this value are carried over to the next
+    /// workbench instead of being emitted.
+    #[serde(default)]
+    pub min_num_docs: usize,
+    /// Partitions whose split holds fewer bytes (uncompressed) than this value are carried over
+    /// to the next workbench instead of being emitted.
+    #[serde(default)]
+    pub min_num_bytes: u64,
+    /// Maximum delay during which partitions are carried over. Past this delay, all the
+    /// partitions are emitted regardless of their size.
+    #[serde(default = "SplitCarryOverSettings::default_max_carry_over_secs")]
+    pub max_carry_over_secs: u64,
+}
+
+impl SplitCarryOverSettings {
+    fn default_max_carry_over_secs() -> u64 {
+        600
+    }
+
+    pub fn max_carry_over(&self) -> Duration {
+        Duration::from_secs(self.max_carry_over_secs)
+    }
+
+    /// Returns whether a split with `num_docs` documents and `num_bytes` bytes is too small to be
+    /// emitted.
+    pub fn is_below_threshold(&self, num_docs: u64, num_bytes: u64) -> bool {
+        num_docs < self.min_num_docs as u64 || num_bytes < self.min_num_bytes
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShardingSettings {
+    /// Number of indexers the documents of a pipeline are spread across.
+    pub num_shards: usize,
+    /// Field whose value is hashed to pick the shard indexing a document. Documents missing the
+    /// field are all routed to the same shard.
+    pub routing_field: String,
+}
+
+/// Calendar buckets of the time range of the splits recorded as split tags.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CalendarTagGranularity {
+    /// Tags the splits with `hour:2022-06-01T13`.
+    Hour,
+    /// Tags the splits with `day:2022-06-01`.
+    Day,
+    /// Tags the splits with `month:2022-06`.
+    Month,
+    /// Tags the splits with `year:2022`.
+    Year,
+}
+
+impl CalendarTagGranularity {
+    /// Returns the name of the tag field of the calendar buckets.
+    pub fn tag_field_name(&self) -> &'static str {
+        match self {
+            CalendarTagGranularity::Hour => "hour",
+            CalendarTagGranularity::Day => "day",
+            CalendarTagGranularity::Month => "month",
+            CalendarTagGranularity::Year => "year",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SplitSamplingSettings {
+    /// Number of documents sampled at random in each split.
+    #[serde(default = "SplitSamplingSettings::default_num_docs")]
+    pub num_docs: usize,
+    /// URI of the storage where the samples are exported, one NDJSON file per split.
+    #[serde(deserialize_with = "deser_and_validate_required_uri")]
+    pub sample_uri: Uri,
+}
+
+impl SplitSamplingSettings {
+    fn default_num_docs() -> usize {
+        100
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeadLetterQueueSettings {
+    /// URI of the storage, a local directory or an object storage prefix, where the invalid
+    /// documents are written as NDJSON files. Exclusive with `index_id`.
+    #[serde(
+        default,
+        deserialize_with = "deser_and_validate_uri",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub uri: Option<Uri>,
+    /// ID of the index to which the invalid documents are sent through the ingest API.
+    /// Exclusive with `uri`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index_id: Option<String>,
+    /// Size above which the buffered invalid documents are flushed to a new file, or to a new
+    /// ingest request.
+    #[serde(default = "DeadLetterQueueSettings::default_max_file_size")]
+    pub max_file_size: Byte,
+    /// Number of files retained by each pipeline. When a pipeline writes a new file beyond this
+    /// limit, it deletes its oldest file.
+    #[serde(default = "DeadLetterQueueSettings::default_max_num_files")]
+    pub max_num_files: usize,
+}
+
+impl DeadLetterQueueSettings {
+    fn default_max_file_size() -> Byte {
+        Byte::from_bytes(10_000_000) // 10MB
+    }
+
+    fn default_max_num_files() -> usize {
+        100
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TenantRoutingSettings {
+    /// Field holding the tenant of the documents, usually the field of the partition key.
+    pub tenant_field: String,
+    /// Tenants whose documents are routed to their own index. When empty, the documents of all
+    /// the tenants are routed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tenants: Vec<String>,
+    /// Template of the ID of the index of a tenant, in which `{index_id}` is replaced by the ID of
+    /// the index and `{tenant}` by the tenant.
+    #[serde(default = "TenantRoutingSettings::default_index_id_template")]
+    pub index_id_template: String,
+    /// Retention policy of the tenant indexes. Defaults to the retention policy of the index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retention_policy: Option<RetentionPolicy>,
+    /// Merge policy of the tenant indexes. Defaults to the merge policy of the index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merge_policy: Option<MergePolicy>,
+}
+
+impl TenantRoutingSettings {
+    fn default_index_id_template() -> String {
+        "{index_id}-{tenant}".to_string()
+    }
+
+    /// Returns whether the documents of `tenant` are routed to their own index.
+    pub fn is_routed(&self, tenant: &str) -> bool {
+        self.tenants.is_empty()
+            || self
+                .tenants
+                .iter()
+                .any(|routed_tenant| routed_tenant == tenant)
+    }
+
+    /// Returns the ID of the index of `tenant`, or an error if the rendered ID is not a valid
+    /// index ID.
+    pub fn tenant_index_id(&self, index_id: &str, tenant: &str) -> anyhow::Result<String> {
+        let tenant_index_id = self
+            .index_id_template
+            .replace("{index_id}", index_id)
+            .replace("{tenant}", tenant);
+        validate_index_id(&tenant_index_id)?;
+        Ok(tenant_index_id)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PublishNotificationSettings {
+    /// URL to which the notifications are posted as JSON. Exclusive with `kafka_topic`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    /// Kafka topic to which the notifications are produced, keyed by index ID. Exclusive with
+    /// `webhook_url`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kafka_topic: Option<String>,
+    /// Configuration parameters of the Kafka producer, such as `bootstrap.servers`.
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub kafka_client_params: serde_json::Value,
+    /// Maximum delay between two delivery attempts of a notification. The delay doubles after
+    /// each failed attempt, and a notification is retried until it is delivered.
+    #[serde(default = "PublishNotificationSettings::default_max_retry_delay_secs")]
+    pub max_retry_delay_secs: u64,
+}
+
+impl PublishNotificationSettings {
+    fn default_max_retry_delay_secs() -> u64 {
+        60
+    }
+
+    /// Returns the maximum delay between two delivery attempts of a notification.
+    pub fn max_retry_delay(&self) -> Duration {
+        Duration::from_secs(self.max_retry_delay_secs)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldProfilingSettings {
+    /// One in this many documents is profiled.
+    #[serde(default = "FieldProfilingSettings::default_sample_interval")]
+    pub sample_interval: u64,
+}
+
+impl FieldProfilingSettings {
+    fn default_sample_interval() -> u64 {
+        100
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StorageRetrySettings {
+    /// Timeout of the storage requests transferring no payload or a small one.
+    #[serde(default = "StorageRetrySettings::default_request_timeout_secs")]
+    pub request_timeout_secs: u64,
+    /// Throughput below which a storage request transferring a payload of known size times out.
+    /// The timeout of such a request is extended by the time required to transfer its payload
+    /// at this throughput.
+    #[serde(default = "StorageRetrySettings::default_min_throughput_mib_per_sec")]
+    pub min_throughput_mib_per_sec: u64,
+    /// Number of times a storage request failing with a transient error is retried.
+    #[serde(default = "StorageRetrySettings::default_max_retries")]
+    pub max_retries: usize,
+    /// Base delay of the exponential backoff between two attempts.
+    #[serde(default = "StorageRetrySettings::default_base_backoff_millis")]
+    pub base_backoff_millis: u64,
+    /// Maximum delay between two attempts.
+    #[serde(default = "StorageRetrySettings::default_max_backoff_millis")]
+    pub max_backoff_millis: u64,
+}
+
+impl StorageRetrySettings {
+    fn default_request_timeout_secs() -> u64 {
+        30
+    }
+
+    fn default_min_throughput_mib_per_sec() -> u64 {
+        8
+    }
+
+    fn default_max_retries() -> usize {
+        3
+    }
+
+    fn default_base_backoff_millis() -> u64 {
+        250
+    }
+
+    fn default_max_backoff_millis() -> u64 {
+        20_000
+    }
+
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_secs(self.request_timeout_secs)
+    }
+
+    pub fn base_backoff(&self) -> Duration {
+        Duration::from_millis(self.base_backoff_millis)
+    }
+
+    pub fn max_backoff(&self) -> Duration {
+        Duration::from_millis(self.max_backoff_millis)
+    }
+}
+
+impl Default for StorageRetrySettings {
+    fn default() -> Self {
+        Self {
+            request_timeout_secs: Self::default_request_timeout_secs(),
+            min_throughput_mib_per_sec: Self::default_min_throughput_mib_per_sec(),
+            max_retries: Self::default_max_retries(),
+            base_backoff_millis: Self::default_base_backoff_millis(),
+            max_backoff_millis: Self::default_max_backoff_millis(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MergeCoordinationSettings {
+    /// Duration of the lease electing the merge planner of the index. A planner that stops
+    /// renewing its lease is replaced by another one once the lease expires.
+    #[serde(default = "MergeCoordinationSettings::default_lease_duration_secs")]
+    pub lease_duration_secs: u64,
+    /// Interval at which the merge planners renew or compete for the lease, and at which the
+    /// elected planner refreshes the splits of the index from the metastore.
+    #[serde(default = "MergeCoordinationSettings::default_refresh_interval_secs")]
+    pub refresh_interval_secs: u64,
+}
+
+impl MergeCoordinationSettings {
+    fn default_lease_duration_secs() -> u64 {
+        60
+    }
+
+    fn default_refresh_interval_secs() -> u64 {
+        15
+    }
+
+    pub fn refresh_interval(&self) -> Duration {
+        Duration::from_secs(self.refresh_interval_secs)
+    }
+}
+
+impl Default for MergeCoordinationSettings {
+    fn default() -> Self {
+        Self {
+            lease_duration_secs: Self::default_lease_duration_secs(),
+            refresh_interval_secs: Self::default_refresh_interval_secs(),
+        }
+    }
+}
+
+/// Serialized sizes of the payloads sent to the metastore by the indexing pipelines above which
+/// a warning is emitted, and above which the payload is rejected.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetastorePayloadLimits {
+    #[serde(default = "MetastorePayloadLimits::default_split_metadata_warn_size")]
+    pub split_metadata_warn_size: Byte,
+    #[serde(default = "MetastorePayloadLimits::default_split_metadata_max_size")]
+    pub split_metadata_max_size: Byte,
+    #[serde(default = "MetastorePayloadLimits::default_checkpoint_delta_warn_size")]
+    pub checkpoint_delta_warn_size: Byte,
+    #[serde(default = "MetastorePayloadLimits::default_checkpoint_delta_max_size")]
+    pub checkpoint_delta_max_size: Byte,
+    /// Limits of the publish requests, made of the IDs of the published and replaced splits and
+    /// of the checkpoint delta.
+    #[serde(default = "MetastorePayloadLimits::default_publish_request_warn_size")]
+    pub publish_request_warn_size: Byte,
+    #[serde(default = "MetastorePayloadLimits::default_publish_request_max_size")]
+    pub publish_request_max_size: Byte,
+}
+
+impl MetastorePayloadLimits {
+    fn default_split_metadata_warn_size() -> Byte {
+        Byte::from_bytes(64_000) // 64KB
+    }
+
+    fn default_split_metadata_max_size() -> Byte {
+        Byte::from_bytes(1_000_000) // 1MB
+    }
+
+    fn default_checkpoint_delta_warn_size() -> Byte {
+        Byte::from_bytes(256_000) // 256KB
+    }
+
+    fn default_checkpoint_delta_max_size() -> Byte {
+        Byte::from_bytes(4_000_000) // 4MB
+    }
+
+    fn default_publish_request_warn_size() -> Byte {
+        Byte::from_bytes(1_000_000) // 1MB
+    }
+
+    fn default_publish_request_max_size() -> Byte {
+        Byte::from_bytes(16_000_000) // 16MB
+    }
+
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Default for MetastorePayloadLimits {
+    fn default() -> Self {
+        Self {
+            split_metadata_warn_size: Self::default_split_metadata_warn_size(),
+            split_metadata_max_size: Self::default_split_metadata_max_size(),
+            checkpoint_delta_warn_size: Self::default_checkpoint_delta_warn_size(),
+            checkpoint_delta_max_size: Self::default_checkpoint_delta_max_size(),
+            publish_request_warn_size: Self::default_publish_request_warn_size(),
+            publish_request_max_size: Self::default_publish_request_max_size(),
         }
     }
 }
@@ -161,10 +642,97 @@ pub struct IndexingSettings {
     pub split_num_docs_target: usize,
     #[serde(default = "IndexingSettings::default_merge_enabled")]
     pub merge_enabled: bool,
+    /// Maximum number of partitions of a workbench with an open split. When a document of a new
+    /// partition exceeds this limit, the split of the least recently used partition is emitted,
+    /// and published along with the checkpoint delta of the commit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_open_partitions: Option<usize>,
+    /// When enabled, splits are re-opened and verified before being published. Corrupted splits
+    /// are quarantined instead of being published.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub verify_splits_before_publish: bool,
+    /// When enabled, the new splits are searchable by the searcher of the indexing node as soon as
+    /// they are packaged, while they are uploaded and published.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub search_before_upload: bool,
+    /// When enabled, failures of the actors off the critical indexing path (garbage collector,
+    /// merge sub-pipeline, delete executor) only respawn the failed actor, or its merge
+    /// sub-pipeline, instead of the whole pipeline.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub isolate_non_critical_failures: bool,
+    /// When enabled, the sources of the index consumed by a single pipeline are read by one
+    /// multiplexed pipeline, which interleaves their batches and tracks their checkpoints
+    /// independently, instead of one pipeline per source.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub multiplex_sources: bool,
+    /// Share of the indexing time of the node granted to the index relative to the other
+    /// indexes, when the indexer limits the batches indexed concurrently. Defaults to 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduling_weight: Option<u32>,
+    /// Period during which the files of the splits marked for deletion are kept, so that the
+    /// queries that selected these splits before they were marked can complete. Defaults to 120
+    /// seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub split_deletion_grace_period_secs: Option<u64>,
+    /// When set, documents redelivered by the sources are deduplicated within a sliding window
+    /// persisted in the metastore along with the splits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deduplication: Option<DeduplicationSettings>,
+    /// When set, documents are stamped with the context of the pipeline indexing them (node ID,
+    /// pipeline ordinal, source ID, and ingest timestamp).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enrichment: Option<EnrichmentSettings>,
+    /// When set, the partitions of a workbench yielding small splits are carried over to the next
+    /// workbench for a bounded time, instead of being emitted as small splits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub split_carry_over: Option<SplitCarryOverSettings>,
+    /// When set, the documents of each pipeline are routed by hash of a field to several
+    /// indexers working in parallel, whose splits are published together.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sharding: Option<ShardingSettings>,
+    /// When set, the storage requests issued by the indexing pipelines (split uploads and
+    /// downloads, garbage collection) are timed out and retried according to these settings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_retry: Option<StorageRetrySettings>,
+    /// When set, a random sample of the documents of each split is exported to a storage, so
+    /// that data-quality tooling can monitor the indexed content without searching the index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub split_sampling: Option<SplitSamplingSettings>,
+    /// When set, the documents failing to parse are written along with their parse error to a
+    /// storage or to a dedicated index, instead of only being counted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dead_letter_queue: Option<DeadLetterQueueSettings>,
+    /// When set, the documents of the routed tenants are sent to per-tenant indexes, created on
+    /// demand from the index, instead of being indexed in the index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_routing: Option<TenantRoutingSettings>,
+    /// Calendar buckets overlapping the time range of each split recorded as split tags, so that
+    /// splits can be pruned and retained per calendar bucket without client-provided tag fields.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub calendar_tags: Vec<CalendarTagGranularity>,
+    /// When set, a single merge planner per index, elected through a lease held in the metastore,
+    /// plans the merges of the splits of all the pipelines of the index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merge_coordination: Option<MergeCoordinationSettings>,
+    /// When set, the pipelines notify a webhook or a Kafka topic of the splits they publish.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publish_notifications: Option<PublishNotificationSettings>,
+    /// When set, the indexers measure the size and the tokenization time of the fields of a
+    /// sample of the documents, to find the fields dominating the indexing cost.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field_profiling: Option<FieldProfilingSettings>,
+    /// Profile selecting tuned defaults for the commit timeout, split size, merge policy,
+    /// docstore compression level, and indexer queue capacity.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<IndexingProfile>,
     #[serde(default)]
     pub merge_policy: MergePolicy,
     #[serde(default)]
     pub resources: IndexingResources,
+    /// Sizes of the split metadata, checkpoint deltas, and publish requests sent to the
+    /// metastore above which the pipelines warn, and above which they fail.
+    #[serde(default, skip_serializing_if = "MetastorePayloadLimits::is_default")]
+    pub metastore_payload_limits: MetastorePayloadLimits,
 }
 
 impl PartialEq for IndexingSettings {
@@ -177,8 +745,29 @@ impl PartialEq for IndexingSettings {
             && self.docstore_blocksize == other.docstore_blocksize
             && self.split_num_docs_target == other.split_num_docs_target
             && self.merge_enabled == other.merge_enabled
+            && self.max_open_partitions == other.max_open_partitions
+            && self.verify_splits_before_publish == other.verify_splits_before_publish
+            && self.search_before_upload == other.search_before_upload
+            && self.isolate_non_critical_failures == other.isolate_non_critical_failures
+            && self.multiplex_sources == other.multiplex_sources
+            && self.scheduling_weight == other.scheduling_weight
+            && self.split_deletion_grace_period_secs == other.split_deletion_grace_period_secs
+            && self.deduplication == other.deduplication
+            && self.enrichment == other.enrichment
+            && self.split_carry_over == other.split_carry_over
+            && self.sharding == other.sharding
+            && self.storage_retry == other.storage_retry
+            && self.split_sampling == other.split_sampling
+            && self.dead_letter_queue == other.dead_letter_queue
+            && self.tenant_routing == other.tenant_routing
+            && self.calendar_tags == other.calendar_tags
+            && self.merge_coordination == other.merge_coordination
+            && self.publish_notifications == other.publish_notifications
+            && self.field_profiling == other.field_profiling
+            && self.profile == other.profile
             && self.merge_policy == other.merge_policy
             && self.resources == other.resources
+            && self.metastore_payload_limits == other.metastore_payload_limits
     }
 }
 
@@ -207,6 +796,44 @@ impl IndexingSettings {
         true
     }
 
+    /// Applies the defaults of `profile`. Settings set to a value other than their default take
+    /// precedence over the profile.
+    pub fn apply_profile(&mut self, profile: IndexingProfile) {
+        let profile_settings = profile.settings();
+        if self.commit_timeout_secs == Self::default_commit_timeout_secs() {
+            self.commit_timeout_secs = profile_settings.commit_timeout_secs;
+        }
+        if self.split_num_docs_target == Self::default_split_num_docs_target() {
+            self.split_num_docs_target = profile_settings.split_num_docs_target;
+        }
+        if self.docstore_compression_level == Self::default_docstore_compression_level() {
+            self.docstore_compression_level = profile_settings.docstore_compression_level;
+        }
+        if self.merge_policy == MergePolicy::default() {
+            self.merge_policy.merge_factor = profile_settings.merge_factor;
+            self.merge_policy.max_merge_factor = profile_settings.max_merge_factor;
+        }
+        self.profile = Some(profile);
+    }
+
+    /// Returns the capacity of the indexer queue, selected by the profile.
+    pub fn indexer_queue_capacity(&self) -> usize {
+        self.profile
+            .map(|profile| profile.settings().indexer_queue_capacity)
+            .unwrap_or(10)
+    }
+
+    /// Returns the scheduling weight of the index, 1 by default.
+    pub fn scheduling_weight(&self) -> u32 {
+        self.scheduling_weight.unwrap_or(1)
+    }
+
+    /// Returns the period during which the files of the splits marked for deletion are kept, 120
+    /// seconds by default.
+    pub fn split_deletion_grace_period(&self) -> Duration {
+        Duration::from_secs(self.split_deletion_grace_period_secs.unwrap_or(120))
+    }
+
     pub fn sort_by(&self) -> SortBy {
         if let Some(field_name) = self.sort_field.clone() {
             let order = self.sort_order.unwrap_or_default();
@@ -237,17 +864,43 @@ impl Default for IndexingSettings {
             docstore_compression_level: Self::default_docstore_compression_level(),
             split_num_docs_target: Self::default_split_num_docs_target(),
             merge_enabled: Self::default_merge_enabled(),
+            max_open_partitions: None,
+            verify_splits_before_publish: false,
+            search_before_upload: false,
+            isolate_non_critical_failures: false,
+            multiplex_sources: false,
+            scheduling_weight: None,
+            split_deletion_grace_period_secs: None,
+            deduplication: None,
+            enrichment: None,
+            split_carry_over: None,
+            sharding: None,
+            storage_retry: None,
+            split_sampling: None,
+            dead_letter_queue: None,
+            tenant_routing: None,
+            calendar_tags: Vec::new(),
+            merge_coordination: None,
+            publish_notifications: None,
+            field_profiling: None,
+            profile: None,
             merge_policy: MergePolicy::default(),
             resources: IndexingResources::default(),
+            metastore_payload_limits: MetastorePayloadLimits::default(),
         }
     }
 }
 
-#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct SearchSettings {
     #[serde(default)]
     pub default_search_fields: Vec<String>,
+    /// Boosts of the scores of the matches on the fields, keyed by field name. They can be
+    /// overridden per query with the `field^boost` syntax of the search fields.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub field_boosts: BTreeMap<String, f32>,
 }
 
 /// Defines on which split attribute the retention policy is applied relatively.
@@ -324,7 +977,7 @@ impl RetentionPolicy {
         )
     }
 
-    fn validate(&self) -> anyhow::Result<()> {
+    pub(crate) fn validate(&self) -> anyhow::Result<()> {
         self.retention_period()?;
         self.evaluation_schedule()?;
         Ok(())
@@ -345,6 +998,9 @@ fn prepend_at_char(schedule: &str) -> String {
     trimmed_schedule.to_string()
 }
 
+/// Most recent version of the index config format.
+pub const INDEX_CONFIG_VERSION: usize = 0;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct IndexConfig {
@@ -415,7 +1071,7 @@ impl IndexConfig {
     }
 
     pub fn validate(&self) -> anyhow::Result<()> {
-        validate_identifier("Index ID", &self.index_id)?;
+        validate_index_id(&self.index_id)?;
 
         if let Some(retention_policy) = &self.retention_policy {
             retention_policy.validate()?;
@@ -451,85 +1107,385 @@ impl IndexConfig {
                  `merge_factor`."
             )
         }
-        Ok(())
-    }
-}
-
-/// Builds and returns the doc mapper associated with index.
-pub fn build_doc_mapper(
-    doc_mapping: &DocMapping,
-    search_settings: &SearchSettings,
-    indexing_settings: &IndexingSettings,
-) -> anyhow::Result<Arc<dyn DocMapper>> {
-    let sort_by = match indexing_settings.sort_by() {
-        SortBy::DocId => None,
-        SortBy::FastField { field_name, order } => Some(SortByConfig { field_name, order }),
-        SortBy::Score { order } => Some(SortByConfig {
-            field_name: "_score".to_string(),
-            order,
-        }),
-    };
-    let builder = DefaultDocMapperBuilder {
-        store_source: doc_mapping.store_source,
-        default_search_fields: search_settings.default_search_fields.clone(),
-        timestamp_field: indexing_settings.timestamp_field.clone(),
-        sort_by,
-        field_mappings: doc_mapping.field_mappings.clone(),
-        tag_fields: doc_mapping.tag_fields.iter().cloned().collect(),
-        mode: doc_mapping.mode,
-        dynamic_mapping: doc_mapping.dynamic_mapping.clone(),
-        partition_key: doc_mapping.partition_key.clone(),
-    };
-    Ok(Arc::new(builder.try_build()?))
-}
-
-/// Deserializes and validates a [`Uri`].
-fn deser_and_validate_uri<'de, D>(deserializer: D) -> Result<Option<Uri>, D::Error>
-where D: Deserializer<'de> {
-    let uri_opt: Option<String> = Deserialize::deserialize(deserializer)?;
-    uri_opt
-        .map(|uri| Uri::try_new(&uri))
-        .transpose()
-        .map_err(D::Error::custom)
-}
-
-#[cfg(test)]
-mod tests {
-
-    use cron::TimeUnitSpec;
-
-    use super::*;
-    use crate::SourceParams;
-
-    fn get_index_config_filepath(index_config_filename: &str) -> String {
-        format!(
-            "{}/resources/tests/index_config/{}",
-            env!("CARGO_MANIFEST_DIR"),
-            index_config_filename
-        )
-    }
-
-    macro_rules! test_parser {
-        ($test_function_name:ident, $file_extension:expr) => {
-            #[tokio::test]
-            async fn $test_function_name() -> anyhow::Result<()> {
-                let index_config_filepath = get_index_config_filepath(&format!(
-                    "hdfs-logs.{}",
-                    stringify!($file_extension)
-                ));
-                let file = std::fs::read_to_string(&index_config_filepath).unwrap();
-                let index_config = IndexConfig::load(
-                    &Uri::try_new(&index_config_filepath).unwrap(),
-                    file.as_bytes(),
+        if self.indexing_settings.resources.workbench_memory_limit == Some(Byte::from_bytes(0)) {
+            bail!("Index config resources `workbench_memory_limit` must be strictly positive.")
+        }
+        if self.indexing_settings.resources.split_write_buffer_size == Some(Byte::from_bytes(0)) {
+            bail!("Index config resources `split_write_buffer_size` must be strictly positive.")
+        }
+        if self.indexing_settings.resources.split_sync_interval == Some(Byte::from_bytes(0)) {
+            bail!("Index config resources `split_sync_interval` must be strictly positive.")
+        }
+        if self.indexing_settings.max_open_partitions == Some(0) {
+            bail!("Index config `max_open_partitions` must be strictly positive.")
+        }
+        if self.indexing_settings.scheduling_weight == Some(0) {
+            bail!("Index config `scheduling_weight` must be strictly positive.")
+        }
+        if let Some(deduplication_settings) = &self.indexing_settings.deduplication {
+            if deduplication_settings.window_secs == 0 {
+                bail!("Index config deduplication `window_secs` must be strictly positive.")
+            }
+        }
+        if let Some(split_carry_over_settings) = &self.indexing_settings.split_carry_over {
+            if split_carry_over_settings.min_num_docs == 0
+                && split_carry_over_settings.min_num_bytes == 0
+            {
+                bail!(
+                    "Index config split carry-over requires `min_num_docs` or `min_num_bytes` to \
+                     be strictly positive."
                 )
-                .await?;
-                assert_eq!(index_config.version, 0);
-
-                assert_eq!(index_config.doc_mapping.field_mappings.len(), 5);
-                assert_eq!(index_config.doc_mapping.field_mappings[0].name, "tenant_id");
-                assert_eq!(index_config.doc_mapping.field_mappings[1].name, "timestamp");
-                assert_eq!(
-                    index_config.doc_mapping.field_mappings[2].name,
+            }
+            if split_carry_over_settings.min_num_docs
+                >= self.indexing_settings.split_num_docs_target
+            {
+                bail!(
+                    "Index config split carry-over `min_num_docs` must be inferior to \
+                     `split_num_docs_target`."
+                )
+            }
+            if split_carry_over_settings.max_carry_over_secs == 0 {
+                bail!(
+                    "Index config split carry-over `max_carry_over_secs` must be strictly \
+                     positive."
+                )
+            }
+        }
+        if let Some(sharding_settings) = &self.indexing_settings.sharding {
+            if sharding_settings.num_shards < 2 {
+                bail!("Index config sharding `num_shards` must be greater than or equal to 2.")
+            }
+            if sharding_settings.routing_field.is_empty() {
+                bail!("Index config sharding `routing_field` must not be empty.")
+            }
+            if self.indexing_settings.split_carry_over.is_some() {
+                bail!("Index config sharding and split carry-over cannot be enabled together.")
+            }
+        }
+        if let Some(storage_retry_settings) = &self.indexing_settings.storage_retry {
+            if storage_retry_settings.request_timeout_secs == 0 {
+                bail!(
+                    "Index config storage retry `request_timeout_secs` must be strictly positive."
+                )
+            }
+            if storage_retry_settings.min_throughput_mib_per_sec == 0 {
+                bail!(
+                    "Index config storage retry `min_throughput_mib_per_sec` must be strictly \
+                     positive."
+                )
+            }
+            if storage_retry_settings.base_backoff_millis == 0
+                || storage_retry_settings.base_backoff_millis
+                    > storage_retry_settings.max_backoff_millis
+            {
+                bail!(
+                    "Index config storage retry `base_backoff_millis` must be strictly positive \
+                     and inferior or equal to `max_backoff_millis`."
+                )
+            }
+        }
+        let payload_limits = &self.indexing_settings.metastore_payload_limits;
+        for (payload, warn_size, max_size) in [
+            (
+                "split_metadata",
+                payload_limits.split_metadata_warn_size,
+                payload_limits.split_metadata_max_size,
+            ),
+            (
+                "checkpoint_delta",
+                payload_limits.checkpoint_delta_warn_size,
+                payload_limits.checkpoint_delta_max_size,
+            ),
+            (
+                "publish_request",
+                payload_limits.publish_request_warn_size,
+                payload_limits.publish_request_max_size,
+            ),
+        ] {
+            if max_size.get_bytes() == 0 || warn_size > max_size {
+                bail!(
+                    "Index config metastore payload limit `{}_max_size` must be strictly positive \
+                     and superior or equal to `{}_warn_size`.",
+                    payload,
+                    payload
+                )
+            }
+        }
+        if let Some(merge_coordination_settings) = &self.indexing_settings.merge_coordination {
+            if merge_coordination_settings.refresh_interval_secs == 0
+                || merge_coordination_settings.refresh_interval_secs
+                    >= merge_coordination_settings.lease_duration_secs
+            {
+                bail!(
+                    "Index config merge coordination `refresh_interval_secs` must be strictly \
+                     positive and inferior to `lease_duration_secs`."
+                )
+            }
+        }
+        if let Some(split_sampling_settings) = &self.indexing_settings.split_sampling {
+            if split_sampling_settings.num_docs == 0 {
+                bail!("Index config split sampling `num_docs` must be strictly positive.")
+            }
+        }
+        if let Some(dead_letter_queue_settings) = &self.indexing_settings.dead_letter_queue {
+            match (
+                &dead_letter_queue_settings.uri,
+                &dead_letter_queue_settings.index_id,
+            ) {
+                (Some(_), None) => {}
+                (None, Some(index_id)) => {
+                    validate_index_id(index_id)?;
+                    if *index_id == self.index_id {
+                        bail!(
+                            "Index config dead letter queue `index_id` must not be the index \
+                             itself."
+                        )
+                    }
+                }
+                _ => bail!(
+                    "Index config dead letter queue must set exactly one of `uri` and `index_id`."
+                ),
+            }
+            if dead_letter_queue_settings.max_file_size.get_bytes() == 0
+                || dead_letter_queue_settings.max_num_files == 0
+            {
+                bail!(
+                    "Index config dead letter queue `max_file_size` and `max_num_files` must be \
+                     strictly positive."
+                )
+            }
+        }
+        if let Some(publish_notification_settings) = &self.indexing_settings.publish_notifications {
+            match (
+                &publish_notification_settings.webhook_url,
+                &publish_notification_settings.kafka_topic,
+            ) {
+                (Some(webhook_url), None) => {
+                    if !webhook_url.starts_with("http://") && !webhook_url.starts_with("https://") {
+                        bail!(
+                            "Index config publish notifications `webhook_url` must be an HTTP or \
+                             HTTPS URL."
+                        )
+                    }
+                }
+                (None, Some(kafka_topic)) => {
+                    if kafka_topic.is_empty() {
+                        bail!("Index config publish notifications `kafka_topic` must not be empty.")
+                    }
+                }
+                _ => bail!(
+                    "Index config publish notifications must set exactly one of `webhook_url` and \
+                     `kafka_topic`."
+                ),
+            }
+            if publish_notification_settings.max_retry_delay_secs == 0 {
+                bail!(
+                    "Index config publish notifications `max_retry_delay_secs` must be strictly \
+                     positive."
+                )
+            }
+        }
+        if let Some(field_profiling_settings) = &self.indexing_settings.field_profiling {
+            if field_profiling_settings.sample_interval == 0 {
+                bail!("Index config field profiling `sample_interval` must be strictly positive.")
+            }
+        }
+        if let Some(tenant_routing_settings) = &self.indexing_settings.tenant_routing {
+            if tenant_routing_settings.tenant_field.is_empty() {
+                bail!("Index config tenant routing `tenant_field` must not be empty.")
+            }
+            if !tenant_routing_settings
+                .index_id_template
+                .contains("{tenant}")
+            {
+                bail!(
+                    "Index config tenant routing `index_id_template` must contain the \
+                     `{{tenant}}` placeholder."
+                )
+            }
+            if self.indexing_settings.sharding.is_some() {
+                bail!("Index config tenant routing and sharding cannot be enabled together.")
+            }
+            if let Some(retention_policy) = &tenant_routing_settings.retention_policy {
+                retention_policy.validate()?;
+                if retention_policy.requires_timestamp_field()
+                    && self.indexing_settings.timestamp_field.is_none()
+                {
+                    bail!(
+                        "Index config tenant routing retention policy cutoff reference requires a \
+                         timestamp field, but the indexing settings do not declare one."
+                    );
+                }
+            }
+            for tenant in &tenant_routing_settings.tenants {
+                tenant_routing_settings
+                    .tenant_index_id(&self.index_id, tenant)
+                    .with_context(|| {
+                        format!(
+                            "Index config tenant routing yields an invalid index ID for tenant \
+                             `{}`.",
+                            tenant
+                        )
+                    })?;
+            }
+        }
+        if !self.indexing_settings.calendar_tags.is_empty()
+            && self.indexing_settings.timestamp_field.is_none()
+        {
+            bail!("Index config calendar tags require a timestamp field.")
+        }
+        for calendar_tag_granularity in &self.indexing_settings.calendar_tags {
+            let tag_field_name = calendar_tag_granularity.tag_field_name();
+            if self.doc_mapping.tag_fields.contains(tag_field_name) {
+                bail!(
+                    "Index config calendar tag `{}` must not be a tag field.",
+                    tag_field_name
+                )
+            }
+        }
+        if let Some(enrichment_settings) = &self.indexing_settings.enrichment {
+            if enrichment_settings.namespace.is_empty() {
+                bail!("Index config enrichment `namespace` must not be empty.")
+            }
+            if self.doc_mapping.mode == ModeType::Strict
+                && !self
+                    .doc_mapping
+                    .field_mappings
+                    .iter()
+                    .any(|field_mapping| field_mapping.name == enrichment_settings.namespace)
+            {
+                bail!(
+                    "Index config enrichment namespace `{}` must be mapped when the doc mapping \
+                     mode is `strict`.",
+                    enrichment_settings.namespace
+                )
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builds and returns the doc mapper associated with index.
+pub fn build_doc_mapper(
+    doc_mapping: &DocMapping,
+    search_settings: &SearchSettings,
+    indexing_settings: &IndexingSettings,
+) -> anyhow::Result<Arc<dyn DocMapper>> {
+    let sort_by = match indexing_settings.sort_by() {
+        SortBy::DocId => None,
+        SortBy::FastField { field_name, order } => Some(SortByConfig { field_name, order }),
+        SortBy::Score { order } => Some(SortByConfig {
+            field_name: "_score".to_string(),
+            order,
+        }),
+    };
+    let builder = DefaultDocMapperBuilder {
+        store_source: doc_mapping.store_source,
+        default_search_fields: search_settings.default_search_fields.clone(),
+        field_boosts: search_settings.field_boosts.clone(),
+        timestamp_field: indexing_settings.timestamp_field.clone(),
+        expire_timestamp_field: doc_mapping.expire_timestamp_field.clone(),
+        sequence_field: doc_mapping.sequence_field.clone(),
+        sort_by,
+        field_mappings: doc_mapping.field_mappings.clone(),
+        tag_fields: doc_mapping.tag_fields.iter().cloned().collect(),
+        mode: doc_mapping.mode,
+        dynamic_mapping: doc_mapping.dynamic_mapping.clone(),
+        partition_key: doc_mapping.partition_key.clone(),
+        doc_id: doc_mapping.doc_id.clone(),
+    };
+    Ok(Arc::new(builder.try_build()?))
+}
+
+/// Validates the update of the doc mapping of an index: only field mappings can be added or
+/// removed, and the fields kept must be mapped exactly as before, as the splits created with the
+/// previous doc mapping are searched with the new one.
+pub fn validate_doc_mapping_update(
+    doc_mapping: &DocMapping,
+    new_doc_mapping: &DocMapping,
+    new_search_settings: &SearchSettings,
+    indexing_settings: &IndexingSettings,
+) -> anyhow::Result<()> {
+    let without_field_mappings = |doc_mapping: &DocMapping| -> anyhow::Result<JsonValue> {
+        let mut doc_mapping = doc_mapping.clone();
+        doc_mapping.field_mappings.clear();
+        Ok(serde_json::to_value(doc_mapping)?)
+    };
+    if without_field_mappings(doc_mapping)? != without_field_mappings(new_doc_mapping)? {
+        bail!("Only the field mappings of the doc mapping of an existing index can be updated.");
+    }
+    let doc_mapper = build_doc_mapper(doc_mapping, &SearchSettings::default(), indexing_settings)?;
+    let new_doc_mapper = build_doc_mapper(new_doc_mapping, new_search_settings, indexing_settings)?;
+    let schema = doc_mapper.schema();
+    let new_schema = new_doc_mapper.schema();
+    for (_, field_entry) in schema.fields() {
+        if let Some(new_field) = new_schema.get_field(field_entry.name()) {
+            if new_schema.get_field_entry(new_field) != field_entry {
+                bail!(
+                    "Field `{}` cannot be modified: only new fields can be added and existing \
+                     fields removed.",
+                    field_entry.name()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Deserializes and validates a [`Uri`].
+pub(crate) fn deser_and_validate_uri<'de, D>(deserializer: D) -> Result<Option<Uri>, D::Error>
+where D: Deserializer<'de> {
+    let uri_opt: Option<String> = Deserialize::deserialize(deserializer)?;
+    uri_opt
+        .map(|uri| Uri::try_new(&uri))
+        .transpose()
+        .map_err(D::Error::custom)
+}
+
+/// Deserializes and validates a required [`Uri`].
+fn deser_and_validate_required_uri<'de, D>(deserializer: D) -> Result<Uri, D::Error>
+where D: Deserializer<'de> {
+    let uri: String = Deserialize::deserialize(deserializer)?;
+    Uri::try_new(&uri).map_err(D::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cron::TimeUnitSpec;
+
+    use super::*;
+    use crate::SourceParams;
+
+    fn get_index_config_filepath(index_config_filename: &str) -> String {
+        format!(
+            "{}/resources/tests/index_config/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            index_config_filename
+        )
+    }
+
+    macro_rules! test_parser {
+        ($test_function_name:ident, $file_extension:expr) => {
+            #[tokio::test]
+            async fn $test_function_name() -> anyhow::Result<()> {
+                let index_config_filepath = get_index_config_filepath(&format!(
+                    "hdfs-logs.{}",
+                    stringify!($file_extension)
+                ));
+                let file = std::fs::read_to_string(&index_config_filepath).unwrap();
+                let index_config = IndexConfig::load(
+                    &Uri::try_new(&index_config_filepath).unwrap(),
+                    file.as_bytes(),
+                )
+                .await?;
+                assert_eq!(index_config.version, 0);
+
+                assert_eq!(index_config.doc_mapping.field_mappings.len(), 5);
+                assert_eq!(index_config.doc_mapping.field_mappings[0].name, "tenant_id");
+                assert_eq!(index_config.doc_mapping.field_mappings[1].name, "timestamp");
+                assert_eq!(
+                    index_config.doc_mapping.field_mappings[2].name,
                     "severity_text"
                 );
                 assert_eq!(index_config.doc_mapping.field_mappings[3].name, "body");
@@ -584,7 +1540,10 @@ mod tests {
                     index_config.indexing_settings.resources,
                     IndexingResources {
                         __num_threads_deprecated: serde::de::IgnoredAny,
-                        heap_size: Byte::from_bytes(3_000_000_000)
+                        heap_size: Byte::from_bytes(3_000_000_000),
+                        workbench_memory_limit: None,
+                        split_write_buffer_size: None,
+                        split_sync_interval: None,
                     }
                 );
                 assert_eq!(
@@ -594,6 +1553,7 @@ mod tests {
                             "severity_text".to_string(),
                             "body".to_string()
                         ],
+                        field_boosts: BTreeMap::new(),
                     }
                 );
                 assert_eq!(index_config.sources.len(), 2);
@@ -641,6 +1601,7 @@ mod tests {
                 index_config.search_settings,
                 SearchSettings {
                     default_search_fields: vec!["body".to_string()],
+                    field_boosts: BTreeMap::new(),
                 }
             );
             assert!(index_config.sources.is_empty());
@@ -682,6 +1643,7 @@ mod tests {
                 index_config.search_settings,
                 SearchSettings {
                     default_search_fields: vec!["body".to_string()],
+                    field_boosts: BTreeMap::new(),
                 }
             );
             assert!(index_config.sources.is_empty());
@@ -720,11 +1682,19 @@ mod tests {
                 SourceConfig {
                     source_id: "void_1".to_string(),
                     num_pipelines: 1,
+                    profile: None,
+                    rate_limit: None,
+                    transforms: Vec::new(),
+                    strict_ordering: false,
                     source_params: SourceParams::void(),
                 },
                 SourceConfig {
                     source_id: "void_1".to_string(),
                     num_pipelines: 1,
+                    profile: None,
+                    rate_limit: None,
+                    transforms: Vec::new(),
+                    strict_ordering: false,
                     source_params: SourceParams::void(),
                 },
             ];
@@ -735,12 +1705,233 @@ mod tests {
                 .to_string()
                 .contains("Index config contains duplicate sources."));
         }
+        {
+            // Enrich documents under a namespace not mapped in strict mode.
+            let mut invalid_index_config = index_config.clone();
+            invalid_index_config.doc_mapping.mode = ModeType::Strict;
+            invalid_index_config.indexing_settings.enrichment = Some(EnrichmentSettings::default());
+            assert!(invalid_index_config
+                .validate()
+                .unwrap_err()
+                .to_string()
+                .contains(
+                    "Index config enrichment namespace `_quickwit` must be mapped when the doc \
+                     mapping mode is `strict`."
+                ));
+        }
+        {
+            // Carry over splits as large as the target split size.
+            let mut invalid_index_config = index_config.clone();
+            invalid_index_config.indexing_settings.split_carry_over =
+                Some(SplitCarryOverSettings {
+                    min_num_docs: index_config.indexing_settings.split_num_docs_target,
+                    min_num_bytes: 0,
+                    max_carry_over_secs: 600,
+                });
+            assert!(invalid_index_config
+                .validate()
+                .unwrap_err()
+                .to_string()
+                .contains(
+                    "Index config split carry-over `min_num_docs` must be inferior to \
+                     `split_num_docs_target`."
+                ));
+        }
+        {
+            // Commit the workbench before indexing any document.
+            let mut invalid_index_config = index_config.clone();
+            invalid_index_config
+                .indexing_settings
+                .resources
+                .workbench_memory_limit = Some(Byte::from_bytes(0));
+            assert!(invalid_index_config
+                .validate()
+                .unwrap_err()
+                .to_string()
+                .contains(
+                    "Index config resources `workbench_memory_limit` must be strictly positive."
+                ));
+        }
+        {
+            // Flush every write of the split files.
+            let mut invalid_index_config = index_config.clone();
+            invalid_index_config
+                .indexing_settings
+                .resources
+                .split_write_buffer_size = Some(Byte::from_bytes(0));
+            assert!(invalid_index_config
+                .validate()
+                .unwrap_err()
+                .to_string()
+                .contains(
+                    "Index config resources `split_write_buffer_size` must be strictly positive."
+                ));
+        }
+        {
+            // Never grant indexing time to the index.
+            let mut invalid_index_config = index_config.clone();
+            invalid_index_config.indexing_settings.scheduling_weight = Some(0);
+            assert!(invalid_index_config
+                .validate()
+                .unwrap_err()
+                .to_string()
+                .contains("Index config `scheduling_weight` must be strictly positive."));
+        }
+        {
+            // Tag splits with calendar buckets without timestamp field.
+            let mut invalid_index_config = index_config.clone();
+            invalid_index_config.indexing_settings.calendar_tags =
+                vec![CalendarTagGranularity::Day];
+            assert!(invalid_index_config
+                .validate()
+                .unwrap_err()
+                .to_string()
+                .contains("Index config calendar tags require a timestamp field."));
+        }
+        {
+            // Shard a pipeline across a single indexer.
+            let mut invalid_index_config = index_config.clone();
+            invalid_index_config.indexing_settings.sharding = Some(ShardingSettings {
+                num_shards: 1,
+                routing_field: "tenant_id".to_string(),
+            });
+            assert!(invalid_index_config
+                .validate()
+                .unwrap_err()
+                .to_string()
+                .contains(
+                    "Index config sharding `num_shards` must be greater than or equal to 2."
+                ));
+        }
+        {
+            // Back off longer on the first retry than on the last ones.
+            let mut invalid_index_config = index_config.clone();
+            invalid_index_config.indexing_settings.storage_retry = Some(StorageRetrySettings {
+                base_backoff_millis: 1_000,
+                max_backoff_millis: 100,
+                ..Default::default()
+            });
+            assert!(invalid_index_config
+                .validate()
+                .unwrap_err()
+                .to_string()
+                .contains(
+                    "Index config storage retry `base_backoff_millis` must be strictly positive \
+                     and inferior or equal to `max_backoff_millis`."
+                ));
+        }
+        {
+            // Warn about checkpoint deltas larger than the ones that are rejected.
+            let mut invalid_index_config = index_config.clone();
+            invalid_index_config
+                .indexing_settings
+                .metastore_payload_limits
+                .checkpoint_delta_warn_size = Byte::from_bytes(10_000_000);
+            assert!(invalid_index_config
+                .validate()
+                .unwrap_err()
+                .to_string()
+                .contains(
+                    "Index config metastore payload limit `checkpoint_delta_max_size` must be \
+                     strictly positive and superior or equal to `checkpoint_delta_warn_size`."
+                ));
+        }
+        {
+            // Let the lease expire between two renewals.
+            let mut invalid_index_config = index_config.clone();
+            invalid_index_config.indexing_settings.merge_coordination =
+                Some(MergeCoordinationSettings {
+                    lease_duration_secs: 10,
+                    refresh_interval_secs: 30,
+                });
+            assert!(invalid_index_config
+                .validate()
+                .unwrap_err()
+                .to_string()
+                .contains(
+                    "Index config merge coordination `refresh_interval_secs` must be strictly \
+                     positive and inferior to `lease_duration_secs`."
+                ));
+        }
+        {
+            // Tenant routing template without tenant placeholder.
+            let mut invalid_index_config = index_config.clone();
+            invalid_index_config.indexing_settings.tenant_routing = Some(TenantRoutingSettings {
+                tenant_field: "tenant_id".to_string(),
+                tenants: Vec::new(),
+                index_id_template: "{index_id}-tenant".to_string(),
+                retention_policy: None,
+                merge_policy: None,
+            });
+            assert!(invalid_index_config
+                .validate()
+                .unwrap_err()
+                .to_string()
+                .contains(
+                    "Index config tenant routing `index_id_template` must contain the `{tenant}` \
+                     placeholder."
+                ));
+        }
+        {
+            // Set both publish notification sinks.
+            let mut invalid_index_config = index_config.clone();
+            invalid_index_config.indexing_settings.publish_notifications =
+                Some(PublishNotificationSettings {
+                    webhook_url: Some("https://example.com/hooks/published-splits".to_string()),
+                    kafka_topic: Some("published-splits".to_string()),
+                    kafka_client_params: serde_json::Value::Null,
+                    max_retry_delay_secs: 60,
+                });
+            assert!(invalid_index_config
+                .validate()
+                .unwrap_err()
+                .to_string()
+                .contains(
+                    "Index config publish notifications must set exactly one of `webhook_url` and \
+                     `kafka_topic`."
+                ));
+        }
+        {
+            // Profile no documents.
+            let mut invalid_index_config = index_config.clone();
+            invalid_index_config.indexing_settings.field_profiling =
+                Some(FieldProfilingSettings { sample_interval: 0 });
+            assert!(invalid_index_config
+                .validate()
+                .unwrap_err()
+                .to_string()
+                .contains(
+                    "Index config field profiling `sample_interval` must be strictly positive."
+                ));
+        }
+        {
+            // Set both dead letter sinks.
+            let mut invalid_index_config = index_config.clone();
+            invalid_index_config.indexing_settings.dead_letter_queue =
+                Some(DeadLetterQueueSettings {
+                    uri: Some(Uri::try_new("s3://quickwit-dlq/hdfs-logs").unwrap()),
+                    index_id: Some("hdfs-logs-dlq".to_string()),
+                    max_file_size: Byte::from_bytes(1_000_000),
+                    max_num_files: 10,
+                });
+            assert!(invalid_index_config
+                .validate()
+                .unwrap_err()
+                .to_string()
+                .contains(
+                    "Index config dead letter queue must set exactly one of `uri` and `index_id`."
+                ));
+        }
         {
             // Add source file params with no filepath.
             let mut invalid_index_config = index_config;
             invalid_index_config.sources = vec![SourceConfig {
                 source_id: "file_params_1".to_string(),
                 num_pipelines: 1,
+                profile: None,
+                rate_limit: None,
+                transforms: Vec::new(),
+                strict_ordering: false,
                 source_params: SourceParams::stdin(),
             }];
             assert!(invalid_index_config.validate().is_err());
@@ -775,6 +1966,556 @@ mod tests {
         assert_eq!(minimal_config.doc_mapping.mode, ModeType::Lenient);
     }
 
+    #[test]
+    fn test_index_config_doc_id() {
+        let config_yaml = r#"
+            version: 0
+            index_id: hdfs-logs
+            doc_mapping:
+              doc_id:
+                source: field
+                field: request_id
+        "#;
+        let index_config = serde_yaml::from_str::<IndexConfig>(config_yaml).unwrap();
+        assert_eq!(
+            index_config.doc_mapping.doc_id,
+            Some(DocIdConfig::Field {
+                field: "request_id".to_string()
+            })
+        );
+        let doc_mapper = build_doc_mapper(
+            &index_config.doc_mapping,
+            &index_config.search_settings,
+            &index_config.indexing_settings,
+        )
+        .unwrap();
+        assert!(doc_mapper.schema().get_field("_id").is_some());
+    }
+
+    #[test]
+    fn test_indexing_settings_verify_splits_before_publish() {
+        let indexing_settings_yaml = r#"
+            verify_splits_before_publish: true
+        "#;
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+        assert!(indexing_settings.verify_splits_before_publish);
+
+        let default_indexing_settings_json =
+            serde_json::to_value(&IndexingSettings::default()).unwrap();
+        assert!(default_indexing_settings_json
+            .get("verify_splits_before_publish")
+            .is_none());
+    }
+
+    #[test]
+    fn test_indexing_settings_search_before_upload() {
+        let indexing_settings_yaml = r#"
+            search_before_upload: true
+        "#;
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+        assert!(indexing_settings.search_before_upload);
+        assert!(!IndexingSettings::default().search_before_upload);
+    }
+
+    #[test]
+    fn test_indexing_settings_max_open_partitions() {
+        let indexing_settings_yaml = r#"
+            max_open_partitions: 100
+        "#;
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+        assert_eq!(indexing_settings.max_open_partitions, Some(100));
+        assert!(IndexingSettings::default().max_open_partitions.is_none());
+    }
+
+    #[test]
+    fn test_indexing_settings_isolate_non_critical_failures() {
+        let indexing_settings_yaml = r#"
+            isolate_non_critical_failures: true
+        "#;
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+        assert!(indexing_settings.isolate_non_critical_failures);
+
+        let default_indexing_settings_json =
+            serde_json::to_value(&IndexingSettings::default()).unwrap();
+        assert!(default_indexing_settings_json
+            .get("isolate_non_critical_failures")
+            .is_none());
+    }
+
+    #[test]
+    fn test_indexing_settings_multiplex_sources() {
+        let indexing_settings_yaml = r#"
+            multiplex_sources: true
+        "#;
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+        assert!(indexing_settings.multiplex_sources);
+        assert!(!IndexingSettings::default().multiplex_sources);
+    }
+
+    #[test]
+    fn test_indexing_settings_scheduling_weight() {
+        let indexing_settings_yaml = r#"
+            scheduling_weight: 4
+        "#;
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+        assert_eq!(indexing_settings.scheduling_weight(), 4);
+        assert_eq!(IndexingSettings::default().scheduling_weight(), 1);
+    }
+
+    #[test]
+    fn test_indexing_settings_split_deletion_grace_period() {
+        let indexing_settings_yaml = r#"
+            split_deletion_grace_period_secs: 600
+        "#;
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+        assert_eq!(
+            indexing_settings.split_deletion_grace_period(),
+            Duration::from_secs(600)
+        );
+        assert_eq!(
+            IndexingSettings::default().split_deletion_grace_period(),
+            Duration::from_secs(120)
+        );
+    }
+
+    #[test]
+    fn test_indexing_settings_deduplication() {
+        {
+            let indexing_settings_yaml = r#"
+                deduplication:
+                    doc_id_field: event_id
+            "#;
+            let indexing_settings =
+                serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+            assert_eq!(
+                indexing_settings.deduplication.unwrap(),
+                DeduplicationSettings {
+                    doc_id_field: Some("event_id".to_string()),
+                    window_secs: 3_600,
+                }
+            );
+        }
+        {
+            let indexing_settings_yaml = r#"
+                deduplication:
+                    window_secs: 60
+            "#;
+            let indexing_settings =
+                serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+            let deduplication_settings = indexing_settings.deduplication.unwrap();
+            assert!(deduplication_settings.doc_id_field.is_none());
+            assert_eq!(deduplication_settings.window(), Duration::from_secs(60));
+        }
+    }
+
+    #[test]
+    fn test_indexing_settings_enrichment() {
+        {
+            let indexing_settings_yaml = r#"
+                enrichment: {}
+            "#;
+            let indexing_settings =
+                serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+            assert_eq!(
+                indexing_settings.enrichment.unwrap().namespace,
+                "_quickwit".to_string()
+            );
+        }
+        {
+            let indexing_settings_yaml = r#"
+                enrichment:
+                    namespace: pipeline
+            "#;
+            let indexing_settings =
+                serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+            assert_eq!(
+                indexing_settings.enrichment.unwrap().namespace,
+                "pipeline".to_string()
+            );
+        }
+        {
+            let default_indexing_settings_json =
+                serde_json::to_value(&IndexingSettings::default()).unwrap();
+            assert!(default_indexing_settings_json.get("enrichment").is_none());
+        }
+    }
+
+    #[test]
+    fn test_indexing_settings_split_carry_over() {
+        let indexing_settings_yaml = r#"
+            split_carry_over:
+                min_num_docs: 1000
+        "#;
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+        let split_carry_over_settings = indexing_settings.split_carry_over.unwrap();
+        assert_eq!(
+            split_carry_over_settings,
+            SplitCarryOverSettings {
+                min_num_docs: 1000,
+                min_num_bytes: 0,
+                max_carry_over_secs: 600,
+            }
+        );
+        assert!(split_carry_over_settings.is_below_threshold(999, 1_000_000));
+        assert!(!split_carry_over_settings.is_below_threshold(1000, 0));
+    }
+
+    #[test]
+    fn test_indexing_settings_sharding() {
+        let indexing_settings_yaml = r#"
+            sharding:
+                num_shards: 4
+                routing_field: tenant_id
+        "#;
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+        assert_eq!(
+            indexing_settings.sharding.unwrap(),
+            ShardingSettings {
+                num_shards: 4,
+                routing_field: "tenant_id".to_string(),
+            }
+        );
+        let default_indexing_settings_json =
+            serde_json::to_value(&IndexingSettings::default()).unwrap();
+        assert!(default_indexing_settings_json.get("sharding").is_none());
+    }
+
+    #[test]
+    fn test_indexing_settings_storage_retry() {
+        let indexing_settings_yaml = r#"
+            storage_retry:
+                request_timeout_secs: 5
+                max_retries: 10
+        "#;
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+        let storage_retry_settings = indexing_settings.storage_retry.unwrap();
+        assert_eq!(
+            storage_retry_settings,
+            StorageRetrySettings {
+                request_timeout_secs: 5,
+                min_throughput_mib_per_sec: 8,
+                max_retries: 10,
+                base_backoff_millis: 250,
+                max_backoff_millis: 20_000,
+            }
+        );
+        assert_eq!(
+            storage_retry_settings.request_timeout(),
+            Duration::from_secs(5)
+        );
+        let default_indexing_settings_json =
+            serde_json::to_value(&IndexingSettings::default()).unwrap();
+        assert!(default_indexing_settings_json
+            .get("storage_retry")
+            .is_none());
+    }
+
+    #[test]
+    fn test_indexing_settings_metastore_payload_limits() {
+        let indexing_settings_yaml = r#"
+            metastore_payload_limits:
+                split_metadata_warn_size: 10KB
+                split_metadata_max_size: 100KB
+        "#;
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+        let payload_limits = &indexing_settings.metastore_payload_limits;
+        assert_eq!(
+            payload_limits.split_metadata_warn_size,
+            Byte::from_bytes(10_000)
+        );
+        assert_eq!(
+            payload_limits.split_metadata_max_size,
+            Byte::from_bytes(100_000)
+        );
+        assert_eq!(
+            payload_limits.publish_request_max_size,
+            Byte::from_bytes(16_000_000)
+        );
+        let default_indexing_settings_json =
+            serde_json::to_value(&IndexingSettings::default()).unwrap();
+        assert!(default_indexing_settings_json
+            .get("metastore_payload_limits")
+            .is_none());
+    }
+
+    #[test]
+    fn test_indexing_settings_merge_coordination() {
+        let indexing_settings_yaml = r#"
+            merge_coordination:
+                lease_duration_secs: 120
+        "#;
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+        let merge_coordination_settings = indexing_settings.merge_coordination.unwrap();
+        assert_eq!(
+            merge_coordination_settings,
+            MergeCoordinationSettings {
+                lease_duration_secs: 120,
+                refresh_interval_secs: 15,
+            }
+        );
+        assert_eq!(
+            merge_coordination_settings.refresh_interval(),
+            Duration::from_secs(15)
+        );
+        let default_indexing_settings_json =
+            serde_json::to_value(&IndexingSettings::default()).unwrap();
+        assert!(default_indexing_settings_json
+            .get("merge_coordination")
+            .is_none());
+    }
+
+    #[test]
+    fn test_indexing_settings_split_sampling() {
+        let indexing_settings_yaml = r#"
+            split_sampling:
+                sample_uri: s3://quickwit-samples/hdfs-logs
+        "#;
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+        assert_eq!(
+            indexing_settings.split_sampling.unwrap(),
+            SplitSamplingSettings {
+                num_docs: 100,
+                sample_uri: Uri::try_new("s3://quickwit-samples/hdfs-logs").unwrap(),
+            }
+        );
+        let missing_uri_yaml = r#"
+            split_sampling:
+                num_docs: 10
+        "#;
+        assert!(serde_yaml::from_str::<IndexingSettings>(missing_uri_yaml).is_err());
+    }
+
+    #[test]
+    fn test_indexing_settings_dead_letter_queue() {
+        let indexing_settings_yaml = r#"
+            dead_letter_queue:
+                uri: s3://quickwit-dlq/hdfs-logs
+                max_file_size: 1MB
+        "#;
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+        assert_eq!(
+            indexing_settings.dead_letter_queue.unwrap(),
+            DeadLetterQueueSettings {
+                uri: Some(Uri::try_new("s3://quickwit-dlq/hdfs-logs").unwrap()),
+                index_id: None,
+                max_file_size: Byte::from_bytes(1_000_000),
+                max_num_files: 100,
+            }
+        );
+        let index_id_yaml = r#"
+            dead_letter_queue:
+                index_id: hdfs-logs-dlq
+        "#;
+        let indexing_settings = serde_yaml::from_str::<IndexingSettings>(index_id_yaml).unwrap();
+        assert_eq!(
+            indexing_settings
+                .dead_letter_queue
+                .unwrap()
+                .index_id
+                .as_deref(),
+            Some("hdfs-logs-dlq")
+        );
+    }
+
+    #[test]
+    fn test_indexing_settings_publish_notifications() {
+        let indexing_settings_yaml = r#"
+            publish_notifications:
+                kafka_topic: hdfs-logs-published-splits
+                kafka_client_params:
+                    bootstrap.servers: localhost:9092
+        "#;
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+        let publish_notification_settings = indexing_settings.publish_notifications.unwrap();
+        assert_eq!(
+            publish_notification_settings.kafka_topic.as_deref(),
+            Some("hdfs-logs-published-splits")
+        );
+        assert_eq!(
+            publish_notification_settings.kafka_client_params,
+            serde_json::json!({"bootstrap.servers": "localhost:9092"})
+        );
+        assert_eq!(
+            publish_notification_settings.max_retry_delay(),
+            Duration::from_secs(60)
+        );
+    }
+
+    #[test]
+    fn test_indexing_settings_field_profiling() {
+        let indexing_settings_yaml = r#"
+            field_profiling: {}
+        "#;
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+        assert_eq!(
+            indexing_settings.field_profiling,
+            Some(FieldProfilingSettings {
+                sample_interval: 100
+            })
+        );
+    }
+
+    #[test]
+    fn test_indexing_settings_tenant_routing() {
+        let indexing_settings_yaml = r#"
+            tenant_routing:
+                tenant_field: tenant_id
+                tenants: [acme, globex]
+                retention_policy:
+                    period: 7 days
+                    cutoff_reference: split_timestamp_field
+                    schedule: daily
+        "#;
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+        let tenant_routing_settings = indexing_settings.tenant_routing.unwrap();
+        assert_eq!(tenant_routing_settings.tenant_field, "tenant_id");
+        assert_eq!(
+            tenant_routing_settings.index_id_template,
+            "{index_id}-{tenant}"
+        );
+        assert!(tenant_routing_settings.is_routed("acme"));
+        assert!(!tenant_routing_settings.is_routed("initech"));
+        assert!(tenant_routing_settings.merge_policy.is_none());
+        assert_eq!(
+            tenant_routing_settings
+                .tenant_index_id("hdfs-logs", "acme")
+                .unwrap(),
+            "hdfs-logs-acme"
+        );
+        assert!(tenant_routing_settings
+            .tenant_index_id("hdfs-logs", "acme corp")
+            .is_err());
+    }
+
+    #[test]
+    fn test_indexing_settings_calendar_tags() {
+        let indexing_settings_yaml = r#"
+            calendar_tags: [day, month]
+        "#;
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+        assert_eq!(
+            indexing_settings.calendar_tags,
+            [CalendarTagGranularity::Day, CalendarTagGranularity::Month]
+        );
+        let default_indexing_settings_json =
+            serde_json::to_value(&IndexingSettings::default()).unwrap();
+        assert!(default_indexing_settings_json
+            .get("calendar_tags")
+            .is_none());
+    }
+
+    #[test]
+    fn test_indexing_resources_workbench_memory_limit() {
+        let indexing_settings_yaml = r#"
+            resources:
+                heap_size: 1G
+                workbench_memory_limit: 4G
+        "#;
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+        assert_eq!(
+            indexing_settings.resources.workbench_memory_limit,
+            Some(Byte::from_bytes(4_000_000_000))
+        );
+        let default_indexing_settings_json =
+            serde_json::to_value(&IndexingSettings::default()).unwrap();
+        assert!(default_indexing_settings_json["resources"]
+            .get("workbench_memory_limit")
+            .is_none());
+    }
+
+    #[test]
+    fn test_indexing_resources_split_write_settings() {
+        let indexing_settings_yaml = r#"
+            resources:
+                split_write_buffer_size: 1MB
+                split_sync_interval: 64MB
+        "#;
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+        assert_eq!(
+            indexing_settings.resources.split_write_buffer_size,
+            Some(Byte::from_bytes(1_000_000))
+        );
+        assert_eq!(
+            indexing_settings.resources.split_sync_interval,
+            Some(Byte::from_bytes(64_000_000))
+        );
+        let default_indexing_settings_json =
+            serde_json::to_value(&IndexingSettings::default()).unwrap();
+        assert!(default_indexing_settings_json["resources"]
+            .get("split_write_buffer_size")
+            .is_none());
+        assert!(default_indexing_settings_json["resources"]
+            .get("split_sync_interval")
+            .is_none());
+    }
+
+    #[test]
+    fn test_indexing_settings_apply_profile() {
+        let indexing_settings_yaml = r#"
+            profile: low-latency
+            commit_timeout_secs: 30
+        "#;
+        let mut indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>(indexing_settings_yaml).unwrap();
+        assert_eq!(indexing_settings.profile, Some(IndexingProfile::LowLatency));
+        assert_eq!(indexing_settings.indexer_queue_capacity(), 2);
+
+        indexing_settings.apply_profile(IndexingProfile::CostOptimized);
+        // The commit timeout set explicitly takes precedence over the profile.
+        assert_eq!(indexing_settings.commit_timeout_secs, 30);
+        assert_eq!(indexing_settings.split_num_docs_target, 20_000_000);
+        assert_eq!(indexing_settings.docstore_compression_level, 19);
+        assert_eq!(indexing_settings.merge_policy.merge_factor, 20);
+        assert_eq!(indexing_settings.merge_policy.max_merge_factor, 30);
+        assert_eq!(indexing_settings.indexer_queue_capacity(), 10);
+        assert_eq!(IndexingSettings::default().indexer_queue_capacity(), 10);
+    }
+
+    #[test]
+    fn test_merge_policy_type_and_params() {
+        let merge_policy_yaml = r#"
+            type: time_bucket
+            merge_factor: 5
+            params:
+                bucket_duration_secs: 3600
+        "#;
+        let merge_policy = serde_yaml::from_str::<MergePolicy>(merge_policy_yaml).unwrap();
+        assert_eq!(merge_policy.merge_policy_type, "time_bucket");
+        assert_eq!(merge_policy.merge_factor, 5);
+        assert_eq!(merge_policy.max_merge_factor, 12);
+        assert_eq!(merge_policy.params["bucket_duration_secs"], 3600);
+        assert_ne!(merge_policy, MergePolicy::default());
+
+        let default_merge_policy = serde_yaml::from_str::<MergePolicy>("{}").unwrap();
+        assert_eq!(
+            default_merge_policy.merge_policy_type,
+            MergePolicy::STABLE_MULTITENANT_TYPE
+        );
+        let default_merge_policy_json = serde_json::to_value(&default_merge_policy).unwrap();
+        assert!(default_merge_policy_json.get("type").is_none());
+        assert!(default_merge_policy_json.get("params").is_none());
+    }
+
     #[test]
     fn test_retention_policy_serialization() {
         let retention_policy = RetentionPolicy {
@@ -790,6 +2531,92 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate_doc_mapping_update() {
+        let doc_mapping: DocMapping = serde_yaml::from_str(
+            r#"
+            field_mappings:
+              - name: timestamp
+                type: i64
+                fast: true
+              - name: body
+                type: text
+            tag_fields: []
+        "#,
+        )
+        .unwrap();
+        let indexing_settings = IndexingSettings {
+            timestamp_field: Some("timestamp".to_string()),
+            ..Default::default()
+        };
+        let update = |new_doc_mapping_yaml: &str| {
+            let new_doc_mapping: DocMapping = serde_yaml::from_str(new_doc_mapping_yaml).unwrap();
+            validate_doc_mapping_update(
+                &doc_mapping,
+                &new_doc_mapping,
+                &SearchSettings::default(),
+                &indexing_settings,
+            )
+        };
+        // Adding a field.
+        update(
+            r#"
+            field_mappings:
+              - name: timestamp
+                type: i64
+                fast: true
+              - name: body
+                type: text
+              - name: severity
+                type: text
+                tokenizer: raw
+        "#,
+        )
+        .unwrap();
+        // Removing a field.
+        update(
+            r#"
+            field_mappings:
+              - name: timestamp
+                type: i64
+                fast: true
+        "#,
+        )
+        .unwrap();
+        // Modifying a field.
+        let error = update(
+            r#"
+            field_mappings:
+              - name: timestamp
+                type: i64
+                fast: true
+              - name: body
+                type: text
+                tokenizer: raw
+        "#,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Field `body` cannot be modified"));
+        // Modifying another parameter of the doc mapping.
+        let error = update(
+            r#"
+            field_mappings:
+              - name: timestamp
+                type: i64
+                fast: true
+              - name: body
+                type: text
+            store_source: true
+        "#,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Only the field mappings of the doc mapping"));
+    }
+
     #[test]
     fn test_retention_policy_deserialization() {
         {
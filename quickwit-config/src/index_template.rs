@@ -0,0 +1,101 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::IndexConfig;
+
+const OTEL_TRACES_INDEX_CONFIG: &str =
+    include_str!("../resources/index-templates/otel-traces.yaml");
+
+/// Built-in index config for a use case, instantiated with the ID of the index to create.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IndexTemplate {
+    /// Spans received by the OTLP traces gRPC service of the indexers, searchable by trace ID,
+    /// service name, span name, duration, and attributes.
+    OtelTraces,
+}
+
+impl IndexTemplate {
+    /// Returns the index config of the template for the index `index_id`.
+    pub fn index_config(self, index_id: &str) -> anyhow::Result<IndexConfig> {
+        let template_index_config = match self {
+            IndexTemplate::OtelTraces => OTEL_TRACES_INDEX_CONFIG,
+        };
+        let mut index_config: IndexConfig = serde_yaml::from_str(template_index_config)
+            .with_context(|| format!("Failed to parse index template `{:?}`.", self))?;
+        index_config.index_id = index_id.to_string();
+        index_config.validate()?;
+        Ok(index_config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::build_doc_mapper;
+
+    #[test]
+    fn test_otel_traces_index_template() {
+        let index_config = IndexTemplate::OtelTraces
+            .index_config("team-a/traces")
+            .unwrap();
+        assert_eq!(index_config.index_id, "team-a/traces");
+        assert!(index_config.index_uri.is_none());
+        assert_eq!(
+            index_config.indexing_settings.timestamp_field.as_deref(),
+            Some("span_start_timestamp_secs")
+        );
+        let doc_mapper = build_doc_mapper(
+            &index_config.doc_mapping,
+            &index_config.search_settings,
+            &index_config.indexing_settings,
+        )
+        .unwrap();
+        let span_doc = json!({
+            "trace_id": "01010101010101010101010101010101",
+            "service_name": "api",
+            "resource_attributes": {"service.name": "api"},
+            "span_id": "0202020202020202",
+            "span_kind": 2,
+            "span_name": "GET /users",
+            "span_start_timestamp_secs": 1_660_000_000i64,
+            "span_start_timestamp_nanos": 1_660_000_000_000_000_000u64,
+            "span_end_timestamp_nanos": 1_660_000_000_250_000_000u64,
+            "span_duration_millis": 250,
+            "span_attributes": {"http.method": "GET"},
+            "span_status": {"code": 1},
+            "events": [{"name": "cache-miss", "timestamp_nanos": 1_660_000_000_100_000_000u64}],
+            "event_names": ["cache-miss"],
+        });
+        doc_mapper.doc_from_json(span_doc.to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_index_template_serde() {
+        assert_eq!(
+            serde_json::from_str::<IndexTemplate>("\"otel-traces\"").unwrap(),
+            IndexTemplate::OtelTraces
+        );
+    }
+}
@@ -0,0 +1,74 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+
+/// Named set of indexing settings tuned for a use case.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IndexingProfile {
+    /// Makes documents searchable quickly, at the cost of more, smaller splits and more merges.
+    LowLatency,
+    /// Maximizes the indexing throughput with large commits and deep indexer queues.
+    Throughput,
+    /// Minimizes storage and requests costs with large, highly compressed splits.
+    CostOptimized,
+}
+
+/// Settings selected by an [`IndexingProfile`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct ProfileSettings {
+    pub commit_timeout_secs: usize,
+    pub split_num_docs_target: usize,
+    pub merge_factor: usize,
+    pub max_merge_factor: usize,
+    pub docstore_compression_level: i32,
+    pub indexer_queue_capacity: usize,
+}
+
+impl IndexingProfile {
+    pub(crate) fn settings(self) -> ProfileSettings {
+        match self {
+            IndexingProfile::LowLatency => ProfileSettings {
+                commit_timeout_secs: 5,
+                split_num_docs_target: 2_000_000,
+                merge_factor: 10,
+                max_merge_factor: 12,
+                docstore_compression_level: 3,
+                indexer_queue_capacity: 2,
+            },
+            IndexingProfile::Throughput => ProfileSettings {
+                commit_timeout_secs: 120,
+                split_num_docs_target: 10_000_000,
+                merge_factor: 10,
+                max_merge_factor: 12,
+                docstore_compression_level: 5,
+                indexer_queue_capacity: 50,
+            },
+            IndexingProfile::CostOptimized => ProfileSettings {
+                commit_timeout_secs: 300,
+                split_num_docs_target: 20_000_000,
+                merge_factor: 20,
+                max_merge_factor: 30,
+                docstore_compression_level: 19,
+                indexer_queue_capacity: 10,
+            },
+        }
+    }
+}
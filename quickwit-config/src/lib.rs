@@ -23,18 +23,31 @@ use regex::Regex;
 
 mod config;
 mod index_config;
+mod index_template;
+mod indexing_profile;
 mod source_config;
 mod templating;
 
-pub use config::{IndexerConfig, QuickwitConfig, SearcherConfig, DEFAULT_QW_CONFIG_PATH};
+pub use config::{
+    IndexerConfig, NamespaceConfig, QuickwitConfig, SearcherConfig, DEFAULT_QW_CONFIG_PATH,
+};
 pub use index_config::{
-    build_doc_mapper, DocMapping, IndexConfig, IndexingResources, IndexingSettings, MergePolicy,
-    RetentionPolicy, RetentionPolicyCutoffReference, SearchSettings,
+    build_doc_mapper, validate_doc_mapping_update, CalendarTagGranularity, DeadLetterQueueSettings,
+    DeduplicationSettings, DocMapping, EnrichmentSettings, FieldProfilingSettings, IndexConfig,
+    IndexingResources, IndexingSettings, MergeCoordinationSettings, MergePolicy,
+    MetastorePayloadLimits, PublishNotificationSettings, RetentionPolicy,
+    RetentionPolicyCutoffReference, SearchSettings, ShardingSettings, SplitCarryOverSettings,
+    SplitSamplingSettings, StorageRetrySettings, TenantRoutingSettings, INDEX_CONFIG_VERSION,
 };
+pub use index_template::IndexTemplate;
+pub use indexing_profile::IndexingProfile;
 pub use source_config::{
-    FileSourceParams, IngestApiSourceParams, KafkaSourceParams, KinesisSourceParams,
-    RegionOrEndpoint, SourceConfig, SourceParams, VecSourceParams, VoidSourceParams,
-    CLI_INGEST_SOURCE_ID,
+    parse_format_template, DocTransform, FileSourceParams, FormatTemplateSegment,
+    IngestApiSourceParams, KafkaBackfillWindow, KafkaSourceParams, KinesisSourceParams,
+    MultiplexedSourceParams, ParquetSourceParams, PulsarSourceParams, PulsarSubscriptionType,
+    RegionOrEndpoint, ReplaySourceParams, SourceConfig, SourceParams, SourceRateLimit,
+    VecSourceParams, VoidSourceParams, WebhookSourceParams, CLI_INGEST_SOURCE_ID,
+    MULTIPLEXED_SOURCE_ID,
 };
 
 fn is_false(val: &bool) -> bool {
@@ -53,9 +66,46 @@ fn validate_identifier(label: &str, value: &str) -> anyhow::Result<()> {
     bail!("{} `{}` is invalid.", label, value);
 }
 
+/// Separator between the namespaces of an index ID and the name of the index, e.g. `team-a/logs`.
+pub const INDEX_NAMESPACE_SEPARATOR: char = '/';
+
+/// Validates an index ID, made of one or more identifiers separated by `/`. The leading
+/// identifiers form the namespace of the index, for instance `team-a` for `team-a/logs`.
+fn validate_index_id(index_id: &str) -> anyhow::Result<()> {
+    if index_id.len() <= 255
+        && index_id
+            .split(INDEX_NAMESPACE_SEPARATOR)
+            .all(|segment| validate_identifier("Index ID", segment).is_ok())
+    {
+        return Ok(());
+    }
+    bail!("Index ID `{}` is invalid.", index_id);
+}
+
+/// Validates the name of an index feature flag, which follows the rules of the other identifiers,
+/// for instance `shadow_indexing`.
+pub fn validate_feature_flag(flag: &str) -> anyhow::Result<()> {
+    validate_identifier("Feature flag", flag)
+}
+
+/// Returns the namespace of an index, or `None` if the index does not belong to a namespace.
+pub fn index_namespace(index_id: &str) -> Option<&str> {
+    index_id
+        .rsplit_once(INDEX_NAMESPACE_SEPARATOR)
+        .map(|(namespace, _)| namespace)
+}
+
+/// Returns whether an index belongs to `namespace` or to one of its nested namespaces.
+pub fn is_index_in_namespace(index_id: &str, namespace: &str) -> bool {
+    index_id
+        .strip_prefix(namespace)
+        .map(|suffix| suffix.starts_with(INDEX_NAMESPACE_SEPARATOR))
+        .unwrap_or(false)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::validate_identifier;
+    use crate::{index_namespace, is_index_in_namespace, validate_identifier, validate_index_id};
 
     #[test]
     fn test_validate_identifier() {
@@ -76,4 +126,32 @@ mod tests {
             "Cluster ID `foo!` is invalid."
         );
     }
+
+    #[test]
+    fn test_validate_index_id() {
+        validate_index_id("logs").unwrap();
+        validate_index_id("team-a/logs").unwrap();
+        validate_index_id("team-a/prod/logs").unwrap();
+        validate_index_id("/logs").unwrap_err();
+        validate_index_id("team-a/").unwrap_err();
+        validate_index_id("team-a//logs").unwrap_err();
+        validate_index_id("team-a/lo").unwrap_err();
+        assert_eq!(
+            validate_index_id("team-a/logs!").unwrap_err().to_string(),
+            "Index ID `team-a/logs!` is invalid."
+        );
+    }
+
+    #[test]
+    fn test_index_namespace() {
+        assert_eq!(index_namespace("logs"), None);
+        assert_eq!(index_namespace("team-a/logs"), Some("team-a"));
+        assert_eq!(index_namespace("team-a/prod/logs"), Some("team-a/prod"));
+
+        assert!(is_index_in_namespace("team-a/logs", "team-a"));
+        assert!(is_index_in_namespace("team-a/prod/logs", "team-a"));
+        assert!(!is_index_in_namespace("team-a", "team-a"));
+        assert!(!is_index_in_namespace("team-ab/logs", "team-a"));
+        assert!(!is_index_in_namespace("logs", "team-a"));
+    }
 }
@@ -17,19 +17,26 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use anyhow::{bail, Context};
+use byte_unit::Byte;
 use json_comments::StripComments;
 use quickwit_common::uri::{Extension, Uri};
+use quickwit_doc_mapper::DateTimeFormat;
 use serde::de::Error;
 use serde::{Deserialize, Deserializer, Serialize};
 
-use crate::{is_false, validate_identifier};
+use crate::index_config::deser_and_validate_uri;
+use crate::{is_false, validate_identifier, IndexingProfile};
 
 /// Reserved source ID for the `quickwit index ingest` CLI command.
 pub const CLI_INGEST_SOURCE_ID: &str = ".cli-ingest-source";
 
+/// Reserved source ID of the pipelines reading several sources of an index at once.
+pub const MULTIPLEXED_SOURCE_ID: &str = ".multiplexed";
+
 fn default_num_pipelines() -> usize {
     1
 }
@@ -49,6 +56,24 @@ pub struct SourceConfig {
     /// indexing pipelines running for the source.
     pub num_pipelines: usize,
 
+    /// Indexing profile of the pipelines of the source, overriding the profile of the index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<IndexingProfile>,
+
+    /// Limits the rate at which each pipeline of the source reads documents.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<SourceRateLimit>,
+
+    /// Processors applied in order to the documents of the source before they are parsed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transforms: Vec<DocTransform>,
+
+    /// Guarantees that the documents of each partition of the source are published in order:
+    /// the pipeline fails instead of publishing a checkpoint delta that does not start exactly
+    /// where the previous one stopped.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub strict_ordering: bool,
+
     #[serde(flatten)]
     pub source_params: SourceParams,
 }
@@ -109,6 +134,14 @@ impl SourceConfig {
         if self.source_id != CLI_INGEST_SOURCE_ID {
             validate_identifier("Source ID", &self.source_id)?;
         }
+        if let Some(rate_limit) = &self.rate_limit {
+            rate_limit.validate(&self.source_id)?;
+        }
+        for transform in &self.transforms {
+            transform
+                .validate()
+                .with_context(|| format!("Invalid transform for source `{}`.", self.source_id))?;
+        }
         match &self.source_params {
             // We want to forbid source_config with no filepath
             SourceParams::File(file_params) => {
@@ -120,11 +153,97 @@ impl SourceConfig {
                 }
                 Ok(())
             }
-            SourceParams::Kafka(_) | SourceParams::Kinesis(_) => {
+            SourceParams::Kafka(kafka_params) => {
+                if let Some(backfill_window) = &kafka_params.backfill_window {
+                    if !kafka_params.enable_backfill_mode {
+                        bail!(
+                            "Source `{}` of type `kafka` must enable backfill mode to set a \
+                             `backfill_window`.",
+                            self.source_id
+                        )
+                    }
+                    if let (Some(start_timestamp_millis), Some(end_timestamp_millis)) = (
+                        backfill_window.start_timestamp_millis,
+                        backfill_window.end_timestamp_millis,
+                    ) {
+                        if start_timestamp_millis >= end_timestamp_millis {
+                            bail!(
+                                "Source `{}` of type `kafka` has an empty `backfill_window`: \
+                                 `start_timestamp_millis` must be lower than \
+                                 `end_timestamp_millis`.",
+                                self.source_id
+                            )
+                        }
+                    }
+                }
+                Ok(())
+            }
+            SourceParams::Kinesis(_) => {
                 // TODO consider any validation opportunity
                 Ok(())
             }
-            SourceParams::Vec(_) | SourceParams::Void(_) | SourceParams::IngestApi(_) => Ok(()),
+            SourceParams::Parquet(parquet_params) => {
+                if parquet_params.filepaths.is_empty() == parquet_params.uri.is_none() {
+                    bail!(
+                        "Source `{}` of type `parquet` must contain either at least one filepath \
+                         or a `uri`.",
+                        self.source_id
+                    )
+                }
+                Ok(())
+            }
+            SourceParams::Pulsar(pulsar_params) => {
+                if pulsar_params.topics.is_empty() {
+                    bail!(
+                        "Source `{}` of type `pulsar` must contain at least one topic.",
+                        self.source_id
+                    )
+                }
+                if pulsar_params.batch_size == 0 {
+                    bail!(
+                        "Source `{}` of type `pulsar` must have a strictly positive `batch_size`.",
+                        self.source_id
+                    )
+                }
+                if pulsar_params.subscription_type == PulsarSubscriptionType::Exclusive
+                    && self.num_pipelines > 1
+                {
+                    bail!(
+                        "Source `{}` of type `pulsar` with an `exclusive` subscription cannot be \
+                         consumed by more than one pipeline. Use a `failover` subscription \
+                         instead.",
+                        self.source_id
+                    )
+                }
+                Ok(())
+            }
+            SourceParams::Webhook(webhook_params) => {
+                if webhook_params.secret_env_var.is_empty() {
+                    bail!(
+                        "Source `{}` of type `webhook` must contain a non-empty `secret_env_var`.",
+                        self.source_id
+                    )
+                }
+                if webhook_params.signature_header.is_empty() {
+                    bail!(
+                        "Source `{}` of type `webhook` must contain a non-empty \
+                         `signature_header`.",
+                        self.source_id
+                    )
+                }
+                Ok(())
+            }
+            SourceParams::Multiplexed(_) => {
+                bail!(
+                    "Source `{}` of type `multiplexed` cannot be created. Enable the \
+                     `multiplex_sources` indexing setting of the index instead.",
+                    self.source_id
+                )
+            }
+            SourceParams::Replay(_)
+            | SourceParams::Vec(_)
+            | SourceParams::Void(_)
+            | SourceParams::IngestApi(_) => Ok(()),
         }
     }
 
@@ -133,9 +252,14 @@ impl SourceConfig {
             SourceParams::File(_) => "file",
             SourceParams::Kafka(_) => "kafka",
             SourceParams::Kinesis(_) => "kinesis",
+            SourceParams::Parquet(_) => "parquet",
+            SourceParams::Pulsar(_) => "pulsar",
+            SourceParams::Replay(_) => "replay",
             SourceParams::Vec(_) => "vec",
             SourceParams::Void(_) => "void",
             SourceParams::IngestApi(_) => "ingest-api",
+            SourceParams::Webhook(_) => "webhook",
+            SourceParams::Multiplexed(_) => "multiplexed",
         }
     }
 
@@ -145,9 +269,14 @@ impl SourceConfig {
             SourceParams::File(params) => serde_json::to_value(params),
             SourceParams::Kafka(params) => serde_json::to_value(params),
             SourceParams::Kinesis(params) => serde_json::to_value(params),
+            SourceParams::Parquet(params) => serde_json::to_value(params),
+            SourceParams::Pulsar(params) => serde_json::to_value(params),
+            SourceParams::Replay(params) => serde_json::to_value(params),
             SourceParams::Vec(params) => serde_json::to_value(params),
             SourceParams::Void(params) => serde_json::to_value(params),
             SourceParams::IngestApi(params) => serde_json::to_value(params),
+            SourceParams::Webhook(params) => serde_json::to_value(params),
+            SourceParams::Multiplexed(params) => serde_json::to_value(params),
         }
         .unwrap()
     }
@@ -155,9 +284,234 @@ impl SourceConfig {
     pub fn num_pipelines(&self) -> Option<usize> {
         match &self.source_params {
             SourceParams::Kafka(_) | SourceParams::Void(_) => Some(self.num_pipelines),
+            SourceParams::Pulsar(params)
+                if params.subscription_type == PulsarSubscriptionType::Failover =>
+            {
+                Some(self.num_pipelines)
+            }
             _ => None,
         }
     }
+
+    /// Returns whether the source can be read by a multiplexed pipeline along with the other
+    /// sources of its index: it must be consumed by a single pipeline, and must not be fed by
+    /// the ingest API.
+    pub fn can_be_multiplexed(&self) -> bool {
+        let is_fed_by_ingest_api = matches!(
+            self.source_params,
+            SourceParams::IngestApi(_) | SourceParams::Webhook(_) | SourceParams::Multiplexed(_)
+        );
+        !is_fed_by_ingest_api && self.num_pipelines().unwrap_or(1) == 1
+    }
+
+    /// Returns the config of the source of a pipeline reading `sources` at once.
+    pub fn multiplexed(sources: Vec<SourceConfig>) -> Self {
+        SourceConfig {
+            source_id: MULTIPLEXED_SOURCE_ID.to_string(),
+            num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::Multiplexed(MultiplexedSourceParams { sources }),
+        }
+    }
+}
+
+/// Limits on the rate at which a pipeline reads documents from its source, so that a backfill
+/// cannot starve the other sources sharing the node. Short bursts of up to one second worth of
+/// documents are allowed.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SourceRateLimit {
+    /// Maximum number of documents read per second.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_docs_per_sec: Option<u64>,
+    /// Maximum number of bytes read per second.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bytes_per_sec: Option<Byte>,
+}
+
+impl SourceRateLimit {
+    fn validate(&self, source_id: &str) -> anyhow::Result<()> {
+        if self.max_docs_per_sec.is_none() && self.max_bytes_per_sec.is_none() {
+            bail!(
+                "The rate limit of source `{}` must set `max_docs_per_sec`, `max_bytes_per_sec`, \
+                 or both.",
+                source_id
+            );
+        }
+        if self.max_docs_per_sec == Some(0) {
+            bail!(
+                "The rate limit of source `{}` must have a strictly positive `max_docs_per_sec`.",
+                source_id
+            );
+        }
+        if self
+            .max_bytes_per_sec
+            .map(|max_bytes_per_sec| max_bytes_per_sec.get_bytes() == 0)
+            .unwrap_or(false)
+        {
+            bail!(
+                "The rate limit of source `{}` must have a strictly positive `max_bytes_per_sec`.",
+                source_id
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Processor of the transform stage applied to the documents of a source before they are parsed
+/// by the doc mapper. Fields are addressed with a dot-separated path, and the processors
+/// addressing a missing field leave the document untouched.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum DocTransform {
+    /// Moves the value of `field` to `target_field`, overwriting it.
+    Rename { field: String, target_field: String },
+    /// Removes `fields`.
+    Remove { fields: Vec<String> },
+    /// Sets `field` to a constant value, overwriting it.
+    Set {
+        field: String,
+        value: serde_json::Value,
+    },
+    /// Sets `target_field` to a string built from `template`, in which `{field}` placeholders are
+    /// replaced with the values of the document fields. Braces are escaped as `{{` and `}}`.
+    Format {
+        target_field: String,
+        template: String,
+    },
+    /// Parses the date time held by `field` with the first matching input format and writes it
+    /// back to `target_field`, or to `field` itself, in the output format.
+    ParseTimestamp {
+        field: String,
+        input_formats: Vec<DateTimeFormat>,
+        #[serde(default = "DocTransform::default_output_format")]
+        output_format: DateTimeFormat,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        target_field: Option<String>,
+    },
+}
+
+impl DocTransform {
+    fn default_output_format() -> DateTimeFormat {
+        DateTimeFormat::RCF3339
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        let field_paths: Vec<&String> = match self {
+            DocTransform::Rename {
+                field,
+                target_field,
+            } => {
+                if field == target_field {
+                    bail!("Processor `rename` must have distinct `field` and `target_field`.");
+                }
+                vec![field, target_field]
+            }
+            DocTransform::Remove { fields } => {
+                if fields.is_empty() {
+                    bail!("Processor `remove` must contain at least one field.");
+                }
+                fields.iter().collect()
+            }
+            DocTransform::Set { field, .. } => vec![field],
+            DocTransform::Format {
+                target_field,
+                template,
+            } => {
+                parse_format_template(template)?;
+                vec![target_field]
+            }
+            DocTransform::ParseTimestamp {
+                field,
+                input_formats,
+                output_format,
+                target_field,
+            } => {
+                if input_formats.is_empty() {
+                    bail!("Processor `parse_timestamp` must contain at least one input format.");
+                }
+                if !matches!(
+                    output_format,
+                    DateTimeFormat::RCF3339 | DateTimeFormat::Timestamp(_)
+                ) {
+                    bail!(
+                        "Processor `parse_timestamp` has an unsupported output format `{}`. \
+                         Supported output formats are `rfc3339`, `unix_ts_secs`, \
+                         `unix_ts_millis`, and `unix_ts_micros`.",
+                        output_format
+                    );
+                }
+                std::iter::once(field).chain(target_field).collect()
+            }
+        };
+        for field_path in field_paths {
+            if field_path.is_empty() || field_path.split('.').any(str::is_empty) {
+                bail!("Field path `{}` is invalid.", field_path);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Segment of the template of a `format` processor.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FormatTemplateSegment {
+    Literal(String),
+    /// Path of the field whose value replaces the placeholder.
+    Field(String),
+}
+
+/// Splits the template of a `format` processor into literals and field placeholders.
+pub fn parse_format_template(template: &str) -> anyhow::Result<Vec<FormatTemplateSegment>> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.chars();
+    while let Some(chr) = chars.next() {
+        match chr {
+            '{' if chars.as_str().starts_with('{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.as_str().starts_with('}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut field_path = String::new();
+                let mut is_closed = false;
+                for chr in chars.by_ref() {
+                    if chr == '}' {
+                        is_closed = true;
+                        break;
+                    }
+                    field_path.push(chr);
+                }
+                let field_path = field_path.trim();
+                if !is_closed || field_path.is_empty() || field_path.split('.').any(str::is_empty) {
+                    bail!(
+                        "Template `{}` contains an invalid or unclosed placeholder.",
+                        template
+                    );
+                }
+                if !literal.is_empty() {
+                    segments.push(FormatTemplateSegment::Literal(std::mem::take(&mut literal)));
+                }
+                segments.push(FormatTemplateSegment::Field(field_path.to_string()));
+            }
+            '}' => bail!(
+                "Template `{}` contains an unmatched `}}`. Escape it as `}}}}`.",
+                template
+            ),
+            _ => literal.push(chr),
+        }
+    }
+    if !literal.is_empty() {
+        segments.push(FormatTemplateSegment::Literal(literal));
+    }
+    Ok(segments)
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -169,12 +523,22 @@ pub enum SourceParams {
     Kafka(KafkaSourceParams),
     #[serde(rename = "kinesis")]
     Kinesis(KinesisSourceParams),
+    #[serde(rename = "parquet")]
+    Parquet(ParquetSourceParams),
+    #[serde(rename = "pulsar")]
+    Pulsar(PulsarSourceParams),
+    #[serde(rename = "replay")]
+    Replay(ReplaySourceParams),
     #[serde(rename = "vec")]
     Vec(VecSourceParams),
     #[serde(rename = "void")]
     Void(VoidSourceParams),
     #[serde(rename = "ingest-api")]
     IngestApi(IngestApiSourceParams),
+    #[serde(rename = "webhook")]
+    Webhook(WebhookSourceParams),
+    #[serde(rename = "multiplexed")]
+    Multiplexed(MultiplexedSourceParams),
 }
 
 impl SourceParams {
@@ -194,7 +558,8 @@ impl SourceParams {
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct FileSourceParams {
-    /// Path of the file to read. Assume stdin if None.
+    /// Path of the file to read, or glob pattern matching the files to read. Assume stdin if
+    /// None.
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     #[serde(deserialize_with = "absolute_filepath_from_str")]
@@ -213,6 +578,20 @@ where D: Deserializer<'de> {
     }
 }
 
+fn absolute_filepaths_from_strs<'de, D>(deserializer: D) -> Result<Vec<PathBuf>, D::Error>
+where D: Deserializer<'de> {
+    let filepaths: Vec<String> = Deserialize::deserialize(deserializer)?;
+    filepaths
+        .iter()
+        .map(|filepath| {
+            let uri = Uri::try_new(filepath).map_err(D::Error::custom)?;
+            uri.filepath()
+                .map(|path| path.to_path_buf())
+                .ok_or_else(|| D::Error::custom(format!("`{filepath}` is not a local file path.")))
+        })
+        .collect()
+}
+
 impl FileSourceParams {
     pub fn file<P: AsRef<Path>>(filepath: P) -> Self {
         FileSourceParams {
@@ -223,6 +602,45 @@ impl FileSourceParams {
     pub fn stdin() -> Self {
         FileSourceParams { filepath: None }
     }
+
+    /// Returns whether the file path is a glob pattern, in which case the source reads all the
+    /// files matching the pattern.
+    pub fn is_glob_pattern(&self) -> bool {
+        self.filepath
+            .as_ref()
+            .map(|filepath| {
+                filepath
+                    .to_string_lossy()
+                    .contains(|c| matches!(c, '*' | '?' | '['))
+            })
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParquetSourceParams {
+    /// Paths of the Parquet files to read. Each file is a partition of the source.
+    #[serde(default)]
+    #[serde(deserialize_with = "absolute_filepaths_from_strs")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub filepaths: Vec<PathBuf>,
+    /// URI of a storage prefix, such as `s3://bucket/exports`, under which all the files with the
+    /// `.parquet` extension are read. Each file is a partition of the source, so that the files
+    /// added to the prefix after an import are the only ones read by the next import.
+    #[serde(default)]
+    #[serde(deserialize_with = "deser_and_validate_uri")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uri: Option<Uri>,
+    /// Columns to read, the others are skipped. All the columns are read when empty.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<String>,
+    /// Maps column names to the names of the doc mapper fields they are indexed into. The columns
+    /// missing from the mapping keep their name.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub column_mapping: BTreeMap<String, String>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -241,6 +659,27 @@ pub struct KafkaSourceParams {
     #[serde(default)]
     #[serde(skip_serializing_if = "is_false")]
     pub enable_backfill_mode: bool,
+    /// Event-time window of the messages read in backfill mode.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backfill_window: Option<KafkaBackfillWindow>,
+}
+
+/// Window of Kafka message timestamps, in milliseconds, read by a Kafka source in backfill mode.
+/// The source looks up the offsets of the bounds of the window in each partition, so that it
+/// starts reading at the first message of the window instead of the checkpoint of the source,
+/// and stops reading a partition at the first message past the window.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KafkaBackfillWindow {
+    /// Messages with a timestamp lower than this timestamp are skipped.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_timestamp_millis: Option<i64>,
+    /// Messages with a timestamp greater than or equal to this timestamp are skipped.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_timestamp_millis: Option<i64>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -292,6 +731,70 @@ impl TryFrom<KinesisSourceParamsInner> for KinesisSourceParams {
     }
 }
 
+/// Type of the Pulsar subscription created by the source. Only the subscription types
+/// preserving the order of the messages within a partition are supported.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PulsarSubscriptionType {
+    /// A single consumer, hence a single pipeline, is attached to the subscription.
+    Exclusive,
+    /// Several consumers can be attached to the subscription, each partition being consumed by a
+    /// single active consumer at a time.
+    Failover,
+}
+
+impl Default for PulsarSubscriptionType {
+    fn default() -> Self {
+        Self::Failover
+    }
+}
+
+fn is_default_pulsar_subscription_type(subscription_type: &PulsarSubscriptionType) -> bool {
+    *subscription_type == PulsarSubscriptionType::default()
+}
+
+fn default_pulsar_batch_size() -> u32 {
+    1_000
+}
+
+fn is_default_pulsar_batch_size(batch_size: &u32) -> bool {
+    *batch_size == default_pulsar_batch_size()
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PulsarSourceParams {
+    /// Names of the topics that the source consumes.
+    pub topics: Vec<String>,
+    /// Address of the Pulsar cluster, for instance `pulsar://localhost:6650`.
+    pub address: String,
+    /// Name of the subscription. Defaults to `quickwit-{index_id}-{source_id}`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscription_name: Option<String>,
+    /// Type of the subscription.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default_pulsar_subscription_type")]
+    pub subscription_type: PulsarSubscriptionType,
+    /// Maximum number of messages prefetched by the consumer of each partition.
+    #[serde(default = "default_pulsar_batch_size")]
+    #[serde(skip_serializing_if = "is_default_pulsar_batch_size")]
+    pub batch_size: u32,
+    /// Token used to authenticate against the Pulsar cluster.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authentication_token: Option<String>,
+}
+
+/// Parameters of a source replaying the batches recorded in a fixture file, so that pipeline tests
+/// can run without the original source.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReplaySourceParams {
+    /// Path of the fixture file holding the recorded batches.
+    pub fixture_path: PathBuf,
+}
+
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct VecSourceParams {
@@ -314,6 +817,39 @@ pub struct IngestApiSourceParams {
     pub batch_num_bytes_limit: Option<u64>,
 }
 
+fn default_webhook_signature_header() -> String {
+    "x-quickwit-signature".to_string()
+}
+
+fn is_default_webhook_signature_header(signature_header: &str) -> bool {
+    signature_header == default_webhook_signature_header()
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookSourceParams {
+    /// Name of the environment variable holding the shared secret used to compute the
+    /// HMAC-SHA256 signature of the request bodies. The secret itself is never stored in the
+    /// metastore: the variable is read by the node receiving the requests.
+    pub secret_env_var: String,
+    /// Name of the HTTP header carrying the hex-encoded signature of the request body,
+    /// optionally prefixed with `sha256=` (GitHub-style).
+    #[serde(default = "default_webhook_signature_header")]
+    #[serde(skip_serializing_if = "is_default_webhook_signature_header")]
+    pub signature_header: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch_num_bytes_limit: Option<u64>,
+}
+
+/// Params of the source of a pipeline reading several sources of an index at once. Such
+/// sources are created by the indexing service when the `multiplex_sources` indexing setting is
+/// enabled.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MultiplexedSourceParams {
+    pub sources: Vec<SourceConfig>,
+}
+
 #[cfg(test)]
 mod tests {
     use quickwit_common::uri::Uri;
@@ -342,11 +878,16 @@ mod tests {
         let expected_source_config = SourceConfig {
             source_id: "hdfs-logs-kafka-source".to_string(),
             num_pipelines: 2,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
             source_params: SourceParams::Kafka(KafkaSourceParams {
                 topic: "cloudera-cluster-logs".to_string(),
                 client_log_level: None,
                 client_params: json! {{"bootstrap.servers": "localhost:9092"}},
                 enable_backfill_mode: false,
+                backfill_window: None,
             }),
         };
         assert_eq!(source_config, expected_source_config);
@@ -361,6 +902,7 @@ mod tests {
                 client_log_level: None,
                 client_params: json!(null),
                 enable_backfill_mode: false,
+                backfill_window: None,
             };
             let params_yaml = serde_yaml::to_string(&params).unwrap();
 
@@ -374,7 +916,11 @@ mod tests {
                 topic: "my-topic".to_string(),
                 client_log_level: Some("info".to_string()),
                 client_params: json! {{"bootstrap.servers": "localhost:9092"}},
-                enable_backfill_mode: false,
+                enable_backfill_mode: true,
+                backfill_window: Some(KafkaBackfillWindow {
+                    start_timestamp_millis: Some(1_660_000_000_000),
+                    end_timestamp_millis: None,
+                }),
             };
             let params_yaml = serde_yaml::to_string(&params).unwrap();
 
@@ -398,6 +944,7 @@ mod tests {
                     client_log_level: None,
                     client_params: json!(null),
                     enable_backfill_mode: false,
+                    backfill_window: None,
                 }
             );
         }
@@ -408,6 +955,9 @@ mod tests {
                     client_params:
                         bootstrap.servers: localhost:9092
                     enable_backfill_mode: true
+                    backfill_window:
+                        start_timestamp_millis: 1660000000000
+                        end_timestamp_millis: 1660003600000
                 "#;
             assert_eq!(
                 serde_yaml::from_str::<KafkaSourceParams>(yaml).unwrap(),
@@ -416,11 +966,50 @@ mod tests {
                     client_log_level: Some("info".to_string()),
                     client_params: json! {{"bootstrap.servers": "localhost:9092"}},
                     enable_backfill_mode: true,
+                    backfill_window: Some(KafkaBackfillWindow {
+                        start_timestamp_millis: Some(1_660_000_000_000),
+                        end_timestamp_millis: Some(1_660_003_600_000),
+                    }),
                 }
             );
         }
     }
 
+    #[test]
+    fn test_kafka_source_backfill_window_validation() {
+        let source_config =
+            |enable_backfill_mode: bool, start: Option<i64>, end: Option<i64>| SourceConfig {
+                source_id: "my-kafka-source".to_string(),
+                num_pipelines: 1,
+                profile: None,
+                rate_limit: None,
+                transforms: Vec::new(),
+                strict_ordering: false,
+                source_params: SourceParams::Kafka(KafkaSourceParams {
+                    topic: "my-topic".to_string(),
+                    client_log_level: None,
+                    client_params: json!({}),
+                    enable_backfill_mode,
+                    backfill_window: Some(KafkaBackfillWindow {
+                        start_timestamp_millis: start,
+                        end_timestamp_millis: end,
+                    }),
+                }),
+            };
+        source_config(true, Some(1_000), Some(2_000))
+            .validate()
+            .unwrap();
+        source_config(true, None, Some(2_000)).validate().unwrap();
+        let error = source_config(false, Some(1_000), None)
+            .validate()
+            .unwrap_err();
+        assert!(error.to_string().contains("must enable backfill mode"));
+        let error = source_config(true, Some(2_000), Some(1_000))
+            .validate()
+            .unwrap_err();
+        assert!(error.to_string().contains("has an empty `backfill_window`"));
+    }
+
     #[tokio::test]
     async fn test_load_kinesis_source_config() {
         let source_config_filepath = get_source_config_filepath("kinesis-source.yaml");
@@ -432,6 +1021,10 @@ mod tests {
         let expected_source_config = SourceConfig {
             source_id: "hdfs-logs-kinesis-source".to_string(),
             num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
             source_params: SourceParams::Kinesis(KinesisSourceParams {
                 stream_name: "emr-cluster-logs".to_string(),
                 region_or_endpoint: None,
@@ -442,6 +1035,136 @@ mod tests {
         assert!(source_config.num_pipelines().is_none());
     }
 
+    #[tokio::test]
+    async fn test_load_pulsar_source_config() {
+        let source_config_filepath = get_source_config_filepath("pulsar-source.yaml");
+        let file_content = std::fs::read_to_string(&source_config_filepath).unwrap();
+        let source_config_uri = Uri::try_new(&source_config_filepath).unwrap();
+        let source_config = SourceConfig::load(&source_config_uri, file_content.as_bytes())
+            .await
+            .unwrap();
+        let expected_source_config = SourceConfig {
+            source_id: "hdfs-logs-pulsar-source".to_string(),
+            num_pipelines: 2,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::Pulsar(PulsarSourceParams {
+                topics: vec!["cloudera-cluster-logs".to_string()],
+                address: "pulsar://localhost:6650".to_string(),
+                subscription_name: None,
+                subscription_type: PulsarSubscriptionType::Failover,
+                batch_size: 500,
+                authentication_token: None,
+            }),
+        };
+        assert_eq!(source_config, expected_source_config);
+        assert_eq!(source_config.num_pipelines().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_pulsar_source_params_deserialization() {
+        {
+            let yaml = r#"
+                    topics:
+                        - my-topic
+                    address: pulsar://localhost:6650
+                "#;
+            let params = serde_yaml::from_str::<PulsarSourceParams>(yaml).unwrap();
+            assert_eq!(
+                params,
+                PulsarSourceParams {
+                    topics: vec!["my-topic".to_string()],
+                    address: "pulsar://localhost:6650".to_string(),
+                    subscription_name: None,
+                    subscription_type: PulsarSubscriptionType::Failover,
+                    batch_size: 1_000,
+                    authentication_token: None,
+                }
+            );
+            let params_yaml = serde_yaml::to_string(&params).unwrap();
+            assert_eq!(
+                serde_yaml::from_str::<PulsarSourceParams>(&params_yaml).unwrap(),
+                params,
+            );
+        }
+        {
+            let yaml = r#"
+                    topics:
+                        - my-topic
+                        - my-other-topic
+                    address: pulsar+ssl://pulsar.example.com:6651
+                    subscription_name: my-subscription
+                    subscription_type: exclusive
+                    batch_size: 100
+                    authentication_token: my-token
+                "#;
+            assert_eq!(
+                serde_yaml::from_str::<PulsarSourceParams>(yaml).unwrap(),
+                PulsarSourceParams {
+                    topics: vec!["my-topic".to_string(), "my-other-topic".to_string()],
+                    address: "pulsar+ssl://pulsar.example.com:6651".to_string(),
+                    subscription_name: Some("my-subscription".to_string()),
+                    subscription_type: PulsarSubscriptionType::Exclusive,
+                    batch_size: 100,
+                    authentication_token: Some("my-token".to_string()),
+                }
+            );
+        }
+        {
+            let yaml = r#"
+                    topics:
+                        - my-topic
+                    address: pulsar://localhost:6650
+                    subscription_type: shared
+                "#;
+            assert!(serde_yaml::from_str::<PulsarSourceParams>(yaml).is_err());
+        }
+    }
+
+    #[test]
+    fn test_pulsar_source_config_validate() {
+        let pulsar_params = PulsarSourceParams {
+            topics: vec!["my-topic".to_string()],
+            address: "pulsar://localhost:6650".to_string(),
+            subscription_name: None,
+            subscription_type: PulsarSubscriptionType::Exclusive,
+            batch_size: 1_000,
+            authentication_token: None,
+        };
+        let mut source_config = SourceConfig {
+            source_id: "pulsar-source".to_string(),
+            num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::Pulsar(pulsar_params.clone()),
+        };
+        source_config.validate().unwrap();
+        assert!(source_config.num_pipelines().is_none());
+
+        source_config.num_pipelines = 2;
+        let error = source_config.validate().unwrap_err();
+        assert!(error.to_string().contains("`exclusive` subscription"));
+
+        source_config.source_params = SourceParams::Pulsar(PulsarSourceParams {
+            topics: Vec::new(),
+            ..pulsar_params.clone()
+        });
+        source_config.num_pipelines = 1;
+        let error = source_config.validate().unwrap_err();
+        assert!(error.to_string().contains("at least one topic"));
+
+        source_config.source_params = SourceParams::Pulsar(PulsarSourceParams {
+            batch_size: 0,
+            ..pulsar_params
+        });
+        let error = source_config.validate().unwrap_err();
+        assert!(error.to_string().contains("`batch_size`"));
+    }
+
     #[test]
     fn test_file_source_params_serialization() {
         {
@@ -451,10 +1174,23 @@ mod tests {
             let file_params = serde_yaml::from_str::<FileSourceParams>(yaml).unwrap();
             let uri = Uri::try_new("source-path.json").unwrap();
             assert_eq!(
-                file_params.filepath.unwrap().as_path(),
+                file_params.filepath.as_deref().unwrap(),
                 uri.filepath().unwrap()
-            )
+            );
+            assert!(!file_params.is_glob_pattern());
         }
+        {
+            let yaml = r#"
+                filepath: /var/log/app/*.json.gz
+            "#;
+            let file_params = serde_yaml::from_str::<FileSourceParams>(yaml).unwrap();
+            assert_eq!(
+                file_params.filepath.as_deref().unwrap(),
+                Path::new("/var/log/app/*.json.gz")
+            );
+            assert!(file_params.is_glob_pattern());
+        }
+        assert!(!FileSourceParams::stdin().is_glob_pattern());
     }
 
     #[test]
@@ -558,4 +1294,382 @@ mod tests {
             Path::new("./qwdata/queues")
         )
     }
+
+    #[test]
+    fn test_webhook_source_params_deserialization() {
+        {
+            let yaml = r#"
+                secret_env_var: QW_GITHUB_WEBHOOK_SECRET
+            "#;
+            let webhook_params = serde_yaml::from_str::<WebhookSourceParams>(yaml).unwrap();
+            assert_eq!(
+                webhook_params,
+                WebhookSourceParams {
+                    secret_env_var: "QW_GITHUB_WEBHOOK_SECRET".to_string(),
+                    signature_header: "x-quickwit-signature".to_string(),
+                    batch_num_bytes_limit: None,
+                }
+            );
+        }
+        {
+            let yaml = r#"
+                secret_env_var: QW_GITHUB_WEBHOOK_SECRET
+                signature_header: x-hub-signature-256
+                batch_num_bytes_limit: 200000
+            "#;
+            let webhook_params = serde_yaml::from_str::<WebhookSourceParams>(yaml).unwrap();
+            assert_eq!(webhook_params.signature_header, "x-hub-signature-256");
+            assert_eq!(webhook_params.batch_num_bytes_limit, Some(200000));
+        }
+    }
+
+    #[test]
+    fn test_webhook_source_config_validation() {
+        let source_config = SourceConfig {
+            source_id: "github-events".to_string(),
+            num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::Webhook(WebhookSourceParams {
+                secret_env_var: "QW_GITHUB_WEBHOOK_SECRET".to_string(),
+                signature_header: "x-hub-signature-256".to_string(),
+                batch_num_bytes_limit: None,
+            }),
+        };
+        source_config.validate().unwrap();
+        assert_eq!(source_config.source_type(), "webhook");
+
+        let source_config = SourceConfig {
+            source_id: "github-events".to_string(),
+            num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::Webhook(WebhookSourceParams {
+                secret_env_var: "".to_string(),
+                signature_header: "x-hub-signature-256".to_string(),
+                batch_num_bytes_limit: None,
+            }),
+        };
+        source_config.validate().unwrap_err();
+    }
+
+    #[test]
+    fn test_multiplexed_source_config() {
+        let kafka_source_config = SourceConfig {
+            source_id: "kafka-source".to_string(),
+            num_pipelines: 2,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::Kafka(KafkaSourceParams {
+                topic: "my-topic".to_string(),
+                client_log_level: None,
+                client_params: json!({}),
+                enable_backfill_mode: false,
+                backfill_window: None,
+            }),
+        };
+        assert!(!kafka_source_config.can_be_multiplexed());
+        assert!(SourceConfig {
+            num_pipelines: 1,
+            ..kafka_source_config
+        }
+        .can_be_multiplexed());
+
+        let file_source_config = SourceConfig {
+            source_id: "file-source".to_string(),
+            num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::file("data/test_corpus.json"),
+        };
+        assert!(file_source_config.can_be_multiplexed());
+
+        let multiplexed_source_config = SourceConfig::multiplexed(vec![file_source_config]);
+        assert_eq!(multiplexed_source_config.source_id, MULTIPLEXED_SOURCE_ID);
+        assert_eq!(multiplexed_source_config.source_type(), "multiplexed");
+        assert!(!multiplexed_source_config.can_be_multiplexed());
+        // Multiplexed sources are created by the indexing service only.
+        multiplexed_source_config.validate().unwrap_err();
+    }
+
+    #[test]
+    fn test_parquet_source_params_deserialization() {
+        {
+            let yaml = r#"
+                filepaths:
+                    - /data/events-0.parquet
+                    - /data/events-1.parquet
+            "#;
+            let parquet_params = serde_yaml::from_str::<ParquetSourceParams>(yaml).unwrap();
+            assert_eq!(
+                parquet_params,
+                ParquetSourceParams {
+                    filepaths: vec![
+                        PathBuf::from("/data/events-0.parquet"),
+                        PathBuf::from("/data/events-1.parquet"),
+                    ],
+                    uri: None,
+                    columns: Vec::new(),
+                    column_mapping: BTreeMap::new(),
+                }
+            );
+        }
+        {
+            let yaml = r#"
+                uri: s3://quickwit-exports/events
+                columns:
+                    - event_ts
+                    - body
+            "#;
+            let parquet_params = serde_yaml::from_str::<ParquetSourceParams>(yaml).unwrap();
+            assert!(parquet_params.filepaths.is_empty());
+            assert_eq!(
+                parquet_params.uri,
+                Some(Uri::new("s3://quickwit-exports/events".to_string()))
+            );
+            assert_eq!(parquet_params.columns, ["event_ts", "body"]);
+        }
+        {
+            let yaml = r#"
+                filepaths:
+                    - /data/events-0.parquet
+                column_mapping:
+                    event_ts: timestamp
+            "#;
+            let parquet_params = serde_yaml::from_str::<ParquetSourceParams>(yaml).unwrap();
+            assert_eq!(
+                parquet_params.column_mapping,
+                BTreeMap::from([("event_ts".to_string(), "timestamp".to_string())])
+            );
+        }
+        {
+            let source_config = SourceConfig {
+                source_id: "events".to_string(),
+                num_pipelines: 1,
+                profile: None,
+                rate_limit: None,
+                transforms: Vec::new(),
+                strict_ordering: false,
+                source_params: SourceParams::Parquet(ParquetSourceParams {
+                    filepaths: Vec::new(),
+                    uri: None,
+                    columns: Vec::new(),
+                    column_mapping: BTreeMap::new(),
+                }),
+            };
+            source_config.validate().unwrap_err();
+            // Both filepaths and a URI.
+            let mut source_config = source_config.clone();
+            source_config.source_params = SourceParams::Parquet(ParquetSourceParams {
+                filepaths: vec![PathBuf::from("/data/events-0.parquet")],
+                uri: Some(Uri::new("s3://quickwit-exports/events".to_string())),
+                columns: Vec::new(),
+                column_mapping: BTreeMap::new(),
+            });
+            source_config.validate().unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_replay_source_params_deserialization() {
+        let yaml = r#"
+            source_id: kafka-fixture
+            source_type: replay
+            params:
+                fixture_path: ./fixtures/kafka-batches.ndjson
+        "#;
+        let source_config = serde_yaml::from_str::<SourceConfig>(yaml).unwrap();
+        source_config.validate().unwrap();
+        assert_eq!(source_config.source_type(), "replay");
+        assert_eq!(
+            source_config.source_params,
+            SourceParams::Replay(ReplaySourceParams {
+                fixture_path: PathBuf::from("./fixtures/kafka-batches.ndjson"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_source_config_strict_ordering_serde() {
+        let source_config_yaml = r#"
+            source_id: my-source
+            source_type: kafka
+            strict_ordering: true
+            params:
+                topic: my-topic
+        "#;
+        let source_config = serde_yaml::from_str::<SourceConfig>(source_config_yaml).unwrap();
+        assert!(source_config.strict_ordering);
+
+        let source_config_json = serde_json::to_value(&SourceConfig {
+            strict_ordering: false,
+            ..source_config
+        })
+        .unwrap();
+        assert!(source_config_json.get("strict_ordering").is_none());
+    }
+
+    #[test]
+    fn test_source_config_profile_serde() {
+        let source_config_yaml = r#"
+            source_id: my-source
+            source_type: file
+            profile: throughput
+            params:
+                filepath: /var/log/events.json
+        "#;
+        let source_config = serde_yaml::from_str::<SourceConfig>(source_config_yaml).unwrap();
+        assert_eq!(source_config.profile, Some(IndexingProfile::Throughput));
+
+        let source_config_json = serde_json::to_value(&SourceConfig {
+            profile: None,
+            ..source_config
+        })
+        .unwrap();
+        assert!(source_config_json.get("profile").is_none());
+    }
+
+    #[test]
+    fn test_source_config_transforms_serde() {
+        let source_config_yaml = r#"
+            source_id: my-source
+            source_type: file
+            transforms:
+              - type: rename
+                field: msg
+                target_field: message
+              - type: remove
+                fields: [debug, internal.trace_id]
+              - type: set
+                field: env
+                value: prod
+              - type: format
+                target_field: service
+                template: "{app.name}-{env}"
+              - type: parse_timestamp
+                field: date
+                input_formats: ["%Y-%m-%d %H:%M:%S"]
+                output_format: unix_ts_secs
+                target_field: timestamp
+            params:
+                filepath: /var/log/events.json
+        "#;
+        let source_config = serde_yaml::from_str::<SourceConfig>(source_config_yaml).unwrap();
+        source_config.validate().unwrap();
+        assert_eq!(source_config.transforms.len(), 5);
+        assert_eq!(
+            source_config.transforms[2],
+            DocTransform::Set {
+                field: "env".to_string(),
+                value: json!("prod"),
+            }
+        );
+        assert_eq!(
+            source_config.transforms[4],
+            DocTransform::ParseTimestamp {
+                field: "date".to_string(),
+                input_formats: vec![DateTimeFormat::Strftime("%Y-%m-%d %H:%M:%S".to_string())],
+                output_format: DateTimeFormat::Timestamp(Default::default()),
+                target_field: Some("timestamp".to_string()),
+            }
+        );
+        let source_config_json = serde_json::to_value(&source_config).unwrap();
+        assert_eq!(source_config_json["transforms"][0]["type"], "rename");
+
+        let invalid_source_config = SourceConfig {
+            transforms: vec![DocTransform::Format {
+                target_field: "service".to_string(),
+                template: "{app.name".to_string(),
+            }],
+            ..source_config.clone()
+        };
+        assert!(invalid_source_config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("Invalid transform for source `my-source`."));
+
+        let invalid_source_config = SourceConfig {
+            transforms: vec![DocTransform::ParseTimestamp {
+                field: "date".to_string(),
+                input_formats: vec![DateTimeFormat::RCF3339],
+                output_format: DateTimeFormat::RFC2822,
+                target_field: None,
+            }],
+            ..source_config
+        };
+        assert!(invalid_source_config.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_format_template() {
+        assert_eq!(
+            parse_format_template("{ app.name }-{{{env}}}").unwrap(),
+            [
+                FormatTemplateSegment::Field("app.name".to_string()),
+                FormatTemplateSegment::Literal("-{".to_string()),
+                FormatTemplateSegment::Field("env".to_string()),
+                FormatTemplateSegment::Literal("}".to_string()),
+            ]
+        );
+        assert!(parse_format_template("{app..name}").is_err());
+        assert!(parse_format_template("{}").is_err());
+        assert!(parse_format_template("app}").is_err());
+    }
+
+    #[test]
+    fn test_source_config_rate_limit_serde() {
+        let source_config_yaml = r#"
+            source_id: my-source
+            source_type: file
+            rate_limit:
+                max_docs_per_sec: 1000
+                max_bytes_per_sec: 10MB
+            params:
+                filepath: /var/log/events.json
+        "#;
+        let source_config = serde_yaml::from_str::<SourceConfig>(source_config_yaml).unwrap();
+        source_config.validate().unwrap();
+        assert_eq!(
+            source_config.rate_limit,
+            Some(SourceRateLimit {
+                max_docs_per_sec: Some(1_000),
+                max_bytes_per_sec: Some(Byte::from_bytes(10_000_000)),
+            })
+        );
+
+        let source_config_json = serde_json::to_value(&SourceConfig {
+            rate_limit: None,
+            ..source_config.clone()
+        })
+        .unwrap();
+        assert!(source_config_json.get("rate_limit").is_none());
+
+        let invalid_source_config = SourceConfig {
+            rate_limit: Some(SourceRateLimit::default()),
+            ..source_config.clone()
+        };
+        assert!(invalid_source_config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("must set `max_docs_per_sec`, `max_bytes_per_sec`, or both"));
+
+        let invalid_source_config = SourceConfig {
+            rate_limit: Some(SourceRateLimit {
+                max_docs_per_sec: Some(0),
+                max_bytes_per_sec: None,
+            }),
+            ..source_config
+        };
+        assert!(invalid_source_config.validate().is_err());
+    }
 }
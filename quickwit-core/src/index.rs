@@ -24,12 +24,16 @@ use std::time::Duration;
 
 use quickwit_common::fs::empty_dir;
 use quickwit_common::uri::Uri;
-use quickwit_config::{IndexConfig, QuickwitConfig};
+use quickwit_config::{
+    index_namespace, validate_doc_mapping_update, DocMapping, IndexConfig, NamespaceConfig,
+    QuickwitConfig, SearchSettings, SourceConfig, INDEX_CONFIG_VERSION,
+};
 use quickwit_indexing::actors::INDEXING_DIR_NAME;
 use quickwit_indexing::models::CACHE;
 use quickwit_indexing::{
-    delete_splits_with_files, run_garbage_collect, FileEntry, IndexingSplitStore,
-    SplitDeletionError,
+    delete_splits_with_files, run_garbage_collect, run_index_repair, run_merge_policy_preview,
+    run_split_retagging, FileEntry, IndexRepair, IndexingSplitStore, MergePolicyPreview,
+    MergeSettingsOverrides, SplitDeletionError, SplitRetagging,
 };
 use quickwit_metastore::{
     quickwit_metastore_uri_resolver, IndexMetadata, Metastore, MetastoreError,
@@ -64,11 +68,24 @@ impl ServiceError for IndexServiceError {
     }
 }
 
+/// Options for importing an exported index definition with [`IndexService::import_index`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ImportIndexOptions {
+    /// ID given to the imported index instead of the ID of the exported index.
+    pub index_id_opt: Option<String>,
+    /// Keeps the URI of the exported index instead of storing the imported index under the
+    /// default index root URI.
+    pub keep_index_uri: bool,
+    /// Overwrites the index with the same ID, if any.
+    pub overwrite: bool,
+}
+
 /// Index service responsible for creating, updating and deleting indexes.
 pub struct IndexService {
     metastore: Arc<dyn Metastore>,
     storage_resolver: StorageUriResolver,
     default_index_root_uri: Uri,
+    namespace_configs: Vec<NamespaceConfig>,
 }
 
 impl IndexService {
@@ -82,15 +99,23 @@ impl IndexService {
             metastore,
             storage_resolver,
             default_index_root_uri,
+            namespace_configs: Vec::new(),
         }
     }
 
+    /// Sets the defaults applied to the indexes created in the namespaces.
+    pub fn with_namespace_configs(mut self, namespace_configs: Vec<NamespaceConfig>) -> Self {
+        self.namespace_configs = namespace_configs;
+        self
+    }
+
     pub async fn from_config(config: QuickwitConfig) -> anyhow::Result<Self> {
         let metastore = quickwit_metastore_uri_resolver()
             .resolve(&config.metastore_uri)
             .await?;
         let storage_resolver = quickwit_storage_uri_resolver().clone();
-        let index_service = Self::new(metastore, storage_resolver, config.default_index_root_uri);
+        let index_service = Self::new(metastore, storage_resolver, config.default_index_root_uri)
+            .with_namespace_configs(config.namespaces);
         Ok(index_service)
     }
 
@@ -112,10 +137,22 @@ impl IndexService {
         Ok(indexes_metadatas)
     }
 
+    /// Get the indexes of a namespace, including the indexes of its nested namespaces.
+    pub async fn list_indexes_in_namespace(
+        &self,
+        namespace: &str,
+    ) -> anyhow::Result<Vec<IndexMetadata>> {
+        let indexes_metadatas = self
+            .metastore
+            .list_indexes_metadatas_in_namespace(namespace)
+            .await?;
+        Ok(indexes_metadatas)
+    }
+
     /// Creates an index from `IndexConfig`.
     pub async fn create_index(
         &self,
-        index_config: IndexConfig,
+        mut index_config: IndexConfig,
         overwrite: bool,
     ) -> Result<IndexMetadata, IndexServiceError> {
         // Delete existing index if it exists.
@@ -132,14 +169,26 @@ impl IndexService {
                 }
             }
         }
+        let namespace_config_opt =
+            NamespaceConfig::find(&self.namespace_configs, &index_config.index_id);
+        if let Some(namespace_config) = namespace_config_opt {
+            if index_config.retention_policy.is_none() {
+                index_config.retention_policy = namespace_config.default_retention_policy.clone();
+            }
+        }
         index_config
             .validate()
             .map_err(|error| IndexServiceError::InvalidIndexConfig(error.to_string()))?;
+        self.check_namespace_conflicts(&index_config.index_id)
+            .await?;
         let index_id = index_config.index_id.clone();
         let index_uri = if let Some(index_uri) = &index_config.index_uri {
             index_uri.clone()
         } else {
-            let index_uri = self.default_index_root_uri.join(&index_id).expect(
+            let default_index_root_uri = namespace_config_opt
+                .and_then(|namespace_config| namespace_config.default_index_root_uri.as_ref())
+                .unwrap_or(&self.default_index_root_uri);
+            let index_uri = default_index_root_uri.join(&index_id).expect(
                 "Failed to create default index URI. This should never happen! Please, report on https://github.com/quickwit-oss/quickwit/issues.",
             );
             info!(
@@ -155,11 +204,16 @@ impl IndexService {
             checkpoint: Default::default(),
             sources: index_config.sources(),
             doc_mapping: index_config.doc_mapping,
+            doc_mapping_version: 0,
             indexing_settings: index_config.indexing_settings,
             search_settings: index_config.search_settings,
             retention_policy: index_config.retention_policy,
             create_timestamp: OffsetDateTime::now_utc().unix_timestamp(),
             update_timestamp: OffsetDateTime::now_utc().unix_timestamp(),
+            maintenance_mode: false,
+            merge_planner_lease: None,
+            quarantined_files: Default::default(),
+            feature_flags: Default::default(),
         };
         self.metastore.create_index(index_metadata).await?;
         let index_metadata = self
@@ -169,6 +223,134 @@ impl IndexService {
         Ok(index_metadata)
     }
 
+    /// Checks that the ID of a new index is neither the ID of a namespace holding indexes nor
+    /// nested in the namespace named after an existing index: the indexing and cache directories
+    /// of an index are named after its ID, so they would be shared.
+    async fn check_namespace_conflicts(&self, index_id: &str) -> Result<(), IndexServiceError> {
+        let nested_indexes = self
+            .metastore
+            .list_indexes_metadatas_in_namespace(index_id)
+            .await?;
+        if let Some(nested_index) = nested_indexes.first() {
+            return Err(IndexServiceError::InvalidIndexConfig(format!(
+                "index ID `{}` is the namespace of index `{}`",
+                index_id, nested_index.index_id
+            )));
+        }
+        let mut namespace_opt = index_namespace(index_id);
+        while let Some(namespace) = namespace_opt {
+            match self.metastore.index_metadata(namespace).await {
+                Ok(_) => {
+                    return Err(IndexServiceError::InvalidIndexConfig(format!(
+                        "namespace `{}` of index `{}` is the ID of an existing index",
+                        namespace, index_id
+                    )));
+                }
+                Err(MetastoreError::IndexDoesNotExist { .. }) => {}
+                Err(error) => return Err(error.into()),
+            }
+            namespace_opt = index_namespace(namespace);
+        }
+        Ok(())
+    }
+
+    /// Exports the definition of an index: its doc mapping, indexing and search settings, sources,
+    /// and retention policy. The exported index config can be imported with
+    /// [`IndexService::import_index`] or passed to `quickwit index create` to create an identical
+    /// index elsewhere.
+    pub async fn export_index(&self, index_id: &str) -> Result<IndexConfig, IndexServiceError> {
+        let index_metadata = self.metastore.index_metadata(index_id).await?;
+        let mut sources: Vec<SourceConfig> = index_metadata.sources.into_values().collect();
+        sources.sort_by(|left, right| left.source_id.cmp(&right.source_id));
+        let index_config = IndexConfig {
+            version: INDEX_CONFIG_VERSION,
+            index_id: index_metadata.index_id,
+            index_uri: Some(index_metadata.index_uri),
+            doc_mapping: index_metadata.doc_mapping,
+            indexing_settings: index_metadata.indexing_settings,
+            search_settings: index_metadata.search_settings,
+            sources,
+            retention_policy: index_metadata.retention_policy,
+        };
+        Ok(index_config)
+    }
+
+    /// Creates an index from an index definition exported with [`IndexService::export_index`],
+    /// possibly by another cluster.
+    pub async fn import_index(
+        &self,
+        mut index_config: IndexConfig,
+        options: ImportIndexOptions,
+    ) -> Result<IndexMetadata, IndexServiceError> {
+        if index_config.version > INDEX_CONFIG_VERSION {
+            return Err(IndexServiceError::InvalidIndexConfig(format!(
+                "index config version `{}` is not supported, the most recent supported version is \
+                 `{}`",
+                index_config.version, INDEX_CONFIG_VERSION
+            )));
+        }
+        if let Some(index_id) = options.index_id_opt {
+            index_config.index_id = index_id;
+        }
+        if !options.keep_index_uri {
+            index_config.index_uri = None;
+        }
+        // Two indexes sharing the same URI would delete each other's splits.
+        if let Some(index_uri) = &index_config.index_uri {
+            let conflicting_index_opt = self
+                .metastore
+                .list_indexes_metadatas()
+                .await?
+                .into_iter()
+                .find(|index_metadata| {
+                    index_metadata.index_uri == *index_uri
+                        && index_metadata.index_id != index_config.index_id
+                });
+            if let Some(conflicting_index) = conflicting_index_opt {
+                return Err(IndexServiceError::InvalidIndexConfig(format!(
+                    "index URI `{}` is already used by index `{}`",
+                    index_uri, conflicting_index.index_id
+                )));
+            }
+        }
+        info!(
+            index_id = %index_config.index_id,
+            version = index_config.version,
+            "Importing index."
+        );
+        self.create_index(index_config, options.overwrite).await
+    }
+
+    /// Updates the doc mapping and the search settings of an index. Only field mappings can be
+    /// added or removed: the new splits are indexed with the new doc mapping, while the splits
+    /// indexed with the previous ones are searched as if the added fields were empty. The running
+    /// indexing pipelines of the index pick up the new doc mapping once restarted.
+    pub async fn update_index(
+        &self,
+        index_id: &str,
+        doc_mapping: DocMapping,
+        search_settings: SearchSettings,
+    ) -> Result<IndexMetadata, IndexServiceError> {
+        let index_metadata = self.metastore.index_metadata(index_id).await?;
+        validate_doc_mapping_update(
+            &index_metadata.doc_mapping,
+            &doc_mapping,
+            &search_settings,
+            &index_metadata.indexing_settings,
+        )
+        .map_err(|error| IndexServiceError::InvalidIndexConfig(error.to_string()))?;
+        self.metastore
+            .update_index(index_id, doc_mapping, search_settings)
+            .await?;
+        let index_metadata = self.metastore.index_metadata(index_id).await?;
+        info!(
+            index_id = %index_id,
+            doc_mapping_version = index_metadata.doc_mapping_version,
+            "Updated index doc mapping."
+        );
+        Ok(index_metadata)
+    }
+
     /// Deletes the index specified with `index_id`.
     /// This is equivalent to running `rm -rf <index path>` for a local index or
     /// `aws s3 rm --recursive <index path>` for a remote Amazon S3 index.
@@ -268,6 +450,64 @@ impl IndexService {
         Ok(deleted_entries)
     }
 
+    /// Recomputes the tags of the published splits of the index from its current doc mapping and
+    /// amends the tags of the splits whose tags changed, without rewriting the splits.
+    ///
+    /// * `index_id` - The target index Id.
+    /// * `reason` - Why the tags are recomputed, recorded along with the amended tags.
+    /// * `dry_run` - Should this only return the tags changes without amending the splits.
+    pub async fn retag_splits(
+        &self,
+        index_id: &str,
+        reason: &str,
+        dry_run: bool,
+    ) -> anyhow::Result<Vec<SplitRetagging>> {
+        let index_metadata = self.metastore.index_metadata(index_id).await?;
+        let storage = self.storage_resolver.resolve(&index_metadata.index_uri)?;
+        let split_store = IndexingSplitStore::create_with_no_local_store(storage);
+
+        let split_retaggings = run_split_retagging(
+            &index_metadata,
+            split_store,
+            self.metastore.clone(),
+            reason,
+            dry_run,
+        )
+        .await?;
+
+        Ok(split_retaggings)
+    }
+
+    /// Restores in the metastore the splits of the index whose files are in the storage of the
+    /// index but that the metastore does not know of, for instance after the metastore was lost
+    /// and the index created again with the same index URI.
+    ///
+    /// * `index_id` - The target index Id.
+    /// * `dry_run` - Should this only return the splits to restore without publishing them.
+    pub async fn repair_index(&self, index_id: &str, dry_run: bool) -> anyhow::Result<IndexRepair> {
+        let index_metadata = self.metastore.index_metadata(index_id).await?;
+        let storage = self.storage_resolver.resolve(&index_metadata.index_uri)?;
+        let index_repair =
+            run_index_repair(&index_metadata, storage, self.metastore.clone(), dry_run).await?;
+        Ok(index_repair)
+    }
+
+    /// Runs the merge policy of the index against its published splits without executing any
+    /// merge, and returns the planned operations along with their estimated write amplification.
+    ///
+    /// * `index_id` - The target index Id.
+    /// * `overrides` - The merge settings to preview instead of the settings of the index.
+    pub async fn preview_merges(
+        &self,
+        index_id: &str,
+        overrides: &MergeSettingsOverrides,
+    ) -> anyhow::Result<MergePolicyPreview> {
+        let index_metadata = self.metastore.index_metadata(index_id).await?;
+        let merge_policy_preview =
+            run_merge_policy_preview(&index_metadata, self.metastore.clone(), overrides).await?;
+        Ok(merge_policy_preview)
+    }
+
     /// Clears the index by applying the following actions:
     /// - mark all splits for deletion in the metastore.
     /// - delete the files of all splits marked for deletion using garbage collection.
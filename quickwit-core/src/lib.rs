@@ -21,7 +21,7 @@ mod index;
 
 pub use index::{
     clear_cache_directory, get_cache_directory_path, remove_indexing_directory,
-    validate_storage_uri, IndexService, IndexServiceError,
+    validate_storage_uri, ImportIndexOptions, IndexService, IndexServiceError,
 };
 
 #[cfg(test)]
@@ -29,12 +29,15 @@ mod tests {
     use std::path::Path;
 
     use quickwit_common::uri::Uri;
-    use quickwit_config::{IndexConfig, IndexingSettings, SearchSettings};
+    use quickwit_config::{
+        IndexConfig, IndexingSettings, NamespaceConfig, RetentionPolicy,
+        RetentionPolicyCutoffReference, SearchSettings, SourceConfig, SourceParams,
+    };
     use quickwit_indexing::{FileEntry, TestSandbox};
     use quickwit_metastore::quickwit_metastore_uri_resolver;
     use quickwit_storage::StorageUriResolver;
 
-    use crate::IndexService;
+    use crate::{ImportIndexOptions, IndexService, IndexServiceError};
 
     #[tokio::test]
     async fn test_file_entry_from_split_and_index_delete() -> anyhow::Result<()> {
@@ -117,4 +120,192 @@ mod tests {
         );
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_export_import_index() -> anyhow::Result<()> {
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: title
+                type: text
+        "#;
+        let index_config = IndexConfig {
+            version: 0,
+            index_id: "test-index--export".to_string(),
+            index_uri: None,
+            doc_mapping: serde_yaml::from_str(doc_mapping_yaml)?,
+            retention_policy: Some(RetentionPolicy::new(
+                "30 days".to_string(),
+                RetentionPolicyCutoffReference::PublishTimestamp,
+                "daily".to_string(),
+            )),
+            indexing_settings: IndexingSettings::default(),
+            search_settings: SearchSettings::default(),
+            sources: vec![SourceConfig {
+                source_id: "void-source".to_string(),
+                num_pipelines: 1,
+                profile: None,
+                rate_limit: None,
+                transforms: Vec::new(),
+                strict_ordering: false,
+                source_params: SourceParams::void(),
+            }],
+        };
+        let metastore = quickwit_metastore_uri_resolver()
+            .resolve(&Uri::new("ram:///metastore".to_string()))
+            .await?;
+        let index_service = IndexService::new(
+            metastore,
+            StorageUriResolver::for_test(),
+            Uri::new("ram:///indexes".to_string()),
+        );
+        index_service.create_index(index_config, false).await?;
+
+        let exported_index_config = index_service.export_index("test-index--export").await?;
+        assert_eq!(
+            exported_index_config.index_uri.as_ref().unwrap(),
+            "ram:///indexes/test-index--export"
+        );
+        // The export round-trips through its serialized form.
+        let exported_json = serde_json::to_string(&exported_index_config)?;
+        let exported_index_config: IndexConfig = serde_json::from_str(&exported_json)?;
+
+        // The exported index URI is already used by the exported index.
+        let import_error = index_service
+            .import_index(
+                exported_index_config.clone(),
+                ImportIndexOptions {
+                    index_id_opt: Some("test-index--import".to_string()),
+                    keep_index_uri: true,
+                    overwrite: false,
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            import_error,
+            IndexServiceError::InvalidIndexConfig(_)
+        ));
+
+        let imported_index_metadata = index_service
+            .import_index(
+                exported_index_config.clone(),
+                ImportIndexOptions {
+                    index_id_opt: Some("test-index--import".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert_eq!(imported_index_metadata.index_id, "test-index--import");
+        assert_eq!(
+            imported_index_metadata.index_uri,
+            "ram:///indexes/test-index--import"
+        );
+        assert_eq!(
+            imported_index_metadata.sources,
+            exported_index_config.sources()
+        );
+        assert_eq!(
+            imported_index_metadata.retention_policy,
+            exported_index_config.retention_policy
+        );
+
+        let mut unsupported_index_config = exported_index_config;
+        unsupported_index_config.version = 1;
+        let import_error = index_service
+            .import_index(
+                unsupported_index_config,
+                ImportIndexOptions {
+                    index_id_opt: Some("test-index--unsupported".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            import_error,
+            IndexServiceError::InvalidIndexConfig(_)
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_create_index_in_namespace() -> anyhow::Result<()> {
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: title
+                type: text
+        "#;
+        let index_config = |index_id: &str| -> anyhow::Result<IndexConfig> {
+            Ok(IndexConfig {
+                version: 0,
+                index_id: index_id.to_string(),
+                index_uri: None,
+                doc_mapping: serde_yaml::from_str(doc_mapping_yaml)?,
+                retention_policy: None,
+                indexing_settings: IndexingSettings::default(),
+                search_settings: SearchSettings::default(),
+                sources: Vec::new(),
+            })
+        };
+        let retention_policy = RetentionPolicy::new(
+            "30 days".to_string(),
+            RetentionPolicyCutoffReference::PublishTimestamp,
+            "daily".to_string(),
+        );
+        let metastore_uri = Uri::new("ram:///metastore".to_string());
+        let metastore = quickwit_metastore_uri_resolver()
+            .resolve(&metastore_uri)
+            .await
+            .unwrap();
+        let index_service = IndexService::new(
+            metastore,
+            StorageUriResolver::for_test(),
+            Uri::new("ram:///indexes".to_string()),
+        )
+        .with_namespace_configs(vec![NamespaceConfig {
+            namespace: "test-team".to_string(),
+            default_index_root_uri: Some(Uri::new("ram:///test-team-indexes".to_string())),
+            default_retention_policy: Some(retention_policy.clone()),
+        }]);
+        let index_metadata = index_service
+            .create_index(index_config("test-team/logs")?, false)
+            .await?;
+        assert_eq!(
+            index_metadata.index_uri,
+            "ram:///test-team-indexes/test-team/logs"
+        );
+        assert_eq!(index_metadata.retention_policy, Some(retention_policy));
+
+        let index_metadata = index_service
+            .create_index(index_config("test-other-team/logs")?, false)
+            .await?;
+        assert_eq!(
+            index_metadata.index_uri,
+            "ram:///indexes/test-other-team/logs"
+        );
+        assert!(index_metadata.retention_policy.is_none());
+
+        let namespace_indexes = index_service.list_indexes_in_namespace("test-team").await?;
+        assert_eq!(namespace_indexes.len(), 1);
+        assert_eq!(namespace_indexes[0].index_id, "test-team/logs");
+
+        // An index cannot be named after a namespace, or the other way around.
+        let create_error = index_service
+            .create_index(index_config("test-team")?, false)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            create_error,
+            IndexServiceError::InvalidIndexConfig(_)
+        ));
+        let create_error = index_service
+            .create_index(index_config("test-team/logs/errors")?, false)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            create_error,
+            IndexServiceError::InvalidIndexConfig(_)
+        ));
+        Ok(())
+    }
 }
@@ -26,12 +26,18 @@
 //! - The `CachingDirectory` wraps a Directory with a dynamic cache.
 //! - The `DebugDirectory` acts as a proxy to another directory to instrument it and record all of
 //!   its IO.
+//! - The `SchemaReconcilingDirectory` exposes the fields of the current schema missing from a split
+//!   created with an older doc mapping.
+//! - The `LocalSplitRegistry` holds the splits packaged on the node, searchable before they are
+//!   uploaded and published.
 #![warn(missing_docs)]
 
 mod bundle_directory;
 mod caching_directory;
 mod debug_proxy_directory;
 mod hot_directory;
+mod local_split_registry;
+mod schema_reconciling_directory;
 mod storage_directory;
 mod union_directory;
 
@@ -39,6 +45,8 @@ pub use self::bundle_directory::{get_hotcache_from_split, read_split_footer, Bun
 pub use self::caching_directory::CachingDirectory;
 pub use self::debug_proxy_directory::{DebugProxyDirectory, ReadOperation};
 pub use self::hot_directory::{write_hotcache, HotDirectory};
+pub use self::local_split_registry::{open_split_directory, LocalSplit, LocalSplitRegistry};
+pub use self::schema_reconciling_directory::SchemaReconcilingDirectory;
 pub use self::storage_directory::StorageDirectory;
 pub use self::union_directory::UnionDirectory;
 
@@ -0,0 +1,253 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use tantivy::directory::{MmapDirectory, OwnedBytes};
+use tantivy::{Index, IndexReader, ReloadPolicy};
+
+use crate::HotDirectory;
+
+/// Opens the index of a split from the directory it was packaged in, reading the files of the
+/// split through its hotcache.
+///
+/// The `meta.json` file is served by the hotcache, so that the index can still be read once the
+/// directory is moved or deleted, as long as the files of its segments remain mapped in memory.
+pub fn open_split_directory(split_dir: &Path, hotcache_bytes: &[u8]) -> tantivy::Result<Index> {
+    let mmap_directory = MmapDirectory::open(split_dir)?;
+    let hot_directory =
+        HotDirectory::open(mmap_directory, OwnedBytes::new(hotcache_bytes.to_vec()))?;
+    Index::open(hot_directory)
+}
+
+/// A split packaged by an indexing pipeline of the node, searchable by the searcher of the node
+/// while it is uploaded and published.
+pub struct LocalSplit {
+    /// ID of the index of the split.
+    pub index_id: String,
+    /// ID of the split.
+    pub split_id: String,
+    /// Time range of the documents of the split, if the index has a timestamp field.
+    pub time_range: Option<RangeInclusive<i64>>,
+    index: Index,
+    // Holds the segment readers of the split, hence the memory maps of its files, which remain
+    // readable after the split directory is moved into the split cache or deleted.
+    _reader: IndexReader,
+    is_alive: Box<dyn Fn() -> bool + Send + Sync>,
+}
+
+impl LocalSplit {
+    /// Creates a local split searching `index`. The split is searchable as long as `is_alive`
+    /// returns true: once it returns false, for instance because the publish lock of the split was
+    /// killed, the split is never searched again.
+    pub fn new(
+        index_id: String,
+        split_id: String,
+        time_range: Option<RangeInclusive<i64>>,
+        index: Index,
+        is_alive: impl Fn() -> bool + Send + Sync + 'static,
+    ) -> tantivy::Result<LocalSplit> {
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        Ok(LocalSplit {
+            index_id,
+            split_id,
+            time_range,
+            index,
+            _reader: reader,
+            is_alive: Box::new(is_alive),
+        })
+    }
+
+    /// Returns the index of the split.
+    pub fn index(&self) -> &Index {
+        &self.index
+    }
+
+    /// Returns whether the split can still be searched.
+    pub fn is_alive(&self) -> bool {
+        (self.is_alive)()
+    }
+}
+
+impl fmt::Debug for LocalSplit {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("LocalSplit")
+            .field("index_id", &self.index_id)
+            .field("split_id", &self.split_id)
+            .field("time_range", &self.time_range)
+            .finish()
+    }
+}
+
+/// Registry of the splits packaged on the node and not published yet, shared by the indexing
+/// pipelines registering them and the searcher of the node.
+///
+/// A split is unregistered once published, after which it is searched like any other published
+/// split. The splits whose liveness check fails are dropped lazily.
+#[derive(Clone, Debug, Default)]
+pub struct LocalSplitRegistry {
+    local_splits: Arc<RwLock<HashMap<String, Arc<LocalSplit>>>>,
+}
+
+impl LocalSplitRegistry {
+    /// Registers a split.
+    pub fn register(&self, local_split: LocalSplit) {
+        self.local_splits
+            .write()
+            .unwrap()
+            .insert(local_split.split_id.clone(), Arc::new(local_split));
+    }
+
+    /// Unregisters splits. Unknown split IDs are ignored.
+    pub fn unregister(&self, split_ids: &[&str]) {
+        let mut local_splits = self.local_splits.write().unwrap();
+        for split_id in split_ids {
+            local_splits.remove(*split_id);
+        }
+    }
+
+    /// Returns the split `split_id` if it is registered and alive.
+    pub fn get(&self, split_id: &str) -> Option<Arc<LocalSplit>> {
+        let local_split = self.local_splits.read().unwrap().get(split_id).cloned()?;
+        if local_split.is_alive() {
+            return Some(local_split);
+        }
+        self.unregister(&[split_id]);
+        None
+    }
+
+    /// Returns the splits of the index `index_id` that are alive, sorted by split ID.
+    pub fn list(&self, index_id: &str) -> Vec<Arc<LocalSplit>> {
+        let mut local_splits = self.local_splits.write().unwrap();
+        local_splits.retain(|_, local_split| local_split.is_alive());
+        let mut index_local_splits: Vec<Arc<LocalSplit>> = local_splits
+            .values()
+            .filter(|local_split| local_split.index_id == index_id)
+            .cloned()
+            .collect();
+        index_local_splits.sort_by(|left, right| left.split_id.cmp(&right.split_id));
+        index_local_splits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use tantivy::collector::Count;
+    use tantivy::query::AllQuery;
+    use tantivy::schema::{Schema, TEXT};
+    use tantivy::{doc, IndexWriter};
+
+    use super::*;
+    use crate::write_hotcache;
+
+    fn create_split(split_dir: &Path) -> anyhow::Result<Vec<u8>> {
+        let mut schema_builder = Schema::builder();
+        let body = schema_builder.add_text_field("body", TEXT);
+        let index = Index::create_in_dir(split_dir, schema_builder.build())?;
+        let mut index_writer: IndexWriter = index.writer_with_num_threads(1, 15_000_000)?;
+        index_writer.add_document(doc!(body => "hello"))?;
+        index_writer.add_document(doc!(body => "happy tax payer"))?;
+        index_writer.commit()?;
+        index_writer.wait_merging_threads()?;
+        let mut hotcache_bytes = Vec::new();
+        write_hotcache(MmapDirectory::open(split_dir)?, &mut hotcache_bytes)?;
+        Ok(hotcache_bytes)
+    }
+
+    #[test]
+    fn test_local_split_remains_searchable_after_split_directory_move() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let split_dir = temp_dir.path().join("scratch");
+        std::fs::create_dir(&split_dir)?;
+        let hotcache_bytes = create_split(&split_dir)?;
+
+        let registry = LocalSplitRegistry::default();
+        let index = open_split_directory(&split_dir, &hotcache_bytes)?;
+        let local_split = LocalSplit::new(
+            "test-index".to_string(),
+            "split-1".to_string(),
+            None,
+            index,
+            || true,
+        )?;
+        registry.register(local_split);
+
+        std::fs::rename(&split_dir, temp_dir.path().join("cache"))?;
+
+        let local_split = registry.get("split-1").unwrap();
+        let reader = local_split
+            .index()
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        assert_eq!(reader.searcher().search(&AllQuery, &Count)?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_local_split_registry_drops_dead_splits() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let hotcache_bytes = create_split(temp_dir.path())?;
+        let registry = LocalSplitRegistry::default();
+        let is_alive = Arc::new(AtomicBool::new(true));
+
+        for (index_id, split_id) in [
+            ("test-index", "split-2"),
+            ("test-index", "split-1"),
+            ("other-index", "split-3"),
+        ] {
+            let index = open_split_directory(temp_dir.path(), &hotcache_bytes)?;
+            let is_alive_clone = is_alive.clone();
+            let local_split = LocalSplit::new(
+                index_id.to_string(),
+                split_id.to_string(),
+                Some(0..=10),
+                index,
+                move || is_alive_clone.load(Ordering::SeqCst),
+            )?;
+            registry.register(local_split);
+        }
+        let split_ids: Vec<String> = registry
+            .list("test-index")
+            .iter()
+            .map(|local_split| local_split.split_id.clone())
+            .collect();
+        assert_eq!(split_ids, ["split-1", "split-2"]);
+
+        registry.unregister(&["split-1", "unknown-split"]);
+        assert!(registry.get("split-1").is_none());
+        assert!(registry.get("split-2").is_some());
+
+        is_alive.store(false, Ordering::SeqCst);
+        assert!(registry.get("split-2").is_none());
+        assert!(registry.list("other-index").is_empty());
+        assert!(registry.local_splits.read().unwrap().is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde_json::Value as JsonValue;
+use tantivy::directory::error::OpenReadError;
+use tantivy::directory::FileHandle;
+use tantivy::schema::Schema;
+use tantivy::Directory;
+
+const META_FILEPATH: &str = "meta.json";
+
+/// Wraps the directory of a split created with an older doc mapping so that its index exposes
+/// the fields of the current schema.
+///
+/// The fields of the current schema missing from the split are appended to the schema stored in
+/// the `meta.json` file of the split. The fields of the split keep their IDs, and the appended
+/// fields have no data in the segments of the split, so queries on them match no document.
+#[derive(Clone, Debug)]
+pub struct SchemaReconcilingDirectory {
+    underlying: Arc<dyn Directory>,
+    schema: Schema,
+}
+
+impl SchemaReconcilingDirectory {
+    /// Creates a directory exposing the fields of `schema` missing from the split stored in
+    /// `underlying`.
+    pub fn wrap(underlying: Arc<dyn Directory>, schema: Schema) -> SchemaReconcilingDirectory {
+        SchemaReconcilingDirectory { underlying, schema }
+    }
+
+    fn reconcile_meta_json(&self, meta_json: &[u8]) -> serde_json::Result<Vec<u8>> {
+        let mut meta: JsonValue = serde_json::from_slice(meta_json)?;
+        let split_field_entries = match meta.get_mut("schema").and_then(JsonValue::as_array_mut) {
+            Some(split_field_entries) => split_field_entries,
+            None => return Ok(meta_json.to_vec()),
+        };
+        let split_field_names: HashSet<String> = split_field_entries
+            .iter()
+            .flat_map(|field_entry| field_entry.get("name").and_then(JsonValue::as_str))
+            .map(str::to_string)
+            .collect();
+        let num_split_fields = split_field_entries.len();
+        for (_, field_entry) in self.schema.fields() {
+            if !split_field_names.contains(field_entry.name()) {
+                split_field_entries.push(serde_json::to_value(field_entry)?);
+            }
+        }
+        if split_field_entries.len() == num_split_fields {
+            return Ok(meta_json.to_vec());
+        }
+        serde_json::to_vec(&meta)
+    }
+}
+
+impl Directory for SchemaReconcilingDirectory {
+    fn get_file_handle(&self, path: &Path) -> Result<Arc<dyn FileHandle>, OpenReadError> {
+        self.underlying.get_file_handle(path)
+    }
+
+    fn exists(&self, path: &Path) -> Result<bool, OpenReadError> {
+        self.underlying.exists(path)
+    }
+
+    fn atomic_read(&self, path: &Path) -> Result<Vec<u8>, OpenReadError> {
+        let payload = self.underlying.atomic_read(path)?;
+        if path != Path::new(META_FILEPATH) {
+            return Ok(payload);
+        }
+        self.reconcile_meta_json(&payload).map_err(|error| {
+            OpenReadError::wrap_io_error(
+                io::Error::new(io::ErrorKind::InvalidData, error),
+                path.to_path_buf(),
+            )
+        })
+    }
+
+    crate::read_only_directory!();
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::collector::Count;
+    use tantivy::directory::RamDirectory;
+    use tantivy::query::TermQuery;
+    use tantivy::schema::{IndexRecordOption, TEXT};
+    use tantivy::{doc, Index, Term};
+
+    use super::*;
+
+    #[test]
+    fn test_schema_reconciling_directory() -> anyhow::Result<()> {
+        let mut split_schema_builder = Schema::builder();
+        let split_body_field = split_schema_builder.add_text_field("body", TEXT);
+        let split_schema = split_schema_builder.build();
+
+        let ram_directory = RamDirectory::create();
+        let index = Index::create(ram_directory.clone(), split_schema, Default::default())?;
+        let mut index_writer = index.writer_with_num_threads(1, 15_000_000)?;
+        index_writer.add_document(doc!(split_body_field => "hello"))?;
+        index_writer.commit()?;
+
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("severity", TEXT);
+        schema_builder.add_text_field("body", TEXT);
+        let schema = schema_builder.build();
+
+        let directory = SchemaReconcilingDirectory::wrap(Arc::new(ram_directory), schema);
+        let index = Index::open(directory)?;
+        let index_schema = index.schema();
+        // The fields of the split keep their IDs.
+        assert_eq!(index_schema.get_field("body"), Some(split_body_field));
+        let severity_field = index_schema.get_field("severity").unwrap();
+
+        let searcher = index.reader()?.searcher();
+        let body_query = TermQuery::new(
+            Term::from_field_text(split_body_field, "hello"),
+            IndexRecordOption::Basic,
+        );
+        assert_eq!(searcher.search(&body_query, &Count)?, 1);
+        let severity_query = TermQuery::new(
+            Term::from_field_text(severity_field, "error"),
+            IndexRecordOption::Basic,
+        );
+        assert_eq!(searcher.search(&severity_query, &Count)?, 0);
+        Ok(())
+    }
+}
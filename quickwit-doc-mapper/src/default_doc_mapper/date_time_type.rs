@@ -25,6 +25,7 @@ use derivative::Derivative;
 use indexmap::IndexSet;
 use itertools::Itertools;
 use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value as JsonValue;
 use tantivy::{DatePrecision as DateTimePrecision, DateTime};
 use time::format_description::well_known::{Iso8601, Rfc2822, Rfc3339};
 use time::OffsetDateTime;
@@ -57,6 +58,11 @@ pub struct QuickwitDateTimeOptions {
 
     #[serde(default)]
     pub fast: bool,
+
+    /// Value the fast field is set to in the documents missing it, instead of rejecting them.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_value: Option<JsonValue>,
 }
 
 impl Default for QuickwitDateTimeOptions {
@@ -68,6 +74,7 @@ impl Default for QuickwitDateTimeOptions {
             indexed: true,
             stored: true,
             fast: false,
+            default_value: None,
         }
     }
 }
@@ -111,6 +118,60 @@ impl QuickwitDateTimeOptions {
     }
 }
 
+/// Parses a date time from a string or a number with the first matching input format.
+pub fn parse_date_time(
+    value: &JsonValue,
+    input_formats: &[DateTimeFormat],
+) -> Result<OffsetDateTime, String> {
+    let date_time_options = QuickwitDateTimeOptions {
+        input_formats: input_formats.iter().cloned().collect(),
+        ..Default::default()
+    };
+    match value {
+        JsonValue::String(value) => date_time_options.parse_string(value.clone()),
+        JsonValue::Number(number) => match number.as_i64() {
+            Some(timestamp) => date_time_options.parse_number(timestamp),
+            None => Err(format!("Expected an integer timestamp, got `{}`.", number)),
+        },
+        _ => Err(format!(
+            "Expected a datetime string or timestamp, got `{}`.",
+            value
+        )),
+    }
+}
+
+/// Formats a date time as an RFC3339 string or as a Unix timestamp.
+pub fn format_date_time(
+    date_time: OffsetDateTime,
+    output_format: &DateTimeFormat,
+) -> Result<JsonValue, String> {
+    let timestamp_nanos = date_time.unix_timestamp_nanos();
+    let formatted_date_time = match output_format {
+        DateTimeFormat::RCF3339 => JsonValue::String(
+            date_time
+                .format(&Rfc3339)
+                .map_err(|error| error.to_string())?,
+        ),
+        DateTimeFormat::Timestamp(DateTimePrecision::Seconds) => {
+            JsonValue::from(date_time.unix_timestamp())
+        }
+        DateTimeFormat::Timestamp(DateTimePrecision::Milliseconds) => {
+            JsonValue::from((timestamp_nanos / 1_000_000) as i64)
+        }
+        DateTimeFormat::Timestamp(DateTimePrecision::Microseconds) => {
+            JsonValue::from((timestamp_nanos / 1_000) as i64)
+        }
+        _ => {
+            return Err(format!(
+                "Output format `{}` is not supported, expected `rfc3339`, `unix_ts_secs`, \
+                 `unix_ts_millis`, or `unix_ts_micros`.",
+                output_format
+            ))
+        }
+    };
+    Ok(formatted_date_time)
+}
+
 /// Parses datetime strings using RFC3339 formatting.
 fn parse_rfc3339(value: &str) -> Result<OffsetDateTime, String> {
     OffsetDateTime::parse(value, &Rfc3339).map_err(|error| error.to_string())
@@ -252,13 +313,15 @@ pub(crate) fn timestamp_to_datetime_str(
 #[cfg(test)]
 mod tests {
     use indexmap::IndexSet;
+    use serde_json::json;
     use tantivy::schema::Cardinality;
     use tantivy::DatePrecision as DateTimePrecision;
     use time::macros::{date, time};
 
     use super::DateTimeFormat;
     use crate::default_doc_mapper::date_time_type::{
-        parse_strftime, parse_unix_timestamp, QuickwitDateTimeOptions,
+        format_date_time, parse_date_time, parse_strftime, parse_unix_timestamp,
+        QuickwitDateTimeOptions,
     };
     use crate::default_doc_mapper::FieldMappingType;
     use crate::FieldMappingEntry;
@@ -330,6 +393,7 @@ mod tests {
             indexed: true,
             fast: true,
             stored: false,
+            default_value: None,
         };
 
         assert!(
@@ -504,4 +568,37 @@ mod tests {
         assert_eq!(date_time.date(), now.date());
         assert_eq!(date_time.time().as_hms_micro(), now.time().as_hms_micro());
     }
+
+    #[test]
+    fn test_parse_and_format_date_time() {
+        let input_formats = [
+            DateTimeFormat::Strftime("%Y-%m-%d %H:%M:%S".to_string()),
+            DateTimeFormat::Timestamp(DateTimePrecision::Milliseconds),
+        ];
+        let date_time = parse_date_time(&json!("2022-09-12 14:09:14"), &input_formats).unwrap();
+        assert_eq!(
+            format_date_time(date_time, &DateTimeFormat::RCF3339).unwrap(),
+            json!("2022-09-12T14:09:14Z")
+        );
+        assert_eq!(
+            format_date_time(
+                date_time,
+                &DateTimeFormat::Timestamp(DateTimePrecision::Seconds)
+            )
+            .unwrap(),
+            json!(1662991754)
+        );
+        let date_time = parse_date_time(&json!(1662991754123i64), &input_formats).unwrap();
+        assert_eq!(
+            format_date_time(
+                date_time,
+                &DateTimeFormat::Timestamp(DateTimePrecision::Milliseconds)
+            )
+            .unwrap(),
+            json!(1662991754123i64)
+        );
+        assert!(parse_date_time(&json!("12/09/2022"), &input_formats).is_err());
+        assert!(parse_date_time(&json!(true), &input_formats).is_err());
+        assert!(format_date_time(date_time, &DateTimeFormat::RFC2822).is_err());
+    }
 }
@@ -18,26 +18,31 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::{BTreeMap, BTreeSet};
+use std::hash::Hasher;
 use std::str::FromStr;
 
 use anyhow::{bail, Context};
 use quickwit_proto::SearchRequest;
 use serde::{Deserialize, Serialize};
 use serde_json::{self, Value as JsonValue};
+use siphasher::sip128::{Hasher128, SipHasher};
 use tantivy::query::Query;
-use tantivy::schema::{Cardinality, Field, FieldType, Schema, STORED};
+use tantivy::schema::{Cardinality, Field, FieldType, Schema, Value, STORED, STRING};
 use tantivy::Document;
+use ulid::Ulid;
+use uuid::Uuid;
 
 use super::field_mapping_entry::QuickwitTextTokenizer;
-use super::DefaultDocMapperBuilder;
+use super::{DefaultDocMapperBuilder, DocIdConfig};
 use crate::default_doc_mapper::mapping_tree::{build_mapping_tree, MappingNode, MappingTree};
 pub use crate::default_doc_mapper::QuickwitJsonOptions;
 use crate::doc_mapper::Partition;
-use crate::query_builder::build_query;
+use crate::query_builder::{build_query, validate_field_boost};
 use crate::routing_expression::RoutingExpr;
 use crate::sort_by::{validate_sort_by_field_name, SortBy, SortOrder};
 use crate::{
-    DocMapper, DocParsingError, ModeType, QueryParserError, DYNAMIC_FIELD_NAME, SOURCE_FIELD_NAME,
+    DocMapper, DocParsingError, ModeType, QueryParserError, TermExpansion, DOC_ID_FIELD_NAME,
+    DYNAMIC_FIELD_NAME, SOURCE_FIELD_NAME,
 };
 
 /// Specifies the name of the sort field and the sort order for an index.
@@ -87,6 +92,54 @@ impl Default for Mode {
     }
 }
 
+impl DocIdConfig {
+    /// Extracts or generates the ID of a document.
+    fn doc_id(
+        &self,
+        doc_json: &str,
+        json_obj: &serde_json::Map<String, JsonValue>,
+    ) -> Result<String, DocParsingError> {
+        match self {
+            DocIdConfig::Field { field } => extract_doc_id(field, json_obj),
+            DocIdConfig::Ulid => Ok(Ulid::new().to_string()),
+            DocIdConfig::Uuid => Ok(Uuid::new_v4().to_string()),
+            DocIdConfig::ContentHash => {
+                let mut hasher = SipHasher::new();
+                hasher.write(doc_json.as_bytes());
+                Ok(format!("{:032x}", hasher.finish128().as_u128()))
+            }
+        }
+    }
+}
+
+fn extract_doc_id(
+    field_path: &str,
+    json_obj: &serde_json::Map<String, JsonValue>,
+) -> Result<String, DocParsingError> {
+    let mut path_iter = field_path.split('.');
+    let mut json_value_opt = path_iter
+        .next()
+        .and_then(|field_name| json_obj.get(field_name));
+    for field_name in path_iter {
+        json_value_opt = json_value_opt.and_then(|json_value| json_value.get(field_name));
+    }
+    match json_value_opt {
+        Some(JsonValue::String(doc_id)) if !doc_id.is_empty() => Ok(doc_id.clone()),
+        Some(JsonValue::Number(doc_id)) => Ok(doc_id.to_string()),
+        Some(JsonValue::Array(_)) => Err(DocParsingError::MultiValuesNotSupported(
+            field_path.to_string(),
+        )),
+        Some(_) => Err(DocParsingError::DocIdError(
+            field_path.to_string(),
+            "the ID must be a non-empty string or a number".to_string(),
+        )),
+        None => Err(DocParsingError::DocIdError(
+            field_path.to_string(),
+            "the field is missing".to_string(),
+        )),
+    }
+}
+
 /// Default [`DocMapper`] implementation
 /// which defines a set of rules to map json fields
 /// to tantivy index fields.
@@ -99,14 +152,24 @@ pub struct DefaultDocMapper {
     /// This field is only valid when using the schema associated with the default
     /// doc mapper, and therefore cannot be used in the `query` method.
     source_field: Option<Field>,
+    /// Field in which the document ID should be stored.
+    doc_id_field: Option<Field>,
+    /// Defines how the document ID is obtained.
+    doc_id_config: Option<DocIdConfig>,
     /// Field in which the dynamically mapped fields should be stored.
     /// This field is only valid when using the schema associated with the default
     /// doc mapper, and therefore cannot be used in the `query` method.
     dynamic_field: Option<Field>,
     /// Default list of field names used for search.
     default_search_field_names: Vec<String>,
+    /// Default boosts of the fields, keyed by field name.
+    field_boosts: BTreeMap<String, f32>,
     /// Timestamp field name.
     timestamp_field_name: Option<String>,
+    /// Expire timestamp field name.
+    expire_timestamp_field_name: Option<String>,
+    /// Sequence field name.
+    sequence_field_name: Option<String>,
     /// Sort field name and order.
     sort_by: SortBy,
     /// Root node of the field mapping tree.
@@ -120,22 +183,38 @@ pub struct DefaultDocMapper {
     /// into specific splits.
     partition_key: RoutingExpr,
     /// List of required fields. Right now this is the list of fast fields.
-    required_fields: Vec<Field>,
+    required_fields: Vec<RequiredField>,
     /// Defines how unmapped fields should be handle.
     mode: Mode,
 }
 
+/// A field every document must hold, and the value it is set to in the documents missing it, if
+/// the mapping declares one.
+#[derive(Clone)]
+struct RequiredField {
+    field: Field,
+    default_value_opt: Option<Value>,
+}
+
 impl DefaultDocMapper {
-    fn check_missing_required_fields(&self, doc: &Document) -> Result<(), DocParsingError> {
-        for &required_field in &self.required_fields {
-            if doc.get_first(required_field).is_none() {
-                let missing_field_name = self.schema.get_field_name(required_field);
-                return Err(DocParsingError::RequiredFastField(
-                    missing_field_name.to_string(),
-                ));
+    /// Sets the missing required fields that have a default value, and returns their names.
+    fn fill_missing_required_fields(
+        &self,
+        doc: &mut Document,
+    ) -> Result<Vec<String>, DocParsingError> {
+        let mut defaulted_field_names = Vec::new();
+        for required_field in &self.required_fields {
+            if doc.get_first(required_field.field).is_some() {
+                continue;
             }
+            let missing_field_name = self.schema.get_field_name(required_field.field);
+            let default_value = required_field.default_value_opt.clone().ok_or_else(|| {
+                DocParsingError::RequiredFastField(missing_field_name.to_string())
+            })?;
+            doc.add_field_value(required_field.field, default_value);
+            defaulted_field_names.push(missing_field_name.to_string());
         }
-        Ok(())
+        Ok(defaulted_field_names)
     }
 }
 
@@ -166,20 +245,39 @@ fn validate_tag_fields(tag_fields: &[String], schema: &Schema) -> anyhow::Result
     Ok(())
 }
 
-fn list_required_fields_for_node(node: &MappingNode) -> Vec<Field> {
-    node.children().flat_map(list_required_fields).collect()
+fn list_required_fields_for_node(
+    node: &MappingNode,
+    schema: &Schema,
+) -> anyhow::Result<Vec<RequiredField>> {
+    let mut required_fields = Vec::new();
+    for child in node.children() {
+        required_fields.extend(list_required_fields(child, schema)?);
+    }
+    Ok(required_fields)
 }
 
-fn list_required_fields(field_mappings: &MappingTree) -> Vec<Field> {
+fn list_required_fields(
+    field_mappings: &MappingTree,
+    schema: &Schema,
+) -> anyhow::Result<Vec<RequiredField>> {
     match field_mappings {
         MappingTree::Leaf(leaf) => {
-            if leaf.get_type().is_fast_field() {
-                vec![leaf.field()]
-            } else {
-                Vec::new()
+            if !leaf.get_type().is_fast_field() {
+                return Ok(Vec::new());
             }
+            let default_value_opt = leaf.get_type().default_value().map_err(|err_msg| {
+                anyhow::anyhow!(
+                    "Invalid default value for field `{}`: {}",
+                    schema.get_field_name(leaf.field()),
+                    err_msg
+                )
+            })?;
+            Ok(vec![RequiredField {
+                field: leaf.field(),
+                default_value_opt,
+            }])
         }
-        MappingTree::Node(node) => list_required_fields_for_node(node),
+        MappingTree::Node(node) => list_required_fields_for_node(node, schema),
     }
 }
 
@@ -231,6 +329,99 @@ fn resolve_timestamp_field(
     Ok(())
 }
 
+/// Checks that the expire timestamp field is an indexed and fast single-valued `i64` or
+/// `datetime` field and returns it.
+fn resolve_expire_timestamp_field(
+    expire_timestamp_field_name_opt: Option<&String>,
+    schema: &Schema,
+) -> anyhow::Result<Option<Field>> {
+    let expire_timestamp_field_name = match expire_timestamp_field_name_opt {
+        Some(expire_timestamp_field_name) => expire_timestamp_field_name,
+        None => return Ok(None),
+    };
+    let expire_timestamp_field =
+        schema
+            .get_field(expire_timestamp_field_name)
+            .with_context(|| {
+                format!(
+                    "Unknown expire timestamp field: `{}`",
+                    expire_timestamp_field_name
+                )
+            })?;
+    let expire_timestamp_field_entry = schema.get_field_entry(expire_timestamp_field);
+    if !expire_timestamp_field_entry.is_fast() || !expire_timestamp_field_entry.is_indexed() {
+        bail!(
+            "Expire timestamp field must be an indexed fast field, please add the indexed and              fast properties to your field `{}`.",
+            expire_timestamp_field_name
+        )
+    }
+    let fastfield_cardinality = match expire_timestamp_field_entry.field_type() {
+        FieldType::I64(options) => options.get_fastfield_cardinality(),
+        FieldType::Date(options) => options.get_fastfield_cardinality(),
+        _ => {
+            bail!(
+                "Expire timestamp field must be of type i64 or datetime, please change your                  field type `{}`.",
+                expire_timestamp_field_name
+            )
+        }
+    };
+    if fastfield_cardinality == Some(Cardinality::MultiValues) {
+        bail!(
+            "Expire timestamp field cannot be an array, please change your field `{}` from an              array to a single value.",
+            expire_timestamp_field_name
+        )
+    }
+    Ok(Some(expire_timestamp_field))
+}
+
+/// Checks that the sequence field is an indexed and fast single-valued `u64` or `i64` field,
+/// and that the documents are assigned an ID extracted from one of their fields: generated IDs
+/// never collide, so no version would ever be superseded.
+fn resolve_sequence_field(
+    sequence_field_name_opt: Option<&String>,
+    doc_id_config_opt: Option<&DocIdConfig>,
+    schema: &Schema,
+) -> anyhow::Result<()> {
+    let sequence_field_name = match sequence_field_name_opt {
+        Some(sequence_field_name) => sequence_field_name,
+        None => return Ok(()),
+    };
+    if !matches!(doc_id_config_opt, Some(DocIdConfig::Field { .. })) {
+        bail!(
+            "The sequence field requires the document ID to be extracted from a field, please set \
+             `doc_id.source` to `field`."
+        )
+    }
+    let sequence_field = schema
+        .get_field(sequence_field_name)
+        .with_context(|| format!("Unknown sequence field: `{}`", sequence_field_name))?;
+    let sequence_field_entry = schema.get_field_entry(sequence_field);
+    if !sequence_field_entry.is_fast() || !sequence_field_entry.is_indexed() {
+        bail!(
+            "Sequence field must be an indexed fast field, please add the indexed and fast \
+             properties to your field `{}`.",
+            sequence_field_name
+        )
+    }
+    let fastfield_cardinality = match sequence_field_entry.field_type() {
+        FieldType::U64(options) | FieldType::I64(options) => options.get_fastfield_cardinality(),
+        _ => {
+            bail!(
+                "Sequence field must be of type u64 or i64, please change your field type `{}`.",
+                sequence_field_name
+            )
+        }
+    };
+    if fastfield_cardinality == Some(Cardinality::MultiValues) {
+        bail!(
+            "Sequence field cannot be an array, please change your field `{}` from an array to a \
+             single value.",
+            sequence_field_name
+        )
+    }
+    Ok(())
+}
+
 fn resolve_sort_field(
     sort_by_config_opt: Option<SortByConfig>,
     schema: &Schema,
@@ -256,6 +447,17 @@ impl TryFrom<DefaultDocMapperBuilder> for DefaultDocMapper {
             None
         };
 
+        let doc_id_field = if let Some(doc_id_config) = &builder.doc_id {
+            if let DocIdConfig::Field { field } = doc_id_config {
+                if field.is_empty() {
+                    bail!("The doc ID field name is empty.");
+                }
+            }
+            Some(schema_builder.add_text_field(DOC_ID_FIELD_NAME, STRING | STORED))
+        } else {
+            None
+        };
+
         let dynamic_field = if let Mode::Dynamic(json_options) = &mode {
             Some(schema_builder.add_json_field(DYNAMIC_FIELD_NAME, json_options.clone()))
         } else {
@@ -279,7 +481,23 @@ impl TryFrom<DefaultDocMapperBuilder> for DefaultDocMapper {
             default_search_field_names.push(field_name.clone());
         }
 
+        // Validate field boosts
+        for (field_name, boost) in &builder.field_boosts {
+            schema
+                .get_field(field_name)
+                .with_context(|| format!("Unknown boosted field: `{}`", field_name))?;
+            validate_field_boost(*boost)
+                .with_context(|| format!("Invalid boost of the field `{}`", field_name))?;
+        }
+
         resolve_timestamp_field(builder.timestamp_field.as_ref(), &schema)?;
+        let expire_timestamp_field_opt =
+            resolve_expire_timestamp_field(builder.expire_timestamp_field.as_ref(), &schema)?;
+        resolve_sequence_field(
+            builder.sequence_field.as_ref(),
+            builder.doc_id.as_ref(),
+            &schema,
+        )?;
         let sort_by = resolve_sort_field(builder.sort_by, &schema)?;
 
         // Resolve tag fields
@@ -294,15 +512,34 @@ impl TryFrom<DefaultDocMapperBuilder> for DefaultDocMapper {
             tag_field_names.insert(tag_field_name.clone());
         }
 
-        let required_fields = list_required_fields_for_node(&field_mappings);
+        let mut required_fields = list_required_fields_for_node(&field_mappings, &schema)?;
+        // Documents without expiry never expire.
+        required_fields
+            .retain(|required_field| Some(required_field.field) != expire_timestamp_field_opt);
+        // Documents without timestamp cannot be assigned to a time range.
+        let timestamp_field_opt = builder
+            .timestamp_field
+            .as_ref()
+            .and_then(|timestamp_field_name| schema.get_field(timestamp_field_name));
+        if required_fields.iter().any(|required_field| {
+            Some(required_field.field) == timestamp_field_opt
+                && required_field.default_value_opt.is_some()
+        }) {
+            bail!("The timestamp field cannot have a default value.");
+        }
         let partition_key = RoutingExpr::from_str(&builder.partition_key)
             .context("Failed to interpret the partition key.")?;
         Ok(DefaultDocMapper {
             schema,
             source_field,
+            doc_id_field,
+            doc_id_config: builder.doc_id,
             dynamic_field,
             default_search_field_names,
+            field_boosts: builder.field_boosts,
             timestamp_field_name: builder.timestamp_field,
+            expire_timestamp_field_name: builder.expire_timestamp_field,
+            sequence_field_name: builder.sequence_field,
             sort_by,
             field_mappings,
             tag_field_names,
@@ -334,13 +571,17 @@ impl From<DefaultDocMapper> for DefaultDocMapperBuilder {
         Self {
             store_source: default_doc_mapper.source_field.is_some(),
             timestamp_field: default_doc_mapper.timestamp_field_name(),
+            expire_timestamp_field: default_doc_mapper.expire_timestamp_field_name(),
+            sequence_field: default_doc_mapper.sequence_field_name(),
             field_mappings: default_doc_mapper.field_mappings.into(),
             sort_by: sort_by_config,
             tag_fields: default_doc_mapper.tag_field_names.into_iter().collect(),
             default_search_fields: default_doc_mapper.default_search_field_names,
+            field_boosts: default_doc_mapper.field_boosts,
             mode,
             dynamic_mapping,
             partition_key: default_doc_mapper.partition_key.to_string(),
+            doc_id: default_doc_mapper.doc_id_config,
         }
     }
 }
@@ -386,6 +627,15 @@ fn extract_single_obj(
 #[typetag::serde(name = "default")]
 impl DocMapper for DefaultDocMapper {
     fn doc_from_json(&self, doc_json: String) -> Result<(Partition, Document), DocParsingError> {
+        let (partition, document, _defaulted_field_names) =
+            self.doc_from_json_with_defaulted_fields(doc_json)?;
+        Ok((partition, document))
+    }
+
+    fn doc_from_json_with_defaulted_fields(
+        &self,
+        doc_json: String,
+    ) -> Result<(Partition, Document, Vec<String>), DocParsingError> {
         let json_obj: serde_json::Map<String, JsonValue> = serde_json::from_str(&doc_json)
             .map_err(|_| {
                 let doc_json_sample = doc_json.chars().take(20).collect();
@@ -398,6 +648,12 @@ impl DocMapper for DefaultDocMapper {
         let mut field_path = Vec::new();
         let mut document = Document::default();
 
+        if let (Some(doc_id_field), Some(doc_id_config)) = (self.doc_id_field, &self.doc_id_config)
+        {
+            let doc_id = doc_id_config.doc_id(&doc_json, &json_obj)?;
+            document.add_text(doc_id_field, doc_id);
+        }
+
         if let Some(source_field) = self.source_field {
             document.add_json_object(source_field, json_obj.clone());
         }
@@ -417,8 +673,8 @@ impl DocMapper for DefaultDocMapper {
             }
         }
 
-        self.check_missing_required_fields(&document)?;
-        Ok((partition, document))
+        let defaulted_field_names = self.fill_missing_required_fields(&mut document)?;
+        Ok((partition, document, defaulted_field_names))
     }
 
     fn doc_to_json(
@@ -431,6 +687,13 @@ impl DocMapper for DefaultDocMapper {
         self.field_mappings
             .populate_json(&mut named_doc, &mut field_path, &mut doc_json);
 
+        if let Some(doc_id) = named_doc
+            .remove(DOC_ID_FIELD_NAME)
+            .and_then(|mut values| values.pop())
+        {
+            doc_json.insert(DOC_ID_FIELD_NAME.to_string(), doc_id);
+        }
+
         if let Some(source_json) = extract_single_obj(&mut named_doc, SOURCE_FIELD_NAME)? {
             doc_json.insert(
                 SOURCE_FIELD_NAME.to_string(),
@@ -441,18 +704,23 @@ impl DocMapper for DefaultDocMapper {
         Ok(doc_json)
     }
 
-    fn query(
+    fn query_with_term_expansions(
         &self,
         split_schema: Schema,
         request: &SearchRequest,
-    ) -> Result<Box<dyn Query>, QueryParserError> {
+    ) -> Result<(Box<dyn Query>, Vec<TermExpansion>), QueryParserError> {
         let mut tantivy_default_search_field_names = self.default_search_field_names.clone();
         if let Mode::Dynamic(default_mapping_options) = &self.mode {
             if default_mapping_options.indexed {
                 tantivy_default_search_field_names.push(DYNAMIC_FIELD_NAME.to_string());
             }
         }
-        build_query(split_schema, request, &tantivy_default_search_field_names)
+        build_query(
+            split_schema,
+            request,
+            &tantivy_default_search_field_names,
+            &self.field_boosts,
+        )
     }
 
     fn schema(&self) -> Schema {
@@ -463,6 +731,14 @@ impl DocMapper for DefaultDocMapper {
         self.timestamp_field_name.clone()
     }
 
+    fn expire_timestamp_field_name(&self) -> Option<String> {
+        self.expire_timestamp_field_name.clone()
+    }
+
+    fn sequence_field_name(&self) -> Option<String> {
+        self.sequence_field_name.clone()
+    }
+
     fn sort_by(&self) -> SortBy {
         self.sort_by.clone()
     }
@@ -474,7 +750,7 @@ impl DocMapper for DefaultDocMapper {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::collections::{BTreeMap, HashMap};
 
     use quickwit_proto::SearchRequest;
     use serde_json::{self, json, Value as JsonValue};
@@ -482,8 +758,8 @@ mod tests {
 
     use super::DefaultDocMapper;
     use crate::{
-        DefaultDocMapperBuilder, DocMapper, DocParsingError, SortBy, SortOrder, DYNAMIC_FIELD_NAME,
-        SOURCE_FIELD_NAME,
+        DefaultDocMapperBuilder, DocMapper, DocParsingError, SortBy, SortOrder, DOC_ID_FIELD_NAME,
+        DYNAMIC_FIELD_NAME, SOURCE_FIELD_NAME,
     };
 
     fn example_json_doc_value() -> serde_json::Value {
@@ -696,6 +972,238 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_doc_mapper_with_expire_timestamp_field() {
+        let doc_mapper_json = r#"{
+            "expire_timestamp_field": "expire_at",
+            "field_mappings": [
+                {
+                    "name": "body",
+                    "type": "text"
+                },
+                {
+                    "name": "expire_at",
+                    "type": "i64",
+                    "fast": true
+                }
+            ]
+        }"#;
+        let doc_mapper = serde_json::from_str::<DefaultDocMapper>(doc_mapper_json).unwrap();
+        assert_eq!(
+            doc_mapper.expire_timestamp_field_name().as_deref(),
+            Some("expire_at")
+        );
+        let expire_timestamp_field = doc_mapper
+            .expire_timestamp_field(&doc_mapper.schema())
+            .unwrap();
+        let (_, document) = doc_mapper
+            .doc_from_json(r#"{"body": "hello", "expire_at": 1660000000}"#.to_string())
+            .unwrap();
+        assert_eq!(
+            document.get_first(expire_timestamp_field).unwrap().as_i64(),
+            Some(1660000000)
+        );
+        // Documents without expiry are valid.
+        doc_mapper
+            .doc_from_json(r#"{"body": "hello"}"#.to_string())
+            .unwrap();
+        let serialized_doc_mapper = serde_json::to_value(&doc_mapper).unwrap();
+        assert_eq!(serialized_doc_mapper["expire_timestamp_field"], "expire_at");
+    }
+
+    #[test]
+    fn test_fail_to_build_doc_mapper_with_invalid_expire_timestamp_field() {
+        let doc_mapper = r#"{
+            "expire_timestamp_field": "expire_at",
+            "field_mappings": [
+                {
+                    "name": "expire_at",
+                    "type": "i64"
+                }
+            ]
+        }"#;
+        let builder = serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper).unwrap();
+        let expected_msg = "Expire timestamp field must be an indexed fast field, please add the \
+                            indexed and fast properties to your field `expire_at`.";
+        assert_eq!(builder.try_build().unwrap_err().to_string(), expected_msg);
+
+        let doc_mapper = r#"{
+            "expire_timestamp_field": "expire_at",
+            "field_mappings": [
+                {
+                    "name": "expire_at",
+                    "type": "f64",
+                    "fast": true
+                }
+            ]
+        }"#;
+        let builder = serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper).unwrap();
+        assert!(builder.try_build().is_err());
+    }
+
+    #[test]
+    fn test_doc_mapper_with_sequence_field() {
+        let doc_mapper_json = r#"{
+            "doc_id": {"source": "field", "field": "order_id"},
+            "sequence_field": "version",
+            "field_mappings": [
+                {
+                    "name": "order_id",
+                    "type": "text",
+                    "tokenizer": "raw"
+                },
+                {
+                    "name": "version",
+                    "type": "u64",
+                    "fast": true
+                }
+            ]
+        }"#;
+        let doc_mapper = serde_json::from_str::<DefaultDocMapper>(doc_mapper_json).unwrap();
+        assert_eq!(doc_mapper.sequence_field_name().as_deref(), Some("version"));
+        assert!(doc_mapper.sequence_field(&doc_mapper.schema()).is_some());
+        // Documents without sequence number are rejected.
+        assert!(matches!(
+            doc_mapper.doc_from_json(r#"{"order_id": "abc"}"#.to_string()),
+            Err(DocParsingError::RequiredFastField(_))
+        ));
+        let serialized_doc_mapper = serde_json::to_value(&doc_mapper).unwrap();
+        assert_eq!(serialized_doc_mapper["sequence_field"], "version");
+    }
+
+    #[test]
+    fn test_fail_to_build_doc_mapper_with_invalid_sequence_field() {
+        let doc_mapper = r#"{
+            "doc_id": {"source": "ulid"},
+            "sequence_field": "version",
+            "field_mappings": [
+                {
+                    "name": "version",
+                    "type": "u64",
+                    "fast": true
+                }
+            ]
+        }"#;
+        let builder = serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper).unwrap();
+        let expected_msg = "The sequence field requires the document ID to be extracted from a \
+                            field, please set `doc_id.source` to `field`.";
+        assert_eq!(builder.try_build().unwrap_err().to_string(), expected_msg);
+
+        let doc_mapper = r#"{
+            "doc_id": {"source": "field", "field": "order_id"},
+            "sequence_field": "version",
+            "field_mappings": [
+                {
+                    "name": "version",
+                    "type": "f64",
+                    "fast": true
+                }
+            ]
+        }"#;
+        let builder = serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper).unwrap();
+        let expected_msg =
+            "Sequence field must be of type u64 or i64, please change your field type `version`.";
+        assert_eq!(builder.try_build().unwrap_err().to_string(), expected_msg);
+    }
+
+    #[test]
+    fn test_doc_mapper_with_default_values() {
+        let doc_mapper_json = r#"{
+            "timestamp_field": "timestamp",
+            "field_mappings": [
+                {
+                    "name": "timestamp",
+                    "type": "i64",
+                    "fast": true
+                },
+                {
+                    "name": "status",
+                    "type": "u64",
+                    "fast": true,
+                    "default_value": 0
+                },
+                {
+                    "name": "latency",
+                    "type": "f64",
+                    "fast": true
+                }
+            ]
+        }"#;
+        let doc_mapper = serde_json::from_str::<DefaultDocMapper>(doc_mapper_json).unwrap();
+        let schema = doc_mapper.schema();
+        let status_field = schema.get_field("status").unwrap();
+
+        let (_, document, defaulted_field_names) = doc_mapper
+            .doc_from_json_with_defaulted_fields(
+                r#"{"timestamp": 1660000000, "status": 200, "latency": 1.5}"#.to_string(),
+            )
+            .unwrap();
+        assert_eq!(
+            document.get_first(status_field).unwrap().as_u64(),
+            Some(200)
+        );
+        assert!(defaulted_field_names.is_empty());
+
+        let (_, document, defaulted_field_names) = doc_mapper
+            .doc_from_json_with_defaulted_fields(
+                r#"{"timestamp": 1660000000, "latency": 1.5}"#.to_string(),
+            )
+            .unwrap();
+        assert_eq!(document.get_first(status_field).unwrap().as_u64(), Some(0));
+        assert_eq!(defaulted_field_names, vec!["status".to_string()]);
+
+        // Fields without default value are still required.
+        let error = doc_mapper
+            .doc_from_json(r#"{"timestamp": 1660000000}"#.to_string())
+            .unwrap_err();
+        assert_eq!(
+            error,
+            DocParsingError::RequiredFastField("latency".to_string())
+        );
+        let serialized_doc_mapper = serde_json::to_value(&doc_mapper).unwrap();
+        assert_eq!(
+            serialized_doc_mapper["field_mappings"][1]["default_value"],
+            0
+        );
+    }
+
+    #[test]
+    fn test_fail_to_build_doc_mapper_with_invalid_default_value() {
+        let doc_mapper = r#"{
+            "field_mappings": [
+                {
+                    "name": "status",
+                    "type": "u64",
+                    "fast": true,
+                    "default_value": "unknown"
+                }
+            ]
+        }"#;
+        let builder = serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper).unwrap();
+        assert!(builder
+            .try_build()
+            .unwrap_err()
+            .to_string()
+            .starts_with("Invalid default value for field `status`"));
+
+        let doc_mapper = r#"{
+            "timestamp_field": "timestamp",
+            "field_mappings": [
+                {
+                    "name": "timestamp",
+                    "type": "i64",
+                    "fast": true,
+                    "default_value": 0
+                }
+            ]
+        }"#;
+        let builder = serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper).unwrap();
+        assert_eq!(
+            builder.try_build().unwrap_err().to_string(),
+            "The timestamp field cannot have a default value."
+        );
+    }
+
     #[test]
     fn test_fail_with_field_name_equal_to_source() {
         let doc_mapper = r#"{
@@ -894,6 +1402,35 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_fail_to_build_doc_mapper_with_invalid_field_boosts() -> anyhow::Result<()> {
+        let doc_mapper = r#"{
+            "default_search_fields": ["title"],
+            "field_boosts": {"title": 2.5},
+            "field_mappings": [
+                {
+                    "name": "title",
+                    "type": "text"
+                }
+            ]
+        }"#;
+        let mut builder = serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper)?;
+        builder.clone().try_build()?;
+
+        builder.field_boosts.insert("title".to_string(), 0.0);
+        assert_eq!(
+            format!("{:#}", builder.clone().try_build().unwrap_err()),
+            "Invalid boost of the field `title`: The boost must be a positive number, got `0`."
+        );
+        builder.field_boosts.clear();
+        builder.field_boosts.insert("unknown".to_string(), 2.0);
+        assert_eq!(
+            builder.try_build().unwrap_err().to_string(),
+            "Unknown boosted field: `unknown`"
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_build_doc_mapper_with_sort_by_field_asc() {
         let doc_mapper = r#"{
@@ -1270,4 +1807,102 @@ mod tests {
             r#"TermQuery(Term(type=Str, field=1, "toto"))"#
         );
     }
+
+    fn doc_id_aux(doc_mapper: &DefaultDocMapper, doc_json: &str) -> String {
+        let doc_id_field = doc_mapper.schema().get_field(DOC_ID_FIELD_NAME).unwrap();
+        let (_, doc) = doc_mapper.doc_from_json(doc_json.to_string()).unwrap();
+        doc.get_first(doc_id_field)
+            .and_then(|value| value.as_text())
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_doc_mapper_without_doc_id_has_no_id_field() {
+        let doc_mapper: DefaultDocMapper = serde_json::from_str("{}").unwrap();
+        assert!(doc_mapper.schema().get_field(DOC_ID_FIELD_NAME).is_none());
+    }
+
+    #[test]
+    fn test_doc_mapper_doc_id_from_field() {
+        let doc_mapper: DefaultDocMapper = serde_json::from_str(
+            r#"{
+            "field_mappings": [{"name": "body", "type": "text"}],
+            "doc_id": {"source": "field", "field": "user.id"}
+        }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            doc_id_aux(&doc_mapper, r#"{"body": "hello", "user": {"id": "abc"}}"#),
+            "abc"
+        );
+        assert_eq!(doc_id_aux(&doc_mapper, r#"{"user": {"id": 42}}"#), "42");
+        assert!(matches!(
+            doc_mapper.doc_from_json(r#"{"body": "hello"}"#.to_string()),
+            Err(DocParsingError::DocIdError(field, _)) if field == "user.id"
+        ));
+        assert!(matches!(
+            doc_mapper.doc_from_json(r#"{"user": {"id": ["a", "b"]}}"#.to_string()),
+            Err(DocParsingError::MultiValuesNotSupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_doc_mapper_generated_doc_id() {
+        let ulid_doc_mapper: DefaultDocMapper =
+            serde_json::from_str(r#"{"doc_id": {"source": "ulid"}}"#).unwrap();
+        let first_ulid = doc_id_aux(&ulid_doc_mapper, r#"{"body": "hello"}"#);
+        let second_ulid = doc_id_aux(&ulid_doc_mapper, r#"{"body": "hello"}"#);
+        assert_eq!(first_ulid.len(), 26);
+        assert_ne!(first_ulid, second_ulid);
+
+        let uuid_doc_mapper: DefaultDocMapper =
+            serde_json::from_str(r#"{"doc_id": {"source": "uuid"}}"#).unwrap();
+        assert_eq!(
+            doc_id_aux(&uuid_doc_mapper, r#"{"body": "hello"}"#).len(),
+            36
+        );
+
+        let hash_doc_mapper: DefaultDocMapper =
+            serde_json::from_str(r#"{"doc_id": {"source": "content_hash"}}"#).unwrap();
+        let first_hash = doc_id_aux(&hash_doc_mapper, r#"{"body": "hello"}"#);
+        assert_eq!(first_hash.len(), 32);
+        assert_eq!(
+            first_hash,
+            doc_id_aux(&hash_doc_mapper, r#"{"body": "hello"}"#)
+        );
+        assert_ne!(
+            first_hash,
+            doc_id_aux(&hash_doc_mapper, r#"{"body": "world"}"#)
+        );
+    }
+
+    #[test]
+    fn test_doc_mapper_doc_id_to_json() {
+        let doc_mapper: DefaultDocMapper = serde_json::from_str(
+            r#"{
+            "field_mappings": [{"name": "body", "type": "text"}],
+            "doc_id": {"source": "field", "field": "id"}
+        }"#,
+        )
+        .unwrap();
+        let mut named_doc = BTreeMap::new();
+        named_doc.insert(DOC_ID_FIELD_NAME.to_string(), vec![json!("abc")]);
+        named_doc.insert("body".to_string(), vec![json!("hello")]);
+        let doc_json = doc_mapper.doc_to_json(named_doc).unwrap();
+        assert_eq!(
+            JsonValue::Object(doc_json),
+            json!({"_id": "abc", "body": "hello"})
+        );
+    }
+
+    #[test]
+    fn test_doc_mapper_doc_id_roundtrip() {
+        let doc_mapper: DefaultDocMapper =
+            serde_json::from_str(r#"{"doc_id": {"source": "content_hash"}}"#).unwrap();
+        let doc_mapper_json = serde_json::to_value(&doc_mapper).unwrap();
+        assert_eq!(doc_mapper_json["doc_id"], json!({"source": "content_hash"}));
+        let doc_mapper: DefaultDocMapper = serde_json::from_value(doc_mapper_json).unwrap();
+        assert!(doc_mapper.schema().get_field(DOC_ID_FIELD_NAME).is_some());
+    }
 }
@@ -17,6 +17,8 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::BTreeMap;
+
 use anyhow::bail;
 use serde::{Deserialize, Serialize};
 
@@ -40,10 +42,25 @@ pub struct DefaultDocMapperBuilder {
     /// Name of the fields that are searched by default, unless overridden.
     #[serde(default)]
     pub default_search_fields: Vec<String>,
+    /// Boosts of the scores of the matches on the fields, keyed by field name. The fields missing
+    /// from the map have a boost of 1.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub field_boosts: BTreeMap<String, f32>,
     /// Name of the field storing the timestamp of the event for time series data.
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub timestamp_field: Option<String>,
+    /// Name of the field storing the expiry of the document. Documents are dropped when the
+    /// splits holding them are rewritten after their expiry.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expire_timestamp_field: Option<String>,
+    /// Name of the field storing the sequence number of the document. Merges only keep the
+    /// document with the highest sequence number among the documents sharing the same ID.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sequence_field: Option<String>,
     /// Specifies the name of the sort field and the sort order.
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -65,6 +82,10 @@ pub struct DefaultDocMapperBuilder {
     /// how the unmapped fields should be handled.
     #[serde(default)]
     pub dynamic_mapping: Option<QuickwitJsonOptions>,
+    /// Defines how the document ID stored in the `_id` field is obtained.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc_id: Option<DocIdConfig>,
 }
 
 /// `Mode` describing how the unmapped field should be handled.
@@ -80,6 +101,24 @@ pub enum ModeType {
     Dynamic,
 }
 
+/// Describes how the ID of a document is obtained at indexing time.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum DocIdConfig {
+    /// The ID is extracted from a field of the document. Nested fields are addressed with a
+    /// dot-separated path.
+    Field {
+        /// Path of the field holding the ID.
+        field: String,
+    },
+    /// A ULID is generated for each document.
+    Ulid,
+    /// A random UUID (v4) is generated for each document.
+    Uuid,
+    /// The ID is the hash of the document content, so identical documents share the same ID.
+    ContentHash,
+}
+
 impl Default for ModeType {
     fn default() -> Self {
         ModeType::Lenient
@@ -131,6 +170,30 @@ mod tests {
         assert!(default_mapper_builder.sort_by.is_none());
         assert_eq!(default_mapper_builder.store_source, false);
         assert!(default_mapper_builder.timestamp_field.is_none());
+        assert!(default_mapper_builder.doc_id.is_none());
+    }
+
+    #[test]
+    fn test_default_mapper_builder_deserialize_doc_id() {
+        let default_mapper_builder: DefaultDocMapperBuilder =
+            serde_json::from_str(r#"{"doc_id": {"source": "field", "field": "attributes.id"}}"#)
+                .unwrap();
+        assert_eq!(
+            default_mapper_builder.doc_id,
+            Some(DocIdConfig::Field {
+                field: "attributes.id".to_string()
+            })
+        );
+        let default_mapper_builder: DefaultDocMapperBuilder =
+            serde_json::from_str(r#"{"doc_id": {"source": "content_hash"}}"#).unwrap();
+        assert_eq!(
+            default_mapper_builder.doc_id,
+            Some(DocIdConfig::ContentHash)
+        );
+        assert!(serde_json::from_str::<DefaultDocMapperBuilder>(
+            r#"{"doc_id": {"source": "sequence"}}"#
+        )
+        .is_err());
     }
 
     #[test]
@@ -21,6 +21,7 @@ use std::convert::TryFrom;
 
 use anyhow::bail;
 use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
 use tantivy::schema::{
     Cardinality, IndexRecordOption, JsonObjectOptions, TextFieldIndexing, TextOptions, Type,
 };
@@ -79,6 +80,10 @@ pub struct QuickwitNumericOptions {
     pub indexed: bool,
     #[serde(default)]
     pub fast: bool,
+    /// Value the fast field is set to in the documents missing it, instead of rejecting them.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_value: Option<JsonValue>,
 }
 
 impl Default for QuickwitNumericOptions {
@@ -88,6 +93,7 @@ impl Default for QuickwitNumericOptions {
             indexed: true,
             stored: true,
             fast: false,
+            default_value: None,
         }
     }
 }
@@ -234,6 +240,14 @@ impl From<QuickwitJsonOptions> for JsonObjectOptions {
     }
 }
 
+/// Default values stand in for missing fast fields, so they make no sense on other fields.
+fn validate_default_value(fast: bool, default_value_opt: &Option<JsonValue>) -> anyhow::Result<()> {
+    if default_value_opt.is_some() && !fast {
+        bail!("`default_value` is only allowed on fast fields.");
+    }
+    Ok(())
+}
+
 fn deserialize_mapping_type(
     quickwit_field_type: QuickwitFieldType,
     json: serde_json::Value,
@@ -241,6 +255,14 @@ fn deserialize_mapping_type(
     let (typ, cardinality) = match quickwit_field_type {
         QuickwitFieldType::Simple(typ) => (typ, Cardinality::SingleValue),
         QuickwitFieldType::Array(typ) => (typ, Cardinality::MultiValues),
+        QuickwitFieldType::IpAddr(cardinality) => {
+            let numeric_options: QuickwitNumericOptions = serde_json::from_value(json)?;
+            validate_default_value(numeric_options.fast, &numeric_options.default_value)?;
+            if numeric_options.fast && cardinality == Cardinality::MultiValues {
+                bail!("fast field is not allowed for array<ip>.");
+            }
+            return Ok(FieldMappingType::IpAddr(numeric_options, cardinality));
+        }
         QuickwitFieldType::Object => {
             let object_options: QuickwitObjectOptions = serde_json::from_value(json)?;
             if object_options.field_mappings.is_empty() {
@@ -268,27 +290,33 @@ fn deserialize_mapping_type(
         }
         Type::U64 => {
             let numeric_options: QuickwitNumericOptions = serde_json::from_value(json)?;
+            validate_default_value(numeric_options.fast, &numeric_options.default_value)?;
             Ok(FieldMappingType::U64(numeric_options, cardinality))
         }
         Type::I64 => {
             let numeric_options: QuickwitNumericOptions = serde_json::from_value(json)?;
+            validate_default_value(numeric_options.fast, &numeric_options.default_value)?;
             Ok(FieldMappingType::I64(numeric_options, cardinality))
         }
         Type::F64 => {
             let numeric_options: QuickwitNumericOptions = serde_json::from_value(json)?;
+            validate_default_value(numeric_options.fast, &numeric_options.default_value)?;
             Ok(FieldMappingType::F64(numeric_options, cardinality))
         }
         Type::Bool => {
             let numeric_options: QuickwitNumericOptions = serde_json::from_value(json)?;
+            validate_default_value(numeric_options.fast, &numeric_options.default_value)?;
             Ok(FieldMappingType::Bool(numeric_options, cardinality))
         }
         Type::Date => {
             let date_time_options = serde_json::from_value::<QuickwitDateTimeOptions>(json)?;
+            validate_default_value(date_time_options.fast, &date_time_options.default_value)?;
             Ok(FieldMappingType::DateTime(date_time_options, cardinality))
         }
         Type::Facet => unimplemented!("Facet are not supported in quickwit yet."),
         Type::Bytes => {
             let numeric_options: QuickwitNumericOptions = serde_json::from_value(json)?;
+            validate_default_value(numeric_options.fast, &numeric_options.default_value)?;
             if numeric_options.fast && cardinality == Cardinality::MultiValues {
                 bail!("fast field is not allowed for array<bytes>.");
             }
@@ -343,6 +371,7 @@ fn typed_mapping_to_json_params(
         FieldMappingType::U64(options, _)
         | FieldMappingType::I64(options, _)
         | FieldMappingType::Bytes(options, _)
+        | FieldMappingType::IpAddr(options, _)
         | FieldMappingType::F64(options, _)
         | FieldMappingType::Bool(options, _) => serialize_to_map(&options),
         FieldMappingType::DateTime(date_time_options, _) => serialize_to_map(&date_time_options),
@@ -995,6 +1024,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_default_value_on_non_fast_field_forbidden() {
+        let err = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+                "name": "status",
+                "type": "u64",
+                "default_value": 0
+            }
+            "#,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Error while parsing field `status`: `default_value` is only allowed on fast fields.",
+        );
+    }
+
     #[test]
     fn test_parse_json_mapping_singlevalue() {
         let field_mapping_entry = serde_json::from_str::<FieldMappingEntry>(
@@ -42,6 +42,8 @@ pub enum FieldMappingType {
     Bool(QuickwitNumericOptions, Cardinality),
     /// Bytes mapping type configuration.
     Bytes(QuickwitNumericOptions, Cardinality),
+    /// IP address mapping type configuration.
+    IpAddr(QuickwitNumericOptions, Cardinality),
     Json(QuickwitJsonOptions, Cardinality),
     /// Object mapping type configuration.
     Object(QuickwitObjectOptions),
@@ -58,6 +60,9 @@ impl FieldMappingType {
             FieldMappingType::DateTime(_, cardinality) => (Type::Date, *cardinality),
             FieldMappingType::Bytes(_, cardinality) => (Type::Bytes, *cardinality),
             FieldMappingType::Json(_, cardinality) => (Type::Json, *cardinality),
+            FieldMappingType::IpAddr(_, cardinality) => {
+                return QuickwitFieldType::IpAddr(*cardinality);
+            }
             FieldMappingType::Object(_) => {
                 return QuickwitFieldType::Object;
             }
@@ -74,6 +79,8 @@ pub enum QuickwitFieldType {
     Simple(Type),
     Object,
     Array(Type),
+    /// IP addresses have no tantivy type of their own: they are indexed as bytes.
+    IpAddr(Cardinality),
 }
 
 impl QuickwitFieldType {
@@ -82,12 +89,17 @@ impl QuickwitFieldType {
             QuickwitFieldType::Simple(typ) => primitive_type_to_str(typ).to_string(),
             QuickwitFieldType::Object => "object".to_string(),
             QuickwitFieldType::Array(typ) => format!("array<{}>", primitive_type_to_str(typ)),
+            QuickwitFieldType::IpAddr(Cardinality::SingleValue) => "ip".to_string(),
+            QuickwitFieldType::IpAddr(Cardinality::MultiValues) => "array<ip>".to_string(),
         }
     }
 
     pub fn parse_type_id(type_str: &str) -> Option<QuickwitFieldType> {
-        if type_str == "object" {
-            return Some(QuickwitFieldType::Object);
+        match type_str {
+            "object" => return Some(QuickwitFieldType::Object),
+            "ip" => return Some(QuickwitFieldType::IpAddr(Cardinality::SingleValue)),
+            "array<ip>" => return Some(QuickwitFieldType::IpAddr(Cardinality::MultiValues)),
+            _ => {}
         }
         if type_str.starts_with("array<") && type_str.ends_with('>') {
             let parsed_type_str = parse_primitive_type(&type_str[6..type_str.len() - 1])?;
@@ -130,7 +142,7 @@ fn primitive_type_to_str(primitive_type: &Type) -> &'static str {
 
 #[cfg(test)]
 mod tests {
-    use tantivy::schema::Type;
+    use tantivy::schema::{Cardinality, Type};
 
     use super::QuickwitFieldType;
 
@@ -149,5 +161,13 @@ mod tests {
         test_parse_type_aux("object", Some(QuickwitFieldType::Object));
         test_parse_type_aux("object2", None);
         test_parse_type_aux("bool", Some(QuickwitFieldType::Simple(Type::Bool)));
+        test_parse_type_aux(
+            "ip",
+            Some(QuickwitFieldType::IpAddr(Cardinality::SingleValue)),
+        );
+        test_parse_type_aux(
+            "array<ip>",
+            Some(QuickwitFieldType::IpAddr(Cardinality::MultiValues)),
+        );
     }
 }
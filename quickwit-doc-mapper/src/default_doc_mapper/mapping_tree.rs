@@ -19,6 +19,7 @@
 
 use std::any::type_name;
 use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv6Addr};
 
 use anyhow::bail;
 use itertools::Itertools;
@@ -55,6 +56,7 @@ pub enum LeafType {
     Bool(QuickwitNumericOptions),
     DateTime(QuickwitDateTimeOptions),
     Bytes(QuickwitNumericOptions),
+    IpAddr(QuickwitNumericOptions),
     Json(QuickwitJsonOptions),
 }
 
@@ -65,7 +67,7 @@ impl LeafType {
             LeafType::I64(_) | LeafType::U64(_) | LeafType::F64(_) => JsonType::Number,
             LeafType::Bool(_) => JsonType::Bool,
             LeafType::DateTime(_) => JsonType::String,
-            LeafType::Bytes(_) => JsonType::String,
+            LeafType::Bytes(_) | LeafType::IpAddr(_) => JsonType::String,
             LeafType::Json(_) => JsonType::Object,
         }
     }
@@ -77,12 +79,30 @@ impl LeafType {
             | LeafType::U64(opt)
             | LeafType::F64(opt)
             | LeafType::Bool(opt)
-            | LeafType::Bytes(opt) => opt.fast,
+            | LeafType::Bytes(opt)
+            | LeafType::IpAddr(opt) => opt.fast,
             LeafType::DateTime(opt) => opt.fast,
             LeafType::Json(_) => false,
         }
     }
 
+    /// Returns the value documents missing the field are set to, if the mapping declares one.
+    pub fn default_value(&self) -> Result<Option<Value>, String> {
+        let default_value_opt = match self {
+            LeafType::I64(opt)
+            | LeafType::U64(opt)
+            | LeafType::F64(opt)
+            | LeafType::Bool(opt)
+            | LeafType::Bytes(opt)
+            | LeafType::IpAddr(opt) => opt.default_value.clone(),
+            LeafType::DateTime(opt) => opt.default_value.clone(),
+            LeafType::Text(_) | LeafType::Json(_) => None,
+        };
+        default_value_opt
+            .map(|default_value| self.value_from_json(default_value))
+            .transpose()
+    }
+
     fn value_from_json(&self, json_val: serde_json::Value) -> Result<Value, String> {
         match self {
             LeafType::Text(_) => {
@@ -131,6 +151,17 @@ impl LeafType {
                 })?;
                 Ok(Value::Bytes(payload))
             }
+            LeafType::IpAddr(_) => {
+                let ip_addr_str = if let JsonValue::String(ip_addr_str) = json_val {
+                    ip_addr_str
+                } else {
+                    return Err(format!("Expected IP address string, got '{}'.", json_val));
+                };
+                let ip_addr: IpAddr = ip_addr_str
+                    .parse()
+                    .map_err(|_| format!("Expected IPv4 or IPv6 address, got '{ip_addr_str}'."))?;
+                Ok(Value::Bytes(ip_addr_to_bytes(ip_addr).to_vec()))
+            }
             LeafType::Json(_) => {
                 if let JsonValue::Object(json_obj) = json_val {
                     Ok(Value::JsonObject(json_obj))
@@ -140,6 +171,49 @@ impl LeafType {
             }
         }
     }
+
+    fn value_error(&self, path: &[String], err_msg: String) -> DocParsingError {
+        match self {
+            LeafType::IpAddr(_) => DocParsingError::InvalidIpAddr(path.join("."), err_msg),
+            _ => DocParsingError::ValueError(path.join("."), err_msg),
+        }
+    }
+}
+
+/// Returns the 16-byte big-endian representation of an IP address, IPv4 addresses being mapped
+/// to IPv6.
+///
+/// Comparing these representations byte-wise orders the IP addresses, so that a CIDR block is a
+/// contiguous range of terms.
+fn ip_addr_to_bytes(ip_addr: IpAddr) -> [u8; 16] {
+    let ipv6_addr = match ip_addr {
+        IpAddr::V4(ipv4_addr) => ipv4_addr.to_ipv6_mapped(),
+        IpAddr::V6(ipv6_addr) => ipv6_addr,
+    };
+    ipv6_addr.octets()
+}
+
+/// Converts back the representation returned by [`ip_addr_to_bytes`] to an IP address.
+fn ip_addr_from_bytes(bytes: &[u8]) -> Option<IpAddr> {
+    let octets: [u8; 16] = bytes.try_into().ok()?;
+    let ipv6_addr = Ipv6Addr::from(octets);
+    let ip_addr = match ipv6_addr.to_ipv4_mapped() {
+        Some(ipv4_addr) => IpAddr::V4(ipv4_addr),
+        None => IpAddr::V6(ipv6_addr),
+    };
+    Some(ip_addr)
+}
+
+/// Converts a base64-encoded IP address of a named document to its string representation.
+fn ip_addr_json_from_base64(json_val: JsonValue) -> JsonValue {
+    let ip_addr_opt = json_val
+        .as_str()
+        .and_then(|base64_str| base64::decode(base64_str).ok())
+        .and_then(|bytes| ip_addr_from_bytes(&bytes));
+    match ip_addr_opt {
+        Some(ip_addr) => JsonValue::String(ip_addr.to_string()),
+        None => json_val,
+    }
 }
 
 #[derive(Clone)]
@@ -172,7 +246,7 @@ impl MappingLeaf {
                 let value = self
                     .typ
                     .value_from_json(el_json_val)
-                    .map_err(|err_msg| DocParsingError::ValueError(path.join("."), err_msg))?;
+                    .map_err(|err_msg| self.typ.value_error(path, err_msg))?;
                 document.add_field_value(self.field, value);
             }
             return Ok(());
@@ -180,7 +254,7 @@ impl MappingLeaf {
         let value = self
             .typ
             .value_from_json(json_val)
-            .map_err(|err_msg| DocParsingError::ValueError(path.join("."), err_msg))?;
+            .map_err(|err_msg| self.typ.value_error(path, err_msg))?;
         document.add_field_value(self.field, value);
         Ok(())
     }
@@ -201,6 +275,18 @@ impl MappingLeaf {
                     .expect("Invalid timestamp is not allowed.");
                 return insert_json_val(field_path, JsonValue::String(date_time_str), doc_json);
             }
+            if let LeafType::IpAddr(_) = self.get_type() {
+                let ip_addr_json_val = match json_val {
+                    JsonValue::Array(json_vals) => JsonValue::Array(
+                        json_vals
+                            .into_iter()
+                            .map(ip_addr_json_from_base64)
+                            .collect(),
+                    ),
+                    json_val => ip_addr_json_from_base64(json_val),
+                };
+                return insert_json_val(field_path, ip_addr_json_val, doc_json);
+            }
 
             insert_json_val(field_path, json_val, doc_json);
         }
@@ -427,6 +513,7 @@ impl From<MappingLeaf> for FieldMappingType {
             LeafType::Bool(opt) => FieldMappingType::Bool(opt, leaf.cardinality),
             LeafType::DateTime(opt) => FieldMappingType::DateTime(opt, leaf.cardinality),
             LeafType::Bytes(opt) => FieldMappingType::Bytes(opt, leaf.cardinality),
+            LeafType::IpAddr(opt) => FieldMappingType::IpAddr(opt, leaf.cardinality),
             LeafType::Json(opt) => FieldMappingType::Json(opt, leaf.cardinality),
         }
     }
@@ -659,6 +746,16 @@ fn build_mapping_from_field_type<'a>(
             };
             Ok(MappingTree::Leaf(mapping_leaf))
         }
+        FieldMappingType::IpAddr(options, cardinality) => {
+            let bytes_options = get_bytes_options(options);
+            let field = schema_builder.add_bytes_field(&field_name, bytes_options);
+            let mapping_leaf = MappingLeaf {
+                field,
+                typ: LeafType::IpAddr(options.clone()),
+                cardinality: *cardinality,
+            };
+            Ok(MappingTree::Leaf(mapping_leaf))
+        }
         FieldMappingType::Json(options, cardinality) => {
             let json_options = JsonObjectOptions::from(options.clone());
             let field = schema_builder.add_json_field(&field_name, json_options);
@@ -681,16 +778,19 @@ fn build_mapping_from_field_type<'a>(
 
 #[cfg(test)]
 mod tests {
+    use std::net::IpAddr;
+
     use serde_json::json;
     use tantivy::schema::{Cardinality, Field, Value};
     use tantivy::{DateTime, Document};
     use time::macros::datetime;
 
-    use super::{LeafType, MappingLeaf};
+    use super::{ip_addr_from_bytes, ip_addr_to_bytes, LeafType, MappingLeaf};
     use crate::default_doc_mapper::date_time_type::QuickwitDateTimeOptions;
     use crate::default_doc_mapper::field_mapping_entry::{
         QuickwitNumericOptions, QuickwitTextOptions,
     };
+    use crate::DocParsingError;
 
     #[test]
     fn test_field_name_from_field_path() {
@@ -1042,4 +1142,56 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn test_parse_ip_addr() {
+        let typ = LeafType::IpAddr(QuickwitNumericOptions::default());
+        let value = typ.value_from_json(json!("192.168.0.1")).unwrap();
+        assert_eq!(
+            value.as_bytes().unwrap(),
+            &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 255, 255, 192, 168, 0, 1]
+        );
+        let value = typ.value_from_json(json!("2001:db8::1")).unwrap();
+        assert_eq!(
+            value.as_bytes().unwrap(),
+            &[32, 1, 13, 184, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]
+        );
+    }
+
+    #[test]
+    fn test_ip_addr_bytes_roundtrip_and_order() {
+        for ip_addr_str in ["10.0.0.1", "2001:db8::1", "::1"] {
+            let ip_addr: IpAddr = ip_addr_str.parse().unwrap();
+            let bytes = ip_addr_to_bytes(ip_addr);
+            assert_eq!(ip_addr_from_bytes(&bytes), Some(ip_addr));
+        }
+        let ip_addr_bytes = |ip_addr_str: &str| ip_addr_to_bytes(ip_addr_str.parse().unwrap());
+        assert!(ip_addr_bytes("10.0.0.255") < ip_addr_bytes("10.0.1.0"));
+        assert!(ip_addr_bytes("9.255.255.255") < ip_addr_bytes("10.0.0.0"));
+        assert!(ip_addr_from_bytes(b"too short").is_none());
+    }
+
+    #[test]
+    fn test_parse_invalid_ip_addr_should_error() {
+        let typ = LeafType::IpAddr(QuickwitNumericOptions::default());
+        let error = typ.value_from_json(json!("192.168.0.256")).unwrap_err();
+        assert_eq!(error, "Expected IPv4 or IPv6 address, got '192.168.0.256'.");
+        let error = typ.value_from_json(json!(2u64)).unwrap_err();
+        assert_eq!(error, "Expected IP address string, got '2'.");
+
+        let leaf_entry = MappingLeaf {
+            field: Field::from_field_id(10),
+            typ,
+            cardinality: Cardinality::SingleValue,
+        };
+        let mut document = Document::default();
+        let mut path = vec!["client_ip".to_string()];
+        let doc_parsing_error = leaf_entry
+            .doc_from_json(json!("not-an-ip"), &mut document, &mut path)
+            .unwrap_err();
+        assert!(matches!(
+            doc_parsing_error,
+            DocParsingError::InvalidIpAddr(field_name, _) if field_name == "client_ip"
+        ));
+    }
 }
@@ -28,8 +28,9 @@ use anyhow::bail;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+pub use self::date_time_type::{format_date_time, parse_date_time, DateTimeFormat};
 pub use self::default_mapper::{DefaultDocMapper, SortByConfig};
-pub use self::default_mapper_builder::{DefaultDocMapperBuilder, ModeType};
+pub use self::default_mapper_builder::{DefaultDocMapperBuilder, DocIdConfig, ModeType};
 pub use self::field_mapping_entry::{
     FieldMappingEntry, QuickwitJsonOptions, QuickwitNumericOptions, QuickwitTextOptions,
 };
@@ -29,7 +29,8 @@ use tantivy::Document;
 
 pub type Partition = u64;
 
-use crate::{DocParsingError, QueryParserError, SortBy};
+use crate::field_cost::measure_field_costs;
+use crate::{DocParsingError, FieldCost, QueryParserError, SortBy, TermExpansion};
 
 /// The `DocMapper` trait defines the way of defining how a (json) document,
 /// and the fields it contains, are stored and indexed.
@@ -46,6 +47,16 @@ pub trait DocMapper: Send + Sync + Debug + DynClone + 'static {
     /// (we pass by value here, as the value can be used as is in the _source field.)
     fn doc_from_json(&self, doc_json: String) -> Result<(Partition, Document), DocParsingError>;
 
+    /// Same as [`DocMapper::doc_from_json`], but also returns the names of the fields missing from
+    /// the document that were set to the default value declared in the mapping.
+    fn doc_from_json_with_defaulted_fields(
+        &self,
+        doc_json: String,
+    ) -> Result<(Partition, Document, Vec<String>), DocParsingError> {
+        let (partition, document) = self.doc_from_json(doc_json)?;
+        Ok((partition, document, Vec::new()))
+    }
+
     /// Converts a tantivy named Document to the json format.
     ///
     /// Tantivy does not have any notion of cardinality nor object.
@@ -75,7 +86,18 @@ pub trait DocMapper: Send + Sync + Debug + DynClone + 'static {
         &self,
         split_schema: Schema,
         request: &SearchRequest,
-    ) -> Result<Box<dyn Query>, QueryParserError>;
+    ) -> Result<Box<dyn Query>, QueryParserError> {
+        let (query, _) = self.query_with_term_expansions(split_schema, request)?;
+        Ok(query)
+    }
+
+    /// Returns the query along with its fuzzy and prefix terms, whose matching terms have to be
+    /// looked up in the term dictionaries of the split before searching it.
+    fn query_with_term_expansions(
+        &self,
+        split_schema: Schema,
+        request: &SearchRequest,
+    ) -> Result<(Box<dyn Query>, Vec<TermExpansion>), QueryParserError>;
 
     /// Returns the default sort
     fn sort_by(&self) -> SortBy {
@@ -95,10 +117,43 @@ pub trait DocMapper: Send + Sync + Debug + DynClone + 'static {
         None
     }
 
+    /// Returns the field storing the expiry of the documents, if any.
+    /// As for the timestamp field, `split_schema` is the schema of the split being operated on.
+    fn expire_timestamp_field(&self, split_schema: &Schema) -> Option<Field> {
+        self.expire_timestamp_field_name()
+            .and_then(|field_name| split_schema.get_field(&field_name))
+    }
+
+    /// Returns the expire timestamp field name.
+    fn expire_timestamp_field_name(&self) -> Option<String> {
+        None
+    }
+
+    /// Returns the field storing the sequence number of the documents, if any.
+    /// As for the timestamp field, `split_schema` is the schema of the split being operated on.
+    fn sequence_field(&self, split_schema: &Schema) -> Option<Field> {
+        self.sequence_field_name()
+            .and_then(|field_name| split_schema.get_field(&field_name))
+    }
+
+    /// Returns the sequence field name.
+    fn sequence_field_name(&self) -> Option<String> {
+        None
+    }
+
     /// Returns the tag field names
     fn tag_field_names(&self) -> BTreeSet<String> {
         Default::default()
     }
+
+    /// Measures the cost of indexing each field of a document built by the `DocMapper`, keyed by
+    /// field name: the size of its values and the time spent tokenizing them.
+    ///
+    /// The text values are tokenized again, so this is meant to profile a sample of the
+    /// documents only.
+    fn field_costs(&self, document: &Document) -> BTreeMap<String, FieldCost> {
+        measure_field_costs(&self.schema(), document)
+    }
 }
 
 clone_trait_object!(DocMapper);
@@ -204,6 +259,10 @@ mod tests {
             sort_order: None,
             sort_by_field: None,
             aggregation_request: None,
+            search_after: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            snippet_fragment_size: None,
         };
         let query = doc_mapper.query(schema, &search_request).unwrap();
         assert_eq!(
@@ -241,6 +300,10 @@ mod tests {
             sort_order: None,
             sort_by_field: Some("text_field".to_string()),
             aggregation_request: None,
+            search_after: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            snippet_fragment_size: None,
         };
         let query = doc_mapper.query(schema, &search_request).unwrap_err();
         assert_eq!(
@@ -276,6 +339,10 @@ mod tests {
             sort_order: None,
             sort_by_field: None,
             aggregation_request: None,
+            search_after: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            snippet_fragment_size: None,
         };
         let query = doc_mapper.query(schema, &search_request).unwrap();
         assert_eq!(
@@ -311,6 +378,10 @@ mod tests {
             sort_order: None,
             sort_by_field: None,
             aggregation_request: None,
+            search_after: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            snippet_fragment_size: None,
         };
         let query = doc_mapper.query(schema, &search_request).unwrap();
         assert_eq!(
@@ -50,6 +50,12 @@ pub enum DocParsingError {
     /// The document does not contains a field that is required.
     #[error("The document must contain field {0:?}. As a fast field, it is implicitly required.")]
     RequiredFastField(String),
+    /// The document ID could not be extracted from the document.
+    #[error("The document ID could not be extracted from field {0:?}: {1}")]
+    DocIdError(String, String),
+    /// The value of an IP address field is not a valid IPv4 or IPv6 address.
+    #[error("The field '{0}' is not a valid IP address: {1}")]
+    InvalidIpAddr(String, String),
 }
 
 impl From<TantivyDocParsingError> for DocParsingError {
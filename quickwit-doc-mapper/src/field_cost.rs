@@ -0,0 +1,178 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tantivy::schema::{FieldType, Schema, Value};
+use tantivy::tokenizer::TextAnalyzer;
+use tantivy::Document;
+
+use crate::QUICKWIT_TOKENIZER_MANAGER;
+
+/// Cost of indexing the values of a field.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FieldCost {
+    /// Number of bytes of the parsed values. Numerical and date values count for 8 bytes.
+    pub num_bytes: u64,
+    /// Number of tokens produced by the tokenizer of the field.
+    pub num_tokens: u64,
+    /// Time spent tokenizing the values, in nanoseconds.
+    pub tokenization_nanos: u64,
+}
+
+impl FieldCost {
+    /// Adds the cost of other values of the same field.
+    pub fn add(&mut self, other: &FieldCost) {
+        self.num_bytes += other.num_bytes;
+        self.num_tokens += other.num_tokens;
+        self.tokenization_nanos += other.tokenization_nanos;
+    }
+
+    fn add_text(&mut self, text: &str, tokenizer_opt: Option<&TextAnalyzer>) {
+        self.num_bytes += text.len() as u64;
+        if let Some(tokenizer) = tokenizer_opt {
+            let start = Instant::now();
+            let mut token_stream = tokenizer.token_stream(text);
+            while token_stream.advance() {
+                self.num_tokens += 1;
+            }
+            self.tokenization_nanos += start.elapsed().as_nanos() as u64;
+        }
+    }
+
+    fn add_json_object(
+        &mut self,
+        json_obj: &serde_json::Map<String, JsonValue>,
+        tokenizer_opt: Option<&TextAnalyzer>,
+    ) {
+        for (key, json_value) in json_obj {
+            self.num_bytes += key.len() as u64;
+            self.add_json_value(json_value, tokenizer_opt);
+        }
+    }
+
+    fn add_json_value(&mut self, json_value: &JsonValue, tokenizer_opt: Option<&TextAnalyzer>) {
+        match json_value {
+            JsonValue::Null => {}
+            JsonValue::Bool(_) => self.num_bytes += 1,
+            JsonValue::Number(_) => self.num_bytes += 8,
+            JsonValue::String(text) => self.add_text(text, tokenizer_opt),
+            JsonValue::Array(json_values) => {
+                for json_value in json_values {
+                    self.add_json_value(json_value, tokenizer_opt);
+                }
+            }
+            JsonValue::Object(json_obj) => self.add_json_object(json_obj, tokenizer_opt),
+        }
+    }
+}
+
+/// Returns the tokenizer of the text values of a field, if the field is tokenized.
+fn field_tokenizer(field_type: &FieldType) -> Option<TextAnalyzer> {
+    let tokenizer_name = match field_type {
+        FieldType::Str(text_options) => text_options.get_indexing_options()?.tokenizer(),
+        FieldType::JsonObject(json_options) => {
+            json_options.get_text_indexing_options()?.tokenizer()
+        }
+        _ => return None,
+    };
+    QUICKWIT_TOKENIZER_MANAGER.get(tokenizer_name)
+}
+
+/// Measures the cost of indexing each field of `document`, keyed by field name. The text values,
+/// including the strings nested in JSON values, are tokenized again with the tokenizer of their
+/// field to time their tokenization.
+pub(crate) fn measure_field_costs(
+    schema: &Schema,
+    document: &Document,
+) -> BTreeMap<String, FieldCost> {
+    let mut field_costs: BTreeMap<String, FieldCost> = BTreeMap::new();
+    for field_value in document.field_values() {
+        let field_entry = schema.get_field_entry(field_value.field());
+        let tokenizer_opt = field_tokenizer(field_entry.field_type());
+        let field_cost = field_costs
+            .entry(field_entry.name().to_string())
+            .or_default();
+        match field_value.value() {
+            Value::Str(text) => field_cost.add_text(text, tokenizer_opt.as_ref()),
+            Value::PreTokStr(pre_tokenized_text) => {
+                field_cost.num_bytes += pre_tokenized_text.text.len() as u64;
+                field_cost.num_tokens += pre_tokenized_text.tokens.len() as u64;
+            }
+            Value::Facet(facet) => field_cost.num_bytes += facet.encoded_str().len() as u64,
+            Value::Bytes(bytes) => field_cost.num_bytes += bytes.len() as u64,
+            Value::JsonObject(json_obj) => {
+                field_cost.add_json_object(json_obj, tokenizer_opt.as_ref())
+            }
+            Value::Bool(_) => field_cost.num_bytes += 1,
+            _ => field_cost.num_bytes += 8,
+        }
+    }
+    field_costs
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{default_doc_mapper_for_test, DocMapper};
+
+    #[test]
+    fn test_doc_mapper_field_costs() {
+        let doc_mapper = default_doc_mapper_for_test();
+        let (_, document) = doc_mapper
+            .doc_from_json(
+                r#"{
+                    "timestamp": 1,
+                    "response_date": "2021-12-19T16:39:57+00:00",
+                    "response_time": 12,
+                    "response_payload": "YWJj",
+                    "body": "the quick brown fox",
+                    "owner": "jumps over",
+                    "properties": {"color": "lazy dog", "legs": 4}
+                }"#
+                .to_string(),
+            )
+            .unwrap();
+        let field_costs = doc_mapper.field_costs(&document);
+
+        let timestamp_cost = &field_costs["timestamp"];
+        assert_eq!(timestamp_cost.num_bytes, 8);
+        assert_eq!(timestamp_cost.num_tokens, 0);
+        assert_eq!(timestamp_cost.tokenization_nanos, 0);
+
+        let body_cost = &field_costs["body"];
+        assert_eq!(body_cost.num_bytes, 19);
+        assert_eq!(body_cost.num_tokens, 4);
+
+        // The `raw` tokenizer yields a single token.
+        let owner_cost = &field_costs["owner"];
+        assert_eq!(owner_cost.num_bytes, 10);
+        assert_eq!(owner_cost.num_tokens, 1);
+
+        let properties_cost = &field_costs["properties"];
+        assert_eq!(properties_cost.num_bytes, 5 + 8 + 4 + 8);
+        assert_eq!(properties_cost.num_tokens, 2);
+
+        assert_eq!(field_costs["response_time"].num_bytes, 8);
+        assert_eq!(field_costs["response_payload"].num_bytes, 3);
+        assert!(!field_costs.contains_key("isImportant"));
+    }
+}
@@ -27,21 +27,28 @@
 mod default_doc_mapper;
 mod doc_mapper;
 mod error;
+mod field_cost;
 mod query_builder;
 mod routing_expression;
 mod sort_by;
+mod term_expansion;
 mod tokenizers;
 
+/// Pruning of splits on the value ranges of their fields.
+pub mod range_pruning;
 /// Pruning tags manipulation.
 pub mod tag_pruning;
 
 pub use default_doc_mapper::{
-    DefaultDocMapper, DefaultDocMapperBuilder, FieldMappingEntry, ModeType, QuickwitJsonOptions,
-    SortByConfig,
+    format_date_time, parse_date_time, DateTimeFormat, DefaultDocMapper, DefaultDocMapperBuilder,
+    DocIdConfig, FieldMappingEntry, ModeType, QuickwitJsonOptions, SortByConfig,
 };
 pub use doc_mapper::DocMapper;
 pub use error::{DocParsingError, QueryParserError};
+pub use field_cost::FieldCost;
+pub use routing_expression::RoutingExpr;
 pub use sort_by::{SortBy, SortByField, SortOrder};
+pub use term_expansion::{TermExpansion, MAX_NUM_TERM_EXPANSIONS};
 pub use tokenizers::QUICKWIT_TOKENIZER_MANAGER;
 
 /// Field name reserved for storing the source document.
@@ -50,6 +57,9 @@ pub const SOURCE_FIELD_NAME: &str = "_source";
 /// Field name reserved for storing the dynamically indexed fields.
 pub const DYNAMIC_FIELD_NAME: &str = "_dynamic";
 
+/// Field name reserved for storing the document ID.
+pub const DOC_ID_FIELD_NAME: &str = "_id";
+
 /// Returns a default `DefaultIndexConfig` for unit tests.
 #[cfg(any(test, feature = "testsuite"))]
 pub fn default_doc_mapper_for_test() -> DefaultDocMapper {
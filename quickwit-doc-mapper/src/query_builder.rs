@@ -17,23 +17,41 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
 
 use quickwit_proto::SearchRequest;
-use tantivy::query::{Query, QueryParser, QueryParserError as TantivyQueryParserError};
+use tantivy::query::{
+    BooleanQuery, BoostQuery, Query, QueryParser, QueryParserError as TantivyQueryParserError,
+};
 use tantivy::schema::{Field, FieldType, Schema};
-use tantivy_query_grammar::{UserInputAst, UserInputLeaf, UserInputLiteral};
+use tantivy::tokenizer::TextAnalyzer;
+use tantivy::Score;
+use tantivy_query_grammar::{Occur, UserInputAst, UserInputLeaf, UserInputLiteral};
 
 use crate::sort_by::validate_sort_by_field_name;
+use crate::term_expansion::{
+    rewrite_term_expansions, TermExpansion, UserTermExpansion, UserTermExpansions,
+};
 use crate::{QueryParserError, DYNAMIC_FIELD_NAME, QUICKWIT_TOKENIZER_MANAGER};
 
 /// Build a `Query` with field resolution & forbidding range clauses.
+///
+/// The scores of the matches on a field are multiplied by the boost of the field, which is either
+/// set in the search fields of the request, e.g. `title^5`, or defaults to its boost in
+/// `default_field_boosts`.
+///
+/// The fuzzy (`quickwit~1`) and prefix (`quick*`) terms of the query are returned along with the
+/// query, as the terms they match have to be looked up in the term dictionaries of the split
+/// before searching it.
 pub(crate) fn build_query(
     schema: Schema,
     request: &SearchRequest,
     default_field_names: &[String],
-) -> Result<Box<dyn Query>, QueryParserError> {
-    let user_input_ast = tantivy_query_grammar::parse_query(&request.query)
+    default_field_boosts: &BTreeMap<String, f32>,
+) -> Result<(Box<dyn Query>, Vec<TermExpansion>), QueryParserError> {
+    let (user_query, user_term_expansions) = rewrite_term_expansions(&request.query)?;
+    let user_input_ast = tantivy_query_grammar::parse_query(&user_query)
         .map_err(|_| TantivyQueryParserError::SyntaxError(request.query.to_string()))?;
 
     if has_range_clause(&user_input_ast) {
@@ -49,23 +67,236 @@ pub(crate) fn build_query(
         );
     }
 
-    validate_requested_snippet_fields(&schema, request, &user_input_ast, default_field_names)?;
+    let mut search_field_names = Vec::with_capacity(request.search_fields.len());
+    let mut field_boosts = default_field_boosts.clone();
+    for boosted_field_name in &request.search_fields {
+        let (field_name, boost_opt) = parse_boosted_field_name(boosted_field_name)?;
+        if let Some(boost) = boost_opt {
+            field_boosts.insert(field_name.to_string(), boost);
+        }
+        search_field_names.push(field_name.to_string());
+    }
 
-    let search_fields = if request.search_fields.is_empty() {
+    validate_requested_snippet_fields(
+        &schema,
+        request,
+        &search_field_names,
+        &user_input_ast,
+        default_field_names,
+    )?;
+
+    let search_fields = if search_field_names.is_empty() {
         resolve_fields(&schema, default_field_names)?
     } else {
-        resolve_fields(&schema, &request.search_fields)?
+        resolve_fields(&schema, &search_field_names)?
     };
 
     if let Some(sort_by_field) = request.sort_by_field.as_ref() {
         validate_sort_by_field_name(sort_by_field, &schema, Some(&search_fields))?;
     }
 
-    let mut query_parser =
-        QueryParser::new(schema, search_fields, QUICKWIT_TOKENIZER_MANAGER.clone());
+    // The splits created before a field was added to the doc mapping do not have the field.
+    let boosted_fields: HashMap<Field, f32> = field_boosts
+        .iter()
+        .filter_map(|(field_name, boost)| Some((schema.get_field(field_name)?, *boost)))
+        .collect();
+    let mut query_parser = QueryParser::new(
+        schema.clone(),
+        search_fields.clone(),
+        QUICKWIT_TOKENIZER_MANAGER.clone(),
+    );
     query_parser.set_conjunction_by_default();
-    let query = query_parser.parse_query(&request.query)?;
-    Ok(query)
+    for (field, boost) in &boosted_fields {
+        query_parser.set_field_boost(*field, *boost);
+    }
+    if user_term_expansions.is_empty() {
+        let query = query_parser.parse_query(&request.query)?;
+        return Ok((query, Vec::new()));
+    }
+    let mut term_expansion_query_builder = TermExpansionQueryBuilder {
+        schema: &schema,
+        query_parser: &query_parser,
+        search_fields: &search_fields,
+        boosted_fields: &boosted_fields,
+        user_term_expansions: &user_term_expansions,
+        term_expansions: Vec::new(),
+    };
+    let query = term_expansion_query_builder.build(&user_input_ast)?;
+    Ok((query, term_expansion_query_builder.term_expansions))
+}
+
+/// Builds the query of a user query holding fuzzy or prefix terms, which the query parser does not
+/// support. The other leaves of the query are handed over to the query parser one by one.
+struct TermExpansionQueryBuilder<'a> {
+    schema: &'a Schema,
+    query_parser: &'a QueryParser,
+    search_fields: &'a [Field],
+    boosted_fields: &'a HashMap<Field, f32>,
+    user_term_expansions: &'a UserTermExpansions,
+    term_expansions: Vec<TermExpansion>,
+}
+
+impl<'a> TermExpansionQueryBuilder<'a> {
+    fn build(&mut self, user_input_ast: &UserInputAst) -> Result<Box<dyn Query>, QueryParserError> {
+        match user_input_ast {
+            UserInputAst::Clause(sub_asts) => {
+                let mut sub_queries = Vec::with_capacity(sub_asts.len());
+                for (occur_opt, sub_ast) in sub_asts {
+                    // The query parser combines the clauses with a conjunction by default.
+                    let occur = occur_opt.unwrap_or(Occur::Must);
+                    sub_queries.push((occur, self.build(sub_ast)?));
+                }
+                Ok(Box::new(BooleanQuery::new(sub_queries)))
+            }
+            UserInputAst::Boost(sub_ast, boost) => Ok(Box::new(BoostQuery::new(
+                self.build(sub_ast)?,
+                *boost as Score,
+            ))),
+            UserInputAst::Leaf(leaf) => self.build_leaf(leaf),
+        }
+    }
+
+    fn build_leaf(&mut self, leaf: &UserInputLeaf) -> Result<Box<dyn Query>, QueryParserError> {
+        let leaf_query = match leaf {
+            UserInputLeaf::Literal(UserInputLiteral {
+                field_name,
+                phrase,
+                slop,
+            }) => {
+                let user_term_expansions = self.user_term_expansions;
+                if let Some(user_term_expansion) = user_term_expansions.get(phrase) {
+                    return self.build_term_expansion(field_name.as_deref(), user_term_expansion);
+                }
+                let mut leaf_query = String::new();
+                if let Some(field_name) = field_name {
+                    write!(leaf_query, "{}:", field_name).unwrap();
+                }
+                // The phrase of an unquoted word may hold escaped quotes.
+                write!(leaf_query, "\"{}\"", phrase.replace('"', "\\\"")).unwrap();
+                if *slop > 0 {
+                    write!(leaf_query, "~{}", slop).unwrap();
+                }
+                leaf_query
+            }
+            UserInputLeaf::All => "*".to_string(),
+            UserInputLeaf::Range { .. } => {
+                return Err(anyhow::anyhow!("Range queries are not currently allowed.").into())
+            }
+        };
+        let query = self.query_parser.parse_query(&leaf_query)?;
+        Ok(query)
+    }
+
+    /// Builds the query of a fuzzy or prefix term, searching the field of the term or else the
+    /// search fields of the request that are indexed text fields.
+    fn build_term_expansion(
+        &mut self,
+        field_name_opt: Option<&str>,
+        user_term_expansion: &UserTermExpansion,
+    ) -> Result<Box<dyn Query>, QueryParserError> {
+        let fields = match field_name_opt {
+            Some(field_name) => vec![self.schema.get_field(field_name).ok_or_else(|| {
+                TantivyQueryParserError::FieldDoesNotExist(field_name.to_string())
+            })?],
+            None => self.search_fields.to_vec(),
+        };
+        let mut field_queries: Vec<(Occur, Box<dyn Query>)> = Vec::with_capacity(fields.len());
+        for field in fields {
+            let field_entry = self.schema.get_field_entry(field);
+            let tokenizer = match text_field_tokenizer(field_entry.field_type()) {
+                Some(tokenizer) => tokenizer,
+                None if field_name_opt.is_none() => continue,
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "The {} term `{}` can only target indexed text fields, `{}` is not one.",
+                        user_term_expansion.kind,
+                        user_term_expansion.text,
+                        field_entry.name()
+                    )
+                    .into())
+                }
+            };
+            let mut tokens = Vec::new();
+            let mut token_stream = tokenizer.token_stream(&user_term_expansion.text);
+            while token_stream.advance() {
+                tokens.push(token_stream.token().text.clone());
+            }
+            if tokens.len() != 1 {
+                return Err(anyhow::anyhow!(
+                    "The {} term `{}` must be a single token for the field `{}`, got {} tokens.",
+                    user_term_expansion.kind,
+                    user_term_expansion.text,
+                    field_entry.name(),
+                    tokens.len()
+                )
+                .into());
+            }
+            let token = tokens.pop().unwrap();
+            user_term_expansion.validate_token(&token)?;
+            let term_expansion = TermExpansion::new(
+                field,
+                field_entry.name().to_string(),
+                token,
+                user_term_expansion.kind,
+            );
+            let mut field_query = term_expansion.query();
+            if let Some(boost) = self.boosted_fields.get(&field) {
+                field_query = Box::new(BoostQuery::new(field_query, *boost));
+            }
+            self.term_expansions.push(term_expansion);
+            field_queries.push((Occur::Should, field_query));
+        }
+        match field_queries.len() {
+            0 => Err(anyhow::anyhow!(
+                "No indexed text field to search the {} term `{}` in.",
+                user_term_expansion.kind,
+                user_term_expansion.text
+            )
+            .into()),
+            1 => Ok(field_queries.pop().unwrap().1),
+            _ => Ok(Box::new(BooleanQuery::new(field_queries))),
+        }
+    }
+}
+
+/// Returns the tokenizer of a field if it is an indexed text field.
+fn text_field_tokenizer(field_type: &FieldType) -> Option<TextAnalyzer> {
+    match field_type {
+        FieldType::Str(text_options) => {
+            QUICKWIT_TOKENIZER_MANAGER.get(text_options.get_indexing_options()?.tokenizer())
+        }
+        _ => None,
+    }
+}
+
+/// Splits a search field name optionally suffixed with a boost, e.g. `title^5`, into the name of
+/// the field and its boost.
+fn parse_boosted_field_name(boosted_field_name: &str) -> anyhow::Result<(&str, Option<f32>)> {
+    let (field_name, boost_str) = match boosted_field_name.rsplit_once('^') {
+        Some((field_name, boost_str)) => (field_name, boost_str),
+        None => return Ok((boosted_field_name, None)),
+    };
+    let boost = boost_str
+        .parse::<f32>()
+        .ok()
+        .filter(|boost| validate_field_boost(*boost).is_ok())
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Invalid boost `{}` for the search field `{}`: the boost must be a positive \
+                 number.",
+                boost_str,
+                field_name
+            )
+        })?;
+    Ok((field_name, Some(boost)))
+}
+
+/// Checks that a field boost is a positive finite number.
+pub(crate) fn validate_field_boost(boost: f32) -> anyhow::Result<()> {
+    if !boost.is_finite() || boost <= 0.0 {
+        anyhow::bail!("The boost must be a positive number, got `{}`.", boost);
+    }
+    Ok(())
 }
 
 fn resolve_fields(schema: &Schema, field_names: &[String]) -> anyhow::Result<Vec<Field>> {
@@ -128,13 +359,14 @@ fn field_names(user_input_ast: &UserInputAst) -> HashSet<&str> {
 fn validate_requested_snippet_fields(
     schema: &Schema,
     request: &SearchRequest,
+    search_field_names: &[String],
     user_input_ast: &UserInputAst,
     default_field_names: &[String],
 ) -> anyhow::Result<()> {
     let query_fields = field_names(user_input_ast);
     for field_name in &request.snippet_fields {
         if !default_field_names.contains(field_name)
-            && !request.search_fields.contains(field_name)
+            && !search_field_names.contains(field_name)
             && !query_fields.contains(field_name.as_str())
         {
             return Err(anyhow::anyhow!(
@@ -171,6 +403,8 @@ fn validate_requested_snippet_fields(
 
 #[cfg(test)]
 mod test {
+    use std::collections::BTreeMap;
+
     use quickwit_proto::SearchRequest;
     use tantivy::query::QueryParserError;
     use tantivy::schema::{Schema, FAST, INDEXED, STORED, TEXT};
@@ -214,12 +448,22 @@ mod test {
             start_offset: 0,
             sort_order: None,
             sort_by_field: None,
+            search_after: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            snippet_fragment_size: None,
         };
 
         let default_field_names =
             default_search_fields.unwrap_or_else(|| vec!["title".to_string(), "desc".to_string()]);
 
-        let query_result = build_query(make_schema(), &request, &default_field_names);
+        let query_result = build_query(
+            make_schema(),
+            &request,
+            &default_field_names,
+            &BTreeMap::new(),
+        )
+        .map(|(query, _)| query);
         match expected {
             TestExpectation::Err(sub_str) => {
                 assert!(
@@ -367,6 +611,10 @@ mod test {
             start_offset: 0,
             sort_order: None,
             sort_by_field: None,
+            search_after: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            snippet_fragment_size: None,
         };
         let user_input_ast = tantivy_query_grammar::parse_query(&request.query)
             .map_err(|_| QueryParserError::SyntaxError(request.query.clone()))
@@ -374,7 +622,137 @@ mod test {
         let default_field_names =
             default_search_fields.unwrap_or_else(|| vec!["title".to_string(), "desc".to_string()]);
 
-        validate_requested_snippet_fields(&schema, &request, &user_input_ast, &default_field_names)
+        validate_requested_snippet_fields(
+            &schema,
+            &request,
+            &request.search_fields,
+            &user_input_ast,
+            &default_field_names,
+        )
+    }
+
+    #[test]
+    fn test_build_query_field_boosts() {
+        check_build_query(
+            "foo",
+            vec!["title^5".to_string(), "desc".to_string()],
+            None,
+            TestExpectation::Ok("boost=5"),
+        )
+        .unwrap();
+        check_build_query(
+            "foo",
+            vec!["title^0".to_string()],
+            None,
+            TestExpectation::Err(
+                "Invalid boost `0` for the search field `title`: the boost must be a positive \
+                 number.",
+            ),
+        )
+        .unwrap();
+        check_build_query(
+            "foo",
+            vec!["title^high".to_string()],
+            None,
+            TestExpectation::Err("Invalid boost `high` for the search field `title`"),
+        )
+        .unwrap();
+
+        let request = SearchRequest {
+            index_id: "test_index".to_string(),
+            query: "foo".to_string(),
+            max_hits: 20,
+            ..Default::default()
+        };
+        let default_field_names = vec!["title".to_string(), "desc".to_string()];
+        let default_field_boosts = BTreeMap::from([("desc".to_string(), 2.5)]);
+        let (query, _) = build_query(
+            make_schema(),
+            &request,
+            &default_field_names,
+            &default_field_boosts,
+        )
+        .unwrap();
+        assert!(format!("{query:?}").contains("boost=2.5"));
+
+        // The boosts of the request override the default boosts.
+        let request = SearchRequest {
+            search_fields: vec!["desc^3".to_string()],
+            ..request
+        };
+        let (query, _) = build_query(
+            make_schema(),
+            &request,
+            &default_field_names,
+            &default_field_boosts,
+        )
+        .unwrap();
+        let query_debug = format!("{query:?}");
+        assert!(query_debug.contains("boost=3"));
+        assert!(!query_debug.contains("boost=2.5"));
+    }
+
+    #[test]
+    fn test_build_query_fuzzy_and_prefix_terms() {
+        let request = SearchRequest {
+            index_id: "test_index".to_string(),
+            query: "title:quickwit~1 AND (quick* OR desc:\"red bike\")".to_string(),
+            search_fields: vec!["title^2".to_string(), "desc".to_string()],
+            max_hits: 20,
+            ..Default::default()
+        };
+        let (query, term_expansions) =
+            build_query(make_schema(), &request, &[], &BTreeMap::new()).unwrap();
+        let query_debug = format!("{query:?}");
+        assert!(query_debug.contains("distance: 1"));
+        assert!(query_debug.contains("prefix: true"));
+        assert!(query_debug.contains("boost=2"));
+        assert!(query_debug.contains("PhraseQuery"));
+        // The fuzzy term targets `title`, the prefix term both search fields.
+        assert_eq!(term_expansions.len(), 3);
+
+        check_build_query(
+            "title:quick desc:bar",
+            vec![],
+            None,
+            TestExpectation::Ok("TermQuery"),
+        )
+        .unwrap();
+        check_build_query(
+            "title:quickwit~3",
+            vec![],
+            None,
+            TestExpectation::Err("must be at most 2"),
+        )
+        .unwrap();
+        check_build_query(
+            "title:q*",
+            vec![],
+            None,
+            TestExpectation::Err("must have a prefix of at least 2 characters"),
+        )
+        .unwrap();
+        check_build_query(
+            "title:foo-bar*",
+            vec![],
+            None,
+            TestExpectation::Err("must be a single token for the field `title`"),
+        )
+        .unwrap();
+        check_build_query(
+            "server.running:tru*",
+            vec![],
+            None,
+            TestExpectation::Err("can only target indexed text fields"),
+        )
+        .unwrap();
+        check_build_query(
+            "foo:bar*",
+            vec![],
+            None,
+            TestExpectation::Err("Field does not exists: 'foo'"),
+        )
+        .unwrap();
     }
 
     #[test]
@@ -0,0 +1,331 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt::Display;
+use std::ops::Bound;
+
+use tantivy::query::QueryParserError as TantivyQueryParserError;
+use tantivy_query_grammar::{Occur, UserInputAst, UserInputBound, UserInputLeaf, UserInputLiteral};
+
+use crate::term_expansion::{rewrite_term_expansions, UserTermExpansions};
+use crate::QueryParserError;
+
+/// Extracts from a user query a RangeFilterAst that represents a predicate over the ranges of
+/// the values of the fields of a split.
+///
+/// If the predicate evaluates to false for the value ranges of a split, we are guaranteed that no
+/// documents in the split matches the query. Term queries are treated as ranges holding a single
+/// value, while fuzzy and prefix terms are uninformative.
+pub fn extract_ranges_from_query(
+    user_query: &str,
+) -> Result<Option<RangeFilterAst>, QueryParserError> {
+    let (rewritten_query, user_term_expansions) = rewrite_term_expansions(user_query)?;
+    let user_input_ast = tantivy_query_grammar::parse_query(&rewritten_query)
+        .map_err(|_| TantivyQueryParserError::SyntaxError(user_query.to_string()))?;
+    Ok(simplify_ast(collect_range_filters(
+        user_input_ast,
+        &user_term_expansions,
+    )))
+}
+
+/// Represents a predicate over the ranges of the values of the fields of a split.
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RangeFilterAst {
+    And(Vec<RangeFilterAst>),
+    Or(Vec<RangeFilterAst>),
+    /// Tests whether the values of the field may fall between the bounds.
+    Range {
+        field: String,
+        lower: Bound<String>,
+        upper: Bound<String>,
+    },
+}
+
+impl RangeFilterAst {
+    /// Evaluates the range filter predicate, given a function telling whether the values of a
+    /// field may fall between two bounds.
+    pub fn evaluate(&self, overlaps: &dyn Fn(&str, Bound<&str>, Bound<&str>) -> bool) -> bool {
+        match self {
+            RangeFilterAst::And(children) => children
+                .iter()
+                .all(|child_ast| child_ast.evaluate(overlaps)),
+            RangeFilterAst::Or(children) => children
+                .iter()
+                .any(|child_ast| child_ast.evaluate(overlaps)),
+            RangeFilterAst::Range {
+                field,
+                lower,
+                upper,
+            } => overlaps(field, as_str_bound(lower), as_str_bound(upper)),
+        }
+    }
+}
+
+fn as_str_bound(bound: &Bound<String>) -> Bound<&str> {
+    match bound {
+        Bound::Included(value) => Bound::Included(value.as_str()),
+        Bound::Excluded(value) => Bound::Excluded(value.as_str()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+impl Display for RangeFilterAst {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let (separator, children) = match self {
+            RangeFilterAst::And(children) => (" ∧ ", children),
+            RangeFilterAst::Or(children) => (" ∨ ", children),
+            RangeFilterAst::Range {
+                field,
+                lower,
+                upper,
+            } => {
+                match lower {
+                    Bound::Included(value) => write!(f, "{}:[{}", field, value)?,
+                    Bound::Excluded(value) => write!(f, "{}:{{{}", field, value)?,
+                    Bound::Unbounded => write!(f, "{}:{{*", field)?,
+                }
+                match upper {
+                    Bound::Included(value) => write!(f, " TO {}]", value)?,
+                    Bound::Excluded(value) => write!(f, " TO {}}}", value)?,
+                    Bound::Unbounded => write!(f, " TO *}}")?,
+                }
+                return Ok(());
+            }
+        };
+        write!(f, "(")?;
+        for (child_ord, child) in children.iter().enumerate() {
+            if child_ord > 0 {
+                write!(f, "{}", separator)?;
+            }
+            write!(f, "{}", child)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Intermediary AST that may contain leaves that are equivalent to the "Uninformative"
+/// predicate, which could be true or false regardless of the value ranges of a split.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum UnsimplifiedRangeFilterAst {
+    And(Vec<UnsimplifiedRangeFilterAst>),
+    Or(Vec<UnsimplifiedRangeFilterAst>),
+    Range {
+        field: String,
+        lower: Bound<String>,
+        upper: Bound<String>,
+    },
+    Uninformative,
+}
+
+// Takes a range filter AST and simplifies it.
+//
+// The resulting AST does not contain any uninformative leaves.
+//
+// Returning None here, is to be interpreted as returning `True`.
+fn simplify_ast(ast: UnsimplifiedRangeFilterAst) -> Option<RangeFilterAst> {
+    match ast {
+        UnsimplifiedRangeFilterAst::And(conditions) => {
+            let mut pruned_conditions: Vec<RangeFilterAst> =
+                conditions.into_iter().filter_map(simplify_ast).collect();
+            match pruned_conditions.len() {
+                0 => None,
+                1 => pruned_conditions.pop(),
+                _ => Some(RangeFilterAst::And(pruned_conditions)),
+            }
+        }
+        UnsimplifiedRangeFilterAst::Or(conditions) => {
+            let mut pruned_conditions: Vec<RangeFilterAst> = Vec::new();
+            for condition in conditions {
+                // If we get None as part of the condition here, we return None
+                // directly. (Remember None means True).
+                pruned_conditions.push(simplify_ast(condition)?);
+            }
+            match pruned_conditions.len() {
+                0 => None,
+                1 => pruned_conditions.pop(),
+                _ => Some(RangeFilterAst::Or(pruned_conditions)),
+            }
+        }
+        UnsimplifiedRangeFilterAst::Range {
+            field,
+            lower,
+            upper,
+        } => Some(RangeFilterAst::Range {
+            field,
+            lower,
+            upper,
+        }),
+        UnsimplifiedRangeFilterAst::Uninformative => None,
+    }
+}
+
+/// Returns a boolean formula implied by the query: if the query matches a document, the value of
+/// its fields satisfy the formula.
+///
+/// Negated clauses are transformed into the predicate `Uninformative`: a split holding values
+/// outside of a range may still hold values inside of it.
+fn collect_range_filters(
+    user_input_ast: UserInputAst,
+    user_term_expansions: &UserTermExpansions,
+) -> UnsimplifiedRangeFilterAst {
+    match user_input_ast {
+        UserInputAst::Clause(sub_queries) => {
+            let clause: Vec<(Occur, UnsimplifiedRangeFilterAst)> = sub_queries
+                .into_iter()
+                .map(|(occur_opt, ast)| {
+                    (
+                        occur_opt.unwrap_or(Occur::Should),
+                        collect_range_filters(ast, user_term_expansions),
+                    )
+                })
+                .collect();
+            if clause.is_empty() {
+                return UnsimplifiedRangeFilterAst::Uninformative;
+            }
+            if clause.iter().any(|(occur, _)| occur == &Occur::Must) {
+                let must_clause = clause
+                    .into_iter()
+                    .filter_map(|(occur, ast)| match occur {
+                        Occur::Must => Some(ast),
+                        Occur::MustNot | Occur::Should => None,
+                    })
+                    .collect();
+                return UnsimplifiedRangeFilterAst::And(must_clause);
+            }
+            let should_clause = clause
+                .into_iter()
+                .map(|(occur, ast)| match occur {
+                    Occur::Should => ast,
+                    Occur::MustNot | Occur::Must => UnsimplifiedRangeFilterAst::Uninformative,
+                })
+                .collect();
+            UnsimplifiedRangeFilterAst::Or(should_clause)
+        }
+        UserInputAst::Boost(ast, _) => collect_range_filters(*ast, user_term_expansions),
+        UserInputAst::Leaf(leaf) => match *leaf {
+            UserInputLeaf::Literal(UserInputLiteral {
+                field_name: Some(field_name),
+                phrase,
+                slop: _,
+            }) if user_term_expansions.get(&phrase).is_none() => {
+                UnsimplifiedRangeFilterAst::Range {
+                    field: field_name,
+                    lower: Bound::Included(phrase.clone()),
+                    upper: Bound::Included(phrase),
+                }
+            }
+            UserInputLeaf::Range {
+                field: Some(field_name),
+                lower,
+                upper,
+            } => UnsimplifiedRangeFilterAst::Range {
+                field: field_name,
+                lower: into_bound(lower),
+                upper: into_bound(upper),
+            },
+            UserInputLeaf::Literal(_)
+            | UserInputLeaf::Range { field: None, .. }
+            | UserInputLeaf::All => UnsimplifiedRangeFilterAst::Uninformative,
+        },
+    }
+}
+
+fn into_bound(user_input_bound: UserInputBound) -> Bound<String> {
+    match user_input_bound {
+        UserInputBound::Inclusive(value) => Bound::Included(value),
+        UserInputBound::Exclusive(value) => Bound::Excluded(value),
+        UserInputBound::Unbounded => Bound::Unbounded,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+
+    use super::extract_ranges_from_query;
+
+    fn extract_ranges_to_string(user_query: &str) -> Option<String> {
+        extract_ranges_from_query(user_query)
+            .unwrap()
+            .map(|range_filter_ast| range_filter_ast.to_string())
+    }
+
+    #[test]
+    fn test_extract_ranges_from_query_invalid_query() {
+        assert!(extract_ranges_from_query(":>").is_err());
+    }
+
+    #[test]
+    fn test_extract_ranges_from_query_uninformative() {
+        assert_eq!(extract_ranges_to_string("*"), None);
+        assert_eq!(extract_ranges_to_string("foo"), None);
+        assert_eq!(extract_ranges_to_string("-status:404"), None);
+        assert_eq!(extract_ranges_to_string("status:404 OR foo"), None);
+        assert_eq!(extract_ranges_to_string("status:40*"), None);
+        assert_eq!(extract_ranges_to_string("status:404~1"), None);
+    }
+
+    #[test]
+    fn test_extract_ranges_from_query_range_and_term() {
+        assert_eq!(
+            extract_ranges_to_string("latency:[100 TO 200]").unwrap(),
+            "latency:[100 TO 200]"
+        );
+        assert_eq!(
+            extract_ranges_to_string("latency:>100").unwrap(),
+            "latency:{100 TO *}"
+        );
+        assert_eq!(
+            extract_ranges_to_string("status:404").unwrap(),
+            "status:[404 TO 404]"
+        );
+    }
+
+    #[test]
+    fn test_extract_ranges_from_query_boolean_query() {
+        assert_eq!(
+            extract_ranges_to_string("latency:>100 AND (status:404 OR status:500) AND foo")
+                .unwrap(),
+            "(latency:{100 TO *} ∧ (status:[404 TO 404] ∨ status:[500 TO 500]))"
+        );
+        assert_eq!(
+            extract_ranges_to_string("latency:>100 AND NOT status:404").unwrap(),
+            "latency:{100 TO *}"
+        );
+    }
+
+    #[test]
+    fn test_range_filter_ast_evaluate() {
+        let range_filter_ast =
+            extract_ranges_from_query("latency:>100 AND (status:404 OR status:500)")
+                .unwrap()
+                .unwrap();
+        let overlaps = |max_latency: u64, statuses: &'static [u64]| {
+            move |field: &str, lower: Bound<&str>, _upper: Bound<&str>| match (field, lower) {
+                ("latency", Bound::Excluded(lower)) => max_latency > lower.parse().unwrap(),
+                ("status", Bound::Included(status)) => statuses.contains(&status.parse().unwrap()),
+                _ => true,
+            }
+        };
+        assert!(range_filter_ast.evaluate(&overlaps(150, &[200, 404])));
+        assert!(!range_filter_ast.evaluate(&overlaps(50, &[200, 404])));
+        assert!(!range_filter_ast.evaluate(&overlaps(150, &[200])));
+    }
+}
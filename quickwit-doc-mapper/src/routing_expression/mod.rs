@@ -24,7 +24,9 @@ use std::sync::Arc;
 
 use siphasher::sip::SipHasher;
 
+/// Context a routing expression is evaluated against, typically a JSON document.
 pub trait RoutingExprContext {
+    /// Feeds the value of the attribute `attr_name` to the hasher.
     // TODO see if we can get rid of the alloc in some specific case
     fn hash_attribute<H: Hasher>(&self, attr_name: &str, hasher: &mut H);
 }
@@ -80,6 +82,8 @@ impl RoutingExprContext for serde_json::Map<String, serde_json::Value> {
     }
 }
 
+/// Expression evaluated against a document to derive a hash from the values of some of its
+/// fields, used to pick the partition or the shard of the document.
 #[derive(Clone)]
 pub struct RoutingExpr {
     inner: Arc<InnerRoutingExpr>,
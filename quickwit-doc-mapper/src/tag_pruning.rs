@@ -24,10 +24,14 @@ use serde::{Deserialize, Serialize};
 use tantivy::query::QueryParserError as TantivyQueryParserError;
 use tantivy_query_grammar::{Occur, UserInputAst, UserInputLeaf, UserInputLiteral};
 
+use crate::term_expansion::{rewrite_term_expansions, UserTermExpansions};
 use crate::QueryParserError;
 
-fn user_input_ast_to_tags_filter_ast(user_input_ast: UserInputAst) -> Option<TagFilterAst> {
-    let filters_ast = collect_tag_filters(user_input_ast);
+fn user_input_ast_to_tags_filter_ast(
+    user_input_ast: UserInputAst,
+    user_term_expansions: &UserTermExpansions,
+) -> Option<TagFilterAst> {
+    let filters_ast = collect_tag_filters(user_input_ast, user_term_expansions);
     let term_filters_ast = simplify_ast(filters_ast)?;
     Some(expand_to_tag_ast(term_filters_ast))
 }
@@ -45,10 +49,16 @@ pub fn match_tag_field_name(field_name: &str, tag: &str) -> bool {
 /// If the predicate evaluates to false for a given set of tags
 /// associated with a split, we are guaranteed that no documents
 /// in the split matches the query.
+///
+/// Fuzzy and prefix terms do not match a single tag, hence are uninformative.
 pub fn extract_tags_from_query(user_query: &str) -> Result<Option<TagFilterAst>, QueryParserError> {
-    let user_input_ast = tantivy_query_grammar::parse_query(user_query)
+    let (rewritten_query, user_term_expansions) = rewrite_term_expansions(user_query)?;
+    let user_input_ast = tantivy_query_grammar::parse_query(&rewritten_query)
         .map_err(|_| TantivyQueryParserError::SyntaxError(user_query.to_string()))?;
-    Ok(user_input_ast_to_tags_filter_ast(user_input_ast))
+    Ok(user_input_ast_to_tags_filter_ast(
+        user_input_ast,
+        &user_term_expansions,
+    ))
 }
 
 /// Intermediary AST that may contain leaf that are
@@ -310,33 +320,37 @@ fn negate_ast(clause: UnsimplifiedTagFilterAst) -> UnsimplifiedTagFilterAst {
 /// In other words, we are guaranteed that if we were to run the query
 /// described by this predicate only, the matched documents would all
 /// be in the original query too (The opposite is rarely true).
-fn collect_tag_filters(user_input_ast: UserInputAst) -> UnsimplifiedTagFilterAst {
+fn collect_tag_filters(
+    user_input_ast: UserInputAst,
+    user_term_expansions: &UserTermExpansions,
+) -> UnsimplifiedTagFilterAst {
     match user_input_ast {
         UserInputAst::Clause(sub_queries) => {
             let clause_with_resolved_occur: Vec<(Occur, UnsimplifiedTagFilterAst)> = sub_queries
                 .into_iter()
                 .map(|(occur_opt, ast)| {
-                    (occur_opt.unwrap_or(Occur::Should), collect_tag_filters(ast))
+                    (
+                        occur_opt.unwrap_or(Occur::Should),
+                        collect_tag_filters(ast, user_term_expansions),
+                    )
                 })
                 .collect();
             collect_tag_filters_for_clause(clause_with_resolved_occur)
         }
-        UserInputAst::Boost(ast, _) => collect_tag_filters(*ast),
+        UserInputAst::Boost(ast, _) => collect_tag_filters(*ast, user_term_expansions),
         UserInputAst::Leaf(leaf) => match *leaf {
             UserInputLeaf::Literal(UserInputLiteral {
                 field_name: Some(field_name),
                 phrase,
                 slop: _,
-            }) => UnsimplifiedTagFilterAst::Tag {
+            }) if user_term_expansions.get(&phrase).is_none() => UnsimplifiedTagFilterAst::Tag {
                 is_present: true,
                 field: field_name,
                 value: phrase,
             },
-            UserInputLeaf::Literal(UserInputLiteral {
-                field_name: None, ..
-            })
-            | UserInputLeaf::All
-            | UserInputLeaf::Range { .. } => UnsimplifiedTagFilterAst::Uninformative,
+            UserInputLeaf::Literal(_) | UserInputLeaf::All | UserInputLeaf::Range { .. } => {
+                UnsimplifiedTagFilterAst::Uninformative
+            }
         },
     }
 }
@@ -378,6 +392,18 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_extract_tags_from_query_fuzzy_and_prefix_terms() -> anyhow::Result<()> {
+        assert_eq!(extract_tags_from_query("lang:f*")?, None);
+        assert_eq!(
+            &extract_tags_from_query("lang:fr AND user:bar~1")?
+                .unwrap()
+                .to_string(),
+            "(¬lang! ∨ lang:fr)"
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_extract_tags_from_query_range_query_conjunction() -> anyhow::Result<()> {
         assert_eq!(
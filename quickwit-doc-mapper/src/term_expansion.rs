@@ -0,0 +1,531 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+use anyhow::bail;
+use levenshtein_automata::{Distance, LevenshteinAutomatonBuilder, DFA, SINK_STATE};
+use once_cell::sync::Lazy;
+use tantivy::query::{FuzzyTermQuery, Query};
+use tantivy::schema::Field;
+use tantivy::termdict::TermDictionary;
+use tantivy::Term;
+use tantivy_fst::Automaton;
+
+/// Edit distance of the fuzzy terms whose distance is omitted, e.g. `quickwit~`.
+const DEFAULT_FUZZY_DISTANCE: u8 = 1;
+
+/// Maximum edit distance of a fuzzy term.
+const MAX_FUZZY_DISTANCE: u8 = 2;
+
+/// Minimum number of characters of the prefix of a prefix term.
+const MIN_PREFIX_NUM_CHARS: usize = 2;
+
+/// Maximum number of terms of a split segment a fuzzy or prefix term can match.
+pub const MAX_NUM_TERM_EXPANSIONS: usize = 1_000;
+
+/// Prefix of the words substituted to the fuzzy and prefix terms of a user query, which the query
+/// grammar does not support. It is lengthened with leading underscores when the user query contains
+/// it.
+const PLACEHOLDER_PREFIX: &str = "__term_expansion_";
+
+static LEVENSHTEIN_AUTOMATON_BUILDERS: Lazy<Vec<LevenshteinAutomatonBuilder>> = Lazy::new(|| {
+    (0..=MAX_FUZZY_DISTANCE)
+        .map(|distance| LevenshteinAutomatonBuilder::new(distance, true))
+        .collect()
+});
+
+/// Kind of the expansion of a term.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TermExpansionKind {
+    /// Matches the terms within an edit distance of the term, e.g. `quickwit~1`.
+    Fuzzy {
+        /// Maximum number of insertions, deletions, substitutions and transpositions.
+        distance: u8,
+    },
+    /// Matches the terms starting with the term, e.g. `quick*`.
+    Prefix,
+}
+
+impl fmt::Display for TermExpansionKind {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TermExpansionKind::Fuzzy { .. } => write!(formatter, "fuzzy"),
+            TermExpansionKind::Prefix => write!(formatter, "prefix"),
+        }
+    }
+}
+
+/// Fuzzy or prefix term of a user query, before its tokenization.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct UserTermExpansion {
+    pub text: String,
+    pub kind: TermExpansionKind,
+}
+
+impl UserTermExpansion {
+    /// Checks that the token of the term is long enough to keep its expansion reasonable.
+    pub fn validate_token(&self, token: &str) -> anyhow::Result<()> {
+        let num_chars = token.chars().count();
+        match self.kind {
+            TermExpansionKind::Fuzzy { distance } if num_chars <= distance as usize => {
+                bail!(
+                    "The fuzzy term `{}` must have more characters than its distance {}.",
+                    self.text,
+                    distance
+                )
+            }
+            TermExpansionKind::Prefix if num_chars < MIN_PREFIX_NUM_CHARS => {
+                bail!(
+                    "The prefix term `{}*` must have a prefix of at least {} characters.",
+                    self.text,
+                    MIN_PREFIX_NUM_CHARS
+                )
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Fuzzy and prefix terms of a user query, substituted by placeholder words in the rewritten query.
+#[derive(Debug)]
+pub(crate) struct UserTermExpansions {
+    /// Prefix of the placeholder words. It does not occur in the user query, so the words and
+    /// phrases of the user query are never mistaken for placeholders.
+    placeholder_prefix: String,
+    user_term_expansions: Vec<UserTermExpansion>,
+}
+
+impl UserTermExpansions {
+    fn new(user_query: &str) -> UserTermExpansions {
+        let mut placeholder_prefix = PLACEHOLDER_PREFIX.to_string();
+        while user_query.contains(&placeholder_prefix) {
+            placeholder_prefix.insert(0, '_');
+        }
+        UserTermExpansions {
+            placeholder_prefix,
+            user_term_expansions: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.user_term_expansions.is_empty()
+    }
+
+    /// Returns the term substituted by `phrase` if it is a placeholder.
+    pub fn get(&self, phrase: &str) -> Option<&UserTermExpansion> {
+        let term_expansion_ord: usize = phrase
+            .strip_prefix(&self.placeholder_prefix)?
+            .parse()
+            .ok()?;
+        self.user_term_expansions.get(term_expansion_ord)
+    }
+
+    fn push(&mut self, user_term_expansion: UserTermExpansion, rewritten_query: &mut String) {
+        rewritten_query.push_str(&self.placeholder_prefix);
+        rewritten_query.push_str(&self.user_term_expansions.len().to_string());
+        self.user_term_expansions.push(user_term_expansion);
+    }
+}
+
+/// Fuzzy or prefix term of a query targeting a field, whose matching terms are only known once
+/// the term dictionary of the field is read.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TermExpansion {
+    field: Field,
+    field_name: String,
+    token: String,
+    kind: TermExpansionKind,
+}
+
+impl TermExpansion {
+    pub(crate) fn new(
+        field: Field,
+        field_name: String,
+        token: String,
+        kind: TermExpansionKind,
+    ) -> TermExpansion {
+        TermExpansion {
+            field,
+            field_name,
+            token,
+            kind,
+        }
+    }
+
+    /// Returns the field targeted by the term.
+    pub fn field(&self) -> Field {
+        self.field
+    }
+
+    pub(crate) fn query(&self) -> Box<dyn Query> {
+        let term = Term::from_field_text(self.field, &self.token);
+        match self.kind {
+            TermExpansionKind::Fuzzy { distance } => {
+                Box::new(FuzzyTermQuery::new(term, distance, true))
+            }
+            TermExpansionKind::Prefix => Box::new(FuzzyTermQuery::new_prefix(term, 0, true)),
+        }
+    }
+
+    /// Returns the terms of `term_dict` matched by the term, failing if there are more than
+    /// [`MAX_NUM_TERM_EXPANSIONS`].
+    pub fn expand(&self, term_dict: &TermDictionary) -> anyhow::Result<Vec<Term>> {
+        let mut terms = Vec::new();
+        match self.kind {
+            TermExpansionKind::Fuzzy { distance } => {
+                let dfa = LEVENSHTEIN_AUTOMATON_BUILDERS[distance as usize].build_dfa(&self.token);
+                let mut term_stream = term_dict.search(LevenshteinDfa(dfa)).into_stream()?;
+                while term_stream.advance() {
+                    self.push_term(term_stream.key(), &mut terms)?;
+                }
+            }
+            TermExpansionKind::Prefix => {
+                let prefix = self.token.as_bytes();
+                let mut term_stream = term_dict.range().ge(prefix).into_stream()?;
+                while term_stream.advance() && term_stream.key().starts_with(prefix) {
+                    self.push_term(term_stream.key(), &mut terms)?;
+                }
+            }
+        }
+        Ok(terms)
+    }
+
+    fn push_term(&self, term_bytes: &[u8], terms: &mut Vec<Term>) -> anyhow::Result<()> {
+        if terms.len() == MAX_NUM_TERM_EXPANSIONS {
+            bail!(
+                "The {} term `{}` matches more than {} terms of the field `{}`.",
+                self.kind,
+                self.token,
+                MAX_NUM_TERM_EXPANSIONS,
+                self.field_name
+            );
+        }
+        let term_text = std::str::from_utf8(term_bytes)?;
+        terms.push(Term::from_field_text(self.field, term_text));
+        Ok(())
+    }
+}
+
+struct LevenshteinDfa(DFA);
+
+impl Automaton for LevenshteinDfa {
+    type State = u32;
+
+    fn start(&self) -> u32 {
+        self.0.initial_state()
+    }
+
+    fn is_match(&self, state: &u32) -> bool {
+        matches!(self.0.distance(*state), Distance::Exact(_))
+    }
+
+    fn can_match(&self, state: &u32) -> bool {
+        *state != SINK_STATE
+    }
+
+    fn accept(&self, state: &u32, byte: u8) -> u32 {
+        self.0.transition(*state, byte)
+    }
+}
+
+/// Substitutes a placeholder word to each fuzzy (`quickwit~1`) and prefix (`quick*`) term of
+/// `user_query`, returning the rewritten query and the substituted terms.
+///
+/// The quoted phrases, the ranges and the escaped characters are left untouched.
+pub(crate) fn rewrite_term_expansions(
+    user_query: &str,
+) -> anyhow::Result<(String, UserTermExpansions)> {
+    let mut rewritten_query = String::with_capacity(user_query.len());
+    let mut user_term_expansions = UserTermExpansions::new(user_query);
+    let mut word_start = 0;
+    let mut is_escaped = false;
+    let mut is_quoted = false;
+    let mut range_depth: usize = 0;
+    for (pos, c) in user_query.char_indices() {
+        if is_escaped {
+            is_escaped = false;
+            continue;
+        }
+        match c {
+            '\\' => is_escaped = true,
+            '"' => is_quoted = !is_quoted,
+            _ if is_quoted => {}
+            '[' | '{' => range_depth += 1,
+            // An unbalanced closing bracket must not prevent the next words from being rewritten.
+            ']' | '}' => range_depth = range_depth.saturating_sub(1),
+            _ if range_depth == 0 && (c.is_whitespace() || c == '(' || c == ')') => {
+                rewrite_word(
+                    &user_query[word_start..pos],
+                    &mut rewritten_query,
+                    &mut user_term_expansions,
+                )?;
+                rewritten_query.push(c);
+                word_start = pos + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    rewrite_word(
+        &user_query[word_start..],
+        &mut rewritten_query,
+        &mut user_term_expansions,
+    )?;
+    Ok((rewritten_query, user_term_expansions))
+}
+
+fn rewrite_word(
+    word: &str,
+    rewritten_query: &mut String,
+    user_term_expansions: &mut UserTermExpansions,
+) -> anyhow::Result<()> {
+    let occur_len = word.len() - word.trim_start_matches(&['+', '-'][..]).len();
+    let term_start = find_unescaped(word, ':').map_or(occur_len, |pos| pos + 1);
+    let term_end =
+        find_unescaped(&word[term_start..], '^').map_or(word.len(), |pos| term_start + pos);
+    match parse_user_term_expansion(&word[term_start..term_end])? {
+        Some(user_term_expansion) => {
+            rewritten_query.push_str(&word[..term_start]);
+            user_term_expansions.push(user_term_expansion, rewritten_query);
+            rewritten_query.push_str(&word[term_end..]);
+        }
+        None => rewritten_query.push_str(word),
+    }
+    Ok(())
+}
+
+fn parse_user_term_expansion(term: &str) -> anyhow::Result<Option<UserTermExpansion>> {
+    if term.starts_with(&['"', '[', '{', '<', '>'][..]) {
+        return Ok(None);
+    }
+    if let Some(prefix) = term.strip_suffix('*') {
+        if prefix.is_empty() || is_escaping(prefix) {
+            return Ok(None);
+        }
+        return Ok(Some(UserTermExpansion {
+            text: unescape(prefix),
+            kind: TermExpansionKind::Prefix,
+        }));
+    }
+    let (text, distance_str) = match term.rsplit_once('~') {
+        Some((text, distance_str))
+            if !text.is_empty()
+                && !is_escaping(text)
+                && distance_str.bytes().all(|byte| byte.is_ascii_digit()) =>
+        {
+            (text, distance_str)
+        }
+        _ => return Ok(None),
+    };
+    let distance = if distance_str.is_empty() {
+        DEFAULT_FUZZY_DISTANCE
+    } else {
+        distance_str
+            .parse::<u8>()
+            .ok()
+            .filter(|distance| *distance <= MAX_FUZZY_DISTANCE)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "The distance of the fuzzy term `{}` must be at most {}, got `{}`.",
+                    text,
+                    MAX_FUZZY_DISTANCE,
+                    distance_str
+                )
+            })?
+    };
+    Ok(Some(UserTermExpansion {
+        text: unescape(text),
+        kind: TermExpansionKind::Fuzzy { distance },
+    }))
+}
+
+/// Returns the position of the first occurrence of `target` in `text` that is not escaped.
+fn find_unescaped(text: &str, target: char) -> Option<usize> {
+    let mut is_escaped = false;
+    for (pos, c) in text.char_indices() {
+        if is_escaped {
+            is_escaped = false;
+        } else if c == '\\' {
+            is_escaped = true;
+        } else if c == target {
+            return Some(pos);
+        }
+    }
+    None
+}
+
+/// Tells whether `text` ends with an antislash escaping the next character.
+fn is_escaping(text: &str) -> bool {
+    let num_trailing_antislashes = text.len() - text.trim_end_matches('\\').len();
+    num_trailing_antislashes % 2 == 1
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            unescaped.extend(chars.next());
+        } else {
+            unescaped.push(c);
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::{Schema, TEXT};
+    use tantivy::{doc, Index};
+
+    use super::*;
+
+    fn fuzzy(text: &str, distance: u8) -> UserTermExpansion {
+        UserTermExpansion {
+            text: text.to_string(),
+            kind: TermExpansionKind::Fuzzy { distance },
+        }
+    }
+
+    fn prefix(text: &str) -> UserTermExpansion {
+        UserTermExpansion {
+            text: text.to_string(),
+            kind: TermExpansionKind::Prefix,
+        }
+    }
+
+    #[test]
+    fn test_rewrite_term_expansions() {
+        let (rewritten_query, user_term_expansions) = rewrite_term_expansions(
+            r#"title:quickwit~1 AND (-body:quick*^2 OR tanti~) "small bike*"~2 ts:[1 TO 2*]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            rewritten_query,
+            r#"title:__term_expansion_0 AND (-body:__term_expansion_1^2 OR __term_expansion_2) "small bike*"~2 ts:[1 TO 2*]"#
+        );
+        assert_eq!(
+            user_term_expansions.user_term_expansions,
+            [fuzzy("quickwit", 1), prefix("quick"), fuzzy("tanti", 1)]
+        );
+        assert_eq!(
+            user_term_expansions.get("__term_expansion_1"),
+            Some(&prefix("quick"))
+        );
+        assert_eq!(user_term_expansions.get("__term_expansion_3"), None);
+        assert_eq!(user_term_expansions.get("quick"), None);
+    }
+
+    #[test]
+    fn test_rewrite_term_expansions_does_not_mistake_user_words_for_placeholders() {
+        let (rewritten_query, user_term_expansions) =
+            rewrite_term_expansions(r#"body:"__term_expansion_0" __term_expansion_0 quick*"#)
+                .unwrap();
+        assert_eq!(
+            rewritten_query,
+            r#"body:"__term_expansion_0" __term_expansion_0 ___term_expansion_0"#
+        );
+        assert_eq!(user_term_expansions.get("__term_expansion_0"), None);
+        assert_eq!(
+            user_term_expansions.get("___term_expansion_0"),
+            Some(&prefix("quick"))
+        );
+    }
+
+    #[test]
+    fn test_rewrite_term_expansions_unbalanced_range_brackets() {
+        let (rewritten_query, user_term_expansions) =
+            rewrite_term_expansions("body:a] body:quick*").unwrap();
+        assert_eq!(rewritten_query, "body:a] body:__term_expansion_0");
+        assert_eq!(user_term_expansions.user_term_expansions, [prefix("quick")]);
+    }
+
+    #[test]
+    fn test_rewrite_term_expansions_ignores_escaped_operators_and_plain_terms() {
+        let query = r#"body:quick\* body:\~1 url:\/home\/\~user * body:foo~bar"#;
+        let (rewritten_query, user_term_expansions) = rewrite_term_expansions(query).unwrap();
+        assert_eq!(rewritten_query, query);
+        assert!(user_term_expansions.is_empty());
+
+        let (_, user_term_expansions) = rewrite_term_expansions(r#"path:a\:b*"#).unwrap();
+        assert_eq!(user_term_expansions.user_term_expansions, [prefix("a:b")]);
+    }
+
+    #[test]
+    fn test_rewrite_term_expansions_rejects_large_distances() {
+        let error = rewrite_term_expansions("title:quickwit~3").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The distance of the fuzzy term `quickwit` must be at most 2, got `3`."
+        );
+    }
+
+    #[test]
+    fn test_user_term_expansion_validate_token() {
+        assert!(fuzzy("ab", 1).validate_token("ab").is_ok());
+        assert!(fuzzy("ab", 2).validate_token("ab").is_err());
+        assert!(prefix("ab").validate_token("ab").is_ok());
+        assert!(prefix("a").validate_token("a").is_err());
+    }
+
+    #[test]
+    fn test_term_expansion_expand() -> anyhow::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let body = schema_builder.add_text_field("body", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 15_000_000)?;
+        index_writer.add_document(doc!(body => "quickwit quickly quick tantivy"))?;
+        let many_terms: Vec<String> = (0..=MAX_NUM_TERM_EXPANSIONS)
+            .map(|term_ord| format!("term{}", term_ord))
+            .collect();
+        index_writer.add_document(doc!(body => many_terms.join(" ")))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let inverted_index = searcher.segment_reader(0).inverted_index(body)?;
+
+        let term_texts = |kind: TermExpansionKind, token: &str| -> anyhow::Result<Vec<String>> {
+            let term_expansion =
+                TermExpansion::new(body, "body".to_string(), token.to_string(), kind);
+            Ok(term_expansion
+                .expand(inverted_index.terms())?
+                .iter()
+                .map(|term| term.as_str().unwrap().to_string())
+                .collect())
+        };
+        assert_eq!(
+            term_texts(TermExpansionKind::Prefix, "quick")?,
+            ["quick", "quickly", "quickwit"]
+        );
+        assert_eq!(
+            term_texts(TermExpansionKind::Fuzzy { distance: 1 }, "quickwir")?,
+            ["quickwit"]
+        );
+        assert_eq!(
+            term_texts(TermExpansionKind::Fuzzy { distance: 2 }, "quickl")?,
+            ["quick", "quickly"]
+        );
+        assert!(term_texts(TermExpansionKind::Prefix, "xyz")?.is_empty());
+        let error = term_texts(TermExpansionKind::Prefix, "term").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The prefix term `term` matches more than 1000 terms of the field `body`."
+        );
+        Ok(())
+    }
+}
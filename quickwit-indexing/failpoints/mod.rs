@@ -44,9 +44,11 @@ use quickwit_actors::{create_test_mailbox, ActorExitStatus, Universe};
 use quickwit_common::rand::append_random_suffix;
 use quickwit_common::split_file;
 use quickwit_indexing::actors::MergeExecutor;
-use quickwit_indexing::merge_policy::MergeOperation;
+use quickwit_indexing::merge_policy::{MergeOperation, MergeOperationType};
 use quickwit_indexing::models::{IndexingPipelineId, MergeScratch, ScratchDirectory};
-use quickwit_indexing::{get_tantivy_directory_from_split_bundle, new_split_id, TestSandbox};
+use quickwit_indexing::{
+    get_tantivy_directory_from_split_bundle, new_split_id, MergePermit, TestSandbox,
+};
 use quickwit_metastore::{SplitMetadata, SplitState};
 use tantivy::Directory;
 
@@ -279,10 +281,12 @@ async fn test_merge_executor_controlled_directory_kill_switch() -> anyhow::Resul
         merge_operation: MergeOperation {
             merge_split_id: new_split_id(),
             splits,
+            operation_type: MergeOperationType::Merge,
         },
         merge_scratch_directory,
         downloaded_splits_directory,
         tantivy_dirs,
+        merge_permit: MergePermit::default(),
     };
     let pipeline_id = IndexingPipelineId {
         index_id: index_id.to_string(),
@@ -291,7 +295,13 @@ async fn test_merge_executor_controlled_directory_kill_switch() -> anyhow::Resul
         pipeline_ord: 0,
     };
     let (merge_packager_mailbox, _merge_packager_inbox) = create_test_mailbox();
-    let merge_executor = MergeExecutor::new(pipeline_id, merge_packager_mailbox);
+    let merge_executor = MergeExecutor::new(
+        pipeline_id,
+        metastore,
+        test_index_builder.doc_mapper(),
+        None,
+        merge_packager_mailbox,
+    );
     let universe = Universe::new();
     let (merge_executor_mailbox, merge_executor_handle) =
         universe.spawn_actor(merge_executor).spawn();
@@ -0,0 +1,427 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use itertools::Itertools;
+use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox};
+use quickwit_metastore::{Metastore, SplitMetadata, SplitProducer, SplitState};
+use time::OffsetDateTime;
+use tracing::info;
+
+use crate::actors::merge_planner::belongs_to_pipeline;
+use crate::actors::publisher::ReplaceNonCriticalMailboxes;
+use crate::actors::{MergePlanner, MergePlannerLeadership};
+use crate::models::{ApplyDeletes, IndexingPipelineId};
+
+const RUN_INTERVAL: Duration = Duration::from_secs(60); // 1 minute
+
+/// Maximum number of splits fetched from the metastore on each pass. The splits lagging the most
+/// behind are processed first, the others are picked up on the following passes.
+const MAX_NUM_SPLITS_PER_PASS: usize = 100;
+
+#[derive(Clone, Debug, Default)]
+pub struct DeleteExecutorCounters {
+    /// The number of passes the delete executor has performed.
+    pub num_passes: usize,
+    /// The number of splits sent to the merge planner to get the delete tasks applied.
+    pub num_rewritten_splits: usize,
+}
+
+#[derive(Debug)]
+struct Loop;
+
+/// An actor periodically looking for the published splits of the pipeline with pending delete
+/// tasks matching their time range, or with expired documents, and handing them over to the merge
+/// planner.
+pub struct DeleteExecutor {
+    pipeline_id: IndexingPipelineId,
+    metastore: Arc<dyn Metastore>,
+    merge_planner_mailbox: Mailbox<MergePlanner>,
+    /// Splits handed over to the merge planner that have not been replaced yet.
+    ongoing_split_ids: HashSet<String>,
+    /// When merges are coordinated across the pipelines of the index, the delete executor covers
+    /// the splits of all the pipelines, but only while its merge planner is elected.
+    leadership_opt: Option<MergePlannerLeadership>,
+    /// Whether the splits with expired documents are rewritten as well.
+    purge_expired_docs: bool,
+    counters: DeleteExecutorCounters,
+}
+
+impl DeleteExecutor {
+    pub fn new(
+        pipeline_id: IndexingPipelineId,
+        metastore: Arc<dyn Metastore>,
+        merge_planner_mailbox: Mailbox<MergePlanner>,
+    ) -> Self {
+        Self {
+            pipeline_id,
+            metastore,
+            merge_planner_mailbox,
+            ongoing_split_ids: HashSet::new(),
+            leadership_opt: None,
+            purge_expired_docs: false,
+            counters: DeleteExecutorCounters::default(),
+        }
+    }
+
+    pub fn with_merge_planner_leadership(mut self, leadership: MergePlannerLeadership) -> Self {
+        self.leadership_opt = Some(leadership);
+        self
+    }
+
+    pub fn with_expired_docs_purge(mut self) -> Self {
+        self.purge_expired_docs = true;
+        self
+    }
+
+    /// Returns the producer of the splits covered by the delete executor, or `None` if it covers
+    /// the splits of all the pipelines of the index.
+    fn split_producer_opt(&self) -> Option<SplitProducer> {
+        if self.leadership_opt.is_some() {
+            return None;
+        }
+        Some(SplitProducer {
+            source_id: self.pipeline_id.source_id.clone(),
+            node_id: self.pipeline_id.node_id.clone(),
+            pipeline_ord: self.pipeline_id.pipeline_ord,
+        })
+    }
+
+    async fn apply_deletes(&mut self, ctx: &ActorContext<Self>) -> anyhow::Result<()> {
+        if let Some(leadership) = &self.leadership_opt {
+            if !leadership.is_leader() {
+                // The splits handed over to a planner that lost its lease may never be rewritten:
+                // they are handed over again once the planner is elected.
+                self.ongoing_split_ids.clear();
+                return Ok(());
+            }
+        }
+        let index_id = &self.pipeline_id.index_id;
+        let last_delete_opstamp = ctx
+            .protect_future(self.metastore.last_delete_opstamp(index_id))
+            .await?;
+        if last_delete_opstamp == 0 && !self.purge_expired_docs {
+            return Ok(());
+        }
+        let mut listed_splits: Vec<SplitMetadata> = Vec::new();
+        let mut is_listing_complete = true;
+        if last_delete_opstamp > 0 {
+            listed_splits.extend(
+                ctx.protect_future(self.metastore.list_splits_for_delete_task(
+                    index_id,
+                    last_delete_opstamp,
+                    self.split_producer_opt(),
+                    MAX_NUM_SPLITS_PER_PASS,
+                ))
+                .await?
+                .into_iter()
+                .map(|split| split.split_metadata),
+            );
+            is_listing_complete = listed_splits.len() < MAX_NUM_SPLITS_PER_PASS;
+        }
+        if self.purge_expired_docs {
+            let now_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+            let published_splits = ctx
+                .protect_future(self.metastore.list_splits(
+                    index_id,
+                    SplitState::Published,
+                    None,
+                    None,
+                ))
+                .await?;
+            let listed_split_ids: HashSet<String> = listed_splits
+                .iter()
+                .map(|split| split.split_id().to_string())
+                .collect();
+            listed_splits.extend(
+                published_splits
+                    .into_iter()
+                    .map(|split| split.split_metadata)
+                    .filter(|split| {
+                        split
+                            .min_expire_timestamp
+                            .map_or(false, |min_expire_timestamp| {
+                                min_expire_timestamp < now_timestamp
+                            })
+                            && !listed_split_ids.contains(split.split_id())
+                    }),
+            );
+        }
+        let stale_splits: Vec<SplitMetadata> = listed_splits
+            .into_iter()
+            .filter(|split| {
+                self.leadership_opt.is_some() || belongs_to_pipeline(&self.pipeline_id, split)
+            })
+            .collect();
+        // The splits that were replaced in the meantime are done. We can only tell when the
+        // listing was not truncated.
+        if is_listing_complete {
+            self.ongoing_split_ids.retain(|split_id| {
+                stale_splits
+                    .iter()
+                    .any(|split| split.split_id() == split_id)
+            });
+        }
+        let splits_to_rewrite: Vec<SplitMetadata> = stale_splits
+            .into_iter()
+            .filter(|split| !self.ongoing_split_ids.contains(split.split_id()))
+            .collect();
+        if splits_to_rewrite.is_empty() {
+            return Ok(());
+        }
+        let split_ids = splits_to_rewrite
+            .iter()
+            .map(|split| split.split_id())
+            .join(",");
+        info!(split_ids=%split_ids, last_delete_opstamp=last_delete_opstamp, "apply-deletes");
+        self.ongoing_split_ids.extend(
+            splits_to_rewrite
+                .iter()
+                .map(|split| split.split_id().to_string()),
+        );
+        self.counters.num_rewritten_splits += splits_to_rewrite.len();
+        ctx.send_message(
+            &self.merge_planner_mailbox,
+            ApplyDeletes {
+                splits: splits_to_rewrite,
+            },
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Actor for DeleteExecutor {
+    type ObservableState = DeleteExecutorCounters;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.counters.clone()
+    }
+
+    fn name(&self) -> String {
+        "DeleteExecutor".to_string()
+    }
+
+    async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        self.handle(Loop, ctx).await
+    }
+}
+
+#[async_trait]
+impl Handler<Loop> for DeleteExecutor {
+    type Reply = ();
+
+    async fn handle(&mut self, _: Loop, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        self.counters.num_passes += 1;
+        self.apply_deletes(ctx).await?;
+        ctx.schedule_self_msg(RUN_INTERVAL, Loop).await;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<ReplaceNonCriticalMailboxes> for DeleteExecutor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        message: ReplaceNonCriticalMailboxes,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        if let Some(merge_planner_mailbox) = message.merge_planner_mailbox_opt {
+            self.merge_planner_mailbox = merge_planner_mailbox;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_actors::{create_test_mailbox, Universe};
+    use quickwit_metastore::{MockMetastore, Split};
+
+    use super::*;
+
+    fn make_split(
+        split_id: &str,
+        pipeline_id: &IndexingPipelineId,
+        delete_opstamp: u64,
+        time_range: std::ops::RangeInclusive<i64>,
+    ) -> Split {
+        Split {
+            split_metadata: SplitMetadata {
+                split_id: split_id.to_string(),
+                source_id: pipeline_id.source_id.clone(),
+                node_id: pipeline_id.node_id.clone(),
+                pipeline_ord: pipeline_id.pipeline_ord,
+                time_range: Some(time_range),
+                delete_opstamp,
+                ..Default::default()
+            },
+            split_state: SplitState::Published,
+            update_timestamp: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_delete_executor() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let splits = vec![make_split("split-1", &pipeline_id, 0, 0..=10)];
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_last_delete_opstamp()
+            .times(2)
+            .returning(|_| Ok(2));
+        mock_metastore
+            .expect_list_splits_for_delete_task()
+            .times(2)
+            .returning(
+                move |index_id, delete_opstamp_threshold, producer_opt, limit| {
+                    assert_eq!(index_id, "test-index");
+                    assert_eq!(delete_opstamp_threshold, 2);
+                    // The splits of the other pipelines are filtered out by the metastore, so that
+                    // they do not count towards the limit.
+                    assert_eq!(
+                        producer_opt,
+                        Some(SplitProducer {
+                            source_id: "test-source".to_string(),
+                            node_id: "test-node".to_string(),
+                            pipeline_ord: 0,
+                        })
+                    );
+                    assert_eq!(limit, MAX_NUM_SPLITS_PER_PASS);
+                    Ok(splits.clone())
+                },
+            );
+        let (merge_planner_mailbox, merge_planner_inbox) = create_test_mailbox();
+        let delete_executor =
+            DeleteExecutor::new(pipeline_id, Arc::new(mock_metastore), merge_planner_mailbox);
+        let universe = Universe::new();
+        let (_mailbox, handle) = universe.spawn_actor(delete_executor).spawn();
+
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_passes, 1);
+        assert_eq!(counters.num_rewritten_splits, 1);
+        let apply_deletes_msgs = merge_planner_inbox.drain_for_test();
+        assert_eq!(apply_deletes_msgs.len(), 1);
+        let apply_deletes = apply_deletes_msgs[0]
+            .downcast_ref::<ApplyDeletes>()
+            .unwrap();
+        assert_eq!(apply_deletes.splits.len(), 1);
+        assert_eq!(apply_deletes.splits[0].split_id(), "split-1");
+
+        // The first split is still being rewritten: it is not handed over again.
+        universe.simulate_time_shift(RUN_INTERVAL).await;
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_passes, 2);
+        assert_eq!(counters.num_rewritten_splits, 1);
+        assert!(merge_planner_inbox.drain_for_test().is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_executor_no_delete_task() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_last_delete_opstamp()
+            .times(1)
+            .returning(|_| Ok(0));
+        mock_metastore.expect_list_splits_for_delete_task().never();
+        let (merge_planner_mailbox, merge_planner_inbox) = create_test_mailbox();
+        let delete_executor =
+            DeleteExecutor::new(pipeline_id, Arc::new(mock_metastore), merge_planner_mailbox);
+        let universe = Universe::new();
+        let (_mailbox, handle) = universe.spawn_actor(delete_executor).spawn();
+
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_passes, 1);
+        assert_eq!(counters.num_rewritten_splits, 0);
+        assert!(merge_planner_inbox.drain_for_test().is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_executor_expired_docs() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let now_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+        let mut expired_split = make_split("split-1", &pipeline_id, 0, 0..=10);
+        expired_split.split_metadata.min_expire_timestamp = Some(now_timestamp - 60);
+        let mut unexpired_split = make_split("split-2", &pipeline_id, 0, 0..=10);
+        unexpired_split.split_metadata.min_expire_timestamp = Some(now_timestamp + 3_600);
+        let splits = vec![
+            expired_split,
+            unexpired_split,
+            make_split("split-3", &pipeline_id, 0, 0..=10),
+        ];
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_last_delete_opstamp()
+            .times(1)
+            .returning(|_| Ok(0));
+        mock_metastore.expect_list_splits_for_delete_task().never();
+        mock_metastore.expect_list_splits().times(1).returning(
+            move |index_id, split_state, time_range, tags| {
+                assert_eq!(index_id, "test-index");
+                assert_eq!(split_state, SplitState::Published);
+                assert!(time_range.is_none());
+                assert!(tags.is_none());
+                Ok(splits.clone())
+            },
+        );
+        let (merge_planner_mailbox, merge_planner_inbox) = create_test_mailbox();
+        let delete_executor =
+            DeleteExecutor::new(pipeline_id, Arc::new(mock_metastore), merge_planner_mailbox)
+                .with_expired_docs_purge();
+        let universe = Universe::new();
+        let (_mailbox, handle) = universe.spawn_actor(delete_executor).spawn();
+
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_passes, 1);
+        assert_eq!(counters.num_rewritten_splits, 1);
+        let apply_deletes_msgs = merge_planner_inbox.drain_for_test();
+        assert_eq!(apply_deletes_msgs.len(), 1);
+        let apply_deletes = apply_deletes_msgs[0]
+            .downcast_ref::<ApplyDeletes>()
+            .unwrap();
+        assert_eq!(apply_deletes.splits.len(), 1);
+        assert_eq!(apply_deletes.splits[0].split_id(), "split-1");
+        Ok(())
+    }
+}
@@ -35,13 +35,6 @@ const RUN_INTERVAL: Duration = Duration::from_secs(60); // 1 minutes
 /// TODO ideally we want clean up all staged splits every time we restart the indexing pipeline, but
 /// the grace period strategy should do the job for the moment.
 const STAGED_GRACE_PERIOD: Duration = Duration::from_secs(60 * 60 * 24); // 24 hours
-/// We cannot safely delete splits right away as a in-flight queries could actually
-/// have selected this split.
-/// We deal this probably by introducing a grace period. A split is first marked as delete,
-/// and hence won't be selected for search. After a few minutes, once it reasonably safe to assume
-/// that all queries involving this split have terminated, we effectively delete the split.
-/// This duration is controlled by `DELETION_GRACE_PERIOD`.
-const DELETION_GRACE_PERIOD: Duration = Duration::from_secs(120); // 2 min
 
 #[derive(Clone, Debug, Default)]
 pub struct GarbageCollectorCounters {
@@ -61,6 +54,14 @@ pub struct GarbageCollector {
     pipeline_id: IndexingPipelineId,
     split_store: IndexingSplitStore,
     metastore: Arc<dyn Metastore>,
+    /// We cannot safely delete splits right away as a in-flight queries could actually
+    /// have selected this split.
+    /// We deal this probably by introducing a grace period. A split is first marked as delete,
+    /// and hence won't be selected for search. After a few minutes, once it reasonably safe to
+    /// assume that all queries involving this split have terminated, we effectively delete the
+    /// split. This duration is controlled by the `split_deletion_grace_period_secs` indexing
+    /// setting.
+    deletion_grace_period: Duration,
     counters: GarbageCollectorCounters,
 }
 
@@ -69,11 +70,13 @@ impl GarbageCollector {
         pipeline_id: IndexingPipelineId,
         split_store: IndexingSplitStore,
         metastore: Arc<dyn Metastore>,
+        deletion_grace_period: Duration,
     ) -> Self {
         Self {
             pipeline_id,
             split_store,
             metastore,
+            deletion_grace_period,
             counters: GarbageCollectorCounters::default(),
         }
     }
@@ -120,7 +123,7 @@ impl Handler<Loop> for GarbageCollector {
             self.split_store.clone(),
             self.metastore.clone(),
             STAGED_GRACE_PERIOD,
-            DELETION_GRACE_PERIOD,
+            self.deletion_grace_period,
             false,
             Some(ctx),
         )
@@ -151,6 +154,7 @@ mod tests {
     use quickwit_actors::Universe;
     use quickwit_metastore::{MockMetastore, Split, SplitMetadata, SplitState};
     use quickwit_storage::MockStorage;
+    use time::OffsetDateTime;
 
     use super::*;
 
@@ -182,7 +186,7 @@ mod tests {
         });
 
         let mut mock_metastore = MockMetastore::default();
-        mock_metastore.expect_list_splits().times(2).returning(
+        mock_metastore.expect_list_splits().times(3).returning(
             |index_id, split_state, _time_range, _tags| {
                 assert_eq!(index_id, "test-index");
                 let splits = match split_state {
@@ -222,6 +226,7 @@ mod tests {
             pipeline_id,
             IndexingSplitStore::create_with_no_local_store(Arc::new(mock_storage)),
             Arc::new(mock_metastore),
+            Duration::from_secs(120),
         );
         let universe = Universe::new();
         let (_maibox, handler) = universe.spawn_actor(garbage_collect_actor).spawn();
@@ -241,7 +246,7 @@ mod tests {
         });
 
         let mut mock_metastore = MockMetastore::default();
-        mock_metastore.expect_list_splits().times(4).returning(
+        mock_metastore.expect_list_splits().times(6).returning(
             |index_id, split_state, _time_range, _tags| {
                 assert_eq!(index_id, "test-index");
                 let splits = match split_state {
@@ -281,6 +286,7 @@ mod tests {
             pipeline_id,
             IndexingSplitStore::create_with_no_local_store(Arc::new(mock_storage)),
             Arc::new(mock_metastore),
+            Duration::from_secs(120),
         );
         let universe = Universe::new();
         let (_maibox, handle) = universe.spawn_actor(garbage_collect_actor).spawn();
@@ -304,4 +310,66 @@ mod tests {
         assert_eq!(state_after_initialization.num_deleted_files, 4);
         assert_eq!(state_after_initialization.num_deleted_bytes, 80);
     }
+
+    #[tokio::test]
+    async fn test_garbage_collect_defers_splits_replaced_by_staged_merge() {
+        let mut mock_storage = MockStorage::default();
+        mock_storage.expect_delete().times(1).returning(|path| {
+            assert_eq!(path, Path::new("a.split"));
+            Ok(())
+        });
+
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore.expect_list_splits().times(3).returning(
+            |_index_id, split_state, _time_range, _tags| {
+                let splits = match split_state {
+                    SplitState::Staged => {
+                        let mut splits = make_splits(&["merged"], SplitState::Staged);
+                        // The staged merge is recent, and is not garbage collected.
+                        splits[0].update_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+                        splits[0].split_metadata.replaced_split_ids = vec!["b".to_string()];
+                        splits
+                    }
+                    SplitState::MarkedForDeletion => {
+                        make_splits(&["a", "b"], SplitState::MarkedForDeletion)
+                    }
+                    _ => panic!("only Staged and MarkedForDeletion expected."),
+                };
+                Ok(splits)
+            },
+        );
+        mock_metastore
+            .expect_mark_splits_for_deletion()
+            .times(1)
+            .returning(|_index_id, split_ids| {
+                assert!(split_ids.is_empty());
+                Ok(())
+            });
+        mock_metastore
+            .expect_delete_splits()
+            .times(1)
+            .returning(|_index_id, split_ids| {
+                assert_eq!(split_ids, vec!["a"]);
+                Ok(())
+            });
+
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let garbage_collect_actor = GarbageCollector::new(
+            pipeline_id,
+            IndexingSplitStore::create_with_no_local_store(Arc::new(mock_storage)),
+            Arc::new(mock_metastore),
+            Duration::from_secs(120),
+        );
+        let universe = Universe::new();
+        let (_mailbox, handle) = universe.spawn_actor(garbage_collect_actor).spawn();
+
+        let state_after_initialization = handle.process_pending_and_observe().await.state;
+        assert_eq!(state_after_initialization.num_passes, 1);
+        assert_eq!(state_after_initialization.num_deleted_files, 1);
+    }
 }
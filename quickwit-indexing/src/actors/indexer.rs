@@ -18,6 +18,8 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::hash_map::Entry;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::ops::RangeInclusive;
 use std::sync::Arc;
 use std::time::Instant;
@@ -29,26 +31,52 @@ use fnv::FnvHashMap;
 use itertools::Itertools;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
 use quickwit_common::runtimes::RuntimeType;
-use quickwit_config::IndexingSettings;
+use quickwit_config::{IndexingSettings, SplitCarryOverSettings};
 use quickwit_doc_mapper::{DocMapper, DocParsingError, SortBy, QUICKWIT_TOKENIZER_MANAGER};
-use quickwit_metastore::checkpoint::{IndexCheckpointDelta, SourceCheckpointDelta};
-use quickwit_metastore::Metastore;
+use quickwit_metastore::checkpoint::{
+    IndexCheckpointDelta, SourceCheckpoint, SourceCheckpointDelta,
+};
+use quickwit_metastore::{
+    bucket_start_timestamp, DedupDigest, Metastore, DEDUP_BUCKET_DURATION_SECS,
+};
+use serde_json::{json, Value as JsonValue};
 use tantivy::schema::{Field, Schema, Value};
 use tantivy::store::{Compressor, ZstdCompressor};
 use tantivy::{Document, IndexBuilder, IndexSettings, IndexSortByField};
+use time::OffsetDateTime;
 use tokio::runtime::Handle;
 use tracing::{info, warn};
 use ulid::Ulid;
 
+use crate::actors::sharding::{DocRouter, MemoryLimitReports};
 use crate::actors::Packager;
+use crate::metrics::{IndexerPipelineMetrics, INDEXER_METRICS};
 use crate::models::{
-    IndexedSplit, IndexedSplitBatch, IndexingDirectory, IndexingPipelineId, NewPublishLock,
-    PublishLock, RawDocBatch,
+    DeadLetterQueue, DedupWindow, DocTransformError, DocTransformer, FieldProfile, IndexedSplit,
+    IndexedSplitBatch, IndexingDirectory, IndexingPipelineId, NewPublishLock, PipelineWarningKind,
+    PipelineWarningSink, PublishLock, RawDocBatch, ShardSplitBatch, SourceRateLimiter,
+    TenantRouter,
 };
+use crate::FairScheduler;
+
+/// Minimum number of documents processed between two commits for the indexer to warn about the
+/// share of invalid documents.
+const MIN_NUM_DOCS_FOR_INVALID_DOCS_WARNING: u64 = 100;
+
+/// Share of invalid documents processed between two commits above which the indexer emits a
+/// warning.
+const INVALID_DOCS_WARNING_RATIO: f64 = 0.1;
 
 #[derive(Debug)]
-struct CommitTimeout {
-    workbench_id: Ulid,
+pub(crate) struct CommitTimeout {
+    pub workbench_id: Ulid,
+}
+
+/// Commits the workbench of a shard indexer. Sent by the router of a sharded pipeline to all the
+/// shards at once.
+#[derive(Debug)]
+pub(crate) struct CommitShard {
+    pub commit_trigger: CommitTrigger,
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -63,12 +91,27 @@ pub struct IndexerCounters {
     pub num_missing_fields: u64,
     pub num_valid_docs: u64,
 
+    /// Number of documents dropped because a document with the same ID was already indexed
+    /// within the deduplication window.
+    pub num_duplicate_docs: u64,
+
+    /// Number of documents routed to the index of their tenant instead of being indexed.
+    pub num_routed_docs: u64,
+
+    /// Time spent indexing batches on the blocking runtime, in microseconds. Excludes the time
+    /// spent waiting for the fair scheduler of the node.
+    pub cpu_time_micros: u64,
+
     /// Number of splits that were emitted by the indexer.
     pub num_splits_emitted: u64,
 
     /// Number of split batches that were emitted by the indexer.
     pub num_split_batches_emitted: u64,
 
+    /// Number of splits emitted before the commit of their workbench because their partition was
+    /// the least recently used one when the number of open partitions reached its limit.
+    pub num_evicted_splits: u64,
+
     /// Number of bytes that went through the indexer
     /// during its entire lifetime.
     ///
@@ -78,12 +121,31 @@ pub struct IndexerCounters {
     /// Number of (valid) documents in the current workbench.
     /// This value is used to trigger commit and for observation.
     pub num_docs_in_workbench: u64,
+
+    /// Highest source positions read from the source and added to a workbench.
+    pub read_watermark: SourceCheckpoint,
+
+    /// Highest source positions of the workbenches that were committed and
+    /// sent to the packager.
+    pub indexed_watermark: SourceCheckpoint,
+
+    /// Preview of the current workbench, if any, to see what a commit that does not happen is
+    /// holding.
+    pub workbench_preview: Option<WorkbenchPreview>,
+
+    /// Profile of the fields of the sampled documents, when field profiling is enabled.
+    pub field_profile: Option<FieldProfile>,
 }
 
 impl IndexerCounters {
-    /// Returns the overall number of docs that went through the indexer (valid or not).
+    /// Returns the overall number of docs that went through the indexer (valid, invalid,
+    /// duplicate, or routed).
     pub fn num_processed_docs(&self) -> u64 {
-        self.num_valid_docs + self.num_parse_errors + self.num_missing_fields
+        self.num_valid_docs
+            + self.num_parse_errors
+            + self.num_missing_fields
+            + self.num_duplicate_docs
+            + self.num_routed_docs
     }
 
     /// Returns the overall number of docs that were sent to the indexer but were invalid.
@@ -92,40 +154,147 @@ impl IndexerCounters {
     pub fn num_invalid_docs(&self) -> u64 {
         self.num_parse_errors + self.num_missing_fields
     }
+
+    /// Adds the counters of another shard of the same pipeline. The watermarks are left
+    /// untouched, as the first shard receives the checkpoint deltas of all the batches.
+    pub fn add_shard_counters(&mut self, other: &IndexerCounters) {
+        self.num_parse_errors += other.num_parse_errors;
+        self.num_missing_fields += other.num_missing_fields;
+        self.num_valid_docs += other.num_valid_docs;
+        self.num_duplicate_docs += other.num_duplicate_docs;
+        self.num_routed_docs += other.num_routed_docs;
+        self.cpu_time_micros += other.cpu_time_micros;
+        self.num_splits_emitted += other.num_splits_emitted;
+        self.num_split_batches_emitted += other.num_split_batches_emitted;
+        self.num_evicted_splits += other.num_evicted_splits;
+        self.overall_num_bytes += other.overall_num_bytes;
+        self.num_docs_in_workbench += other.num_docs_in_workbench;
+        if let Some(other_workbench_preview) = &other.workbench_preview {
+            match &mut self.workbench_preview {
+                Some(workbench_preview) => {
+                    workbench_preview.add_shard_preview(other_workbench_preview)
+                }
+                None => self.workbench_preview = Some(other_workbench_preview.clone()),
+            }
+        }
+        if let Some(other_field_profile) = &other.field_profile {
+            self.field_profile
+                .get_or_insert_with(FieldProfile::default)
+                .merge(other_field_profile);
+        }
+    }
+}
+
+/// Preview of the workbench of an indexer: the checkpoint delta its commit will publish and the
+/// splits it is building, one per partition.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WorkbenchPreview {
+    pub workbench_id: String,
+    /// Checkpoint delta published along with the splits on commit.
+    pub checkpoint_delta: SourceCheckpointDelta,
+    /// Time elapsed since the creation of the workbench, or of the first workbench its partitions
+    /// were carried over from.
+    pub age_secs: u64,
+    pub num_docs: u64,
+    /// Estimated memory held by the index writers of the workbench.
+    pub estimated_memory_usage_in_bytes: u64,
+    /// Partitions of the workbench, sorted by partition ID.
+    pub partitions: Vec<WorkbenchPartitionPreview>,
+}
+
+impl WorkbenchPreview {
+    /// Adds the partitions of the workbench of another shard of the same pipeline. The checkpoint
+    /// delta is left untouched, as the first shard receives the checkpoint deltas of all the
+    /// batches.
+    fn add_shard_preview(&mut self, other: &WorkbenchPreview) {
+        self.age_secs = self.age_secs.max(other.age_secs);
+        self.num_docs += other.num_docs;
+        self.estimated_memory_usage_in_bytes += other.estimated_memory_usage_in_bytes;
+        self.partitions.extend(other.partitions.iter().cloned());
+        self.partitions
+            .sort_by_key(|partition| partition.partition_id);
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WorkbenchPartitionPreview {
+    pub partition_id: u64,
+    pub split_id: String,
+    pub num_docs: u64,
+    pub uncompressed_docs_size_in_bytes: u64,
+    /// Time elapsed since the split of the partition was opened. Partitions carried over keep
+    /// their age.
+    pub age_secs: u64,
 }
 
 struct IndexerState {
     pipeline_id: IndexingPipelineId,
     doc_mapper: Arc<dyn DocMapper>,
+    metastore: Arc<dyn Metastore>,
     indexing_directory: IndexingDirectory,
     indexing_settings: IndexingSettings,
     publish_lock: PublishLock,
     timestamp_field_opt: Option<Field>,
+    expire_timestamp_field_opt: Option<Field>,
     schema: Schema,
     index_settings: IndexSettings,
+    metrics: IndexerPipelineMetrics,
+    /// Receives the documents failing to parse, when a dead-letter queue is configured.
+    dead_letter_queue_opt: Option<DeadLetterQueue>,
+    /// Transforms of the source, applied to the documents before they are parsed.
+    doc_transformer_opt: Option<DocTransformer>,
+    /// Routes the documents of the tenants configured in the tenant routing settings to their
+    /// own index.
+    tenant_router_opt: Option<TenantRouter>,
+    /// Version of the doc mapping the documents are indexed with.
+    doc_mapping_version: u64,
+    /// Fingerprint of the doc mapping and the indexing settings the documents are indexed with.
+    config_fingerprint_opt: Option<String>,
 }
 
 enum PrepareDocumentOutcome {
-    ParsingError,
-    MissingField,
+    TransformError(DocTransformError),
+    Routed,
+    ParsingError(DocParsingError),
+    MissingField(DocParsingError),
     Document {
         document: Document,
         timestamp_opt: Option<i64>,
+        expire_timestamp_opt: Option<i64>,
         partition: u64,
     },
 }
 
 impl IndexerState {
+    /// Number of bloom filters a lookup in the deduplication window is expected to go through,
+    /// assuming that each of the `num_partitions` partitions emits a split per commit, and that
+    /// the digest of a split holds one filter per minute bucket.
+    fn num_dedup_window_filters(&self, num_partitions: usize) -> usize {
+        let window_secs = self
+            .indexing_settings
+            .deduplication
+            .as_ref()
+            .map(|deduplication_settings| deduplication_settings.window_secs)
+            .unwrap_or(0);
+        let bucket_duration_secs = DEDUP_BUCKET_DURATION_SECS as usize;
+        let num_buckets = window_secs as usize / bucket_duration_secs + 1;
+        // A commit overlapping two buckets yields a filter in each of them.
+        let num_commits_per_bucket =
+            bucket_duration_secs / self.indexing_settings.commit_timeout_secs.max(1) + 1;
+        num_buckets * num_commits_per_bucket * num_partitions.max(1)
+    }
+
     fn create_indexed_split(
         &self,
         partition_id: u64,
+        delete_opstamp: u64,
         ctx: &ActorContext<Indexer>,
     ) -> anyhow::Result<IndexedSplit> {
         let index_builder = IndexBuilder::new()
             .settings(self.index_settings.clone())
             .schema(self.schema.clone())
             .tokenizers(QUICKWIT_TOKENIZER_MANAGER.clone());
-        let indexed_split = IndexedSplit::new_in_dir(
+        let mut indexed_split = IndexedSplit::new_in_dir(
             self.pipeline_id.clone(),
             partition_id,
             self.indexing_directory.scratch_directory.clone(),
@@ -134,6 +303,9 @@ impl IndexerState {
             ctx.progress().clone(),
             ctx.kill_switch().clone(),
         )?;
+        indexed_split.split_attrs.delete_opstamp = delete_opstamp;
+        indexed_split.split_attrs.doc_mapping_version = self.doc_mapping_version;
+        indexed_split.split_attrs.config_fingerprint = self.config_fingerprint_opt.clone();
         info!(split_id = indexed_split.split_id(), "new-split");
         Ok(indexed_split)
     }
@@ -141,19 +313,32 @@ impl IndexerState {
     fn get_or_create_indexed_split<'a>(
         &self,
         partition_id: u64,
+        delete_opstamp: u64,
         splits: &'a mut FnvHashMap<u64, IndexedSplit>,
         ctx: &ActorContext<Indexer>,
     ) -> anyhow::Result<&'a mut IndexedSplit> {
         match splits.entry(partition_id) {
             Entry::Occupied(indexed_split) => Ok(indexed_split.into_mut()),
             Entry::Vacant(vacant_entry) => {
-                let indexed_split = self.create_indexed_split(partition_id, ctx)?;
+                let indexed_split = self.create_indexed_split(partition_id, delete_opstamp, ctx)?;
                 Ok(vacant_entry.insert(indexed_split))
             }
         }
     }
 
-    fn create_workbench(&self) -> anyhow::Result<IndexingWorkbench> {
+    async fn create_workbench(
+        &self,
+        ctx: &ActorContext<Indexer>,
+    ) -> anyhow::Result<IndexingWorkbench> {
+        // The documents of the workbench are indexed after all the delete tasks created so far,
+        // so its splits need not be touched by them.
+        let delete_opstamp = ctx
+            .protect_future(
+                self.metastore
+                    .last_delete_opstamp(&self.pipeline_id.index_id),
+            )
+            .await
+            .context("Failed to fetch the last delete opstamp.")?;
         let workbench = IndexingWorkbench {
             workbench_id: Ulid::new(),
             indexed_splits: FnvHashMap::with_capacity_and_hasher(250, Default::default()),
@@ -163,10 +348,41 @@ impl IndexerState {
             },
             publish_lock: self.publish_lock.clone(),
             date_of_birth: Instant::now(),
+            dedup_hashes: FnvHashMap::default(),
+            partition_last_doc_ords: FnvHashMap::default(),
+            partition_opened_ats: FnvHashMap::default(),
+            num_added_docs: 0,
+            carried_over_since_opt: None,
+            delete_opstamp,
         };
         Ok(workbench)
     }
 
+    /// Removes and returns the split of the least recently used partition of the workbench if
+    /// opening a split for `partition_id` would exceed the maximum number of open partitions.
+    fn pop_least_recently_used_split(
+        &self,
+        partition_id: u64,
+        indexed_splits: &mut FnvHashMap<u64, IndexedSplit>,
+        partition_last_doc_ords: &mut FnvHashMap<u64, u64>,
+    ) -> Option<IndexedSplit> {
+        let max_open_partitions = self.indexing_settings.max_open_partitions?;
+        if indexed_splits.len() < max_open_partitions || indexed_splits.contains_key(&partition_id)
+        {
+            return None;
+        }
+        // Partitions carried over from a previous workbench have no ordinal yet and are the least
+        // recently used ones.
+        let evicted_partition_id = *indexed_splits.keys().min_by_key(|partition_id| {
+            partition_last_doc_ords
+                .get(partition_id)
+                .copied()
+                .unwrap_or_default()
+        })?;
+        partition_last_doc_ords.remove(&evicted_partition_id);
+        indexed_splits.remove(&evicted_partition_id)
+    }
+
     /// Returns the current_indexed_split. If this is the first message, then
     /// the indexed_split does not exist yet.
     ///
@@ -177,7 +393,7 @@ impl IndexerState {
         ctx: &'a ActorContext<Indexer>,
     ) -> anyhow::Result<&'a mut IndexingWorkbench> {
         if indexing_workbench_opt.is_none() {
-            let indexing_workbench = self.create_workbench()?;
+            let indexing_workbench = self.create_workbench(ctx).await?;
             let commit_timeout_message = CommitTimeout {
                 workbench_id: indexing_workbench.workbench_id,
             };
@@ -194,19 +410,80 @@ impl IndexerState {
         Ok(current_indexing_workbench)
     }
 
-    fn prepare_document(&self, doc_json: String) -> PrepareDocumentOutcome {
+    /// Prepares a document for indexing. When `field_profile_opt` is set, the document is sampled
+    /// for field profiling, and its parse time and field costs are recorded in the profile.
+    fn prepare_document(
+        &self,
+        doc_json: String,
+        ingest_timestamp: i64,
+        field_profile_opt: Option<&mut FieldProfile>,
+    ) -> PrepareDocumentOutcome {
+        let doc_json = match &self.doc_transformer_opt {
+            Some(doc_transformer) => match doc_transformer.transform(&doc_json) {
+                Ok(transformed_doc_json) => transformed_doc_json,
+                Err(doc_transform_error) => {
+                    warn!(err=?doc_transform_error);
+                    return PrepareDocumentOutcome::TransformError(doc_transform_error);
+                }
+            },
+            None => doc_json,
+        };
+        // Routed documents are enriched by the pipeline of their tenant index.
+        if let Some(tenant_router) = &self.tenant_router_opt {
+            if tenant_router.route(&doc_json) {
+                return PrepareDocumentOutcome::Routed;
+            }
+        }
+        let doc_json = if let Some(enrichment_settings) = &self.indexing_settings.enrichment {
+            enrich_document(
+                doc_json,
+                &enrichment_settings.namespace,
+                &self.pipeline_id,
+                ingest_timestamp,
+            )
+        } else {
+            doc_json
+        };
         // Parse the document
-        let doc_parsing_result = self.doc_mapper.doc_from_json(doc_json);
+        let parse_start_opt = field_profile_opt.as_ref().map(|_| Instant::now());
+        let doc_parsing_result = self
+            .doc_mapper
+            .doc_from_json_with_defaulted_fields(doc_json);
         let (partition, document) = match doc_parsing_result {
-            Ok(doc) => doc,
+            Ok((partition, document, defaulted_field_names)) => {
+                if let (Some(field_profile), Some(parse_start)) =
+                    (field_profile_opt, parse_start_opt)
+                {
+                    let parse_duration = parse_start.elapsed();
+                    field_profile
+                        .record_doc(parse_duration, self.doc_mapper.field_costs(&document));
+                }
+                for defaulted_field_name in &defaulted_field_names {
+                    self.metrics
+                        .defaulted_fields_total(defaulted_field_name)
+                        .inc();
+                }
+                (partition, document)
+            }
             Err(doc_parsing_error) => {
                 warn!(err=?doc_parsing_error);
                 return match doc_parsing_error {
-                    DocParsingError::RequiredFastField(_) => PrepareDocumentOutcome::MissingField,
-                    _ => PrepareDocumentOutcome::ParsingError,
+                    DocParsingError::RequiredFastField(_) => {
+                        PrepareDocumentOutcome::MissingField(doc_parsing_error)
+                    }
+                    DocParsingError::InvalidIpAddr(_, _) => {
+                        self.metrics.invalid_ip_addrs_total.inc();
+                        PrepareDocumentOutcome::ParsingError(doc_parsing_error)
+                    }
+                    _ => PrepareDocumentOutcome::ParsingError(doc_parsing_error),
                 };
             }
         };
+        // Documents without expiry never expire.
+        let expire_timestamp_opt = self
+            .expire_timestamp_field_opt
+            .and_then(|expire_timestamp_field| document.get_first(expire_timestamp_field))
+            .and_then(timestamp_secs);
         // Extract timestamp if necessary
         let timestamp_field = if let Some(timestamp_field) = self.timestamp_field_opt {
             timestamp_field
@@ -215,15 +492,11 @@ impl IndexerState {
             return PrepareDocumentOutcome::Document {
                 document,
                 timestamp_opt: None,
+                expire_timestamp_opt,
                 partition,
             };
         };
-        let timestamp_opt = document
-            .get_first(timestamp_field)
-            .and_then(|value| match value {
-                Value::Date(date_time) => Some(date_time.into_timestamp_secs()),
-                value => value.as_i64(),
-            });
+        let timestamp_opt = document.get_first(timestamp_field).and_then(timestamp_secs);
         assert!(
             timestamp_opt.is_some(),
             "We should always have a timestamp here as doc parsing returns a `RequiredFastField` \
@@ -232,21 +505,39 @@ impl IndexerState {
         PrepareDocumentOutcome::Document {
             document,
             timestamp_opt,
+            expire_timestamp_opt,
             partition,
         }
     }
 
+    fn push_dead_letter(&self, doc_json_opt: Option<String>, error: &dyn fmt::Display) {
+        if let (Some(dead_letter_queue), Some(doc_json)) =
+            (&self.dead_letter_queue_opt, doc_json_opt)
+        {
+            dead_letter_queue.push(&doc_json, &error.to_string());
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     async fn process_batch(
         &self,
         batch: RawDocBatch,
         indexing_workbench_opt: &mut Option<IndexingWorkbench>,
+        dedup_window_opt: &mut Option<DedupWindow>,
         counters: &mut IndexerCounters,
+        packager_mailbox: &Mailbox<Packager>,
         ctx: &ActorContext<Indexer>,
     ) -> Result<(), ActorExitStatus> {
         let IndexingWorkbench {
             checkpoint_delta,
             indexed_splits,
             publish_lock,
+            date_of_birth,
+            dedup_hashes,
+            partition_last_doc_ords,
+            partition_opened_ats,
+            num_added_docs,
+            delete_opstamp,
             ..
         } = self
             .get_or_create_workbench(indexing_workbench_opt, ctx)
@@ -254,38 +545,153 @@ impl IndexerState {
         if publish_lock.is_dead() {
             return Ok(());
         }
+        counters
+            .read_watermark
+            .advance_with_delta(&batch.checkpoint_delta);
         checkpoint_delta
             .source_delta
             .extend(batch.checkpoint_delta)
             .context("Batch delta does not follow indexer checkpoint")?;
+        let now_timestamp = OffsetDateTime::now_utc().unix_timestamp();
         for doc_json in batch.docs {
             let doc_json_num_bytes = doc_json.len() as u64;
             counters.overall_num_bytes += doc_json_num_bytes;
+            self.metrics
+                .processed_bytes_total
+                .inc_by(doc_json_num_bytes);
+            let doc_hash_opt = dedup_window_opt
+                .as_ref()
+                .and_then(|dedup_window| dedup_window.doc_hash(&doc_json));
+            if let (Some(dedup_window), Some(doc_hash)) = (dedup_window_opt.as_ref(), doc_hash_opt)
+            {
+                if dedup_window.contains(doc_hash) {
+                    counters.num_duplicate_docs += 1;
+                    self.metrics.duplicate_docs_total.inc();
+                    ctx.record_progress();
+                    continue;
+                }
+            }
+            // The document is consumed by the parsing: it is only copied for the dead-letter
+            // queue, if any.
+            let dead_letter_doc_opt = self
+                .dead_letter_queue_opt
+                .as_ref()
+                .map(|_| doc_json.clone());
+            let field_profile_opt = match &self.indexing_settings.field_profiling {
+                Some(field_profiling_settings)
+                    if counters.num_processed_docs() % field_profiling_settings.sample_interval
+                        == 0 =>
+                {
+                    Some(
+                        counters
+                            .field_profile
+                            .get_or_insert_with(FieldProfile::default),
+                    )
+                }
+                _ => None,
+            };
             let prepared_doc = {
                 let _protect_zone = ctx.protect_zone();
-                self.prepare_document(doc_json)
+                self.prepare_document(doc_json, now_timestamp, field_profile_opt)
             };
             match prepared_doc {
-                PrepareDocumentOutcome::ParsingError => {
+                PrepareDocumentOutcome::Routed => {
+                    counters.num_routed_docs += 1;
+                }
+                PrepareDocumentOutcome::TransformError(doc_transform_error) => {
                     counters.num_parse_errors += 1;
+                    self.metrics.parse_errors_total.inc();
+                    self.push_dead_letter(dead_letter_doc_opt, &doc_transform_error);
                 }
-                PrepareDocumentOutcome::MissingField => {
+                PrepareDocumentOutcome::ParsingError(doc_parsing_error) => {
+                    counters.num_parse_errors += 1;
+                    self.metrics.parse_errors_total.inc();
+                    self.push_dead_letter(dead_letter_doc_opt, &doc_parsing_error);
+                }
+                PrepareDocumentOutcome::MissingField(doc_parsing_error) => {
                     counters.num_missing_fields += 1;
+                    self.metrics.missing_fields_total.inc();
+                    self.push_dead_letter(dead_letter_doc_opt, &doc_parsing_error);
                 }
                 PrepareDocumentOutcome::Document {
                     document,
                     timestamp_opt,
+                    expire_timestamp_opt,
                     partition,
                 } => {
-                    let indexed_split =
-                        self.get_or_create_indexed_split(partition, indexed_splits, ctx)?;
+                    if let Some(mut evicted_split) = self.pop_least_recently_used_split(
+                        partition,
+                        indexed_splits,
+                        partition_last_doc_ords,
+                    ) {
+                        attach_dedup_digests(
+                            std::slice::from_mut(&mut evicted_split),
+                            dedup_hashes,
+                            self.num_dedup_window_filters(indexed_splits.len() + 1),
+                        );
+                        partition_opened_ats.remove(&evicted_split.split_attrs.partition_id);
+                        counters.num_docs_in_workbench -= evicted_split.split_attrs.num_docs;
+                        counters.num_splits_emitted += 1;
+                        counters.num_split_batches_emitted += 1;
+                        counters.num_evicted_splits += 1;
+                        info!(
+                            split_id = evicted_split.split_id(),
+                            partition_id = evicted_split.split_attrs.partition_id,
+                            "evict-split"
+                        );
+                        // The evicted split is uploaded right away but held back by the publisher
+                        // until the checkpoint delta of the commit, which covers its documents.
+                        ctx.send_message(
+                            packager_mailbox,
+                            IndexedSplitBatch {
+                                splits: vec![evicted_split],
+                                checkpoint_delta: None,
+                                publish_with_next_delta: true,
+                                publish_lock: publish_lock.clone(),
+                                date_of_birth: *date_of_birth,
+                            },
+                        )
+                        .await?;
+                    }
+                    *num_added_docs += 1;
+                    partition_last_doc_ords.insert(partition, *num_added_docs);
+                    partition_opened_ats
+                        .entry(partition)
+                        .or_insert_with(Instant::now);
+                    let indexed_split = self.get_or_create_indexed_split(
+                        partition,
+                        *delete_opstamp,
+                        indexed_splits,
+                        ctx,
+                    )?;
                     indexed_split.split_attrs.uncompressed_docs_size_in_bytes += doc_json_num_bytes;
                     counters.num_docs_in_workbench += 1;
                     counters.num_valid_docs += 1;
+                    self.metrics.valid_docs_total.inc();
                     indexed_split.split_attrs.num_docs += 1;
                     if let Some(timestamp) = timestamp_opt {
                         record_timestamp(timestamp, &mut indexed_split.split_attrs.time_range);
                     }
+                    if let Some(expire_timestamp) = expire_timestamp_opt {
+                        let min_expire_timestamp = indexed_split
+                            .split_attrs
+                            .min_expire_timestamp
+                            .map_or(expire_timestamp, |min_expire_timestamp| {
+                                min_expire_timestamp.min(expire_timestamp)
+                            });
+                        indexed_split.split_attrs.min_expire_timestamp = Some(min_expire_timestamp);
+                    }
+                    if let (Some(dedup_window), Some(doc_hash)) =
+                        (dedup_window_opt.as_mut(), doc_hash_opt)
+                    {
+                        dedup_window.insert(doc_hash, now_timestamp);
+                        dedup_hashes
+                            .entry(partition)
+                            .or_default()
+                            .entry(bucket_start_timestamp(now_timestamp))
+                            .or_default()
+                            .push(doc_hash);
+                    }
                     let _protect_guard = ctx.protect_zone();
                     indexed_split
                         .index_writer
@@ -299,6 +705,29 @@ impl IndexerState {
     }
 }
 
+/// Stamps the document with the context of the pipeline indexing it under the `namespace` key.
+///
+/// Documents that are not JSON objects are returned untouched: the doc mapper will reject them.
+fn enrich_document(
+    doc_json: String,
+    namespace: &str,
+    pipeline_id: &IndexingPipelineId,
+    ingest_timestamp: i64,
+) -> String {
+    let mut json_obj: serde_json::Map<String, JsonValue> = match serde_json::from_str(&doc_json) {
+        Ok(json_obj) => json_obj,
+        Err(_) => return doc_json,
+    };
+    let pipeline_context = json!({
+        "node_id": pipeline_id.node_id,
+        "pipeline_ord": pipeline_id.pipeline_ord,
+        "source_id": pipeline_id.source_id,
+        "ingest_timestamp": ingest_timestamp,
+    });
+    json_obj.insert(namespace.to_string(), pipeline_context);
+    serde_json::to_string(&json_obj).unwrap_or(doc_json)
+}
+
 /// A workbench hosts the set of `IndexedSplit` that will are being built.
 struct IndexingWorkbench {
     workbench_id: Ulid,
@@ -310,14 +739,102 @@ struct IndexingWorkbench {
     // does not include the amount of time a document could have been
     // staying in the indexer queue or in the push api queue.
     date_of_birth: Instant,
+    /// Hashes of the IDs of the documents added to the splits of the workbench, per partition and
+    /// time bucket.
+    dedup_hashes: FnvHashMap<u64, BTreeMap<i64, Vec<u64>>>,
+    /// Ordinal of the last document added to each partition, used to find the least recently used
+    /// partition when the number of open partitions is capped.
+    partition_last_doc_ords: FnvHashMap<u64, u64>,
+    /// Instant at which the split of each partition was opened.
+    partition_opened_ats: FnvHashMap<u64, Instant>,
+    /// Number of documents added to the workbench, evicted splits included.
+    num_added_docs: u64,
+    /// Instant at which the first partitions were carried over from a previous workbench to this
+    /// workbench, if any.
+    carried_over_since_opt: Option<Instant>,
+    /// Opstamp of the last delete task at the creation of the workbench.
+    delete_opstamp: u64,
+}
+
+impl IndexingWorkbench {
+    /// Estimates the memory held by the index writers of the workbench. The memory arena of an
+    /// index writer grows with the documents it indexes until it reaches the writer heap size, at
+    /// which point it is flushed to a new segment.
+    fn estimated_memory_usage_in_bytes(&self, heap_size_in_bytes: u64) -> u64 {
+        self.indexed_splits
+            .values()
+            .map(|split| {
+                split
+                    .split_attrs
+                    .uncompressed_docs_size_in_bytes
+                    .min(heap_size_in_bytes)
+            })
+            .sum()
+    }
+
+    fn preview(&self, heap_size_in_bytes: u64) -> WorkbenchPreview {
+        let mut partitions: Vec<WorkbenchPartitionPreview> = self
+            .indexed_splits
+            .values()
+            .map(|split| {
+                let partition_id = split.split_attrs.partition_id;
+                let age_secs = self
+                    .partition_opened_ats
+                    .get(&partition_id)
+                    .map(|opened_at| opened_at.elapsed().as_secs())
+                    .unwrap_or_default();
+                WorkbenchPartitionPreview {
+                    partition_id,
+                    split_id: split.split_attrs.split_id.clone(),
+                    num_docs: split.split_attrs.num_docs,
+                    uncompressed_docs_size_in_bytes: split
+                        .split_attrs
+                        .uncompressed_docs_size_in_bytes,
+                    age_secs,
+                }
+            })
+            .collect();
+        partitions.sort_by_key(|partition| partition.partition_id);
+        WorkbenchPreview {
+            workbench_id: self.workbench_id.to_string(),
+            checkpoint_delta: self.checkpoint_delta.source_delta.clone(),
+            age_secs: self.date_of_birth.elapsed().as_secs(),
+            num_docs: partitions.iter().map(|partition| partition.num_docs).sum(),
+            estimated_memory_usage_in_bytes: self
+                .estimated_memory_usage_in_bytes(heap_size_in_bytes),
+            partitions,
+        }
+    }
 }
 
 pub struct Indexer {
     indexer_state: IndexerState,
     packager_mailbox: Mailbox<Packager>,
     indexing_workbench_opt: Option<IndexingWorkbench>,
-    metastore: Arc<dyn Metastore>,
+    /// Loaded lazily when deduplication is enabled, and reloaded after a new publish lock as the
+    /// documents of the dropped workbench will be redelivered.
+    dedup_window_opt: Option<DedupWindow>,
     counters: IndexerCounters,
+    /// Counters as of the last commit, used to compute the share of invalid documents per commit.
+    counters_at_last_commit: IndexerCounters,
+    /// Number of docs in the workbench last reported to the workbench gauge, which is shared by
+    /// the pipelines of the same source.
+    reported_workbench_num_docs: i64,
+    warning_sink: PipelineWarningSink,
+    /// Set for the shard indexers of a sharded pipeline, which only commit on the demand of the
+    /// router.
+    shard_ord_opt: Option<usize>,
+    /// Set for the shard indexers of a sharded pipeline, which report to the router when their
+    /// workbench exceeds the workbench memory limit.
+    memory_limit_reports_opt: Option<MemoryLimitReports>,
+    /// ID of the last workbench reported to the router, so that a workbench is reported once.
+    memory_limit_reported_workbench_id_opt: Option<Ulid>,
+    /// Set for the indexer routing the documents of a sharded pipeline to the shard indexers.
+    doc_router_opt: Option<DocRouter>,
+    /// Rate limiter of the source, debited with the batches received from the source.
+    source_rate_limiter_opt: Option<SourceRateLimiter>,
+    /// Scheduler sharing the indexing time of the node between the indexes.
+    fair_scheduler_opt: Option<Arc<FairScheduler>>,
 }
 
 #[async_trait]
@@ -325,11 +842,27 @@ impl Actor for Indexer {
     type ObservableState = IndexerCounters;
 
     fn observable_state(&self) -> Self::ObservableState {
-        self.counters.clone()
+        let heap_size_in_bytes = self
+            .indexer_state
+            .indexing_settings
+            .resources
+            .heap_size
+            .get_bytes();
+        IndexerCounters {
+            workbench_preview: self
+                .indexing_workbench_opt
+                .as_ref()
+                .map(|indexing_workbench| indexing_workbench.preview(heap_size_in_bytes)),
+            ..self.counters.clone()
+        }
     }
 
     fn queue_capacity(&self) -> QueueCapacity {
-        QueueCapacity::Bounded(10)
+        QueueCapacity::Bounded(
+            self.indexer_state
+                .indexing_settings
+                .indexer_queue_capacity(),
+        )
     }
 
     fn name(&self) -> String {
@@ -345,6 +878,12 @@ impl Actor for Indexer {
         exit_status: &ActorExitStatus,
         ctx: &ActorContext<Self>,
     ) -> anyhow::Result<()> {
+        // The workbench is either committed or dropped: either way, its docs leave the gauge.
+        self.indexer_state
+            .metrics
+            .workbench_num_docs
+            .sub(self.reported_workbench_num_docs);
+        self.reported_workbench_num_docs = 0;
         match exit_status {
             ActorExitStatus::DownstreamClosed
             | ActorExitStatus::Killed
@@ -359,6 +898,14 @@ impl Actor for Indexer {
     }
 }
 
+/// Returns the timestamp in seconds held by an `i64` or `datetime` field value.
+fn timestamp_secs(value: &Value) -> Option<i64> {
+    match value {
+        Value::Date(date_time) => Some(date_time.into_timestamp_secs()),
+        value => value.as_i64(),
+    }
+}
+
 fn record_timestamp(timestamp: i64, time_range: &mut Option<RangeInclusive<i64>>) {
     let new_timestamp_range = match time_range.as_ref() {
         Some(range) => {
@@ -378,6 +925,13 @@ impl Handler<CommitTimeout> for Indexer {
         commit_timeout: CommitTimeout,
         ctx: &ActorContext<Self>,
     ) -> Result<(), ActorExitStatus> {
+        if let Some(doc_router) = &mut self.doc_router_opt {
+            return doc_router.handle_commit_timeout(commit_timeout, ctx).await;
+        }
+        if self.shard_ord_opt.is_some() {
+            // Shards commit on the demand of the router only.
+            return Ok(());
+        }
         if let Some(indexing_workbench) = &self.indexing_workbench_opt {
             // If this is a timeout for a different workbench, we must ignore it.
             if indexing_workbench.workbench_id != commit_timeout.workbench_id {
@@ -385,6 +939,7 @@ impl Handler<CommitTimeout> for Indexer {
             }
         }
         self.send_to_packager(CommitTrigger::Timeout, ctx).await?;
+        self.report_workbench_num_docs();
         Ok(())
     }
 }
@@ -402,6 +957,22 @@ impl Handler<RawDocBatch> for Indexer {
     }
 }
 
+#[async_trait]
+impl Handler<CommitShard> for Indexer {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        commit_shard: CommitShard,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.send_to_packager(commit_shard.commit_trigger, ctx)
+            .await?;
+        self.report_workbench_num_docs();
+        Ok(())
+    }
+}
+
 #[async_trait]
 impl Handler<NewPublishLock> for Indexer {
     type Reply = ();
@@ -409,20 +980,38 @@ impl Handler<NewPublishLock> for Indexer {
     async fn handle(
         &mut self,
         message: NewPublishLock,
-        _ctx: &ActorContext<Self>,
+        ctx: &ActorContext<Self>,
     ) -> Result<(), ActorExitStatus> {
         let NewPublishLock(publish_lock) = message;
+        if let Some(doc_router) = &mut self.doc_router_opt {
+            doc_router
+                .forward_publish_lock(publish_lock.clone(), ctx)
+                .await?;
+        }
         self.indexing_workbench_opt = None;
+        self.dedup_window_opt = None;
         self.indexer_state.publish_lock = publish_lock;
         Ok(())
     }
 }
 
 #[derive(Clone, Copy, Debug)]
-enum CommitTrigger {
+pub(crate) enum CommitTrigger {
     Timeout,
     NoMoreDocs,
     NumDocsLimit,
+    MemoryLimit,
+}
+
+impl CommitTrigger {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CommitTrigger::Timeout => "timeout",
+            CommitTrigger::NoMoreDocs => "no_more_docs",
+            CommitTrigger::NumDocsLimit => "num_docs_limit",
+            CommitTrigger::MemoryLimit => "memory_limit",
+        }
+    }
 }
 
 impl Indexer {
@@ -433,9 +1022,11 @@ impl Indexer {
         indexing_directory: IndexingDirectory,
         indexing_settings: IndexingSettings,
         packager_mailbox: Mailbox<Packager>,
+        warning_sink: PipelineWarningSink,
     ) -> Self {
         let schema = doc_mapper.schema();
         let timestamp_field_opt = doc_mapper.timestamp_field(&schema);
+        let expire_timestamp_field_opt = doc_mapper.expire_timestamp_field(&schema);
         let sort_by_field_opt = match indexing_settings.sort_by() {
             SortBy::DocId | SortBy::Score { .. } => None,
             SortBy::FastField { field_name, order } => Some(IndexSortByField {
@@ -452,21 +1043,189 @@ impl Indexer {
             }),
         };
         let publish_lock = PublishLock::default();
+        let metrics = IndexerPipelineMetrics::new(&pipeline_id);
         Self {
             indexer_state: IndexerState {
                 pipeline_id,
                 doc_mapper,
+                metastore,
                 indexing_directory,
                 indexing_settings,
                 publish_lock,
                 timestamp_field_opt,
+                expire_timestamp_field_opt,
                 schema,
                 index_settings,
+                metrics,
+                dead_letter_queue_opt: None,
+                doc_transformer_opt: None,
+                tenant_router_opt: None,
+                doc_mapping_version: 0,
+                config_fingerprint_opt: None,
             },
             packager_mailbox,
             indexing_workbench_opt: None,
-            metastore,
+            dedup_window_opt: None,
             counters: IndexerCounters::default(),
+            counters_at_last_commit: IndexerCounters::default(),
+            reported_workbench_num_docs: 0,
+            warning_sink,
+            shard_ord_opt: None,
+            memory_limit_reports_opt: None,
+            memory_limit_reported_workbench_id_opt: None,
+            doc_router_opt: None,
+            source_rate_limiter_opt: None,
+            fair_scheduler_opt: None,
+        }
+    }
+
+    /// Records the batches received from the source in the rate limiter of the source.
+    pub fn with_source_rate_limiter(mut self, source_rate_limiter: SourceRateLimiter) -> Self {
+        self.source_rate_limiter_opt = Some(source_rate_limiter);
+        self
+    }
+
+    /// Writes the documents failing to parse to the dead-letter queue.
+    pub fn with_dead_letter_queue(mut self, dead_letter_queue: DeadLetterQueue) -> Self {
+        self.indexer_state.dead_letter_queue_opt = Some(dead_letter_queue);
+        self
+    }
+
+    /// Applies the transforms of the source to the documents before parsing them. The documents
+    /// failing to be transformed are rejected like the documents failing to parse.
+    pub fn with_doc_transformer(mut self, doc_transformer: DocTransformer) -> Self {
+        self.indexer_state.doc_transformer_opt = Some(doc_transformer);
+        self
+    }
+
+    /// Routes the documents of the tenants configured in the tenant routing settings of the index
+    /// to their own index instead of indexing them.
+    pub fn with_tenant_router(mut self, tenant_router: TenantRouter) -> Self {
+        self.indexer_state.tenant_router_opt = Some(tenant_router);
+        self
+    }
+
+    /// Waits for a permit of the fair scheduler of the node before indexing each batch.
+    pub fn with_fair_scheduler(mut self, fair_scheduler: Arc<FairScheduler>) -> Self {
+        self.fair_scheduler_opt = Some(fair_scheduler);
+        self
+    }
+
+    /// Records the version of the doc mapping in the attributes of the splits.
+    pub fn with_doc_mapping_version(mut self, doc_mapping_version: u64) -> Self {
+        self.indexer_state.doc_mapping_version = doc_mapping_version;
+        self
+    }
+
+    /// Records the fingerprint of the doc mapping and the indexing settings in the attributes of
+    /// the splits.
+    pub fn with_config_fingerprint(mut self, config_fingerprint: String) -> Self {
+        self.indexer_state.config_fingerprint_opt = Some(config_fingerprint);
+        self
+    }
+
+    /// Turns the indexer into the shard `shard_ord` of a sharded pipeline. Shards emit a
+    /// [`ShardSplitBatch`] on every commit, even when empty, and never commit on their own: they
+    /// report the workbenches exceeding the workbench memory limit to the router instead.
+    pub(crate) fn into_shard(
+        mut self,
+        shard_ord: usize,
+        memory_limit_reports: MemoryLimitReports,
+    ) -> Self {
+        self.shard_ord_opt = Some(shard_ord);
+        self.memory_limit_reports_opt = Some(memory_limit_reports);
+        self
+    }
+
+    /// Turns the indexer into the router of a sharded pipeline, which routes the documents to the
+    /// shards instead of indexing them.
+    pub(crate) fn into_router(mut self, doc_router: DocRouter) -> Self {
+        self.doc_router_opt = Some(doc_router);
+        self
+    }
+
+    fn is_workbench_memory_limit_exceeded(&self) -> bool {
+        let resources = &self.indexer_state.indexing_settings.resources;
+        let workbench_memory_limit = match resources.workbench_memory_limit {
+            Some(workbench_memory_limit) => workbench_memory_limit.get_bytes(),
+            None => return false,
+        };
+        self.indexing_workbench_opt
+            .as_ref()
+            .map(|indexing_workbench| {
+                indexing_workbench.estimated_memory_usage_in_bytes(resources.heap_size.get_bytes())
+            })
+            .unwrap_or(0)
+            >= workbench_memory_limit
+    }
+
+    /// Reports to the router of a sharded pipeline that the workbench of the shard exceeds the
+    /// workbench memory limit, once per workbench.
+    fn report_memory_limit_exceeded(&mut self) {
+        let workbench_id_opt = self
+            .indexing_workbench_opt
+            .as_ref()
+            .map(|indexing_workbench| indexing_workbench.workbench_id);
+        if workbench_id_opt.is_none()
+            || workbench_id_opt == self.memory_limit_reported_workbench_id_opt
+        {
+            return;
+        }
+        if let Some(memory_limit_reports) = &self.memory_limit_reports_opt {
+            memory_limit_reports.report();
+        }
+        self.memory_limit_reported_workbench_id_opt = workbench_id_opt;
+    }
+
+    /// Reports the number of docs in the workbench to the workbench gauge as a delta.
+    fn report_workbench_num_docs(&mut self) {
+        let num_docs_in_workbench = self.counters.num_docs_in_workbench as i64;
+        self.indexer_state
+            .metrics
+            .workbench_num_docs
+            .add(num_docs_in_workbench - self.reported_workbench_num_docs);
+        self.reported_workbench_num_docs = num_docs_in_workbench;
+    }
+
+    /// Warns if the share of documents missing their timestamp or failing to parse since the
+    /// last commit is too high.
+    fn check_invalid_docs(&mut self) {
+        let num_docs =
+            self.counters.num_processed_docs() - self.counters_at_last_commit.num_processed_docs();
+        let num_missing_fields =
+            self.counters.num_missing_fields - self.counters_at_last_commit.num_missing_fields;
+        let num_parse_errors =
+            self.counters.num_parse_errors - self.counters_at_last_commit.num_parse_errors;
+        self.counters_at_last_commit = self.counters.clone();
+        if num_docs < MIN_NUM_DOCS_FOR_INVALID_DOCS_WARNING {
+            return;
+        }
+        for (kind, num_invalid_docs, reason) in [
+            (
+                PipelineWarningKind::MissingTimestamp,
+                num_missing_fields,
+                "missing the timestamp field",
+            ),
+            (
+                PipelineWarningKind::InvalidDocs,
+                num_parse_errors,
+                "failing to parse",
+            ),
+        ] {
+            let ratio = num_invalid_docs as f64 / num_docs as f64;
+            if ratio >= INVALID_DOCS_WARNING_RATIO {
+                self.warning_sink.warn(
+                    kind,
+                    "Indexer",
+                    format!(
+                        "{}% of the docs of the last commit ({}/{}) were {}.",
+                        (ratio * 100.0).round(),
+                        num_invalid_docs,
+                        num_docs,
+                        reason
+                    ),
+                );
+            }
         }
     }
 
@@ -476,49 +1235,260 @@ impl Indexer {
         ctx: &ActorContext<Self>,
     ) -> Result<(), ActorExitStatus> {
         fail_point!("indexer:batch:before");
+        if let Some(source_rate_limiter) = &self.source_rate_limiter_opt {
+            let num_bytes: usize = batch.docs.iter().map(String::len).sum();
+            source_rate_limiter.record_batch(batch.docs.len() as u64, num_bytes as u64);
+        }
+        if let Some(doc_router) = &mut self.doc_router_opt {
+            self.counters
+                .read_watermark
+                .advance_with_delta(&batch.checkpoint_delta);
+            return doc_router.route_batch(batch, ctx).await;
+        }
+        self.load_dedup_window_if_required(ctx).await?;
+        let fair_scheduler_permit_opt = match &self.fair_scheduler_opt {
+            Some(fair_scheduler) => {
+                let index_id = &self.indexer_state.pipeline_id.index_id;
+                let weight = self.indexer_state.indexing_settings.scheduling_weight();
+                Some(
+                    ctx.protect_future(fair_scheduler.acquire_permit(index_id, weight))
+                        .await,
+                )
+            }
+            None => None,
+        };
+        let start = Instant::now();
         self.indexer_state
             .process_batch(
                 batch,
                 &mut self.indexing_workbench_opt,
+                &mut self.dedup_window_opt,
                 &mut self.counters,
+                &self.packager_mailbox,
                 ctx,
             )
             .await?;
-        if self.counters.num_docs_in_workbench
+        // Hands the slot over to the next batch before committing.
+        drop(fair_scheduler_permit_opt);
+        let cpu_time_micros = start.elapsed().as_micros() as u64;
+        self.counters.cpu_time_micros += cpu_time_micros;
+        self.indexer_state
+            .metrics
+            .cpu_time_micros_total
+            .inc_by(cpu_time_micros);
+        if self.shard_ord_opt.is_some() {
+            // Shards commit on the demand of the router, which commits all the shards when one of
+            // them exceeds the memory limit.
+            if self.is_workbench_memory_limit_exceeded() {
+                self.report_memory_limit_exceeded();
+            }
+        } else if self.counters.num_docs_in_workbench
             >= self.indexer_state.indexing_settings.split_num_docs_target as u64
         {
             self.send_to_packager(CommitTrigger::NumDocsLimit, ctx)
                 .await?;
+        } else if self.is_workbench_memory_limit_exceeded() {
+            self.send_to_packager(CommitTrigger::MemoryLimit, ctx)
+                .await?;
+        }
+        if self
+            .indexer_state
+            .dead_letter_queue_opt
+            .as_ref()
+            .map(DeadLetterQueue::is_full)
+            .unwrap_or(false)
+        {
+            self.flush_dead_letter_queue(ctx).await;
+        }
+        if self
+            .indexer_state
+            .tenant_router_opt
+            .as_ref()
+            .map(TenantRouter::is_full)
+            .unwrap_or(false)
+        {
+            self.flush_tenant_router(ctx).await?;
         }
+        self.report_workbench_num_docs();
         fail_point!("indexer:batch:after");
         Ok(())
     }
 
+    async fn load_dedup_window_if_required(
+        &mut self,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<()> {
+        let deduplication_settings = match &self.indexer_state.indexing_settings.deduplication {
+            Some(deduplication_settings) if self.dedup_window_opt.is_none() => {
+                deduplication_settings
+            }
+            _ => return Ok(()),
+        };
+        let pipeline_id = &self.indexer_state.pipeline_id;
+        let dedup_window = ctx
+            .protect_future(DedupWindow::load(
+                deduplication_settings,
+                &*self.indexer_state.metastore,
+                &pipeline_id.index_id,
+                &pipeline_id.source_id,
+                OffsetDateTime::now_utc().unix_timestamp(),
+            ))
+            .await
+            .context("Failed to load the deduplication window.")?;
+        self.dedup_window_opt = Some(dedup_window);
+        Ok(())
+    }
+
+    /// Writes the documents buffered in the dead-letter queue. A failure to write them does not
+    /// fail the indexer: the documents are dropped and a warning is raised.
+    async fn flush_dead_letter_queue(&self, ctx: &ActorContext<Self>) {
+        let dead_letter_queue = match &self.indexer_state.dead_letter_queue_opt {
+            Some(dead_letter_queue) => dead_letter_queue,
+            None => return,
+        };
+        if let Err(error) = ctx.protect_future(dead_letter_queue.flush()).await {
+            warn!(error=?error, "Failed to write to the dead-letter queue.");
+            self.warning_sink.warn(
+                PipelineWarningKind::DeadLetterQueueFailure,
+                "Indexer",
+                format!(
+                    "Failed to write the documents failing to parse to the dead-letter queue: {}",
+                    error
+                ),
+            );
+        }
+    }
+
+    /// Appends the documents buffered in the tenant router to the queues of their tenant index.
+    /// Unlike the dead-letter queue, a failure fails the indexer, so that the documents are read
+    /// again from the last published checkpoint.
+    async fn flush_tenant_router(&self, ctx: &ActorContext<Self>) -> anyhow::Result<()> {
+        if let Some(tenant_router) = &self.indexer_state.tenant_router_opt {
+            ctx.protect_future(tenant_router.flush())
+                .await
+                .context("Failed to route the documents to their tenant index.")?;
+        }
+        Ok(())
+    }
+
     /// Extract the indexed split and send it to the Packager.
     async fn send_to_packager(
         &mut self,
         commit_trigger: CommitTrigger,
         ctx: &ActorContext<Self>,
     ) -> anyhow::Result<()> {
+        // The invalid documents of the commit are written before its checkpoint is published.
+        self.flush_dead_letter_queue(ctx).await;
+        // Likewise for the documents routed to the tenant indexes.
+        self.flush_tenant_router(ctx).await?;
         let IndexingWorkbench {
             indexed_splits,
             checkpoint_delta,
             publish_lock,
             date_of_birth,
+            mut dedup_hashes,
+            partition_opened_ats,
+            carried_over_since_opt,
+            delete_opstamp,
             ..
         } = if let Some(indexing_workbench) = self.indexing_workbench_opt.take() {
             indexing_workbench
         } else {
+            if let Some(shard_ord) = self.shard_ord_opt {
+                // The packager pairs up the batches of the shards: shards emit a batch on every
+                // commit.
+                let batch = IndexedSplitBatch {
+                    splits: Vec::new(),
+                    checkpoint_delta: None,
+                    publish_with_next_delta: false,
+                    publish_lock: self.indexer_state.publish_lock.clone(),
+                    date_of_birth: Instant::now(),
+                };
+                ctx.send_message(&self.packager_mailbox, ShardSplitBatch { shard_ord, batch })
+                    .await?;
+            }
             return Ok(());
         };
+        self.check_invalid_docs();
+        let pipeline_id = &self.indexer_state.pipeline_id;
+        INDEXER_METRICS
+            .commits_total
+            .with_label_values(&[
+                &pipeline_id.index_id,
+                &pipeline_id.source_id,
+                commit_trigger.as_str(),
+            ])
+            .inc();
+        self.indexer_state
+            .metrics
+            .split_build_duration_secs
+            .observe(date_of_birth.elapsed().as_secs_f64());
 
-        let splits: Vec<IndexedSplit> = indexed_splits.into_values().collect();
+        let mut splits: Vec<IndexedSplit> = indexed_splits.into_values().collect();
+        if let Some(split_carry_over_settings) =
+            self.split_carry_over_settings_opt(commit_trigger, carried_over_since_opt)
+        {
+            let (carried_over_splits, emitted_splits): (Vec<IndexedSplit>, Vec<IndexedSplit>) =
+                splits.into_iter().partition(|split| {
+                    split_carry_over_settings.is_below_threshold(
+                        split.split_attrs.num_docs,
+                        split.split_attrs.uncompressed_docs_size_in_bytes,
+                    )
+                });
+            splits = emitted_splits;
+            if !carried_over_splits.is_empty() {
+                let mut carried_over_dedup_hashes = FnvHashMap::default();
+                let mut carried_over_opened_ats = FnvHashMap::default();
+                for split in &carried_over_splits {
+                    let partition_id = split.split_attrs.partition_id;
+                    if let Some(buckets) = dedup_hashes.remove(&partition_id) {
+                        carried_over_dedup_hashes.insert(partition_id, buckets);
+                    }
+                    if let Some(opened_at) = partition_opened_ats.get(&partition_id) {
+                        carried_over_opened_ats.insert(partition_id, *opened_at);
+                    }
+                }
+                let carried_over_workbench = IndexingWorkbench {
+                    workbench_id: Ulid::new(),
+                    indexed_splits: carried_over_splits
+                        .into_iter()
+                        .map(|split| (split.split_attrs.partition_id, split))
+                        .collect(),
+                    checkpoint_delta,
+                    publish_lock: publish_lock.clone(),
+                    date_of_birth,
+                    dedup_hashes: carried_over_dedup_hashes,
+                    partition_last_doc_ords: FnvHashMap::default(),
+                    partition_opened_ats: carried_over_opened_ats,
+                    num_added_docs: 0,
+                    carried_over_since_opt: Some(
+                        carried_over_since_opt.unwrap_or_else(Instant::now),
+                    ),
+                    delete_opstamp,
+                };
+                return self
+                    .carry_over(
+                        commit_trigger,
+                        carried_over_workbench,
+                        splits,
+                        dedup_hashes,
+                        ctx,
+                    )
+                    .await;
+            }
+        }
+        let num_dedup_window_filters = self.indexer_state.num_dedup_window_filters(splits.len());
+        attach_dedup_digests(&mut splits, &mut dedup_hashes, num_dedup_window_filters);
+        self.counters
+            .indexed_watermark
+            .advance_with_delta(&checkpoint_delta.source_delta);
 
         // Avoid producing empty split, but still update the checkpoint to avoid
-        // reprocessing the same faulty documents.
-        if splits.is_empty() {
+        // reprocessing the same faulty documents. Shards leave it to the packager, which publishes
+        // the checkpoint deltas of the commits yielding no split.
+        if splits.is_empty() && self.shard_ord_opt.is_none() {
             if let Some(_guard) = publish_lock.acquire().await {
-                ctx.protect_future(self.metastore.publish_splits(
+                ctx.protect_future(self.indexer_state.metastore.publish_splits(
                     &self.indexer_state.pipeline_id.index_id,
                     &[],
                     &[],
@@ -545,36 +1515,159 @@ impl Indexer {
         let num_splits = splits.len() as u64;
         let split_ids = splits.iter().map(|split| split.split_id()).join(",");
         info!(commit_trigger=?commit_trigger, split_ids=%split_ids, num_docs=self.counters.num_docs_in_workbench, "send-to-packager");
+        let batch = IndexedSplitBatch {
+            splits,
+            checkpoint_delta: Some(checkpoint_delta),
+            publish_with_next_delta: false,
+            publish_lock,
+            date_of_birth,
+        };
+        if let Some(shard_ord) = self.shard_ord_opt {
+            ctx.send_message(&self.packager_mailbox, ShardSplitBatch { shard_ord, batch })
+                .await?;
+        } else {
+            ctx.send_message(&self.packager_mailbox, batch).await?;
+        }
+        self.counters.num_docs_in_workbench = 0;
+        self.counters.num_splits_emitted += num_splits;
+        self.counters.num_split_batches_emitted += 1;
+        Ok(())
+    }
+
+    /// Returns the split carry-over settings if the partitions yielding small splits can be
+    /// carried over to the next workbench on this commit.
+    fn split_carry_over_settings_opt(
+        &self,
+        commit_trigger: CommitTrigger,
+        carried_over_since_opt: Option<Instant>,
+    ) -> Option<&SplitCarryOverSettings> {
+        let split_carry_over_settings = self
+            .indexer_state
+            .indexing_settings
+            .split_carry_over
+            .as_ref()?;
+        if let CommitTrigger::NoMoreDocs = commit_trigger {
+            return None;
+        }
+        if let Some(carried_over_since) = carried_over_since_opt {
+            if carried_over_since.elapsed() >= split_carry_over_settings.max_carry_over() {
+                return None;
+            }
+        }
+        Some(split_carry_over_settings)
+    }
+
+    /// Emits the splits large enough and carries over the other ones to the next workbench.
+    ///
+    /// The checkpoint delta is carried over as well, so the emitted splits are only staged and
+    /// uploaded: the publisher holds them back and publishes them in the same metastore
+    /// transaction as the carried-over splits and their checkpoint delta. Should the pipeline fail
+    /// in between, the documents of the emitted splits are redelivered.
+    async fn carry_over(
+        &mut self,
+        commit_trigger: CommitTrigger,
+        carried_over_workbench: IndexingWorkbench,
+        mut emitted_splits: Vec<IndexedSplit>,
+        mut dedup_hashes: FnvHashMap<u64, BTreeMap<i64, Vec<u64>>>,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<()> {
+        let commit_timeout_message = CommitTimeout {
+            workbench_id: carried_over_workbench.workbench_id,
+        };
+        ctx.schedule_self_msg(
+            self.indexer_state.indexing_settings.commit_timeout(),
+            commit_timeout_message,
+        )
+        .await;
+        let publish_lock = carried_over_workbench.publish_lock.clone();
+        let date_of_birth = carried_over_workbench.date_of_birth;
+        let carried_over_split_ids = carried_over_workbench
+            .indexed_splits
+            .values()
+            .map(|split| split.split_id())
+            .join(",");
+        let num_carried_over_splits = carried_over_workbench.indexed_splits.len();
+        let num_carried_over_docs: u64 = carried_over_workbench
+            .indexed_splits
+            .values()
+            .map(|split| split.split_attrs.num_docs)
+            .sum();
+        info!(commit_trigger=?commit_trigger, split_ids=%carried_over_split_ids, num_docs=num_carried_over_docs, "carry-over");
+        self.indexing_workbench_opt = Some(carried_over_workbench);
+        self.counters.num_docs_in_workbench = num_carried_over_docs;
+
+        if emitted_splits.is_empty() {
+            return Ok(());
+        }
+        let num_dedup_window_filters = self
+            .indexer_state
+            .num_dedup_window_filters(emitted_splits.len() + num_carried_over_splits);
+        attach_dedup_digests(
+            &mut emitted_splits,
+            &mut dedup_hashes,
+            num_dedup_window_filters,
+        );
+        let num_splits = emitted_splits.len() as u64;
+        let split_ids = emitted_splits
+            .iter()
+            .map(|split| split.split_id())
+            .join(",");
+        info!(commit_trigger=?commit_trigger, split_ids=%split_ids, "send-to-packager");
         ctx.send_message(
             &self.packager_mailbox,
             IndexedSplitBatch {
-                splits,
-                checkpoint_delta: Some(checkpoint_delta),
+                splits: emitted_splits,
+                checkpoint_delta: None,
+                publish_with_next_delta: true,
                 publish_lock,
                 date_of_birth,
             },
         )
         .await?;
-        self.counters.num_docs_in_workbench = 0;
         self.counters.num_splits_emitted += num_splits;
         self.counters.num_split_batches_emitted += 1;
         Ok(())
     }
 }
 
+/// Attaches to each split the digest of the hashes of the IDs of its documents.
+fn attach_dedup_digests(
+    splits: &mut [IndexedSplit],
+    dedup_hashes: &mut FnvHashMap<u64, BTreeMap<i64, Vec<u64>>>,
+    num_window_filters: usize,
+) {
+    for split in splits {
+        split.split_attrs.dedup_digest_opt = dedup_hashes
+            .remove(&split.split_attrs.partition_id)
+            .map(|buckets| {
+                DedupDigest::from_buckets(
+                    buckets
+                        .iter()
+                        .map(|(bucket_timestamp, hashes)| (*bucket_timestamp, &hashes[..])),
+                    num_window_filters,
+                )
+            });
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
     use std::time::Duration;
 
+    use byte_unit::Byte;
     use quickwit_actors::{create_test_mailbox, Universe};
-    use quickwit_doc_mapper::{default_doc_mapper_for_test, DefaultDocMapper, SortOrder};
+    use quickwit_config::{DeadLetterQueueSettings, DeduplicationSettings, FieldProfilingSettings};
+    use quickwit_doc_mapper::{
+        default_doc_mapper_for_test, DefaultDocMapper, SortOrder, DYNAMIC_FIELD_NAME,
+    };
     use quickwit_metastore::checkpoint::SourceCheckpointDelta;
-    use quickwit_metastore::MockMetastore;
+    use quickwit_metastore::{MockMetastore, Split, SplitMetadata, SplitState};
+    use quickwit_storage::RamStorage;
 
     use super::*;
     use crate::actors::indexer::{record_timestamp, IndexerCounters};
-    use crate::models::{IndexingDirectory, RawDocBatch};
+    use crate::models::{DeadLetterQueue, DeadLetterSink, IndexingDirectory, RawDocBatch};
 
     #[test]
     fn test_record_timestamp() {
@@ -587,6 +1680,37 @@ mod tests {
         assert_eq!(time_range, Some(1628664112..=1628665112))
     }
 
+    #[test]
+    fn test_enrich_document() {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 2,
+        };
+        let enriched_doc_json = enrich_document(
+            r#"{"body": "happy"}"#.to_string(),
+            "_quickwit",
+            &pipeline_id,
+            1628664679,
+        );
+        let enriched_doc: JsonValue = serde_json::from_str(&enriched_doc_json).unwrap();
+        assert_eq!(
+            enriched_doc,
+            json!({
+                "body": "happy",
+                "_quickwit": {
+                    "node_id": "test-node",
+                    "pipeline_ord": 2,
+                    "source_id": "test-source",
+                    "ingest_timestamp": 1628664679,
+                }
+            })
+        );
+        let not_an_object = enrich_document("[1, 2]".to_string(), "_quickwit", &pipeline_id, 0);
+        assert_eq!(not_an_object, "[1, 2]");
+    }
+
     #[tokio::test]
     async fn test_indexer_simple() -> anyhow::Result<()> {
         let pipeline_id = IndexingPipelineId {
@@ -604,6 +1728,7 @@ mod tests {
         indexing_settings.timestamp_field = Some("timestamp".to_string());
         let (packager_mailbox, packager_inbox) = create_test_mailbox();
         let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(7));
         metastore
             .expect_publish_splits()
             .returning(move |_, splits, _, _| {
@@ -617,6 +1742,7 @@ mod tests {
             indexing_directory,
             indexing_settings,
             packager_mailbox,
+            PipelineWarningSink::default(),
         );
         let universe = Universe::new();
         let (indexer_mailbox, indexer_handle) = universe.spawn_actor(indexer).spawn();
@@ -637,11 +1763,19 @@ mod tests {
             IndexerCounters {
                 num_parse_errors: 1,
                 num_missing_fields: 1,
+                num_duplicate_docs: 0,
+                num_routed_docs: 0,
+                cpu_time_micros: indexer_counters.cpu_time_micros,
                 num_valid_docs: 2,
                 num_splits_emitted: 0,
                 num_split_batches_emitted: 0,
+                num_evicted_splits: 0,
                 num_docs_in_workbench: 2, //< we have not reached the commit limit yet.
-                overall_num_bytes: 387
+                overall_num_bytes: 387,
+                read_watermark: SourceCheckpointDelta::from(0..4).get_source_checkpoint(),
+                indexed_watermark: SourceCheckpoint::default(),
+                workbench_preview: indexer_counters.workbench_preview.clone(),
+                field_profile: None,
             }
         );
         indexer_mailbox
@@ -658,11 +1792,19 @@ mod tests {
             IndexerCounters {
                 num_parse_errors: 1,
                 num_missing_fields: 1,
+                num_duplicate_docs: 0,
+                num_routed_docs: 0,
+                cpu_time_micros: indexer_counters.cpu_time_micros,
                 num_valid_docs: 3,
                 num_splits_emitted: 1,
                 num_split_batches_emitted: 1,
+                num_evicted_splits: 0,
                 num_docs_in_workbench: 0, //< the num docs in split counter has been reset.
-                overall_num_bytes: 525
+                overall_num_bytes: 525,
+                read_watermark: SourceCheckpointDelta::from(0..5).get_source_checkpoint(),
+                indexed_watermark: SourceCheckpointDelta::from(0..5).get_source_checkpoint(),
+                workbench_preview: indexer_counters.workbench_preview.clone(),
+                field_profile: None,
             }
         );
         let output_messages = packager_inbox.drain_for_test();
@@ -671,6 +1813,7 @@ mod tests {
             .downcast_ref::<IndexedSplitBatch>()
             .unwrap();
         assert_eq!(batch.splits[0].split_attrs.num_docs, 3);
+        assert_eq!(batch.splits[0].split_attrs.delete_opstamp, 7);
         let sort_by_field = batch.splits[0].index.settings().sort_by_field.as_ref();
         assert!(sort_by_field.is_some());
         assert_eq!(sort_by_field.unwrap().field, "timestamp");
@@ -679,24 +1822,20 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_indexer_timeout() -> anyhow::Result<()> {
+    async fn test_indexer_reports_metrics() -> anyhow::Result<()> {
         let pipeline_id = IndexingPipelineId {
-            index_id: "test-index".to_string(),
+            index_id: "test-index-metrics".to_string(),
             source_id: "test-source".to_string(),
             node_id: "test-node".to_string(),
             pipeline_ord: 0,
         };
         let doc_mapper = Arc::new(default_doc_mapper_for_test());
         let indexing_directory = IndexingDirectory::for_test().await?;
-        let indexing_settings = IndexingSettings::for_test();
-        let (packager_mailbox, packager_inbox) = create_test_mailbox();
+        let mut indexing_settings = IndexingSettings::for_test();
+        indexing_settings.split_num_docs_target = 2;
+        let (packager_mailbox, _packager_inbox) = create_test_mailbox();
         let mut metastore = MockMetastore::default();
-        metastore
-            .expect_publish_splits()
-            .returning(move |_, splits, _, _| {
-                assert!(splits.is_empty());
-                Ok(())
-            });
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
         let indexer = Indexer::new(
             pipeline_id,
             doc_mapper,
@@ -704,13 +1843,325 @@ mod tests {
             indexing_directory,
             indexing_settings,
             packager_mailbox,
+            PipelineWarningSink::default(),
         );
         let universe = Universe::new();
         let (indexer_mailbox, indexer_handle) = universe.spawn_actor(indexer).spawn();
         indexer_mailbox
-            .send_message(
-                RawDocBatch {
-                    docs: vec![r#"{"body": "happy", "timestamp": 1628837062, "response_date": "2021-12-19T16:39:57+00:00", "response_time": 12, "response_payload": "YWJj"}"#.to_string()],
+            .send_message(RawDocBatch {
+                docs: vec![
+                    r#"{"body": "happy", "response_date": "2021-12-19T16:39:57+00:00", "response_time": 12, "response_payload": "YWJj"}"#.to_string(), // missing timestamp
+                    r#"{"body": "happy", "timestamp": 1628837062, "response_date": "2021-12-19T16:39:59+00:00", "response_time": 2, "response_payload": "YWJj"}"#.to_string(), // ok
+                    "{".to_string(),                    // invalid json
+                ],
+                checkpoint_delta: SourceCheckpointDelta::from(0..3),
+            })
+            .await?;
+        indexer_handle.process_pending_and_observe().await;
+        let labels = |status: &str| ["test-index-metrics", "test-source", status];
+        let processed_docs_total = &INDEXER_METRICS.processed_docs_total;
+        assert_eq!(
+            processed_docs_total
+                .with_label_values(&labels("valid"))
+                .get(),
+            1
+        );
+        assert_eq!(
+            processed_docs_total
+                .with_label_values(&labels("missing_field"))
+                .get(),
+            1
+        );
+        assert_eq!(
+            processed_docs_total
+                .with_label_values(&labels("parse_error"))
+                .get(),
+            1
+        );
+        let pipeline_labels = ["test-index-metrics", "test-source"];
+        assert_eq!(
+            INDEXER_METRICS
+                .workbench_num_docs
+                .with_label_values(&pipeline_labels)
+                .get(),
+            1
+        );
+        indexer_mailbox
+            .send_message(RawDocBatch {
+                docs: vec![r#"{"body": "happy2", "timestamp": 1628837062, "response_date": "2021-12-19T16:39:59+00:00", "response_time": 2, "response_payload": "YWJj"}"#.to_string()],
+                checkpoint_delta: SourceCheckpointDelta::from(3..4),
+            })
+            .await?;
+        indexer_handle.process_pending_and_observe().await;
+        assert_eq!(
+            INDEXER_METRICS
+                .workbench_num_docs
+                .with_label_values(&pipeline_labels)
+                .get(),
+            0
+        );
+        assert_eq!(
+            INDEXER_METRICS
+                .commits_total
+                .with_label_values(&labels("num_docs_limit"))
+                .get(),
+            1
+        );
+        assert_eq!(
+            INDEXER_METRICS
+                .split_build_duration_secs
+                .with_label_values(&pipeline_labels)
+                .get_sample_count(),
+            1
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_indexer_commits_on_memory_limit() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index-memory-limit".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapper = Arc::new(default_doc_mapper_for_test());
+        let indexing_directory = IndexingDirectory::for_test().await?;
+        let mut indexing_settings = IndexingSettings::for_test();
+        indexing_settings.resources.workbench_memory_limit = Some(Byte::from_bytes(250));
+        let (packager_mailbox, packager_inbox) = create_test_mailbox();
+        let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
+        let indexer = Indexer::new(
+            pipeline_id,
+            doc_mapper,
+            Arc::new(metastore),
+            indexing_directory,
+            indexing_settings,
+            packager_mailbox,
+            PipelineWarningSink::default(),
+        );
+        let universe = Universe::new();
+        let (indexer_mailbox, indexer_handle) = universe.spawn_actor(indexer).spawn();
+        indexer_mailbox
+            .send_message(RawDocBatch {
+                docs: vec![r#"{"body": "happy", "timestamp": 1628837062, "response_date": "2021-12-19T16:39:59+00:00", "response_time": 2, "response_payload": "YWJj"}"#.to_string()],
+                checkpoint_delta: SourceCheckpointDelta::from(0..1),
+            })
+            .await?;
+        let indexer_counters = indexer_handle.process_pending_and_observe().await.state;
+        assert_eq!(indexer_counters.num_docs_in_workbench, 1);
+        assert!(packager_inbox.drain_for_test().is_empty());
+
+        let workbench_preview = indexer_counters.workbench_preview.unwrap();
+        assert_eq!(
+            workbench_preview.checkpoint_delta,
+            SourceCheckpointDelta::from(0..1)
+        );
+        assert_eq!(workbench_preview.num_docs, 1);
+        assert_eq!(workbench_preview.estimated_memory_usage_in_bytes, 136);
+        assert_eq!(workbench_preview.partitions.len(), 1);
+        assert_eq!(workbench_preview.partitions[0].num_docs, 1);
+        assert_eq!(
+            workbench_preview.partitions[0].uncompressed_docs_size_in_bytes,
+            136
+        );
+
+        // The two documents of the workbench amount to 274 bytes.
+        indexer_mailbox
+            .send_message(RawDocBatch {
+                docs: vec![r#"{"body": "happy2", "timestamp": 1628837062, "response_date": "2021-12-19T16:40:57+00:00", "response_time": 13, "response_payload": "YWJj"}"#.to_string()],
+                checkpoint_delta: SourceCheckpointDelta::from(1..2),
+            })
+            .await?;
+        let indexer_counters = indexer_handle.process_pending_and_observe().await.state;
+        assert_eq!(indexer_counters.num_docs_in_workbench, 0);
+        assert_eq!(indexer_counters.num_splits_emitted, 1);
+        assert!(indexer_counters.workbench_preview.is_none());
+        let output_messages = packager_inbox.drain_for_test();
+        assert_eq!(output_messages.len(), 1);
+        let batch = output_messages[0]
+            .downcast_ref::<IndexedSplitBatch>()
+            .unwrap();
+        assert_eq!(batch.splits[0].split_attrs.num_docs, 2);
+        assert_eq!(
+            INDEXER_METRICS
+                .commits_total
+                .with_label_values(&["test-index-memory-limit", "test-source", "memory_limit"])
+                .get(),
+            1
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_shard_indexer_reports_memory_limit() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index-shard-memory-limit".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapper = Arc::new(default_doc_mapper_for_test());
+        let indexing_directory = IndexingDirectory::for_test().await?;
+        let mut indexing_settings = IndexingSettings::for_test();
+        indexing_settings.resources.workbench_memory_limit = Some(Byte::from_bytes(250));
+        let (packager_mailbox, packager_inbox) = create_test_mailbox();
+        let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
+        let memory_limit_reports = MemoryLimitReports::default();
+        let indexer = Indexer::new(
+            pipeline_id,
+            doc_mapper,
+            Arc::new(metastore),
+            indexing_directory,
+            indexing_settings,
+            packager_mailbox,
+            PipelineWarningSink::default(),
+        )
+        .into_shard(0, memory_limit_reports.clone());
+        let universe = Universe::new();
+        let (indexer_mailbox, indexer_handle) = universe.spawn_actor(indexer).spawn();
+        indexer_mailbox
+            .send_message(RawDocBatch {
+                docs: vec![r#"{"body": "happy", "timestamp": 1628837062, "response_date": "2021-12-19T16:39:59+00:00", "response_time": 2, "response_payload": "YWJj"}"#.to_string()],
+                checkpoint_delta: SourceCheckpointDelta::from(0..1),
+            })
+            .await?;
+        indexer_handle.process_pending_and_observe().await;
+        assert!(!memory_limit_reports.take());
+
+        // The two documents of the workbench amount to 274 bytes: the shard reports the memory
+        // limit to the router instead of committing.
+        indexer_mailbox
+            .send_message(RawDocBatch {
+                docs: vec![r#"{"body": "happy2", "timestamp": 1628837062, "response_date": "2021-12-19T16:40:57+00:00", "response_time": 13, "response_payload": "YWJj"}"#.to_string()],
+                checkpoint_delta: SourceCheckpointDelta::from(1..2),
+            })
+            .await?;
+        let indexer_counters = indexer_handle.process_pending_and_observe().await.state;
+        assert_eq!(indexer_counters.num_docs_in_workbench, 2);
+        assert!(packager_inbox.drain_for_test().is_empty());
+        assert!(memory_limit_reports.take());
+
+        // The workbench is reported once.
+        indexer_mailbox
+            .send_message(RawDocBatch {
+                docs: vec![r#"{"body": "happy3", "timestamp": 1628837062, "response_date": "2021-12-19T16:41:57+00:00", "response_time": 13, "response_payload": "YWJj"}"#.to_string()],
+                checkpoint_delta: SourceCheckpointDelta::from(2..3),
+            })
+            .await?;
+        indexer_handle.process_pending_and_observe().await;
+        assert!(!memory_limit_reports.take());
+
+        indexer_mailbox
+            .send_message(CommitShard {
+                commit_trigger: CommitTrigger::MemoryLimit,
+            })
+            .await?;
+        let indexer_counters = indexer_handle.process_pending_and_observe().await.state;
+        assert_eq!(indexer_counters.num_docs_in_workbench, 0);
+        let output_messages = packager_inbox.drain_for_test();
+        assert_eq!(output_messages.len(), 1);
+        let shard_batch = output_messages[0]
+            .downcast_ref::<ShardSplitBatch>()
+            .unwrap();
+        assert_eq!(shard_batch.batch.splits[0].split_attrs.num_docs, 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_indexer_warns_on_missing_timestamps() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapper = Arc::new(default_doc_mapper_for_test());
+        let indexing_directory = IndexingDirectory::for_test().await?;
+        let mut indexing_settings = IndexingSettings::for_test();
+        indexing_settings.split_num_docs_target = 70;
+        let (packager_mailbox, _packager_inbox) = create_test_mailbox();
+        let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
+        let warning_sink = PipelineWarningSink::default();
+        let indexer = Indexer::new(
+            pipeline_id,
+            doc_mapper,
+            Arc::new(metastore),
+            indexing_directory,
+            indexing_settings,
+            packager_mailbox,
+            warning_sink.clone(),
+        );
+        let universe = Universe::new();
+        let (indexer_mailbox, indexer_handle) = universe.spawn_actor(indexer).spawn();
+        let docs = (0..100)
+            .map(|doc_id| {
+                if doc_id < 30 {
+                    r#"{"body": "happy"}"#.to_string()
+                } else {
+                    r#"{"body": "happy", "timestamp": 1628837062}"#.to_string()
+                }
+            })
+            .collect();
+        indexer_mailbox
+            .send_message(RawDocBatch {
+                docs,
+                checkpoint_delta: SourceCheckpointDelta::from(0..100),
+            })
+            .await?;
+        let indexer_counters = indexer_handle.process_pending_and_observe().await.state;
+        assert_eq!(indexer_counters.num_missing_fields, 30);
+        assert_eq!(indexer_counters.num_split_batches_emitted, 1);
+
+        let warnings = warning_sink.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, PipelineWarningKind::MissingTimestamp);
+        assert_eq!(warnings[0].actor_name, "Indexer");
+        assert_eq!(
+            warnings[0].message,
+            "30% of the docs of the last commit (30/100) were missing the timestamp field."
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_indexer_timeout() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapper = Arc::new(default_doc_mapper_for_test());
+        let indexing_directory = IndexingDirectory::for_test().await?;
+        let indexing_settings = IndexingSettings::for_test();
+        let (packager_mailbox, packager_inbox) = create_test_mailbox();
+        let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
+        metastore
+            .expect_publish_splits()
+            .returning(move |_, splits, _, _| {
+                assert!(splits.is_empty());
+                Ok(())
+            });
+        let indexer = Indexer::new(
+            pipeline_id,
+            doc_mapper,
+            Arc::new(metastore),
+            indexing_directory,
+            indexing_settings,
+            packager_mailbox,
+            PipelineWarningSink::default(),
+        );
+        let universe = Universe::new();
+        let (indexer_mailbox, indexer_handle) = universe.spawn_actor(indexer).spawn();
+        indexer_mailbox
+            .send_message(
+                RawDocBatch {
+                    docs: vec![r#"{"body": "happy", "timestamp": 1628837062, "response_date": "2021-12-19T16:39:57+00:00", "response_time": 12, "response_payload": "YWJj"}"#.to_string()],
                     checkpoint_delta: SourceCheckpointDelta::from(0..1),
                 }
             )
@@ -721,11 +2172,19 @@ mod tests {
             IndexerCounters {
                 num_parse_errors: 0,
                 num_missing_fields: 0,
+                num_duplicate_docs: 0,
+                num_routed_docs: 0,
+                cpu_time_micros: indexer_counters.cpu_time_micros,
                 num_valid_docs: 1,
                 num_splits_emitted: 0,
                 num_split_batches_emitted: 0,
+                num_evicted_splits: 0,
                 num_docs_in_workbench: 1,
-                overall_num_bytes: 137
+                overall_num_bytes: 137,
+                read_watermark: SourceCheckpointDelta::from(0..1).get_source_checkpoint(),
+                indexed_watermark: SourceCheckpoint::default(),
+                workbench_preview: indexer_counters.workbench_preview.clone(),
+                field_profile: None,
             }
         );
         universe.simulate_time_shift(Duration::from_secs(61)).await;
@@ -735,11 +2194,19 @@ mod tests {
             IndexerCounters {
                 num_parse_errors: 0,
                 num_missing_fields: 0,
+                num_duplicate_docs: 0,
+                num_routed_docs: 0,
+                cpu_time_micros: indexer_counters.cpu_time_micros,
                 num_valid_docs: 1,
                 num_splits_emitted: 1,
                 num_split_batches_emitted: 1,
+                num_evicted_splits: 0,
                 num_docs_in_workbench: 0,
-                overall_num_bytes: 137
+                overall_num_bytes: 137,
+                read_watermark: SourceCheckpointDelta::from(0..1).get_source_checkpoint(),
+                indexed_watermark: SourceCheckpointDelta::from(0..1).get_source_checkpoint(),
+                workbench_preview: indexer_counters.workbench_preview.clone(),
+                field_profile: None,
             }
         );
         let output_messages = packager_inbox.drain_for_test();
@@ -764,6 +2231,7 @@ mod tests {
         let indexing_settings = IndexingSettings::for_test();
         let (packager_mailbox, packager_inbox) = create_test_mailbox();
         let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
         metastore
             .expect_publish_splits()
             .returning(move |_, splits, _, _| {
@@ -777,6 +2245,7 @@ mod tests {
             indexing_directory,
             indexing_settings,
             packager_mailbox,
+            PipelineWarningSink::default(),
         );
         let universe = Universe::new();
         let (indexer_mailbox, indexer_handle) = universe.spawn_actor(indexer).spawn();
@@ -796,11 +2265,19 @@ mod tests {
             IndexerCounters {
                 num_parse_errors: 0,
                 num_missing_fields: 0,
+                num_duplicate_docs: 0,
+                num_routed_docs: 0,
+                cpu_time_micros: indexer_counters.cpu_time_micros,
                 num_valid_docs: 1,
                 num_splits_emitted: 1,
                 num_split_batches_emitted: 1,
+                num_evicted_splits: 0,
                 num_docs_in_workbench: 0,
-                overall_num_bytes: 137
+                overall_num_bytes: 137,
+                read_watermark: SourceCheckpointDelta::from(0..1).get_source_checkpoint(),
+                indexed_watermark: SourceCheckpointDelta::from(0..1).get_source_checkpoint(),
+                workbench_preview: indexer_counters.workbench_preview.clone(),
+                field_profile: None,
             }
         );
         let output_messages = packager_inbox.drain_for_test();
@@ -817,6 +2294,93 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_indexer_deduplicates_docs() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapper = Arc::new(default_doc_mapper_for_test());
+        let indexing_directory = IndexingDirectory::for_test().await?;
+        let deduplication_settings = DeduplicationSettings {
+            doc_id_field: Some("body".to_string()),
+            window_secs: 3_600,
+        };
+        let indexing_settings = IndexingSettings {
+            deduplication: Some(deduplication_settings.clone()),
+            ..IndexingSettings::for_test()
+        };
+        let published_doc_hash = DedupWindow::new(&deduplication_settings, DedupDigest::default())
+            .doc_hash(r#"{"body": "happy"}"#)
+            .unwrap();
+        let (packager_mailbox, packager_inbox) = create_test_mailbox();
+        let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
+        metastore
+            .expect_list_splits()
+            .times(1)
+            .returning(move |_, _, _, _| {
+                let now_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+                let split_metadata = SplitMetadata {
+                    source_id: "test-source".to_string(),
+                    dedup_digest: Some(DedupDigest::from_buckets(
+                        [(now_timestamp, &[published_doc_hash][..])],
+                        1,
+                    )),
+                    ..Default::default()
+                };
+                Ok(vec![Split {
+                    split_state: SplitState::Published,
+                    update_timestamp: now_timestamp,
+                    split_metadata,
+                }])
+            });
+        let indexer = Indexer::new(
+            pipeline_id,
+            doc_mapper,
+            Arc::new(metastore),
+            indexing_directory,
+            indexing_settings,
+            packager_mailbox,
+            PipelineWarningSink::default(),
+        );
+        let universe = Universe::new();
+        let (indexer_mailbox, indexer_handle) = universe.spawn_actor(indexer).spawn();
+        indexer_mailbox
+            .send_message(RawDocBatch {
+                docs: vec![
+                    r#"{"body": "happy", "timestamp": 1628837062, "response_date": "2021-12-19T16:39:57+00:00", "response_time": 12, "response_payload": "YWJj"}"#.to_string(), // published
+                    r#"{"body": "happy2", "timestamp": 1628837062, "response_date": "2021-12-19T16:39:57+00:00", "response_time": 12, "response_payload": "YWJj"}"#.to_string(), // ok
+                    r#"{"body": "happy2", "timestamp": 1628837063, "response_date": "2021-12-19T16:39:58+00:00", "response_time": 13, "response_payload": "YWJj"}"#.to_string(), // redelivered
+                ],
+                checkpoint_delta: SourceCheckpointDelta::from(0..3),
+            })
+            .await?;
+        universe.send_exit_with_success(&indexer_mailbox).await?;
+        let (exit_status, indexer_counters) = indexer_handle.join().await;
+        assert!(exit_status.is_success());
+        assert_eq!(indexer_counters.num_valid_docs, 1);
+        assert_eq!(indexer_counters.num_duplicate_docs, 2);
+        assert_eq!(indexer_counters.num_processed_docs(), 3);
+
+        let output_messages = packager_inbox.drain_for_test();
+        assert_eq!(output_messages.len(), 1);
+        let split_attrs = &output_messages[0]
+            .downcast_ref::<IndexedSplitBatch>()
+            .unwrap()
+            .splits[0]
+            .split_attrs;
+        assert_eq!(split_attrs.num_docs, 1);
+        let dedup_digest = split_attrs.dedup_digest_opt.as_ref().unwrap();
+        let indexed_doc_hash = DedupWindow::new(&deduplication_settings, DedupDigest::default())
+            .doc_hash(r#"{"body": "happy2"}"#)
+            .unwrap();
+        assert!(dedup_digest.contains(indexed_doc_hash));
+        Ok(())
+    }
+
     const DOCMAPPER_WITH_PARTITION_JSON: &str = r#"
         {
             "tag_fields": ["tenant"],
@@ -842,6 +2406,7 @@ mod tests {
         let indexing_settings = IndexingSettings::for_test();
         let (packager_mailbox, packager_inbox) = create_test_mailbox();
         let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
         metastore
             .expect_publish_splits()
             .returning(move |_, splits, _, _| {
@@ -856,6 +2421,7 @@ mod tests {
             indexing_directory,
             indexing_settings,
             packager_mailbox,
+            PipelineWarningSink::default(),
         );
         let universe = Universe::new();
         let (indexer_mailbox, indexer_handle) = universe.spawn_actor(indexer).spawn();
@@ -876,11 +2442,19 @@ mod tests {
             IndexerCounters {
                 num_parse_errors: 0,
                 num_missing_fields: 0,
+                num_duplicate_docs: 0,
+                num_routed_docs: 0,
+                cpu_time_micros: indexer_counters.cpu_time_micros,
                 num_valid_docs: 3,
                 num_docs_in_workbench: 3,
                 num_splits_emitted: 0,
                 num_split_batches_emitted: 0,
-                overall_num_bytes: 169
+                num_evicted_splits: 0,
+                overall_num_bytes: 169,
+                read_watermark: SourceCheckpointDelta::from(0..2).get_source_checkpoint(),
+                indexed_watermark: SourceCheckpoint::default(),
+                workbench_preview: indexer_counters.workbench_preview.clone(),
+                field_profile: None,
             }
         );
         universe.send_exit_with_success(&indexer_mailbox).await?;
@@ -891,11 +2465,19 @@ mod tests {
             IndexerCounters {
                 num_parse_errors: 0,
                 num_missing_fields: 0,
+                num_duplicate_docs: 0,
+                num_routed_docs: 0,
+                cpu_time_micros: indexer_counters.cpu_time_micros,
                 num_valid_docs: 3,
                 num_docs_in_workbench: 0,
                 num_splits_emitted: 2,
                 num_split_batches_emitted: 1,
-                overall_num_bytes: 169
+                num_evicted_splits: 0,
+                overall_num_bytes: 169,
+                read_watermark: SourceCheckpointDelta::from(0..2).get_source_checkpoint(),
+                indexed_watermark: SourceCheckpointDelta::from(0..2).get_source_checkpoint(),
+                workbench_preview: indexer_counters.workbench_preview.clone(),
+                field_profile: None,
             }
         );
 
@@ -909,6 +2491,407 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_indexer_evicts_least_recently_used_partition() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapper: Arc<dyn DocMapper> = Arc::new(
+            serde_json::from_str::<DefaultDocMapper>(DOCMAPPER_WITH_PARTITION_JSON).unwrap(),
+        );
+        let indexing_directory = IndexingDirectory::for_test().await?;
+        let mut indexing_settings = IndexingSettings::for_test();
+        indexing_settings.max_open_partitions = Some(2);
+        let (packager_mailbox, packager_inbox) = create_test_mailbox();
+        let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
+
+        let indexer = Indexer::new(
+            pipeline_id,
+            doc_mapper,
+            Arc::new(metastore),
+            indexing_directory,
+            indexing_settings,
+            packager_mailbox,
+            PipelineWarningSink::default(),
+        );
+        let universe = Universe::new();
+        let (indexer_mailbox, indexer_handle) = universe.spawn_actor(indexer).spawn();
+        indexer_mailbox
+            .send_message(RawDocBatch {
+                docs: vec![
+                    r#"{"tenant": "tenant_1", "body": "first doc for tenant 1"}"#.to_string(),
+                    r#"{"tenant": "tenant_2", "body": "first doc for tenant 2"}"#.to_string(),
+                    r#"{"tenant": "tenant_1", "body": "second doc for tenant 1"}"#.to_string(),
+                    r#"{"tenant": "tenant_3", "body": "first doc for tenant 3"}"#.to_string(),
+                ],
+                checkpoint_delta: SourceCheckpointDelta::from(0..4),
+            })
+            .await?;
+        let indexer_counters = indexer_handle.process_pending_and_observe().await.state;
+        assert_eq!(indexer_counters.num_evicted_splits, 1);
+        assert_eq!(indexer_counters.num_docs_in_workbench, 3);
+
+        universe.send_exit_with_success(&indexer_mailbox).await?;
+        let (exit_status, indexer_counters) = indexer_handle.join().await;
+        assert!(matches!(exit_status, ActorExitStatus::Success));
+        assert_eq!(indexer_counters.num_splits_emitted, 3);
+        assert_eq!(indexer_counters.num_split_batches_emitted, 2);
+
+        let indexed_split_batches = packager_inbox.drain_for_test_typed::<IndexedSplitBatch>();
+        assert_eq!(indexed_split_batches.len(), 2);
+        // The split of `tenant_2` is evicted without checkpoint delta, and published along with the
+        // checkpoint delta of the commit.
+        let evicted_split_batch = &indexed_split_batches[0];
+        assert!(evicted_split_batch.checkpoint_delta.is_none());
+        assert!(evicted_split_batch.publish_with_next_delta);
+        assert_eq!(evicted_split_batch.splits.len(), 1);
+        assert_eq!(evicted_split_batch.splits[0].split_attrs.num_docs, 1);
+
+        let committed_split_batch = &indexed_split_batches[1];
+        assert_eq!(
+            committed_split_batch
+                .checkpoint_delta
+                .as_ref()
+                .unwrap()
+                .source_delta,
+            SourceCheckpointDelta::from(0..4)
+        );
+        assert!(!committed_split_batch.publish_with_next_delta);
+        let mut num_docs_per_split: Vec<u64> = committed_split_batch
+            .splits
+            .iter()
+            .map(|split| split.split_attrs.num_docs)
+            .collect();
+        num_docs_per_split.sort_unstable();
+        assert_eq!(num_docs_per_split, [1, 2]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_indexer_schemaless() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        // No field mappings: all the fields are captured by the dynamic field.
+        let doc_mapper: Arc<dyn DocMapper> =
+            Arc::new(serde_json::from_str::<DefaultDocMapper>(r#"{"mode": "dynamic"}"#).unwrap());
+        let indexing_directory = IndexingDirectory::for_test().await?;
+        let indexing_settings = IndexingSettings::for_test();
+        let (packager_mailbox, packager_inbox) = create_test_mailbox();
+        let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
+        let indexer = Indexer::new(
+            pipeline_id,
+            doc_mapper,
+            Arc::new(metastore),
+            indexing_directory,
+            indexing_settings,
+            packager_mailbox,
+            PipelineWarningSink::default(),
+        );
+        let universe = Universe::new();
+        let (indexer_mailbox, indexer_handle) = universe.spawn_actor(indexer).spawn();
+        indexer_mailbox
+            .send_message(RawDocBatch {
+                docs: vec![
+                    r#"{"body": "happy", "severity": 3}"#.to_string(),
+                    r#"{"endpoint": "/admin", "src": {"ip": "8.8.8.8", "port": 53}}"#.to_string(),
+                    r#"{"body": ["happy", "sad"], "latency_secs": 0.5, "ok": true}"#.to_string(),
+                    r#"["not", "an", "object"]"#.to_string(),
+                ],
+                checkpoint_delta: SourceCheckpointDelta::from(0..4),
+            })
+            .await?;
+        universe.send_exit_with_success(&indexer_mailbox).await?;
+        let (exit_status, indexer_counters) = indexer_handle.join().await;
+        assert!(matches!(exit_status, ActorExitStatus::Success));
+        assert_eq!(indexer_counters.num_valid_docs, 3);
+        assert_eq!(indexer_counters.num_parse_errors, 1);
+        assert_eq!(indexer_counters.num_splits_emitted, 1);
+
+        let output_messages = packager_inbox.drain_for_test();
+        assert_eq!(output_messages.len(), 1);
+        let indexed_split_batch = output_messages[0]
+            .downcast_ref::<IndexedSplitBatch>()
+            .unwrap();
+        assert_eq!(indexed_split_batch.splits.len(), 1);
+        assert_eq!(indexed_split_batch.splits[0].split_attrs.num_docs, 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_indexer_profiles_sampled_docs() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapper: Arc<dyn DocMapper> = Arc::new(
+            serde_json::from_str::<DefaultDocMapper>(
+                r#"{
+                    "mode": "dynamic",
+                    "field_mappings": [{"name": "body", "type": "text"}]
+                }"#,
+            )
+            .unwrap(),
+        );
+        let indexing_directory = IndexingDirectory::for_test().await?;
+        let mut indexing_settings = IndexingSettings::for_test();
+        indexing_settings.field_profiling = Some(FieldProfilingSettings { sample_interval: 2 });
+        let (packager_mailbox, _packager_inbox) = create_test_mailbox();
+        let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
+        let indexer = Indexer::new(
+            pipeline_id,
+            doc_mapper,
+            Arc::new(metastore),
+            indexing_directory,
+            indexing_settings,
+            packager_mailbox,
+            PipelineWarningSink::default(),
+        );
+        let universe = Universe::new();
+        let (indexer_mailbox, indexer_handle) = universe.spawn_actor(indexer).spawn();
+        indexer_mailbox
+            .send_message(RawDocBatch {
+                docs: (0..5)
+                    .map(|doc_ord| {
+                        format!(r#"{{"body": "happy happy {}", "severity": 3}}"#, doc_ord)
+                    })
+                    .collect(),
+                checkpoint_delta: SourceCheckpointDelta::from(0..5),
+            })
+            .await?;
+        let indexer_counters = indexer_handle.process_pending_and_observe().await.state;
+        assert_eq!(indexer_counters.num_valid_docs, 5);
+        let field_profile = indexer_counters.field_profile.unwrap();
+        assert_eq!(field_profile.num_sampled_docs, 3);
+        let body_cost = &field_profile.field_costs["body"];
+        assert_eq!(body_cost.num_bytes, 3 * 13);
+        assert_eq!(body_cost.num_tokens, 3 * 3);
+        assert!(field_profile.field_costs.contains_key(DYNAMIC_FIELD_NAME));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_indexer_writes_invalid_docs_to_dead_letter_queue() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapper = Arc::new(default_doc_mapper_for_test());
+        let indexing_directory = IndexingDirectory::for_test().await?;
+        let indexing_settings = IndexingSettings::for_test();
+        let (packager_mailbox, _packager_inbox) = create_test_mailbox();
+        let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
+        let ram_storage = RamStorage::default();
+        let dead_letter_queue = DeadLetterQueue::new(
+            pipeline_id.clone(),
+            DeadLetterSink::Storage(Arc::new(ram_storage.clone())),
+            &DeadLetterQueueSettings {
+                uri: None,
+                index_id: None,
+                max_file_size: Byte::from_bytes(10_000_000),
+                max_num_files: 10,
+            },
+        );
+        let indexer = Indexer::new(
+            pipeline_id,
+            doc_mapper,
+            Arc::new(metastore),
+            indexing_directory,
+            indexing_settings,
+            packager_mailbox,
+            PipelineWarningSink::default(),
+        )
+        .with_dead_letter_queue(dead_letter_queue.clone());
+        let universe = Universe::new();
+        let (indexer_mailbox, indexer_handle) = universe.spawn_actor(indexer).spawn();
+        indexer_mailbox
+            .send_message(RawDocBatch {
+                docs: vec![
+                    r#"{"body": "happy", "timestamp": 1628837062, "response_date": "2021-12-19T16:39:59+00:00", "response_time": 2, "response_payload": "YWJj"}"#.to_string(),
+                    r#"{"body": "happy", "#.to_string(),
+                ],
+                checkpoint_delta: SourceCheckpointDelta::from(0..2),
+            })
+            .await?;
+        universe.send_exit_with_success(&indexer_mailbox).await?;
+        let (exit_status, indexer_counters) = indexer_handle.join().await;
+        assert!(matches!(exit_status, ActorExitStatus::Success));
+        assert_eq!(indexer_counters.num_valid_docs, 1);
+        assert_eq!(indexer_counters.num_parse_errors, 1);
+
+        let files = ram_storage.list_files().await;
+        assert_eq!(files.len(), 1);
+        let payload = ram_storage.get_all(&files[0]).await?;
+        let record: JsonValue = serde_json::from_slice(payload.as_slice())?;
+        assert_eq!(record["source_id"], "test-source");
+        assert_eq!(record["doc"], r#"{"body": "happy", "#);
+        assert_eq!(dead_letter_queue.counters().num_docs_written, 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_indexer_records_min_expire_timestamp() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapper: Arc<dyn DocMapper> = Arc::new(
+            serde_json::from_str::<DefaultDocMapper>(
+                r#"{
+                    "expire_timestamp_field": "expire_at",
+                    "field_mappings": [
+                        {"name": "body", "type": "text"},
+                        {"name": "expire_at", "type": "i64", "fast": true}
+                    ]
+                }"#,
+            )
+            .unwrap(),
+        );
+        let indexing_directory = IndexingDirectory::for_test().await?;
+        let indexing_settings = IndexingSettings::for_test();
+        let (packager_mailbox, packager_inbox) = create_test_mailbox();
+        let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
+        let indexer = Indexer::new(
+            pipeline_id,
+            doc_mapper,
+            Arc::new(metastore),
+            indexing_directory,
+            indexing_settings,
+            packager_mailbox,
+            PipelineWarningSink::default(),
+        );
+        let universe = Universe::new();
+        let (indexer_mailbox, indexer_handle) = universe.spawn_actor(indexer).spawn();
+        indexer_mailbox
+            .send_message(RawDocBatch {
+                docs: vec![
+                    r#"{"body": "happy", "expire_at": 1660000200}"#.to_string(),
+                    r#"{"body": "happy2"}"#.to_string(),
+                    r#"{"body": "happy3", "expire_at": 1660000100}"#.to_string(),
+                ],
+                checkpoint_delta: SourceCheckpointDelta::from(0..3),
+            })
+            .await?;
+        universe.send_exit_with_success(&indexer_mailbox).await?;
+        let (exit_status, indexer_counters) = indexer_handle.join().await;
+        assert!(matches!(exit_status, ActorExitStatus::Success));
+        assert_eq!(indexer_counters.num_valid_docs, 3);
+
+        let output_messages = packager_inbox.drain_for_test();
+        assert_eq!(output_messages.len(), 1);
+        let indexed_split_batch = output_messages[0]
+            .downcast_ref::<IndexedSplitBatch>()
+            .unwrap();
+        assert_eq!(
+            indexed_split_batch.splits[0]
+                .split_attrs
+                .min_expire_timestamp,
+            Some(1660000100)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_indexer_carries_over_small_partitions() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapper: Arc<dyn DocMapper> = Arc::new(
+            serde_json::from_str::<DefaultDocMapper>(DOCMAPPER_WITH_PARTITION_JSON).unwrap(),
+        );
+        let indexing_directory = IndexingDirectory::for_test().await?;
+        let mut indexing_settings = IndexingSettings::for_test();
+        indexing_settings.split_num_docs_target = 3;
+        indexing_settings.split_carry_over = Some(SplitCarryOverSettings {
+            min_num_docs: 2,
+            min_num_bytes: 0,
+            max_carry_over_secs: 600,
+        });
+        let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
+        let (packager_mailbox, packager_inbox) = create_test_mailbox();
+        let indexer = Indexer::new(
+            pipeline_id,
+            doc_mapper,
+            Arc::new(metastore),
+            indexing_directory,
+            indexing_settings,
+            packager_mailbox,
+            PipelineWarningSink::default(),
+        );
+        let universe = Universe::new();
+        let (indexer_mailbox, indexer_handle) = universe.spawn_actor(indexer).spawn();
+        indexer_mailbox
+            .send_message(RawDocBatch {
+                docs: vec![
+                    r#"{"tenant": "tenant_1", "body": "first doc for tenant 1"}"#.to_string(),
+                    r#"{"tenant": "tenant_2", "body": "first doc for tenant 2"}"#.to_string(),
+                    r#"{"tenant": "tenant_1", "body": "second doc for tenant 1"}"#.to_string(),
+                ],
+                checkpoint_delta: SourceCheckpointDelta::from(0..2),
+            })
+            .await?;
+
+        let indexer_counters = indexer_handle.process_pending_and_observe().await.state;
+        assert_eq!(indexer_counters.num_docs_in_workbench, 1);
+        assert_eq!(indexer_counters.num_splits_emitted, 1);
+        assert_eq!(indexer_counters.num_split_batches_emitted, 1);
+        // The checkpoint delta is carried over along with the split of tenant 2.
+        assert_eq!(
+            indexer_counters.indexed_watermark,
+            SourceCheckpoint::default()
+        );
+
+        universe.send_exit_with_success(&indexer_mailbox).await?;
+        let (exit_status, indexer_counters) = indexer_handle.join().await;
+        assert!(matches!(exit_status, ActorExitStatus::Success));
+        assert_eq!(indexer_counters.num_docs_in_workbench, 0);
+        assert_eq!(indexer_counters.num_splits_emitted, 2);
+        assert_eq!(indexer_counters.num_split_batches_emitted, 2);
+        assert_eq!(
+            indexer_counters.indexed_watermark,
+            SourceCheckpointDelta::from(0..2).get_source_checkpoint()
+        );
+
+        let indexed_split_batches = packager_inbox.drain_for_test_typed::<IndexedSplitBatch>();
+        assert_eq!(indexed_split_batches.len(), 2);
+        assert_eq!(indexed_split_batches[0].splits.len(), 1);
+        assert_eq!(indexed_split_batches[0].splits[0].split_attrs.num_docs, 2);
+        assert!(indexed_split_batches[0].checkpoint_delta.is_none());
+        assert!(indexed_split_batches[0].publish_with_next_delta);
+        assert_eq!(indexed_split_batches[1].splits.len(), 1);
+        assert!(!indexed_split_batches[1].publish_with_next_delta);
+        assert_eq!(indexed_split_batches[1].splits[0].split_attrs.num_docs, 1);
+        assert_eq!(
+            indexed_split_batches[1].checkpoint_delta,
+            Some(IndexCheckpointDelta {
+                source_id: "test-source".to_string(),
+                source_delta: SourceCheckpointDelta::from(0..2),
+            })
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_indexer_propagates_publish_lock() {
         let pipeline_id = IndexingPipelineId {
@@ -922,6 +2905,7 @@ mod tests {
         let mut indexing_settings = IndexingSettings::for_test();
         indexing_settings.split_num_docs_target = 1;
         let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
         metastore.expect_publish_splits().never();
         let (packager_mailbox, packager_inbox) = create_test_mailbox();
         let indexer = Indexer::new(
@@ -931,6 +2915,7 @@ mod tests {
             indexing_directory,
             indexing_settings,
             packager_mailbox,
+            PipelineWarningSink::default(),
         );
         let universe = Universe::new();
         let (indexer_mailbox, indexer_handle) = universe.spawn_actor(indexer).spawn();
@@ -980,6 +2965,7 @@ mod tests {
         let mut indexing_settings = IndexingSettings::for_test();
         indexing_settings.split_num_docs_target = 1;
         let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
         metastore.expect_publish_splits().never();
         let (packager_mailbox, packager_inbox) = create_test_mailbox();
         let indexer = Indexer::new(
@@ -989,6 +2975,7 @@ mod tests {
             indexing_directory,
             indexing_settings,
             packager_mailbox,
+            PipelineWarningSink::default(),
         );
         let universe = Universe::new();
         let (indexer_mailbox, indexer_handle) = universe.spawn_actor(indexer).spawn();
@@ -1032,6 +3019,7 @@ mod tests {
         let indexing_directory = IndexingDirectory::for_test().await.unwrap();
         let indexing_settings = IndexingSettings::for_test();
         let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
         metastore.expect_publish_splits().never();
         let (packager_mailbox, packager_inbox) = create_test_mailbox();
         let indexer = Indexer::new(
@@ -1041,6 +3029,7 @@ mod tests {
             indexing_directory,
             indexing_settings,
             packager_mailbox,
+            PipelineWarningSink::default(),
         );
         let universe = Universe::new();
         let (indexer_mailbox, indexer_handle) = universe.spawn_actor(indexer).spawn();
@@ -21,44 +21,91 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
-use anyhow::Context;
 use async_trait::async_trait;
 use quickwit_actors::{
-    create_mailbox, Actor, ActorContext, ActorExitStatus, ActorHandle, Handler, Health, KillSwitch,
-    QueueCapacity, Supervisable,
+    create_mailbox, Actor, ActorContext, ActorExitStatus, ActorHandle, Handler, Health, Inbox,
+    KillSwitch, Mailbox, QueueCapacity, Supervisable,
 };
-use quickwit_config::{build_doc_mapper, IndexingSettings, SourceConfig};
+use quickwit_common::fs::check_file_name_supported;
+use quickwit_config::{build_doc_mapper, IndexingSettings, SourceConfig, SourceParams};
+use quickwit_directories::LocalSplitRegistry;
 use quickwit_doc_mapper::DocMapper;
-use quickwit_metastore::{IndexMetadata, Metastore, MetastoreError, SplitState};
-use quickwit_storage::Storage;
+use quickwit_metastore::checkpoint::IncompatibleCheckpointDelta;
+use quickwit_metastore::{IndexMetadata, Metastore, MetastoreError, SplitMetadata, SplitState};
+use quickwit_storage::{
+    wrap_storage_with_ops_counters, wrap_storage_with_retry_policy, Storage, StorageOpsCounters,
+    StorageRetryPolicy,
+};
 use tokio::join;
-use tracing::{debug, error, info, info_span, instrument, Span};
+use tracing::{debug, error, info, info_span, instrument, warn, Span};
 
 use crate::actors::merge_split_downloader::MergeSplitDownloader;
-use crate::actors::publisher::PublisherType;
+use crate::actors::publisher::{PublisherType, ReplaceNonCriticalMailboxes};
 use crate::actors::sequencer::Sequencer;
+use crate::actors::sharding::{DocRouter, MemoryLimitReports, ShardBatchCombiner};
 use crate::actors::{
-    GarbageCollector, Indexer, MergeExecutor, MergePlanner, NamedField, Packager, Publisher,
-    Uploader,
+    tag_fields, DeleteExecutor, GarbageCollector, Indexer, MergeExecutor, MergePlanner,
+    MergePlannerLeadership, NamedField, Packager, Publisher, PublisherCounters,
+    RepeatedSplitVerificationFailure, Uploader,
+};
+use crate::models::{
+    ActorEdge, ActorTopology, DeadLetterQueue, DeadLetterSink, DocTransformer, ForceMerge,
+    IndexFeatureFlags, IndexingDirectory, IndexingPipelineId, IndexingStatistics, KillSwitchGroup,
+    KillSwitchTopology, MetastorePayloadGuard, Observe, PipelineTopology, PipelineWarningKind,
+    PipelineWarningSink, PublishNotifier, RecompressSplits, SourceRateLimiter, TenantRouter,
+    UploadCircuitBreaker,
 };
-use crate::models::{IndexingDirectory, IndexingPipelineId, IndexingStatistics, Observe};
 use crate::source::{quickwit_supported_sources, SourceActor, SourceExecutionContext};
 use crate::split_store::{IndexingSplitStore, IndexingSplitStoreParams};
-use crate::{MergePolicy, StableMultitenantWithTimestampMergePolicy};
+use crate::{load_merge_policy, FairScheduler, MergePolicy, MergeScheduler};
 
 const MAX_RETRY_DELAY: Duration = Duration::from_secs(600); // 10 min.
 
+/// Interval at which the pipeline checks whether the maintenance mode of its index was toggled.
+const MAINTENANCE_MODE_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Number of consecutive generations of the pipeline failing on the same rejected checkpoint delta
+/// after which the pipeline gives up.
+const MAX_IDENTICAL_CHECKPOINT_REJECTIONS: usize = 3;
+
+/// Number of consecutive failures to store a split for a reason that looks permanent, such as
+/// rejected credentials or a missing bucket, after which the pipeline is paused.
+const MAX_CONSECUTIVE_PERMANENT_UPLOAD_FAILURES: usize = 5;
+
+/// Delay after which a pipeline paused by its upload circuit breaker is respawned to probe the
+/// storage again.
+const UPLOAD_CIRCUIT_BREAKER_COOL_DOWN: Duration = if cfg!(test) {
+    Duration::from_secs(1)
+} else {
+    Duration::from_secs(300)
+};
+
 pub struct IndexingPipelineHandle {
     /// Indexing pipeline
     pub source: ActorHandle<SourceActor>,
     pub indexer: ActorHandle<Indexer>,
+    /// Indexers of the shards of a sharded pipeline, fed by `indexer` acting as a router. Empty
+    /// for pipelines without sharding.
+    pub shard_indexers: Vec<ActorHandle<Indexer>>,
     pub packager: ActorHandle<Packager>,
     pub uploader: ActorHandle<Uploader>,
     pub sequencer: ActorHandle<Sequencer<Publisher>>,
     pub publisher: ActorHandle<Publisher>,
     pub garbage_collector: ActorHandle<GarbageCollector>,
+    /// Delete executor, spawned along with the merge sub-pipeline it applies delete tasks through.
+    pub delete_executor_opt: Option<ActorHandle<DeleteExecutor>>,
+
+    /// Merging pipeline subpipeline, not spawned for pipelines with a disabled merge pipeline.
+    pub merge_pipeline_opt: Option<MergePipelineHandle>,
+    /// Leadership of the merge planner, shared with the delete executor. It outlives the merge
+    /// planner, so that the delete executor keeps following the respawned merge planners.
+    pub merge_planner_leadership: MergePlannerLeadership,
 
-    /// Merging pipeline subpipeline
+    /// Split store of the generation, reused when the non-critical actors are respawned.
+    pub split_store: IndexingSplitStore,
+}
+
+pub struct MergePipelineHandle {
     pub merge_planner: ActorHandle<MergePlanner>,
     pub merge_split_downloader: ActorHandle<MergeSplitDownloader>,
     pub merge_executor: ActorHandle<MergeExecutor>,
@@ -68,6 +115,97 @@ pub struct IndexingPipelineHandle {
     pub merge_publisher: ActorHandle<Publisher>,
 }
 
+impl MergePipelineHandle {
+    async fn kill(self) {
+        tokio::join!(
+            self.merge_planner.kill(),
+            self.merge_split_downloader.kill(),
+            self.merge_executor.kill(),
+            self.merge_packager.kill(),
+            self.merge_uploader.kill(),
+            self.merge_sequencer.kill(),
+            self.merge_publisher.kill(),
+        );
+    }
+}
+
+/// Actors off the critical indexing path: their failures delay merges, deletes, and garbage
+/// collection, but not the indexing of new documents.
+struct NonCriticalActors {
+    garbage_collector_mailbox: Mailbox<GarbageCollector>,
+    garbage_collector: ActorHandle<GarbageCollector>,
+    merge_planner_mailbox: Mailbox<MergePlanner>,
+    delete_executor_mailbox: Mailbox<DeleteExecutor>,
+    delete_executor_opt: Option<ActorHandle<DeleteExecutor>>,
+    merge_pipeline_opt: Option<MergePipelineHandle>,
+    merge_planner_leadership: MergePlannerLeadership,
+}
+
+/// Kill switches of the groups of non-critical actors: the garbage collector, the delete
+/// executor, and the merge sub-pipeline. Each group is killed and respawned on its own.
+#[derive(Clone, Default)]
+struct NonCriticalKillSwitches {
+    garbage_collector: KillSwitch,
+    delete_executor: KillSwitch,
+    merge: KillSwitch,
+}
+
+impl NonCriticalKillSwitches {
+    /// Returns the kill switches of a new generation of the pipeline. Unless the failures of the
+    /// non-critical actors are isolated, all the groups share the kill switch of the critical
+    /// actors.
+    fn new(isolate_non_critical_failures: bool, critical_kill_switch: &KillSwitch) -> Self {
+        if isolate_non_critical_failures {
+            return NonCriticalKillSwitches::default();
+        }
+        NonCriticalKillSwitches {
+            garbage_collector: critical_kill_switch.clone(),
+            delete_executor: critical_kill_switch.clone(),
+            merge: critical_kill_switch.clone(),
+        }
+    }
+
+    fn get(&self, kill_switch_group: KillSwitchGroup) -> Option<&KillSwitch> {
+        match kill_switch_group {
+            KillSwitchGroup::Critical => None,
+            KillSwitchGroup::GarbageCollector => Some(&self.garbage_collector),
+            KillSwitchGroup::DeleteExecutor => Some(&self.delete_executor),
+            KillSwitchGroup::Merge => Some(&self.merge),
+        }
+    }
+
+    /// Kills the actors of the group and gives the group a new kill switch for its respawned
+    /// actors.
+    fn renew(&mut self, kill_switch_group: KillSwitchGroup) {
+        let kill_switch = match kill_switch_group {
+            KillSwitchGroup::Critical => return,
+            KillSwitchGroup::GarbageCollector => &mut self.garbage_collector,
+            KillSwitchGroup::DeleteExecutor => &mut self.delete_executor,
+            KillSwitchGroup::Merge => &mut self.merge,
+        };
+        kill_switch.kill();
+        *kill_switch = KillSwitch::default();
+    }
+
+    fn kill(&self) {
+        self.garbage_collector.kill();
+        self.delete_executor.kill();
+        self.merge.kill();
+    }
+}
+
+/// Consolidated health of the actors of a pipeline.
+#[derive(Debug, Eq, PartialEq)]
+enum PipelineHealth {
+    Healthy,
+    /// Some non-critical actors failed while all the actors of the critical path are running.
+    /// Holds the groups of the failed actors. Only reported when the failures of the non-critical
+    /// actors are isolated.
+    NonCriticalFailure(Vec<KillSwitchGroup>),
+    FailureOrUnhealthy,
+    Success,
+}
+
 // Messages
 
 #[derive(Clone, Copy, Debug)]
@@ -78,6 +216,48 @@ pub struct Spawn {
     retry_count: usize,
 }
 
+/// Pauses or resumes the source of the pipeline depending on the maintenance mode of the index,
+/// and refreshes the feature flags of the index.
+#[derive(Clone, Copy, Debug)]
+pub struct CheckMaintenanceMode;
+
+/// Stops the source of the pipeline. The downstream actors commit, package, upload, and publish
+/// the current workbench before the pipeline exits successfully.
+#[derive(Clone, Copy, Debug)]
+pub struct DrainPipeline;
+
+/// Returns the topology of the current generation of the pipeline.
+#[derive(Clone, Copy, Debug)]
+pub struct ObservePipelineTopology;
+
+/// Tracks the checkpoint deltas rejected by the metastore across the generations of a pipeline.
+#[derive(Debug, Default)]
+struct CheckpointRejections {
+    last_rejection_opt: Option<IncompatibleCheckpointDelta>,
+    num_identical_rejections: usize,
+}
+
+impl CheckpointRejections {
+    /// Records the rejection that made the last generation fail, if any, and returns the number
+    /// of consecutive generations that failed on this same rejection.
+    fn record(&mut self, rejection_opt: Option<IncompatibleCheckpointDelta>) -> usize {
+        match rejection_opt {
+            Some(rejection) if self.last_rejection_opt.as_ref() == Some(&rejection) => {
+                self.num_identical_rejections += 1;
+            }
+            Some(rejection) => {
+                self.last_rejection_opt = Some(rejection);
+                self.num_identical_rejections = 1;
+            }
+            None => {
+                self.last_rejection_opt = None;
+                self.num_identical_rejections = 0;
+            }
+        }
+        self.num_identical_rejections
+    }
+}
+
 pub struct IndexingPipeline {
     params: IndexingPipelineParams,
     previous_generations_statistics: IndexingStatistics,
@@ -85,6 +265,27 @@ pub struct IndexingPipeline {
     handles: Option<IndexingPipelineHandle>,
     // Killswitch used for the actors in the pipeline. This is not the supervisor killswitch.
     kill_switch: KillSwitch,
+    // Killswitches used for the groups of non-critical actors. They are `kill_switch` unless the
+    // failures of these actors are isolated.
+    non_critical_kill_switches: NonCriticalKillSwitches,
+    // Number of times the non-critical actors were respawned across all the generations.
+    num_non_critical_respawns: usize,
+    // A draining pipeline is never respawned.
+    is_draining: bool,
+    // Whether the source was paused because the index is in maintenance mode.
+    is_source_paused: bool,
+    // Shared by the actors of all the generations of the pipeline.
+    warning_sink: PipelineWarningSink,
+    // Counts the storage requests issued by all the generations of the pipeline.
+    storage_ops_counters: StorageOpsCounters,
+    // Shared by the uploaders of all the generations of the pipeline.
+    upload_circuit_breaker: UploadCircuitBreaker,
+    // Shared by the sources and the indexers of all the generations of the pipeline.
+    source_rate_limiter_opt: Option<SourceRateLimiter>,
+    // Shared by the indexers of all the generations of the pipeline.
+    dead_letter_queue_opt: Option<DeadLetterQueue>,
+    // Checkpoint deltas rejected by the metastore across all the generations of the pipeline.
+    checkpoint_rejections: CheckpointRejections,
 }
 
 #[async_trait]
@@ -107,39 +308,103 @@ impl Actor for IndexingPipeline {
         self.handle(Spawn::default(), ctx).await?;
         self.handle(Observe, ctx).await?;
         self.handle(Supervise, ctx).await?;
+        self.handle(CheckMaintenanceMode, ctx).await?;
         Ok(())
     }
 }
 
 impl IndexingPipeline {
     pub fn new(params: IndexingPipelineParams) -> Self {
+        let upload_circuit_breaker = UploadCircuitBreaker::new(
+            params.pipeline_id.clone(),
+            MAX_CONSECUTIVE_PERMANENT_UPLOAD_FAILURES,
+        );
+        let source_rate_limiter_opt = params
+            .source_config
+            .rate_limit
+            .as_ref()
+            .map(SourceRateLimiter::new);
+        let dead_letter_queue_opt = params
+            .indexing_settings
+            .dead_letter_queue
+            .as_ref()
+            .zip(params.dead_letter_sink_opt.clone())
+            .map(|(dead_letter_queue_settings, dead_letter_sink)| {
+                DeadLetterQueue::new(
+                    params.pipeline_id.clone(),
+                    dead_letter_sink,
+                    dead_letter_queue_settings,
+                )
+            });
         Self {
             params,
             previous_generations_statistics: Default::default(),
             handles: None,
             kill_switch: KillSwitch::default(),
+            non_critical_kill_switches: NonCriticalKillSwitches::default(),
+            num_non_critical_respawns: 0,
+            is_draining: false,
+            is_source_paused: false,
             statistics: IndexingStatistics::default(),
+            warning_sink: PipelineWarningSink::default(),
+            storage_ops_counters: StorageOpsCounters::default(),
+            upload_circuit_breaker,
+            source_rate_limiter_opt,
+            dead_letter_queue_opt,
+            checkpoint_rejections: CheckpointRejections::default(),
         }
     }
 
-    fn supervisables(&self) -> Vec<&dyn Supervisable> {
+    /// Returns the actors on the critical indexing path, from the source to the publisher.
+    fn critical_supervisables(&self) -> Vec<&dyn Supervisable> {
         if let Some(handles) = &self.handles {
-            let supervisables: Vec<&dyn Supervisable> = vec![
+            let mut supervisables: Vec<&dyn Supervisable> = vec![
                 &handles.source,
                 &handles.indexer,
                 &handles.packager,
                 &handles.uploader,
                 &handles.sequencer,
                 &handles.publisher,
-                &handles.garbage_collector,
-                &handles.merge_planner,
-                &handles.merge_split_downloader,
-                &handles.merge_executor,
-                &handles.merge_packager,
-                &handles.merge_uploader,
-                &handles.merge_sequencer,
-                &handles.merge_publisher,
             ];
+            supervisables.extend(
+                handles
+                    .shard_indexers
+                    .iter()
+                    .map(|shard_indexer| shard_indexer as &dyn Supervisable),
+            );
+            supervisables
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Returns the garbage collector, the delete executor, and the actors of the merge
+    /// sub-pipeline, along with their kill switch group.
+    fn non_critical_supervisables(&self) -> Vec<(KillSwitchGroup, &dyn Supervisable)> {
+        if let Some(handles) = &self.handles {
+            let mut supervisables: Vec<(KillSwitchGroup, &dyn Supervisable)> = vec![(
+                KillSwitchGroup::GarbageCollector,
+                &handles.garbage_collector,
+            )];
+            if let Some(delete_executor) = &handles.delete_executor_opt {
+                supervisables.push((KillSwitchGroup::DeleteExecutor, delete_executor));
+            }
+            if let Some(merge_pipeline) = &handles.merge_pipeline_opt {
+                let merge_supervisables: [&dyn Supervisable; 7] = [
+                    &merge_pipeline.merge_planner,
+                    &merge_pipeline.merge_split_downloader,
+                    &merge_pipeline.merge_executor,
+                    &merge_pipeline.merge_packager,
+                    &merge_pipeline.merge_uploader,
+                    &merge_pipeline.merge_sequencer,
+                    &merge_pipeline.merge_publisher,
+                ];
+                supervisables.extend(
+                    merge_supervisables
+                        .into_iter()
+                        .map(|supervisable| (KillSwitchGroup::Merge, supervisable)),
+                );
+            }
             supervisables
         } else {
             Vec::new()
@@ -148,18 +413,35 @@ impl IndexingPipeline {
 
     /// Performs healthcheck on all of the actors in the pipeline,
     /// and consolidates the result.
-    fn healthcheck(&self) -> Health {
+    fn healthcheck(&self) -> PipelineHealth {
         let mut healthy_actors: Vec<&str> = Default::default();
         let mut failure_or_unhealthy_actors: Vec<&str> = Default::default();
         let mut success_actors: Vec<&str> = Default::default();
-        for supervisable in self.supervisables() {
+        // The health of an actor must be checked only once, as it consumes its progress.
+        let critical_supervisables = self.critical_supervisables();
+        let num_critical_actors = critical_supervisables.len();
+        let mut num_healthy_critical_actors = 0;
+        let mut failed_non_critical_groups: Vec<KillSwitchGroup> = Vec::new();
+        let supervisables = critical_supervisables
+            .into_iter()
+            .map(|supervisable| (KillSwitchGroup::Critical, supervisable))
+            .chain(self.non_critical_supervisables());
+        for (kill_switch_group, supervisable) in supervisables {
             match supervisable.health() {
                 Health::Healthy => {
                     // At least one other actor is running.
                     healthy_actors.push(supervisable.name());
+                    if kill_switch_group == KillSwitchGroup::Critical {
+                        num_healthy_critical_actors += 1;
+                    }
                 }
                 Health::FailureOrUnhealthy => {
                     failure_or_unhealthy_actors.push(supervisable.name());
+                    if kill_switch_group != KillSwitchGroup::Critical
+                        && !failed_non_critical_groups.contains(&kill_switch_group)
+                    {
+                        failed_non_critical_groups.push(kill_switch_group);
+                    }
                 }
                 Health::Success => {
                     success_actors.push(supervisable.name());
@@ -168,6 +450,19 @@ impl IndexingPipeline {
         }
 
         if !failure_or_unhealthy_actors.is_empty() {
+            // Once an actor of the critical path exits, the pipeline is winding down and the
+            // non-critical actors are not worth respawning anymore.
+            if self.params.indexing_settings.isolate_non_critical_failures
+                && num_healthy_critical_actors == num_critical_actors
+            {
+                warn!(
+                    pipeline_id=?self.params.pipeline_id,
+                    generation=self.generation(),
+                    failed_or_unhealthy_actors=?failure_or_unhealthy_actors,
+                    "Indexing pipeline non-critical actors failure."
+                );
+                return PipelineHealth::NonCriticalFailure(failed_non_critical_groups);
+            }
             error!(
                 pipeline_id=?self.params.pipeline_id,
                 generation=self.generation(),
@@ -176,7 +471,7 @@ impl IndexingPipeline {
                 success_actors=?success_actors,
                 "Indexing pipeline failure."
             );
-            return Health::FailureOrUnhealthy;
+            return PipelineHealth::FailureOrUnhealthy;
         }
         if healthy_actors.is_empty() {
             // All the actors finished successfully.
@@ -185,7 +480,7 @@ impl IndexingPipeline {
                 generation=self.generation(),
                 "Indexing pipeline success."
             );
-            return Health::Success;
+            return PipelineHealth::Success;
         }
         // No error at this point and there are still some actors running.
         debug!(
@@ -196,26 +491,245 @@ impl IndexingPipeline {
             success_actors=?success_actors,
             "Indexing pipeline running."
         );
-        Health::Healthy
+        PipelineHealth::Healthy
     }
 
     fn generation(&self) -> usize {
         self.statistics.generation
     }
 
+    /// Lists the actors of the current generation and the mailboxes they hold. The actors are
+    /// listed in the order of the data flow, and mailboxes without an actor are left out.
+    fn topology(&self) -> PipelineTopology {
+        let mut actors = Vec::new();
+        let mut edges = Vec::new();
+        if let Some(handles) = &self.handles {
+            let mut add_edge = |from: &ActorTopology, to: &ActorTopology| {
+                edges.push(ActorEdge {
+                    from: from.actor_id.clone(),
+                    to: to.actor_id.clone(),
+                });
+            };
+            let critical = KillSwitchGroup::Critical;
+            let source = ActorTopology::new("source", critical, &handles.source);
+            let indexer = ActorTopology::new("indexer", critical, &handles.indexer);
+            let shard_indexers: Vec<ActorTopology> = handles
+                .shard_indexers
+                .iter()
+                .map(|shard_indexer| ActorTopology::new("shard_indexer", critical, shard_indexer))
+                .collect();
+            let packager = ActorTopology::new("packager", critical, &handles.packager);
+            let uploader = ActorTopology::new("uploader", critical, &handles.uploader);
+            let sequencer = ActorTopology::new("sequencer", critical, &handles.sequencer);
+            let publisher = ActorTopology::new("publisher", critical, &handles.publisher);
+
+            add_edge(&source, &indexer);
+            if shard_indexers.is_empty() {
+                add_edge(&indexer, &packager);
+            }
+            for shard_indexer in &shard_indexers {
+                add_edge(&indexer, shard_indexer);
+                add_edge(shard_indexer, &packager);
+            }
+            add_edge(&packager, &uploader);
+            add_edge(&uploader, &sequencer);
+            add_edge(&sequencer, &publisher);
+            add_edge(&publisher, &source);
+
+            let garbage_collector = ActorTopology::new(
+                "garbage_collector",
+                KillSwitchGroup::GarbageCollector,
+                &handles.garbage_collector,
+            );
+            add_edge(&publisher, &garbage_collector);
+
+            let mut merge_actors = Vec::new();
+            if let (Some(delete_executor), Some(merge_pipeline)) =
+                (&handles.delete_executor_opt, &handles.merge_pipeline_opt)
+            {
+                let delete_executor = ActorTopology::new(
+                    "delete_executor",
+                    KillSwitchGroup::DeleteExecutor,
+                    delete_executor,
+                );
+                let merge = KillSwitchGroup::Merge;
+                let merge_planner =
+                    ActorTopology::new("merge_planner", merge, &merge_pipeline.merge_planner);
+                let merge_split_downloader = ActorTopology::new(
+                    "merge_split_downloader",
+                    merge,
+                    &merge_pipeline.merge_split_downloader,
+                );
+                let merge_executor =
+                    ActorTopology::new("merge_executor", merge, &merge_pipeline.merge_executor);
+                let merge_packager =
+                    ActorTopology::new("merge_packager", merge, &merge_pipeline.merge_packager);
+                let merge_uploader =
+                    ActorTopology::new("merge_uploader", merge, &merge_pipeline.merge_uploader);
+                let merge_sequencer =
+                    ActorTopology::new("merge_sequencer", merge, &merge_pipeline.merge_sequencer);
+                let merge_publisher =
+                    ActorTopology::new("merge_publisher", merge, &merge_pipeline.merge_publisher);
+                add_edge(&publisher, &merge_planner);
+                add_edge(&publisher, &delete_executor);
+                add_edge(&delete_executor, &merge_planner);
+                add_edge(&merge_planner, &merge_split_downloader);
+                add_edge(&merge_split_downloader, &merge_executor);
+                add_edge(&merge_split_downloader, &merge_planner);
+                add_edge(&merge_executor, &merge_packager);
+                add_edge(&merge_packager, &merge_uploader);
+                add_edge(&merge_uploader, &merge_sequencer);
+                add_edge(&merge_sequencer, &merge_publisher);
+                add_edge(&merge_publisher, &merge_planner);
+                add_edge(&merge_publisher, &garbage_collector);
+                add_edge(&merge_publisher, &delete_executor);
+                merge_actors = vec![
+                    delete_executor,
+                    merge_planner,
+                    merge_split_downloader,
+                    merge_executor,
+                    merge_packager,
+                    merge_uploader,
+                    merge_sequencer,
+                    merge_publisher,
+                ];
+            }
+            actors.extend([source, indexer]);
+            actors.extend(shard_indexers);
+            actors.extend([packager, uploader, sequencer, publisher, garbage_collector]);
+            actors.extend(merge_actors);
+        }
+        PipelineTopology {
+            pipeline_id: self.params.pipeline_id.clone(),
+            generation: self.generation(),
+            isolate_non_critical_failures: self
+                .params
+                .indexing_settings
+                .isolate_non_critical_failures,
+            kill_switches: [
+                KillSwitchGroup::Critical,
+                KillSwitchGroup::GarbageCollector,
+                KillSwitchGroup::DeleteExecutor,
+                KillSwitchGroup::Merge,
+            ]
+            .into_iter()
+            .map(|group| KillSwitchTopology {
+                group,
+                is_alive: self
+                    .non_critical_kill_switches
+                    .get(group)
+                    .unwrap_or(&self.kill_switch)
+                    .is_alive(),
+            })
+            .collect(),
+            actors,
+            edges,
+        }
+    }
+
+    /// Refreshes the feature flags shared with the actors from the metadata of the index.
+    fn refresh_feature_flags(&self, index_metadata: &IndexMetadata) {
+        let toggled_flags = self
+            .params
+            .feature_flags
+            .update(&index_metadata.feature_flags);
+        if !toggled_flags.is_empty() {
+            info!(
+                pipeline_id=?self.params.pipeline_id,
+                generation=self.generation(),
+                toggled_flags=?toggled_flags,
+                feature_flags=?index_metadata.feature_flags,
+                "Index feature flags changed."
+            );
+        }
+    }
+
+    /// Returns the number of documents sampled in each split, zero if split sampling is disabled.
+    fn num_sample_docs(&self) -> usize {
+        if self.params.split_sample_storage_opt.is_none() {
+            return 0;
+        }
+        self.params
+            .indexing_settings
+            .split_sampling
+            .as_ref()
+            .map(|split_sampling_settings| split_sampling_settings.num_docs)
+            .unwrap_or(0)
+    }
+
+    /// Returns the guard checking the size of the payloads sent to the metastore against the
+    /// limits of the index.
+    fn metastore_payload_guard(&self) -> MetastorePayloadGuard {
+        MetastorePayloadGuard::new(
+            &self.params.pipeline_id,
+            self.params
+                .indexing_settings
+                .metastore_payload_limits
+                .clone(),
+            self.warning_sink.clone(),
+        )
+    }
+
+    fn merge_policy(&self) -> anyhow::Result<Arc<dyn MergePolicy>> {
+        let merge_policy = load_merge_policy(&self.params.indexing_settings)?;
+        Ok(merge_policy)
+    }
+
+    fn tag_fields(&self) -> anyhow::Result<Vec<NamedField>> {
+        tag_fields(&*self.params.doc_mapper)
+    }
+
+    fn quarantine_directory_opt(&self) -> Option<PathBuf> {
+        self.params
+            .indexing_settings
+            .verify_splits_before_publish
+            .then(|| self.params.indexing_directory.quarantine_directory.clone())
+    }
+
+    async fn list_published_splits(
+        &self,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<Vec<SplitMetadata>> {
+        let published_splits = ctx
+            .protect_future(self.params.metastore.list_splits(
+                &self.params.pipeline_id.index_id,
+                SplitState::Published,
+                None,
+                None,
+            ))
+            .await?
+            .into_iter()
+            .map(|split| split.split_metadata)
+            .collect();
+        Ok(published_splits)
+    }
+
     // TODO this should return an error saying whether we can retry or not.
     #[instrument(name="", level="info", skip_all, fields(index=%self.params.pipeline_id.index_id, gen=self.generation()))]
     async fn spawn_pipeline(&mut self, ctx: &ActorContext<Self>) -> anyhow::Result<()> {
         self.statistics.num_spawn_attempts += 1;
+        // Fetch index_metadata to be sure to have the last updated checkpoint.
+        let index_metadata = self
+            .params
+            .metastore
+            .index_metadata(&self.params.pipeline_id.index_id)
+            .await?;
+        // The pipeline is not spawned while the index is read-only, as its splits could not be
+        // staged nor published.
+        if index_metadata.maintenance_mode {
+            return Err(MetastoreError::MaintenanceMode {
+                index_id: index_metadata.index_id,
+            }
+            .into());
+        }
+        self.refresh_feature_flags(&index_metadata);
         self.kill_switch = KillSwitch::default();
-        let stable_multitenant_merge_policy = StableMultitenantWithTimestampMergePolicy {
-            merge_enabled: self.params.indexing_settings.merge_enabled,
-            merge_factor: self.params.indexing_settings.merge_policy.merge_factor,
-            max_merge_factor: self.params.indexing_settings.merge_policy.max_merge_factor,
-            split_num_docs_target: self.params.indexing_settings.split_num_docs_target,
-            ..Default::default()
-        };
-        let merge_policy: Arc<dyn MergePolicy> = Arc::new(stable_multitenant_merge_policy);
+        self.non_critical_kill_switches = NonCriticalKillSwitches::new(
+            self.params.indexing_settings.isolate_non_critical_failures,
+            &self.kill_switch,
+        );
+        self.is_source_paused = false;
+        let merge_policy = self.merge_policy()?;
         info!(
             index_id=%self.params.pipeline_id.index_id,
             source_id=%self.params.pipeline_id.source_id,
@@ -224,8 +738,27 @@ impl IndexingPipeline {
             merge_policy=?merge_policy,
             "Spawning indexing pipeline.",
         );
-        let split_store = IndexingSplitStore::create_with_local_store(
+        // The uploaders, the merge split downloader, and the garbage collector all access the
+        // index storage through the split store.
+        // Each attempt of a retried request is counted, as object storages bill them.
+        let mut storage = wrap_storage_with_ops_counters(
             self.params.storage.clone(),
+            self.storage_ops_counters.clone(),
+        );
+        if let Some(storage_retry_settings) = &self.params.indexing_settings.storage_retry {
+            let storage_retry_policy = StorageRetryPolicy {
+                request_timeout: storage_retry_settings.request_timeout(),
+                min_throughput_bytes_per_sec: storage_retry_settings.min_throughput_mib_per_sec
+                    * 1024
+                    * 1024,
+                max_retries: storage_retry_settings.max_retries,
+                base_backoff: storage_retry_settings.base_backoff(),
+                max_backoff: storage_retry_settings.max_backoff(),
+            };
+            storage = wrap_storage_with_retry_policy(storage, storage_retry_policy);
+        }
+        let split_store = IndexingSplitStore::create_with_local_store(
+            storage,
             self.params.indexing_directory.cache_directory.as_path(),
             IndexingSplitStoreParams {
                 max_num_bytes: self.params.split_store_max_num_bytes,
@@ -233,194 +766,182 @@ impl IndexingPipeline {
             },
             merge_policy.clone(),
         )?;
-        let published_splits = self
-            .params
-            .metastore
-            .list_splits(
-                &self.params.pipeline_id.index_id,
-                SplitState::Published,
-                None,
-                None,
-            )
-            .await?
-            .into_iter()
-            .map(|split| split.split_metadata)
-            .collect::<Vec<_>>();
+        let published_splits = self.list_published_splits(ctx).await?;
         split_store
             .remove_dangling_splits(&published_splits)
             .await?;
 
-        let (merge_planner_mailbox, merge_planner_inbox) =
-            create_mailbox::<MergePlanner>("MergePlanner".to_string(), QueueCapacity::Unbounded);
-
-        // Garbage colletor
-        let garbage_collector = GarbageCollector::new(
-            self.params.pipeline_id.clone(),
-            split_store.clone(),
-            self.params.metastore.clone(),
-        );
-        let (garbage_collector_mailbox, garbage_collector_handler) = ctx
-            .spawn_actor(garbage_collector)
-            .set_kill_switch(self.kill_switch.clone())
-            .spawn();
-
-        // Merge publisher
-        let merge_publisher = Publisher::new(
-            PublisherType::MergePublisher,
-            self.params.metastore.clone(),
-            merge_planner_mailbox.clone(),
-            garbage_collector_mailbox.clone(),
-            None,
-        );
-        let (merge_publisher_mailbox, merge_publisher_handler) = ctx
-            .spawn_actor(merge_publisher)
-            .set_kill_switch(self.kill_switch.clone())
-            .spawn();
-
-        let merge_sequencer = Sequencer::new(merge_publisher_mailbox);
-        let (merge_sequencer_mailbox, merge_sequencer_handler) = ctx
-            .spawn_actor(merge_sequencer)
-            .set_kill_switch(self.kill_switch.clone())
-            .spawn();
-
-        // Merge uploader
-        let merge_uploader = Uploader::new(
-            "MergeUploader",
-            self.params.metastore.clone(),
-            split_store.clone(),
-            merge_sequencer_mailbox,
-        );
-        let (merge_uploader_mailbox, merge_uploader_handler) = ctx
-            .spawn_actor(merge_uploader)
-            .set_kill_switch(self.kill_switch.clone())
-            .spawn();
-
-        // Merge Packager
-        let index_schema = self.params.doc_mapper.schema();
-        let tag_fields = self
-            .params
-            .doc_mapper
-            .tag_field_names()
-            .iter()
-            .map(|field_name| {
-                index_schema
-                    .get_field(field_name)
-                    .context(format!("Field `{}` must exist in the schema.", field_name))
-                    .map(|field| NamedField {
-                        name: field_name.clone(),
-                        field,
-                        field_type: index_schema.get_field_entry(field).field_type().clone(),
-                    })
-            })
-            .collect::<Result<Vec<_>, _>>()?;
-        let merge_packager =
-            Packager::new("MergePackager", tag_fields.clone(), merge_uploader_mailbox);
-        let (merge_packager_mailbox, merge_packager_handler) = ctx
-            .spawn_actor(merge_packager)
-            .set_kill_switch(self.kill_switch.clone())
-            .spawn();
-
-        let merge_executor =
-            MergeExecutor::new(self.params.pipeline_id.clone(), merge_packager_mailbox);
-        let (merge_executor_mailbox, merge_executor_handler) = ctx
-            .spawn_actor(merge_executor)
-            .set_kill_switch(self.kill_switch.clone())
-            .spawn();
-
-        let merge_split_downloader = MergeSplitDownloader {
-            scratch_directory: self.params.indexing_directory.scratch_directory.clone(),
-            storage: split_store.clone(),
-            merge_executor_mailbox,
-        };
-        let (merge_split_downloader_mailbox, merge_split_downloader_handler) = ctx
-            .spawn_actor(merge_split_downloader)
-            .set_kill_switch(self.kill_switch.clone())
-            .spawn();
-
-        // Merge planner
-        let merge_planner = MergePlanner::new(
-            self.params.pipeline_id.clone(),
+        let tag_fields = self.tag_fields()?;
+        let quarantine_directory_opt = self.quarantine_directory_opt();
+        // The generation is only incremented once the pipeline is spawned.
+        let pipeline_generation = self.generation() + 1;
+        let non_critical_actors = self.spawn_non_critical_actors(
+            ctx,
+            &split_store,
             published_splits,
-            merge_policy.clone(),
-            merge_split_downloader_mailbox,
+            merge_policy,
+            tag_fields.clone(),
+            quarantine_directory_opt.clone(),
+            pipeline_generation,
         );
-        let (merge_planner_mailbox, merge_planner_handler) = ctx
-            .spawn_actor(merge_planner)
-            .set_kill_switch(self.kill_switch.clone())
-            .set_mailboxes(merge_planner_mailbox, merge_planner_inbox)
-            .spawn();
 
         let (source_mailbox, source_inbox) =
             create_mailbox::<SourceActor>("SourceActor".to_string(), QueueCapacity::Unbounded);
 
         // Publisher
-        let publisher = Publisher::new(
+        let mut publisher = Publisher::new(
             PublisherType::MainPublisher,
             self.params.metastore.clone(),
-            merge_planner_mailbox,
-            garbage_collector_mailbox,
+            non_critical_actors.merge_planner_mailbox,
+            non_critical_actors.garbage_collector_mailbox,
+            non_critical_actors.delete_executor_mailbox,
             Some(source_mailbox.clone()),
-        );
+        )
+        .with_metastore_payload_guard(self.metastore_payload_guard());
+        if let Some(publish_notifier) = &self.params.publish_notifier_opt {
+            publisher = publisher.with_publish_notifier(publish_notifier.clone());
+        }
+        if self.params.source_config.strict_ordering {
+            publisher = publisher.with_strict_ordering();
+        }
+        if let Some(local_split_registry) = &self.params.local_split_registry_opt {
+            publisher = publisher.with_local_split_registry(local_split_registry.clone());
+        }
         let (publisher_mailbox, publisher_handler) = ctx
             .spawn_actor(publisher)
             .set_kill_switch(self.kill_switch.clone())
             .spawn();
 
-        let sequencer = Sequencer::new(publisher_mailbox);
+        let mut sequencer = Sequencer::new(publisher_mailbox);
+        if self.params.source_config.strict_ordering {
+            sequencer = sequencer.with_strict_ordering();
+        }
         let (sequencer_mailbox, sequencer_handler) = ctx
             .spawn_actor(sequencer)
             .set_kill_switch(self.kill_switch.clone())
             .spawn();
 
         // Uploader
-        let uploader = Uploader::new(
+        let mut uploader = Uploader::new(
             "Uploader",
             self.params.metastore.clone(),
             split_store.clone(),
             sequencer_mailbox,
-        );
+            self.warning_sink.clone(),
+        )
+        .with_upload_circuit_breaker(self.upload_circuit_breaker.clone())
+        .with_metastore_payload_guard(self.metastore_payload_guard())
+        .with_pipeline_generation(pipeline_generation);
+        if let Some(split_sample_storage) = &self.params.split_sample_storage_opt {
+            uploader = uploader.with_split_sample_storage(split_sample_storage.clone());
+        }
+        if let Some(local_split_registry) = &self.params.local_split_registry_opt {
+            uploader = uploader.with_local_split_registry(local_split_registry.clone());
+        }
         let (uploader_mailbox, uploader_handler) = ctx
             .spawn_actor(uploader)
             .set_kill_switch(self.kill_switch.clone())
             .spawn();
 
         // Packager
-        let packager = Packager::new("Packager", tag_fields, uploader_mailbox);
+        let mut packager = Packager::new(
+            "Packager",
+            tag_fields,
+            quarantine_directory_opt,
+            uploader_mailbox,
+        )
+        .with_num_sample_docs(self.num_sample_docs())
+        .with_calendar_tags(self.params.indexing_settings.calendar_tags.clone())
+        .with_upload_pipelining();
+        if let Some(sharding_settings) = &self.params.indexing_settings.sharding {
+            packager = packager.with_shard_batch_combiner(ShardBatchCombiner::new(
+                self.params.pipeline_id.index_id.clone(),
+                sharding_settings.num_shards,
+                self.params.metastore.clone(),
+            ));
+        }
         let (packager_mailbox, packager_handler) = ctx
             .spawn_actor(packager)
             .set_kill_switch(self.kill_switch.clone())
             .spawn();
         // Indexer
-        let indexer = Indexer::new(
-            self.params.pipeline_id.clone(),
-            self.params.doc_mapper.clone(),
-            self.params.metastore.clone(),
-            self.params.indexing_directory.clone(),
-            self.params.indexing_settings.clone(),
-            packager_mailbox,
-        );
+        let doc_transformer_opt = DocTransformer::try_new(&self.params.source_config.transforms)?;
+        let new_indexer = || {
+            let mut indexer = Indexer::new(
+                self.params.pipeline_id.clone(),
+                self.params.doc_mapper.clone(),
+                self.params.metastore.clone(),
+                self.params.indexing_directory.clone(),
+                self.params.indexing_settings.clone(),
+                packager_mailbox.clone(),
+                self.warning_sink.clone(),
+            )
+            .with_fair_scheduler(self.params.fair_scheduler.clone());
+            if let Some(dead_letter_queue) = &self.dead_letter_queue_opt {
+                indexer = indexer.with_dead_letter_queue(dead_letter_queue.clone());
+            }
+            if let Some(doc_transformer) = &doc_transformer_opt {
+                indexer = indexer.with_doc_transformer(doc_transformer.clone());
+            }
+            if let Some(tenant_router) = &self.params.tenant_router_opt {
+                indexer = indexer.with_tenant_router(tenant_router.clone());
+            }
+            if let Some(config_fingerprint) = &self.params.config_fingerprint {
+                indexer = indexer.with_config_fingerprint(config_fingerprint.clone());
+            }
+            indexer.with_doc_mapping_version(self.params.doc_mapping_version)
+        };
+        let mut shard_indexer_mailboxes = Vec::new();
+        let mut shard_indexer_handlers = Vec::new();
+        let mut indexer = new_indexer();
+        if let Some(source_rate_limiter) = &self.source_rate_limiter_opt {
+            indexer = indexer.with_source_rate_limiter(source_rate_limiter.clone());
+        }
+        if let Some(sharding_settings) = &self.params.indexing_settings.sharding {
+            let memory_limit_reports = MemoryLimitReports::default();
+            for shard_ord in 0..sharding_settings.num_shards {
+                let (shard_indexer_mailbox, shard_indexer_handler) = ctx
+                    .spawn_actor(new_indexer().into_shard(shard_ord, memory_limit_reports.clone()))
+                    .set_kill_switch(self.kill_switch.clone())
+                    .spawn();
+                shard_indexer_mailboxes.push(shard_indexer_mailbox);
+                shard_indexer_handlers.push(shard_indexer_handler);
+            }
+            let doc_router = DocRouter::new(
+                sharding_settings,
+                &self.params.indexing_settings,
+                shard_indexer_mailboxes,
+                memory_limit_reports,
+            )?;
+            indexer = indexer.into_router(doc_router);
+        }
         let (indexer_mailbox, indexer_handler) = ctx
             .spawn_actor(indexer)
             .set_kill_switch(self.kill_switch.clone())
             .spawn();
 
-        // Fetch index_metadata to be sure to have the last updated checkpoint.
-        let index_metadata = self
-            .params
-            .metastore
-            .index_metadata(&self.params.pipeline_id.index_id)
-            .await?;
-        let source_checkpoint = index_metadata
-            .checkpoint
-            .source_checkpoint(&self.params.pipeline_id.source_id)
-            .cloned()
-            .unwrap_or_default(); // TODO Have a stricter check.
+        let source_checkpoint = match &self.params.source_config.source_params {
+            SourceParams::Multiplexed(multiplexed_params) => {
+                index_metadata.checkpoint.multiplexed_source_checkpoint(
+                    multiplexed_params
+                        .sources
+                        .iter()
+                        .map(|source_config| source_config.source_id.as_str()),
+                )
+            }
+            _ => index_metadata
+                .checkpoint
+                .source_checkpoint(&self.params.pipeline_id.source_id)
+                .cloned()
+                .unwrap_or_default(), // TODO Have a stricter check.
+        };
         let source = quickwit_supported_sources()
             .load_source(
                 Arc::new(SourceExecutionContext {
                     metastore: self.params.metastore.clone(),
                     index_id: self.params.pipeline_id.index_id.clone(),
                     source_config: self.params.source_config.clone(),
+                    warning_sink: self.warning_sink.clone(),
                 }),
                 source_checkpoint,
             )
@@ -428,6 +949,7 @@ impl IndexingPipeline {
         let actor_source = SourceActor {
             source,
             indexer_mailbox,
+            rate_limiter_opt: self.source_rate_limiter_opt.clone(),
         };
         let (_source_mailbox, source_handler) = ctx
             .spawn_actor(actor_source)
@@ -441,12 +963,395 @@ impl IndexingPipeline {
         self.handles = Some(IndexingPipelineHandle {
             source: source_handler,
             indexer: indexer_handler,
+            shard_indexers: shard_indexer_handlers,
             packager: packager_handler,
             uploader: uploader_handler,
             sequencer: sequencer_handler,
             publisher: publisher_handler,
+            garbage_collector: non_critical_actors.garbage_collector,
+            delete_executor_opt: non_critical_actors.delete_executor_opt,
+            merge_pipeline_opt: non_critical_actors.merge_pipeline_opt,
+            merge_planner_leadership: non_critical_actors.merge_planner_leadership,
+            split_store,
+        });
+        Ok(())
+    }
+
+    /// Spawns the garbage collector, and the merge sub-pipeline along with the delete executor
+    /// unless the merge pipeline is disabled.
+    #[allow(clippy::too_many_arguments)]
+    fn spawn_non_critical_actors(
+        &self,
+        ctx: &ActorContext<Self>,
+        split_store: &IndexingSplitStore,
+        published_splits: Vec<SplitMetadata>,
+        merge_policy: Arc<dyn MergePolicy>,
+        tag_fields: Vec<NamedField>,
+        quarantine_directory_opt: Option<PathBuf>,
+        pipeline_generation: usize,
+    ) -> NonCriticalActors {
+        let (garbage_collector_mailbox, garbage_collector_handler) =
+            self.spawn_garbage_collector(ctx, split_store);
+        let merge_planner_leadership = MergePlannerLeadership::default();
+        if !self.params.merge_pipeline_enabled {
+            // The publisher notifies the merge planner and the delete executor on a best effort
+            // basis, so they can be replaced by mailboxes without actors.
+            let (merge_planner_mailbox, _merge_planner_inbox) = create_mailbox::<MergePlanner>(
+                "MergePlanner".to_string(),
+                QueueCapacity::Unbounded,
+            );
+            let (delete_executor_mailbox, _delete_executor_inbox) = create_mailbox::<DeleteExecutor>(
+                "DeleteExecutor".to_string(),
+                QueueCapacity::Unbounded,
+            );
+            return NonCriticalActors {
+                garbage_collector_mailbox,
+                garbage_collector: garbage_collector_handler,
+                merge_planner_mailbox,
+                delete_executor_mailbox,
+                delete_executor_opt: None,
+                merge_pipeline_opt: None,
+                merge_planner_leadership,
+            };
+        }
+        let (merge_planner_mailbox, merge_planner_inbox) =
+            create_mailbox::<MergePlanner>("MergePlanner".to_string(), QueueCapacity::Unbounded);
+        let (delete_executor_mailbox, delete_executor_handler) = self.spawn_delete_executor(
+            ctx,
+            merge_planner_mailbox.clone(),
+            merge_planner_leadership.clone(),
+        );
+        let merge_pipeline_handle = self.spawn_merge_pipeline(
+            ctx,
+            split_store,
+            published_splits,
+            merge_policy,
+            tag_fields,
+            quarantine_directory_opt,
+            merge_planner_mailbox.clone(),
+            merge_planner_inbox,
+            garbage_collector_mailbox.clone(),
+            delete_executor_mailbox.clone(),
+            merge_planner_leadership.clone(),
+            pipeline_generation,
+        );
+        NonCriticalActors {
+            garbage_collector_mailbox,
             garbage_collector: garbage_collector_handler,
+            merge_planner_mailbox,
+            delete_executor_mailbox,
+            delete_executor_opt: Some(delete_executor_handler),
+            merge_pipeline_opt: Some(merge_pipeline_handle),
+            merge_planner_leadership,
+        }
+    }
+
+    /// Kills and respawns a group of non-critical actors, leaving the other groups, and the
+    /// actors of the critical path and their workbench untouched. The actors holding the
+    /// mailboxes of the respawned group are pointed to the new mailboxes.
+    ///
+    /// When the merge sub-pipeline is respawned, the merges in flight are dropped, and planned
+    /// again by the new merge planner from the published splits.
+    async fn respawn_non_critical_actors(
+        &mut self,
+        kill_switch_group: KillSwitchGroup,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<()> {
+        let published_splits = if kill_switch_group == KillSwitchGroup::Merge {
+            self.list_published_splits(ctx).await?
+        } else {
+            Vec::new()
+        };
+        let tag_fields = self.tag_fields()?;
+        let merge_policy = self.merge_policy()?;
+        let mut handles = if let Some(handles) = self.handles.take() {
+            handles
+        } else {
+            return Ok(());
+        };
+        self.non_critical_kill_switches.renew(kill_switch_group);
+        let replacement = match kill_switch_group {
+            KillSwitchGroup::GarbageCollector => {
+                handles.garbage_collector.kill().await;
+                let (garbage_collector_mailbox, garbage_collector_handler) =
+                    self.spawn_garbage_collector(ctx, &handles.split_store);
+                handles.garbage_collector = garbage_collector_handler;
+                ReplaceNonCriticalMailboxes {
+                    garbage_collector_mailbox_opt: Some(garbage_collector_mailbox),
+                    ..Default::default()
+                }
+            }
+            KillSwitchGroup::DeleteExecutor => {
+                if let Some(delete_executor) = handles.delete_executor_opt.take() {
+                    delete_executor.kill().await;
+                }
+                let merge_planner_mailbox = handles
+                    .merge_pipeline_opt
+                    .as_ref()
+                    .map(|merge_pipeline| merge_pipeline.merge_planner.mailbox().clone())
+                    .expect("The delete executor should be spawned along with the merge pipeline.");
+                let (delete_executor_mailbox, delete_executor_handler) = self
+                    .spawn_delete_executor(
+                        ctx,
+                        merge_planner_mailbox,
+                        handles.merge_planner_leadership.clone(),
+                    );
+                handles.delete_executor_opt = Some(delete_executor_handler);
+                ReplaceNonCriticalMailboxes {
+                    delete_executor_mailbox_opt: Some(delete_executor_mailbox),
+                    ..Default::default()
+                }
+            }
+            KillSwitchGroup::Merge => {
+                if let Some(merge_pipeline) = handles.merge_pipeline_opt.take() {
+                    merge_pipeline.kill().await;
+                }
+                let delete_executor_mailbox = handles
+                    .delete_executor_opt
+                    .as_ref()
+                    .map(|delete_executor| delete_executor.mailbox().clone())
+                    .expect("The delete executor should be spawned along with the merge pipeline.");
+                let (merge_planner_mailbox, merge_planner_inbox) = create_mailbox::<MergePlanner>(
+                    "MergePlanner".to_string(),
+                    QueueCapacity::Unbounded,
+                );
+                let merge_pipeline_handle = self.spawn_merge_pipeline(
+                    ctx,
+                    &handles.split_store,
+                    published_splits,
+                    merge_policy,
+                    tag_fields,
+                    self.quarantine_directory_opt(),
+                    merge_planner_mailbox.clone(),
+                    merge_planner_inbox,
+                    handles.garbage_collector.mailbox().clone(),
+                    delete_executor_mailbox.clone(),
+                    handles.merge_planner_leadership.clone(),
+                    self.generation(),
+                );
+                handles.merge_pipeline_opt = Some(merge_pipeline_handle);
+                // The delete executor may have failed as well, in which case it is respawned
+                // with the new mailbox along with its own group.
+                let _ = ctx
+                    .send_message(
+                        &delete_executor_mailbox,
+                        ReplaceNonCriticalMailboxes {
+                            merge_planner_mailbox_opt: Some(merge_planner_mailbox.clone()),
+                            ..Default::default()
+                        },
+                    )
+                    .await;
+                ReplaceNonCriticalMailboxes {
+                    merge_planner_mailbox_opt: Some(merge_planner_mailbox),
+                    ..Default::default()
+                }
+            }
+            KillSwitchGroup::Critical => {
+                unreachable!("The critical actors are never respawned on their own.")
+            }
+        };
+        // Besides the main publisher, the merge publisher holds the mailboxes of the garbage
+        // collector and of the delete executor.
+        let merge_publisher_mailbox_opt = handles
+            .merge_pipeline_opt
+            .as_ref()
+            .filter(|_| kill_switch_group != KillSwitchGroup::Merge)
+            .map(|merge_pipeline| merge_pipeline.merge_publisher.mailbox().clone());
+        let publisher_mailbox = handles.publisher.mailbox().clone();
+        self.handles = Some(handles);
+        self.num_non_critical_respawns += 1;
+        info!(
+            pipeline_id=?self.params.pipeline_id,
+            generation=self.generation(),
+            kill_switch_group=?kill_switch_group,
+            num_non_critical_respawns=self.num_non_critical_respawns,
+            "Respawned indexing pipeline non-critical actors."
+        );
+        if let Some(merge_publisher_mailbox) = merge_publisher_mailbox_opt {
+            let _ = ctx
+                .send_message(&merge_publisher_mailbox, replacement.clone())
+                .await;
+        }
+        ctx.send_message(&publisher_mailbox, replacement).await?;
+        Ok(())
+    }
+
+    fn spawn_garbage_collector(
+        &self,
+        ctx: &ActorContext<Self>,
+        split_store: &IndexingSplitStore,
+    ) -> (Mailbox<GarbageCollector>, ActorHandle<GarbageCollector>) {
+        let garbage_collector = GarbageCollector::new(
+            self.params.pipeline_id.clone(),
+            split_store.clone(),
+            self.params.metastore.clone(),
+            self.params.indexing_settings.split_deletion_grace_period(),
+        );
+        ctx.spawn_actor(garbage_collector)
+            .set_kill_switch(self.non_critical_kill_switches.garbage_collector.clone())
+            .spawn()
+    }
+
+    /// Spawns the delete executor, which applies delete tasks through the merge sub-pipeline.
+    fn spawn_delete_executor(
+        &self,
+        ctx: &ActorContext<Self>,
+        merge_planner_mailbox: Mailbox<MergePlanner>,
+        merge_planner_leadership: MergePlannerLeadership,
+    ) -> (Mailbox<DeleteExecutor>, ActorHandle<DeleteExecutor>) {
+        let mut delete_executor = DeleteExecutor::new(
+            self.params.pipeline_id.clone(),
+            self.params.metastore.clone(),
+            merge_planner_mailbox,
+        );
+        if self.params.indexing_settings.merge_coordination.is_some() {
+            delete_executor =
+                delete_executor.with_merge_planner_leadership(merge_planner_leadership);
+        }
+        if self
+            .params
+            .doc_mapper
+            .expire_timestamp_field_name()
+            .is_some()
+        {
+            delete_executor = delete_executor.with_expired_docs_purge();
+        }
+        ctx.spawn_actor(delete_executor)
+            .set_kill_switch(self.non_critical_kill_switches.delete_executor.clone())
+            .spawn()
+    }
+
+    /// Spawns the merge sub-pipeline, from the merge planner reading `merge_planner_inbox` to the
+    /// merge publisher.
+    #[allow(clippy::too_many_arguments)]
+    fn spawn_merge_pipeline(
+        &self,
+        ctx: &ActorContext<Self>,
+        split_store: &IndexingSplitStore,
+        published_splits: Vec<SplitMetadata>,
+        merge_policy: Arc<dyn MergePolicy>,
+        tag_fields: Vec<NamedField>,
+        quarantine_directory_opt: Option<PathBuf>,
+        merge_planner_mailbox: Mailbox<MergePlanner>,
+        merge_planner_inbox: Inbox<MergePlanner>,
+        garbage_collector_mailbox: Mailbox<GarbageCollector>,
+        delete_executor_mailbox: Mailbox<DeleteExecutor>,
+        merge_planner_leadership: MergePlannerLeadership,
+        pipeline_generation: usize,
+    ) -> MergePipelineHandle {
+        let merge_kill_switch = &self.non_critical_kill_switches.merge;
+
+        // Merge publisher
+        let merge_publisher = Publisher::new(
+            PublisherType::MergePublisher,
+            self.params.metastore.clone(),
+            merge_planner_mailbox.clone(),
+            garbage_collector_mailbox,
+            delete_executor_mailbox,
+            None,
+        )
+        .with_metastore_payload_guard(self.metastore_payload_guard());
+        let (merge_publisher_mailbox, merge_publisher_handler) = ctx
+            .spawn_actor(merge_publisher)
+            .set_kill_switch(merge_kill_switch.clone())
+            .spawn();
+
+        let merge_sequencer = Sequencer::new(merge_publisher_mailbox);
+        let (merge_sequencer_mailbox, merge_sequencer_handler) = ctx
+            .spawn_actor(merge_sequencer)
+            .set_kill_switch(merge_kill_switch.clone())
+            .spawn();
+
+        // Merge uploader
+        let mut merge_uploader = Uploader::new(
+            "MergeUploader",
+            self.params.metastore.clone(),
+            split_store.clone(),
+            merge_sequencer_mailbox,
+            self.warning_sink.clone(),
+        )
+        .with_metastore_payload_guard(self.metastore_payload_guard())
+        .with_pipeline_generation(pipeline_generation);
+        if let Some(split_sample_storage) = &self.params.split_sample_storage_opt {
+            merge_uploader = merge_uploader.with_split_sample_storage(split_sample_storage.clone());
+        }
+        let (merge_uploader_mailbox, merge_uploader_handler) = ctx
+            .spawn_actor(merge_uploader)
+            .set_kill_switch(merge_kill_switch.clone())
+            .spawn();
+
+        // Merge Packager
+        let merge_packager = Packager::new(
+            "MergePackager",
+            tag_fields,
+            quarantine_directory_opt,
+            merge_uploader_mailbox,
+        )
+        .with_num_sample_docs(self.num_sample_docs())
+        .with_calendar_tags(self.params.indexing_settings.calendar_tags.clone());
+        let (merge_packager_mailbox, merge_packager_handler) = ctx
+            .spawn_actor(merge_packager)
+            .set_kill_switch(merge_kill_switch.clone())
+            .spawn();
+
+        let dedup_window_opt = self
+            .params
+            .indexing_settings
+            .deduplication
+            .as_ref()
+            .map(|deduplication_settings| deduplication_settings.window());
+        let merge_executor = MergeExecutor::new(
+            self.params.pipeline_id.clone(),
+            self.params.metastore.clone(),
+            self.params.doc_mapper.clone(),
+            dedup_window_opt,
+            merge_packager_mailbox,
+        )
+        .with_indexing_resources(&self.params.indexing_settings.resources)
+        .with_docstore_settings(&self.params.indexing_settings);
+        let (merge_executor_mailbox, merge_executor_handler) = ctx
+            .spawn_actor(merge_executor)
+            .set_kill_switch(merge_kill_switch.clone())
+            .spawn();
+
+        let merge_split_downloader = MergeSplitDownloader {
+            index_id: self.params.pipeline_id.index_id.clone(),
+            scratch_directory: self.params.indexing_directory.scratch_directory.clone(),
+            storage: split_store.clone(),
+            metastore: self.params.metastore.clone(),
+            merge_executor_mailbox,
+            merge_planner_mailbox: merge_planner_mailbox.clone(),
+            merge_scheduler: self.params.merge_scheduler.clone(),
+            warning_sink: self.warning_sink.clone(),
+        };
+        let (merge_split_downloader_mailbox, merge_split_downloader_handler) = ctx
+            .spawn_actor(merge_split_downloader)
+            .set_kill_switch(merge_kill_switch.clone())
+            .spawn();
+
+        // Merge planner
+        let mut merge_planner = MergePlanner::new(
+            self.params.pipeline_id.clone(),
+            published_splits,
+            merge_policy,
+            merge_split_downloader_mailbox,
+        );
+        if let Some(merge_coordination_settings) =
+            self.params.indexing_settings.merge_coordination.clone()
+        {
+            merge_planner = merge_planner.with_coordination(
+                self.params.metastore.clone(),
+                merge_coordination_settings,
+                merge_planner_leadership,
+            );
+        }
+        let (_merge_planner_mailbox, merge_planner_handler) = ctx
+            .spawn_actor(merge_planner)
+            .set_kill_switch(merge_kill_switch.clone())
+            .set_mailboxes(merge_planner_mailbox, merge_planner_inbox)
+            .spawn();
 
+        MergePipelineHandle {
             merge_planner: merge_planner_handler,
             merge_split_downloader: merge_split_downloader_handler,
             merge_executor: merge_executor_handler,
@@ -454,8 +1359,7 @@ impl IndexingPipeline {
             merge_uploader: merge_uploader_handler,
             merge_sequencer: merge_sequencer_handler,
             merge_publisher: merge_publisher_handler,
-        });
-        Ok(())
+        }
     }
 
     // retry_count, wait_time
@@ -471,24 +1375,35 @@ impl IndexingPipeline {
         Duration::from_secs(2u64.pow(max_power) as u64).min(MAX_RETRY_DELAY)
     }
 
-    async fn terminate(&mut self) {
+    /// Kills all the actors of the pipeline and returns the final state of the publisher.
+    /// Kills the actors of the pipeline, and returns the exit status of the packager along with
+    /// the counters of the publisher.
+    async fn terminate(&mut self) -> Option<(ActorExitStatus, PublisherCounters)> {
         self.kill_switch.kill();
-        if let Some(handlers) = self.handles.take() {
-            tokio::join!(
-                handlers.source.kill(),
-                handlers.indexer.kill(),
-                handlers.packager.kill(),
-                handlers.uploader.kill(),
-                handlers.publisher.kill(),
-                handlers.garbage_collector.kill(),
-                handlers.merge_planner.kill(),
-                handlers.merge_split_downloader.kill(),
-                handlers.merge_executor.kill(),
-                handlers.merge_packager.kill(),
-                handlers.merge_uploader.kill(),
-                handlers.merge_publisher.kill(),
-            );
+        self.non_critical_kill_switches.kill();
+        let handlers = self.handles.take()?;
+        let (_, _, _, (packager_exit_status, _), _, _, (_, publisher_counters), _) = tokio::join!(
+            handlers.source.kill(),
+            handlers.indexer.kill(),
+            futures::future::join_all(
+                handlers
+                    .shard_indexers
+                    .into_iter()
+                    .map(|shard_indexer| shard_indexer.kill())
+            ),
+            handlers.packager.kill(),
+            handlers.uploader.kill(),
+            handlers.sequencer.kill(),
+            handlers.publisher.kill(),
+            handlers.garbage_collector.kill(),
+        );
+        if let Some(delete_executor) = handlers.delete_executor_opt {
+            delete_executor.kill().await;
         }
+        if let Some(merge_pipeline) = handlers.merge_pipeline_opt {
+            merge_pipeline.kill().await;
+        }
+        Some((packager_exit_status, publisher_counters))
     }
 }
 
@@ -501,21 +1416,49 @@ impl Handler<Observe> for IndexingPipeline {
         ctx: &ActorContext<Self>,
     ) -> Result<(), ActorExitStatus> {
         if let Some(handles) = &self.handles {
-            let (indexer_counters, uploader_counters, publisher_counters) = join!(
+            let (indexer_counters, shard_indexers_counters, uploader_counters, publisher_counters) = join!(
                 handles.indexer.observe(),
+                futures::future::join_all(
+                    handles
+                        .shard_indexers
+                        .iter()
+                        .map(|shard_indexer| shard_indexer.observe())
+                ),
                 handles.uploader.observe(),
                 handles.publisher.observe(),
             );
-            self.statistics = self
+            let mut indexer_counters = (*indexer_counters).clone();
+            for (shard_ord, shard_indexer_counters) in shard_indexers_counters.iter().enumerate() {
+                indexer_counters.add_shard_counters(shard_indexer_counters);
+                // The first shard receives the checkpoint deltas of all the routed batches.
+                if shard_ord == 0 {
+                    indexer_counters.indexed_watermark =
+                        shard_indexer_counters.indexed_watermark.clone();
+                }
+            }
+            let mut statistics = self
                 .previous_generations_statistics
                 .clone()
-                .add_actor_counters(
-                    &*indexer_counters,
-                    &*uploader_counters,
-                    &*publisher_counters,
-                )
+                .add_actor_counters(&indexer_counters, &*uploader_counters, &*publisher_counters);
+            if let Some(merge_pipeline) = &handles.merge_pipeline_opt {
+                // The merge executor cannot be observed while merging, but its counters are
+                // shared.
+                statistics = statistics
+                    .add_merge_executor_counters(&merge_pipeline.merge_executor.last_observation());
+            }
+            self.statistics = statistics
                 .set_generation(self.statistics.generation)
-                .set_num_spawn_attempts(self.statistics.num_spawn_attempts);
+                .set_num_spawn_attempts(self.statistics.num_spawn_attempts)
+                .set_num_non_critical_respawns(self.num_non_critical_respawns)
+                .set_warnings(self.warning_sink.warnings())
+                .set_storage_ops(self.storage_ops_counters.counts())
+                .set_upload_circuit_breaker_trip(self.upload_circuit_breaker.trip_opt())
+                .set_source_throttling(
+                    self.source_rate_limiter_opt
+                        .as_ref()
+                        .map(SourceRateLimiter::counters)
+                        .unwrap_or_default(),
+                );
         }
         ctx.schedule_self_msg(Duration::from_secs(1), Observe).await;
         Ok(())
@@ -533,13 +1476,121 @@ impl Handler<Supervise> for IndexingPipeline {
     ) -> Result<(), ActorExitStatus> {
         if self.handles.is_some() {
             match self.healthcheck() {
-                Health::Healthy => {}
-                Health::FailureOrUnhealthy => {
-                    self.terminate().await;
-                    ctx.schedule_self_msg(quickwit_actors::HEARTBEAT, Spawn { retry_count: 0 })
+                PipelineHealth::Healthy => {}
+                PipelineHealth::NonCriticalFailure(failed_groups) => {
+                    // The failed actors are left as is until the next supervision if they cannot
+                    // be respawned.
+                    for kill_switch_group in failed_groups {
+                        if let Err(error) = self
+                            .respawn_non_critical_actors(kill_switch_group, ctx)
+                            .await
+                        {
+                            error!(
+                                pipeline_id=?self.params.pipeline_id,
+                                generation=self.generation(),
+                                kill_switch_group=?kill_switch_group,
+                                error=?error,
+                                "Failed to respawn indexing pipeline non-critical actors."
+                            );
+                        }
+                    }
+                }
+                PipelineHealth::FailureOrUnhealthy => {
+                    let terminated_opt = self.terminate().await;
+                    // Respawning the pipeline would build the same corrupted splits over and over
+                    // again.
+                    if let Some((ActorExitStatus::Failure(error), _)) = &terminated_opt {
+                        if error
+                            .downcast_ref::<RepeatedSplitVerificationFailure>()
+                            .is_some()
+                        {
+                            error!(
+                                pipeline_id=?self.params.pipeline_id,
+                                generation=self.generation(),
+                                error=?error,
+                                "Indexing pipeline keeps producing corrupted splits, giving up."
+                            );
+                            return Err(ActorExitStatus::Failure(error.clone()));
+                        }
+                    }
+                    // The respawned pipeline reloads the checkpoint, which resolves a rejection
+                    // caused by a concurrent publish. Replaying the same positions and hitting
+                    // the same rejection over and over again, however, is pointless.
+                    let incompatible_delta_opt = terminated_opt
+                        .and_then(|(_, counters)| counters.incompatible_checkpoint_delta_opt);
+                    let num_identical_rejections = self
+                        .checkpoint_rejections
+                        .record(incompatible_delta_opt.clone());
+                    if let Some(incompatible_delta) = incompatible_delta_opt {
+                        if num_identical_rejections >= MAX_IDENTICAL_CHECKPOINT_REJECTIONS {
+                            error!(
+                                pipeline_id=?self.params.pipeline_id,
+                                generation=self.generation(),
+                                num_rejections=num_identical_rejections,
+                                error=%incompatible_delta,
+                                "Indexing pipeline is poisoned by an overlapping checkpoint delta, giving up."
+                            );
+                            return Err(ActorExitStatus::from(anyhow::Error::new(
+                                MetastoreError::from(incompatible_delta),
+                            )));
+                        }
+                        warn!(
+                            pipeline_id=?self.params.pipeline_id,
+                            generation=self.generation(),
+                            num_rejections=num_identical_rejections,
+                            error=%incompatible_delta,
+                            "Metastore rejected an overlapping checkpoint delta, respawning pipeline."
+                        );
+                    }
+                    if self.is_draining {
+                        return Err(ActorExitStatus::from(anyhow::anyhow!(
+                            "Indexing pipeline failed while draining."
+                        )));
+                    }
+                    // Respawning the pipeline right away would hammer a misconfigured storage:
+                    // the pipeline stays paused until the cool-down elapses, and is paused again
+                    // if the first upload after the respawn fails for the same reason.
+                    if let Some(trip) = self.upload_circuit_breaker.trip_opt() {
+                        error!(
+                            pipeline_id=?self.params.pipeline_id,
+                            generation=self.generation(),
+                            reason=%trip.reason,
+                            error=%trip.message,
+                            cool_down=?UPLOAD_CIRCUIT_BREAKER_COOL_DOWN,
+                            "Upload circuit breaker tripped, pausing indexing pipeline."
+                        );
+                        self.warning_sink.warn(
+                            PipelineWarningKind::UploadCircuitBreakerTripped,
+                            "IndexingPipeline",
+                            format!(
+                                "Failed to store splits {} times in a row ({}): {}. Pausing the \
+                                 pipeline for {} seconds.",
+                                trip.num_consecutive_failures,
+                                trip.reason,
+                                trip.message,
+                                UPLOAD_CIRCUIT_BREAKER_COOL_DOWN.as_secs()
+                            ),
+                        );
+                        self.statistics.warnings = self.warning_sink.warnings();
+                        self.statistics.upload_circuit_breaker_trip = Some(trip);
+                        ctx.schedule_self_msg(
+                            UPLOAD_CIRCUIT_BREAKER_COOL_DOWN,
+                            Spawn { retry_count: 0 },
+                        )
                         .await;
+                    } else if num_identical_rejections > 0 {
+                        // Backs off so that the concurrent publish causing the rejection, if any,
+                        // has time to complete.
+                        let retry_delay =
+                            Self::wait_duration_before_retry(num_identical_rejections - 1);
+                        ctx.schedule_self_msg(retry_delay, Spawn { retry_count: 0 })
+                            .await;
+                    } else {
+                        ctx.schedule_self_msg(quickwit_actors::HEARTBEAT, Spawn { retry_count: 0 })
+                            .await;
+                    }
                 }
-                Health::Success => {
+                PipelineHealth::Success => {
                     return Err(ActorExitStatus::Success);
                 }
             }
@@ -551,36 +1602,211 @@ impl Handler<Supervise> for IndexingPipeline {
 }
 
 #[async_trait]
-impl Handler<Spawn> for IndexingPipeline {
-    type Reply = ();
+impl Handler<Spawn> for IndexingPipeline {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        spawn: Spawn,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        if self.handles.is_some() {
+            return Ok(());
+        }
+        if self.is_draining {
+            // The pipeline was drained while waiting to be respawned: there is nothing left to
+            // publish.
+            return Err(ActorExitStatus::Success);
+        }
+        self.previous_generations_statistics.num_spawn_attempts = 1 + spawn.retry_count;
+        if let Err(spawn_error) = self.spawn_pipeline(ctx).await {
+            if let Some(MetastoreError::IndexDoesNotExist { .. }) =
+                spawn_error.downcast_ref::<MetastoreError>()
+            {
+                info!(error = ?spawn_error, "Could not spawn pipeline, index might have been deleted.");
+                return Err(ActorExitStatus::Success);
+            }
+            if let Some(MetastoreError::MaintenanceMode { .. }) =
+                spawn_error.downcast_ref::<MetastoreError>()
+            {
+                // Waiting for the end of the maintenance is not a failure: the retry delay does
+                // not grow.
+                info!(
+                    index_id=%self.params.pipeline_id.index_id,
+                    "Index is in maintenance mode, waiting before spawning pipeline."
+                );
+                ctx.schedule_self_msg(MAINTENANCE_MODE_CHECK_INTERVAL, spawn)
+                    .await;
+                return Ok(());
+            }
+            let retry_delay = Self::wait_duration_before_retry(spawn.retry_count);
+            error!(error = ?spawn_error, retry_count = spawn.retry_count, retry_delay = ?retry_delay, "Error while spawning indexing pipeline, retrying after some time.");
+            ctx.schedule_self_msg(
+                retry_delay,
+                Spawn {
+                    retry_count: spawn.retry_count + 1,
+                },
+            )
+            .await;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<CheckMaintenanceMode> for IndexingPipeline {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _: CheckMaintenanceMode,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        if let Some(handles) = &self.handles {
+            match ctx
+                .protect_future(
+                    self.params
+                        .metastore
+                        .index_metadata(&self.params.pipeline_id.index_id),
+                )
+                .await
+            {
+                Ok(index_metadata) => {
+                    self.refresh_feature_flags(&index_metadata);
+                    if index_metadata.maintenance_mode && !self.is_source_paused {
+                        info!(
+                            pipeline_id=?self.params.pipeline_id,
+                            generation=self.generation(),
+                            "Index is in maintenance mode, pausing source."
+                        );
+                        handles.source.pause();
+                        self.is_source_paused = true;
+                    } else if !index_metadata.maintenance_mode && self.is_source_paused {
+                        info!(
+                            pipeline_id=?self.params.pipeline_id,
+                            generation=self.generation(),
+                            "Index is out of maintenance mode, resuming source."
+                        );
+                        handles.source.resume();
+                        self.is_source_paused = false;
+                    }
+                }
+                Err(error) => {
+                    // The supervisor takes care of the failures caused by an unavailable
+                    // metastore.
+                    debug!(error=?error, "Failed to check maintenance mode.");
+                }
+            }
+        }
+        ctx.schedule_self_msg(MAINTENANCE_MODE_CHECK_INTERVAL, CheckMaintenanceMode)
+            .await;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<DrainPipeline> for IndexingPipeline {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _: DrainPipeline,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        if self.is_draining {
+            return Ok(());
+        }
+        self.is_draining = true;
+        info!(
+            pipeline_id=?self.params.pipeline_id,
+            generation=self.generation(),
+            "Draining indexing pipeline."
+        );
+        if let Some(handles) = &self.handles {
+            // The source may have already exited, in which case the pipeline drains by itself.
+            let _ = ctx.send_exit_with_success(handles.source.mailbox()).await;
+        } else {
+            return Err(ActorExitStatus::Success);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<ObservePipelineTopology> for IndexingPipeline {
+    type Reply = PipelineTopology;
+
+    async fn handle(
+        &mut self,
+        _: ObservePipelineTopology,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<PipelineTopology, ActorExitStatus> {
+        Ok(self.topology())
+    }
+}
+
+/// Hands over a force merge to the merge planner of the pipeline. Replies whether the pipeline
+/// runs a merge pipeline.
+#[async_trait]
+impl Handler<ForceMerge> for IndexingPipeline {
+    type Reply = bool;
+
+    async fn handle(
+        &mut self,
+        force_merge: ForceMerge,
+        ctx: &ActorContext<Self>,
+    ) -> Result<bool, ActorExitStatus> {
+        let merge_pipeline = match self
+            .handles
+            .as_ref()
+            .and_then(|handles| handles.merge_pipeline_opt.as_ref())
+        {
+            Some(merge_pipeline) => merge_pipeline,
+            None => return Ok(false),
+        };
+        info!(
+            pipeline_id=?self.params.pipeline_id,
+            num_splits=force_merge.splits.len(),
+            target_num_splits=force_merge.target_num_splits,
+            "Handing over force merge to the merge planner."
+        );
+        // The merge planner may have failed, in which case the force merge is dropped.
+        let _ = ctx
+            .send_message(merge_pipeline.merge_planner.mailbox(), force_merge)
+            .await;
+        Ok(true)
+    }
+}
+
+/// Hands over the splits to recompress to the merge planner of the pipeline. Replies whether the
+/// pipeline runs a merge pipeline.
+#[async_trait]
+impl Handler<RecompressSplits> for IndexingPipeline {
+    type Reply = bool;
 
     async fn handle(
         &mut self,
-        spawn: Spawn,
+        recompress_splits: RecompressSplits,
         ctx: &ActorContext<Self>,
-    ) -> Result<(), ActorExitStatus> {
-        if self.handles.is_some() {
-            return Ok(());
-        }
-        self.previous_generations_statistics.num_spawn_attempts = 1 + spawn.retry_count;
-        if let Err(spawn_error) = self.spawn_pipeline(ctx).await {
-            if let Some(MetastoreError::IndexDoesNotExist { .. }) =
-                spawn_error.downcast_ref::<MetastoreError>()
-            {
-                info!(error = ?spawn_error, "Could not spawn pipeline, index might have been deleted.");
-                return Err(ActorExitStatus::Success);
-            }
-            let retry_delay = Self::wait_duration_before_retry(spawn.retry_count);
-            error!(error = ?spawn_error, retry_count = spawn.retry_count, retry_delay = ?retry_delay, "Error while spawning indexing pipeline, retrying after some time.");
-            ctx.schedule_self_msg(
-                retry_delay,
-                Spawn {
-                    retry_count: spawn.retry_count + 1,
-                },
-            )
+    ) -> Result<bool, ActorExitStatus> {
+        let merge_pipeline = match self
+            .handles
+            .as_ref()
+            .and_then(|handles| handles.merge_pipeline_opt.as_ref())
+        {
+            Some(merge_pipeline) => merge_pipeline,
+            None => return Ok(false),
+        };
+        info!(
+            pipeline_id=?self.params.pipeline_id,
+            num_splits=recompress_splits.splits.len(),
+            "Handing over recompress to the merge planner."
+        );
+        // The merge planner may have failed, in which case the recompress is dropped.
+        let _ = ctx
+            .send_message(merge_pipeline.merge_planner.mailbox(), recompress_splits)
             .await;
-        }
-        Ok(())
+        Ok(true)
     }
 }
 
@@ -594,6 +1820,37 @@ pub struct IndexingPipelineParams {
     pub split_store_max_num_splits: usize,
     pub metastore: Arc<dyn Metastore>,
     pub storage: Arc<dyn Storage>,
+    /// Whether to spawn the merge sub-pipeline and the delete executor. Disabling it saves the
+    /// resources of these actors for ephemeral pipelines, such as backfills, but their splits are
+    /// neither merged nor touched by delete tasks until another pipeline of the index runs.
+    pub merge_pipeline_enabled: bool,
+    /// Storage where the document samples of the splits are exported, resolved from the split
+    /// sampling settings of the index.
+    pub split_sample_storage_opt: Option<Arc<dyn Storage>>,
+    /// Scheduler bounding the merges executed concurrently by the pipelines of the node.
+    pub merge_scheduler: Arc<MergeScheduler>,
+    /// Scheduler sharing the indexing time of the node between the indexes.
+    pub fair_scheduler: Arc<FairScheduler>,
+    /// Sink of the documents failing to parse, resolved from the dead-letter queue settings of
+    /// the index.
+    pub dead_letter_sink_opt: Option<DeadLetterSink>,
+    /// Router of the documents of the tenants configured in the tenant routing settings of the
+    /// index. Shared by the indexers of all the generations of the pipeline.
+    pub tenant_router_opt: Option<TenantRouter>,
+    /// Notifier of the splits published by the pipeline, resolved from the publish notification
+    /// settings of the index. Shared by the publishers of all the generations of the pipeline.
+    pub publish_notifier_opt: Option<PublishNotifier>,
+    /// Feature flags of the index, read from the metastore when the pipeline spawns and refreshed
+    /// while it runs. Shared by the actors of all the generations of the pipeline.
+    pub feature_flags: IndexFeatureFlags,
+    /// Version of the doc mapping of the index, recorded in the splits produced by the pipeline.
+    pub doc_mapping_version: u64,
+    /// Fingerprint of the doc mapping and the indexing settings of the index, recorded in the
+    /// splits produced by the pipeline.
+    pub config_fingerprint: Option<String>,
+    /// Registry of the splits searchable by the searcher of the node before they are uploaded,
+    /// set when the `search_before_upload` indexing setting is enabled.
+    pub local_split_registry_opt: Option<LocalSplitRegistry>,
 }
 
 impl IndexingPipelineParams {
@@ -605,28 +1862,52 @@ impl IndexingPipelineParams {
         indexing_dir_path: PathBuf,
         split_store_max_num_bytes: usize,
         split_store_max_num_splits: usize,
+        max_num_retained_orphan_scratch_dirs: usize,
         metastore: Arc<dyn Metastore>,
         storage: Arc<dyn Storage>,
     ) -> anyhow::Result<Self> {
+        let config_fingerprint = index_metadata.config_fingerprint();
+        let mut indexing_settings = index_metadata.indexing_settings;
+        if let Some(profile) = source_config.profile.or(indexing_settings.profile) {
+            indexing_settings.apply_profile(profile);
+        }
         let doc_mapper = build_doc_mapper(
             &index_metadata.doc_mapping,
             &index_metadata.search_settings,
-            &index_metadata.indexing_settings,
+            &indexing_settings,
         )?;
+        // The index and source IDs are used as directory names, which some platforms reject.
+        check_file_name_supported(&pipeline_id.index_id)?;
+        check_file_name_supported(&pipeline_id.source_id)?;
         let indexing_directory_path = indexing_dir_path
             .join(&pipeline_id.index_id)
             .join(&pipeline_id.source_id);
-        let indexing_directory = IndexingDirectory::create_in_dir(indexing_directory_path).await?;
+        let indexing_directory = IndexingDirectory::create_in_dir(
+            indexing_directory_path,
+            max_num_retained_orphan_scratch_dirs,
+        )
+        .await?;
         Ok(Self {
             pipeline_id,
             doc_mapper,
             indexing_directory,
-            indexing_settings: index_metadata.indexing_settings,
+            indexing_settings,
             source_config,
             split_store_max_num_bytes,
             split_store_max_num_splits,
             metastore,
             storage,
+            merge_pipeline_enabled: true,
+            split_sample_storage_opt: None,
+            merge_scheduler: Arc::new(MergeScheduler::default()),
+            fair_scheduler: Arc::new(FairScheduler::default()),
+            dead_letter_sink_opt: None,
+            tenant_router_opt: None,
+            publish_notifier_opt: None,
+            feature_flags: IndexFeatureFlags::new(index_metadata.feature_flags),
+            doc_mapping_version: index_metadata.doc_mapping_version,
+            config_fingerprint: Some(config_fingerprint),
+            local_split_registry_opt: None,
         })
     }
 }
@@ -639,12 +1920,31 @@ mod tests {
     use quickwit_actors::Universe;
     use quickwit_config::{IndexingSettings, SourceParams};
     use quickwit_doc_mapper::default_doc_mapper_for_test;
+    use quickwit_metastore::checkpoint::{PartitionId, Position};
     use quickwit_metastore::{IndexMetadata, MetastoreError, MockMetastore};
     use quickwit_storage::RamStorage;
 
     use super::{IndexingPipeline, *};
     use crate::models::IndexingDirectory;
 
+    #[test]
+    fn test_checkpoint_rejections() {
+        let rejection = |position: &str| IncompatibleCheckpointDelta {
+            partition_id: PartitionId::from("partition"),
+            current_position: Position::from(position.to_string()),
+            delta_position_from: Position::from("0".to_string()),
+        };
+        let mut checkpoint_rejections = CheckpointRejections::default();
+        assert_eq!(checkpoint_rejections.record(None), 0);
+        assert_eq!(checkpoint_rejections.record(Some(rejection("1"))), 1);
+        assert_eq!(checkpoint_rejections.record(Some(rejection("1"))), 2);
+        assert_eq!(checkpoint_rejections.record(Some(rejection("2"))), 1);
+        assert_eq!(checkpoint_rejections.record(Some(rejection("2"))), 2);
+        assert_eq!(checkpoint_rejections.record(Some(rejection("2"))), 3);
+        assert_eq!(checkpoint_rejections.record(None), 0);
+        assert_eq!(checkpoint_rejections.record(Some(rejection("2"))), 1);
+    }
+
     #[test]
     fn test_wait_duration() {
         assert_eq!(
@@ -677,6 +1977,7 @@ mod tests {
         mut num_fails: usize,
     ) -> anyhow::Result<bool> {
         let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
         metastore
             .expect_index_metadata()
             .withf(|index_id| index_id == "test-index")
@@ -727,6 +2028,10 @@ mod tests {
         let source_config = SourceConfig {
             source_id: "test-source".to_string(),
             num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
             source_params: SourceParams::file(PathBuf::from("data/test_corpus.json")),
         };
         let pipeline_params = IndexingPipelineParams {
@@ -739,6 +2044,17 @@ mod tests {
             split_store_max_num_splits: 100,
             metastore: Arc::new(metastore),
             storage: Arc::new(RamStorage::default()),
+            merge_pipeline_enabled: true,
+            split_sample_storage_opt: None,
+            merge_scheduler: Arc::new(MergeScheduler::default()),
+            fair_scheduler: Arc::new(FairScheduler::default()),
+            dead_letter_sink_opt: None,
+            tenant_router_opt: None,
+            publish_notifier_opt: None,
+            feature_flags: IndexFeatureFlags::default(),
+            doc_mapping_version: 0,
+            config_fingerprint: None,
+            local_split_registry_opt: None,
         };
         let pipeline = IndexingPipeline::new(pipeline_params);
         let (_pipeline_mailbox, pipeline_handler) = universe.spawn_actor(pipeline).spawn();
@@ -769,6 +2085,7 @@ mod tests {
     #[tokio::test]
     async fn test_indexing_pipeline_simple() -> anyhow::Result<()> {
         let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
         metastore
             .expect_index_metadata()
             .withf(|index_id| index_id == "test-index")
@@ -816,6 +2133,10 @@ mod tests {
         let source_config = SourceConfig {
             source_id: "test-source".to_string(),
             num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
             source_params: SourceParams::file(PathBuf::from("data/test_corpus.json")),
         };
         let pipeline_params = IndexingPipelineParams {
@@ -828,6 +2149,17 @@ mod tests {
             split_store_max_num_splits: 100,
             metastore: Arc::new(metastore),
             storage: Arc::new(RamStorage::default()),
+            merge_pipeline_enabled: true,
+            split_sample_storage_opt: None,
+            merge_scheduler: Arc::new(MergeScheduler::default()),
+            fair_scheduler: Arc::new(FairScheduler::default()),
+            dead_letter_sink_opt: None,
+            tenant_router_opt: None,
+            publish_notifier_opt: None,
+            feature_flags: IndexFeatureFlags::default(),
+            doc_mapping_version: 0,
+            config_fingerprint: None,
+            local_split_registry_opt: None,
         };
         let pipeline = IndexingPipeline::new(pipeline_params);
         let (_pipeline_mailbox, pipeline_handler) = universe.spawn_actor(pipeline).spawn();
@@ -836,6 +2168,416 @@ mod tests {
         assert_eq!(pipeline_statistics.generation, 1);
         assert_eq!(pipeline_statistics.num_spawn_attempts, 1);
         assert_eq!(pipeline_statistics.num_published_splits, 1);
+        assert!(pipeline_statistics.storage_ops.num_put_requests > 0);
+        assert!(pipeline_statistics.storage_ops.num_put_bytes > 0);
+        assert!(!pipeline_statistics.published_watermark.is_empty());
+        assert_eq!(
+            pipeline_statistics.read_watermark,
+            pipeline_statistics.published_watermark
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_indexing_pipeline_waits_for_end_of_maintenance_mode() -> anyhow::Result<()> {
+        let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
+        let mut num_calls_in_maintenance_mode = 1;
+        metastore
+            .expect_index_metadata()
+            .withf(|index_id| index_id == "test-index")
+            .returning(move |_| {
+                let mut index_metadata =
+                    IndexMetadata::for_test("test-index", "ram:///indexes/test-index");
+                if num_calls_in_maintenance_mode > 0 {
+                    num_calls_in_maintenance_mode -= 1;
+                    index_metadata.maintenance_mode = true;
+                } else {
+                    index_metadata
+                        .feature_flags
+                        .insert("shadow_indexing".to_string(), true);
+                }
+                Ok(index_metadata)
+            });
+        metastore
+            .expect_list_splits()
+            .returning(|_, _, _, _| Ok(Vec::new()));
+        metastore
+            .expect_mark_splits_for_deletion()
+            .returning(|_, _| Ok(()));
+        metastore
+            .expect_stage_split()
+            .times(1)
+            .returning(|_, _| Ok(()));
+        metastore
+            .expect_publish_splits()
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+        let universe = Universe::new();
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let source_config = SourceConfig {
+            source_id: "test-source".to_string(),
+            num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::file(PathBuf::from("data/test_corpus.json")),
+        };
+        let feature_flags = IndexFeatureFlags::default();
+        let pipeline_params = IndexingPipelineParams {
+            pipeline_id,
+            doc_mapper: Arc::new(default_doc_mapper_for_test()),
+            source_config,
+            indexing_directory: IndexingDirectory::for_test().await?,
+            indexing_settings: IndexingSettings::for_test(),
+            split_store_max_num_bytes: 10_000_000,
+            split_store_max_num_splits: 100,
+            metastore: Arc::new(metastore),
+            storage: Arc::new(RamStorage::default()),
+            merge_pipeline_enabled: true,
+            split_sample_storage_opt: None,
+            merge_scheduler: Arc::new(MergeScheduler::default()),
+            fair_scheduler: Arc::new(FairScheduler::default()),
+            dead_letter_sink_opt: None,
+            tenant_router_opt: None,
+            publish_notifier_opt: None,
+            feature_flags: feature_flags.clone(),
+            doc_mapping_version: 0,
+            config_fingerprint: None,
+            local_split_registry_opt: None,
+        };
+        let pipeline = IndexingPipeline::new(pipeline_params);
+        let (_pipeline_mailbox, pipeline_handler) = universe.spawn_actor(pipeline).spawn();
+        // The pipeline is not spawned while the index is in maintenance mode.
+        let pipeline_statistics = pipeline_handler.process_pending_and_observe().await;
+        assert_eq!(pipeline_statistics.generation, 0);
+        universe
+            .simulate_time_shift(MAINTENANCE_MODE_CHECK_INTERVAL)
+            .await;
+        let (pipeline_exit_status, pipeline_statistics) = pipeline_handler.join().await;
+        assert!(pipeline_exit_status.is_success());
+        assert_eq!(pipeline_statistics.generation, 1);
+        assert_eq!(pipeline_statistics.num_published_splits, 1);
+        // The feature flags are read when the pipeline spawns.
+        assert!(feature_flags.is_enabled("shadow_indexing"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_indexing_pipeline_without_merge_pipeline() -> anyhow::Result<()> {
+        let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
+        metastore
+            .expect_index_metadata()
+            .withf(|index_id| index_id == "test-index")
+            .returning(|_| {
+                Ok(IndexMetadata::for_test(
+                    "test-index",
+                    "ram:///indexes/test-index",
+                ))
+            });
+        metastore
+            .expect_list_splits()
+            .returning(|_, _, _, _| Ok(Vec::new()));
+        metastore
+            .expect_mark_splits_for_deletion()
+            .returning(|_, _| Ok(()));
+        metastore
+            .expect_stage_split()
+            .times(1)
+            .returning(|_, _| Ok(()));
+        metastore
+            .expect_publish_splits()
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+        let universe = Universe::new();
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let source_config = SourceConfig {
+            source_id: "test-source".to_string(),
+            num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::file(PathBuf::from("data/test_corpus.json")),
+        };
+        let pipeline_params = IndexingPipelineParams {
+            pipeline_id,
+            doc_mapper: Arc::new(default_doc_mapper_for_test()),
+            source_config,
+            indexing_directory: IndexingDirectory::for_test().await?,
+            indexing_settings: IndexingSettings::for_test(),
+            split_store_max_num_bytes: 10_000_000,
+            split_store_max_num_splits: 100,
+            metastore: Arc::new(metastore),
+            storage: Arc::new(RamStorage::default()),
+            merge_pipeline_enabled: false,
+            split_sample_storage_opt: None,
+            merge_scheduler: Arc::new(MergeScheduler::default()),
+            fair_scheduler: Arc::new(FairScheduler::default()),
+            dead_letter_sink_opt: None,
+            tenant_router_opt: None,
+            publish_notifier_opt: None,
+            feature_flags: IndexFeatureFlags::default(),
+            doc_mapping_version: 0,
+            config_fingerprint: None,
+            local_split_registry_opt: None,
+        };
+        let pipeline = IndexingPipeline::new(pipeline_params);
+        let (_pipeline_mailbox, pipeline_handler) = universe.spawn_actor(pipeline).spawn();
+        let (pipeline_exit_status, pipeline_statistics) = pipeline_handler.join().await;
+        assert!(pipeline_exit_status.is_success());
+        assert_eq!(pipeline_statistics.generation, 1);
+        assert_eq!(pipeline_statistics.num_published_splits, 1);
+        assert_eq!(pipeline_statistics.num_merges, 0);
+        assert!(pipeline_statistics.merge_progress.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_indexing_pipeline_isolates_non_critical_failures() -> anyhow::Result<()> {
+        let mut metastore = MockMetastore::default();
+        let mut num_failing_calls = 1;
+        metastore.expect_last_delete_opstamp().returning(move |_| {
+            if num_failing_calls > 0 {
+                num_failing_calls -= 1;
+                return Err(MetastoreError::ConnectionError {
+                    message: "Connection refused.".to_string(),
+                });
+            }
+            Ok(0)
+        });
+        metastore
+            .expect_index_metadata()
+            .withf(|index_id| index_id == "test-index")
+            .returning(|_| {
+                Ok(IndexMetadata::for_test(
+                    "test-index",
+                    "ram:///indexes/test-index",
+                ))
+            });
+        metastore
+            .expect_list_splits()
+            .returning(|_, _, _, _| Ok(Vec::new()));
+        metastore
+            .expect_mark_splits_for_deletion()
+            .returning(|_, _| Ok(()));
+        let universe = Universe::new();
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let source_config = SourceConfig {
+            source_id: "test-source".to_string(),
+            num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::void(),
+        };
+        let indexing_settings = IndexingSettings {
+            isolate_non_critical_failures: true,
+            ..IndexingSettings::for_test()
+        };
+        let pipeline_params = IndexingPipelineParams {
+            pipeline_id,
+            doc_mapper: Arc::new(default_doc_mapper_for_test()),
+            source_config,
+            indexing_directory: IndexingDirectory::for_test().await?,
+            indexing_settings,
+            split_store_max_num_bytes: 10_000_000,
+            split_store_max_num_splits: 100,
+            metastore: Arc::new(metastore),
+            storage: Arc::new(RamStorage::default()),
+            merge_pipeline_enabled: true,
+            split_sample_storage_opt: None,
+            merge_scheduler: Arc::new(MergeScheduler::default()),
+            fair_scheduler: Arc::new(FairScheduler::default()),
+            dead_letter_sink_opt: None,
+            tenant_router_opt: None,
+            publish_notifier_opt: None,
+            feature_flags: IndexFeatureFlags::default(),
+            doc_mapping_version: 0,
+            config_fingerprint: None,
+            local_split_registry_opt: None,
+        };
+        let pipeline = IndexingPipeline::new(pipeline_params);
+        let (pipeline_mailbox, pipeline_handler) = universe.spawn_actor(pipeline).spawn();
+        let actor_ids = |pipeline_topology: PipelineTopology| -> Vec<String> {
+            pipeline_topology
+                .actors
+                .into_iter()
+                .map(|actor| actor.actor_id)
+                .collect()
+        };
+        let initial_actor_ids = actor_ids(pipeline_mailbox.ask(ObservePipelineTopology).await?);
+        // The delete executor fails on its first pass and is respawned at the next supervision.
+        tokio::time::sleep(quickwit_actors::HEARTBEAT * 2).await;
+        let pipeline_statistics = pipeline_handler.process_pending_and_observe().await;
+        assert_eq!(pipeline_statistics.generation, 1);
+        assert_eq!(pipeline_statistics.num_non_critical_respawns, 1);
+
+        // Only the delete executor is respawned.
+        let respawned_actor_ids = actor_ids(pipeline_mailbox.ask(ObservePipelineTopology).await?);
+        assert_eq!(respawned_actor_ids.len(), initial_actor_ids.len());
+        let respawned_actor_idxs: Vec<usize> = (0..initial_actor_ids.len())
+            .filter(|&actor_idx| initial_actor_ids[actor_idx] != respawned_actor_ids[actor_idx])
+            .collect();
+        // The delete executor comes right after the garbage collector in the topology.
+        assert_eq!(respawned_actor_idxs, [7]);
+
+        // The respawned non-critical actors exit along with the critical path.
+        pipeline_mailbox.send_message(DrainPipeline).await?;
+        let (pipeline_exit_status, pipeline_statistics) = pipeline_handler.join().await;
+        assert!(pipeline_exit_status.is_success());
+        assert_eq!(pipeline_statistics.generation, 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_indexing_pipeline_topology() -> anyhow::Result<()> {
+        let mut metastore = MockMetastore::default();
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(0));
+        metastore
+            .expect_index_metadata()
+            .withf(|index_id| index_id == "test-index")
+            .returning(|_| {
+                Ok(IndexMetadata::for_test(
+                    "test-index",
+                    "ram:///indexes/test-index",
+                ))
+            });
+        metastore
+            .expect_list_splits()
+            .returning(|_, _, _, _| Ok(Vec::new()));
+        metastore
+            .expect_mark_splits_for_deletion()
+            .returning(|_, _| Ok(()));
+        let universe = Universe::new();
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let source_config = SourceConfig {
+            source_id: "test-source".to_string(),
+            num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::void(),
+        };
+        let pipeline_params = IndexingPipelineParams {
+            pipeline_id: pipeline_id.clone(),
+            doc_mapper: Arc::new(default_doc_mapper_for_test()),
+            source_config,
+            indexing_directory: IndexingDirectory::for_test().await?,
+            indexing_settings: IndexingSettings::for_test(),
+            split_store_max_num_bytes: 10_000_000,
+            split_store_max_num_splits: 100,
+            metastore: Arc::new(metastore),
+            storage: Arc::new(RamStorage::default()),
+            merge_pipeline_enabled: true,
+            split_sample_storage_opt: None,
+            merge_scheduler: Arc::new(MergeScheduler::default()),
+            fair_scheduler: Arc::new(FairScheduler::default()),
+            dead_letter_sink_opt: None,
+            tenant_router_opt: None,
+            publish_notifier_opt: None,
+            feature_flags: IndexFeatureFlags::default(),
+            doc_mapping_version: 0,
+            config_fingerprint: None,
+            local_split_registry_opt: None,
+        };
+        let pipeline = IndexingPipeline::new(pipeline_params);
+        let (pipeline_mailbox, pipeline_handler) = universe.spawn_actor(pipeline).spawn();
+        let pipeline_topology = pipeline_mailbox.ask(ObservePipelineTopology).await?;
+        assert_eq!(pipeline_topology.pipeline_id, pipeline_id);
+        assert_eq!(pipeline_topology.generation, 1);
+        assert!(pipeline_topology
+            .kill_switches
+            .iter()
+            .all(|kill_switch| kill_switch.is_alive));
+        let kill_switch_groups: Vec<KillSwitchGroup> = pipeline_topology
+            .kill_switches
+            .iter()
+            .map(|kill_switch| kill_switch.group)
+            .collect();
+        assert_eq!(
+            kill_switch_groups,
+            [
+                KillSwitchGroup::Critical,
+                KillSwitchGroup::GarbageCollector,
+                KillSwitchGroup::DeleteExecutor,
+                KillSwitchGroup::Merge,
+            ]
+        );
+
+        let roles: Vec<&str> = pipeline_topology
+            .actors
+            .iter()
+            .map(|actor| actor.role.as_str())
+            .collect();
+        assert_eq!(
+            roles,
+            [
+                "source",
+                "indexer",
+                "packager",
+                "uploader",
+                "sequencer",
+                "publisher",
+                "garbage_collector",
+                "delete_executor",
+                "merge_planner",
+                "merge_split_downloader",
+                "merge_executor",
+                "merge_packager",
+                "merge_uploader",
+                "merge_sequencer",
+                "merge_publisher",
+            ]
+        );
+        let num_critical_actors = pipeline_topology
+            .actors
+            .iter()
+            .filter(|actor| actor.kill_switch_group == KillSwitchGroup::Critical)
+            .count();
+        assert_eq!(num_critical_actors, 6);
+        assert_eq!(pipeline_topology.edges.len(), 20);
+        let source = &pipeline_topology.actors[0];
+        let indexer = &pipeline_topology.actors[1];
+        assert_eq!(
+            pipeline_topology.edges[0],
+            ActorEdge {
+                from: source.actor_id.clone(),
+                to: indexer.actor_id.clone(),
+            }
+        );
+        assert!(pipeline_topology.to_dot().contains(&format!(
+            "\"{}\" -> \"{}\";",
+            source.actor_id, indexer.actor_id
+        )));
+
+        pipeline_mailbox.send_message(DrainPipeline).await?;
+        let (pipeline_exit_status, _) = pipeline_handler.join().await;
+        assert!(pipeline_exit_status.is_success());
         Ok(())
     }
 }
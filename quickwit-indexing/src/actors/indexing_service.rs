@@ -17,36 +17,72 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use quickwit_actors::{
     Actor, ActorContext, ActorExitStatus, ActorHandle, Handler, Health, Observation, Supervisable,
 };
 use quickwit_config::{
-    IndexerConfig, IngestApiSourceParams, SourceConfig, SourceParams, VecSourceParams,
+    DeadLetterQueueSettings, IndexerConfig, IngestApiSourceParams, SourceConfig, SourceParams,
+    VecSourceParams, WebhookSourceParams, MULTIPLEXED_SOURCE_ID,
 };
-use quickwit_ingest_api::{get_ingest_api_service, QUEUES_DIR_NAME};
-use quickwit_metastore::{IndexMetadata, Metastore, MetastoreError};
+use quickwit_directories::LocalSplitRegistry;
+use quickwit_ingest_api::{get_ingest_api_service, webhook_queue_id, QUEUES_DIR_NAME};
+use quickwit_metastore::checkpoint::{PartitionId, Position, SourceCheckpoint};
+use quickwit_metastore::{IndexMetadata, Metastore, MetastoreError, SplitMetadata, SplitState};
 use quickwit_proto::ingest_api::CreateQueueIfNotExistsRequest;
 use quickwit_proto::{ServiceError, ServiceErrorCode};
-use quickwit_storage::{StorageResolverError, StorageUriResolver};
+use quickwit_storage::{StorageOpsCounts, StorageResolverError, StorageUriResolver};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tracing::{error, info};
+use time::OffsetDateTime;
+use tracing::{error, info, warn};
 
+use crate::actors::{DrainPipeline, ObservePipelineTopology};
 use crate::models::{
-    DetachPipeline, IndexingPipelineId, Observe, ObservePipeline, ShutdownPipeline,
-    ShutdownPipelines, SpawnMergePipeline, SpawnPipeline, SpawnPipelines,
+    partition_lags, AdoptPipeline, DataLossDetector, DataLossSuspicion, DataLossSuspicionKind,
+    DeadLetterSink, DetachPipeline, FieldProfile, FieldProfileReport, ForceMerge, ForceMergeIndex,
+    IndexingPipelineId, ListPipelineWarnings, ListPipelines, Observe, ObserveFieldProfile,
+    ObservePipeline, ObservePipelineTopologies, ObserveRelocations, ObserveSourceHealth,
+    ObserveStorageOps, PipelineRelocation, PipelineTopology, PipelineWarning, PipelineWarnings,
+    PublishNotifier, RecompressIndex, RecompressSplits, RelocatePipeline, RelocationState,
+    ShutdownPipeline, ShutdownPipelines, SourceConnectivity, SourceHealth, SourceHealthStatus,
+    SourceProgressSample, SpawnMergePipeline, SpawnPipeline, SpawnPipelines, TenantRouter,
+    ThroughputSample,
+};
+use crate::source::{
+    check_source_connectivity, fetch_source_partition_bounds, INGEST_API_SOURCE_ID,
+};
+use crate::{
+    FairScheduler, IndexingPipeline, IndexingPipelineParams, IndexingStatistics, MergeScheduler,
+    INDEXER_METRICS,
 };
-use crate::source::INGEST_API_SOURCE_ID;
-use crate::{IndexingPipeline, IndexingPipelineParams, IndexingStatistics};
 
 /// Name of the indexing directory, usually located at `<data_dir_path>/indexing`.
 pub const INDEXING_DIR_NAME: &str = "indexing";
 
+/// Time after which the connectivity check of a source reports the source as unreachable.
+const SOURCE_CONNECTIVITY_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Interval at which the progress of the sources read on the node is compared to the records
+/// available in their partitions.
+const DATA_LOSS_DETECTION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Window over which a partition must not progress to be deemed stuck, and over which the
+/// published positions and documents of a source are compared.
+const DATA_LOSS_DETECTION_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// Kinds of data loss suspicions, each reported by its own gauge.
+const DATA_LOSS_SUSPICION_KINDS: [DataLossSuspicionKind; 3] = [
+    DataLossSuspicionKind::StuckPartition,
+    DataLossSuspicionKind::ExpiredRecords,
+    DataLossSuspicionKind::UnaccountedPositions,
+];
+
 #[derive(Error, Debug)]
 pub enum IndexingServiceError {
     #[error("Indexing pipeline `{index_id}` for source `{source_id}` does not exist.")]
@@ -65,6 +101,10 @@ pub enum IndexingServiceError {
     MetastoreError(#[from] MetastoreError),
     #[error("Invalid params `{0}`.")]
     InvalidParams(anyhow::Error),
+    #[error("No merge pipeline of index `{index_id}` is running on this node.")]
+    MissingMergePipeline { index_id: String },
+    #[error("Source `{source_id}` of index `{index_id}` does not exist.")]
+    MissingSource { index_id: String, source_id: String },
 }
 
 impl ServiceError for IndexingServiceError {
@@ -75,6 +115,8 @@ impl ServiceError for IndexingServiceError {
             Self::StorageError(_) => ServiceErrorCode::Internal,
             Self::MetastoreError(_) => ServiceErrorCode::Internal,
             Self::InvalidParams(_) => ServiceErrorCode::BadRequest,
+            Self::MissingMergePipeline { .. } => ServiceErrorCode::NotFound,
+            Self::MissingSource { .. } => ServiceErrorCode::NotFound,
         }
     }
 }
@@ -91,11 +133,28 @@ pub struct IndexingService {
     data_dir_path: PathBuf,
     split_store_max_num_bytes: usize,
     split_store_max_num_splits: usize,
+    max_num_retained_orphan_scratch_dirs: usize,
     metastore: Arc<dyn Metastore>,
     storage_resolver: StorageUriResolver,
+    merge_scheduler: Arc<MergeScheduler>,
+    fair_scheduler: Arc<FairScheduler>,
     pipeline_handles: HashMap<IndexingPipelineId, ActorHandle<IndexingPipeline>>,
+    /// Relocations of pipelines from this node to other nodes.
+    relocations: HashMap<IndexingPipelineId, PipelineRelocation>,
+    /// IDs of the pipelines relocated from other nodes and adopted by this node.
+    adopted_pipeline_ids: HashSet<IndexingPipelineId>,
+    /// Counters of the pipelines of the sources sampled by the last request for their health,
+    /// keyed by index and source IDs.
+    source_throughput_samples: HashMap<(String, String), ThroughputSample>,
+    /// Data loss detectors of the sources read on the node, keyed by index and source IDs.
+    data_loss_detectors: HashMap<(String, String), DataLossDetector>,
+    /// Suspicions of data loss raised by the last run of the data loss detectors.
+    data_loss_suspicions: HashMap<(String, String), Vec<DataLossSuspicion>>,
     state: IndexingServiceState,
     enable_ingest_api: bool,
+    /// Registry of the splits searchable by the searcher of the node before they are uploaded,
+    /// shared with the pipelines of the indexes enabling `search_before_upload`.
+    local_split_registry_opt: Option<LocalSplitRegistry>,
 }
 
 impl IndexingService {
@@ -118,14 +177,34 @@ impl IndexingService {
             split_store_max_num_bytes: indexer_config.split_store_max_num_bytes.get_bytes()
                 as usize,
             split_store_max_num_splits: indexer_config.split_store_max_num_splits,
+            max_num_retained_orphan_scratch_dirs: indexer_config
+                .max_num_retained_orphan_scratch_dirs,
             metastore,
             storage_resolver,
+            merge_scheduler: Arc::new(MergeScheduler::from_indexer_config(&indexer_config)),
+            fair_scheduler: Arc::new(FairScheduler::from_indexer_config(&indexer_config)),
             pipeline_handles: Default::default(),
+            relocations: Default::default(),
+            adopted_pipeline_ids: Default::default(),
+            source_throughput_samples: Default::default(),
+            data_loss_detectors: Default::default(),
+            data_loss_suspicions: Default::default(),
             state: Default::default(),
             enable_ingest_api,
+            local_split_registry_opt: None,
         }
     }
 
+    /// Makes the splits of the indexes enabling `search_before_upload` searchable by the searcher
+    /// of the node through `local_split_registry` before they are uploaded.
+    pub fn with_local_split_registry(
+        mut self,
+        local_split_registry: LocalSplitRegistry,
+    ) -> IndexingService {
+        self.local_split_registry_opt = Some(local_split_registry);
+        self
+    }
+
     async fn detach_pipeline(
         &mut self,
         pipeline_id: &IndexingPipelineId,
@@ -154,12 +233,374 @@ impl IndexingService {
         Ok(observation)
     }
 
+    fn list_pipelines(&self) -> Vec<IndexingPipelineId> {
+        let mut pipeline_ids: Vec<IndexingPipelineId> =
+            self.pipeline_handles.keys().cloned().collect();
+        pipeline_ids.sort_by(|left, right| {
+            (&left.index_id, &left.source_id, left.pipeline_ord).cmp(&(
+                &right.index_id,
+                &right.source_id,
+                right.pipeline_ord,
+            ))
+        });
+        pipeline_ids
+    }
+
+    async fn relocate_pipeline(
+        &mut self,
+        ctx: &ActorContext<Self>,
+        pipeline_id: IndexingPipelineId,
+        target_node_id: String,
+    ) -> Result<PipelineRelocation, IndexingServiceError> {
+        if target_node_id == self.node_id {
+            return Err(IndexingServiceError::InvalidParams(anyhow::anyhow!(
+                "Pipeline cannot be relocated to the node `{}` it is running on.",
+                target_node_id
+            )));
+        }
+        if pipeline_id.source_id == INGEST_API_SOURCE_ID {
+            return Err(IndexingServiceError::InvalidParams(anyhow::anyhow!(
+                "Ingest API pipelines read a queue local to the node and cannot be relocated."
+            )));
+        }
+        let pipeline_handle = self.pipeline_handles.get(&pipeline_id).ok_or_else(|| {
+            IndexingServiceError::MissingPipeline {
+                index_id: pipeline_id.index_id.clone(),
+                source_id: pipeline_id.source_id.clone(),
+            }
+        })?;
+        if let Some(relocation) = self.relocations.get(&pipeline_id) {
+            if relocation.state == RelocationState::Draining {
+                return Ok(relocation.clone());
+            }
+        }
+        let _ = ctx
+            .send_message(pipeline_handle.mailbox(), DrainPipeline)
+            .await;
+        info!(
+            index_id=%pipeline_id.index_id,
+            source_id=%pipeline_id.source_id,
+            pipeline_ord=%pipeline_id.pipeline_ord,
+            target_node_id=%target_node_id,
+            "Relocating indexing pipeline."
+        );
+        let relocation = PipelineRelocation {
+            pipeline_id: pipeline_id.clone(),
+            target_node_id,
+            state: RelocationState::Draining,
+            published_checkpoint: pipeline_handle.last_observation().published_watermark,
+        };
+        self.relocations.insert(pipeline_id, relocation.clone());
+        Ok(relocation)
+    }
+
+    fn list_pipeline_warnings(&self) -> Vec<PipelineWarnings> {
+        self.list_pipelines()
+            .into_iter()
+            .filter_map(|pipeline_id| {
+                let warnings = self
+                    .pipeline_handles
+                    .get(&pipeline_id)?
+                    .last_observation()
+                    .warnings;
+                if warnings.is_empty() {
+                    return None;
+                }
+                Some(PipelineWarnings {
+                    pipeline_id,
+                    warnings,
+                })
+            })
+            .collect()
+    }
+
+    /// Aggregates per index the storage requests issued by the pipelines running on the node.
+    fn observe_storage_ops(&self) -> BTreeMap<String, StorageOpsCounts> {
+        let mut storage_ops_per_index: BTreeMap<String, StorageOpsCounts> = BTreeMap::new();
+        for (pipeline_id, pipeline_handle) in &self.pipeline_handles {
+            *storage_ops_per_index
+                .entry(pipeline_id.index_id.clone())
+                .or_default() += pipeline_handle.last_observation().storage_ops;
+        }
+        storage_ops_per_index
+    }
+
+    /// Aggregates the health of a source over the pipelines reading it on the node. The sources of
+    /// a multiplexed pipeline share its throughput and warnings.
+    async fn source_health(
+        &mut self,
+        ctx: &ActorContext<Self>,
+        index_id: String,
+        source_id: String,
+    ) -> Result<SourceHealth, IndexingServiceError> {
+        let index_metadata = self.index_metadata(ctx, &index_id).await?;
+        let source_config_opt = index_metadata.sources.get(&source_id);
+        if source_config_opt.is_none() && source_id != INGEST_API_SOURCE_ID {
+            return Err(IndexingServiceError::MissingSource {
+                index_id,
+                source_id,
+            });
+        }
+        let connectivity_check_result = match source_config_opt {
+            Some(source_config) => ctx
+                .protect_future(tokio::time::timeout(
+                    SOURCE_CONNECTIVITY_CHECK_TIMEOUT,
+                    check_source_connectivity(source_config),
+                ))
+                .await
+                .unwrap_or_else(|_| {
+                    Err(anyhow::anyhow!(
+                        "Connectivity check timed out after {} seconds.",
+                        SOURCE_CONNECTIVITY_CHECK_TIMEOUT.as_secs()
+                    ))
+                }),
+            None => Ok(()),
+        };
+        let connectivity = SourceConnectivity::from_check_result(connectivity_check_result);
+
+        let mut num_pipelines = 0;
+        let mut read_positions: BTreeMap<PartitionId, Position> = BTreeMap::new();
+        let mut num_docs = 0;
+        let mut num_bytes = 0;
+        let mut is_upload_circuit_breaker_tripped = false;
+        let mut last_error_opt: Option<PipelineWarning> = None;
+        for (pipeline_id, pipeline_handle) in &self.pipeline_handles {
+            if pipeline_id.index_id != index_id {
+                continue;
+            }
+            let statistics = pipeline_handle.last_observation();
+            let read_watermark = if pipeline_id.source_id == source_id {
+                statistics.read_watermark.clone()
+            } else if pipeline_id.source_id == MULTIPLEXED_SOURCE_ID {
+                match statistics.read_watermark.demultiplex().remove(&source_id) {
+                    Some(read_watermark) => read_watermark,
+                    None => continue,
+                }
+            } else {
+                continue;
+            };
+            num_pipelines += 1;
+            // The pipelines of a source with several pipelines read distinct partitions.
+            for (partition_id, position) in read_watermark.iter() {
+                let read_position = read_positions
+                    .entry(partition_id)
+                    .or_insert(Position::Beginning);
+                if position > *read_position {
+                    *read_position = position;
+                }
+            }
+            num_docs += statistics.num_docs;
+            num_bytes += statistics.total_bytes_processed;
+            is_upload_circuit_breaker_tripped |= statistics.upload_circuit_breaker_trip.is_some();
+            for warning in statistics.warnings {
+                if last_error_opt
+                    .as_ref()
+                    .map(|last_error| warning.last_timestamp > last_error.last_timestamp)
+                    .unwrap_or(true)
+                {
+                    last_error_opt = Some(warning);
+                }
+            }
+        }
+        let partitions = partition_lags(
+            &read_positions,
+            index_metadata.checkpoint.source_checkpoint(&source_id),
+        );
+        let sample = ThroughputSample {
+            instant: Instant::now(),
+            num_docs,
+            num_bytes,
+        };
+        let throughput = self
+            .source_throughput_samples
+            .insert((index_id.clone(), source_id.clone()), sample)
+            .and_then(|previous_sample| sample.throughput_since(&previous_sample));
+        let mut status = SourceHealthStatus::evaluate(
+            &connectivity,
+            num_pipelines,
+            is_upload_circuit_breaker_tripped,
+            last_error_opt.as_ref(),
+            OffsetDateTime::now_utc().unix_timestamp(),
+        );
+        let data_loss_suspicions = self
+            .data_loss_suspicions
+            .get(&(index_id.clone(), source_id.clone()))
+            .cloned()
+            .unwrap_or_default();
+        if status == SourceHealthStatus::Healthy && !data_loss_suspicions.is_empty() {
+            status = SourceHealthStatus::Degraded;
+        }
+        Ok(SourceHealth {
+            index_id,
+            source_id,
+            status,
+            connectivity,
+            num_pipelines,
+            partitions,
+            throughput,
+            last_error: last_error_opt,
+            data_loss_suspicions,
+        })
+    }
+
+    /// Compares the progress of the sources read by the pipelines of the node with the records
+    /// available in their partitions and with the documents published over the detection window,
+    /// and records the resulting suspicions of data loss. Only the sources whose positions are
+    /// record offsets are checked.
+    async fn detect_data_loss(&mut self, ctx: &ActorContext<Self>) {
+        let now = Instant::now();
+        let mut source_samples: BTreeMap<(String, String), SourceProgressSample> = BTreeMap::new();
+        for (pipeline_id, pipeline_handle) in &self.pipeline_handles {
+            // The counters of the pipelines of multiplexed sources are not broken down per source.
+            if pipeline_id.source_id == MULTIPLEXED_SOURCE_ID {
+                continue;
+            }
+            let statistics = pipeline_handle.last_observation();
+            let sample = source_samples
+                .entry((pipeline_id.index_id.clone(), pipeline_id.source_id.clone()))
+                .or_insert_with(|| SourceProgressSample {
+                    instant: now,
+                    published_checkpoint: SourceCheckpoint::default(),
+                    local_published_watermark: SourceCheckpoint::default(),
+                    num_published_docs: 0,
+                    num_skipped_docs: 0,
+                });
+            sample
+                .local_published_watermark
+                .advance_with_checkpoint(&statistics.published_watermark);
+            sample.num_published_docs += statistics.num_published_docs;
+            sample.num_skipped_docs += statistics.num_invalid_docs
+                + statistics.num_duplicate_docs
+                + statistics.num_routed_docs;
+        }
+        self.data_loss_detectors
+            .retain(|key, _| source_samples.contains_key(key));
+        self.data_loss_suspicions
+            .retain(|(index_id, source_id), _| {
+                let is_read = source_samples.contains_key(&(index_id.clone(), source_id.clone()));
+                if !is_read {
+                    report_data_loss_suspicions(index_id, source_id, &[]);
+                }
+                is_read
+            });
+        for ((index_id, source_id), mut sample) in source_samples {
+            let index_metadata = match self.index_metadata(ctx, &index_id).await {
+                Ok(index_metadata) => index_metadata,
+                Err(error) => {
+                    warn!(index_id=%index_id, error=?error, "Failed to fetch index metadata for data loss detection.");
+                    continue;
+                }
+            };
+            let source_config = match index_metadata.sources.get(&source_id) {
+                Some(source_config) => source_config,
+                None => continue,
+            };
+            let partition_bounds_opt = match ctx
+                .protect_future(tokio::time::timeout(
+                    SOURCE_CONNECTIVITY_CHECK_TIMEOUT,
+                    fetch_source_partition_bounds(source_config),
+                ))
+                .await
+            {
+                Ok(Ok(Some(partition_bounds))) => Some(partition_bounds),
+                Ok(Ok(None)) => continue,
+                Ok(Err(error)) => {
+                    warn!(index_id=%index_id, source_id=%source_id, error=?error, "Failed to fetch partition bounds for data loss detection.");
+                    None
+                }
+                Err(_) => {
+                    warn!(index_id=%index_id, source_id=%source_id, "Fetching partition bounds for data loss detection timed out.");
+                    None
+                }
+            };
+            sample.published_checkpoint = index_metadata
+                .checkpoint
+                .source_checkpoint(&source_id)
+                .cloned()
+                .unwrap_or_default();
+            let suspicions = self
+                .data_loss_detectors
+                .entry((index_id.clone(), source_id.clone()))
+                .or_insert_with(|| DataLossDetector::new(DATA_LOSS_DETECTION_WINDOW))
+                .record_sample(
+                    sample,
+                    partition_bounds_opt.as_ref(),
+                    OffsetDateTime::now_utc().unix_timestamp(),
+                );
+            for suspicion in &suspicions {
+                warn!(
+                    index_id=%index_id,
+                    source_id=%source_id,
+                    kind=suspicion.kind.as_str(),
+                    partition_id=?suspicion.partition_id,
+                    num_records=suspicion.num_records,
+                    "{}", suspicion.message
+                );
+            }
+            report_data_loss_suspicions(&index_id, &source_id, &suspicions);
+            self.data_loss_suspicions
+                .insert((index_id, source_id), suspicions);
+        }
+    }
+
+    fn observe_relocations(&self) -> Vec<PipelineRelocation> {
+        self.relocations.values().cloned().collect()
+    }
+
+    async fn adopt_pipeline(
+        &mut self,
+        ctx: &ActorContext<Self>,
+        relocated_pipeline_id: IndexingPipelineId,
+    ) -> Result<IndexingPipelineId, IndexingServiceError> {
+        let pipeline_id = IndexingPipelineId {
+            node_id: self.node_id.clone(),
+            ..relocated_pipeline_id.clone()
+        };
+        // Relocations remain advertised by the node they come from, so adopting a pipeline must
+        // be idempotent.
+        if self.adopted_pipeline_ids.contains(&relocated_pipeline_id)
+            || self.pipeline_handles.contains_key(&pipeline_id)
+        {
+            return Ok(pipeline_id);
+        }
+        let index_metadata = self.index_metadata(ctx, &pipeline_id.index_id).await?;
+        let source_config_opt = if pipeline_id.source_id == MULTIPLEXED_SOURCE_ID {
+            let multiplexed_sources = multiplexed_sources(&index_metadata);
+            (!multiplexed_sources.is_empty())
+                .then(|| SourceConfig::multiplexed(multiplexed_sources))
+        } else {
+            index_metadata.sources.get(&pipeline_id.source_id).cloned()
+        };
+        let source_config =
+            source_config_opt.ok_or_else(|| MetastoreError::SourceDoesNotExist {
+                source_id: pipeline_id.source_id.clone(),
+            })?;
+        self.spawn_pipeline_inner(
+            ctx,
+            pipeline_id.clone(),
+            index_metadata,
+            source_config,
+            true,
+        )
+        .await?;
+        info!(
+            index_id=%pipeline_id.index_id,
+            source_id=%pipeline_id.source_id,
+            pipeline_ord=%pipeline_id.pipeline_ord,
+            relocated_from_node_id=%relocated_pipeline_id.node_id,
+            "Adopted relocated indexing pipeline."
+        );
+        self.adopted_pipeline_ids.insert(relocated_pipeline_id);
+        Ok(pipeline_id)
+    }
+
     async fn spawn_pipeline(
         &mut self,
         ctx: &ActorContext<Self>,
         index_id: String,
         source_config: SourceConfig,
         pipeline_ord: usize,
+        merge_pipeline_enabled: bool,
     ) -> Result<IndexingPipelineId, IndexingServiceError> {
         let pipeline_id = IndexingPipelineId {
             index_id,
@@ -168,8 +609,14 @@ impl IndexingService {
             pipeline_ord,
         };
         let index_metadata = self.index_metadata(ctx, &pipeline_id.index_id).await?;
-        self.spawn_pipeline_inner(ctx, pipeline_id.clone(), index_metadata, source_config)
-            .await?;
+        self.spawn_pipeline_inner(
+            ctx,
+            pipeline_id.clone(),
+            index_metadata,
+            source_config,
+            merge_pipeline_enabled,
+        )
+        .await?;
         Ok(pipeline_id)
     }
 
@@ -181,8 +628,13 @@ impl IndexingService {
         let mut pipeline_ids = Vec::new();
 
         let index_metadata = self.index_metadata(ctx, &index_id).await?;
+        let multiplexed_sources = multiplexed_sources(&index_metadata);
 
         for source_config in index_metadata.sources.values() {
+            // These sources are read by the multiplexed pipeline of the index.
+            if multiplexed_sources.contains(source_config) {
+                continue;
+            }
             let pipeline_ords = 0..source_config.num_pipelines().unwrap_or(1);
             for pipeline_ord in pipeline_ords {
                 let pipeline_id = IndexingPipelineId {
@@ -199,6 +651,26 @@ impl IndexingService {
                     pipeline_id.clone(),
                     index_metadata.clone(),
                     source_config.clone(),
+                    true,
+                )
+                .await?;
+                pipeline_ids.push(pipeline_id);
+            }
+        }
+        if !multiplexed_sources.is_empty() {
+            let pipeline_id = IndexingPipelineId {
+                index_id: index_id.clone(),
+                source_id: MULTIPLEXED_SOURCE_ID.to_string(),
+                node_id: self.node_id.clone(),
+                pipeline_ord: 0,
+            };
+            if !self.pipeline_handles.contains_key(&pipeline_id) {
+                self.spawn_pipeline_inner(
+                    ctx,
+                    pipeline_id.clone(),
+                    index_metadata.clone(),
+                    SourceConfig::multiplexed(multiplexed_sources),
+                    true,
                 )
                 .await?;
                 pipeline_ids.push(pipeline_id);
@@ -219,6 +691,7 @@ impl IndexingService {
         pipeline_id: IndexingPipelineId,
         index_metadata: IndexMetadata,
         source_config: SourceConfig,
+        merge_pipeline_enabled: bool,
     ) -> Result<(), IndexingServiceError> {
         if self.pipeline_handles.contains_key(&pipeline_id) {
             return Err(IndexingServiceError::PipelineAlreadyExists {
@@ -227,20 +700,85 @@ impl IndexingService {
                 pipeline_ord: pipeline_id.pipeline_ord,
             });
         }
+        let pipeline_source_config = match source_config.source_params {
+            SourceParams::Webhook(webhook_params) => {
+                self.webhook_pipeline_source_config(
+                    &pipeline_id.index_id,
+                    source_config.source_id,
+                    webhook_params,
+                )
+                .await?
+            }
+            _ => source_config,
+        };
         let indexing_dir_path = self.data_dir_path.join(INDEXING_DIR_NAME);
         let storage = self.storage_resolver.resolve(&index_metadata.index_uri)?;
-        let pipeline_params = IndexingPipelineParams::try_new(
+        let split_sample_storage_opt = index_metadata
+            .indexing_settings
+            .split_sampling
+            .as_ref()
+            .map(|split_sampling_settings| {
+                self.storage_resolver
+                    .resolve(&split_sampling_settings.sample_uri)
+            })
+            .transpose()?;
+        let dead_letter_sink_opt = match &index_metadata.indexing_settings.dead_letter_queue {
+            Some(dead_letter_queue_settings) => {
+                Some(self.dead_letter_sink(dead_letter_queue_settings).await?)
+            }
+            None => None,
+        };
+        let tenant_router_opt = match &index_metadata.indexing_settings.tenant_routing {
+            Some(tenant_routing_settings) => {
+                let queues_dir_path = self.data_dir_path.join(QUEUES_DIR_NAME);
+                let ingest_api_service = get_ingest_api_service(&queues_dir_path)
+                    .await
+                    .map_err(IndexingServiceError::InvalidParams)?;
+                let tenant_router = TenantRouter::new(
+                    pipeline_id.clone(),
+                    index_metadata.clone(),
+                    tenant_routing_settings.clone(),
+                    self.metastore.clone(),
+                    ingest_api_service,
+                );
+                Some(tenant_router.with_indexing_service(ctx.mailbox().clone()))
+            }
+            None => None,
+        };
+        let mut pipeline_params = IndexingPipelineParams::try_new(
             pipeline_id.clone(),
             index_metadata,
-            source_config,
+            pipeline_source_config,
             indexing_dir_path,
             self.split_store_max_num_bytes,
             self.split_store_max_num_splits,
+            self.max_num_retained_orphan_scratch_dirs,
             self.metastore.clone(),
             storage,
         )
         .await
         .map_err(IndexingServiceError::InvalidParams)?;
+        pipeline_params.merge_pipeline_enabled = merge_pipeline_enabled;
+        pipeline_params.split_sample_storage_opt = split_sample_storage_opt;
+        pipeline_params.dead_letter_sink_opt = dead_letter_sink_opt;
+        pipeline_params.tenant_router_opt = tenant_router_opt;
+        if pipeline_params.indexing_settings.search_before_upload {
+            pipeline_params.local_split_registry_opt = self.local_split_registry_opt.clone();
+        }
+        if let Some(publish_notification_settings) =
+            &pipeline_params.indexing_settings.publish_notifications
+        {
+            let publish_notifier = PublishNotifier::spawn(
+                pipeline_id.clone(),
+                publish_notification_settings,
+                &pipeline_params.indexing_directory,
+                self.metastore.clone(),
+            )
+            .map_err(IndexingServiceError::InvalidParams)?;
+            pipeline_params.publish_notifier_opt = Some(publish_notifier);
+        }
+        pipeline_params.merge_scheduler = self.merge_scheduler.clone();
+        pipeline_params.fair_scheduler = self.fair_scheduler.clone();
 
         let pipeline = IndexingPipeline::new(pipeline_params);
         let (_pipeline_mailbox, pipeline_handle) = ctx.spawn_actor(pipeline).spawn();
@@ -249,6 +787,75 @@ impl IndexingService {
         Ok(())
     }
 
+    /// Resolves the sink of the dead-letter queue of an index. When the sink is an index, the
+    /// documents are appended to its ingest API queue, which is created if necessary.
+    async fn dead_letter_sink(
+        &self,
+        dead_letter_queue_settings: &DeadLetterQueueSettings,
+    ) -> Result<DeadLetterSink, IndexingServiceError> {
+        if let Some(uri) = &dead_letter_queue_settings.uri {
+            let storage = self.storage_resolver.resolve(uri)?;
+            return Ok(DeadLetterSink::Storage(storage));
+        }
+        let index_id = dead_letter_queue_settings.index_id.clone().ok_or_else(|| {
+            IndexingServiceError::InvalidParams(anyhow::anyhow!(
+                "Dead letter queue must set either `uri` or `index_id`."
+            ))
+        })?;
+        let queues_dir_path = self.data_dir_path.join(QUEUES_DIR_NAME);
+        let ingest_api_service = get_ingest_api_service(&queues_dir_path)
+            .await
+            .map_err(IndexingServiceError::InvalidParams)?;
+        let create_queue_req = CreateQueueIfNotExistsRequest {
+            queue_id: index_id.clone(),
+        };
+        ingest_api_service
+            .ask_for_res(create_queue_req)
+            .await
+            .map_err(|err| IndexingServiceError::InvalidParams(err.into()))?;
+        Ok(DeadLetterSink::IngestApi {
+            ingest_api_service,
+            index_id,
+        })
+    }
+
+    /// Webhook sources do not read events directly: the webhook REST endpoint appends them to a
+    /// dedicated ingest API queue. This function ensures the queue exists and returns the config
+    /// of an ingest API source consuming it. The source ID is preserved, so the pipeline
+    /// checkpoints the queue positions under the webhook source.
+    async fn webhook_pipeline_source_config(
+        &self,
+        index_id: &str,
+        source_id: String,
+        webhook_params: WebhookSourceParams,
+    ) -> Result<SourceConfig, IndexingServiceError> {
+        let queue_id = webhook_queue_id(index_id, &source_id);
+        let queues_dir_path = self.data_dir_path.join(QUEUES_DIR_NAME);
+        let ingest_api_service = get_ingest_api_service(&queues_dir_path)
+            .await
+            .map_err(IndexingServiceError::InvalidParams)?;
+        let create_queue_req = CreateQueueIfNotExistsRequest {
+            queue_id: queue_id.clone(),
+        };
+        ingest_api_service
+            .ask_for_res(create_queue_req)
+            .await
+            .map_err(|err| IndexingServiceError::InvalidParams(err.into()))?;
+        Ok(SourceConfig {
+            source_id,
+            num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::IngestApi(IngestApiSourceParams {
+                index_id: queue_id,
+                batch_num_bytes_limit: webhook_params.batch_num_bytes_limit,
+                queues_dir_path,
+            }),
+        })
+    }
+
     async fn spawn_ingest_api_pipeline(
         &mut self,
         ctx: &ActorContext<Self>,
@@ -283,6 +890,10 @@ impl IndexingService {
         let source_config = SourceConfig {
             source_id,
             num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
             source_params: SourceParams::IngestApi(IngestApiSourceParams {
                 index_id,
                 batch_num_bytes_limit: None,
@@ -294,6 +905,7 @@ impl IndexingService {
             pipeline_id.clone(),
             index_metadata.clone(),
             source_config,
+            true,
         )
         .await?;
         Ok(pipeline_id)
@@ -316,13 +928,207 @@ impl IndexingService {
         let source_config = SourceConfig {
             source_id: pipeline_id.source_id.clone(),
             num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
             source_params: SourceParams::Vec(VecSourceParams::default()),
         };
-        self.spawn_pipeline_inner(ctx, pipeline_id.clone(), index_metadata, source_config)
-            .await?;
+        self.spawn_pipeline_inner(
+            ctx,
+            pipeline_id.clone(),
+            index_metadata,
+            source_config,
+            true,
+        )
+        .await?;
         Ok(pipeline_id)
     }
 
+    /// Hands over the published splits of the index to the merge planner of one of its pipelines,
+    /// which merges them down to `target_num_splits` splits. When the merges of the index are
+    /// coordinated, all its pipelines receive the splits and the elected merge planner merges
+    /// them.
+    async fn force_merge_index(
+        &mut self,
+        ctx: &ActorContext<Self>,
+        index_id: String,
+        target_num_splits: usize,
+    ) -> Result<Vec<IndexingPipelineId>, IndexingServiceError> {
+        if target_num_splits == 0 {
+            return Err(IndexingServiceError::InvalidParams(anyhow::anyhow!(
+                "Target number of splits must be strictly positive."
+            )));
+        }
+        let pipeline_ids = self.sorted_index_pipeline_ids(&index_id);
+        let index_metadata = self.index_metadata(ctx, &index_id).await?;
+        let is_coordinated = index_metadata
+            .indexing_settings
+            .merge_coordination
+            .is_some();
+        let splits: Vec<SplitMetadata> = {
+            let _protect_guard = ctx.protect_zone();
+            self.metastore
+                .list_splits(&index_id, SplitState::Published, None, None)
+                .await?
+                .into_iter()
+                .map(|split| split.split_metadata)
+                .collect()
+        };
+        let mut force_merged_pipeline_ids = Vec::new();
+        for pipeline_id in pipeline_ids {
+            let pipeline_handle = &self.pipeline_handles[&pipeline_id];
+            let force_merge = ForceMerge {
+                splits: splits.clone(),
+                target_num_splits,
+            };
+            let has_merge_pipeline = ctx
+                .protect_future(pipeline_handle.mailbox().ask(force_merge))
+                .await
+                .unwrap_or(false);
+            if has_merge_pipeline {
+                force_merged_pipeline_ids.push(pipeline_id);
+                if !is_coordinated {
+                    break;
+                }
+            }
+        }
+        if force_merged_pipeline_ids.is_empty() {
+            return Err(IndexingServiceError::MissingMergePipeline { index_id });
+        }
+        info!(
+            index_id=%index_id,
+            num_splits=splits.len(),
+            target_num_splits=target_num_splits,
+            "Force merging index."
+        );
+        Ok(force_merged_pipeline_ids)
+    }
+
+    /// Hands over the published splits of the index to the merge planner of one of its pipelines,
+    /// which rewrites their doc store with the docstore compression settings of the index. When
+    /// the merges of the index are coordinated, all its pipelines receive the splits and the
+    /// elected merge planner recompresses them.
+    async fn recompress_index(
+        &mut self,
+        ctx: &ActorContext<Self>,
+        index_id: String,
+    ) -> Result<Vec<IndexingPipelineId>, IndexingServiceError> {
+        let pipeline_ids = self.sorted_index_pipeline_ids(&index_id);
+        let index_metadata = self.index_metadata(ctx, &index_id).await?;
+        let is_coordinated = index_metadata
+            .indexing_settings
+            .merge_coordination
+            .is_some();
+        let splits: Vec<SplitMetadata> = {
+            let _protect_guard = ctx.protect_zone();
+            self.metastore
+                .list_splits(&index_id, SplitState::Published, None, None)
+                .await?
+                .into_iter()
+                .map(|split| split.split_metadata)
+                .collect()
+        };
+        let mut recompressed_pipeline_ids = Vec::new();
+        for pipeline_id in pipeline_ids {
+            let pipeline_handle = &self.pipeline_handles[&pipeline_id];
+            let recompress_splits = RecompressSplits {
+                splits: splits.clone(),
+            };
+            let has_merge_pipeline = ctx
+                .protect_future(pipeline_handle.mailbox().ask(recompress_splits))
+                .await
+                .unwrap_or(false);
+            if has_merge_pipeline {
+                recompressed_pipeline_ids.push(pipeline_id);
+                if !is_coordinated {
+                    break;
+                }
+            }
+        }
+        if recompressed_pipeline_ids.is_empty() {
+            return Err(IndexingServiceError::MissingMergePipeline { index_id });
+        }
+        info!(
+            index_id=%index_id,
+            num_splits=splits.len(),
+            docstore_compression_level=index_metadata.indexing_settings.docstore_compression_level,
+            "Recompressing index."
+        );
+        Ok(recompressed_pipeline_ids)
+    }
+
+    /// Asks the pipelines of the source for their topology. Pipelines that exited are left out.
+    async fn pipeline_topologies(
+        &self,
+        ctx: &ActorContext<Self>,
+        index_id: String,
+        source_id: String,
+    ) -> Result<Vec<PipelineTopology>, IndexingServiceError> {
+        let pipeline_ids: Vec<IndexingPipelineId> = self
+            .sorted_index_pipeline_ids(&index_id)
+            .into_iter()
+            .filter(|pipeline_id| pipeline_id.source_id == source_id)
+            .collect();
+        if pipeline_ids.is_empty() {
+            return Err(IndexingServiceError::MissingPipeline {
+                index_id,
+                source_id,
+            });
+        }
+        let mut pipeline_topologies = Vec::with_capacity(pipeline_ids.len());
+        for pipeline_id in pipeline_ids {
+            let pipeline_handle = &self.pipeline_handles[&pipeline_id];
+            if let Ok(pipeline_topology) = ctx
+                .protect_future(pipeline_handle.mailbox().ask(ObservePipelineTopology))
+                .await
+            {
+                pipeline_topologies.push(pipeline_topology);
+            }
+        }
+        Ok(pipeline_topologies)
+    }
+
+    fn field_profile(
+        &self,
+        index_id: String,
+        source_id: String,
+    ) -> Result<FieldProfileReport, IndexingServiceError> {
+        let mut field_profile = FieldProfile::default();
+        let mut num_pipelines = 0;
+        for (pipeline_id, pipeline_handle) in &self.pipeline_handles {
+            if pipeline_id.index_id != index_id || pipeline_id.source_id != source_id {
+                continue;
+            }
+            num_pipelines += 1;
+            if let Some(pipeline_field_profile) = &pipeline_handle.last_observation().field_profile
+            {
+                field_profile.merge(pipeline_field_profile);
+            }
+        }
+        if num_pipelines == 0 {
+            return Err(IndexingServiceError::MissingPipeline {
+                index_id,
+                source_id,
+            });
+        }
+        Ok(field_profile.report())
+    }
+
+    /// Returns the IDs of the pipelines of the index, ordered by source and pipeline ordinal.
+    fn sorted_index_pipeline_ids(&self, index_id: &str) -> Vec<IndexingPipelineId> {
+        let mut pipeline_ids: Vec<IndexingPipelineId> = self
+            .pipeline_handles
+            .keys()
+            .filter(|pipeline_id| pipeline_id.index_id == index_id)
+            .cloned()
+            .collect();
+        pipeline_ids.sort_by(|left, right| {
+            (&left.source_id, left.pipeline_ord).cmp(&(&right.source_id, right.pipeline_ord))
+        });
+        pipeline_ids
+    }
+
     async fn index_metadata(
         &self,
         ctx: &ActorContext<Self>,
@@ -361,6 +1167,20 @@ impl Handler<DetachPipeline> for IndexingService {
     }
 }
 
+/// Sets the gauges counting the data loss suspicions of a source per kind.
+fn report_data_loss_suspicions(index_id: &str, source_id: &str, suspicions: &[DataLossSuspicion]) {
+    for kind in DATA_LOSS_SUSPICION_KINDS {
+        let num_suspicions = suspicions
+            .iter()
+            .filter(|suspicion| suspicion.kind == kind)
+            .count();
+        INDEXER_METRICS
+            .data_loss_suspicions
+            .with_label_values(&[index_id, source_id, kind.as_str()])
+            .set(num_suspicions as i64);
+    }
+}
+
 #[derive(Debug)]
 struct SuperviseLoop;
 
@@ -374,8 +1194,33 @@ impl Handler<SuperviseLoop> for IndexingService {
         ctx: &ActorContext<Self>,
     ) -> Result<(), ActorExitStatus> {
         self.pipeline_handles
-            .retain(
-                |pipeline_id, pipeline_handle| match pipeline_handle.health() {
+            .retain(|pipeline_id, pipeline_handle| {
+                let health = pipeline_handle.health();
+                if let Some(relocation) = self
+                    .relocations
+                    .get_mut(pipeline_id)
+                    .filter(|relocation| relocation.state == RelocationState::Draining)
+                {
+                    relocation.published_checkpoint =
+                        pipeline_handle.last_observation().published_watermark;
+                    match health {
+                        Health::Healthy => {}
+                        Health::Success => {
+                            info!(
+                                index_id=%pipeline_id.index_id,
+                                source_id=%pipeline_id.source_id,
+                                pipeline_ord=%pipeline_id.pipeline_ord,
+                                target_node_id=%relocation.target_node_id,
+                                "Indexing pipeline drained."
+                            );
+                            relocation.state = RelocationState::Drained;
+                        }
+                        Health::FailureOrUnhealthy => {
+                            relocation.state = RelocationState::Failed;
+                        }
+                    }
+                }
+                match health {
                     Health::Healthy => true,
                     Health::Success => {
                         info!(
@@ -399,8 +1244,8 @@ impl Handler<SuperviseLoop> for IndexingService {
                         self.state.num_running_pipelines -= 1;
                         false
                     }
-                },
-            );
+                }
+            });
         ctx.schedule_self_msg(quickwit_actors::HEARTBEAT, SuperviseLoop)
             .await;
         Ok(())
@@ -416,10 +1261,31 @@ impl Actor for IndexingService {
     }
 
     async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        ctx.schedule_self_msg(DATA_LOSS_DETECTION_INTERVAL, DetectDataLoss)
+            .await;
         self.handle(SuperviseLoop, ctx).await
     }
 }
 
+#[derive(Debug)]
+struct DetectDataLoss;
+
+#[async_trait]
+impl Handler<DetectDataLoss> for IndexingService {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _message: DetectDataLoss,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.detect_data_loss(ctx).await;
+        ctx.schedule_self_msg(DATA_LOSS_DETECTION_INTERVAL, DetectDataLoss)
+            .await;
+        Ok(())
+    }
+}
+
 #[async_trait]
 impl Handler<SpawnMergePipeline> for IndexingService {
     type Reply = Result<IndexingPipelineId, IndexingServiceError>;
@@ -435,19 +1301,46 @@ impl Handler<SpawnMergePipeline> for IndexingService {
 }
 
 #[async_trait]
-impl Handler<SpawnPipeline> for IndexingService {
-    type Reply = Result<IndexingPipelineId, IndexingServiceError>;
+impl Handler<ForceMergeIndex> for IndexingService {
+    type Reply = Result<Vec<IndexingPipelineId>, IndexingServiceError>;
     async fn handle(
         &mut self,
-        message: SpawnPipeline,
+        message: ForceMergeIndex,
         ctx: &ActorContext<Self>,
-    ) -> Result<Result<IndexingPipelineId, IndexingServiceError>, ActorExitStatus> {
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self
+            .force_merge_index(ctx, message.index_id, message.target_num_splits)
+            .await)
+    }
+}
+
+#[async_trait]
+impl Handler<RecompressIndex> for IndexingService {
+    type Reply = Result<Vec<IndexingPipelineId>, IndexingServiceError>;
+    async fn handle(
+        &mut self,
+        message: RecompressIndex,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self.recompress_index(ctx, message.index_id).await)
+    }
+}
+
+#[async_trait]
+impl Handler<SpawnPipeline> for IndexingService {
+    type Reply = Result<IndexingPipelineId, IndexingServiceError>;
+    async fn handle(
+        &mut self,
+        message: SpawnPipeline,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Result<IndexingPipelineId, IndexingServiceError>, ActorExitStatus> {
         Ok(self
             .spawn_pipeline(
                 ctx,
                 message.index_id,
                 message.source_config,
                 message.pipeline_ord,
+                message.merge_pipeline_enabled,
             )
             .await)
     }
@@ -485,11 +1378,15 @@ impl Handler<ShutdownPipelines> for IndexingService {
         message: ShutdownPipelines,
         _ctx: &ActorContext<Self>,
     ) -> Result<Self::Reply, ActorExitStatus> {
+        // The multiplexed pipeline of an index may read any of its sources.
         let source_filter_fn = |pipeline_id: &IndexingPipelineId| {
             message
                 .source_id
                 .as_ref()
-                .map(|source_id| pipeline_id.source_id == *source_id)
+                .map(|source_id| {
+                    pipeline_id.source_id == *source_id
+                        || pipeline_id.source_id == MULTIPLEXED_SOURCE_ID
+                })
                 .unwrap_or(true)
         };
         let pipelines_to_shutdown: Vec<IndexingPipelineId> = self
@@ -526,16 +1423,152 @@ impl Handler<ShutdownPipeline> for IndexingService {
     }
 }
 
+#[async_trait]
+impl Handler<ListPipelines> for IndexingService {
+    type Reply = Vec<IndexingPipelineId>;
+    async fn handle(
+        &mut self,
+        _message: ListPipelines,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self.list_pipelines())
+    }
+}
+
+#[async_trait]
+impl Handler<ListPipelineWarnings> for IndexingService {
+    type Reply = Vec<PipelineWarnings>;
+    async fn handle(
+        &mut self,
+        _message: ListPipelineWarnings,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self.list_pipeline_warnings())
+    }
+}
+
+#[async_trait]
+impl Handler<ObserveStorageOps> for IndexingService {
+    type Reply = BTreeMap<String, StorageOpsCounts>;
+    async fn handle(
+        &mut self,
+        _message: ObserveStorageOps,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self.observe_storage_ops())
+    }
+}
+
+#[async_trait]
+impl Handler<ObserveSourceHealth> for IndexingService {
+    type Reply = Result<SourceHealth, IndexingServiceError>;
+    async fn handle(
+        &mut self,
+        message: ObserveSourceHealth,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self
+            .source_health(ctx, message.index_id, message.source_id)
+            .await)
+    }
+}
+
+#[async_trait]
+impl Handler<ObservePipelineTopologies> for IndexingService {
+    type Reply = Result<Vec<PipelineTopology>, IndexingServiceError>;
+    async fn handle(
+        &mut self,
+        message: ObservePipelineTopologies,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self
+            .pipeline_topologies(ctx, message.index_id, message.source_id)
+            .await)
+    }
+}
+
+#[async_trait]
+impl Handler<ObserveFieldProfile> for IndexingService {
+    type Reply = Result<FieldProfileReport, IndexingServiceError>;
+    async fn handle(
+        &mut self,
+        message: ObserveFieldProfile,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self.field_profile(message.index_id, message.source_id))
+    }
+}
+
+#[async_trait]
+impl Handler<RelocatePipeline> for IndexingService {
+    type Reply = Result<PipelineRelocation, IndexingServiceError>;
+    async fn handle(
+        &mut self,
+        message: RelocatePipeline,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self
+            .relocate_pipeline(ctx, message.pipeline_id, message.target_node_id)
+            .await)
+    }
+}
+
+#[async_trait]
+impl Handler<ObserveRelocations> for IndexingService {
+    type Reply = Vec<PipelineRelocation>;
+    async fn handle(
+        &mut self,
+        _message: ObserveRelocations,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self.observe_relocations())
+    }
+}
+
+#[async_trait]
+impl Handler<AdoptPipeline> for IndexingService {
+    type Reply = Result<IndexingPipelineId, IndexingServiceError>;
+    async fn handle(
+        &mut self,
+        message: AdoptPipeline,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self
+            .adopt_pipeline(ctx, message.relocated_pipeline_id)
+            .await)
+    }
+}
+
+/// Returns the sources of an index read by its multiplexed pipeline: the sources that can be
+/// multiplexed, if the index enables source multiplexing and has at least two of them.
+fn multiplexed_sources(index_metadata: &IndexMetadata) -> Vec<SourceConfig> {
+    if !index_metadata.indexing_settings.multiplex_sources {
+        return Vec::new();
+    }
+    let mut multiplexed_sources: Vec<SourceConfig> = index_metadata
+        .sources
+        .values()
+        .filter(|source_config| source_config.can_be_multiplexed())
+        .cloned()
+        .collect();
+    multiplexed_sources.sort_by(|left, right| left.source_id.cmp(&right.source_id));
+    if multiplexed_sources.len() < 2 {
+        return Vec::new();
+    }
+    multiplexed_sources
+}
+
 #[cfg(test)]
 mod tests {
     use std::time::Duration;
 
-    use quickwit_actors::{ObservationType, Universe};
+    use quickwit_actors::{AskError, ObservationType, Universe};
     use quickwit_common::rand::append_random_suffix;
     use quickwit_common::uri::Uri;
     use quickwit_config::{SourceConfig, VecSourceParams};
     use quickwit_ingest_api::init_ingest_api;
     use quickwit_metastore::quickwit_metastore_uri_resolver;
+    use quickwit_proto::ingest_api::QueueExistsRequest;
 
     use super::*;
 
@@ -581,12 +1614,17 @@ mod tests {
         let source_config_0 = SourceConfig {
             source_id: "test-indexing-service--source-0".to_string(),
             num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
             source_params: SourceParams::void(),
         };
         let spawn_pipeline_msg = SpawnPipeline {
             index_id: index_id.clone(),
             pipeline_ord: 0,
             source_config: source_config_0.clone(),
+            merge_pipeline_enabled: true,
         };
         let pipeline_id_0 = indexing_server_mailbox
             .ask_for_res(spawn_pipeline_msg.clone())
@@ -639,6 +1677,10 @@ mod tests {
         let source_config_1 = SourceConfig {
             source_id: "test-indexing-service--source-1".to_string(),
             num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
             source_params: SourceParams::void(),
         };
         metastore
@@ -660,6 +1702,10 @@ mod tests {
         let source_config_2 = SourceConfig {
             source_id: "test-indexing-service--source-2".to_string(),
             num_pipelines: 2,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
             source_params: SourceParams::void(),
         };
         metastore
@@ -736,6 +1782,10 @@ mod tests {
         let source_config_3 = SourceConfig {
             source_id: "test-indexing-service--source-3".to_string(),
             num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
             source_params: SourceParams::Vec(VecSourceParams {
                 docs: Vec::new(),
                 batch_num_docs: 10,
@@ -747,6 +1797,7 @@ mod tests {
                 index_id: index_id.clone(),
                 source_config: source_config_3,
                 pipeline_ord: 0,
+                merge_pipeline_enabled: true,
             })
             .await
             .unwrap();
@@ -759,4 +1810,456 @@ mod tests {
         }
         panic!("Sleep");
     }
+
+    #[tokio::test]
+    async fn test_indexing_service_relocate_pipeline() {
+        let metastore_uri = Uri::new("ram:///metastore".to_string());
+        let metastore = quickwit_metastore_uri_resolver()
+            .resolve(&metastore_uri)
+            .await
+            .unwrap();
+
+        let index_id = append_random_suffix("test-indexing-service-relocate");
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(&index_id, &index_uri);
+        metastore.create_index(index_metadata).await.unwrap();
+
+        let source_config = SourceConfig {
+            source_id: "test-relocate-source".to_string(),
+            num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::void(),
+        };
+        metastore
+            .add_source(&index_id, source_config.clone())
+            .await
+            .unwrap();
+
+        let universe = Universe::new();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let indexing_server = IndexingService::new(
+            "test-node".to_string(),
+            temp_dir.path().join("test-node"),
+            IndexerConfig::for_test().unwrap(),
+            metastore.clone(),
+            StorageUriResolver::for_test(),
+            false,
+        );
+        let (indexing_server_mailbox, indexing_server_handle) =
+            universe.spawn_actor(indexing_server).spawn();
+        let pipeline_id = indexing_server_mailbox
+            .ask_for_res(SpawnPipeline {
+                index_id: index_id.clone(),
+                source_config,
+                pipeline_ord: 0,
+                merge_pipeline_enabled: true,
+            })
+            .await
+            .unwrap();
+        let pipeline_ids = indexing_server_mailbox.ask(ListPipelines).await.unwrap();
+        assert_eq!(pipeline_ids, vec![pipeline_id.clone()]);
+
+        indexing_server_mailbox
+            .ask_for_res(RelocatePipeline {
+                pipeline_id: pipeline_id.clone(),
+                target_node_id: "test-node".to_string(),
+            })
+            .await
+            .unwrap_err();
+
+        let relocation = indexing_server_mailbox
+            .ask_for_res(RelocatePipeline {
+                pipeline_id: pipeline_id.clone(),
+                target_node_id: "test-other-node".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(relocation.state, RelocationState::Draining);
+
+        let mut relocations = Vec::new();
+        for _ in 0..2000 {
+            relocations = indexing_server_mailbox
+                .ask(ObserveRelocations)
+                .await
+                .unwrap();
+            if relocations[0].state != RelocationState::Draining {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(relocations.len(), 1);
+        assert_eq!(relocations[0].state, RelocationState::Drained);
+        assert_eq!(relocations[0].target_node_id, "test-other-node");
+        let observation = indexing_server_handle.observe().await;
+        assert_eq!(observation.num_running_pipelines, 0);
+        assert_eq!(observation.num_successful_pipelines, 1);
+
+        let other_indexing_server = IndexingService::new(
+            "test-other-node".to_string(),
+            temp_dir.path().join("test-other-node"),
+            IndexerConfig::for_test().unwrap(),
+            metastore.clone(),
+            StorageUriResolver::for_test(),
+            false,
+        );
+        let (other_indexing_server_mailbox, other_indexing_server_handle) =
+            universe.spawn_actor(other_indexing_server).spawn();
+        for _ in 0..2 {
+            let adopted_pipeline_id = other_indexing_server_mailbox
+                .ask_for_res(AdoptPipeline {
+                    relocated_pipeline_id: pipeline_id.clone(),
+                })
+                .await
+                .unwrap();
+            assert_eq!(adopted_pipeline_id.node_id, "test-other-node");
+            assert_eq!(adopted_pipeline_id.source_id, pipeline_id.source_id);
+            assert_eq!(adopted_pipeline_id.pipeline_ord, pipeline_id.pipeline_ord);
+        }
+        assert_eq!(
+            other_indexing_server_handle
+                .observe()
+                .await
+                .num_running_pipelines,
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_indexing_service_force_merge() {
+        let metastore_uri = Uri::new("ram:///metastore".to_string());
+        let metastore = quickwit_metastore_uri_resolver()
+            .resolve(&metastore_uri)
+            .await
+            .unwrap();
+
+        let index_id = append_random_suffix("test-indexing-service-force-merge");
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(&index_id, &index_uri);
+        metastore.create_index(index_metadata).await.unwrap();
+
+        let source_config = SourceConfig {
+            source_id: "test-force-merge-source".to_string(),
+            num_pipelines: 2,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::void(),
+        };
+        metastore
+            .add_source(&index_id, source_config.clone())
+            .await
+            .unwrap();
+
+        let universe = Universe::new();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let indexing_server = IndexingService::new(
+            "test-node".to_string(),
+            temp_dir.path().to_path_buf(),
+            IndexerConfig::for_test().unwrap(),
+            metastore.clone(),
+            StorageUriResolver::for_test(),
+            false,
+        );
+        let (indexing_server_mailbox, _indexing_server_handle) =
+            universe.spawn_actor(indexing_server).spawn();
+        let force_merge_index = ForceMergeIndex {
+            index_id: index_id.clone(),
+            target_num_splits: 1,
+        };
+        let error = indexing_server_mailbox
+            .ask_for_res(force_merge_index.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            AskError::ErrorReply(IndexingServiceError::MissingMergePipeline { .. })
+        ));
+
+        indexing_server_mailbox
+            .ask_for_res(SpawnPipeline {
+                index_id: index_id.clone(),
+                source_config: source_config.clone(),
+                pipeline_ord: 0,
+                merge_pipeline_enabled: false,
+            })
+            .await
+            .unwrap();
+        let error = indexing_server_mailbox
+            .ask_for_res(force_merge_index.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            AskError::ErrorReply(IndexingServiceError::MissingMergePipeline { .. })
+        ));
+
+        let pipeline_id = indexing_server_mailbox
+            .ask_for_res(SpawnPipeline {
+                index_id: index_id.clone(),
+                source_config,
+                pipeline_ord: 1,
+                merge_pipeline_enabled: true,
+            })
+            .await
+            .unwrap();
+        let force_merged_pipeline_ids = indexing_server_mailbox
+            .ask_for_res(force_merge_index.clone())
+            .await
+            .unwrap();
+        assert_eq!(force_merged_pipeline_ids, vec![pipeline_id.clone()]);
+
+        let recompressed_pipeline_ids = indexing_server_mailbox
+            .ask_for_res(RecompressIndex {
+                index_id: index_id.clone(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(recompressed_pipeline_ids, vec![pipeline_id]);
+
+        indexing_server_mailbox
+            .ask_for_res(ForceMergeIndex {
+                index_id,
+                target_num_splits: 0,
+            })
+            .await
+            .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_indexing_service_spawn_webhook_pipeline() {
+        let metastore_uri = Uri::new("ram:///metastore".to_string());
+        let metastore = quickwit_metastore_uri_resolver()
+            .resolve(&metastore_uri)
+            .await
+            .unwrap();
+
+        let index_id = append_random_suffix("test-indexing-service-webhook");
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(&index_id, &index_uri);
+        metastore.create_index(index_metadata).await.unwrap();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let data_dir_path = temp_dir.path().to_path_buf();
+        let indexer_config = IndexerConfig::for_test().unwrap();
+        let storage_resolver = StorageUriResolver::for_test();
+        let universe = Universe::new();
+        let queues_dir_path = data_dir_path.join(QUEUES_DIR_NAME);
+        let ingest_api_service = init_ingest_api(&universe, &queues_dir_path).await.unwrap();
+        let indexing_server = IndexingService::new(
+            "test-node".to_string(),
+            data_dir_path,
+            indexer_config,
+            metastore.clone(),
+            storage_resolver.clone(),
+            false,
+        );
+        let (indexing_server_mailbox, indexing_server_handle) =
+            universe.spawn_actor(indexing_server).spawn();
+
+        let source_config = SourceConfig {
+            source_id: "test-webhook-source".to_string(),
+            num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::Webhook(WebhookSourceParams {
+                secret_env_var: "QW_GITHUB_WEBHOOK_SECRET".to_string(),
+                signature_header: "x-quickwit-signature".to_string(),
+                batch_num_bytes_limit: None,
+            }),
+        };
+        let pipeline_id = indexing_server_mailbox
+            .ask_for_res(SpawnPipeline {
+                index_id: index_id.clone(),
+                pipeline_ord: 0,
+                source_config,
+                merge_pipeline_enabled: true,
+            })
+            .await
+            .unwrap();
+        assert_eq!(pipeline_id.source_id, "test-webhook-source");
+        assert_eq!(
+            indexing_server_handle.observe().await.num_running_pipelines,
+            1
+        );
+        let queue_exists = ingest_api_service
+            .ask_for_res(QueueExistsRequest {
+                queue_id: webhook_queue_id(&index_id, "test-webhook-source"),
+            })
+            .await
+            .unwrap();
+        assert!(queue_exists);
+    }
+
+    #[tokio::test]
+    async fn test_indexing_service_spawn_multiplexed_pipeline() {
+        let metastore_uri = Uri::new("ram:///metastore".to_string());
+        let metastore = quickwit_metastore_uri_resolver()
+            .resolve(&metastore_uri)
+            .await
+            .unwrap();
+
+        let index_id = append_random_suffix("test-indexing-service-multiplexed");
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let mut index_metadata = IndexMetadata::for_test(&index_id, &index_uri);
+        index_metadata.indexing_settings.multiplex_sources = true;
+        metastore.create_index(index_metadata).await.unwrap();
+        for (source_id, num_pipelines) in [("source-a", 1), ("source-b", 1), ("source-c", 2)] {
+            let source_config = SourceConfig {
+                source_id: source_id.to_string(),
+                num_pipelines,
+                profile: None,
+                rate_limit: None,
+                transforms: Vec::new(),
+                strict_ordering: false,
+                source_params: SourceParams::void(),
+            };
+            metastore
+                .add_source(&index_id, source_config)
+                .await
+                .unwrap();
+        }
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let data_dir_path = temp_dir.path().to_path_buf();
+        let indexer_config = IndexerConfig::for_test().unwrap();
+        let storage_resolver = StorageUriResolver::for_test();
+        let universe = Universe::new();
+        let indexing_server = IndexingService::new(
+            "test-node".to_string(),
+            data_dir_path,
+            indexer_config,
+            metastore.clone(),
+            storage_resolver.clone(),
+            false,
+        );
+        let (indexing_server_mailbox, indexing_server_handle) =
+            universe.spawn_actor(indexing_server).spawn();
+
+        // Sources A and B are read by the multiplexed pipeline, while source C runs its own
+        // pipelines.
+        let mut pipeline_ids = indexing_server_mailbox
+            .ask_for_res(SpawnPipelines {
+                index_id: index_id.clone(),
+            })
+            .await
+            .unwrap();
+        pipeline_ids.sort_by(|left, right| {
+            (&left.source_id, left.pipeline_ord).cmp(&(&right.source_id, right.pipeline_ord))
+        });
+        assert_eq!(
+            pipeline_ids
+                .iter()
+                .map(|pipeline_id| (pipeline_id.source_id.as_str(), pipeline_id.pipeline_ord))
+                .collect::<Vec<_>>(),
+            [(MULTIPLEXED_SOURCE_ID, 0), ("source-c", 0), ("source-c", 1)]
+        );
+        assert_eq!(
+            indexing_server_handle.observe().await.num_running_pipelines,
+            3
+        );
+
+        // Removing one of the multiplexed sources shuts down the multiplexed pipeline.
+        indexing_server_mailbox
+            .ask_for_res(ShutdownPipelines {
+                index_id: index_id.clone(),
+                source_id: Some("source-a".to_string()),
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            indexing_server_handle.observe().await.num_running_pipelines,
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_indexing_service_source_health() {
+        let metastore_uri = Uri::new("ram:///metastore".to_string());
+        let metastore = quickwit_metastore_uri_resolver()
+            .resolve(&metastore_uri)
+            .await
+            .unwrap();
+
+        let index_id = append_random_suffix("test-indexing-service-source-health");
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(&index_id, &index_uri);
+        metastore.create_index(index_metadata).await.unwrap();
+        let source_config = SourceConfig {
+            source_id: "void-source".to_string(),
+            num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::void(),
+        };
+        metastore
+            .add_source(&index_id, source_config)
+            .await
+            .unwrap();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let data_dir_path = temp_dir.path().to_path_buf();
+        let indexer_config = IndexerConfig::for_test().unwrap();
+        let storage_resolver = StorageUriResolver::for_test();
+        let universe = Universe::new();
+        let indexing_server = IndexingService::new(
+            "test-node".to_string(),
+            data_dir_path,
+            indexer_config,
+            metastore.clone(),
+            storage_resolver.clone(),
+            false,
+        );
+        let (indexing_server_mailbox, _indexing_server_handle) =
+            universe.spawn_actor(indexing_server).spawn();
+
+        let observe_source_health = || ObserveSourceHealth {
+            index_id: index_id.clone(),
+            source_id: "void-source".to_string(),
+        };
+        // No pipeline reads the source yet.
+        let source_health = indexing_server_mailbox
+            .ask_for_res(observe_source_health())
+            .await
+            .unwrap();
+        assert_eq!(source_health.status, SourceHealthStatus::Unhealthy);
+        assert!(source_health.connectivity.is_reachable);
+        assert_eq!(source_health.num_pipelines, 0);
+        assert!(source_health.throughput.is_none());
+
+        indexing_server_mailbox
+            .ask_for_res(SpawnPipelines {
+                index_id: index_id.clone(),
+            })
+            .await
+            .unwrap();
+        let source_health = indexing_server_mailbox
+            .ask_for_res(observe_source_health())
+            .await
+            .unwrap();
+        assert_eq!(source_health.status, SourceHealthStatus::Healthy);
+        assert_eq!(source_health.num_pipelines, 1);
+        assert!(source_health.throughput.is_some());
+        assert!(source_health.last_error.is_none());
+        assert!(source_health.data_loss_suspicions.is_empty());
+
+        let error = indexing_server_mailbox
+            .ask_for_res(ObserveSourceHealth {
+                index_id: index_id.clone(),
+                source_id: "missing-source".to_string(),
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            AskError::ErrorReply(IndexingServiceError::MissingSource { .. })
+        ));
+    }
 }
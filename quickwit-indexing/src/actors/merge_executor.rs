@@ -17,27 +17,44 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
-use std::ops::RangeInclusive;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap};
+use std::io::Write;
+use std::ops::{Bound, RangeInclusive};
 use std::path::Path;
-use std::time::Instant;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use async_trait::async_trait;
 use fail::fail_point;
 use itertools::Itertools;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
 use quickwit_common::runtimes::RuntimeType;
+use quickwit_config::{IndexingResources, IndexingSettings};
 use quickwit_directories::UnionDirectory;
-use quickwit_doc_mapper::QUICKWIT_TOKENIZER_MANAGER;
-use quickwit_metastore::SplitMetadata;
-use tantivy::directory::{DirectoryClone, MmapDirectory, RamDirectory};
-use tantivy::{Directory, Index, IndexMeta, SegmentId};
+use quickwit_doc_mapper::{DocMapper, DOC_ID_FIELD_NAME, QUICKWIT_TOKENIZER_MANAGER};
+use quickwit_metastore::{DedupDigest, DeleteTask, Metastore, SplitMetadata};
+use quickwit_proto::SearchRequest;
+use serde::Serialize;
+use tantivy::directory::{DirectoryClone, MmapDirectory, RamDirectory, TerminatingWrite};
+use tantivy::fastfield::FastFieldReader;
+use tantivy::merge_policy::NoMergePolicy;
+use tantivy::query::{BooleanQuery, Occur, Query, RangeQuery, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption, Schema, Type};
+use tantivy::store::{Compressor, StoreWriter, ZstdCompressor};
+use tantivy::{
+    DateTime, Directory, DocId, DocSet, Index, IndexMeta, SegmentComponent, SegmentId,
+    SegmentReader, Term, TERMINATED,
+};
+use time::OffsetDateTime;
 use tokio::runtime::Handle;
 use tracing::{debug, info, info_span, Span};
 
 use crate::actors::Packager;
-use crate::controlled_directory::ControlledDirectory;
+use crate::controlled_directory::{ControlledDirectory, DirectoryWriteSettings};
+use crate::merge_policy::{MergeExecutorCapabilities, MergeOperationType};
 use crate::models::{
     IndexedSplit, IndexedSplitBatch, IndexingPipelineId, MergeScratch, PublishLock,
     ScratchDirectory, SplitAttrs,
@@ -45,18 +62,126 @@ use crate::models::{
 
 pub struct MergeExecutor {
     pipeline_id: IndexingPipelineId,
+    metastore: Arc<dyn Metastore>,
+    /// Doc mapper of the index, used to build the queries of the delete tasks.
+    doc_mapper: Arc<dyn DocMapper>,
     merge_packager_mailbox: Mailbox<Packager>,
+    /// Deduplication window of the index. The digests of the merged splits are combined, minus
+    /// the buckets that fell out of the window.
+    dedup_window_opt: Option<Duration>,
+    /// Buffer capacity and sync cadence of the files of the merged splits.
+    write_settings: DirectoryWriteSettings,
+    /// Doc store codec and block size the recompress operations rewrite the splits with.
+    docstore_compression: Compressor,
+    docstore_blocksize: usize,
+    counters: MergeExecutorCounters,
+}
+
+/// Progress of the merge operation being executed.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct MergeProgress {
+    pub merge_split_id: String,
+    pub num_splits: usize,
+    /// Overall number of documents of the merged splits.
+    pub num_docs: u64,
+    /// Number of documents merged so far, estimated from the number of bytes written.
+    pub num_merged_docs: u64,
+    /// Estimated size of the merge output, that is, the overall size of the merged splits.
+    pub num_bytes: u64,
+    pub num_written_bytes: u64,
+    pub elapsed_secs: u64,
+    /// Estimated remaining time, extrapolated from the write throughput so far.
+    pub eta_secs: Option<u64>,
+}
+
+#[derive(Debug)]
+struct OngoingMerge {
+    merge_split_id: String,
+    num_splits: usize,
+    num_docs: u64,
+    num_bytes: u64,
+    start: Instant,
+    num_written_bytes: Arc<AtomicU64>,
+}
+
+impl OngoingMerge {
+    fn progress(&self) -> MergeProgress {
+        let elapsed = self.start.elapsed();
+        let num_written_bytes = self.num_written_bytes.load(Ordering::Relaxed);
+        let progress_ratio = if self.num_bytes == 0 {
+            0.0
+        } else {
+            (num_written_bytes as f64 / self.num_bytes as f64).min(1.0)
+        };
+        let eta_secs = if progress_ratio > 0.0 {
+            Some((elapsed.as_secs_f64() * (1.0 - progress_ratio) / progress_ratio) as u64)
+        } else {
+            None
+        };
+        MergeProgress {
+            merge_split_id: self.merge_split_id.clone(),
+            num_splits: self.num_splits,
+            num_docs: self.num_docs,
+            num_merged_docs: (self.num_docs as f64 * progress_ratio) as u64,
+            num_bytes: self.num_bytes,
+            num_written_bytes,
+            elapsed_secs: elapsed.as_secs(),
+            eta_secs,
+        }
+    }
+}
+
+/// The counters are shared with the merge executor, so that they stay up to date while a merge
+/// is running, even though the merge executor cannot process observations in the meantime.
+#[derive(Clone, Debug, Default)]
+pub struct MergeExecutorCounters {
+    pub num_merges: Arc<AtomicU64>,
+    ongoing_merge: Arc<Mutex<Option<OngoingMerge>>>,
+}
+
+impl MergeExecutorCounters {
+    /// Returns the progress of the ongoing merge, if any.
+    pub fn merge_progress(&self) -> Option<MergeProgress> {
+        self.ongoing_merge
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(OngoingMerge::progress)
+    }
+
+    fn start_merge(
+        &self,
+        merge_split_id: &str,
+        splits: &[SplitMetadata],
+        num_written_bytes: Arc<AtomicU64>,
+    ) {
+        let ongoing_merge = OngoingMerge {
+            merge_split_id: merge_split_id.to_string(),
+            num_splits: splits.len(),
+            num_docs: sum_num_docs(splits),
+            num_bytes: splits.iter().map(|split| split.footer_offsets.end).sum(),
+            start: Instant::now(),
+            num_written_bytes,
+        };
+        *self.ongoing_merge.lock().unwrap() = Some(ongoing_merge);
+    }
+
+    fn end_merge(&self) {
+        *self.ongoing_merge.lock().unwrap() = None;
+    }
 }
 
 #[async_trait]
 impl Actor for MergeExecutor {
-    type ObservableState = ();
+    type ObservableState = MergeExecutorCounters;
 
     fn runtime_handle(&self) -> Handle {
         RuntimeType::Blocking.get_runtime_handle()
     }
 
-    fn observable_state(&self) -> Self::ObservableState {}
+    fn observable_state(&self) -> Self::ObservableState {
+        self.counters.clone()
+    }
 
     fn queue_capacity(&self) -> QueueCapacity {
         QueueCapacity::Bounded(1)
@@ -98,6 +223,34 @@ impl Handler<MergeScratch> for MergeExecutor {
         ctx: &ActorContext<Self>,
     ) -> Result<(), ActorExitStatus> {
         let merge_op = &merge_scratch.merge_operation;
+        if !Self::capabilities().supports(merge_op.operation_type) {
+            return Err(ActorExitStatus::from(anyhow::anyhow!(
+                "Merge executor does not support `{:?}` operations.",
+                merge_op.operation_type
+            )));
+        }
+        if merge_op.operation_type == MergeOperationType::DeleteApply {
+            self.process_delete_and_merge(
+                merge_op.merge_split_id.clone(),
+                merge_op.splits.clone(),
+                merge_scratch.tantivy_dirs,
+                merge_scratch.merge_scratch_directory,
+                ctx,
+            )
+            .await?;
+            return Ok(());
+        }
+        if merge_op.operation_type == MergeOperationType::Recompress {
+            self.process_recompress(
+                merge_op.merge_split_id.clone(),
+                merge_op.splits.clone(),
+                merge_scratch.tantivy_dirs,
+                merge_scratch.merge_scratch_directory,
+                ctx,
+            )
+            .await?;
+            return Ok(());
+        }
         self.process_merge(
             merge_op.merge_split_id.clone(),
             merge_op.splits.clone(),
@@ -163,6 +316,241 @@ fn sum_num_docs(splits: &[SplitMetadata]) -> u64 {
     splits.iter().map(|split| split.num_docs as u64).sum()
 }
 
+/// The merged split may still be touched by the delete tasks that were not applied to all the
+/// splits it replaces.
+/// The merge planner only merges splits sharing the same doc mapping version.
+fn merge_doc_mapping_version(splits: &[SplitMetadata]) -> u64 {
+    splits
+        .iter()
+        .map(|split| split.doc_mapping_version)
+        .max()
+        .unwrap_or(0)
+}
+
+/// The config fingerprint of the merged split is only known if all the splits it replaces were
+/// built with the same config. Otherwise, the merged split is listed as built with an outdated
+/// config.
+fn merge_config_fingerprint(splits: &[SplitMetadata]) -> Option<String> {
+    let (first_split, other_splits) = splits.split_first()?;
+    if other_splits
+        .iter()
+        .all(|split| split.config_fingerprint == first_split.config_fingerprint)
+    {
+        first_split.config_fingerprint.clone()
+    } else {
+        None
+    }
+}
+
+fn min_delete_opstamp(splits: &[SplitMetadata]) -> u64 {
+    splits
+        .iter()
+        .map(|split| split.delete_opstamp)
+        .min()
+        .unwrap_or(0)
+}
+
+/// Whether some documents of the splits expired before `now_timestamp`.
+fn has_expired_docs(splits: &[SplitMetadata], now_timestamp: i64) -> bool {
+    splits.iter().any(|split| {
+        split
+            .min_expire_timestamp
+            .map_or(false, |min_expire_timestamp| {
+                min_expire_timestamp < now_timestamp
+            })
+    })
+}
+
+/// Builds the query matching the documents whose expiry is within the bounds.
+fn expire_timestamp_range_query(
+    schema: &Schema,
+    expire_timestamp_field: Field,
+    lower_bound: Bound<i64>,
+    upper_bound: Bound<i64>,
+) -> anyhow::Result<Box<dyn Query>> {
+    let to_date_time = |bound: Bound<i64>| match bound {
+        Bound::Included(timestamp) => Bound::Included(DateTime::from_timestamp_secs(timestamp)),
+        Bound::Excluded(timestamp) => Bound::Excluded(DateTime::from_timestamp_secs(timestamp)),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+    let field_type = schema.get_field_entry(expire_timestamp_field).field_type();
+    let range_query = match field_type.value_type() {
+        Type::I64 => RangeQuery::new_i64_bounds(expire_timestamp_field, lower_bound, upper_bound),
+        Type::Date => RangeQuery::new_date_bounds(
+            expire_timestamp_field,
+            to_date_time(lower_bound),
+            to_date_time(upper_bound),
+        ),
+        value_type => bail!(
+            "Expire timestamp field must be of type i64 or datetime, got `{:?}`.",
+            value_type
+        ),
+    };
+    Ok(Box::new(range_query))
+}
+
+/// Returns the earliest expiry of the documents of the index that did not expire before
+/// `now_timestamp`.
+fn compute_min_expire_timestamp(
+    index: &Index,
+    expire_timestamp_field: Field,
+    now_timestamp: i64,
+) -> anyhow::Result<Option<i64>> {
+    let schema = index.schema();
+    // The range query only matches the documents with an expiry, unlike the fast field which
+    // holds a default value for the other documents.
+    let unexpired_docs_query = expire_timestamp_range_query(
+        &schema,
+        expire_timestamp_field,
+        Bound::Included(now_timestamp),
+        Bound::Unbounded,
+    )?;
+    let searcher = index.reader()?.searcher();
+    let weight = unexpired_docs_query.weight(&searcher, false)?;
+    let mut min_expire_timestamp_opt: Option<i64> = None;
+    for segment_reader in searcher.segment_readers() {
+        let expire_timestamp_reader: Box<dyn Fn(DocId) -> i64> = match schema
+            .get_field_entry(expire_timestamp_field)
+            .field_type()
+            .value_type()
+        {
+            Type::Date => {
+                let fast_field_reader =
+                    segment_reader.fast_fields().date(expire_timestamp_field)?;
+                Box::new(move |doc| fast_field_reader.get(doc).into_timestamp_secs())
+            }
+            _ => {
+                let fast_field_reader = segment_reader.fast_fields().i64(expire_timestamp_field)?;
+                Box::new(move |doc| fast_field_reader.get(doc))
+            }
+        };
+        let mut scorer = weight.scorer(segment_reader, 1.0)?;
+        let mut doc = scorer.doc();
+        while doc != TERMINATED {
+            if !segment_reader.is_deleted(doc) {
+                let expire_timestamp = expire_timestamp_reader(doc);
+                min_expire_timestamp_opt = Some(
+                    min_expire_timestamp_opt.map_or(expire_timestamp, |min_expire_timestamp| {
+                        min_expire_timestamp.min(expire_timestamp)
+                    }),
+                );
+            }
+            doc = scorer.advance();
+        }
+    }
+    Ok(min_expire_timestamp_opt)
+}
+
+/// Builds the queries matching the documents superseded by a document with the same ID and a
+/// higher sequence number. The highest sequence number of every ID of the index is held in
+/// memory.
+fn superseded_docs_queries(
+    index: &Index,
+    doc_id_field: Field,
+    sequence_field: Field,
+) -> anyhow::Result<Vec<Box<dyn Query>>> {
+    let sequence_type = index
+        .schema()
+        .get_field_entry(sequence_field)
+        .field_type()
+        .value_type();
+    let searcher = index.reader()?.searcher();
+    // Highest sequence number of each document ID, and whether some documents with this ID
+    // have a lower sequence number.
+    let mut sequence_numbers: HashMap<Vec<u8>, (i128, bool)> = HashMap::new();
+    for segment_reader in searcher.segment_readers() {
+        let sequence_number_reader: Box<dyn Fn(DocId) -> i128> = match sequence_type {
+            Type::U64 => {
+                let fast_field_reader = segment_reader.fast_fields().u64(sequence_field)?;
+                Box::new(move |doc| fast_field_reader.get(doc) as i128)
+            }
+            _ => {
+                let fast_field_reader = segment_reader.fast_fields().i64(sequence_field)?;
+                Box::new(move |doc| fast_field_reader.get(doc) as i128)
+            }
+        };
+        let inverted_index = segment_reader.inverted_index(doc_id_field)?;
+        let mut terms_streamer = inverted_index.terms().stream()?;
+        while let Some((doc_id, term_info)) = terms_streamer.next() {
+            let mut postings =
+                inverted_index.read_postings_from_terminfo(term_info, IndexRecordOption::Basic)?;
+            let mut doc = postings.doc();
+            while doc != TERMINATED {
+                if !segment_reader.is_deleted(doc) {
+                    let sequence_number = sequence_number_reader(doc);
+                    match sequence_numbers.entry(doc_id.to_vec()) {
+                        Entry::Occupied(mut entry) => {
+                            let (max_sequence_number, has_superseded_docs) = entry.get_mut();
+                            *has_superseded_docs |= sequence_number != *max_sequence_number;
+                            *max_sequence_number = sequence_number.max(*max_sequence_number);
+                        }
+                        Entry::Vacant(entry) => {
+                            entry.insert((sequence_number, false));
+                        }
+                    }
+                }
+                doc = postings.advance();
+            }
+        }
+    }
+    let mut queries: Vec<Box<dyn Query>> = Vec::new();
+    for (doc_id, (max_sequence_number, has_superseded_docs)) in sequence_numbers {
+        if !has_superseded_docs {
+            continue;
+        }
+        let doc_id_term = Term::from_field_text(doc_id_field, std::str::from_utf8(&doc_id)?);
+        let lower_sequence_numbers_query: Box<dyn Query> = match sequence_type {
+            Type::U64 => Box::new(RangeQuery::new_u64_bounds(
+                sequence_field,
+                Bound::Unbounded,
+                Bound::Excluded(max_sequence_number as u64),
+            )),
+            _ => Box::new(RangeQuery::new_i64_bounds(
+                sequence_field,
+                Bound::Unbounded,
+                Bound::Excluded(max_sequence_number as i64),
+            )),
+        };
+        let query = BooleanQuery::new(vec![
+            (
+                Occur::Must,
+                Box::new(TermQuery::new(doc_id_term, IndexRecordOption::Basic)),
+            ),
+            (Occur::Must, lower_sequence_numbers_query),
+        ]);
+        queries.push(Box::new(query));
+    }
+    Ok(queries)
+}
+
+fn num_alive_docs(index: &Index) -> anyhow::Result<u64> {
+    let num_docs = index
+        .searchable_segment_metas()?
+        .iter()
+        .map(|segment_meta| segment_meta.num_docs() as u64)
+        .sum();
+    Ok(num_docs)
+}
+
+/// Combines the dedup digests of the splits, dropping the buckets older than the dedup window.
+fn merge_dedup_digests(
+    splits: &[SplitMetadata],
+    dedup_window_opt: Option<Duration>,
+) -> Option<DedupDigest> {
+    let dedup_window = dedup_window_opt?;
+    let mut merged_dedup_digest = DedupDigest::default();
+    for dedup_digest in splits.iter().flat_map(|split| split.dedup_digest.clone()) {
+        merged_dedup_digest.merge(dedup_digest);
+    }
+    let window_start_timestamp =
+        OffsetDateTime::now_utc().unix_timestamp() - dedup_window.as_secs() as i64;
+    merged_dedup_digest.retain_since(window_start_timestamp);
+    if merged_dedup_digest.is_empty() {
+        return None;
+    }
+    Some(merged_dedup_digest)
+}
+
 fn merge_all_segments(index: &Index) -> anyhow::Result<()> {
     let segment_ids: Vec<SegmentId> = index
         .searchable_segment_metas()?
@@ -208,16 +596,10 @@ pub fn combine_partition_ids(splits: &[SplitMetadata]) -> u64 {
 fn merge_split_directories(
     union_index_meta: IndexMeta,
     split_directories: Vec<Box<dyn Directory>>,
-    output_path: &Path,
+    output_directory: ControlledDirectory,
     ctx: &ActorContext<MergeExecutor>,
 ) -> anyhow::Result<ControlledDirectory> {
     let shadowing_meta_json_directory = create_shadowing_meta_json_directory(union_index_meta)?;
-    // This directory is here to receive the merged split, as well as the final meta.json file.
-    let output_directory = ControlledDirectory::new(
-        Box::new(MmapDirectory::open(output_path)?),
-        ctx.progress().clone(),
-        ctx.kill_switch().clone(),
-    );
     let mut directory_stack: Vec<Box<dyn Directory>> = vec![
         output_directory.box_clone(),
         Box::new(shadowing_meta_json_directory),
@@ -231,11 +613,209 @@ fn merge_split_directories(
     Ok(output_directory)
 }
 
+/// Applies the delete tasks to the splits and merges their segments, so that the deleted
+/// documents are dropped. The documents that expired before `now_timestamp` and the documents
+/// superseded by a more recent version are deleted as well.
+fn delete_and_merge_split_directories(
+    union_index_meta: IndexMeta,
+    split_directories: Vec<Box<dyn Directory>>,
+    output_directory: ControlledDirectory,
+    delete_tasks: &[DeleteTask],
+    now_timestamp: i64,
+    doc_mapper: &dyn DocMapper,
+    ctx: &ActorContext<MergeExecutor>,
+) -> anyhow::Result<ControlledDirectory> {
+    let shadowing_meta_json_directory = create_shadowing_meta_json_directory(union_index_meta)?;
+    let mut directory_stack: Vec<Box<dyn Directory>> = vec![
+        output_directory.box_clone(),
+        Box::new(shadowing_meta_json_directory),
+    ];
+    directory_stack.extend(split_directories.into_iter());
+    let union_directory = UnionDirectory::union_of(directory_stack);
+    let union_index = open_index(union_directory)?;
+    ctx.record_progress();
+    let _protect_guard = ctx.protect_zone();
+
+    let mut index_writer = union_index.writer_with_num_threads(1, 10_000_000)?;
+    index_writer.set_merge_policy(Box::new(NoMergePolicy));
+    let schema = union_index.schema();
+    for delete_task in delete_tasks {
+        let delete_query = &delete_task.delete_query;
+        let search_request = SearchRequest {
+            index_id: delete_query.index_id.clone(),
+            query: delete_query.query.clone(),
+            search_fields: delete_query.search_fields.clone(),
+            start_timestamp: delete_query.start_timestamp,
+            end_timestamp: delete_query.end_timestamp,
+            ..Default::default()
+        };
+        let query = doc_mapper
+            .query(schema.clone(), &search_request)
+            .with_context(|| format!("Failed to build delete query `{}`.", delete_query.query))?;
+        index_writer.delete_query(query)?;
+    }
+    if let Some(expire_timestamp_field) = doc_mapper.expire_timestamp_field(&schema) {
+        let expired_docs_query = expire_timestamp_range_query(
+            &schema,
+            expire_timestamp_field,
+            Bound::Unbounded,
+            Bound::Excluded(now_timestamp),
+        )?;
+        index_writer.delete_query(expired_docs_query)?;
+    }
+    if let (Some(sequence_field), Some(doc_id_field)) = (
+        doc_mapper.sequence_field(&schema),
+        schema.get_field(DOC_ID_FIELD_NAME),
+    ) {
+        let superseded_docs_queries =
+            superseded_docs_queries(&union_index, doc_id_field, sequence_field)?;
+        debug!(
+            num_superseded_doc_ids = superseded_docs_queries.len(),
+            "delete-superseded-docs"
+        );
+        for superseded_docs_query in superseded_docs_queries {
+            index_writer.delete_query(superseded_docs_query)?;
+        }
+    }
+    index_writer.commit()?;
+    let segment_ids: Vec<SegmentId> = union_index
+        .searchable_segment_metas()?
+        .into_iter()
+        .map(|segment_meta| segment_meta.id())
+        .collect();
+    // Unlike regular merges, a split with a single segment is also rewritten.
+    debug!(segment_ids=?segment_ids,"delete-and-merge-segments");
+    index_writer.merge(&segment_ids).wait()?;
+    index_writer.wait_merging_threads()?;
+    Ok(output_directory)
+}
+
+/// Size of the chunks in which the files of a split are copied.
+const COPY_CHUNK_NUM_BYTES: usize = 1 << 20;
+
+/// Rewrites the doc store of the segments of a split with the given codec and block size. The
+/// other files of the segments are copied as is, so the doc ids, the inverted index, and the fast
+/// fields of the split are preserved.
+fn recompress_split_directory(
+    mut index_meta: IndexMeta,
+    split_directory: Box<dyn Directory>,
+    output_directory: ControlledDirectory,
+    docstore_compression: Compressor,
+    docstore_blocksize: usize,
+    ctx: &ActorContext<MergeExecutor>,
+) -> anyhow::Result<ControlledDirectory> {
+    let split_index = open_index(split_directory.box_clone())?;
+    ctx.record_progress();
+    let _protect_guard = ctx.protect_zone();
+    for segment in split_index.searchable_segments()? {
+        let store_path = segment.relative_path(SegmentComponent::Store);
+        for relative_path in segment.meta().list_files() {
+            // `list_files` may return files that do not exist, for instance the delete file of
+            // a segment without deletes.
+            if relative_path == store_path || !split_directory.exists(&relative_path)? {
+                continue;
+            }
+            copy_file(&*split_directory, &output_directory, &relative_path)?;
+        }
+        let segment_reader = SegmentReader::open(&segment)?;
+        let store_reader = segment_reader.get_store_reader(1)?;
+        let store_write = output_directory.open_write(&store_path)?;
+        let mut store_writer =
+            StoreWriter::new(store_write, docstore_compression, docstore_blocksize);
+        // The deleted documents are kept so that the doc ids do not change.
+        for doc_id in 0..segment_reader.max_doc() {
+            let doc_bytes = store_reader.get_document_bytes(doc_id)?;
+            store_writer.store_bytes(doc_bytes.as_slice())?;
+        }
+        store_writer.close()?;
+    }
+    index_meta.index_settings.docstore_compression = docstore_compression;
+    index_meta.index_settings.docstore_blocksize = docstore_blocksize;
+    let index_meta_json = serde_json::to_string_pretty(&index_meta)?;
+    output_directory.atomic_write(Path::new("meta.json"), index_meta_json.as_bytes())?;
+    Ok(output_directory)
+}
+
+fn copy_file(
+    source_directory: &dyn Directory,
+    target_directory: &dyn Directory,
+    relative_path: &Path,
+) -> anyhow::Result<()> {
+    let file_slice = source_directory.open_read(relative_path)?;
+    let mut write = target_directory.open_write(relative_path)?;
+    let mut offset = 0;
+    while offset < file_slice.len() {
+        let end = (offset + COPY_CHUNK_NUM_BYTES).min(file_slice.len());
+        let chunk = file_slice.read_bytes_slice(offset..end)?;
+        write.write_all(chunk.as_slice())?;
+        offset = end;
+    }
+    write.terminate()?;
+    Ok(())
+}
+
 impl MergeExecutor {
-    pub fn new(pipeline_id: IndexingPipelineId, merge_packager_mailbox: Mailbox<Packager>) -> Self {
+    pub fn new(
+        pipeline_id: IndexingPipelineId,
+        metastore: Arc<dyn Metastore>,
+        doc_mapper: Arc<dyn DocMapper>,
+        dedup_window_opt: Option<Duration>,
+        merge_packager_mailbox: Mailbox<Packager>,
+    ) -> Self {
         MergeExecutor {
             pipeline_id,
+            metastore,
+            doc_mapper,
             merge_packager_mailbox,
+            dedup_window_opt,
+            write_settings: DirectoryWriteSettings::default(),
+            docstore_compression: Compressor::Zstd(ZstdCompressor {
+                compression_level: Some(IndexingSettings::default_docstore_compression_level()),
+            }),
+            docstore_blocksize: IndexingSettings::default_docstore_blocksize(),
+            counters: MergeExecutorCounters::default(),
+        }
+    }
+
+    /// Writes the merged splits with the buffer capacity and sync cadence set in the indexing
+    /// resources of the index.
+    pub fn with_indexing_resources(mut self, indexing_resources: &IndexingResources) -> Self {
+        self.write_settings = DirectoryWriteSettings::from(indexing_resources);
+        self
+    }
+
+    /// Rewrites the doc store of the splits of the recompress operations with the docstore
+    /// compression level and block size of the indexing settings of the index.
+    pub fn with_docstore_settings(mut self, indexing_settings: &IndexingSettings) -> Self {
+        self.docstore_compression = Compressor::Zstd(ZstdCompressor {
+            compression_level: Some(indexing_settings.docstore_compression_level),
+        });
+        self.docstore_blocksize = indexing_settings.docstore_blocksize;
+        self
+    }
+
+    /// Returns the operation types the merge executor can run. The merge planner only sends
+    /// operations of these types.
+    pub fn capabilities() -> MergeExecutorCapabilities {
+        MergeExecutorCapabilities::new([
+            MergeOperationType::Merge,
+            MergeOperationType::DeleteApply,
+            MergeOperationType::Recompress,
+        ])
+    }
+
+    /// Returns the earliest expiry of the documents of the index, if the index has an expire
+    /// timestamp field.
+    fn min_expire_timestamp(
+        &self,
+        index: &Index,
+        now_timestamp: i64,
+    ) -> anyhow::Result<Option<i64>> {
+        match self.doc_mapper.expire_timestamp_field(&index.schema()) {
+            Some(expire_timestamp_field) => {
+                compute_min_expire_timestamp(index, expire_timestamp_field, now_timestamp)
+            }
+            None => Ok(None),
         }
     }
 
@@ -256,14 +836,41 @@ impl MergeExecutor {
             .map(|split| split.split_id().to_string())
             .collect();
         let (union_index_meta, split_directories) = open_split_directories(&tantivy_dirs)?;
+        // This directory is here to receive the merged split, as well as the final meta.json file.
+        let output_directory = ControlledDirectory::new(
+            Box::new(MmapDirectory::open(merge_scratch_directory.path())?),
+            ctx.progress().clone(),
+            ctx.kill_switch().clone(),
+        )
+        .with_write_settings(self.write_settings);
+        self.counters.start_merge(
+            &merge_split_id,
+            &splits,
+            output_directory.num_bytes_written_counter(),
+        );
+        // The expired documents and the documents superseded by a more recent version are dropped
+        // along the way.
+        let now_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+        let drops_docs = has_expired_docs(&splits, now_timestamp)
+            || self.doc_mapper.sequence_field_name().is_some();
         // TODO it would be nice if tantivy could let us run the merge in the current thread.
         fail_point!("before-merge-split");
-        let controlled_directory = merge_split_directories(
-            union_index_meta,
-            split_directories,
-            merge_scratch_directory.path(),
-            ctx,
-        )?;
+        let merge_result = if drops_docs {
+            delete_and_merge_split_directories(
+                union_index_meta,
+                split_directories,
+                output_directory,
+                &[],
+                now_timestamp,
+                &*self.doc_mapper,
+                ctx,
+            )
+        } else {
+            merge_split_directories(union_index_meta, split_directories, output_directory, ctx)
+        };
+        self.counters.end_merge();
+        let controlled_directory = merge_result?;
+        self.counters.num_merges.fetch_add(1, Ordering::Relaxed);
         fail_point!("after-merge-split");
         info!(
             elapsed_secs = start.elapsed().as_secs_f32(),
@@ -273,11 +880,33 @@ impl MergeExecutor {
         // This will have the side effect of deleting the directory containing the downloaded
         // splits.
         let time_range = merge_time_range(&splits);
-        let uncompressed_docs_size_in_bytes = sum_doc_sizes_in_bytes(&splits);
-        let num_docs = sum_num_docs(&splits);
+        let mut uncompressed_docs_size_in_bytes = sum_doc_sizes_in_bytes(&splits);
+        let mut num_docs = sum_num_docs(&splits);
+        let dedup_digest_opt = merge_dedup_digests(&splits, self.dedup_window_opt);
+        let delete_opstamp = min_delete_opstamp(&splits);
+        let doc_mapping_version = merge_doc_mapping_version(&splits);
+        let config_fingerprint = merge_config_fingerprint(&splits);
 
         let merged_index = open_index(controlled_directory.clone())?;
         ctx.record_progress();
+        if drops_docs {
+            let num_remaining_docs = num_alive_docs(&merged_index)?;
+            if num_remaining_docs == 0 {
+                info!("merge-all-docs-expired");
+                let split_ids: Vec<&str> = replaced_split_ids.iter().map(String::as_str).collect();
+                ctx.protect_future(
+                    self.metastore
+                        .mark_splits_for_deletion(&self.pipeline_id.index_id, &split_ids),
+                )
+                .await?;
+                return Ok(());
+            }
+            // The size of the dropped documents is unknown, so it is estimated pro rata.
+            uncompressed_docs_size_in_bytes =
+                uncompressed_docs_size_in_bytes * num_remaining_docs / num_docs.max(1);
+            num_docs = num_remaining_docs;
+        }
+        let min_expire_timestamp = self.min_expire_timestamp(&merged_index, now_timestamp)?;
         let index_writer = merged_index.writer_with_num_threads(1, 3_000_000)?;
         ctx.record_progress();
 
@@ -287,9 +916,14 @@ impl MergeExecutor {
                 partition_id,
                 pipeline_id,
                 replaced_split_ids,
+                delete_opstamp,
+                dedup_digest_opt,
                 time_range,
                 num_docs,
                 uncompressed_docs_size_in_bytes,
+                min_expire_timestamp,
+                doc_mapping_version,
+                config_fingerprint,
             },
             index: merged_index,
             index_writer,
@@ -302,6 +936,232 @@ impl MergeExecutor {
             IndexedSplitBatch {
                 splits: vec![indexed_split],
                 checkpoint_delta: Default::default(),
+                publish_with_next_delta: false,
+                publish_lock: PublishLock::default(),
+                date_of_birth: start,
+            },
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Applies the pending delete tasks to a single split. The split is rewritten without the
+    /// deleted, expired, and superseded documents and replaces the original split on publish.
+    async fn process_delete_and_merge(
+        &mut self,
+        merge_split_id: String,
+        mut splits: Vec<SplitMetadata>,
+        tantivy_dirs: Vec<Box<dyn Directory>>,
+        merge_scratch_directory: ScratchDirectory,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<()> {
+        if splits.len() != 1 {
+            anyhow::bail!(
+                "Delete apply operations expect a single split, got {}.",
+                splits.len()
+            );
+        }
+        let split = splits.pop().expect("There should be exactly one split.");
+        let index_id = self.pipeline_id.index_id.clone();
+        let delete_tasks = ctx
+            .protect_future(
+                self.metastore
+                    .list_delete_tasks(&index_id, split.delete_opstamp),
+            )
+            .await?;
+        let now_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+        let has_expired_docs = has_expired_docs(std::slice::from_ref(&split), now_timestamp);
+        let last_delete_opstamp = match delete_tasks.last() {
+            Some(delete_task) => delete_task.opstamp,
+            None if has_expired_docs => split.delete_opstamp,
+            None => {
+                info!(split_id=%split.split_id(), "no-pending-delete-task");
+                return Ok(());
+            }
+        };
+        let start = Instant::now();
+        info!(
+            split_id=%split.split_id(),
+            num_delete_tasks=delete_tasks.len(),
+            has_expired_docs=has_expired_docs,
+            "delete-apply-start"
+        );
+        let (union_index_meta, split_directories) = open_split_directories(&tantivy_dirs)?;
+        let output_directory = ControlledDirectory::new(
+            Box::new(MmapDirectory::open(merge_scratch_directory.path())?),
+            ctx.progress().clone(),
+            ctx.kill_switch().clone(),
+        )
+        .with_write_settings(self.write_settings);
+        self.counters.start_merge(
+            &merge_split_id,
+            std::slice::from_ref(&split),
+            output_directory.num_bytes_written_counter(),
+        );
+        let delete_result = delete_and_merge_split_directories(
+            union_index_meta,
+            split_directories,
+            output_directory,
+            &delete_tasks,
+            now_timestamp,
+            &*self.doc_mapper,
+            ctx,
+        );
+        self.counters.end_merge();
+        let controlled_directory = delete_result?;
+        self.counters.num_merges.fetch_add(1, Ordering::Relaxed);
+
+        let merged_index = open_index(controlled_directory.clone())?;
+        ctx.record_progress();
+        let num_docs = num_alive_docs(&merged_index)?;
+        if num_docs == 0 {
+            info!(split_id=%split.split_id(), "delete-apply-all-docs-deleted");
+            ctx.protect_future(
+                self.metastore
+                    .mark_splits_for_deletion(&index_id, &[split.split_id()]),
+            )
+            .await?;
+            return Ok(());
+        }
+        info!(
+            elapsed_secs = start.elapsed().as_secs_f32(),
+            num_deleted_docs = split.num_docs as u64 - num_docs,
+            "delete-apply-success"
+        );
+        // The size of the deleted documents is unknown, so it is estimated pro rata.
+        let uncompressed_docs_size_in_bytes =
+            split.uncompressed_docs_size_in_bytes * num_docs / (split.num_docs as u64).max(1);
+        let min_expire_timestamp = self.min_expire_timestamp(&merged_index, now_timestamp)?;
+        let index_writer = merged_index.writer_with_num_threads(1, 3_000_000)?;
+        ctx.record_progress();
+
+        let indexed_split = IndexedSplit {
+            split_attrs: SplitAttrs {
+                split_id: merge_split_id,
+                partition_id: split.partition_id,
+                pipeline_id: self.pipeline_id.clone(),
+                replaced_split_ids: vec![split.split_id().to_string()],
+                delete_opstamp: last_delete_opstamp,
+                dedup_digest_opt: split.dedup_digest.clone(),
+                min_expire_timestamp,
+                time_range: split.time_range.clone(),
+                num_docs,
+                uncompressed_docs_size_in_bytes,
+                doc_mapping_version: split.doc_mapping_version,
+                config_fingerprint: split.config_fingerprint.clone(),
+            },
+            index: merged_index,
+            index_writer,
+            split_scratch_directory: merge_scratch_directory,
+            controlled_directory_opt: Some(controlled_directory),
+        };
+        ctx.send_message(
+            &self.merge_packager_mailbox,
+            IndexedSplitBatch {
+                splits: vec![indexed_split],
+                checkpoint_delta: Default::default(),
+                publish_with_next_delta: false,
+                publish_lock: PublishLock::default(),
+                date_of_birth: start,
+            },
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Rewrites the doc store of a single split with the docstore compression settings of the
+    /// executor. The rewritten split replaces the original split on publish. Splits already
+    /// written with these settings are left as is.
+    async fn process_recompress(
+        &mut self,
+        merge_split_id: String,
+        mut splits: Vec<SplitMetadata>,
+        tantivy_dirs: Vec<Box<dyn Directory>>,
+        merge_scratch_directory: ScratchDirectory,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<()> {
+        if splits.len() != 1 || tantivy_dirs.len() != 1 {
+            anyhow::bail!(
+                "Recompress operations expect a single split, got {}.",
+                splits.len()
+            );
+        }
+        let split = splits.pop().expect("There should be exactly one split.");
+        let split_directory = tantivy_dirs
+            .into_iter()
+            .next()
+            .expect("There should be exactly one split directory.");
+        let index_meta = open_index(split_directory.box_clone())?.load_metas()?;
+        if index_meta.index_settings.docstore_compression == self.docstore_compression
+            && index_meta.index_settings.docstore_blocksize == self.docstore_blocksize
+        {
+            info!(split_id=%split.split_id(), "split-already-recompressed");
+            return Ok(());
+        }
+        let start = Instant::now();
+        info!(
+            split_id=%split.split_id(),
+            from_docstore_compression=?index_meta.index_settings.docstore_compression,
+            to_docstore_compression=?self.docstore_compression,
+            "recompress-start"
+        );
+        let output_directory = ControlledDirectory::new(
+            Box::new(MmapDirectory::open(merge_scratch_directory.path())?),
+            ctx.progress().clone(),
+            ctx.kill_switch().clone(),
+        )
+        .with_write_settings(self.write_settings);
+        self.counters.start_merge(
+            &merge_split_id,
+            std::slice::from_ref(&split),
+            output_directory.num_bytes_written_counter(),
+        );
+        let recompress_result = recompress_split_directory(
+            index_meta,
+            split_directory,
+            output_directory,
+            self.docstore_compression,
+            self.docstore_blocksize,
+            ctx,
+        );
+        self.counters.end_merge();
+        let controlled_directory = recompress_result?;
+        self.counters.num_merges.fetch_add(1, Ordering::Relaxed);
+        info!(
+            elapsed_secs = start.elapsed().as_secs_f32(),
+            "recompress-success"
+        );
+
+        let recompressed_index = open_index(controlled_directory.clone())?;
+        let index_writer = recompressed_index.writer_with_num_threads(1, 3_000_000)?;
+        ctx.record_progress();
+
+        let indexed_split = IndexedSplit {
+            split_attrs: SplitAttrs {
+                split_id: merge_split_id,
+                partition_id: split.partition_id,
+                pipeline_id: self.pipeline_id.clone(),
+                replaced_split_ids: vec![split.split_id().to_string()],
+                delete_opstamp: split.delete_opstamp,
+                dedup_digest_opt: split.dedup_digest.clone(),
+                min_expire_timestamp: split.min_expire_timestamp,
+                time_range: split.time_range.clone(),
+                num_docs: split.num_docs as u64,
+                uncompressed_docs_size_in_bytes: split.uncompressed_docs_size_in_bytes,
+                doc_mapping_version: split.doc_mapping_version,
+                config_fingerprint: split.config_fingerprint.clone(),
+            },
+            index: recompressed_index,
+            index_writer,
+            split_scratch_directory: merge_scratch_directory,
+            controlled_directory_opt: Some(controlled_directory),
+        };
+        ctx.send_message(
+            &self.merge_packager_mailbox,
+            IndexedSplitBatch {
+                splits: vec![indexed_split],
+                checkpoint_delta: Default::default(),
+                publish_with_next_delta: false,
                 publish_lock: PublishLock::default(),
                 date_of_birth: start,
             },
@@ -321,12 +1181,13 @@ fn open_index<T: Into<Box<dyn Directory>>>(directory: T) -> tantivy::Result<Inde
 mod tests {
     use quickwit_actors::{create_test_mailbox, Universe};
     use quickwit_common::split_file;
-    use quickwit_metastore::SplitMetadata;
+    use quickwit_metastore::{DeleteQuery, SplitMetadata};
+    use tantivy::collector::TopDocs;
 
     use super::*;
     use crate::merge_policy::MergeOperation;
     use crate::models::{IndexingPipelineId, ScratchDirectory};
-    use crate::{get_tantivy_directory_from_split_bundle, TestSandbox};
+    use crate::{get_tantivy_directory_from_split_bundle, MergePermit, TestSandbox};
 
     #[tokio::test]
     async fn test_merge_executor() -> anyhow::Result<()> {
@@ -378,18 +1239,34 @@ mod tests {
             merge_operation: MergeOperation {
                 merge_split_id: crate::new_split_id(),
                 splits: split_metas,
+                operation_type: MergeOperationType::Merge,
             },
             tantivy_dirs,
             merge_scratch_directory,
             downloaded_splits_directory,
+            merge_permit: MergePermit::default(),
         };
         let (merge_packager_mailbox, merge_packager_inbox) = create_test_mailbox();
-        let merge_executor = MergeExecutor::new(pipeline_id, merge_packager_mailbox);
+        let merge_executor = MergeExecutor::new(
+            pipeline_id,
+            test_sandbox.metastore(),
+            test_sandbox.doc_mapper(),
+            None,
+            merge_packager_mailbox,
+        );
         let universe = Universe::new();
         let (merge_executor_mailbox, merge_executor_handle) =
             universe.spawn_actor(merge_executor).spawn();
         merge_executor_mailbox.send_message(merge_scratch).await?;
-        merge_executor_handle.process_pending_and_observe().await;
+        let merge_executor_counters = merge_executor_handle
+            .process_pending_and_observe()
+            .await
+            .state;
+        assert_eq!(
+            merge_executor_counters.num_merges.load(Ordering::Relaxed),
+            1
+        );
+        assert!(merge_executor_counters.merge_progress().is_none());
         let mut packager_msgs = merge_packager_inbox.drain_for_test();
         assert_eq!(packager_msgs.len(), 1);
         let packager_msg = packager_msgs
@@ -410,6 +1287,352 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_merge_executor_drops_superseded_docs() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index-sequence".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: order_id
+                type: text
+                tokenizer: raw
+              - name: version
+                type: u64
+                fast: true
+            doc_id:
+              source: field
+              field: order_id
+            sequence_field: version
+        "#;
+        let test_sandbox =
+            TestSandbox::create(&pipeline_id.index_id, doc_mapping_yaml, "{}", &["order_id"])
+                .await?;
+        let batches = [
+            vec![
+                serde_json::json!({"order_id": "order-1", "version": 1}),
+                serde_json::json!({"order_id": "order-2", "version": 1}),
+            ],
+            vec![serde_json::json!({"order_id": "order-1", "version": 3})],
+            vec![
+                serde_json::json!({"order_id": "order-1", "version": 2}),
+                serde_json::json!({"order_id": "order-2", "version": 1}),
+            ],
+        ];
+        for docs in batches {
+            test_sandbox.add_documents(docs).await?;
+        }
+        let metastore = test_sandbox.metastore();
+        let split_metas: Vec<SplitMetadata> = metastore
+            .list_all_splits(&pipeline_id.index_id)
+            .await?
+            .into_iter()
+            .map(|split| split.split_metadata)
+            .collect();
+        assert_eq!(split_metas.len(), 3);
+        let merge_scratch_directory = ScratchDirectory::for_test()?;
+        let downloaded_splits_directory =
+            merge_scratch_directory.named_temp_child("downloaded-splits-")?;
+        let mut tantivy_dirs: Vec<Box<dyn Directory>> = vec![];
+        for split_meta in &split_metas {
+            let split_filename = split_file(split_meta.split_id());
+            let dest_filepath = downloaded_splits_directory.path().join(&split_filename);
+            test_sandbox
+                .storage()
+                .copy_to_file(Path::new(&split_filename), &dest_filepath)
+                .await?;
+            tantivy_dirs.push(get_tantivy_directory_from_split_bundle(&dest_filepath).unwrap())
+        }
+        let merge_scratch = MergeScratch {
+            merge_operation: MergeOperation::new_operation(MergeOperationType::Merge, split_metas),
+            tantivy_dirs,
+            merge_scratch_directory,
+            downloaded_splits_directory,
+            merge_permit: MergePermit::default(),
+        };
+        let (merge_packager_mailbox, merge_packager_inbox) = create_test_mailbox();
+        let merge_executor = MergeExecutor::new(
+            pipeline_id,
+            metastore,
+            test_sandbox.doc_mapper(),
+            None,
+            merge_packager_mailbox,
+        );
+        let universe = Universe::new();
+        let (merge_executor_mailbox, merge_executor_handle) =
+            universe.spawn_actor(merge_executor).spawn();
+        merge_executor_mailbox.send_message(merge_scratch).await?;
+        merge_executor_handle.process_pending_and_observe().await;
+
+        let mut packager_msgs = merge_packager_inbox.drain_for_test();
+        assert_eq!(packager_msgs.len(), 1);
+        let packager_msg = packager_msgs
+            .pop()
+            .unwrap()
+            .downcast::<IndexedSplitBatch>()
+            .unwrap();
+        // Only the latest version of `order-1` is kept. The versions of `order-2` share the same
+        // sequence number, so none of them supersedes the other.
+        assert_eq!(packager_msg.splits[0].split_attrs.num_docs, 3);
+        let merged_index = &packager_msg.splits[0].index;
+        let schema = merged_index.schema();
+        let doc_id_field = schema.get_field(DOC_ID_FIELD_NAME).unwrap();
+        let version_field = schema.get_field("version").unwrap();
+        let searcher = merged_index.reader()?.searcher();
+        let order_1_query = TermQuery::new(
+            Term::from_field_text(doc_id_field, "order-1"),
+            IndexRecordOption::Basic,
+        );
+        let order_1_docs = searcher.search(&order_1_query, &TopDocs::with_limit(10))?;
+        assert_eq!(order_1_docs.len(), 1);
+        let order_1_doc = searcher.doc(order_1_docs[0].1)?;
+        assert_eq!(
+            order_1_doc.get_first(version_field).unwrap().as_u64(),
+            Some(3)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_and_merge_executor() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index-delete".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: ts
+                type: i64
+                fast: true
+        "#;
+        let test_sandbox =
+            TestSandbox::create(&pipeline_id.index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+        let docs = vec![
+            serde_json::json!({"body": "info", "ts": 1631072713u64 }),
+            serde_json::json!({"body": "delete", "ts": 1631072714u64 }),
+            serde_json::json!({"body": "info", "ts": 1631072715u64 }),
+        ];
+        test_sandbox.add_documents(docs).await?;
+        let metastore = test_sandbox.metastore();
+        let delete_task = metastore
+            .create_delete_task(DeleteQuery {
+                index_id: pipeline_id.index_id.clone(),
+                query: "body:delete".to_string(),
+                ..Default::default()
+            })
+            .await?;
+        let mut split_metas: Vec<SplitMetadata> = metastore
+            .list_all_splits(&pipeline_id.index_id)
+            .await?
+            .into_iter()
+            .map(|split| split.split_metadata)
+            .collect();
+        assert_eq!(split_metas.len(), 1);
+        let split_meta = split_metas.pop().unwrap();
+        assert_eq!(split_meta.delete_opstamp, 0);
+
+        let merge_scratch_directory = ScratchDirectory::for_test()?;
+        let downloaded_splits_directory =
+            merge_scratch_directory.named_temp_child("downloaded-splits-")?;
+        let split_filename = split_file(split_meta.split_id());
+        let dest_filepath = downloaded_splits_directory.path().join(&split_filename);
+        test_sandbox
+            .storage()
+            .copy_to_file(Path::new(&split_filename), &dest_filepath)
+            .await?;
+        let tantivy_dirs = vec![get_tantivy_directory_from_split_bundle(&dest_filepath).unwrap()];
+        let merge_scratch = MergeScratch {
+            merge_operation: MergeOperation::new_operation(
+                MergeOperationType::DeleteApply,
+                vec![split_meta.clone()],
+            ),
+            tantivy_dirs,
+            merge_scratch_directory,
+            downloaded_splits_directory,
+            merge_permit: MergePermit::default(),
+        };
+        let (merge_packager_mailbox, merge_packager_inbox) = create_test_mailbox();
+        let merge_executor = MergeExecutor::new(
+            pipeline_id,
+            metastore,
+            test_sandbox.doc_mapper(),
+            None,
+            merge_packager_mailbox,
+        );
+        let universe = Universe::new();
+        let (merge_executor_mailbox, merge_executor_handle) =
+            universe.spawn_actor(merge_executor).spawn();
+        merge_executor_mailbox.send_message(merge_scratch).await?;
+        merge_executor_handle.process_pending_and_observe().await;
+
+        let mut packager_msgs = merge_packager_inbox.drain_for_test();
+        assert_eq!(packager_msgs.len(), 1);
+        let packager_msg = packager_msgs
+            .pop()
+            .unwrap()
+            .downcast::<IndexedSplitBatch>()
+            .unwrap();
+        let split_attrs = &packager_msg.splits[0].split_attrs;
+        assert_eq!(split_attrs.num_docs, 2);
+        assert_eq!(split_attrs.delete_opstamp, delete_task.opstamp);
+        assert_eq!(
+            split_attrs.replaced_split_ids,
+            vec![split_meta.split_id().to_string()]
+        );
+        let reader = packager_msg.splits[0].index.reader()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        assert_eq!(searcher.num_docs(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recompress_executor() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index-recompress".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: ts
+                type: i64
+                fast: true
+        "#;
+        let test_sandbox =
+            TestSandbox::create(&pipeline_id.index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+        let docs = vec![
+            serde_json::json!({"body": "first", "ts": 1631072713u64 }),
+            serde_json::json!({"body": "second", "ts": 1631072714u64 }),
+            serde_json::json!({"body": "third", "ts": 1631072715u64 }),
+        ];
+        test_sandbox.add_documents(docs).await?;
+        let metastore = test_sandbox.metastore();
+        let mut split_metas: Vec<SplitMetadata> = metastore
+            .list_all_splits(&pipeline_id.index_id)
+            .await?
+            .into_iter()
+            .map(|split| split.split_metadata)
+            .collect();
+        assert_eq!(split_metas.len(), 1);
+        let split_meta = split_metas.pop().unwrap();
+
+        let merge_scratch_directory = ScratchDirectory::for_test()?;
+        let downloaded_splits_directory =
+            merge_scratch_directory.named_temp_child("downloaded-splits-")?;
+        let split_filename = split_file(split_meta.split_id());
+        let dest_filepath = downloaded_splits_directory.path().join(&split_filename);
+        test_sandbox
+            .storage()
+            .copy_to_file(Path::new(&split_filename), &dest_filepath)
+            .await?;
+        let tantivy_dirs = vec![get_tantivy_directory_from_split_bundle(&dest_filepath).unwrap()];
+        let merge_scratch = MergeScratch {
+            merge_operation: MergeOperation::new_operation(
+                MergeOperationType::Recompress,
+                vec![split_meta.clone()],
+            ),
+            tantivy_dirs,
+            merge_scratch_directory,
+            downloaded_splits_directory,
+            merge_permit: MergePermit::default(),
+        };
+        let indexing_settings = IndexingSettings {
+            docstore_compression_level: 3,
+            ..Default::default()
+        };
+        let (merge_packager_mailbox, merge_packager_inbox) = create_test_mailbox();
+        let merge_executor = MergeExecutor::new(
+            pipeline_id,
+            metastore,
+            test_sandbox.doc_mapper(),
+            None,
+            merge_packager_mailbox,
+        )
+        .with_docstore_settings(&indexing_settings);
+        let universe = Universe::new();
+        let (merge_executor_mailbox, merge_executor_handle) =
+            universe.spawn_actor(merge_executor).spawn();
+        merge_executor_mailbox.send_message(merge_scratch).await?;
+        merge_executor_handle.process_pending_and_observe().await;
+
+        let mut packager_msgs = merge_packager_inbox.drain_for_test();
+        assert_eq!(packager_msgs.len(), 1);
+        let packager_msg = packager_msgs
+            .pop()
+            .unwrap()
+            .downcast::<IndexedSplitBatch>()
+            .unwrap();
+        let split_attrs = &packager_msg.splits[0].split_attrs;
+        assert_eq!(split_attrs.num_docs, 3);
+        assert_eq!(
+            split_attrs.replaced_split_ids,
+            vec![split_meta.split_id().to_string()]
+        );
+        let recompressed_index = &packager_msg.splits[0].index;
+        assert_eq!(
+            recompressed_index.settings().docstore_compression,
+            Compressor::Zstd(ZstdCompressor {
+                compression_level: Some(3)
+            })
+        );
+        let schema = recompressed_index.schema();
+        let body_field = schema.get_field("body").unwrap();
+        let reader = recompressed_index.reader()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let bodies: Vec<String> = (0..3)
+            .map(|doc_id| {
+                let doc = searcher.doc(tantivy::DocAddress::new(0, doc_id)).unwrap();
+                doc.get_first(body_field)
+                    .and_then(|value| value.as_text())
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(bodies, ["first", "second", "third"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_progress() {
+        let num_written_bytes = Arc::new(AtomicU64::new(0));
+        let ongoing_merge = OngoingMerge {
+            merge_split_id: "merged-split".to_string(),
+            num_splits: 10,
+            num_docs: 1_000,
+            num_bytes: 4_000,
+            start: Instant::now() - Duration::from_secs(30),
+            num_written_bytes: num_written_bytes.clone(),
+        };
+        let merge_progress = ongoing_merge.progress();
+        assert_eq!(merge_progress.num_merged_docs, 0);
+        assert_eq!(merge_progress.elapsed_secs, 30);
+        assert!(merge_progress.eta_secs.is_none());
+
+        num_written_bytes.store(1_000, Ordering::Relaxed);
+        let merge_progress = ongoing_merge.progress();
+        assert_eq!(merge_progress.num_merged_docs, 250);
+        assert_eq!(merge_progress.num_written_bytes, 1_000);
+        assert!((89..=91).contains(&merge_progress.eta_secs.unwrap()));
+
+        // The merge output may be larger than the estimation.
+        num_written_bytes.store(5_000, Ordering::Relaxed);
+        let merge_progress = ongoing_merge.progress();
+        assert_eq!(merge_progress.num_merged_docs, 1_000);
+        assert_eq!(merge_progress.eta_secs, Some(0));
+    }
+
     #[test]
     fn test_combine_partition_ids_singleton_unchanged() {
         assert_eq!(combine_partition_ids_aux([17].into_iter()), 17);
@@ -438,4 +1661,25 @@ mod tests {
             combine_partition_ids_aux([12, 13, 7].into_iter())
         );
     }
+
+    #[test]
+    fn test_merge_config_fingerprint() {
+        let split = |config_fingerprint: Option<&str>| SplitMetadata {
+            config_fingerprint: config_fingerprint.map(str::to_string),
+            ..Default::default()
+        };
+        assert_eq!(merge_config_fingerprint(&[]), None);
+        assert_eq!(
+            merge_config_fingerprint(&[split(Some("abc")), split(Some("abc"))]),
+            Some("abc".to_string())
+        );
+        assert_eq!(
+            merge_config_fingerprint(&[split(Some("abc")), split(Some("def"))]),
+            None
+        );
+        assert_eq!(
+            merge_config_fingerprint(&[split(Some("abc")), split(None)]),
+            None
+        );
+    }
 }
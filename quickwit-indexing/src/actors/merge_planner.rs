@@ -17,27 +17,81 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use async_trait::async_trait;
 use itertools::Itertools;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
-use quickwit_metastore::SplitMetadata;
-use tracing::info;
+use quickwit_common::metrics::IntGauge;
+use quickwit_config::MergeCoordinationSettings;
+use quickwit_metastore::{Metastore, SplitMetadata, SplitState};
+use tracing::{info, warn};
 
-use crate::actors::MergeSplitDownloader;
-use crate::models::{IndexingPipelineId, NewSplits};
+use crate::actors::{MergeExecutor, MergeSplitDownloader};
+use crate::merge_policy::{MergeExecutorCapabilities, MergeOperation, MergeOperationType};
+use crate::metrics::INDEXER_METRICS;
+use crate::models::{
+    AbortedMerge, ApplyDeletes, ForceMerge, IndexingPipelineId, NewSplits, RecompressSplits,
+};
 use crate::MergePolicy;
 
+/// Whether the merge planner of a pipeline holds the merge planner lease of its index. It is
+/// shared with the delete executor of the pipeline, which only hands over splits to an elected
+/// merge planner.
+#[derive(Clone, Debug, Default)]
+pub struct MergePlannerLeadership(Arc<AtomicBool>);
+
+impl MergePlannerLeadership {
+    pub fn is_leader(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Updates the leadership and returns the previous one.
+    fn swap(&self, is_leader: bool) -> bool {
+        self.0.swap(is_leader, Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug)]
+struct RefreshMergeCoordination;
+
+/// State of a merge planner competing with the merge planners of the other pipelines of the index
+/// for the right to plan the merges of all their splits.
+struct MergeCoordination {
+    metastore: Arc<dyn Metastore>,
+    settings: MergeCoordinationSettings,
+    lease_owner: String,
+    leadership: MergePlannerLeadership,
+    /// Splits handed over to the merge pipeline that have not been replaced yet.
+    ongoing_split_ids: HashSet<String>,
+}
+
+/// Young splits are merged with the splits of the same partition and doc mapping version only.
+type PartitionKey = (u64, u64);
+
+fn partition_key(split: &SplitMetadata) -> PartitionKey {
+    (split.partition_id, split.doc_mapping_version)
+}
+
 /// The merge planner decides when to start a merge task.
 pub struct MergePlanner {
     pipeline_id: IndexingPipelineId,
     /// A young split is a split that has not reached maturity
     /// yet and can be candidate to merge operations.
-    partitioned_young_splits: HashMap<u64, Vec<SplitMetadata>>,
+    partitioned_young_splits: HashMap<PartitionKey, Vec<SplitMetadata>>,
     merge_policy: Arc<dyn MergePolicy>,
+    /// Operation types supported by the merge executor of the pipeline.
+    executor_capabilities: MergeExecutorCapabilities,
     merge_split_downloader_mailbox: Mailbox<MergeSplitDownloader>,
+    merge_backlog_gauge: IntGauge,
+    /// Number of young splits last reported to the merge backlog gauge, which is shared by the
+    /// pipelines of the same source.
+    reported_num_young_splits: i64,
+    /// When set, the merge planner only plans merges while it holds the merge planner lease of
+    /// the index, and then covers the splits of all the pipelines of the index.
+    coordination_opt: Option<MergeCoordination>,
 }
 
 #[async_trait]
@@ -55,10 +109,47 @@ impl Actor for MergePlanner {
     }
 
     async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        if self.coordination_opt.is_some() {
+            return self.handle(RefreshMergeCoordination, ctx).await;
+        }
         let target_partition_ids = self.partitioned_young_splits.keys().cloned().collect_vec();
         self.send_merge_ops(ctx, &target_partition_ids).await?;
         Ok(())
     }
+
+    async fn finalize(
+        &mut self,
+        _exit_status: &ActorExitStatus,
+        _ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<()> {
+        self.merge_backlog_gauge.sub(self.reported_num_young_splits);
+        self.reported_num_young_splits = 0;
+        if let Some(coordination) = &self.coordination_opt {
+            coordination.leadership.swap(false);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<RefreshMergeCoordination> for MergePlanner {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _: RefreshMergeCoordination,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.refresh_coordination(ctx).await?;
+        if let Some(coordination) = &self.coordination_opt {
+            ctx.schedule_self_msg(
+                coordination.settings.refresh_interval(),
+                RefreshMergeCoordination,
+            )
+            .await;
+        }
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -70,6 +161,10 @@ impl Handler<NewSplits> for MergePlanner {
         message: NewSplits,
         ctx: &ActorContext<Self>,
     ) -> Result<(), ActorExitStatus> {
+        if !self.is_elected() {
+            // The elected merge planner picks up the new splits from the metastore.
+            return Ok(());
+        }
         let mut target_partition_ids = Vec::new();
 
         let partitioned_new_young_splits = message
@@ -90,21 +185,270 @@ impl Handler<NewSplits> for MergePlanner {
                 }
                 is_immature
             })
-            .group_by(|split| split.partition_id);
+            .group_by(partition_key);
 
-        for (partition_id, new_young_splits) in &partitioned_new_young_splits {
+        for (partition_key, new_young_splits) in &partitioned_new_young_splits {
             let young_splits = self
                 .partitioned_young_splits
-                .entry(partition_id)
+                .entry(partition_key)
                 .or_default();
             young_splits.extend(new_young_splits);
-            target_partition_ids.push(partition_id);
+            target_partition_ids.push(partition_key);
         }
         self.send_merge_ops(ctx, &target_partition_ids).await?;
         Ok(())
     }
 }
 
+#[async_trait]
+impl Handler<ApplyDeletes> for MergePlanner {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        message: ApplyDeletes,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        if !self.is_elected() {
+            // The lease was lost since the delete executor handed over these splits: they are
+            // left to the elected merge planner.
+            return Ok(());
+        }
+        for split in message.splits {
+            if !self.merge_policy.is_mature(&split) {
+                // A young split absent from the young splits is part of an ongoing merge: the
+                // delete tasks will be applied to the merged split later on.
+                let young_splits = match self
+                    .partitioned_young_splits
+                    .get_mut(&partition_key(&split))
+                {
+                    Some(young_splits) => young_splits,
+                    None => continue,
+                };
+                let split_pos = match young_splits
+                    .iter()
+                    .position(|young_split| young_split.split_id() == split.split_id())
+                {
+                    Some(split_pos) => split_pos,
+                    None => continue,
+                };
+                young_splits.swap_remove(split_pos);
+            }
+            if let Some(coordination) = &mut self.coordination_opt {
+                coordination
+                    .ongoing_split_ids
+                    .insert(split.split_id().to_string());
+            }
+            let delete_apply_operation =
+                MergeOperation::new_operation(MergeOperationType::DeleteApply, vec![split]);
+            info!(merge_operation=?delete_apply_operation, "Planned delete apply operation.");
+            ctx.send_message(&self.merge_split_downloader_mailbox, delete_apply_operation)
+                .await?;
+        }
+        self.report_merge_backlog();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<ForceMerge> for MergePlanner {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        message: ForceMerge,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        if !self.is_elected() {
+            return Ok(());
+        }
+        let mut splits_to_merge = Vec::with_capacity(message.splits.len());
+        for split in message.splits {
+            if let Some(coordination) = &self.coordination_opt {
+                if coordination.ongoing_split_ids.contains(split.split_id()) {
+                    continue;
+                }
+            }
+            let young_split_pos_opt = self
+                .partitioned_young_splits
+                .get(&partition_key(&split))
+                .and_then(|young_splits| {
+                    young_splits
+                        .iter()
+                        .position(|young_split| young_split.split_id() == split.split_id())
+                });
+            match young_split_pos_opt {
+                Some(young_split_pos) => {
+                    self.partitioned_young_splits
+                        .get_mut(&partition_key(&split))
+                        .expect("The partition of the young split should exist.")
+                        .swap_remove(young_split_pos);
+                }
+                None => {
+                    // A young split of the pipeline absent from the young splits is part of an
+                    // ongoing merge.
+                    if self.coordination_opt.is_none()
+                        && belongs_to_pipeline(&self.pipeline_id, &split)
+                        && !self.merge_policy.is_mature(&split)
+                    {
+                        continue;
+                    }
+                }
+            }
+            splits_to_merge.push(split);
+        }
+        let merge_operations =
+            force_merge_operations(&mut splits_to_merge, message.target_num_splits);
+        // The young splits left out of the merge operations go back to the young splits.
+        for split in splits_to_merge {
+            let is_young_split = !self.merge_policy.is_mature(&split)
+                && (self.coordination_opt.is_some()
+                    || belongs_to_pipeline(&self.pipeline_id, &split));
+            if is_young_split {
+                self.partitioned_young_splits
+                    .entry(partition_key(&split))
+                    .or_default()
+                    .push(split);
+            }
+        }
+        info!(
+            index_id=%self.pipeline_id.index_id,
+            target_num_splits=message.target_num_splits,
+            num_merge_operations=merge_operations.len(),
+            "Planned force merge."
+        );
+        for merge_operation in merge_operations {
+            info!(merge_operation=?merge_operation, "Planned merge operation.");
+            if let Some(coordination) = &mut self.coordination_opt {
+                coordination.ongoing_split_ids.extend(
+                    merge_operation
+                        .splits_as_slice()
+                        .iter()
+                        .map(|split| split.split_id().to_string()),
+                );
+            }
+            ctx.send_message(&self.merge_split_downloader_mailbox, merge_operation)
+                .await?;
+        }
+        self.report_merge_backlog();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<RecompressSplits> for MergePlanner {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        message: RecompressSplits,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        if !self.is_elected() {
+            return Ok(());
+        }
+        if !self
+            .executor_capabilities
+            .supports(MergeOperationType::Recompress)
+        {
+            warn!(
+                index_id=%self.pipeline_id.index_id,
+                "Merge executor does not support recompress operations."
+            );
+            return Ok(());
+        }
+        let mut num_recompress_operations = 0;
+        for split in message.splits {
+            if let Some(coordination) = &self.coordination_opt {
+                if coordination.ongoing_split_ids.contains(split.split_id()) {
+                    continue;
+                }
+            }
+            let young_split_pos_opt = self
+                .partitioned_young_splits
+                .get(&partition_key(&split))
+                .and_then(|young_splits| {
+                    young_splits
+                        .iter()
+                        .position(|young_split| young_split.split_id() == split.split_id())
+                });
+            match young_split_pos_opt {
+                Some(young_split_pos) => {
+                    self.partitioned_young_splits
+                        .get_mut(&partition_key(&split))
+                        .expect("The partition of the young split should exist.")
+                        .swap_remove(young_split_pos);
+                }
+                None => {
+                    // A young split of the pipeline absent from the young splits is part of an
+                    // ongoing merge.
+                    if self.coordination_opt.is_none()
+                        && belongs_to_pipeline(&self.pipeline_id, &split)
+                        && !self.merge_policy.is_mature(&split)
+                    {
+                        continue;
+                    }
+                }
+            }
+            if let Some(coordination) = &mut self.coordination_opt {
+                coordination
+                    .ongoing_split_ids
+                    .insert(split.split_id().to_string());
+            }
+            let recompress_operation =
+                MergeOperation::new_operation(MergeOperationType::Recompress, vec![split]);
+            info!(merge_operation=?recompress_operation, "Planned recompress operation.");
+            ctx.send_message(&self.merge_split_downloader_mailbox, recompress_operation)
+                .await?;
+            num_recompress_operations += 1;
+        }
+        info!(
+            index_id=%self.pipeline_id.index_id,
+            num_recompress_operations=num_recompress_operations,
+            "Planned recompress."
+        );
+        self.report_merge_backlog();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<AbortedMerge> for MergePlanner {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        message: AbortedMerge,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        if let Some(coordination) = &mut self.coordination_opt {
+            for split_id in &message.split_ids {
+                coordination.ongoing_split_ids.remove(split_id);
+            }
+        }
+        if !self.is_elected() {
+            return Ok(());
+        }
+        let mut target_partition_ids = Vec::new();
+        for split in message.published_splits {
+            let is_young_split = !self.merge_policy.is_mature(&split)
+                && (self.coordination_opt.is_some()
+                    || belongs_to_pipeline(&self.pipeline_id, &split));
+            if !is_young_split {
+                continue;
+            }
+            if !target_partition_ids.contains(&partition_key(&split)) {
+                target_partition_ids.push(partition_key(&split));
+            }
+            self.partitioned_young_splits
+                .entry(partition_key(&split))
+                .or_default()
+                .push(split);
+        }
+        self.send_merge_ops(ctx, &target_partition_ids).await
+    }
+}
+
 impl MergePlanner {
     pub fn new(
         pipeline_id: IndexingPipelineId,
@@ -112,45 +456,270 @@ impl MergePlanner {
         merge_policy: Arc<dyn MergePolicy>,
         merge_split_downloader_mailbox: Mailbox<MergeSplitDownloader>,
     ) -> MergePlanner {
-        let mut partitioned_young_splits: HashMap<u64, Vec<SplitMetadata>> = HashMap::new();
+        let mut partitioned_young_splits: HashMap<PartitionKey, Vec<SplitMetadata>> =
+            HashMap::new();
         for split in published_splits {
             if !belongs_to_pipeline(&pipeline_id, &split) || merge_policy.is_mature(&split) {
                 continue;
             }
             partitioned_young_splits
-                .entry(split.partition_id)
+                .entry(partition_key(&split))
                 .or_default()
                 .push(split);
         }
+        let merge_backlog_gauge = INDEXER_METRICS
+            .merge_backlog_num_splits
+            .with_label_values(&[&pipeline_id.index_id, &pipeline_id.source_id]);
         MergePlanner {
             pipeline_id,
             partitioned_young_splits,
             merge_policy,
+            executor_capabilities: MergeExecutor::capabilities(),
             merge_split_downloader_mailbox,
+            merge_backlog_gauge,
+            reported_num_young_splits: 0,
+            coordination_opt: None,
         }
     }
 
+    /// Coordinates the merge planner with the merge planners of the other pipelines of the index:
+    /// the planner holding the merge planner lease of the index plans the merges of the splits of
+    /// all the pipelines, fetched from the metastore, while the others stay idle.
+    pub fn with_coordination(
+        mut self,
+        metastore: Arc<dyn Metastore>,
+        settings: MergeCoordinationSettings,
+        leadership: MergePlannerLeadership,
+    ) -> Self {
+        // The young splits are fetched from the metastore once the lease is acquired.
+        self.partitioned_young_splits.clear();
+        let lease_owner = format!(
+            "{}/{}/{}",
+            self.pipeline_id.node_id, self.pipeline_id.source_id, self.pipeline_id.pipeline_ord
+        );
+        self.coordination_opt = Some(MergeCoordination {
+            metastore,
+            settings,
+            lease_owner,
+            leadership,
+            ongoing_split_ids: HashSet::new(),
+        });
+        self
+    }
+
+    /// Returns whether the planner plans merges: always, unless merges are coordinated and the
+    /// planner does not hold the lease.
+    fn is_elected(&self) -> bool {
+        self.coordination_opt
+            .as_ref()
+            .map(|coordination| coordination.leadership.is_leader())
+            .unwrap_or(true)
+    }
+
+    /// Renews or competes for the merge planner lease. Once elected, the planner rebuilds its young
+    /// splits from the splits published by all the pipelines of the index and plans their merges.
+    async fn refresh_coordination(
+        &mut self,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        let coordination = match &mut self.coordination_opt {
+            Some(coordination) => coordination,
+            None => return Ok(()),
+        };
+        let index_id = &self.pipeline_id.index_id;
+        let is_leader = ctx
+            .protect_future(coordination.metastore.acquire_merge_planner_lease(
+                index_id,
+                &coordination.lease_owner,
+                coordination.settings.lease_duration_secs,
+            ))
+            .await
+            .unwrap_or_else(|error| {
+                // The planner cannot tell whether it is still elected: it steps down.
+                warn!(
+                    index_id=%index_id,
+                    error=?error,
+                    "Failed to acquire the merge planner lease."
+                );
+                false
+            });
+        let was_leader = coordination.leadership.swap(is_leader);
+        if !is_leader {
+            if was_leader {
+                info!(
+                    index_id=%index_id,
+                    lease_owner=%coordination.lease_owner,
+                    "Lost the merge planner lease."
+                );
+            }
+            coordination.ongoing_split_ids.clear();
+            self.partitioned_young_splits.clear();
+            self.report_merge_backlog();
+            return Ok(());
+        }
+        if !was_leader {
+            info!(
+                index_id=%index_id,
+                lease_owner=%coordination.lease_owner,
+                "Acquired the merge planner lease."
+            );
+        }
+        let published_splits: Vec<SplitMetadata> = match ctx
+            .protect_future(coordination.metastore.list_splits(
+                index_id,
+                SplitState::Published,
+                None,
+                None,
+            ))
+            .await
+        {
+            Ok(splits) => splits
+                .into_iter()
+                .map(|split| split.split_metadata)
+                .collect(),
+            Err(error) => {
+                warn!(
+                    index_id=%index_id,
+                    error=?error,
+                    "Failed to list the published splits."
+                );
+                return Ok(());
+            }
+        };
+        // The ongoing splits that are no longer published were replaced.
+        let published_split_ids: HashSet<&str> = published_splits
+            .iter()
+            .map(|split| split.split_id())
+            .collect();
+        coordination
+            .ongoing_split_ids
+            .retain(|split_id| published_split_ids.contains(split_id.as_str()));
+        self.partitioned_young_splits.clear();
+        for split in published_splits {
+            if coordination.ongoing_split_ids.contains(split.split_id())
+                || self.merge_policy.is_mature(&split)
+            {
+                continue;
+            }
+            self.partitioned_young_splits
+                .entry(partition_key(&split))
+                .or_default()
+                .push(split);
+        }
+        let target_partition_ids = self.partitioned_young_splits.keys().cloned().collect_vec();
+        self.send_merge_ops(ctx, &target_partition_ids).await
+    }
+
+    /// Reports the number of young splits to the merge backlog gauge as a delta.
+    fn report_merge_backlog(&mut self) {
+        let num_young_splits: usize = self.partitioned_young_splits.values().map(Vec::len).sum();
+        let num_young_splits = num_young_splits as i64;
+        self.merge_backlog_gauge
+            .add(num_young_splits - self.reported_num_young_splits);
+        self.reported_num_young_splits = num_young_splits;
+    }
+
     async fn send_merge_ops(
         &mut self,
         ctx: &ActorContext<Self>,
-        target_partition_ids: &[u64],
+        target_partition_ids: &[PartitionKey],
     ) -> Result<(), ActorExitStatus> {
-        for partition_id in target_partition_ids {
-            if let Some(young_splits) = self.partitioned_young_splits.get_mut(partition_id) {
-                let merge_operations = self.merge_policy.operations(young_splits);
+        for partition_key in target_partition_ids {
+            if let Some(young_splits) = self.partitioned_young_splits.get_mut(partition_key) {
+                let merge_operations = self
+                    .merge_policy
+                    .plan_operations(young_splits, &self.executor_capabilities);
 
                 for merge_operation in merge_operations {
+                    if !self
+                        .executor_capabilities
+                        .supports(merge_operation.operation_type)
+                    {
+                        // The splits go back to the young splits so that they can be part of
+                        // future operations.
+                        warn!(
+                            merge_operation=?merge_operation,
+                            "Merge policy planned an operation not supported by the merge \
+                             executor."
+                        );
+                        young_splits.extend(merge_operation.splits);
+                        continue;
+                    }
                     info!(merge_operation=?merge_operation, "Planned merge operation.");
+                    if let Some(coordination) = &mut self.coordination_opt {
+                        coordination.ongoing_split_ids.extend(
+                            merge_operation
+                                .splits_as_slice()
+                                .iter()
+                                .map(|split| split.split_id().to_string()),
+                        );
+                    }
                     ctx.send_message(&self.merge_split_downloader_mailbox, merge_operation)
                         .await?;
                 }
             }
         }
+        self.report_merge_backlog();
         Ok(())
     }
 }
 
-fn belongs_to_pipeline(pipeline_id: &IndexingPipelineId, split: &SplitMetadata) -> bool {
+/// Groups the splits of each doc mapping version, sorted by time, into at most
+/// `target_num_splits` merge operations of about the same number of splits. As with
+/// [`MergePolicy::operations`], the splits left out of the merge operations remain in `splits`.
+fn force_merge_operations(
+    splits: &mut Vec<SplitMetadata>,
+    target_num_splits: usize,
+) -> Vec<MergeOperation> {
+    let mut versioned_splits: BTreeMap<u64, Vec<SplitMetadata>> = BTreeMap::new();
+    for split in splits.drain(..) {
+        versioned_splits
+            .entry(split.doc_mapping_version)
+            .or_default()
+            .push(split);
+    }
+    let mut merge_operations = Vec::new();
+    for mut version_splits in versioned_splits.into_values() {
+        merge_operations.extend(force_merge_version_operations(
+            &mut version_splits,
+            target_num_splits,
+        ));
+        splits.append(&mut version_splits);
+    }
+    merge_operations
+}
+
+fn force_merge_version_operations(
+    splits: &mut Vec<SplitMetadata>,
+    target_num_splits: usize,
+) -> Vec<MergeOperation> {
+    let target_num_splits = target_num_splits.max(1);
+    if splits.len() <= target_num_splits {
+        return Vec::new();
+    }
+    splits.sort_by_key(|split| {
+        split
+            .time_range
+            .as_ref()
+            .map(|time_range| (*time_range.start(), *time_range.end()))
+    });
+    let num_splits_per_group = splits.len() / target_num_splits;
+    let num_larger_groups = splits.len() % target_num_splits;
+    let mut splits_it = std::mem::take(splits).into_iter();
+    let mut merge_operations = Vec::new();
+    for group_ord in 0..target_num_splits {
+        let group_len = num_splits_per_group + usize::from(group_ord < num_larger_groups);
+        let mut group: Vec<SplitMetadata> = splits_it.by_ref().take(group_len).collect();
+        if group.len() >= 2 {
+            merge_operations.push(MergeOperation::new_merge_operation(group));
+        } else {
+            splits.append(&mut group);
+        }
+    }
+    merge_operations
+}
+
+pub(crate) fn belongs_to_pipeline(pipeline_id: &IndexingPipelineId, split: &SplitMetadata) -> bool {
     pipeline_id.source_id == split.source_id
         && pipeline_id.node_id == split.node_id
         && pipeline_id.pipeline_ord == split.pipeline_ord
@@ -164,12 +733,13 @@ mod tests {
 
     use proptest::sample::select;
     use quickwit_actors::{create_test_mailbox, ObservationType, Universe};
+    use quickwit_metastore::{MockMetastore, Split};
     use tokio::runtime::Runtime;
 
     use super::*;
     use crate::actors::combine_partition_ids;
-    use crate::merge_policy::MergeOperation;
-    use crate::{new_split_id, StableMultitenantWithTimestampMergePolicy};
+    use crate::merge_policy::StableMultitenantWithTimestampMergePolicy;
+    use crate::new_split_id;
 
     fn merge_time_range(splits: &[SplitMetadata]) -> Option<RangeInclusive<i64>> {
         let time_range_start = splits
@@ -388,4 +958,392 @@ mod tests {
         .await?;
         Ok(())
     }
+
+    #[derive(Debug)]
+    struct DemuxEverythingMergePolicy;
+
+    impl MergePolicy for DemuxEverythingMergePolicy {
+        fn operations(&self, _splits: &mut Vec<SplitMetadata>) -> Vec<MergeOperation> {
+            Vec::new()
+        }
+
+        fn plan_operations(
+            &self,
+            splits: &mut Vec<SplitMetadata>,
+            _executor_capabilities: &MergeExecutorCapabilities,
+        ) -> Vec<MergeOperation> {
+            if splits.len() < 2 {
+                return Vec::new();
+            }
+            vec![MergeOperation::new_operation(
+                MergeOperationType::Demux,
+                std::mem::take(splits),
+            )]
+        }
+
+        fn is_mature(&self, _split: &SplitMetadata) -> bool {
+            false
+        }
+    }
+
+    #[tokio::test]
+    async fn test_merge_planner_skips_operations_unsupported_by_executor() -> anyhow::Result<()> {
+        let (merge_op_mailbox, merge_op_inbox) = create_test_mailbox::<MergeSplitDownloader>();
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let merge_planner = MergePlanner::new(
+            pipeline_id,
+            Vec::new(),
+            Arc::new(DemuxEverythingMergePolicy),
+            merge_op_mailbox,
+        );
+        let universe = Universe::new();
+        let (merge_planner_mailbox, merge_planner_handler) =
+            universe.spawn_actor(merge_planner).spawn();
+        merge_planner_mailbox
+            .send_message(NewSplits {
+                new_splits: vec![
+                    mock_split_meta_from_num_docs(0..=10, 10),
+                    mock_split_meta_from_num_docs(0..=10, 10),
+                ],
+            })
+            .await?;
+        let obs = merge_planner_handler.process_pending_and_observe().await;
+        assert_eq!(obs.obs_type, ObservationType::Alive);
+        assert!(merge_op_inbox.drain_for_test().is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_merge_planner_apply_deletes_skips_splits_being_merged() -> anyhow::Result<()> {
+        let (merge_op_mailbox, merge_op_inbox) = create_test_mailbox::<MergeSplitDownloader>();
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let young_split = mock_split_meta_from_num_docs(0..=10, 10);
+        let merge_planner = MergePlanner::new(
+            pipeline_id,
+            Vec::new(),
+            Arc::new(DemuxEverythingMergePolicy),
+            merge_op_mailbox,
+        );
+        let universe = Universe::new();
+        let (merge_planner_mailbox, merge_planner_handler) =
+            universe.spawn_actor(merge_planner).spawn();
+        merge_planner_mailbox
+            .send_message(NewSplits {
+                new_splits: vec![young_split.clone()],
+            })
+            .await?;
+        merge_planner_handler.process_pending_and_observe().await;
+        assert!(merge_op_inbox.drain_for_test().is_empty());
+        // The second split is unknown to the merge planner, as if it were being merged.
+        merge_planner_mailbox
+            .send_message(ApplyDeletes {
+                splits: vec![
+                    young_split.clone(),
+                    mock_split_meta_from_num_docs(0..=10, 10),
+                ],
+            })
+            .await?;
+        merge_planner_handler.process_pending_and_observe().await;
+        let merge_ops: Vec<MergeOperation> = merge_op_inbox
+            .drain_for_test()
+            .into_iter()
+            .flat_map(|op| op.downcast::<MergeOperation>())
+            .map(|op| *op)
+            .collect();
+        assert_eq!(merge_ops.len(), 1);
+        assert_eq!(merge_ops[0].operation_type, MergeOperationType::DeleteApply);
+        assert_eq!(
+            merge_ops[0].splits_as_slice()[0].split_id(),
+            young_split.split_id()
+        );
+
+        // The split is now in flight.
+        merge_planner_mailbox
+            .send_message(ApplyDeletes {
+                splits: vec![young_split],
+            })
+            .await?;
+        merge_planner_handler.process_pending_and_observe().await;
+        assert!(merge_op_inbox.drain_for_test().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_force_merge_operations() {
+        let mut splits: Vec<SplitMetadata> = [40, 0, 20, 10, 30]
+            .into_iter()
+            .map(|start| mock_split_meta_from_num_docs(start..=start + 5, 10))
+            .collect();
+        let merge_ops = force_merge_operations(&mut splits, 2);
+        assert!(splits.is_empty());
+        let merged_time_ranges: Vec<Vec<i64>> = merge_ops
+            .iter()
+            .map(|merge_op| {
+                merge_op
+                    .splits_as_slice()
+                    .iter()
+                    .map(|split| *split.time_range.as_ref().unwrap().start())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(merged_time_ranges, vec![vec![0, 10, 20], vec![30, 40]]);
+
+        // A group of a single split is left out.
+        let mut splits: Vec<SplitMetadata> = (0..3)
+            .map(|_| mock_split_meta_from_num_docs(0..=10, 10))
+            .collect();
+        let merge_ops = force_merge_operations(&mut splits, 2);
+        assert_eq!(merge_ops.len(), 1);
+        assert_eq!(merge_ops[0].splits_as_slice().len(), 2);
+        assert_eq!(splits.len(), 1);
+
+        let mut splits = vec![mock_split_meta_from_num_docs(0..=10, 10)];
+        assert!(force_merge_operations(&mut splits, 1).is_empty());
+        assert_eq!(splits.len(), 1);
+
+        // Splits of different doc mapping versions are never merged together.
+        let mut splits: Vec<SplitMetadata> = [0, 0, 1]
+            .into_iter()
+            .map(|doc_mapping_version| SplitMetadata {
+                doc_mapping_version,
+                ..mock_split_meta_from_num_docs(0..=10, 10)
+            })
+            .collect();
+        let merge_ops = force_merge_operations(&mut splits, 1);
+        assert_eq!(merge_ops.len(), 1);
+        assert!(merge_ops[0]
+            .splits_as_slice()
+            .iter()
+            .all(|split| split.doc_mapping_version == 0));
+        assert_eq!(splits.len(), 1);
+        assert_eq!(splits[0].doc_mapping_version, 1);
+    }
+
+    #[tokio::test]
+    async fn test_merge_planner_force_merge() -> anyhow::Result<()> {
+        let (merge_op_mailbox, merge_op_inbox) = create_test_mailbox::<MergeSplitDownloader>();
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let mut young_split = mock_split_meta_from_num_docs(0..=10, 10);
+        young_split.source_id = pipeline_id.source_id.clone();
+        young_split.node_id = pipeline_id.node_id.clone();
+        let merge_planner = MergePlanner::new(
+            pipeline_id,
+            vec![young_split.clone()],
+            Arc::new(DemuxEverythingMergePolicy),
+            merge_op_mailbox,
+        );
+        let universe = Universe::new();
+        let (merge_planner_mailbox, merge_planner_handler) =
+            universe.spawn_actor(merge_planner).spawn();
+        let mut splits: Vec<SplitMetadata> = (0..4)
+            .map(|_| mock_split_meta_from_num_docs(0..=10, 10))
+            .collect();
+        splits.push(young_split);
+        merge_planner_mailbox
+            .send_message(ForceMerge {
+                splits,
+                target_num_splits: 2,
+            })
+            .await?;
+        merge_planner_handler.process_pending_and_observe().await;
+        let merge_ops: Vec<MergeOperation> = merge_op_inbox
+            .drain_for_test()
+            .into_iter()
+            .flat_map(|op| op.downcast::<MergeOperation>())
+            .map(|op| *op)
+            .collect();
+        assert_eq!(merge_ops.len(), 2);
+        assert!(merge_ops
+            .iter()
+            .all(|merge_op| merge_op.operation_type == MergeOperationType::Merge));
+        let num_merged_splits: usize = merge_ops
+            .iter()
+            .map(|merge_op| merge_op.splits_as_slice().len())
+            .sum();
+        assert_eq!(num_merged_splits, 5);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_merge_planner_recompress_splits() -> anyhow::Result<()> {
+        let (merge_op_mailbox, merge_op_inbox) = create_test_mailbox::<MergeSplitDownloader>();
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let mut young_split = mock_split_meta_from_num_docs(0..=10, 10);
+        young_split.source_id = pipeline_id.source_id.clone();
+        young_split.node_id = pipeline_id.node_id.clone();
+        let merge_planner = MergePlanner::new(
+            pipeline_id,
+            vec![young_split.clone()],
+            Arc::new(DemuxEverythingMergePolicy),
+            merge_op_mailbox,
+        );
+        let universe = Universe::new();
+        let (merge_planner_mailbox, merge_planner_handler) =
+            universe.spawn_actor(merge_planner).spawn();
+        let mut splits: Vec<SplitMetadata> = (0..2)
+            .map(|_| mock_split_meta_from_num_docs(0..=10, 10))
+            .collect();
+        splits.push(young_split);
+        merge_planner_mailbox
+            .send_message(RecompressSplits { splits })
+            .await?;
+        merge_planner_handler.process_pending_and_observe().await;
+        let merge_ops: Vec<MergeOperation> = merge_op_inbox
+            .drain_for_test()
+            .into_iter()
+            .flat_map(|op| op.downcast::<MergeOperation>())
+            .map(|op| *op)
+            .collect();
+        assert_eq!(merge_ops.len(), 3);
+        assert!(merge_ops.iter().all(|merge_op| {
+            merge_op.operation_type == MergeOperationType::Recompress
+                && merge_op.splits_as_slice().len() == 1
+        }));
+        Ok(())
+    }
+
+    #[derive(Debug)]
+    struct MergeEverythingMergePolicy;
+
+    impl MergePolicy for MergeEverythingMergePolicy {
+        fn operations(&self, splits: &mut Vec<SplitMetadata>) -> Vec<MergeOperation> {
+            if splits.len() < 2 {
+                return Vec::new();
+            }
+            vec![MergeOperation::new_merge_operation(std::mem::take(splits))]
+        }
+
+        fn is_mature(&self, _split: &SplitMetadata) -> bool {
+            false
+        }
+    }
+
+    fn coordinated_merge_planner_for_test(
+        metastore: MockMetastore,
+        leadership: MergePlannerLeadership,
+        merge_op_mailbox: Mailbox<MergeSplitDownloader>,
+    ) -> MergePlanner {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        MergePlanner::new(
+            pipeline_id,
+            Vec::new(),
+            Arc::new(MergeEverythingMergePolicy),
+            merge_op_mailbox,
+        )
+        .with_coordination(
+            Arc::new(metastore),
+            MergeCoordinationSettings::default(),
+            leadership,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_merge_planner_coordinated_merges_splits_of_all_pipelines() -> anyhow::Result<()> {
+        let published_splits: Vec<Split> = (0..2)
+            .map(|pipeline_ord| Split {
+                split_state: SplitState::Published,
+                update_timestamp: 0,
+                split_metadata: SplitMetadata {
+                    split_id: format!("split-{pipeline_ord}"),
+                    source_id: "test-source".to_string(),
+                    node_id: "test-node".to_string(),
+                    pipeline_ord,
+                    ..Default::default()
+                },
+            })
+            .collect();
+        let mut metastore = MockMetastore::default();
+        metastore
+            .expect_acquire_merge_planner_lease()
+            .withf(|index_id, owner, lease_duration_secs| {
+                index_id == "test-index"
+                    && owner == "test-node/test-source/0"
+                    && *lease_duration_secs == 60
+            })
+            .returning(|_, _, _| Ok(true));
+        metastore
+            .expect_list_splits()
+            .returning(move |_, split_state, _, _| {
+                assert_eq!(split_state, SplitState::Published);
+                Ok(published_splits.clone())
+            });
+        let (merge_op_mailbox, merge_op_inbox) = create_test_mailbox::<MergeSplitDownloader>();
+        let leadership = MergePlannerLeadership::default();
+        let merge_planner =
+            coordinated_merge_planner_for_test(metastore, leadership.clone(), merge_op_mailbox);
+        let universe = Universe::new();
+        let (_merge_planner_mailbox, merge_planner_handler) =
+            universe.spawn_actor(merge_planner).spawn();
+        merge_planner_handler.process_pending_and_observe().await;
+        assert!(leadership.is_leader());
+
+        let merge_ops: Vec<MergeOperation> = merge_op_inbox
+            .drain_for_test()
+            .into_iter()
+            .flat_map(|op| op.downcast::<MergeOperation>())
+            .map(|op| *op)
+            .collect();
+        assert_eq!(merge_ops.len(), 1);
+        let merged_split_ids: Vec<&str> = merge_ops[0]
+            .splits_as_slice()
+            .iter()
+            .map(|split| split.split_id())
+            .sorted()
+            .collect();
+        assert_eq!(merged_split_ids, ["split-0", "split-1"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_merge_planner_coordinated_stays_idle_without_lease() -> anyhow::Result<()> {
+        let mut metastore = MockMetastore::default();
+        metastore
+            .expect_acquire_merge_planner_lease()
+            .returning(|_, _, _| Ok(false));
+        metastore.expect_list_splits().never();
+        let (merge_op_mailbox, merge_op_inbox) = create_test_mailbox::<MergeSplitDownloader>();
+        let leadership = MergePlannerLeadership::default();
+        let merge_planner =
+            coordinated_merge_planner_for_test(metastore, leadership.clone(), merge_op_mailbox);
+        let universe = Universe::new();
+        let (merge_planner_mailbox, merge_planner_handler) =
+            universe.spawn_actor(merge_planner).spawn();
+        merge_planner_mailbox
+            .send_message(NewSplits {
+                new_splits: vec![
+                    mock_split_meta_from_num_docs(0..=10, 10),
+                    mock_split_meta_from_num_docs(0..=10, 10),
+                ],
+            })
+            .await?;
+        merge_planner_handler.process_pending_and_observe().await;
+        assert!(!leadership.is_leader());
+        assert!(merge_op_inbox.drain_for_test().is_empty());
+        Ok(())
+    }
 }
@@ -17,23 +17,46 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashMap;
+use std::ops::Range;
 use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
-use quickwit_metastore::SplitMetadata;
+use quickwit_metastore::{Metastore, SplitMetadata, SplitState};
 use tantivy::Directory;
-use tracing::{info, info_span, warn, Span};
+use tracing::{error, info, info_span, warn, Span};
 
-use crate::actors::MergeExecutor;
+use crate::actors::{MergeExecutor, MergePlanner};
 use crate::merge_policy::MergeOperation;
-use crate::models::{MergeScratch, ScratchDirectory};
+use crate::merge_scheduler::{forecast_merge_scratch_num_bytes, MergeScheduler, ScratchSpaceCheck};
+use crate::metrics::INDEXER_METRICS;
+use crate::models::{
+    AbortedMerge, MergeScratch, PipelineWarningKind, PipelineWarningSink, ScratchDirectory,
+};
 use crate::split_store::IndexingSplitStore;
 
+/// Delay before retrying a merge deferred until the ongoing merges free up scratch space.
+const DEFERRED_MERGE_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
 pub struct MergeSplitDownloader {
+    pub index_id: String,
     pub scratch_directory: ScratchDirectory,
     pub storage: IndexingSplitStore,
+    /// Metastore checked for the splits of a merge operation before they are downloaded.
+    pub metastore: Arc<dyn Metastore>,
     pub merge_executor_mailbox: Mailbox<MergeExecutor>,
+    /// Mailbox of the merge planner, notified of the merge operations aborted because some of
+    /// their splits are no longer published.
+    pub merge_planner_mailbox: Mailbox<MergePlanner>,
+    /// Scheduler shared by the merge pipelines of the node, which bounds the merges downloaded
+    /// or executed concurrently.
+    pub merge_scheduler: Arc<MergeScheduler>,
+    /// Sink of the warnings of the pipeline, notified of the merges rejected for lack of scratch
+    /// space.
+    pub warning_sink: PipelineWarningSink,
 }
 
 impl Actor for MergeSplitDownloader {
@@ -71,6 +94,85 @@ impl Handler<MergeOperation> for MergeSplitDownloader {
         merge_operation: MergeOperation,
         ctx: &ActorContext<Self>,
     ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        let mut merge_permit = {
+            let _protect_guard = ctx.protect_zone();
+            self.merge_scheduler.acquire_permit(&merge_operation).await
+        };
+        if let Some(aborted_merge) = self.check_splits_published(&merge_operation, ctx).await {
+            warn!(
+                index_id=%self.index_id,
+                split_ids=?aborted_merge.split_ids,
+                "Some of the splits to merge are no longer published. Aborting merge."
+            );
+            ctx.send_message(&self.merge_planner_mailbox, aborted_merge)
+                .await?;
+            return Ok(());
+        }
+        let scratch_space_check_res = self.merge_scheduler.reserve_scratch_space(
+            &mut merge_permit,
+            &merge_operation,
+            self.scratch_directory.path(),
+        );
+        match scratch_space_check_res {
+            Ok(ScratchSpaceCheck::Reserved) => {}
+            Ok(ScratchSpaceCheck::Deferred {
+                available_num_bytes,
+                reserved_num_bytes,
+            }) => {
+                info!(
+                    index_id=%self.index_id,
+                    available_num_bytes=available_num_bytes,
+                    reserved_num_bytes=reserved_num_bytes,
+                    "Not enough scratch space for the merge next to the ongoing merges. Deferring \
+                     merge."
+                );
+                INDEXER_METRICS.merges_deferred_total.inc();
+                // The permit is released so that the ongoing merges are not held back.
+                drop(merge_permit);
+                ctx.schedule_self_msg(DEFERRED_MERGE_RETRY_INTERVAL, merge_operation)
+                    .await;
+                return Ok(());
+            }
+            Ok(ScratchSpaceCheck::Insufficient {
+                available_num_bytes,
+            }) => {
+                let split_ids: Vec<String> = merge_operation
+                    .splits_as_slice()
+                    .iter()
+                    .map(|split| split.split_id().to_string())
+                    .collect();
+                let message = format!(
+                    "Rejected merge of splits {:?}: it needs {} bytes of scratch space, but only \
+                     {} bytes are available in `{}`.",
+                    split_ids,
+                    forecast_merge_scratch_num_bytes(&merge_operation),
+                    available_num_bytes,
+                    self.scratch_directory.path().display()
+                );
+                error!(index_id=%self.index_id, "{}", message);
+                INDEXER_METRICS.merges_rejected_total.inc();
+                self.warning_sink.warn(
+                    PipelineWarningKind::InsufficientMergeScratchSpace,
+                    &self.name(),
+                    message,
+                );
+                // The splits are left unmerged rather than planned again into the same merge.
+                let aborted_merge = AbortedMerge {
+                    split_ids,
+                    published_splits: Vec::new(),
+                };
+                ctx.send_message(&self.merge_planner_mailbox, aborted_merge)
+                    .await?;
+                return Ok(());
+            }
+            Err(error) => {
+                warn!(
+                    index_id=%self.index_id,
+                    error=?error,
+                    "Failed to check the scratch space available for the merge."
+                );
+            }
+        }
         let merge_scratch_directory = self
             .scratch_directory
             .named_temp_child("merge-")
@@ -91,6 +193,7 @@ impl Handler<MergeOperation> for MergeSplitDownloader {
             merge_scratch_directory,
             downloaded_splits_directory,
             tantivy_dirs,
+            merge_permit,
         };
         ctx.send_message(&self.merge_executor_mailbox, msg).await?;
         Ok(())
@@ -98,6 +201,67 @@ impl Handler<MergeOperation> for MergeSplitDownloader {
 }
 
 impl MergeSplitDownloader {
+    /// Checks that the splits of the merge operation are still published, as they may have been
+    /// deleted by the retention policy or replaced while the operation was waiting for its turn.
+    /// Returns the aborted merge to report to the merge planner if they are not.
+    ///
+    /// Failing to list the splits does not abort the merge: it then fails on the download of the
+    /// missing splits, if any.
+    async fn check_splits_published(
+        &self,
+        merge_operation: &MergeOperation,
+        ctx: &ActorContext<Self>,
+    ) -> Option<AbortedMerge> {
+        let splits = merge_operation.splits_as_slice();
+        // The splits without time range are always listed.
+        let time_range_opt: Option<Range<i64>> = splits
+            .iter()
+            .flat_map(|split| split.time_range.clone())
+            .map(|time_range| *time_range.start()..time_range.end().saturating_add(1))
+            .reduce(|left, right| left.start.min(right.start)..left.end.max(right.end));
+        let published_splits = match ctx
+            .protect_future(self.metastore.list_splits(
+                &self.index_id,
+                SplitState::Published,
+                time_range_opt,
+                None,
+            ))
+            .await
+        {
+            Ok(published_splits) => published_splits,
+            Err(error) => {
+                warn!(
+                    index_id=%self.index_id,
+                    error=?error,
+                    "Failed to check that the splits to merge are published."
+                );
+                return None;
+            }
+        };
+        let mut published_splits: HashMap<String, SplitMetadata> = published_splits
+            .into_iter()
+            .map(|split| (split.split_metadata.split_id.clone(), split.split_metadata))
+            .collect();
+        if splits
+            .iter()
+            .all(|split| published_splits.contains_key(split.split_id()))
+        {
+            return None;
+        }
+        let split_ids = splits
+            .iter()
+            .map(|split| split.split_id().to_string())
+            .collect();
+        let published_splits = splits
+            .iter()
+            .flat_map(|split| published_splits.remove(split.split_id()))
+            .collect();
+        Some(AbortedMerge {
+            split_ids,
+            published_splits,
+        })
+    }
+
     async fn download_splits(
         &self,
         splits: &[SplitMetadata],
@@ -129,15 +293,35 @@ impl MergeSplitDownloader {
 #[cfg(test)]
 mod tests {
     use std::iter;
-    use std::sync::Arc;
 
-    use quickwit_actors::{create_test_mailbox, Universe};
+    use quickwit_actors::{create_test_mailbox, ObservationType, Universe};
     use quickwit_common::split_file;
+    use quickwit_metastore::{MockMetastore, Split};
     use quickwit_storage::{PutPayload, RamStorageBuilder, SplitPayloadBuilder};
 
     use super::*;
     use crate::new_split_id;
 
+    fn mock_metastore_with_published_splits(splits: Vec<SplitMetadata>) -> MockMetastore {
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore.expect_list_splits().times(1).returning(
+            move |index_id, split_state, _time_range, tags| {
+                assert_eq!(index_id, "test-index");
+                assert_eq!(split_state, SplitState::Published);
+                assert!(tags.is_none());
+                Ok(splits
+                    .iter()
+                    .map(|split_metadata| Split {
+                        split_metadata: split_metadata.clone(),
+                        split_state: SplitState::Published,
+                        update_timestamp: 0,
+                    })
+                    .collect())
+            },
+        );
+        mock_metastore
+    }
+
     #[tokio::test]
     async fn test_merge_split_downloader() -> anyhow::Result<()> {
         let scratch_directory = ScratchDirectory::for_test()?;
@@ -164,11 +348,18 @@ mod tests {
         };
 
         let universe = Universe::new();
+        let metastore = mock_metastore_with_published_splits(splits_to_merge.clone());
         let (merge_executor_mailbox, merge_executor_inbox) = create_test_mailbox();
+        let (merge_planner_mailbox, _merge_planner_inbox) = create_test_mailbox();
         let merge_split_downloader = MergeSplitDownloader {
+            index_id: "test-index".to_string(),
             scratch_directory,
             storage,
+            metastore: Arc::new(metastore),
             merge_executor_mailbox,
+            merge_planner_mailbox,
+            merge_scheduler: Arc::new(MergeScheduler::default()),
+            warning_sink: PipelineWarningSink::default(),
         };
         let (merge_split_downloader_mailbox, merge_split_downloader_handler) =
             universe.spawn_actor(merge_split_downloader).spawn();
@@ -198,4 +389,61 @@ mod tests {
         }
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_merge_split_downloader_aborts_merge_of_unpublished_splits() -> anyhow::Result<()>
+    {
+        let scratch_directory = ScratchDirectory::for_test()?;
+        let splits_to_merge: Vec<SplitMetadata> = (0..3)
+            .map(|i| SplitMetadata {
+                split_id: format!("split-{i}"),
+                time_range: Some(i * 10..=i * 10 + 9),
+                ..Default::default()
+            })
+            .collect();
+        // The last split was deleted by the retention policy.
+        let metastore = mock_metastore_with_published_splits(splits_to_merge[..2].to_vec());
+        let storage = IndexingSplitStore::create_with_no_local_store(Arc::new(
+            RamStorageBuilder::default().build(),
+        ));
+
+        let universe = Universe::new();
+        let (merge_executor_mailbox, merge_executor_inbox) = create_test_mailbox();
+        let (merge_planner_mailbox, merge_planner_inbox) = create_test_mailbox();
+        let merge_split_downloader = MergeSplitDownloader {
+            index_id: "test-index".to_string(),
+            scratch_directory,
+            storage,
+            metastore: Arc::new(metastore),
+            merge_executor_mailbox,
+            merge_planner_mailbox,
+            merge_scheduler: Arc::new(MergeScheduler::default()),
+            warning_sink: PipelineWarningSink::default(),
+        };
+        let (merge_split_downloader_mailbox, merge_split_downloader_handler) =
+            universe.spawn_actor(merge_split_downloader).spawn();
+        let merge_operation = MergeOperation::new_merge_operation(splits_to_merge);
+        merge_split_downloader_mailbox
+            .send_message(merge_operation)
+            .await?;
+        let observation = merge_split_downloader_handler
+            .process_pending_and_observe()
+            .await;
+        assert_eq!(observation.obs_type, ObservationType::Alive);
+        assert!(merge_executor_inbox.drain_for_test().is_empty());
+
+        let aborted_merges = merge_planner_inbox.drain_for_test_typed::<AbortedMerge>();
+        assert_eq!(aborted_merges.len(), 1);
+        assert_eq!(
+            aborted_merges[0].split_ids,
+            vec!["split-0", "split-1", "split-2"]
+        );
+        let published_split_ids: Vec<&str> = aborted_merges[0]
+            .published_splits
+            .iter()
+            .map(|split| split.split_id())
+            .collect();
+        assert_eq!(published_split_ids, vec!["split-0", "split-1"]);
+        Ok(())
+    }
 }
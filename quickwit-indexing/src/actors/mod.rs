@@ -19,6 +19,7 @@
 
 mod indexing_pipeline;
 
+mod delete_executor;
 mod garbage_collector;
 mod indexer;
 mod indexing_service;
@@ -26,26 +27,38 @@ mod ingest_api_garbage_collector;
 mod packager;
 mod publisher;
 mod sequencer;
+mod sharding;
 mod uploader;
 
-pub use indexing_pipeline::{IndexingPipeline, IndexingPipelineHandle, IndexingPipelineParams};
+use anyhow::Context;
+pub use indexing_pipeline::{
+    DrainPipeline, IndexingPipeline, IndexingPipelineHandle, IndexingPipelineParams,
+    MergePipelineHandle, ObservePipelineTopology,
+};
 pub use indexing_service::{
     IndexingService, IndexingServiceError, IndexingServiceState, INDEXING_DIR_NAME,
 };
+use quickwit_doc_mapper::DocMapper;
 use tantivy::schema::{Field, FieldType};
 mod merge_executor;
 mod merge_planner;
 mod merge_split_downloader;
 
+pub use self::delete_executor::{DeleteExecutor, DeleteExecutorCounters};
 pub use self::garbage_collector::{GarbageCollector, GarbageCollectorCounters};
-pub use self::indexer::{Indexer, IndexerCounters};
+pub use self::indexer::{Indexer, IndexerCounters, WorkbenchPartitionPreview, WorkbenchPreview};
 pub use self::ingest_api_garbage_collector::{
     IngestApiGarbageCollector, IngestApiGarbageCollectorCounters,
 };
-pub use self::merge_executor::{combine_partition_ids, MergeExecutor};
-pub use self::merge_planner::MergePlanner;
+pub use self::merge_executor::{
+    combine_partition_ids, MergeExecutor, MergeExecutorCounters, MergeProgress,
+};
+pub use self::merge_planner::{MergePlanner, MergePlannerLeadership};
 pub use self::merge_split_downloader::MergeSplitDownloader;
 pub use self::packager::Packager;
+pub(crate) use self::packager::{
+    extract_split_field_stats, extract_split_tags, RepeatedSplitVerificationFailure,
+};
 pub use self::publisher::{Publisher, PublisherCounters};
 pub use self::uploader::{Uploader, UploaderCounters};
 
@@ -59,3 +72,22 @@ pub struct NamedField {
     /// Tantivy schema field type.
     pub field_type: FieldType,
 }
+
+/// Returns the tag fields of the doc mapper.
+pub(crate) fn tag_fields(doc_mapper: &dyn DocMapper) -> anyhow::Result<Vec<NamedField>> {
+    let index_schema = doc_mapper.schema();
+    doc_mapper
+        .tag_field_names()
+        .iter()
+        .map(|field_name| {
+            index_schema
+                .get_field(field_name)
+                .context(format!("Field `{}` must exist in the schema.", field_name))
+                .map(|field| NamedField {
+                    name: field_name.clone(),
+                    field,
+                    field_type: index_schema.get_field_entry(field).field_type().clone(),
+                })
+        })
+        .collect()
+}
@@ -17,8 +17,9 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::io;
+use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
@@ -28,12 +29,22 @@ use fail::fail_point;
 use itertools::Itertools;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
 use quickwit_common::runtimes::RuntimeType;
-use quickwit_directories::write_hotcache;
+use quickwit_config::CalendarTagGranularity;
+use quickwit_directories::{write_hotcache, HotDirectory};
 use quickwit_doc_mapper::tag_pruning::append_to_tag_set;
-use tantivy::schema::FieldType;
-use tantivy::{InvertedIndexReader, ReloadPolicy, SegmentId, SegmentMeta};
+use quickwit_metastore::{FieldValueRange, SplitFieldStats};
+use rand::Rng;
+use tantivy::directory::{MmapDirectory, OwnedBytes};
+use tantivy::fastfield::FastFieldReader;
+use tantivy::schema::{Cardinality, Field, FieldType, IndexRecordOption};
+use tantivy::{
+    DocAddress, DocSet, Index, InvertedIndexReader, ReloadPolicy, Searcher, SegmentId, SegmentMeta,
+    SegmentReader, TERMINATED,
+};
+use time::OffsetDateTime;
 use tokio::runtime::Handle;
-use tracing::{debug, info, info_span, warn, Span};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, info_span, warn, Span};
 
 /// Maximum distinct values allowed for a tag field within a split.
 const MAX_VALUES_PER_TAG_FIELD: usize = if cfg!(any(test, feature = "testsuite")) {
@@ -42,10 +53,42 @@ const MAX_VALUES_PER_TAG_FIELD: usize = if cfg!(any(test, feature = "testsuite")
     1000
 };
 
+/// Maximum number of documents fetched from the doc store of each segment when verifying a split.
+const MAX_VERIFIED_DOCS_PER_SEGMENT: u32 = 16;
+
+/// Maximum number of terms looked up in the inverted index of each indexed field when verifying a
+/// split.
+const MAX_VERIFIED_TERMS_PER_FIELD: usize = 16;
+
+/// Name of the file holding the hotcache of a quarantined split.
+const QUARANTINED_HOTCACHE_FILE_NAME: &str = "hotcache";
+
+/// Name of the file holding the input fingerprint of a quarantined split.
+const QUARANTINED_INPUT_FILE_NAME: &str = "input";
+
+/// Maximum size of the quarantine directory. The oldest quarantined splits are removed to make
+/// room for the new ones.
+const MAX_QUARANTINE_DIRECTORY_NUM_BYTES: u64 = 10_000_000_000; // 10GB
+
+/// Number of times the splits built from the same input may fail verification before the
+/// pipeline stops replaying it.
+const MAX_SPLIT_VERIFICATION_FAILURES: usize = 3;
+
+/// Error returned when the splits built from the same input fail verification over and over
+/// again. The indexing pipeline gives up instead of restarting.
+#[derive(Debug, thiserror::Error)]
+#[error("Splits built from the same input failed verification {num_failures} times.")]
+pub struct RepeatedSplitVerificationFailure {
+    pub num_failures: usize,
+}
+
 use super::NamedField;
+use crate::actors::sharding::ShardBatchCombiner;
 use crate::actors::Uploader;
+use crate::metrics::INDEXER_METRICS;
 use crate::models::{
-    IndexedSplit, IndexedSplitBatch, PackagedSplit, PackagedSplitBatch, ScratchDirectory,
+    IndexedSplit, IndexedSplitBatch, PackagedSplit, PackagedSplitBatch, PackagedSplitBatchStream,
+    PackagedSplitEvent, ScratchDirectory, ShardSplitBatch,
 };
 
 /// The role of the packager is to get an index writer and
@@ -57,6 +100,10 @@ use crate::models::{
 /// - creating a bundle file
 /// - computing the hotcache
 /// - appending it to the split file.
+/// - optionally, re-opening the split to verify it.
+///
+/// When upload pipelining is enabled, the files of a split are streamed to the uploader as soon
+/// as its segments are merged, so that their upload overlaps the end of the packaging.
 ///
 /// The split format is described in `internals/split-format.md`
 pub struct Packager {
@@ -64,21 +111,66 @@ pub struct Packager {
     uploader_mailbox: Mailbox<Uploader>,
     /// List of tag fields ([`Vec<NamedField>`]) defined in the index config.
     tag_fields: Vec<NamedField>,
+    /// When set, packaged splits are verified before being sent to the uploader. The splits
+    /// failing verification are copied into this directory and never published.
+    quarantine_directory_opt: Option<PathBuf>,
+    /// Set when the packager receives the split batches of the shards of a sharded pipeline.
+    shard_batch_combiner_opt: Option<ShardBatchCombiner>,
+    /// Number of documents sampled at random in each split and exported by the uploader. No
+    /// sample is taken when zero.
+    num_sample_docs: usize,
+    /// Calendar buckets overlapping the time range of the splits recorded as split tags.
+    calendar_tag_granularities: Vec<CalendarTagGranularity>,
+    /// When set, the splits are streamed to the uploader as a [`PackagedSplitBatchStream`]
+    /// rather than sent as a [`PackagedSplitBatch`] once packaged.
+    upload_pipelining: bool,
 }
 
 impl Packager {
     pub fn new(
         actor_name: &'static str,
         tag_fields: Vec<NamedField>,
+        quarantine_directory_opt: Option<PathBuf>,
         uploader_mailbox: Mailbox<Uploader>,
     ) -> Packager {
         Packager {
             actor_name,
             uploader_mailbox,
             tag_fields,
+            quarantine_directory_opt,
+            shard_batch_combiner_opt: None,
+            num_sample_docs: 0,
+            calendar_tag_granularities: Vec::new(),
+            upload_pipelining: false,
         }
     }
 
+    pub fn with_upload_pipelining(mut self) -> Packager {
+        self.upload_pipelining = true;
+        self
+    }
+
+    pub fn with_calendar_tags(
+        mut self,
+        calendar_tag_granularities: Vec<CalendarTagGranularity>,
+    ) -> Packager {
+        self.calendar_tag_granularities = calendar_tag_granularities;
+        self
+    }
+
+    pub fn with_num_sample_docs(mut self, num_sample_docs: usize) -> Packager {
+        self.num_sample_docs = num_sample_docs;
+        self
+    }
+
+    pub(crate) fn with_shard_batch_combiner(
+        mut self,
+        shard_batch_combiner: ShardBatchCombiner,
+    ) -> Packager {
+        self.shard_batch_combiner_opt = Some(shard_batch_combiner);
+        self
+    }
+
     pub async fn process_indexed_split(
         &self,
         mut split: IndexedSplit,
@@ -86,10 +178,86 @@ impl Packager {
     ) -> anyhow::Result<PackagedSplit> {
         commit_split(&mut split, ctx)?;
         let segment_metas = merge_segments_if_required(&mut split, ctx).await?;
-        let packaged_split =
-            create_packaged_split(&segment_metas[..], split, &self.tag_fields, ctx)?;
+        self.package_split(&segment_metas[..], split, ctx)
+    }
+
+    fn package_split(
+        &self,
+        segment_metas: &[SegmentMeta],
+        split: IndexedSplit,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<PackagedSplit> {
+        let packaged_split = create_packaged_split(
+            segment_metas,
+            split,
+            &self.tag_fields,
+            &self.calendar_tag_granularities,
+            self.num_sample_docs,
+            ctx,
+        )?;
+        if let Some(quarantine_directory) = &self.quarantine_directory_opt {
+            verify_or_quarantine_split(&packaged_split, quarantine_directory, ctx)?;
+        }
         Ok(packaged_split)
     }
+
+    /// Packages the splits of the batch one after the other, streaming the files of each split to
+    /// the uploader as soon as they are final, and then the packaged split.
+    async fn stream_indexed_split_batch(
+        &self,
+        batch: IndexedSplitBatch,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        let split_ids: Vec<String> = batch
+            .splits
+            .iter()
+            .map(|split| split.split_id().to_string())
+            .collect_vec();
+        let index_id = batch
+            .splits
+            .get(0)
+            .map(|split| split.split_attrs.pipeline_id.index_id.clone())
+            .context("A split batch must have at least one split.")?;
+        let (split_events_tx, split_events_rx) = mpsc::unbounded_channel();
+        let mut batch_stream_opt = Some(PackagedSplitBatchStream {
+            index_id,
+            split_ids: split_ids.clone(),
+            split_events_rx,
+            checkpoint_delta_opt: batch.checkpoint_delta,
+            publish_with_next_delta: batch.publish_with_next_delta,
+            publish_lock: batch.publish_lock.clone(),
+            date_of_birth: batch.date_of_birth,
+        });
+        for mut split in batch.splits {
+            if batch.publish_lock.is_dead() {
+                // Dropping the sender closes the stream, and the uploader discards the batch.
+                info!(
+                    split_ids=?split_ids,
+                    "Splits' publish lock is dead."
+                );
+                return Ok(());
+            }
+            commit_split(&mut split, ctx)?;
+            let segment_metas = merge_segments_if_required(&mut split, ctx).await?;
+            if let Some(batch_stream) = batch_stream_opt.take() {
+                ctx.send_message(&self.uploader_mailbox, batch_stream)
+                    .await?;
+            }
+            let split_files = list_split_files(&segment_metas, &split.split_scratch_directory);
+            split_events_tx
+                .send(PackagedSplitEvent::SplitFiles {
+                    split_id: split.split_id().to_string(),
+                    split_files,
+                })
+                .context("The uploader stopped receiving the split stream.")?;
+            let packaged_split = self.package_split(&segment_metas[..], split, ctx)?;
+            split_events_tx
+                .send(PackagedSplitEvent::Packaged(packaged_split))
+                .context("The uploader stopped receiving the split stream.")?;
+        }
+        fail_point!("packager:after");
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -145,6 +313,9 @@ impl Handler<IndexedSplitBatch> for Packager {
             }
         }
         fail_point!("packager:before");
+        if self.upload_pipelining {
+            return self.stream_indexed_split_batch(batch, ctx).await;
+        }
         let mut packaged_splits = Vec::new();
         for split in batch.splits {
             if batch.publish_lock.is_dead() {
@@ -158,21 +329,52 @@ impl Handler<IndexedSplitBatch> for Packager {
             let packaged_split = self.process_indexed_split(split, ctx).await?;
             packaged_splits.push(packaged_split);
         }
-        ctx.send_message(
-            &self.uploader_mailbox,
-            PackagedSplitBatch::new(
-                packaged_splits,
-                batch.checkpoint_delta,
-                batch.publish_lock,
-                batch.date_of_birth,
-            ),
-        )
-        .await?;
+        let mut packaged_split_batch = PackagedSplitBatch::new(
+            packaged_splits,
+            batch.checkpoint_delta,
+            batch.publish_lock,
+            batch.date_of_birth,
+        );
+        packaged_split_batch.publish_with_next_delta = batch.publish_with_next_delta;
+        ctx.send_message(&self.uploader_mailbox, packaged_split_batch)
+            .await?;
         fail_point!("packager:after");
         Ok(())
     }
 }
 
+#[async_trait]
+impl Handler<ShardSplitBatch> for Packager {
+    type Reply = ();
+
+    fn message_span(&self, msg_id: u64, shard_batch: &ShardSplitBatch) -> Span {
+        info_span!("", msg_id=&msg_id, shard_ord=%shard_batch.shard_ord, num_splits=%shard_batch.batch.splits.len())
+    }
+
+    async fn handle(
+        &mut self,
+        shard_batch: ShardSplitBatch,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        let shard_batch_combiner = self
+            .shard_batch_combiner_opt
+            .as_mut()
+            .context("Received a shard split batch in a pipeline without shards.")?;
+        let batch = if let Some(batch) = shard_batch_combiner.push(shard_batch)? {
+            batch
+        } else {
+            return Ok(());
+        };
+        if batch.splits.is_empty() {
+            shard_batch_combiner
+                .publish_checkpoint_delta(batch, ctx)
+                .await?;
+            return Ok(());
+        }
+        Handler::<IndexedSplitBatch>::handle(self, batch, ctx).await
+    }
+}
+
 /// returns true iff merge is required to reach a state where
 /// we have zero, or a single segment with no deletes segment.
 fn is_merge_required(segment_metas: &[SegmentMeta]) -> bool {
@@ -315,23 +517,150 @@ fn create_packaged_split(
     segment_metas: &[SegmentMeta],
     split: IndexedSplit,
     tag_fields: &[NamedField],
+    calendar_tag_granularities: &[CalendarTagGranularity],
+    num_sample_docs: usize,
     ctx: &ActorContext<Packager>,
 ) -> anyhow::Result<PackagedSplit> {
     info!(split_id = split.split_id(), "create-packaged-split");
     let split_files = list_split_files(segment_metas, &split.split_scratch_directory);
 
-    // Extracts tag values from inverted indexes only when a field cardinality is less
-    // than `MAX_VALUES_PER_TAG_FIELD`.
     debug!(split_id = split.split_id(), tag_fields =? tag_fields, "extract-tags-values");
     let index_reader = split
         .index
         .reader_builder()
         .reload_policy(ReloadPolicy::Manual)
         .try_into()?;
+    let tags = extract_split_tags(
+        &index_reader.searcher(),
+        tag_fields,
+        calendar_tag_granularities,
+        split.split_attrs.time_range.as_ref(),
+    )?;
+    ctx.record_progress();
+
+    debug!(split_id = split.split_id(), "extract-field-stats");
+    let field_stats = extract_split_field_stats(&index_reader.searcher())?;
+    ctx.record_progress();
+
+    let sample_docs_opt = if num_sample_docs > 0 {
+        debug!(split_id = split.split_id(), "sample-docs");
+        let sample_docs = sample_split_docs(&index_reader.searcher(), num_sample_docs)?;
+        ctx.record_progress();
+        Some(sample_docs)
+    } else {
+        None
+    };
+
+    debug!(split_id = split.split_id(), "build-hotcache");
+    let mut hotcache_bytes = Vec::new();
+    build_hotcache(split.split_scratch_directory.path(), &mut hotcache_bytes)?;
+    ctx.record_progress();
+
+    let packaged_split = PackagedSplit {
+        split_attrs: split.split_attrs,
+        split_scratch_directory: split.split_scratch_directory,
+        tags,
+        field_stats,
+        split_files,
+        hotcache_bytes,
+        sample_docs_opt,
+    };
+    Ok(packaged_split)
+}
+
+/// Extracts the statistics of the single-valued numeric fast fields of a split: the range of their
+/// values, read from the bounds of the fast fields of its non-empty segments, and, for the indexed
+/// fields, the number of terms of their term dictionaries.
+pub(crate) fn extract_split_field_stats(
+    searcher: &Searcher,
+) -> anyhow::Result<BTreeMap<String, SplitFieldStats>> {
+    let mut field_stats = BTreeMap::new();
+    for (field, field_entry) in searcher.schema().fields() {
+        let field_type = field_entry.field_type();
+        if field_type.get_fastfield_cardinality() != Some(Cardinality::SingleValue) {
+            continue;
+        }
+        let value_range_opt = match field_type {
+            FieldType::I64(_) => fast_field_value_range(searcher, field, |segment_reader| {
+                let fast_field_reader = segment_reader.fast_fields().i64(field)?;
+                Ok((fast_field_reader.min_value(), fast_field_reader.max_value()))
+            })?
+            .map(|(min, max)| FieldValueRange::I64 { min, max }),
+            FieldType::U64(_) => fast_field_value_range(searcher, field, |segment_reader| {
+                let fast_field_reader = segment_reader.fast_fields().u64(field)?;
+                Ok((fast_field_reader.min_value(), fast_field_reader.max_value()))
+            })?
+            .map(|(min, max)| FieldValueRange::U64 { min, max }),
+            FieldType::F64(_) => fast_field_value_range(searcher, field, |segment_reader| {
+                let fast_field_reader = segment_reader.fast_fields().f64(field)?;
+                Ok((fast_field_reader.min_value(), fast_field_reader.max_value()))
+            })?
+            .filter(|(min, max)| !min.is_nan() && !max.is_nan())
+            .map(|(min, max)| FieldValueRange::F64 { min, max }),
+            _ => None,
+        };
+        let value_range = match value_range_opt {
+            Some(value_range) => value_range,
+            None => continue,
+        };
+        let num_distinct_values = if field_entry.is_indexed() {
+            let mut num_terms = 0;
+            for segment_reader in searcher.segment_readers() {
+                num_terms += segment_reader.inverted_index(field)?.terms().num_terms() as u64;
+            }
+            Some(num_terms)
+        } else {
+            None
+        };
+        field_stats.insert(
+            field_entry.name().to_string(),
+            SplitFieldStats {
+                value_range,
+                num_distinct_values,
+            },
+        );
+    }
+    Ok(field_stats)
+}
+
+/// Returns the range of the values of a fast field over the non-empty segments of a split.
+fn fast_field_value_range<T: PartialOrd>(
+    searcher: &Searcher,
+    field: Field,
+    segment_value_range: impl Fn(&SegmentReader) -> tantivy::Result<(T, T)>,
+) -> anyhow::Result<Option<(T, T)>> {
+    let mut value_range_opt: Option<(T, T)> = None;
+    for segment_reader in searcher.segment_readers() {
+        if segment_reader.num_docs() == 0 {
+            continue;
+        }
+        let (segment_min, segment_max) = segment_value_range(segment_reader)
+            .with_context(|| format!("Failed to open fast field {:?}.", field))?;
+        value_range_opt = Some(match value_range_opt {
+            Some((min, max)) => (
+                if segment_min < min { segment_min } else { min },
+                if segment_max > max { segment_max } else { max },
+            ),
+            None => (segment_min, segment_max),
+        });
+    }
+    Ok(value_range_opt)
+}
+
+/// Extracts the tags of a split from the inverted indexes of its tag fields and from its time
+/// range for the calendar tags.
+///
+/// The values of a tag field are only extracted when its cardinality is less than
+/// `MAX_VALUES_PER_TAG_FIELD`.
+pub(crate) fn extract_split_tags(
+    searcher: &Searcher,
+    tag_fields: &[NamedField],
+    calendar_tag_granularities: &[CalendarTagGranularity],
+    time_range_opt: Option<&RangeInclusive<i64>>,
+) -> anyhow::Result<BTreeSet<String>> {
     let mut tags = BTreeSet::default();
     for named_field in tag_fields {
-        let inverted_indexes = index_reader
-            .searcher()
+        let inverted_indexes = searcher
             .segment_readers()
             .iter()
             .map(|segment| segment.inverted_index(named_field.field))
@@ -346,22 +675,400 @@ fn create_packaged_split(
             }
         }
     }
+    if let Some(time_range) = time_range_opt {
+        for calendar_tag_granularity in calendar_tag_granularities {
+            let tag_field_name = calendar_tag_granularity.tag_field_name();
+            match calendar_buckets(
+                *calendar_tag_granularity,
+                time_range,
+                MAX_VALUES_PER_TAG_FIELD,
+            ) {
+                Ok(calendar_buckets) => {
+                    append_to_tag_set(tag_field_name, &calendar_buckets, &mut tags);
+                }
+                Err(calendar_bucket_error) => {
+                    warn!(err=?calendar_bucket_error, tag_field=tag_field_name, "No calendar buckets will be registered in the split metadata.");
+                }
+            }
+        }
+    }
+    Ok(tags)
+}
 
-    ctx.record_progress();
+/// Returns the calendar buckets overlapping the time range, formatted in UTC. Returns an error if
+/// there are more than `max_num_buckets` of them.
+fn calendar_buckets(
+    granularity: CalendarTagGranularity,
+    time_range: &RangeInclusive<i64>,
+    max_num_buckets: usize,
+) -> anyhow::Result<Vec<String>> {
+    let start_bucket_ord = calendar_bucket_ord(granularity, *time_range.start())?;
+    let end_bucket_ord = calendar_bucket_ord(granularity, *time_range.end())?;
+    if end_bucket_ord - start_bucket_ord >= max_num_buckets as i64 {
+        bail!(
+            "Time range spans more than {} {} buckets.",
+            max_num_buckets,
+            granularity.tag_field_name()
+        );
+    }
+    (start_bucket_ord..=end_bucket_ord)
+        .map(|bucket_ord| format_calendar_bucket(granularity, bucket_ord))
+        .collect()
+}
 
-    debug!(split_id = split.split_id(), "build-hotcache");
-    let mut hotcache_bytes = Vec::new();
-    build_hotcache(split.split_scratch_directory.path(), &mut hotcache_bytes)?;
-    ctx.record_progress();
+/// Returns the ordinal of the calendar bucket of a timestamp expressed in seconds. Consecutive
+/// buckets have consecutive ordinals.
+fn calendar_bucket_ord(granularity: CalendarTagGranularity, timestamp: i64) -> anyhow::Result<i64> {
+    let date_time = OffsetDateTime::from_unix_timestamp(timestamp)?;
+    let bucket_ord = match granularity {
+        CalendarTagGranularity::Hour => timestamp.div_euclid(3_600),
+        CalendarTagGranularity::Day => timestamp.div_euclid(86_400),
+        CalendarTagGranularity::Month => {
+            date_time.year() as i64 * 12 + u8::from(date_time.month()) as i64 - 1
+        }
+        CalendarTagGranularity::Year => date_time.year() as i64,
+    };
+    Ok(bucket_ord)
+}
 
-    let packaged_split = PackagedSplit {
-        split_attrs: split.split_attrs,
-        split_scratch_directory: split.split_scratch_directory,
-        tags,
-        split_files,
-        hotcache_bytes,
+fn format_calendar_bucket(
+    granularity: CalendarTagGranularity,
+    bucket_ord: i64,
+) -> anyhow::Result<String> {
+    let calendar_bucket = match granularity {
+        CalendarTagGranularity::Hour => {
+            let date_time = OffsetDateTime::from_unix_timestamp(bucket_ord * 3_600)?;
+            format!(
+                "{:04}-{:02}-{:02}T{:02}",
+                date_time.year(),
+                u8::from(date_time.month()),
+                date_time.day(),
+                date_time.hour()
+            )
+        }
+        CalendarTagGranularity::Day => {
+            let date_time = OffsetDateTime::from_unix_timestamp(bucket_ord * 86_400)?;
+            format!(
+                "{:04}-{:02}-{:02}",
+                date_time.year(),
+                u8::from(date_time.month()),
+                date_time.day()
+            )
+        }
+        CalendarTagGranularity::Month => format!(
+            "{:04}-{:02}",
+            bucket_ord.div_euclid(12),
+            bucket_ord.rem_euclid(12) + 1
+        ),
+        CalendarTagGranularity::Year => format!("{:04}", bucket_ord),
     };
-    Ok(packaged_split)
+    Ok(calendar_bucket)
+}
+
+/// Picks up to `num_sample_docs` alive documents of the split uniformly at random (reservoir
+/// sampling) and serializes their stored fields as NDJSON.
+fn sample_split_docs(searcher: &Searcher, num_sample_docs: usize) -> anyhow::Result<Vec<u8>> {
+    let mut rng = rand::thread_rng();
+    let mut sampled_doc_addresses: Vec<DocAddress> = Vec::with_capacity(num_sample_docs);
+    let mut num_alive_docs = 0;
+
+    for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+        for doc_id in 0..segment_reader.max_doc() {
+            if segment_reader.is_deleted(doc_id) {
+                continue;
+            }
+            let doc_address = DocAddress::new(segment_ord as u32, doc_id);
+            if sampled_doc_addresses.len() < num_sample_docs {
+                sampled_doc_addresses.push(doc_address);
+            } else {
+                let slot = rng.gen_range(0..=num_alive_docs);
+                if slot < num_sample_docs {
+                    sampled_doc_addresses[slot] = doc_address;
+                }
+            }
+            num_alive_docs += 1;
+        }
+    }
+    // Fetching the documents in order reads each doc store block once.
+    sampled_doc_addresses.sort();
+
+    let schema = searcher.schema();
+    let mut sample_docs = Vec::new();
+    for doc_address in sampled_doc_addresses {
+        let doc = searcher.doc(doc_address)?;
+        sample_docs.extend_from_slice(schema.to_json(&doc).as_bytes());
+        sample_docs.push(b'\n');
+    }
+    Ok(sample_docs)
+}
+
+/// Verifies the packaged split and, if it is corrupted, copies it into the quarantine directory.
+///
+/// Returning an error fails the pipeline, which then restarts from the last published checkpoint,
+/// so the documents of a corrupted split are indexed again rather than lost. Once the splits built
+/// from the same input have been quarantined `MAX_SPLIT_VERIFICATION_FAILURES` times, the error is
+/// a [`RepeatedSplitVerificationFailure`] and the pipeline is not restarted.
+fn verify_or_quarantine_split(
+    packaged_split: &PackagedSplit,
+    quarantine_directory: &Path,
+    ctx: &ActorContext<Packager>,
+) -> anyhow::Result<()> {
+    debug!(split_id = packaged_split.split_id(), "verify-split");
+    let verification_result = {
+        let _protect_guard = ctx.protect_zone();
+        let _verification_timer = INDEXER_METRICS
+            .split_verification_duration_secs
+            .start_timer();
+        verify_packaged_split(packaged_split)
+    };
+    INDEXER_METRICS.splits_verified_total.inc();
+
+    let verification_error = match verification_result {
+        Ok(()) => return Ok(()),
+        Err(verification_error) => verification_error,
+    };
+    INDEXER_METRICS.splits_quarantined_total.inc();
+    let input_fingerprint = split_input_fingerprint(packaged_split);
+    let quarantine_result =
+        quarantine_split(packaged_split, quarantine_directory, &input_fingerprint).and_then(
+            |quarantined_split_path| {
+                let num_failures =
+                    count_quarantined_inputs(quarantine_directory, &input_fingerprint)?;
+                Ok((quarantined_split_path, num_failures))
+            },
+        );
+    let (quarantined_split_path, num_failures) = quarantine_result.with_context(|| {
+        format!(
+            "Failed to quarantine corrupted split `{}`.",
+            packaged_split.split_id()
+        )
+    })?;
+    error!(
+        split_id = packaged_split.split_id(),
+        quarantined_split_path = %quarantined_split_path.display(),
+        num_failures = num_failures,
+        error = ?verification_error,
+        "Split failed verification and was quarantined."
+    );
+    if num_failures >= MAX_SPLIT_VERIFICATION_FAILURES {
+        return Err(
+            anyhow::Error::new(RepeatedSplitVerificationFailure { num_failures }).context(format!(
+                "Split `{}` failed verification: {:#}",
+                packaged_split.split_id(),
+                verification_error
+            )),
+        );
+    }
+    Err(verification_error.context(format!(
+        "Split `{}` failed verification.",
+        packaged_split.split_id()
+    )))
+}
+
+/// Re-opens the split with its hotcache, fetches a sample of documents from the doc store, and
+/// reads the postings of a sample of terms for every indexed field.
+fn verify_packaged_split(packaged_split: &PackagedSplit) -> anyhow::Result<()> {
+    let mmap_directory = MmapDirectory::open(packaged_split.split_scratch_directory.path())?;
+    let hotcache_bytes = OwnedBytes::new(packaged_split.hotcache_bytes.clone());
+    let hot_directory =
+        HotDirectory::open(mmap_directory, hotcache_bytes).context("Failed to open hotcache.")?;
+    let index = Index::open(hot_directory)?;
+    let index_reader = index
+        .reader_builder()
+        .reload_policy(ReloadPolicy::Manual)
+        .try_into()?;
+    let searcher = index_reader.searcher();
+
+    let num_docs = searcher.num_docs();
+    if num_docs != packaged_split.split_attrs.num_docs {
+        bail!(
+            "Split contains {} documents, expected {}.",
+            num_docs,
+            packaged_split.split_attrs.num_docs
+        );
+    }
+    let schema = index.schema();
+
+    for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+        for doc_id in sample_doc_ids(segment_reader) {
+            searcher
+                .doc(DocAddress::new(segment_ord as u32, doc_id))
+                .with_context(|| format!("Failed to fetch document {}.", doc_id))?;
+        }
+        for (field, field_entry) in schema.fields() {
+            if !field_entry.is_indexed() {
+                continue;
+            }
+            let inverted_index = segment_reader.inverted_index(field)?;
+            verify_terms(&inverted_index, segment_reader.max_doc())
+                .with_context(|| format!("Invalid inverted index for `{}`.", field_entry.name()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns up to `MAX_VERIFIED_DOCS_PER_SEGMENT` alive doc IDs evenly spread over the segment.
+fn sample_doc_ids(segment_reader: &SegmentReader) -> Vec<u32> {
+    let max_doc = segment_reader.max_doc();
+    let step = (max_doc / MAX_VERIFIED_DOCS_PER_SEGMENT).max(1);
+    (0..max_doc)
+        .step_by(step as usize)
+        .filter(|doc_id| !segment_reader.is_deleted(*doc_id))
+        .take(MAX_VERIFIED_DOCS_PER_SEGMENT as usize)
+        .collect()
+}
+
+/// Checks that the postings of the first `MAX_VERIFIED_TERMS_PER_FIELD` terms of the term
+/// dictionary are consistent with their term infos.
+fn verify_terms(inverted_index: &InvertedIndexReader, max_doc: u32) -> anyhow::Result<()> {
+    let mut terms_streamer = inverted_index.terms().stream()?;
+    let mut num_verified_terms = 0;
+
+    while num_verified_terms < MAX_VERIFIED_TERMS_PER_FIELD {
+        let term_info = match terms_streamer.next() {
+            Some((_, term_info)) => term_info.clone(),
+            None => break,
+        };
+        let mut postings =
+            inverted_index.read_postings_from_terminfo(&term_info, IndexRecordOption::Basic)?;
+        let mut num_matching_docs = 0;
+
+        while postings.doc() != TERMINATED {
+            if postings.doc() >= max_doc {
+                bail!("Posting list references document {}.", postings.doc());
+            }
+            num_matching_docs += 1;
+            postings.advance();
+        }
+        if num_matching_docs != term_info.doc_freq {
+            bail!(
+                "Posting list contains {} documents, expected {}.",
+                num_matching_docs,
+                term_info.doc_freq
+            );
+        }
+        num_verified_terms += 1;
+    }
+    Ok(())
+}
+
+/// Identifies the input a split was built from, so that the splits built again from the same
+/// documents after a pipeline restart can be recognized.
+fn split_input_fingerprint(packaged_split: &PackagedSplit) -> String {
+    let split_attrs = &packaged_split.split_attrs;
+    format!(
+        "{}/{}/{}/{}/{}/{}/{:?}",
+        split_attrs.pipeline_id.index_id,
+        split_attrs.pipeline_id.source_id,
+        split_attrs.pipeline_id.pipeline_ord,
+        split_attrs.partition_id,
+        split_attrs.num_docs,
+        split_attrs.uncompressed_docs_size_in_bytes,
+        split_attrs.time_range
+    )
+}
+
+/// Copies the files and the hotcache of the split into `<quarantine dir>/<split ID>`, along with
+/// the fingerprint of its input.
+///
+/// The oldest quarantined splits are removed to keep the quarantine directory under
+/// `MAX_QUARANTINE_DIRECTORY_NUM_BYTES`. Only the input of a split larger than that is recorded.
+fn quarantine_split(
+    packaged_split: &PackagedSplit,
+    quarantine_directory: &Path,
+    input_fingerprint: &str,
+) -> io::Result<PathBuf> {
+    let mut split_num_bytes = packaged_split.hotcache_bytes.len() as u64;
+    for split_file in &packaged_split.split_files {
+        split_num_bytes += std::fs::metadata(split_file)?.len();
+    }
+    let is_too_large = split_num_bytes > MAX_QUARANTINE_DIRECTORY_NUM_BYTES;
+    std::fs::create_dir_all(quarantine_directory)?;
+
+    if !is_too_large {
+        evict_quarantined_splits(
+            quarantine_directory,
+            MAX_QUARANTINE_DIRECTORY_NUM_BYTES - split_num_bytes,
+        )?;
+    }
+    let quarantined_split_path = quarantine_directory.join(packaged_split.split_id());
+    std::fs::create_dir_all(&quarantined_split_path)?;
+    std::fs::write(
+        quarantined_split_path.join(QUARANTINED_INPUT_FILE_NAME),
+        input_fingerprint,
+    )?;
+    if is_too_large {
+        warn!(
+            split_id = packaged_split.split_id(),
+            split_num_bytes = split_num_bytes,
+            "Split is too large to be quarantined, only recording its input."
+        );
+        return Ok(quarantined_split_path);
+    }
+    for split_file in &packaged_split.split_files {
+        if let Some(file_name) = split_file.file_name() {
+            std::fs::copy(split_file, quarantined_split_path.join(file_name))?;
+        }
+    }
+    std::fs::write(
+        quarantined_split_path.join(QUARANTINED_HOTCACHE_FILE_NAME),
+        &packaged_split.hotcache_bytes,
+    )?;
+    Ok(quarantined_split_path)
+}
+
+/// Removes the oldest quarantined splits until the quarantine directory holds at most
+/// `max_num_bytes`.
+fn evict_quarantined_splits(quarantine_directory: &Path, max_num_bytes: u64) -> io::Result<()> {
+    let mut quarantined_splits = Vec::new();
+    let mut total_num_bytes = 0;
+    for entry in std::fs::read_dir(quarantine_directory)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_dir() {
+            continue;
+        }
+        let mut num_bytes = 0;
+        for file_entry in std::fs::read_dir(entry.path())? {
+            num_bytes += file_entry?.metadata()?.len();
+        }
+        total_num_bytes += num_bytes;
+        quarantined_splits.push((metadata.modified()?, entry.path(), num_bytes));
+    }
+    quarantined_splits.sort();
+
+    for (_, quarantined_split_path, num_bytes) in quarantined_splits {
+        if total_num_bytes <= max_num_bytes {
+            break;
+        }
+        info!(
+            quarantined_split_path = %quarantined_split_path.display(),
+            "Removing quarantined split to make room for a new one."
+        );
+        std::fs::remove_dir_all(&quarantined_split_path)?;
+        total_num_bytes -= num_bytes;
+    }
+    Ok(())
+}
+
+/// Counts the quarantined splits built from the input identified by `input_fingerprint`.
+fn count_quarantined_inputs(
+    quarantine_directory: &Path,
+    input_fingerprint: &str,
+) -> io::Result<usize> {
+    let mut num_quarantined_inputs = 0;
+    for entry in std::fs::read_dir(quarantine_directory)? {
+        let input_path = entry?.path().join(QUARANTINED_INPUT_FILE_NAME);
+        match std::fs::read_to_string(&input_path) {
+            Ok(quarantined_input) if quarantined_input == input_fingerprint => {
+                num_quarantined_inputs += 1;
+            }
+            Ok(_) => {}
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(num_quarantined_inputs)
 }
 
 /// Reads u64 from stored term data.
@@ -456,6 +1163,11 @@ mod tests {
                 uncompressed_docs_size_in_bytes: num_docs * 15,
                 time_range: timerange_opt,
                 replaced_split_ids: Vec::new(),
+                delete_opstamp: 0,
+                dedup_digest_opt: None,
+                min_expire_timestamp: None,
+                doc_mapping_version: 0,
+                config_fingerprint: None,
             },
             index,
             index_writer,
@@ -492,12 +1204,13 @@ mod tests {
                 "tag_str", "tag_many", "tag_u64", "tag_i64", "tag_f64", "tag_bool",
             ],
         );
-        let packager = Packager::new("TestPackager", tag_fields, mailbox);
+        let packager = Packager::new("TestPackager", tag_fields, None, mailbox);
         let (packager_mailbox, packager_handle) = universe.spawn_actor(packager).spawn();
         packager_mailbox
             .send_message(IndexedSplitBatch {
                 splits: vec![indexed_split],
                 checkpoint_delta: IndexCheckpointDelta::for_test("source_id", 10..20).into(),
+                publish_with_next_delta: false,
                 publish_lock: PublishLock::default(),
                 date_of_birth: Instant::now(),
             })
@@ -527,6 +1240,78 @@ mod tests {
                 "tag_u64:42"
             ]
         );
+        // Only the single-valued numeric fast fields have statistics.
+        assert_eq!(
+            split.field_stats,
+            BTreeMap::from([(
+                "timestamp".to_string(),
+                SplitFieldStats {
+                    value_range: FieldValueRange::U64 {
+                        min: 1628203589,
+                        max: 1628203640
+                    },
+                    num_distinct_values: None,
+                }
+            )])
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_packager_upload_pipelining() -> anyhow::Result<()> {
+        quickwit_common::setup_logging_for_tests();
+        let universe = Universe::new();
+        let (mailbox, inbox) = create_test_mailbox();
+        let indexed_split = make_indexed_split_for_test(&[&[1628203589], &[1628203640]])?;
+        let split_id = indexed_split.split_id().to_string();
+        let tag_fields = get_tag_fields(indexed_split.index.schema(), &[]);
+        let packager =
+            Packager::new("TestPackager", tag_fields, None, mailbox).with_upload_pipelining();
+        let (packager_mailbox, packager_handle) = universe.spawn_actor(packager).spawn();
+        packager_mailbox
+            .send_message(IndexedSplitBatch {
+                splits: vec![indexed_split],
+                checkpoint_delta: IndexCheckpointDelta::for_test("source_id", 10..20).into(),
+                publish_with_next_delta: false,
+                publish_lock: PublishLock::default(),
+                date_of_birth: Instant::now(),
+            })
+            .await?;
+        assert_eq!(
+            packager_handle.process_pending_and_observe().await.obs_type,
+            ObservationType::Alive
+        );
+        let mut batch_streams = inbox.drain_for_test_typed::<PackagedSplitBatchStream>();
+        assert_eq!(batch_streams.len(), 1);
+        let mut batch_stream = batch_streams.pop().unwrap();
+        assert_eq!(batch_stream.split_ids, vec![split_id.clone()]);
+        let streamed_split_files = match batch_stream.split_events_rx.recv().await {
+            Some(PackagedSplitEvent::SplitFiles {
+                split_id: streamed_split_id,
+                split_files,
+            }) => {
+                assert_eq!(streamed_split_id, split_id);
+                split_files
+            }
+            other => panic!("Expected split files event, got {:?}.", other),
+        };
+        // The segments are merged before the files are streamed.
+        assert_eq!(
+            streamed_split_files
+                .iter()
+                .filter(|path| path.extension() == Some("idx".as_ref()))
+                .count(),
+            1
+        );
+        match batch_stream.split_events_rx.recv().await {
+            Some(PackagedSplitEvent::Packaged(packaged_split)) => {
+                assert_eq!(packaged_split.split_id(), split_id);
+                assert_eq!(packaged_split.split_files, streamed_split_files);
+                assert!(!packaged_split.hotcache_bytes.is_empty());
+            }
+            other => panic!("Expected packaged split event, got {:?}.", other),
+        }
+        assert!(batch_stream.split_events_rx.recv().await.is_none());
         Ok(())
     }
 
@@ -537,12 +1322,13 @@ mod tests {
         let (mailbox, inbox) = create_test_mailbox();
         let indexed_split = make_indexed_split_for_test(&[&[1628203589], &[1628203640]])?;
         let tag_fields = get_tag_fields(indexed_split.index.schema(), &[]);
-        let packager = Packager::new("TestPackager", tag_fields, mailbox);
+        let packager = Packager::new("TestPackager", tag_fields, None, mailbox);
         let (packager_mailbox, packager_handle) = universe.spawn_actor(packager).spawn();
         packager_mailbox
             .send_message(IndexedSplitBatch {
                 splits: vec![indexed_split],
                 checkpoint_delta: IndexCheckpointDelta::for_test("source_id", 10..20).into(),
+                publish_with_next_delta: false,
                 publish_lock: PublishLock::default(),
                 date_of_birth: Instant::now(),
             })
@@ -564,12 +1350,13 @@ mod tests {
         let indexed_split_1 = make_indexed_split_for_test(&[&[1628203589], &[1628203640]])?;
         let indexed_split_2 = make_indexed_split_for_test(&[&[1628204589], &[1629203640]])?;
         let tag_fields = get_tag_fields(indexed_split_1.index.schema(), &[]);
-        let packager = Packager::new("TestPackager", tag_fields, mailbox);
+        let packager = Packager::new("TestPackager", tag_fields, None, mailbox);
         let (packager_mailbox, packager_handle) = universe.spawn_actor(packager).spawn();
         packager_mailbox
             .send_message(IndexedSplitBatch {
                 splits: vec![indexed_split_1, indexed_split_2],
                 checkpoint_delta: IndexCheckpointDelta::for_test("source_id", 10..20).into(),
+                publish_with_next_delta: false,
                 publish_lock: PublishLock::default(),
                 date_of_birth: Instant::now(),
             })
@@ -590,4 +1377,252 @@ mod tests {
         );
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_packager_samples_docs() -> anyhow::Result<()> {
+        quickwit_common::setup_logging_for_tests();
+        let universe = Universe::new();
+        let (mailbox, inbox) = create_test_mailbox();
+        let indexed_split_1 = make_indexed_split_for_test(&[&[1628203589], &[1628203640]])?;
+        let indexed_split_2 = make_indexed_split_for_test(&[&[1628204589]])?;
+        let tag_fields = get_tag_fields(indexed_split_1.index.schema(), &[]);
+        let packager =
+            Packager::new("TestPackager", tag_fields, None, mailbox).with_num_sample_docs(12);
+        let (packager_mailbox, packager_handle) = universe.spawn_actor(packager).spawn();
+        packager_mailbox
+            .send_message(IndexedSplitBatch {
+                splits: vec![indexed_split_1, indexed_split_2],
+                checkpoint_delta: IndexCheckpointDelta::for_test("source_id", 10..20).into(),
+                publish_with_next_delta: false,
+                publish_lock: PublishLock::default(),
+                date_of_birth: Instant::now(),
+            })
+            .await?;
+        packager_handle.process_pending_and_observe().await;
+        let packaged_splits = inbox.drain_for_test();
+        assert_eq!(packaged_splits.len(), 1);
+        let packaged_split_batch = packaged_splits[0]
+            .downcast_ref::<PackagedSplitBatch>()
+            .unwrap();
+        let num_sample_docs: Vec<usize> = packaged_split_batch
+            .splits
+            .iter()
+            .map(|split| {
+                let sample_docs = split.sample_docs_opt.as_ref().unwrap();
+                std::str::from_utf8(sample_docs)
+                    .unwrap()
+                    .lines()
+                    .map(|doc_json| serde_json::from_str::<serde_json::Value>(doc_json).unwrap())
+                    .filter(|doc| doc.is_object())
+                    .count()
+            })
+            .collect();
+        // The first split holds 18 documents, the second one 9.
+        assert_eq!(num_sample_docs, [12, 9]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_packager_calendar_tags() -> anyhow::Result<()> {
+        quickwit_common::setup_logging_for_tests();
+        let universe = Universe::new();
+        let (mailbox, inbox) = create_test_mailbox();
+        // 2021-08-05T22:46:29Z and 2021-08-06T00:33:20Z.
+        let indexed_split = make_indexed_split_for_test(&[&[1628203589, 1628210000]])?;
+        let tag_fields = get_tag_fields(indexed_split.index.schema(), &[]);
+        let packager =
+            Packager::new("TestPackager", tag_fields, None, mailbox).with_calendar_tags(vec![
+                CalendarTagGranularity::Hour,
+                CalendarTagGranularity::Day,
+                CalendarTagGranularity::Month,
+                CalendarTagGranularity::Year,
+            ]);
+        let (packager_mailbox, packager_handle) = universe.spawn_actor(packager).spawn();
+        packager_mailbox
+            .send_message(IndexedSplitBatch {
+                splits: vec![indexed_split],
+                checkpoint_delta: IndexCheckpointDelta::for_test("source_id", 10..20).into(),
+                publish_with_next_delta: false,
+                publish_lock: PublishLock::default(),
+                date_of_birth: Instant::now(),
+            })
+            .await?;
+        packager_handle.process_pending_and_observe().await;
+        let packaged_splits = inbox.drain_for_test();
+        assert_eq!(packaged_splits.len(), 1);
+        let packaged_split = packaged_splits[0]
+            .downcast_ref::<PackagedSplitBatch>()
+            .unwrap();
+        let split = &packaged_split.splits[0];
+        assert_eq!(
+            &split.tags.iter().map(|s| s.as_str()).collect::<Vec<&str>>(),
+            &[
+                "day!",
+                "day:2021-08-05",
+                "day:2021-08-06",
+                "hour!",
+                "hour:2021-08-05T22",
+                "hour:2021-08-05T23",
+                "hour:2021-08-06T00",
+                "month!",
+                "month:2021-08",
+                "year!",
+                "year:2021",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_calendar_buckets() {
+        // 2021-08-05T22:46:29Z..=2022-01-02T00:00:00Z
+        let time_range = 1628203589..=1641081600;
+        assert_eq!(
+            calendar_buckets(CalendarTagGranularity::Month, &time_range, 6).unwrap(),
+            ["2021-08", "2021-09", "2021-10", "2021-11", "2021-12", "2022-01"]
+        );
+        assert!(calendar_buckets(CalendarTagGranularity::Month, &time_range, 5).is_err());
+        assert_eq!(
+            calendar_buckets(CalendarTagGranularity::Year, &time_range, 6).unwrap(),
+            ["2021", "2022"]
+        );
+        assert!(calendar_buckets(CalendarTagGranularity::Day, &time_range, 6).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_packager_verifies_split() -> anyhow::Result<()> {
+        quickwit_common::setup_logging_for_tests();
+        let universe = Universe::new();
+        let (mailbox, inbox) = create_test_mailbox();
+        let quarantine_directory = tempfile::tempdir()?;
+        let indexed_split = make_indexed_split_for_test(&[&[1628203589], &[1628203640]])?;
+        let tag_fields = get_tag_fields(indexed_split.index.schema(), &[]);
+        let packager = Packager::new(
+            "TestPackager",
+            tag_fields,
+            Some(quarantine_directory.path().to_path_buf()),
+            mailbox,
+        );
+        let (packager_mailbox, packager_handle) = universe.spawn_actor(packager).spawn();
+        packager_mailbox
+            .send_message(IndexedSplitBatch {
+                splits: vec![indexed_split],
+                checkpoint_delta: IndexCheckpointDelta::for_test("source_id", 10..20).into(),
+                publish_with_next_delta: false,
+                publish_lock: PublishLock::default(),
+                date_of_birth: Instant::now(),
+            })
+            .await?;
+        assert_eq!(
+            packager_handle.process_pending_and_observe().await.obs_type,
+            ObservationType::Alive
+        );
+        let packaged_splits = inbox.drain_for_test();
+        assert_eq!(packaged_splits.len(), 1);
+        assert_eq!(std::fs::read_dir(quarantine_directory.path())?.count(), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_packager_quarantines_corrupted_split() -> anyhow::Result<()> {
+        quickwit_common::setup_logging_for_tests();
+        let universe = Universe::new();
+        let (mailbox, inbox) = create_test_mailbox();
+        let quarantine_directory = tempfile::tempdir()?;
+        let mut indexed_split = make_indexed_split_for_test(&[&[1628203589]])?;
+        // The split attributes no longer match the content of the split.
+        indexed_split.split_attrs.num_docs += 1;
+        let tag_fields = get_tag_fields(indexed_split.index.schema(), &[]);
+        let packager = Packager::new(
+            "TestPackager",
+            tag_fields,
+            Some(quarantine_directory.path().to_path_buf()),
+            mailbox,
+        );
+        let (packager_mailbox, packager_handle) = universe.spawn_actor(packager).spawn();
+        packager_mailbox
+            .send_message(IndexedSplitBatch {
+                splits: vec![indexed_split],
+                checkpoint_delta: IndexCheckpointDelta::for_test("source_id", 10..20).into(),
+                publish_with_next_delta: false,
+                publish_lock: PublishLock::default(),
+                date_of_birth: Instant::now(),
+            })
+            .await?;
+        let (exit_status, _) = packager_handle.join().await;
+        assert!(matches!(exit_status, ActorExitStatus::Failure(_)));
+        assert!(inbox.drain_for_test().is_empty());
+
+        let quarantined_split_path = quarantine_directory.path().join("test-split");
+        assert!(quarantined_split_path.join("meta.json").exists());
+        assert!(quarantined_split_path
+            .join(QUARANTINED_HOTCACHE_FILE_NAME)
+            .exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_packager_gives_up_on_repeated_verification_failures() -> anyhow::Result<()> {
+        quickwit_common::setup_logging_for_tests();
+        let universe = Universe::new();
+        let quarantine_directory = tempfile::tempdir()?;
+
+        for attempt in 1..=MAX_SPLIT_VERIFICATION_FAILURES {
+            let (mailbox, _inbox) = create_test_mailbox();
+            let mut indexed_split = make_indexed_split_for_test(&[&[1628203589]])?;
+            // The splits built again from the same input get a new ID.
+            indexed_split.split_attrs.split_id = format!("test-split-{}", attempt);
+            indexed_split.split_attrs.num_docs += 1;
+            let tag_fields = get_tag_fields(indexed_split.index.schema(), &[]);
+            let packager = Packager::new(
+                "TestPackager",
+                tag_fields,
+                Some(quarantine_directory.path().to_path_buf()),
+                mailbox,
+            );
+            let (packager_mailbox, packager_handle) = universe.spawn_actor(packager).spawn();
+            packager_mailbox
+                .send_message(IndexedSplitBatch {
+                    splits: vec![indexed_split],
+                    checkpoint_delta: IndexCheckpointDelta::for_test("source_id", 10..20).into(),
+                    publish_with_next_delta: false,
+                    publish_lock: PublishLock::default(),
+                    date_of_birth: Instant::now(),
+                })
+                .await?;
+            let (exit_status, _) = packager_handle.join().await;
+            let error = match exit_status {
+                ActorExitStatus::Failure(error) => error,
+                other_exit_status => panic!("Unexpected exit status {:?}.", other_exit_status),
+            };
+            let is_repeated_failure = error
+                .downcast_ref::<RepeatedSplitVerificationFailure>()
+                .is_some();
+            assert_eq!(
+                is_repeated_failure,
+                attempt == MAX_SPLIT_VERIFICATION_FAILURES
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_evict_quarantined_splits() -> anyhow::Result<()> {
+        let quarantine_directory = tempfile::tempdir()?;
+        for split_id in ["split-1", "split-2", "split-3"] {
+            let quarantined_split_path = quarantine_directory.path().join(split_id);
+            std::fs::create_dir(&quarantined_split_path)?;
+            std::fs::write(quarantined_split_path.join("split"), vec![0u8; 10])?;
+            // Modification times must differ for the eviction order to be deterministic.
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        evict_quarantined_splits(quarantine_directory.path(), 25)?;
+        assert!(!quarantine_directory.path().join("split-1").exists());
+        assert!(quarantine_directory.path().join("split-2").exists());
+        assert!(quarantine_directory.path().join("split-3").exists());
+
+        evict_quarantined_splits(quarantine_directory.path(), 0)?;
+        assert_eq!(std::fs::read_dir(quarantine_directory.path())?.count(), 0);
+        Ok(())
+    }
 }
@@ -19,21 +19,32 @@
 
 use std::sync::Arc;
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use async_trait::async_trait;
 use fail::fail_point;
 use quickwit_actors::{Actor, ActorContext, Handler, Mailbox};
-use quickwit_metastore::Metastore;
-use tracing::info;
-
-use crate::actors::{GarbageCollector, MergePlanner};
-use crate::models::{NewSplits, SplitUpdate};
+use quickwit_directories::LocalSplitRegistry;
+use quickwit_metastore::checkpoint::{
+    IncompatibleCheckpointDelta, IndexCheckpointDelta, SourceCheckpoint,
+};
+use quickwit_metastore::{Metastore, MetastoreError, SplitMetadata};
+use tracing::{error, info};
+
+use crate::actors::{DeleteExecutor, GarbageCollector, MergePlanner};
+use crate::models::{MetastorePayloadGuard, NewSplits, PublishLock, PublishNotifier, SplitUpdate};
 use crate::source::{SourceActor, SuggestTruncate};
 
 #[derive(Clone, Debug, Default)]
 pub struct PublisherCounters {
     pub num_published_splits: u64,
+    /// Number of documents of the published splits, excluding the splits replacing other splits.
+    pub num_published_docs: u64,
     pub num_replace_operations: u64,
+    /// Highest source positions published along with a split.
+    pub published_watermark: SourceCheckpoint,
+    /// Set when the metastore rejected a checkpoint delta overlapping already acknowledged
+    /// positions. This is a poison condition: retrying the publish can never succeed.
+    pub incompatible_checkpoint_delta_opt: Option<IncompatibleCheckpointDelta>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -51,12 +62,35 @@ impl PublisherType {
     }
 }
 
+/// Points the actor to the mailboxes of a group of non-critical actors after the pipeline
+/// respawned it. The mailboxes of the other groups are left as is.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ReplaceNonCriticalMailboxes {
+    pub merge_planner_mailbox_opt: Option<Mailbox<MergePlanner>>,
+    pub garbage_collector_mailbox_opt: Option<Mailbox<GarbageCollector>>,
+    pub delete_executor_mailbox_opt: Option<Mailbox<DeleteExecutor>>,
+}
+
 pub struct Publisher {
     publisher_type: PublisherType,
     metastore: Arc<dyn Metastore>,
     merge_planner_mailbox: Mailbox<MergePlanner>,
     garbage_collector_mailbox: Mailbox<GarbageCollector>,
+    delete_executor_mailbox: Mailbox<DeleteExecutor>,
     source_mailbox_opt: Option<Mailbox<SourceActor>>,
+    publish_notifier_opt: Option<PublishNotifier>,
+    strict_ordering: bool,
+    /// Published checkpoint of the source in strict ordering mode, once read from the metastore.
+    published_checkpoint_opt: Option<SourceCheckpoint>,
+    /// Guard rejecting the checkpoint deltas and publish requests too large to be published.
+    payload_guard_opt: Option<MetastorePayloadGuard>,
+    /// Registry of the splits searchable before they are published, from which the published
+    /// splits are unregistered.
+    local_split_registry_opt: Option<LocalSplitRegistry>,
+    /// Splits held back until the next checkpoint delta, along with their publish lock. They are
+    /// never published on their own: if the pipeline stops first, their documents are
+    /// redelivered and the staged splits are garbage collected.
+    held_back_splits: Vec<(PublishLock, SplitMetadata)>,
     counters: PublisherCounters,
 }
 
@@ -66,6 +100,7 @@ impl Publisher {
         metastore: Arc<dyn Metastore>,
         merge_planner_mailbox: Mailbox<MergePlanner>,
         garbage_collector_mailbox: Mailbox<GarbageCollector>,
+        delete_executor_mailbox: Mailbox<DeleteExecutor>,
         source_mailbox_opt: Option<Mailbox<SourceActor>>,
     ) -> Publisher {
         Publisher {
@@ -73,10 +108,126 @@ impl Publisher {
             metastore,
             merge_planner_mailbox,
             garbage_collector_mailbox,
+            delete_executor_mailbox,
             source_mailbox_opt,
+            publish_notifier_opt: None,
+            strict_ordering: false,
+            published_checkpoint_opt: None,
+            payload_guard_opt: None,
+            local_split_registry_opt: None,
+            held_back_splits: Vec::new(),
             counters: PublisherCounters::default(),
         }
     }
+
+    /// Refuses to publish a checkpoint delta that does not start exactly at the published
+    /// position of each of its partitions, even if the metastore would accept it.
+    pub fn with_strict_ordering(mut self) -> Self {
+        self.strict_ordering = true;
+        self
+    }
+
+    async fn load_published_checkpoint(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<SourceCheckpoint> {
+        let index_metadata = ctx
+            .protect_future(self.metastore.index_metadata(index_id))
+            .await
+            .context("Failed to fetch the published checkpoint.")?;
+        let published_checkpoint = index_metadata
+            .checkpoint
+            .source_checkpoint(source_id)
+            .cloned()
+            .unwrap_or_default();
+        Ok(published_checkpoint)
+    }
+
+    /// In strict ordering mode, checks that the checkpoint delta chains perfectly after the
+    /// published checkpoint. The published checkpoint is cached and advanced with the deltas
+    /// published by the publisher. It is read again from the metastore before rejecting a delta,
+    /// in case another pipeline published positions of the same partitions in the meantime.
+    async fn check_strict_ordering(
+        &mut self,
+        index_id: &str,
+        checkpoint_delta: &IndexCheckpointDelta,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<()> {
+        if !self.strict_ordering {
+            return Ok(());
+        }
+        if let Some(cached_checkpoint) = &self.published_checkpoint_opt {
+            if cached_checkpoint
+                .check_chaining(&checkpoint_delta.source_delta)
+                .is_ok()
+            {
+                return Ok(());
+            }
+        }
+        let published_checkpoint = self
+            .load_published_checkpoint(index_id, &checkpoint_delta.source_id, ctx)
+            .await?;
+        let chaining_result = published_checkpoint.check_chaining(&checkpoint_delta.source_delta);
+        self.published_checkpoint_opt = Some(published_checkpoint);
+
+        if let Err(incompatible_delta) = chaining_result {
+            error!(
+                index_id=%index_id,
+                checkpoint_delta=?checkpoint_delta,
+                partition_id=%incompatible_delta.partition_id.0,
+                expected_position=%incompatible_delta.current_position.as_str(),
+                given_position=%incompatible_delta.delta_position_from.as_str(),
+                "Checkpoint delta does not start at the published position."
+            );
+            return Err(anyhow!(incompatible_delta)
+                .context("Refusing to publish an out of order checkpoint delta."));
+        }
+        Ok(())
+    }
+
+    /// Unregisters the new splits from `local_split_registry` once they are published.
+    pub fn with_local_split_registry(mut self, local_split_registry: LocalSplitRegistry) -> Self {
+        self.local_split_registry_opt = Some(local_split_registry);
+        self
+    }
+
+    /// Notifies the sink of the notifier of the new splits once they are published.
+    pub fn with_publish_notifier(mut self, publish_notifier: PublishNotifier) -> Self {
+        self.publish_notifier_opt = Some(publish_notifier);
+        self
+    }
+
+    /// Checks the size of the checkpoint deltas and of the publish requests before publishing
+    /// the splits.
+    pub fn with_metastore_payload_guard(mut self, payload_guard: MetastorePayloadGuard) -> Self {
+        self.payload_guard_opt = Some(payload_guard);
+        self
+    }
+
+    fn check_payload_sizes(
+        &self,
+        split_ids: &[&str],
+        replaced_split_ids: &[&str],
+        checkpoint_delta_opt: Option<&IndexCheckpointDelta>,
+    ) -> anyhow::Result<()> {
+        let payload_guard = match &self.payload_guard_opt {
+            Some(payload_guard) => payload_guard,
+            None => return Ok(()),
+        };
+        let actor_name = self.publisher_type.actor_name();
+        if let Some(checkpoint_delta) = checkpoint_delta_opt {
+            payload_guard.check_checkpoint_delta(checkpoint_delta, actor_name)?;
+        }
+        payload_guard.check_publish_request(
+            split_ids,
+            replaced_split_ids,
+            checkpoint_delta_opt,
+            actor_name,
+        )?;
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -96,7 +247,7 @@ impl Actor for Publisher {
         _exit_status: &quickwit_actors::ActorExitStatus,
         ctx: &ActorContext<Self>,
     ) -> anyhow::Result<()> {
-        // The `garbage_collector` actor runs for ever.
+        // The `garbage_collector` and `delete_executor` actors run for ever.
         // Periodically scheduling new messages for itself.
         //
         // The publisher actor being the last standing actor of the pipeline,
@@ -106,6 +257,9 @@ impl Actor for Publisher {
         let _ = ctx
             .send_exit_with_success(&self.garbage_collector_mailbox)
             .await;
+        let _ = ctx
+            .send_exit_with_success(&self.delete_executor_mailbox)
+            .await;
         let _ = ctx
             .send_exit_with_success(&self.merge_planner_mailbox)
             .await;
@@ -113,6 +267,28 @@ impl Actor for Publisher {
     }
 }
 
+#[async_trait]
+impl Handler<ReplaceNonCriticalMailboxes> for Publisher {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        message: ReplaceNonCriticalMailboxes,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        if let Some(merge_planner_mailbox) = message.merge_planner_mailbox_opt {
+            self.merge_planner_mailbox = merge_planner_mailbox;
+        }
+        if let Some(garbage_collector_mailbox) = message.garbage_collector_mailbox_opt {
+            self.garbage_collector_mailbox = garbage_collector_mailbox;
+        }
+        if let Some(delete_executor_mailbox) = message.delete_executor_mailbox_opt {
+            self.delete_executor_mailbox = delete_executor_mailbox;
+        }
+        Ok(())
+    }
+}
+
 #[async_trait]
 impl Handler<SplitUpdate> for Publisher {
     type Reply = ();
@@ -126,27 +302,99 @@ impl Handler<SplitUpdate> for Publisher {
 
         let SplitUpdate {
             index_id,
-            new_splits,
+            mut new_splits,
             replaced_split_ids,
             checkpoint_delta_opt,
+            publish_with_next_delta,
             publish_lock,
             date_of_birth,
         } = split_update;
 
+        if publish_with_next_delta {
+            info!(
+                split_ids=?new_splits.iter().map(|split| split.split_id()).collect::<Vec<_>>(),
+                "hold-back-splits"
+            );
+            self.held_back_splits.extend(
+                new_splits
+                    .into_iter()
+                    .map(|split| (publish_lock.clone(), split)),
+            );
+            return Ok(());
+        }
+        if checkpoint_delta_opt.is_some() && !self.held_back_splits.is_empty() {
+            // The splits held back are published in the same metastore transaction as the
+            // checkpoint delta covering their documents. The ones whose publish lock died since
+            // are dropped along with the rest of their workbench.
+            new_splits = self
+                .held_back_splits
+                .drain(..)
+                .filter(|(held_back_publish_lock, _)| held_back_publish_lock.is_alive())
+                .map(|(_, split)| split)
+                .chain(new_splits)
+                .collect();
+        }
+
         let split_ids: Vec<&str> = new_splits.iter().map(|split| split.split_id()).collect();
+        let num_docs: u64 = new_splits.iter().map(|split| split.num_docs as u64).sum();
 
         let replaced_split_ids_ref_vec: Vec<&str> =
             replaced_split_ids.iter().map(String::as_str).collect();
 
         if let Some(_guard) = publish_lock.acquire().await {
-            ctx.protect_future(self.metastore.publish_splits(
-                &index_id,
-                &split_ids[..],
+            self.check_payload_sizes(
+                &split_ids,
                 &replaced_split_ids_ref_vec,
-                checkpoint_delta_opt.clone(),
-            ))
-            .await
-            .context("Failed to publish splits.")?;
+                checkpoint_delta_opt.as_ref(),
+            )?;
+            if let Some(checkpoint_delta) = &checkpoint_delta_opt {
+                self.check_strict_ordering(&index_id, checkpoint_delta, ctx)
+                    .await?;
+            }
+            // The notification is recorded before publishing the splits so that it is not lost
+            // if the node crashes right after.
+            let pending_notification_opt = match &self.publish_notifier_opt {
+                Some(publish_notifier) if !new_splits.is_empty() => Some(
+                    publish_notifier
+                        .prepare(&new_splits)
+                        .await
+                        .context("Failed to record publish notification.")?,
+                ),
+                _ => None,
+            };
+            let publish_result = ctx
+                .protect_future(self.metastore.publish_splits(
+                    &index_id,
+                    &split_ids[..],
+                    &replaced_split_ids_ref_vec,
+                    checkpoint_delta_opt.clone(),
+                ))
+                .await;
+            if let Err(MetastoreError::CheckpointConflict(incompatible_delta)) = &publish_result {
+                error!(
+                    index_id=%index_id,
+                    split_ids=?split_ids,
+                    checkpoint_delta=?checkpoint_delta_opt,
+                    partition_id=%incompatible_delta.partition_id.0,
+                    expected_position=%incompatible_delta.current_position.as_str(),
+                    given_position=%incompatible_delta.delta_position_from.as_str(),
+                    "Metastore rejected an overlapping checkpoint delta. Publishing these splits will never succeed."
+                );
+                self.counters.incompatible_checkpoint_delta_opt = Some(incompatible_delta.clone());
+            }
+            if let (Some(publish_notifier), Some(pending_notification)) =
+                (&self.publish_notifier_opt, pending_notification_opt)
+            {
+                if publish_result.is_ok() {
+                    publish_notifier
+                        .commit(pending_notification)
+                        .await
+                        .context("Failed to queue publish notification.")?;
+                } else {
+                    publish_notifier.abort(pending_notification).await;
+                }
+            }
+            publish_result.context("Failed to publish splits.")?;
         } else {
             // TODO: Remove the junk right away?
             info!(
@@ -155,7 +403,19 @@ impl Handler<SplitUpdate> for Publisher {
             );
             return Ok(());
         }
+        // From now on, the splits are searched from the storage like any other published split.
+        if let Some(local_split_registry) = &self.local_split_registry_opt {
+            local_split_registry.unregister(&split_ids);
+        }
         info!(new_splits=?split_ids, tts=%date_of_birth.elapsed().as_secs_f32(), checkpoint_delta=?checkpoint_delta_opt, "publish-new-splits");
+        if let Some(checkpoint_delta) = &checkpoint_delta_opt {
+            self.counters
+                .published_watermark
+                .advance_with_delta(&checkpoint_delta.source_delta);
+            if let Some(published_checkpoint) = &mut self.published_checkpoint_opt {
+                published_checkpoint.advance_with_delta(&checkpoint_delta.source_delta);
+            }
+        }
         if let Some(source_mailbox) = self.source_mailbox_opt.as_ref() {
             if let Some(checkpoint) = checkpoint_delta_opt {
                 // We voluntarily do not log anything here.
@@ -182,6 +442,7 @@ impl Handler<SplitUpdate> for Publisher {
             .await;
         if replaced_split_ids.is_empty() {
             self.counters.num_published_splits += 1;
+            self.counters.num_published_docs += num_docs;
         } else {
             self.counters.num_replace_operations += 1;
         }
@@ -194,14 +455,14 @@ impl Handler<SplitUpdate> for Publisher {
 mod tests {
     use std::time::Instant;
 
-    use quickwit_actors::{create_test_mailbox, Universe};
+    use quickwit_actors::{create_test_mailbox, ActorExitStatus, Universe};
+    use quickwit_directories::LocalSplit;
     use quickwit_metastore::checkpoint::{
         IndexCheckpointDelta, PartitionId, Position, SourceCheckpoint, SourceCheckpointDelta,
     };
-    use quickwit_metastore::{MockMetastore, SplitMetadata};
+    use quickwit_metastore::{IndexMetadata, MockMetastore};
 
     use super::*;
-    use crate::models::PublishLock;
 
     #[tokio::test]
     async fn test_publisher_publish_operation() {
@@ -222,6 +483,7 @@ mod tests {
             .returning(|_, _, _, _| Ok(()));
         let (merge_planner_mailbox, merge_planner_inbox) = create_test_mailbox();
         let (garbage_collector_mailbox, _garbage_collector_inbox) = create_test_mailbox();
+        let (delete_executor_mailbox, _delete_executor_inbox) = create_test_mailbox();
 
         let (source_mailbox, source_inbox) = create_test_mailbox();
 
@@ -230,6 +492,7 @@ mod tests {
             Arc::new(mock_metastore),
             merge_planner_mailbox,
             garbage_collector_mailbox,
+            delete_executor_mailbox,
             Some(source_mailbox),
         );
         let universe = Universe::new();
@@ -240,9 +503,11 @@ mod tests {
                 index_id: "index".to_string(),
                 new_splits: vec![SplitMetadata {
                     split_id: "split".to_string(),
+                    num_docs: 2,
                     ..Default::default()
                 }],
                 replaced_split_ids: Vec::new(),
+                publish_with_next_delta: false,
                 checkpoint_delta_opt: Some(IndexCheckpointDelta {
                     source_id: "source".to_string(),
                     source_delta: SourceCheckpointDelta::from(1..3),
@@ -255,6 +520,11 @@ mod tests {
 
         let publisher_observation = publisher_handle.process_pending_and_observe().await.state;
         assert_eq!(publisher_observation.num_published_splits, 1);
+        assert_eq!(publisher_observation.num_published_docs, 2);
+        assert_eq!(
+            publisher_observation.published_watermark,
+            SourceCheckpointDelta::from(1..3).get_source_checkpoint()
+        );
 
         let suggest_truncate_checkpoints: Vec<SourceCheckpoint> = source_inbox
             .drain_for_test_typed::<SuggestTruncate>()
@@ -275,6 +545,80 @@ mod tests {
         assert_eq!(merger_msgs[0].new_splits.len(), 1);
     }
 
+    #[tokio::test]
+    async fn test_publisher_publishes_held_back_splits_with_next_delta() {
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_publish_splits()
+            .withf(
+                |index_id, split_ids, replaced_split_ids, checkpoint_delta_opt| {
+                    index_id == "index"
+                        && split_ids[..] == ["held-back-split", "split"]
+                        && replaced_split_ids.is_empty()
+                        && checkpoint_delta_opt.as_ref().unwrap().source_delta
+                            == SourceCheckpointDelta::from(1..3)
+                },
+            )
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+        let (merge_planner_mailbox, merge_planner_inbox) = create_test_mailbox();
+        let (garbage_collector_mailbox, _garbage_collector_inbox) = create_test_mailbox();
+        let (delete_executor_mailbox, _delete_executor_inbox) = create_test_mailbox();
+
+        let publisher = Publisher::new(
+            PublisherType::MainPublisher,
+            Arc::new(mock_metastore),
+            merge_planner_mailbox,
+            garbage_collector_mailbox,
+            delete_executor_mailbox,
+            None,
+        );
+        let universe = Universe::new();
+        let (publisher_mailbox, publisher_handle) = universe.spawn_actor(publisher).spawn();
+
+        let publish_lock = PublishLock::default();
+        let dead_publish_lock = PublishLock::default();
+        dead_publish_lock.kill().await;
+        for (split_id, publish_lock) in [
+            ("held-back-split", publish_lock.clone()),
+            ("dead-held-back-split", dead_publish_lock),
+        ] {
+            publisher_mailbox
+                .send_message(SplitUpdate {
+                    index_id: "index".to_string(),
+                    new_splits: vec![SplitMetadata::for_test(split_id.to_string())],
+                    replaced_split_ids: Vec::new(),
+                    checkpoint_delta_opt: None,
+                    publish_with_next_delta: true,
+                    publish_lock,
+                    date_of_birth: Instant::now(),
+                })
+                .await
+                .unwrap();
+        }
+        let publisher_observation = publisher_handle.process_pending_and_observe().await.state;
+        assert_eq!(publisher_observation.num_published_splits, 0);
+
+        publisher_mailbox
+            .send_message(SplitUpdate {
+                index_id: "index".to_string(),
+                new_splits: vec![SplitMetadata::for_test("split".to_string())],
+                replaced_split_ids: Vec::new(),
+                checkpoint_delta_opt: Some(IndexCheckpointDelta::for_test("source", 1..3)),
+                publish_with_next_delta: false,
+                publish_lock,
+                date_of_birth: Instant::now(),
+            })
+            .await
+            .unwrap();
+        let publisher_observation = publisher_handle.process_pending_and_observe().await.state;
+        assert_eq!(publisher_observation.num_published_splits, 1);
+
+        let merge_planner_msgs = merge_planner_inbox.drain_for_test_typed::<NewSplits>();
+        assert_eq!(merge_planner_msgs.len(), 1);
+        assert_eq!(merge_planner_msgs[0].new_splits.len(), 2);
+    }
+
     #[tokio::test]
     async fn test_publisher_replace_operation() {
         let mut mock_metastore = MockMetastore::default();
@@ -292,11 +636,13 @@ mod tests {
             .returning(|_, _, _, _| Ok(()));
         let (merge_planner_mailbox, merge_planner_inbox) = create_test_mailbox();
         let (garbage_collector_mailbox, _garbage_collector_inbox) = create_test_mailbox();
+        let (delete_executor_mailbox, _delete_executor_inbox) = create_test_mailbox();
         let publisher = Publisher::new(
             PublisherType::MainPublisher,
             Arc::new(mock_metastore),
             merge_planner_mailbox,
             garbage_collector_mailbox,
+            delete_executor_mailbox,
             None,
         );
         let universe = Universe::new();
@@ -309,6 +655,7 @@ mod tests {
             }],
             replaced_split_ids: vec!["split1".to_string(), "split2".to_string()],
             checkpoint_delta_opt: None,
+            publish_with_next_delta: false,
             publish_lock: PublishLock::default(),
             date_of_birth: Instant::now(),
         };
@@ -324,18 +671,194 @@ mod tests {
         assert_eq!(merge_planner_msgs[0].new_splits.len(), 1);
     }
 
+    #[tokio::test]
+    async fn test_publisher_unregisters_published_local_splits() {
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_publish_splits()
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+        let (merge_planner_mailbox, _merge_planner_inbox) = create_test_mailbox();
+        let (garbage_collector_mailbox, _garbage_collector_inbox) = create_test_mailbox();
+        let (delete_executor_mailbox, _delete_executor_inbox) = create_test_mailbox();
+        let local_split_registry = LocalSplitRegistry::default();
+        for split_id in ["split-1", "split-2"] {
+            let index = tantivy::Index::create_in_ram(tantivy::schema::Schema::builder().build());
+            let local_split = LocalSplit::new(
+                "index".to_string(),
+                split_id.to_string(),
+                None,
+                index,
+                || true,
+            )
+            .unwrap();
+            local_split_registry.register(local_split);
+        }
+        let publisher = Publisher::new(
+            PublisherType::MainPublisher,
+            Arc::new(mock_metastore),
+            merge_planner_mailbox,
+            garbage_collector_mailbox,
+            delete_executor_mailbox,
+            None,
+        )
+        .with_local_split_registry(local_split_registry.clone());
+        let universe = Universe::new();
+        let (publisher_mailbox, publisher_handle) = universe.spawn_actor(publisher).spawn();
+        publisher_mailbox
+            .send_message(SplitUpdate {
+                index_id: "index".to_string(),
+                new_splits: vec![SplitMetadata {
+                    split_id: "split-1".to_string(),
+                    ..Default::default()
+                }],
+                replaced_split_ids: Vec::new(),
+                checkpoint_delta_opt: None,
+                publish_with_next_delta: false,
+                publish_lock: PublishLock::default(),
+                date_of_birth: Instant::now(),
+            })
+            .await
+            .unwrap();
+        let publisher_observation = publisher_handle.process_pending_and_observe().await.state;
+        assert_eq!(publisher_observation.num_published_splits, 1);
+        assert!(local_split_registry.get("split-1").is_none());
+        assert!(local_split_registry.get("split-2").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_publisher_fails_on_incompatible_checkpoint_delta() {
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_publish_splits()
+            .times(1)
+            .returning(|_, _, _, _| {
+                Err(MetastoreError::CheckpointConflict(
+                    IncompatibleCheckpointDelta {
+                        partition_id: PartitionId::default(),
+                        current_position: Position::from(10u64),
+                        delta_position_from: Position::from(0u64),
+                    },
+                ))
+            });
+        let (merge_planner_mailbox, merge_planner_inbox) = create_test_mailbox();
+        let (garbage_collector_mailbox, _garbage_collector_inbox) = create_test_mailbox();
+        let (delete_executor_mailbox, _delete_executor_inbox) = create_test_mailbox();
+        let (source_mailbox, source_inbox) = create_test_mailbox();
+        let publisher = Publisher::new(
+            PublisherType::MainPublisher,
+            Arc::new(mock_metastore),
+            merge_planner_mailbox,
+            garbage_collector_mailbox,
+            delete_executor_mailbox,
+            Some(source_mailbox),
+        );
+        let universe = Universe::new();
+        let (publisher_mailbox, publisher_handle) = universe.spawn_actor(publisher).spawn();
+        publisher_mailbox
+            .send_message(SplitUpdate {
+                index_id: "index".to_string(),
+                new_splits: vec![SplitMetadata::for_test("split".to_string())],
+                replaced_split_ids: Vec::new(),
+                publish_with_next_delta: false,
+                checkpoint_delta_opt: Some(IndexCheckpointDelta {
+                    source_id: "source".to_string(),
+                    source_delta: SourceCheckpointDelta::from(1..3),
+                }),
+                publish_lock: PublishLock::default(),
+                date_of_birth: Instant::now(),
+            })
+            .await
+            .unwrap();
+        let (exit_status, publisher_counters) = publisher_handle.join().await;
+        assert!(matches!(exit_status, ActorExitStatus::Failure(_)));
+        assert_eq!(publisher_counters.num_published_splits, 0);
+        assert_eq!(
+            publisher_counters.incompatible_checkpoint_delta_opt,
+            Some(IncompatibleCheckpointDelta {
+                partition_id: PartitionId::default(),
+                current_position: Position::from(10u64),
+                delta_position_from: Position::from(0u64),
+            })
+        );
+        assert!(publisher_counters.published_watermark.is_empty());
+        assert!(source_inbox
+            .drain_for_test_typed::<SuggestTruncate>()
+            .is_empty());
+        assert!(merge_planner_inbox
+            .drain_for_test_typed::<NewSplits>()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_publisher_strict_ordering() {
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_index_metadata()
+            .times(2)
+            .returning(|index_id| {
+                let mut index_metadata =
+                    IndexMetadata::for_test(index_id, "ram:///indexes/test-index");
+                index_metadata
+                    .checkpoint
+                    .try_apply_delta(IndexCheckpointDelta::for_test("source", 0..3))
+                    .unwrap();
+                Ok(index_metadata)
+            });
+        mock_metastore
+            .expect_publish_splits()
+            .withf(|_, split_ids, _, _| split_ids[..] == ["split-1"])
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+        let (merge_planner_mailbox, _merge_planner_inbox) = create_test_mailbox();
+        let (garbage_collector_mailbox, _garbage_collector_inbox) = create_test_mailbox();
+        let (delete_executor_mailbox, _delete_executor_inbox) = create_test_mailbox();
+        let publisher = Publisher::new(
+            PublisherType::MainPublisher,
+            Arc::new(mock_metastore),
+            merge_planner_mailbox,
+            garbage_collector_mailbox,
+            delete_executor_mailbox,
+            None,
+        )
+        .with_strict_ordering();
+        let universe = Universe::new();
+        let (publisher_mailbox, publisher_handle) = universe.spawn_actor(publisher).spawn();
+
+        for (split_id, pos_range) in [("split-1", 3..5), ("split-2", 6..8)] {
+            publisher_mailbox
+                .send_message(SplitUpdate {
+                    index_id: "test-index".to_string(),
+                    new_splits: vec![SplitMetadata::for_test(split_id.to_string())],
+                    replaced_split_ids: Vec::new(),
+                    checkpoint_delta_opt: Some(IndexCheckpointDelta::for_test("source", pos_range)),
+                    publish_with_next_delta: false,
+                    publish_lock: PublishLock::default(),
+                    date_of_birth: Instant::now(),
+                })
+                .await
+                .unwrap();
+        }
+        // The metastore would accept the gap between the two deltas, the publisher does not.
+        let (exit_status, publisher_counters) = publisher_handle.join().await;
+        assert!(matches!(exit_status, ActorExitStatus::Failure(_)));
+        assert_eq!(publisher_counters.num_published_splits, 1);
+    }
+
     #[tokio::test]
     async fn publisher_acquires_publish_lock() {
         let mut mock_metastore = MockMetastore::default();
         mock_metastore.expect_publish_splits().never();
         let (merge_planner_mailbox, merge_planner_inbox) = create_test_mailbox();
         let (garbage_collector_mailbox, _garbage_collector_inbox) = create_test_mailbox();
+        let (delete_executor_mailbox, _delete_executor_inbox) = create_test_mailbox();
 
         let publisher = Publisher::new(
             PublisherType::MainPublisher,
             Arc::new(mock_metastore),
             merge_planner_mailbox,
             garbage_collector_mailbox,
+            delete_executor_mailbox,
             None,
         );
         let universe = Universe::new();
@@ -350,6 +873,7 @@ mod tests {
                 new_splits: vec![SplitMetadata::for_test("test-split".to_string())],
                 replaced_split_ids: Vec::new(),
                 checkpoint_delta_opt: None,
+                publish_with_next_delta: false,
                 publish_lock,
                 date_of_birth: Instant::now(),
             })
@@ -19,10 +19,14 @@
 
 use std::fmt::Debug;
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use async_trait::async_trait;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox};
+use quickwit_metastore::checkpoint::{SourceCheckpoint, SourceCheckpointDelta};
 use tokio::sync::oneshot;
+use tracing::error;
+
+use crate::models::SplitUpdate;
 
 /// The sequencer serves as a proxy to another actor,
 /// delivering message in a specific order.
@@ -35,13 +39,48 @@ use tokio::sync::oneshot;
 ///
 /// It is used by the uploader actor, to run uploads concurrently and yet
 /// ensures that publish message are send in the right order.
+///
+/// In strict ordering mode, the sequencer also keeps track, for each partition, of the position
+/// reached by the messages it forwarded, and fails rather than forwarding a message whose
+/// checkpoint delta does not start exactly at that position.
 pub struct Sequencer<A: Actor> {
     mailbox: Mailbox<A>,
+    strict_ordering: bool,
+    forwarded_positions: SourceCheckpoint,
 }
 
 impl<A: Actor> Sequencer<A> {
     pub fn new(mailbox: Mailbox<A>) -> Self {
-        Sequencer { mailbox }
+        Sequencer {
+            mailbox,
+            strict_ordering: false,
+            forwarded_positions: SourceCheckpoint::default(),
+        }
+    }
+
+    /// Checks that the checkpoint deltas of the forwarded messages chain perfectly for each
+    /// partition.
+    pub fn with_strict_ordering(mut self) -> Self {
+        self.strict_ordering = true;
+        self
+    }
+}
+
+/// Messages forwarded by a [`Sequencer`].
+pub trait SequencedMessage {
+    /// Returns the checkpoint delta the message is meant to publish, if any.
+    fn checkpoint_delta_opt(&self) -> Option<&SourceCheckpointDelta>;
+}
+
+impl SequencedMessage for SplitUpdate {
+    fn checkpoint_delta_opt(&self) -> Option<&SourceCheckpointDelta> {
+        // The splits of a dead publish lock are never published.
+        if self.publish_lock.is_dead() {
+            return None;
+        }
+        self.checkpoint_delta_opt
+            .as_ref()
+            .map(|checkpoint_delta| &checkpoint_delta.source_delta)
     }
 }
 
@@ -65,7 +104,7 @@ impl<A, M> Handler<oneshot::Receiver<SequencerCommand<M>>> for Sequencer<A>
 where
     A: Actor,
     A: Handler<M>,
-    M: Send + Sync + 'static + std::fmt::Debug,
+    M: SequencedMessage + Send + Sync + 'static + std::fmt::Debug,
 {
     type Reply = ();
 
@@ -78,21 +117,75 @@ where
             .protect_future(message)
             .await
             .context("Failed to receive command from uploader.")?;
-        if let SequencerCommand::Proceed(msg) = command {
-            ctx.send_message(&self.mailbox, msg)
-                .await
-                .context("Failed to send message to publisher.")?;
+        let msg = match command {
+            SequencerCommand::Proceed(msg) => msg,
+            SequencerCommand::Discard => {
+                // The positions covered by the discarded message are unknown, so we start
+                // tracking the partitions afresh. The publisher still checks the deltas against
+                // the published checkpoint.
+                self.forwarded_positions = SourceCheckpoint::default();
+                return Ok(());
+            }
+        };
+        if self.strict_ordering {
+            if let Some(checkpoint_delta) = msg.checkpoint_delta_opt() {
+                if let Err(incompatible_delta) =
+                    self.forwarded_positions.check_chaining(checkpoint_delta)
+                {
+                    error!(
+                        partition_id=%incompatible_delta.partition_id.0,
+                        expected_position=%incompatible_delta.current_position.as_str(),
+                        given_position=%incompatible_delta.delta_position_from.as_str(),
+                        "Checkpoint delta is out of order."
+                    );
+                    return Err(anyhow!(incompatible_delta)
+                        .context("Refusing to forward an out of order checkpoint delta.")
+                        .into());
+                }
+                self.forwarded_positions
+                    .advance_with_delta(checkpoint_delta);
+            } else {
+                self.forwarded_positions = SourceCheckpoint::default();
+            }
         }
+        ctx.send_message(&self.mailbox, msg)
+            .await
+            .context("Failed to send message to publisher.")?;
         Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use quickwit_actors::Universe;
+    use quickwit_actors::{KillSwitch, Universe};
+    use quickwit_metastore::checkpoint::{PartitionId, Position};
 
     use super::*;
 
+    impl SequencedMessage for usize {
+        fn checkpoint_delta_opt(&self) -> Option<&SourceCheckpointDelta> {
+            None
+        }
+    }
+
+    #[derive(Debug)]
+    struct SequencedDelta(usize, SourceCheckpointDelta);
+
+    impl SequencedMessage for SequencedDelta {
+        fn checkpoint_delta_opt(&self) -> Option<&SourceCheckpointDelta> {
+            Some(&self.1)
+        }
+    }
+
+    fn sequenced_delta(msg: usize, partition: &str, from: u64, to: u64) -> SequencedDelta {
+        let checkpoint_delta = SourceCheckpointDelta::from_partition_delta(
+            PartitionId::from(partition),
+            Position::from(from),
+            Position::from(to),
+        );
+        SequencedDelta(msg, checkpoint_delta)
+    }
+
     #[derive(Default)]
     struct SequencerTestActor {
         messages: Vec<usize>,
@@ -120,6 +213,20 @@ mod tests {
         }
     }
 
+    #[async_trait]
+    impl Handler<SequencedDelta> for SequencerTestActor {
+        type Reply = ();
+
+        async fn handle(
+            &mut self,
+            message: SequencedDelta,
+            _ctx: &ActorContext<Self>,
+        ) -> Result<(), ActorExitStatus> {
+            self.messages.push(message.0);
+            Ok(())
+        }
+    }
+
     #[tokio::test]
     async fn test_sequencer() {
         let universe = Universe::new();
@@ -146,4 +253,42 @@ mod tests {
         let (sequencer_exit_status, _) = sequencer_handle.join().await;
         assert!(matches!(sequencer_exit_status, ActorExitStatus::Success));
     }
+    #[tokio::test]
+    async fn test_sequencer_strict_ordering() {
+        let universe = Universe::new();
+        let test_actor = SequencerTestActor::default();
+        let (test_mailbox, test_handle) = universe.spawn_actor(test_actor).spawn();
+        let sequencer = Sequencer::new(test_mailbox).with_strict_ordering();
+        let (sequencer_mailbox, sequencer_handle) = universe
+            .spawn_actor(sequencer)
+            .set_kill_switch(KillSwitch::default())
+            .spawn();
+        let (fut_tx_1, fut_rx_1) = oneshot::channel();
+        let (fut_tx_2, fut_rx_2) = oneshot::channel();
+        let (fut_tx_3, fut_rx_3) = oneshot::channel();
+        let (fut_tx_4, fut_rx_4) = oneshot::channel();
+        sequencer_mailbox.send_message(fut_rx_1).await.unwrap();
+        sequencer_mailbox.send_message(fut_rx_2).await.unwrap();
+        sequencer_mailbox.send_message(fut_rx_3).await.unwrap();
+        sequencer_mailbox.send_message(fut_rx_4).await.unwrap();
+        fut_tx_4
+            .send(SequencerCommand::Proceed(sequenced_delta(4, "a", 20, 30)))
+            .unwrap();
+        fut_tx_3
+            .send(SequencerCommand::Proceed(sequenced_delta(3, "b", 0, 5)))
+            .unwrap();
+        fut_tx_2
+            .send(SequencerCommand::Proceed(sequenced_delta(2, "a", 10, 15)))
+            .unwrap();
+        fut_tx_1
+            .send(SequencerCommand::Proceed(sequenced_delta(1, "a", 0, 10)))
+            .unwrap();
+        std::mem::drop(sequencer_mailbox);
+        // The delta of the fourth message skips the positions of partition `a` between 15 and 20.
+        let (sequencer_exit_status, _) = sequencer_handle.join().await;
+        assert!(matches!(sequencer_exit_status, ActorExitStatus::Failure(_)));
+        let (exit_status, last_state) = test_handle.join().await;
+        assert!(matches!(exit_status, ActorExitStatus::Success));
+        assert_eq!(&last_state, &[1, 2, 3]);
+    }
 }
@@ -0,0 +1,464 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Sharding of the documents of a pipeline across several indexers.
+//!
+//! The indexer receiving the batches of the source acts as a router: it routes each document to
+//! one of the shard indexers by hash of a routing field and decides when the shards commit. All
+//! the shards commit at the same position of their stream of batches, so the packager can pair
+//! up the split batches emitted by the shards for the same commit and publish them along with
+//! the checkpoint delta of the commit in a single metastore call.
+
+use std::collections::VecDeque;
+use std::mem;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use quickwit_actors::{ActorContext, ActorExitStatus, Mailbox};
+use quickwit_config::{IndexingSettings, ShardingSettings};
+use quickwit_doc_mapper::RoutingExpr;
+use quickwit_metastore::Metastore;
+use serde_json::Value as JsonValue;
+use tracing::info;
+use ulid::Ulid;
+
+use crate::actors::indexer::{CommitShard, CommitTimeout, CommitTrigger};
+use crate::actors::{Indexer, Packager};
+use crate::models::{IndexedSplitBatch, NewPublishLock, PublishLock, RawDocBatch, ShardSplitBatch};
+
+/// Flag raised by the shard indexers of a pipeline when their workbench exceeds the workbench
+/// memory limit. Shards only commit on the demand of the router, which checks the flag after
+/// routing each batch and then commits all the shards.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct MemoryLimitReports {
+    memory_limit_exceeded: Arc<AtomicBool>,
+}
+
+impl MemoryLimitReports {
+    pub fn report(&self) {
+        self.memory_limit_exceeded.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns whether a shard reported the memory limit since the last call, and clears the flag.
+    pub fn take(&self) -> bool {
+        self.memory_limit_exceeded.swap(false, Ordering::SeqCst)
+    }
+}
+
+/// Routes the documents of a pipeline to the shard indexers and triggers their commits.
+pub(crate) struct DocRouter {
+    routing_expr: RoutingExpr,
+    shard_mailboxes: Vec<Mailbox<Indexer>>,
+    split_num_docs_target: u64,
+    commit_timeout: Duration,
+    /// Number of docs routed to each shard since the last commit.
+    num_docs_since_commit: Vec<u64>,
+    /// ID of the ongoing commit period, used to ignore the timeouts of the previous periods.
+    commit_period_id_opt: Option<Ulid>,
+    memory_limit_reports: MemoryLimitReports,
+}
+
+impl DocRouter {
+    pub fn new(
+        sharding_settings: &ShardingSettings,
+        indexing_settings: &IndexingSettings,
+        shard_mailboxes: Vec<Mailbox<Indexer>>,
+        memory_limit_reports: MemoryLimitReports,
+    ) -> anyhow::Result<Self> {
+        let routing_expr = RoutingExpr::from_str(&sharding_settings.routing_field)?;
+        let num_shards = shard_mailboxes.len();
+        Ok(Self {
+            routing_expr,
+            shard_mailboxes,
+            split_num_docs_target: indexing_settings.split_num_docs_target as u64,
+            commit_timeout: indexing_settings.commit_timeout(),
+            num_docs_since_commit: vec![0; num_shards],
+            commit_period_id_opt: None,
+            memory_limit_reports,
+        })
+    }
+
+    /// Returns the ordinal of the shard indexing `doc_json`. Documents that are not JSON objects
+    /// are routed to the first shard, which rejects them.
+    fn shard_ord(&self, doc_json: &str) -> usize {
+        let json_obj: serde_json::Map<String, JsonValue> = match serde_json::from_str(doc_json) {
+            Ok(json_obj) => json_obj,
+            Err(_) => return 0,
+        };
+        (self.routing_expr.eval_hash(&json_obj) % self.shard_mailboxes.len() as u64) as usize
+    }
+
+    /// Splits the batch into one batch per shard. The first shard receives the checkpoint deltas
+    /// of all the batches, so that the watermarks of the pipeline are carried by a single shard.
+    pub async fn route_batch(
+        &mut self,
+        batch: RawDocBatch,
+        ctx: &ActorContext<Indexer>,
+    ) -> Result<(), ActorExitStatus> {
+        let mut shard_docs: Vec<Vec<String>> = vec![Vec::new(); self.shard_mailboxes.len()];
+        for doc_json in batch.docs {
+            let shard_ord = self.shard_ord(&doc_json);
+            shard_docs[shard_ord].push(doc_json);
+        }
+        if self.commit_period_id_opt.is_none() {
+            let commit_period_id = Ulid::new();
+            ctx.schedule_self_msg(
+                self.commit_timeout,
+                CommitTimeout {
+                    workbench_id: commit_period_id,
+                },
+            )
+            .await;
+            self.commit_period_id_opt = Some(commit_period_id);
+        }
+        let mut checkpoint_delta = batch.checkpoint_delta;
+        for (shard_ord, docs) in shard_docs.into_iter().enumerate() {
+            if shard_ord > 0 && docs.is_empty() {
+                continue;
+            }
+            self.num_docs_since_commit[shard_ord] += docs.len() as u64;
+            let shard_batch = RawDocBatch {
+                docs,
+                checkpoint_delta: mem::take(&mut checkpoint_delta),
+            };
+            ctx.send_message(&self.shard_mailboxes[shard_ord], shard_batch)
+                .await?;
+        }
+        let max_num_docs_since_commit = self
+            .num_docs_since_commit
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or(0);
+        if max_num_docs_since_commit >= self.split_num_docs_target {
+            self.commit_shards(CommitTrigger::NumDocsLimit, ctx).await?;
+        } else if self.memory_limit_reports.take() {
+            self.commit_shards(CommitTrigger::MemoryLimit, ctx).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn handle_commit_timeout(
+        &mut self,
+        commit_timeout: CommitTimeout,
+        ctx: &ActorContext<Indexer>,
+    ) -> Result<(), ActorExitStatus> {
+        if self.commit_period_id_opt != Some(commit_timeout.workbench_id) {
+            return Ok(());
+        }
+        self.commit_shards(CommitTrigger::Timeout, ctx).await
+    }
+
+    /// Forwards the new publish lock to the shards, which drop their workbench.
+    pub async fn forward_publish_lock(
+        &mut self,
+        publish_lock: PublishLock,
+        ctx: &ActorContext<Indexer>,
+    ) -> Result<(), ActorExitStatus> {
+        for shard_mailbox in &self.shard_mailboxes {
+            ctx.send_message(shard_mailbox, NewPublishLock(publish_lock.clone()))
+                .await?;
+        }
+        self.reset_commit_period();
+        Ok(())
+    }
+
+    async fn commit_shards(
+        &mut self,
+        commit_trigger: CommitTrigger,
+        ctx: &ActorContext<Indexer>,
+    ) -> Result<(), ActorExitStatus> {
+        info!(commit_trigger=?commit_trigger, num_docs=?self.num_docs_since_commit, "commit-shards");
+        for shard_mailbox in &self.shard_mailboxes {
+            ctx.send_message(shard_mailbox, CommitShard { commit_trigger })
+                .await?;
+        }
+        self.reset_commit_period();
+        Ok(())
+    }
+
+    fn reset_commit_period(&mut self) {
+        self.num_docs_since_commit.fill(0);
+        self.commit_period_id_opt = None;
+        // The workbenches reported by the shards are committed or dropped along with the period.
+        self.memory_limit_reports.take();
+    }
+}
+
+/// Pairs up the split batches emitted by the shards for the same commit.
+pub(crate) struct ShardBatchCombiner {
+    index_id: String,
+    metastore: Arc<dyn Metastore>,
+    /// Batches received from each shard and not combined yet.
+    pending_batches: Vec<VecDeque<IndexedSplitBatch>>,
+}
+
+impl ShardBatchCombiner {
+    pub fn new(index_id: String, num_shards: usize, metastore: Arc<dyn Metastore>) -> Self {
+        Self {
+            index_id,
+            metastore,
+            pending_batches: (0..num_shards).map(|_| VecDeque::new()).collect(),
+        }
+    }
+
+    /// Queues the batch of a shard and returns the combined batch of the oldest commit once all
+    /// the shards have sent their batch for it.
+    pub fn push(
+        &mut self,
+        shard_batch: ShardSplitBatch,
+    ) -> anyhow::Result<Option<IndexedSplitBatch>> {
+        self.pending_batches
+            .get_mut(shard_batch.shard_ord)
+            .with_context(|| format!("Unknown shard `{}`.", shard_batch.shard_ord))?
+            .push_back(shard_batch.batch);
+        if self
+            .pending_batches
+            .iter()
+            .any(|shard_batches| shard_batches.is_empty())
+        {
+            return Ok(None);
+        }
+        let mut shard_batches = self.pending_batches.iter_mut().map(|shard_batches| {
+            shard_batches
+                .pop_front()
+                .expect("Queue should not be empty.")
+        });
+        let mut combined_batch = shard_batches
+            .next()
+            .expect("There should be one shard or more.");
+        for shard_batch in shard_batches {
+            combined_batch.splits.extend(shard_batch.splits);
+            combined_batch.checkpoint_delta = match (
+                combined_batch.checkpoint_delta.take(),
+                shard_batch.checkpoint_delta,
+            ) {
+                (Some(mut checkpoint_delta), Some(shard_checkpoint_delta)) => {
+                    checkpoint_delta
+                        .source_delta
+                        .extend(shard_checkpoint_delta.source_delta)
+                        .context("Checkpoint deltas of the shards do not chain.")?;
+                    Some(checkpoint_delta)
+                }
+                (checkpoint_delta_opt, shard_checkpoint_delta_opt) => {
+                    checkpoint_delta_opt.or(shard_checkpoint_delta_opt)
+                }
+            };
+            combined_batch.date_of_birth =
+                combined_batch.date_of_birth.min(shard_batch.date_of_birth);
+        }
+        Ok(Some(combined_batch))
+    }
+
+    /// Publishes the checkpoint delta of a commit that yielded no split, so that the faulty
+    /// documents of the commit are not processed again.
+    pub async fn publish_checkpoint_delta(
+        &self,
+        batch: IndexedSplitBatch,
+        ctx: &ActorContext<Packager>,
+    ) -> anyhow::Result<()> {
+        let checkpoint_delta = match batch.checkpoint_delta {
+            Some(checkpoint_delta) if !checkpoint_delta.is_empty() => checkpoint_delta,
+            _ => return Ok(()),
+        };
+        if let Some(_guard) = batch.publish_lock.acquire().await {
+            ctx.protect_future(self.metastore.publish_splits(
+                &self.index_id,
+                &[],
+                &[],
+                Some(checkpoint_delta),
+            ))
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to update the checkpoint of index `{}` after a commit yielding no \
+                     split.",
+                    self.index_id
+                )
+            })?;
+        } else {
+            info!("Splits' publish lock is dead.");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use quickwit_actors::{create_test_mailbox, Inbox, Universe};
+    use quickwit_metastore::checkpoint::{IndexCheckpointDelta, SourceCheckpointDelta};
+    use quickwit_metastore::MockMetastore;
+    use tokio::sync::watch;
+
+    use super::*;
+
+    fn shard_split_batch(
+        shard_ord: usize,
+        checkpoint_delta_opt: Option<IndexCheckpointDelta>,
+    ) -> ShardSplitBatch {
+        ShardSplitBatch {
+            shard_ord,
+            batch: IndexedSplitBatch {
+                splits: Vec::new(),
+                checkpoint_delta: checkpoint_delta_opt,
+                publish_with_next_delta: false,
+                publish_lock: PublishLock::default(),
+                date_of_birth: Instant::now(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_doc_router_shard_ord() {
+        let sharding_settings = ShardingSettings {
+            num_shards: 4,
+            routing_field: "tenant_id".to_string(),
+        };
+        let shard_mailboxes = (0..4).map(|_| create_test_mailbox::<Indexer>().0).collect();
+        let doc_router = DocRouter::new(
+            &sharding_settings,
+            &IndexingSettings::default(),
+            shard_mailboxes,
+            MemoryLimitReports::default(),
+        )
+        .unwrap();
+        let shard_ords: Vec<usize> = (0..32)
+            .map(|tenant_id| {
+                let doc_json = format!(r#"{{"tenant_id": "tenant-{}", "body": "foo"}}"#, tenant_id);
+                let shard_ord = doc_router.shard_ord(&doc_json);
+                assert!(shard_ord < 4);
+                let other_doc_json =
+                    format!(r#"{{"tenant_id": "tenant-{}", "body": "bar"}}"#, tenant_id);
+                assert_eq!(doc_router.shard_ord(&other_doc_json), shard_ord);
+                shard_ord
+            })
+            .collect();
+        assert!(shard_ords
+            .iter()
+            .any(|shard_ord| *shard_ord != shard_ords[0]));
+        assert_eq!(doc_router.shard_ord("not a json object"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_doc_router_commits_shards_on_memory_limit_report() {
+        let sharding_settings = ShardingSettings {
+            num_shards: 2,
+            routing_field: "tenant_id".to_string(),
+        };
+        let (shard_mailboxes, shard_inboxes): (Vec<_>, Vec<_>) =
+            (0..2).map(|_| create_test_mailbox::<Indexer>()).unzip();
+        let memory_limit_reports = MemoryLimitReports::default();
+        let mut doc_router = DocRouter::new(
+            &sharding_settings,
+            &IndexingSettings::default(),
+            shard_mailboxes,
+            memory_limit_reports.clone(),
+        )
+        .unwrap();
+        let universe = Universe::new();
+        let (router_mailbox, _router_inbox) = create_test_mailbox();
+        let (observable_state_tx, _observable_state_rx) = watch::channel(Default::default());
+        let ctx = ActorContext::for_test(&universe, router_mailbox, observable_state_tx);
+        let raw_doc_batch = |position: u64| RawDocBatch {
+            docs: vec![r#"{"tenant_id": "tenant-1"}"#.to_string()],
+            checkpoint_delta: SourceCheckpointDelta::from(position..position + 1),
+        };
+        let commit_triggers = |shard_inbox: &Inbox<Indexer>| {
+            shard_inbox
+                .drain_for_test_typed::<CommitShard>()
+                .into_iter()
+                .map(|commit_shard| commit_shard.commit_trigger)
+                .collect::<Vec<_>>()
+        };
+        doc_router
+            .route_batch(raw_doc_batch(0), &ctx)
+            .await
+            .unwrap();
+        for shard_inbox in &shard_inboxes {
+            assert!(commit_triggers(shard_inbox).is_empty());
+        }
+        memory_limit_reports.report();
+        doc_router
+            .route_batch(raw_doc_batch(1), &ctx)
+            .await
+            .unwrap();
+        for shard_inbox in &shard_inboxes {
+            let shard_commit_triggers = commit_triggers(shard_inbox);
+            assert!(matches!(
+                shard_commit_triggers[..],
+                [CommitTrigger::MemoryLimit]
+            ));
+        }
+        doc_router
+            .route_batch(raw_doc_batch(2), &ctx)
+            .await
+            .unwrap();
+        for shard_inbox in &shard_inboxes {
+            assert!(commit_triggers(shard_inbox).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_shard_batch_combiner() {
+        let mut shard_batch_combiner = ShardBatchCombiner::new(
+            "test-index".to_string(),
+            2,
+            Arc::new(MockMetastore::default()),
+        );
+        assert!(shard_batch_combiner
+            .push(shard_split_batch(1, None))
+            .unwrap()
+            .is_none());
+        assert!(shard_batch_combiner
+            .push(shard_split_batch(1, None))
+            .unwrap()
+            .is_none());
+        let combined_batch = shard_batch_combiner
+            .push(shard_split_batch(
+                0,
+                Some(IndexCheckpointDelta::for_test("test-source", 0..10)),
+            ))
+            .unwrap()
+            .unwrap();
+        assert!(combined_batch.splits.is_empty());
+        assert_eq!(
+            combined_batch.checkpoint_delta,
+            Some(IndexCheckpointDelta::for_test("test-source", 0..10))
+        );
+        let combined_batch = shard_batch_combiner
+            .push(shard_split_batch(
+                0,
+                Some(IndexCheckpointDelta::for_test("test-source", 10..20)),
+            ))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            combined_batch.checkpoint_delta,
+            Some(IndexCheckpointDelta::for_test("test-source", 10..20))
+        );
+        assert!(shard_batch_combiner
+            .push(shard_split_batch(2, None))
+            .is_err());
+    }
+}
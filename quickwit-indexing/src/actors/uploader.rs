@@ -21,29 +21,44 @@ use std::collections::HashSet;
 use std::iter::FromIterator;
 use std::mem;
 use std::ops::Range;
+use std::path::Path;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use anyhow::{bail, Context};
 use async_trait::async_trait;
 use fail::fail_point;
 use itertools::Itertools;
-use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
+use quickwit_actors::{
+    Actor, ActorContext, ActorExitStatus, Handler, KillSwitch, Mailbox, QueueCapacity,
+};
+use quickwit_directories::{open_split_directory, LocalSplit, LocalSplitRegistry};
+use quickwit_doc_mapper::QUICKWIT_TOKENIZER_MANAGER;
 use quickwit_metastore::checkpoint::IndexCheckpointDelta;
 use quickwit_metastore::{Metastore, SplitMetadata};
-use quickwit_storage::SplitPayloadBuilder;
+use quickwit_storage::{SplitPayloadBuilder, Storage};
 use time::OffsetDateTime;
 use tokio::sync::{oneshot, Semaphore, SemaphorePermit};
 use tracing::{info, info_span, warn, Instrument, Span};
 
 use crate::actors::sequencer::{Sequencer, SequencerCommand};
 use crate::actors::Publisher;
-use crate::models::{PackagedSplit, PackagedSplitBatch, PublishLock, SplitUpdate};
-use crate::split_store::IndexingSplitStore;
+use crate::models::{
+    MetastorePayloadGuard, PackagedSplit, PackagedSplitBatch, PackagedSplitBatchStream,
+    PackagedSplitEvent, PipelineWarningKind, PipelineWarningSink, PublishLock, SplitUpdate,
+    UploadCircuitBreaker,
+};
+use crate::split_store::{IndexingSplitStore, StreamedSplitUpload};
 
 pub const MAX_CONCURRENT_SPLIT_UPLOAD: usize = 4;
 
+/// Maximum number of attempts to store a split before giving up and killing the pipeline.
+const MAX_STORE_SPLIT_ATTEMPTS: usize = 3;
+
+/// Delay before retrying to store a split, multiplied by the number of failed attempts.
+const STORE_SPLIT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
 /// This semaphore ensures that at most `MAX_CONCURRENT_SPLIT_UPLOAD` uploads can happen
 /// concurrently.
 ///
@@ -58,6 +73,20 @@ pub struct Uploader {
     index_storage: IndexingSplitStore,
     sequencer_mailbox: Mailbox<Sequencer<Publisher>>,
     counters: UploaderCounters,
+    warning_sink: PipelineWarningSink,
+    /// Storage where the document samples of the splits are exported, when split sampling is
+    /// enabled.
+    split_sample_storage_opt: Option<Arc<dyn Storage>>,
+    /// Circuit breaker recording the failures to store splits, shared by the uploaders of all the
+    /// generations of a pipeline.
+    upload_circuit_breaker_opt: Option<UploadCircuitBreaker>,
+    /// Generation of the pipeline the uploader belongs to, stamped on the staged splits.
+    pipeline_generation: usize,
+    /// Guard rejecting the split metadata too large to be staged.
+    payload_guard_opt: Option<MetastorePayloadGuard>,
+    /// Registry where the new splits are registered as searchable by the searcher of the node
+    /// before they are uploaded.
+    local_split_registry_opt: Option<LocalSplitRegistry>,
 }
 
 impl Uploader {
@@ -66,6 +95,7 @@ impl Uploader {
         metastore: Arc<dyn Metastore>,
         index_storage: IndexingSplitStore,
         sequencer_mailbox: Mailbox<Sequencer<Publisher>>,
+        warning_sink: PipelineWarningSink,
     ) -> Uploader {
         Uploader {
             actor_name,
@@ -73,9 +103,52 @@ impl Uploader {
             index_storage,
             sequencer_mailbox,
             counters: Default::default(),
+            warning_sink,
+            split_sample_storage_opt: None,
+            upload_circuit_breaker_opt: None,
+            pipeline_generation: 0,
+            payload_guard_opt: None,
+            local_split_registry_opt: None,
         }
     }
 
+    pub fn with_pipeline_generation(mut self, pipeline_generation: usize) -> Uploader {
+        self.pipeline_generation = pipeline_generation;
+        self
+    }
+
+    pub fn with_split_sample_storage(mut self, split_sample_storage: Arc<dyn Storage>) -> Uploader {
+        self.split_sample_storage_opt = Some(split_sample_storage);
+        self
+    }
+
+    pub fn with_upload_circuit_breaker(
+        mut self,
+        upload_circuit_breaker: UploadCircuitBreaker,
+    ) -> Uploader {
+        self.upload_circuit_breaker_opt = Some(upload_circuit_breaker);
+        self
+    }
+
+    /// Checks the size of the metadata of the splits before staging them.
+    pub fn with_metastore_payload_guard(
+        mut self,
+        payload_guard: MetastorePayloadGuard,
+    ) -> Uploader {
+        self.payload_guard_opt = Some(payload_guard);
+        self
+    }
+
+    /// Registers the new splits in `local_split_registry` before uploading them, so that they
+    /// are searchable by the searcher of the node until they are published.
+    pub fn with_local_split_registry(
+        mut self,
+        local_split_registry: LocalSplitRegistry,
+    ) -> Uploader {
+        self.local_split_registry_opt = Some(local_split_registry);
+        self
+    }
+
     async fn acquire_semaphore(
         &self,
         ctx: &ActorContext<Self>,
@@ -91,6 +164,7 @@ impl Uploader {
 pub struct UploaderCounters {
     pub num_staged_splits: Arc<AtomicU64>,
     pub num_uploaded_splits: Arc<AtomicU64>,
+    pub num_store_split_retries: Arc<AtomicU64>,
 }
 
 #[async_trait]
@@ -160,6 +234,13 @@ impl Handler<PackagedSplitBatch> for Uploader {
         let metastore = self.metastore.clone();
         let index_storage = self.index_storage.clone();
         let counters = self.counters.clone();
+        let warning_sink = self.warning_sink.clone();
+        let split_sample_storage_opt = self.split_sample_storage_opt.clone();
+        let upload_circuit_breaker_opt = self.upload_circuit_breaker_opt.clone();
+        let payload_guard_opt = self.payload_guard_opt.clone();
+        let local_split_registry_opt = self.local_split_registry_opt.clone();
+        let pipeline_generation = self.pipeline_generation;
+        let actor_name = self.actor_name;
         let index_id = batch.index_id();
         let span = Span::current();
         info!(split_ids=?split_ids, "start-stage-and-store-splits");
@@ -179,11 +260,21 @@ impl Handler<PackagedSplitBatch> for Uploader {
                         }
                         return Ok(())
                     }
+                    if let Some(local_split_registry) = &local_split_registry_opt {
+                        register_local_split(local_split_registry, &split, &batch.publish_lock, &kill_switch);
+                    }
                     let upload_result = stage_and_upload_split(
                         &split,
+                        None,
                         &index_storage,
+                        split_sample_storage_opt.as_deref(),
+                        upload_circuit_breaker_opt.as_ref(),
+                        payload_guard_opt.as_ref(),
+                        pipeline_generation,
                         &*metastore,
                         counters.clone(),
+                        &warning_sink,
+                        actor_name,
                     )
                     .await;
                     if let Err(cause) = upload_result {
@@ -193,7 +284,7 @@ impl Handler<PackagedSplitBatch> for Uploader {
                     }
                     packaged_splits_and_metadatas.push((split, upload_result.unwrap()));
                 }
-                let publisher_message = make_publish_operation(index_id, batch.publish_lock, packaged_splits_and_metadatas, batch.checkpoint_delta_opt, batch.date_of_birth);
+                let publisher_message = make_publish_operation(index_id, batch.publish_lock, packaged_splits_and_metadatas, batch.checkpoint_delta_opt, batch.publish_with_next_delta, batch.date_of_birth);
                 if let Err(publisher_message) = split_uploaded_tx.send(publisher_message) {
                     bail!(
                         "Failed to send upload split `{:?}`. The publisher is probably dead.",
@@ -213,19 +304,199 @@ impl Handler<PackagedSplitBatch> for Uploader {
     }
 }
 
-fn create_split_metadata(split: &PackagedSplit, footer_offsets: Range<u64>) -> SplitMetadata {
+#[async_trait]
+impl Handler<PackagedSplitBatchStream> for Uploader {
+    type Reply = ();
+
+    fn message_span(&self, msg_id: u64, batch: &PackagedSplitBatchStream) -> Span {
+        info_span!("", msg_id=&msg_id, num_splits=%batch.split_ids.len())
+    }
+
+    async fn handle(
+        &mut self,
+        batch: PackagedSplitBatchStream,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        fail_point!("uploader:before");
+        let (split_uploaded_tx, split_uploaded_rx) =
+            oneshot::channel::<SequencerCommand<SplitUpdate>>();
+        ctx.send_message(&self.sequencer_mailbox, split_uploaded_rx)
+            .await?;
+        // See the comment on the permit of the `PackagedSplitBatch` handler.
+        let permit_guard = self.acquire_semaphore(ctx).await?;
+        let kill_switch = ctx.kill_switch().clone();
+        let split_ids = batch.split_ids.clone();
+        if kill_switch.is_dead() {
+            warn!(split_ids=?split_ids,"Kill switch was activated. Cancelling upload.");
+            return Err(ActorExitStatus::Killed);
+        }
+        let metastore = self.metastore.clone();
+        let index_storage = self.index_storage.clone();
+        let counters = self.counters.clone();
+        let warning_sink = self.warning_sink.clone();
+        let split_sample_storage_opt = self.split_sample_storage_opt.clone();
+        let upload_circuit_breaker_opt = self.upload_circuit_breaker_opt.clone();
+        let payload_guard_opt = self.payload_guard_opt.clone();
+        let local_split_registry_opt = self.local_split_registry_opt.clone();
+        let pipeline_generation = self.pipeline_generation;
+        let actor_name = self.actor_name;
+        let span = Span::current();
+        info!(split_ids=?split_ids, "start-stage-and-store-streamed-splits");
+        tokio::spawn(
+            async move {
+                let mut split_events_rx = batch.split_events_rx;
+                let mut packaged_splits_and_metadatas = Vec::new();
+                while let Some(split_event) = split_events_rx.recv().await {
+                    let (split_id, split_files) = match split_event {
+                        PackagedSplitEvent::SplitFiles {
+                            split_id,
+                            split_files,
+                        } => (split_id, split_files),
+                        PackagedSplitEvent::Packaged(split) => {
+                            kill_switch.kill();
+                            bail!("Received split `{}` before its files. Killing!", split.split_id());
+                        }
+                    };
+                    if batch.publish_lock.is_dead() {
+                        break;
+                    }
+                    // Failing to start uploading the files is not fatal: the split is uploaded
+                    // as a whole once packaged.
+                    let streamed_upload_opt =
+                        match index_storage.start_streamed_upload(&split_id, &split_files) {
+                            Ok(streamed_upload) => Some(streamed_upload),
+                            Err(error) => {
+                                warn!(split_id=%split_id, error=?error, "Failed to start streamed split upload.");
+                                None
+                            }
+                        };
+                    let split = match split_events_rx.recv().await {
+                        Some(PackagedSplitEvent::Packaged(split))
+                            if split.split_id() == split_id => split,
+                        _ => break,
+                    };
+                    if let Some(local_split_registry) = &local_split_registry_opt {
+                        register_local_split(local_split_registry, &split, &batch.publish_lock, &kill_switch);
+                    }
+                    let upload_result = stage_and_upload_split(
+                        &split,
+                        streamed_upload_opt,
+                        &index_storage,
+                        split_sample_storage_opt.as_deref(),
+                        upload_circuit_breaker_opt.as_ref(),
+                        payload_guard_opt.as_ref(),
+                        pipeline_generation,
+                        &*metastore,
+                        counters.clone(),
+                        &warning_sink,
+                        actor_name,
+                    )
+                    .await;
+                    if let Err(cause) = upload_result {
+                        warn!(cause=?cause, split_id=split.split_id(), "Failed to upload split. Killing!");
+                        kill_switch.kill();
+                        bail!("Failed to upload split `{}`. Killing!", split.split_id());
+                    }
+                    packaged_splits_and_metadatas.push((split, upload_result.unwrap()));
+                }
+                if batch.publish_lock.is_dead() {
+                    info!(
+                        split_ids=?split_ids,
+                        "Splits' publish lock is dead."
+                    );
+                    if split_uploaded_tx.send(SequencerCommand::Discard).is_err() {
+                        bail!("Failed to send cancel command to sequencer. The sequencer is probably dead.");
+                    }
+                    return Ok(())
+                }
+                if packaged_splits_and_metadatas.len() < split_ids.len() {
+                    // The packager failed before streaming all the splits of the batch.
+                    warn!(split_ids=?split_ids, "Split stream closed early. Killing!");
+                    kill_switch.kill();
+                    bail!("Split stream closed before all the splits were packaged. Killing!");
+                }
+                let publisher_message = make_publish_operation(batch.index_id, batch.publish_lock, packaged_splits_and_metadatas, batch.checkpoint_delta_opt, batch.publish_with_next_delta, batch.date_of_birth);
+                if let Err(publisher_message) = split_uploaded_tx.send(publisher_message) {
+                    bail!(
+                        "Failed to send upload split `{:?}`. The publisher is probably dead.",
+                        &publisher_message
+                    );
+                }
+                info!("success-stage-and-store-streamed-splits");
+                mem::drop(permit_guard);
+                Result::<(), anyhow::Error>::Ok(())
+            }
+            .instrument(span),
+        );
+        Ok(())
+    }
+}
+
+/// Registers a new split as searchable by the searcher of the node until it is published. The
+/// split is no longer searched as soon as its publish lock or the pipeline is killed, as its
+/// documents may then be indexed again by another split.
+///
+/// Failing to open the split is not fatal: the split is then searchable once published.
+fn register_local_split(
+    local_split_registry: &LocalSplitRegistry,
+    split: &PackagedSplit,
+    publish_lock: &PublishLock,
+    kill_switch: &KillSwitch,
+) {
+    // The splits replacing other splits would be searched along with the splits they replace.
+    if !split.split_attrs.replaced_split_ids.is_empty() {
+        return;
+    }
+    let publish_lock = publish_lock.clone();
+    let kill_switch = kill_switch.clone();
+    let local_split_res =
+        open_split_directory(split.split_scratch_directory.path(), &split.hotcache_bytes).and_then(
+            |mut index| {
+                index.set_tokenizers(QUICKWIT_TOKENIZER_MANAGER.clone());
+                LocalSplit::new(
+                    split.split_attrs.pipeline_id.index_id.clone(),
+                    split.split_id().to_string(),
+                    split.split_attrs.time_range.clone(),
+                    index,
+                    move || publish_lock.is_alive() && kill_switch.is_alive(),
+                )
+            },
+        );
+    match local_split_res {
+        Ok(local_split) => local_split_registry.register(local_split),
+        Err(error) => {
+            warn!(split_id=split.split_id(), error=?error, "Failed to register split as searchable before upload.");
+        }
+    }
+}
+
+fn create_split_metadata(
+    split: &PackagedSplit,
+    footer_offsets: Range<u64>,
+    pipeline_generation: usize,
+) -> SplitMetadata {
     SplitMetadata {
         split_id: split.split_attrs.split_id.clone(),
         partition_id: split.split_attrs.partition_id,
         source_id: split.split_attrs.pipeline_id.source_id.clone(),
         node_id: split.split_attrs.pipeline_id.node_id.clone(),
         pipeline_ord: split.split_attrs.pipeline_id.pipeline_ord,
+        pipeline_generation,
         num_docs: split.split_attrs.num_docs as usize,
         time_range: split.split_attrs.time_range.clone(),
         uncompressed_docs_size_in_bytes: split.split_attrs.uncompressed_docs_size_in_bytes,
         create_timestamp: OffsetDateTime::now_utc().unix_timestamp(),
         tags: split.tags.clone(),
         footer_offsets,
+        dedup_digest: split.split_attrs.dedup_digest_opt.clone(),
+        delete_opstamp: split.split_attrs.delete_opstamp,
+        sample_uri: None,
+        min_expire_timestamp: split.split_attrs.min_expire_timestamp,
+        tags_amendments: Vec::new(),
+        doc_mapping_version: split.split_attrs.doc_mapping_version,
+        config_fingerprint: split.split_attrs.config_fingerprint.clone(),
+        replaced_split_ids: split.split_attrs.replaced_split_ids.clone(),
+        field_stats: split.field_stats.clone(),
     }
 }
 
@@ -234,6 +505,7 @@ fn make_publish_operation(
     publish_lock: PublishLock,
     packaged_splits_and_metadatas: Vec<(PackagedSplit, SplitMetadata)>,
     checkpoint_delta_opt: Option<IndexCheckpointDelta>,
+    publish_with_next_delta: bool,
     date_of_birth: Instant,
 ) -> SequencerCommand<SplitUpdate> {
     assert!(!packaged_splits_and_metadatas.is_empty());
@@ -250,24 +522,60 @@ fn make_publish_operation(
             .collect_vec(),
         replaced_split_ids: Vec::from_iter(replaced_split_ids),
         checkpoint_delta_opt,
+        publish_with_next_delta,
         date_of_birth,
     })
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn stage_and_upload_split(
     packaged_split: &PackagedSplit,
+    mut streamed_upload_opt: Option<StreamedSplitUpload>,
     split_store: &IndexingSplitStore,
+    split_sample_storage_opt: Option<&dyn Storage>,
+    upload_circuit_breaker_opt: Option<&UploadCircuitBreaker>,
+    payload_guard_opt: Option<&MetastorePayloadGuard>,
+    pipeline_generation: usize,
     metastore: &dyn Metastore,
     counters: UploaderCounters,
+    warning_sink: &PipelineWarningSink,
+    actor_name: &str,
 ) -> anyhow::Result<SplitMetadata> {
-    let split_streamer = SplitPayloadBuilder::get_split_payload(
-        &packaged_split.split_files,
-        &packaged_split.hotcache_bytes,
-    )?;
-    let split_metadata = create_split_metadata(
+    let split_streamer = if let Some(streamed_upload) = streamed_upload_opt.as_mut() {
+        streamed_upload.split_payload(&packaged_split.hotcache_bytes)?
+    } else {
+        SplitPayloadBuilder::get_split_payload(
+            &packaged_split.split_files,
+            &packaged_split.hotcache_bytes,
+        )?
+    };
+    let mut split_metadata = create_split_metadata(
         packaged_split,
         split_streamer.footer_range.start as u64..split_streamer.footer_range.end as u64,
+        pipeline_generation,
     );
+    // The sample is exported before staging the split, so that the metadata of a split never
+    // references a missing sample.
+    if let (Some(sample_docs), Some(split_sample_storage)) =
+        (&packaged_split.sample_docs_opt, split_sample_storage_opt)
+    {
+        info!(split_id = packaged_split.split_id(), "storing-split-sample");
+        let sample_file_name = format!("{}.ndjson", packaged_split.split_id());
+        split_sample_storage
+            .put(Path::new(&sample_file_name), Box::new(sample_docs.clone()))
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to store the sample of split `{}`.",
+                    packaged_split.split_id()
+                )
+            })?;
+        let sample_uri = split_sample_storage.uri().join(&sample_file_name)?;
+        split_metadata.sample_uri = Some(sample_uri.to_string());
+    }
+    if let Some(payload_guard) = payload_guard_opt {
+        payload_guard.check_split_metadata(&split_metadata, actor_name)?;
+    }
     let index_id = &packaged_split.split_attrs.pipeline_id.index_id.clone();
     info!(split_id = packaged_split.split_id(), "staging-split");
     metastore
@@ -275,14 +583,88 @@ async fn stage_and_upload_split(
         .await?;
     counters.num_staged_splits.fetch_add(1, Ordering::SeqCst);
 
+    // The split is staged before its streamed upload completes, so that a split object is never
+    // left in the storage without the metastore knowing about it.
+    if let Some(streamed_upload) = streamed_upload_opt {
+        let split_folder = packaged_split.split_scratch_directory.path();
+        if let Err(error) = streamed_upload
+            .complete(&split_streamer, split_folder)
+            .await
+        {
+            warn!(
+                split_id = packaged_split.split_id(),
+                error = ?error,
+                "Failed to complete streamed split upload. Uploading the split again."
+            );
+        }
+    }
+
     info!(split_id = packaged_split.split_id(), "storing-split");
-    split_store
-        .store_split(
-            &split_metadata,
-            packaged_split.split_scratch_directory.path(),
-            Box::new(split_streamer),
-        )
-        .await?;
+    // The split store records completed and partial uploads in the split scratch directory, so
+    // retrying only uploads the files and parts that are missing in the remote storage.
+    let mut num_attempts = 0;
+    loop {
+        num_attempts += 1;
+        let store_result = split_store
+            .store_split(
+                &split_metadata,
+                packaged_split.split_scratch_directory.path(),
+                Box::new(split_streamer.clone()),
+            )
+            .await;
+        let error = match store_result {
+            Ok(()) => {
+                if let Some(upload_circuit_breaker) = upload_circuit_breaker_opt {
+                    upload_circuit_breaker.record_success();
+                }
+                break;
+            }
+            Err(error) => error,
+        };
+        // Retrying is pointless once the circuit breaker has tripped.
+        let is_circuit_breaker_tripped = upload_circuit_breaker_opt
+            .map(|upload_circuit_breaker| {
+                upload_circuit_breaker.record_failure(&error);
+                upload_circuit_breaker.trip_opt().is_some()
+            })
+            .unwrap_or(false);
+        if is_circuit_breaker_tripped || num_attempts >= MAX_STORE_SPLIT_ATTEMPTS {
+            if let Err(abort_error) = split_store
+                .abort_split_upload(
+                    packaged_split.split_id(),
+                    packaged_split.split_scratch_directory.path(),
+                )
+                .await
+            {
+                warn!(
+                    split_id = packaged_split.split_id(),
+                    error = ?abort_error,
+                    "Failed to abort split upload."
+                );
+            }
+            return Err(error);
+        }
+        warn!(
+            split_id = packaged_split.split_id(),
+            num_attempts = num_attempts,
+            error = ?error,
+            "Failed to store split. Retrying."
+        );
+        counters
+            .num_store_split_retries
+            .fetch_add(1, Ordering::SeqCst);
+        warning_sink.warn(
+            PipelineWarningKind::UploadRetries,
+            actor_name,
+            format!(
+                "Failed to store split `{}` after {} attempt(s): {}.",
+                packaged_split.split_id(),
+                num_attempts,
+                error
+            ),
+        );
+        tokio::time::sleep(STORE_SPLIT_RETRY_DELAY * num_attempts as u32).await;
+    }
     counters.num_uploaded_splits.fetch_add(1, Ordering::SeqCst);
     Ok(split_metadata)
 }
@@ -295,7 +677,7 @@ mod tests {
     use quickwit_actors::{create_test_mailbox, ObservationType, Universe};
     use quickwit_metastore::checkpoint::{IndexCheckpointDelta, SourceCheckpointDelta};
     use quickwit_metastore::MockMetastore;
-    use quickwit_storage::RamStorage;
+    use quickwit_storage::{PutPayload, RamStorage};
     use tokio::sync::oneshot;
 
     use super::*;
@@ -329,6 +711,7 @@ mod tests {
             Arc::new(mock_metastore),
             index_storage,
             sequencer_mailbox,
+            PipelineWarningSink::default(),
         );
         let (uploader_mailbox, uploader_handle) = universe.spawn_actor(uploader).spawn();
         let split_scratch_directory = ScratchDirectory::for_test()?;
@@ -346,11 +729,18 @@ mod tests {
                         uncompressed_docs_size_in_bytes: 1_000,
                         num_docs: 10,
                         replaced_split_ids: Vec::new(),
+                        delete_opstamp: 0,
+                        dedup_digest_opt: None,
+                        min_expire_timestamp: None,
                         split_id: "test-split".to_string(),
+                        doc_mapping_version: 0,
+                        config_fingerprint: None,
                     },
                     split_scratch_directory,
                     tags: Default::default(),
+                    field_stats: Default::default(),
                     hotcache_bytes: vec![],
+                    sample_docs_opt: None,
                     split_files: vec![],
                 }],
                 checkpoint_delta_opt,
@@ -397,6 +787,181 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_uploader_streamed_split_batch() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let universe = Universe::new();
+        let (sequencer_mailbox, sequencer_inbox) = create_test_mailbox::<Sequencer<Publisher>>();
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_stage_split()
+            .withf(move |index_id, metadata| -> bool {
+                index_id == "test-index" && metadata.split_id() == "test-split"
+            })
+            .times(1)
+            .returning(|_, _| Ok(()));
+        let ram_storage = RamStorage::default();
+        let index_storage: IndexingSplitStore =
+            IndexingSplitStore::create_with_no_local_store(Arc::new(ram_storage.clone()));
+        let uploader = Uploader::new(
+            "TestUploader",
+            Arc::new(mock_metastore),
+            index_storage,
+            sequencer_mailbox,
+            PipelineWarningSink::default(),
+        );
+        let (uploader_mailbox, uploader_handle) = universe.spawn_actor(uploader).spawn();
+        let split_scratch_directory = ScratchDirectory::for_test()?;
+        let split_file = split_scratch_directory.path().join("segment.idx");
+        std::fs::write(&split_file, b"segment")?;
+        let hotcache_bytes = b"hotcache".to_vec();
+        let expected_split_bytes =
+            SplitPayloadBuilder::get_split_payload(&[split_file.clone()], &hotcache_bytes)?
+                .read_all()
+                .await?;
+
+        let (split_events_tx, split_events_rx) = tokio::sync::mpsc::unbounded_channel();
+        uploader_mailbox
+            .send_message(PackagedSplitBatchStream {
+                index_id: "test-index".to_string(),
+                split_ids: vec!["test-split".to_string()],
+                split_events_rx,
+                checkpoint_delta_opt: None,
+                publish_with_next_delta: false,
+                publish_lock: PublishLock::default(),
+                date_of_birth: Instant::now(),
+            })
+            .await?;
+        split_events_tx.send(PackagedSplitEvent::SplitFiles {
+            split_id: "test-split".to_string(),
+            split_files: vec![split_file.clone()],
+        })?;
+        split_events_tx.send(PackagedSplitEvent::Packaged(PackagedSplit {
+            split_attrs: SplitAttrs {
+                partition_id: 3u64,
+                pipeline_id,
+                time_range: None,
+                uncompressed_docs_size_in_bytes: 1_000,
+                num_docs: 10,
+                replaced_split_ids: Vec::new(),
+                delete_opstamp: 0,
+                dedup_digest_opt: None,
+                min_expire_timestamp: None,
+                split_id: "test-split".to_string(),
+                doc_mapping_version: 0,
+                config_fingerprint: None,
+            },
+            split_scratch_directory,
+            tags: Default::default(),
+            field_stats: Default::default(),
+            hotcache_bytes,
+            sample_docs_opt: None,
+            split_files: vec![split_file],
+        }))?;
+        drop(split_events_tx);
+        assert_eq!(
+            uploader_handle.process_pending_and_observe().await.obs_type,
+            ObservationType::Alive
+        );
+        let mut publish_futures: Vec<oneshot::Receiver<SequencerCommand<SplitUpdate>>> =
+            sequencer_inbox.drain_for_test_typed();
+        assert_eq!(publish_futures.len(), 1);
+        let publisher_message = match publish_futures.pop().unwrap().await? {
+            SequencerCommand::Discard => panic!(
+                "Expected `SequencerCommand::Proceed(SplitUpdate)`, got \
+                 `SequencerCommand::Discard`."
+            ),
+            SequencerCommand::Proceed(publisher_message) => publisher_message,
+        };
+        assert_eq!(publisher_message.new_splits.len(), 1);
+        assert_eq!(
+            publisher_message.new_splits[0].footer_offsets,
+            7..expected_split_bytes.len() as u64
+        );
+        let split_bytes = ram_storage.get_all(Path::new("test-split.split")).await?;
+        assert_eq!(split_bytes.as_slice(), expected_split_bytes.as_slice());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_uploader_exports_split_sample() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let universe = Universe::new();
+        let (sequencer_mailbox, sequencer_inbox) = create_test_mailbox::<Sequencer<Publisher>>();
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_stage_split()
+            .withf(move |_, metadata| -> bool {
+                metadata.sample_uri.as_deref() == Some("ram:///test-split.ndjson")
+            })
+            .times(1)
+            .returning(|_, _| Ok(()));
+        let index_storage: IndexingSplitStore =
+            IndexingSplitStore::create_with_no_local_store(Arc::new(RamStorage::default()));
+        let split_sample_storage = RamStorage::default();
+        let uploader = Uploader::new(
+            "TestUploader",
+            Arc::new(mock_metastore),
+            index_storage,
+            sequencer_mailbox,
+            PipelineWarningSink::default(),
+        )
+        .with_split_sample_storage(Arc::new(split_sample_storage.clone()));
+        let (uploader_mailbox, uploader_handle) = universe.spawn_actor(uploader).spawn();
+        uploader_mailbox
+            .send_message(PackagedSplitBatch::new(
+                vec![PackagedSplit {
+                    split_attrs: SplitAttrs {
+                        partition_id: 3u64,
+                        pipeline_id,
+                        time_range: None,
+                        uncompressed_docs_size_in_bytes: 1_000,
+                        num_docs: 10,
+                        replaced_split_ids: Vec::new(),
+                        delete_opstamp: 0,
+                        dedup_digest_opt: None,
+                        min_expire_timestamp: None,
+                        split_id: "test-split".to_string(),
+                        doc_mapping_version: 0,
+                        config_fingerprint: None,
+                    },
+                    split_scratch_directory: ScratchDirectory::for_test()?,
+                    tags: Default::default(),
+                    field_stats: Default::default(),
+                    hotcache_bytes: vec![],
+                    sample_docs_opt: Some(b"{\"body\":[\"happy\"]}\n".to_vec()),
+                    split_files: vec![],
+                }],
+                None,
+                PublishLock::default(),
+                Instant::now(),
+            ))
+            .await?;
+        uploader_handle.process_pending_and_observe().await;
+        let mut publish_futures: Vec<oneshot::Receiver<SequencerCommand<SplitUpdate>>> =
+            sequencer_inbox.drain_for_test_typed();
+        assert_eq!(publish_futures.len(), 1);
+        assert!(matches!(
+            publish_futures.pop().unwrap().await?,
+            SequencerCommand::Proceed(_)
+        ));
+        let sample_docs = split_sample_storage
+            .get_all(Path::new("test-split.ndjson"))
+            .await?;
+        assert_eq!(sample_docs.as_slice(), b"{\"body\":[\"happy\"]}\n");
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_uploader_emits_replace() -> anyhow::Result<()> {
         let pipeline_id = IndexingPipelineId {
@@ -426,6 +991,7 @@ mod tests {
             Arc::new(mock_metastore),
             index_storage,
             sequencer_mailbox,
+            PipelineWarningSink::default(),
         );
         let (uploader_mailbox, uploader_handle) = universe.spawn_actor(uploader).spawn();
         let split_scratch_directory_1 = ScratchDirectory::for_test()?;
@@ -442,11 +1008,18 @@ mod tests {
                     "replaced-split-1".to_string(),
                     "replaced-split-2".to_string(),
                 ],
+                delete_opstamp: 0,
+                dedup_digest_opt: None,
+                min_expire_timestamp: None,
+                doc_mapping_version: 0,
+                config_fingerprint: None,
             },
             split_scratch_directory: split_scratch_directory_1,
             tags: Default::default(),
+            field_stats: Default::default(),
             split_files: vec![],
             hotcache_bytes: vec![],
+            sample_docs_opt: None,
         };
         let package_split_2 = PackagedSplit {
             split_attrs: SplitAttrs {
@@ -460,11 +1033,18 @@ mod tests {
                     "replaced-split-1".to_string(),
                     "replaced-split-2".to_string(),
                 ],
+                delete_opstamp: 0,
+                dedup_digest_opt: None,
+                min_expire_timestamp: None,
+                doc_mapping_version: 0,
+                config_fingerprint: None,
             },
             split_scratch_directory: split_scratch_directory_2,
             tags: Default::default(),
+            field_stats: Default::default(),
             split_files: vec![],
             hotcache_bytes: vec![],
+            sample_docs_opt: None,
         };
         uploader_mailbox
             .send_message(PackagedSplitBatch::new(
@@ -19,27 +19,67 @@
 
 use std::io::{BufWriter, IntoInnerError};
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
 use std::{fmt, io};
 
 use arc_swap::ArcSwap;
 use quickwit_actors::{KillSwitch, Progress, ProtectedZoneGuard};
+use quickwit_config::IndexingResources;
 use tantivy::directory::error::{DeleteError, OpenReadError, OpenWriteError};
 use tantivy::directory::{
     AntiCallToken, FileHandle, TerminatingWrite, WatchCallback, WatchHandle, WritePtr,
 };
 use tantivy::Directory;
 
+use crate::metrics::INDEXER_METRICS;
+
 /// Buffer capacity.
 ///
 /// This is the current default for the BufWriter, but considering this constant
 /// will have a direct impact on health check, we'd better fix it.
 const BUFFER_NUM_BYTES: usize = 8_192;
 
+/// Settings of the writes to the files of a [`ControlledDirectory`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DirectoryWriteSettings {
+    /// Capacity of the buffer of each file.
+    pub buffer_num_bytes: usize,
+    /// Number of bytes written to a file after which the file is synced to disk. If `None`,
+    /// files are only synced once complete.
+    pub sync_interval_num_bytes_opt: Option<u64>,
+}
+
+impl Default for DirectoryWriteSettings {
+    fn default() -> Self {
+        DirectoryWriteSettings {
+            buffer_num_bytes: BUFFER_NUM_BYTES,
+            sync_interval_num_bytes_opt: None,
+        }
+    }
+}
+
+impl From<&IndexingResources> for DirectoryWriteSettings {
+    fn from(indexing_resources: &IndexingResources) -> Self {
+        DirectoryWriteSettings {
+            buffer_num_bytes: indexing_resources
+                .split_write_buffer_size
+                .map(|buffer_size| buffer_size.get_bytes() as usize)
+                .unwrap_or(BUFFER_NUM_BYTES),
+            sync_interval_num_bytes_opt: indexing_resources
+                .split_sync_interval
+                .map(|sync_interval| sync_interval.get_bytes()),
+        }
+    }
+}
+
 /// The `ControlledDirectory` wraps another directory and enhances it
 /// with functionalities such as
 /// - records progress everytime a write (Note there is however a buffer writer above it)
 /// - if the killswitch is activated, returns an error on the first write happening after it
+/// - counts the number of bytes written
+/// - buffers the writes and syncs the files to disk as set by its [`DirectoryWriteSettings`]
 /// - in the future, record a writing speed, possibly introduce some throttling, etc.
 #[derive(Clone)]
 pub struct ControlledDirectory {
@@ -59,10 +99,23 @@ impl ControlledDirectory {
                     kill_switch,
                 }))),
                 underlying: directory.into(),
+                num_bytes_written: Arc::new(AtomicU64::new(0)),
+                write_settings: DirectoryWriteSettings::default(),
             },
         }
     }
 
+    /// Sets the buffer capacity and the sync cadence of the files written in the directory.
+    pub fn with_write_settings(mut self, write_settings: DirectoryWriteSettings) -> Self {
+        self.inner.write_settings = write_settings;
+        self
+    }
+
+    /// Returns the counter of the number of bytes written in the directory.
+    pub fn num_bytes_written_counter(&self) -> Arc<AtomicU64> {
+        self.inner.num_bytes_written.clone()
+    }
+
     fn check_if_alive(&self) -> io::Result<ProtectedZoneGuard> {
         self.inner.controls.load().check_if_alive()
     }
@@ -105,23 +158,60 @@ impl Controls {
 struct Inner {
     controls: Arc<ArcSwap<Controls>>,
     underlying: Arc<dyn Directory>,
+    num_bytes_written: Arc<AtomicU64>,
+    write_settings: DirectoryWriteSettings,
 }
 
 struct ControlledWrite {
     controls: Arc<ArcSwap<Controls>>,
     underlying_wrt: Box<dyn TerminatingWrite>,
+    num_bytes_written: Arc<AtomicU64>,
+    sync_interval_num_bytes_opt: Option<u64>,
+    num_bytes_since_last_sync: u64,
 }
 
 impl ControlledWrite {
     fn check_if_alive(&self) -> io::Result<ProtectedZoneGuard> {
         self.controls.load().check_if_alive()
     }
+
+    /// Records the bytes flushed by the buffer writer above, and syncs the file if the sync
+    /// interval is reached.
+    fn record_bytes_written(&mut self, num_bytes: usize, start: Instant) -> io::Result<()> {
+        INDEXER_METRICS
+            .split_file_flush_duration_secs
+            .observe(start.elapsed().as_secs_f64());
+        self.num_bytes_written
+            .fetch_add(num_bytes as u64, Ordering::Relaxed);
+        if let Some(sync_interval_num_bytes) = self.sync_interval_num_bytes_opt {
+            self.num_bytes_since_last_sync += num_bytes as u64;
+            if self.num_bytes_since_last_sync >= sync_interval_num_bytes {
+                self.sync()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Flushes the underlying writer, which syncs the file to disk for files backed by a
+    /// `MmapDirectory`.
+    fn sync(&mut self) -> io::Result<()> {
+        let start = Instant::now();
+        self.underlying_wrt.flush()?;
+        INDEXER_METRICS
+            .split_file_sync_duration_secs
+            .observe(start.elapsed().as_secs_f64());
+        self.num_bytes_since_last_sync = 0;
+        Ok(())
+    }
 }
 
 impl io::Write for ControlledWrite {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         let _guard = self.check_if_alive()?;
-        self.underlying_wrt.write(buf)
+        let start = Instant::now();
+        let num_bytes = self.underlying_wrt.write(buf)?;
+        self.record_bytes_written(num_bytes, start)?;
+        Ok(num_bytes)
     }
 
     fn flush(&mut self) -> io::Result<()> {
@@ -129,22 +219,22 @@ impl io::Write for ControlledWrite {
         // This is because the RAMDirectory currently panics if flush
         // is not called before Drop.
         let _guard = self.check_if_alive();
-        self.underlying_wrt.flush()
+        self.sync()
     }
 
     fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
         let _guard = self.check_if_alive()?;
-        self.underlying_wrt.write_vectored(bufs)
+        let start = Instant::now();
+        let num_bytes = self.underlying_wrt.write_vectored(bufs)?;
+        self.record_bytes_written(num_bytes, start)?;
+        Ok(num_bytes)
     }
 
     fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
         let _guard = self.check_if_alive()?;
-        self.underlying_wrt.write_all(buf)
-    }
-
-    fn write_fmt(&mut self, fmt: fmt::Arguments<'_>) -> io::Result<()> {
-        let _guard = self.check_if_alive()?;
-        self.underlying_wrt.write_fmt(fmt)
+        let start = Instant::now();
+        self.underlying_wrt.write_all(buf)?;
+        self.record_bytes_written(buf.len(), start)
     }
 }
 
@@ -182,12 +272,16 @@ impl Directory for ControlledDirectory {
             .map_err(IntoInnerError::into_error)
             .map_err(|io_err| OpenWriteError::wrap_io_error(io_err, path.to_path_buf()))?;
         let controls = self.inner.controls.clone();
+        let write_settings = self.inner.write_settings;
         let controlled_wrt = ControlledWrite {
             controls,
             underlying_wrt,
+            num_bytes_written: self.inner.num_bytes_written.clone(),
+            sync_interval_num_bytes_opt: write_settings.sync_interval_num_bytes_opt,
+            num_bytes_since_last_sync: 0,
         };
         Ok(BufWriter::with_capacity(
-            BUFFER_NUM_BYTES,
+            write_settings.buffer_num_bytes,
             Box::new(controlled_wrt),
         ))
     }
@@ -200,7 +294,11 @@ impl Directory for ControlledDirectory {
 
     fn atomic_write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
         self.check_if_alive()?;
-        self.inner.underlying.atomic_write(path, data)
+        self.inner.underlying.atomic_write(path, data)?;
+        self.inner
+            .num_bytes_written
+            .fetch_add(data.len() as u64, Ordering::Relaxed);
+        Ok(())
     }
 
     fn watch(&self, watch_callback: WatchCallback) -> tantivy::Result<WatchHandle> {
@@ -217,7 +315,7 @@ impl Directory for ControlledDirectory {
 impl TerminatingWrite for ControlledWrite {
     #[inline]
     fn terminate_ref(&mut self, token: AntiCallToken) -> io::Result<()> {
-        self.underlying_wrt.flush()?;
+        self.sync()?;
         self.underlying_wrt.terminate_ref(token)
     }
 }
@@ -226,6 +324,7 @@ impl TerminatingWrite for ControlledWrite {
 mod tests {
     use std::io::Write;
 
+    use byte_unit::Byte;
     use tantivy::directory::RamDirectory;
 
     use super::*;
@@ -256,6 +355,77 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_counts_bytes_written() -> anyhow::Result<()> {
+        let directory = RamDirectory::default();
+        let controlled_directory = ControlledDirectory::new(
+            Box::new(directory),
+            Progress::default(),
+            KillSwitch::default(),
+        );
+        let num_bytes_written = controlled_directory.num_bytes_written_counter();
+        let mut wrt = controlled_directory.open_write(Path::new("test"))?;
+        let large_buffer = vec![0u8; wrt.capacity() + 1];
+        wrt.write_all(&large_buffer)?;
+        wrt.write_all(b"small payload")?;
+        wrt.terminate()?;
+        assert_eq!(
+            num_bytes_written.load(Ordering::Relaxed),
+            large_buffer.len() as u64 + 13
+        );
+        controlled_directory.atomic_write(Path::new("meta.json"), b"{}")?;
+        assert_eq!(
+            num_bytes_written.load(Ordering::Relaxed),
+            large_buffer.len() as u64 + 15
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_settings() -> anyhow::Result<()> {
+        let directory = RamDirectory::default();
+        let controlled_directory = ControlledDirectory::new(
+            Box::new(directory),
+            Progress::default(),
+            KillSwitch::default(),
+        )
+        .with_write_settings(DirectoryWriteSettings {
+            buffer_num_bytes: 100,
+            sync_interval_num_bytes_opt: Some(250),
+        });
+        let path = Path::new("test");
+        let mut wrt = controlled_directory.open_write(path)?;
+        assert_eq!(wrt.capacity(), 100);
+        // The files of a `RamDirectory` are only readable once synced.
+        wrt.write_all(&[0u8; 200])?;
+        assert!(controlled_directory.atomic_read(path)?.is_empty());
+        wrt.write_all(&[0u8; 100])?;
+        assert_eq!(controlled_directory.atomic_read(path)?.len(), 300);
+        wrt.write_all(&[0u8; 10])?;
+        assert_eq!(controlled_directory.atomic_read(path)?.len(), 300);
+        wrt.terminate()?;
+        assert_eq!(controlled_directory.atomic_read(path)?.len(), 310);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_settings_from_indexing_resources() {
+        let mut indexing_resources = IndexingResources::for_test();
+        assert_eq!(
+            DirectoryWriteSettings::from(&indexing_resources),
+            DirectoryWriteSettings::default()
+        );
+        indexing_resources.split_write_buffer_size = Some(Byte::from_bytes(1_000));
+        indexing_resources.split_sync_interval = Some(Byte::from_bytes(1_000_000));
+        assert_eq!(
+            DirectoryWriteSettings::from(&indexing_resources),
+            DirectoryWriteSettings {
+                buffer_num_bytes: 1_000,
+                sync_interval_num_bytes_opt: Some(1_000_000),
+            }
+        );
+    }
+
     #[test]
     fn test_records_kill_switch_triggers_io_error() -> anyhow::Result<()> {
         let directory = RamDirectory::default();
@@ -0,0 +1,313 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use quickwit_config::IndexerConfig;
+use tokio::sync::oneshot;
+
+/// Indexing time granted to an index and its scheduling weight.
+struct IndexShare {
+    weight: u32,
+    /// Indexing time consumed by the index divided by its weight, in nanoseconds.
+    virtual_time_nanos: u64,
+}
+
+struct Waiter {
+    index_id: String,
+    seq: u64,
+    permit_tx: oneshot::Sender<FairSchedulerPermit>,
+}
+
+#[derive(Default)]
+struct FairSchedulerState {
+    num_running_batches: usize,
+    index_shares: HashMap<String, IndexShare>,
+    waiters: Vec<Waiter>,
+    /// Virtual time of the last batch granted a permit. An index resuming after being idle starts
+    /// from it, so that it does not get a burst of indexing time for the time it was idle.
+    system_virtual_time_nanos: u64,
+    next_seq: u64,
+}
+
+impl FairSchedulerState {
+    /// Hands out the free slots to the waiting batches of the indexes with the lowest virtual
+    /// time.
+    fn grant_permits(&mut self, state_arc: &Arc<Mutex<FairSchedulerState>>, max_batches: usize) {
+        while self.num_running_batches < max_batches && !self.waiters.is_empty() {
+            let (waiter_idx, _) = self
+                .waiters
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, waiter)| {
+                    let virtual_time_nanos = self
+                        .index_shares
+                        .get(&waiter.index_id)
+                        .map(|index_share| index_share.virtual_time_nanos)
+                        .unwrap_or_default();
+                    (virtual_time_nanos, waiter.seq)
+                })
+                .expect("The waiters should not be empty.");
+            let waiter = self.waiters.swap_remove(waiter_idx);
+            let permit = self.new_permit(state_arc, waiter.index_id, max_batches);
+            if let Err(mut permit) = waiter.permit_tx.send(permit) {
+                // The batch stopped waiting: its slot is handed to the next one.
+                permit.disarm();
+                self.num_running_batches -= 1;
+            }
+        }
+    }
+
+    fn new_permit(
+        &mut self,
+        state_arc: &Arc<Mutex<FairSchedulerState>>,
+        index_id: String,
+        max_batches: usize,
+    ) -> FairSchedulerPermit {
+        self.num_running_batches += 1;
+        if let Some(index_share) = self.index_shares.get(&index_id) {
+            self.system_virtual_time_nanos = index_share.virtual_time_nanos;
+        }
+        FairSchedulerPermit {
+            state_opt: Some((state_arc.clone(), max_batches)),
+            index_id,
+            start: Instant::now(),
+        }
+    }
+}
+
+/// Shares the indexing time of the node between the indexes, so that the indexers of a
+/// high-throughput index cannot monopolize the threads of the blocking runtime.
+///
+/// Indexers hold a [`FairSchedulerPermit`] while indexing a batch of documents. Once
+/// `max_concurrent_indexing_batches` batches are running, the next batches wait for a permit,
+/// which is granted to the batch of the index that consumed the least indexing time relative to
+/// its scheduling weight, a policy known as weighted fair queuing. The granularity of the time
+/// slices is the batch: a running batch is never preempted.
+pub struct FairScheduler {
+    max_concurrent_batches_opt: Option<usize>,
+    state: Arc<Mutex<FairSchedulerState>>,
+}
+
+impl fmt::Debug for FairScheduler {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.state.lock().unwrap();
+        formatter
+            .debug_struct("FairScheduler")
+            .field("max_concurrent_batches", &self.max_concurrent_batches_opt)
+            .field("num_running_batches", &state.num_running_batches)
+            .field("num_waiting_batches", &state.waiters.len())
+            .finish()
+    }
+}
+
+impl Default for FairScheduler {
+    /// Returns a scheduler that does not limit the batches indexed concurrently.
+    fn default() -> Self {
+        FairScheduler::new(None)
+    }
+}
+
+impl FairScheduler {
+    pub fn new(max_concurrent_batches_opt: Option<usize>) -> Self {
+        FairScheduler {
+            max_concurrent_batches_opt: max_concurrent_batches_opt
+                .map(|max_concurrent_batches| max_concurrent_batches.max(1)),
+            state: Arc::default(),
+        }
+    }
+
+    pub fn from_indexer_config(indexer_config: &IndexerConfig) -> Self {
+        FairScheduler::new(indexer_config.max_concurrent_indexing_batches)
+    }
+
+    /// Waits until a batch of the index `index_id` can be indexed and returns its permit.
+    pub async fn acquire_permit(&self, index_id: &str, weight: u32) -> FairSchedulerPermit {
+        let max_batches = match self.max_concurrent_batches_opt {
+            Some(max_batches) => max_batches,
+            None => return FairSchedulerPermit::unlimited(index_id),
+        };
+        let permit_rx = {
+            let mut state = self.state.lock().unwrap();
+            let system_virtual_time_nanos = state.system_virtual_time_nanos;
+            let index_share = state
+                .index_shares
+                .entry(index_id.to_string())
+                .or_insert_with(|| IndexShare {
+                    weight,
+                    virtual_time_nanos: system_virtual_time_nanos,
+                });
+            index_share.weight = weight.max(1);
+            index_share.virtual_time_nanos = index_share
+                .virtual_time_nanos
+                .max(system_virtual_time_nanos);
+            if state.num_running_batches < max_batches && state.waiters.is_empty() {
+                return state.new_permit(&self.state, index_id.to_string(), max_batches);
+            }
+            let (permit_tx, permit_rx) = oneshot::channel();
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.waiters.push(Waiter {
+                index_id: index_id.to_string(),
+                seq,
+                permit_tx,
+            });
+            permit_rx
+        };
+        permit_rx
+            .await
+            .expect("The fair scheduler should never drop a waiter.")
+    }
+
+    /// Returns the indexing time consumed by each index divided by its weight, in nanoseconds.
+    pub fn virtual_times_nanos(&self) -> HashMap<String, u64> {
+        self.state
+            .lock()
+            .unwrap()
+            .index_shares
+            .iter()
+            .map(|(index_id, index_share)| (index_id.clone(), index_share.virtual_time_nanos))
+            .collect()
+    }
+}
+
+/// Permit to index a batch, handed back to the [`FairScheduler`] when dropped. The time elapsed
+/// since the permit was granted is charged to its index.
+pub struct FairSchedulerPermit {
+    state_opt: Option<(Arc<Mutex<FairSchedulerState>>, usize)>,
+    index_id: String,
+    start: Instant,
+}
+
+impl FairSchedulerPermit {
+    fn unlimited(index_id: &str) -> Self {
+        FairSchedulerPermit {
+            state_opt: None,
+            index_id: index_id.to_string(),
+            start: Instant::now(),
+        }
+    }
+
+    fn disarm(&mut self) {
+        self.state_opt = None;
+    }
+}
+
+impl fmt::Debug for FairSchedulerPermit {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("FairSchedulerPermit")
+            .field("index_id", &self.index_id)
+            .field("is_limited", &self.state_opt.is_some())
+            .finish()
+    }
+}
+
+impl Drop for FairSchedulerPermit {
+    fn drop(&mut self) {
+        let (state_arc, max_batches) = match self.state_opt.take() {
+            Some(state) => state,
+            None => return,
+        };
+        let elapsed_nanos = self.start.elapsed().as_nanos() as u64;
+        let mut state = state_arc.lock().unwrap();
+        if let Some(index_share) = state.index_shares.get_mut(&self.index_id) {
+            index_share.virtual_time_nanos += elapsed_nanos / index_share.weight as u64;
+        }
+        state.num_running_batches -= 1;
+        state.grant_permits(&state_arc, max_batches);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fair_scheduler_limits_concurrent_batches() {
+        let fair_scheduler = FairScheduler::new(Some(1));
+        let permit = fair_scheduler.acquire_permit("index-a", 1).await;
+        assert!(tokio::time::timeout(
+            Duration::from_millis(50),
+            fair_scheduler.acquire_permit("index-b", 1)
+        )
+        .await
+        .is_err());
+        // The abandoned waiter does not hold on to the slot.
+        drop(permit);
+        tokio::time::timeout(
+            Duration::from_millis(50),
+            fair_scheduler.acquire_permit("index-b", 1),
+        )
+        .await
+        .unwrap();
+
+        let unlimited_scheduler = FairScheduler::default();
+        let _first_permit = unlimited_scheduler.acquire_permit("index-a", 1).await;
+        let _second_permit = unlimited_scheduler.acquire_permit("index-a", 1).await;
+    }
+
+    #[tokio::test]
+    async fn test_fair_scheduler_grants_least_served_index() {
+        let fair_scheduler = Arc::new(FairScheduler::new(Some(1)));
+        // Index A consumes indexing time while index B is idle.
+        let permit = fair_scheduler.acquire_permit("index-a", 1).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        drop(permit);
+        let permit = fair_scheduler.acquire_permit("index-b", 1).await;
+
+        let (granted_tx, mut granted_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut join_handles = Vec::new();
+        for index_id in ["index-a", "index-b"] {
+            let fair_scheduler = fair_scheduler.clone();
+            let granted_tx = granted_tx.clone();
+            join_handles.push(tokio::spawn(async move {
+                let _permit = fair_scheduler.acquire_permit(index_id, 1).await;
+                granted_tx.send(index_id).unwrap();
+            }));
+            // Index A queues up first.
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        drop(permit);
+        for join_handle in join_handles {
+            join_handle.await.unwrap();
+        }
+        // Index B consumed less indexing time than index A, so it goes first.
+        assert_eq!(granted_rx.recv().await, Some("index-b"));
+        assert_eq!(granted_rx.recv().await, Some("index-a"));
+    }
+
+    #[tokio::test]
+    async fn test_fair_scheduler_weights() {
+        let fair_scheduler = FairScheduler::new(Some(1));
+        for (index_id, weight) in [("index-a", 1), ("index-b", 4)] {
+            let permit = fair_scheduler.acquire_permit(index_id, weight).await;
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            drop(permit);
+        }
+        let virtual_times_nanos = fair_scheduler.virtual_times_nanos();
+        // Both indexes consumed the same indexing time, but index B is granted four times more.
+        assert!(virtual_times_nanos["index-a"] > virtual_times_nanos["index-b"]);
+    }
+}
@@ -17,6 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -27,7 +28,7 @@ use quickwit_storage::StorageError;
 use serde::Serialize;
 use thiserror::Error;
 use time::OffsetDateTime;
-use tracing::error;
+use tracing::{error, info};
 
 use crate::actors::GarbageCollector;
 use crate::split_store::IndexingSplitStore;
@@ -71,7 +72,8 @@ impl From<&SplitMetadata> for FileEntry {
 /// * `staged_grace_period` -  Threshold period after which a staged split can be safely garbage
 ///   collected.
 /// * `deletion_grace_period` -  Threshold period after which a marked as deleted split can be
-///   safely deleted.
+///   safely deleted. The files of a split replaced by a staged merge are kept until the merge is
+///   published or garbage collected.
 /// * `dry_run` - Should this only return a list of affected files without performing deletion.
 /// * `ctx_opt` - A context for reporting progress (only useful within quickwit actor).
 pub async fn run_garbage_collect(
@@ -124,10 +126,10 @@ pub async fn run_garbage_collect(
         .mark_splits_for_deletion(index_id, &split_ids)
         .await?;
 
-    // We wait another 2 minutes until the split is actually deleted.
+    // We wait for the deletion grace period until the split is actually deleted.
     let grace_period_deletion =
         OffsetDateTime::now_utc().unix_timestamp() - deletion_grace_period.as_secs() as i64;
-    let splits_to_delete = metastore
+    let candidate_splits: Vec<SplitMetadata> = metastore
         .list_splits(index_id, SplitState::MarkedForDeletion, None, None)
         .await?
         .into_iter()
@@ -135,6 +137,33 @@ pub async fn run_garbage_collect(
         .filter(|meta| meta.update_timestamp <= grace_period_deletion)
         .map(|meta| meta.split_metadata)
         .collect();
+    if candidate_splits.is_empty() {
+        return Ok(Vec::new());
+    }
+    if let Some(ctx) = ctx_opt {
+        ctx.record_progress();
+    }
+
+    // A merge may have staged its split after the candidates were selected. The splits it
+    // replaces are still read by the merge pipeline, so their deletion is deferred to a later
+    // pass.
+    let referenced_split_ids: HashSet<String> = metastore
+        .list_splits(index_id, SplitState::Staged, None, None)
+        .await?
+        .into_iter()
+        .flat_map(|meta| meta.split_metadata.replaced_split_ids)
+        .collect();
+    let (deferred_splits, splits_to_delete): (Vec<SplitMetadata>, Vec<SplitMetadata>) =
+        candidate_splits
+            .into_iter()
+            .partition(|split| referenced_split_ids.contains(split.split_id()));
+    if !deferred_splits.is_empty() {
+        let deferred_split_ids: Vec<&str> = deferred_splits
+            .iter()
+            .map(|split| split.split_id())
+            .collect();
+        info!(index_id = %index_id, deferred_split_ids = ?deferred_split_ids, "Deferring the deletion of splits replaced by staged merges.");
+    }
 
     let deleted_files = delete_splits_with_files(
         index_id,
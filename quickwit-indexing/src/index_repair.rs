@@ -0,0 +1,360 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::Arc;
+
+use quickwit_config::build_doc_mapper;
+use quickwit_directories::read_split_footer;
+use quickwit_doc_mapper::DocMapper;
+use quickwit_metastore::{IndexMetadata, Metastore, SplitMetadata};
+use quickwit_storage::Storage;
+use serde::Serialize;
+use tantivy::fastfield::FastFieldReader;
+use tantivy::schema::Type;
+use tantivy::{Index, ReloadPolicy, Searcher};
+use time::OffsetDateTime;
+use tracing::{info, warn};
+
+use crate::actors::{extract_split_field_stats, extract_split_tags, tag_fields};
+use crate::get_tantivy_directory_from_split_bundle;
+
+/// Number of restored splits published in the metastore at once.
+const REPAIR_SPLITS_BATCH_SIZE: usize = if cfg!(test) { 2 } else { 100 };
+
+/// Split restored in the metastore by [`run_index_repair`].
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct RestoredSplit {
+    pub split_id: String,
+    pub num_docs: usize,
+    pub time_range: Option<RangeInclusive<i64>>,
+    pub num_bytes: u64,
+}
+
+/// Outcome of [`run_index_repair`].
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct IndexRepair {
+    pub restored_splits: Vec<RestoredSplit>,
+    /// Split files that could not be read, for instance because their upload never completed.
+    pub unreadable_split_files: Vec<String>,
+}
+
+/// Scans the storage of an index for split files missing from the metastore, rebuilds their
+/// metadata from their content, and publishes them. This recovers the splits of an index whose
+/// metastore was lost, once the index was created again with the same index URI.
+///
+/// The number of documents, the time range, and the tags of the splits are recomputed from the
+/// split files. The source checkpoints are not restored.
+///
+/// * `index_metadata` - The metadata of the target index.
+/// * `storage` - The storage of the target index.
+/// * `metastore` - The metastore managing the target index.
+/// * `dry_run` - Should this only return the splits to restore without publishing them.
+pub async fn run_index_repair(
+    index_metadata: &IndexMetadata,
+    storage: Arc<dyn Storage>,
+    metastore: Arc<dyn Metastore>,
+    dry_run: bool,
+) -> anyhow::Result<IndexRepair> {
+    let index_id = &index_metadata.index_id;
+    let doc_mapper = build_doc_mapper(
+        &index_metadata.doc_mapping,
+        &index_metadata.search_settings,
+        &index_metadata.indexing_settings,
+    )?;
+    let known_split_ids: HashSet<String> = metastore
+        .list_all_splits(index_id)
+        .await?
+        .into_iter()
+        .map(|split| split.split_metadata.split_id)
+        .collect();
+    let missing_split_ids: Vec<String> = storage
+        .list_prefix(Path::new(""))
+        .await?
+        .into_iter()
+        .filter(|path| path.parent() == Some(Path::new("")))
+        .filter(|path| path.extension().and_then(|extension| extension.to_str()) == Some("split"))
+        .filter_map(|path| {
+            path.file_stem()
+                .and_then(|file_stem| file_stem.to_str())
+                .map(str::to_string)
+        })
+        .filter(|split_id| !known_split_ids.contains(split_id))
+        .collect();
+
+    let mut index_repair = IndexRepair::default();
+    for split_id_batch in missing_split_ids.chunks(REPAIR_SPLITS_BATCH_SIZE) {
+        let mut split_metadatas = Vec::new();
+        for split_id in split_id_batch {
+            match restore_split_metadata(index_metadata, &*doc_mapper, storage.clone(), split_id)
+                .await
+            {
+                Ok(split_metadata) => split_metadatas.push(split_metadata),
+                Err(error) => {
+                    warn!(index_id = %index_id, split_id = %split_id, error = ?error, "Failed to read split file.");
+                    index_repair
+                        .unreadable_split_files
+                        .push(quickwit_common::split_file(split_id));
+                }
+            }
+        }
+        index_repair
+            .restored_splits
+            .extend(split_metadatas.iter().map(|split_metadata| RestoredSplit {
+                split_id: split_metadata.split_id.clone(),
+                num_docs: split_metadata.num_docs,
+                time_range: split_metadata.time_range.clone(),
+                num_bytes: split_metadata.footer_offsets.end,
+            }));
+        if dry_run || split_metadatas.is_empty() {
+            continue;
+        }
+        let split_ids: Vec<String> = split_metadatas
+            .iter()
+            .map(|split_metadata| split_metadata.split_id.clone())
+            .collect();
+        for split_metadata in split_metadatas {
+            metastore.stage_split(index_id, split_metadata).await?;
+        }
+        let split_ids_ref: Vec<&str> = split_ids.iter().map(String::as_str).collect();
+        metastore
+            .publish_splits(index_id, &split_ids_ref, &[], None)
+            .await?;
+        info!(index_id = %index_id, num_splits = split_ids.len(), "restore-splits");
+    }
+    Ok(index_repair)
+}
+
+/// Downloads a split file and rebuilds its metadata.
+async fn restore_split_metadata(
+    index_metadata: &IndexMetadata,
+    doc_mapper: &dyn DocMapper,
+    storage: Arc<dyn Storage>,
+    split_id: &str,
+) -> anyhow::Result<SplitMetadata> {
+    let split_file = quickwit_common::split_file(split_id);
+    let split_path = Path::new(&split_file);
+    let num_bytes = storage.file_num_bytes(split_path).await?;
+    let (split_footer, _) = read_split_footer(storage.clone(), split_path).await?;
+
+    let scratch_directory = tempfile::tempdir()?;
+    let split_filepath = scratch_directory.path().join(&split_file);
+    storage.copy_to_file(split_path, &split_filepath).await?;
+    let split_directory = get_tantivy_directory_from_split_bundle(&split_filepath)?;
+    let index_reader = Index::open(split_directory)?
+        .reader_builder()
+        .reload_policy(ReloadPolicy::Manual)
+        .try_into()?;
+    let searcher = index_reader.searcher();
+
+    let time_range = match doc_mapper.timestamp_field_name() {
+        Some(timestamp_field_name) => split_time_range(&searcher, &timestamp_field_name)?,
+        None => None,
+    };
+    let tags = extract_split_tags(
+        &searcher,
+        &tag_fields(doc_mapper)?,
+        &index_metadata.indexing_settings.calendar_tags,
+        time_range.as_ref(),
+    )?;
+    Ok(SplitMetadata {
+        split_id: split_id.to_string(),
+        source_id: "unknown".to_string(),
+        node_id: "unknown".to_string(),
+        num_docs: searcher.num_docs() as usize,
+        // The size of the documents sent to the indexer is not recorded in the split file.
+        uncompressed_docs_size_in_bytes: num_bytes,
+        time_range,
+        create_timestamp: OffsetDateTime::now_utc().unix_timestamp(),
+        tags,
+        field_stats: extract_split_field_stats(&searcher)?,
+        footer_offsets: num_bytes - split_footer.len() as u64..num_bytes,
+        ..Default::default()
+    })
+}
+
+/// Returns the range of the timestamps of the documents of a split, read from the bounds of the
+/// timestamp fast field of its segments.
+fn split_time_range(
+    searcher: &Searcher,
+    timestamp_field_name: &str,
+) -> anyhow::Result<Option<RangeInclusive<i64>>> {
+    let timestamp_field = match searcher.schema().get_field(timestamp_field_name) {
+        Some(timestamp_field) => timestamp_field,
+        None => return Ok(None),
+    };
+    let value_type = searcher
+        .schema()
+        .get_field_entry(timestamp_field)
+        .field_type()
+        .value_type();
+    let mut time_range_opt: Option<RangeInclusive<i64>> = None;
+    for segment_reader in searcher.segment_readers() {
+        if segment_reader.num_docs() == 0 {
+            continue;
+        }
+        let fast_fields = segment_reader.fast_fields();
+        let (min_timestamp, max_timestamp) = match value_type {
+            Type::I64 => {
+                let fast_field_reader = fast_fields.i64(timestamp_field)?;
+                (fast_field_reader.min_value(), fast_field_reader.max_value())
+            }
+            Type::Date => {
+                let fast_field_reader = fast_fields.date(timestamp_field)?;
+                (
+                    fast_field_reader.min_value().into_timestamp_secs(),
+                    fast_field_reader.max_value().into_timestamp_secs(),
+                )
+            }
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Timestamp field `{}` must be of type i64 or datetime.",
+                    timestamp_field_name
+                ))
+            }
+        };
+        time_range_opt = Some(match time_range_opt {
+            Some(time_range) => {
+                min_timestamp.min(*time_range.start())..=max_timestamp.max(*time_range.end())
+            }
+            None => min_timestamp..=max_timestamp,
+        });
+    }
+    Ok(time_range_opt)
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_metastore::SplitState;
+
+    use super::*;
+    use crate::TestSandbox;
+
+    #[tokio::test]
+    async fn test_run_index_repair() -> anyhow::Result<()> {
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: tenant
+                type: text
+                tokenizer: raw
+              - name: body
+                type: text
+              - name: ts
+                type: i64
+                fast: true
+            tag_fields: [tenant]
+        "#;
+        let indexing_settings_yaml = r#"
+            timestamp_field: ts
+        "#;
+        let test_sandbox = TestSandbox::create(
+            "test-index-repair",
+            doc_mapping_yaml,
+            indexing_settings_yaml,
+            &["body"],
+        )
+        .await?;
+        for (tenant, ts) in [("foo", 10), ("bar", 20), ("baz", 30)] {
+            test_sandbox
+                .add_documents(vec![
+                    serde_json::json!({"tenant": tenant, "body": "hello", "ts": ts}),
+                    serde_json::json!({"tenant": tenant, "body": "world", "ts": ts + 5}),
+                ])
+                .await?;
+        }
+        let metastore = test_sandbox.metastore();
+        let index_metadata = metastore.index_metadata("test-index-repair").await?;
+        let mut published_splits: Vec<SplitMetadata> = metastore
+            .list_splits("test-index-repair", SplitState::Published, None, None)
+            .await?
+            .into_iter()
+            .map(|split| split.split_metadata)
+            .collect();
+        published_splits.sort_by(|left, right| left.split_id.cmp(&right.split_id));
+
+        // The metastore is up to date.
+        let index_repair = run_index_repair(
+            &index_metadata,
+            test_sandbox.storage(),
+            metastore.clone(),
+            false,
+        )
+        .await?;
+        assert!(index_repair.restored_splits.is_empty());
+
+        // The metastore loses track of the splits, but not their files.
+        let split_ids: Vec<&str> = published_splits
+            .iter()
+            .map(|split| split.split_id())
+            .collect();
+        metastore
+            .mark_splits_for_deletion("test-index-repair", &split_ids)
+            .await?;
+        metastore
+            .delete_splits("test-index-repair", &split_ids)
+            .await?;
+        test_sandbox
+            .storage()
+            .put(Path::new("truncated.split"), Box::new(b"split".to_vec()))
+            .await?;
+
+        let index_repair = run_index_repair(
+            &index_metadata,
+            test_sandbox.storage(),
+            metastore.clone(),
+            true,
+        )
+        .await?;
+        assert_eq!(index_repair.restored_splits.len(), 3);
+        assert_eq!(index_repair.unreadable_split_files, ["truncated.split"]);
+        assert!(metastore
+            .list_all_splits("test-index-repair")
+            .await?
+            .is_empty());
+
+        run_index_repair(
+            &index_metadata,
+            test_sandbox.storage(),
+            metastore.clone(),
+            false,
+        )
+        .await?;
+        let mut restored_splits: Vec<SplitMetadata> = metastore
+            .list_splits("test-index-repair", SplitState::Published, None, None)
+            .await?
+            .into_iter()
+            .map(|split| split.split_metadata)
+            .collect();
+        restored_splits.sort_by(|left, right| left.split_id.cmp(&right.split_id));
+        assert_eq!(restored_splits.len(), 3);
+        for (restored_split, published_split) in restored_splits.iter().zip(&published_splits) {
+            assert_eq!(restored_split.split_id, published_split.split_id);
+            assert_eq!(restored_split.num_docs, published_split.num_docs);
+            assert_eq!(restored_split.time_range, published_split.time_range);
+            assert_eq!(restored_split.tags, published_split.tags);
+            assert_eq!(
+                restored_split.footer_offsets,
+                published_split.footer_offsets
+            );
+        }
+        Ok(())
+    }
+}
@@ -22,6 +22,7 @@ use std::sync::Arc;
 use itertools::Itertools;
 use quickwit_actors::{Mailbox, Universe};
 use quickwit_config::QuickwitConfig;
+use quickwit_directories::LocalSplitRegistry;
 use quickwit_ingest_api::{get_ingest_api_service, QUEUES_DIR_NAME};
 use quickwit_metastore::Metastore;
 use quickwit_storage::StorageUriResolver;
@@ -39,10 +40,17 @@ pub use crate::split_store::{
 
 pub mod actors;
 mod controlled_directory;
+mod fair_scheduler;
 mod garbage_collection;
+mod index_repair;
 pub mod merge_policy;
+mod merge_policy_factory;
+mod merge_policy_preview;
+mod merge_scheduler;
+mod metrics;
 pub mod models;
 pub mod source;
+mod split_retagging;
 mod split_store;
 #[cfg(any(test, feature = "testsuite"))]
 mod test_utils;
@@ -50,11 +58,24 @@ mod test_utils;
 #[cfg(any(test, feature = "testsuite"))]
 pub use test_utils::{mock_split, mock_split_meta, TestSandbox};
 
+pub use self::fair_scheduler::{FairScheduler, FairSchedulerPermit};
 pub use self::garbage_collection::{
     delete_splits_with_files, run_garbage_collect, FileEntry, SplitDeletionError,
 };
-use self::merge_policy::{MergePolicy, StableMultitenantWithTimestampMergePolicy};
+pub use self::index_repair::{run_index_repair, IndexRepair, RestoredSplit};
+use self::merge_policy::MergePolicy;
+pub use self::merge_policy_factory::{
+    load_merge_policy, register_merge_policy, MergePolicyFactory, MergePolicyLoader,
+    MergePolicyLoaderError, TypedMergePolicyFactory,
+};
+pub use self::merge_policy_preview::{
+    preview_merge_policy, run_merge_policy_preview, MergePolicyPreview, MergeSettingsOverrides,
+    PlannedMergeOperation,
+};
+pub use self::merge_scheduler::{MergePermit, MergeScheduler};
+pub use self::metrics::INDEXER_METRICS;
 pub use self::source::check_source_connectivity;
+pub use self::split_retagging::{run_split_retagging, SplitRetagging};
 
 pub fn new_split_id() -> String {
     ulid::Ulid::new().to_string()
@@ -66,6 +87,7 @@ pub async fn start_indexing_service(
     metastore: Arc<dyn Metastore>,
     storage_resolver: StorageUriResolver,
     enable_ingest_api: bool,
+    local_split_registry: LocalSplitRegistry,
 ) -> anyhow::Result<Mailbox<IndexingService>> {
     info!("Starting indexer service.");
     // Spawn indexing service.
@@ -76,7 +98,8 @@ pub async fn start_indexing_service(
         metastore.clone(),
         storage_resolver,
         enable_ingest_api,
-    );
+    )
+    .with_local_split_registry(local_split_registry);
     let (indexing_service, _) = universe.spawn_actor(indexing_service).spawn();
 
     // List indexes and spawn indexing pipeline(s) for each of them.
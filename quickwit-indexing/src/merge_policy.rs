@@ -18,24 +18,87 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::cmp::Reverse;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::ops::Range;
 
 use quickwit_metastore::SplitMetadata;
+use serde::Serialize;
 use tracing::debug;
 
 use crate::new_split_id;
 
+/// Type of an operation planned by a merge policy.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeOperationType {
+    /// Merges the splits into a single split.
+    Merge,
+    /// Merges the splits and redistributes their documents into several splits according to
+    /// the value of a demux field.
+    Demux,
+    /// Rewrites a single split to reclaim space, for instance after documents were deleted.
+    Compact,
+    /// Applies the pending delete tasks to the splits.
+    DeleteApply,
+    /// Rewrites the doc store of a single split with the docstore compression settings of the
+    /// index, preserving its doc ids and fast fields.
+    Recompress,
+}
+
+/// Describes the operation types a merge executor is able to run.
+///
+/// The merge planner negotiates the operations with the executor: merge policies are given the
+/// capabilities of the executor and the operations that are not supported are never sent.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MergeExecutorCapabilities {
+    supported_operation_types: Vec<MergeOperationType>,
+}
+
+impl MergeExecutorCapabilities {
+    pub fn new(
+        supported_operation_types: impl IntoIterator<Item = MergeOperationType>,
+    ) -> MergeExecutorCapabilities {
+        MergeExecutorCapabilities {
+            supported_operation_types: supported_operation_types.into_iter().collect(),
+        }
+    }
+
+    /// Capabilities of an executor that can only merge splits.
+    pub fn merge_only() -> MergeExecutorCapabilities {
+        MergeExecutorCapabilities::new([MergeOperationType::Merge])
+    }
+
+    pub fn supports(&self, operation_type: MergeOperationType) -> bool {
+        self.supported_operation_types.contains(&operation_type)
+    }
+}
+
+impl Default for MergeExecutorCapabilities {
+    fn default() -> Self {
+        MergeExecutorCapabilities::merge_only()
+    }
+}
+
 pub struct MergeOperation {
     pub merge_split_id: String,
     pub splits: Vec<SplitMetadata>,
+    pub operation_type: MergeOperationType,
 }
 
 impl MergeOperation {
     pub fn new_merge_operation(splits: Vec<SplitMetadata>) -> MergeOperation {
+        MergeOperation::new_operation(MergeOperationType::Merge, splits)
+    }
+
+    pub fn new_operation(
+        operation_type: MergeOperationType,
+        splits: Vec<SplitMetadata>,
+    ) -> MergeOperation {
         Self {
             merge_split_id: new_split_id(),
             splits,
+            operation_type,
         }
     }
 
@@ -46,7 +109,11 @@ impl MergeOperation {
 
 impl fmt::Debug for MergeOperation {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Merge(merged_split_id={},splits=[", self.merge_split_id)?;
+        write!(
+            f,
+            "{:?}(merged_split_id={},splits=[",
+            self.operation_type, self.merge_split_id
+        )?;
         for split in &self.splits {
             write!(f, "{},", split.split_id())?;
         }
@@ -62,6 +129,22 @@ impl fmt::Debug for MergeOperation {
 pub trait MergePolicy: Send + Sync + fmt::Debug {
     /// Returns the list of merge operations that should be performed.
     fn operations(&self, splits: &mut Vec<SplitMetadata>) -> Vec<MergeOperation>;
+    /// Returns the list of operations that should be performed, restricted to the operation
+    /// types supported by the executor.
+    ///
+    /// The default implementation adapts policies that only plan merges: it returns the
+    /// operations of [`MergePolicy::operations`] if the executor supports merges. Policies
+    /// planning other operation types (demux, compaction, deletes) override this method.
+    fn plan_operations(
+        &self,
+        splits: &mut Vec<SplitMetadata>,
+        executor_capabilities: &MergeExecutorCapabilities,
+    ) -> Vec<MergeOperation> {
+        if !executor_capabilities.supports(MergeOperationType::Merge) {
+            return Vec::new();
+        }
+        self.operations(splits)
+    }
     /// A mature split is a split that won't undergo a merge operation in the future.
     fn is_mature(&self, split: &SplitMetadata) -> bool;
 }
@@ -375,6 +458,118 @@ impl StableMultitenantWithTimestampMergePolicy {
     }
 }
 
+/// TimeBucketMergePolicy divides time into buckets of constant duration and only merges splits
+/// whose time range lies within the same bucket. Merges never cross a bucket boundary, so time
+/// pruning stays efficient even when the documents are not ingested in time order, and the splits
+/// of a bucket can be dropped as a whole once it falls out of the retention period. The splits
+/// straddling a bucket boundary are never merged.
+///
+/// Within a bucket, the smallest splits are merged first, by groups of `merge_factor` to
+/// `max_merge_factor` splits. A group with fewer than `merge_factor` splits is merged anyway if
+/// the merged split reaches `split_num_docs_target`. Splits without a time range are merged
+/// together as if they belonged to the same bucket.
+#[derive(Clone, Debug)]
+pub struct TimeBucketMergePolicy {
+    pub merge_enabled: bool,
+    pub bucket_duration_secs: u64,
+    pub merge_factor: usize,
+    pub max_merge_factor: usize,
+    /// Splits that contain a number of documents greater than or equal to
+    /// `split_num_docs_target` are considered mature and never merged.
+    pub split_num_docs_target: usize,
+}
+
+impl Default for TimeBucketMergePolicy {
+    fn default() -> Self {
+        TimeBucketMergePolicy {
+            merge_enabled: true,
+            bucket_duration_secs: 86_400,
+            merge_factor: 10,
+            max_merge_factor: 12,
+            split_num_docs_target: 10_000_000,
+        }
+    }
+}
+
+impl TimeBucketMergePolicy {
+    fn bucket(&self, timestamp: i64) -> i64 {
+        timestamp.div_euclid(self.bucket_duration_secs.max(1) as i64)
+    }
+
+    fn split_bucket(&self, split: &SplitMetadata) -> Option<i64> {
+        split
+            .time_range
+            .as_ref()
+            .map(|time_range| self.bucket(*time_range.end()))
+    }
+
+    fn straddles_bucket_boundary(&self, split: &SplitMetadata) -> bool {
+        split.time_range.as_ref().map_or(false, |time_range| {
+            self.bucket(*time_range.start()) != self.bucket(*time_range.end())
+        })
+    }
+
+    /// Returns the number of splits to merge at the beginning of `bucket_splits`, sorted by
+    /// increasing number of documents, or `None` if they are not worth merging yet.
+    fn merge_candidate_len(&self, bucket_splits: &[SplitMetadata]) -> Option<usize> {
+        let mut num_splits_in_merge = 0;
+        let mut num_docs_in_merge = 0;
+        for split in bucket_splits {
+            if num_splits_in_merge >= self.max_merge_factor
+                || num_docs_in_merge >= self.split_num_docs_target
+            {
+                break;
+            }
+            num_splits_in_merge += 1;
+            num_docs_in_merge += split.num_docs;
+        }
+        if num_splits_in_merge < 2 {
+            return None;
+        }
+        if num_splits_in_merge < self.merge_factor && num_docs_in_merge < self.split_num_docs_target
+        {
+            return None;
+        }
+        Some(num_splits_in_merge)
+    }
+}
+
+impl MergePolicy for TimeBucketMergePolicy {
+    fn operations(&self, splits: &mut Vec<SplitMetadata>) -> Vec<MergeOperation> {
+        if !self.merge_enabled || splits.len() < 2 {
+            return Vec::new();
+        }
+        let splits_not_for_merge = remove_matching_items(splits, |split| self.is_mature(split));
+
+        let mut bucket_to_splits: BTreeMap<Option<i64>, Vec<SplitMetadata>> = BTreeMap::new();
+        for split in splits.drain(..) {
+            bucket_to_splits
+                .entry(self.split_bucket(&split))
+                .or_default()
+                .push(split);
+        }
+        let mut merge_operations: Vec<MergeOperation> = Vec::new();
+        for (bucket, mut bucket_splits) in bucket_to_splits {
+            bucket_splits.sort_by_key(|split| split.num_docs);
+            debug!(bucket=?bucket, splits=?splits_short_debug(&bucket_splits[..]), "merge-policy-run");
+            while let Some(merge_len) = self.merge_candidate_len(&bucket_splits) {
+                let splits_in_merge: Vec<SplitMetadata> =
+                    bucket_splits.drain(..merge_len).collect();
+                merge_operations.push(MergeOperation::new_merge_operation(splits_in_merge));
+            }
+            splits.extend(bucket_splits);
+        }
+        splits.extend(splits_not_for_merge);
+        merge_operations
+    }
+
+    fn is_mature(&self, split: &SplitMetadata) -> bool {
+        !self.merge_enabled
+            || split.num_docs >= self.split_num_docs_target
+            || self.straddles_bucket_boundary(split)
+    }
+}
+
 fn is_sorted(els: &[usize]) -> bool {
     els.windows(2).all(|w| w[0] <= w[1])
 }
@@ -665,4 +860,154 @@ mod tests {
         assert_eq!(splits.len(), 10);
         assert_eq!(merge_ops.len(), 0);
     }
+
+    #[test]
+    fn test_time_bucket_merge_policy_merges_splits_of_the_same_bucket() {
+        let merge_policy = TimeBucketMergePolicy {
+            bucket_duration_secs: 3_600,
+            merge_factor: 3,
+            max_merge_factor: 4,
+            ..Default::default()
+        };
+        let mut splits = create_splits_with_timestamps(vec![
+            (100, 0..=3_599),
+            (200, 10..=20),
+            (300, 3_000..=3_100),
+            (400, 100..=200),
+            (500, 3_700..=3_800),
+            (600, 7_200..=7_300),
+        ]);
+        let merge_ops = merge_policy.operations(&mut splits);
+        assert_eq!(merge_ops.len(), 1);
+        let merged_split_ids: Vec<&str> = merge_ops[0]
+            .splits_as_slice()
+            .iter()
+            .map(|split| split.split_id())
+            .collect();
+        assert_eq!(
+            merged_split_ids,
+            &["split_00", "split_01", "split_02", "split_03"]
+        );
+        assert_eq!(splits.len(), 2);
+    }
+
+    #[test]
+    fn test_time_bucket_merge_policy_never_merges_across_bucket_boundaries() {
+        let merge_policy = TimeBucketMergePolicy {
+            bucket_duration_secs: 3_600,
+            merge_factor: 2,
+            max_merge_factor: 2,
+            ..Default::default()
+        };
+        let mut splits = create_splits_with_timestamps(vec![
+            (100, 3_500..=3_600),
+            (100, 3_700..=3_800),
+            (100, 3_599..=3_599),
+        ]);
+        assert!(merge_policy.is_mature(&splits[0]));
+        assert!(!merge_policy.is_mature(&splits[1]));
+        let merge_ops = merge_policy.operations(&mut splits);
+        assert!(merge_ops.is_empty());
+        assert_eq!(splits.len(), 3);
+    }
+
+    #[test]
+    fn test_time_bucket_merge_policy_smallest_splits_first() {
+        let merge_policy = TimeBucketMergePolicy {
+            merge_factor: 2,
+            max_merge_factor: 2,
+            split_num_docs_target: 1_000,
+            ..Default::default()
+        };
+        let mut splits = create_splits(vec![500, 100, 2_000, 300, 50]);
+        let merge_ops = merge_policy.operations(&mut splits);
+        assert_eq!(merge_ops.len(), 2);
+        let merged_num_docs: Vec<Vec<usize>> = merge_ops
+            .iter()
+            .map(|merge_op| {
+                merge_op
+                    .splits_as_slice()
+                    .iter()
+                    .map(|split| split.num_docs)
+                    .collect()
+            })
+            .collect();
+        assert_eq!(merged_num_docs, vec![vec![50, 100], vec![300, 500]]);
+        // The mature split is left untouched.
+        assert_eq!(splits.len(), 1);
+        assert_eq!(splits[0].num_docs, 2_000);
+    }
+
+    #[test]
+    fn test_time_bucket_merge_policy_merges_below_merge_factor_when_reaching_target() {
+        let merge_policy = TimeBucketMergePolicy {
+            split_num_docs_target: 1_000,
+            ..Default::default()
+        };
+        let mut splits = create_splits(vec![400, 700, 10]);
+        let merge_ops = merge_policy.operations(&mut splits);
+        assert_eq!(merge_ops.len(), 1);
+        assert_eq!(merge_ops[0].splits_as_slice().len(), 3);
+        assert!(splits.is_empty());
+
+        let mut splits = create_splits(vec![400, 10]);
+        assert!(merge_policy.operations(&mut splits).is_empty());
+        assert_eq!(splits.len(), 2);
+    }
+
+    #[test]
+    fn test_time_bucket_merge_policy_splits_without_time_range() {
+        let merge_policy = TimeBucketMergePolicy {
+            merge_factor: 2,
+            ..Default::default()
+        };
+        let mut splits = create_splits(vec![100, 100]);
+        for split in &mut splits {
+            split.time_range = None;
+        }
+        splits.extend(create_splits(vec![100]));
+        let merge_ops = merge_policy.operations(&mut splits);
+        assert_eq!(merge_ops.len(), 1);
+        assert!(merge_ops[0]
+            .splits_as_slice()
+            .iter()
+            .all(|split| split.time_range.is_none()));
+        assert_eq!(splits.len(), 1);
+    }
+
+    #[test]
+    fn test_merge_executor_capabilities() {
+        let capabilities = MergeExecutorCapabilities::default();
+        assert_eq!(capabilities, MergeExecutorCapabilities::merge_only());
+        assert!(capabilities.supports(MergeOperationType::Merge));
+        assert!(!capabilities.supports(MergeOperationType::Demux));
+        assert!(!capabilities.supports(MergeOperationType::Compact));
+        assert!(!capabilities.supports(MergeOperationType::DeleteApply));
+
+        let capabilities = MergeExecutorCapabilities::new([
+            MergeOperationType::Merge,
+            MergeOperationType::DeleteApply,
+        ]);
+        assert!(capabilities.supports(MergeOperationType::DeleteApply));
+        assert!(!capabilities.supports(MergeOperationType::Demux));
+    }
+
+    #[test]
+    fn test_merge_policy_plan_operations_adapter() {
+        let merge_policy = StableMultitenantWithTimestampMergePolicy::default();
+        {
+            let mut splits = create_splits(vec![100; 10]);
+            let capabilities = MergeExecutorCapabilities::merge_only();
+            let merge_ops = merge_policy.plan_operations(&mut splits, &capabilities);
+            assert_eq!(merge_ops.len(), 1);
+            assert_eq!(merge_ops[0].operation_type, MergeOperationType::Merge);
+        }
+        {
+            let mut splits = create_splits(vec![100; 10]);
+            let capabilities = MergeExecutorCapabilities::new([MergeOperationType::Compact]);
+            let merge_ops = merge_policy.plan_operations(&mut splits, &capabilities);
+            assert!(merge_ops.is_empty());
+            assert_eq!(splits.len(), 10);
+        }
+    }
 }
@@ -0,0 +1,339 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use anyhow::bail;
+use itertools::Itertools;
+use once_cell::sync::OnceCell;
+use quickwit_config::{IndexingSettings, MergePolicy as MergePolicyConfig};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::merge_policy::{
+    MergePolicy, StableMultitenantWithTimestampMergePolicy, TimeBucketMergePolicy,
+};
+
+pub trait MergePolicyFactory: 'static + Send + Sync {
+    fn create_merge_policy(
+        &self,
+        indexing_settings: &IndexingSettings,
+    ) -> anyhow::Result<Arc<dyn MergePolicy>>;
+}
+
+pub trait TypedMergePolicyFactory: Send + Sync + 'static {
+    type MergePolicy: MergePolicy + 'static;
+    type Params: serde::de::DeserializeOwned;
+    fn typed_create_merge_policy(
+        indexing_settings: &IndexingSettings,
+        params: Self::Params,
+    ) -> anyhow::Result<Self::MergePolicy>;
+}
+
+impl<T: TypedMergePolicyFactory> MergePolicyFactory for T {
+    fn create_merge_policy(
+        &self,
+        indexing_settings: &IndexingSettings,
+    ) -> anyhow::Result<Arc<dyn MergePolicy>> {
+        let params_json = serde_json::Value::Object(indexing_settings.merge_policy.params.clone());
+        let typed_params: T::Params = serde_json::from_value(params_json)?;
+        let merge_policy = Self::typed_create_merge_policy(indexing_settings, typed_params)?;
+        Ok(Arc::new(merge_policy))
+    }
+}
+
+#[derive(Default)]
+pub struct MergePolicyLoader {
+    type_to_factory: HashMap<String, Arc<dyn MergePolicyFactory>>,
+}
+
+#[derive(Error, Debug)]
+pub enum MergePolicyLoaderError {
+    #[error(
+        "Unknown merge policy type `{requested_merge_policy_type}` (available merge policy types \
+         are {available_merge_policy_types})."
+    )]
+    UnknownMergePolicyType {
+        requested_merge_policy_type: String,
+        available_merge_policy_types: String, //< a comma separated list of the available types.
+    },
+    #[error("Failed to create merge policy of type `{merge_policy_type}`. Cause: {error:?}")]
+    FailedToCreateMergePolicy {
+        merge_policy_type: String,
+        #[source]
+        error: anyhow::Error,
+    },
+}
+
+impl MergePolicyLoader {
+    pub fn add_merge_policy<S: ToString, F: MergePolicyFactory>(
+        &mut self,
+        merge_policy_type: S,
+        factory: F,
+    ) {
+        self.type_to_factory
+            .insert(merge_policy_type.to_string(), Arc::new(factory));
+    }
+
+    pub fn load_merge_policy(
+        &self,
+        indexing_settings: &IndexingSettings,
+    ) -> Result<Arc<dyn MergePolicy>, MergePolicyLoaderError> {
+        let merge_policy_type = &indexing_settings.merge_policy.merge_policy_type;
+        let factory = self.type_to_factory.get(merge_policy_type).ok_or_else(|| {
+            MergePolicyLoaderError::UnknownMergePolicyType {
+                requested_merge_policy_type: merge_policy_type.clone(),
+                available_merge_policy_types: self.type_to_factory.keys().sorted().join(", "),
+            }
+        })?;
+        factory
+            .create_merge_policy(indexing_settings)
+            .map_err(|error| MergePolicyLoaderError::FailedToCreateMergePolicy {
+                merge_policy_type: merge_policy_type.clone(),
+                error,
+            })
+    }
+}
+
+fn merge_policy_loader() -> &'static RwLock<MergePolicyLoader> {
+    static MERGE_POLICY_LOADER: OnceCell<RwLock<MergePolicyLoader>> = OnceCell::new();
+    MERGE_POLICY_LOADER.get_or_init(|| {
+        let mut merge_policy_loader = MergePolicyLoader::default();
+        merge_policy_loader.add_merge_policy(
+            MergePolicyConfig::STABLE_MULTITENANT_TYPE,
+            StableMultitenantMergePolicyFactory,
+        );
+        merge_policy_loader.add_merge_policy("time_bucket", TimeBucketMergePolicyFactory);
+        RwLock::new(merge_policy_loader)
+    })
+}
+
+/// Registers a merge policy factory, selected by the indexing pipelines of the indexes whose
+/// `merge_policy.type` is `merge_policy_type`. Registering a factory under the type of a
+/// registered factory replaces it.
+pub fn register_merge_policy<S: ToString, F: MergePolicyFactory>(merge_policy_type: S, factory: F) {
+    merge_policy_loader()
+        .write()
+        .unwrap()
+        .add_merge_policy(merge_policy_type, factory);
+}
+
+/// Creates the merge policy of `indexing_settings` with the registered merge policy factories.
+pub fn load_merge_policy(
+    indexing_settings: &IndexingSettings,
+) -> Result<Arc<dyn MergePolicy>, MergePolicyLoaderError> {
+    merge_policy_loader()
+        .read()
+        .unwrap()
+        .load_merge_policy(indexing_settings)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StableMultitenantMergePolicyParams {}
+
+pub struct StableMultitenantMergePolicyFactory;
+
+impl TypedMergePolicyFactory for StableMultitenantMergePolicyFactory {
+    type MergePolicy = StableMultitenantWithTimestampMergePolicy;
+    type Params = StableMultitenantMergePolicyParams;
+
+    fn typed_create_merge_policy(
+        indexing_settings: &IndexingSettings,
+        _params: StableMultitenantMergePolicyParams,
+    ) -> anyhow::Result<StableMultitenantWithTimestampMergePolicy> {
+        Ok(StableMultitenantWithTimestampMergePolicy {
+            merge_enabled: indexing_settings.merge_enabled,
+            merge_factor: indexing_settings.merge_policy.merge_factor,
+            max_merge_factor: indexing_settings.merge_policy.max_merge_factor,
+            split_num_docs_target: indexing_settings.split_num_docs_target,
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeBucket {
+    Hour,
+    Day,
+}
+
+impl TimeBucket {
+    fn duration_secs(&self) -> u64 {
+        match self {
+            TimeBucket::Hour => 3_600,
+            TimeBucket::Day => 86_400,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimeBucketMergePolicyParams {
+    /// Calendar bucket, aligned on UTC hours or days.
+    #[serde(default)]
+    pub bucket: Option<TimeBucket>,
+    #[serde(default)]
+    pub bucket_duration_secs: Option<u64>,
+}
+
+impl TimeBucketMergePolicyParams {
+    fn bucket_duration_secs(&self) -> anyhow::Result<u64> {
+        match (self.bucket, self.bucket_duration_secs) {
+            (Some(_), Some(_)) => bail!(
+                "Merge policy parameters `bucket` and `bucket_duration_secs` are mutually \
+                 exclusive."
+            ),
+            (Some(bucket), None) => Ok(bucket.duration_secs()),
+            (None, Some(bucket_duration_secs)) => Ok(bucket_duration_secs),
+            (None, None) => Ok(TimeBucket::Day.duration_secs()),
+        }
+    }
+}
+
+pub struct TimeBucketMergePolicyFactory;
+
+impl TypedMergePolicyFactory for TimeBucketMergePolicyFactory {
+    type MergePolicy = TimeBucketMergePolicy;
+    type Params = TimeBucketMergePolicyParams;
+
+    fn typed_create_merge_policy(
+        indexing_settings: &IndexingSettings,
+        params: TimeBucketMergePolicyParams,
+    ) -> anyhow::Result<TimeBucketMergePolicy> {
+        let bucket_duration_secs = params.bucket_duration_secs()?;
+        if bucket_duration_secs == 0 {
+            bail!("Merge policy `bucket_duration_secs` must be strictly positive.");
+        }
+        Ok(TimeBucketMergePolicy {
+            merge_enabled: indexing_settings.merge_enabled,
+            bucket_duration_secs,
+            merge_factor: indexing_settings.merge_policy.merge_factor,
+            max_merge_factor: indexing_settings.merge_policy.max_merge_factor,
+            split_num_docs_target: indexing_settings.split_num_docs_target,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_metastore::SplitMetadata;
+    use serde_json::json;
+
+    use super::*;
+    use crate::merge_policy::MergeOperation;
+
+    fn indexing_settings_for_test(
+        merge_policy_type: &str,
+        params: serde_json::Value,
+    ) -> IndexingSettings {
+        let mut indexing_settings = IndexingSettings::default();
+        indexing_settings.merge_policy.merge_policy_type = merge_policy_type.to_string();
+        indexing_settings.merge_policy.params = params.as_object().unwrap().clone();
+        indexing_settings
+    }
+
+    #[test]
+    fn test_load_builtin_merge_policies() {
+        let merge_policy = load_merge_policy(&IndexingSettings::default()).unwrap();
+        assert!(format!("{:?}", merge_policy).starts_with("StableMultitenantWithTimestamp"));
+
+        let indexing_settings =
+            indexing_settings_for_test("time_bucket", json!({"bucket_duration_secs": 3600}));
+        let merge_policy = load_merge_policy(&indexing_settings).unwrap();
+        assert!(format!("{:?}", merge_policy).contains("bucket_duration_secs: 3600"));
+
+        let indexing_settings = indexing_settings_for_test("time_bucket", json!({}));
+        let merge_policy = load_merge_policy(&indexing_settings).unwrap();
+        assert!(format!("{:?}", merge_policy).contains("bucket_duration_secs: 86400"));
+
+        let indexing_settings =
+            indexing_settings_for_test("time_bucket", json!({"bucket": "hour"}));
+        let merge_policy = load_merge_policy(&indexing_settings).unwrap();
+        assert!(format!("{:?}", merge_policy).contains("bucket_duration_secs: 3600"));
+    }
+
+    #[test]
+    fn test_load_merge_policy_errors() {
+        let indexing_settings = indexing_settings_for_test("unknown", json!({}));
+        assert!(matches!(
+            load_merge_policy(&indexing_settings).unwrap_err(),
+            MergePolicyLoaderError::UnknownMergePolicyType { .. }
+        ));
+        let indexing_settings =
+            indexing_settings_for_test("time_bucket", json!({"bucket_duration_secs": 0}));
+        assert!(matches!(
+            load_merge_policy(&indexing_settings).unwrap_err(),
+            MergePolicyLoaderError::FailedToCreateMergePolicy { .. }
+        ));
+        for params in [
+            json!({"bucket": "week"}),
+            json!({"bucket": "day", "bucket_duration_secs": 3600}),
+        ] {
+            let indexing_settings = indexing_settings_for_test("time_bucket", params);
+            assert!(matches!(
+                load_merge_policy(&indexing_settings).unwrap_err(),
+                MergePolicyLoaderError::FailedToCreateMergePolicy { .. }
+            ));
+        }
+        let indexing_settings = indexing_settings_for_test(
+            MergePolicyConfig::STABLE_MULTITENANT_TYPE,
+            json!({"foo": 1}),
+        );
+        assert!(matches!(
+            load_merge_policy(&indexing_settings).unwrap_err(),
+            MergePolicyLoaderError::FailedToCreateMergePolicy { .. }
+        ));
+    }
+
+    #[derive(Debug)]
+    struct NeverMergePolicy;
+
+    impl MergePolicy for NeverMergePolicy {
+        fn operations(&self, _splits: &mut Vec<SplitMetadata>) -> Vec<MergeOperation> {
+            Vec::new()
+        }
+
+        fn is_mature(&self, _split: &SplitMetadata) -> bool {
+            true
+        }
+    }
+
+    struct NeverMergePolicyFactory;
+
+    impl MergePolicyFactory for NeverMergePolicyFactory {
+        fn create_merge_policy(
+            &self,
+            _indexing_settings: &IndexingSettings,
+        ) -> anyhow::Result<Arc<dyn MergePolicy>> {
+            Ok(Arc::new(NeverMergePolicy))
+        }
+    }
+
+    #[test]
+    fn test_register_custom_merge_policy() {
+        let indexing_settings = indexing_settings_for_test("never", json!({}));
+        assert!(load_merge_policy(&indexing_settings).is_err());
+        register_merge_policy("never", NeverMergePolicyFactory);
+        let merge_policy = load_merge_policy(&indexing_settings).unwrap();
+        assert!(merge_policy.is_mature(&SplitMetadata::default()));
+    }
+}
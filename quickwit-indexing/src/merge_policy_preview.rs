@@ -0,0 +1,333 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use anyhow::bail;
+use quickwit_config::IndexingSettings;
+use quickwit_metastore::{IndexMetadata, Metastore, SplitMetadata, SplitState};
+use serde::Serialize;
+
+use crate::actors::{combine_partition_ids, MergeExecutor};
+use crate::load_merge_policy;
+use crate::merge_policy::{MergeOperationType, MergePolicy};
+
+/// Upper bound of the number of planning rounds simulated, in case a merge policy keeps planning
+/// operations that do not reduce the number of splits.
+const MAX_PREVIEW_ROUNDS: usize = 1_000;
+
+/// Merge settings overriding the indexing settings of an index when previewing its merges.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MergeSettingsOverrides {
+    pub merge_policy_type: Option<String>,
+    pub merge_factor: Option<usize>,
+    pub max_merge_factor: Option<usize>,
+    pub split_num_docs_target: Option<usize>,
+}
+
+impl MergeSettingsOverrides {
+    /// Applies the overrides to `indexing_settings`.
+    pub fn apply(&self, indexing_settings: &mut IndexingSettings) -> anyhow::Result<()> {
+        if let Some(merge_policy_type) = &self.merge_policy_type {
+            indexing_settings.merge_policy.merge_policy_type = merge_policy_type.clone();
+        }
+        if let Some(merge_factor) = self.merge_factor {
+            indexing_settings.merge_policy.merge_factor = merge_factor;
+        }
+        if let Some(max_merge_factor) = self.max_merge_factor {
+            indexing_settings.merge_policy.max_merge_factor = max_merge_factor;
+        }
+        if let Some(split_num_docs_target) = self.split_num_docs_target {
+            indexing_settings.split_num_docs_target = split_num_docs_target;
+        }
+        if indexing_settings.merge_policy.merge_factor < 2 {
+            bail!("Merge policy `merge_factor` must be greater than or equal to 2.");
+        }
+        if indexing_settings.merge_policy.max_merge_factor
+            < indexing_settings.merge_policy.merge_factor
+        {
+            bail!("Merge policy `max_merge_factor` must be superior or equal to `merge_factor`.");
+        }
+        Ok(())
+    }
+}
+
+/// Operation planned by the merge policy during a preview.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct PlannedMergeOperation {
+    /// Planning round in which the operation is planned. Operations of later rounds take as
+    /// input the splits produced by the operations of earlier rounds.
+    pub round: usize,
+    pub operation_type: MergeOperationType,
+    pub partition_id: u64,
+    /// ID of the split produced by the operation, referenced by the operations of later rounds.
+    pub merge_split_id: String,
+    pub split_ids: Vec<String>,
+    pub num_docs: usize,
+    /// Number of bytes of the input splits, which is also an estimate of the number of bytes
+    /// written by the operation.
+    pub num_bytes: u64,
+}
+
+/// Result of the simulation of a merge policy against the splits of an index, computed by
+/// [`preview_merge_policy`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MergePolicyPreview {
+    pub operations: Vec<PlannedMergeOperation>,
+    pub num_splits_before: usize,
+    pub num_splits_after: usize,
+    /// Number of bytes of the splits of the index.
+    pub num_bytes: u64,
+    /// Estimated number of bytes written by the planned operations.
+    pub num_bytes_merged: u64,
+    /// Estimated number of times the bytes of the splits are rewritten by the planned
+    /// operations: `num_bytes_merged / num_bytes`.
+    pub write_amplification: f64,
+}
+
+/// Simulates `merge_policy` against `splits` without executing any operation.
+///
+/// Like the merge planner, the policy plans operations on the young splits of each partition and
+/// doc mapping version. The splits produced by the planned operations are simulated by summing
+/// the docs and bytes of their input splits, and fed back to the policy until it stops planning
+/// operations. Operations not supported by the merge executor are ignored.
+pub fn preview_merge_policy(
+    merge_policy: &dyn MergePolicy,
+    splits: Vec<SplitMetadata>,
+) -> MergePolicyPreview {
+    let executor_capabilities = MergeExecutor::capabilities();
+    let num_splits_before = splits.len();
+    let num_bytes: u64 = splits.iter().map(|split| split.footer_offsets.end).sum();
+    let mut num_splits_after = 0;
+    let mut partitioned_young_splits: BTreeMap<(u64, u64), Vec<SplitMetadata>> = BTreeMap::new();
+    for split in splits {
+        if merge_policy.is_mature(&split) {
+            num_splits_after += 1;
+            continue;
+        }
+        partitioned_young_splits
+            .entry((split.partition_id, split.doc_mapping_version))
+            .or_default()
+            .push(split);
+    }
+    let mut operations = Vec::new();
+    for ((partition_id, _), mut young_splits) in partitioned_young_splits {
+        for round in 0..MAX_PREVIEW_ROUNDS {
+            let merge_operations =
+                merge_policy.plan_operations(&mut young_splits, &executor_capabilities);
+            let mut num_planned_operations = 0;
+            for merge_operation in merge_operations {
+                if !executor_capabilities.supports(merge_operation.operation_type) {
+                    young_splits.extend(merge_operation.splits);
+                    continue;
+                }
+                num_planned_operations += 1;
+                let merged_split =
+                    simulate_merge(&merge_operation.merge_split_id, &merge_operation.splits);
+                operations.push(PlannedMergeOperation {
+                    round,
+                    operation_type: merge_operation.operation_type,
+                    partition_id,
+                    merge_split_id: merge_operation.merge_split_id,
+                    split_ids: merge_operation
+                        .splits
+                        .iter()
+                        .map(|split| split.split_id().to_string())
+                        .collect(),
+                    num_docs: merged_split.num_docs,
+                    num_bytes: merged_split.footer_offsets.end,
+                });
+                if merge_policy.is_mature(&merged_split) {
+                    num_splits_after += 1;
+                } else {
+                    young_splits.push(merged_split);
+                }
+            }
+            if num_planned_operations == 0 {
+                break;
+            }
+        }
+        num_splits_after += young_splits.len();
+    }
+    let num_bytes_merged: u64 = operations.iter().map(|operation| operation.num_bytes).sum();
+    let write_amplification = if num_bytes == 0 {
+        0.0
+    } else {
+        num_bytes_merged as f64 / num_bytes as f64
+    };
+    MergePolicyPreview {
+        operations,
+        num_splits_before,
+        num_splits_after,
+        num_bytes,
+        num_bytes_merged,
+        write_amplification,
+    }
+}
+
+/// Returns the metadata of the split that would result from merging `splits`.
+fn simulate_merge(merge_split_id: &str, splits: &[SplitMetadata]) -> SplitMetadata {
+    let time_range_start = splits
+        .iter()
+        .flat_map(|split| &split.time_range)
+        .map(|time_range| *time_range.start())
+        .min();
+    let time_range_end = splits
+        .iter()
+        .flat_map(|split| &split.time_range)
+        .map(|time_range| *time_range.end())
+        .max();
+    let time_range = time_range_start
+        .zip(time_range_end)
+        .map(|(start, end)| start..=end);
+    let num_bytes = splits.iter().map(|split| split.footer_offsets.end).sum();
+    SplitMetadata {
+        split_id: merge_split_id.to_string(),
+        partition_id: combine_partition_ids(splits),
+        num_docs: splits.iter().map(|split| split.num_docs).sum(),
+        uncompressed_docs_size_in_bytes: splits
+            .iter()
+            .map(|split| split.uncompressed_docs_size_in_bytes)
+            .sum(),
+        time_range,
+        tags: splits
+            .iter()
+            .flat_map(|split| split.tags.iter().cloned())
+            .collect(),
+        footer_offsets: num_bytes..num_bytes,
+        doc_mapping_version: splits[0].doc_mapping_version,
+        ..Default::default()
+    }
+}
+
+/// Runs the merge policy of an index, possibly with overridden merge settings, against its
+/// published splits without executing any merge.
+///
+/// * `index_metadata` - The metadata of the target index.
+/// * `metastore` - The metastore managing the target index.
+/// * `overrides` - The merge settings overriding the indexing settings of the index.
+pub async fn run_merge_policy_preview(
+    index_metadata: &IndexMetadata,
+    metastore: Arc<dyn Metastore>,
+    overrides: &MergeSettingsOverrides,
+) -> anyhow::Result<MergePolicyPreview> {
+    let mut indexing_settings = index_metadata.indexing_settings.clone();
+    overrides.apply(&mut indexing_settings)?;
+    let merge_policy = load_merge_policy(&indexing_settings)?;
+    let published_splits: Vec<SplitMetadata> = metastore
+        .list_splits(&index_metadata.index_id, SplitState::Published, None, None)
+        .await?
+        .into_iter()
+        .map(|split| split.split_metadata)
+        .collect();
+    Ok(preview_merge_policy(&*merge_policy, published_splits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merge_policy::StableMultitenantWithTimestampMergePolicy;
+
+    fn splits_for_test(num_splits: usize, partition_id: u64) -> Vec<SplitMetadata> {
+        (0..num_splits)
+            .map(|split_ord| SplitMetadata {
+                split_id: format!("split-{partition_id}-{split_ord}"),
+                partition_id,
+                num_docs: 10_000,
+                footer_offsets: 900..1_000,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn merge_policy_for_test() -> StableMultitenantWithTimestampMergePolicy {
+        StableMultitenantWithTimestampMergePolicy {
+            min_level_num_docs: 100_000,
+            merge_enabled: true,
+            merge_factor: 10,
+            max_merge_factor: 10,
+            split_num_docs_target: 10_000_000,
+        }
+    }
+
+    #[test]
+    fn test_preview_merge_policy_cascading_merges() {
+        let merge_policy = merge_policy_for_test();
+        let mut splits = splits_for_test(100, 0);
+        splits.extend(splits_for_test(5, 1));
+        let preview = preview_merge_policy(&merge_policy, splits);
+        // The 100 splits of partition 0 are merged into 10 splits, then into a single split.
+        assert_eq!(preview.operations.len(), 11);
+        assert!(preview
+            .operations
+            .iter()
+            .all(|operation| operation.partition_id == 0
+                && operation.operation_type == MergeOperationType::Merge));
+        let last_operation = preview.operations.last().unwrap();
+        assert_eq!(last_operation.num_docs, 1_000_000);
+        assert!(last_operation.split_ids.iter().all(|split_id| preview
+            .operations
+            .iter()
+            .any(|operation| &operation.merge_split_id == split_id)));
+        // The splits of partition 1 are too few to be merged.
+        assert_eq!(preview.num_splits_before, 105);
+        assert_eq!(preview.num_splits_after, 6);
+        assert_eq!(preview.num_bytes, 105_000);
+        assert_eq!(preview.num_bytes_merged, 200_000);
+        assert!((preview.write_amplification - 200.0 / 105.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_preview_merge_policy_mature_splits() {
+        let merge_policy = StableMultitenantWithTimestampMergePolicy {
+            split_num_docs_target: 10_000,
+            min_level_num_docs: 1_000,
+            ..merge_policy_for_test()
+        };
+        let preview = preview_merge_policy(&merge_policy, splits_for_test(20, 0));
+        assert!(preview.operations.is_empty());
+        assert_eq!(preview.num_splits_after, 20);
+        assert_eq!(preview.write_amplification, 0.0);
+
+        let preview = preview_merge_policy(&merge_policy, Vec::new());
+        assert_eq!(preview.num_splits_after, 0);
+        assert_eq!(preview.write_amplification, 0.0);
+    }
+
+    #[test]
+    fn test_merge_settings_overrides() {
+        let mut indexing_settings = IndexingSettings::default();
+        let overrides = MergeSettingsOverrides {
+            merge_factor: Some(4),
+            split_num_docs_target: Some(1_000_000),
+            ..Default::default()
+        };
+        overrides.apply(&mut indexing_settings).unwrap();
+        assert_eq!(indexing_settings.merge_policy.merge_factor, 4);
+        assert_eq!(indexing_settings.merge_policy.max_merge_factor, 12);
+        assert_eq!(indexing_settings.split_num_docs_target, 1_000_000);
+
+        let overrides = MergeSettingsOverrides {
+            merge_factor: Some(20),
+            ..Default::default()
+        };
+        assert!(overrides.apply(&mut indexing_settings).is_err());
+    }
+}
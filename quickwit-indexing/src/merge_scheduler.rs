@@ -0,0 +1,456 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::{fmt, io};
+
+use quickwit_config::IndexerConfig;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::merge_policy::MergeOperation;
+use crate::metrics::INDEXER_METRICS;
+
+/// The size budget of the merges is accounted for in MiB, as semaphore permits are acquired by
+/// batches of at most `u32::MAX`.
+const MIB: u64 = 1024 * 1024;
+
+/// A merge needs scratch space for its downloaded splits and for the merged split, which is at
+/// most as large as its inputs.
+const MERGE_SCRATCH_SPACE_FACTOR: u64 = 2;
+
+/// Free space left on the scratch disk after reserving the scratch space of a merge, for the
+/// indexers and the other files of the node.
+const MIN_FREE_SCRATCH_SPACE_NUM_BYTES: u64 = 100 * MIB;
+
+/// Returns the scratch space forecast for a merge operation, in bytes.
+pub(crate) fn forecast_merge_scratch_num_bytes(merge_operation: &MergeOperation) -> u64 {
+    merge_operation_num_bytes(merge_operation) * MERGE_SCRATCH_SPACE_FACTOR
+}
+
+fn merge_operation_num_bytes(merge_operation: &MergeOperation) -> u64 {
+    merge_operation
+        .splits_as_slice()
+        .iter()
+        .map(|split| split.footer_offsets.end)
+        .sum()
+}
+
+/// Outcome of the check of the scratch space available for a merge.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ScratchSpaceCheck {
+    /// The scratch space of the merge was reserved.
+    Reserved,
+    /// The merge does not fit next to the ongoing merges, and should be retried once they
+    /// release their scratch space.
+    Deferred {
+        available_num_bytes: u64,
+        reserved_num_bytes: u64,
+    },
+    /// The merge does not fit on the scratch disk, even without any other ongoing merge.
+    Insufficient { available_num_bytes: u64 },
+}
+
+/// Checks whether a merge needing `forecast_num_bytes` of scratch space fits on a disk with
+/// `available_num_bytes` free, of which `reserved_num_bytes` are already reserved by the ongoing
+/// merges.
+fn check_scratch_space(
+    forecast_num_bytes: u64,
+    available_num_bytes: u64,
+    reserved_num_bytes: u64,
+) -> ScratchSpaceCheck {
+    let required_num_bytes = forecast_num_bytes
+        .saturating_add(reserved_num_bytes)
+        .saturating_add(MIN_FREE_SCRATCH_SPACE_NUM_BYTES);
+    if required_num_bytes <= available_num_bytes {
+        return ScratchSpaceCheck::Reserved;
+    }
+    // The ongoing merges free up their scratch space when they complete. The free space they
+    // did not consume yet is still reported as available.
+    if reserved_num_bytes > 0 {
+        return ScratchSpaceCheck::Deferred {
+            available_num_bytes,
+            reserved_num_bytes,
+        };
+    }
+    ScratchSpaceCheck::Insufficient {
+        available_num_bytes,
+    }
+}
+
+/// Limits the merges executed concurrently by all the pipelines of a node, so that merges do not
+/// saturate the CPUs and the disks at the expense of indexing.
+///
+/// A merge holds a [`MergePermit`] from the download of its splits until the end of its
+/// execution. The merges exceeding the budget wait for a permit in FIFO order, and their merge
+/// operations queue up in the mailboxes of the merge pipelines in the meantime.
+pub struct MergeScheduler {
+    /// Budget on the number of concurrent merges.
+    merge_permits_opt: Option<Arc<Semaphore>>,
+    /// Budget on the total size of the splits of the concurrent merges, in MiB.
+    mib_permits_opt: Option<(Arc<Semaphore>, u32)>,
+    /// Scratch space reserved by the ongoing merges, in bytes.
+    reserved_scratch_num_bytes: Arc<AtomicU64>,
+}
+
+impl fmt::Debug for MergeScheduler {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("MergeScheduler")
+            .field(
+                "available_merge_permits",
+                &self
+                    .merge_permits_opt
+                    .as_ref()
+                    .map(|merge_permits| merge_permits.available_permits()),
+            )
+            .field(
+                "available_mib_permits",
+                &self
+                    .mib_permits_opt
+                    .as_ref()
+                    .map(|(mib_permits, _)| mib_permits.available_permits()),
+            )
+            .field(
+                "reserved_scratch_num_bytes",
+                &self.reserved_scratch_num_bytes.load(Ordering::Relaxed),
+            )
+            .finish()
+    }
+}
+
+impl Default for MergeScheduler {
+    /// Returns a merge scheduler that does not limit merges.
+    fn default() -> Self {
+        MergeScheduler::new(None, None)
+    }
+}
+
+impl MergeScheduler {
+    pub fn new(
+        max_concurrent_merges_opt: Option<usize>,
+        max_concurrent_merge_bytes_opt: Option<u64>,
+    ) -> Self {
+        let merge_permits_opt = max_concurrent_merges_opt
+            .map(|max_concurrent_merges| Arc::new(Semaphore::new(max_concurrent_merges.max(1))));
+        let mib_permits_opt = max_concurrent_merge_bytes_opt.map(|max_concurrent_merge_bytes| {
+            let max_mib = (max_concurrent_merge_bytes / MIB).clamp(1, u32::MAX as u64) as u32;
+            (Arc::new(Semaphore::new(max_mib as usize)), max_mib)
+        });
+        MergeScheduler {
+            merge_permits_opt,
+            mib_permits_opt,
+            reserved_scratch_num_bytes: Arc::default(),
+        }
+    }
+
+    pub fn from_indexer_config(indexer_config: &IndexerConfig) -> Self {
+        MergeScheduler::new(
+            indexer_config.max_concurrent_merges,
+            indexer_config
+                .max_concurrent_merge_bytes
+                .map(|max_concurrent_merge_bytes| max_concurrent_merge_bytes.get_bytes()),
+        )
+    }
+
+    /// Waits until the merge operation fits in the budget and returns its permit.
+    pub async fn acquire_permit(&self, merge_operation: &MergeOperation) -> MergePermit {
+        INDEXER_METRICS.pending_merges.inc();
+        let merge_permit_opt = if let Some(merge_permits) = &self.merge_permits_opt {
+            Some(
+                merge_permits
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .expect("The merge scheduler semaphore should never be closed."),
+            )
+        } else {
+            None
+        };
+        let mib_permit_opt = if let Some((mib_permits, max_mib)) = &self.mib_permits_opt {
+            let num_bytes = merge_operation_num_bytes(merge_operation);
+            // A merge larger than the budget runs alone rather than never.
+            let num_mib = ((num_bytes + MIB - 1) / MIB).clamp(1, *max_mib as u64) as u32;
+            Some(
+                mib_permits
+                    .clone()
+                    .acquire_many_owned(num_mib)
+                    .await
+                    .expect("The merge scheduler semaphore should never be closed."),
+            )
+        } else {
+            None
+        };
+        INDEXER_METRICS.pending_merges.dec();
+        INDEXER_METRICS.ongoing_merges.inc();
+        MergePermit {
+            merge_permit_opt,
+            mib_permit_opt,
+            scratch_space_reservation_opt: None,
+            is_accounted: true,
+        }
+    }
+
+    /// Reserves the scratch space forecast for the merge operation on the disk of
+    /// `scratch_path`, accounting for the scratch space reserved by the ongoing merges. The
+    /// reservation is released along with the permit.
+    pub fn reserve_scratch_space(
+        &self,
+        merge_permit: &mut MergePermit,
+        merge_operation: &MergeOperation,
+        scratch_path: &Path,
+    ) -> io::Result<ScratchSpaceCheck> {
+        let available_num_bytes = fs2::available_space(scratch_path)?;
+        Ok(self.reserve_scratch_space_with_available_space(
+            merge_permit,
+            forecast_merge_scratch_num_bytes(merge_operation),
+            available_num_bytes,
+        ))
+    }
+
+    fn reserve_scratch_space_with_available_space(
+        &self,
+        merge_permit: &mut MergePermit,
+        forecast_num_bytes: u64,
+        available_num_bytes: u64,
+    ) -> ScratchSpaceCheck {
+        let mut scratch_space_check = ScratchSpaceCheck::Reserved;
+        let update_result = self.reserved_scratch_num_bytes.fetch_update(
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+            |reserved_num_bytes| {
+                scratch_space_check = check_scratch_space(
+                    forecast_num_bytes,
+                    available_num_bytes,
+                    reserved_num_bytes,
+                );
+                if scratch_space_check == ScratchSpaceCheck::Reserved {
+                    Some(reserved_num_bytes + forecast_num_bytes)
+                } else {
+                    None
+                }
+            },
+        );
+        if update_result.is_ok() {
+            merge_permit.scratch_space_reservation_opt = Some(ScratchSpaceReservation {
+                num_bytes: forecast_num_bytes,
+                reserved_scratch_num_bytes: self.reserved_scratch_num_bytes.clone(),
+            });
+        }
+        scratch_space_check
+    }
+}
+
+/// Scratch space reserved for a merge, released when dropped.
+struct ScratchSpaceReservation {
+    num_bytes: u64,
+    reserved_scratch_num_bytes: Arc<AtomicU64>,
+}
+
+impl Drop for ScratchSpaceReservation {
+    fn drop(&mut self) {
+        self.reserved_scratch_num_bytes
+            .fetch_sub(self.num_bytes, Ordering::SeqCst);
+    }
+}
+
+/// Permit to execute a merge, handed back to the [`MergeScheduler`] when dropped.
+#[derive(Default)]
+pub struct MergePermit {
+    merge_permit_opt: Option<OwnedSemaphorePermit>,
+    mib_permit_opt: Option<OwnedSemaphorePermit>,
+    scratch_space_reservation_opt: Option<ScratchSpaceReservation>,
+    /// Whether the permit was acquired from a scheduler and is counted as an ongoing merge.
+    is_accounted: bool,
+}
+
+impl fmt::Debug for MergePermit {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("MergePermit")
+            .field("has_merge_permit", &self.merge_permit_opt.is_some())
+            .field(
+                "num_mib_permits",
+                &self
+                    .mib_permit_opt
+                    .as_ref()
+                    .map(OwnedSemaphorePermit::num_permits),
+            )
+            .field(
+                "reserved_scratch_num_bytes",
+                &self
+                    .scratch_space_reservation_opt
+                    .as_ref()
+                    .map(|reservation| reservation.num_bytes),
+            )
+            .finish()
+    }
+}
+
+impl Drop for MergePermit {
+    fn drop(&mut self) {
+        if self.is_accounted {
+            INDEXER_METRICS.ongoing_merges.dec();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use quickwit_metastore::SplitMetadata;
+
+    use super::*;
+
+    fn merge_operation_for_test(split_num_bytes: &[u64]) -> MergeOperation {
+        let splits = split_num_bytes
+            .iter()
+            .map(|&num_bytes| SplitMetadata {
+                footer_offsets: num_bytes - 100..num_bytes,
+                ..Default::default()
+            })
+            .collect();
+        MergeOperation::new_merge_operation(splits)
+    }
+
+    #[tokio::test]
+    async fn test_merge_scheduler_limits_concurrent_merges() {
+        let merge_scheduler = MergeScheduler::new(Some(2), None);
+        let merge_operation = merge_operation_for_test(&[1_000, 1_000]);
+        let first_permit = merge_scheduler.acquire_permit(&merge_operation).await;
+        let _second_permit = merge_scheduler.acquire_permit(&merge_operation).await;
+        assert!(tokio::time::timeout(
+            Duration::from_millis(50),
+            merge_scheduler.acquire_permit(&merge_operation)
+        )
+        .await
+        .is_err());
+        drop(first_permit);
+        tokio::time::timeout(
+            Duration::from_millis(50),
+            merge_scheduler.acquire_permit(&merge_operation),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_merge_scheduler_limits_concurrent_merge_bytes() {
+        let merge_scheduler = MergeScheduler::new(None, Some(10 * MIB));
+        let small_merge_operation = merge_operation_for_test(&[3 * MIB, 3 * MIB]);
+        let large_merge_operation = merge_operation_for_test(&[20 * MIB, 20 * MIB]);
+        let small_permit = merge_scheduler.acquire_permit(&small_merge_operation).await;
+        assert!(tokio::time::timeout(
+            Duration::from_millis(50),
+            merge_scheduler.acquire_permit(&small_merge_operation)
+        )
+        .await
+        .is_err());
+        drop(small_permit);
+        // The merge larger than the budget gets the whole budget.
+        let large_permit = merge_scheduler.acquire_permit(&large_merge_operation).await;
+        assert_eq!(
+            large_permit.mib_permit_opt.as_ref().unwrap().num_permits(),
+            10
+        );
+    }
+
+    #[test]
+    fn test_check_scratch_space() {
+        let headroom = MIN_FREE_SCRATCH_SPACE_NUM_BYTES;
+        assert_eq!(
+            check_scratch_space(10 * MIB, 10 * MIB + headroom, 0),
+            ScratchSpaceCheck::Reserved
+        );
+        assert_eq!(
+            check_scratch_space(10 * MIB, 15 * MIB + headroom, 5 * MIB),
+            ScratchSpaceCheck::Reserved
+        );
+        assert_eq!(
+            check_scratch_space(10 * MIB, 10 * MIB + headroom, 5 * MIB),
+            ScratchSpaceCheck::Deferred {
+                available_num_bytes: 10 * MIB + headroom,
+                reserved_num_bytes: 5 * MIB,
+            }
+        );
+        assert_eq!(
+            check_scratch_space(10 * MIB, 10 * MIB, 0),
+            ScratchSpaceCheck::Insufficient {
+                available_num_bytes: 10 * MIB
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_scheduler_reserves_scratch_space() {
+        let merge_scheduler = MergeScheduler::default();
+        let merge_operation = merge_operation_for_test(&[10 * MIB, 10 * MIB]);
+        assert_eq!(forecast_merge_scratch_num_bytes(&merge_operation), 40 * MIB);
+        let available_num_bytes = 60 * MIB + MIN_FREE_SCRATCH_SPACE_NUM_BYTES;
+        let mut first_permit = merge_scheduler.acquire_permit(&merge_operation).await;
+        assert_eq!(
+            merge_scheduler.reserve_scratch_space_with_available_space(
+                &mut first_permit,
+                40 * MIB,
+                available_num_bytes
+            ),
+            ScratchSpaceCheck::Reserved
+        );
+        let mut second_permit = merge_scheduler.acquire_permit(&merge_operation).await;
+        assert_eq!(
+            merge_scheduler.reserve_scratch_space_with_available_space(
+                &mut second_permit,
+                40 * MIB,
+                available_num_bytes
+            ),
+            ScratchSpaceCheck::Deferred {
+                available_num_bytes,
+                reserved_num_bytes: 40 * MIB,
+            }
+        );
+        assert!(second_permit.scratch_space_reservation_opt.is_none());
+        drop(first_permit);
+        assert_eq!(
+            merge_scheduler
+                .reserved_scratch_num_bytes
+                .load(Ordering::SeqCst),
+            0
+        );
+        assert_eq!(
+            merge_scheduler.reserve_scratch_space_with_available_space(
+                &mut second_permit,
+                40 * MIB,
+                available_num_bytes
+            ),
+            ScratchSpaceCheck::Reserved
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_scheduler_default_is_unlimited() {
+        let merge_scheduler = MergeScheduler::default();
+        let merge_operation = merge_operation_for_test(&[1_000]);
+        let mut permits = Vec::new();
+        for _ in 0..100 {
+            permits.push(merge_scheduler.acquire_permit(&merge_operation).await);
+        }
+    }
+}
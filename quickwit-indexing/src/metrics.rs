@@ -0,0 +1,300 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use once_cell::sync::Lazy;
+use quickwit_common::metrics::{
+    new_counter, new_counter_vec, new_gauge, new_gauge_vec, new_histogram, new_histogram_vec,
+    Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
+};
+
+use crate::models::IndexingPipelineId;
+
+/// Labels of the metrics reported per indexing pipeline.
+pub(crate) const PIPELINE_LABELS: [&str; 2] = ["index_id", "source_id"];
+
+pub struct IndexerMetrics {
+    pub orphan_scratch_dirs_removed_total: IntCounter,
+    pub orphan_scratch_reclaimed_bytes_total: IntCounter,
+    pub splits_verified_total: IntCounter,
+    pub splits_quarantined_total: IntCounter,
+    pub split_verification_duration_secs: Histogram,
+    pub processed_docs_total: IntCounterVec,
+    pub processed_bytes_total: IntCounterVec,
+    pub workbench_num_docs: IntGaugeVec,
+    pub split_build_duration_secs: HistogramVec,
+    pub commits_total: IntCounterVec,
+    pub merge_backlog_num_splits: IntGaugeVec,
+    pub invalid_ip_addrs_total: IntCounterVec,
+    pub defaulted_fields_total: IntCounterVec,
+    pub dead_letter_docs_total: IntCounterVec,
+    pub tenant_routed_docs_total: IntCounterVec,
+    pub cpu_time_micros_total: IntCounterVec,
+    pub upload_failures_total: IntCounterVec,
+    pub upload_circuit_breaker_tripped: IntGaugeVec,
+    pub publish_notifications_total: IntCounterVec,
+    pub publish_notification_failures_total: IntCounterVec,
+    pub pending_merges: IntGauge,
+    pub ongoing_merges: IntGauge,
+    pub merges_deferred_total: IntCounter,
+    pub merges_rejected_total: IntCounter,
+    pub split_file_flush_duration_secs: Histogram,
+    pub split_file_sync_duration_secs: Histogram,
+    pub data_loss_suspicions: IntGaugeVec,
+    pub oversized_metastore_payloads_total: IntCounterVec,
+}
+
+impl Default for IndexerMetrics {
+    fn default() -> Self {
+        IndexerMetrics {
+            orphan_scratch_dirs_removed_total: new_counter(
+                "orphan_scratch_dirs_removed_total",
+                "Number of orphan scratch directories removed when starting indexing pipelines.",
+                "quickwit_indexing",
+            ),
+            orphan_scratch_reclaimed_bytes_total: new_counter(
+                "orphan_scratch_reclaimed_bytes_total",
+                "Number of bytes reclaimed by removing orphan scratch directories.",
+                "quickwit_indexing",
+            ),
+            splits_verified_total: new_counter(
+                "splits_verified_total",
+                "Number of splits verified before being published.",
+                "quickwit_indexing",
+            ),
+            splits_quarantined_total: new_counter(
+                "splits_quarantined_total",
+                "Number of splits that failed verification and were quarantined.",
+                "quickwit_indexing",
+            ),
+            split_verification_duration_secs: new_histogram(
+                "split_verification_duration_secs",
+                "Time spent verifying a split before publishing it.",
+                "quickwit_indexing",
+            ),
+            processed_docs_total: new_counter_vec(
+                "processed_docs_total",
+                "Number of documents processed by the indexer, per status (`valid`, \
+                 `parse_error`, `missing_field`, or `duplicate`).",
+                "quickwit_indexing",
+                &["index_id", "source_id", "docs_processed_status"],
+            ),
+            processed_bytes_total: new_counter_vec(
+                "processed_bytes_total",
+                "Number of bytes of the documents processed by the indexer, valid or not.",
+                "quickwit_indexing",
+                &PIPELINE_LABELS,
+            ),
+            workbench_num_docs: new_gauge_vec(
+                "workbench_num_docs",
+                "Number of valid documents in the workbenches of the indexers.",
+                "quickwit_indexing",
+                &PIPELINE_LABELS,
+            ),
+            split_build_duration_secs: new_histogram_vec(
+                "split_build_duration_secs",
+                "Time elapsed between the creation of a workbench and the commit of its splits.",
+                "quickwit_indexing",
+                &PIPELINE_LABELS,
+            ),
+            commits_total: new_counter_vec(
+                "commits_total",
+                "Number of workbench commits, per trigger (`timeout`, `no_more_docs`, \
+                 `num_docs_limit`, or `memory_limit`).",
+                "quickwit_indexing",
+                &["index_id", "source_id", "commit_trigger"],
+            ),
+            merge_backlog_num_splits: new_gauge_vec(
+                "merge_backlog_num_splits",
+                "Number of young splits waiting to be merged by the merge planners.",
+                "quickwit_indexing",
+                &PIPELINE_LABELS,
+            ),
+            invalid_ip_addrs_total: new_counter_vec(
+                "invalid_ip_addrs_total",
+                "Number of documents rejected by the indexer because of a malformed IP address.",
+                "quickwit_indexing",
+                &PIPELINE_LABELS,
+            ),
+            defaulted_fields_total: new_counter_vec(
+                "defaulted_fields_total",
+                "Number of documents missing a fast field that were set to the default value \
+                 declared in the mapping, per field.",
+                "quickwit_indexing",
+                &["index_id", "source_id", "field_name"],
+            ),
+            dead_letter_docs_total: new_counter_vec(
+                "dead_letter_docs_total",
+                "Number of documents failing to parse written to the dead-letter queue.",
+                "quickwit_indexing",
+                &PIPELINE_LABELS,
+            ),
+            tenant_routed_docs_total: new_counter_vec(
+                "tenant_routed_docs_total",
+                "Number of documents routed to the index of their tenant.",
+                "quickwit_indexing",
+                &PIPELINE_LABELS,
+            ),
+            cpu_time_micros_total: new_counter_vec(
+                "cpu_time_micros_total",
+                "Time spent indexing batches on the blocking runtime, in microseconds.",
+                "quickwit_indexing",
+                &PIPELINE_LABELS,
+            ),
+            upload_failures_total: new_counter_vec(
+                "upload_failures_total",
+                "Number of failed attempts to store a split, per reason (`unauthorized`, \
+                 `storage_not_found`, `storage_misconfigured`, or `transient`).",
+                "quickwit_indexing",
+                &["index_id", "source_id", "reason"],
+            ),
+            upload_circuit_breaker_tripped: new_gauge_vec(
+                "upload_circuit_breaker_tripped",
+                "Whether the upload circuit breaker of the pipeline is tripped (1) or not (0).",
+                "quickwit_indexing",
+                &PIPELINE_LABELS,
+            ),
+            publish_notifications_total: new_counter_vec(
+                "publish_notifications_total",
+                "Number of publish notifications delivered to the sink of the index.",
+                "quickwit_indexing",
+                &PIPELINE_LABELS,
+            ),
+            publish_notification_failures_total: new_counter_vec(
+                "publish_notification_failures_total",
+                "Number of failed attempts to deliver a publish notification.",
+                "quickwit_indexing",
+                &PIPELINE_LABELS,
+            ),
+            pending_merges: new_gauge(
+                "pending_merges",
+                "Number of merges waiting for a permit of the merge scheduler.",
+                "quickwit_indexing",
+            ),
+            ongoing_merges: new_gauge(
+                "ongoing_merges",
+                "Number of merges holding a permit of the merge scheduler.",
+                "quickwit_indexing",
+            ),
+            merges_deferred_total: new_counter(
+                "merges_deferred_total",
+                "Number of times a merge was deferred until the ongoing merges free up scratch \
+                 space.",
+                "quickwit_indexing",
+            ),
+            merges_rejected_total: new_counter(
+                "merges_rejected_total",
+                "Number of merges rejected because they do not fit on the scratch disk.",
+                "quickwit_indexing",
+            ),
+            split_file_flush_duration_secs: new_histogram(
+                "split_file_flush_duration_secs",
+                "Time spent flushing the write buffers of the files of the splits being built or \
+                 merged.",
+                "quickwit_indexing",
+            ),
+            split_file_sync_duration_secs: new_histogram(
+                "split_file_sync_duration_secs",
+                "Time spent syncing the files of the splits being built or merged to disk.",
+                "quickwit_indexing",
+            ),
+            data_loss_suspicions: new_gauge_vec(
+                "data_loss_suspicions",
+                "Number of suspicions of data loss raised by the last run of the data loss \
+                 detector, per kind (`stuck_partition`, `expired_records`, or \
+                 `unaccounted_positions`).",
+                "quickwit_indexing",
+                &["index_id", "source_id", "kind"],
+            ),
+            oversized_metastore_payloads_total: new_counter_vec(
+                "oversized_metastore_payloads_total",
+                "Number of payloads sent to the metastore exceeding their size limits, per \
+                 payload (`split_metadata`, `checkpoint_delta`, or `publish_request`) and outcome \
+                 (`warned` or `rejected`).",
+                "quickwit_indexing",
+                &["index_id", "source_id", "payload", "outcome"],
+            ),
+        }
+    }
+}
+
+/// Indexer metrics exposes a set of metrics about the indexing pipelines of the node.
+pub static INDEXER_METRICS: Lazy<IndexerMetrics> = Lazy::new(IndexerMetrics::default);
+
+/// Metrics of the indexer of a pipeline, with their labels resolved once and for all.
+pub(crate) struct IndexerPipelineMetrics {
+    pub valid_docs_total: IntCounter,
+    pub parse_errors_total: IntCounter,
+    pub missing_fields_total: IntCounter,
+    pub duplicate_docs_total: IntCounter,
+    pub processed_bytes_total: IntCounter,
+    pub workbench_num_docs: IntGauge,
+    pub split_build_duration_secs: Histogram,
+    pub invalid_ip_addrs_total: IntCounter,
+    pub cpu_time_micros_total: IntCounter,
+    index_id: String,
+    source_id: String,
+}
+
+impl IndexerPipelineMetrics {
+    pub fn new(pipeline_id: &IndexingPipelineId) -> Self {
+        let index_id = pipeline_id.index_id.as_str();
+        let source_id = pipeline_id.source_id.as_str();
+        let processed_docs_total = |docs_processed_status: &str| {
+            INDEXER_METRICS.processed_docs_total.with_label_values(&[
+                index_id,
+                source_id,
+                docs_processed_status,
+            ])
+        };
+        Self {
+            valid_docs_total: processed_docs_total("valid"),
+            parse_errors_total: processed_docs_total("parse_error"),
+            missing_fields_total: processed_docs_total("missing_field"),
+            duplicate_docs_total: processed_docs_total("duplicate"),
+            processed_bytes_total: INDEXER_METRICS
+                .processed_bytes_total
+                .with_label_values(&[index_id, source_id]),
+            workbench_num_docs: INDEXER_METRICS
+                .workbench_num_docs
+                .with_label_values(&[index_id, source_id]),
+            split_build_duration_secs: INDEXER_METRICS
+                .split_build_duration_secs
+                .with_label_values(&[index_id, source_id]),
+            invalid_ip_addrs_total: INDEXER_METRICS
+                .invalid_ip_addrs_total
+                .with_label_values(&[index_id, source_id]),
+            cpu_time_micros_total: INDEXER_METRICS
+                .cpu_time_micros_total
+                .with_label_values(&[index_id, source_id]),
+            index_id: index_id.to_string(),
+            source_id: source_id.to_string(),
+        }
+    }
+
+    /// Returns the counter of the documents of the pipeline missing the field `field_name` that
+    /// were set to its default value. Unlike the other metrics, its labels are resolved on the fly
+    /// as the defaulted fields are only known while parsing the documents.
+    pub fn defaulted_fields_total(&self, field_name: &str) -> IntCounter {
+        INDEXER_METRICS.defaulted_fields_total.with_label_values(&[
+            &self.index_id,
+            &self.source_id,
+            field_name,
+        ])
+    }
+}
@@ -0,0 +1,419 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use quickwit_metastore::checkpoint::{PartitionId, Position, SourceCheckpoint};
+use serde::{Deserialize, Serialize};
+
+use super::source_health::position_offset;
+
+/// Number of records past the published position of a partition above which the partition is
+/// deemed stuck if its published position does not move. Partitions written by transactional
+/// producers end with control records that carry no document.
+const STUCK_PARTITION_MIN_NUM_PENDING_RECORDS: u64 = 10;
+
+/// Number of positions published over a window that may be unaccounted for before they are
+/// flagged as lost, whatever the number of positions published over the window.
+const MIN_NUM_TOLERATED_UNACCOUNTED_POSITIONS: u64 = 100;
+
+/// Share of the positions published over a window that may be unaccounted for before they are
+/// flagged as lost. Empty messages and control records do not yield any document.
+const MAX_TOLERATED_UNACCOUNTED_POSITIONS_RATIO: f64 = 0.01;
+
+/// Offsets of the records available in a partition of a source.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PartitionBounds {
+    /// Offset of the oldest record retained by the source.
+    pub earliest_offset: u64,
+    /// Offset of the next record appended to the partition.
+    pub next_offset: u64,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataLossSuspicionKind {
+    /// Records are available past the published position of the partition, but the published
+    /// position did not move over the detection window.
+    StuckPartition,
+    /// The source deleted records of the partition before they were published.
+    ExpiredRecords,
+    /// More positions were published over the detection window than documents were published
+    /// or skipped by the pipelines.
+    UnaccountedPositions,
+}
+
+impl DataLossSuspicionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DataLossSuspicionKind::StuckPartition => "stuck_partition",
+            DataLossSuspicionKind::ExpiredRecords => "expired_records",
+            DataLossSuspicionKind::UnaccountedPositions => "unaccounted_positions",
+        }
+    }
+}
+
+/// Gap between the records of a source and the documents published for them, raised by the data
+/// loss detector.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DataLossSuspicion {
+    pub kind: DataLossSuspicionKind,
+    /// Partition of the source concerned by the suspicion, if any.
+    pub partition_id: Option<String>,
+    /// Number of records suspected to be lost or stuck.
+    pub num_records: u64,
+    pub message: String,
+    /// Unix timestamp at which the suspicion was raised, in seconds.
+    pub timestamp: i64,
+}
+
+/// Progress of a source on the node, sampled periodically by the data loss detector.
+#[derive(Clone, Debug)]
+pub(crate) struct SourceProgressSample {
+    pub instant: Instant,
+    /// Checkpoint of the source published in the metastore.
+    pub published_checkpoint: SourceCheckpoint,
+    /// Highest positions published by the pipelines of the source on the node.
+    pub local_published_watermark: SourceCheckpoint,
+    /// Number of documents published by the pipelines of the source on the node.
+    pub num_published_docs: u64,
+    /// Number of documents read by the pipelines of the source on the node but not published in
+    /// their splits: invalid, duplicate, or routed to the index of their tenant.
+    pub num_skipped_docs: u64,
+}
+
+/// Compares the progress of a source with the records available in its partitions and with the
+/// documents published over a sliding window, and raises suspicions of data loss.
+pub(crate) struct DataLossDetector {
+    window: Duration,
+    /// Samples taken over the last window, preceded by the most recent sample taken at least one
+    /// window ago, which serves as the baseline.
+    samples: VecDeque<SourceProgressSample>,
+}
+
+impl DataLossDetector {
+    pub fn new(window: Duration) -> Self {
+        DataLossDetector {
+            window,
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Records a sample of the progress of the source and returns the suspicions of data loss
+    /// raised by comparing it to the bounds of the partitions of the source, if they are known,
+    /// and to the sample taken one window ago.
+    pub fn record_sample(
+        &mut self,
+        sample: SourceProgressSample,
+        partition_bounds_opt: Option<&BTreeMap<PartitionId, PartitionBounds>>,
+        now_timestamp: i64,
+    ) -> Vec<DataLossSuspicion> {
+        if let Some(last_sample) = self.samples.back() {
+            // The counters are reset when the pipelines of the source on the node are respawned.
+            if sample.num_published_docs < last_sample.num_published_docs
+                || sample.num_skipped_docs < last_sample.num_skipped_docs
+            {
+                self.samples.clear();
+            }
+        }
+        while self.samples.len() > 1
+            && sample
+                .instant
+                .saturating_duration_since(self.samples[1].instant)
+                >= self.window
+        {
+            self.samples.pop_front();
+        }
+        let baseline_opt = self.samples.front().filter(|baseline| {
+            sample.instant.saturating_duration_since(baseline.instant) >= self.window
+        });
+        let mut suspicions = Vec::new();
+
+        if let Some(partition_bounds) = partition_bounds_opt {
+            for (partition_id, bounds) in partition_bounds {
+                let published_position = sample
+                    .published_checkpoint
+                    .position_for_partition(partition_id)
+                    .cloned()
+                    .unwrap_or(Position::Beginning);
+                // Pipelines start reading unpublished partitions from their oldest record.
+                let next_offset_to_publish = match position_offset(&published_position) {
+                    Some(published_offset) => published_offset + 1,
+                    None => bounds.earliest_offset,
+                };
+                if next_offset_to_publish < bounds.earliest_offset {
+                    let num_records = bounds.earliest_offset - next_offset_to_publish;
+                    suspicions.push(DataLossSuspicion {
+                        kind: DataLossSuspicionKind::ExpiredRecords,
+                        partition_id: Some(partition_id.0.to_string()),
+                        num_records,
+                        message: format!(
+                            "{} records of partition `{}` following published position `{}` were \
+                             deleted by the source before being published.",
+                            num_records,
+                            partition_id.0,
+                            published_position.as_str()
+                        ),
+                        timestamp: now_timestamp,
+                    });
+                }
+                let num_pending_records = bounds
+                    .next_offset
+                    .saturating_sub(next_offset_to_publish.max(bounds.earliest_offset));
+                let is_published_position_unchanged = baseline_opt
+                    .map(|baseline| {
+                        baseline
+                            .published_checkpoint
+                            .position_for_partition(partition_id)
+                            .cloned()
+                            .unwrap_or(Position::Beginning)
+                            == published_position
+                    })
+                    .unwrap_or(false);
+                if num_pending_records > STUCK_PARTITION_MIN_NUM_PENDING_RECORDS
+                    && is_published_position_unchanged
+                {
+                    suspicions.push(DataLossSuspicion {
+                        kind: DataLossSuspicionKind::StuckPartition,
+                        partition_id: Some(partition_id.0.to_string()),
+                        num_records: num_pending_records,
+                        message: format!(
+                            "Partition `{}` has {} records past published position `{}`, which \
+                             did not move for {} seconds.",
+                            partition_id.0,
+                            num_pending_records,
+                            published_position.as_str(),
+                            self.window.as_secs()
+                        ),
+                        timestamp: now_timestamp,
+                    });
+                }
+            }
+        }
+        if let Some(baseline) = baseline_opt {
+            let num_published_positions = num_positions_between(
+                &baseline.local_published_watermark,
+                &sample.local_published_watermark,
+            );
+            let num_accounted_docs = (sample.num_published_docs - baseline.num_published_docs)
+                + (sample.num_skipped_docs - baseline.num_skipped_docs);
+            let num_unaccounted_positions =
+                num_published_positions.saturating_sub(num_accounted_docs);
+            let num_tolerated_unaccounted_positions = MIN_NUM_TOLERATED_UNACCOUNTED_POSITIONS.max(
+                (num_published_positions as f64 * MAX_TOLERATED_UNACCOUNTED_POSITIONS_RATIO) as u64,
+            );
+            if num_unaccounted_positions > num_tolerated_unaccounted_positions {
+                suspicions.push(DataLossSuspicion {
+                    kind: DataLossSuspicionKind::UnaccountedPositions,
+                    partition_id: None,
+                    num_records: num_unaccounted_positions,
+                    message: format!(
+                        "{} positions were published over the last {} seconds, but only {} \
+                         documents were published or skipped: {} records may have been lost.",
+                        num_published_positions,
+                        self.window.as_secs(),
+                        num_accounted_docs,
+                        num_unaccounted_positions
+                    ),
+                    timestamp: now_timestamp,
+                });
+            }
+        }
+        self.samples.push_back(sample);
+        suspicions
+    }
+}
+
+/// Returns the number of numeric positions between two watermarks of a source. The partitions
+/// absent from the earlier watermark are ignored because their starting offset is unknown.
+fn num_positions_between(earlier: &SourceCheckpoint, later: &SourceCheckpoint) -> u64 {
+    later
+        .iter()
+        .filter_map(|(partition_id, later_position)| {
+            let earlier_offset = earlier
+                .position_for_partition(&partition_id)
+                .and_then(position_offset)?;
+            let later_offset = position_offset(&later_position)?;
+            Some(later_offset.saturating_sub(earlier_offset))
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_secs(900);
+
+    fn checkpoint(offsets: &[(u64, u64)]) -> SourceCheckpoint {
+        SourceCheckpoint::from_iter(
+            offsets.iter().map(|(partition, offset)| {
+                (PartitionId::from(*partition), Position::from(*offset))
+            }),
+        )
+    }
+
+    fn sample(
+        instant: Instant,
+        published_offsets: &[(u64, u64)],
+        num_published_docs: u64,
+        num_skipped_docs: u64,
+    ) -> SourceProgressSample {
+        SourceProgressSample {
+            instant,
+            published_checkpoint: checkpoint(published_offsets),
+            local_published_watermark: checkpoint(published_offsets),
+            num_published_docs,
+            num_skipped_docs,
+        }
+    }
+
+    fn bounds(earliest_offset: u64, next_offset: u64) -> BTreeMap<PartitionId, PartitionBounds> {
+        BTreeMap::from([(
+            PartitionId::from(0u64),
+            PartitionBounds {
+                earliest_offset,
+                next_offset,
+            },
+        )])
+    }
+
+    #[test]
+    fn test_data_loss_detector_healthy_source() {
+        let mut detector = DataLossDetector::new(WINDOW);
+        let start = Instant::now();
+        assert!(detector
+            .record_sample(
+                sample(start, &[(0, 999)], 1_000, 0),
+                Some(&bounds(0, 1_000)),
+                0
+            )
+            .is_empty());
+        // 20 empty messages are tolerated.
+        assert!(detector
+            .record_sample(
+                sample(start + WINDOW, &[(0, 9_999)], 9_950, 30),
+                Some(&bounds(0, 10_005)),
+                900
+            )
+            .is_empty());
+    }
+
+    #[test]
+    fn test_data_loss_detector_stuck_partition() {
+        let mut detector = DataLossDetector::new(WINDOW);
+        let start = Instant::now();
+        assert!(detector
+            .record_sample(
+                sample(start, &[(0, 999)], 1_000, 0),
+                Some(&bounds(0, 2_000)),
+                0
+            )
+            .is_empty());
+        // The published position did not move for less than a window.
+        assert!(detector
+            .record_sample(
+                sample(start + WINDOW / 2, &[(0, 999)], 1_000, 0),
+                Some(&bounds(0, 3_000)),
+                450
+            )
+            .is_empty());
+        let suspicions = detector.record_sample(
+            sample(start + WINDOW, &[(0, 999)], 1_000, 0),
+            Some(&bounds(0, 4_000)),
+            900,
+        );
+        assert_eq!(suspicions.len(), 1);
+        assert_eq!(suspicions[0].kind, DataLossSuspicionKind::StuckPartition);
+        assert_eq!(suspicions[0].num_records, 3_000);
+        assert_eq!(suspicions[0].timestamp, 900);
+    }
+
+    #[test]
+    fn test_data_loss_detector_expired_records() {
+        let mut detector = DataLossDetector::new(WINDOW);
+        let start = Instant::now();
+        let suspicions = detector.record_sample(
+            sample(start, &[(0, 999)], 1_000, 0),
+            Some(&bounds(1_500, 1_600)),
+            0,
+        );
+        assert_eq!(suspicions.len(), 1);
+        assert_eq!(suspicions[0].kind, DataLossSuspicionKind::ExpiredRecords);
+        assert_eq!(
+            suspicions[0].partition_id.as_deref(),
+            Some("00000000000000000000")
+        );
+        assert_eq!(suspicions[0].num_records, 500);
+
+        // Unpublished partitions are read from their oldest record.
+        let mut detector = DataLossDetector::new(WINDOW);
+        assert!(detector
+            .record_sample(sample(start, &[], 0, 0), Some(&bounds(1_500, 1_500)), 0)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_data_loss_detector_unaccounted_positions() {
+        let mut detector = DataLossDetector::new(WINDOW);
+        let start = Instant::now();
+        detector.record_sample(sample(start, &[(0, 999)], 1_000, 0), None, 0);
+        detector.record_sample(
+            sample(start + WINDOW / 2, &[(0, 5_999)], 5_500, 0),
+            None,
+            450,
+        );
+        let suspicions = detector.record_sample(
+            sample(start + WINDOW, &[(0, 10_999)], 9_000, 500),
+            None,
+            900,
+        );
+        assert_eq!(suspicions.len(), 1);
+        assert_eq!(
+            suspicions[0].kind,
+            DataLossSuspicionKind::UnaccountedPositions
+        );
+        assert_eq!(suspicions[0].partition_id, None);
+        assert_eq!(suspicions[0].num_records, 1_500);
+
+        // The sample taken at half window becomes the baseline.
+        let suspicions = detector.record_sample(
+            sample(start + WINDOW * 3 / 2, &[(0, 15_999)], 14_000, 500),
+            None,
+            1_350,
+        );
+        assert_eq!(suspicions.len(), 1);
+        assert_eq!(suspicions[0].num_records, 1_000);
+    }
+
+    #[test]
+    fn test_data_loss_detector_resets_on_counter_reset() {
+        let mut detector = DataLossDetector::new(WINDOW);
+        let start = Instant::now();
+        detector.record_sample(sample(start, &[(0, 999)], 1_000, 0), None, 0);
+        // The pipelines were respawned, the counters start over.
+        assert!(detector
+            .record_sample(sample(start + WINDOW / 2, &[(0, 5_999)], 0, 0), None, 450)
+            .is_empty());
+        assert!(detector
+            .record_sample(sample(start + WINDOW, &[(0, 10_999)], 5_000, 0), None, 900)
+            .is_empty());
+    }
+}
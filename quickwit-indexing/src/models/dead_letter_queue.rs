@@ -0,0 +1,329 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use quickwit_actors::Mailbox;
+use quickwit_config::DeadLetterQueueSettings;
+use quickwit_ingest_api::IngestApiService;
+use quickwit_proto::ingest_api::{DocBatch, IngestRequest};
+use quickwit_storage::Storage;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use time::OffsetDateTime;
+use tracing::info;
+
+use super::IndexingPipelineId;
+use crate::metrics::INDEXER_METRICS;
+
+/// Destination of the documents of a dead-letter queue.
+#[derive(Clone)]
+pub enum DeadLetterSink {
+    /// The documents are written as NDJSON files to a storage, a local directory or an object
+    /// storage prefix.
+    Storage(Arc<dyn Storage>),
+    /// The documents are appended to the ingest API queue of a dedicated index.
+    IngestApi {
+        ingest_api_service: Mailbox<IngestApiService>,
+        index_id: String,
+    },
+}
+
+impl fmt::Debug for DeadLetterSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeadLetterSink::Storage(storage) => f
+                .debug_tuple("Storage")
+                .field(&storage.uri().as_str())
+                .finish(),
+            DeadLetterSink::IngestApi { index_id, .. } => f
+                .debug_struct("IngestApi")
+                .field("index_id", index_id)
+                .finish(),
+        }
+    }
+}
+
+/// Counters of the documents written by a dead-letter queue.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeadLetterQueueCounters {
+    /// Number of documents written to the sink.
+    pub num_docs_written: u64,
+    /// Number of documents lost because writing them to the sink failed.
+    pub num_docs_dropped: u64,
+    /// Number of files written to the storage sink.
+    pub num_files_written: u64,
+    /// Number of files deleted from the storage sink to honor the `max_num_files` limit.
+    pub num_files_deleted: u64,
+}
+
+#[derive(Default)]
+struct DeadLetterQueueState {
+    /// Documents waiting to be written, concatenated, along with their lengths.
+    concat_docs: Vec<u8>,
+    doc_lens: Vec<u64>,
+    /// Files written to the storage sink, oldest first.
+    written_files: VecDeque<PathBuf>,
+    counters: DeadLetterQueueCounters,
+}
+
+/// Dead-letter queue shared by the indexers of all the generations of a pipeline.
+///
+/// The indexers push the documents failing to parse along with their parse error, and flush the
+/// queue on every commit or whenever the buffered documents reach `max_file_size`. Each flush
+/// writes a new file, named after a ULID so that files sort by creation time, and deletes the
+/// oldest files written by the pipeline beyond `max_num_files`.
+#[derive(Clone)]
+pub struct DeadLetterQueue {
+    pipeline_id: IndexingPipelineId,
+    sink: DeadLetterSink,
+    max_file_size: usize,
+    max_num_files: usize,
+    state: Arc<Mutex<DeadLetterQueueState>>,
+}
+
+impl DeadLetterQueue {
+    pub fn new(
+        pipeline_id: IndexingPipelineId,
+        sink: DeadLetterSink,
+        settings: &DeadLetterQueueSettings,
+    ) -> Self {
+        DeadLetterQueue {
+            pipeline_id,
+            sink,
+            max_file_size: settings.max_file_size.get_bytes() as usize,
+            max_num_files: settings.max_num_files,
+            state: Arc::default(),
+        }
+    }
+
+    /// Buffers a document that failed to parse, along with its parse error.
+    pub fn push(&self, doc_json: &str, error: &str) {
+        let record = json!({
+            "index_id": self.pipeline_id.index_id,
+            "source_id": self.pipeline_id.source_id,
+            "timestamp": OffsetDateTime::now_utc().unix_timestamp(),
+            "error": error,
+            "doc": doc_json,
+        })
+        .to_string();
+        let mut state = self.state.lock().unwrap();
+        state.concat_docs.extend_from_slice(record.as_bytes());
+        state.doc_lens.push(record.len() as u64);
+    }
+
+    /// Returns whether the buffered documents reached the max file size and should be flushed.
+    pub fn is_full(&self) -> bool {
+        self.state.lock().unwrap().concat_docs.len() >= self.max_file_size
+    }
+
+    /// Writes the buffered documents to the sink. The documents are dropped if the write fails.
+    pub async fn flush(&self) -> anyhow::Result<()> {
+        let (concat_docs, doc_lens) = {
+            let mut state = self.state.lock().unwrap();
+            (
+                std::mem::take(&mut state.concat_docs),
+                std::mem::take(&mut state.doc_lens),
+            )
+        };
+        if doc_lens.is_empty() {
+            return Ok(());
+        }
+        let num_docs = doc_lens.len() as u64;
+        let write_result = match &self.sink {
+            DeadLetterSink::Storage(storage) => {
+                self.write_file(&**storage, concat_docs, doc_lens).await
+            }
+            DeadLetterSink::IngestApi {
+                ingest_api_service,
+                index_id,
+            } => {
+                let ingest_req = IngestRequest {
+                    doc_batches: vec![DocBatch {
+                        index_id: index_id.clone(),
+                        concat_docs,
+                        doc_lens,
+                    }],
+                };
+                ingest_api_service
+                    .ask_for_res(ingest_req)
+                    .await
+                    .map(|_| ())
+                    .map_err(|error| anyhow::anyhow!(error.to_string()))
+            }
+        };
+        let mut state = self.state.lock().unwrap();
+        if write_result.is_err() {
+            state.counters.num_docs_dropped += num_docs;
+            return write_result;
+        }
+        state.counters.num_docs_written += num_docs;
+        INDEXER_METRICS
+            .dead_letter_docs_total
+            .with_label_values(&[&self.pipeline_id.index_id, &self.pipeline_id.source_id])
+            .inc_by(num_docs);
+        Ok(())
+    }
+
+    async fn write_file(
+        &self,
+        storage: &dyn Storage,
+        concat_docs: Vec<u8>,
+        doc_lens: Vec<u64>,
+    ) -> anyhow::Result<()> {
+        let mut payload = Vec::with_capacity(concat_docs.len() + doc_lens.len());
+        let mut offset = 0;
+        for doc_len in doc_lens {
+            let end = offset + doc_len as usize;
+            payload.extend_from_slice(&concat_docs[offset..end]);
+            payload.push(b'\n');
+            offset = end;
+        }
+        let file_path = PathBuf::from(&self.pipeline_id.source_id)
+            .join(format!("{}.ndjson", ulid::Ulid::new()));
+        info!(file_path=%file_path.display(), "write-dead-letter-file");
+        storage.put(&file_path, Box::new(payload)).await?;
+
+        let expired_files: Vec<PathBuf> = {
+            let mut state = self.state.lock().unwrap();
+            state.counters.num_files_written += 1;
+            state.written_files.push_back(file_path);
+            let num_expired_files = state.written_files.len().saturating_sub(self.max_num_files);
+            state.written_files.drain(..num_expired_files).collect()
+        };
+        for expired_file in expired_files {
+            storage.delete(&expired_file).await?;
+            self.state.lock().unwrap().counters.num_files_deleted += 1;
+        }
+        Ok(())
+    }
+
+    pub fn counters(&self) -> DeadLetterQueueCounters {
+        self.state.lock().unwrap().counters
+    }
+}
+
+impl fmt::Debug for DeadLetterQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeadLetterQueue")
+            .field("sink", &self.sink)
+            .field("counters", &self.counters())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use byte_unit::Byte;
+    use quickwit_storage::RamStorage;
+
+    use super::*;
+
+    fn dead_letter_queue_for_test(storage: RamStorage, max_num_files: usize) -> DeadLetterQueue {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let settings = DeadLetterQueueSettings {
+            uri: None,
+            index_id: None,
+            max_file_size: Byte::from_bytes(200),
+            max_num_files,
+        };
+        DeadLetterQueue::new(
+            pipeline_id,
+            DeadLetterSink::Storage(Arc::new(storage)),
+            &settings,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_dead_letter_queue_storage_sink() -> anyhow::Result<()> {
+        let storage = RamStorage::default();
+        let dead_letter_queue = dead_letter_queue_for_test(storage.clone(), 10);
+        // Flushing an empty queue does not write any file.
+        dead_letter_queue.flush().await?;
+        assert!(storage.list_files().await.is_empty());
+
+        dead_letter_queue.push("{\"body\": 42", "The provided string is not valid JSON.");
+        assert!(!dead_letter_queue.is_full());
+        dead_letter_queue.push("[]", "The provided string is not a valid JSON object.");
+        assert!(dead_letter_queue.is_full());
+        dead_letter_queue.flush().await?;
+
+        let files = storage.list_files().await;
+        assert_eq!(files.len(), 1);
+        assert!(files[0].starts_with("test-source"));
+        let payload = storage.get_all(&files[0]).await?;
+        let records: Vec<serde_json::Value> = std::str::from_utf8(payload.as_slice())?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["index_id"], "test-index");
+        assert_eq!(records[0]["doc"], "{\"body\": 42");
+        assert_eq!(
+            records[1]["error"],
+            "The provided string is not a valid JSON object."
+        );
+        assert_eq!(
+            dead_letter_queue.counters(),
+            DeadLetterQueueCounters {
+                num_docs_written: 2,
+                num_files_written: 1,
+                ..Default::default()
+            }
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dead_letter_queue_rotation() -> anyhow::Result<()> {
+        let storage = RamStorage::default();
+        let dead_letter_queue = dead_letter_queue_for_test(storage.clone(), 2);
+        let mut written_files = Vec::new();
+        for doc_ord in 0..3 {
+            dead_letter_queue.push(&doc_ord.to_string(), "Invalid doc.");
+            dead_letter_queue.flush().await?;
+            let new_file = storage
+                .list_files()
+                .await
+                .into_iter()
+                .find(|file| !written_files.contains(file))
+                .unwrap();
+            written_files.push(new_file);
+        }
+        // The oldest file was deleted.
+        let files = storage.list_files().await;
+        assert_eq!(files.len(), 2);
+        assert!(written_files[1..].iter().all(|file| files.contains(file)));
+        assert!(!storage.exists(Path::new(&written_files[0])).await?);
+        let counters = dead_letter_queue.counters();
+        assert_eq!(counters.num_files_written, 3);
+        assert_eq!(counters.num_files_deleted, 1);
+        Ok(())
+    }
+}
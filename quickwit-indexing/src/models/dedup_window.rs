@@ -0,0 +1,229 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, HashSet};
+use std::hash::Hasher;
+use std::time::Duration;
+
+use quickwit_config::DeduplicationSettings;
+use quickwit_metastore::{bucket_start_timestamp, DedupDigest, Metastore, SplitState};
+use serde_json::Value as JsonValue;
+use siphasher::sip::SipHasher;
+
+/// Sliding window of the hashes of the document IDs indexed for a source.
+///
+/// The window is made of the digests persisted in the metadata of the splits published for the
+/// source, which survive pipeline restarts, and of the hashes of the documents indexed since the
+/// window was loaded.
+pub struct DedupWindow {
+    doc_id_path_opt: Option<Vec<String>>,
+    window: Duration,
+    published_digest: DedupDigest,
+    recent_hashes: BTreeMap<i64, HashSet<u64>>,
+}
+
+impl DedupWindow {
+    pub fn new(
+        deduplication_settings: &DeduplicationSettings,
+        published_digest: DedupDigest,
+    ) -> Self {
+        let doc_id_path_opt = deduplication_settings
+            .doc_id_field
+            .as_ref()
+            .map(|doc_id_field| doc_id_field.split('.').map(ToString::to_string).collect());
+        DedupWindow {
+            doc_id_path_opt,
+            window: deduplication_settings.window(),
+            published_digest,
+            recent_hashes: BTreeMap::new(),
+        }
+    }
+
+    /// Loads the window from the digests of the splits published for the source.
+    pub async fn load(
+        deduplication_settings: &DeduplicationSettings,
+        metastore: &dyn Metastore,
+        index_id: &str,
+        source_id: &str,
+        now_timestamp: i64,
+    ) -> anyhow::Result<Self> {
+        let published_splits = metastore
+            .list_splits(index_id, SplitState::Published, None, None)
+            .await?;
+        let mut published_digest = DedupDigest::default();
+        for split in published_splits {
+            if split.split_metadata.source_id != source_id {
+                continue;
+            }
+            if let Some(dedup_digest) = split.split_metadata.dedup_digest {
+                published_digest.merge(dedup_digest);
+            }
+        }
+        let mut dedup_window = DedupWindow::new(deduplication_settings, published_digest);
+        dedup_window.evict_expired_buckets(now_timestamp);
+        Ok(dedup_window)
+    }
+
+    /// Returns the hash of the ID of the document, or `None` if the document has no ID.
+    pub fn doc_hash(&self, doc_json: &str) -> Option<u64> {
+        let mut hasher = SipHasher::new();
+        let doc_id_path = match &self.doc_id_path_opt {
+            Some(doc_id_path) => doc_id_path,
+            None => {
+                hasher.write(doc_json.as_bytes());
+                return Some(hasher.finish());
+            }
+        };
+        let doc_value: JsonValue = serde_json::from_str(doc_json).ok()?;
+        let mut doc_id_value = &doc_value;
+        for key in doc_id_path {
+            doc_id_value = doc_id_value.get(key)?;
+        }
+        match doc_id_value {
+            JsonValue::Null => return None,
+            JsonValue::String(doc_id) => hasher.write(doc_id.as_bytes()),
+            _ => hasher.write(doc_id_value.to_string().as_bytes()),
+        }
+        Some(hasher.finish())
+    }
+
+    /// Returns whether a document with the same ID hash was already indexed within the window.
+    pub fn contains(&self, doc_hash: u64) -> bool {
+        self.recent_hashes
+            .values()
+            .any(|bucket_hashes| bucket_hashes.contains(&doc_hash))
+            || self.published_digest.contains(doc_hash)
+    }
+
+    /// Records the ID hash of a document indexed at `now_timestamp`.
+    pub fn insert(&mut self, doc_hash: u64, now_timestamp: i64) {
+        self.evict_expired_buckets(now_timestamp);
+        self.recent_hashes
+            .entry(bucket_start_timestamp(now_timestamp))
+            .or_default()
+            .insert(doc_hash);
+    }
+
+    fn evict_expired_buckets(&mut self, now_timestamp: i64) {
+        let window_start_timestamp = now_timestamp - self.window.as_secs() as i64;
+        let first_bucket_timestamp = bucket_start_timestamp(window_start_timestamp);
+        self.recent_hashes = self.recent_hashes.split_off(&first_bucket_timestamp);
+        self.published_digest.retain_since(window_start_timestamp);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_metastore::{MockMetastore, Split, SplitMetadata};
+
+    use super::*;
+
+    #[test]
+    fn test_dedup_window_doc_hash() {
+        let deduplication_settings = DeduplicationSettings {
+            doc_id_field: Some("event.id".to_string()),
+            window_secs: 60,
+        };
+        let dedup_window = DedupWindow::new(&deduplication_settings, DedupDigest::default());
+        let doc_hash = dedup_window.doc_hash(r#"{"event": {"id": "a"}, "body": "foo"}"#);
+        assert!(doc_hash.is_some());
+        assert_eq!(
+            dedup_window.doc_hash(r#"{"event": {"id": "a"}, "body": "bar"}"#),
+            doc_hash
+        );
+        assert_ne!(
+            dedup_window.doc_hash(r#"{"event": {"id": "b"}, "body": "foo"}"#),
+            doc_hash
+        );
+        assert!(dedup_window.doc_hash(r#"{"body": "foo"}"#).is_none());
+        assert!(dedup_window.doc_hash("{").is_none());
+
+        let deduplication_settings = DeduplicationSettings {
+            doc_id_field: None,
+            window_secs: 60,
+        };
+        let dedup_window = DedupWindow::new(&deduplication_settings, DedupDigest::default());
+        assert_ne!(
+            dedup_window.doc_hash(r#"{"body": "foo"}"#),
+            dedup_window.doc_hash(r#"{"body": "bar"}"#)
+        );
+    }
+
+    #[test]
+    fn test_dedup_window_evicts_expired_hashes() {
+        let deduplication_settings = DeduplicationSettings {
+            doc_id_field: None,
+            window_secs: 120,
+        };
+        let mut dedup_window = DedupWindow::new(&deduplication_settings, DedupDigest::default());
+        dedup_window.insert(1, 1_000);
+        dedup_window.insert(2, 1_100);
+        assert!(dedup_window.contains(1));
+        assert!(dedup_window.contains(2));
+        assert!(!dedup_window.contains(3));
+
+        dedup_window.insert(3, 1_200);
+        assert!(!dedup_window.contains(1));
+        assert!(dedup_window.contains(2));
+        assert!(dedup_window.contains(3));
+    }
+
+    #[tokio::test]
+    async fn test_dedup_window_load() {
+        let now_timestamp = 10_000;
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_list_splits()
+            .returning(move |_, _, _, _| {
+                let make_split = |source_id: &str, hash: u64, bucket_timestamp: i64| Split {
+                    split_state: SplitState::Published,
+                    update_timestamp: 0,
+                    split_metadata: SplitMetadata {
+                        source_id: source_id.to_string(),
+                        dedup_digest: Some(DedupDigest::from_buckets(
+                            [(bucket_timestamp, &[hash][..])],
+                            1,
+                        )),
+                        ..Default::default()
+                    },
+                };
+                Ok(vec![
+                    make_split("test-source", 1, now_timestamp - 30),
+                    make_split("test-source", 2, now_timestamp - 3_600),
+                    make_split("other-source", 3, now_timestamp - 30),
+                ])
+            });
+        let deduplication_settings = DeduplicationSettings {
+            doc_id_field: None,
+            window_secs: 600,
+        };
+        let dedup_window = DedupWindow::load(
+            &deduplication_settings,
+            &mock_metastore,
+            "test-index",
+            "test-source",
+            now_timestamp,
+        )
+        .await
+        .unwrap();
+        assert!(dedup_window.contains(1));
+        assert!(!dedup_window.contains(2));
+        assert!(!dedup_window.contains(3));
+    }
+}
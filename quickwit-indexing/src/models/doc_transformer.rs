@@ -0,0 +1,353 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use quickwit_config::{parse_format_template, DocTransform, FormatTemplateSegment};
+use quickwit_doc_mapper::{format_date_time, parse_date_time, DateTimeFormat};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+type JsonObject = JsonMap<String, JsonValue>;
+
+/// Error raised when a document cannot be transformed. The document is then rejected like a
+/// document failing to parse.
+#[derive(Debug, thiserror::Error)]
+#[error("Failed to transform document: {0}")]
+pub struct DocTransformError(String);
+
+/// Processor of the transform stage, with its template parsed.
+#[derive(Clone, Debug)]
+enum Processor {
+    Rename {
+        field: Vec<String>,
+        target_field: Vec<String>,
+    },
+    Remove {
+        fields: Vec<Vec<String>>,
+    },
+    Set {
+        field: Vec<String>,
+        value: JsonValue,
+    },
+    Format {
+        target_field: Vec<String>,
+        segments: Vec<FormatTemplateSegment>,
+    },
+    ParseTimestamp {
+        field: Vec<String>,
+        input_formats: Vec<DateTimeFormat>,
+        output_format: DateTimeFormat,
+        target_field: Vec<String>,
+    },
+}
+
+fn field_path(path: &str) -> Vec<String> {
+    path.split('.').map(str::to_string).collect()
+}
+
+/// Applies the transforms of a source to its documents before they are parsed by the doc mapper.
+#[derive(Clone, Debug)]
+pub struct DocTransformer {
+    processors: Vec<Processor>,
+}
+
+impl DocTransformer {
+    /// Returns a transformer applying `transforms` in order, or `None` if there is no transform.
+    pub fn try_new(transforms: &[DocTransform]) -> anyhow::Result<Option<Self>> {
+        if transforms.is_empty() {
+            return Ok(None);
+        }
+        let mut processors = Vec::with_capacity(transforms.len());
+        for transform in transforms {
+            let processor = match transform {
+                DocTransform::Rename {
+                    field,
+                    target_field,
+                } => Processor::Rename {
+                    field: field_path(field),
+                    target_field: field_path(target_field),
+                },
+                DocTransform::Remove { fields } => Processor::Remove {
+                    fields: fields.iter().map(|field| field_path(field)).collect(),
+                },
+                DocTransform::Set { field, value } => Processor::Set {
+                    field: field_path(field),
+                    value: value.clone(),
+                },
+                DocTransform::Format {
+                    target_field,
+                    template,
+                } => Processor::Format {
+                    target_field: field_path(target_field),
+                    segments: parse_format_template(template)?,
+                },
+                DocTransform::ParseTimestamp {
+                    field,
+                    input_formats,
+                    output_format,
+                    target_field,
+                } => Processor::ParseTimestamp {
+                    field: field_path(field),
+                    input_formats: input_formats.clone(),
+                    output_format: output_format.clone(),
+                    target_field: field_path(target_field.as_ref().unwrap_or(field)),
+                },
+            };
+            processors.push(processor);
+        }
+        Ok(Some(DocTransformer { processors }))
+    }
+
+    /// Applies the transforms to a JSON document and returns the transformed document.
+    pub fn transform(&self, doc_json: &str) -> Result<String, DocTransformError> {
+        let mut json_obj: JsonObject = serde_json::from_str(doc_json)
+            .map_err(|error| DocTransformError(format!("Invalid JSON object: {}", error)))?;
+        for processor in &self.processors {
+            apply_processor(processor, &mut json_obj)?;
+        }
+        Ok(JsonValue::Object(json_obj).to_string())
+    }
+}
+
+fn apply_processor(
+    processor: &Processor,
+    json_obj: &mut JsonObject,
+) -> Result<(), DocTransformError> {
+    match processor {
+        Processor::Rename {
+            field,
+            target_field,
+        } => {
+            if let Some(value) = remove_value(json_obj, field) {
+                insert_value(json_obj, target_field, value)?;
+            }
+        }
+        Processor::Remove { fields } => {
+            for field in fields {
+                remove_value(json_obj, field);
+            }
+        }
+        Processor::Set { field, value } => {
+            insert_value(json_obj, field, value.clone())?;
+        }
+        Processor::Format {
+            target_field,
+            segments,
+        } => {
+            let mut formatted = String::new();
+            for segment in segments {
+                match segment {
+                    FormatTemplateSegment::Literal(literal) => formatted.push_str(literal),
+                    FormatTemplateSegment::Field(path) => {
+                        match get_value(json_obj, &field_path(path)) {
+                            Some(JsonValue::String(value)) => formatted.push_str(value),
+                            Some(value @ (JsonValue::Number(_) | JsonValue::Bool(_))) => {
+                                formatted.push_str(&value.to_string())
+                            }
+                            Some(_) => {
+                                return Err(DocTransformError(format!(
+                                    "Field `{}` referenced by the template is not a string, a \
+                                     number, or a boolean.",
+                                    path
+                                )))
+                            }
+                            None => {
+                                return Err(DocTransformError(format!(
+                                    "Field `{}` referenced by the template is missing.",
+                                    path
+                                )))
+                            }
+                        }
+                    }
+                }
+            }
+            insert_value(json_obj, target_field, JsonValue::String(formatted))?;
+        }
+        Processor::ParseTimestamp {
+            field,
+            input_formats,
+            output_format,
+            target_field,
+        } => {
+            let value = match get_value(json_obj, field) {
+                Some(value) => value,
+                None => return Ok(()),
+            };
+            let formatted_date_time = parse_date_time(value, input_formats)
+                .and_then(|date_time| format_date_time(date_time, output_format))
+                .map_err(|error| {
+                    DocTransformError(format!(
+                        "Failed to parse timestamp field `{}`: {}",
+                        field.join("."),
+                        error
+                    ))
+                })?;
+            insert_value(json_obj, target_field, formatted_date_time)?;
+        }
+    }
+    Ok(())
+}
+
+fn get_value<'a>(json_obj: &'a JsonObject, path: &[String]) -> Option<&'a JsonValue> {
+    let (last_key, parent_keys) = path.split_last()?;
+    let mut current_obj = json_obj;
+    for key in parent_keys {
+        current_obj = current_obj.get(key)?.as_object()?;
+    }
+    current_obj.get(last_key)
+}
+
+fn remove_value(json_obj: &mut JsonObject, path: &[String]) -> Option<JsonValue> {
+    let (last_key, parent_keys) = path.split_last()?;
+    let mut current_obj = json_obj;
+    for key in parent_keys {
+        current_obj = current_obj.get_mut(key)?.as_object_mut()?;
+    }
+    current_obj.remove(last_key)
+}
+
+/// Inserts a value, creating the missing parent objects along the way.
+fn insert_value(
+    json_obj: &mut JsonObject,
+    path: &[String],
+    value: JsonValue,
+) -> Result<(), DocTransformError> {
+    let (last_key, parent_keys) = path.split_last().expect("Field paths should not be empty.");
+    let mut current_obj = json_obj;
+    for key in parent_keys {
+        current_obj = current_obj
+            .entry(key.clone())
+            .or_insert_with(|| JsonValue::Object(JsonObject::new()))
+            .as_object_mut()
+            .ok_or_else(|| {
+                DocTransformError(format!(
+                    "Cannot write field `{}`: `{}` is not an object.",
+                    path.join("."),
+                    key
+                ))
+            })?;
+    }
+    current_obj.insert(last_key.clone(), value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn transform(transforms_yaml: &str, doc: JsonValue) -> Result<JsonValue, DocTransformError> {
+        let transforms: Vec<DocTransform> = serde_yaml::from_str(transforms_yaml).unwrap();
+        let doc_transformer = DocTransformer::try_new(&transforms).unwrap().unwrap();
+        let transformed_doc_json = doc_transformer.transform(&doc.to_string())?;
+        Ok(serde_json::from_str(&transformed_doc_json).unwrap())
+    }
+
+    #[test]
+    fn test_doc_transformer() {
+        let transforms_yaml = r#"
+            - type: rename
+              field: msg
+              target_field: event.message
+            - type: remove
+              fields: [debug, internal.trace_id, missing]
+            - type: set
+              field: env
+              value: prod
+            - type: format
+              target_field: service
+              template: "{app.name}-{env}/{app.shard}"
+            - type: parse_timestamp
+              field: date
+              input_formats: ["%Y-%m-%d %H:%M:%S"]
+              output_format: unix_ts_secs
+              target_field: timestamp
+        "#;
+        let doc = json!({
+            "msg": "hello",
+            "debug": true,
+            "internal": {"trace_id": "abc", "span_id": "def"},
+            "app": {"name": "checkout", "shard": 3},
+            "date": "2022-09-12 14:09:14",
+        });
+        assert_eq!(
+            transform(transforms_yaml, doc).unwrap(),
+            json!({
+                "event": {"message": "hello"},
+                "internal": {"span_id": "def"},
+                "app": {"name": "checkout", "shard": 3},
+                "env": "prod",
+                "service": "checkout-prod/3",
+                "date": "2022-09-12 14:09:14",
+                "timestamp": 1662991754,
+            })
+        );
+    }
+
+    #[test]
+    fn test_doc_transformer_missing_fields() {
+        let transforms_yaml = r#"
+            - type: rename
+              field: msg
+              target_field: message
+            - type: parse_timestamp
+              field: date
+              input_formats: [rfc3339]
+        "#;
+        // Processors addressing a missing field leave the document untouched.
+        let doc = json!({"body": "hello"});
+        assert_eq!(transform(transforms_yaml, doc.clone()).unwrap(), doc);
+
+        let format_yaml = r#"
+            - type: format
+              target_field: service
+              template: "{app.name}"
+        "#;
+        let error = transform(format_yaml, doc).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Field `app.name` referenced by the template is missing."));
+    }
+
+    #[test]
+    fn test_doc_transformer_errors() {
+        let transforms_yaml = r#"
+            - type: parse_timestamp
+              field: date
+              input_formats: [rfc3339]
+        "#;
+        let error = transform(transforms_yaml, json!({"date": "yesterday"})).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Failed to parse timestamp field `date`"));
+
+        let set_yaml = r#"
+            - type: set
+              field: app.name
+              value: checkout
+        "#;
+        assert!(transform(set_yaml, json!({"app": "checkout"})).is_err());
+
+        let transformer =
+            DocTransformer::try_new(&serde_yaml::from_str::<Vec<DocTransform>>(set_yaml).unwrap())
+                .unwrap()
+                .unwrap();
+        assert!(transformer.transform("[1, 2]").is_err());
+        assert!(DocTransformer::try_new(&[]).unwrap().is_none());
+    }
+}
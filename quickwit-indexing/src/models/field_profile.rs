@@ -0,0 +1,207 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use quickwit_doc_mapper::FieldCost;
+use serde::Serialize;
+
+/// Size and tokenization time of the fields of the documents sampled by the indexer when field
+/// profiling is enabled.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FieldProfile {
+    pub num_sampled_docs: u64,
+    /// Time spent parsing the sampled documents, tokenization excluded.
+    pub parse_nanos: u64,
+    /// Cost of the fields of the sampled documents, keyed by field name.
+    pub field_costs: BTreeMap<String, FieldCost>,
+}
+
+impl FieldProfile {
+    /// Records the parse time and the field costs of a sampled document.
+    pub fn record_doc(
+        &mut self,
+        parse_duration: Duration,
+        field_costs: BTreeMap<String, FieldCost>,
+    ) {
+        self.num_sampled_docs += 1;
+        self.parse_nanos += parse_duration.as_nanos() as u64;
+        for (field_name, field_cost) in field_costs {
+            self.field_costs
+                .entry(field_name)
+                .or_default()
+                .add(&field_cost);
+        }
+    }
+
+    /// Adds the documents sampled by another indexer.
+    pub fn merge(&mut self, other: &FieldProfile) {
+        self.num_sampled_docs += other.num_sampled_docs;
+        self.parse_nanos += other.parse_nanos;
+        for (field_name, field_cost) in &other.field_costs {
+            self.field_costs
+                .entry(field_name.clone())
+                .or_default()
+                .add(field_cost);
+        }
+    }
+
+    /// Returns the report of the profile, listing the fields from the most to the least expensive.
+    pub fn report(&self) -> FieldProfileReport {
+        let total_num_bytes: u64 = self
+            .field_costs
+            .values()
+            .map(|field_cost| field_cost.num_bytes)
+            .sum();
+        let total_tokenization_nanos: u64 = self
+            .field_costs
+            .values()
+            .map(|field_cost| field_cost.tokenization_nanos)
+            .sum();
+        let mut fields: Vec<FieldProfileEntry> = self
+            .field_costs
+            .iter()
+            .map(|(field_name, field_cost)| FieldProfileEntry {
+                field_name: field_name.clone(),
+                num_bytes_per_doc: field_cost.num_bytes as f64 / self.num_sampled_docs as f64,
+                num_tokens_per_doc: field_cost.num_tokens as f64 / self.num_sampled_docs as f64,
+                tokenization_nanos_per_doc: field_cost.tokenization_nanos as f64
+                    / self.num_sampled_docs as f64,
+                num_bytes_share: share(field_cost.num_bytes, total_num_bytes),
+                tokenization_share: share(field_cost.tokenization_nanos, total_tokenization_nanos),
+            })
+            .collect();
+        fields.sort_by(|left, right| {
+            right
+                .cost_share()
+                .total_cmp(&left.cost_share())
+                .then_with(|| left.field_name.cmp(&right.field_name))
+        });
+        FieldProfileReport {
+            num_sampled_docs: self.num_sampled_docs,
+            parse_nanos_per_doc: if self.num_sampled_docs == 0 {
+                0.0
+            } else {
+                self.parse_nanos as f64 / self.num_sampled_docs as f64
+            },
+            fields,
+        }
+    }
+}
+
+fn share(value: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    value as f64 / total as f64
+}
+
+/// Field profile averaged per sampled document.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FieldProfileReport {
+    pub num_sampled_docs: u64,
+    pub parse_nanos_per_doc: f64,
+    /// Fields sorted by decreasing share of the size or of the tokenization time of the sampled
+    /// documents, whichever is the largest.
+    pub fields: Vec<FieldProfileEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FieldProfileEntry {
+    pub field_name: String,
+    pub num_bytes_per_doc: f64,
+    pub num_tokens_per_doc: f64,
+    pub tokenization_nanos_per_doc: f64,
+    /// Share of the size of the sampled documents held by the field, between 0 and 1.
+    pub num_bytes_share: f64,
+    /// Share of the tokenization time of the sampled documents spent on the field, between 0
+    /// and 1.
+    pub tokenization_share: f64,
+}
+
+impl FieldProfileEntry {
+    fn cost_share(&self) -> f64 {
+        self.num_bytes_share.max(self.tokenization_share)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field_cost(num_bytes: u64, num_tokens: u64, tokenization_nanos: u64) -> FieldCost {
+        FieldCost {
+            num_bytes,
+            num_tokens,
+            tokenization_nanos,
+        }
+    }
+
+    #[test]
+    fn test_field_profile_report() {
+        let mut field_profile = FieldProfile::default();
+        assert_eq!(field_profile.report().num_sampled_docs, 0);
+        assert!(field_profile.report().fields.is_empty());
+
+        field_profile.record_doc(
+            Duration::from_nanos(1_000),
+            BTreeMap::from([
+                ("body".to_string(), field_cost(100, 20, 800)),
+                ("timestamp".to_string(), field_cost(8, 0, 0)),
+            ]),
+        );
+        let mut other_field_profile = FieldProfile::default();
+        other_field_profile.record_doc(
+            Duration::from_nanos(3_000),
+            BTreeMap::from([
+                ("payload".to_string(), field_cost(284, 0, 0)),
+                ("body".to_string(), field_cost(100, 20, 1_200)),
+                ("timestamp".to_string(), field_cost(8, 0, 0)),
+            ]),
+        );
+        field_profile.merge(&other_field_profile);
+        assert_eq!(field_profile.num_sampled_docs, 2);
+        assert_eq!(
+            field_profile.field_costs["body"],
+            field_cost(200, 40, 2_000)
+        );
+
+        let report = field_profile.report();
+        assert_eq!(report.parse_nanos_per_doc, 2_000.0);
+        let field_names: Vec<&str> = report
+            .fields
+            .iter()
+            .map(|field| field.field_name.as_str())
+            .collect();
+        assert_eq!(field_names, ["body", "payload", "timestamp"]);
+
+        let body = &report.fields[0];
+        assert_eq!(body.num_bytes_per_doc, 100.0);
+        assert_eq!(body.num_tokens_per_doc, 20.0);
+        assert_eq!(body.tokenization_nanos_per_doc, 1_000.0);
+        assert_eq!(body.num_bytes_share, 0.4);
+        assert_eq!(body.tokenization_share, 1.0);
+
+        let payload = &report.fields[1];
+        assert_eq!(payload.num_bytes_per_doc, 142.0);
+        assert_eq!(payload.num_bytes_share, 0.568);
+        assert_eq!(payload.tokenization_share, 0.0);
+    }
+}
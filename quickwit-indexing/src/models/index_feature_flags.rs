@@ -0,0 +1,103 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, RwLock};
+
+/// Feature flags of an index, as last read from the metastore by the pipeline. Cloning the flags
+/// shares them: the actors holding a clone see the flags refreshed by the pipeline without being
+/// respawned.
+#[derive(Clone, Debug, Default)]
+pub struct IndexFeatureFlags {
+    inner: Arc<RwLock<BTreeMap<String, bool>>>,
+}
+
+impl IndexFeatureFlags {
+    pub fn new(feature_flags: BTreeMap<String, bool>) -> Self {
+        IndexFeatureFlags {
+            inner: Arc::new(RwLock::new(feature_flags)),
+        }
+    }
+
+    /// Returns whether the feature flag `flag` is enabled. Flags that are not set are disabled.
+    pub fn is_enabled(&self, flag: &str) -> bool {
+        self.inner
+            .read()
+            .expect("Lock should not be poisoned.")
+            .get(flag)
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Returns a copy of the feature flags.
+    pub fn snapshot(&self) -> BTreeMap<String, bool> {
+        self.inner
+            .read()
+            .expect("Lock should not be poisoned.")
+            .clone()
+    }
+
+    /// Replaces the feature flags. Returns the names of the flags that were toggled.
+    pub(crate) fn update(&self, feature_flags: &BTreeMap<String, bool>) -> Vec<String> {
+        let mut inner = self.inner.write().expect("Lock should not be poisoned.");
+        let toggled_flags: Vec<String> = inner
+            .keys()
+            .chain(feature_flags.keys())
+            .filter(|flag| {
+                inner.get(*flag).copied().unwrap_or(false)
+                    != feature_flags.get(*flag).copied().unwrap_or(false)
+            })
+            .cloned()
+            .collect::<BTreeSet<String>>()
+            .into_iter()
+            .collect();
+        *inner = feature_flags.clone();
+        toggled_flags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_feature_flags_update() {
+        let feature_flags = IndexFeatureFlags::new(BTreeMap::from([
+            ("shadow_indexing".to_string(), true),
+            ("dictionary_compression".to_string(), false),
+        ]));
+        let shared_feature_flags = feature_flags.clone();
+        assert!(shared_feature_flags.is_enabled("shadow_indexing"));
+        assert!(!shared_feature_flags.is_enabled("dictionary_compression"));
+        assert!(!shared_feature_flags.is_enabled("time_bucket_merges"));
+
+        // Unsetting a disabled flag does not toggle it.
+        let toggled_flags = feature_flags.update(&BTreeMap::from([
+            ("shadow_indexing".to_string(), false),
+            ("time_bucket_merges".to_string(), true),
+        ]));
+        assert_eq!(toggled_flags, ["shadow_indexing", "time_bucket_merges"]);
+        assert!(!shared_feature_flags.is_enabled("shadow_indexing"));
+        assert!(shared_feature_flags.is_enabled("time_bucket_merges"));
+
+        assert!(feature_flags
+            .update(&shared_feature_flags.snapshot())
+            .is_empty());
+    }
+}
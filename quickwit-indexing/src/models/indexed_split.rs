@@ -28,7 +28,7 @@ use tantivy::directory::MmapDirectory;
 use tantivy::merge_policy::NoMergePolicy;
 use tantivy::IndexBuilder;
 
-use crate::controlled_directory::ControlledDirectory;
+use crate::controlled_directory::{ControlledDirectory, DirectoryWriteSettings};
 use crate::models::{IndexingPipelineId, PublishLock, ScratchDirectory, SplitAttrs};
 use crate::new_split_id;
 
@@ -71,7 +71,8 @@ impl IndexedSplit {
         let mmap_directory = MmapDirectory::open(split_scratch_directory.path())?;
         let box_mmap_directory = Box::new(mmap_directory);
         let controlled_directory =
-            ControlledDirectory::new(box_mmap_directory, progress, kill_switch);
+            ControlledDirectory::new(box_mmap_directory, progress, kill_switch)
+                .with_write_settings(DirectoryWriteSettings::from(&indexing_resources));
         let index = index_builder.open_or_create(controlled_directory.clone())?;
         let index_writer = index.writer_with_num_threads(
             1, // DO NOT MODIFY THIS!
@@ -86,6 +87,11 @@ impl IndexedSplit {
             uncompressed_docs_size_in_bytes: 0,
             time_range: None,
             replaced_split_ids: Vec::new(),
+            delete_opstamp: 0,
+            dedup_digest_opt: None,
+            min_expire_timestamp: None,
+            doc_mapping_version: 0,
+            config_fingerprint: None,
         };
         index_writer.set_merge_policy(Box::new(NoMergePolicy));
         Ok(IndexedSplit {
@@ -110,6 +116,17 @@ impl IndexedSplit {
 pub struct IndexedSplitBatch {
     pub splits: Vec<IndexedSplit>,
     pub checkpoint_delta: Option<IndexCheckpointDelta>,
+    /// When set, the splits are staged and uploaded right away but the publisher holds them back
+    /// and publishes them along with the next checkpoint delta of the pipeline.
+    pub publish_with_next_delta: bool,
     pub publish_lock: PublishLock,
     pub date_of_birth: Instant,
 }
+
+/// Batch of splits emitted on commit by one of the shards of a sharded pipeline. The packager
+/// combines the batches of all the shards for the same commit into a single batch.
+#[derive(Debug)]
+pub struct ShardSplitBatch {
+    pub shard_ord: usize,
+    pub batch: IndexedSplitBatch,
+}
@@ -19,16 +19,39 @@
 
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::{fs as std_fs, io};
 
 use anyhow::Context;
-use quickwit_common::fs::empty_dir;
 use tempfile::TempDir;
+use time::OffsetDateTime;
 use tokio::fs;
+use tracing::info;
 
 use super::ScratchDirectory;
+use crate::metrics::INDEXER_METRICS;
 
 pub const CACHE: &str = "cache";
 
+pub const SCRATCH: &str = "scratch";
+
+/// Directory holding the splits that failed verification before being published.
+pub const QUARANTINE: &str = "quarantine";
+
+/// Directory holding the orphan scratch directories retained for debugging. Each cleanup moves
+/// the orphans it finds into a new time slice `<indexing dir>/orphan-scratch/<timestamp>`.
+pub const ORPHAN_SCRATCH: &str = "orphan-scratch";
+
+/// Statistics about the orphan scratch data cleaned up when an indexing directory is created.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ScratchCleanupStats {
+    /// Number of orphan entries found in the scratch directory.
+    pub num_orphan_entries: usize,
+    /// Number of time slices of orphan entries deleted.
+    pub num_removed_slices: usize,
+    /// Number of bytes reclaimed by deleting time slices.
+    pub num_reclaimed_bytes: u64,
+}
+
 /// Root of an [`IndexingDirectory`].
 #[derive(Clone)]
 enum Root {
@@ -40,21 +63,31 @@ enum Root {
 
 /// An indexing directory is created in the data directory on the local file system for each index
 /// at the following location: `<data dir>/indexing/<index ID>/<source ID>`.
-/// The indexing directory consists of two directories:
+/// The indexing directory consists of three directories:
 /// - a scratch directory that stores temporary intermediate files
 /// - a cache directory that stores frequently accessed data structures
+/// - a quarantine directory that stores the splits that failed verification
 /// While the scratch directory is emptied upon restart, the cache directory is not, and it is
 /// the responsability of the users of this folder to properly manage the lifecycle of the data
 /// that they write to it.
+///
+/// Workbenches are never resumed after a restart: pipelines restart from the last published
+/// checkpoint. Therefore, all the data found in the scratch directory upon restart is orphaned.
+/// The last `max_num_retained_orphan_scratch_dirs` slices of orphan data are kept for debugging
+/// in the orphan scratch directory.
 #[derive(Clone)]
 pub struct IndexingDirectory {
     root: Root,
     pub cache_directory: PathBuf,
     pub scratch_directory: ScratchDirectory,
+    pub quarantine_directory: PathBuf,
 }
 
 impl IndexingDirectory {
-    pub async fn create_in_dir<P: AsRef<Path>>(dir_path: P) -> anyhow::Result<IndexingDirectory> {
+    pub async fn create_in_dir<P: AsRef<Path>>(
+        dir_path: P,
+        max_num_retained_orphan_scratch_dirs: usize,
+    ) -> anyhow::Result<IndexingDirectory> {
         // Create cache directory if does not exist.
         let cache_directory_path = dir_path.as_ref().join(CACHE);
         fs::create_dir_all(&cache_directory_path)
@@ -66,7 +99,7 @@ impl IndexingDirectory {
                 )
             })?;
         // Create scratch directory if does not exist.
-        let scratch_directory_path = dir_path.as_ref().join("scratch");
+        let scratch_directory_path = dir_path.as_ref().join(SCRATCH);
         fs::create_dir_all(&scratch_directory_path)
             .await
             .with_context(|| {
@@ -75,19 +108,51 @@ impl IndexingDirectory {
                     scratch_directory_path.display(),
                 )
             })?;
-        // Empty scratch directory if already exists.
-        empty_dir(&scratch_directory_path).await.with_context(|| {
+        // Create quarantine directory if does not exist.
+        let quarantine_directory_path = dir_path.as_ref().join(QUARANTINE);
+        fs::create_dir_all(&quarantine_directory_path)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to create quarantine directory `{}`. ",
+                    quarantine_directory_path.display(),
+                )
+            })?;
+        // Move the orphan data out of the scratch directory and delete old orphan data.
+        let orphan_scratch_directory_path = dir_path.as_ref().join(ORPHAN_SCRATCH);
+        let cleanup_stats = {
+            let scratch_directory_path = scratch_directory_path.clone();
+            tokio::task::spawn_blocking(move || {
+                clean_up_scratch_directory(
+                    &scratch_directory_path,
+                    &orphan_scratch_directory_path,
+                    max_num_retained_orphan_scratch_dirs,
+                )
+            })
+            .await?
+        }
+        .with_context(|| {
             format!(
                 "Failed to empty scratch directory `{}`.",
                 scratch_directory_path.display(),
             )
         })?;
+        if cleanup_stats.num_orphan_entries > 0 || cleanup_stats.num_removed_slices > 0 {
+            info!(
+                scratch_directory=%scratch_directory_path.display(),
+                num_orphan_entries=cleanup_stats.num_orphan_entries,
+                num_removed_slices=cleanup_stats.num_removed_slices,
+                num_reclaimed_bytes=cleanup_stats.num_reclaimed_bytes,
+                "Cleaned up orphan scratch data."
+            );
+        }
         let scratch_directory = ScratchDirectory::new_in_dir(scratch_directory_path);
 
         let indexing_directory = Self {
             root: Root::Dir(dir_path.as_ref().to_path_buf()),
             cache_directory: cache_directory_path,
             scratch_directory,
+            quarantine_directory: quarantine_directory_path,
         };
         Ok(indexing_directory)
     }
@@ -101,12 +166,81 @@ impl IndexingDirectory {
 
     pub async fn for_test() -> anyhow::Result<Self> {
         let tempdir = tempfile::tempdir()?;
-        let mut indexing_directory = IndexingDirectory::create_in_dir(tempdir.path()).await?;
+        let mut indexing_directory = IndexingDirectory::create_in_dir(tempdir.path(), 0).await?;
         indexing_directory.root = Root::TempDir(Arc::new(tempdir));
         Ok(indexing_directory)
     }
 }
 
+/// Moves the entries of the scratch directory into a new time slice of the orphan scratch
+/// directory, then deletes the oldest slices so that at most `max_num_retained_slices` remain.
+fn clean_up_scratch_directory(
+    scratch_directory_path: &Path,
+    orphan_scratch_directory_path: &Path,
+    max_num_retained_slices: usize,
+) -> io::Result<ScratchCleanupStats> {
+    let mut cleanup_stats = ScratchCleanupStats::default();
+    let orphan_entry_paths: Vec<PathBuf> = std_fs::read_dir(scratch_directory_path)?
+        .map(|entry_res| entry_res.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+    cleanup_stats.num_orphan_entries = orphan_entry_paths.len();
+
+    if !orphan_entry_paths.is_empty() {
+        // Slice names are zero-padded timestamps, so sorting them by name sorts them by time.
+        let slice_name = format!("{:020}", OffsetDateTime::now_utc().unix_timestamp_nanos());
+        let slice_path = orphan_scratch_directory_path.join(slice_name);
+        std_fs::create_dir_all(&slice_path)?;
+        for orphan_entry_path in orphan_entry_paths {
+            if let Some(entry_name) = orphan_entry_path.file_name() {
+                std_fs::rename(&orphan_entry_path, slice_path.join(entry_name))?;
+            }
+        }
+    }
+    if !orphan_scratch_directory_path.exists() {
+        return Ok(cleanup_stats);
+    }
+    let mut slice_paths: Vec<PathBuf> = std_fs::read_dir(orphan_scratch_directory_path)?
+        .map(|entry_res| entry_res.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+    slice_paths.sort();
+    let num_slices_to_remove = slice_paths.len().saturating_sub(max_num_retained_slices);
+
+    for slice_path in &slice_paths[..num_slices_to_remove] {
+        let slice_num_bytes = num_bytes_in_path(slice_path)?;
+        if slice_path.is_dir() {
+            std_fs::remove_dir_all(slice_path)?;
+        } else {
+            std_fs::remove_file(slice_path)?;
+        }
+        cleanup_stats.num_removed_slices += 1;
+        cleanup_stats.num_reclaimed_bytes += slice_num_bytes;
+    }
+    INDEXER_METRICS
+        .orphan_scratch_dirs_removed_total
+        .inc_by(cleanup_stats.num_removed_slices as u64);
+    INDEXER_METRICS
+        .orphan_scratch_reclaimed_bytes_total
+        .inc_by(cleanup_stats.num_reclaimed_bytes);
+    Ok(cleanup_stats)
+}
+
+/// Returns the total size of the files located under `path`.
+fn num_bytes_in_path(path: &Path) -> io::Result<u64> {
+    let mut num_bytes = 0;
+    let mut pending_paths = vec![path.to_path_buf()];
+    while let Some(pending_path) = pending_paths.pop() {
+        let metadata = std_fs::symlink_metadata(&pending_path)?;
+        if metadata.is_dir() {
+            for entry_res in std_fs::read_dir(&pending_path)? {
+                pending_paths.push(entry_res?.path());
+            }
+        } else {
+            num_bytes += metadata.len();
+        }
+    }
+    Ok(num_bytes)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -114,7 +248,7 @@ mod tests {
     #[tokio::test]
     async fn test_indexing_directory() -> anyhow::Result<()> {
         let tempdir = tempfile::tempdir()?;
-        let indexing_directory = IndexingDirectory::create_in_dir(tempdir.path()).await?;
+        let indexing_directory = IndexingDirectory::create_in_dir(tempdir.path(), 0).await?;
         let indexing_directory_path = indexing_directory.path().to_path_buf();
         assert_eq!(indexing_directory_path, tempdir.path());
 
@@ -128,11 +262,18 @@ mod tests {
             indexing_directory.scratch_directory.path(),
             scratch_directory_path
         );
+
+        let quarantine_directory_path = indexing_directory_path.join("quarantine");
+        assert!(quarantine_directory_path.exists());
+        assert_eq!(
+            indexing_directory.quarantine_directory,
+            quarantine_directory_path
+        );
         {
             let scratch_file_path = scratch_directory_path.join("file");
             tokio::fs::File::create(&scratch_file_path).await?;
             assert!(scratch_file_path.exists());
-            let _indexing_directory = IndexingDirectory::create_in_dir(tempdir.path()).await?;
+            let _indexing_directory = IndexingDirectory::create_in_dir(tempdir.path(), 0).await?;
             assert!(!scratch_file_path.exists());
         }
         Ok(())
@@ -146,4 +287,48 @@ mod tests {
         assert!(!indexing_directory_path.exists());
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_indexing_directory_retains_last_orphan_scratch_dirs() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let scratch_directory_path = tempdir.path().join(SCRATCH);
+        let orphan_scratch_directory_path = tempdir.path().join(ORPHAN_SCRATCH);
+        for run in 0..3 {
+            let indexing_directory = IndexingDirectory::create_in_dir(tempdir.path(), 2).await?;
+            let workbench_path = scratch_directory_path.join(format!("workbench-{}", run));
+            tokio::fs::create_dir(&workbench_path).await?;
+            tokio::fs::write(workbench_path.join("split"), b"abcd").await?;
+            drop(indexing_directory);
+        }
+        let cleanup_stats =
+            clean_up_scratch_directory(&scratch_directory_path, &orphan_scratch_directory_path, 2)?;
+        assert_eq!(
+            cleanup_stats,
+            ScratchCleanupStats {
+                num_orphan_entries: 1,
+                num_removed_slices: 1,
+                num_reclaimed_bytes: 4,
+            }
+        );
+        assert_eq!(std::fs::read_dir(&scratch_directory_path)?.count(), 0);
+
+        let mut slice_paths: Vec<PathBuf> = std::fs::read_dir(&orphan_scratch_directory_path)?
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        slice_paths.sort();
+        assert_eq!(slice_paths.len(), 2);
+        assert!(slice_paths[0].join("workbench-1").join("split").exists());
+        assert!(slice_paths[1].join("workbench-2").join("split").exists());
+
+        let cleanup_stats =
+            clean_up_scratch_directory(&scratch_directory_path, &orphan_scratch_directory_path, 0)?;
+        assert_eq!(cleanup_stats.num_orphan_entries, 0);
+        assert_eq!(cleanup_stats.num_removed_slices, 2);
+        assert_eq!(cleanup_stats.num_reclaimed_bytes, 8);
+        assert_eq!(
+            std::fs::read_dir(&orphan_scratch_directory_path)?.count(),
+            0
+        );
+        Ok(())
+    }
 }
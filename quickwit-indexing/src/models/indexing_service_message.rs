@@ -33,6 +33,9 @@ pub struct SpawnPipeline {
     pub index_id: String,
     pub source_config: SourceConfig,
     pub pipeline_ord: usize,
+    /// Whether to spawn the merge sub-pipeline, see
+    /// [`IndexingPipelineParams::merge_pipeline_enabled`](crate::IndexingPipelineParams).
+    pub merge_pipeline_enabled: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -66,3 +69,79 @@ pub struct SpawnMergePipeline {
     pub index_id: String,
     pub merge_enabled: bool,
 }
+
+/// Merges the published splits of an index down to `target_num_splits` splits, regardless of the
+/// maturity rules of its merge policy. Replies the IDs of the pipelines of the node whose merge
+/// planner received the splits.
+#[derive(Clone, Debug)]
+pub struct ForceMergeIndex {
+    pub index_id: String,
+    pub target_num_splits: usize,
+}
+
+/// Rewrites the doc store of the published splits of an index with its current docstore
+/// compression settings, without reindexing the documents. Replies the IDs of the pipelines of
+/// the node whose merge planner received the splits.
+#[derive(Clone, Debug)]
+pub struct RecompressIndex {
+    pub index_id: String,
+}
+
+/// Lists the pipelines managed by the indexing service.
+#[derive(Debug)]
+pub struct ListPipelines;
+
+/// Lists the warnings emitted by the pipelines managed by the indexing service.
+#[derive(Debug)]
+pub struct ListPipelineWarnings;
+
+/// Returns the storage requests issued by the pipelines managed by the indexing service,
+/// aggregated per index.
+#[derive(Debug)]
+pub struct ObserveStorageOps;
+
+/// Returns the health of a source, aggregating its connectivity, the lag of its checkpoint, its
+/// throughput, and the last warning of the pipelines reading it on this node.
+#[derive(Debug)]
+pub struct ObserveSourceHealth {
+    pub index_id: String,
+    pub source_id: String,
+}
+
+/// Returns the topology of the current generation of the pipelines of a source running on this
+/// node, ordered by pipeline ordinal.
+#[derive(Debug)]
+pub struct ObservePipelineTopologies {
+    pub index_id: String,
+    pub source_id: String,
+}
+
+/// Returns the report of the field profile of the documents sampled by the pipelines of a source
+/// running on this node. The profile is empty if field profiling is disabled.
+#[derive(Debug)]
+pub struct ObserveFieldProfile {
+    pub index_id: String,
+    pub source_id: String,
+}
+
+/// Relocates a pipeline to another node.
+///
+/// The pipeline is drained: it stops reading from its source, then commits and publishes its
+/// workbench, handing off its checkpoint to the target node through the metastore.
+#[derive(Clone, Debug)]
+pub struct RelocatePipeline {
+    pub pipeline_id: IndexingPipelineId,
+    pub target_node_id: String,
+}
+
+/// Returns the state of the relocations requested on this node.
+#[derive(Debug)]
+pub struct ObserveRelocations;
+
+/// Spawns on this node a pipeline drained and relocated from another node. The pipeline resumes
+/// from the checkpoint published by the drained pipeline.
+#[derive(Clone, Debug)]
+pub struct AdoptPipeline {
+    /// ID of the drained pipeline on the node it was relocated from.
+    pub relocated_pipeline_id: IndexingPipelineId,
+}
@@ -19,7 +19,16 @@
 
 use std::sync::atomic::Ordering;
 
-use crate::actors::{IndexerCounters, PublisherCounters, UploaderCounters};
+use quickwit_metastore::checkpoint::SourceCheckpoint;
+use quickwit_storage::StorageOpsCounts;
+
+use crate::actors::{
+    IndexerCounters, MergeExecutorCounters, MergeProgress, PublisherCounters, UploaderCounters,
+    WorkbenchPreview,
+};
+use crate::models::{
+    FieldProfile, PipelineWarning, SourceThrottlingCounters, UploadCircuitBreakerTrip,
+};
 
 /// A Struct that holds all statistical data about indexing
 #[derive(Clone, Debug, Default)]
@@ -28,6 +37,12 @@ pub struct IndexingStatistics {
     pub num_docs: u64,
     /// Number of document parse error, or missing timestamps
     pub num_invalid_docs: u64,
+    /// Number of documents dropped by deduplication
+    pub num_duplicate_docs: u64,
+    /// Number of documents routed to the index of their tenant
+    pub num_routed_docs: u64,
+    /// Time spent indexing batches on the blocking runtime, in microseconds
+    pub cpu_time_micros: u64,
     /// Number of created split
     pub num_local_splits: u64,
     /// Number of staged splits
@@ -36,6 +51,8 @@ pub struct IndexingStatistics {
     pub num_uploaded_splits: u64,
     /// Number of published splits
     pub num_published_splits: u64,
+    /// Number of documents of the published splits
+    pub num_published_docs: u64,
     /// Size in byte of document processed
     pub total_bytes_processed: u64,
     /// Size in bytes of resulting split
@@ -44,6 +61,37 @@ pub struct IndexingStatistics {
     pub generation: usize,
     /// Number of successive pipeline spawn attempts.
     pub num_spawn_attempts: usize,
+    /// Number of times the non-critical actors were respawned without restarting the pipeline.
+    pub num_non_critical_respawns: usize,
+    /// Highest source positions read from the source and added to a workbench.
+    pub read_watermark: SourceCheckpoint,
+    /// Highest source positions committed by the indexer and sent to the packager.
+    pub indexed_watermark: SourceCheckpoint,
+    /// Highest source positions published.
+    ///
+    /// Comparing the three watermarks tells whether lag comes from reading,
+    /// indexing, or publishing.
+    pub published_watermark: SourceCheckpoint,
+    /// Checkpoint delta and partitions of the workbench of the indexer, if any.
+    ///
+    /// Tells what a commit that does not happen is holding.
+    pub workbench_preview: Option<WorkbenchPreview>,
+    /// Profile of the fields of the documents sampled by the indexer of the current generation,
+    /// when field profiling is enabled.
+    pub field_profile: Option<FieldProfile>,
+    /// Number of merges executed.
+    pub num_merges: u64,
+    /// Progress of the ongoing merge, if any.
+    pub merge_progress: Option<MergeProgress>,
+    /// Warnings emitted by the actors of the pipeline across all generations.
+    pub warnings: Vec<PipelineWarning>,
+    /// Storage requests issued by the pipeline across all generations.
+    pub storage_ops: StorageOpsCounts,
+    /// Failure that tripped the upload circuit breaker, if the pipeline is paused because storing
+    /// splits keeps failing.
+    pub upload_circuit_breaker_trip: Option<UploadCircuitBreakerTrip>,
+    /// Throttling of the source by its rate limiter across all generations.
+    pub source_throttling: SourceThrottlingCounters,
 }
 
 impl IndexingStatistics {
@@ -55,11 +103,34 @@ impl IndexingStatistics {
     ) -> Self {
         self.num_docs += indexer_counters.num_processed_docs();
         self.num_invalid_docs += indexer_counters.num_invalid_docs();
+        self.num_duplicate_docs += indexer_counters.num_duplicate_docs;
+        self.num_routed_docs += indexer_counters.num_routed_docs;
+        self.cpu_time_micros += indexer_counters.cpu_time_micros;
         self.num_local_splits += indexer_counters.num_splits_emitted;
         self.total_bytes_processed += indexer_counters.overall_num_bytes;
         self.num_staged_splits += uploader_counters.num_staged_splits.load(Ordering::SeqCst);
         self.num_uploaded_splits += uploader_counters.num_uploaded_splits.load(Ordering::SeqCst);
         self.num_published_splits += publisher_counters.num_published_splits;
+        self.num_published_docs += publisher_counters.num_published_docs;
+        // Watermarks from previous generations are kept until the current generation
+        // goes beyond them.
+        self.read_watermark
+            .advance_with_checkpoint(&indexer_counters.read_watermark);
+        self.indexed_watermark
+            .advance_with_checkpoint(&indexer_counters.indexed_watermark);
+        self.published_watermark
+            .advance_with_checkpoint(&publisher_counters.published_watermark);
+        self.workbench_preview = indexer_counters.workbench_preview.clone();
+        self.field_profile = indexer_counters.field_profile.clone();
+        self
+    }
+
+    pub fn add_merge_executor_counters(
+        mut self,
+        merge_executor_counters: &MergeExecutorCounters,
+    ) -> Self {
+        self.num_merges += merge_executor_counters.num_merges.load(Ordering::SeqCst);
+        self.merge_progress = merge_executor_counters.merge_progress();
         self
     }
 
@@ -68,8 +139,36 @@ impl IndexingStatistics {
         self
     }
 
+    pub fn set_num_non_critical_respawns(mut self, num_non_critical_respawns: usize) -> Self {
+        self.num_non_critical_respawns = num_non_critical_respawns;
+        self
+    }
+
     pub fn set_generation(mut self, generation: usize) -> Self {
         self.generation = generation;
         self
     }
+
+    pub fn set_warnings(mut self, warnings: Vec<PipelineWarning>) -> Self {
+        self.warnings = warnings;
+        self
+    }
+
+    pub fn set_storage_ops(mut self, storage_ops: StorageOpsCounts) -> Self {
+        self.storage_ops = storage_ops;
+        self
+    }
+
+    pub fn set_upload_circuit_breaker_trip(
+        mut self,
+        upload_circuit_breaker_trip: Option<UploadCircuitBreakerTrip>,
+    ) -> Self {
+        self.upload_circuit_breaker_trip = upload_circuit_breaker_trip;
+        self
+    }
+
+    pub fn set_source_throttling(mut self, source_throttling: SourceThrottlingCounters) -> Self {
+        self.source_throttling = source_throttling;
+        self
+    }
 }
@@ -23,3 +23,36 @@ use quickwit_metastore::SplitMetadata;
 pub struct NewSplits {
     pub new_splits: Vec<SplitMetadata>,
 }
+
+/// Published splits that have pending delete tasks. The merge planner schedules a delete apply
+/// operation for each of them, unless the split is already part of an ongoing merge.
+#[derive(Clone, Debug)]
+pub struct ApplyDeletes {
+    pub splits: Vec<SplitMetadata>,
+}
+
+/// Published splits to merge down to `target_num_splits` splits, regardless of the maturity
+/// rules of the merge policy. The splits already part of an ongoing merge are left out.
+#[derive(Clone, Debug)]
+pub struct ForceMerge {
+    pub splits: Vec<SplitMetadata>,
+    pub target_num_splits: usize,
+}
+
+/// Published splits whose doc store should be rewritten with the docstore compression settings
+/// of the index. The merge planner schedules a recompress operation for each of them, unless the
+/// split is already part of an ongoing merge.
+#[derive(Clone, Debug)]
+pub struct RecompressSplits {
+    pub splits: Vec<SplitMetadata>,
+}
+
+/// Merge operation dropped before its execution because some of its splits are no longer
+/// published, for instance because they were deleted by the retention policy or replaced in the
+/// meantime. The merge planner forgets the operation and plans the merges of the splits of the
+/// operation that are still published again.
+#[derive(Clone, Debug)]
+pub struct AbortedMerge {
+    pub split_ids: Vec<String>,
+    pub published_splits: Vec<SplitMetadata>,
+}
@@ -20,6 +20,7 @@
 use tantivy::Directory;
 
 use crate::merge_policy::MergeOperation;
+use crate::merge_scheduler::MergePermit;
 use crate::models::ScratchDirectory;
 
 #[derive(Debug)]
@@ -29,4 +30,6 @@ pub struct MergeScratch {
     pub merge_scratch_directory: ScratchDirectory,
     pub downloaded_splits_directory: ScratchDirectory,
     pub tantivy_dirs: Vec<Box<dyn Directory>>,
+    /// Permit of the merge scheduler, released once the merge is executed.
+    pub merge_permit: MergePermit,
 }
@@ -0,0 +1,323 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+use quickwit_common::metrics::IntCounter;
+use quickwit_config::MetastorePayloadLimits;
+use quickwit_metastore::checkpoint::IndexCheckpointDelta;
+use quickwit_metastore::SplitMetadata;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{IndexingPipelineId, PipelineWarningKind, PipelineWarningSink};
+use crate::metrics::INDEXER_METRICS;
+
+/// Payload sent to the metastore by the indexing pipelines.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetastorePayload {
+    /// Metadata of a split, sent when the split is staged.
+    SplitMetadata,
+    /// Checkpoint delta published along with the splits.
+    CheckpointDelta,
+    /// Publish request, made of the IDs of the published and replaced splits and of the
+    /// checkpoint delta.
+    PublishRequest,
+}
+
+impl MetastorePayload {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetastorePayload::SplitMetadata => "split_metadata",
+            MetastorePayload::CheckpointDelta => "checkpoint_delta",
+            MetastorePayload::PublishRequest => "publish_request",
+        }
+    }
+}
+
+impl fmt::Display for MetastorePayload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Error returned when a payload exceeds the maximum size allowed by the metastore payload limits
+/// of the index.
+#[derive(Clone, Debug, Eq, PartialEq, Error)]
+#[error(
+    "The {payload} payload of {num_bytes} bytes exceeds the maximum size of {max_num_bytes} bytes \
+     of index `{index_id}`."
+)]
+pub struct MetastorePayloadTooLarge {
+    pub index_id: String,
+    pub payload: MetastorePayload,
+    pub num_bytes: u64,
+    pub max_num_bytes: u64,
+}
+
+#[derive(Clone)]
+struct PayloadCounters {
+    warned: IntCounter,
+    rejected: IntCounter,
+}
+
+impl PayloadCounters {
+    fn new(pipeline_id: &IndexingPipelineId, payload: MetastorePayload) -> Self {
+        let counter = |outcome: &str| {
+            INDEXER_METRICS
+                .oversized_metastore_payloads_total
+                .with_label_values(&[
+                    pipeline_id.index_id.as_str(),
+                    pipeline_id.source_id.as_str(),
+                    payload.as_str(),
+                    outcome,
+                ])
+        };
+        PayloadCounters {
+            warned: counter("warned"),
+            rejected: counter("rejected"),
+        }
+    }
+}
+
+/// Measures the serialized size of the payloads sent to the metastore by the actors of a
+/// pipeline. Payloads larger than their warning size are reported as pipeline warnings, and
+/// payloads larger than their maximum size are rejected, so that unbounded tag sets or huge
+/// checkpoints are caught before they degrade the metastore.
+#[derive(Clone)]
+pub struct MetastorePayloadGuard {
+    index_id: String,
+    limits: MetastorePayloadLimits,
+    warning_sink: PipelineWarningSink,
+    split_metadata_counters: PayloadCounters,
+    checkpoint_delta_counters: PayloadCounters,
+    publish_request_counters: PayloadCounters,
+}
+
+impl MetastorePayloadGuard {
+    pub fn new(
+        pipeline_id: &IndexingPipelineId,
+        limits: MetastorePayloadLimits,
+        warning_sink: PipelineWarningSink,
+    ) -> Self {
+        MetastorePayloadGuard {
+            index_id: pipeline_id.index_id.clone(),
+            limits,
+            warning_sink,
+            split_metadata_counters: PayloadCounters::new(
+                pipeline_id,
+                MetastorePayload::SplitMetadata,
+            ),
+            checkpoint_delta_counters: PayloadCounters::new(
+                pipeline_id,
+                MetastorePayload::CheckpointDelta,
+            ),
+            publish_request_counters: PayloadCounters::new(
+                pipeline_id,
+                MetastorePayload::PublishRequest,
+            ),
+        }
+    }
+
+    pub fn check_split_metadata(
+        &self,
+        split_metadata: &SplitMetadata,
+        actor_name: &str,
+    ) -> Result<(), MetastorePayloadTooLarge> {
+        let num_bytes = serialized_num_bytes(split_metadata);
+        self.check(MetastorePayload::SplitMetadata, num_bytes, actor_name)
+    }
+
+    pub fn check_checkpoint_delta(
+        &self,
+        checkpoint_delta: &IndexCheckpointDelta,
+        actor_name: &str,
+    ) -> Result<(), MetastorePayloadTooLarge> {
+        let num_bytes = serialized_num_bytes(checkpoint_delta);
+        self.check(MetastorePayload::CheckpointDelta, num_bytes, actor_name)
+    }
+
+    pub fn check_publish_request(
+        &self,
+        split_ids: &[&str],
+        replaced_split_ids: &[&str],
+        checkpoint_delta_opt: Option<&IndexCheckpointDelta>,
+        actor_name: &str,
+    ) -> Result<(), MetastorePayloadTooLarge> {
+        let publish_request = serde_json::json!({
+            "index_id": self.index_id,
+            "split_ids": split_ids,
+            "replaced_split_ids": replaced_split_ids,
+            "checkpoint_delta": checkpoint_delta_opt,
+        });
+        let num_bytes = serialized_num_bytes(&publish_request);
+        self.check(MetastorePayload::PublishRequest, num_bytes, actor_name)
+    }
+
+    fn check(
+        &self,
+        payload: MetastorePayload,
+        num_bytes: u64,
+        actor_name: &str,
+    ) -> Result<(), MetastorePayloadTooLarge> {
+        let (warn_size, max_size, counters) = match payload {
+            MetastorePayload::SplitMetadata => (
+                self.limits.split_metadata_warn_size,
+                self.limits.split_metadata_max_size,
+                &self.split_metadata_counters,
+            ),
+            MetastorePayload::CheckpointDelta => (
+                self.limits.checkpoint_delta_warn_size,
+                self.limits.checkpoint_delta_max_size,
+                &self.checkpoint_delta_counters,
+            ),
+            MetastorePayload::PublishRequest => (
+                self.limits.publish_request_warn_size,
+                self.limits.publish_request_max_size,
+                &self.publish_request_counters,
+            ),
+        };
+        let max_num_bytes = max_size.get_bytes() as u64;
+        if num_bytes > max_num_bytes {
+            counters.rejected.inc();
+            let error = MetastorePayloadTooLarge {
+                index_id: self.index_id.clone(),
+                payload,
+                num_bytes,
+                max_num_bytes,
+            };
+            self.warning_sink.warn(
+                PipelineWarningKind::LargeMetastorePayload,
+                actor_name,
+                error.to_string(),
+            );
+            return Err(error);
+        }
+        let warn_num_bytes = warn_size.get_bytes() as u64;
+        if num_bytes > warn_num_bytes {
+            counters.warned.inc();
+            self.warning_sink.warn(
+                PipelineWarningKind::LargeMetastorePayload,
+                actor_name,
+                format!(
+                    "The {} payload of {} bytes exceeds the warning size of {} bytes of index \
+                     `{}`.",
+                    payload, num_bytes, warn_num_bytes, self.index_id
+                ),
+            );
+        }
+        Ok(())
+    }
+}
+
+fn serialized_num_bytes<T: Serialize>(value: &T) -> u64 {
+    serde_json::to_vec(value)
+        .map(|bytes| bytes.len() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use byte_unit::Byte;
+
+    use super::*;
+
+    #[test]
+    fn test_metastore_payload_guard() {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let split_metadata = SplitMetadata::for_test("test-split".to_string());
+        let num_bytes = serialized_num_bytes(&split_metadata);
+        let warning_sink = PipelineWarningSink::default();
+
+        let limits = MetastorePayloadLimits::default();
+        let guard = MetastorePayloadGuard::new(&pipeline_id, limits.clone(), warning_sink.clone());
+        guard
+            .check_split_metadata(&split_metadata, "Uploader")
+            .unwrap();
+        assert!(warning_sink.warnings().is_empty());
+
+        let limits = MetastorePayloadLimits {
+            split_metadata_warn_size: Byte::from_bytes(num_bytes as u128 - 1),
+            ..Default::default()
+        };
+        let guard = MetastorePayloadGuard::new(&pipeline_id, limits, warning_sink.clone());
+        guard
+            .check_split_metadata(&split_metadata, "Uploader")
+            .unwrap();
+        let warnings = warning_sink.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, PipelineWarningKind::LargeMetastorePayload);
+        assert_eq!(warnings[0].count, 1);
+
+        let limits = MetastorePayloadLimits {
+            split_metadata_warn_size: Byte::from_bytes(1),
+            split_metadata_max_size: Byte::from_bytes(num_bytes as u128 - 1),
+            ..Default::default()
+        };
+        let guard = MetastorePayloadGuard::new(&pipeline_id, limits, warning_sink.clone());
+        let error = guard
+            .check_split_metadata(&split_metadata, "Uploader")
+            .unwrap_err();
+        assert_eq!(
+            error,
+            MetastorePayloadTooLarge {
+                index_id: "test-index".to_string(),
+                payload: MetastorePayload::SplitMetadata,
+                num_bytes,
+                max_num_bytes: num_bytes - 1,
+            }
+        );
+        assert_eq!(warning_sink.warnings()[0].count, 2);
+    }
+
+    #[test]
+    fn test_metastore_payload_guard_publish_request() {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let limits = MetastorePayloadLimits {
+            publish_request_warn_size: Byte::from_bytes(100),
+            publish_request_max_size: Byte::from_bytes(100),
+            ..Default::default()
+        };
+        let guard =
+            MetastorePayloadGuard::new(&pipeline_id, limits, PipelineWarningSink::default());
+        guard
+            .check_publish_request(&["split-1"], &[], None, "Publisher")
+            .unwrap();
+        let split_ids: Vec<String> = (0..10)
+            .map(|split_ord| format!("split-{}", split_ord))
+            .collect();
+        let split_ids_ref: Vec<&str> = split_ids.iter().map(String::as_str).collect();
+        let error = guard
+            .check_publish_request(&split_ids_ref, &[], None, "Publisher")
+            .unwrap_err();
+        assert_eq!(error.payload, MetastorePayload::PublishRequest);
+        assert_eq!(error.max_num_bytes, 100);
+    }
+}
@@ -17,6 +17,12 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+mod data_loss_detector;
+mod dead_letter_queue;
+mod dedup_window;
+mod doc_transformer;
+mod field_profile;
+mod index_feature_flags;
 mod indexed_split;
 mod indexing_directory;
 mod indexing_pipeline_id;
@@ -24,29 +30,75 @@ mod indexing_service_message;
 mod indexing_statistics;
 mod merge_planner_message;
 mod merge_scratch;
+mod metastore_payload_guard;
 mod packaged_split;
+mod pipeline_relocation;
+mod pipeline_topology;
+mod pipeline_warning;
 mod publish_lock;
+mod publish_notifier;
 mod publisher_message;
 mod raw_doc_batch;
 mod scratch_directory;
+mod source_health;
+mod source_rate_limiter;
 mod split_attrs;
+mod tenant_router;
+mod upload_circuit_breaker;
 
-pub use indexed_split::{IndexedSplit, IndexedSplitBatch};
+pub(crate) use data_loss_detector::{DataLossDetector, SourceProgressSample};
+pub use data_loss_detector::{DataLossSuspicion, DataLossSuspicionKind, PartitionBounds};
+pub use dead_letter_queue::{DeadLetterQueue, DeadLetterQueueCounters, DeadLetterSink};
+pub use dedup_window::DedupWindow;
+pub use doc_transformer::{DocTransformError, DocTransformer};
+pub use field_profile::{FieldProfile, FieldProfileEntry, FieldProfileReport};
+pub use index_feature_flags::IndexFeatureFlags;
+pub use indexed_split::{IndexedSplit, IndexedSplitBatch, ShardSplitBatch};
 pub use indexing_directory::{IndexingDirectory, CACHE};
 pub use indexing_pipeline_id::IndexingPipelineId;
 pub use indexing_service_message::{
-    DetachPipeline, ObservePipeline, ShutdownPipeline, ShutdownPipelines, SpawnMergePipeline,
-    SpawnPipeline, SpawnPipelines,
+    AdoptPipeline, DetachPipeline, ForceMergeIndex, ListPipelineWarnings, ListPipelines,
+    ObserveFieldProfile, ObservePipeline, ObservePipelineTopologies, ObserveRelocations,
+    ObserveSourceHealth, ObserveStorageOps, RecompressIndex, RelocatePipeline, ShutdownPipeline,
+    ShutdownPipelines, SpawnMergePipeline, SpawnPipeline, SpawnPipelines,
 };
 pub use indexing_statistics::IndexingStatistics;
-pub use merge_planner_message::NewSplits;
+pub use merge_planner_message::{
+    AbortedMerge, ApplyDeletes, ForceMerge, NewSplits, RecompressSplits,
+};
 pub use merge_scratch::MergeScratch;
-pub use packaged_split::{PackagedSplit, PackagedSplitBatch};
+pub use metastore_payload_guard::{
+    MetastorePayload, MetastorePayloadGuard, MetastorePayloadTooLarge,
+};
+pub use packaged_split::{
+    PackagedSplit, PackagedSplitBatch, PackagedSplitBatchStream, PackagedSplitEvent,
+};
+pub use pipeline_relocation::{PipelineRelocation, RelocationState};
+pub use pipeline_topology::{
+    ActorEdge, ActorHealth, ActorTopology, KillSwitchGroup, KillSwitchTopology, PipelineTopology,
+};
+pub use pipeline_warning::{
+    PipelineWarning, PipelineWarningKind, PipelineWarningSink, PipelineWarnings,
+};
 pub use publish_lock::{NewPublishLock, PublishLock};
+pub use publish_notifier::{
+    PendingPublishNotification, PublishNotification, PublishNotificationSink, PublishNotifier,
+    PublishedSplit, PUBLISH_NOTIFICATIONS,
+};
 pub use publisher_message::SplitUpdate;
 pub use raw_doc_batch::RawDocBatch;
 pub use scratch_directory::ScratchDirectory;
+pub(crate) use source_health::ThroughputSample;
+pub use source_health::{
+    partition_lags, PartitionLag, SourceConnectivity, SourceHealth, SourceHealthStatus,
+    SourceThroughput,
+};
+pub use source_rate_limiter::{SourceRateLimiter, SourceThrottlingCounters};
 pub use split_attrs::SplitAttrs;
+pub use tenant_router::{TenantRouter, TenantRouterCounters};
+pub use upload_circuit_breaker::{
+    UploadCircuitBreaker, UploadCircuitBreakerTrip, UploadFailureReason,
+};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Observe;
@@ -17,11 +17,14 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::{BTreeSet, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fmt;
+use std::path::PathBuf;
 use std::time::Instant;
 
 use quickwit_metastore::checkpoint::IndexCheckpointDelta;
+use quickwit_metastore::SplitFieldStats;
+use tokio::sync::mpsc::UnboundedReceiver;
 
 use crate::models::{PublishLock, ScratchDirectory, SplitAttrs};
 
@@ -29,8 +32,13 @@ pub struct PackagedSplit {
     pub split_attrs: SplitAttrs,
     pub split_scratch_directory: ScratchDirectory,
     pub tags: BTreeSet<String>,
+    /// Statistics of the numeric fast fields of the split, keyed by field name.
+    pub field_stats: BTreeMap<String, SplitFieldStats>,
     pub split_files: Vec<std::path::PathBuf>,
     pub hotcache_bytes: Vec<u8>,
+    /// Random sample of the documents of the split, serialized as NDJSON, exported by the
+    /// uploader when split sampling is enabled.
+    pub sample_docs_opt: Option<Vec<u8>>,
 }
 
 impl PackagedSplit {
@@ -45,6 +53,7 @@ impl fmt::Debug for PackagedSplit {
             .field("split_attrs", &self.split_attrs)
             .field("split_scratch_directory", &self.split_scratch_directory)
             .field("tags", &self.tags)
+            .field("field_stats", &self.field_stats)
             .field("split_files", &self.split_files)
             .finish()
     }
@@ -54,6 +63,7 @@ impl fmt::Debug for PackagedSplit {
 pub struct PackagedSplitBatch {
     pub splits: Vec<PackagedSplit>,
     pub checkpoint_delta_opt: Option<IndexCheckpointDelta>,
+    pub publish_with_next_delta: bool,
     pub publish_lock: PublishLock,
     pub date_of_birth: Instant,
 }
@@ -82,6 +92,7 @@ impl PackagedSplitBatch {
         Self {
             splits,
             checkpoint_delta_opt,
+            publish_with_next_delta: false,
             publish_lock,
             date_of_birth,
         }
@@ -101,3 +112,35 @@ impl PackagedSplitBatch {
             .collect::<Vec<_>>()
     }
 }
+
+/// Event streamed by the packager to the uploader while it packages the splits of a
+/// [`PackagedSplitBatchStream`].
+#[derive(Debug)]
+pub enum PackagedSplitEvent {
+    /// The files of the split will not change anymore: the uploader can start uploading them
+    /// while the packager computes the tags and the hotcache of the split.
+    SplitFiles {
+        split_id: String,
+        split_files: Vec<PathBuf>,
+    },
+    /// The split is packaged. Its files are the ones of the preceding `SplitFiles` event, and its
+    /// hotcache completes the upload.
+    Packaged(PackagedSplit),
+}
+
+/// Streaming counterpart of [`PackagedSplitBatch`].
+///
+/// The packager sends it to the uploader as soon as the files of the first split of the batch
+/// are final, and then streams a `SplitFiles` event followed by a `Packaged` event for each
+/// split of the batch. The batch is complete once a `Packaged` event has been received for every
+/// split. If the packager fails, the stream is closed early and the batch is never published.
+#[derive(Debug)]
+pub struct PackagedSplitBatchStream {
+    pub index_id: String,
+    pub split_ids: Vec<String>,
+    pub split_events_rx: UnboundedReceiver<PackagedSplitEvent>,
+    pub checkpoint_delta_opt: Option<IndexCheckpointDelta>,
+    pub publish_with_next_delta: bool,
+    pub publish_lock: PublishLock,
+    pub date_of_birth: Instant,
+}
@@ -0,0 +1,46 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use quickwit_metastore::checkpoint::SourceCheckpoint;
+use serde::{Deserialize, Serialize};
+
+use super::IndexingPipelineId;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelocationState {
+    /// The pipeline stopped reading from its source and is committing its workbench.
+    Draining,
+    /// The pipeline published its workbench and exited. The target node can resume it.
+    Drained,
+    /// The pipeline failed while draining. Its last published checkpoint is still valid.
+    Failed,
+}
+
+/// Relocation of a pipeline from this node to another one.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PipelineRelocation {
+    /// ID of the relocated pipeline on this node.
+    pub pipeline_id: IndexingPipelineId,
+    pub target_node_id: String,
+    pub state: RelocationState,
+    /// Positions published by the pipeline. Once drained, the pipeline resumes from these
+    /// positions on the target node.
+    pub published_checkpoint: SourceCheckpoint,
+}
@@ -0,0 +1,268 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt::Write;
+
+use quickwit_actors::{Actor, ActorHandle, ActorState, QueueCapacity};
+use serde::Serialize;
+
+use super::IndexingPipelineId;
+
+/// Group of actors of a pipeline sharing a kill switch. The groups off the critical path share
+/// the kill switch of the critical actors unless their failures are isolated, in which case each
+/// of them is respawned on its own.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KillSwitchGroup {
+    /// Actors of the indexing path, from the source to the publisher.
+    Critical,
+    /// Garbage collector of the staged and deleted splits.
+    GarbageCollector,
+    /// Delete executor applying the delete tasks and purging the expired documents.
+    DeleteExecutor,
+    /// Actors of the merge sub-pipeline, from the merge planner to the merge publisher.
+    Merge,
+}
+
+impl KillSwitchGroup {
+    fn as_str(&self) -> &'static str {
+        match self {
+            KillSwitchGroup::Critical => "critical",
+            KillSwitchGroup::GarbageCollector => "garbage_collector",
+            KillSwitchGroup::DeleteExecutor => "delete_executor",
+            KillSwitchGroup::Merge => "merge",
+        }
+    }
+}
+
+/// Health of an actor, read from its state. Unlike the supervisor healthcheck, it does not
+/// consume the progress of the actor.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActorHealth {
+    Running,
+    Paused,
+    Success,
+    Failure,
+}
+
+impl From<ActorState> for ActorHealth {
+    fn from(actor_state: ActorState) -> Self {
+        match actor_state {
+            ActorState::Processing | ActorState::Idle => ActorHealth::Running,
+            ActorState::Paused => ActorHealth::Paused,
+            ActorState::Success => ActorHealth::Success,
+            ActorState::Failure => ActorHealth::Failure,
+        }
+    }
+}
+
+/// Actor of a pipeline along with its mailbox.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct ActorTopology {
+    pub actor_id: String,
+    /// Role of the actor in the pipeline, for instance `indexer` or `merge_uploader`.
+    pub role: String,
+    pub kill_switch_group: KillSwitchGroup,
+    pub health: ActorHealth,
+    /// Capacity of the queue of the mailbox of the actor, `None` if it is unbounded.
+    pub queue_capacity: Option<usize>,
+    pub num_pending_messages: usize,
+}
+
+impl ActorTopology {
+    pub fn new<A: Actor>(
+        role: &str,
+        kill_switch_group: KillSwitchGroup,
+        actor_handle: &ActorHandle<A>,
+    ) -> Self {
+        let mailbox = actor_handle.mailbox();
+        let queue_capacity = match mailbox.queue_capacity() {
+            QueueCapacity::Bounded(capacity) => Some(capacity),
+            QueueCapacity::Unbounded => None,
+        };
+        ActorTopology {
+            actor_id: mailbox.actor_instance_id().to_string(),
+            role: role.to_string(),
+            kill_switch_group,
+            health: actor_handle.state().into(),
+            queue_capacity,
+            num_pending_messages: mailbox.num_pending_messages(),
+        }
+    }
+}
+
+/// Mailbox of the actor `to` held by the actor `from`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct ActorEdge {
+    pub from: String,
+    pub to: String,
+}
+
+/// Kill switch shared by a group of actors.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct KillSwitchTopology {
+    pub group: KillSwitchGroup,
+    pub is_alive: bool,
+}
+
+/// Actors spawned by the current generation of a pipeline, the mailboxes connecting them, and
+/// their kill switches.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct PipelineTopology {
+    pub pipeline_id: IndexingPipelineId,
+    pub generation: usize,
+    pub isolate_non_critical_failures: bool,
+    pub kill_switches: Vec<KillSwitchTopology>,
+    pub actors: Vec<ActorTopology>,
+    pub edges: Vec<ActorEdge>,
+}
+
+impl PipelineTopology {
+    /// Renders the topology in the DOT language of Graphviz. The actors of each kill switch group
+    /// are clustered together.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        let graph_name = format!(
+            "{}/{}/{}",
+            self.pipeline_id.index_id, self.pipeline_id.source_id, self.pipeline_id.pipeline_ord
+        );
+        let _ = writeln!(dot, "digraph {} {{", quote(&graph_name));
+        let _ = writeln!(
+            dot,
+            "  label={};",
+            quote(&format!("{} (generation {})", graph_name, self.generation))
+        );
+        for kill_switch in &self.kill_switches {
+            let _ = writeln!(dot, "  subgraph cluster_{} {{", kill_switch.group.as_str());
+            let kill_switch_state = if kill_switch.is_alive {
+                "alive"
+            } else {
+                "dead"
+            };
+            let _ = writeln!(
+                dot,
+                "    label={};",
+                quote(&format!(
+                    "{} ({})",
+                    kill_switch.group.as_str(),
+                    kill_switch_state
+                ))
+            );
+            for actor in &self.actors {
+                if actor.kill_switch_group != kill_switch.group {
+                    continue;
+                }
+                let queue_capacity = actor
+                    .queue_capacity
+                    .map(|capacity| capacity.to_string())
+                    .unwrap_or_else(|| "unbounded".to_string());
+                let label = format!(
+                    "{}\\n{}\\n{:?}, {}/{} messages",
+                    actor.role,
+                    actor.actor_id,
+                    actor.health,
+                    actor.num_pending_messages,
+                    queue_capacity
+                );
+                let _ = writeln!(
+                    dot,
+                    "    {} [label={}];",
+                    quote(&actor.actor_id),
+                    quote(&label)
+                );
+            }
+            let _ = writeln!(dot, "  }}");
+        }
+        for edge in &self.edges {
+            let _ = writeln!(dot, "  {} -> {};", quote(&edge.from), quote(&edge.to));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn quote(id: &str) -> String {
+    format!("\"{}\"", id.replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline_topology_to_dot() {
+        let pipeline_topology = PipelineTopology {
+            pipeline_id: IndexingPipelineId {
+                index_id: "test-index".to_string(),
+                source_id: "test-source".to_string(),
+                node_id: "test-node".to_string(),
+                pipeline_ord: 0,
+            },
+            generation: 2,
+            isolate_non_critical_failures: true,
+            kill_switches: vec![
+                KillSwitchTopology {
+                    group: KillSwitchGroup::Critical,
+                    is_alive: true,
+                },
+                KillSwitchTopology {
+                    group: KillSwitchGroup::GarbageCollector,
+                    is_alive: false,
+                },
+            ],
+            actors: vec![
+                ActorTopology {
+                    actor_id: "Indexer-1".to_string(),
+                    role: "indexer".to_string(),
+                    kill_switch_group: KillSwitchGroup::Critical,
+                    health: ActorHealth::Running,
+                    queue_capacity: Some(10),
+                    num_pending_messages: 3,
+                },
+                ActorTopology {
+                    actor_id: "GarbageCollector-1".to_string(),
+                    role: "garbage_collector".to_string(),
+                    kill_switch_group: KillSwitchGroup::GarbageCollector,
+                    health: ActorHealth::Failure,
+                    queue_capacity: None,
+                    num_pending_messages: 0,
+                },
+            ],
+            edges: vec![ActorEdge {
+                from: "Indexer-1".to_string(),
+                to: "GarbageCollector-1".to_string(),
+            }],
+        };
+        let expected_dot = r#"digraph "test-index/test-source/0" {
+  label="test-index/test-source/0 (generation 2)";
+  subgraph cluster_critical {
+    label="critical (alive)";
+    "Indexer-1" [label="indexer\nIndexer-1\nRunning, 3/10 messages"];
+  }
+  subgraph cluster_garbage_collector {
+    label="garbage_collector (dead)";
+    "GarbageCollector-1" [label="garbage_collector\nGarbageCollector-1\nFailure, 0/unbounded messages"];
+  }
+  "Indexer-1" -> "GarbageCollector-1";
+}
+"#;
+        assert_eq!(pipeline_topology.to_dot(), expected_dot);
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::warn;
+
+use super::IndexingPipelineId;
+
+/// Minimum delay between two logs of the same warning kind.
+const WARNING_LOG_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineWarningKind {
+    /// A large share of the documents of a commit were missing the timestamp field.
+    MissingTimestamp,
+    /// A large share of the documents of a commit could not be parsed.
+    InvalidDocs,
+    /// Storing splits required retries.
+    UploadRetries,
+    /// Storing splits kept failing for a reason that looks permanent, and the pipeline was
+    /// paused.
+    UploadCircuitBreakerTripped,
+    /// A merge was rejected because its scratch space does not fit on the scratch disk.
+    InsufficientMergeScratchSpace,
+    /// Writing documents failing to parse to the dead-letter queue failed, and the documents
+    /// were dropped.
+    DeadLetterQueueFailure,
+    /// A source could not read a file and quarantined it.
+    QuarantinedSourceFile,
+    /// A payload sent to the metastore exceeded its warning size or was rejected because it
+    /// exceeded its maximum size.
+    LargeMetastorePayload,
+}
+
+/// Warning emitted by an actor of an indexing pipeline.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PipelineWarning {
+    pub kind: PipelineWarningKind,
+    /// Name of the actor that emitted the warning.
+    pub actor_name: String,
+    /// Message of the last reported occurrence of the warning.
+    pub message: String,
+    /// Number of times the warning was emitted.
+    pub count: u64,
+    /// Unix timestamps of the first and last occurrences of the warning.
+    pub first_timestamp: i64,
+    pub last_timestamp: i64,
+}
+
+/// Warnings of the pipeline identified by `pipeline_id`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PipelineWarnings {
+    pub pipeline_id: IndexingPipelineId,
+    pub warnings: Vec<PipelineWarning>,
+}
+
+struct WarningEntry {
+    warning: PipelineWarning,
+    last_logged_at: Instant,
+}
+
+/// Shared sink in which the actors of a pipeline record warnings meant for the operators.
+///
+/// Warnings are accumulated per kind: emitting the same kind of warning again bumps its count
+/// instead of adding an entry, and logs it at most once every minute.
+#[derive(Clone, Default)]
+pub struct PipelineWarningSink {
+    entries: Arc<Mutex<BTreeMap<PipelineWarningKind, WarningEntry>>>,
+}
+
+impl PipelineWarningSink {
+    pub fn warn(&self, kind: PipelineWarningKind, actor_name: &str, message: String) {
+        let now = Instant::now();
+        let now_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+        let mut entries = self.entries.lock().unwrap();
+        let should_log = match entries.get_mut(&kind) {
+            Some(entry) => {
+                entry.warning.actor_name = actor_name.to_string();
+                entry.warning.count += 1;
+                entry.warning.last_timestamp = now_timestamp;
+                let should_log = now.duration_since(entry.last_logged_at) >= WARNING_LOG_INTERVAL;
+                if should_log {
+                    entry.last_logged_at = now;
+                }
+                entry.warning.message = message.clone();
+                should_log
+            }
+            None => {
+                let warning = PipelineWarning {
+                    kind,
+                    actor_name: actor_name.to_string(),
+                    message: message.clone(),
+                    count: 1,
+                    first_timestamp: now_timestamp,
+                    last_timestamp: now_timestamp,
+                };
+                entries.insert(
+                    kind,
+                    WarningEntry {
+                        warning,
+                        last_logged_at: now,
+                    },
+                );
+                true
+            }
+        };
+        if should_log {
+            warn!(kind=?kind, actor=actor_name, "{}", message);
+        }
+    }
+
+    /// Returns the warnings recorded so far, sorted by kind.
+    pub fn warnings(&self) -> Vec<PipelineWarning> {
+        self.entries
+            .lock()
+            .unwrap()
+            .values()
+            .map(|entry| entry.warning.clone())
+            .collect()
+    }
+}
+
+impl std::fmt::Debug for PipelineWarningSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PipelineWarningSink")
+            .field("warnings", &self.warnings())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline_warning_sink() {
+        let warning_sink = PipelineWarningSink::default();
+        assert!(warning_sink.warnings().is_empty());
+
+        warning_sink.warn(
+            PipelineWarningKind::UploadRetries,
+            "Uploader",
+            "Retried to store split `split-1`.".to_string(),
+        );
+        warning_sink.warn(
+            PipelineWarningKind::MissingTimestamp,
+            "Indexer",
+            "Timestamp field missing in 30% of the docs.".to_string(),
+        );
+        warning_sink.clone().warn(
+            PipelineWarningKind::UploadRetries,
+            "Uploader",
+            "Retried to store split `split-2`.".to_string(),
+        );
+        let warnings = warning_sink.warnings();
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].kind, PipelineWarningKind::MissingTimestamp);
+        assert_eq!(warnings[0].count, 1);
+        assert_eq!(warnings[1].kind, PipelineWarningKind::UploadRetries);
+        assert_eq!(warnings[1].count, 2);
+        assert_eq!(warnings[1].message, "Retried to store split `split-2`.");
+        assert!(warnings[1].first_timestamp <= warnings[1].last_timestamp);
+    }
+}
@@ -0,0 +1,678 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use quickwit_config::PublishNotificationSettings;
+use quickwit_metastore::{Metastore, SplitMetadata, SplitState};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tokio::fs;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use ulid::Ulid;
+
+use super::{IndexingDirectory, IndexingPipelineId};
+use crate::metrics::INDEXER_METRICS;
+
+/// Directory of the indexing directory holding the outboxes of the publish notifiers, one per
+/// pipeline ordinal.
+pub const PUBLISH_NOTIFICATIONS: &str = "publish-notifications";
+
+/// Extension of the notifications recorded before their splits are published.
+const PENDING_EXTENSION: &str = "pending";
+
+/// Extension of the notifications whose splits are published, waiting to be delivered.
+const READY_EXTENSION: &str = "json";
+
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Split described in a [`PublishNotification`].
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PublishedSplit {
+    pub split_id: String,
+    pub num_docs: usize,
+    pub time_range: Option<RangeInclusive<i64>>,
+}
+
+/// Notification of the splits published by a pipeline, sent to the downstream consumers of an
+/// index.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PublishNotification {
+    /// ID of the notification. Notifications are delivered at least once: consumers should
+    /// deduplicate them by ID.
+    pub notification_id: String,
+    pub index_id: String,
+    pub source_id: String,
+    pub node_id: String,
+    pub pipeline_ord: usize,
+    pub splits: Vec<PublishedSplit>,
+    /// Number of documents of the published splits.
+    pub num_docs: usize,
+    /// Union of the time ranges of the published splits.
+    pub time_range: Option<RangeInclusive<i64>>,
+    pub publish_timestamp: i64,
+}
+
+impl PublishNotification {
+    fn new(pipeline_id: &IndexingPipelineId, splits: &[SplitMetadata]) -> Self {
+        let time_range = splits
+            .iter()
+            .filter_map(|split| split.time_range.clone())
+            .reduce(|left, right| *left.start().min(right.start())..=*left.end().max(right.end()));
+        PublishNotification {
+            notification_id: Ulid::new().to_string(),
+            index_id: pipeline_id.index_id.clone(),
+            source_id: pipeline_id.source_id.clone(),
+            node_id: pipeline_id.node_id.clone(),
+            pipeline_ord: pipeline_id.pipeline_ord,
+            splits: splits
+                .iter()
+                .map(|split| PublishedSplit {
+                    split_id: split.split_id.clone(),
+                    num_docs: split.num_docs,
+                    time_range: split.time_range.clone(),
+                })
+                .collect(),
+            num_docs: splits.iter().map(|split| split.num_docs).sum(),
+            time_range,
+            publish_timestamp: OffsetDateTime::now_utc().unix_timestamp(),
+        }
+    }
+}
+
+/// Destination of the publish notifications of an index.
+pub enum PublishNotificationSink {
+    /// The notifications are posted as JSON to a URL.
+    Webhook {
+        client: reqwest::Client,
+        url: String,
+    },
+    /// The notifications are produced to a Kafka topic, keyed by index ID.
+    #[cfg(feature = "kafka")]
+    Kafka {
+        producer: rdkafka::producer::FutureProducer,
+        topic: String,
+    },
+}
+
+impl fmt::Debug for PublishNotificationSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PublishNotificationSink::Webhook { url, .. } => {
+                f.debug_struct("Webhook").field("url", url).finish()
+            }
+            #[cfg(feature = "kafka")]
+            PublishNotificationSink::Kafka { topic, .. } => {
+                f.debug_struct("Kafka").field("topic", topic).finish()
+            }
+        }
+    }
+}
+
+impl PublishNotificationSink {
+    pub fn from_settings(settings: &PublishNotificationSettings) -> anyhow::Result<Self> {
+        if let Some(url) = &settings.webhook_url {
+            let client = reqwest::Client::builder()
+                .timeout(DELIVERY_TIMEOUT)
+                .build()
+                .context("Failed to create the publish notification HTTP client.")?;
+            return Ok(PublishNotificationSink::Webhook {
+                client,
+                url: url.clone(),
+            });
+        }
+        if let Some(topic) = &settings.kafka_topic {
+            return Self::kafka(topic, &settings.kafka_client_params);
+        }
+        bail!("Publish notifications must set exactly one of `webhook_url` and `kafka_topic`.")
+    }
+
+    #[cfg(feature = "kafka")]
+    fn kafka(topic: &str, client_params: &serde_json::Value) -> anyhow::Result<Self> {
+        let client_params = if client_params.is_null() {
+            serde_json::Value::Object(serde_json::Map::new())
+        } else {
+            client_params.clone()
+        };
+        let producer = crate::source::parse_client_params(client_params)?
+            .create()
+            .context("Failed to create the publish notification Kafka producer.")?;
+        Ok(PublishNotificationSink::Kafka {
+            producer,
+            topic: topic.to_string(),
+        })
+    }
+
+    #[cfg(not(feature = "kafka"))]
+    fn kafka(_topic: &str, _client_params: &serde_json::Value) -> anyhow::Result<Self> {
+        bail!("Quickwit binary was not compiled with the `kafka` feature.")
+    }
+
+    async fn deliver(&self, notification: &PublishNotification) -> anyhow::Result<()> {
+        let payload = serde_json::to_vec(notification)?;
+        match self {
+            PublishNotificationSink::Webhook { client, url } => {
+                client
+                    .post(url)
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body(payload)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+            #[cfg(feature = "kafka")]
+            PublishNotificationSink::Kafka { producer, topic } => {
+                let record = rdkafka::producer::FutureRecord::to(topic)
+                    .key(&notification.index_id)
+                    .payload(&payload);
+                producer
+                    .send(record, DELIVERY_TIMEOUT)
+                    .await
+                    .map_err(|(error, _)| error)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Notification recorded in the outbox before its splits are published.
+#[derive(Debug)]
+pub struct PendingPublishNotification {
+    path: PathBuf,
+}
+
+/// Notifies a sink of the splits published by a pipeline, with at-least-once delivery. Shared by
+/// the publishers of all the generations of the pipeline.
+///
+/// Notifications go through an outbox directory: the publisher records a notification as pending
+/// before publishing its splits, and marks it as ready once they are published. A background task
+/// delivers the ready notifications in order, retrying with an exponential backoff until the sink
+/// acknowledges them, then deletes them. On startup, the notifications left pending by a previous
+/// run are delivered if their splits were published, and dropped otherwise.
+#[derive(Clone)]
+pub struct PublishNotifier {
+    pipeline_id: IndexingPipelineId,
+    outbox_directory: PathBuf,
+    ready_tx: mpsc::UnboundedSender<PathBuf>,
+    /// The delivery task gives up retrying once the notifier is dropped. The undelivered
+    /// notifications remain in the outbox for the next run.
+    _liveness: Arc<()>,
+}
+
+impl fmt::Debug for PublishNotifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PublishNotifier")
+            .field("pipeline_id", &self.pipeline_id)
+            .field("outbox_directory", &self.outbox_directory)
+            .finish()
+    }
+}
+
+impl PublishNotifier {
+    /// Creates the outbox of the pipeline and spawns the task delivering its notifications.
+    pub fn spawn(
+        pipeline_id: IndexingPipelineId,
+        settings: &PublishNotificationSettings,
+        indexing_directory: &IndexingDirectory,
+        metastore: Arc<dyn Metastore>,
+    ) -> anyhow::Result<Self> {
+        let sink = PublishNotificationSink::from_settings(settings)?;
+        let outbox_directory = indexing_directory
+            .path()
+            .join(PUBLISH_NOTIFICATIONS)
+            .join(pipeline_id.pipeline_ord.to_string());
+        std::fs::create_dir_all(&outbox_directory).with_context(|| {
+            format!(
+                "Failed to create publish notification outbox `{}`.",
+                outbox_directory.display()
+            )
+        })?;
+        // The notifications left behind by a previous run are listed before the publisher
+        // records new ones, which must not be mistaken for them.
+        let leftover_paths = list_outbox(&outbox_directory)?;
+        let (ready_tx, ready_rx) = mpsc::unbounded_channel();
+        let liveness = Arc::new(());
+        let delivery_task = DeliveryTask {
+            pipeline_id: pipeline_id.clone(),
+            sink,
+            metastore,
+            max_retry_delay: settings.max_retry_delay(),
+            liveness: Arc::downgrade(&liveness),
+        };
+        tokio::spawn(delivery_task.run(leftover_paths, ready_rx));
+        Ok(PublishNotifier {
+            pipeline_id,
+            outbox_directory,
+            ready_tx,
+            _liveness: liveness,
+        })
+    }
+
+    /// Records the notification of splits about to be published.
+    pub async fn prepare(
+        &self,
+        splits: &[SplitMetadata],
+    ) -> anyhow::Result<PendingPublishNotification> {
+        let notification = PublishNotification::new(&self.pipeline_id, splits);
+        let path = self.outbox_directory.join(format!(
+            "{}.{}",
+            notification.notification_id, PENDING_EXTENSION
+        ));
+        fs::write(&path, serde_json::to_vec(&notification)?)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to record publish notification `{}`.",
+                    path.display()
+                )
+            })?;
+        Ok(PendingPublishNotification { path })
+    }
+
+    /// Marks the notification as ready once its splits are published, and queues it for delivery.
+    pub async fn commit(&self, pending: PendingPublishNotification) -> anyhow::Result<()> {
+        let ready_path = pending.path.with_extension(READY_EXTENSION);
+        fs::rename(&pending.path, &ready_path)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to mark publish notification `{}` as ready.",
+                    pending.path.display()
+                )
+            })?;
+        // The delivery task only stops once the notifier is dropped.
+        let _ = self.ready_tx.send(ready_path);
+        Ok(())
+    }
+
+    /// Drops the notification of splits that failed to be published.
+    pub async fn abort(&self, pending: PendingPublishNotification) {
+        if let Err(error) = fs::remove_file(&pending.path).await {
+            warn!(
+                path = %pending.path.display(),
+                error = ?error,
+                "Failed to delete aborted publish notification."
+            );
+        }
+    }
+}
+
+/// Lists the notifications of an outbox, sorted by ID, hence by creation time.
+fn list_outbox(outbox_directory: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for dir_entry_res in std::fs::read_dir(outbox_directory)? {
+        let path = dir_entry_res?.path();
+        let extension_opt = path.extension().and_then(|extension| extension.to_str());
+        if matches!(extension_opt, Some(PENDING_EXTENSION | READY_EXTENSION)) {
+            paths.push(path);
+        }
+    }
+    paths.sort_by(|left, right| left.file_stem().cmp(&right.file_stem()));
+    Ok(paths)
+}
+
+async fn read_notification(path: &Path) -> anyhow::Result<PublishNotification> {
+    let payload = fs::read(path).await?;
+    let notification = serde_json::from_slice(&payload)?;
+    Ok(notification)
+}
+
+struct DeliveryTask {
+    pipeline_id: IndexingPipelineId,
+    sink: PublishNotificationSink,
+    metastore: Arc<dyn Metastore>,
+    max_retry_delay: Duration,
+    liveness: Weak<()>,
+}
+
+impl DeliveryTask {
+    async fn run(
+        self,
+        leftover_paths: Vec<PathBuf>,
+        mut ready_rx: mpsc::UnboundedReceiver<PathBuf>,
+    ) {
+        let split_states = match self.leftover_split_states(&leftover_paths).await {
+            Ok(split_states) => split_states,
+            Err(error) => {
+                // The leftover notifications are resolved by the next run.
+                warn!(
+                    index_id = %self.pipeline_id.index_id,
+                    source_id = %self.pipeline_id.source_id,
+                    error = ?error,
+                    "Failed to resolve leftover publish notifications."
+                );
+                return;
+            }
+        };
+        for path in leftover_paths {
+            if let Some(ready_path) = self.resolve_leftover(path, &split_states).await {
+                if !self.deliver_with_retries(&ready_path).await {
+                    return;
+                }
+            }
+        }
+        while let Some(ready_path) = ready_rx.recv().await {
+            if !self.deliver_with_retries(&ready_path).await {
+                return;
+            }
+        }
+    }
+
+    /// Returns the states of the splits of the index if some leftover notifications are pending.
+    async fn leftover_split_states(
+        &self,
+        leftover_paths: &[PathBuf],
+    ) -> anyhow::Result<HashMap<String, SplitState>> {
+        let has_pending_notifications = leftover_paths.iter().any(|path| {
+            path.extension().and_then(|extension| extension.to_str()) == Some(PENDING_EXTENSION)
+        });
+        if !has_pending_notifications {
+            return Ok(HashMap::new());
+        }
+        let split_states = self
+            .metastore
+            .list_all_splits(&self.pipeline_id.index_id)
+            .await?
+            .into_iter()
+            .map(|split| (split.split_metadata.split_id, split.split_state))
+            .collect();
+        Ok(split_states)
+    }
+
+    /// Returns the path of a leftover notification to deliver, marking it as ready if it was
+    /// pending and its splits were published. Pending notifications of splits that were not
+    /// published are deleted.
+    async fn resolve_leftover(
+        &self,
+        path: PathBuf,
+        split_states: &HashMap<String, SplitState>,
+    ) -> Option<PathBuf> {
+        if path.extension().and_then(|extension| extension.to_str()) == Some(READY_EXTENSION) {
+            return Some(path);
+        }
+        let is_published = match read_notification(&path).await {
+            Ok(notification) => notification.splits.iter().all(|split| {
+                matches!(
+                    split_states.get(&split.split_id),
+                    Some(SplitState::Published | SplitState::MarkedForDeletion)
+                )
+            }),
+            Err(_) => false,
+        };
+        if !is_published {
+            let _ = fs::remove_file(&path).await;
+            return None;
+        }
+        let ready_path = path.with_extension(READY_EXTENSION);
+        match fs::rename(&path, &ready_path).await {
+            Ok(()) => Some(ready_path),
+            Err(error) => {
+                warn!(path = %path.display(), error = ?error, "Failed to mark publish notification as ready.");
+                None
+            }
+        }
+    }
+
+    /// Delivers a notification, retrying until it succeeds, then deletes it from the outbox.
+    /// Returns `false` if the notifier was dropped before the notification could be delivered.
+    async fn deliver_with_retries(&self, path: &Path) -> bool {
+        let notification = match read_notification(path).await {
+            Ok(notification) => notification,
+            Err(error) => {
+                // A corrupted notification would be retried forever.
+                warn!(path = %path.display(), error = ?error, "Dropping unreadable publish notification.");
+                let _ = fs::remove_file(path).await;
+                return true;
+            }
+        };
+        let index_id = &self.pipeline_id.index_id;
+        let source_id = &self.pipeline_id.source_id;
+        let mut retry_delay = INITIAL_RETRY_DELAY;
+        loop {
+            match self.sink.deliver(&notification).await {
+                Ok(()) => break,
+                Err(error) => {
+                    INDEXER_METRICS
+                        .publish_notification_failures_total
+                        .with_label_values(&[index_id, source_id])
+                        .inc();
+                    warn!(
+                        index_id = %index_id,
+                        source_id = %source_id,
+                        notification_id = %notification.notification_id,
+                        retry_delay_secs = retry_delay.as_secs(),
+                        error = ?error,
+                        "Failed to deliver publish notification."
+                    );
+                }
+            }
+            tokio::time::sleep(retry_delay).await;
+            if self.liveness.strong_count() == 0 {
+                return false;
+            }
+            retry_delay = (retry_delay * 2).min(self.max_retry_delay);
+        }
+        INDEXER_METRICS
+            .publish_notifications_total
+            .with_label_values(&[index_id, source_id])
+            .inc();
+        info!(
+            index_id = %index_id,
+            source_id = %source_id,
+            notification_id = %notification.notification_id,
+            sink = ?self.sink,
+            "Delivered publish notification."
+        );
+        if let Err(error) = fs::remove_file(path).await {
+            warn!(path = %path.display(), error = ?error, "Failed to delete delivered publish notification.");
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use quickwit_metastore::MockMetastore;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Accepts HTTP requests, answering `503 Service Unavailable` to the first `num_failures`
+    /// ones and `200 OK` to the next ones, and forwards the bodies of the requests answered with
+    /// `200 OK`.
+    async fn spawn_webhook(
+        num_failures: usize,
+    ) -> (SocketAddr, mpsc::UnboundedReceiver<PublishNotification>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (body_tx, body_rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut num_requests = 0;
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 4096];
+                let body = loop {
+                    let num_bytes = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..num_bytes]);
+                    let request_str = String::from_utf8_lossy(&request).to_string();
+                    if let Some((headers, body)) = request_str.split_once("\r\n\r\n") {
+                        let content_length: usize = headers
+                            .lines()
+                            .find_map(|line| {
+                                line.to_lowercase()
+                                    .strip_prefix("content-length: ")
+                                    .map(|value| value.trim().parse().unwrap())
+                            })
+                            .unwrap_or(0);
+                        if body.len() >= content_length {
+                            break body.to_string();
+                        }
+                    }
+                };
+                num_requests += 1;
+                let status = if num_requests <= num_failures {
+                    "503 Service Unavailable"
+                } else {
+                    body_tx.send(serde_json::from_str(&body).unwrap()).unwrap();
+                    "200 OK"
+                };
+                let response = format!("HTTP/1.1 {status}\r\ncontent-length: 0\r\n\r\n");
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (addr, body_rx)
+    }
+
+    fn pipeline_id_for_test() -> IndexingPipelineId {
+        IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        }
+    }
+
+    fn webhook_settings(addr: SocketAddr) -> PublishNotificationSettings {
+        PublishNotificationSettings {
+            webhook_url: Some(format!("http://{addr}/published-splits")),
+            kafka_topic: None,
+            kafka_client_params: serde_json::Value::Null,
+            max_retry_delay_secs: 1,
+        }
+    }
+
+    fn split_metadata(
+        split_id: &str,
+        num_docs: usize,
+        time_range: RangeInclusive<i64>,
+    ) -> SplitMetadata {
+        SplitMetadata {
+            split_id: split_id.to_string(),
+            num_docs,
+            time_range: Some(time_range),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_publish_notifier_retries_until_delivered() {
+        let (addr, mut body_rx) = spawn_webhook(1).await;
+        let indexing_directory = IndexingDirectory::for_test().await.unwrap();
+        let pipeline_id = pipeline_id_for_test();
+        let publish_notifier = PublishNotifier::spawn(
+            pipeline_id,
+            &webhook_settings(addr),
+            &indexing_directory,
+            Arc::new(MockMetastore::default()),
+        )
+        .unwrap();
+        let splits = [
+            split_metadata("split-1", 10, 100..=200),
+            split_metadata("split-2", 5, 50..=150),
+        ];
+        let pending = publish_notifier.prepare(&splits).await.unwrap();
+        publish_notifier.commit(pending).await.unwrap();
+
+        let notification = body_rx.recv().await.unwrap();
+        assert_eq!(notification.index_id, "test-index");
+        assert_eq!(notification.splits.len(), 2);
+        assert_eq!(notification.num_docs, 15);
+        assert_eq!(notification.time_range, Some(50..=200));
+
+        // The notification is deleted from the outbox once delivered.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(list_outbox(&publish_notifier.outbox_directory)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_publish_notifier_resolves_leftover_notifications() {
+        let (addr, mut body_rx) = spawn_webhook(0).await;
+        let indexing_directory = IndexingDirectory::for_test().await.unwrap();
+        let pipeline_id = pipeline_id_for_test();
+        let settings = webhook_settings(addr);
+        {
+            // The previous run records two notifications but crashes before publishing the
+            // splits of the second one, and before marking the first one as ready.
+            let mut metastore = MockMetastore::default();
+            metastore
+                .expect_list_all_splits()
+                .returning(|_| Ok(Vec::new()));
+            let (unreachable_addr, _) = spawn_webhook(usize::MAX).await;
+            let previous_notifier = PublishNotifier::spawn(
+                pipeline_id.clone(),
+                &webhook_settings(unreachable_addr),
+                &indexing_directory,
+                Arc::new(metastore),
+            )
+            .unwrap();
+            previous_notifier
+                .prepare(&[split_metadata("split-1", 10, 100..=200)])
+                .await
+                .unwrap();
+            previous_notifier
+                .prepare(&[split_metadata("split-2", 10, 100..=200)])
+                .await
+                .unwrap();
+        }
+        let mut metastore = MockMetastore::default();
+        metastore.expect_list_all_splits().returning(|_| {
+            let published_split = quickwit_metastore::Split {
+                split_state: SplitState::Published,
+                update_timestamp: 0,
+                split_metadata: split_metadata("split-1", 10, 100..=200),
+            };
+            let staged_split = quickwit_metastore::Split {
+                split_state: SplitState::Staged,
+                update_timestamp: 0,
+                split_metadata: split_metadata("split-2", 10, 100..=200),
+            };
+            Ok(vec![published_split, staged_split])
+        });
+        let publish_notifier = PublishNotifier::spawn(
+            pipeline_id,
+            &settings,
+            &indexing_directory,
+            Arc::new(metastore),
+        )
+        .unwrap();
+        let notification = body_rx.recv().await.unwrap();
+        assert_eq!(notification.splits[0].split_id, "split-1");
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(body_rx.try_recv().is_err());
+        assert!(list_outbox(&publish_notifier.outbox_directory)
+            .unwrap()
+            .is_empty());
+    }
+}
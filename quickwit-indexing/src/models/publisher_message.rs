@@ -31,6 +31,9 @@ pub struct SplitUpdate {
     pub new_splits: Vec<SplitMetadata>,
     pub replaced_split_ids: Vec<String>,
     pub checkpoint_delta_opt: Option<IndexCheckpointDelta>,
+    /// When set, the publisher holds the new splits back and publishes them along with the next
+    /// checkpoint delta.
+    pub publish_with_next_delta: bool,
     pub publish_lock: PublishLock,
     pub date_of_birth: Instant, // for logging
 }
@@ -46,6 +49,7 @@ impl fmt::Debug for SplitUpdate {
             .field("index_id", &self.index_id)
             .field("new_splits", &new_split_ids)
             .field("checkpoint_delta", &self.checkpoint_delta_opt)
+            .field("publish_with_next_delta", &self.publish_with_next_delta)
             .field("tts_in_secs", &self.date_of_birth.elapsed().as_secs_f32())
             .finish()
     }
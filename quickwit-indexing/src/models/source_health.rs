@@ -0,0 +1,298 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Instant;
+
+use quickwit_metastore::checkpoint::{PartitionId, Position, SourceCheckpoint};
+use serde::{Deserialize, Serialize};
+
+use super::{DataLossSuspicion, PipelineWarning};
+
+/// Period during which a warning emitted by a pipeline of a source degrades its health.
+const DEGRADED_WARNING_PERIOD_SECS: i64 = 300;
+
+/// Overall health of a source.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceHealthStatus {
+    /// The source is reachable and its pipelines run without recent warnings.
+    Healthy,
+    /// The pipelines of the source emitted a warning recently.
+    Degraded,
+    /// The source is unreachable, no pipeline reads it on the node, or its pipelines are paused
+    /// because storing splits keeps failing.
+    Unhealthy,
+}
+
+/// Outcome of the connectivity check of a source, which reaches its brokers or files with the
+/// credentials of the source.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SourceConnectivity {
+    pub is_reachable: bool,
+    /// Error raised by the connectivity check, if the source is unreachable.
+    pub error: Option<String>,
+}
+
+impl SourceConnectivity {
+    pub fn from_check_result(check_result: anyhow::Result<()>) -> Self {
+        match check_result {
+            Ok(()) => SourceConnectivity {
+                is_reachable: true,
+                error: None,
+            },
+            Err(error) => SourceConnectivity {
+                is_reachable: false,
+                error: Some(format!("{:#}", error)),
+            },
+        }
+    }
+}
+
+/// Positions of a partition of a source read by the pipelines and published in the metastore.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PartitionLag {
+    pub partition_id: String,
+    pub read_position: String,
+    pub published_position: String,
+    /// Number of positions read but not published yet, when the positions are numeric offsets.
+    pub num_unpublished_positions: Option<u64>,
+}
+
+/// Documents and bytes read per second by the pipelines of a source.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SourceThroughput {
+    pub num_docs_per_sec: f64,
+    pub num_bytes_per_sec: f64,
+    /// Duration over which the throughput is measured.
+    pub period_secs: f64,
+}
+
+/// Health of a source, aggregated over the pipelines reading it on the node.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SourceHealth {
+    pub index_id: String,
+    pub source_id: String,
+    pub status: SourceHealthStatus,
+    pub connectivity: SourceConnectivity,
+    /// Number of pipelines reading the source on the node.
+    pub num_pipelines: usize,
+    pub partitions: Vec<PartitionLag>,
+    /// Throughput since the previous request for the health of the source, if any.
+    pub throughput: Option<SourceThroughput>,
+    /// Most recent warning emitted by the pipelines of the source.
+    pub last_error: Option<PipelineWarning>,
+    /// Suspicions of data loss raised by the last run of the data loss detector.
+    pub data_loss_suspicions: Vec<DataLossSuspicion>,
+}
+
+impl SourceHealthStatus {
+    pub fn evaluate(
+        connectivity: &SourceConnectivity,
+        num_pipelines: usize,
+        is_upload_circuit_breaker_tripped: bool,
+        last_error_opt: Option<&PipelineWarning>,
+        now_timestamp: i64,
+    ) -> Self {
+        if !connectivity.is_reachable || num_pipelines == 0 || is_upload_circuit_breaker_tripped {
+            return SourceHealthStatus::Unhealthy;
+        }
+        match last_error_opt {
+            Some(last_error)
+                if last_error.last_timestamp + DEGRADED_WARNING_PERIOD_SECS >= now_timestamp =>
+            {
+                SourceHealthStatus::Degraded
+            }
+            _ => SourceHealthStatus::Healthy,
+        }
+    }
+}
+
+pub(super) fn position_offset(position: &Position) -> Option<u64> {
+    match position {
+        Position::Beginning => None,
+        Position::Offset(offset) => offset.parse().ok(),
+    }
+}
+
+/// Returns the lag of each partition, from the positions read by the pipelines and the published
+/// checkpoint of the source.
+pub fn partition_lags(
+    read_positions: &BTreeMap<PartitionId, Position>,
+    published_checkpoint_opt: Option<&SourceCheckpoint>,
+) -> Vec<PartitionLag> {
+    let mut partition_ids: BTreeSet<PartitionId> = read_positions.keys().cloned().collect();
+    if let Some(published_checkpoint) = published_checkpoint_opt {
+        partition_ids.extend(
+            published_checkpoint
+                .iter()
+                .map(|(partition_id, _)| partition_id),
+        );
+    }
+    partition_ids
+        .into_iter()
+        .map(|partition_id| {
+            let published_position = published_checkpoint_opt
+                .and_then(|published_checkpoint| {
+                    published_checkpoint.position_for_partition(&partition_id)
+                })
+                .cloned()
+                .unwrap_or(Position::Beginning);
+            // The pipelines do not report the partitions they did not read since they started.
+            let read_position = read_positions
+                .get(&partition_id)
+                .cloned()
+                .unwrap_or_else(|| published_position.clone());
+            let num_unpublished_positions = position_offset(&read_position)
+                .zip(position_offset(&published_position))
+                .map(|(read_offset, published_offset)| {
+                    read_offset.saturating_sub(published_offset)
+                });
+            PartitionLag {
+                partition_id: partition_id.0.to_string(),
+                read_position: read_position.as_str().to_string(),
+                published_position: published_position.as_str().to_string(),
+                num_unpublished_positions,
+            }
+        })
+        .collect()
+}
+
+/// Cumulative counters of the pipelines of a source, sampled to measure its throughput.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ThroughputSample {
+    pub instant: Instant,
+    pub num_docs: u64,
+    pub num_bytes: u64,
+}
+
+impl ThroughputSample {
+    /// Returns the throughput between a previous sample and this one, or `None` if the counters
+    /// were reset in the meantime, for instance because a pipeline was respawned.
+    pub fn throughput_since(&self, previous: &ThroughputSample) -> Option<SourceThroughput> {
+        let period_secs = self
+            .instant
+            .saturating_duration_since(previous.instant)
+            .as_secs_f64();
+        if period_secs == 0.0
+            || self.num_docs < previous.num_docs
+            || self.num_bytes < previous.num_bytes
+        {
+            return None;
+        }
+        Some(SourceThroughput {
+            num_docs_per_sec: (self.num_docs - previous.num_docs) as f64 / period_secs,
+            num_bytes_per_sec: (self.num_bytes - previous.num_bytes) as f64 / period_secs,
+            period_secs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::models::PipelineWarningKind;
+
+    #[test]
+    fn test_partition_lags() {
+        let read_positions = BTreeMap::from_iter([
+            (PartitionId::from(0u64), Position::from(1_200u64)),
+            (PartitionId::from(1u64), Position::from("not-a-number")),
+        ]);
+        let published_checkpoint = SourceCheckpoint::from_iter([
+            (PartitionId::from(0u64), Position::from(1_000u64)),
+            (PartitionId::from(2u64), Position::from(500u64)),
+        ]);
+        let partition_lags = partition_lags(&read_positions, Some(&published_checkpoint));
+        assert_eq!(partition_lags.len(), 3);
+        assert_eq!(partition_lags[0].num_unpublished_positions, Some(200));
+        assert_eq!(partition_lags[1].published_position, "");
+        assert_eq!(partition_lags[1].num_unpublished_positions, None);
+        // Partitions not read since the pipelines started have no lag.
+        assert_eq!(
+            partition_lags[2].read_position,
+            partition_lags[2].published_position
+        );
+        assert_eq!(partition_lags[2].num_unpublished_positions, Some(0));
+    }
+
+    #[test]
+    fn test_source_health_status() {
+        let reachable = SourceConnectivity::from_check_result(Ok(()));
+        let unreachable =
+            SourceConnectivity::from_check_result(Err(anyhow::anyhow!("Broker unreachable.")));
+        assert_eq!(unreachable.error.as_deref(), Some("Broker unreachable."));
+        let warning = PipelineWarning {
+            kind: PipelineWarningKind::InvalidDocs,
+            actor_name: "Indexer".to_string(),
+            message: "10% of the docs of the last commit (10/100) were failing to parse."
+                .to_string(),
+            count: 1,
+            first_timestamp: 1_000,
+            last_timestamp: 1_000,
+        };
+        assert_eq!(
+            SourceHealthStatus::evaluate(&reachable, 1, false, None, 1_000),
+            SourceHealthStatus::Healthy
+        );
+        assert_eq!(
+            SourceHealthStatus::evaluate(&reachable, 1, false, Some(&warning), 1_100),
+            SourceHealthStatus::Degraded
+        );
+        assert_eq!(
+            SourceHealthStatus::evaluate(&reachable, 1, false, Some(&warning), 2_000),
+            SourceHealthStatus::Healthy
+        );
+        assert_eq!(
+            SourceHealthStatus::evaluate(&unreachable, 1, false, None, 1_000),
+            SourceHealthStatus::Unhealthy
+        );
+        assert_eq!(
+            SourceHealthStatus::evaluate(&reachable, 0, false, None, 1_000),
+            SourceHealthStatus::Unhealthy
+        );
+        assert_eq!(
+            SourceHealthStatus::evaluate(&reachable, 1, true, None, 1_000),
+            SourceHealthStatus::Unhealthy
+        );
+    }
+
+    #[test]
+    fn test_throughput_since() {
+        let now = Instant::now();
+        let previous = ThroughputSample {
+            instant: now,
+            num_docs: 100,
+            num_bytes: 1_000,
+        };
+        let sample = ThroughputSample {
+            instant: now + Duration::from_secs(10),
+            num_docs: 600,
+            num_bytes: 11_000,
+        };
+        let throughput = sample.throughput_since(&previous).unwrap();
+        assert_eq!(throughput.num_docs_per_sec, 50.0);
+        assert_eq!(throughput.num_bytes_per_sec, 1_000.0);
+        assert_eq!(throughput.period_secs, 10.0);
+        // The counters were reset.
+        assert!(previous.throughput_since(&sample).is_none());
+    }
+}
@@ -0,0 +1,223 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use quickwit_config::SourceRateLimit;
+use serde::{Deserialize, Serialize};
+
+/// Counters of the throttling of a source by its rate limiter.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SourceThrottlingCounters {
+    /// Number of times the source was paused because it exceeded its rate limit.
+    pub num_throttles: u64,
+    /// Total time the source was paused, in milliseconds.
+    pub throttled_millis: u64,
+}
+
+/// Token bucket of one of the limits of a source.
+struct Bucket {
+    max_per_sec: f64,
+    /// Number of docs or bytes that can be read right away. It goes negative when a batch
+    /// exceeds the budget, and the source is then paused until the bucket is refilled.
+    budget: f64,
+}
+
+impl Bucket {
+    fn new(max_per_sec: u64) -> Self {
+        let max_per_sec = max_per_sec as f64;
+        Bucket {
+            max_per_sec,
+            budget: max_per_sec,
+        }
+    }
+
+    fn refill(&mut self, elapsed: Duration) {
+        // The budget is capped to one second worth of reads.
+        self.budget =
+            (self.budget + elapsed.as_secs_f64() * self.max_per_sec).min(self.max_per_sec);
+    }
+
+    fn wait_duration(&self) -> Duration {
+        if self.budget >= 0.0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(-self.budget / self.max_per_sec)
+    }
+}
+
+struct RateLimiterState {
+    docs_bucket_opt: Option<Bucket>,
+    bytes_bucket_opt: Option<Bucket>,
+    last_refill_at: Instant,
+    counters: SourceThrottlingCounters,
+}
+
+impl RateLimiterState {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill_at);
+        self.last_refill_at = now;
+        for bucket in self
+            .docs_bucket_opt
+            .iter_mut()
+            .chain(self.bytes_bucket_opt.iter_mut())
+        {
+            bucket.refill(elapsed);
+        }
+    }
+}
+
+/// Rate limiter shared by the source and the indexer of all the generations of a pipeline.
+///
+/// The indexer records the docs and bytes of the batches it receives from the source, and the
+/// source pauses before emitting its next batches for as long as the pipeline is over budget.
+#[derive(Clone)]
+pub struct SourceRateLimiter {
+    state: Arc<Mutex<RateLimiterState>>,
+}
+
+impl SourceRateLimiter {
+    pub fn new(rate_limit: &SourceRateLimit) -> Self {
+        let state = RateLimiterState {
+            docs_bucket_opt: rate_limit.max_docs_per_sec.map(Bucket::new),
+            bytes_bucket_opt: rate_limit
+                .max_bytes_per_sec
+                .map(|max_bytes_per_sec| Bucket::new(max_bytes_per_sec.get_bytes())),
+            last_refill_at: Instant::now(),
+            counters: SourceThrottlingCounters::default(),
+        };
+        SourceRateLimiter {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Records a batch read from the source.
+    pub fn record_batch(&self, num_docs: u64, num_bytes: u64) {
+        self.record_batch_at(num_docs, num_bytes, Instant::now());
+    }
+
+    fn record_batch_at(&self, num_docs: u64, num_bytes: u64, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        state.refill(now);
+        if let Some(docs_bucket) = &mut state.docs_bucket_opt {
+            docs_bucket.budget -= num_docs as f64;
+        }
+        if let Some(bytes_bucket) = &mut state.bytes_bucket_opt {
+            bytes_bucket.budget -= num_bytes as f64;
+        }
+    }
+
+    /// Returns how long the source should be paused before emitting its next batches, if it is
+    /// over budget, and counts the throttling.
+    pub fn throttle_duration(&self) -> Option<Duration> {
+        self.throttle_duration_at(Instant::now())
+    }
+
+    fn throttle_duration_at(&self, now: Instant) -> Option<Duration> {
+        let mut state = self.state.lock().unwrap();
+        state.refill(now);
+        let throttle_duration = state
+            .docs_bucket_opt
+            .iter()
+            .chain(state.bytes_bucket_opt.iter())
+            .map(Bucket::wait_duration)
+            .max()
+            .unwrap_or_default();
+        if throttle_duration.is_zero() {
+            return None;
+        }
+        state.counters.num_throttles += 1;
+        state.counters.throttled_millis += throttle_duration.as_millis() as u64;
+        Some(throttle_duration)
+    }
+
+    pub fn counters(&self) -> SourceThrottlingCounters {
+        self.state.lock().unwrap().counters
+    }
+}
+
+impl fmt::Debug for SourceRateLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SourceRateLimiter")
+            .field("counters", &self.counters())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byte_unit::Byte;
+
+    use super::*;
+
+    #[test]
+    fn test_source_rate_limiter_docs() {
+        let rate_limiter = SourceRateLimiter::new(&SourceRateLimit {
+            max_docs_per_sec: Some(1_000),
+            max_bytes_per_sec: None,
+        });
+        let start = rate_limiter.state.lock().unwrap().last_refill_at;
+        // Bursts of up to one second worth of docs are allowed.
+        rate_limiter.record_batch_at(1_000, 1_000_000, start);
+        assert!(rate_limiter.throttle_duration_at(start).is_none());
+
+        rate_limiter.record_batch_at(500, 0, start);
+        assert_eq!(
+            rate_limiter.throttle_duration_at(start),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            rate_limiter.throttle_duration_at(start + Duration::from_millis(250)),
+            Some(Duration::from_millis(250))
+        );
+        assert!(rate_limiter
+            .throttle_duration_at(start + Duration::from_millis(500))
+            .is_none());
+        assert_eq!(
+            rate_limiter.counters(),
+            SourceThrottlingCounters {
+                num_throttles: 2,
+                throttled_millis: 750,
+            }
+        );
+    }
+
+    #[test]
+    fn test_source_rate_limiter_applies_strictest_limit() {
+        let rate_limiter = SourceRateLimiter::new(&SourceRateLimit {
+            max_docs_per_sec: Some(1_000),
+            max_bytes_per_sec: Some(Byte::from_bytes(1_000)),
+        });
+        let start = rate_limiter.state.lock().unwrap().last_refill_at;
+        rate_limiter.record_batch_at(10, 3_000, start);
+        assert_eq!(
+            rate_limiter.throttle_duration_at(start),
+            Some(Duration::from_secs(2))
+        );
+        // The budget does not accumulate beyond one second worth of reads while idle.
+        let later = start + Duration::from_secs(60);
+        rate_limiter.record_batch_at(1_500, 0, later);
+        assert_eq!(
+            rate_limiter.throttle_duration_at(later),
+            Some(Duration::from_millis(500))
+        );
+    }
+}
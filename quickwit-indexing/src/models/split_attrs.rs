@@ -20,6 +20,8 @@
 use std::fmt;
 use std::ops::RangeInclusive;
 
+use quickwit_metastore::DedupDigest;
+
 use crate::models::IndexingPipelineId;
 
 pub struct SplitAttrs {
@@ -50,6 +52,23 @@ pub struct SplitAttrs {
     pub time_range: Option<RangeInclusive<i64>>,
 
     pub replaced_split_ids: Vec<String>,
+
+    /// Opstamp of the last delete task applied to the split.
+    pub delete_opstamp: u64,
+
+    /// Digest of the IDs of the documents in the split, when deduplication is enabled.
+    pub dedup_digest_opt: Option<DedupDigest>,
+
+    /// Earliest expiry of the documents of the split, if the doc mapping declares an expire
+    /// timestamp field.
+    pub min_expire_timestamp: Option<i64>,
+
+    /// Version of the doc mapping of the index the split was produced with.
+    pub doc_mapping_version: u64,
+
+    /// Fingerprint of the doc mapping and the indexing settings of the index the split was
+    /// produced with.
+    pub config_fingerprint: Option<String>,
 }
 
 impl fmt::Debug for SplitAttrs {
@@ -64,6 +83,8 @@ impl fmt::Debug for SplitAttrs {
                 &self.uncompressed_docs_size_in_bytes,
             )
             .field("num_docs", &self.num_docs)
+            .field("delete_opstamp", &self.delete_opstamp)
+            .field("min_expire_timestamp", &self.min_expire_timestamp)
             .finish()
     }
 }
@@ -0,0 +1,369 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use quickwit_actors::Mailbox;
+use quickwit_config::TenantRoutingSettings;
+use quickwit_ingest_api::IngestApiService;
+use quickwit_metastore::checkpoint::IndexCheckpoint;
+use quickwit_metastore::{IndexMetadata, Metastore, MetastoreError};
+use quickwit_proto::ingest_api::{CreateQueueIfNotExistsRequest, DocBatch, IngestRequest};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use time::OffsetDateTime;
+use tracing::info;
+
+use super::{IndexingPipelineId, SpawnPipelines};
+use crate::actors::IndexingService;
+use crate::metrics::INDEXER_METRICS;
+
+/// Number of bytes of routed documents buffered before the indexer flushes the router.
+const MAX_NUM_PENDING_BYTES: usize = 10_000_000;
+
+/// Counters of the documents routed to tenant indexes.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TenantRouterCounters {
+    /// Number of documents appended to the queue of a tenant index.
+    pub num_docs_routed: u64,
+    /// Number of tenant indexes created by the router.
+    pub num_tenant_indexes_created: u64,
+}
+
+#[derive(Default)]
+struct PendingDocs {
+    concat_docs: Vec<u8>,
+    doc_lens: Vec<u64>,
+}
+
+#[derive(Default)]
+struct TenantRouterState {
+    /// Documents waiting to be appended to the queue of their tenant index, keyed by index ID.
+    pending_docs: BTreeMap<String, PendingDocs>,
+    num_pending_bytes: usize,
+    /// Tenant indexes known to exist, along with their queue.
+    tenant_index_ids: HashSet<String>,
+    counters: TenantRouterCounters,
+}
+
+/// Routes the documents of the tenants configured in the tenant routing settings of an index to
+/// per-tenant indexes. Shared by the indexers of all the generations of a pipeline.
+///
+/// The tenant indexes are created on demand, from the index metadata, with no source and the
+/// retention and merge policies of the tenant routing settings. The routed documents are appended
+/// to the ingest API queues of the tenant indexes, which are indexed by the ingest API pipelines
+/// of the indexing service. The router is flushed before the checkpoint of a commit is published,
+/// so that a failure to route documents fails the indexer and the documents are read again.
+#[derive(Clone)]
+pub struct TenantRouter {
+    pipeline_id: IndexingPipelineId,
+    index_metadata: Arc<IndexMetadata>,
+    settings: TenantRoutingSettings,
+    metastore: Arc<dyn Metastore>,
+    ingest_api_service: Mailbox<IngestApiService>,
+    indexing_service_opt: Option<Mailbox<IndexingService>>,
+    state: Arc<Mutex<TenantRouterState>>,
+}
+
+impl TenantRouter {
+    pub fn new(
+        pipeline_id: IndexingPipelineId,
+        index_metadata: IndexMetadata,
+        settings: TenantRoutingSettings,
+        metastore: Arc<dyn Metastore>,
+        ingest_api_service: Mailbox<IngestApiService>,
+    ) -> Self {
+        TenantRouter {
+            pipeline_id,
+            index_metadata: Arc::new(index_metadata),
+            settings,
+            metastore,
+            ingest_api_service,
+            indexing_service_opt: None,
+            state: Arc::default(),
+        }
+    }
+
+    /// Asks the indexing service to spawn the pipelines of the tenant indexes as they are
+    /// created.
+    pub fn with_indexing_service(mut self, indexing_service: Mailbox<IndexingService>) -> Self {
+        self.indexing_service_opt = Some(indexing_service);
+        self
+    }
+
+    /// Returns the ID of the tenant index of a document, if the document belongs to a routed
+    /// tenant. Documents without a tenant, or whose tenant yields an invalid index ID, stay in the
+    /// index.
+    fn tenant_index_id(&self, doc_json: &str) -> Option<String> {
+        let doc: JsonValue = serde_json::from_str(doc_json).ok()?;
+        let tenant = self
+            .settings
+            .tenant_field
+            .split('.')
+            .try_fold(&doc, |value, key| value.get(key))?
+            .as_str()?;
+        if !self.settings.is_routed(tenant) {
+            return None;
+        }
+        self.settings
+            .tenant_index_id(&self.index_metadata.index_id, tenant)
+            .ok()
+    }
+
+    /// Buffers the document if it belongs to a routed tenant and returns whether it was routed.
+    pub fn route(&self, doc_json: &str) -> bool {
+        let tenant_index_id = match self.tenant_index_id(doc_json) {
+            Some(tenant_index_id) => tenant_index_id,
+            None => return false,
+        };
+        let mut state = self.state.lock().unwrap();
+        let pending_docs = state.pending_docs.entry(tenant_index_id).or_default();
+        pending_docs
+            .concat_docs
+            .extend_from_slice(doc_json.as_bytes());
+        pending_docs.doc_lens.push(doc_json.len() as u64);
+        state.num_pending_bytes += doc_json.len();
+        true
+    }
+
+    /// Returns whether the buffered documents should be flushed.
+    pub fn is_full(&self) -> bool {
+        self.state.lock().unwrap().num_pending_bytes >= MAX_NUM_PENDING_BYTES
+    }
+
+    /// Appends the buffered documents to the queues of their tenant index, creating the missing
+    /// tenant indexes beforehand.
+    pub async fn flush(&self) -> anyhow::Result<()> {
+        let (pending_docs, known_tenant_index_ids) = {
+            let mut state = self.state.lock().unwrap();
+            state.num_pending_bytes = 0;
+            (
+                std::mem::take(&mut state.pending_docs),
+                state.tenant_index_ids.clone(),
+            )
+        };
+        let mut doc_batches = Vec::with_capacity(pending_docs.len());
+        for (tenant_index_id, pending_docs) in pending_docs {
+            if !known_tenant_index_ids.contains(&tenant_index_id) {
+                self.prepare_tenant_index(&tenant_index_id).await?;
+            }
+            doc_batches.push(DocBatch {
+                index_id: tenant_index_id,
+                concat_docs: pending_docs.concat_docs,
+                doc_lens: pending_docs.doc_lens,
+            });
+        }
+        if doc_batches.is_empty() {
+            return Ok(());
+        }
+        let num_docs: u64 = doc_batches
+            .iter()
+            .map(|doc_batch| doc_batch.doc_lens.len() as u64)
+            .sum();
+        self.ingest_api_service
+            .ask_for_res(IngestRequest { doc_batches })
+            .await
+            .map_err(|error| anyhow::anyhow!(error.to_string()))
+            .context("Failed to append the documents to the queues of the tenant indexes.")?;
+        self.state.lock().unwrap().counters.num_docs_routed += num_docs;
+        INDEXER_METRICS
+            .tenant_routed_docs_total
+            .with_label_values(&[&self.pipeline_id.index_id, &self.pipeline_id.source_id])
+            .inc_by(num_docs);
+        Ok(())
+    }
+
+    /// Creates the tenant index if it does not exist yet, along with its queue, and asks the
+    /// indexing service to spawn its pipelines.
+    async fn prepare_tenant_index(&self, tenant_index_id: &str) -> anyhow::Result<()> {
+        match self.metastore.index_metadata(tenant_index_id).await {
+            Ok(_) => {}
+            Err(MetastoreError::IndexDoesNotExist { .. }) => {
+                let tenant_index_metadata = self.tenant_index_metadata(tenant_index_id)?;
+                match self.metastore.create_index(tenant_index_metadata).await {
+                    // Another pipeline of the index may have created the tenant index meanwhile.
+                    Ok(()) | Err(MetastoreError::IndexAlreadyExists { .. }) => {}
+                    Err(error) => return Err(error.into()),
+                }
+                info!(
+                    index_id = %self.pipeline_id.index_id,
+                    tenant_index_id = %tenant_index_id,
+                    "create-tenant-index"
+                );
+                self.state
+                    .lock()
+                    .unwrap()
+                    .counters
+                    .num_tenant_indexes_created += 1;
+            }
+            Err(error) => return Err(error.into()),
+        }
+        let create_queue_req = CreateQueueIfNotExistsRequest {
+            queue_id: tenant_index_id.to_string(),
+        };
+        self.ingest_api_service
+            .ask_for_res(create_queue_req)
+            .await
+            .map_err(|error| anyhow::anyhow!(error.to_string()))?;
+        if let Some(indexing_service) = &self.indexing_service_opt {
+            // The indexing service does not spawn the pipelines of an index twice, and this
+            // pipeline must not wait for the indexing service, which may be waiting for it.
+            let spawn_pipelines = SpawnPipelines {
+                index_id: tenant_index_id.to_string(),
+            };
+            let _ = indexing_service.send_message(spawn_pipelines).await;
+        }
+        self.state
+            .lock()
+            .unwrap()
+            .tenant_index_ids
+            .insert(tenant_index_id.to_string());
+        Ok(())
+    }
+
+    /// Returns the metadata of a new tenant index, derived from the metadata of the index.
+    fn tenant_index_metadata(&self, tenant_index_id: &str) -> anyhow::Result<IndexMetadata> {
+        let index_metadata = &*self.index_metadata;
+        let index_uri = index_metadata
+            .index_uri
+            .parent()
+            .with_context(|| {
+                format!(
+                    "Failed to derive the URI of tenant index `{}` from index URI `{}`.",
+                    tenant_index_id, index_metadata.index_uri
+                )
+            })?
+            .join(tenant_index_id)?;
+        let mut indexing_settings = index_metadata.indexing_settings.clone();
+        indexing_settings.tenant_routing = None;
+        if let Some(merge_policy) = &self.settings.merge_policy {
+            indexing_settings.merge_policy = merge_policy.clone();
+        }
+        let retention_policy = self
+            .settings
+            .retention_policy
+            .clone()
+            .or_else(|| index_metadata.retention_policy.clone());
+        let now_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+        Ok(IndexMetadata {
+            index_id: tenant_index_id.to_string(),
+            index_uri,
+            checkpoint: IndexCheckpoint::default(),
+            doc_mapping: index_metadata.doc_mapping.clone(),
+            doc_mapping_version: 0,
+            indexing_settings,
+            search_settings: index_metadata.search_settings.clone(),
+            sources: HashMap::new(),
+            retention_policy,
+            create_timestamp: now_timestamp,
+            update_timestamp: now_timestamp,
+            maintenance_mode: false,
+            merge_planner_lease: None,
+            quarantined_files: HashMap::new(),
+            feature_flags: index_metadata.feature_flags.clone(),
+        })
+    }
+
+    pub fn counters(&self) -> TenantRouterCounters {
+        self.state.lock().unwrap().counters
+    }
+}
+
+impl fmt::Debug for TenantRouter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TenantRouter")
+            .field("tenant_field", &self.settings.tenant_field)
+            .field("counters", &self.counters())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_actors::create_test_mailbox;
+    use quickwit_metastore::MockMetastore;
+
+    use super::*;
+
+    fn tenant_router_for_test(tenants: &[&str]) -> TenantRouter {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let index_metadata = IndexMetadata::for_test("test-index", "ram:///indexes/test-index");
+        let settings = TenantRoutingSettings {
+            tenant_field: "attributes.tenant".to_string(),
+            tenants: tenants.iter().map(|tenant| tenant.to_string()).collect(),
+            index_id_template: "{index_id}-{tenant}".to_string(),
+            retention_policy: None,
+            merge_policy: None,
+        };
+        let (ingest_api_service, _inbox) = create_test_mailbox();
+        TenantRouter::new(
+            pipeline_id,
+            index_metadata,
+            settings,
+            Arc::new(MockMetastore::default()),
+            ingest_api_service,
+        )
+    }
+
+    #[test]
+    fn test_tenant_router_route() {
+        let tenant_router = tenant_router_for_test(&["acme"]);
+        assert!(tenant_router.route(r#"{"body": "hello", "attributes": {"tenant": "acme"}}"#));
+        // Documents of other tenants, without a tenant, or with an invalid tenant stay in the
+        // index.
+        assert!(!tenant_router.route(r#"{"body": "hello", "attributes": {"tenant": "globex"}}"#));
+        assert!(!tenant_router.route(r#"{"body": "hello"}"#));
+        assert!(!tenant_router.route(r#"{"body": "hello", "attributes": {"tenant": 42}}"#));
+        assert!(!tenant_router.route("{\"body\": 42"));
+
+        let state = tenant_router.state.lock().unwrap();
+        assert_eq!(state.pending_docs.len(), 1);
+        assert_eq!(state.pending_docs["test-index-acme"].doc_lens.len(), 1);
+    }
+
+    #[test]
+    fn test_tenant_router_tenant_index_metadata() {
+        let mut tenant_router = tenant_router_for_test(&[]);
+        assert!(tenant_router.route(r#"{"attributes": {"tenant": "globex"}}"#));
+        assert!(!tenant_router.route(r#"{"attributes": {"tenant": "acme corp"}}"#));
+
+        tenant_router.settings.merge_policy = Some(Default::default());
+        let tenant_index_metadata = tenant_router
+            .tenant_index_metadata("test-index-globex")
+            .unwrap();
+        assert_eq!(tenant_index_metadata.index_id, "test-index-globex");
+        assert_eq!(
+            tenant_index_metadata.index_uri.as_str(),
+            "ram:///indexes/test-index-globex"
+        );
+        assert!(tenant_index_metadata.sources.is_empty());
+        assert!(tenant_index_metadata
+            .indexing_settings
+            .tenant_routing
+            .is_none());
+    }
+}
@@ -0,0 +1,246 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use quickwit_common::metrics::IntGauge;
+use quickwit_storage::{StorageError, StorageErrorKind};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use super::IndexingPipelineId;
+use crate::metrics::INDEXER_METRICS;
+
+/// Reason code of a failure to store a split, surfaced to the operators.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UploadFailureReason {
+    /// The storage rejected the credentials of the node.
+    Unauthorized,
+    /// The bucket or directory of the index does not exist.
+    StorageNotFound,
+    /// The storage service forbids the operation or is misconfigured.
+    StorageMisconfigured,
+    /// Any other failure, such as a network or I/O error, which may resolve itself.
+    Transient,
+}
+
+impl UploadFailureReason {
+    /// Classifies the error returned when storing a split.
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        let storage_error_kind_opt = error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<StorageError>())
+            .map(StorageError::kind);
+        match storage_error_kind_opt {
+            Some(StorageErrorKind::Unauthorized) => UploadFailureReason::Unauthorized,
+            Some(StorageErrorKind::DoesNotExist) => UploadFailureReason::StorageNotFound,
+            Some(StorageErrorKind::Service) => UploadFailureReason::StorageMisconfigured,
+            Some(StorageErrorKind::InternalError) | Some(StorageErrorKind::Io) | None => {
+                UploadFailureReason::Transient
+            }
+        }
+    }
+
+    /// Returns whether retrying is unlikely to succeed until an operator steps in.
+    pub fn is_permanent(&self) -> bool {
+        *self != UploadFailureReason::Transient
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UploadFailureReason::Unauthorized => "unauthorized",
+            UploadFailureReason::StorageNotFound => "storage_not_found",
+            UploadFailureReason::StorageMisconfigured => "storage_misconfigured",
+            UploadFailureReason::Transient => "transient",
+        }
+    }
+}
+
+impl fmt::Display for UploadFailureReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Failure that tripped the upload circuit breaker of a pipeline.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct UploadCircuitBreakerTrip {
+    pub reason: UploadFailureReason,
+    /// Message of the last failure.
+    pub message: String,
+    pub num_consecutive_failures: usize,
+    /// Unix timestamp of the last failure.
+    pub timestamp: i64,
+}
+
+#[derive(Default)]
+struct CircuitBreakerState {
+    num_consecutive_permanent_failures: usize,
+    trip_opt: Option<UploadCircuitBreakerTrip>,
+}
+
+/// Circuit breaker shared by the uploaders of all the generations of a pipeline.
+///
+/// The breaker trips after `max_consecutive_permanent_failures` consecutive failures that look
+/// permanent, such as rejected credentials or a missing bucket. A transient failure or a
+/// successful upload resets the count, and a successful upload closes a tripped breaker.
+#[derive(Clone)]
+pub struct UploadCircuitBreaker {
+    max_consecutive_permanent_failures: usize,
+    state: Arc<Mutex<CircuitBreakerState>>,
+    tripped_gauge: IntGauge,
+    pipeline_id: IndexingPipelineId,
+}
+
+impl UploadCircuitBreaker {
+    pub fn new(pipeline_id: IndexingPipelineId, max_consecutive_permanent_failures: usize) -> Self {
+        let tripped_gauge = INDEXER_METRICS
+            .upload_circuit_breaker_tripped
+            .with_label_values(&[&pipeline_id.index_id, &pipeline_id.source_id]);
+        UploadCircuitBreaker {
+            max_consecutive_permanent_failures,
+            state: Arc::default(),
+            tripped_gauge,
+            pipeline_id,
+        }
+    }
+
+    /// Records a failure to store a split and returns its reason code.
+    pub fn record_failure(&self, error: &anyhow::Error) -> UploadFailureReason {
+        let reason = UploadFailureReason::from_error(error);
+        INDEXER_METRICS
+            .upload_failures_total
+            .with_label_values(&[
+                &self.pipeline_id.index_id,
+                &self.pipeline_id.source_id,
+                reason.as_str(),
+            ])
+            .inc();
+        let mut state = self.state.lock().unwrap();
+        if !reason.is_permanent() {
+            state.num_consecutive_permanent_failures = 0;
+            return reason;
+        }
+        state.num_consecutive_permanent_failures += 1;
+        if state.num_consecutive_permanent_failures >= self.max_consecutive_permanent_failures {
+            state.trip_opt = Some(UploadCircuitBreakerTrip {
+                reason,
+                message: format!("{:#}", error),
+                num_consecutive_failures: state.num_consecutive_permanent_failures,
+                timestamp: OffsetDateTime::now_utc().unix_timestamp(),
+            });
+            self.tripped_gauge.set(1);
+        }
+        reason
+    }
+
+    /// Records a successful upload, closing the breaker.
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.num_consecutive_permanent_failures = 0;
+        if state.trip_opt.take().is_some() {
+            self.tripped_gauge.set(0);
+        }
+    }
+
+    /// Returns the failure that tripped the breaker, if it is tripped.
+    pub fn trip_opt(&self) -> Option<UploadCircuitBreakerTrip> {
+        self.state.lock().unwrap().trip_opt.clone()
+    }
+}
+
+impl fmt::Debug for UploadCircuitBreaker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UploadCircuitBreaker")
+            .field("trip", &self.trip_opt())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn storage_error(kind: StorageErrorKind) -> anyhow::Error {
+        anyhow::Error::new(kind.with_error(anyhow::anyhow!("Access denied.")))
+            .context("Failed uploading key foo.split in bucket s3://bucket")
+    }
+
+    #[test]
+    fn test_upload_failure_reason_from_error() {
+        assert_eq!(
+            UploadFailureReason::from_error(&storage_error(StorageErrorKind::Unauthorized)),
+            UploadFailureReason::Unauthorized
+        );
+        assert_eq!(
+            UploadFailureReason::from_error(&storage_error(StorageErrorKind::DoesNotExist)),
+            UploadFailureReason::StorageNotFound
+        );
+        assert_eq!(
+            UploadFailureReason::from_error(&storage_error(StorageErrorKind::Service)),
+            UploadFailureReason::StorageMisconfigured
+        );
+        assert_eq!(
+            UploadFailureReason::from_error(&storage_error(StorageErrorKind::Io)),
+            UploadFailureReason::Transient
+        );
+        assert_eq!(
+            UploadFailureReason::from_error(&anyhow::anyhow!("Connection reset.")),
+            UploadFailureReason::Transient
+        );
+    }
+
+    #[test]
+    fn test_upload_circuit_breaker() {
+        let circuit_breaker = UploadCircuitBreaker::new(
+            IndexingPipelineId {
+                index_id: "test-index".to_string(),
+                source_id: "test-source".to_string(),
+                node_id: "test-node".to_string(),
+                pipeline_ord: 0,
+            },
+            3,
+        );
+        let unauthorized_error = storage_error(StorageErrorKind::Unauthorized);
+        circuit_breaker.record_failure(&unauthorized_error);
+        circuit_breaker.record_failure(&unauthorized_error);
+        // A transient failure resets the count of consecutive permanent failures.
+        assert_eq!(
+            circuit_breaker.record_failure(&storage_error(StorageErrorKind::Io)),
+            UploadFailureReason::Transient
+        );
+        circuit_breaker.record_failure(&unauthorized_error);
+        circuit_breaker.record_failure(&unauthorized_error);
+        assert!(circuit_breaker.trip_opt().is_none());
+
+        assert_eq!(
+            circuit_breaker.record_failure(&unauthorized_error),
+            UploadFailureReason::Unauthorized
+        );
+        let trip = circuit_breaker.trip_opt().unwrap();
+        assert_eq!(trip.reason, UploadFailureReason::Unauthorized);
+        assert_eq!(trip.num_consecutive_failures, 3);
+        assert!(trip.message.contains("Access denied."));
+
+        circuit_breaker.record_success();
+        assert!(circuit_breaker.trip_opt().is_none());
+    }
+}
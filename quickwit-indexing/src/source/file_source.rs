@@ -17,45 +17,122 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::VecDeque;
 use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use std::{fmt, io};
 
-use anyhow::Context;
+use anyhow::{bail, Context};
+use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
 use async_trait::async_trait;
 use quickwit_actors::{ActorExitStatus, Mailbox};
 use quickwit_config::FileSourceParams;
 use quickwit_metastore::checkpoint::{PartitionId, Position, SourceCheckpoint};
+use quickwit_metastore::QuarantinedFile;
 use serde::Serialize;
+use time::OffsetDateTime;
 use tokio::fs::File;
-use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncSeekExt, BufReader};
-use tracing::info;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncSeekExt, BufReader};
+use tracing::{info, warn};
 
 use crate::actors::Indexer;
-use crate::models::RawDocBatch;
+use crate::models::{PipelineWarningKind, RawDocBatch};
 use crate::source::{Source, SourceContext, SourceExecutionContext, TypedSourceFactory};
 
 /// Number of bytes after which a new batch is cut.
 pub(crate) const BATCH_NUM_BYTES_LIMIT: u64 = 500_000u64;
 
+type FileReader = BufReader<Box<dyn AsyncRead + Send + Sync + Unpin>>;
+
 #[derive(Default, Clone, Debug, Eq, PartialEq, Serialize)]
 pub struct FileSourceCounters {
     pub previous_offset: u64,
     pub current_offset: u64,
     pub num_lines_processed: u64,
+    pub num_quarantined_files: u64,
 }
 
+/// Source reading newline-delimited documents from a file, from the files matching a glob
+/// pattern, or from stdin.
+///
+/// When reading the files matching a glob pattern, a file that cannot be opened or read is
+/// quarantined: it is recorded in the metastore, skipped, and ignored by the following runs of
+/// the source until its checkpoint is reset.
 pub struct FileSource {
-    source_id: String,
-    params: FileSourceParams,
+    ctx: Arc<SourceExecutionContext>,
     counters: FileSourceCounters,
-    reader: BufReader<Box<dyn AsyncRead + Send + Sync + Unpin>>,
+    /// Whether unreadable files are quarantined instead of failing the source.
+    quarantine_unreadable_files: bool,
+    /// Files left to read, along with the offset to resume reading them from.
+    pending_files: VecDeque<(PathBuf, u64)>,
+    /// Path of the file being read, or `None` when reading stdin.
+    current_filepath_opt: Option<PathBuf>,
+    reader_opt: Option<FileReader>,
 }
 
 impl fmt::Debug for FileSource {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "FileSource {{ source_id: {} }}", self.source_id)
+        write!(
+            f,
+            "FileSource {{ source_id: {} }}",
+            self.ctx.source_config.source_id
+        )
+    }
+}
+
+impl FileSource {
+    /// Opens the next pending file, quarantining the files that cannot be opened. Returns `false`
+    /// if there is no file left to read.
+    async fn open_next_file(&mut self, ctx: &SourceContext) -> anyhow::Result<bool> {
+        while let Some((filepath, offset)) = self.pending_files.pop_front() {
+            match open_file_at_offset(&filepath, offset).await {
+                Ok(reader) => {
+                    info!(filepath=%filepath.display(), offset=offset, "open-source-file");
+                    self.counters.previous_offset = offset;
+                    self.counters.current_offset = offset;
+                    self.current_filepath_opt = Some(filepath);
+                    self.reader_opt = Some(BufReader::new(reader));
+                    return Ok(true);
+                }
+                Err(error) if self.quarantine_unreadable_files => {
+                    self.quarantine_file(&filepath, &error, ctx).await?;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(false)
+    }
+
+    async fn quarantine_file(
+        &mut self,
+        filepath: &Path,
+        error: &anyhow::Error,
+        ctx: &SourceContext,
+    ) -> anyhow::Result<()> {
+        let quarantined_file = QuarantinedFile {
+            filepath: filepath.to_string_lossy().to_string(),
+            reason: format!("{:#}", error),
+            quarantine_timestamp: OffsetDateTime::now_utc().unix_timestamp(),
+        };
+        let warning_message = format!(
+            "Quarantined source file `{}`: {}",
+            quarantined_file.filepath, quarantined_file.reason
+        );
+        ctx.protect_future(self.ctx.metastore.quarantine_source_file(
+            &self.ctx.index_id,
+            &self.ctx.source_config.source_id,
+            quarantined_file,
+        ))
+        .await?;
+        self.ctx.warning_sink.warn(
+            PipelineWarningKind::QuarantinedSourceFile,
+            &self.name(),
+            warning_message,
+        );
+        self.counters.num_quarantined_files += 1;
+        Ok(())
     }
 }
 
@@ -66,17 +143,30 @@ impl Source for FileSource {
         batch_sink: &Mailbox<Indexer>,
         ctx: &SourceContext,
     ) -> Result<Duration, ActorExitStatus> {
+        if self.reader_opt.is_none() && !self.open_next_file(ctx).await? {
+            info!("EOF");
+            ctx.send_exit_with_success(batch_sink).await?;
+            return Err(ActorExitStatus::Success);
+        }
+        let reader = self
+            .reader_opt
+            .as_mut()
+            .expect("The reader should be open.");
         // We collect batches of documents before sending them to the indexer.
         let limit_num_bytes = self.counters.previous_offset + BATCH_NUM_BYTES_LIMIT;
         let mut reached_eof = false;
+        let mut read_error_opt: Option<io::Error> = None;
         let mut doc_batch = RawDocBatch::default();
         while self.counters.current_offset < limit_num_bytes {
             let mut doc_line = String::new();
-            let num_bytes = self
-                .reader
-                .read_line(&mut doc_line)
-                .await
-                .map_err(|io_err: io::Error| anyhow::anyhow!(io_err))?;
+            let num_bytes = match reader.read_line(&mut doc_line).await {
+                Ok(num_bytes) => num_bytes,
+                Err(io_error) if self.quarantine_unreadable_files => {
+                    read_error_opt = Some(io_error);
+                    break;
+                }
+                Err(io_error) => return Err(anyhow::anyhow!(io_error).into()),
+            };
             if num_bytes == 0 {
                 reached_eof = true;
                 break;
@@ -86,7 +176,7 @@ impl Source for FileSource {
             self.counters.num_lines_processed += 1;
         }
         if !doc_batch.docs.is_empty() {
-            if let Some(filepath) = &self.params.filepath {
+            if let Some(filepath) = &self.current_filepath_opt {
                 let filepath_str = filepath
                     .to_str()
                     .context("Path is invalid utf-8")?
@@ -104,16 +194,26 @@ impl Source for FileSource {
             self.counters.previous_offset = self.counters.current_offset;
             ctx.send_message(batch_sink, doc_batch).await?;
         }
-        if reached_eof {
-            info!("EOF");
-            ctx.send_exit_with_success(batch_sink).await?;
-            return Err(ActorExitStatus::Success);
+        if let Some(read_error) = read_error_opt {
+            self.reader_opt = None;
+            if let Some(filepath) = self.current_filepath_opt.clone() {
+                let error = anyhow::anyhow!(read_error).context(format!(
+                    "Failed to read source file `{}`.",
+                    filepath.display()
+                ));
+                self.quarantine_file(&filepath, &error, ctx).await?;
+            }
+        } else if reached_eof {
+            self.reader_opt = None;
         }
         Ok(Duration::default())
     }
 
     fn name(&self) -> String {
-        format!("FileSource{{source_id={}}}", self.source_id)
+        format!(
+            "FileSource{{source_id={}}}",
+            self.ctx.source_config.source_id
+        )
     }
 
     fn observable_state(&self) -> serde_json::Value {
@@ -121,6 +221,86 @@ impl Source for FileSource {
     }
 }
 
+/// Returns the files matching the glob pattern `pattern`, in alphabetical order. Directories and
+/// paths that cannot be accessed are ignored.
+pub(crate) fn list_files_matching_pattern(pattern: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let pattern_str = pattern.to_str().context("Path is invalid utf-8")?;
+    let filepaths = glob::glob(pattern_str)
+        .with_context(|| format!("Invalid glob pattern `{}`.", pattern_str))?
+        .flatten()
+        .filter(|filepath| filepath.is_file())
+        .collect();
+    Ok(filepaths)
+}
+
+fn checkpoint_offset(checkpoint: &SourceCheckpoint, filepath: &Path) -> anyhow::Result<u64> {
+    let partition_id = PartitionId::from(filepath.to_string_lossy().to_string());
+    match checkpoint.position_for_partition(&partition_id) {
+        Some(Position::Offset(offset_str)) => Ok(offset_str.parse::<u64>()?),
+        _ => Ok(0),
+    }
+}
+
+/// Compression of a source file, inferred from its extension.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum FileCompression {
+    Gzip,
+    Zstd,
+    Uncompressed,
+}
+
+impl FileCompression {
+    fn from_filepath(filepath: &Path) -> Self {
+        match filepath
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("gz") => FileCompression::Gzip,
+            Some("zst") => FileCompression::Zstd,
+            _ => FileCompression::Uncompressed,
+        }
+    }
+}
+
+/// Opens the file at `filepath`, decompressing it if needed, and positions the returned reader at
+/// `offset`. For compressed files, `offset` is an offset in the decompressed stream, which cannot
+/// be seeked: the decompressed bytes preceding it are read and discarded.
+async fn open_file_at_offset(
+    filepath: &Path,
+    offset: u64,
+) -> anyhow::Result<Box<dyn AsyncRead + Send + Sync + Unpin>> {
+    let mut file = File::open(filepath)
+        .await
+        .with_context(|| format!("Failed to open source file `{}`.", filepath.display()))?;
+    let mut reader: Box<dyn AsyncRead + Send + Sync + Unpin> =
+        match FileCompression::from_filepath(filepath) {
+            FileCompression::Gzip => {
+                let mut decoder = GzipDecoder::new(BufReader::new(file));
+                decoder.multiple_members(true);
+                Box::new(decoder)
+            }
+            FileCompression::Zstd => {
+                let mut decoder = ZstdDecoder::new(BufReader::new(file));
+                decoder.multiple_members(true);
+                Box::new(decoder)
+            }
+            FileCompression::Uncompressed => {
+                file.seek(SeekFrom::Start(offset)).await?;
+                return Ok(Box::new(file));
+            }
+        };
+    let num_skipped_bytes =
+        tokio::io::copy(&mut (&mut reader).take(offset), &mut tokio::io::sink()).await?;
+    if num_skipped_bytes != offset {
+        bail!(
+            "Source file `{}` is shorter than the checkpoint offset {}.",
+            filepath.display(),
+            offset
+        );
+    }
+    Ok(reader)
+}
+
 pub struct FileSourceFactory;
 
 #[async_trait]
@@ -128,40 +308,54 @@ impl TypedSourceFactory for FileSourceFactory {
     type Source = FileSource;
     type Params = FileSourceParams;
 
-    // TODO handle checkpoint for files.
     async fn typed_create_source(
         ctx: Arc<SourceExecutionContext>,
         params: FileSourceParams,
         checkpoint: SourceCheckpoint,
     ) -> anyhow::Result<FileSource> {
-        let mut offset = 0;
-        let reader: Box<dyn AsyncRead + Send + Sync + Unpin> =
-            if let Some(filepath) = &params.filepath {
-                let mut file = File::open(&filepath).await.with_context(|| {
-                    format!("Failed to open source file `{}`.", filepath.display())
-                })?;
-                let partition_id = PartitionId::from(filepath.to_string_lossy().to_string());
-                if let Some(Position::Offset(offset_str)) =
-                    checkpoint.position_for_partition(&partition_id).cloned()
-                {
-                    offset = offset_str.parse::<u64>()?;
-                    file.seek(SeekFrom::Start(offset)).await?;
+        let mut file_source = FileSource {
+            ctx: ctx.clone(),
+            counters: FileSourceCounters::default(),
+            quarantine_unreadable_files: params.is_glob_pattern(),
+            pending_files: VecDeque::new(),
+            current_filepath_opt: None,
+            reader_opt: None,
+        };
+        match &params.filepath {
+            Some(pattern) if params.is_glob_pattern() => {
+                let index_metadata = ctx.metastore.index_metadata(&ctx.index_id).await?;
+                let quarantined_files = index_metadata
+                    .quarantined_files
+                    .get(&ctx.source_config.source_id)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                for filepath in list_files_matching_pattern(pattern)? {
+                    let filepath_str = filepath.to_string_lossy();
+                    if quarantined_files
+                        .iter()
+                        .any(|quarantined_file| quarantined_file.filepath == filepath_str)
+                    {
+                        warn!(filepath=%filepath_str, "skip-quarantined-source-file");
+                        continue;
+                    }
+                    let offset = checkpoint_offset(&checkpoint, &filepath)?;
+                    file_source.pending_files.push_back((filepath, offset));
                 }
-                Box::new(file)
-            } else {
+            }
+            Some(filepath) => {
+                let offset = checkpoint_offset(&checkpoint, filepath)?;
+                let reader = open_file_at_offset(filepath, offset).await?;
+                file_source.counters.previous_offset = offset;
+                file_source.counters.current_offset = offset;
+                file_source.current_filepath_opt = Some(filepath.clone());
+                file_source.reader_opt = Some(BufReader::new(reader));
+            }
+            None => {
                 // We cannot use the checkpoint.
-                Box::new(tokio::io::stdin())
-            };
-        let file_source = FileSource {
-            source_id: ctx.source_config.source_id.clone(),
-            counters: FileSourceCounters {
-                previous_offset: offset,
-                current_offset: offset,
-                num_lines_processed: 0,
-            },
-            reader: BufReader::new(reader),
-            params,
-        };
+                let reader: Box<dyn AsyncRead + Send + Sync + Unpin> = Box::new(tokio::io::stdin());
+                file_source.reader_opt = Some(BufReader::new(reader));
+            }
+        }
         Ok(file_source)
     }
 }
@@ -173,7 +367,7 @@ mod tests {
     use quickwit_actors::{create_test_mailbox, Command, Universe};
     use quickwit_config::{SourceConfig, SourceParams};
     use quickwit_metastore::checkpoint::{SourceCheckpoint, SourceCheckpointDelta};
-    use quickwit_metastore::metastore_for_test;
+    use quickwit_metastore::{metastore_for_test, IndexMetadata};
 
     use super::*;
     use crate::source::SourceActor;
@@ -192,6 +386,10 @@ mod tests {
                 SourceConfig {
                     source_id: "test-file-source".to_string(),
                     num_pipelines: 1,
+                    profile: None,
+                    rate_limit: None,
+                    transforms: Vec::new(),
+                    strict_ordering: false,
                     source_params: SourceParams::File(params.clone()),
                 },
             ),
@@ -202,6 +400,7 @@ mod tests {
         let file_source_actor = SourceActor {
             source: Box::new(file_source),
             indexer_mailbox,
+            rate_limiter_opt: None,
         };
         let (_file_source_mailbox, file_source_handle) =
             universe.spawn_actor(file_source_actor).spawn();
@@ -212,7 +411,8 @@ mod tests {
             serde_json::json!({
                 "previous_offset": 1030u64,
                 "current_offset": 1030u64,
-                "num_lines_processed": 4u32
+                "num_lines_processed": 4u32,
+                "num_quarantined_files": 0u64
             })
         );
         let batch = indexer_inbox.drain_for_test();
@@ -253,6 +453,10 @@ mod tests {
                 SourceConfig {
                     source_id: "test-file-source".to_string(),
                     num_pipelines: 1,
+                    profile: None,
+                    rate_limit: None,
+                    transforms: Vec::new(),
+                    strict_ordering: false,
                     source_params: SourceParams::File(params.clone()),
                 },
             ),
@@ -263,6 +467,7 @@ mod tests {
         let file_source_actor = SourceActor {
             source: Box::new(source),
             indexer_mailbox: mailbox,
+            rate_limiter_opt: None,
         };
         let (_file_source_mailbox, file_source_handle) =
             universe.spawn_actor(file_source_actor).spawn();
@@ -273,7 +478,8 @@ mod tests {
             serde_json::json!({
                 "previous_offset": 700_000u64,
                 "current_offset": 700_000u64,
-                "num_lines_processed": 20_000u64
+                "num_lines_processed": 20_000u64,
+                "num_quarantined_files": 0u64
             })
         );
         let indexer_msgs = inbox.drain_for_test();
@@ -337,6 +543,10 @@ mod tests {
                 SourceConfig {
                     source_id: "test-file-source".to_string(),
                     num_pipelines: 1,
+                    profile: None,
+                    rate_limit: None,
+                    transforms: Vec::new(),
+                    strict_ordering: false,
                     source_params: SourceParams::File(params.clone()),
                 },
             ),
@@ -347,6 +557,7 @@ mod tests {
         let file_source_actor = SourceActor {
             source: Box::new(source),
             indexer_mailbox: mailbox,
+            rate_limiter_opt: None,
         };
         let (_file_source_mailbox, file_source_handle) =
             universe.spawn_actor(file_source_actor).spawn();
@@ -357,11 +568,214 @@ mod tests {
             serde_json::json!({
                 "previous_offset": 290u64,
                 "current_offset": 290u64,
-                "num_lines_processed": 98u64
+                "num_lines_processed": 98u64,
+                "num_quarantined_files": 0u64
             })
         );
         let indexer_messages: Vec<RawDocBatch> = inbox.drain_for_test_typed();
         assert!(indexer_messages[0].docs[0].starts_with("2\n"));
         Ok(())
     }
+
+    async fn compress(data: &[u8], compression: FileCompression) -> anyhow::Result<Vec<u8>> {
+        use async_compression::tokio::write::{GzipEncoder, ZstdEncoder};
+        use tokio::io::AsyncWriteExt;
+
+        let compressed_data = match compression {
+            FileCompression::Gzip => {
+                let mut encoder = GzipEncoder::new(Vec::new());
+                encoder.write_all(data).await?;
+                encoder.shutdown().await?;
+                encoder.into_inner()
+            }
+            FileCompression::Zstd => {
+                let mut encoder = ZstdEncoder::new(Vec::new());
+                encoder.write_all(data).await?;
+                encoder.shutdown().await?;
+                encoder.into_inner()
+            }
+            FileCompression::Uncompressed => data.to_vec(),
+        };
+        Ok(compressed_data)
+    }
+
+    async fn test_compressed_file_source_resume_from_checkpoint_aux(
+        compression: FileCompression,
+        extension: &str,
+    ) -> anyhow::Result<()> {
+        let universe = Universe::new();
+        let (mailbox, inbox) = create_test_mailbox();
+        let temp_dir = tempfile::tempdir()?;
+        let filepath = temp_dir.path().join(format!("docs.json.{extension}"));
+        assert_eq!(FileCompression::from_filepath(&filepath), compression);
+
+        let data: String = (0..100).map(|i| format!("{}\n", i)).collect();
+        std::fs::write(&filepath, compress(data.as_bytes(), compression).await?)?;
+
+        let params = FileSourceParams::file(&filepath);
+        let mut checkpoint = SourceCheckpoint::default();
+        let partition_id = PartitionId::from(filepath.to_string_lossy().to_string());
+        let checkpoint_delta = SourceCheckpointDelta::from_partition_delta(
+            partition_id,
+            Position::from(0u64),
+            Position::from(4u64),
+        );
+        checkpoint.try_apply_delta(checkpoint_delta)?;
+
+        let metastore = metastore_for_test();
+        let source = FileSourceFactory::typed_create_source(
+            SourceExecutionContext::for_test(
+                metastore,
+                "test-index",
+                SourceConfig {
+                    source_id: "test-file-source".to_string(),
+                    num_pipelines: 1,
+                    profile: None,
+                    rate_limit: None,
+                    transforms: Vec::new(),
+                    strict_ordering: false,
+                    source_params: SourceParams::File(params.clone()),
+                },
+            ),
+            params,
+            checkpoint,
+        )
+        .await?;
+        let file_source_actor = SourceActor {
+            source: Box::new(source),
+            indexer_mailbox: mailbox,
+            rate_limiter_opt: None,
+        };
+        let (_file_source_mailbox, file_source_handle) =
+            universe.spawn_actor(file_source_actor).spawn();
+        let (actor_termination, counters) = file_source_handle.join().await;
+        assert!(actor_termination.is_success());
+        assert_eq!(
+            counters,
+            serde_json::json!({
+                "previous_offset": 290u64,
+                "current_offset": 290u64,
+                "num_lines_processed": 98u64,
+                "num_quarantined_files": 0u64
+            })
+        );
+        let indexer_messages: Vec<RawDocBatch> = inbox.drain_for_test_typed();
+        assert_eq!(indexer_messages[0].docs[0], "2\n");
+        assert_eq!(
+            &extract_position_delta(&indexer_messages[0].checkpoint_delta).unwrap(),
+            "00000000000000000004..00000000000000000290"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_gzip_file_source_resume_from_checkpoint() -> anyhow::Result<()> {
+        test_compressed_file_source_resume_from_checkpoint_aux(FileCompression::Gzip, "gz").await
+    }
+
+    #[tokio::test]
+    async fn test_zstd_file_source_resume_from_checkpoint() -> anyhow::Result<()> {
+        test_compressed_file_source_resume_from_checkpoint_aux(FileCompression::Zstd, "zst").await
+    }
+
+    #[tokio::test]
+    async fn test_compressed_file_source_checkpoint_past_end_of_file() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let filepath = temp_dir.path().join("docs.json.gz");
+        std::fs::write(&filepath, compress(b"0\n1\n", FileCompression::Gzip).await?)?;
+        let error = open_file_at_offset(&filepath, 10)
+            .await
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("is shorter than the checkpoint offset 10"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_file_source_glob_pattern_quarantines_unreadable_files() -> anyhow::Result<()> {
+        let universe = Universe::new();
+        let (mailbox, inbox) = create_test_mailbox();
+        let temp_dir = tempfile::tempdir()?;
+        std::fs::write(temp_dir.path().join("docs-1.json"), "0\n1\n2\n")?;
+        let corrupted_filepath = temp_dir.path().join("docs-2.json.gz");
+        std::fs::write(&corrupted_filepath, "not a gzip stream")?;
+        std::fs::write(temp_dir.path().join("docs-3.json"), "3\n4\n")?;
+        std::fs::create_dir(temp_dir.path().join("docs-4.json"))?;
+
+        let params = FileSourceParams::file(temp_dir.path().join("docs-*.json*"));
+        assert!(params.is_glob_pattern());
+        let source_config = SourceConfig {
+            source_id: "test-file-source".to_string(),
+            num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::File(params.clone()),
+        };
+        let metastore = metastore_for_test();
+        metastore
+            .create_index(IndexMetadata::for_test(
+                "test-index",
+                "ram:///indexes/test-index",
+            ))
+            .await?;
+        let ctx = SourceExecutionContext::for_test(
+            metastore.clone(),
+            "test-index",
+            source_config.clone(),
+        );
+        let source = FileSourceFactory::typed_create_source(
+            ctx.clone(),
+            params.clone(),
+            SourceCheckpoint::default(),
+        )
+        .await?;
+        assert_eq!(source.pending_files.len(), 3);
+        let file_source_actor = SourceActor {
+            source: Box::new(source),
+            indexer_mailbox: mailbox,
+            rate_limiter_opt: None,
+        };
+        let (_file_source_mailbox, file_source_handle) =
+            universe.spawn_actor(file_source_actor).spawn();
+        let (actor_termination, counters) = file_source_handle.join().await;
+        assert!(actor_termination.is_success());
+        assert_eq!(counters["num_lines_processed"], 5);
+        assert_eq!(counters["num_quarantined_files"], 1);
+
+        let indexer_messages: Vec<RawDocBatch> = inbox.drain_for_test_typed();
+        let docs: Vec<&str> = indexer_messages
+            .iter()
+            .flat_map(|batch| batch.docs.iter().map(String::as_str))
+            .collect();
+        assert_eq!(docs, ["0\n", "1\n", "2\n", "3\n", "4\n"]);
+
+        let warnings = ctx.warning_sink.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, PipelineWarningKind::QuarantinedSourceFile);
+
+        let index_metadata = metastore.index_metadata("test-index").await?;
+        let quarantined_files = &index_metadata.quarantined_files["test-file-source"];
+        assert_eq!(quarantined_files.len(), 1);
+        assert_eq!(
+            quarantined_files[0].filepath,
+            corrupted_filepath.to_string_lossy()
+        );
+        assert!(quarantined_files[0]
+            .reason
+            .contains("Failed to read source file"));
+
+        // The quarantined file is skipped by the following runs of the source.
+        let source =
+            FileSourceFactory::typed_create_source(ctx, params, SourceCheckpoint::default())
+                .await?;
+        assert_eq!(source.pending_files.len(), 2);
+        assert!(source
+            .pending_files
+            .iter()
+            .all(|(filepath, _)| filepath != &corrupted_filepath));
+        Ok(())
+    }
 }
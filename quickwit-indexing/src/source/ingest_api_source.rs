@@ -269,6 +269,7 @@ mod tests {
         let ingest_api_source_actor = SourceActor {
             source: Box::new(ingest_api_source),
             indexer_mailbox,
+            rate_limiter_opt: None,
         };
         let (_ingest_api_source_mailbox, ingest_api_source_handle) =
             universe.spawn_actor(ingest_api_source_actor).spawn();
@@ -315,6 +316,7 @@ mod tests {
         let ingest_api_source_actor = SourceActor {
             source: Box::new(ingest_api_source),
             indexer_mailbox,
+            rate_limiter_opt: None,
         };
         let (_ingest_api_source_mailbox, ingest_api_source_handle) =
             universe.spawn_actor(ingest_api_source_actor).spawn();
@@ -363,6 +365,7 @@ mod tests {
         let ingest_api_source_actor = SourceActor {
             source: Box::new(ingest_api_source),
             indexer_mailbox,
+            rate_limiter_opt: None,
         };
         let (_ingest_api_source_mailbox, ingest_api_source_handle) =
             universe.spawn_actor(ingest_api_source_actor).spawn();
@@ -419,6 +422,7 @@ mod tests {
         let ingest_api_source_actor = SourceActor {
             source: Box::new(ingest_api_source),
             indexer_mailbox,
+            rate_limiter_opt: None,
         };
         let (_ingest_api_source_mailbox, ingest_api_source_handle) =
             universe.spawn_actor(ingest_api_source_actor).spawn();
@@ -17,7 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -27,7 +27,7 @@ use async_trait::async_trait;
 use itertools::Itertools;
 use oneshot;
 use quickwit_actors::{ActorExitStatus, Mailbox};
-use quickwit_config::KafkaSourceParams;
+use quickwit_config::{KafkaBackfillWindow, KafkaSourceParams};
 use quickwit_metastore::checkpoint::{
     PartitionId, Position, SourceCheckpoint, SourceCheckpointDelta,
 };
@@ -46,7 +46,7 @@ use tokio::time;
 use tracing::{debug, info, warn};
 
 use crate::actors::Indexer;
-use crate::models::{NewPublishLock, PublishLock, RawDocBatch};
+use crate::models::{NewPublishLock, PartitionBounds, PublishLock, RawDocBatch};
 use crate::source::{Source, SourceContext, SourceExecutionContext, TypedSourceFactory};
 
 /// Number of bytes after which we cut a new batch.
@@ -99,6 +99,7 @@ struct KafkaMessage {
     payload_len: u64,
     partition: i32,
     offset: i64,
+    timestamp_millis_opt: Option<i64>,
 }
 
 impl From<BorrowedMessage<'_>> for KafkaMessage {
@@ -108,6 +109,7 @@ impl From<BorrowedMessage<'_>> for KafkaMessage {
             payload_len: message.payload_len() as u64,
             partition: message.partition(),
             offset: message.offset() as i64,
+            timestamp_millis_opt: message.timestamp().to_millis(),
         }
     }
 }
@@ -205,8 +207,10 @@ pub struct KafkaSourceState {
     pub assigned_partitions: HashMap<i32, PartitionId>,
     /// Offset for each partition of the last message received.
     pub current_positions: HashMap<i32, Position>,
-    /// Number of inactive partitions, i.e., that have reached EOF.
-    pub num_inactive_partitions: usize,
+    /// Inactive partitions, i.e., that have reached EOF or the end of the backfill window.
+    pub inactive_partitions: HashSet<i32>,
+    /// Offset for each partition of the first message past the end of the backfill window.
+    pub window_end_offsets: HashMap<i32, i64>,
     /// Number of bytes processed by the source.
     pub num_bytes_processed: u64,
     /// Number of messages processed by the source (including invalid messages).
@@ -223,6 +227,7 @@ pub struct KafkaSource {
     topic: String,
     state: KafkaSourceState,
     backfill_mode_enabled: bool,
+    backfill_window_opt: Option<KafkaBackfillWindow>,
     events_rx: mpsc::Receiver<KafkaEvent>,
     consumer: Arc<RdKafkaConsumer>,
     poll_loop_jh: JoinHandle<()>,
@@ -248,6 +253,7 @@ impl KafkaSource {
     ) -> anyhow::Result<Self> {
         let topic = params.topic.clone();
         let backfill_mode_enabled = params.enable_backfill_mode;
+        let backfill_window_opt = params.backfill_window;
 
         let (events_tx, events_rx) = mpsc::channel(100);
         let consumer = create_consumer(&ctx.source_config.source_id, params, events_tx.clone())?;
@@ -267,6 +273,7 @@ impl KafkaSource {
             source_id=%ctx.source_config.source_id,
             topic=%topic,
             rebalance_protocol=%rebalance_protocol_str,
+            backfill_window=?backfill_window_opt,
             "Starting Kafka source."
         );
         let state = KafkaSourceState {
@@ -277,6 +284,7 @@ impl KafkaSource {
             topic,
             state,
             backfill_mode_enabled,
+            backfill_window_opt,
             events_rx,
             consumer,
             poll_loop_jh,
@@ -294,13 +302,23 @@ impl KafkaSource {
             payload_len,
             partition,
             offset,
-            ..
+            timestamp_millis_opt,
         } = message;
 
-        if let Some(doc) = doc_opt {
-            batch.push(doc, payload_len);
-        } else {
-            self.state.num_invalid_messages += 1;
+        if let Some(&window_end_offset) = self.state.window_end_offsets.get(&partition) {
+            if offset >= window_end_offset {
+                self.process_window_end(partition);
+                return Ok(());
+            }
+        }
+        // The messages out of the backfill window are skipped, but their position is still
+        // recorded in the checkpoint.
+        if self.is_within_backfill_window(timestamp_millis_opt) {
+            if let Some(doc) = doc_opt {
+                batch.push(doc, payload_len);
+            } else {
+                self.state.num_invalid_messages += 1;
+            }
         }
         self.state.num_bytes_processed += payload_len;
         self.state.num_messages_processed += 1;
@@ -354,7 +372,21 @@ impl KafkaSource {
 
         self.state.assigned_partitions.clear();
         self.state.current_positions.clear();
-        self.state.num_inactive_partitions = 0;
+        self.state.inactive_partitions.clear();
+        self.state.window_end_offsets.clear();
+
+        let window_start_timestamp_opt = self
+            .backfill_window_opt
+            .and_then(|backfill_window| backfill_window.start_timestamp_millis);
+        let window_end_timestamp_opt = self
+            .backfill_window_opt
+            .and_then(|backfill_window| backfill_window.end_timestamp_millis);
+        let window_start_offsets = self
+            .lookup_offsets_for_timestamp(ctx, partitions, window_start_timestamp_opt)
+            .await?;
+        let window_end_offsets = self
+            .lookup_offsets_for_timestamp(ctx, partitions, window_end_timestamp_opt)
+            .await?;
 
         let mut next_offsets: Vec<(i32, Offset)> = Vec::with_capacity(partitions.len());
 
@@ -364,13 +396,40 @@ impl KafkaSource {
                 .position_for_partition(&partition_id)
                 .cloned()
                 .unwrap_or(Position::Beginning);
-            let next_offset = match &current_position {
+            let mut next_offset = match &current_position {
                 Position::Beginning => Offset::Beginning,
                 Position::Offset(offset_str) => {
                     let offset: i64 = offset_str.parse().expect("Failed to parse checkpoint position to i64. This should never happen! Please, report on https://github.com/quickwit-oss/quickwit/issues.");
                     Offset::Offset(offset + 1)
                 }
             };
+            // Instead of reading the messages preceding the backfill window, we start from the
+            // first message of the window. The skipped messages are recorded in the checkpoint
+            // delta of the first message read.
+            match window_start_offsets.get(&partition) {
+                Some(Some(window_start_offset)) => {
+                    next_offset = match next_offset {
+                        Offset::Offset(offset) => Offset::Offset(offset.max(*window_start_offset)),
+                        _ => Offset::Offset(*window_start_offset),
+                    };
+                }
+                Some(None) => {
+                    // No message of the partition is recent enough.
+                    self.state.inactive_partitions.insert(partition);
+                    next_offset = Offset::End;
+                }
+                None => {}
+            }
+            if let Some(Some(window_end_offset)) = window_end_offsets.get(&partition) {
+                self.state
+                    .window_end_offsets
+                    .insert(partition, *window_end_offset);
+                if let Offset::Offset(offset) = next_offset {
+                    if offset >= *window_end_offset {
+                        self.state.inactive_partitions.insert(partition);
+                    }
+                }
+            }
             self.state
                 .assigned_partitions
                 .insert(partition, partition_id);
@@ -406,21 +465,111 @@ impl KafkaSource {
     }
 
     fn process_partition_eof(&mut self, partition: i32) {
-        self.state.num_inactive_partitions += 1;
+        self.state.inactive_partitions.insert(partition);
 
         info!(
             topic=%self.topic,
             partition=%partition,
-            num_inactive_partitions=?self.state.num_inactive_partitions,
+            num_inactive_partitions=?self.state.inactive_partitions.len(),
             "Reached end of partition."
         );
     }
 
+    /// Pauses the consumption of a partition that reached the end of the backfill window.
+    fn process_window_end(&mut self, partition: i32) {
+        if !self.state.inactive_partitions.insert(partition) {
+            return;
+        }
+        info!(
+            topic=%self.topic,
+            partition=%partition,
+            num_inactive_partitions=?self.state.inactive_partitions.len(),
+            "Reached end of backfill window."
+        );
+        let mut partitions = TopicPartitionList::new();
+        partitions.add_partition(&self.topic, partition);
+        if let Err(error) = self.consumer.pause(&partitions) {
+            warn!(
+                topic=%self.topic,
+                partition=%partition,
+                error=?error,
+                "Failed to pause partition."
+            );
+        }
+    }
+
+    fn is_within_backfill_window(&self, timestamp_millis_opt: Option<i64>) -> bool {
+        let (backfill_window, timestamp_millis) =
+            match (self.backfill_window_opt, timestamp_millis_opt) {
+                (Some(backfill_window), Some(timestamp_millis)) => {
+                    (backfill_window, timestamp_millis)
+                }
+                _ => return true,
+            };
+        backfill_window
+            .start_timestamp_millis
+            .map(|start_timestamp_millis| timestamp_millis >= start_timestamp_millis)
+            .unwrap_or(true)
+            && backfill_window
+                .end_timestamp_millis
+                .map(|end_timestamp_millis| timestamp_millis < end_timestamp_millis)
+                .unwrap_or(true)
+    }
+
+    /// Looks up, for each partition, the offset of the first message whose timestamp is greater
+    /// than or equal to `timestamp_millis`, or `None` if there is no such message. Returns an
+    /// empty map if `timestamp_millis_opt` is `None`.
+    async fn lookup_offsets_for_timestamp(
+        &self,
+        ctx: &SourceContext,
+        partitions: &[i32],
+        timestamp_millis_opt: Option<i64>,
+    ) -> anyhow::Result<HashMap<i32, Option<i64>>> {
+        let timestamp_millis = match timestamp_millis_opt {
+            Some(timestamp_millis) => timestamp_millis,
+            None => return Ok(HashMap::new()),
+        };
+        let consumer = self.consumer.clone();
+        let topic = self.topic.clone();
+        let partitions = partitions.to_vec();
+        let lookup_future = spawn_blocking(move || {
+            let mut timestamps = TopicPartitionList::with_capacity(partitions.len());
+            for partition in partitions {
+                timestamps.add_partition_offset(
+                    &topic,
+                    partition,
+                    Offset::Offset(timestamp_millis),
+                )?;
+            }
+            let offsets = consumer
+                .offsets_for_times(timestamps, Duration::from_secs(5))
+                .with_context(|| {
+                    format!(
+                        "Failed to look up the offsets of timestamp {} in topic `{}`.",
+                        timestamp_millis, topic
+                    )
+                })?;
+            let offsets_per_partition: HashMap<i32, Option<i64>> = offsets
+                .elements()
+                .iter()
+                .map(|element| {
+                    let offset_opt = match element.offset() {
+                        Offset::Offset(offset) => Some(offset),
+                        _ => None,
+                    };
+                    (element.partition(), offset_opt)
+                })
+                .collect();
+            anyhow::Ok(offsets_per_partition)
+        });
+        ctx.protect_future(lookup_future).await?
+    }
+
     fn should_exit(&self) -> bool {
         self.backfill_mode_enabled
             // This check ensures that we don't shutdown the source before the first partition assignment.
-            && self.state.num_inactive_partitions > 0
-            && self.state.num_inactive_partitions == self.state.assigned_partitions.len()
+            && !self.state.inactive_partitions.is_empty()
+            && self.state.inactive_partitions.len() == self.state.assigned_partitions.len()
     }
 }
 
@@ -549,7 +698,7 @@ impl Source for KafkaSource {
             "topic": self.topic,
             "assigned_partitions": assigned_partitions,
             "current_positions": current_positions,
-            "num_inactive_partitions": self.state.num_inactive_partitions,
+            "num_inactive_partitions": self.state.inactive_partitions.len(),
             "num_bytes_processed": self.state.num_bytes_processed,
             "num_messages_processed": self.state.num_messages_processed,
             "num_invalid_messages": self.state.num_invalid_messages,
@@ -624,6 +773,51 @@ pub(super) async fn check_connectivity(params: KafkaSourceParams) -> anyhow::Res
     Ok(())
 }
 
+/// Fetches the offsets of the oldest and next records of each partition of the topic.
+pub(super) async fn fetch_partition_bounds(
+    params: KafkaSourceParams,
+) -> anyhow::Result<BTreeMap<PartitionId, PartitionBounds>> {
+    let mut client_config = parse_client_params(params.client_params)?;
+
+    let consumer: BaseConsumer<DefaultConsumerContext> = client_config
+        .set("group.id", "quickwit-data-loss-detector".to_string())
+        .set_log_level(RDKafkaLogLevel::Error)
+        .create()?;
+
+    let topic = params.topic;
+    let timeout = Timeout::After(Duration::from_secs(5));
+    spawn_blocking(move || {
+        let cluster_metadata = consumer
+            .fetch_metadata(Some(&topic), timeout)
+            .with_context(|| format!("Failed to fetch metadata for topic `{}`.", topic))?;
+        let topic_metadata = cluster_metadata
+            .topics()
+            .first()
+            .ok_or_else(|| anyhow!("Topic `{}` does not exist.", topic))?;
+        let mut partition_bounds = BTreeMap::new();
+        for partition_metadata in topic_metadata.partitions() {
+            let partition = partition_metadata.id();
+            let (low_watermark, high_watermark) = consumer
+                .fetch_watermarks(&topic, partition, timeout)
+                .with_context(|| {
+                    format!(
+                        "Failed to fetch watermarks of partition {} of topic `{}`.",
+                        partition, topic
+                    )
+                })?;
+            partition_bounds.insert(
+                PartitionId::from(partition as i64),
+                PartitionBounds {
+                    earliest_offset: low_watermark.max(0) as u64,
+                    next_offset: high_watermark.max(0) as u64,
+                },
+            );
+        }
+        Ok(partition_bounds)
+    })
+    .await?
+}
+
 /// Creates a new `KafkaSourceConsumer`.
 fn create_consumer(
     source_id: &str,
@@ -672,7 +866,9 @@ fn parse_client_log_level(client_log_level: Option<String>) -> anyhow::Result<RD
     Ok(log_level)
 }
 
-fn parse_client_params(client_params: serde_json::Value) -> anyhow::Result<ClientConfig> {
+pub(crate) fn parse_client_params(
+    client_params: serde_json::Value,
+) -> anyhow::Result<ClientConfig> {
     let params = if let serde_json::Value::Object(params) = client_params {
         params
     } else {
@@ -838,6 +1034,10 @@ mod kafka_broker_tests {
         let source_config = SourceConfig {
             source_id: source_id.clone(),
             num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
             source_params: SourceParams::Kafka(KafkaSourceParams {
                 topic: topic.to_string(),
                 client_log_level: None,
@@ -845,6 +1045,7 @@ mod kafka_broker_tests {
                     "bootstrap.servers": "localhost:9092",
                 }),
                 enable_backfill_mode: true,
+                backfill_window: None,
             }),
         };
         (source_id, source_config)
@@ -944,6 +1145,7 @@ mod kafka_broker_tests {
             payload_len: 7,
             partition: 1,
             offset: 0,
+            timestamp_millis_opt: None,
         };
         kafka_source
             .process_message(message, &mut batch)
@@ -965,6 +1167,7 @@ mod kafka_broker_tests {
             payload_len: 8,
             partition: 1,
             offset: 1,
+            timestamp_millis_opt: None,
         };
         kafka_source
             .process_message(message, &mut batch)
@@ -987,6 +1190,7 @@ mod kafka_broker_tests {
             payload_len: 8,
             partition: 2,
             offset: 42,
+            timestamp_millis_opt: None,
         };
         kafka_source
             .process_message(message, &mut batch)
@@ -1019,6 +1223,7 @@ mod kafka_broker_tests {
             payload_len: 8,
             partition: 3,
             offset: 42,
+            timestamp_millis_opt: None,
         };
         kafka_source
             .process_message(message, &mut batch)
@@ -1048,7 +1253,7 @@ mod kafka_broker_tests {
         let mut kafka_source = KafkaSource::try_new(ctx, params, ignored_checkpoint)
             .await
             .unwrap();
-        kafka_source.state.num_inactive_partitions = 1;
+        kafka_source.state.inactive_partitions = HashSet::from([1]);
 
         let universe = Universe::new();
         let (source_mailbox, _source_inbox) = create_test_mailbox();
@@ -1062,7 +1267,7 @@ mod kafka_broker_tests {
             .await
             .unwrap();
 
-        assert_eq!(kafka_source.state.num_inactive_partitions, 0);
+        assert!(kafka_source.state.inactive_partitions.is_empty());
 
         let expected_assigned_partitions =
             HashMap::from_iter([(1, PartitionId::from(1u64)), (2, PartitionId::from(2u64))]);
@@ -1160,7 +1365,8 @@ mod kafka_broker_tests {
         assert!(!kafka_source.should_exit());
 
         kafka_source.process_partition_eof(1);
-        assert_eq!(kafka_source.state.num_inactive_partitions, 1);
+        kafka_source.process_partition_eof(1);
+        assert_eq!(kafka_source.state.inactive_partitions, HashSet::from([1]));
         assert!(kafka_source.should_exit());
 
         kafka_source.backfill_mode_enabled = false;
@@ -1197,6 +1403,7 @@ mod kafka_broker_tests {
             let source_actor = SourceActor {
                 source,
                 indexer_mailbox: indexer_mailbox.clone(),
+                rate_limiter_opt: None,
             };
             let (_source_mailbox, source_handle) = universe.spawn_actor(source_actor).spawn();
             let (exit_status, exit_state) = source_handle.join().await;
@@ -1258,6 +1465,7 @@ mod kafka_broker_tests {
             let source_actor = SourceActor {
                 source,
                 indexer_mailbox: indexer_mailbox.clone(),
+                rate_limiter_opt: None,
             };
             let (_source_mailbox, source_handle) = universe.spawn_actor(source_actor).spawn();
             let (exit_status, exit_state) = source_handle.join().await;
@@ -1329,6 +1537,7 @@ mod kafka_broker_tests {
             let source_actor = SourceActor {
                 source,
                 indexer_mailbox: indexer_mailbox.clone(),
+                rate_limiter_opt: None,
             };
             let (_source_mailbox, source_handle) = universe.spawn_actor(source_actor).spawn();
             let (exit_status, exit_state) = source_handle.join().await;
@@ -1371,6 +1580,70 @@ mod kafka_broker_tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_kafka_source_backfill_window() -> anyhow::Result<()> {
+        let universe = Universe::new();
+        let admin_client = create_admin_client()?;
+        let topic = append_random_suffix("test-kafka-source--backfill-window--topic");
+        create_topic(&admin_client, &topic, 1).await?;
+
+        for (timestamp_idx, timestamp) in [1_000, 2_000, 3_000].into_iter().enumerate() {
+            populate_topic(
+                &topic,
+                3,
+                &key_fn,
+                &|message_id| format!("Message #{:0>3}", timestamp_idx * 100 + message_id as usize),
+                Some(0),
+                Some(timestamp),
+            )
+            .await?;
+        }
+        let metastore = metastore_for_test();
+        let index_id = append_random_suffix("test-kafka-source--backfill-window--index");
+        let (source_id, mut source_config) = get_source_config(&topic);
+        if let SourceParams::Kafka(params) = &mut source_config.source_params {
+            params.backfill_window = Some(KafkaBackfillWindow {
+                start_timestamp_millis: Some(2_000),
+                end_timestamp_millis: Some(3_000),
+            });
+        }
+        let source = quickwit_supported_sources()
+            .load_source(
+                SourceExecutionContext::for_test(metastore.clone(), &index_id, source_config),
+                SourceCheckpoint::default(),
+            )
+            .await?;
+        setup_index(metastore.clone(), &index_id, &source_id, &[]).await;
+
+        let (indexer_mailbox, indexer_inbox) = create_test_mailbox();
+        let source_actor = SourceActor {
+            source,
+            indexer_mailbox: indexer_mailbox.clone(),
+            rate_limiter_opt: None,
+        };
+        let (_source_mailbox, source_handle) = universe.spawn_actor(source_actor).spawn();
+        let (exit_status, exit_state) = source_handle.join().await;
+        assert!(exit_status.is_success());
+
+        let messages: Vec<RawDocBatch> = indexer_inbox.drain_for_test_typed();
+        let batch = merge_doc_batches(messages)?;
+        let mut docs = batch.docs;
+        docs.sort();
+        assert_eq!(docs, vec!["Message #100", "Message #101", "Message #102"]);
+
+        // The messages preceding the window are skipped without being read.
+        let mut expected_checkpoint_delta = SourceCheckpointDelta::default();
+        expected_checkpoint_delta.record_partition_delta(
+            PartitionId::from(0u64),
+            Position::Beginning,
+            Position::from(5u64),
+        )?;
+        assert_eq!(batch.checkpoint_delta, expected_checkpoint_delta);
+        assert_eq!(exit_state["num_messages_processed"], 3);
+        assert_eq!(exit_state["num_inactive_partitions"], 1);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_kafka_connectivity() {
         let bootstrap_servers = "localhost:9092".to_string();
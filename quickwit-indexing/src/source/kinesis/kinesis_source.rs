@@ -41,6 +41,7 @@ use tracing::{info, warn};
 
 use super::api::list_shards;
 use super::shard_consumer::{ShardConsumer, ShardConsumerHandle, ShardConsumerMessage};
+use super::shard_lineage::ShardLineage;
 use crate::models::RawDocBatch;
 use crate::source::kinesis::helpers::get_kinesis_client;
 use crate::source::{Indexer, Source, SourceContext, SourceExecutionContext, TypedSourceFactory};
@@ -77,6 +78,8 @@ struct ShardConsumerState {
 pub struct KinesisSourceState {
     /// Pool of [`ShardConsumer`] managed by the source.
     shard_consumers: HashMap<ShardId, ShardConsumerState>,
+    /// Lineage of the shards of the stream, used to read child shards only after their parents.
+    shard_lineage: ShardLineage,
     /// Number of bytes processed by the source.
     pub num_bytes_processed: u64,
     /// Number of records processed by the source (including invalid messages).
@@ -190,12 +193,15 @@ impl Source for KinesisSource {
                 None,
             ))
             .await?;
-        for shard in shards {
-            self.spawn_shard_consumer(ctx, shard.shard_id);
+        let (shard_lineage, shard_ids) = ShardLineage::new(shards, &self.checkpoint);
+        self.state.shard_lineage = shard_lineage;
+        for shard_id in shard_ids {
+            self.spawn_shard_consumer(ctx, shard_id);
         }
         info!(
             stream_name = %self.stream_name,
             assigned_shards = %self.state.shard_consumers.keys().sorted().join(", "),
+            pending_shards = %self.state.shard_lineage.pending_shards().join(", "),
             "Starting Kinesis source."
         );
         Ok(())
@@ -218,10 +224,8 @@ impl Source for KinesisSource {
                 message_opt = self.shard_consumers_rx.recv() => {
                     // The source always carries a sender for this channel.
                     match message_opt.expect("Channel unexpectedly closed.") {
-                        ShardConsumerMessage::ChildShards(shard_ids) => {
-                            for shard_id in shard_ids {
-                                self.spawn_shard_consumer(ctx, shard_id);
-                            }
+                        ShardConsumerMessage::ChildShards { shard_id, child_shards } => {
+                            self.state.shard_lineage.add_child_shards(&shard_id, child_shards);
                         }
                         ShardConsumerMessage::Records { shard_id, records, lag_millis } => {
                             let num_records = records.len();
@@ -286,8 +290,32 @@ impl Source for KinesisSource {
                                 num_active_shards = %self.state.shard_consumers.len(),
                                 "Shard is closed."
                             );
-                            self.state.shard_consumers.remove(&shard_id);
-
+                            let shard_consumer_state = self
+                                .state
+                                .shard_consumers
+                                .remove(&shard_id)
+                                .ok_or_else(|| {
+                                    anyhow::anyhow!("Closed shard `{}` is unassigned.", shard_id)
+                                })?;
+                            // The closed position records the lineage of the shard in the
+                            // checkpoint, so that the shard is not read again and its children
+                            // are read right away after a restart.
+                            let (closed_position, child_shard_ids) =
+                                self.state.shard_lineage.close_shard(&shard_id);
+                            checkpoint_delta.record_partition_delta(
+                                shard_consumer_state.partition_id,
+                                shard_consumer_state.position,
+                                closed_position,
+                            ).context("Failed to record partition delta.")?;
+                            for child_shard_id in child_shard_ids {
+                                info!(
+                                    stream_name = %self.stream_name,
+                                    shard_id = %child_shard_id,
+                                    parent_shard_id = %shard_id,
+                                    "Starting to read child shard."
+                                );
+                                self.spawn_shard_consumer(ctx, child_shard_id);
+                            }
                         }
                         ShardConsumerMessage::ShardEOF(shard_id) => {
                             info!(
@@ -436,6 +464,7 @@ mod tests {
             let actor = SourceActor {
                 source: Box::new(kinesis_source),
                 indexer_mailbox: mailbox.clone(),
+                rate_limiter_opt: None,
             };
             let (_mailbox, handle) = universe.spawn_actor(actor).spawn();
             let (exit_status, exit_state) = handle.join().await;
@@ -490,6 +519,7 @@ mod tests {
             let actor = SourceActor {
                 source: Box::new(kinesis_source),
                 indexer_mailbox: mailbox.clone(),
+                rate_limiter_opt: None,
             };
             let (_mailbox, handle) = universe.spawn_actor(actor).spawn();
             let (exit_status, exit_state) = handle.join().await;
@@ -561,6 +591,7 @@ mod tests {
             let actor = SourceActor {
                 source: Box::new(kinesis_source),
                 indexer_mailbox: mailbox,
+                rate_limiter_opt: None,
             };
             let (_mailbox, handle) = universe.spawn_actor(actor).spawn();
             let (exit_status, exit_state) = handle.join().await;
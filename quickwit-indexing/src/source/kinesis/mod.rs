@@ -21,6 +21,7 @@ mod api;
 mod helpers;
 pub mod kinesis_source;
 mod shard_consumer;
+mod shard_lineage;
 
 use quickwit_aws::retry::RetryParams;
 use quickwit_config::KinesisSourceParams;
@@ -23,7 +23,7 @@ use std::time::Duration;
 use async_trait::async_trait;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, ActorHandle, Handler, Mailbox};
 use quickwit_aws::retry::RetryParams;
-use rusoto_kinesis::{KinesisClient, Record};
+use rusoto_kinesis::{ChildShard, KinesisClient, Record};
 use serde_json::json;
 use tokio::sync::mpsc;
 
@@ -33,8 +33,11 @@ use crate::source::SourceContext;
 #[derive(Debug)]
 pub(super) enum ShardConsumerMessage {
     /// The shard was the subject of a merge or a split and points to one (merge) or two (split)
-    /// children.
-    ChildShards(Vec<String>),
+    /// children. With a merge, both parents report the child.
+    ChildShards {
+        shard_id: String,
+        child_shards: Vec<ChildShard>,
+    },
     Records {
         shard_id: String,
         records: Vec<Record>,
@@ -208,15 +211,12 @@ impl Handler<Loop> for ShardConsumer {
                 };
                 self.send_message(ctx, message).await?;
             }
-            if let Some(children) = response.child_shards {
-                let shard_ids: Vec<String> = children
-                    .into_iter()
-                    // Filter out duplicate message when two shards are merged.
-                    .filter(|child| child.parent_shards.first() == Some(&self.shard_id))
-                    .map(|child| child.shard_id)
-                    .collect();
-                if !shard_ids.is_empty() {
-                    let message = ShardConsumerMessage::ChildShards(shard_ids);
+            if let Some(child_shards) = response.child_shards {
+                if !child_shards.is_empty() {
+                    let message = ShardConsumerMessage::ChildShards {
+                        shard_id: self.shard_id.clone(),
+                        child_shards,
+                    };
                     self.send_message(ctx, message).await?;
                 }
             }
@@ -438,7 +438,7 @@ mod tests {
 
             assert!(matches!(
                 &messages[0],
-                ShardConsumerMessage::ChildShards(shard_ids) if *shard_ids == vec![make_shard_id(2)]
+                ShardConsumerMessage::ChildShards { shard_id, child_shards } if *shard_id == shard_id_0 && child_shards.len() == 1 && child_shards[0].shard_id == make_shard_id(2)
             ));
             assert!(matches!(
                 &messages[1],
@@ -460,10 +460,14 @@ mod tests {
             assert!(exit_status.is_success());
 
             let messages = drain_messages(&mut sink_rx).await;
-            assert_eq!(messages.len(), 1);
+            assert_eq!(messages.len(), 2);
 
             assert!(matches!(
                 &messages[0],
+                ShardConsumerMessage::ChildShards { shard_id, child_shards } if *shard_id == shard_id_1 && child_shards.len() == 1 && child_shards[0].shard_id == make_shard_id(2)
+            ));
+            assert!(matches!(
+                &messages[1],
                 ShardConsumerMessage::ShardClosed(shard_id) if *shard_id == shard_id_1
             ));
         }
@@ -500,7 +504,7 @@ mod tests {
 
         assert!(matches!(
             &messages[0],
-            ShardConsumerMessage::ChildShards(shard_ids) if *shard_ids == vec![make_shard_id(1), make_shard_id(2)]
+            ShardConsumerMessage::ChildShards { shard_id, child_shards } if *shard_id == shard_id_0 && child_shards.iter().map(|child_shard| child_shard.shard_id.clone()).collect::<Vec<_>>() == vec![make_shard_id(1), make_shard_id(2)]
         ));
         assert!(matches!(
             &messages[1],
@@ -0,0 +1,281 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use itertools::Itertools;
+use quickwit_metastore::checkpoint::{PartitionId, Position, SourceCheckpoint};
+use rusoto_kinesis::{ChildShard, Shard};
+
+type ShardId = String;
+
+/// Prefix of the position recorded in the checkpoint for a shard read until it was closed by a
+/// resharding. It sorts after any sequence number, and is followed by the comma-separated IDs of
+/// the child shards of the closed shard.
+const CLOSED_SHARD_POSITION_PREFIX: &str = "~closed:";
+
+/// Returns the position recorded for a shard closed by a resharding.
+pub(super) fn closed_shard_position<'a>(
+    child_shard_ids: impl IntoIterator<Item = &'a ShardId>,
+) -> Position {
+    Position::from(format!(
+        "{}{}",
+        CLOSED_SHARD_POSITION_PREFIX,
+        child_shard_ids.into_iter().join(",")
+    ))
+}
+
+/// Returns whether `position` marks a shard closed by a resharding.
+pub(super) fn is_closed_shard_position(position: &Position) -> bool {
+    position.as_str().starts_with(CLOSED_SHARD_POSITION_PREFIX)
+}
+
+/// Tracks the parent-child relationships between the shards of a stream resulting from shard
+/// splits and merges, so that the records of a child shard are read only after all the records of
+/// its parents, preserving the order of the records sharing a partition key.
+#[derive(Debug, Default)]
+pub(super) struct ShardLineage {
+    /// Shards listed when the source started or reported as children since then.
+    known_shards: HashSet<ShardId>,
+    /// Shards read until they were closed, as recorded in the checkpoint.
+    closed_shards: HashSet<ShardId>,
+    /// Shards waiting for some of their parents to be closed, with their parents.
+    pending_shards: BTreeMap<ShardId, Vec<ShardId>>,
+    /// Children of the shards being read, reported when the shards are closed.
+    child_shards: BTreeMap<ShardId, BTreeSet<ShardId>>,
+}
+
+impl ShardLineage {
+    /// Builds the lineage of the shards of a stream listed with the `ListShards` API, and
+    /// returns it along with the shards that can be read right away: the shards that are not
+    /// closed according to `checkpoint` and whose parents, if any, are closed or expired.
+    pub fn new(shards: Vec<Shard>, checkpoint: &SourceCheckpoint) -> (Self, Vec<ShardId>) {
+        let mut shard_lineage = ShardLineage {
+            known_shards: shards.iter().map(|shard| shard.shard_id.clone()).collect(),
+            ..Default::default()
+        };
+        let mut shards_to_read = Vec::new();
+        for shard in shards {
+            let position_opt =
+                checkpoint.position_for_partition(&PartitionId::from(shard.shard_id.as_str()));
+            match position_opt {
+                Some(position) if is_closed_shard_position(position) => {
+                    shard_lineage.closed_shards.insert(shard.shard_id);
+                }
+                // Reading the shard already started.
+                Some(Position::Offset(_)) => shards_to_read.push(shard.shard_id),
+                _ => {
+                    let parent_shard_ids = shard
+                        .parent_shard_id
+                        .into_iter()
+                        .chain(shard.adjacent_parent_shard_id)
+                        .collect();
+                    shard_lineage
+                        .pending_shards
+                        .insert(shard.shard_id, parent_shard_ids);
+                }
+            }
+        }
+        shards_to_read.extend(shard_lineage.take_ready_shards());
+        shards_to_read.sort();
+        (shard_lineage, shards_to_read)
+    }
+
+    /// Records the children of `shard_id`, reported when reaching the end of a closed shard.
+    pub fn add_child_shards(&mut self, shard_id: &str, child_shards: Vec<ChildShard>) {
+        for child_shard in child_shards {
+            self.child_shards
+                .entry(shard_id.to_string())
+                .or_default()
+                .insert(child_shard.shard_id.clone());
+            // With a merge, both parents report the child.
+            if self.known_shards.insert(child_shard.shard_id.clone()) {
+                self.pending_shards
+                    .insert(child_shard.shard_id, child_shard.parent_shards);
+            }
+        }
+    }
+
+    /// Marks `shard_id` as closed. Returns the position to record in the checkpoint for the
+    /// shard, and the children that can now be read.
+    pub fn close_shard(&mut self, shard_id: &str) -> (Position, Vec<ShardId>) {
+        self.closed_shards.insert(shard_id.to_string());
+        let child_shard_ids = self.child_shards.remove(shard_id).unwrap_or_default();
+        let closed_position = closed_shard_position(&child_shard_ids);
+        (closed_position, self.take_ready_shards())
+    }
+
+    /// Returns the shards waiting for their parents to be closed.
+    pub fn pending_shards(&self) -> impl Iterator<Item = &ShardId> {
+        self.pending_shards.keys()
+    }
+
+    /// Removes and returns the pending shards whose parents are all closed or expired.
+    fn take_ready_shards(&mut self) -> Vec<ShardId> {
+        let ready_shard_ids: Vec<ShardId> = self
+            .pending_shards
+            .iter()
+            .filter(|(_, parent_shard_ids)| {
+                parent_shard_ids.iter().all(|parent_shard_id| {
+                    self.closed_shards.contains(parent_shard_id)
+                        || !self.known_shards.contains(parent_shard_id)
+                })
+            })
+            .map(|(shard_id, _)| shard_id.clone())
+            .collect();
+        for shard_id in &ready_shard_ids {
+            self.pending_shards.remove(shard_id);
+        }
+        ready_shard_ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shard(shard_id: &str, parent_shard_ids: &[&str]) -> Shard {
+        Shard {
+            shard_id: shard_id.to_string(),
+            parent_shard_id: parent_shard_ids
+                .first()
+                .map(|shard_id| shard_id.to_string()),
+            adjacent_parent_shard_id: parent_shard_ids.get(1).map(|shard_id| shard_id.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn child_shard(shard_id: &str, parent_shard_ids: &[&str]) -> ChildShard {
+        ChildShard {
+            shard_id: shard_id.to_string(),
+            parent_shards: parent_shard_ids
+                .iter()
+                .map(|shard_id| shard_id.to_string())
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_closed_shard_position() {
+        let closed_position =
+            closed_shard_position(&["shard-1".to_string(), "shard-2".to_string()]);
+        assert_eq!(closed_position.as_str(), "~closed:shard-1,shard-2");
+        assert!(is_closed_shard_position(&closed_position));
+        assert!(
+            closed_position
+                > Position::from("49590338271490256608559692538361571095921575989136588898")
+        );
+        assert!(!is_closed_shard_position(&Position::from("4959033827149")));
+        assert!(!is_closed_shard_position(&Position::Beginning));
+    }
+
+    #[test]
+    fn test_shard_lineage_split_and_merge() {
+        // Shard 0 was split into shards 1 and 2, which were then merged into shard 3.
+        let shards = vec![
+            shard("shard-0", &[]),
+            shard("shard-1", &["shard-0"]),
+            shard("shard-2", &["shard-0"]),
+            shard("shard-3", &["shard-1", "shard-2"]),
+        ];
+        let (mut shard_lineage, shards_to_read) =
+            ShardLineage::new(shards, &SourceCheckpoint::default());
+        assert_eq!(shards_to_read, ["shard-0"]);
+        assert_eq!(
+            shard_lineage.pending_shards().collect::<Vec<_>>(),
+            ["shard-1", "shard-2", "shard-3"]
+        );
+
+        shard_lineage.add_child_shards(
+            "shard-0",
+            vec![
+                child_shard("shard-1", &["shard-0"]),
+                child_shard("shard-2", &["shard-0"]),
+            ],
+        );
+        let (closed_position, ready_shards) = shard_lineage.close_shard("shard-0");
+        assert_eq!(closed_position.as_str(), "~closed:shard-1,shard-2");
+        assert_eq!(ready_shards, ["shard-1", "shard-2"]);
+
+        shard_lineage.add_child_shards(
+            "shard-2",
+            vec![child_shard("shard-3", &["shard-1", "shard-2"])],
+        );
+        let (closed_position, ready_shards) = shard_lineage.close_shard("shard-2");
+        assert_eq!(closed_position.as_str(), "~closed:shard-3");
+        assert!(ready_shards.is_empty());
+
+        shard_lineage.add_child_shards(
+            "shard-1",
+            vec![child_shard("shard-3", &["shard-1", "shard-2"])],
+        );
+        let (_, ready_shards) = shard_lineage.close_shard("shard-1");
+        assert_eq!(ready_shards, ["shard-3"]);
+        assert_eq!(shard_lineage.pending_shards().count(), 0);
+    }
+
+    #[test]
+    fn test_shard_lineage_resharding_while_running() {
+        let (mut shard_lineage, shards_to_read) =
+            ShardLineage::new(vec![shard("shard-0", &[])], &SourceCheckpoint::default());
+        assert_eq!(shards_to_read, ["shard-0"]);
+
+        // The children of a shard are not listed when the source starts.
+        shard_lineage.add_child_shards(
+            "shard-0",
+            vec![
+                child_shard("shard-1", &["shard-0"]),
+                child_shard("shard-2", &["shard-0"]),
+            ],
+        );
+        assert_eq!(shard_lineage.pending_shards().count(), 2);
+        let (_, ready_shards) = shard_lineage.close_shard("shard-0");
+        assert_eq!(ready_shards, ["shard-1", "shard-2"]);
+    }
+
+    #[test]
+    fn test_shard_lineage_from_checkpoint() {
+        let shards = vec![
+            shard("shard-0", &[]),
+            shard("shard-1", &["shard-0"]),
+            shard("shard-2", &["shard-0"]),
+            shard("shard-3", &["shard-1", "shard-2"]),
+            // The parent of shard 4 expired.
+            shard("shard-4", &["shard-expired"]),
+        ];
+        let checkpoint: SourceCheckpoint = [
+            (
+                "shard-0",
+                closed_shard_position(&["shard-1".to_string(), "shard-2".to_string()]),
+            ),
+            ("shard-1", closed_shard_position(&["shard-3".to_string()])),
+            ("shard-2", Position::from("4959033827149")),
+        ]
+        .into_iter()
+        .map(|(shard_id, position)| (PartitionId::from(shard_id), position))
+        .collect();
+        let (shard_lineage, shards_to_read) = ShardLineage::new(shards, &checkpoint);
+        assert_eq!(shards_to_read, ["shard-2", "shard-4"]);
+        assert_eq!(
+            shard_lineage.pending_shards().collect::<Vec<_>>(),
+            ["shard-3"]
+        );
+    }
+}
@@ -57,16 +57,29 @@
 //!   that file.
 //! - the kafka source: the partition id is a kafka topic partition id, and the position is a kafka
 //!   offset.
+//! - the pulsar source: the partition id is the name of a pulsar topic partition, and the position
+//!   is a pulsar message id.
+//! - the parquet source: the partition id is a filepath or the URI of a stored file, and the
+//!   position is the number of row groups of that file already read.
+//! - the replay source: replays the batches of another source recorded in a fixture file with
+//!   [`record_source`], so that pipeline tests can run without the original source.
 mod file_source;
 mod ingest_api_source;
 #[cfg(feature = "kafka")]
 mod kafka_source;
 #[cfg(feature = "kinesis")]
 mod kinesis;
+mod multiplexed_source;
+#[cfg(feature = "parquet")]
+mod parquet_source;
+#[cfg(feature = "pulsar")]
+mod pulsar_source;
+mod replay_source;
 mod source_factory;
 mod vec_source;
 mod void_source;
 
+use std::collections::BTreeMap;
 use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
@@ -75,15 +88,23 @@ use anyhow::bail;
 use async_trait::async_trait;
 pub use file_source::{FileSource, FileSourceFactory};
 #[cfg(feature = "kafka")]
+pub(crate) use kafka_source::parse_client_params;
+#[cfg(feature = "kafka")]
 pub use kafka_source::{KafkaSource, KafkaSourceFactory};
 #[cfg(feature = "kinesis")]
 pub use kinesis::kinesis_source::{KinesisSource, KinesisSourceFactory};
+pub use multiplexed_source::{MultiplexedSource, MultiplexedSourceFactory};
 use once_cell::sync::OnceCell;
+#[cfg(feature = "parquet")]
+pub use parquet_source::{ParquetSource, ParquetSourceFactory};
+#[cfg(feature = "pulsar")]
+pub use pulsar_source::{PulsarSource, PulsarSourceFactory};
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox};
 use quickwit_common::runtimes::RuntimeType;
 use quickwit_config::{SourceConfig, SourceParams};
-use quickwit_metastore::checkpoint::SourceCheckpoint;
+use quickwit_metastore::checkpoint::{PartitionId, SourceCheckpoint};
 use quickwit_metastore::Metastore;
+pub use replay_source::{record_source, RecordedBatch, ReplaySource, ReplaySourceFactory};
 pub use source_factory::{SourceFactory, SourceLoader, TypedSourceFactory};
 use tokio::runtime::Handle;
 use tracing::error;
@@ -91,6 +112,7 @@ pub use vec_source::{VecSource, VecSourceFactory};
 pub use void_source::{VoidSource, VoidSourceFactory};
 
 use crate::actors::Indexer;
+use crate::models::{PartitionBounds, PipelineWarningSink, SourceRateLimiter};
 use crate::source::ingest_api_source::IngestApiSourceFactory;
 
 /// Reserved source ID used for the ingest API.
@@ -101,6 +123,8 @@ pub struct SourceExecutionContext {
     pub metastore: Arc<dyn Metastore>,
     pub index_id: String,
     pub source_config: SourceConfig,
+    /// Sink in which the source records the warnings of its pipeline.
+    pub warning_sink: PipelineWarningSink,
 }
 
 impl SourceExecutionContext {
@@ -114,6 +138,7 @@ impl SourceExecutionContext {
             metastore,
             index_id: index_id.to_string(),
             source_config,
+            warning_sink: PipelineWarningSink::default(),
         })
     }
 }
@@ -214,6 +239,8 @@ pub trait Source: Send + Sync + 'static {
 pub struct SourceActor {
     pub source: Box<dyn Source>,
     pub indexer_mailbox: Mailbox<Indexer>,
+    /// Rate limiter of the source, which pauses the loop while the pipeline is over budget.
+    pub rate_limiter_opt: Option<SourceRateLimiter>,
 }
 
 #[derive(Debug)]
@@ -256,6 +283,14 @@ impl Handler<Loop> for SourceActor {
     type Reply = ();
 
     async fn handle(&mut self, _message: Loop, ctx: &SourceContext) -> Result<(), ActorExitStatus> {
+        if let Some(throttle_duration) = self
+            .rate_limiter_opt
+            .as_ref()
+            .and_then(SourceRateLimiter::throttle_duration)
+        {
+            ctx.schedule_self_msg(throttle_duration, Loop).await;
+            return Ok(());
+        }
         let wait_for = self.source.emit_batches(&self.indexer_mailbox, ctx).await?;
         if wait_for.is_zero() {
             ctx.send_self_message(Loop).await?;
@@ -275,9 +310,15 @@ pub fn quickwit_supported_sources() -> &'static SourceLoader {
         source_factory.add_source("kafka", KafkaSourceFactory);
         #[cfg(feature = "kinesis")]
         source_factory.add_source("kinesis", KinesisSourceFactory);
+        #[cfg(feature = "parquet")]
+        source_factory.add_source("parquet", ParquetSourceFactory);
+        #[cfg(feature = "pulsar")]
+        source_factory.add_source("pulsar", PulsarSourceFactory);
+        source_factory.add_source("replay", ReplaySourceFactory);
         source_factory.add_source("vec", VecSourceFactory);
         source_factory.add_source("void", VoidSourceFactory);
         source_factory.add_source("ingest-api", IngestApiSourceFactory);
+        source_factory.add_source("multiplexed", MultiplexedSourceFactory);
         source_factory
     })
 }
@@ -286,12 +327,25 @@ pub async fn check_source_connectivity(source_config: &SourceConfig) -> anyhow::
     match &source_config.source_params {
         SourceParams::File(params) => {
             if let Some(filepath) = &params.filepath {
-                if !Path::new(filepath).exists() {
+                if params.is_glob_pattern() {
+                    if file_source::list_files_matching_pattern(filepath)?.is_empty() {
+                        bail!("No file matches the pattern `{}`.", filepath.display())
+                    }
+                } else if !Path::new(filepath).exists() {
                     bail!("File `{}` does not exist.", filepath.display())
                 }
             }
             Ok(())
         }
+        SourceParams::Replay(params) => {
+            if !params.fixture_path.exists() {
+                bail!(
+                    "Fixture `{}` does not exist.",
+                    params.fixture_path.display()
+                )
+            }
+            Ok(())
+        }
         #[allow(unused_variables)]
         SourceParams::Kafka(params) => {
             #[cfg(not(feature = "kafka"))]
@@ -314,10 +368,64 @@ pub async fn check_source_connectivity(source_config: &SourceConfig) -> anyhow::
                 Ok(())
             }
         }
+        #[allow(unused_variables)]
+        SourceParams::Parquet(params) => {
+            #[cfg(not(feature = "parquet"))]
+            bail!("Quickwit binary was not compiled with the `parquet` feature.");
+
+            #[cfg(feature = "parquet")]
+            {
+                for filepath in &params.filepaths {
+                    if !filepath.exists() {
+                        bail!("File `{}` does not exist.", filepath.display())
+                    }
+                }
+                if let Some(uri) = &params.uri {
+                    quickwit_storage::quickwit_storage_uri_resolver()
+                        .resolve(uri)?
+                        .check_connectivity()
+                        .await?;
+                }
+                Ok(())
+            }
+        }
+        #[allow(unused_variables)]
+        SourceParams::Pulsar(params) => {
+            #[cfg(not(feature = "pulsar"))]
+            bail!("Quickwit binary was not compiled with the `pulsar` feature.");
+
+            #[cfg(feature = "pulsar")]
+            {
+                pulsar_source::check_connectivity(params.clone()).await?;
+                Ok(())
+            }
+        }
         _ => Ok(()),
     }
 }
 
+/// Fetches the offsets of the records available in each partition of the source, for the sources
+/// whose positions are record offsets. Returns `None` for the other sources.
+pub async fn fetch_source_partition_bounds(
+    source_config: &SourceConfig,
+) -> anyhow::Result<Option<BTreeMap<PartitionId, PartitionBounds>>> {
+    match &source_config.source_params {
+        #[allow(unused_variables)]
+        SourceParams::Kafka(params) => {
+            #[cfg(not(feature = "kafka"))]
+            {
+                Ok(None)
+            }
+            #[cfg(feature = "kafka")]
+            {
+                let partition_bounds = kafka_source::fetch_partition_bounds(params.clone()).await?;
+                Ok(Some(partition_bounds))
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
 #[derive(Debug)]
 pub struct SuggestTruncate(pub SourceCheckpoint);
 
@@ -352,6 +460,10 @@ mod tests {
             let source_config = SourceConfig {
                 source_id: "void".to_string(),
                 num_pipelines: 1,
+                profile: None,
+                rate_limit: None,
+                transforms: Vec::new(),
+                strict_ordering: false,
                 source_params: SourceParams::void(),
             };
             check_source_connectivity(&source_config).await?;
@@ -360,6 +472,10 @@ mod tests {
             let source_config = SourceConfig {
                 source_id: "vec".to_string(),
                 num_pipelines: 1,
+                profile: None,
+                rate_limit: None,
+                transforms: Vec::new(),
+                strict_ordering: false,
                 source_params: SourceParams::Vec(VecSourceParams::default()),
             };
             check_source_connectivity(&source_config).await?;
@@ -368,6 +484,10 @@ mod tests {
             let source_config = SourceConfig {
                 source_id: "file".to_string(),
                 num_pipelines: 1,
+                profile: None,
+                rate_limit: None,
+                transforms: Vec::new(),
+                strict_ordering: false,
                 source_params: SourceParams::file("file-does-not-exist.json"),
             };
             assert!(check_source_connectivity(&source_config).await.is_err());
@@ -376,10 +496,31 @@ mod tests {
             let source_config = SourceConfig {
                 source_id: "file".to_string(),
                 num_pipelines: 1,
+                profile: None,
+                rate_limit: None,
+                transforms: Vec::new(),
+                strict_ordering: false,
                 source_params: SourceParams::file("data/test_corpus.json"),
             };
             assert!(check_source_connectivity(&source_config).await.is_ok());
         }
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_fetch_source_partition_bounds() -> anyhow::Result<()> {
+        let source_config = SourceConfig {
+            source_id: "void".to_string(),
+            num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::void(),
+        };
+        assert!(fetch_source_partition_bounds(&source_config)
+            .await?
+            .is_none());
+        Ok(())
+    }
 }
@@ -0,0 +1,383 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use quickwit_actors::{create_mailbox, ActorExitStatus, Inbox, Mailbox, QueueCapacity};
+use quickwit_config::MultiplexedSourceParams;
+use quickwit_metastore::checkpoint::SourceCheckpoint;
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::actors::Indexer;
+use crate::models::{DocTransformer, RawDocBatch, SourceRateLimiter};
+use crate::source::{
+    quickwit_supported_sources, Source, SourceContext, SourceExecutionContext, TypedSourceFactory,
+};
+
+/// One of the sources read by a [`MultiplexedSource`].
+struct MultiplexedSourceMember {
+    source_id: String,
+    source: Box<dyn Source>,
+    /// The source emits its batches into this mailbox, whose inbox is drained by the multiplexed
+    /// source to tag the batches before forwarding them to the indexer.
+    batch_sink: Mailbox<Indexer>,
+    batch_inbox: Inbox<Indexer>,
+    rate_limiter_opt: Option<SourceRateLimiter>,
+    doc_transformer_opt: Option<DocTransformer>,
+    /// The source is not polled before this instant, as requested by the source itself or by its
+    /// rate limiter.
+    next_poll_at: Instant,
+    /// Set once the source has reached its end.
+    is_exhausted: bool,
+    num_batches_emitted: u64,
+    num_docs_emitted: u64,
+    /// Number of documents dropped because they could not be transformed.
+    num_transform_errors: u64,
+}
+
+/// Source reading several sources of an index in a single pipeline, so that indexes with many
+/// small sources do not pay for one pipeline per source.
+///
+/// The sources are polled in a round-robin fashion, one source per call to `emit_batches`, so that
+/// a source with a large backlog cannot starve the others. The transforms of each source are
+/// applied here, as the indexer of the pipeline does not know the source of the documents: the
+/// documents failing to be transformed are dropped. The partitions of the checkpoint
+/// deltas of the batches are prefixed with the ID of their source: the metastore applies them to
+/// the checkpoint of each source independently.
+pub struct MultiplexedSource {
+    members: Vec<MultiplexedSourceMember>,
+    next_member_ord: usize,
+}
+
+pub struct MultiplexedSourceFactory;
+
+#[async_trait]
+impl TypedSourceFactory for MultiplexedSourceFactory {
+    type Source = MultiplexedSource;
+    type Params = MultiplexedSourceParams;
+
+    async fn typed_create_source(
+        ctx: Arc<SourceExecutionContext>,
+        params: MultiplexedSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<Self::Source> {
+        let mut source_checkpoints = checkpoint.demultiplex();
+        let mut members = Vec::with_capacity(params.sources.len());
+        for source_config in params.sources {
+            let source_id = source_config.source_id.clone();
+            let rate_limiter_opt = source_config
+                .rate_limit
+                .as_ref()
+                .map(SourceRateLimiter::new);
+            let doc_transformer_opt = DocTransformer::try_new(&source_config.transforms)?;
+            let source_checkpoint = source_checkpoints.remove(&source_id).unwrap_or_default();
+            let source = quickwit_supported_sources()
+                .load_source(
+                    Arc::new(SourceExecutionContext {
+                        metastore: ctx.metastore.clone(),
+                        index_id: ctx.index_id.clone(),
+                        source_config,
+                        warning_sink: ctx.warning_sink.clone(),
+                    }),
+                    source_checkpoint,
+                )
+                .await?;
+            let (batch_sink, batch_inbox) =
+                create_mailbox(format!("{}-batches", source_id), QueueCapacity::Unbounded);
+            members.push(MultiplexedSourceMember {
+                source_id,
+                source,
+                batch_sink,
+                batch_inbox,
+                rate_limiter_opt,
+                doc_transformer_opt,
+                next_poll_at: Instant::now(),
+                is_exhausted: false,
+                num_batches_emitted: 0,
+                num_docs_emitted: 0,
+                num_transform_errors: 0,
+            });
+        }
+        Ok(MultiplexedSource {
+            members,
+            next_member_ord: 0,
+        })
+    }
+}
+
+impl MultiplexedSource {
+    /// Returns the ordinal of the next source to poll in the round-robin order, if any source is
+    /// ready to be polled.
+    fn next_ready_member_ord(&mut self, now: Instant) -> Option<usize> {
+        let num_members = self.members.len();
+        for _ in 0..num_members {
+            let member_ord = self.next_member_ord;
+            self.next_member_ord = (self.next_member_ord + 1) % num_members;
+            let member = &mut self.members[member_ord];
+            if member.is_exhausted || member.next_poll_at > now {
+                continue;
+            }
+            if let Some(throttle_duration) = member
+                .rate_limiter_opt
+                .as_ref()
+                .and_then(SourceRateLimiter::throttle_duration)
+            {
+                member.next_poll_at = now + throttle_duration;
+                continue;
+            }
+            return Some(member_ord);
+        }
+        None
+    }
+
+    /// Returns how long to wait before a source is ready to be polled.
+    fn wait_duration(&self, now: Instant) -> Duration {
+        self.members
+            .iter()
+            .filter(|member| !member.is_exhausted)
+            .map(|member| member.next_poll_at.saturating_duration_since(now))
+            .min()
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl Source for MultiplexedSource {
+    async fn initialize(
+        &mut self,
+        _indexer_mailbox: &Mailbox<Indexer>,
+        ctx: &SourceContext,
+    ) -> Result<(), ActorExitStatus> {
+        for member in &mut self.members {
+            member.source.initialize(&member.batch_sink, ctx).await?;
+        }
+        Ok(())
+    }
+
+    async fn emit_batches(
+        &mut self,
+        indexer_mailbox: &Mailbox<Indexer>,
+        ctx: &SourceContext,
+    ) -> Result<Duration, ActorExitStatus> {
+        let now = Instant::now();
+        if let Some(member_ord) = self.next_ready_member_ord(now) {
+            let member = &mut self.members[member_ord];
+            let emit_result = member.source.emit_batches(&member.batch_sink, ctx).await;
+            for mut batch in member.batch_inbox.drain_typed::<RawDocBatch>() {
+                if let Some(rate_limiter) = &member.rate_limiter_opt {
+                    let num_bytes: usize = batch.docs.iter().map(String::len).sum();
+                    rate_limiter.record_batch(batch.docs.len() as u64, num_bytes as u64);
+                }
+                if let Some(doc_transformer) = &member.doc_transformer_opt {
+                    let num_docs = batch.docs.len();
+                    batch.docs = batch
+                        .docs
+                        .into_iter()
+                        .filter_map(|doc_json| match doc_transformer.transform(&doc_json) {
+                            Ok(transformed_doc_json) => Some(transformed_doc_json),
+                            Err(doc_transform_error) => {
+                                warn!(source_id=%member.source_id, err=?doc_transform_error);
+                                None
+                            }
+                        })
+                        .collect();
+                    member.num_transform_errors += (num_docs - batch.docs.len()) as u64;
+                }
+                member.num_batches_emitted += 1;
+                member.num_docs_emitted += batch.docs.len() as u64;
+                batch.checkpoint_delta = batch.checkpoint_delta.multiplexed(&member.source_id);
+                ctx.send_message(indexer_mailbox, batch).await?;
+            }
+            match emit_result {
+                Ok(wait_for) => member.next_poll_at = Instant::now() + wait_for,
+                Err(ActorExitStatus::Success) => {
+                    info!(source_id=%member.source_id, "Reached end of multiplexed source.");
+                    member.is_exhausted = true;
+                    member
+                        .source
+                        .finalize(&ActorExitStatus::Success, ctx)
+                        .await?;
+                }
+                Err(exit_status) => return Err(exit_status),
+            }
+        }
+        if self.members.iter().all(|member| member.is_exhausted) {
+            ctx.send_exit_with_success(indexer_mailbox).await?;
+            return Err(ActorExitStatus::Success);
+        }
+        Ok(self.wait_duration(Instant::now()))
+    }
+
+    async fn suggest_truncate(
+        &self,
+        checkpoint: SourceCheckpoint,
+        ctx: &SourceContext,
+    ) -> anyhow::Result<()> {
+        let mut source_checkpoints = checkpoint.demultiplex();
+        for member in &self.members {
+            if let Some(source_checkpoint) = source_checkpoints.remove(&member.source_id) {
+                member
+                    .source
+                    .suggest_truncate(source_checkpoint, ctx)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn finalize(
+        &mut self,
+        exit_status: &ActorExitStatus,
+        ctx: &SourceContext,
+    ) -> anyhow::Result<()> {
+        for member in &mut self.members {
+            if !member.is_exhausted {
+                member.source.finalize(exit_status, ctx).await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> String {
+        format!("MultiplexedSource {{ num_sources={} }}", self.members.len())
+    }
+
+    fn observable_state(&self) -> serde_json::Value {
+        let sources: serde_json::Map<String, serde_json::Value> = self
+            .members
+            .iter()
+            .map(|member| {
+                let source_state = json!({
+                    "num_batches_emitted": member.num_batches_emitted,
+                    "num_docs_emitted": member.num_docs_emitted,
+                    "num_transform_errors": member.num_transform_errors,
+                    "is_exhausted": member.is_exhausted,
+                    "source": member.source.observable_state(),
+                });
+                (member.source_id.clone(), source_state)
+            })
+            .collect();
+        json!({ "sources": sources })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_actors::{create_test_mailbox, Command, Universe};
+    use quickwit_config::{SourceConfig, SourceParams, VecSourceParams};
+    use quickwit_metastore::checkpoint::{
+        IndexCheckpoint, IndexCheckpointDelta, PartitionId, Position, SourceCheckpointDelta,
+    };
+    use quickwit_metastore::metastore_for_test;
+
+    use super::*;
+    use crate::source::SourceActor;
+
+    fn vec_source_config(source_id: &str, num_docs: usize) -> SourceConfig {
+        SourceConfig {
+            source_id: source_id.to_string(),
+            num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::Vec(VecSourceParams {
+                docs: (0..num_docs).map(|doc_ord| doc_ord.to_string()).collect(),
+                batch_num_docs: 2,
+                partition: "partition".to_string(),
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multiplexed_source() -> anyhow::Result<()> {
+        let universe = Universe::new();
+        let (indexer_mailbox, indexer_inbox) = create_test_mailbox();
+        let source_config = SourceConfig::multiplexed(vec![
+            vec_source_config("source-a", 6),
+            vec_source_config("source-b", 2),
+        ]);
+        // Source A resumes from its checkpoint.
+        let mut index_checkpoint = IndexCheckpoint::default();
+        index_checkpoint
+            .try_apply_delta(IndexCheckpointDelta {
+                source_id: "source-a".to_string(),
+                source_delta: SourceCheckpointDelta::from_partition_delta(
+                    PartitionId::from("partition"),
+                    Position::Beginning,
+                    Position::from(1u64),
+                ),
+            })
+            .unwrap();
+        let checkpoint = index_checkpoint.multiplexed_source_checkpoint(["source-a", "source-b"]);
+        let source = quickwit_supported_sources()
+            .load_source(
+                SourceExecutionContext::for_test(metastore_for_test(), "test-index", source_config),
+                checkpoint,
+            )
+            .await?;
+        let source_actor = SourceActor {
+            source,
+            indexer_mailbox,
+            rate_limiter_opt: None,
+        };
+        let (_source_mailbox, source_handle) = universe.spawn_actor(source_actor).spawn();
+        let (actor_termination, last_observation) = source_handle.join().await;
+        assert!(actor_termination.is_success());
+        assert_eq!(
+            last_observation["sources"]["source-a"]["num_docs_emitted"],
+            4
+        );
+        assert_eq!(
+            last_observation["sources"]["source-b"]["num_docs_emitted"],
+            2
+        );
+        let messages = indexer_inbox.drain_for_test();
+        let checkpoint_deltas: Vec<String> = messages
+            .iter()
+            .flat_map(|message| message.downcast_ref::<RawDocBatch>())
+            .map(|batch| format!("{:?}", batch.checkpoint_delta))
+            .collect();
+        // The sources take turns.
+        assert_eq!(
+            checkpoint_deltas,
+            [
+                "∆(source-a/partition:(00000000000000000001..00000000000000000003])",
+                "∆(source-b/partition:(..00000000000000000001])",
+                "∆(source-a/partition:(00000000000000000003..00000000000000000005])",
+            ]
+        );
+        // The indexer is asked to exit once all the sources reached their end.
+        assert!(matches!(
+            messages.last().unwrap().downcast_ref::<Command>().unwrap(),
+            &Command::ExitWithSuccess
+        ));
+        assert_eq!(
+            messages
+                .iter()
+                .filter(|message| message.downcast_ref::<Command>().is_some())
+                .count(),
+            1
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,612 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use parquet::file::footer::decode_metadata;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::schema::types::Type;
+use quickwit_actors::{ActorExitStatus, Mailbox};
+use quickwit_config::ParquetSourceParams;
+use quickwit_metastore::checkpoint::{
+    PartitionId, Position, SourceCheckpoint, SourceCheckpointDelta,
+};
+use quickwit_storage::{quickwit_storage_uri_resolver, Storage};
+use serde::Serialize;
+use tempfile::TempDir;
+use tracing::{info, warn};
+
+use crate::actors::Indexer;
+use crate::models::RawDocBatch;
+use crate::source::{Source, SourceContext, SourceExecutionContext, TypedSourceFactory};
+
+/// Size of the footer of a Parquet file: the length of the file metadata followed by the magic
+/// number.
+const PARQUET_FOOTER_NUM_BYTES: usize = 8;
+
+#[derive(Default, Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct ParquetSourceCounters {
+    pub num_files_downloaded: u64,
+    pub num_row_groups_processed: u64,
+    pub num_rows_processed: u64,
+}
+
+/// A Parquet file and the ordinal of the next row group to read.
+#[derive(Debug)]
+struct ParquetFileCursor {
+    /// Path of the file on the local disk. For the files of the storage of the source, the file
+    /// is downloaded to the scratch directory of the source before its first row group is read.
+    filepath: PathBuf,
+    /// Path of the file in the storage of the source, if it is not a local file.
+    storage_path_opt: Option<PathBuf>,
+    partition_id: PartitionId,
+    next_row_group_ord: usize,
+}
+
+/// Row group conversion settings shared by the files of the source.
+#[derive(Debug)]
+struct RowConversion {
+    /// Columns to read, all of them when empty.
+    columns: Vec<String>,
+    column_mapping: BTreeMap<String, String>,
+}
+
+/// Source reading the rows of Parquet files, either local or stored under a storage prefix.
+///
+/// Each file is a partition of the source, and each row group of a file is emitted as a batch.
+/// The position of a partition is the number of row groups of the file already read, so that an
+/// interrupted ingestion resumes from the first row group that was not published, and that only
+/// the files added to the storage prefix since the last import are read by the next one.
+pub struct ParquetSource {
+    source_id: String,
+    row_conversion: Arc<RowConversion>,
+    cursors: VecDeque<ParquetFileCursor>,
+    storage_opt: Option<Arc<dyn Storage>>,
+    /// Holds the stored files being read, removed once all their row groups are read.
+    scratch_dir_opt: Option<TempDir>,
+    counters: ParquetSourceCounters,
+}
+
+impl fmt::Debug for ParquetSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ParquetSource {{ source_id: {} }}", self.source_id)
+    }
+}
+
+pub struct ParquetSourceFactory;
+
+#[async_trait]
+impl TypedSourceFactory for ParquetSourceFactory {
+    type Source = ParquetSource;
+    type Params = ParquetSourceParams;
+
+    async fn typed_create_source(
+        ctx: Arc<SourceExecutionContext>,
+        params: ParquetSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<ParquetSource> {
+        let next_row_group_ord = |partition_id: &PartitionId| -> anyhow::Result<usize> {
+            match checkpoint.position_for_partition(partition_id) {
+                Some(Position::Offset(offset_str)) => Ok(offset_str.parse::<usize>()?),
+                Some(Position::Beginning) | None => Ok(0),
+            }
+        };
+        let mut cursors = VecDeque::with_capacity(params.filepaths.len());
+        for filepath in params.filepaths {
+            let partition_id = PartitionId::from(filepath.to_string_lossy().to_string());
+            cursors.push_back(ParquetFileCursor {
+                next_row_group_ord: next_row_group_ord(&partition_id)?,
+                filepath,
+                storage_path_opt: None,
+                partition_id,
+            });
+        }
+        let mut storage_opt = None;
+        let mut scratch_dir_opt = None;
+        if let Some(uri) = &params.uri {
+            let storage = quickwit_storage_uri_resolver().resolve(uri)?;
+            let storage_paths = storage
+                .list_prefix(Path::new(""))
+                .await
+                .with_context(|| format!("Failed to list the files under `{}`.", uri))?;
+            let scratch_dir = tempfile::tempdir()?;
+            for (file_ord, storage_path) in storage_paths
+                .into_iter()
+                .filter(|storage_path| {
+                    storage_path
+                        .extension()
+                        .map(|extension| extension == "parquet")
+                        .unwrap_or(false)
+                })
+                .enumerate()
+            {
+                let partition_id = PartitionId::from(uri.join(&storage_path)?.into_string());
+                cursors.push_back(ParquetFileCursor {
+                    next_row_group_ord: next_row_group_ord(&partition_id)?,
+                    filepath: scratch_dir.path().join(format!("{}.parquet", file_ord)),
+                    storage_path_opt: Some(storage_path),
+                    partition_id,
+                });
+            }
+            info!(uri=%uri, num_files=cursors.len(), "Listed the Parquet files of the source.");
+            storage_opt = Some(storage);
+            scratch_dir_opt = Some(scratch_dir);
+        }
+        Ok(ParquetSource {
+            source_id: ctx.source_config.source_id.clone(),
+            row_conversion: Arc::new(RowConversion {
+                columns: params.columns,
+                column_mapping: params.column_mapping,
+            }),
+            cursors,
+            storage_opt,
+            scratch_dir_opt,
+            counters: ParquetSourceCounters::default(),
+        })
+    }
+}
+
+/// Returns the number of row groups of a stored Parquet file, fetching its metadata only.
+async fn num_stored_row_groups(storage: &dyn Storage, path: &Path) -> anyhow::Result<usize> {
+    let num_bytes = storage.file_num_bytes(path).await? as usize;
+    if num_bytes < PARQUET_FOOTER_NUM_BYTES {
+        bail!("File `{}` is not a Parquet file.", path.display())
+    }
+    let metadata_end = num_bytes - PARQUET_FOOTER_NUM_BYTES;
+    let footer = storage.get_slice(path, metadata_end..num_bytes).await?;
+    if &footer.as_slice()[4..] != b"PAR1" {
+        bail!("File `{}` is not a Parquet file.", path.display())
+    }
+    let metadata_num_bytes = u32::from_le_bytes(footer.as_slice()[..4].try_into()?) as usize;
+    let metadata_start = metadata_end
+        .checked_sub(metadata_num_bytes)
+        .with_context(|| format!("Parquet file `{}` is truncated.", path.display()))?;
+    let metadata = storage
+        .get_slice(path, metadata_start..metadata_end)
+        .await?;
+    Ok(decode_metadata(metadata.as_slice())?.num_row_groups())
+}
+
+/// Returns the schema projecting the columns of a Parquet file on `columns`, or `None` to read all
+/// the columns when `columns` is empty.
+fn projection(
+    file_reader: &SerializedFileReader<File>,
+    columns: &[String],
+    filepath: &Path,
+) -> anyhow::Result<Option<Type>> {
+    if columns.is_empty() {
+        return Ok(None);
+    }
+    let schema = file_reader.metadata().file_metadata().schema();
+    let mut projected_fields = Vec::with_capacity(columns.len());
+    for column in columns {
+        let field = schema
+            .get_fields()
+            .iter()
+            .find(|field| field.name() == column)
+            .with_context(|| {
+                format!(
+                    "Parquet file `{}` has no column `{}`.",
+                    filepath.display(),
+                    column
+                )
+            })?;
+        projected_fields.push(field.clone());
+    }
+    let projection = Type::group_type_builder(schema.name())
+        .with_fields(&mut projected_fields)
+        .build()?;
+    Ok(Some(projection))
+}
+
+/// Reads a row group of a Parquet file and serializes its rows as JSON objects, keeping the
+/// columns to read and renaming them according to the column mapping. Returns `None` if the file
+/// has no such row group, otherwise the rows along with the number of row groups of the file.
+fn read_row_group(
+    filepath: &Path,
+    row_group_ord: usize,
+    row_conversion: &RowConversion,
+) -> anyhow::Result<Option<(Vec<String>, usize)>> {
+    let file = File::open(filepath)
+        .with_context(|| format!("Failed to open source file `{}`.", filepath.display()))?;
+    let file_reader = SerializedFileReader::new(file)
+        .with_context(|| format!("Failed to read Parquet file `{}`.", filepath.display()))?;
+    let num_row_groups = file_reader.metadata().num_row_groups();
+    if row_group_ord >= num_row_groups {
+        return Ok(None);
+    }
+    let projection_opt = projection(&file_reader, &row_conversion.columns, filepath)?;
+    let row_group_reader = file_reader.get_row_group(row_group_ord)?;
+    let mut docs = Vec::with_capacity(row_group_reader.metadata().num_rows() as usize);
+    for row in row_group_reader.get_row_iter(projection_opt)? {
+        let doc: serde_json::Map<String, serde_json::Value> = row
+            .get_column_iter()
+            .map(|(column_name, field)| {
+                let field_name = row_conversion
+                    .column_mapping
+                    .get(column_name)
+                    .unwrap_or(column_name)
+                    .clone();
+                (field_name, field.to_json_value())
+            })
+            .collect();
+        docs.push(serde_json::to_string(&doc)?);
+    }
+    Ok(Some((docs, num_row_groups)))
+}
+
+impl ParquetSource {
+    /// Removes the local copy of a stored file once all its row groups are read.
+    fn remove_downloaded_file(&self, cursor: &ParquetFileCursor) {
+        if cursor.storage_path_opt.is_some() {
+            if let Err(error) = std::fs::remove_file(&cursor.filepath) {
+                warn!(
+                    filepath=%cursor.filepath.display(),
+                    error=?error,
+                    "Failed to remove downloaded Parquet file."
+                );
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Source for ParquetSource {
+    async fn emit_batches(
+        &mut self,
+        batch_sink: &Mailbox<Indexer>,
+        ctx: &SourceContext,
+    ) -> Result<Duration, ActorExitStatus> {
+        while let Some(mut cursor) = self.cursors.pop_front() {
+            if let Some(storage_path) = &cursor.storage_path_opt {
+                if !cursor.filepath.exists() {
+                    let storage = self
+                        .storage_opt
+                        .as_ref()
+                        .expect("The storage of the source should be resolved.");
+                    // Files read by a previous import are only downloaded if they have row groups
+                    // left to read.
+                    if cursor.next_row_group_ord > 0 {
+                        let num_row_groups = ctx
+                            .protect_future(num_stored_row_groups(&**storage, storage_path))
+                            .await?;
+                        if cursor.next_row_group_ord >= num_row_groups {
+                            continue;
+                        }
+                    }
+                    ctx.protect_future(storage.copy_to_file(storage_path, &cursor.filepath))
+                        .await
+                        .with_context(|| {
+                            format!("Failed to download `{}`.", cursor.partition_id.0)
+                        })?;
+                    self.counters.num_files_downloaded += 1;
+                }
+            }
+            let filepath = cursor.filepath.clone();
+            let row_group_ord = cursor.next_row_group_ord;
+            let row_conversion = self.row_conversion.clone();
+            let row_group_opt = tokio::task::spawn_blocking(move || {
+                read_row_group(&filepath, row_group_ord, &row_conversion)
+            })
+            .await
+            .map_err(|join_error| anyhow::anyhow!(join_error))??;
+            let (docs, num_row_groups) = match row_group_opt {
+                Some(row_group) => row_group,
+                None => {
+                    self.remove_downloaded_file(&cursor);
+                    continue;
+                }
+            };
+            self.counters.num_row_groups_processed += 1;
+            self.counters.num_rows_processed += docs.len() as u64;
+
+            let mut checkpoint_delta = SourceCheckpointDelta::default();
+            checkpoint_delta
+                .record_partition_delta(
+                    cursor.partition_id.clone(),
+                    Position::from(row_group_ord as u64),
+                    Position::from(row_group_ord as u64 + 1),
+                )
+                .context("Failed to record partition delta.")?;
+            cursor.next_row_group_ord += 1;
+            if cursor.next_row_group_ord < num_row_groups {
+                self.cursors.push_front(cursor);
+            } else {
+                self.remove_downloaded_file(&cursor);
+            }
+            ctx.send_message(batch_sink, RawDocBatch::new(docs, checkpoint_delta))
+                .await?;
+            return Ok(Duration::default());
+        }
+        info!("Reached end of source.");
+        // Removes the scratch directory along with its remaining files.
+        self.scratch_dir_opt.take();
+        ctx.send_exit_with_success(batch_sink).await?;
+        Err(ActorExitStatus::Success)
+    }
+
+    fn name(&self) -> String {
+        format!("ParquetSource {{ source_id={} }}", self.source_id)
+    }
+
+    fn observable_state(&self) -> serde_json::Value {
+        serde_json::to_value(&self.counters).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+    use quickwit_actors::{create_test_mailbox, Actor, Command, Universe};
+    use quickwit_common::uri::Uri;
+    use quickwit_config::{SourceConfig, SourceParams};
+    use quickwit_metastore::metastore_for_test;
+    use serde_json::json;
+
+    use super::*;
+    use crate::source::SourceActor;
+
+    /// Writes a Parquet file with one row group per item of `row_groups`.
+    fn write_parquet_file(filepath: &Path, row_groups: &[&[(i64, &str)]]) -> anyhow::Result<()> {
+        let schema = Arc::new(parse_message_type(
+            "message event { REQUIRED INT64 id; REQUIRED BINARY body (UTF8); }",
+        )?);
+        let properties = Arc::new(WriterProperties::builder().build());
+        let file = File::create(filepath)?;
+        let mut file_writer = SerializedFileWriter::new(file, schema, properties)?;
+        for rows in row_groups {
+            let ids: Vec<i64> = rows.iter().map(|(id, _)| *id).collect();
+            let bodies: Vec<ByteArray> = rows
+                .iter()
+                .map(|(_, body)| ByteArray::from(*body))
+                .collect();
+            let mut row_group_writer = file_writer.next_row_group()?;
+            let mut column_writer = row_group_writer.next_column()?.unwrap();
+            column_writer
+                .typed::<Int64Type>()
+                .write_batch(&ids, None, None)?;
+            column_writer.close()?;
+            let mut column_writer = row_group_writer.next_column()?.unwrap();
+            column_writer
+                .typed::<ByteArrayType>()
+                .write_batch(&bodies, None, None)?;
+            column_writer.close()?;
+            row_group_writer.close()?;
+        }
+        file_writer.close()?;
+        Ok(())
+    }
+
+    fn parquet_source_params(filepaths: Vec<PathBuf>) -> ParquetSourceParams {
+        ParquetSourceParams {
+            filepaths,
+            uri: None,
+            columns: Vec::new(),
+            column_mapping: BTreeMap::from([("body".to_string(), "message".to_string())]),
+        }
+    }
+
+    async fn run_parquet_source(
+        params: ParquetSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<(Vec<RawDocBatch>, serde_json::Value)> {
+        let universe = Universe::new();
+        let (indexer_mailbox, indexer_inbox) = create_test_mailbox();
+        let parquet_source = ParquetSourceFactory::typed_create_source(
+            SourceExecutionContext::for_test(
+                metastore_for_test(),
+                "test-index",
+                SourceConfig {
+                    source_id: "test-parquet-source".to_string(),
+                    num_pipelines: 1,
+                    profile: None,
+                    rate_limit: None,
+                    transforms: Vec::new(),
+                    strict_ordering: false,
+                    source_params: SourceParams::Parquet(params.clone()),
+                },
+            ),
+            params,
+            checkpoint,
+        )
+        .await?;
+        let parquet_source_actor = SourceActor {
+            source: Box::new(parquet_source),
+            indexer_mailbox,
+            rate_limiter_opt: None,
+        };
+        assert_eq!(
+            parquet_source_actor.name(),
+            "ParquetSource { source_id=test-parquet-source }"
+        );
+        let (_parquet_source_mailbox, parquet_source_handle) =
+            universe.spawn_actor(parquet_source_actor).spawn();
+        let (actor_termination, last_observation) = parquet_source_handle.join().await;
+        assert!(actor_termination.is_success());
+        let messages = indexer_inbox.drain_for_test();
+        assert!(matches!(
+            messages.last().unwrap().downcast_ref::<Command>().unwrap(),
+            &Command::ExitWithSuccess
+        ));
+        let batches = messages
+            .into_iter()
+            .flat_map(|message| message.downcast::<RawDocBatch>().ok())
+            .map(|batch| *batch)
+            .collect();
+        Ok((batches, last_observation))
+    }
+
+    #[tokio::test]
+    async fn test_parquet_source() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let filepath_0 = temp_dir.path().join("events-0.parquet");
+        let filepath_1 = temp_dir.path().join("events-1.parquet");
+        write_parquet_file(&filepath_0, &[&[(0, "foo"), (1, "bar")], &[(2, "baz")]])?;
+        write_parquet_file(&filepath_1, &[&[(3, "qux")]])?;
+
+        let params = parquet_source_params(vec![filepath_0.clone(), filepath_1]);
+        let (batches, last_observation) =
+            run_parquet_source(params, SourceCheckpoint::default()).await?;
+        assert_eq!(
+            last_observation,
+            json!({
+                "num_files_downloaded": 0,
+                "num_row_groups_processed": 3,
+                "num_rows_processed": 4
+            })
+        );
+        assert_eq!(batches.len(), 3);
+        let docs: Vec<serde_json::Value> = batches[0]
+            .docs
+            .iter()
+            .map(|doc| serde_json::from_str(doc).unwrap())
+            .collect();
+        assert_eq!(
+            docs,
+            [
+                json!({"id": 0, "message": "foo"}),
+                json!({"id": 1, "message": "bar"})
+            ]
+        );
+        let partition_id = PartitionId::from(filepath_0.to_string_lossy().to_string());
+        let mut expected_checkpoint_delta = SourceCheckpointDelta::default();
+        expected_checkpoint_delta.record_partition_delta(
+            partition_id,
+            Position::from(1u64),
+            Position::from(2u64),
+        )?;
+        assert_eq!(batches[1].checkpoint_delta, expected_checkpoint_delta);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_parquet_source_resume_from_checkpoint() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let filepath_0 = temp_dir.path().join("events-0.parquet");
+        let filepath_1 = temp_dir.path().join("events-1.parquet");
+        write_parquet_file(&filepath_0, &[&[(0, "foo"), (1, "bar")], &[(2, "baz")]])?;
+        write_parquet_file(&filepath_1, &[&[(3, "qux")]])?;
+
+        let mut checkpoint = SourceCheckpoint::default();
+        let mut checkpoint_delta = SourceCheckpointDelta::default();
+        checkpoint_delta.record_partition_delta(
+            PartitionId::from(filepath_0.to_string_lossy().to_string()),
+            Position::from(0u64),
+            Position::from(1u64),
+        )?;
+        checkpoint_delta.record_partition_delta(
+            PartitionId::from(filepath_1.to_string_lossy().to_string()),
+            Position::from(0u64),
+            Position::from(1u64),
+        )?;
+        checkpoint.try_apply_delta(checkpoint_delta)?;
+
+        let params = parquet_source_params(vec![filepath_0, filepath_1]);
+        let (batches, _) = run_parquet_source(params, checkpoint).await?;
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].docs, [r#"{"id":2,"message":"baz"}"#]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_parquet_source_storage_prefix() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let filepath = temp_dir.path().join("events.parquet");
+        write_parquet_file(&filepath, &[&[(0, "foo"), (1, "bar")], &[(2, "baz")]])?;
+        let payload = std::fs::read(&filepath)?;
+
+        let uri = Uri::new("ram:///test-parquet-source/exports".to_string());
+        let storage = quickwit_storage_uri_resolver().resolve(&uri)?;
+        storage
+            .put(
+                Path::new("2022/events-0.parquet"),
+                Box::new(payload.clone()),
+            )
+            .await?;
+        storage
+            .put(Path::new("2022/events-1.parquet"), Box::new(payload))
+            .await?;
+        storage
+            .put(Path::new("2022/_SUCCESS"), Box::new(Vec::new()))
+            .await?;
+
+        // The first file was imported by a previous run.
+        let mut checkpoint = SourceCheckpoint::default();
+        let mut checkpoint_delta = SourceCheckpointDelta::default();
+        checkpoint_delta.record_partition_delta(
+            PartitionId::from("ram:///test-parquet-source/exports/2022/events-0.parquet"),
+            Position::from(0u64),
+            Position::from(2u64),
+        )?;
+        checkpoint.try_apply_delta(checkpoint_delta)?;
+
+        let params = ParquetSourceParams {
+            filepaths: Vec::new(),
+            uri: Some(uri),
+            columns: vec!["body".to_string()],
+            column_mapping: BTreeMap::from([("body".to_string(), "message".to_string())]),
+        };
+        let (batches, last_observation) = run_parquet_source(params, checkpoint).await?;
+        assert_eq!(
+            last_observation,
+            json!({
+                "num_files_downloaded": 1,
+                "num_row_groups_processed": 2,
+                "num_rows_processed": 3
+            })
+        );
+        assert_eq!(batches.len(), 2);
+        assert_eq!(
+            batches[0].docs,
+            [r#"{"message":"foo"}"#, r#"{"message":"bar"}"#]
+        );
+        let mut expected_checkpoint_delta = SourceCheckpointDelta::default();
+        expected_checkpoint_delta.record_partition_delta(
+            PartitionId::from("ram:///test-parquet-source/exports/2022/events-1.parquet"),
+            Position::from(1u64),
+            Position::from(2u64),
+        )?;
+        assert_eq!(batches[1].checkpoint_delta, expected_checkpoint_delta);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parquet_source_projection_missing_column() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let filepath = temp_dir.path().join("events.parquet");
+        write_parquet_file(&filepath, &[&[(0, "foo")]])?;
+        let row_conversion = RowConversion {
+            columns: vec!["severity".to_string()],
+            column_mapping: BTreeMap::new(),
+        };
+        let error = read_row_group(&filepath, 0, &row_conversion).unwrap_err();
+        assert!(error.to_string().contains("has no column `severity`"));
+        Ok(())
+    }
+}
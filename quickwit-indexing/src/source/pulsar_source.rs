@@ -0,0 +1,671 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context};
+use async_trait::async_trait;
+use futures::StreamExt;
+use itertools::Itertools;
+use pulsar::consumer::{InitialPosition, Message};
+use pulsar::message::proto::MessageIdData;
+use pulsar::{Authentication, Consumer, ConsumerOptions, Pulsar, SubType, TokioExecutor};
+use quickwit_actors::{ActorExitStatus, Mailbox};
+use quickwit_config::{PulsarSourceParams, PulsarSubscriptionType};
+use quickwit_metastore::checkpoint::{
+    PartitionId, Position, SourceCheckpoint, SourceCheckpointDelta,
+};
+use serde_json::json;
+use tokio::time;
+use tracing::{debug, info, warn};
+
+use crate::actors::Indexer;
+use crate::models::RawDocBatch;
+use crate::source::{Source, SourceContext, SourceExecutionContext, TypedSourceFactory};
+
+/// Number of bytes after which we cut a new batch.
+///
+/// See the Kafka source for the rationale behind this value.
+const BATCH_NUM_BYTES_LIMIT: u64 = 5_000_000;
+
+type PulsarConsumer = Consumer<Vec<u8>, TokioExecutor>;
+
+/// Factory for instantiating a `PulsarSource`.
+pub struct PulsarSourceFactory;
+
+#[async_trait]
+impl TypedSourceFactory for PulsarSourceFactory {
+    type Source = PulsarSource;
+    type Params = PulsarSourceParams;
+
+    async fn typed_create_source(
+        ctx: Arc<SourceExecutionContext>,
+        params: PulsarSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<Self::Source> {
+        PulsarSource::try_new(ctx, params, checkpoint).await
+    }
+}
+
+#[derive(Debug)]
+struct PulsarMessage {
+    /// Fully qualified name of the topic partition the message belongs to.
+    partition_id: PartitionId,
+    position: Position,
+    doc_opt: Option<String>,
+    payload_len: u64,
+}
+
+impl From<Message<Vec<u8>>> for PulsarMessage {
+    fn from(message: Message<Vec<u8>>) -> Self {
+        let message_id = message.message_id();
+        let payload = &message.payload.data;
+        Self {
+            partition_id: PartitionId::from(message.topic.as_str()),
+            position: position_from_message_id(message_id),
+            doc_opt: parse_message_payload(&message.topic, message_id, payload),
+            payload_len: payload.len() as u64,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct PulsarSourceState {
+    /// Position of the last message processed for each partition.
+    pub current_positions: BTreeMap<PartitionId, Position>,
+    /// Number of bytes processed by the source.
+    pub num_bytes_processed: u64,
+    /// Number of messages processed by the source (including invalid messages).
+    pub num_messages_processed: u64,
+    // Number of invalid messages, i.e., that were empty or could not be parsed.
+    pub num_invalid_messages: u64,
+    /// Number of messages redelivered by the broker that were already processed and skipped.
+    pub num_skipped_messages: u64,
+}
+
+impl PulsarSourceState {
+    fn process_message(
+        &mut self,
+        message: PulsarMessage,
+        batch: &mut BatchBuilder,
+    ) -> anyhow::Result<()> {
+        let PulsarMessage {
+            partition_id,
+            position,
+            doc_opt,
+            payload_len,
+        } = message;
+
+        let previous_position = self
+            .current_positions
+            .get(&partition_id)
+            .cloned()
+            .unwrap_or(Position::Beginning);
+        // After a seek or a reconnection, the broker may redeliver messages that were already
+        // processed.
+        if position <= previous_position {
+            self.num_skipped_messages += 1;
+            return Ok(());
+        }
+        if let Some(doc) = doc_opt {
+            batch.push(doc, payload_len);
+        } else {
+            self.num_invalid_messages += 1;
+        }
+        self.num_bytes_processed += payload_len;
+        self.num_messages_processed += 1;
+
+        self.current_positions
+            .insert(partition_id.clone(), position.clone());
+        batch
+            .checkpoint_delta
+            .record_partition_delta(partition_id, previous_position, position)
+            .context("Failed to record partition delta.")?;
+        Ok(())
+    }
+}
+
+/// A `PulsarSource` consumes one or several topics through a subscription and forwards their
+/// messages to an `Indexer`.
+///
+/// The partitions of the source are the topic partitions, and positions are the IDs of the
+/// messages. Messages are only acknowledged once they have been published in a split, so the
+/// subscription never gets ahead of the checkpoint.
+pub struct PulsarSource {
+    ctx: Arc<SourceExecutionContext>,
+    topics: Vec<String>,
+    subscription_name: String,
+    pulsar: Pulsar<TokioExecutor>,
+    consumer: PulsarConsumer,
+    state: PulsarSourceState,
+    /// Positions published in a split that remain to be acknowledged, populated by
+    /// `suggest_truncate`.
+    pending_acks: Mutex<BTreeMap<PartitionId, Position>>,
+}
+
+impl fmt::Debug for PulsarSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PulsarSource {{ source_id: {}, topics: {} }}",
+            self.ctx.source_config.source_id,
+            self.topics.join(", ")
+        )
+    }
+}
+
+impl PulsarSource {
+    /// Instantiates a new `PulsarSource`.
+    pub async fn try_new(
+        ctx: Arc<SourceExecutionContext>,
+        params: PulsarSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<Self> {
+        let topics = params.topics.clone();
+        let subscription_name = params.subscription_name.clone().unwrap_or_else(|| {
+            format!("quickwit-{}-{}", ctx.index_id, ctx.source_config.source_id)
+        });
+        let pulsar = connect_pulsar(&params).await?;
+        let mut consumer: PulsarConsumer = pulsar
+            .consumer()
+            .with_topics(&topics)
+            .with_subscription(&subscription_name)
+            .with_subscription_type(subscription_type(params.subscription_type))
+            .with_consumer_name(format!(
+                "{}-{}",
+                subscription_name,
+                quickwit_common::rand::append_random_suffix("consumer")
+            ))
+            .with_batch_size(params.batch_size)
+            .with_options(ConsumerOptions {
+                initial_position: InitialPosition::Earliest,
+                ..Default::default()
+            })
+            .build()
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to subscribe to topics `{}` with subscription `{}`.",
+                    topics.join(", "),
+                    subscription_name
+                )
+            })?;
+        // The subscription cursor is never ahead of the checkpoint, but it may lag behind it, or
+        // be unrelated to it if the checkpoint was reset. Positioning the consumer on the
+        // checkpoint avoids reading the same messages again. The messages already indexed that
+        // are redelivered anyway are skipped.
+        for (partition_id, position) in checkpoint.iter() {
+            let message_id = match message_id_from_position(&position)? {
+                Some(message_id) => message_id,
+                None => continue,
+            };
+            if let Err(error) = consumer
+                .seek(
+                    Some(vec![partition_id.0.to_string()]),
+                    Some(message_id),
+                    None,
+                    pulsar.clone(),
+                )
+                .await
+            {
+                warn!(
+                    partition_id=%partition_id.0,
+                    position=%position.as_str(),
+                    error=?error,
+                    "Failed to seek partition to its checkpoint position."
+                );
+            }
+        }
+        info!(
+            index_id=%ctx.index_id,
+            source_id=%ctx.source_config.source_id,
+            topics=%topics.join(", "),
+            subscription_name=%subscription_name,
+            subscription_type=?params.subscription_type,
+            "Starting Pulsar source."
+        );
+        let state = PulsarSourceState {
+            current_positions: checkpoint.iter().collect(),
+            ..Default::default()
+        };
+        Ok(PulsarSource {
+            ctx,
+            topics,
+            subscription_name,
+            pulsar,
+            consumer,
+            state,
+            pending_acks: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    /// Loads the position of a partition that was not consumed by the source so far from the
+    /// checkpoint of the index. With a failover subscription, the partition may have been
+    /// consumed by another pipeline in the meantime.
+    async fn load_partition_position(
+        &mut self,
+        ctx: &SourceContext,
+        partition_id: &PartitionId,
+    ) -> anyhow::Result<()> {
+        let index_metadata = ctx
+            .protect_future(self.ctx.metastore.index_metadata(&self.ctx.index_id))
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to fetch index metadata for index `{}`.",
+                    self.ctx.index_id
+                )
+            })?;
+        let position = index_metadata
+            .checkpoint
+            .source_checkpoint(&self.ctx.source_config.source_id)
+            .and_then(|checkpoint| checkpoint.position_for_partition(partition_id))
+            .cloned()
+            .unwrap_or(Position::Beginning);
+        info!(
+            partition_id=%partition_id.0,
+            position=%position.as_str(),
+            "New partition assignment."
+        );
+        self.state
+            .current_positions
+            .insert(partition_id.clone(), position);
+        Ok(())
+    }
+
+    /// Acknowledges the messages published in a split.
+    async fn process_pending_acks(&mut self) {
+        let pending_acks = std::mem::take(&mut *self.pending_acks.lock().unwrap());
+        for (partition_id, position) in pending_acks {
+            // Acknowledging a message ID acknowledges all the messages of its batch, some of
+            // which may not have been indexed yet. We acknowledge the previous entry instead.
+            let message_id = match message_id_from_position(&position) {
+                Ok(Some(message_id)) if message_id.entry_id > 0 => MessageIdData {
+                    entry_id: message_id.entry_id - 1,
+                    ..message_id
+                },
+                _ => continue,
+            };
+            if let Err(error) = self
+                .consumer
+                .cumulative_ack_with_id(&partition_id.0, message_id)
+                .await
+            {
+                warn!(
+                    partition_id=%partition_id.0,
+                    position=%position.as_str(),
+                    error=?error,
+                    "Failed to acknowledge messages."
+                );
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct BatchBuilder {
+    docs: Vec<String>,
+    num_bytes: u64,
+    checkpoint_delta: SourceCheckpointDelta,
+}
+
+impl BatchBuilder {
+    fn build(self) -> RawDocBatch {
+        RawDocBatch {
+            docs: self.docs,
+            checkpoint_delta: self.checkpoint_delta,
+        }
+    }
+
+    fn push(&mut self, doc: String, num_bytes: u64) {
+        self.docs.push(doc);
+        self.num_bytes += num_bytes;
+    }
+}
+
+#[async_trait]
+impl Source for PulsarSource {
+    async fn emit_batches(
+        &mut self,
+        indexer_mailbox: &Mailbox<Indexer>,
+        ctx: &SourceContext,
+    ) -> Result<Duration, ActorExitStatus> {
+        self.process_pending_acks().await;
+
+        let now = Instant::now();
+        let mut batch = BatchBuilder::default();
+        let deadline = time::sleep(quickwit_actors::HEARTBEAT / 2);
+        tokio::pin!(deadline);
+
+        loop {
+            tokio::select! {
+                message_opt = self.consumer.next() => {
+                    let message = message_opt
+                        .ok_or_else(|| ActorExitStatus::from(anyhow!("Consumer was dropped.")))?
+                        .map_err(|error| ActorExitStatus::from(anyhow!(error)))?;
+                    let message = PulsarMessage::from(message);
+                    if !self.state.current_positions.contains_key(&message.partition_id) {
+                        self.load_partition_position(ctx, &message.partition_id).await?;
+                    }
+                    self.state.process_message(message, &mut batch)?;
+                    if batch.num_bytes >= BATCH_NUM_BYTES_LIMIT {
+                        break;
+                    }
+                }
+                _ = &mut deadline => {
+                    break;
+                }
+            }
+            ctx.record_progress();
+        }
+        if !batch.checkpoint_delta.is_empty() {
+            debug!(
+                num_docs=%batch.docs.len(),
+                num_bytes=%batch.num_bytes,
+                num_millis=%now.elapsed().as_millis(),
+                "Sending doc batch to indexer.");
+            let message = batch.build();
+            ctx.send_message(indexer_mailbox, message).await?;
+        }
+        Ok(Duration::default())
+    }
+
+    async fn suggest_truncate(
+        &self,
+        checkpoint: SourceCheckpoint,
+        _ctx: &SourceContext,
+    ) -> anyhow::Result<()> {
+        let mut pending_acks = self.pending_acks.lock().unwrap();
+        for (partition_id, position) in checkpoint.iter() {
+            if self.state.current_positions.contains_key(&partition_id) {
+                pending_acks.insert(partition_id, position);
+            }
+        }
+        Ok(())
+    }
+
+    async fn finalize(
+        &mut self,
+        _exit_status: &ActorExitStatus,
+        _ctx: &SourceContext,
+    ) -> anyhow::Result<()> {
+        self.process_pending_acks().await;
+        if let Err(error) = self.consumer.close().await {
+            warn!(error=?error, "Failed to close Pulsar consumer.");
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> String {
+        format!(
+            "PulsarSource{{source_id={}}}",
+            self.ctx.source_config.source_id
+        )
+    }
+
+    fn observable_state(&self) -> serde_json::Value {
+        let current_positions: Vec<(&str, &str)> = self
+            .state
+            .current_positions
+            .iter()
+            .map(|(partition_id, position)| (partition_id.0.as_str(), position.as_str()))
+            .collect();
+        json!({
+            "index_id": self.ctx.index_id,
+            "source_id": self.ctx.source_config.source_id,
+            "topics": self.topics,
+            "subscription_name": self.subscription_name,
+            "current_positions": current_positions,
+            "num_bytes_processed": self.state.num_bytes_processed,
+            "num_messages_processed": self.state.num_messages_processed,
+            "num_invalid_messages": self.state.num_invalid_messages,
+            "num_skipped_messages": self.state.num_skipped_messages,
+        })
+    }
+}
+
+fn subscription_type(subscription_type: PulsarSubscriptionType) -> SubType {
+    match subscription_type {
+        PulsarSubscriptionType::Exclusive => SubType::Exclusive,
+        PulsarSubscriptionType::Failover => SubType::Failover,
+    }
+}
+
+async fn connect_pulsar(params: &PulsarSourceParams) -> anyhow::Result<Pulsar<TokioExecutor>> {
+    let mut builder = Pulsar::builder(params.address.clone(), TokioExecutor);
+    if let Some(token) = &params.authentication_token {
+        builder = builder.with_auth(Authentication {
+            name: "token".to_string(),
+            data: token.clone().into_bytes(),
+        });
+    }
+    let pulsar = builder
+        .build()
+        .await
+        .with_context(|| format!("Failed to connect to Pulsar cluster `{}`.", params.address))?;
+    Ok(pulsar)
+}
+
+/// Checks whether we can establish a connection to the Pulsar cluster and whether the topics
+/// exist.
+pub(super) async fn check_connectivity(params: PulsarSourceParams) -> anyhow::Result<()> {
+    let pulsar = connect_pulsar(&params).await?;
+    for topic in &params.topics {
+        pulsar
+            .lookup_partitioned_topic_number(topic.as_str())
+            .await
+            .with_context(|| format!("Failed to look up topic `{}`.", topic))?;
+    }
+    Ok(())
+}
+
+/// Encodes a message ID as a position, preserving the order of the messages within a partition.
+fn position_from_message_id(message_id: &MessageIdData) -> Position {
+    let batch_index = message_id.batch_index.unwrap_or(0).max(0);
+    Position::from(format!(
+        "{:0>20},{:0>20},{:0>10}",
+        message_id.ledger_id, message_id.entry_id, batch_index
+    ))
+}
+
+/// Decodes the message ID of a position, pointing to the entry (a single message or a batch of
+/// messages) containing the message. Returns `None` for the beginning of the partition.
+fn message_id_from_position(position: &Position) -> anyhow::Result<Option<MessageIdData>> {
+    let position_str = match position {
+        Position::Beginning => return Ok(None),
+        Position::Offset(position_str) => position_str,
+    };
+    let (ledger_id, entry_id, _batch_index): (u64, u64, u32) = position_str
+        .split(',')
+        .collect_tuple()
+        .and_then(|(ledger_id, entry_id, batch_index)| {
+            Some((
+                ledger_id.parse().ok()?,
+                entry_id.parse().ok()?,
+                batch_index.parse().ok()?,
+            ))
+        })
+        .ok_or_else(|| anyhow!("Failed to parse Pulsar message ID from `{}`.", position_str))?;
+    Ok(Some(MessageIdData {
+        ledger_id,
+        entry_id,
+        ..Default::default()
+    }))
+}
+
+/// Converts the raw bytes of the message payload to a `String` skipping corrupted or empty
+/// messages.
+fn parse_message_payload(
+    topic: &str,
+    message_id: &MessageIdData,
+    payload: &[u8],
+) -> Option<String> {
+    if payload.is_empty() {
+        debug!(
+            topic = ?topic,
+            message_id = ?message_id,
+            "Message payload is empty."
+        );
+        return None;
+    }
+    match std::str::from_utf8(payload) {
+        Ok(doc) => Some(doc.to_string()),
+        Err(error) => {
+            warn!(
+                topic = ?topic,
+                message_id = ?message_id,
+                error = ?error,
+                "Failed to deserialize message payload."
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message_id(ledger_id: u64, entry_id: u64, batch_index: Option<i32>) -> MessageIdData {
+        MessageIdData {
+            ledger_id,
+            entry_id,
+            batch_index,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_position_from_message_id() {
+        let position = position_from_message_id(&message_id(1, 2, None));
+        assert_eq!(
+            position.as_str(),
+            "00000000000000000001,00000000000000000002,0000000000"
+        );
+        let message_id_opt = message_id_from_position(&position).unwrap();
+        assert_eq!(message_id_opt, Some(message_id(1, 2, None)));
+
+        // The order of the positions follows the order of the message IDs.
+        let positions = [
+            position_from_message_id(&message_id(1, 2, None)),
+            position_from_message_id(&message_id(1, 2, Some(3))),
+            position_from_message_id(&message_id(1, 10, Some(0))),
+            position_from_message_id(&message_id(2, 0, None)),
+        ];
+        assert!(positions.windows(2).all(|window| window[0] < window[1]));
+
+        assert_eq!(
+            message_id_from_position(&Position::Beginning).unwrap(),
+            None
+        );
+        assert!(message_id_from_position(&Position::from("foo")).is_err());
+    }
+
+    #[test]
+    fn test_parse_message_payload() {
+        let message_id = message_id(1, 2, None);
+        assert_eq!(
+            parse_message_payload("my-topic", &message_id, b"{\"body\": \"foo\"}"),
+            Some("{\"body\": \"foo\"}".to_string())
+        );
+        assert_eq!(parse_message_payload("my-topic", &message_id, b""), None);
+        assert_eq!(
+            parse_message_payload("my-topic", &message_id, &[0xff, 0xfe]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_pulsar_source_state_process_message() {
+        let partition_id_1 = PartitionId::from("persistent://public/default/my-topic-partition-0");
+        let partition_id_2 = PartitionId::from("persistent://public/default/my-topic-partition-1");
+        let mut state = PulsarSourceState {
+            current_positions: BTreeMap::from_iter([(
+                partition_id_2.clone(),
+                position_from_message_id(&message_id(1, 5, None)),
+            )]),
+            ..Default::default()
+        };
+        let mut batch = BatchBuilder::default();
+
+        let message = PulsarMessage {
+            partition_id: partition_id_1.clone(),
+            position: position_from_message_id(&message_id(1, 0, None)),
+            doc_opt: Some("{\"body\": \"foo\"}".to_string()),
+            payload_len: 15,
+        };
+        state.process_message(message, &mut batch).unwrap();
+
+        let message = PulsarMessage {
+            partition_id: partition_id_1.clone(),
+            position: position_from_message_id(&message_id(1, 1, None)),
+            doc_opt: None,
+            payload_len: 3,
+        };
+        state.process_message(message, &mut batch).unwrap();
+
+        // Already processed message.
+        let message = PulsarMessage {
+            partition_id: partition_id_2.clone(),
+            position: position_from_message_id(&message_id(1, 5, None)),
+            doc_opt: Some("{\"body\": \"bar\"}".to_string()),
+            payload_len: 15,
+        };
+        state.process_message(message, &mut batch).unwrap();
+
+        let message = PulsarMessage {
+            partition_id: partition_id_2.clone(),
+            position: position_from_message_id(&message_id(1, 6, None)),
+            doc_opt: Some("{\"body\": \"baz\"}".to_string()),
+            payload_len: 15,
+        };
+        state.process_message(message, &mut batch).unwrap();
+
+        assert_eq!(batch.docs.len(), 2);
+        assert_eq!(batch.num_bytes, 30);
+        assert_eq!(state.num_bytes_processed, 33);
+        assert_eq!(state.num_messages_processed, 3);
+        assert_eq!(state.num_invalid_messages, 1);
+        assert_eq!(state.num_skipped_messages, 1);
+        assert_eq!(
+            state.current_positions.get(&partition_id_1).unwrap(),
+            &position_from_message_id(&message_id(1, 1, None))
+        );
+
+        let mut expected_checkpoint_delta = SourceCheckpointDelta::default();
+        expected_checkpoint_delta
+            .record_partition_delta(
+                partition_id_1,
+                Position::Beginning,
+                position_from_message_id(&message_id(1, 1, None)),
+            )
+            .unwrap();
+        expected_checkpoint_delta
+            .record_partition_delta(
+                partition_id_2,
+                position_from_message_id(&message_id(1, 5, None)),
+                position_from_message_id(&message_id(1, 6, None)),
+            )
+            .unwrap();
+        assert_eq!(batch.checkpoint_delta, expected_checkpoint_delta);
+    }
+}
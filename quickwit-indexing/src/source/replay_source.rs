@@ -0,0 +1,348 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use quickwit_actors::{create_mailbox, ActorExitStatus, Mailbox, QueueCapacity, Universe};
+use quickwit_config::ReplaySourceParams;
+use quickwit_metastore::checkpoint::{SourceCheckpoint, SourceCheckpointDelta};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::actors::Indexer;
+use crate::models::RawDocBatch;
+use crate::source::{
+    quickwit_supported_sources, Source, SourceActor, SourceContext, SourceExecutionContext,
+    TypedSourceFactory,
+};
+
+/// Batch emitted by a source, as recorded in a fixture file. A fixture file holds one recorded
+/// batch per line, serialized as JSON.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RecordedBatch {
+    pub docs: Vec<String>,
+    pub checkpoint_delta: SourceCheckpointDelta,
+}
+
+impl From<RawDocBatch> for RecordedBatch {
+    fn from(raw_doc_batch: RawDocBatch) -> Self {
+        RecordedBatch {
+            docs: raw_doc_batch.docs,
+            checkpoint_delta: raw_doc_batch.checkpoint_delta,
+        }
+    }
+}
+
+impl From<RecordedBatch> for RawDocBatch {
+    fn from(recorded_batch: RecordedBatch) -> Self {
+        RawDocBatch::new(recorded_batch.docs, recorded_batch.checkpoint_delta)
+    }
+}
+
+/// Runs the source described by `ctx` from `checkpoint` and records the batches it emits to the
+/// fixture file at `fixture_path`, which can then be replayed by a `replay` source.
+///
+/// The source is killed if it does not reach its end within `max_duration`, which is usually the
+/// case of message queues such as Kafka. Returns the number of recorded batches.
+pub async fn record_source(
+    ctx: Arc<SourceExecutionContext>,
+    checkpoint: SourceCheckpoint,
+    fixture_path: &Path,
+    max_duration: Duration,
+) -> anyhow::Result<usize> {
+    let source = quickwit_supported_sources()
+        .load_source(ctx, checkpoint)
+        .await?;
+    let universe = Universe::new();
+    let (indexer_mailbox, indexer_inbox) =
+        create_mailbox::<Indexer>("RecordingIndexer".to_string(), QueueCapacity::Unbounded);
+    let source_actor = SourceActor {
+        source,
+        indexer_mailbox,
+        rate_limiter_opt: None,
+    };
+    let (_source_mailbox, source_handle) = universe.spawn_actor(source_actor).spawn();
+    let exit_status = match tokio::time::timeout(max_duration, source_handle.join()).await {
+        Ok((exit_status, _)) => exit_status,
+        Err(_) => {
+            info!(max_duration=?max_duration, "Source recording timed out.");
+            universe.kill();
+            ActorExitStatus::Killed
+        }
+    };
+    if let ActorExitStatus::Failure(error) = exit_status {
+        bail!("Failed to record source: {:?}.", error);
+    }
+    let raw_doc_batches = indexer_inbox.drain_for_test_typed::<RawDocBatch>();
+    let num_recorded_batches = raw_doc_batches.len();
+    let mut fixture = String::new();
+    for raw_doc_batch in raw_doc_batches {
+        let recorded_batch = RecordedBatch::from(raw_doc_batch);
+        fixture.push_str(&serde_json::to_string(&recorded_batch)?);
+        fixture.push('\n');
+    }
+    tokio::fs::write(fixture_path, fixture)
+        .await
+        .with_context(|| format!("Failed to write fixture `{}`.", fixture_path.display()))?;
+    Ok(num_recorded_batches)
+}
+
+/// Source replaying the batches recorded in a fixture file by [`record_source`], skipping the
+/// batches already covered by its checkpoint.
+pub struct ReplaySource {
+    source_id: String,
+    remaining_batches: VecDeque<RecordedBatch>,
+    num_replayed_batches: usize,
+}
+
+impl fmt::Debug for ReplaySource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ReplaySource {{ source_id: {} }}", self.source_id)
+    }
+}
+
+pub struct ReplaySourceFactory;
+
+#[async_trait]
+impl TypedSourceFactory for ReplaySourceFactory {
+    type Source = ReplaySource;
+    type Params = ReplaySourceParams;
+    async fn typed_create_source(
+        ctx: Arc<SourceExecutionContext>,
+        params: ReplaySourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<Self::Source> {
+        let fixture = tokio::fs::read_to_string(&params.fixture_path)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to read fixture `{}`.",
+                    params.fixture_path.display()
+                )
+            })?;
+        let mut replayed_checkpoint = checkpoint;
+        let mut remaining_batches = VecDeque::new();
+        for (line_idx, line) in fixture.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let recorded_batch: RecordedBatch = serde_json::from_str(line).with_context(|| {
+                format!(
+                    "Failed to parse batch at line {} of fixture `{}`.",
+                    line_idx + 1,
+                    params.fixture_path.display()
+                )
+            })?;
+            // The batches whose checkpoint delta cannot be applied were already indexed.
+            if replayed_checkpoint
+                .try_apply_delta(recorded_batch.checkpoint_delta.clone())
+                .is_ok()
+            {
+                remaining_batches.push_back(recorded_batch);
+            }
+        }
+        Ok(ReplaySource {
+            source_id: ctx.source_config.source_id.clone(),
+            remaining_batches,
+            num_replayed_batches: 0,
+        })
+    }
+}
+
+#[async_trait]
+impl Source for ReplaySource {
+    async fn emit_batches(
+        &mut self,
+        batch_sink: &Mailbox<Indexer>,
+        ctx: &SourceContext,
+    ) -> Result<Duration, ActorExitStatus> {
+        let recorded_batch = match self.remaining_batches.pop_front() {
+            Some(recorded_batch) => recorded_batch,
+            None => {
+                info!("Reached end of source.");
+                ctx.send_exit_with_success(batch_sink).await?;
+                return Err(ActorExitStatus::Success);
+            }
+        };
+        ctx.send_message(batch_sink, RawDocBatch::from(recorded_batch))
+            .await?;
+        self.num_replayed_batches += 1;
+        Ok(Duration::default())
+    }
+
+    fn name(&self) -> String {
+        format!("ReplaySource {{ source_id={} }}", self.source_id)
+    }
+
+    fn observable_state(&self) -> serde_json::Value {
+        serde_json::json!({
+            "num_replayed_batches": self.num_replayed_batches,
+            "num_remaining_batches": self.remaining_batches.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_actors::{create_test_mailbox, Actor};
+    use quickwit_config::{SourceConfig, SourceParams, VecSourceParams};
+    use quickwit_metastore::metastore_for_test;
+    use serde_json::json;
+
+    use super::*;
+
+    fn vec_source_config() -> SourceConfig {
+        SourceConfig {
+            source_id: "test-vec-source".to_string(),
+            num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::Vec(VecSourceParams {
+                docs: (0..10)
+                    .map(|doc_idx| format!("{{\"id\": {doc_idx}}}"))
+                    .collect(),
+                batch_num_docs: 3,
+                partition: "partition".to_string(),
+            }),
+        }
+    }
+
+    fn replay_source_config(fixture_path: &Path) -> SourceConfig {
+        SourceConfig {
+            source_id: "test-replay-source".to_string(),
+            num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::Replay(ReplaySourceParams {
+                fixture_path: fixture_path.to_path_buf(),
+            }),
+        }
+    }
+
+    async fn replay(
+        fixture_path: &Path,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<(Vec<RawDocBatch>, serde_json::Value)> {
+        let universe = Universe::new();
+        let (indexer_mailbox, indexer_inbox) = create_test_mailbox();
+        let replay_source = ReplaySourceFactory::typed_create_source(
+            SourceExecutionContext::for_test(
+                metastore_for_test(),
+                "test-index",
+                replay_source_config(fixture_path),
+            ),
+            ReplaySourceParams {
+                fixture_path: fixture_path.to_path_buf(),
+            },
+            checkpoint,
+        )
+        .await?;
+        let replay_source_actor = SourceActor {
+            source: Box::new(replay_source),
+            indexer_mailbox,
+            rate_limiter_opt: None,
+        };
+        assert_eq!(
+            replay_source_actor.name(),
+            "ReplaySource { source_id=test-replay-source }"
+        );
+        let (_replay_source_mailbox, replay_source_handle) =
+            universe.spawn_actor(replay_source_actor).spawn();
+        let (actor_termination, last_observation) = replay_source_handle.join().await;
+        assert!(actor_termination.is_success());
+        Ok((
+            indexer_inbox.drain_for_test_typed::<RawDocBatch>(),
+            last_observation,
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay_source() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let fixture_path = temp_dir.path().join("fixture.ndjson");
+        let num_recorded_batches = record_source(
+            SourceExecutionContext::for_test(
+                metastore_for_test(),
+                "test-index",
+                vec_source_config(),
+            ),
+            SourceCheckpoint::default(),
+            &fixture_path,
+            Duration::from_secs(10),
+        )
+        .await?;
+        assert_eq!(num_recorded_batches, 4);
+
+        let (batches, last_observation) =
+            replay(&fixture_path, SourceCheckpoint::default()).await?;
+        assert_eq!(
+            last_observation,
+            json!({"num_replayed_batches": 4, "num_remaining_batches": 0})
+        );
+        assert_eq!(batches.len(), 4);
+        assert_eq!(
+            batches[0].docs,
+            ["{\"id\": 0}", "{\"id\": 1}", "{\"id\": 2}"]
+        );
+        assert_eq!(batches[3].docs, ["{\"id\": 9}"]);
+        assert_eq!(
+            format!("{:?}", batches[1].checkpoint_delta),
+            "∆(partition:(00000000000000000002..00000000000000000005])"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_replay_source_from_checkpoint() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let fixture_path = temp_dir.path().join("fixture.ndjson");
+        record_source(
+            SourceExecutionContext::for_test(
+                metastore_for_test(),
+                "test-index",
+                vec_source_config(),
+            ),
+            SourceCheckpoint::default(),
+            &fixture_path,
+            Duration::from_secs(10),
+        )
+        .await?;
+
+        let (batches, _) = replay(&fixture_path, SourceCheckpoint::default()).await?;
+        let mut checkpoint = SourceCheckpoint::default();
+        for batch in &batches[..2] {
+            checkpoint.try_apply_delta(batch.checkpoint_delta.clone())?;
+        }
+        let (replayed_batches, _) = replay(&fixture_path, checkpoint).await?;
+        assert_eq!(replayed_batches.len(), 2);
+        assert_eq!(replayed_batches[0].docs, batches[2].docs);
+        assert_eq!(replayed_batches[1].docs, batches[3].docs);
+        Ok(())
+    }
+}
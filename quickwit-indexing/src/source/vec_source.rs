@@ -123,11 +123,12 @@ impl Source for VecSource {
 #[cfg(test)]
 mod tests {
     use quickwit_actors::{create_test_mailbox, Actor, Command, Universe};
-    use quickwit_config::{SourceConfig, SourceParams};
+    use quickwit_config::{SourceConfig, SourceParams, SourceRateLimit};
     use quickwit_metastore::metastore_for_test;
     use serde_json::json;
 
     use super::*;
+    use crate::models::SourceRateLimiter;
     use crate::source::SourceActor;
 
     #[tokio::test]
@@ -150,6 +151,10 @@ mod tests {
                 SourceConfig {
                     source_id: "test-vec-source".to_string(),
                     num_pipelines: 1,
+                    profile: None,
+                    rate_limit: None,
+                    transforms: Vec::new(),
+                    strict_ordering: false,
                     source_params: SourceParams::Vec(params.clone()),
                 },
             ),
@@ -160,6 +165,7 @@ mod tests {
         let vec_source_actor = SourceActor {
             source: Box::new(vec_source),
             indexer_mailbox,
+            rate_limiter_opt: None,
         };
         assert_eq!(
             vec_source_actor.name(),
@@ -184,6 +190,56 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_vec_source_with_rate_limiter() -> anyhow::Result<()> {
+        let universe = Universe::new();
+        let (indexer_mailbox, indexer_inbox) = create_test_mailbox();
+        let docs = (0..10).map(|i| format!("{}", i)).collect();
+        let params = VecSourceParams {
+            docs,
+            batch_num_docs: 5,
+            partition: "".to_string(),
+        };
+        let rate_limit = SourceRateLimit {
+            max_docs_per_sec: Some(100),
+            max_bytes_per_sec: None,
+        };
+        let vec_source = VecSourceFactory::typed_create_source(
+            SourceExecutionContext::for_test(
+                metastore_for_test(),
+                "test-index",
+                SourceConfig {
+                    source_id: "test-vec-source".to_string(),
+                    num_pipelines: 1,
+                    profile: None,
+                    rate_limit: Some(rate_limit.clone()),
+                    transforms: Vec::new(),
+                    strict_ordering: false,
+                    source_params: SourceParams::Vec(params.clone()),
+                },
+            ),
+            params,
+            SourceCheckpoint::default(),
+        )
+        .await?;
+        // The indexer already received more docs than the budget allows.
+        let rate_limiter = SourceRateLimiter::new(&rate_limit);
+        rate_limiter.record_batch(105, 0);
+        let vec_source_actor = SourceActor {
+            source: Box::new(vec_source),
+            indexer_mailbox,
+            rate_limiter_opt: Some(rate_limiter.clone()),
+        };
+        let (_vec_source_mailbox, vec_source_handle) =
+            universe.spawn_actor(vec_source_actor).spawn();
+        let (actor_termination, last_observation) = vec_source_handle.join().await;
+        assert!(actor_termination.is_success());
+        assert_eq!(last_observation, json!({"next_item_idx": 10}));
+        assert_eq!(indexer_inbox.drain_for_test_typed::<RawDocBatch>().len(), 2);
+        assert!(rate_limiter.counters().num_throttles >= 1);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_vec_source_from_checkpoint() -> anyhow::Result<()> {
         let universe = Universe::new();
@@ -205,6 +261,10 @@ mod tests {
                 SourceConfig {
                     source_id: "test-vec-source".to_string(),
                     num_pipelines: 1,
+                    profile: None,
+                    rate_limit: None,
+                    transforms: Vec::new(),
+                    strict_ordering: false,
                     source_params: SourceParams::Vec(params.clone()),
                 },
             ),
@@ -215,6 +275,7 @@ mod tests {
         let vec_source_actor = SourceActor {
             source: Box::new(vec_source),
             indexer_mailbox,
+            rate_limiter_opt: None,
         };
         let (_vec_source_mailbox, vec_source_handle) =
             universe.spawn_actor(vec_source_actor).spawn();
@@ -84,6 +84,10 @@ mod tests {
         let source_config = SourceConfig {
             source_id: "test-void-source".to_string(),
             num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
             source_params: SourceParams::void(),
         };
         let metastore = metastore_for_test();
@@ -105,6 +109,10 @@ mod tests {
                 SourceConfig {
                     source_id: "test-void-source".to_string(),
                     num_pipelines: 1,
+                    profile: None,
+                    rate_limit: None,
+                    transforms: Vec::new(),
+                    strict_ordering: false,
                     source_params: SourceParams::void(),
                 },
             ),
@@ -116,6 +124,7 @@ mod tests {
         let void_source_actor = SourceActor {
             source: Box::new(void_source),
             indexer_mailbox,
+            rate_limiter_opt: None,
         };
         let universe = Universe::new();
         let (_, void_source_handle) = universe.spawn_actor(void_source_actor).spawn();
@@ -0,0 +1,201 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use quickwit_config::build_doc_mapper;
+use quickwit_metastore::{IndexMetadata, Metastore, SplitMetadata, SplitState};
+use serde::Serialize;
+use tantivy::{Index, ReloadPolicy};
+use tracing::info;
+
+use crate::actors::{extract_split_tags, tag_fields};
+use crate::split_store::IndexingSplitStore;
+
+/// Number of splits whose tags are amended in the metastore at once.
+const RETAG_SPLITS_BATCH_SIZE: usize = if cfg!(test) { 2 } else { 100 };
+
+/// Change of the tags of a split computed by [`run_split_retagging`].
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct SplitRetagging {
+    pub split_id: String,
+    pub added_tags: BTreeSet<String>,
+    pub removed_tags: BTreeSet<String>,
+}
+
+/// Recomputes the tags of the published splits of an index from its current doc mapping and
+/// indexing settings, and amends the tags of the splits whose tags changed. The split files are
+/// downloaded to recompute the tags but never rewritten.
+///
+/// * `index_metadata` - The metadata of the target index.
+/// * `split_store` - The split store holding the splits of the target index.
+/// * `metastore` - The metastore managing the target index.
+/// * `reason` - Why the tags are recomputed, recorded along with the amended tags.
+/// * `dry_run` - Should this only return the tags changes without amending the splits.
+pub async fn run_split_retagging(
+    index_metadata: &IndexMetadata,
+    split_store: IndexingSplitStore,
+    metastore: Arc<dyn Metastore>,
+    reason: &str,
+    dry_run: bool,
+) -> anyhow::Result<Vec<SplitRetagging>> {
+    let index_id = &index_metadata.index_id;
+    let doc_mapper = build_doc_mapper(
+        &index_metadata.doc_mapping,
+        &index_metadata.search_settings,
+        &index_metadata.indexing_settings,
+    )?;
+    let tag_fields = tag_fields(&*doc_mapper)?;
+    let calendar_tag_granularities = &index_metadata.indexing_settings.calendar_tags;
+
+    let published_splits: Vec<SplitMetadata> = metastore
+        .list_splits(index_id, SplitState::Published, None, None)
+        .await?
+        .into_iter()
+        .map(|split| split.split_metadata)
+        .collect();
+
+    let mut split_retaggings = Vec::new();
+    for split_batch in published_splits.chunks(RETAG_SPLITS_BATCH_SIZE) {
+        let mut splits_tags = BTreeMap::new();
+        for split_metadata in split_batch {
+            let split_id = split_metadata.split_id();
+            let scratch_directory = tempfile::tempdir()?;
+            let split_directory = split_store
+                .fetch_split(split_id, scratch_directory.path())
+                .await?;
+            let index_reader = Index::open(split_directory)?
+                .reader_builder()
+                .reload_policy(ReloadPolicy::Manual)
+                .try_into()?;
+            let tags = extract_split_tags(
+                &index_reader.searcher(),
+                &tag_fields,
+                calendar_tag_granularities,
+                split_metadata.time_range.as_ref(),
+            )?;
+            if tags == split_metadata.tags {
+                continue;
+            }
+            split_retaggings.push(SplitRetagging {
+                split_id: split_id.to_string(),
+                added_tags: tags.difference(&split_metadata.tags).cloned().collect(),
+                removed_tags: split_metadata.tags.difference(&tags).cloned().collect(),
+            });
+            splits_tags.insert(split_id.to_string(), tags);
+        }
+        if dry_run || splits_tags.is_empty() {
+            continue;
+        }
+        info!(index_id = %index_id, num_splits = splits_tags.len(), "amend-splits-tags");
+        metastore
+            .amend_splits_tags(index_id, splits_tags, reason)
+            .await?;
+    }
+    Ok(split_retaggings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TestSandbox;
+
+    #[tokio::test]
+    async fn test_run_split_retagging() -> anyhow::Result<()> {
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: tenant
+                type: text
+                tokenizer: raw
+              - name: body
+                type: text
+        "#;
+        let test_sandbox =
+            TestSandbox::create("test-split-retagging", doc_mapping_yaml, "{}", &["body"]).await?;
+        for tenant in ["foo", "bar", "baz"] {
+            test_sandbox
+                .add_documents(vec![serde_json::json!({"tenant": tenant, "body": "hello"})])
+                .await?;
+        }
+        let metastore = test_sandbox.metastore();
+        let mut index_metadata = metastore.index_metadata("test-split-retagging").await?;
+        let split_store = IndexingSplitStore::create_with_no_local_store(test_sandbox.storage());
+
+        // No tag field is declared yet: the tags of the splits are up to date.
+        let split_retaggings = run_split_retagging(
+            &index_metadata,
+            split_store.clone(),
+            metastore.clone(),
+            "Add tenant tag field.",
+            false,
+        )
+        .await?;
+        assert!(split_retaggings.is_empty());
+
+        index_metadata
+            .doc_mapping
+            .tag_fields
+            .insert("tenant".to_string());
+
+        let split_retaggings = run_split_retagging(
+            &index_metadata,
+            split_store.clone(),
+            metastore.clone(),
+            "Add tenant tag field.",
+            true,
+        )
+        .await?;
+        assert_eq!(split_retaggings.len(), 3);
+        let splits = metastore
+            .list_splits("test-split-retagging", SplitState::Published, None, None)
+            .await?;
+        assert!(splits
+            .iter()
+            .all(|split| split.split_metadata.tags_amendments.is_empty()));
+
+        let split_retaggings = run_split_retagging(
+            &index_metadata,
+            split_store,
+            metastore.clone(),
+            "Add tenant tag field.",
+            false,
+        )
+        .await?;
+        assert_eq!(split_retaggings.len(), 3);
+        let splits = metastore
+            .list_splits("test-split-retagging", SplitState::Published, None, None)
+            .await?;
+        let mut tags: Vec<&str> = splits
+            .iter()
+            .flat_map(|split| split.split_metadata.tags.iter().map(String::as_str))
+            .filter(|tag| tag.starts_with("tenant:"))
+            .collect();
+        tags.sort_unstable();
+        assert_eq!(tags, ["tenant:bar", "tenant:baz", "tenant:foo"]);
+        for split in splits {
+            assert_eq!(split.split_metadata.tags_amendments.len(), 1);
+            assert_eq!(
+                split.split_metadata.tags_amendments[0].reason,
+                "Add tenant tag field."
+            );
+        }
+        Ok(())
+    }
+}
@@ -19,18 +19,22 @@
 
 #[cfg(test)]
 use std::collections::HashMap;
+use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Instant;
 
 use anyhow::Context;
+use futures::channel::mpsc;
+use futures::StreamExt;
 use quickwit_metastore::SplitMetadata;
-use quickwit_storage::{PutPayload, Storage, StorageResult};
+use quickwit_storage::{PutPayload, SplitPayload, SplitPayloadBuilder, Storage, StorageResult};
 use tantivy::Directory;
 use tokio::sync::Mutex;
-use tracing::info;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
 
-use super::LocalSplitStore;
+use super::{LocalSplitStore, UploadManifest};
 use crate::split_store::SPLIT_CACHE_DIR_NAME;
 use crate::{
     get_tantivy_directory_from_split_bundle, MergePolicy, SplitFolder,
@@ -125,6 +129,11 @@ impl IndexingSplitStore {
     /// the store).
     /// In other words, after calling this function the file will not be available
     /// at `split_folder` anymore.
+    ///
+    /// Completed and failed uploads are recorded in an upload manifest located in `split_folder`.
+    /// When storing a split is retried after a failure, the files that the manifest and the
+    /// remote storage agree on are not uploaded again, and the multipart upload of a partial file
+    /// is resumed from its completed parts.
     pub async fn store_split<'a>(
         &'a self,
         split: &'a SplitMetadata,
@@ -137,16 +146,38 @@ impl IndexingSplitStore {
         let split_num_bytes = put_payload.len();
 
         let key = PathBuf::from(quickwit_common::split_file(split.split_id()));
-        self.remote_storage
-            .put(&key, put_payload)
+        let mut upload_manifest = UploadManifest::load(split_folder).await?;
+        if upload_manifest
+            .is_uploaded(&*self.remote_storage, &key, split_num_bytes)
             .await
-            .with_context(|| {
-                format!(
-                    "Failed uploading key {} in bucket {}",
-                    key.display(),
-                    self.remote_storage.uri()
-                )
-            })?;
+        {
+            info!(split_id = split.split_id(), "store-split-remote-skip");
+        } else {
+            let mut upload_state = upload_manifest.pending_upload(&key);
+            let put_result = self
+                .remote_storage
+                .put_resumable(&key, put_payload, &mut upload_state)
+                .await;
+            if let Err(put_error) = put_result {
+                upload_manifest.record_pending_upload(&key, upload_state);
+                if let Err(save_error) = upload_manifest.save(split_folder).await {
+                    warn!(
+                        split_id = split.split_id(),
+                        error = ?save_error,
+                        "Failed to save upload manifest."
+                    );
+                }
+                return Err(put_error).with_context(|| {
+                    format!(
+                        "Failed uploading key {} in bucket {}",
+                        key.display(),
+                        self.remote_storage.uri()
+                    )
+                });
+            }
+            upload_manifest.record_upload(&key, split_num_bytes);
+            upload_manifest.save(split_folder).await?;
+        }
         let elapsed_secs = start.elapsed().as_secs_f32();
         let split_size_in_megabytes = split_num_bytes / 1_000_000;
         let throughput_mb_s = split_size_in_megabytes as f32 / elapsed_secs;
@@ -161,6 +192,8 @@ impl IndexingSplitStore {
             "store-split-remote-success"
         );
 
+        UploadManifest::remove(split_folder).await?;
+
         if !is_mature {
             info!("store-in-cache");
             if let Some(split_store) = self.local_split_store.as_ref() {
@@ -180,6 +213,60 @@ impl IndexingSplitStore {
         Ok(())
     }
 
+    /// Aborts the partial upload of a split recorded in the upload manifest of `split_folder`, so
+    /// that the remote storage releases the parts uploaded so far. This is meant to be called
+    /// once storing the split is given up.
+    pub async fn abort_split_upload(
+        &self,
+        split_id: &str,
+        split_folder: &Path,
+    ) -> anyhow::Result<()> {
+        let key = PathBuf::from(quickwit_common::split_file(split_id));
+        let upload_manifest = UploadManifest::load(split_folder).await?;
+        let upload_state = upload_manifest.pending_upload(&key);
+        if !upload_state.is_empty() {
+            self.remote_storage
+                .abort_resumable_upload(&key, &upload_state)
+                .await?;
+        }
+        UploadManifest::remove(split_folder).await?;
+        Ok(())
+    }
+
+    /// Starts uploading the files of a split before the split is packaged, so that the upload
+    /// overlaps the computation of the hotcache.
+    ///
+    /// The upload only completes with [`StreamedSplitUpload::complete`], and is aborted if the
+    /// [`StreamedSplitUpload`] is dropped before.
+    pub fn start_streamed_upload(
+        &self,
+        split_id: &str,
+        split_files: &[PathBuf],
+    ) -> io::Result<StreamedSplitUpload> {
+        info!(split_id = split_id, "start-streamed-split-upload");
+        let key = PathBuf::from(quickwit_common::split_file(split_id));
+        let mut split_payload_builder = SplitPayloadBuilder::default();
+        let (payload_tx, payload_rx) = mpsc::unbounded();
+        for split_file in split_files {
+            let file_payload = split_payload_builder.add_file(split_file)?;
+            // The receiver is alive until the upload task is spawned below.
+            let _ = payload_tx.unbounded_send(Ok(file_payload));
+        }
+        let remote_storage = self.remote_storage.clone();
+        let upload_key = key.clone();
+        let upload_handle = tokio::spawn(async move {
+            remote_storage
+                .put_streamed(&upload_key, payload_rx.boxed())
+                .await
+        });
+        Ok(StreamedSplitUpload {
+            key,
+            split_payload_builder,
+            payload_tx_opt: Some(payload_tx),
+            upload_handle_opt: Some(upload_handle),
+        })
+    }
+
     /// Delete a split.
     pub async fn delete(&self, split_id: &str) -> StorageResult<()> {
         let split_filename = quickwit_common::split_file(split_id);
@@ -256,6 +343,62 @@ impl IndexingSplitStore {
     }
 }
 
+/// Upload of the files of a split started before the split is packaged. See
+/// [`IndexingSplitStore::start_streamed_upload`].
+pub struct StreamedSplitUpload {
+    key: PathBuf,
+    split_payload_builder: SplitPayloadBuilder,
+    payload_tx_opt: Option<mpsc::UnboundedSender<io::Result<Box<dyn PutPayload>>>>,
+    upload_handle_opt: Option<JoinHandle<StorageResult<()>>>,
+}
+
+impl StreamedSplitUpload {
+    /// Returns the payload of the whole split, given its hotcache.
+    pub fn split_payload(&mut self, hotcache: &[u8]) -> io::Result<SplitPayload> {
+        std::mem::take(&mut self.split_payload_builder).finalize(hotcache)
+    }
+
+    /// Uploads the footer of the split, which completes the upload, and records the upload in the
+    /// upload manifest of `split_folder`, so that storing the split does not upload it again.
+    pub async fn complete(
+        mut self,
+        split_payload: &SplitPayload,
+        split_folder: &Path,
+    ) -> anyhow::Result<()> {
+        let payload_tx = self
+            .payload_tx_opt
+            .take()
+            .expect("The payload sender should be set until the upload completes.");
+        let upload_handle = self
+            .upload_handle_opt
+            .take()
+            .expect("The upload handle should be set until the upload completes.");
+        // If the upload task has already failed, the error is returned by its handle below.
+        let _ = payload_tx.unbounded_send(Ok(split_payload.footer_payload()));
+        drop(payload_tx);
+        upload_handle
+            .await?
+            .with_context(|| format!("Failed uploading key {}", self.key.display()))?;
+        let mut upload_manifest = UploadManifest::load(split_folder).await?;
+        upload_manifest.record_upload(&self.key, split_payload.len());
+        upload_manifest.save(split_folder).await?;
+        info!(key = %self.key.display(), "complete-streamed-split-upload");
+        Ok(())
+    }
+}
+
+impl Drop for StreamedSplitUpload {
+    fn drop(&mut self) {
+        // Closing the stream would complete the upload with a truncated split.
+        if let Some(payload_tx) = self.payload_tx_opt.take() {
+            let _ = payload_tx.unbounded_send(Err(io::Error::new(
+                io::ErrorKind::Interrupted,
+                "The streamed split upload was aborted.",
+            )));
+        }
+    }
+}
+
 #[cfg(test)]
 mod test_split_store {
     use std::path::Path;
@@ -269,8 +412,8 @@ mod test_split_store {
     use tokio::fs;
 
     use super::{IndexingSplitStore, IndexingSplitStoreParams};
-    use crate::split_store::SPLIT_CACHE_DIR_NAME;
-    use crate::{MergePolicy, StableMultitenantWithTimestampMergePolicy};
+    use crate::merge_policy::{MergePolicy, StableMultitenantWithTimestampMergePolicy};
+    use crate::split_store::{UploadManifest, SPLIT_CACHE_DIR_NAME};
 
     #[tokio::test]
     async fn test_create_should_error_with_wrong_num_files() -> anyhow::Result<()> {
@@ -701,4 +844,46 @@ mod test_split_store {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_store_split_skips_files_already_uploaded() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let remote_storage = Arc::new(RamStorage::default());
+        let split_store = IndexingSplitStore::create_with_no_local_store(remote_storage.clone());
+        let split_key = Path::new("split1.split");
+        remote_storage
+            .put(split_key, Box::new(vec![9, 9, 9, 9]))
+            .await?;
+        {
+            // The remote file is not recorded in the manifest: the split is uploaded.
+            let split_path = temp_dir.path().join("split1");
+            fs::create_dir_all(&split_path).await?;
+            let split_metadata = create_test_split_metadata("split1");
+            split_store
+                .store_split(&split_metadata, &split_path, Box::new(vec![1, 2, 3, 4]))
+                .await?;
+            assert!(!split_path.exists());
+            let split_bytes = remote_storage.get_all(split_key).await?;
+            assert_eq!(split_bytes.as_slice(), &[1, 2, 3, 4]);
+        }
+        {
+            // A previous attempt recorded the upload: the split is not uploaded again.
+            let split_path = temp_dir.path().join("split1");
+            fs::create_dir_all(&split_path).await?;
+            let mut upload_manifest = UploadManifest::default();
+            upload_manifest.record_upload(split_key, 4);
+            upload_manifest.save(&split_path).await?;
+            remote_storage
+                .put(split_key, Box::new(vec![9, 9, 9, 9]))
+                .await?;
+            let split_metadata = create_test_split_metadata("split1");
+            split_store
+                .store_split(&split_metadata, &split_path, Box::new(vec![1, 2, 3, 4]))
+                .await?;
+            assert!(!split_path.exists());
+            let split_bytes = remote_storage.get_all(split_key).await?;
+            assert_eq!(split_bytes.as_slice(), &[9, 9, 9, 9]);
+        }
+        Ok(())
+    }
 }
@@ -22,6 +22,7 @@ use std::fs::DirEntry;
 use std::path::{Path, PathBuf};
 use std::{fs, io};
 
+use quickwit_common::fs::{is_file_in_use_error, replace_dir};
 use quickwit_common::split_file;
 use quickwit_directories::BundleDirectory;
 use quickwit_storage::{PutPayload, SplitPayloadBuilder, StorageErrorKind, StorageResult};
@@ -67,7 +68,7 @@ impl SplitFolder {
     async fn move_to(&mut self, new_folder: &Path, split_id: &str) -> StorageResult<()> {
         let new_path = PathBuf::from(split_file(split_id));
         let to_full_path = new_folder.join(new_path);
-        tokio::fs::rename(&self.path, &to_full_path).await?;
+        replace_dir(&self.path, &to_full_path).await?;
         self.path = to_full_path.to_path_buf();
         Ok(())
     }
@@ -187,7 +188,16 @@ impl LocalSplitStore {
             return Ok(());
         }
         if let Some((_, split_file)) = self.split_files.remove(split_id) {
-            split_file.delete().await?;
+            match split_file.delete().await {
+                Ok(()) => {}
+                // On Windows, the files of a split still opened by a merge cannot be deleted. The
+                // split folder is left behind and picked up again the next time the store is
+                // opened.
+                Err(io_error) if is_file_in_use_error(&io_error) => {
+                    warn!(split_id = split_id, error = ?io_error, "Failed to delete split folder in use.");
+                }
+                Err(io_error) => return Err(io_error.into()),
+            }
         }
         Ok(())
     }
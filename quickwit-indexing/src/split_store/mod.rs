@@ -19,10 +19,12 @@
 
 mod indexing_split_store;
 mod local_split_store;
+mod upload_manifest;
 
-pub use indexing_split_store::{IndexingSplitStore, IndexingSplitStoreParams};
+pub use indexing_split_store::{IndexingSplitStore, IndexingSplitStoreParams, StreamedSplitUpload};
 use local_split_store::LocalSplitStore;
 pub use local_split_store::{get_tantivy_directory_from_split_bundle, SplitFolder};
+use upload_manifest::UploadManifest;
 
 /// An intermediate folder created at `<cache dir>/SPLIT_CACHE_DIR_NAME`
 /// to hold the local split files.
@@ -0,0 +1,167 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use quickwit_storage::{ResumableUploadState, Storage};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Name of the file recording the uploads completed for a split, located in the split scratch
+/// directory.
+const UPLOAD_MANIFEST_FILE_NAME: &str = "upload-manifest.json";
+
+/// Records the files of a split that were successfully uploaded to the remote storage, as well as
+/// the uploads that failed midway, so that retrying a failed upload only re-uploads the missing
+/// files and the missing parts of the partial ones.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) struct UploadManifest {
+    /// Maps the storage paths of the uploaded files to their size in bytes.
+    uploaded_files: BTreeMap<PathBuf, u64>,
+    /// Maps the storage paths of the partially uploaded files to the state of their upload.
+    #[serde(default)]
+    pending_uploads: BTreeMap<PathBuf, ResumableUploadState>,
+}
+
+impl UploadManifest {
+    /// Loads the manifest located in `split_folder`. Returns an empty manifest if the file does
+    /// not exist or cannot be parsed.
+    pub async fn load(split_folder: &Path) -> io::Result<UploadManifest> {
+        let manifest_path = split_folder.join(UPLOAD_MANIFEST_FILE_NAME);
+        let manifest_bytes = match tokio::fs::read(&manifest_path).await {
+            Ok(manifest_bytes) => manifest_bytes,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                return Ok(UploadManifest::default());
+            }
+            Err(error) => return Err(error),
+        };
+        match serde_json::from_slice(&manifest_bytes) {
+            Ok(upload_manifest) => Ok(upload_manifest),
+            Err(error) => {
+                warn!(
+                    manifest_path=%manifest_path.display(),
+                    error=?error,
+                    "Failed to parse upload manifest."
+                );
+                Ok(UploadManifest::default())
+            }
+        }
+    }
+
+    /// Saves the manifest in `split_folder`. The file is written to a temporary file and then
+    /// renamed, so a crash cannot leave a truncated manifest behind.
+    pub async fn save(&self, split_folder: &Path) -> io::Result<()> {
+        let manifest_bytes = serde_json::to_vec(self)?;
+        let manifest_path = split_folder.join(UPLOAD_MANIFEST_FILE_NAME);
+        let temp_manifest_path = manifest_path.with_extension("json.temp");
+        tokio::fs::write(&temp_manifest_path, manifest_bytes).await?;
+        tokio::fs::rename(&temp_manifest_path, &manifest_path).await?;
+        Ok(())
+    }
+
+    /// Removes the manifest from `split_folder` if it exists.
+    pub async fn remove(split_folder: &Path) -> io::Result<()> {
+        let manifest_path = split_folder.join(UPLOAD_MANIFEST_FILE_NAME);
+        match tokio::fs::remove_file(&manifest_path).await {
+            Ok(()) => Ok(()),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn record_upload(&mut self, path: &Path, num_bytes: u64) {
+        self.pending_uploads.remove(path);
+        self.uploaded_files.insert(path.to_path_buf(), num_bytes);
+    }
+
+    /// Returns the state of the partial upload of the file, if any.
+    pub fn pending_upload(&self, path: &Path) -> ResumableUploadState {
+        self.pending_uploads.get(path).cloned().unwrap_or_default()
+    }
+
+    pub fn record_pending_upload(&mut self, path: &Path, upload_state: ResumableUploadState) {
+        if upload_state.is_empty() {
+            self.pending_uploads.remove(path);
+        } else {
+            self.pending_uploads
+                .insert(path.to_path_buf(), upload_state);
+        }
+    }
+
+    /// Returns true if the file was recorded as uploaded with the expected size and the remote
+    /// storage still holds a file of that size.
+    pub async fn is_uploaded(&self, storage: &dyn Storage, path: &Path, num_bytes: u64) -> bool {
+        if self.uploaded_files.get(path) != Some(&num_bytes) {
+            return false;
+        }
+        match storage.file_num_bytes(path).await {
+            Ok(remote_num_bytes) => remote_num_bytes == num_bytes,
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use quickwit_storage::RamStorage;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_upload_manifest() -> anyhow::Result<()> {
+        let split_folder = tempfile::tempdir()?;
+        let storage = RamStorage::default();
+        let split_path = Path::new("split1.split");
+
+        let mut upload_manifest = UploadManifest::load(split_folder.path()).await?;
+        assert_eq!(upload_manifest, UploadManifest::default());
+        assert!(!upload_manifest.is_uploaded(&storage, split_path, 4).await);
+        assert!(upload_manifest.pending_upload(split_path).is_empty());
+
+        let upload_state = ResumableUploadState {
+            upload_id: Some("upload-1".to_string()),
+            part_num_bytes: 2,
+        };
+        upload_manifest.record_pending_upload(split_path, upload_state.clone());
+        upload_manifest.save(split_folder.path()).await?;
+        let mut upload_manifest = UploadManifest::load(split_folder.path()).await?;
+        assert_eq!(upload_manifest.pending_upload(split_path), upload_state);
+
+        upload_manifest.record_upload(split_path, 4);
+        assert!(upload_manifest.pending_upload(split_path).is_empty());
+        upload_manifest.save(split_folder.path()).await?;
+        let upload_manifest = UploadManifest::load(split_folder.path()).await?;
+        // The manifest alone is not enough: the remote file must exist.
+        assert!(!upload_manifest.is_uploaded(&storage, split_path, 4).await);
+
+        storage.put(split_path, Box::new(vec![1, 2, 3, 4])).await?;
+        assert!(upload_manifest.is_uploaded(&storage, split_path, 4).await);
+        // A partial upload is detected by the size mismatch.
+        assert!(!upload_manifest.is_uploaded(&storage, split_path, 5).await);
+
+        UploadManifest::remove(split_folder.path()).await?;
+        assert!(!split_folder.path().join(UPLOAD_MANIFEST_FILE_NAME).exists());
+        UploadManifest::remove(split_folder.path()).await?;
+        Ok(())
+    }
+}
@@ -131,6 +131,10 @@ impl TestSandbox {
         let source_config = SourceConfig {
             source_id: self.index_id.clone(),
             num_pipelines: 0,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
             source_params: SourceParams::Vec(VecSourceParams {
                 docs,
                 batch_num_docs: 10,
@@ -143,6 +147,7 @@ impl TestSandbox {
                 index_id: self.index_id.clone(),
                 source_config,
                 pipeline_ord: 0,
+                merge_pipeline_enabled: true,
             })
             .await?;
         let pipeline_handle = self
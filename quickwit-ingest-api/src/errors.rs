@@ -31,6 +31,8 @@ pub enum IngestApiError {
     IndexAlreadyExists { index_id: String },
     #[error("Ingest API service is down")]
     IngestAPIServiceDown,
+    #[error("Documents were not published within {timeout_secs} seconds.")]
+    PublishTimeout { timeout_secs: u64 },
 }
 
 impl ServiceError for IngestApiError {
@@ -40,6 +42,7 @@ impl ServiceError for IngestApiError {
             IngestApiError::IndexDoesNotExist { .. } => ServiceErrorCode::NotFound,
             IngestApiError::IndexAlreadyExists { .. } => ServiceErrorCode::BadRequest,
             IngestApiError::IngestAPIServiceDown => ServiceErrorCode::Internal,
+            IngestApiError::PublishTimeout { .. } => ServiceErrorCode::Timeout,
         }
     }
 }
@@ -71,6 +74,7 @@ impl From<IngestApiError> for tonic::Status {
             IngestApiError::IndexDoesNotExist { .. } => tonic::Code::NotFound,
             IngestApiError::IndexAlreadyExists { .. } => tonic::Code::AlreadyExists,
             IngestApiError::IngestAPIServiceDown => tonic::Code::Internal,
+            IngestApiError::PublishTimeout { .. } => tonic::Code::DeadlineExceeded,
         };
         let message = error.to_string();
         tonic::Status::new(code, message)
@@ -17,10 +17,12 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
+use std::time::Duration;
 
 use async_trait::async_trait;
+use futures::future::try_join_all;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, QueueCapacity};
 use quickwit_common::runtimes::RuntimeType;
 use quickwit_proto::ingest_api::{
@@ -28,20 +30,92 @@ use quickwit_proto::ingest_api::{
     FetchResponse, IngestRequest, IngestResponse, ListQueuesRequest, ListQueuesResponse,
     QueueExistsRequest, SuggestTruncateRequest, TailRequest,
 };
+use tokio::sync::oneshot;
 
 use crate::{iter_doc_payloads, IngestApiError, Position, Queues};
 
+/// Ingests a batch of documents like an [`IngestRequest`], and returns along with the response a
+/// [`PublishWaiter`] resolving once the documents are published.
+#[derive(Debug)]
+pub struct IngestWaitForPublishRequest(pub IngestRequest);
+
+/// Resolves once the documents of an ingest request are published, that is, once the indexing
+/// pipelines consuming the queues suggest truncating them past the last document position.
+#[derive(Debug)]
+pub struct PublishWaiter {
+    publish_rxs: Vec<(String, oneshot::Receiver<()>)>,
+}
+
+impl PublishWaiter {
+    /// Waits for the documents to be published, at most `timeout`.
+    pub async fn wait(self, timeout: Duration) -> crate::Result<()> {
+        let publish_futures = self
+            .publish_rxs
+            .into_iter()
+            .map(|(queue_id, publish_rx)| async {
+                // The sender is dropped without notification when the queue is dropped.
+                publish_rx
+                    .await
+                    .map_err(|_| IngestApiError::IndexDoesNotExist { index_id: queue_id })
+            });
+        tokio::time::timeout(timeout, try_join_all(publish_futures))
+            .await
+            .map_err(|_| IngestApiError::PublishTimeout {
+                timeout_secs: timeout.as_secs(),
+            })??;
+        Ok(())
+    }
+}
+
 pub struct IngestApiService {
     queues: Queues,
+    /// Positions awaited by [`PublishWaiter`]s per queue.
+    publish_txs: HashMap<String, Vec<(Position, oneshot::Sender<()>)>>,
 }
 
 impl IngestApiService {
     pub fn with_queues_dir(queues_dir_path: &Path) -> crate::Result<Self> {
         let queues = Queues::open(queues_dir_path)?;
-        Ok(IngestApiService { queues })
+        Ok(IngestApiService {
+            queues,
+            publish_txs: HashMap::new(),
+        })
+    }
+
+    /// Sets whether the write-ahead log of the queues is synced before the documents are
+    /// acknowledged. Enabled by default.
+    pub fn set_sync_writes(&mut self, sync_writes: bool) {
+        self.queues.set_sync_writes(sync_writes);
     }
 
     async fn ingest(&mut self, request: IngestRequest) -> crate::Result<IngestResponse> {
+        let (ingest_response, _) = self.ingest_inner(request).await?;
+        Ok(ingest_response)
+    }
+
+    async fn ingest_wait_for_publish(
+        &mut self,
+        request: IngestRequest,
+    ) -> crate::Result<(IngestResponse, PublishWaiter)> {
+        let (ingest_response, appended_positions) = self.ingest_inner(request).await?;
+        let mut publish_rxs = Vec::with_capacity(appended_positions.len());
+        for (queue_id, position) in appended_positions {
+            let (publish_tx, publish_rx) = oneshot::channel();
+            self.publish_txs
+                .entry(queue_id.clone())
+                .or_default()
+                .push((position, publish_tx));
+            publish_rxs.push((queue_id, publish_rx));
+        }
+        Ok((ingest_response, PublishWaiter { publish_rxs }))
+    }
+
+    /// Appends the document batches to their queues and returns the position of the last document
+    /// appended to each queue.
+    async fn ingest_inner(
+        &mut self,
+        request: IngestRequest,
+    ) -> crate::Result<(IngestResponse, HashMap<String, Position>)> {
         // Check all indexes exist assuming existing queues always have a corresponding index.
         let first_non_existing_queue_opt = request
             .doc_batches
@@ -56,16 +130,20 @@ impl IngestApiService {
         }
 
         let mut num_docs = 0usize;
+        let mut appended_positions = HashMap::new();
         for doc_batch in &request.doc_batches {
             // TODO better error handling.
             // If there is an error, we probably want a transactional behavior.
             let records_it = iter_doc_payloads(doc_batch);
-            self.queues.append_batch(&doc_batch.index_id, records_it)?;
+            if let Some(position) = self.queues.append_batch(&doc_batch.index_id, records_it)? {
+                appended_positions.insert(doc_batch.index_id.clone(), position);
+            }
             num_docs += doc_batch.doc_lens.len();
         }
-        Ok(IngestResponse {
+        let ingest_response = IngestResponse {
             num_docs_for_processing: num_docs as u64,
-        })
+        };
+        Ok((ingest_response, appended_positions))
     }
 
     fn fetch(&mut self, fetch_req: FetchRequest) -> crate::Result<FetchResponse> {
@@ -78,12 +156,32 @@ impl IngestApiService {
     }
 
     fn suggest_truncate(&mut self, request: SuggestTruncateRequest) -> crate::Result<()> {
-        self.queues.suggest_truncate(
-            &request.index_id,
-            Position::from(request.up_to_position_included),
-        )?;
+        let up_to_position_included = Position::from(request.up_to_position_included);
+        self.queues
+            .suggest_truncate(&request.index_id, up_to_position_included)?;
+        // Truncation is suggested once the documents are published.
+        self.notify_published(&request.index_id, up_to_position_included);
         Ok(())
     }
+
+    fn notify_published(&mut self, queue_id: &str, up_to_position_included: Position) {
+        let publish_txs = if let Some(publish_txs) = self.publish_txs.remove(queue_id) {
+            publish_txs
+        } else {
+            return;
+        };
+        let (published_txs, pending_txs): (Vec<_>, Vec<_>) = publish_txs
+            .into_iter()
+            // Waiters that timed out are not interested anymore.
+            .filter(|(_, publish_tx)| !publish_tx.is_closed())
+            .partition(|(position, _)| *position <= up_to_position_included);
+        for (_, publish_tx) in published_txs {
+            let _ = publish_tx.send(());
+        }
+        if !pending_txs.is_empty() {
+            self.publish_txs.insert(queue_id.to_string(), pending_txs);
+        }
+    }
 }
 
 #[async_trait]
@@ -149,6 +247,8 @@ impl Handler<DropQueueRequest> for IngestApiService {
         drop_queue_req: DropQueueRequest,
         _ctx: &ActorContext<Self>,
     ) -> Result<Self::Reply, ActorExitStatus> {
+        // Dropping the senders notifies the waiters that the queue no longer exists.
+        self.publish_txs.remove(&drop_queue_req.queue_id);
         Ok(self.queues.drop_queue(&drop_queue_req.queue_id))
     }
 }
@@ -165,6 +265,18 @@ impl Handler<IngestRequest> for IngestApiService {
     }
 }
 
+#[async_trait]
+impl Handler<IngestWaitForPublishRequest> for IngestApiService {
+    type Reply = crate::Result<(IngestResponse, PublishWaiter)>;
+    async fn handle(
+        &mut self,
+        request: IngestWaitForPublishRequest,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self.ingest_wait_for_publish(request.0).await)
+    }
+}
+
 #[async_trait]
 impl Handler<FetchRequest> for IngestApiService {
     type Reply = crate::Result<FetchResponse>;
@@ -212,3 +324,78 @@ impl Handler<ListQueuesRequest> for IngestApiService {
         Ok(self.queues.list_queues())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use quickwit_actors::Universe;
+    use quickwit_proto::ingest_api::DocBatch;
+
+    use super::*;
+    use crate::add_doc;
+
+    #[tokio::test]
+    async fn test_ingest_wait_for_publish() {
+        let universe = Universe::new();
+        let tempdir = tempfile::tempdir().unwrap();
+        let ingest_api_service = IngestApiService::with_queues_dir(tempdir.path()).unwrap();
+        let (ingest_api_mailbox, _ingest_api_handle) =
+            universe.spawn_actor(ingest_api_service).spawn();
+        ingest_api_mailbox
+            .ask_for_res(CreateQueueRequest {
+                queue_id: "test-index".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let mut doc_batch = DocBatch {
+            index_id: "test-index".to_string(),
+            ..Default::default()
+        };
+        add_doc(b"doc-0", &mut doc_batch);
+        add_doc(b"doc-1", &mut doc_batch);
+        let ingest_request = IngestRequest {
+            doc_batches: vec![doc_batch],
+        };
+        let (ingest_response, publish_waiter) = ingest_api_mailbox
+            .ask_for_res(IngestWaitForPublishRequest(ingest_request.clone()))
+            .await
+            .unwrap();
+        assert_eq!(ingest_response.num_docs_for_processing, 2);
+
+        let publish_error = publish_waiter
+            .wait(Duration::from_millis(50))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            publish_error,
+            IngestApiError::PublishTimeout { .. }
+        ));
+
+        let (_, publish_waiter) = ingest_api_mailbox
+            .ask_for_res(IngestWaitForPublishRequest(ingest_request))
+            .await
+            .unwrap();
+        let wait_handle =
+            tokio::spawn(async move { publish_waiter.wait(Duration::from_secs(5)).await });
+
+        // The first two documents are published: the second batch is still pending.
+        ingest_api_mailbox
+            .ask_for_res(SuggestTruncateRequest {
+                index_id: "test-index".to_string(),
+                up_to_position_included: 1,
+            })
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!wait_handle.is_finished());
+
+        ingest_api_mailbox
+            .ask_for_res(SuggestTruncateRequest {
+                index_id: "test-index".to_string(),
+                up_to_position_included: 3,
+            })
+            .await
+            .unwrap();
+        wait_handle.await.unwrap().unwrap();
+    }
+}
@@ -28,7 +28,7 @@ use std::path::{Path, PathBuf};
 use anyhow::{bail, Context};
 pub use errors::IngestApiError;
 use errors::Result;
-pub use ingest_api_service::IngestApiService;
+pub use ingest_api_service::{IngestApiService, IngestWaitForPublishRequest, PublishWaiter};
 use once_cell::sync::OnceCell;
 pub use position::Position;
 pub use queue::Queues;
@@ -47,6 +47,14 @@ pub static INGEST_API_SERVICE_MAILBOXES: OnceCell<Mutex<IngestApiServiceMailboxe
 pub async fn init_ingest_api(
     universe: &Universe,
     queues_dir_path: &Path,
+) -> anyhow::Result<Mailbox<IngestApiService>> {
+    init_ingest_api_with_sync_writes(universe, queues_dir_path, true).await
+}
+
+async fn init_ingest_api_with_sync_writes(
+    universe: &Universe,
+    queues_dir_path: &Path,
+    sync_writes: bool,
 ) -> anyhow::Result<Mailbox<IngestApiService>> {
     let mut guard = INGEST_API_SERVICE_MAILBOXES
         .get_or_init(|| Mutex::new(HashMap::new()))
@@ -55,13 +63,14 @@ pub async fn init_ingest_api(
     if let Some(mailbox) = guard.get(queues_dir_path) {
         return Ok(mailbox.clone());
     }
-    let ingest_api_actor =
+    let mut ingest_api_actor =
         IngestApiService::with_queues_dir(queues_dir_path).with_context(|| {
             format!(
                 "Failed to open RocksDB instance located at `{}`.",
                 queues_dir_path.display()
             )
         })?;
+    ingest_api_actor.set_sync_writes(sync_writes);
     let (ingest_api_service, _ingest_api_handle) = universe.spawn_actor(ingest_api_actor).spawn();
     guard.insert(queues_dir_path.to_path_buf(), ingest_api_service.clone());
     Ok(ingest_api_service)
@@ -84,13 +93,23 @@ pub async fn get_ingest_api_service(
     )
 }
 
-/// Starts an [`IngestApiService`] instance at `<data_dir_path>/queues`.
+/// Starts an [`IngestApiService`] instance at `<data_dir_path>/queues`. When `sync_writes` is
+/// false, the write-ahead log of the queues is not synced before the documents are acknowledged.
 pub async fn start_ingest_api_service(
     universe: &Universe,
     data_dir_path: &Path,
+    sync_writes: bool,
 ) -> anyhow::Result<Mailbox<IngestApiService>> {
     let queues_dir_path = data_dir_path.join(QUEUES_DIR_NAME);
-    init_ingest_api(universe, &queues_dir_path).await
+    init_ingest_api_with_sync_writes(universe, &queues_dir_path, sync_writes).await
+}
+
+/// Returns the ID of the queue buffering the events received by the webhook source `source_id`
+/// of the index `index_id`.
+pub fn webhook_queue_id(index_id: &str, source_id: &str) -> String {
+    // Index and source IDs cannot contain a `:`, so queue IDs cannot collide with index IDs, even
+    // with namespaced index IDs such as `team-a/logs`.
+    format!("{}:{}", index_id, source_id)
 }
 
 /// Adds a document raw bytes to a [`DocBatch`]
@@ -153,4 +172,19 @@ mod tests {
             .await
             .unwrap();
     }
+
+    #[test]
+    fn test_webhook_queue_id_does_not_collide_with_index_ids() {
+        // The queue of the index `team-a/logs` is named after the index ID.
+        assert_eq!(webhook_queue_id("team-a", "logs"), "team-a:logs");
+        assert_ne!(webhook_queue_id("team-a", "logs"), "team-a/logs");
+        assert_eq!(
+            webhook_queue_id("team-a/logs", "github-webhook"),
+            "team-a/logs:github-webhook"
+        );
+        assert_ne!(
+            webhook_queue_id("team-a/logs", "github-webhook"),
+            "team-a/logs/github-webhook"
+        );
+    }
 }
@@ -33,6 +33,7 @@ const QUICKWIT_CF_PREFIX: &str = ".queue_";
 pub struct Queues {
     db: DB,
     last_position_per_queue: HashMap<String, Option<Position>>,
+    sync_writes: bool,
 }
 
 fn default_rocks_db_options() -> rocksdb::Options {
@@ -44,9 +45,11 @@ fn default_rocks_db_options() -> rocksdb::Options {
     options
 }
 
-fn default_rocks_db_write_options() -> rocksdb::WriteOptions {
+fn rocks_db_write_options(sync_writes: bool) -> rocksdb::WriteOptions {
     let mut write_options = WriteOptions::default();
-    write_options.set_sync(false);
+    // Documents are acknowledged once appended: unless disabled, the write-ahead log is synced so
+    // that they are not lost if the node crashes before they are indexed and published.
+    write_options.set_sync(sync_writes);
     write_options.disable_wal(false);
     write_options
 }
@@ -86,9 +89,23 @@ impl Queues {
         Ok(Queues {
             db,
             last_position_per_queue: next_position_per_queue,
+            sync_writes: true,
         })
     }
 
+    /// Returns whether the write-ahead log is synced before an append returns.
+    pub fn sync_writes(&self) -> bool {
+        self.sync_writes
+    }
+
+    /// Sets whether the write-ahead log is synced before an append returns. Enabled by default.
+    ///
+    /// Without syncing, appended records survive a crash of the process but may be lost if the
+    /// machine crashes, in exchange for a higher ingest throughput.
+    pub fn set_sync_writes(&mut self, sync_writes: bool) {
+        self.sync_writes = sync_writes;
+    }
+
     pub fn queue_exists(&self, queue_id: &str) -> bool {
         let real_queue_id = format!("{}{}", QUICKWIT_CF_PREFIX, queue_id);
         self.db.cf_handle(&real_queue_id).is_some()
@@ -167,17 +184,20 @@ impl Queues {
     // Append a single record to a target queue.
     #[cfg(test)]
     fn append(&mut self, queue_id: &str, record: &[u8]) -> crate::Result<()> {
-        self.append_batch(queue_id, std::iter::once(record))
+        self.append_batch(queue_id, std::iter::once(record))?;
+        Ok(())
     }
 
     // Append a batch of records to a target queue.
     //
     // This operation is atomic: the batch of records is either entirely added or not.
+    //
+    // Returns the position of the last record appended, or `None` if the batch is empty.
     pub fn append_batch<'a>(
         &mut self,
         queue_id: &str,
         records_it: impl Iterator<Item = &'a [u8]>,
-    ) -> crate::Result<()> {
+    ) -> crate::Result<Option<Position>> {
         let real_queue_id = format!("{}{}", QUICKWIT_CF_PREFIX, queue_id);
         let column_does_not_exist = || crate::IngestApiError::IndexDoesNotExist {
             index_id: queue_id.to_string(),
@@ -198,16 +218,20 @@ impl Queues {
             .ok_or_else(column_does_not_exist)?;
 
         let mut batch = WriteBatch::default();
+        let mut appended_position_opt = None;
         for record in records_it {
             batch.put_cf(&cf_ref, next_position.as_ref(), record);
-            *last_position_opt = Some(next_position);
+            appended_position_opt = Some(next_position);
             next_position = next_position.inc();
         }
 
-        let write_options = default_rocks_db_write_options();
+        let write_options = rocks_db_write_options(self.sync_writes);
         self.db.write_opt(batch, &write_options)?;
 
-        Ok(())
+        if appended_position_opt.is_some() {
+            *last_position_opt = appended_position_opt;
+        }
+        Ok(appended_position_opt)
     }
 
     // Streams messages from in `]after_position, +∞[`.
@@ -402,12 +426,18 @@ mod tests {
         let mut queues = QueuesForTest::default();
 
         queues.create_queue(TEST_QUEUE_ID).unwrap();
-        queues
+        let appended_position_opt = queues
+            .append_batch(TEST_QUEUE_ID, std::iter::empty())
+            .unwrap();
+        assert!(appended_position_opt.is_none());
+
+        let appended_position_opt = queues
             .append_batch(
                 TEST_QUEUE_ID,
                 [b"hello", b"happy"].iter().map(|bytes| bytes.as_slice()),
             )
             .unwrap();
+        assert_eq!(appended_position_opt, Some(Position::from(1u64)));
 
         queues.reload();
         queues.fetch_test(
@@ -458,6 +488,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_append_without_sync_writes() {
+        let mut queues = QueuesForTest::default();
+        assert!(queues.sync_writes());
+        queues.set_sync_writes(false);
+        assert!(!queues.sync_writes());
+        queues.create_queue(TEST_QUEUE_ID).unwrap();
+        queues.append(TEST_QUEUE_ID, b"hello").unwrap();
+
+        queues.reload();
+        assert!(queues.sync_writes());
+        queues.fetch_test(TEST_QUEUE_ID, None, Some(0), &[&b"hello"[..]]);
+    }
+
     // Note this test is specific to the current implementation of truncate.
     //
     // The truncate contract is actually not as accurate as what we are testing here.
@@ -0,0 +1 @@
+DROP INDEX IF EXISTS splits_tags_idx;
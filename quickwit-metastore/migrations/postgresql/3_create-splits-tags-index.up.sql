@@ -0,0 +1,3 @@
+-- Tag filters are translated into `tags @> ARRAY[...]` containment predicates, which
+-- a GIN index can serve without scanning every split of the index.
+CREATE INDEX IF NOT EXISTS splits_tags_idx ON splits USING GIN (tags);
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS delete_tasks (
+    opstamp BIGSERIAL PRIMARY KEY,
+    create_timestamp TIMESTAMP NOT NULL DEFAULT (CURRENT_TIMESTAMP AT TIME ZONE 'UTC'),
+    index_id VARCHAR(50) NOT NULL,
+    delete_query_json TEXT NOT NULL,
+
+    FOREIGN KEY(index_id) REFERENCES indexes(index_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS delete_tasks_index_id_idx ON delete_tasks (index_id);
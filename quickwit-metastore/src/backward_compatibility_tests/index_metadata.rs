@@ -143,6 +143,9 @@ pub(crate) fn sample_index_metadata_for_regression() -> IndexMetadata {
         mode: ModeType::Dynamic,
         dynamic_mapping: None,
         partition_key: "".to_string(),
+        doc_id: None,
+        expire_timestamp_field: None,
+        sequence_field: None,
     };
     let retention_policy = Some(RetentionPolicy::new(
         "90 days".to_string(),
@@ -157,6 +160,9 @@ pub(crate) fn sample_index_metadata_for_regression() -> IndexMetadata {
     let indexing_resources = IndexingResources {
         __num_threads_deprecated: serde::de::IgnoredAny,
         heap_size: Byte::from_bytes(3),
+        workbench_memory_limit: None,
+        split_write_buffer_size: None,
+        split_sync_interval: None,
     };
     let indexing_settings = IndexingSettings {
         timestamp_field: Some("timestamp".to_string()),
@@ -173,15 +179,21 @@ pub(crate) fn sample_index_metadata_for_regression() -> IndexMetadata {
     };
     let search_settings = SearchSettings {
         default_search_fields: vec!["message".to_string()],
+        field_boosts: BTreeMap::new(),
     };
     let kafka_source = SourceConfig {
         source_id: "kafka-source".to_string(),
         num_pipelines: 2,
+        profile: None,
+        rate_limit: None,
+        transforms: Vec::new(),
+        strict_ordering: false,
         source_params: SourceParams::Kafka(KafkaSourceParams {
             topic: "kafka-topic".to_string(),
             client_log_level: None,
             client_params: serde_json::json!({}),
             enable_backfill_mode: false,
+            backfill_window: None,
         }),
     };
     let mut sources = HashMap::default();
@@ -192,12 +204,17 @@ pub(crate) fn sample_index_metadata_for_regression() -> IndexMetadata {
         index_uri: Uri::new("s3://quickwit-indexes/my-index".to_string()),
         checkpoint,
         doc_mapping,
+        doc_mapping_version: 0,
         indexing_settings,
         retention_policy,
         search_settings,
         sources,
         create_timestamp: 1789,
         update_timestamp: 1789,
+        maintenance_mode: false,
+        merge_planner_lease: None,
+        quarantined_files: HashMap::default(),
+        feature_flags: BTreeMap::default(),
     }
 }
 
@@ -17,6 +17,8 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::BTreeMap;
+
 use crate::SplitMetadata;
 
 /// Creates a split metadata object that will be
@@ -27,6 +29,7 @@ pub(crate) fn sample_split_metadata_for_regression() -> SplitMetadata {
         source_id: "source".to_string(),
         node_id: "node".to_string(),
         pipeline_ord: 1,
+        pipeline_generation: 0,
         partition_id: 7u64,
         num_docs: 12303,
         uncompressed_docs_size_in_bytes: 234234,
@@ -34,6 +37,15 @@ pub(crate) fn sample_split_metadata_for_regression() -> SplitMetadata {
         create_timestamp: 3,
         tags: ["234".to_string(), "aaa".to_string()].into_iter().collect(),
         footer_offsets: 1000..2000,
+        dedup_digest: None,
+        delete_opstamp: 0,
+        sample_uri: None,
+        min_expire_timestamp: None,
+        tags_amendments: Vec::new(),
+        doc_mapping_version: 0,
+        config_fingerprint: None,
+        replaced_split_ids: Vec::new(),
+        field_stats: BTreeMap::new(),
     }
 }
 
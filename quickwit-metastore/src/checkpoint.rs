@@ -25,6 +25,7 @@ use std::iter::FromIterator;
 use std::ops::Range;
 use std::sync::Arc;
 
+use quickwit_config::MULTIPLEXED_SOURCE_ID;
 use serde::ser::SerializeMap;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -46,6 +47,28 @@ impl From<&str> for PartitionId {
     }
 }
 
+/// Separator between the ID of a source and the ID of one of its partitions in the checkpoints of
+/// a multiplexed pipeline. Source IDs cannot contain it.
+const MULTIPLEXED_PARTITION_SEPARATOR: char = '/';
+
+impl PartitionId {
+    /// Returns the ID of the partition in the checkpoints of a multiplexed pipeline, prefixed
+    /// with the ID of its source.
+    pub fn multiplexed(&self, source_id: &str) -> PartitionId {
+        PartitionId::from(format!(
+            "{}{}{}",
+            source_id, MULTIPLEXED_PARTITION_SEPARATOR, self.0
+        ))
+    }
+
+    /// Splits the ID of a partition of a multiplexed pipeline into the ID of its source and the
+    /// ID of the partition within that source.
+    fn demultiplexed(&self) -> Option<(&str, PartitionId)> {
+        let (source_id, partition_id) = self.0.split_once(MULTIPLEXED_PARTITION_SEPARATOR)?;
+        Some((source_id, PartitionId::from(partition_id)))
+    }
+}
+
 impl From<u64> for PartitionId {
     fn from(partition_id: u64) -> Self {
         let partition_id_str = format!("{:0>20}", partition_id);
@@ -150,10 +173,24 @@ impl IndexCheckpoint {
     /// checkpoint remains unchanged.
     ///
     /// See [`SourceCheckpoint::try_apply_delta`] for more details.
+    ///
+    /// The delta of a multiplexed pipeline is split and applied to the checkpoints of the sources
+    /// it reads: either all of them are updated, or none of them.
     pub fn try_apply_delta(
         &mut self,
         delta: IndexCheckpointDelta,
     ) -> Result<(), IncompatibleCheckpointDelta> {
+        if delta.source_id == MULTIPLEXED_SOURCE_ID {
+            let mut source_checkpoints = Vec::new();
+            for (source_id, source_delta) in delta.source_delta.demultiplex() {
+                let mut source_checkpoint =
+                    self.per_source.get(&source_id).cloned().unwrap_or_default();
+                source_checkpoint.try_apply_delta(source_delta)?;
+                source_checkpoints.push((source_id, source_checkpoint));
+            }
+            self.per_source.extend(source_checkpoints);
+            return Ok(());
+        }
         self.per_source
             .entry(delta.source_id)
             .or_default()
@@ -161,6 +198,25 @@ impl IndexCheckpoint {
         Ok(())
     }
 
+    /// Returns the checkpoint of a multiplexed pipeline reading the sources `source_ids`, made of
+    /// the partitions of these sources prefixed with their source ID.
+    pub fn multiplexed_source_checkpoint<'a>(
+        &self,
+        source_ids: impl IntoIterator<Item = &'a str>,
+    ) -> SourceCheckpoint {
+        let mut multiplexed_checkpoint = SourceCheckpoint::default();
+        for source_id in source_ids {
+            if let Some(source_checkpoint) = self.per_source.get(source_id) {
+                for (partition_id, position) in source_checkpoint.iter() {
+                    multiplexed_checkpoint
+                        .per_partition
+                        .insert(partition_id.multiplexed(source_id), position);
+                }
+            }
+        }
+        multiplexed_checkpoint
+    }
+
     /// Resets the checkpoint of the source identified by `source_id`. Returns whether a mutation
     /// occurred.
     pub(crate) fn reset_source(&mut self, source_id: &str) -> bool {
@@ -266,11 +322,14 @@ impl<'de> Deserialize<'de> for SourceCheckpoint {
 
 /// Error returned when trying to apply a checkpoint delta to a checkpoint that is not
 /// compatible. ie: the checkpoint delta starts from a point anterior to
-/// the checkpoint.
-#[derive(Debug, Error, Eq, PartialEq, Serialize, Deserialize)]
+/// the checkpoint, and would therefore overlap positions that were already acknowledged.
+#[derive(Clone, Debug, Error, Eq, PartialEq, Serialize, Deserialize)]
 #[error(
-    "IncompatibleChkptDelta at partition: {partition_id:?} cur_pos:{current_position:?} \
-     delta_pos:{delta_position_from:?}"
+    "Checkpoint delta overlaps already acknowledged positions for partition `{}`: expected the \
+     delta to start from position `{}` or after, got `{}`.",
+    .partition_id.0,
+    .current_position.as_str(),
+    .delta_position_from.as_str()
 )]
 pub struct IncompatibleCheckpointDelta {
     /// One PartitionId for which the incompatibility has been detected.
@@ -294,6 +353,23 @@ impl SourceCheckpoint {
             .map(|(partition_id, position)| (partition_id.clone(), position.clone()))
     }
 
+    /// Splits the checkpoint of a multiplexed pipeline into the checkpoints of the sources it
+    /// reads, keyed by source ID.
+    pub fn demultiplex(&self) -> BTreeMap<String, SourceCheckpoint> {
+        let mut source_checkpoints: BTreeMap<String, SourceCheckpoint> = BTreeMap::new();
+        for (partition_id, position) in &self.per_partition {
+            let (source_id, partition_id) = partition_id
+                .demultiplexed()
+                .unwrap_or_else(|| (MULTIPLEXED_SOURCE_ID, partition_id.clone()));
+            source_checkpoints
+                .entry(source_id.to_string())
+                .or_default()
+                .per_partition
+                .insert(partition_id, position.clone());
+        }
+        source_checkpoints
+    }
+
     fn check_compatibility(
         &self,
         delta: &SourceCheckpointDelta,
@@ -349,6 +425,61 @@ impl SourceCheckpoint {
         }
         Ok(())
     }
+
+    /// Moves the position of every partition covered by the delta forward to the end of the
+    /// delta. Partitions that are already beyond that position are left untouched.
+    ///
+    /// Contrary to [`SourceCheckpoint::try_apply_delta`], this method never fails. It is meant to
+    /// track watermarks, not to enforce exactly-once semantics.
+    pub fn advance_with_delta(&mut self, delta: &SourceCheckpointDelta) {
+        for (partition_id, partition_delta) in &delta.per_partition {
+            self.advance_partition(partition_id, &partition_delta.to);
+        }
+    }
+
+    /// Moves the position of every partition forward to the position reached in `checkpoint`.
+    /// Partitions that are already beyond that position are left untouched.
+    pub fn advance_with_checkpoint(&mut self, checkpoint: &SourceCheckpoint) {
+        for (partition_id, position) in &checkpoint.per_partition {
+            self.advance_partition(partition_id, position);
+        }
+    }
+
+    /// Checks that the delta chains perfectly after the checkpoint: for all the partitions of
+    /// the checkpoint, the delta must start exactly at the current position.
+    ///
+    /// Contrary to [`SourceCheckpoint::try_apply_delta`], gaps are rejected as well as overlaps.
+    /// Partitions unknown to the checkpoint are accepted, whatever their origin position.
+    pub fn check_chaining(
+        &self,
+        delta: &SourceCheckpointDelta,
+    ) -> Result<(), IncompatibleCheckpointDelta> {
+        for (delta_partition, delta_position) in &delta.per_partition {
+            if let Some(position) = self.per_partition.get(delta_partition) {
+                if *position != delta_position.from {
+                    return Err(IncompatibleCheckpointDelta {
+                        partition_id: delta_partition.clone(),
+                        current_position: position.clone(),
+                        delta_position_from: delta_position.from.clone(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn advance_partition(&mut self, partition_id: &PartitionId, position: &Position) {
+        match self.per_partition.entry(partition_id.clone()) {
+            Entry::Occupied(mut occupied_entry) => {
+                if occupied_entry.get() < position {
+                    occupied_entry.insert(position.clone());
+                }
+            }
+            Entry::Vacant(vacant_entry) => {
+                vacant_entry.insert(position.clone());
+            }
+        }
+    }
 }
 
 impl fmt::Debug for SourceCheckpoint {
@@ -523,6 +654,38 @@ impl SourceCheckpointDelta {
         Ok(())
     }
 
+    /// Prefixes the partitions of the delta with `source_id`, so that the delta can be merged
+    /// with the deltas of the other sources of a multiplexed pipeline.
+    pub fn multiplexed(self, source_id: &str) -> SourceCheckpointDelta {
+        let per_partition = self
+            .per_partition
+            .into_iter()
+            .map(|(partition_id, partition_delta)| {
+                (partition_id.multiplexed(source_id), partition_delta)
+            })
+            .collect();
+        SourceCheckpointDelta { per_partition }
+    }
+
+    /// Splits the delta of a multiplexed pipeline into the deltas of the sources it reads, keyed
+    /// by source ID. Partitions that are not prefixed with a source ID are kept under the
+    /// multiplexed source ID.
+    pub fn demultiplex(self) -> BTreeMap<String, SourceCheckpointDelta> {
+        let mut source_deltas: BTreeMap<String, SourceCheckpointDelta> = BTreeMap::new();
+        for (partition_id, partition_delta) in self.per_partition {
+            let (source_id, partition_id) = partition_id
+                .demultiplexed()
+                .map(|(source_id, partition_id)| (source_id.to_string(), partition_id))
+                .unwrap_or_else(|| (MULTIPLEXED_SOURCE_ID.to_string(), partition_id.clone()));
+            source_deltas
+                .entry(source_id)
+                .or_default()
+                .per_partition
+                .insert(partition_id, partition_delta);
+        }
+        source_deltas
+    }
+
     /// Returns the number of partitions covered by the checkpoint delta.
     pub fn num_partitions(&self) -> usize {
         self.per_partition.len()
@@ -607,10 +770,20 @@ mod tests {
             )?;
             delta
         };
-        assert!(matches!(
-            checkpoint.try_apply_delta(delta2),
-            Err(IncompatibleCheckpointDelta { .. })
-        ));
+        let incompatible_delta = checkpoint.try_apply_delta(delta2).unwrap_err();
+        assert_eq!(
+            incompatible_delta,
+            IncompatibleCheckpointDelta {
+                partition_id: PartitionId::from("b"),
+                current_position: Position::from("60187"),
+                delta_position_from: Position::from("50099"),
+            }
+        );
+        assert_eq!(
+            incompatible_delta.to_string(),
+            "Checkpoint delta overlaps already acknowledged positions for partition `b`: expected \
+             the delta to start from position `60187` or after, got `50099`."
+        );
         // checkpoint was unchanged
         assert_eq!(format!("{:?}", checkpoint), "Ckpt(a:00128 b:60187)");
         Ok(())
@@ -746,6 +919,82 @@ mod tests {
             .is_none());
     }
 
+    #[test]
+    fn test_index_checkpoint_multiplexed_delta() {
+        let mut index_checkpoint = IndexCheckpoint::default();
+        index_checkpoint
+            .try_apply_delta(IndexCheckpointDelta::for_test("source-a", 0..3))
+            .unwrap();
+        let multiplexed_checkpoint =
+            index_checkpoint.multiplexed_source_checkpoint(["source-a", "source-b"]);
+        assert_eq!(
+            format!("{:?}", multiplexed_checkpoint),
+            "Ckpt(source-a/:00000000000000000002)"
+        );
+        let source_checkpoints = multiplexed_checkpoint.demultiplex();
+        assert_eq!(source_checkpoints.len(), 1);
+        assert_eq!(
+            &source_checkpoints["source-a"],
+            index_checkpoint.source_checkpoint("source-a").unwrap()
+        );
+
+        let mut multiplexed_delta = SourceCheckpointDelta::from(3..5).multiplexed("source-a");
+        multiplexed_delta
+            .extend(
+                SourceCheckpointDelta::from_partition_delta(
+                    PartitionId::from("data/logs.json"),
+                    Position::Beginning,
+                    Position::from(10u64),
+                )
+                .multiplexed("source-b"),
+            )
+            .unwrap();
+        index_checkpoint
+            .try_apply_delta(IndexCheckpointDelta {
+                source_id: MULTIPLEXED_SOURCE_ID.to_string(),
+                source_delta: multiplexed_delta,
+            })
+            .unwrap();
+        assert!(index_checkpoint
+            .source_checkpoint(MULTIPLEXED_SOURCE_ID)
+            .is_none());
+        assert_eq!(
+            format!(
+                "{:?}",
+                index_checkpoint.source_checkpoint("source-a").unwrap()
+            ),
+            "Ckpt(:00000000000000000004)"
+        );
+        assert_eq!(
+            format!(
+                "{:?}",
+                index_checkpoint.source_checkpoint("source-b").unwrap()
+            ),
+            "Ckpt(data/logs.json:00000000000000000010)"
+        );
+
+        // An incompatible delta for one of the sources leaves all the checkpoints unchanged.
+        let mut incompatible_delta = SourceCheckpointDelta::from(5..6).multiplexed("source-a");
+        incompatible_delta
+            .extend(
+                SourceCheckpointDelta::from_partition_delta(
+                    PartitionId::from("data/logs.json"),
+                    Position::from(5u64),
+                    Position::from(6u64),
+                )
+                .multiplexed("source-b"),
+            )
+            .unwrap();
+        let index_checkpoint_before = index_checkpoint.clone();
+        index_checkpoint
+            .try_apply_delta(IndexCheckpointDelta {
+                source_id: MULTIPLEXED_SOURCE_ID.to_string(),
+                source_delta: incompatible_delta,
+            })
+            .unwrap_err();
+        assert_eq!(index_checkpoint, index_checkpoint_before);
+    }
+
     #[test]
     fn test_get_source_checkpoint() {
         let partition = PartitionId::from("a");
@@ -760,4 +1009,85 @@ mod tests {
             &Position::from(43u64)
         );
     }
+
+    #[test]
+    fn test_advance_checkpoint() -> anyhow::Result<()> {
+        let mut checkpoint = SourceCheckpoint::default();
+        let delta = {
+            let mut delta = SourceCheckpointDelta::from_partition_delta(
+                PartitionId::from("a"),
+                Position::from("00123"),
+                Position::from("00128"),
+            );
+            delta.record_partition_delta(
+                PartitionId::from("b"),
+                Position::from("60002"),
+                Position::from("60187"),
+            )?;
+            delta
+        };
+        checkpoint.advance_with_delta(&delta);
+        assert_eq!(format!("{:?}", checkpoint), "Ckpt(a:00128 b:60187)");
+        // Going backward is a no-op, unlike `try_apply_delta` this does not fail.
+        let backward_delta = SourceCheckpointDelta::from_partition_delta(
+            PartitionId::from("b"),
+            Position::from("50099"),
+            Position::from("60002"),
+        );
+        checkpoint.advance_with_delta(&backward_delta);
+        assert_eq!(format!("{:?}", checkpoint), "Ckpt(a:00128 b:60187)");
+
+        let other_checkpoint: SourceCheckpoint = vec![
+            (PartitionId::from("a"), Position::from("00130")),
+            (PartitionId::from("c"), Position::from("00001")),
+        ]
+        .into_iter()
+        .collect();
+        checkpoint.advance_with_checkpoint(&other_checkpoint);
+        assert_eq!(format!("{:?}", checkpoint), "Ckpt(a:00130 b:60187 c:00001)");
+        Ok(())
+    }
+    #[test]
+    fn test_check_chaining() {
+        let checkpoint: SourceCheckpoint = vec![
+            (PartitionId::from("a"), Position::from("00128")),
+            (PartitionId::from("b"), Position::from("60187")),
+        ]
+        .into_iter()
+        .collect();
+        let chained_delta = SourceCheckpointDelta::from_partition_delta(
+            PartitionId::from("a"),
+            Position::from("00128"),
+            Position::from("00130"),
+        );
+        assert!(checkpoint.check_chaining(&chained_delta).is_ok());
+
+        let new_partition_delta = SourceCheckpointDelta::from_partition_delta(
+            PartitionId::from("c"),
+            Position::from("00042"),
+            Position::from("00043"),
+        );
+        assert!(checkpoint.check_chaining(&new_partition_delta).is_ok());
+
+        // Gaps are rejected, unlike with `try_apply_delta`.
+        let gap_delta = SourceCheckpointDelta::from_partition_delta(
+            PartitionId::from("b"),
+            Position::from("60190"),
+            Position::from("60200"),
+        );
+        let incompatible_delta = checkpoint.check_chaining(&gap_delta).unwrap_err();
+        assert_eq!(incompatible_delta.partition_id, PartitionId::from("b"));
+        assert_eq!(incompatible_delta.current_position, Position::from("60187"));
+        assert_eq!(
+            incompatible_delta.delta_position_from,
+            Position::from("60190")
+        );
+
+        let overlapping_delta = SourceCheckpointDelta::from_partition_delta(
+            PartitionId::from("a"),
+            Position::from("00120"),
+            Position::from("00130"),
+        );
+        assert!(checkpoint.check_chaining(&overlapping_delta).is_err());
+    }
 }
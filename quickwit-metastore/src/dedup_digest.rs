@@ -0,0 +1,301 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use tracing::warn;
+
+/// False positive rate targeted for a lookup in a whole deduplication window. A lookup goes
+/// through all the bloom filters of the window and their false positive rates add up, so each
+/// filter is sized for this rate divided by the number of filters of the window.
+const TARGET_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// Maximum size of the bloom filters of a digest, which are stored base64-encoded in the split
+/// metadata. The digests of splits with too many documents get fewer bits per hash, and a higher
+/// false positive rate.
+const MAX_DEDUP_DIGEST_NUM_BYTES: usize = 256_000;
+
+/// Duration of the time buckets of a digest.
+pub const DEDUP_BUCKET_DURATION_SECS: i64 = 60;
+
+/// Compact digest of the hashes of the document IDs indexed in a split.
+///
+/// The hashes are stored in bloom filters bucketed by the minute the documents were indexed in,
+/// so that deduplication windows can be enforced by dropping the expired buckets. A digest may
+/// return false positives but never false negatives.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DedupDigest {
+    buckets: BTreeMap<i64, Vec<BloomFilter>>,
+}
+
+impl DedupDigest {
+    /// Builds a digest from hashes grouped by the start timestamp of their time bucket.
+    ///
+    /// `num_window_filters` is the number of bloom filters a lookup in the deduplication window
+    /// is expected to go through, across the digests of all the splits of the window.
+    pub fn from_buckets<'a>(
+        buckets: impl IntoIterator<Item = (i64, &'a [u64])>,
+        num_window_filters: usize,
+    ) -> Self {
+        let buckets: Vec<(i64, &[u64])> = buckets
+            .into_iter()
+            .filter(|(_, hashes)| !hashes.is_empty())
+            .collect();
+        let num_hashes: usize = buckets.iter().map(|(_, hashes)| hashes.len()).sum();
+        let false_positive_rate = TARGET_FALSE_POSITIVE_RATE / num_window_filters.max(1) as f64;
+        let optimal_num_bits_per_hash =
+            -false_positive_rate.ln() / (std::f64::consts::LN_2 * std::f64::consts::LN_2);
+        let max_num_bits_per_hash =
+            (MAX_DEDUP_DIGEST_NUM_BYTES * 8) as f64 / num_hashes.max(1) as f64;
+        if optimal_num_bits_per_hash > max_num_bits_per_hash {
+            warn!(
+                num_hashes = num_hashes,
+                max_num_bytes = MAX_DEDUP_DIGEST_NUM_BYTES,
+                "Deduplication digest exceeds its maximum size, increasing its false positive \
+                 rate."
+            );
+        }
+        let num_bits_per_hash = optimal_num_bits_per_hash
+            .min(max_num_bits_per_hash)
+            .max(1.0);
+        let num_hash_functions = (num_bits_per_hash * std::f64::consts::LN_2).round() as u32;
+
+        let mut digest = DedupDigest::default();
+        for (bucket_timestamp, hashes) in buckets {
+            let bloom_filter =
+                BloomFilter::from_hashes(hashes, num_bits_per_hash, num_hash_functions.max(1));
+            digest
+                .buckets
+                .entry(bucket_start_timestamp(bucket_timestamp))
+                .or_default()
+                .push(bloom_filter);
+        }
+        digest
+    }
+
+    /// Returns whether the digest may contain `hash`.
+    pub fn contains(&self, hash: u64) -> bool {
+        self.buckets
+            .values()
+            .flatten()
+            .any(|bloom_filter| bloom_filter.contains(hash))
+    }
+
+    /// Merges `other` into this digest.
+    pub fn merge(&mut self, other: DedupDigest) {
+        for (bucket_timestamp, bloom_filters) in other.buckets {
+            self.buckets
+                .entry(bucket_timestamp)
+                .or_default()
+                .extend(bloom_filters);
+        }
+    }
+
+    /// Drops the buckets that ended before `start_timestamp`.
+    pub fn retain_since(&mut self, start_timestamp: i64) {
+        let first_bucket_timestamp = bucket_start_timestamp(start_timestamp);
+        self.buckets = self.buckets.split_off(&first_bucket_timestamp);
+    }
+
+    /// Returns the number of bloom filters of the digest.
+    pub fn num_bloom_filters(&self) -> usize {
+        self.buckets.values().map(Vec::len).sum()
+    }
+
+    /// Returns the size of the bloom filters of the digest in bytes.
+    pub fn num_bytes(&self) -> usize {
+        self.buckets
+            .values()
+            .flatten()
+            .map(|bloom_filter| bloom_filter.bits.len())
+            .sum()
+    }
+
+    /// Returns true if the digest holds no hash.
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    /// Returns the start timestamp of the most recent bucket of the digest.
+    pub fn last_bucket_timestamp(&self) -> Option<i64> {
+        self.buckets.keys().next_back().copied()
+    }
+}
+
+/// Returns the start timestamp of the time bucket `timestamp` belongs to.
+pub fn bucket_start_timestamp(timestamp: i64) -> i64 {
+    timestamp - timestamp.rem_euclid(DEDUP_BUCKET_DURATION_SECS)
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+struct BloomFilter {
+    num_hash_functions: u32,
+    #[serde(
+        serialize_with = "serialize_bits",
+        deserialize_with = "deserialize_bits"
+    )]
+    bits: Vec<u8>,
+}
+
+impl BloomFilter {
+    fn from_hashes(hashes: &[u64], num_bits_per_hash: f64, num_hash_functions: u32) -> Self {
+        let num_bits = (hashes.len() as f64 * num_bits_per_hash).ceil() as usize;
+        let num_bytes = (num_bits + 7) / 8;
+        let mut bloom_filter = BloomFilter {
+            num_hash_functions,
+            bits: vec![0u8; num_bytes.max(1)],
+        };
+        for &hash in hashes {
+            for bit_index in bloom_filter.bit_indexes(hash) {
+                bloom_filter.bits[bit_index / 8] |= 1 << (bit_index % 8);
+            }
+        }
+        bloom_filter
+    }
+
+    fn contains(&self, hash: u64) -> bool {
+        self.bit_indexes(hash)
+            .all(|bit_index| self.bits[bit_index / 8] & (1 << (bit_index % 8)) != 0)
+    }
+
+    /// Derives the bit indexes of a hash using double hashing.
+    fn bit_indexes(&self, hash: u64) -> impl Iterator<Item = usize> {
+        let num_bits = (self.bits.len() * 8) as u64;
+        let low_hash = hash & u32::MAX as u64;
+        let high_hash = (hash >> 32) | 1;
+        (0..self.num_hash_functions as u64).map(move |hash_function_ord| {
+            (low_hash.wrapping_add(hash_function_ord.wrapping_mul(high_hash)) % num_bits) as usize
+        })
+    }
+}
+
+fn serialize_bits<S>(bits: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+where S: Serializer {
+    serializer.serialize_str(&base64::encode(bits))
+}
+
+fn deserialize_bits<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where D: Deserializer<'de> {
+    let encoded_bits = String::deserialize(deserializer)?;
+    let bits = base64::decode(encoded_bits).map_err(serde::de::Error::custom)?;
+    if bits.is_empty() {
+        return Err(serde::de::Error::custom("Bloom filter must not be empty."));
+    }
+    Ok(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedup_digest_contains() {
+        let hashes: Vec<u64> = (0..1_000u64)
+            .map(|hash| hash.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+            .collect();
+        let digest = DedupDigest::from_buckets([(120, &hashes[..500]), (185, &hashes[500..])], 2);
+        assert_eq!(digest.last_bucket_timestamp(), Some(180));
+        assert!(hashes.iter().all(|&hash| digest.contains(hash)));
+
+        let num_false_positives = (1_000..11_000u64)
+            .map(|hash| hash.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+            .filter(|&hash| digest.contains(hash))
+            .count();
+        assert!(num_false_positives < 200);
+    }
+
+    #[test]
+    fn test_dedup_digest_window_false_positive_rate() {
+        // One digest per split, each with one filter per minute of a one-hour window.
+        let num_splits = 10;
+        let num_buckets = 60;
+        let num_window_filters = num_splits * num_buckets;
+        let mut window_digest = DedupDigest::default();
+        for split_ord in 0..num_splits as u64 {
+            let hashes: Vec<Vec<u64>> = (0..num_buckets as u64)
+                .map(|bucket_ord| {
+                    (0..100u64)
+                        .map(|hash_ord| {
+                            (split_ord << 32 | bucket_ord << 16 | hash_ord)
+                                .wrapping_mul(0x9E37_79B9_7F4A_7C15)
+                        })
+                        .collect()
+                })
+                .collect();
+            let digest = DedupDigest::from_buckets(
+                hashes
+                    .iter()
+                    .enumerate()
+                    .map(|(bucket_ord, bucket_hashes)| {
+                        (
+                            bucket_ord as i64 * DEDUP_BUCKET_DURATION_SECS,
+                            &bucket_hashes[..],
+                        )
+                    }),
+                num_window_filters,
+            );
+            window_digest.merge(digest);
+        }
+        assert_eq!(window_digest.num_bloom_filters(), num_window_filters);
+
+        let num_lookups = 20_000;
+        let num_false_positives = (0..num_lookups)
+            .map(|hash_ord: u64| (1 << 48 | hash_ord).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+            .filter(|&hash| window_digest.contains(hash))
+            .count();
+        assert!(num_false_positives * 100 < num_lookups as usize * 2);
+    }
+
+    #[test]
+    fn test_dedup_digest_max_num_bytes() {
+        let hashes: Vec<u64> = (0..1_000_000u64)
+            .map(|hash| hash.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+            .collect();
+        let digest = DedupDigest::from_buckets([(0, &hashes[..])], 1_000);
+        assert!(digest.num_bytes() <= MAX_DEDUP_DIGEST_NUM_BYTES + 1);
+        assert!(hashes.iter().all(|&hash| digest.contains(hash)));
+    }
+
+    #[test]
+    fn test_dedup_digest_merge_and_retain_since() {
+        let mut digest = DedupDigest::from_buckets([(0, &[1u64, 2][..]), (60, &[3u64][..])], 4);
+        digest.merge(DedupDigest::from_buckets(
+            [(60, &[4u64][..]), (120, &[5u64][..])],
+            4,
+        ));
+        assert!((1..=5).all(|hash| digest.contains(hash)));
+
+        digest.retain_since(90);
+        assert!(!digest.contains(1));
+        assert!(!digest.contains(2));
+        assert!((3..=5).all(|hash| digest.contains(hash)));
+
+        digest.retain_since(180);
+        assert!(digest.is_empty());
+    }
+
+    #[test]
+    fn test_dedup_digest_serialization() {
+        let digest = DedupDigest::from_buckets([(60, &[1u64, 2, 3][..])], 1);
+        let digest_json = serde_json::to_string(&digest).unwrap();
+        let deserialized_digest: DedupDigest = serde_json::from_str(&digest_json).unwrap();
+        assert_eq!(deserialized_digest, digest);
+    }
+}
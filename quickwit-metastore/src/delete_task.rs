@@ -0,0 +1,106 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Serialize};
+
+/// Query selecting the documents of an index to delete.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeleteQuery {
+    /// Index ID.
+    pub index_id: String,
+    /// Query text, using the same syntax as search queries.
+    pub query: String,
+    /// Fields to search on when the query does not target a field explicitly. Defaults to the
+    /// default search fields of the index.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub search_fields: Vec<String>,
+    /// If set, restricts the deletion to the documents with a timestamp greater than or equal to
+    /// this value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_timestamp: Option<i64>,
+    /// If set, restricts the deletion to the documents with a timestamp strictly lower than this
+    /// value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_timestamp: Option<i64>,
+}
+
+impl DeleteQuery {
+    /// Returns whether the query may match documents of a split covering `split_time_range`.
+    pub fn overlaps_time_range(&self, split_time_range: &Option<RangeInclusive<i64>>) -> bool {
+        let split_time_range = match split_time_range {
+            Some(split_time_range) => split_time_range,
+            None => return true,
+        };
+        if let Some(start_timestamp) = self.start_timestamp {
+            if *split_time_range.end() < start_timestamp {
+                return false;
+            }
+        }
+        if let Some(end_timestamp) = self.end_timestamp {
+            if *split_time_range.start() >= end_timestamp {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A delete task records a delete query along with its opstamp.
+///
+/// Opstamps are strictly increasing within an index. A split whose `delete_opstamp` is lower than
+/// the opstamp of a delete task may still contain documents matching the delete query.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeleteTask {
+    /// Timestamp at which the delete task was created.
+    pub create_timestamp: i64,
+    /// Operation stamp of the delete task.
+    pub opstamp: u64,
+    /// Query selecting the documents to delete.
+    pub delete_query: DeleteQuery,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delete_query_overlaps_time_range() {
+        let delete_query = DeleteQuery {
+            index_id: "test-index".to_string(),
+            query: "user_id:42".to_string(),
+            start_timestamp: Some(100),
+            end_timestamp: Some(200),
+            ..Default::default()
+        };
+        assert!(delete_query.overlaps_time_range(&None));
+        assert!(delete_query.overlaps_time_range(&Some(150..=250)));
+        assert!(delete_query.overlaps_time_range(&Some(0..=100)));
+        assert!(!delete_query.overlaps_time_range(&Some(0..=99)));
+        assert!(!delete_query.overlaps_time_range(&Some(200..=300)));
+
+        let delete_query = DeleteQuery {
+            index_id: "test-index".to_string(),
+            query: "user_id:42".to_string(),
+            ..Default::default()
+        };
+        assert!(delete_query.overlaps_time_range(&Some(0..=99)));
+    }
+}
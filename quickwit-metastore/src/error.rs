@@ -39,6 +39,9 @@ pub enum MetastoreError {
     #[error("Index `{index_id}` does not exist.")]
     IndexDoesNotExist { index_id: String },
 
+    #[error("Index `{index_id}` is in maintenance mode and is read-only.")]
+    MaintenanceMode { index_id: String },
+
     /// Any generic internal error.
     /// The message can be helpful to users, but the detail of the error
     /// are judged uncoverable and not useful for error handling.
@@ -60,8 +63,13 @@ pub enum MetastoreError {
     #[error("Splits `{split_ids:?}` are not staged.")]
     SplitsNotStaged { split_ids: Vec<String> },
 
-    #[error("Publish checkpoint delta overlaps with the current checkpoint: {0:?}.")]
-    IncompatibleCheckpointDelta(#[from] IncompatibleCheckpointDelta),
+    #[error("Splits `{split_ids:?}` are not published.")]
+    SplitsNotPublished { split_ids: Vec<String> },
+
+    /// The checkpoint delta of a publish overlaps the current checkpoint of the index, typically
+    /// because a concurrent publish already covered the same positions.
+    #[error("Publish checkpoint delta conflicts with the current checkpoint: {0}")]
+    CheckpointConflict(#[from] IncompatibleCheckpointDelta),
 
     #[error("Source `{source_id}` of type `{source_type}` already exists.")]
     SourceAlreadyExists {
@@ -103,19 +111,21 @@ impl From<MetastoreError> for quickwit_proto::tonic::Status {
 impl ServiceError for MetastoreError {
     fn status_code(&self) -> ServiceErrorCode {
         match self {
+            Self::CheckpointConflict(_) => ServiceErrorCode::Conflict,
             Self::ConnectionError { .. } => ServiceErrorCode::Internal,
             Self::Forbidden { .. } => ServiceErrorCode::Internal,
-            Self::IncompatibleCheckpointDelta(_) => ServiceErrorCode::BadRequest,
             Self::IndexAlreadyExists { .. } => ServiceErrorCode::BadRequest,
             Self::IndexDoesNotExist { .. } => ServiceErrorCode::BadRequest,
             Self::InternalError { .. } => ServiceErrorCode::Internal,
             Self::InvalidManifest { .. } => ServiceErrorCode::Internal,
+            Self::MaintenanceMode { .. } => ServiceErrorCode::MethodNotAllowed,
             Self::Io { .. } => ServiceErrorCode::Internal,
             Self::SourceAlreadyExists { .. } => ServiceErrorCode::BadRequest,
             Self::SourceDoesNotExist { .. } => ServiceErrorCode::BadRequest,
             Self::SplitsDoNotExist { .. } => ServiceErrorCode::BadRequest,
             Self::SplitsNotDeletable { .. } => ServiceErrorCode::BadRequest,
             Self::SplitsNotStaged { .. } => ServiceErrorCode::BadRequest,
+            Self::SplitsNotPublished { .. } => ServiceErrorCode::BadRequest,
             Self::DbError { .. } => ServiceErrorCode::Internal,
             Self::JsonDeserializeError { .. } => ServiceErrorCode::Internal,
             Self::JsonSerializeError { .. } => ServiceErrorCode::Internal,
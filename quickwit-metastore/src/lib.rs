@@ -23,10 +23,13 @@
 //! `quickwit-metastore` is the abstraction used in quickwit to interface itself to different
 //! metastore:
 //! - file-backed metastore
+//! - RAM metastore
 //! etc.
 
 #[macro_use]
 mod tests;
+mod dedup_digest;
+mod delete_task;
 mod split_metadata;
 mod split_metadata_version;
 
@@ -34,20 +37,31 @@ mod split_metadata_version;
 pub mod checkpoint;
 mod error;
 mod metastore;
+mod metastore_migration;
 mod metastore_resolver;
 
+pub use dedup_digest::{bucket_start_timestamp, DedupDigest, DEDUP_BUCKET_DURATION_SECS};
+pub use delete_task::{DeleteQuery, DeleteTask};
 pub use error::{MetastoreError, MetastoreResolverError, MetastoreResult};
 pub use metastore::file_backed_metastore::FileBackedMetastore;
 pub use metastore::grpc_metastore::{GrpcMetastoreAdapter, MetastoreGrpcClient};
 #[cfg(feature = "postgres")]
 pub use metastore::postgresql_metastore::PostgresqlMetastore;
+pub use metastore::ram_metastore::{RamMetastore, RamMetastoreFactory};
 #[cfg(any(test, feature = "testsuite"))]
 pub use metastore::MockMetastore;
-pub use metastore::{file_backed_metastore, IndexMetadata, Metastore};
+pub use metastore::{
+    file_backed_metastore, IndexMetadata, ListSplitsQuery, MergePlannerLease, Metastore,
+    QuarantinedFile, SplitProducer,
+};
+pub use metastore_migration::{migrate_metastore, MigratedIndex};
 pub use metastore_resolver::{
     quickwit_metastore_uri_resolver, MetastoreFactory, MetastoreUriResolver,
 };
-pub use split_metadata::{Split, SplitMetadata, SplitState};
+pub use split_metadata::{
+    FieldValueRange, Split, SplitFieldStats, SplitMetadata, SplitState, SplitTagsAmendment,
+    MAX_TAGS_AMENDMENTS_PER_SPLIT,
+};
 pub(crate) use split_metadata_version::VersionedSplitMetadata;
 
 #[cfg(test)]
@@ -21,17 +21,20 @@
 //! import [`FileBackedIndex`] and run backward-compatibility tests. You should not have to import
 //! anything from here directly.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::ops::{Range, RangeInclusive};
 
 use itertools::Itertools;
-use quickwit_config::SourceConfig;
+use quickwit_config::{DocMapping, SearchSettings, SourceConfig};
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
 use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 
 use crate::checkpoint::IndexCheckpointDelta;
-use crate::{IndexMetadata, MetastoreError, MetastoreResult, Split, SplitMetadata, SplitState};
+use crate::{
+    DeleteQuery, DeleteTask, IndexMetadata, ListSplitsQuery, MetastoreError, MetastoreResult,
+    QuarantinedFile, Split, SplitMetadata, SplitProducer, SplitState,
+};
 
 /// A `FileBackedIndex` object carries an index metadata and its split metadata.
 // This struct is meant to be used only within the [`FileBackedMetastore`]. The public visibility is
@@ -43,6 +46,13 @@ pub struct FileBackedIndex {
     metadata: IndexMetadata,
     /// List of splits belonging to the index.
     splits: HashMap<String, Split>,
+    /// Delete tasks of the index, sorted by opstamp.
+    delete_tasks: Vec<DeleteTask>,
+    /// Position of the last mutation of the index in its write-ahead log.
+    wal_position: u64,
+    /// Position in the write-ahead log of the last mutation included in the snapshot of the index
+    /// stored on the storage.
+    snapshot_wal_position: u64,
     /// Has been discarded. This field exists to make
     /// it possible to discard this entry if there is an error
     /// while mutating the Index.
@@ -54,6 +64,9 @@ impl From<IndexMetadata> for FileBackedIndex {
         Self {
             metadata: index_metadata,
             splits: Default::default(),
+            delete_tasks: Vec::new(),
+            wal_position: 0,
+            snapshot_wal_position: 0,
             discarded: false,
         }
     }
@@ -85,6 +98,14 @@ pub(crate) struct FileBackedIndexV0 {
     #[serde(rename = "index")]
     metadata: IndexMetadata,
     splits: Vec<Split>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    delete_tasks: Vec<DeleteTask>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    wal_position: u64,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
 }
 
 impl From<FileBackedIndex> for FileBackedIndexV0 {
@@ -96,16 +117,38 @@ impl From<FileBackedIndex> for FileBackedIndexV0 {
                 .into_values()
                 .sorted_by_key(|split| split.update_timestamp)
                 .collect(),
+            delete_tasks: index.delete_tasks,
+            wal_position: index.wal_position,
         }
     }
 }
 
 impl From<FileBackedIndexV0> for FileBackedIndex {
     fn from(index: FileBackedIndexV0) -> Self {
-        Self::new(index.metadata, index.splits)
+        let mut file_backed_index = Self::new(index.metadata, index.splits);
+        file_backed_index.delete_tasks = index.delete_tasks;
+        file_backed_index.wal_position = index.wal_position;
+        file_backed_index.snapshot_wal_position = index.wal_position;
+        file_backed_index
     }
 }
 
+/// Record of a mutation of a [`FileBackedIndex`] in its write-ahead log. It holds the state of the
+/// index entities modified by the mutation rather than the operation itself, so that replaying it
+/// does not depend on the time at which it is replayed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct WalRecord {
+    pub position: u64,
+    #[serde(rename = "index")]
+    pub metadata: IndexMetadata,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub upserted_splits: Vec<Split>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deleted_split_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub new_delete_tasks: Vec<DeleteTask>,
+}
+
 enum DeleteSplitOutcome {
     Success,
     SplitNotFound,
@@ -117,6 +160,59 @@ fn is_disjoint(left: &Range<i64>, right: &RangeInclusive<i64>) -> bool {
     left.end <= *right.start() || *right.end() < left.start
 }
 
+fn split_matches_query(split: &Split, query: &ListSplitsQuery) -> bool {
+    if let Some(split_state) = query.split_state_opt {
+        if split.split_state != split_state {
+            return false;
+        }
+    }
+    // A split without time range always matches the time range filter.
+    if let (Some(filter_time_range), Some(split_time_range)) = (
+        query.time_range_opt.as_ref(),
+        split.split_metadata.time_range.as_ref(),
+    ) {
+        if is_disjoint(filter_time_range, split_time_range) {
+            return false;
+        }
+    }
+    if let Some(create_timestamp_range) = query.create_timestamp_range_opt.as_ref() {
+        if !create_timestamp_range.contains(&split.split_metadata.create_timestamp) {
+            return false;
+        }
+    }
+    if let Some(tags_filter_ast) = query.tags_opt.as_ref() {
+        if !tags_filter_ast.evaluate(&split.split_metadata.tags) {
+            return false;
+        }
+    }
+    if let Some(source_id) = query.source_id_opt.as_ref() {
+        if split.split_metadata.source_id != *source_id {
+            return false;
+        }
+    }
+    if let Some(node_id) = query.node_id_opt.as_ref() {
+        if split.split_metadata.node_id != *node_id {
+            return false;
+        }
+    }
+    if let Some(pipeline_ord) = query.pipeline_ord_opt {
+        if split.split_metadata.pipeline_ord != pipeline_ord {
+            return false;
+        }
+    }
+    if let Some(pipeline_generation) = query.pipeline_generation_opt {
+        if split.split_metadata.pipeline_generation != pipeline_generation {
+            return false;
+        }
+    }
+    if let Some(current_config_fingerprint) = query.current_config_fingerprint_opt.as_ref() {
+        if split.split_metadata.config_fingerprint.as_ref() == Some(current_config_fingerprint) {
+            return false;
+        }
+    }
+    true
+}
+
 impl FileBackedIndex {
     /// Constructor.
     pub fn new(metadata: IndexMetadata, splits: Vec<Split>) -> Self {
@@ -126,6 +222,9 @@ impl FileBackedIndex {
                 .into_iter()
                 .map(|split| (split.split_id().to_string(), split))
                 .collect(),
+            delete_tasks: Vec::new(),
+            wal_position: 0,
+            snapshot_wal_position: 0,
             discarded: false,
         }
     }
@@ -145,10 +244,68 @@ impl FileBackedIndex {
         &self.splits
     }
 
+    /// Position of the last mutation of the index in its write-ahead log.
+    pub(crate) fn wal_position(&self) -> u64 {
+        self.wal_position
+    }
+
+    /// Position in the write-ahead log of the last mutation included in the snapshot of the index
+    /// stored on the storage.
+    pub(crate) fn snapshot_wal_position(&self) -> u64 {
+        self.snapshot_wal_position
+    }
+
+    /// Records that the snapshot of the index stored on the storage includes all its mutations.
+    pub(crate) fn set_snapshot_up_to_date(&mut self) {
+        self.snapshot_wal_position = self.wal_position;
+    }
+
+    /// Appends the mutations of the index since `previous`, its state before the mutations, to
+    /// its write-ahead log and returns the corresponding record.
+    pub(crate) fn record_mutations(&mut self, previous: &FileBackedIndex) -> WalRecord {
+        self.wal_position = previous.wal_position + 1;
+        let upserted_splits = self
+            .splits
+            .values()
+            .filter(|split| previous.splits.get(split.split_id()) != Some(*split))
+            .cloned()
+            .collect();
+        let deleted_split_ids = previous
+            .splits
+            .keys()
+            .filter(|split_id| !self.splits.contains_key(*split_id))
+            .cloned()
+            .collect();
+        let new_delete_tasks = self.list_delete_tasks(previous.last_delete_opstamp());
+        WalRecord {
+            position: self.wal_position,
+            metadata: self.metadata.clone(),
+            upserted_splits,
+            deleted_split_ids,
+            new_delete_tasks,
+        }
+    }
+
+    /// Replays a record of the write-ahead log of the index, which must directly follow the last
+    /// mutation of the index.
+    pub(crate) fn apply_wal_record(&mut self, wal_record: WalRecord) {
+        debug_assert_eq!(wal_record.position, self.wal_position + 1);
+        self.metadata = wal_record.metadata;
+        for split in wal_record.upserted_splits {
+            self.splits.insert(split.split_id().to_string(), split);
+        }
+        for split_id in &wal_record.deleted_split_ids {
+            self.splits.remove(split_id);
+        }
+        self.delete_tasks.extend(wal_record.new_delete_tasks);
+        self.wal_position = wal_record.position;
+    }
+
     pub(crate) fn stage_split(
         &mut self,
         split_metadata: SplitMetadata,
     ) -> crate::MetastoreResult<()> {
+        self.metadata.check_not_in_maintenance()?;
         // Check whether the split exists.
         // If the split exists, return an error to prevent the split from being registered.
         if self.splits.contains_key(split_metadata.split_id()) {
@@ -181,6 +338,7 @@ impl FileBackedIndex {
         split_ids: &[&str],
         deletable_states: &[SplitState],
     ) -> MetastoreResult<bool> {
+        self.metadata.check_not_in_maintenance()?;
         let mut is_modified = false;
         let mut split_not_found_ids = Vec::new();
         let mut non_deletable_split_ids = Vec::new();
@@ -281,6 +439,7 @@ impl FileBackedIndex {
         replaced_split_ids: &[&'a str],
         checkpoint_delta_opt: Option<IndexCheckpointDelta>,
     ) -> MetastoreResult<()> {
+        self.metadata.check_not_in_maintenance()?;
         if let Some(checkpoint_delta) = checkpoint_delta_opt {
             self.metadata.checkpoint.try_apply_delta(checkpoint_delta)?;
         }
@@ -295,28 +454,30 @@ impl FileBackedIndex {
         time_range_opt: Option<Range<i64>>,
         tags_filter: Option<TagFilterAst>,
     ) -> MetastoreResult<Vec<Split>> {
-        let time_range_filter = |split: &&Split| match (
-            time_range_opt.as_ref(),
-            split.split_metadata.time_range.as_ref(),
-        ) {
-            (Some(filter_time_range), Some(split_time_range)) => {
-                !is_disjoint(filter_time_range, split_time_range)
-            }
-            _ => true, // Return `true` if `time_range` is omitted or the split has no time range.
+        let query = ListSplitsQuery {
+            split_state_opt: Some(state),
+            time_range_opt,
+            tags_opt: tags_filter,
+            ..Default::default()
         };
+        let splits = self
+            .splits
+            .values()
+            .filter(|split| split_matches_query(split, &query))
+            .cloned()
+            .collect();
+        Ok(splits)
+    }
 
-        let tag_filter = |split: &&Split| {
-            tags_filter
-                .as_ref()
-                .map(|tags_filter_ast| tags_filter_ast.evaluate(&split.split_metadata.tags))
-                .unwrap_or(true)
-        };
+    pub(crate) fn list_splits_page(&self, query: &ListSplitsQuery) -> MetastoreResult<Vec<Split>> {
+        let limit = query.limit_opt.unwrap_or(usize::MAX);
         let splits = self
             .splits
             .values()
-            .filter(|&split| split.split_state == state)
-            .filter(time_range_filter)
-            .filter(tag_filter)
+            .filter(|split| split_matches_query(split, query))
+            .sorted_by(|left, right| left.split_id().cmp(right.split_id()))
+            .skip(query.offset)
+            .take(limit)
             .cloned()
             .collect();
         Ok(splits)
@@ -341,6 +502,7 @@ impl FileBackedIndex {
 
     /// Deletes multiple splits.
     pub(crate) fn delete_splits(&mut self, split_ids: &[&str]) -> MetastoreResult<()> {
+        self.metadata.check_not_in_maintenance()?;
         let mut split_not_found_ids = Vec::new();
         let mut split_not_deletable_ids = Vec::new();
 
@@ -381,6 +543,198 @@ impl FileBackedIndex {
 
     /// Resets the checkpoint of a source. Returns whether a mutation occurred.
     pub(crate) fn reset_source_checkpoint(&mut self, source_id: &str) -> MetastoreResult<bool> {
-        Ok(self.metadata.checkpoint.reset_source(source_id))
+        Ok(self.metadata.reset_source_checkpoint(source_id))
+    }
+
+    /// Adds a file to the quarantine of a source. Returns whether a mutation occurred.
+    pub(crate) fn quarantine_source_file(
+        &mut self,
+        source_id: &str,
+        quarantined_file: QuarantinedFile,
+    ) -> bool {
+        self.metadata
+            .quarantine_source_file(source_id, quarantined_file)
+    }
+
+    /// Replaces the doc mapping and the search settings of the index. Returns whether a mutation
+    /// occurred.
+    pub(crate) fn update_doc_mapping(
+        &mut self,
+        doc_mapping: DocMapping,
+        search_settings: SearchSettings,
+    ) -> bool {
+        self.metadata
+            .update_doc_mapping(doc_mapping, search_settings);
+        true
+    }
+
+    /// Creates a delete task with the next opstamp of the index.
+    pub(crate) fn create_delete_task(
+        &mut self,
+        delete_query: DeleteQuery,
+    ) -> MetastoreResult<DeleteTask> {
+        self.metadata.check_not_in_maintenance()?;
+        let delete_task = DeleteTask {
+            create_timestamp: OffsetDateTime::now_utc().unix_timestamp(),
+            opstamp: self.last_delete_opstamp() + 1,
+            delete_query,
+        };
+        self.delete_tasks.push(delete_task.clone());
+        Ok(delete_task)
+    }
+
+    /// Enables or disables the maintenance mode. Returns whether a mutation occurred.
+    pub(crate) fn set_maintenance_mode(&mut self, enabled: bool) -> bool {
+        if self.metadata.maintenance_mode == enabled {
+            return false;
+        }
+        self.metadata.maintenance_mode = enabled;
+        self.metadata.update_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+        true
+    }
+
+    /// Enables or disables a feature flag. Returns whether a mutation occurred.
+    pub(crate) fn set_feature_flag(&mut self, flag: &str, enabled: bool) -> bool {
+        self.metadata.set_feature_flag(flag, enabled)
+    }
+
+    /// Acquires or renews the merge planner lease for `owner`. Returns whether `owner` holds the
+    /// lease, in which case the lease was mutated.
+    pub(crate) fn acquire_merge_planner_lease(
+        &mut self,
+        owner: &str,
+        lease_duration_secs: u64,
+    ) -> bool {
+        let now_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+        let expire_timestamp = now_timestamp + lease_duration_secs as i64;
+        self.metadata
+            .acquire_merge_planner_lease(owner, now_timestamp, expire_timestamp)
+    }
+
+    pub(crate) fn last_delete_opstamp(&self) -> u64 {
+        self.delete_tasks
+            .last()
+            .map(|delete_task| delete_task.opstamp)
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn list_delete_tasks(&self, opstamp_start: u64) -> Vec<DeleteTask> {
+        self.delete_tasks
+            .iter()
+            .filter(|delete_task| delete_task.opstamp > opstamp_start)
+            .cloned()
+            .collect()
+    }
+
+    pub(crate) fn list_splits_for_delete_task(
+        &self,
+        delete_opstamp_threshold: u64,
+        producer_opt: Option<&SplitProducer>,
+        limit: usize,
+    ) -> Vec<Split> {
+        let mut splits: Vec<Split> = self
+            .splits
+            .values()
+            .filter(|split| {
+                split.split_state == SplitState::Published
+                    && split.split_metadata.delete_opstamp < delete_opstamp_threshold
+                    && producer_opt
+                        .map_or(true, |producer| producer.produced(&split.split_metadata))
+            })
+            .filter(|split| {
+                self.delete_tasks
+                    .iter()
+                    .filter(|delete_task| {
+                        delete_task.opstamp > split.split_metadata.delete_opstamp
+                            && delete_task.opstamp <= delete_opstamp_threshold
+                    })
+                    .any(|delete_task| {
+                        delete_task
+                            .delete_query
+                            .overlaps_time_range(&split.split_metadata.time_range)
+                    })
+            })
+            .cloned()
+            .collect();
+        splits.sort_by(|left, right| {
+            left.split_metadata
+                .delete_opstamp
+                .cmp(&right.split_metadata.delete_opstamp)
+                .then_with(|| left.update_timestamp.cmp(&right.update_timestamp))
+                .then_with(|| left.split_id().cmp(right.split_id()))
+        });
+        splits.truncate(limit);
+        splits
+    }
+
+    /// Updates the delete opstamp of the splits. Returns whether a mutation occurred.
+    pub(crate) fn update_splits_delete_opstamp(
+        &mut self,
+        split_ids: &[&str],
+        delete_opstamp: u64,
+    ) -> MetastoreResult<bool> {
+        self.metadata.check_not_in_maintenance()?;
+        let mut split_not_found_ids = Vec::new();
+        let mut is_modified = false;
+        for &split_id in split_ids {
+            match self.splits.get_mut(split_id) {
+                Some(split) => {
+                    if split.split_metadata.delete_opstamp != delete_opstamp {
+                        split.split_metadata.delete_opstamp = delete_opstamp;
+                        is_modified = true;
+                    }
+                }
+                None => split_not_found_ids.push(split_id.to_string()),
+            }
+        }
+        if !split_not_found_ids.is_empty() {
+            return Err(MetastoreError::SplitsDoNotExist {
+                split_ids: split_not_found_ids,
+            });
+        }
+        if is_modified {
+            self.metadata.update_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+        }
+        Ok(is_modified)
+    }
+
+    /// Amends the tags of published splits. Returns whether a mutation occurred.
+    pub(crate) fn amend_splits_tags(
+        &mut self,
+        splits_tags: BTreeMap<String, BTreeSet<String>>,
+        reason: &str,
+    ) -> MetastoreResult<bool> {
+        self.metadata.check_not_in_maintenance()?;
+        let mut split_not_found_ids = Vec::new();
+        let mut split_not_published_ids = Vec::new();
+        for split_id in splits_tags.keys() {
+            match self.splits.get(split_id) {
+                Some(split) if split.split_state == SplitState::Published => {}
+                Some(_) => split_not_published_ids.push(split_id.clone()),
+                None => split_not_found_ids.push(split_id.clone()),
+            }
+        }
+        if !split_not_found_ids.is_empty() {
+            return Err(MetastoreError::SplitsDoNotExist {
+                split_ids: split_not_found_ids,
+            });
+        }
+        if !split_not_published_ids.is_empty() {
+            return Err(MetastoreError::SplitsNotPublished {
+                split_ids: split_not_published_ids,
+            });
+        }
+        let mut is_modified = false;
+        for (split_id, tags) in splits_tags {
+            let split = self
+                .splits
+                .get_mut(&split_id)
+                .expect("The split should exist.");
+            is_modified |= split.split_metadata.amend_tags(tags, reason);
+        }
+        if is_modified {
+            self.metadata.update_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+        }
+        Ok(is_modified)
     }
 }
@@ -24,9 +24,9 @@
 pub mod file_backed_index;
 mod file_backed_metastore_factory;
 mod lazy_file_backed_index;
-mod store_operations;
+pub(crate) mod store_operations;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::ops::Range;
 use std::sync::Arc;
 use std::time::Duration;
@@ -34,23 +34,29 @@ use std::time::Duration;
 use async_trait::async_trait;
 use futures::future::try_join_all;
 use quickwit_common::uri::Uri;
-use quickwit_config::SourceConfig;
+use quickwit_config::{is_index_in_namespace, DocMapping, SearchSettings, SourceConfig};
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
 use quickwit_storage::Storage;
 use tokio::sync::{Mutex, OwnedMutexGuard, RwLock};
+use tracing::warn;
 
 use self::file_backed_index::FileBackedIndex;
 pub use self::file_backed_metastore_factory::FileBackedMetastoreFactory;
 use self::lazy_file_backed_index::LazyFileBackedIndex;
 use self::store_operations::{
-    delete_index, fetch_and_build_indexes_states, fetch_index, index_exists, put_index,
-    put_indexes_states,
+    delete_index, delete_wal_records, fetch_and_build_indexes_states, fetch_index, index_exists,
+    put_index, put_indexes_states, put_wal_record,
 };
 use crate::checkpoint::IndexCheckpointDelta;
 use crate::{
-    IndexMetadata, Metastore, MetastoreError, MetastoreResult, Split, SplitMetadata, SplitState,
+    DeleteQuery, DeleteTask, IndexMetadata, ListSplitsQuery, Metastore, MetastoreError,
+    MetastoreResult, QuarantinedFile, Split, SplitMetadata, SplitProducer, SplitState,
 };
 
+/// Number of records appended to the write-ahead log of an index after which the index metadata
+/// file is rewritten and the records are deleted.
+const WAL_CHECKPOINT_INTERVAL: u64 = 100;
+
 /// State of an index tracked by the metastore.
 pub(crate) enum IndexState {
     /// Index is being created but its metadata has not been created on the storage yet.
@@ -88,6 +94,13 @@ pub(crate) enum IndexState {
 /// - on deletion, same story, the metastore deletes an index metadata file present on the storage
 ///   even if the index is not in the map.
 ///
+/// The mutations of an index are not written to its metadata file `{index_id}/metastore.json`
+/// right away but appended to its write-ahead log, as one record per mutation stored in the
+/// `{index_id}/wal` directory, which is much cheaper for indexes with many splits. Every
+/// `WAL_CHECKPOINT_INTERVAL` records, the metadata file is rewritten and the records it includes
+/// are deleted. When fetching an index, the records following the last mutation included in its
+/// metadata file are replayed.
+///
 /// !!! Important note 2: it is strongly advised to restrict the `FileBackedMetastore`
 /// usage to the following use cases:
 /// - testing;
@@ -151,9 +164,13 @@ impl FileBackedMetastore {
             return Ok(());
         }
 
-        let put_result = put_index(&*self.storage, &index).await;
+        let wal_record = index.record_mutations(&locked_index);
+        let put_result = put_wal_record(&*self.storage, index_id, &wal_record).await;
         match put_result {
             Ok(()) => {
+                if index.wal_position() - index.snapshot_wal_position() >= WAL_CHECKPOINT_INTERVAL {
+                    self.checkpoint(&mut index).await;
+                }
                 *locked_index = index;
                 Ok(())
             }
@@ -181,6 +198,27 @@ impl FileBackedMetastore {
         }
     }
 
+    /// Rewrites the metadata file of the index so that it includes all its mutations, then deletes
+    /// the records of its write-ahead log. The mutations are already durable: a failure is not
+    /// fatal and the checkpoint is attempted again on the next mutation.
+    async fn checkpoint(&self, index: &mut FileBackedIndex) {
+        if let Err(error) = put_index(&*self.storage, index).await {
+            warn!(index_id=%index.index_id(), error=?error, "Failed to checkpoint index.");
+            return;
+        }
+        let wal_positions = index.snapshot_wal_position() + 1..=index.wal_position();
+        index.set_snapshot_up_to_date();
+        if let Err(error) =
+            delete_wal_records(&*self.storage, index.index_id(), wal_positions).await
+        {
+            warn!(
+                index_id=%index.index_id(),
+                error=?error,
+                "Failed to delete write-ahead log records."
+            );
+        }
+    }
+
     async fn read<T, F>(&self, index_id: &str, view: F) -> MetastoreResult<T>
     where F: FnOnce(&FileBackedIndex) -> MetastoreResult<T> {
         let locked_index = self.get_locked_index(index_id).await?;
@@ -452,6 +490,99 @@ impl Metastore for FileBackedMetastore {
             .await
     }
 
+    async fn create_delete_task(&self, delete_query: DeleteQuery) -> MetastoreResult<DeleteTask> {
+        let index_id = delete_query.index_id.clone();
+        let mut delete_task_opt = None;
+        self.mutate(&index_id, |index| {
+            delete_task_opt = Some(index.create_delete_task(delete_query)?);
+            Ok(true)
+        })
+        .await?;
+        Ok(delete_task_opt.expect("The delete task should have been created."))
+    }
+
+    async fn update_splits_delete_opstamp<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+        delete_opstamp: u64,
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| {
+            index.update_splits_delete_opstamp(split_ids, delete_opstamp)
+        })
+        .await
+    }
+
+    async fn amend_splits_tags(
+        &self,
+        index_id: &str,
+        splits_tags: BTreeMap<String, BTreeSet<String>>,
+        reason: &str,
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| {
+            index.amend_splits_tags(splits_tags, reason)
+        })
+        .await
+    }
+
+    async fn set_index_maintenance_mode(
+        &self,
+        index_id: &str,
+        enabled: bool,
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| Ok(index.set_maintenance_mode(enabled)))
+            .await
+    }
+
+    async fn set_index_feature_flag(
+        &self,
+        index_id: &str,
+        flag: &str,
+        enabled: bool,
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| Ok(index.set_feature_flag(flag, enabled)))
+            .await
+    }
+
+    async fn acquire_merge_planner_lease(
+        &self,
+        index_id: &str,
+        owner: &str,
+        lease_duration_secs: u64,
+    ) -> MetastoreResult<bool> {
+        let mut is_lease_holder = false;
+        self.mutate(index_id, |index| {
+            is_lease_holder = index.acquire_merge_planner_lease(owner, lease_duration_secs);
+            Ok(is_lease_holder)
+        })
+        .await?;
+        Ok(is_lease_holder)
+    }
+
+    async fn quarantine_source_file(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        quarantined_file: QuarantinedFile,
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| {
+            Ok(index.quarantine_source_file(source_id, quarantined_file))
+        })
+        .await
+    }
+
+    async fn update_index(
+        &self,
+        index_id: &str,
+        doc_mapping: DocMapping,
+        search_settings: SearchSettings,
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| {
+            Ok(index.update_doc_mapping(doc_mapping, search_settings))
+        })
+        .await
+    }
+
     /// -------------------------------------------------------------------------------
     /// Read-only accessors
 
@@ -468,10 +599,50 @@ impl Metastore for FileBackedMetastore {
         .await
     }
 
+    async fn list_splits_page(
+        &self,
+        index_id: &str,
+        query: ListSplitsQuery,
+    ) -> MetastoreResult<Vec<Split>> {
+        self.read(index_id, |index| index.list_splits_page(&query))
+            .await
+    }
+
     async fn list_all_splits(&self, index_id: &str) -> MetastoreResult<Vec<Split>> {
         self.read(index_id, |index| index.list_all_splits()).await
     }
 
+    async fn last_delete_opstamp(&self, index_id: &str) -> MetastoreResult<u64> {
+        self.read(index_id, |index| Ok(index.last_delete_opstamp()))
+            .await
+    }
+
+    async fn list_delete_tasks(
+        &self,
+        index_id: &str,
+        opstamp_start: u64,
+    ) -> MetastoreResult<Vec<DeleteTask>> {
+        self.read(index_id, |index| Ok(index.list_delete_tasks(opstamp_start)))
+            .await
+    }
+
+    async fn list_splits_for_delete_task(
+        &self,
+        index_id: &str,
+        delete_opstamp_threshold: u64,
+        producer_opt: Option<SplitProducer>,
+        limit: usize,
+    ) -> MetastoreResult<Vec<Split>> {
+        self.read(index_id, |index| {
+            Ok(index.list_splits_for_delete_task(
+                delete_opstamp_threshold,
+                producer_opt.as_ref(),
+                limit,
+            ))
+        })
+        .await
+    }
+
     async fn index_metadata(&self, index_id: &str) -> MetastoreResult<IndexMetadata> {
         self.read(index_id, |index| Ok(index.metadata().clone()))
             .await
@@ -491,6 +662,25 @@ impl Metastore for FileBackedMetastore {
         .await
     }
 
+    async fn list_indexes_metadatas_in_namespace(
+        &self,
+        namespace: &str,
+    ) -> MetastoreResult<Vec<IndexMetadata>> {
+        let per_index_metastores_rlock = self.per_index_metastores.read().await;
+        try_join_all(
+            per_index_metastores_rlock
+                .iter()
+                .filter_map(|(index_id, index_state)| match index_state {
+                    IndexState::Alive(_) if is_index_in_namespace(index_id, namespace) => {
+                        Some(index_id)
+                    }
+                    _ => None,
+                })
+                .map(|index_id| self.index_metadata(index_id)),
+        )
+        .await
+    }
+
     fn uri(&self) -> &Uri {
         self.storage.uri()
     }
@@ -551,8 +741,9 @@ mod tests {
     use super::lazy_file_backed_index::LazyFileBackedIndex;
     use super::store_operations::{
         fetch_and_build_indexes_states, meta_path, put_index_given_index_id, put_indexes_states,
+        wal_record_path,
     };
-    use super::{FileBackedIndex, FileBackedMetastore, IndexState};
+    use super::{FileBackedIndex, FileBackedMetastore, IndexState, WAL_CHECKPOINT_INTERVAL};
     use crate::tests::test_suite::DefaultForTest;
     use crate::{IndexMetadata, Metastore, MetastoreError, SplitMetadata, SplitState};
 
@@ -618,13 +809,17 @@ mod tests {
             .times(4)
             .returning(move |path, put_payload| {
                 assert!(
-                    path == Path::new("indexes_states.json") || path == meta_path("test-index")
+                    path == Path::new("indexes_states.json")
+                        || path == meta_path("test-index")
+                        || path == wal_record_path("test-index", 1)
                 );
                 block_on(ram_storage_clone.put(path, put_payload))
             });
+        // Once the index is discarded, its metadata file is fetched and its write-ahead log
+        // replayed.
         mock_storage
             .expect_get_all()
-            .times(1)
+            .times(3)
             .returning(move |path| block_on(ram_storage.get_all(path)));
         mock_storage.expect_put().times(1).returning(|_uri, _| {
             Err(StorageErrorKind::Io
@@ -1013,6 +1208,9 @@ mod tests {
                 Err(StorageErrorKind::Io
                     .with_error(anyhow::anyhow!("Oops. Some network problem maybe?")))
             });
+        mock_storage
+            .expect_get_all()
+            .returning(move |path| block_on(ram_storage.get_all(path)));
         mock_storage
             .expect_put()
             .times(1)
@@ -1055,6 +1253,9 @@ mod tests {
         mock_storage // remove this if we end up changing the semantics of create.
             .expect_delete()
             .returning(|_| Ok(()));
+        mock_storage
+            .expect_get_all()
+            .returning(move |path| block_on(ram_storage.get_all(path)));
         mock_storage
             .expect_put()
             .times(2)
@@ -1153,4 +1354,186 @@ mod tests {
 
         Ok(())
     }
+
+    async fn fetch_index_snapshot(storage: &dyn Storage, index_id: &str) -> FileBackedIndex {
+        let content = storage.get_all(&meta_path(index_id)).await.unwrap();
+        serde_json::from_slice(&content[..]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_file_backed_metastore_replays_wal() -> crate::MetastoreResult<()> {
+        let storage = Arc::new(RamStorage::default());
+        let metastore = FileBackedMetastore::try_new(storage.clone(), None).await?;
+        let index_id = "test-index";
+        let index_metadata = IndexMetadata::for_test(index_id, "ram:///indexes/test-index");
+        metastore.create_index(index_metadata).await?;
+
+        for split_id in ["split-1", "split-2", "split-3"] {
+            let split_metadata = SplitMetadata {
+                split_id: split_id.to_string(),
+                ..Default::default()
+            };
+            metastore.stage_split(index_id, split_metadata).await?;
+        }
+        metastore
+            .publish_splits(index_id, &["split-1", "split-2"], &[], None)
+            .await?;
+        metastore
+            .mark_splits_for_deletion(index_id, &["split-3"])
+            .await?;
+        metastore.delete_splits(index_id, &["split-3"]).await?;
+
+        // The metadata file of the index does not include the mutations yet.
+        let index_snapshot = fetch_index_snapshot(&*storage, index_id).await;
+        assert!(index_snapshot.splits().is_empty());
+        assert_eq!(index_snapshot.wal_position(), 0);
+
+        // A corrupted record left over by a crash is ignored.
+        storage
+            .put(
+                &wal_record_path(index_id, 7),
+                Box::new(b"{\"position".to_vec()),
+            )
+            .await
+            .unwrap();
+
+        let metastore = FileBackedMetastore::try_new(storage.clone(), None).await?;
+        let index = metastore.get_index(index_id).await?;
+        assert_eq!(index.wal_position(), 6);
+        let mut split_ids: Vec<&str> = index.splits().keys().map(String::as_str).collect();
+        split_ids.sort_unstable();
+        assert_eq!(split_ids, ["split-1", "split-2"]);
+        assert!(index
+            .splits()
+            .values()
+            .all(|split| split.split_state == SplitState::Published));
+
+        // The next mutation overwrites the corrupted record.
+        metastore
+            .mark_splits_for_deletion(index_id, &["split-1"])
+            .await?;
+        let metastore = FileBackedMetastore::try_new(storage.clone(), None).await?;
+        let index = metastore.get_index(index_id).await?;
+        assert_eq!(index.wal_position(), 7);
+        assert_eq!(
+            index.splits()["split-1"].split_state,
+            SplitState::MarkedForDeletion
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_file_backed_metastore_nested_namespaces() -> crate::MetastoreResult<()> {
+        assert_eq!(
+            meta_path("team-a/logs"),
+            Path::new("team-a%2Flogs/metastore.json")
+        );
+        let storage = Arc::new(RamStorage::default());
+        let metastore = FileBackedMetastore::try_new(storage.clone(), None).await?;
+        // The directory of `team-a/logs/wal` would otherwise be the write-ahead log directory of
+        // `team-a/logs`.
+        for index_id in ["team-a/logs", "team-a/logs/wal"] {
+            let index_uri = format!("ram:///indexes/{index_id}");
+            let index_metadata = IndexMetadata::for_test(index_id, &index_uri);
+            metastore.create_index(index_metadata).await?;
+
+            let split_metadata = SplitMetadata {
+                split_id: format!("{index_id}-split"),
+                ..Default::default()
+            };
+            metastore.stage_split(index_id, split_metadata).await?;
+        }
+        let metastore = FileBackedMetastore::try_new(storage.clone(), None).await?;
+        for index_id in ["team-a/logs", "team-a/logs/wal"] {
+            let index = metastore.get_index(index_id).await?;
+            assert_eq!(index.index_id(), index_id);
+            let split_ids: Vec<&str> = index.splits().keys().map(String::as_str).collect();
+            assert_eq!(split_ids, [format!("{index_id}-split")]);
+        }
+        metastore.delete_index("team-a/logs").await?;
+
+        let metastore = FileBackedMetastore::try_new(storage.clone(), None).await?;
+        let index = metastore.get_index("team-a/logs/wal").await?;
+        assert_eq!(index.wal_position(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_file_backed_metastore_rejects_corrupted_wal_record() -> crate::MetastoreResult<()>
+    {
+        let storage = Arc::new(RamStorage::default());
+        let metastore = FileBackedMetastore::try_new(storage.clone(), None).await?;
+        let index_id = "test-index";
+        let index_metadata = IndexMetadata::for_test(index_id, "ram:///indexes/test-index");
+        metastore.create_index(index_metadata).await?;
+
+        for split_id in ["split-1", "split-2", "split-3"] {
+            let split_metadata = SplitMetadata {
+                split_id: split_id.to_string(),
+                ..Default::default()
+            };
+            metastore.stage_split(index_id, split_metadata).await?;
+        }
+        // Unlike a truncated record at the tail of the log, a corrupted record followed by other
+        // records cannot be skipped without losing the mutations that followed it.
+        storage
+            .put(
+                &wal_record_path(index_id, 2),
+                Box::new(b"{\"position".to_vec()),
+            )
+            .await
+            .unwrap();
+
+        let metastore = FileBackedMetastore::try_new(storage.clone(), None).await?;
+        let error = metastore.get_index(index_id).await.unwrap_err();
+        assert!(matches!(error, MetastoreError::InvalidManifest { .. }));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_file_backed_metastore_checkpoints_wal() -> crate::MetastoreResult<()> {
+        let storage = Arc::new(RamStorage::default());
+        let metastore = FileBackedMetastore::try_new(storage.clone(), None).await?;
+        let index_id = "test-index";
+        let index_metadata = IndexMetadata::for_test(index_id, "ram:///indexes/test-index");
+        metastore.create_index(index_metadata).await?;
+
+        for i in 0..WAL_CHECKPOINT_INTERVAL + 1 {
+            let split_metadata = SplitMetadata {
+                split_id: format!("split-{i}"),
+                ..Default::default()
+            };
+            metastore.stage_split(index_id, split_metadata).await?;
+        }
+        let index_snapshot = fetch_index_snapshot(&*storage, index_id).await;
+        assert_eq!(index_snapshot.wal_position(), WAL_CHECKPOINT_INTERVAL);
+        assert_eq!(
+            index_snapshot.splits().len(),
+            WAL_CHECKPOINT_INTERVAL as usize
+        );
+        // The records included in the metadata file are deleted.
+        for position in 1..=WAL_CHECKPOINT_INTERVAL {
+            assert!(!storage
+                .exists(&wal_record_path(index_id, position))
+                .await
+                .unwrap());
+        }
+        assert!(storage
+            .exists(&wal_record_path(index_id, WAL_CHECKPOINT_INTERVAL + 1))
+            .await
+            .unwrap());
+
+        let metastore = FileBackedMetastore::try_new(storage.clone(), None).await?;
+        let index = metastore.get_index(index_id).await?;
+        assert_eq!(index.wal_position(), WAL_CHECKPOINT_INTERVAL + 1);
+        assert_eq!(index.splits().len(), WAL_CHECKPOINT_INTERVAL as usize + 1);
+
+        // Deleting the index deletes its write-ahead log.
+        metastore.delete_index(index_id).await?;
+        assert!(!storage
+            .exists(&wal_record_path(index_id, WAL_CHECKPOINT_INTERVAL + 1))
+            .await
+            .unwrap());
+        Ok(())
+    }
 }
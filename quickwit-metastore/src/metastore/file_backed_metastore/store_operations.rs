@@ -18,15 +18,17 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::HashMap;
+use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 
 use quickwit_storage::{Storage, StorageError, StorageErrorKind};
 use serde::{Deserialize, Serialize};
+use tracing::warn;
 
 use super::{IndexState, LazyFileBackedIndex};
-use crate::metastore::file_backed_metastore::file_backed_index::FileBackedIndex;
+use crate::metastore::file_backed_metastore::file_backed_index::{FileBackedIndex, WalRecord};
 use crate::{MetastoreError, MetastoreResult};
 
 /// Indexes states file managed by [`FileBackedMetastore`](crate::FileBackedMetastore).
@@ -35,6 +37,9 @@ const INDEXES_STATES_FILENAME: &str = "indexes_states.json";
 /// Index metadata file managed by [`FileBackedMetastore`](crate::FileBackedMetastore).
 const META_FILENAME: &str = "metastore.json";
 
+/// Directory of the write-ahead log records of an index, relative to the index directory.
+const WAL_DIRNAME: &str = "wal";
+
 /// Index state used for serialization/deserialization only.
 #[derive(Serialize, Deserialize)]
 enum IndexStateValue {
@@ -53,9 +58,24 @@ impl From<&IndexState> for IndexStateValue {
     }
 }
 
+/// Directory of the files of the given index ID.
+///
+/// The namespace separators of the index ID are escaped, so that the directory of an index is
+/// never nested in the directory of another one, e.g. `team-a/logs` and `team-a/logs/wal`.
+fn index_directory(index_id: &str) -> PathBuf {
+    PathBuf::from(index_id.replace('/', "%2F"))
+}
+
 /// Path to the metadata file from the given index ID.
 pub(crate) fn meta_path(index_id: &str) -> PathBuf {
-    Path::new(index_id).join(META_FILENAME)
+    index_directory(index_id).join(META_FILENAME)
+}
+
+/// Path to the write-ahead log record of the given index ID at the given position.
+pub(crate) fn wal_record_path(index_id: &str, position: u64) -> PathBuf {
+    index_directory(index_id)
+        .join(WAL_DIRNAME)
+        .join(format!("{position:020}.json"))
 }
 
 fn convert_error(index_id: &str, storage_err: StorageError) -> MetastoreError {
@@ -73,12 +93,11 @@ fn convert_error(index_id: &str, storage_err: StorageError) -> MetastoreError {
     }
 }
 
-/// Fetch `INDEXES_STATES_FILENAME` file and build the map (index, state).
+/// Fetch `INDEXES_STATES_FILENAME` file and deserialize the map (index, state).
 /// If the file does not exist, return an empty map.
-pub(crate) async fn fetch_and_build_indexes_states(
-    storage: Arc<dyn Storage>,
-    polling_interval_opt: Option<Duration>,
-) -> MetastoreResult<HashMap<String, IndexState>> {
+async fn fetch_indexes_states_values(
+    storage: &dyn Storage,
+) -> MetastoreResult<HashMap<String, IndexStateValue>> {
     let indexes_list_path = Path::new(INDEXES_STATES_FILENAME);
     let exists = storage
         .exists(indexes_list_path)
@@ -100,7 +119,17 @@ pub(crate) async fn fetch_and_build_indexes_states(
                 message: serde_err.to_string(),
             }
         })?;
-    Ok(indexes_states_deserialized
+    Ok(indexes_states_deserialized)
+}
+
+/// Fetch `INDEXES_STATES_FILENAME` file and build the map (index, state).
+/// If the file does not exist, return an empty map.
+pub(crate) async fn fetch_and_build_indexes_states(
+    storage: Arc<dyn Storage>,
+    polling_interval_opt: Option<Duration>,
+) -> MetastoreResult<HashMap<String, IndexState>> {
+    Ok(fetch_indexes_states_values(&*storage)
+        .await?
         .into_iter()
         .map(|(index_id, index_state)| match index_state {
             IndexStateValue::Creating => (index_id, IndexState::Creating),
@@ -118,6 +147,19 @@ pub(crate) async fn fetch_and_build_indexes_states(
         .collect())
 }
 
+/// Fetch the indexes in the `Alive` state of the `INDEXES_STATES_FILENAME` file.
+pub(crate) async fn fetch_alive_indexes(
+    storage: &dyn Storage,
+) -> MetastoreResult<Vec<FileBackedIndex>> {
+    let mut indexes = Vec::new();
+    for (index_id, index_state) in fetch_indexes_states_values(storage).await? {
+        if let IndexStateValue::Alive = index_state {
+            indexes.push(fetch_index(storage, &index_id).await?);
+        }
+    }
+    Ok(indexes)
+}
+
 pub(crate) async fn put_indexes_states(
     storage: &dyn Storage,
     indexes_states: &HashMap<String, IndexState>,
@@ -126,9 +168,27 @@ pub(crate) async fn put_indexes_states(
         .iter()
         .map(|(index_id, index_state)| (index_id.clone(), IndexStateValue::from(index_state)))
         .collect();
+    put_indexes_states_values(storage, &indexes_states_serializable).await
+}
+
+/// Stores an `INDEXES_STATES_FILENAME` file listing the given indexes in the `Alive` state.
+pub(crate) async fn put_alive_indexes_states<'a>(
+    storage: &dyn Storage,
+    index_ids: impl Iterator<Item = &'a String>,
+) -> MetastoreResult<()> {
+    let indexes_states_serializable: HashMap<String, IndexStateValue> = index_ids
+        .map(|index_id| (index_id.clone(), IndexStateValue::Alive))
+        .collect();
+    put_indexes_states_values(storage, &indexes_states_serializable).await
+}
+
+async fn put_indexes_states_values(
+    storage: &dyn Storage,
+    indexes_states_serializable: &HashMap<String, IndexStateValue>,
+) -> MetastoreResult<()> {
     let indexes_list_path = Path::new(INDEXES_STATES_FILENAME);
     let content: Vec<u8> =
-        serde_json::to_vec_pretty(&indexes_states_serializable).map_err(|serde_err| {
+        serde_json::to_vec_pretty(indexes_states_serializable).map_err(|serde_err| {
             MetastoreError::InternalError {
                 message: "Failed to serialize indexes map".to_string(),
                 cause: serde_err.to_string(),
@@ -170,9 +230,112 @@ pub(crate) async fn fetch_index(
             ),
         });
     }
+    let mut index = index;
+    replay_wal(storage, &mut index).await?;
     Ok(index)
 }
 
+/// Fetches the write-ahead log record of an index at the given position, or `None` if there is no
+/// valid record for the index at this position.
+async fn fetch_wal_record(
+    storage: &dyn Storage,
+    index: &FileBackedIndex,
+    position: u64,
+) -> MetastoreResult<Option<WalRecord>> {
+    let wal_record_path = wal_record_path(index.index_id(), position);
+    let content = match storage.get_all(&wal_record_path).await {
+        Ok(content) => content,
+        Err(storage_err) if storage_err.kind() == StorageErrorKind::DoesNotExist => {
+            return Ok(None);
+        }
+        Err(storage_err) => return Err(convert_error(index.index_id(), storage_err)),
+    };
+    let wal_record: WalRecord = match serde_json::from_slice(&content[..]) {
+        Ok(wal_record) => wal_record,
+        Err(serde_err) => {
+            // A truncated record can only be the last one of the log, written by a writer that
+            // crashed before the mutation succeeded. A record followed by others is corrupted.
+            let next_wal_record_path = wal_record_path(index.index_id(), position + 1);
+            let is_last_record = !storage
+                .exists(&next_wal_record_path)
+                .await
+                .map_err(|storage_err| convert_error(index.index_id(), storage_err))?;
+            if !is_last_record {
+                return Err(MetastoreError::InvalidManifest {
+                    message: format!(
+                        "Failed to deserialize write-ahead log record at position {position} of \
+                         index `{}`: {serde_err}",
+                        index.index_id()
+                    ),
+                });
+            }
+            warn!(
+                index_id=%index.index_id(),
+                position=position,
+                error=?serde_err,
+                "Ignoring truncated write-ahead log record at the tail of the log."
+            );
+            return Ok(None);
+        }
+    };
+    // The records of a deleted index with the same index ID may have been left over.
+    if wal_record.position != position
+        || wal_record.metadata.index_id != index.index_id()
+        || wal_record.metadata.create_timestamp != index.metadata().create_timestamp
+    {
+        warn!(
+            index_id=%index.index_id(),
+            position=position,
+            "Ignoring write-ahead log record of another index."
+        );
+        return Ok(None);
+    }
+    Ok(Some(wal_record))
+}
+
+/// Replays the records of the write-ahead log of an index following the last mutation included in
+/// its snapshot.
+async fn replay_wal(storage: &dyn Storage, index: &mut FileBackedIndex) -> MetastoreResult<()> {
+    while let Some(wal_record) = fetch_wal_record(storage, index, index.wal_position() + 1).await? {
+        index.apply_wal_record(wal_record);
+    }
+    Ok(())
+}
+
+/// Stores a record of the write-ahead log of an index.
+pub(crate) async fn put_wal_record(
+    storage: &dyn Storage,
+    index_id: &str,
+    wal_record: &WalRecord,
+) -> MetastoreResult<()> {
+    let content: Vec<u8> =
+        serde_json::to_vec(wal_record).map_err(|serde_err| MetastoreError::InternalError {
+            message: "Failed to serialize write-ahead log record".to_string(),
+            cause: serde_err.to_string(),
+        })?;
+    let wal_record_path = wal_record_path(index_id, wal_record.position);
+    storage
+        .put(&wal_record_path, Box::new(content))
+        .await
+        .map_err(|storage_err| convert_error(index_id, storage_err))?;
+    Ok(())
+}
+
+/// Deletes the records of the write-ahead log of an index in the given range of positions.
+pub(crate) async fn delete_wal_records(
+    storage: &dyn Storage,
+    index_id: &str,
+    positions: RangeInclusive<u64>,
+) -> MetastoreResult<()> {
+    for position in positions {
+        storage
+            .delete(&wal_record_path(index_id, position))
+            .await
+            .map_err(|storage_err| convert_error(index_id, storage_err))?;
+    }
+    Ok(())
+}
+
 pub(crate) async fn index_exists(storage: &dyn Storage, index_id: &str) -> MetastoreResult<bool> {
     let metadata_path = meta_path(index_id);
     let exists = storage
@@ -230,6 +393,27 @@ pub(crate) async fn delete_index(storage: &dyn Storage, index_id: &str) -> Metas
         });
     }
 
+    // The records of the write-ahead log are deleted first, as they cannot be found without the
+    // index metadata file. The records left over are ignored by an index created later on with
+    // the same index ID.
+    match fetch_index(storage, index_id).await {
+        Ok(index) => {
+            delete_wal_records(
+                storage,
+                index_id,
+                index.snapshot_wal_position() + 1..=index.wal_position(),
+            )
+            .await?
+        }
+        Err(error) => {
+            warn!(
+                index_id=%index_id,
+                error=?error,
+                "Failed to fetch the index to delete its write-ahead log records."
+            );
+        }
+    }
+
     // Put data back into storage.
     storage
         .delete(&metadata_path)
@@ -17,25 +17,81 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::{BTreeMap, BTreeSet};
+use std::pin::Pin;
 use std::str::FromStr;
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use futures::{stream, Stream, StreamExt};
 use itertools::Itertools;
 use quickwit_common::extract_time_range;
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
 use quickwit_proto::metastore_api::metastore_api_service_server::{self as grpc};
 use quickwit_proto::metastore_api::{
-    AddSourceRequest, CreateIndexRequest, CreateIndexResponse, DeleteIndexRequest,
-    DeleteIndexResponse, DeleteSourceRequest, DeleteSplitsRequest, IndexMetadataRequest,
-    IndexMetadataResponse, ListAllSplitsRequest, ListIndexesMetadatasRequest,
-    ListIndexesMetadatasResponse, ListSplitsRequest, ListSplitsResponse,
-    MarkSplitsForDeletionRequest, PublishSplitsRequest, ResetSourceCheckpointRequest,
-    SourceResponse, SplitResponse, StageSplitRequest,
+    AcquireMergePlannerLeaseRequest, AcquireMergePlannerLeaseResponse, AddSourceRequest,
+    AmendSplitsTagsRequest, CreateDeleteTaskRequest, CreateIndexRequest, CreateIndexResponse,
+    DeleteIndexRequest, DeleteIndexResponse, DeleteSourceRequest, DeleteSplitsRequest,
+    DeleteTaskResponse, IndexMetadataRequest, IndexMetadataResponse, LastDeleteOpstampRequest,
+    LastDeleteOpstampResponse, ListAllSplitsRequest, ListDeleteTasksRequest,
+    ListDeleteTasksResponse, ListIndexesMetadatasInNamespaceRequest, ListIndexesMetadatasRequest,
+    ListIndexesMetadatasResponse, ListSplitsForDeleteTaskRequest, ListSplitsPageRequest,
+    ListSplitsRequest, ListSplitsResponse, MarkSplitsForDeletionRequest, PublishSplitsRequest,
+    QuarantineSourceFileRequest, ResetSourceCheckpointRequest, SetIndexFeatureFlagRequest,
+    SetIndexFeatureFlagResponse, SetIndexMaintenanceModeRequest, SetIndexMaintenanceModeResponse,
+    SourceResponse, SplitResponse, StageSplitRequest, UpdateIndexRequest, UpdateIndexResponse,
+    UpdateSplitsDeleteOpstampRequest,
 };
 use quickwit_proto::tonic;
 
-use crate::{IndexMetadata, Metastore, MetastoreError, SplitState};
+use super::payload_codec::{serialize_payload, SerializedPayload};
+use crate::{
+    DeleteQuery, IndexMetadata, ListSplitsQuery, Metastore, MetastoreError, Split, SplitProducer,
+    SplitState,
+};
+
+/// Number of splits sent per message by the `list_splits` and `list_all_splits` RPCs.
+const LIST_SPLITS_BATCH_SIZE: usize = if cfg!(test) { 2 } else { 1_000 };
+
+type SplitBatchStream =
+    Pin<Box<dyn Stream<Item = Result<ListSplitsResponse, tonic::Status>> + Send>>;
+
+/// Streams the splits in batches of [`LIST_SPLITS_BATCH_SIZE`] splits, so that neither the server
+/// nor the client has to hold all the splits serialized at once.
+fn stream_split_batches(
+    splits: Vec<Split>,
+    binary_payload_version_opt: Option<u32>,
+) -> SplitBatchStream {
+    let mut splits_iter = splits.into_iter();
+    let split_batches = std::iter::from_fn(move || {
+        let split_batch: Vec<Split> = splits_iter.by_ref().take(LIST_SPLITS_BATCH_SIZE).collect();
+        if split_batch.is_empty() {
+            None
+        } else {
+            Some(split_batch)
+        }
+    });
+    let split_batch_stream = stream::iter(split_batches).map(move |split_batch| {
+        serialize_payload(&split_batch, "Vec<Split>", binary_payload_version_opt)
+            .map(list_splits_response)
+            .map_err(tonic::Status::from)
+    });
+    Box::pin(split_batch_stream)
+}
+
+fn list_splits_response(payload: SerializedPayload) -> ListSplitsResponse {
+    ListSplitsResponse {
+        splits_serialized_json: payload.json,
+        splits_serialized_binary: payload.binary,
+    }
+}
+
+fn list_indexes_metadatas_response(payload: SerializedPayload) -> ListIndexesMetadatasResponse {
+    ListIndexesMetadatasResponse {
+        indexes_metadatas_serialized_json: payload.json,
+        indexes_metadatas_serialized_binary: payload.binary,
+    }
+}
 
 #[allow(missing_docs)]
 #[derive(Clone)]
@@ -78,32 +134,48 @@ impl grpc::MetastoreApiService for GrpcMetastoreAdapter {
             .0
             .index_metadata(&index_metadata_request.index_id)
             .await?;
-        let index_metadata_reply = serde_json::to_string(&index_metadata)
-            .map(|index_metadata_serialized_json| IndexMetadataResponse {
-                index_metadata_serialized_json,
-            })
-            .map_err(|error| MetastoreError::JsonSerializeError {
-                name: "IndexMetadata".to_string(),
-                message: error.to_string(),
-            })?;
+        let payload = serialize_payload(
+            &index_metadata,
+            "IndexMetadata",
+            index_metadata_request.binary_payload_version,
+        )?;
+        let index_metadata_reply = IndexMetadataResponse {
+            index_metadata_serialized_json: payload.json,
+            index_metadata_serialized_binary: payload.binary,
+        };
         Ok(tonic::Response::new(index_metadata_reply))
     }
 
     async fn list_indexes_metadatas(
         &self,
-        _: tonic::Request<ListIndexesMetadatasRequest>,
+        request: tonic::Request<ListIndexesMetadatasRequest>,
     ) -> Result<tonic::Response<ListIndexesMetadatasResponse>, tonic::Status> {
+        let binary_payload_version_opt = request.into_inner().binary_payload_version;
         let indexes_metadatas = self.0.list_indexes_metadatas().await?;
-        let list_indexes_metadatas_reply = serde_json::to_string(&indexes_metadatas)
-            .map(
-                |indexes_metadatas_serialized_json| ListIndexesMetadatasResponse {
-                    indexes_metadatas_serialized_json,
-                },
-            )
-            .map_err(|error| MetastoreError::JsonSerializeError {
-                name: "Vec<IndexMetadata>".to_string(),
-                message: error.to_string(),
-            })?;
+        let list_indexes_metadatas_reply = serialize_payload(
+            &indexes_metadatas,
+            "Vec<IndexMetadata>",
+            binary_payload_version_opt,
+        )
+        .map(list_indexes_metadatas_response)?;
+        Ok(tonic::Response::new(list_indexes_metadatas_reply))
+    }
+
+    async fn list_indexes_metadatas_in_namespace(
+        &self,
+        request: tonic::Request<ListIndexesMetadatasInNamespaceRequest>,
+    ) -> Result<tonic::Response<ListIndexesMetadatasResponse>, tonic::Status> {
+        let list_indexes_request = request.into_inner();
+        let indexes_metadatas = self
+            .0
+            .list_indexes_metadatas_in_namespace(&list_indexes_request.namespace)
+            .await?;
+        let list_indexes_metadatas_reply = serialize_payload(
+            &indexes_metadatas,
+            "Vec<IndexMetadata>",
+            list_indexes_request.binary_payload_version,
+        )
+        .map(list_indexes_metadatas_response)?;
         Ok(tonic::Response::new(list_indexes_metadatas_reply))
     }
 
@@ -120,30 +192,29 @@ impl grpc::MetastoreApiService for GrpcMetastoreAdapter {
         Ok(tonic::Response::new(delete_reply))
     }
 
+    type list_all_splitsStream = SplitBatchStream;
+
     async fn list_all_splits(
         &self,
         request: tonic::Request<ListAllSplitsRequest>,
-    ) -> Result<tonic::Response<ListSplitsResponse>, tonic::Status> {
+    ) -> Result<tonic::Response<Self::list_all_splitsStream>, tonic::Status> {
         let list_all_splits_request = request.into_inner();
         let splits = self
             .0
             .list_all_splits(&list_all_splits_request.index_id)
             .await?;
-        let list_all_splits_reply = serde_json::to_string(&splits)
-            .map(|splits_serialized_json| ListSplitsResponse {
-                splits_serialized_json,
-            })
-            .map_err(|error| MetastoreError::JsonSerializeError {
-                name: "Vec<Split>".to_string(),
-                message: error.to_string(),
-            })?;
-        Ok(tonic::Response::new(list_all_splits_reply))
+        Ok(tonic::Response::new(stream_split_batches(
+            splits,
+            list_all_splits_request.binary_payload_version,
+        )))
     }
 
+    type list_splitsStream = SplitBatchStream;
+
     async fn list_splits(
         &self,
         request: tonic::Request<ListSplitsRequest>,
-    ) -> Result<tonic::Response<ListSplitsResponse>, tonic::Status> {
+    ) -> Result<tonic::Response<Self::list_splitsStream>, tonic::Status> {
         let list_splits_request = request.into_inner();
         let split_state: SplitState = SplitState::from_str(&list_splits_request.split_state)
             .map_err(|error| MetastoreError::JsonDeserializeError {
@@ -169,14 +240,72 @@ impl grpc::MetastoreApiService for GrpcMetastoreAdapter {
             .0
             .list_splits(&list_splits_request.index_id, split_state, time_range, tags)
             .await?;
-        let list_splits_reply = serde_json::to_string(&splits)
-            .map(|splits_serialized_json| ListSplitsResponse {
-                splits_serialized_json,
+        Ok(tonic::Response::new(stream_split_batches(
+            splits,
+            list_splits_request.binary_payload_version,
+        )))
+    }
+
+    async fn list_splits_page(
+        &self,
+        request: tonic::Request<ListSplitsPageRequest>,
+    ) -> Result<tonic::Response<ListSplitsResponse>, tonic::Status> {
+        let list_splits_page_request = request.into_inner();
+        let split_state_opt = list_splits_page_request
+            .split_state
+            .map(|split_state| {
+                SplitState::from_str(&split_state).map_err(|error| {
+                    MetastoreError::JsonDeserializeError {
+                        name: "SplitState".to_string(),
+                        message: error,
+                    }
+                })
             })
-            .map_err(|error| MetastoreError::JsonSerializeError {
-                name: "Vec<Split>".to_string(),
-                message: error.to_string(),
-            })?;
+            .transpose()?;
+        let tags_opt = list_splits_page_request
+            .tags_serialized_json
+            .map(|tags| {
+                serde_json::from_str::<TagFilterAst>(&tags).map_err(|error| {
+                    MetastoreError::JsonDeserializeError {
+                        name: "TagFilterAst".to_string(),
+                        message: error.to_string(),
+                    }
+                })
+            })
+            .transpose()?;
+        let query = ListSplitsQuery {
+            split_state_opt,
+            time_range_opt: extract_time_range(
+                list_splits_page_request.time_range_start,
+                list_splits_page_request.time_range_end,
+            ),
+            create_timestamp_range_opt: extract_time_range(
+                list_splits_page_request.create_timestamp_start,
+                list_splits_page_request.create_timestamp_end,
+            ),
+            tags_opt,
+            source_id_opt: list_splits_page_request.source_id,
+            node_id_opt: list_splits_page_request.node_id,
+            pipeline_ord_opt: list_splits_page_request
+                .pipeline_ord
+                .map(|pipeline_ord| pipeline_ord as usize),
+            pipeline_generation_opt: list_splits_page_request
+                .pipeline_generation
+                .map(|pipeline_generation| pipeline_generation as usize),
+            current_config_fingerprint_opt: list_splits_page_request.current_config_fingerprint,
+            offset: list_splits_page_request.offset as usize,
+            limit_opt: list_splits_page_request.limit.map(|limit| limit as usize),
+        };
+        let splits = self
+            .0
+            .list_splits_page(&list_splits_page_request.index_id, query)
+            .await?;
+        let list_splits_reply = serialize_payload(
+            &splits,
+            "Vec<Split>",
+            list_splits_page_request.binary_payload_version,
+        )
+        .map(list_splits_response)?;
         Ok(tonic::Response::new(list_splits_reply))
     }
 
@@ -318,4 +447,242 @@ impl grpc::MetastoreApiService for GrpcMetastoreAdapter {
             .map(|_| SourceResponse {})?;
         Ok(tonic::Response::new(reply))
     }
+
+    async fn create_delete_task(
+        &self,
+        request: tonic::Request<CreateDeleteTaskRequest>,
+    ) -> Result<tonic::Response<DeleteTaskResponse>, tonic::Status> {
+        let create_delete_task_request = request.into_inner();
+        let delete_query = serde_json::from_str::<DeleteQuery>(
+            &create_delete_task_request.delete_query_serialized_json,
+        )
+        .map_err(|error| MetastoreError::JsonDeserializeError {
+            name: "DeleteQuery".to_string(),
+            message: error.to_string(),
+        })?;
+        let delete_task = self.0.create_delete_task(delete_query).await?;
+        let create_delete_task_reply = serde_json::to_string(&delete_task)
+            .map(|delete_task_serialized_json| DeleteTaskResponse {
+                delete_task_serialized_json,
+            })
+            .map_err(|error| MetastoreError::JsonSerializeError {
+                name: "DeleteTask".to_string(),
+                message: error.to_string(),
+            })?;
+        Ok(tonic::Response::new(create_delete_task_reply))
+    }
+
+    async fn last_delete_opstamp(
+        &self,
+        request: tonic::Request<LastDeleteOpstampRequest>,
+    ) -> Result<tonic::Response<LastDeleteOpstampResponse>, tonic::Status> {
+        let last_delete_opstamp_request = request.into_inner();
+        let last_delete_opstamp = self
+            .0
+            .last_delete_opstamp(&last_delete_opstamp_request.index_id)
+            .await?;
+        Ok(tonic::Response::new(LastDeleteOpstampResponse {
+            last_delete_opstamp,
+        }))
+    }
+
+    async fn list_delete_tasks(
+        &self,
+        request: tonic::Request<ListDeleteTasksRequest>,
+    ) -> Result<tonic::Response<ListDeleteTasksResponse>, tonic::Status> {
+        let list_delete_tasks_request = request.into_inner();
+        let delete_tasks = self
+            .0
+            .list_delete_tasks(
+                &list_delete_tasks_request.index_id,
+                list_delete_tasks_request.opstamp_start,
+            )
+            .await?;
+        let list_delete_tasks_reply = serde_json::to_string(&delete_tasks)
+            .map(|delete_tasks_serialized_json| ListDeleteTasksResponse {
+                delete_tasks_serialized_json,
+            })
+            .map_err(|error| MetastoreError::JsonSerializeError {
+                name: "Vec<DeleteTask>".to_string(),
+                message: error.to_string(),
+            })?;
+        Ok(tonic::Response::new(list_delete_tasks_reply))
+    }
+
+    async fn list_splits_for_delete_task(
+        &self,
+        request: tonic::Request<ListSplitsForDeleteTaskRequest>,
+    ) -> Result<tonic::Response<ListSplitsResponse>, tonic::Status> {
+        let list_splits_request = request.into_inner();
+        let producer_opt = match (
+            list_splits_request.producer_source_id,
+            list_splits_request.producer_node_id,
+            list_splits_request.producer_pipeline_ord,
+        ) {
+            (Some(source_id), Some(node_id), Some(pipeline_ord)) => Some(SplitProducer {
+                source_id,
+                node_id,
+                pipeline_ord: pipeline_ord as usize,
+            }),
+            (None, None, None) => None,
+            _ => {
+                return Err(tonic::Status::invalid_argument(
+                    "The source ID, node ID and pipeline ordinal of the split producer must be \
+                     set together.",
+                ))
+            }
+        };
+        let splits = self
+            .0
+            .list_splits_for_delete_task(
+                &list_splits_request.index_id,
+                list_splits_request.delete_opstamp_threshold,
+                producer_opt,
+                list_splits_request.limit as usize,
+            )
+            .await?;
+        let list_splits_reply = serialize_payload(
+            &splits,
+            "Vec<Split>",
+            list_splits_request.binary_payload_version,
+        )
+        .map(list_splits_response)?;
+        Ok(tonic::Response::new(list_splits_reply))
+    }
+
+    async fn update_splits_delete_opstamp(
+        &self,
+        request: tonic::Request<UpdateSplitsDeleteOpstampRequest>,
+    ) -> Result<tonic::Response<SplitResponse>, tonic::Status> {
+        let update_request = request.into_inner();
+        let split_ids = update_request
+            .split_ids
+            .iter()
+            .map(|split_id| split_id.as_str())
+            .collect_vec();
+        let update_reply = self
+            .0
+            .update_splits_delete_opstamp(
+                &update_request.index_id,
+                &split_ids,
+                update_request.delete_opstamp,
+            )
+            .await
+            .map(|_| SplitResponse {})?;
+        Ok(tonic::Response::new(update_reply))
+    }
+
+    async fn amend_splits_tags(
+        &self,
+        request: tonic::Request<AmendSplitsTagsRequest>,
+    ) -> Result<tonic::Response<SplitResponse>, tonic::Status> {
+        let amend_request = request.into_inner();
+        let splits_tags: BTreeMap<String, BTreeSet<String>> =
+            serde_json::from_str(&amend_request.splits_tags_serialized_json).map_err(|error| {
+                MetastoreError::JsonDeserializeError {
+                    name: "BTreeMap<String, BTreeSet<String>>".to_string(),
+                    message: error.to_string(),
+                }
+            })?;
+        let amend_reply = self
+            .0
+            .amend_splits_tags(&amend_request.index_id, splits_tags, &amend_request.reason)
+            .await
+            .map(|_| SplitResponse {})?;
+        Ok(tonic::Response::new(amend_reply))
+    }
+
+    async fn set_index_maintenance_mode(
+        &self,
+        request: tonic::Request<SetIndexMaintenanceModeRequest>,
+    ) -> Result<tonic::Response<SetIndexMaintenanceModeResponse>, tonic::Status> {
+        let set_request = request.into_inner();
+        let set_reply = self
+            .0
+            .set_index_maintenance_mode(&set_request.index_id, set_request.enabled)
+            .await
+            .map(|_| SetIndexMaintenanceModeResponse {})?;
+        Ok(tonic::Response::new(set_reply))
+    }
+
+    async fn set_index_feature_flag(
+        &self,
+        request: tonic::Request<SetIndexFeatureFlagRequest>,
+    ) -> Result<tonic::Response<SetIndexFeatureFlagResponse>, tonic::Status> {
+        let set_request = request.into_inner();
+        let set_reply = self
+            .0
+            .set_index_feature_flag(
+                &set_request.index_id,
+                &set_request.flag,
+                set_request.enabled,
+            )
+            .await
+            .map(|_| SetIndexFeatureFlagResponse {})?;
+        Ok(tonic::Response::new(set_reply))
+    }
+
+    async fn acquire_merge_planner_lease(
+        &self,
+        request: tonic::Request<AcquireMergePlannerLeaseRequest>,
+    ) -> Result<tonic::Response<AcquireMergePlannerLeaseResponse>, tonic::Status> {
+        let acquire_request = request.into_inner();
+        let acquire_reply = self
+            .0
+            .acquire_merge_planner_lease(
+                &acquire_request.index_id,
+                &acquire_request.owner,
+                acquire_request.lease_duration_secs,
+            )
+            .await
+            .map(|is_lease_holder| AcquireMergePlannerLeaseResponse { is_lease_holder })?;
+        Ok(tonic::Response::new(acquire_reply))
+    }
+
+    async fn quarantine_source_file(
+        &self,
+        request: tonic::Request<QuarantineSourceFileRequest>,
+    ) -> Result<tonic::Response<SourceResponse>, tonic::Status> {
+        let quarantine_request = request.into_inner();
+        let quarantined_file = serde_json::from_str(
+            &quarantine_request.quarantined_file_serialized_json,
+        )
+        .map_err(|error| MetastoreError::JsonDeserializeError {
+            name: "QuarantinedFile".to_string(),
+            message: error.to_string(),
+        })?;
+        let quarantine_reply = self
+            .0
+            .quarantine_source_file(
+                &quarantine_request.index_id,
+                &quarantine_request.source_id,
+                quarantined_file,
+            )
+            .await
+            .map(|_| SourceResponse {})?;
+        Ok(tonic::Response::new(quarantine_reply))
+    }
+
+    async fn update_index(
+        &self,
+        request: tonic::Request<UpdateIndexRequest>,
+    ) -> Result<tonic::Response<UpdateIndexResponse>, tonic::Status> {
+        let update_request = request.into_inner();
+        let doc_mapping = serde_json::from_str(&update_request.doc_mapping_serialized_json)
+            .map_err(|error| MetastoreError::JsonDeserializeError {
+                name: "DocMapping".to_string(),
+                message: error.to_string(),
+            })?;
+        let search_settings = serde_json::from_str(&update_request.search_settings_serialized_json)
+            .map_err(|error| MetastoreError::JsonDeserializeError {
+                name: "SearchSettings".to_string(),
+                message: error.to_string(),
+            })?;
+        let update_reply = self
+            .0
+            .update_index(&update_request.index_id, doc_mapping, search_settings)
+            .await
+            .map(|_| UpdateIndexResponse {})?;
+        Ok(tonic::Response::new(update_reply))
+    }
 }
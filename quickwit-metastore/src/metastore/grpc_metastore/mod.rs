@@ -18,8 +18,9 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 mod grpc_adapter;
+mod payload_codec;
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::error::Error;
 use std::net::SocketAddr;
 use std::ops::Range;
@@ -30,15 +31,20 @@ pub use grpc_adapter::GrpcMetastoreAdapter;
 use http::Uri;
 use quickwit_cluster::{ClusterMember, QuickwitService};
 use quickwit_common::uri::Uri as QuickwitUri;
-use quickwit_config::SourceConfig;
+use quickwit_config::{DocMapping, SearchSettings, SourceConfig};
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
 use quickwit_proto::metastore_api::metastore_api_service_client::MetastoreApiServiceClient;
 use quickwit_proto::metastore_api::{
-    AddSourceRequest, CreateIndexRequest, DeleteIndexRequest, DeleteSourceRequest,
-    DeleteSplitsRequest, IndexMetadataRequest, ListAllSplitsRequest, ListIndexesMetadatasRequest,
-    ListSplitsRequest, MarkSplitsForDeletionRequest, PublishSplitsRequest,
-    ResetSourceCheckpointRequest, StageSplitRequest,
+    AcquireMergePlannerLeaseRequest, AddSourceRequest, AmendSplitsTagsRequest,
+    CreateDeleteTaskRequest, CreateIndexRequest, DeleteIndexRequest, DeleteSourceRequest,
+    DeleteSplitsRequest, IndexMetadataRequest, LastDeleteOpstampRequest, ListAllSplitsRequest,
+    ListDeleteTasksRequest, ListIndexesMetadatasInNamespaceRequest, ListIndexesMetadatasRequest,
+    ListSplitsForDeleteTaskRequest, ListSplitsPageRequest, ListSplitsRequest, ListSplitsResponse,
+    MarkSplitsForDeletionRequest, PublishSplitsRequest, QuarantineSourceFileRequest,
+    ResetSourceCheckpointRequest, SetIndexFeatureFlagRequest, SetIndexMaintenanceModeRequest,
+    StageSplitRequest, UpdateIndexRequest, UpdateSplitsDeleteOpstampRequest,
 };
+use quickwit_proto::tonic::codec::Streaming;
 use quickwit_proto::tonic::transport::{Channel, Endpoint};
 use quickwit_proto::tonic::Status;
 use tokio::sync::mpsc::Sender;
@@ -50,9 +56,11 @@ use tower::timeout::error::Elapsed;
 use tower::timeout::Timeout;
 use tracing::{debug, error, info};
 
+use self::payload_codec::{deserialize_payload, BINARY_PAYLOAD_VERSION};
 use crate::checkpoint::IndexCheckpointDelta;
 use crate::{
-    IndexMetadata, Metastore, MetastoreError, MetastoreResult, Split, SplitMetadata, SplitState,
+    DeleteQuery, DeleteTask, IndexMetadata, ListSplitsQuery, Metastore, MetastoreError,
+    MetastoreResult, QuarantinedFile, Split, SplitMetadata, SplitProducer, SplitState,
 };
 
 const CLIENT_TIMEOUT_DURATION: Duration = if cfg!(test) {
@@ -159,37 +167,60 @@ impl Metastore for MetastoreGrpcClient {
         let response = self
             .0
             .clone()
-            .list_indexes_metadatas(ListIndexesMetadatasRequest {})
+            .list_indexes_metadatas(ListIndexesMetadatasRequest {
+                binary_payload_version: Some(BINARY_PAYLOAD_VERSION),
+            })
             .await
-            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
-        let indexes_metadatas =
-            serde_json::from_str(&response.into_inner().indexes_metadatas_serialized_json)
-                .map_err(|error| MetastoreError::JsonDeserializeError {
-                    name: "Vec<IndexMetadata>".to_string(),
-                    message: error.to_string(),
-                })?;
-        Ok(indexes_metadatas)
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?
+            .into_inner();
+        deserialize_payload(
+            &response.indexes_metadatas_serialized_json,
+            &response.indexes_metadatas_serialized_binary,
+            "Vec<IndexMetadata>",
+        )
+    }
+
+    /// Lists the indexes of a namespace.
+    async fn list_indexes_metadatas_in_namespace(
+        &self,
+        namespace: &str,
+    ) -> MetastoreResult<Vec<IndexMetadata>> {
+        let request = ListIndexesMetadatasInNamespaceRequest {
+            namespace: namespace.to_string(),
+            binary_payload_version: Some(BINARY_PAYLOAD_VERSION),
+        };
+        let response = self
+            .0
+            .clone()
+            .list_indexes_metadatas_in_namespace(request)
+            .await
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?
+            .into_inner();
+        deserialize_payload(
+            &response.indexes_metadatas_serialized_json,
+            &response.indexes_metadatas_serialized_binary,
+            "Vec<IndexMetadata>",
+        )
     }
 
     /// Returns the [`IndexMetadata`] for a given index.
     async fn index_metadata(&self, index_id: &str) -> MetastoreResult<IndexMetadata> {
         let request = IndexMetadataRequest {
             index_id: index_id.to_string(),
+            binary_payload_version: Some(BINARY_PAYLOAD_VERSION),
         };
         let response = self
             .0
             .clone()
             .index_metadata(request)
             .await
-            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
-        let index_metadata = serde_json::from_str(
-            &response.into_inner().index_metadata_serialized_json,
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?
+            .into_inner();
+        deserialize_payload(
+            &response.index_metadata_serialized_json,
+            &response.index_metadata_serialized_binary,
+            "IndexMetadata",
         )
-        .map_err(|error| MetastoreError::JsonDeserializeError {
-            name: "IndexMetadata".to_string(),
-            message: error.to_string(),
-        })?;
-        Ok(index_metadata)
     }
 
     /// Deletes an index.
@@ -287,44 +318,85 @@ impl Metastore for MetastoreGrpcClient {
             time_range_start: time_range.as_ref().map(|range| range.start),
             time_range_end: time_range.as_ref().map(|range| range.end),
             tags_serialized_json,
+            binary_payload_version: Some(BINARY_PAYLOAD_VERSION),
         };
-        let response = self
+        let split_batches = self
             .0
             .clone()
             .list_splits(request)
             .await
             .map(|tonic_response| tonic_response.into_inner())
             .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
-        let splits: Vec<Split> =
-            serde_json::from_str(&response.splits_serialized_json).map_err(|error| {
-                MetastoreError::JsonDeserializeError {
-                    name: "Vec<Split>".to_string(),
-                    message: error.to_string(),
-                }
+        collect_split_batches(split_batches).await
+    }
+
+    /// Lists a page of the splits matching a query.
+    async fn list_splits_page(
+        &self,
+        index_id: &str,
+        query: ListSplitsQuery,
+    ) -> MetastoreResult<Vec<Split>> {
+        let tags_serialized_json = query
+            .tags_opt
+            .map(|tags_filter| serde_json::to_string(&tags_filter))
+            .transpose()
+            .map_err(|error| MetastoreError::JsonSerializeError {
+                name: "TagFilterAst".to_string(),
+                message: error.to_string(),
             })?;
-        Ok(splits)
+        let request = ListSplitsPageRequest {
+            index_id: index_id.to_string(),
+            split_state: query
+                .split_state_opt
+                .map(|split_state| split_state.as_str().to_string()),
+            time_range_start: query.time_range_opt.as_ref().map(|range| range.start),
+            time_range_end: query.time_range_opt.as_ref().map(|range| range.end),
+            create_timestamp_start: query
+                .create_timestamp_range_opt
+                .as_ref()
+                .map(|range| range.start),
+            create_timestamp_end: query
+                .create_timestamp_range_opt
+                .as_ref()
+                .map(|range| range.end),
+            tags_serialized_json,
+            offset: query.offset as u64,
+            limit: query.limit_opt.map(|limit| limit as u64),
+            binary_payload_version: Some(BINARY_PAYLOAD_VERSION),
+            source_id: query.source_id_opt,
+            node_id: query.node_id_opt,
+            pipeline_ord: query
+                .pipeline_ord_opt
+                .map(|pipeline_ord| pipeline_ord as u64),
+            pipeline_generation: query
+                .pipeline_generation_opt
+                .map(|pipeline_generation| pipeline_generation as u64),
+            current_config_fingerprint: query.current_config_fingerprint_opt,
+        };
+        let response = self
+            .0
+            .clone()
+            .list_splits_page(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        deserialize_splits(&response)
     }
 
     /// Lists all the splits without filtering.
     async fn list_all_splits(&self, index_id: &str) -> MetastoreResult<Vec<Split>> {
         let request = ListAllSplitsRequest {
             index_id: index_id.to_string(),
+            binary_payload_version: Some(BINARY_PAYLOAD_VERSION),
         };
-        let response = self
+        let split_batches = self
             .0
             .clone()
             .list_all_splits(request)
             .await
             .map(|tonic_response| tonic_response.into_inner())
             .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
-        let splits: Vec<Split> =
-            serde_json::from_str(&response.splits_serialized_json).map_err(|error| {
-                MetastoreError::JsonDeserializeError {
-                    name: "Vec<Split>".to_string(),
-                    message: error.to_string(),
-                }
-            })?;
-        Ok(splits)
+        collect_split_batches(split_batches).await
     }
 
     /// Marks a list of splits for deletion.
@@ -426,6 +498,276 @@ impl Metastore for MetastoreGrpcClient {
             .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
         Ok(())
     }
+
+    /// Creates a delete task.
+    async fn create_delete_task(&self, delete_query: DeleteQuery) -> MetastoreResult<DeleteTask> {
+        let delete_query_serialized_json =
+            serde_json::to_string(&delete_query).map_err(|error| {
+                MetastoreError::JsonSerializeError {
+                    name: "DeleteQuery".to_string(),
+                    message: error.to_string(),
+                }
+            })?;
+        let request = CreateDeleteTaskRequest {
+            delete_query_serialized_json,
+        };
+        let response = self
+            .0
+            .clone()
+            .create_delete_task(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        let delete_task: DeleteTask = serde_json::from_str(&response.delete_task_serialized_json)
+            .map_err(|error| MetastoreError::JsonDeserializeError {
+            name: "DeleteTask".to_string(),
+            message: error.to_string(),
+        })?;
+        Ok(delete_task)
+    }
+
+    /// Returns the opstamp of the last delete task of an index.
+    async fn last_delete_opstamp(&self, index_id: &str) -> MetastoreResult<u64> {
+        let request = LastDeleteOpstampRequest {
+            index_id: index_id.to_string(),
+        };
+        let response = self
+            .0
+            .clone()
+            .last_delete_opstamp(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(response.last_delete_opstamp)
+    }
+
+    /// Lists the delete tasks of an index created after `opstamp_start`.
+    async fn list_delete_tasks(
+        &self,
+        index_id: &str,
+        opstamp_start: u64,
+    ) -> MetastoreResult<Vec<DeleteTask>> {
+        let request = ListDeleteTasksRequest {
+            index_id: index_id.to_string(),
+            opstamp_start,
+        };
+        let response = self
+            .0
+            .clone()
+            .list_delete_tasks(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        let delete_tasks: Vec<DeleteTask> =
+            serde_json::from_str(&response.delete_tasks_serialized_json).map_err(|error| {
+                MetastoreError::JsonDeserializeError {
+                    name: "Vec<DeleteTask>".to_string(),
+                    message: error.to_string(),
+                }
+            })?;
+        Ok(delete_tasks)
+    }
+
+    /// Lists the published splits of an index lagging behind `delete_opstamp_threshold`.
+    async fn list_splits_for_delete_task(
+        &self,
+        index_id: &str,
+        delete_opstamp_threshold: u64,
+        producer_opt: Option<SplitProducer>,
+        limit: usize,
+    ) -> MetastoreResult<Vec<Split>> {
+        let request = ListSplitsForDeleteTaskRequest {
+            index_id: index_id.to_string(),
+            delete_opstamp_threshold,
+            limit: limit as u64,
+            binary_payload_version: Some(BINARY_PAYLOAD_VERSION),
+            producer_source_id: producer_opt
+                .as_ref()
+                .map(|producer| producer.source_id.clone()),
+            producer_node_id: producer_opt
+                .as_ref()
+                .map(|producer| producer.node_id.clone()),
+            producer_pipeline_ord: producer_opt.map(|producer| producer.pipeline_ord as u64),
+        };
+        let response = self
+            .0
+            .clone()
+            .list_splits_for_delete_task(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        deserialize_splits(&response)
+    }
+
+    /// Updates the delete opstamp of a list of splits.
+    async fn update_splits_delete_opstamp<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+        delete_opstamp: u64,
+    ) -> MetastoreResult<()> {
+        let split_ids_vec: Vec<String> = split_ids
+            .iter()
+            .map(|split_id| split_id.to_string())
+            .collect();
+        let request = UpdateSplitsDeleteOpstampRequest {
+            index_id: index_id.to_string(),
+            split_ids: split_ids_vec,
+            delete_opstamp,
+        };
+        self.0
+            .clone()
+            .update_splits_delete_opstamp(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
+
+    async fn amend_splits_tags(
+        &self,
+        index_id: &str,
+        splits_tags: BTreeMap<String, BTreeSet<String>>,
+        reason: &str,
+    ) -> MetastoreResult<()> {
+        let splits_tags_serialized_json = serde_json::to_string(&splits_tags).map_err(|error| {
+            MetastoreError::JsonSerializeError {
+                name: "BTreeMap<String, BTreeSet<String>>".to_string(),
+                message: error.to_string(),
+            }
+        })?;
+        let request = AmendSplitsTagsRequest {
+            index_id: index_id.to_string(),
+            splits_tags_serialized_json,
+            reason: reason.to_string(),
+        };
+        self.0
+            .clone()
+            .amend_splits_tags(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
+
+    async fn set_index_maintenance_mode(
+        &self,
+        index_id: &str,
+        enabled: bool,
+    ) -> MetastoreResult<()> {
+        let request = SetIndexMaintenanceModeRequest {
+            index_id: index_id.to_string(),
+            enabled,
+        };
+        self.0
+            .clone()
+            .set_index_maintenance_mode(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
+
+    async fn set_index_feature_flag(
+        &self,
+        index_id: &str,
+        flag: &str,
+        enabled: bool,
+    ) -> MetastoreResult<()> {
+        let request = SetIndexFeatureFlagRequest {
+            index_id: index_id.to_string(),
+            flag: flag.to_string(),
+            enabled,
+        };
+        self.0
+            .clone()
+            .set_index_feature_flag(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
+
+    async fn acquire_merge_planner_lease(
+        &self,
+        index_id: &str,
+        owner: &str,
+        lease_duration_secs: u64,
+    ) -> MetastoreResult<bool> {
+        let request = AcquireMergePlannerLeaseRequest {
+            index_id: index_id.to_string(),
+            owner: owner.to_string(),
+            lease_duration_secs,
+        };
+        let response = self
+            .0
+            .clone()
+            .acquire_merge_planner_lease(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(response.is_lease_holder)
+    }
+
+    async fn quarantine_source_file(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        quarantined_file: QuarantinedFile,
+    ) -> MetastoreResult<()> {
+        let quarantined_file_serialized_json =
+            serde_json::to_string(&quarantined_file).map_err(|error| {
+                MetastoreError::JsonSerializeError {
+                    name: "QuarantinedFile".to_string(),
+                    message: error.to_string(),
+                }
+            })?;
+        let request = QuarantineSourceFileRequest {
+            index_id: index_id.to_string(),
+            source_id: source_id.to_string(),
+            quarantined_file_serialized_json,
+        };
+        self.0
+            .clone()
+            .quarantine_source_file(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
+
+    async fn update_index(
+        &self,
+        index_id: &str,
+        doc_mapping: DocMapping,
+        search_settings: SearchSettings,
+    ) -> MetastoreResult<()> {
+        let doc_mapping_serialized_json = serde_json::to_string(&doc_mapping).map_err(|error| {
+            MetastoreError::JsonSerializeError {
+                name: "DocMapping".to_string(),
+                message: error.to_string(),
+            }
+        })?;
+        let search_settings_serialized_json =
+            serde_json::to_string(&search_settings).map_err(|error| {
+                MetastoreError::JsonSerializeError {
+                    name: "SearchSettings".to_string(),
+                    message: error.to_string(),
+                }
+            })?;
+        let request = UpdateIndexRequest {
+            index_id: index_id.to_string(),
+            doc_mapping_serialized_json,
+            search_settings_serialized_json,
+        };
+        self.0
+            .clone()
+            .update_index(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
 }
 
 fn get_metastore_grpc_addresses(members: &[ClusterMember]) -> HashSet<SocketAddr> {
@@ -513,6 +855,29 @@ pub fn parse_grpc_error(grpc_error: &Status) -> MetastoreError {
     })
 }
 
+fn deserialize_splits(response: &ListSplitsResponse) -> MetastoreResult<Vec<Split>> {
+    deserialize_payload(
+        &response.splits_serialized_json,
+        &response.splits_serialized_binary,
+        "Vec<Split>",
+    )
+}
+
+/// Collects the batches of splits streamed by the `list_splits` and `list_all_splits` RPCs.
+async fn collect_split_batches(
+    mut split_batches: Streaming<ListSplitsResponse>,
+) -> MetastoreResult<Vec<Split>> {
+    let mut splits = Vec::new();
+    while let Some(split_batch) = split_batches
+        .message()
+        .await
+        .map_err(|tonic_error| parse_grpc_error(&tonic_error))?
+    {
+        splits.extend(deserialize_splits(&split_batch)?);
+    }
+    Ok(splits)
+}
+
 #[cfg(test)]
 #[async_trait]
 impl crate::tests::test_suite::DefaultForTest for MetastoreGrpcClient {
@@ -557,7 +922,10 @@ mod tests {
     use tokio_stream::wrappers::WatchStream;
 
     use super::grpc_adapter::GrpcMetastoreAdapter;
-    use super::{IndexMetadata, Metastore, MetastoreError, MetastoreGrpcClient};
+    use super::{
+        IndexMetadata, Metastore, MetastoreError, MetastoreGrpcClient, Split, SplitMetadata,
+        SplitState,
+    };
     use crate::MockMetastore;
 
     pub async fn create_duplex_stream_server_and_client(
@@ -766,4 +1134,38 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_grpc_metastore_service_streams_splits_in_batches() -> anyhow::Result<()> {
+        let mut metastore = MockMetastore::new();
+        let index_id = "test-index";
+        metastore
+            .expect_list_all_splits()
+            .return_once(|index_id: &str| {
+                assert_eq!(index_id, "test-index");
+                let splits = (0..5)
+                    .map(|split_ord| Split {
+                        split_state: SplitState::Published,
+                        update_timestamp: 0,
+                        split_metadata: SplitMetadata::for_test(format!("split-{split_ord}")),
+                    })
+                    .collect();
+                Ok(splits)
+            });
+        let metastore_client = create_duplex_stream_server_and_client(Arc::new(metastore))
+            .await
+            .unwrap();
+        // The splits are sent in batches of two splits.
+        let split_ids: Vec<String> = metastore_client
+            .list_all_splits(index_id)
+            .await?
+            .into_iter()
+            .map(|split| split.split_id().to_string())
+            .collect();
+        assert_eq!(
+            split_ids,
+            ["split-0", "split-1", "split-2", "split-3", "split-4"]
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,177 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Encoding of the index metadata and splits returned by the gRPC metastore.
+//!
+//! These payloads are encoded in JSON by default. A client may request a binary payload, which
+//! is cheaper to produce and parse and smaller on the wire. A binary payload starts with the
+//! version of its encoding:
+//! - version 1: CBOR serialization of the same serde model as the JSON payload.
+//!
+//! A server that does not support binary payloads ignores the request and responds with JSON,
+//! so clients and servers can be upgraded in any order.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{MetastoreError, MetastoreResult};
+
+/// Highest version of the binary payload encoding supported by this node.
+pub(super) const BINARY_PAYLOAD_VERSION: u32 = 1;
+
+/// Payload of a response, serialized in JSON or in the binary encoding. Only one of the two
+/// fields is set.
+#[derive(Debug, Default)]
+pub(super) struct SerializedPayload {
+    pub json: String,
+    pub binary: Vec<u8>,
+}
+
+/// Serializes a payload in the binary encoding if the client supports it, or in JSON otherwise.
+pub(super) fn serialize_payload<T: Serialize>(
+    value: &T,
+    name: &str,
+    binary_payload_version_opt: Option<u32>,
+) -> MetastoreResult<SerializedPayload> {
+    let serialize_error = |message: String| MetastoreError::JsonSerializeError {
+        name: name.to_string(),
+        message,
+    };
+    match binary_payload_version_opt {
+        Some(binary_payload_version) if binary_payload_version >= 1 => {
+            let mut binary = vec![1u8];
+            serde_cbor::to_writer(&mut binary, value)
+                .map_err(|error| serialize_error(error.to_string()))?;
+            Ok(SerializedPayload {
+                binary,
+                ..Default::default()
+            })
+        }
+        _ => {
+            let json =
+                serde_json::to_string(value).map_err(|error| serialize_error(error.to_string()))?;
+            Ok(SerializedPayload {
+                json,
+                ..Default::default()
+            })
+        }
+    }
+}
+
+/// Deserializes a payload from the binary field of a response if it is set, or from its JSON
+/// field otherwise.
+pub(super) fn deserialize_payload<T: DeserializeOwned>(
+    json: &str,
+    binary: &[u8],
+    name: &str,
+) -> MetastoreResult<T> {
+    let deserialize_error = |message: String| MetastoreError::JsonDeserializeError {
+        name: name.to_string(),
+        message,
+    };
+    let (version, cbor) = match binary.split_first() {
+        Some((version, cbor)) => (*version, cbor),
+        None => {
+            return serde_json::from_str(json)
+                .map_err(|error| deserialize_error(error.to_string()));
+        }
+    };
+    match version {
+        1 => serde_cbor::from_slice(cbor).map_err(|error| deserialize_error(error.to_string())),
+        _ => Err(deserialize_error(format!(
+            "Binary payload version `{}` is not supported.",
+            version
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IndexMetadata, Split, SplitMetadata, SplitState};
+
+    fn splits_for_test() -> Vec<Split> {
+        (0..3)
+            .map(|split_ord| Split {
+                split_metadata: SplitMetadata {
+                    split_id: format!("split-{split_ord}"),
+                    num_docs: 1_000,
+                    time_range: Some(split_ord * 100..=split_ord * 100 + 99),
+                    tags: ["tenant:acme".to_string()].into_iter().collect(),
+                    footer_offsets: 1_000..2_000,
+                    ..Default::default()
+                },
+                split_state: SplitState::Published,
+                update_timestamp: 1_662_000_000,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_payload_codec_json() {
+        let splits = splits_for_test();
+        let payload = serialize_payload(&splits, "Vec<Split>", None).unwrap();
+        assert!(payload.binary.is_empty());
+        let deserialized_splits: Vec<Split> =
+            deserialize_payload(&payload.json, &payload.binary, "Vec<Split>").unwrap();
+        assert_eq!(deserialized_splits, splits);
+    }
+
+    #[test]
+    fn test_payload_codec_binary() {
+        let splits = splits_for_test();
+        let payload =
+            serialize_payload(&splits, "Vec<Split>", Some(BINARY_PAYLOAD_VERSION)).unwrap();
+        assert!(payload.json.is_empty());
+        assert_eq!(payload.binary[0], 1);
+        assert!(payload.binary.len() < serde_json::to_vec(&splits).unwrap().len());
+        let deserialized_splits: Vec<Split> =
+            deserialize_payload(&payload.json, &payload.binary, "Vec<Split>").unwrap();
+        assert_eq!(deserialized_splits, splits);
+
+        let index_metadata = IndexMetadata::for_test("test-index", "ram:///indexes/test-index");
+        let payload = serialize_payload(
+            &index_metadata,
+            "IndexMetadata",
+            Some(BINARY_PAYLOAD_VERSION),
+        )
+        .unwrap();
+        let deserialized_index_metadata: IndexMetadata =
+            deserialize_payload(&payload.json, &payload.binary, "IndexMetadata").unwrap();
+        assert_eq!(
+            serde_json::to_value(&deserialized_index_metadata).unwrap(),
+            serde_json::to_value(&index_metadata).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_payload_codec_future_versions() {
+        let splits = splits_for_test();
+        // A client supporting a future version gets the highest version supported by the server.
+        let payload = serialize_payload(&splits, "Vec<Split>", Some(42)).unwrap();
+        assert_eq!(payload.binary[0], 1);
+
+        let mut binary = payload.binary;
+        binary[0] = 42;
+        let error = deserialize_payload::<Vec<Split>>("", &binary, "Vec<Split>").unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Binary payload version `42` is not supported."));
+    }
+}
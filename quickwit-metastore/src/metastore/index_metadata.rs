@@ -18,7 +18,8 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hasher;
 
 use itertools::Itertools;
 use quickwit_common::uri::Uri;
@@ -27,6 +28,7 @@ use quickwit_config::{
 };
 use quickwit_doc_mapper::SortOrder;
 use serde::{Deserialize, Serialize};
+use siphasher::sip128::{Hasher128, SipHasher};
 
 use crate::checkpoint::IndexCheckpoint;
 use crate::split_metadata::utc_now_timestamp;
@@ -46,6 +48,9 @@ pub struct IndexMetadata {
     pub checkpoint: IndexCheckpoint,
     /// Describes how ingested JSON documents are indexed.
     pub doc_mapping: DocMapping,
+    /// Version of the doc mapping, incremented every time the doc mapping is updated. Splits
+    /// record the version of the doc mapping they were produced with.
+    pub doc_mapping_version: u64,
     /// Configures various indexing settings such as commit timeout, max split size, indexing
     /// resources.
     pub indexing_settings: IndexingSettings,
@@ -59,6 +64,38 @@ pub struct IndexMetadata {
     pub create_timestamp: i64,
     /// Time at which the index was last updated.
     pub update_timestamp: i64,
+    /// When set, the index is read-only: the metastore rejects the operations writing to it with
+    /// a [`MaintenanceMode`](crate::MetastoreError::MaintenanceMode) error.
+    pub maintenance_mode: bool,
+    /// Lease of the merge planner elected to plan the merges of all the pipelines of the index,
+    /// when merges are coordinated across pipelines.
+    pub merge_planner_lease: Option<MergePlannerLease>,
+    /// Files skipped by the sources of the index because they could not be read, keyed by source
+    /// ID.
+    pub quarantined_files: HashMap<String, Vec<QuarantinedFile>>,
+    /// Feature flags of the index, keyed by name. They gate new indexing behaviors per index,
+    /// which enables rolling them out gradually. Flags that are not set are disabled.
+    pub feature_flags: BTreeMap<String, bool>,
+}
+
+/// File skipped by a source because it could not be read.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct QuarantinedFile {
+    /// Path of the file.
+    pub filepath: String,
+    /// Error raised while reading the file.
+    pub reason: String,
+    /// Time at which the file was quarantined.
+    pub quarantine_timestamp: i64,
+}
+
+/// Lease granting a merge planner the exclusive right to plan the merges of an index.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MergePlannerLease {
+    /// Identifies the merge planner holding the lease.
+    pub owner: String,
+    /// Time at which the lease expires unless renewed by its owner.
+    pub expire_timestamp: i64,
 }
 
 impl IndexMetadata {
@@ -138,6 +175,7 @@ impl IndexMetadata {
                 r#"attributes.server"#.to_string(),
                 r#"attributes.server\.status"#.to_string(),
             ],
+            field_boosts: BTreeMap::new(),
         };
         let now_timestamp = utc_now_timestamp();
         Self {
@@ -145,13 +183,120 @@ impl IndexMetadata {
             index_uri,
             checkpoint: Default::default(),
             doc_mapping,
+            doc_mapping_version: 0,
             indexing_settings,
             search_settings,
             sources: Default::default(),
             retention_policy: None, // TODO
             create_timestamp: now_timestamp,
             update_timestamp: now_timestamp,
+            maintenance_mode: false,
+            merge_planner_lease: None,
+            quarantined_files: HashMap::new(),
+            feature_flags: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the fingerprint of the doc mapping and the indexing settings of the index. The
+    /// splits record the fingerprint of the config they were built with, so that the splits built
+    /// with an outdated config can be listed and re-indexed after the config is fixed.
+    pub fn config_fingerprint(&self) -> String {
+        // The keys of the objects of a `serde_json::Value` are sorted, so the fingerprint does not
+        // depend on the iteration order of the maps of the config.
+        let config_json = serde_json::to_value((&self.doc_mapping, &self.indexing_settings))
+            .expect("The doc mapping and the indexing settings should be serializable.")
+            .to_string();
+        let mut hasher = SipHasher::new();
+        hasher.write(config_json.as_bytes());
+        format!("{:032x}", hasher.finish128().as_u128())
+    }
+
+    /// Returns whether the feature flag `flag` is enabled for the index.
+    pub fn is_feature_enabled(&self, flag: &str) -> bool {
+        self.feature_flags.get(flag).copied().unwrap_or(false)
+    }
+
+    /// Enables or disables a feature flag. Returns whether a mutation occurred.
+    pub(crate) fn set_feature_flag(&mut self, flag: &str, enabled: bool) -> bool {
+        if self.feature_flags.get(flag) == Some(&enabled) {
+            return false;
+        }
+        self.feature_flags.insert(flag.to_string(), enabled);
+        self.update_timestamp = utc_now_timestamp();
+        true
+    }
+
+    /// Returns a [`MaintenanceMode`](crate::MetastoreError::MaintenanceMode) error if the index is
+    /// in maintenance mode.
+    pub(crate) fn check_not_in_maintenance(&self) -> MetastoreResult<()> {
+        if self.maintenance_mode {
+            return Err(MetastoreError::MaintenanceMode {
+                index_id: self.index_id.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Grants the merge planner lease to `owner` until `expire_timestamp` if the lease is free,
+    /// expired, or already held by `owner`. Returns whether `owner` holds the lease.
+    pub(crate) fn acquire_merge_planner_lease(
+        &mut self,
+        owner: &str,
+        now_timestamp: i64,
+        expire_timestamp: i64,
+    ) -> bool {
+        if let Some(lease) = &self.merge_planner_lease {
+            if lease.owner != owner && lease.expire_timestamp > now_timestamp {
+                return false;
+            }
         }
+        self.merge_planner_lease = Some(MergePlannerLease {
+            owner: owner.to_string(),
+            expire_timestamp,
+        });
+        true
+    }
+
+    /// Adds a file to the quarantine of a source. Returns whether the file was not already
+    /// quarantined.
+    pub(crate) fn quarantine_source_file(
+        &mut self,
+        source_id: &str,
+        quarantined_file: QuarantinedFile,
+    ) -> bool {
+        let source_quarantined_files = self
+            .quarantined_files
+            .entry(source_id.to_string())
+            .or_default();
+        if source_quarantined_files
+            .iter()
+            .any(|file| file.filepath == quarantined_file.filepath)
+        {
+            return false;
+        }
+        source_quarantined_files.push(quarantined_file);
+        true
+    }
+
+    /// Resets the checkpoint of a source and releases its quarantined files, so that they are
+    /// read again. Returns whether a mutation occurred.
+    pub(crate) fn reset_source_checkpoint(&mut self, source_id: &str) -> bool {
+        let checkpoint_reset = self.checkpoint.reset_source(source_id);
+        let quarantine_released = self.quarantined_files.remove(source_id).is_some();
+        checkpoint_reset || quarantine_released
+    }
+
+    /// Replaces the doc mapping and the search settings of the index, and bumps the version of the
+    /// doc mapping.
+    pub(crate) fn update_doc_mapping(
+        &mut self,
+        doc_mapping: DocMapping,
+        search_settings: SearchSettings,
+    ) {
+        self.doc_mapping = doc_mapping;
+        self.search_settings = search_settings;
+        self.doc_mapping_version += 1;
+        self.update_timestamp = utc_now_timestamp();
     }
 
     pub(crate) fn add_source(&mut self, source: SourceConfig) -> MetastoreResult<()> {
@@ -175,6 +320,7 @@ impl IndexMetadata {
                 source_id: source_id.to_string(),
             })?;
         self.checkpoint.remove_source(source_id);
+        self.quarantined_files.remove(source_id);
         Ok(())
     }
 }
@@ -207,6 +353,9 @@ pub(crate) struct IndexMetadataV1 {
     pub checkpoint: IndexCheckpoint,
     pub doc_mapping: DocMapping,
     #[serde(default)]
+    #[serde(skip_serializing_if = "is_zero")]
+    pub doc_mapping_version: u64,
+    #[serde(default)]
     pub indexing_settings: IndexingSettings,
     pub search_settings: SearchSettings,
     #[serde(default)]
@@ -219,6 +368,26 @@ pub(crate) struct IndexMetadataV1 {
     pub create_timestamp: i64,
     #[serde(default = "utc_now_timestamp")]
     pub update_timestamp: i64,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub maintenance_mode: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merge_planner_lease: Option<MergePlannerLease>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub quarantined_files: HashMap<String, Vec<QuarantinedFile>>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub feature_flags: BTreeMap<String, bool>,
+}
+
+fn is_false(val: &bool) -> bool {
+    !*val
+}
+
+fn is_zero(val: &u64) -> bool {
+    *val == 0
 }
 
 impl From<IndexMetadata> for IndexMetadataV1 {
@@ -233,12 +402,17 @@ impl From<IndexMetadata> for IndexMetadataV1 {
             index_uri: index_metadata.index_uri.into_string(),
             checkpoint: index_metadata.checkpoint,
             doc_mapping: index_metadata.doc_mapping,
+            doc_mapping_version: index_metadata.doc_mapping_version,
             indexing_settings: index_metadata.indexing_settings,
             search_settings: index_metadata.search_settings,
             sources,
             retention_policy: index_metadata.retention_policy,
             create_timestamp: index_metadata.create_timestamp,
             update_timestamp: index_metadata.update_timestamp,
+            maintenance_mode: index_metadata.maintenance_mode,
+            merge_planner_lease: index_metadata.merge_planner_lease,
+            quarantined_files: index_metadata.quarantined_files,
+            feature_flags: index_metadata.feature_flags,
         }
     }
 }
@@ -255,12 +429,17 @@ impl From<IndexMetadataV1> for IndexMetadata {
             index_uri: Uri::new(v1.index_uri),
             checkpoint: v1.checkpoint,
             doc_mapping: v1.doc_mapping,
+            doc_mapping_version: v1.doc_mapping_version,
             indexing_settings: v1.indexing_settings,
             search_settings: v1.search_settings,
             sources,
             retention_policy: v1.retention_policy,
             create_timestamp: v1.create_timestamp,
             update_timestamp: v1.update_timestamp,
+            maintenance_mode: v1.maintenance_mode,
+            merge_planner_lease: v1.merge_planner_lease,
+            quarantined_files: v1.quarantined_files,
+            feature_flags: v1.feature_flags,
         }
     }
 }
@@ -24,17 +24,67 @@ mod index_metadata;
 pub mod postgresql_metastore;
 #[cfg(feature = "postgres")]
 mod postgresql_model;
+pub mod ram_metastore;
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::ops::Range;
 
 use async_trait::async_trait;
-pub use index_metadata::IndexMetadata;
+pub use index_metadata::{IndexMetadata, MergePlannerLease, QuarantinedFile};
 use quickwit_common::uri::Uri;
-use quickwit_config::SourceConfig;
+use quickwit_config::{DocMapping, SearchSettings, SourceConfig};
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
 
 use crate::checkpoint::IndexCheckpointDelta;
-use crate::{MetastoreResult, Split, SplitMetadata, SplitState};
+use crate::{DeleteQuery, DeleteTask, MetastoreResult, Split, SplitMetadata, SplitState};
+
+/// Indexing pipeline that produced a split, used to restrict a listing to its splits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SplitProducer {
+    pub source_id: String,
+    pub node_id: String,
+    pub pipeline_ord: usize,
+}
+
+impl SplitProducer {
+    /// Returns whether the split was produced by this pipeline.
+    pub fn produced(&self, split_metadata: &SplitMetadata) -> bool {
+        self.source_id == split_metadata.source_id
+            && self.node_id == split_metadata.node_id
+            && self.pipeline_ord == split_metadata.pipeline_ord
+    }
+}
+
+/// Filters and pagination of [`Metastore::list_splits_page`].
+///
+/// The default query matches all the splits of an index and returns them all.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ListSplitsQuery {
+    /// Only lists the splits in this state. Lists the splits in any state if `None`.
+    pub split_state_opt: Option<SplitState>,
+    /// Only lists the splits whose time range intersects this range. A split without time range
+    /// always matches.
+    pub time_range_opt: Option<Range<i64>>,
+    /// Only lists the splits whose create timestamp falls in this range.
+    pub create_timestamp_range_opt: Option<Range<i64>>,
+    /// Only lists the splits whose tags match this filter.
+    pub tags_opt: Option<TagFilterAst>,
+    /// Only lists the splits produced by this source.
+    pub source_id_opt: Option<String>,
+    /// Only lists the splits produced by this node.
+    pub node_id_opt: Option<String>,
+    /// Only lists the splits produced by the pipeline with this ordinal.
+    pub pipeline_ord_opt: Option<usize>,
+    /// Only lists the splits produced by this generation of their pipeline.
+    pub pipeline_generation_opt: Option<usize>,
+    /// Only lists the splits built with an outdated config, that is, whose config fingerprint
+    /// differs from this one or was not recorded. See [`IndexMetadata::config_fingerprint`].
+    pub current_config_fingerprint_opt: Option<String>,
+    /// Number of matching splits, sorted by split ID, to skip.
+    pub offset: usize,
+    /// Maximum number of splits to return. Returns all the remaining splits if `None`.
+    pub limit_opt: Option<usize>,
+}
 
 /// Metastore meant to manage Quickwit's indexes and their splits.
 ///
@@ -87,6 +137,15 @@ pub trait Metastore: Send + Sync + 'static {
     /// [`IndexMetadata`].
     async fn list_indexes_metadatas(&self) -> MetastoreResult<Vec<IndexMetadata>>;
 
+    /// Lists the indexes of a namespace.
+    ///
+    /// This API lists the indexes whose ID starts with `{namespace}/`, including the indexes of
+    /// the nested namespaces, and returns a collection of [`IndexMetadata`].
+    async fn list_indexes_metadatas_in_namespace(
+        &self,
+        namespace: &str,
+    ) -> MetastoreResult<Vec<IndexMetadata>>;
+
     /// Returns the [`IndexMetadata`] for a given index.
     /// TODO consider merging with list_splits to remove one round-trip
     async fn index_metadata(&self, index_id: &str) -> MetastoreResult<IndexMetadata>;
@@ -139,6 +198,19 @@ pub trait Metastore: Send + Sync + 'static {
         tags: Option<TagFilterAst>,
     ) -> MetastoreResult<Vec<Split>>;
 
+    /// Lists a page of the splits matching `query`.
+    ///
+    /// The matching splits are sorted by split ID, so that the splits of an index can be
+    /// iterated over page by page by increasing the `offset` of the query by its `limit`.
+    /// The filters are applied by the metastore, which avoids transferring the whole list of
+    /// splits of an index. An error will occur if an index that does not exist in the storage
+    /// is specified.
+    async fn list_splits_page(
+        &self,
+        index_id: &str,
+        query: ListSplitsQuery,
+    ) -> MetastoreResult<Vec<Split>>;
+
     /// Lists all the splits without filtering.
     ///
     /// Returns a list of all splits currently known to the metastore regardless of their state.
@@ -183,6 +255,123 @@ pub trait Metastore: Send + Sync + 'static {
     async fn reset_source_checkpoint(&self, index_id: &str, source_id: &str)
         -> MetastoreResult<()>;
 
+    /// Creates a delete task from a delete query and assigns it the next opstamp of the index.
+    /// Fails with [`IndexDoesNotExist`](crate::MetastoreError::IndexDoesNotExist) if the index
+    /// of the query does not exist.
+    async fn create_delete_task(&self, delete_query: DeleteQuery) -> MetastoreResult<DeleteTask>;
+
+    /// Returns the opstamp of the last delete task of an index, or 0 if the index has no delete
+    /// task.
+    async fn last_delete_opstamp(&self, index_id: &str) -> MetastoreResult<u64>;
+
+    /// Lists the delete tasks of an index with an opstamp strictly greater than
+    /// `opstamp_start`, sorted by opstamp.
+    async fn list_delete_tasks(
+        &self,
+        index_id: &str,
+        opstamp_start: u64,
+    ) -> MetastoreResult<Vec<DeleteTask>>;
+
+    /// Lists at most `limit` published splits of an index with a delete opstamp strictly lower
+    /// than `delete_opstamp_threshold`, whose time range overlaps the time range of at least
+    /// one of the delete tasks they have not been applied yet, i.e. with an opstamp in
+    /// `]split.delete_opstamp, delete_opstamp_threshold]`.
+    ///
+    /// If `producer_opt` is set, only the splits produced by this indexing pipeline are listed,
+    /// so that the splits of the other pipelines do not count towards the limit.
+    ///
+    /// The splits are sorted by increasing delete opstamp, so that the splits lagging the most
+    /// behind are processed first.
+    async fn list_splits_for_delete_task(
+        &self,
+        index_id: &str,
+        delete_opstamp_threshold: u64,
+        producer_opt: Option<SplitProducer>,
+        limit: usize,
+    ) -> MetastoreResult<Vec<Split>>;
+
+    /// Updates the delete opstamp of a list of splits, once the delete tasks up to
+    /// `delete_opstamp` are known not to match any of their documents.
+    async fn update_splits_delete_opstamp<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+        delete_opstamp: u64,
+    ) -> MetastoreResult<()>;
+
+    /// Replaces the tags of published splits without rewriting their data, for instance to let
+    /// older splits benefit from a tag field added to the index after they were created.
+    ///
+    /// `splits_tags` maps the IDs of the splits to their new tags. Each split whose tags change
+    /// records the amendment and its `reason` in its metadata. The splits must all be published:
+    /// otherwise, no split is amended.
+    async fn amend_splits_tags(
+        &self,
+        index_id: &str,
+        splits_tags: BTreeMap<String, BTreeSet<String>>,
+        reason: &str,
+    ) -> MetastoreResult<()>;
+
+    /// Enables or disables the maintenance mode of an index.
+    ///
+    /// While the maintenance mode is enabled, the index is read-only: the operations writing to
+    /// the splits of the index (staging, publishing, marking for deletion, deleting, updating
+    /// their delete opstamp, amending their tags) and the creation of delete tasks fail with
+    /// [`MaintenanceMode`](crate::MetastoreError::MaintenanceMode), while reads keep working.
+    async fn set_index_maintenance_mode(
+        &self,
+        index_id: &str,
+        enabled: bool,
+    ) -> MetastoreResult<()>;
+
+    /// Enables or disables a feature flag of an index.
+    ///
+    /// The indexing pipelines of the index read the feature flags when they spawn and refresh them
+    /// while they run, so that new indexing behaviors can be rolled out index by index.
+    async fn set_index_feature_flag(
+        &self,
+        index_id: &str,
+        flag: &str,
+        enabled: bool,
+    ) -> MetastoreResult<()>;
+
+    /// Acquires or renews the merge planner lease of an index on behalf of `owner` for
+    /// `lease_duration_secs` from now.
+    ///
+    /// The lease is granted if it is free, expired, or already held by `owner`. Returns whether
+    /// `owner` holds the lease after the call. This elects a single merge planner per index when
+    /// merges are coordinated across the pipelines of the index.
+    async fn acquire_merge_planner_lease(
+        &self,
+        index_id: &str,
+        owner: &str,
+        lease_duration_secs: u64,
+    ) -> MetastoreResult<bool>;
+
+    /// Records a file that a source could not read in the quarantine of the source, so that the
+    /// source skips it from now on.
+    ///
+    /// Quarantining a file already quarantined by the source is a no-op. Resetting the checkpoint
+    /// of the source releases its quarantined files.
+    async fn quarantine_source_file(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        quarantined_file: QuarantinedFile,
+    ) -> MetastoreResult<()>;
+
+    /// Replaces the doc mapping and the search settings of an index, and increments the version
+    /// of its doc mapping. The splits produced from then on record the new version.
+    ///
+    /// The metastore does not check that the new doc mapping is compatible with the current one,
+    /// see [`quickwit_config::validate_doc_mapping_update`].
+    async fn update_index(
+        &self,
+        index_id: &str,
+        doc_mapping: DocMapping,
+        search_settings: SearchSettings,
+    ) -> MetastoreResult<()>;
+
     /// Returns the metastore uri.
     fn uri(&self) -> &Uri;
 }
@@ -17,7 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt::Write;
 use std::ops::Range;
 use std::sync::Arc;
@@ -26,11 +26,12 @@ use std::time::Duration;
 use async_trait::async_trait;
 use itertools::Itertools;
 use quickwit_common::uri::Uri;
-use quickwit_config::SourceConfig;
+use quickwit_config::{DocMapping, SearchSettings, SourceConfig, INDEX_NAMESPACE_SEPARATOR};
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
 use sqlx::migrate::Migrator;
 use sqlx::postgres::{PgConnectOptions, PgDatabaseError, PgPoolOptions};
 use sqlx::{ConnectOptions, Pool, Postgres, Row, Transaction};
+use time::OffsetDateTime;
 use tokio::sync::Mutex;
 use tracing::log::LevelFilter;
 use tracing::{debug, error, instrument, warn};
@@ -38,14 +39,19 @@ use tracing::{debug, error, instrument, warn};
 use crate::checkpoint::IndexCheckpointDelta;
 use crate::metastore::postgresql_model::{self, Index, IndexIdSplitIdRow};
 use crate::{
-    IndexMetadata, Metastore, MetastoreError, MetastoreFactory, MetastoreResolverError,
-    MetastoreResult, Split, SplitMetadata, SplitState,
+    DeleteQuery, DeleteTask, IndexMetadata, ListSplitsQuery, Metastore, MetastoreError,
+    MetastoreFactory, MetastoreResolverError, MetastoreResult, QuarantinedFile, Split,
+    SplitMetadata, SplitProducer, SplitState,
 };
 
 static MIGRATOR: Migrator = sqlx::migrate!("migrations/postgresql");
 
 const CONNECTION_POOL_MAX_SIZE: u32 = 10;
 
+/// Class of the advisory locks taken on indexes, so that they do not collide with the advisory
+/// locks of other applications sharing the database.
+const INDEX_ADVISORY_LOCK_CLASS_ID: i32 = 0x5157; // "QW"
+
 // https://www.postgresql.org/docs/current/errcodes-appendix.html
 mod pg_error_code {
     pub const FOREIGN_KEY_VIOLATION: &str = "23503";
@@ -141,6 +147,32 @@ async fn index_metadata(
         .index_metadata()
 }
 
+/// Returns a [`MaintenanceMode`](MetastoreError::MaintenanceMode) error if the index is in
+/// maintenance mode.
+async fn check_index_writable(
+    tx: &mut Transaction<'_, Postgres>,
+    index_id: &str,
+) -> MetastoreResult<()> {
+    index_metadata(tx, index_id)
+        .await?
+        .check_not_in_maintenance()
+}
+
+/// Takes a transaction-level advisory lock on the index.
+///
+/// The read-modify-write cycles of the metadata of an index are serialized through this lock
+/// across all the nodes sharing the database. Otherwise, two concurrent transactions could both
+/// apply a checkpoint delta on the same checkpoint, and the last one to commit would silently
+/// overwrite the other. The lock is released when the transaction commits or rolls back.
+async fn lock_index(tx: &mut Transaction<'_, Postgres>, index_id: &str) -> MetastoreResult<()> {
+    sqlx::query("SELECT pg_advisory_xact_lock($1, hashtext($2))")
+        .bind(INDEX_ADVISORY_LOCK_CLASS_ID)
+        .bind(index_id)
+        .execute(tx)
+        .await?;
+    Ok(())
+}
+
 /// Publishes mutiple splits.
 /// Returns the IDs of the splits successfully published.
 #[instrument(skip(tx))]
@@ -210,9 +242,7 @@ async fn mark_splits_for_deletion(
 async fn list_splits_helper(
     tx: &mut Transaction<'_, Postgres>,
     index_id: &str,
-    state_opt: Option<SplitState>,
-    time_range_opt: Option<Range<i64>>,
-    tags_opt: Option<TagFilterAst>,
+    query: ListSplitsQuery,
 ) -> MetastoreResult<Vec<Split>> {
     let mut sql = r#"
         SELECT *
@@ -220,10 +250,10 @@ async fn list_splits_helper(
         WHERE index_id = $1
     "#
     .to_string();
-    if let Some(state) = state_opt {
+    if let Some(state) = query.split_state_opt {
         let _ = write!(sql, " AND split_state = '{}'", state.as_str());
     }
-    if let Some(time_range) = time_range_opt {
+    if let Some(time_range) = query.time_range_opt {
         let _ = write!(
             sql,
             " AND (time_range_end >= {} OR time_range_end IS NULL) ",
@@ -235,13 +265,73 @@ async fn list_splits_helper(
             time_range.end
         );
     }
+    if let Some(create_timestamp_range) = query.create_timestamp_range_opt {
+        // The create timestamp of a split is only stored in its serialized metadata.
+        let _ = write!(
+            sql,
+            " AND (split_metadata_json::jsonb ->> 'create_timestamp')::BIGINT >= {} ",
+            create_timestamp_range.start
+        );
+        let _ = write!(
+            sql,
+            " AND (split_metadata_json::jsonb ->> 'create_timestamp')::BIGINT < {} ",
+            create_timestamp_range.end
+        );
+    }
 
-    if let Some(tags) = tags_opt {
+    if let Some(tags) = query.tags_opt {
         sql.push_str(" AND (");
         sql.push_str(&tags_filter_expression_helper(tags));
         sql.push_str(") ");
     }
 
+    // The producer of a split is only stored in its serialized metadata, where the node ID and
+    // the pipeline ordinal are serialized together as `{node_id}/{pipeline_ord}`.
+    if let Some(source_id) = query.source_id_opt {
+        let _ = write!(
+            sql,
+            " AND split_metadata_json::jsonb ->> 'source_id' = {} ",
+            quote_string_literal(&source_id)
+        );
+    }
+    if let Some(node_id) = query.node_id_opt {
+        let _ = write!(
+            sql,
+            " AND regexp_replace(split_metadata_json::jsonb ->> 'node_id', '/[^/]*$', '') = {} ",
+            quote_string_literal(&node_id)
+        );
+    }
+    if let Some(pipeline_ord) = query.pipeline_ord_opt {
+        let _ = write!(
+            sql,
+            " AND substring(split_metadata_json::jsonb ->> 'node_id' from '[^/]*$') = \
+             '{pipeline_ord}' "
+        );
+    }
+    if let Some(pipeline_generation) = query.pipeline_generation_opt {
+        let _ = write!(
+            sql,
+            " AND COALESCE((split_metadata_json::jsonb ->> 'pipeline_generation')::BIGINT, 0) = \
+             {pipeline_generation} "
+        );
+    }
+    // The splits without a config fingerprint were built with an unknown, hence outdated, config.
+    if let Some(current_config_fingerprint) = query.current_config_fingerprint_opt {
+        let _ = write!(
+            sql,
+            " AND (split_metadata_json::jsonb ->> 'config_fingerprint') IS DISTINCT FROM {} ",
+            quote_string_literal(&current_config_fingerprint)
+        );
+    }
+
+    sql.push_str(" ORDER BY split_id ");
+    if query.offset > 0 {
+        let _ = write!(sql, " OFFSET {} ", query.offset);
+    }
+    if let Some(limit) = query.limit_opt {
+        let _ = write!(sql, " LIMIT {limit} ");
+    }
+
     let splits = sqlx::query_as::<_, postgresql_model::Split>(&sql)
         .bind(index_id)
         .fetch_all(&mut *tx)
@@ -405,6 +495,7 @@ async fn mutate_index_metadata<E, M: FnOnce(&mut IndexMetadata) -> Result<(), E>
 where
     MetastoreError: From<E>,
 {
+    lock_index(tx, index_id).await?;
     let mut index_metadata = index_metadata(tx, index_id).await?;
     mutation(&mut index_metadata)?;
     let index_metadata_json =
@@ -451,6 +542,24 @@ impl Metastore for PostgresqlMetastore {
         })
     }
 
+    async fn list_indexes_metadatas_in_namespace(
+        &self,
+        namespace: &str,
+    ) -> MetastoreResult<Vec<IndexMetadata>> {
+        run_with_tx!(self.connection_pool, tx, {
+            let indexes: Vec<Index> =
+                sqlx::query_as::<_, Index>("SELECT * FROM indexes WHERE index_id LIKE $1")
+                    .bind(namespace_like_pattern(namespace))
+                    .fetch_all(tx)
+                    .await?;
+            let index_metadata: MetastoreResult<Vec<IndexMetadata>> = indexes
+                .into_iter()
+                .map(|index| index.index_metadata())
+                .collect::<MetastoreResult<_>>();
+            index_metadata
+        })
+    }
+
     #[instrument(skip(self),fields(index_id=index_metadata.index_id.as_str()))]
     async fn create_index(&self, index_metadata: IndexMetadata) -> MetastoreResult<()> {
         run_with_tx!(self.connection_pool, tx, {
@@ -461,13 +570,19 @@ impl Metastore for PostgresqlMetastore {
                     cause: err.to_string(),
                 }
             })?;
-            // Create index.
-            let create_index_statement_res =
-                sqlx::query("INSERT INTO indexes (index_id, index_metadata_json) VALUES ($1, $2)")
-                    .bind(&index_metadata.index_id)
-                    .bind(&index_metadata_json)
-                    .execute(tx)
-                    .await;
+            // Create index. The create timestamp of the index metadata is preserved, so that
+            // migrating an index from another metastore does not reset it.
+            let create_index_statement_res = sqlx::query(
+                r#"
+                INSERT INTO indexes (index_id, index_metadata_json, create_timestamp)
+                VALUES ($1, $2, to_timestamp($3) AT TIME ZONE 'UTC')
+            "#,
+            )
+            .bind(&index_metadata.index_id)
+            .bind(&index_metadata_json)
+            .bind(index_metadata.create_timestamp)
+            .execute(tx)
+            .await;
             create_index_statement_res
                 .map_err(|err| convert_sqlx_err(&index_metadata.index_id, err))?;
             Ok(())
@@ -493,6 +608,7 @@ impl Metastore for PostgresqlMetastore {
     #[instrument(skip(self, metadata),fields(split_id=metadata.split_id.as_str()))]
     async fn stage_split(&self, index_id: &str, metadata: SplitMetadata) -> MetastoreResult<()> {
         run_with_tx!(self.connection_pool, tx, {
+            check_index_writable(tx, index_id).await?;
             // Fit the time_range to the database model.
             let time_range_start = metadata.time_range.clone().map(|range| *range.start());
             let time_range_end = metadata.time_range.clone().map(|range| *range.end());
@@ -509,9 +625,9 @@ impl Metastore for PostgresqlMetastore {
             let split_id = metadata.split_id.clone();
             sqlx::query(r#"
                 INSERT INTO splits
-                    (split_id, split_state, time_range_start, time_range_end, tags, split_metadata_json, index_id)
+                    (split_id, split_state, time_range_start, time_range_end, tags, split_metadata_json, index_id, create_timestamp)
                 VALUES
-                    ($1, $2, $3, $4, $5, $6, $7)
+                    ($1, $2, $3, $4, $5, $6, $7, to_timestamp($8) AT TIME ZONE 'UTC')
             "#)
             .bind(&metadata.split_id)
             .bind(&SplitState::Staged.as_str())
@@ -520,6 +636,7 @@ impl Metastore for PostgresqlMetastore {
             .bind(tags)
             .bind(split_metadata_json)
             .bind(index_id)
+            .bind(metadata.create_timestamp)
             .execute(tx)
             .await
                 .map_err(|err| convert_sqlx_err(index_id, err))?;
@@ -538,6 +655,10 @@ impl Metastore for PostgresqlMetastore {
         checkpoint_delta_opt: Option<IndexCheckpointDelta>,
     ) -> MetastoreResult<()> {
         run_with_tx!(self.connection_pool, tx, {
+            // Concurrent publishes of overlapping checkpoint deltas are serialized, so that all
+            // but the first one fail with a `CheckpointConflict` error.
+            lock_index(tx, index_id).await?;
+            check_index_writable(tx, index_id).await?;
             if let Some(checkpoint_delta) = checkpoint_delta_opt {
                 mutate_index_metadata(tx, index_id, |index_metadata| {
                     index_metadata.checkpoint.try_apply_delta(checkpoint_delta)
@@ -602,14 +723,31 @@ impl Metastore for PostgresqlMetastore {
         tags: Option<TagFilterAst>,
     ) -> MetastoreResult<Vec<Split>> {
         run_with_tx!(self.connection_pool, tx, {
-            list_splits_helper(tx, index_id, Some(state), time_range_opt, tags).await
+            let query = ListSplitsQuery {
+                split_state_opt: Some(state),
+                time_range_opt,
+                tags_opt: tags,
+                ..Default::default()
+            };
+            list_splits_helper(tx, index_id, query).await
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn list_splits_page(
+        &self,
+        index_id: &str,
+        query: ListSplitsQuery,
+    ) -> MetastoreResult<Vec<Split>> {
+        run_with_tx!(self.connection_pool, tx, {
+            list_splits_helper(tx, index_id, query).await
         })
     }
 
     #[instrument(skip(self))]
     async fn list_all_splits(&self, index_id: &str) -> MetastoreResult<Vec<Split>> {
         run_with_tx!(self.connection_pool, tx, {
-            list_splits_helper(tx, index_id, None, None, None).await
+            list_splits_helper(tx, index_id, ListSplitsQuery::default()).await
         })
     }
 
@@ -620,6 +758,7 @@ impl Metastore for PostgresqlMetastore {
         split_ids: &[&'a str],
     ) -> MetastoreResult<()> {
         run_with_tx!(self.connection_pool, tx, {
+            check_index_writable(tx, index_id).await?;
             let marked_split_ids: Vec<String> = mark_splits_for_deletion(
                 tx,
                 index_id,
@@ -653,6 +792,7 @@ impl Metastore for PostgresqlMetastore {
         split_ids: &[&'a str],
     ) -> MetastoreResult<()> {
         run_with_tx!(self.connection_pool, tx, {
+            check_index_writable(tx, index_id).await?;
             let deletable_states = [
                 SplitState::Staged.as_str(),
                 SplitState::MarkedForDeletion.as_str(),
@@ -722,7 +862,391 @@ impl Metastore for PostgresqlMetastore {
     ) -> MetastoreResult<()> {
         run_with_tx!(self.connection_pool, tx, {
             mutate_index_metadata(tx, index_id, |index_metadata| {
-                index_metadata.checkpoint.reset_source(source_id);
+                index_metadata.reset_source_checkpoint(source_id);
+                Ok::<_, MetastoreError>(())
+            })
+            .await
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn create_delete_task(&self, delete_query: DeleteQuery) -> MetastoreResult<DeleteTask> {
+        run_with_tx!(self.connection_pool, tx, {
+            check_index_writable(tx, &delete_query.index_id).await?;
+            let delete_query_json = serde_json::to_string(&delete_query).map_err(|err| {
+                MetastoreError::InternalError {
+                    message: "Failed to serialize delete query.".to_string(),
+                    cause: err.to_string(),
+                }
+            })?;
+            let delete_task: postgresql_model::DeleteTask =
+                sqlx::query_as::<_, postgresql_model::DeleteTask>(
+                    r#"
+                INSERT INTO delete_tasks (index_id, delete_query_json)
+                VALUES ($1, $2)
+                RETURNING *
+            "#,
+                )
+                .bind(&delete_query.index_id)
+                .bind(&delete_query_json)
+                .fetch_one(tx)
+                .await
+                .map_err(|err| convert_sqlx_err(&delete_query.index_id, err))?;
+            delete_task.try_into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn last_delete_opstamp(&self, index_id: &str) -> MetastoreResult<u64> {
+        run_with_tx!(self.connection_pool, tx, {
+            index_metadata(tx, index_id).await?;
+            let last_delete_opstamp: i64 = sqlx::query(
+                r#"
+                SELECT COALESCE(MAX(opstamp), 0)
+                FROM delete_tasks
+                WHERE index_id = $1
+            "#,
+            )
+            .bind(index_id)
+            .map(|row| row.get(0))
+            .fetch_one(tx)
+            .await?;
+            Ok(last_delete_opstamp as u64)
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn list_delete_tasks(
+        &self,
+        index_id: &str,
+        opstamp_start: u64,
+    ) -> MetastoreResult<Vec<DeleteTask>> {
+        run_with_tx!(self.connection_pool, tx, {
+            index_metadata(tx, index_id).await?;
+            let delete_tasks = sqlx::query_as::<_, postgresql_model::DeleteTask>(
+                r#"
+                SELECT *
+                FROM delete_tasks
+                WHERE
+                        index_id = $1
+                    AND opstamp > $2
+                ORDER BY opstamp ASC
+            "#,
+            )
+            .bind(index_id)
+            .bind(opstamp_start as i64)
+            .fetch_all(tx)
+            .await?;
+            delete_tasks
+                .into_iter()
+                .map(|delete_task| delete_task.try_into())
+                .collect()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn list_splits_for_delete_task(
+        &self,
+        index_id: &str,
+        delete_opstamp_threshold: u64,
+        producer_opt: Option<SplitProducer>,
+        limit: usize,
+    ) -> MetastoreResult<Vec<Split>> {
+        run_with_tx!(self.connection_pool, tx, {
+            index_metadata(tx, index_id).await?;
+            // The delete opstamp and the producer are only stored in the serialized split
+            // metadata. The delete opstamp is omitted when it is equal to 0, and the node ID and
+            // the pipeline ordinal are serialized together as `{node_id}/{pipeline_ord}`.
+            let splits = sqlx::query_as::<_, postgresql_model::Split>(
+                r#"
+                SELECT splits.*
+                FROM splits
+                WHERE
+                        splits.index_id = $1
+                    AND splits.split_state = $2
+                    AND COALESCE((splits.split_metadata_json::jsonb->>'delete_opstamp')::BIGINT, 0) < $3
+                    AND (
+                           $4::TEXT IS NULL
+                        OR (
+                                splits.split_metadata_json::jsonb->>'source_id' = $4
+                            AND regexp_replace(splits.split_metadata_json::jsonb->>'node_id', '/[^/]*$', '') = $5
+                            AND substring(splits.split_metadata_json::jsonb->>'node_id' from '[^/]*$') = $6
+                        )
+                    )
+                    AND EXISTS (
+                        SELECT 1
+                        FROM delete_tasks
+                        WHERE
+                                delete_tasks.index_id = splits.index_id
+                            AND delete_tasks.opstamp > COALESCE((splits.split_metadata_json::jsonb->>'delete_opstamp')::BIGINT, 0)
+                            AND delete_tasks.opstamp <= $3
+                            AND (
+                                   splits.time_range_end IS NULL
+                                OR delete_tasks.delete_query_json::jsonb->>'start_timestamp' IS NULL
+                                OR splits.time_range_end >= (delete_tasks.delete_query_json::jsonb->>'start_timestamp')::BIGINT
+                            )
+                            AND (
+                                   splits.time_range_start IS NULL
+                                OR delete_tasks.delete_query_json::jsonb->>'end_timestamp' IS NULL
+                                OR splits.time_range_start < (delete_tasks.delete_query_json::jsonb->>'end_timestamp')::BIGINT
+                            )
+                    )
+                ORDER BY
+                    COALESCE((splits.split_metadata_json::jsonb->>'delete_opstamp')::BIGINT, 0) ASC,
+                    splits.update_timestamp ASC,
+                    splits.split_id ASC
+                LIMIT $7
+            "#,
+            )
+            .bind(index_id)
+            .bind(SplitState::Published.as_str())
+            .bind(delete_opstamp_threshold as i64)
+            .bind(producer_opt.as_ref().map(|producer| producer.source_id.as_str()))
+            .bind(producer_opt.as_ref().map(|producer| producer.node_id.as_str()))
+            .bind(
+                producer_opt
+                    .as_ref()
+                    .map(|producer| producer.pipeline_ord.to_string()),
+            )
+            .bind(limit as i64)
+            .fetch_all(tx)
+            .await?;
+            splits.into_iter().map(|split| split.try_into()).collect()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn update_splits_delete_opstamp<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+        delete_opstamp: u64,
+    ) -> MetastoreResult<()> {
+        run_with_tx!(self.connection_pool, tx, {
+            if split_ids.is_empty() {
+                return Ok(());
+            }
+            check_index_writable(tx, index_id).await?;
+            // The delete opstamp is only stored in the serialized split metadata.
+            let updated_split_ids: Vec<String> = sqlx::query(
+                r#"
+                UPDATE splits
+                SET split_metadata_json = (
+                    split_metadata_json::jsonb || jsonb_build_object('delete_opstamp', $1::BIGINT)
+                )::TEXT
+                WHERE
+                        index_id = $2
+                    AND split_id = ANY($3)
+                RETURNING split_id
+            "#,
+            )
+            .bind(delete_opstamp as i64)
+            .bind(index_id)
+            .bind(split_ids)
+            .map(|row| row.get(0))
+            .fetch_all(&mut *tx)
+            .await?;
+
+            if updated_split_ids.len() == split_ids.len() {
+                return Ok(());
+            }
+            if index_opt(tx, index_id).await?.is_none() {
+                return Err(MetastoreError::IndexDoesNotExist {
+                    index_id: index_id.to_string(),
+                });
+            }
+            let split_not_found_ids = split_ids
+                .iter()
+                .filter(|split_id| {
+                    updated_split_ids
+                        .iter()
+                        .all(|updated_split_id| updated_split_id != *split_id)
+                })
+                .map(|split_id| split_id.to_string())
+                .collect();
+            Err(MetastoreError::SplitsDoNotExist {
+                split_ids: split_not_found_ids,
+            })
+        })
+    }
+
+    #[instrument(skip(self, splits_tags))]
+    async fn amend_splits_tags(
+        &self,
+        index_id: &str,
+        splits_tags: BTreeMap<String, BTreeSet<String>>,
+        reason: &str,
+    ) -> MetastoreResult<()> {
+        run_with_tx!(self.connection_pool, tx, {
+            if splits_tags.is_empty() {
+                return Ok(());
+            }
+            check_index_writable(tx, index_id).await?;
+            let split_ids: Vec<&str> = splits_tags.keys().map(String::as_str).collect();
+            let splits: Vec<Split> = sqlx::query_as::<_, postgresql_model::Split>(
+                r#"
+                SELECT *
+                FROM splits
+                WHERE
+                        index_id = $1
+                    AND split_id = ANY($2)
+                FOR UPDATE
+            "#,
+            )
+            .bind(index_id)
+            .bind(&split_ids)
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|split| split.try_into())
+            .collect::<MetastoreResult<_>>()?;
+
+            if splits.len() != split_ids.len() {
+                if index_opt(tx, index_id).await?.is_none() {
+                    return Err(MetastoreError::IndexDoesNotExist {
+                        index_id: index_id.to_string(),
+                    });
+                }
+                let split_not_found_ids = split_ids
+                    .iter()
+                    .filter(|split_id| splits.iter().all(|split| split.split_id() != **split_id))
+                    .map(|split_id| split_id.to_string())
+                    .collect();
+                return Err(MetastoreError::SplitsDoNotExist {
+                    split_ids: split_not_found_ids,
+                });
+            }
+            let split_not_published_ids: Vec<String> = splits
+                .iter()
+                .filter(|split| split.split_state != SplitState::Published)
+                .map(|split| split.split_id().to_string())
+                .collect();
+            if !split_not_published_ids.is_empty() {
+                return Err(MetastoreError::SplitsNotPublished {
+                    split_ids: split_not_published_ids,
+                });
+            }
+            for split in splits {
+                let mut split_metadata = split.split_metadata;
+                let tags = splits_tags
+                    .get(split_metadata.split_id())
+                    .cloned()
+                    .expect("The split should have tags.");
+                if !split_metadata.amend_tags(tags, reason) {
+                    continue;
+                }
+                let tags: Vec<String> = split_metadata.tags.iter().cloned().collect();
+                let split_metadata_json =
+                    serde_json::to_string(&split_metadata).map_err(|err| {
+                        MetastoreError::InternalError {
+                            message: "Failed to serialize split metadata.".to_string(),
+                            cause: err.to_string(),
+                        }
+                    })?;
+                sqlx::query(
+                    r#"
+                    UPDATE splits
+                    SET
+                        tags = $1,
+                        split_metadata_json = $2
+                    WHERE
+                            index_id = $3
+                        AND split_id = $4
+                "#,
+                )
+                .bind(tags)
+                .bind(split_metadata_json)
+                .bind(index_id)
+                .bind(split_metadata.split_id())
+                .execute(&mut *tx)
+                .await?;
+            }
+            Ok(())
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn set_index_maintenance_mode(
+        &self,
+        index_id: &str,
+        enabled: bool,
+    ) -> MetastoreResult<()> {
+        run_with_tx!(self.connection_pool, tx, {
+            mutate_index_metadata(tx, index_id, |index_metadata| {
+                index_metadata.maintenance_mode = enabled;
+                Ok::<_, MetastoreError>(())
+            })
+            .await
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn set_index_feature_flag(
+        &self,
+        index_id: &str,
+        flag: &str,
+        enabled: bool,
+    ) -> MetastoreResult<()> {
+        run_with_tx!(self.connection_pool, tx, {
+            mutate_index_metadata(tx, index_id, |index_metadata| {
+                index_metadata.set_feature_flag(flag, enabled);
+                Ok::<_, MetastoreError>(())
+            })
+            .await
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn acquire_merge_planner_lease(
+        &self,
+        index_id: &str,
+        owner: &str,
+        lease_duration_secs: u64,
+    ) -> MetastoreResult<bool> {
+        run_with_tx!(self.connection_pool, tx, {
+            // Concurrent candidates are serialized by the index lock of `mutate_index_metadata`.
+            let now_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+            let expire_timestamp = now_timestamp + lease_duration_secs as i64;
+            let mut is_lease_holder = false;
+            mutate_index_metadata(tx, index_id, |index_metadata| {
+                is_lease_holder = index_metadata.acquire_merge_planner_lease(
+                    owner,
+                    now_timestamp,
+                    expire_timestamp,
+                );
+                Ok::<_, MetastoreError>(())
+            })
+            .await?;
+            Ok(is_lease_holder)
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn quarantine_source_file(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        quarantined_file: QuarantinedFile,
+    ) -> MetastoreResult<()> {
+        run_with_tx!(self.connection_pool, tx, {
+            mutate_index_metadata(tx, index_id, |index_metadata| {
+                index_metadata.quarantine_source_file(source_id, quarantined_file);
+                Ok::<_, MetastoreError>(())
+            })
+            .await
+        })
+    }
+
+    #[instrument(skip(self, doc_mapping, search_settings))]
+    async fn update_index(
+        &self,
+        index_id: &str,
+        doc_mapping: DocMapping,
+        search_settings: SearchSettings,
+    ) -> MetastoreResult<()> {
+        run_with_tx!(self.connection_pool, tx, {
+            mutate_index_metadata(tx, index_id, |index_metadata| {
+                index_metadata.update_doc_mapping(doc_mapping, search_settings);
                 Ok::<_, MetastoreError>(())
             })
             .await
@@ -755,8 +1279,32 @@ fn generate_dollar_guard(s: &str) -> String {
     }
 }
 
+/// Quotes a string as a dollar-quoted SQL literal.
+fn quote_string_literal(value: &str) -> String {
+    let dollar_guard = generate_dollar_guard(value);
+    format!("${dollar_guard}${value}${dollar_guard}$")
+}
+
+/// Returns the `LIKE` pattern matching the IDs of the indexes of `namespace`. The `_` and `%`
+/// wildcards and the `\` escape character are escaped since they may appear in a namespace.
+fn namespace_like_pattern(namespace: &str) -> String {
+    let mut pattern = String::with_capacity(namespace.len() + 2);
+    for ch in namespace.chars() {
+        if matches!(ch, '_' | '%' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(ch);
+    }
+    pattern.push(INDEX_NAMESPACE_SEPARATOR);
+    pattern.push('%');
+    pattern
+}
+
 /// Takes a tag filters AST and returns a sql expression that can be used as
 /// a filter.
+///
+/// Tags are matched with the array containment operator `@>` rather than `= ANY(tags)` so
+/// that the GIN index on the `tags` column can be used.
 fn tags_filter_expression_helper(tags: TagFilterAst) -> String {
     match tags {
         TagFilterAst::And(child_asts) => {
@@ -782,9 +1330,9 @@ fn tags_filter_expression_helper(tags: TagFilterAst) -> String {
         TagFilterAst::Tag { is_present, tag } => {
             let dollar_guard = generate_dollar_guard(&tag);
             if is_present {
-                format!("${dollar_guard}${tag}${dollar_guard}$ = ANY(tags)")
+                format!("tags @> ARRAY[${dollar_guard}${tag}${dollar_guard}$]")
             } else {
-                format!("NOT (${dollar_guard}${tag}${dollar_guard}$ = ANY(tags))")
+                format!("NOT (tags @> ARRAY[${dollar_guard}${tag}${dollar_guard}$])")
             }
         }
     }
@@ -869,7 +1417,7 @@ metastore_test_suite!(crate::PostgresqlMetastore);
 mod tests {
     use quickwit_doc_mapper::tag_pruning::{no_tag, tag, TagFilterAst};
 
-    use super::tags_filter_expression_helper;
+    use super::{namespace_like_pattern, tags_filter_expression_helper};
 
     fn test_tags_filter_expression_helper(tags_ast: TagFilterAst, expected: &str) {
         assert_eq!(tags_filter_expression_helper(tags_ast), expected);
@@ -878,14 +1426,14 @@ mod tests {
     #[test]
     fn test_tags_filter_expression_single_tag() {
         let tags_ast = tag("my_field:titi");
-        test_tags_filter_expression_helper(tags_ast, r#"$$my_field:titi$$ = ANY(tags)"#);
+        test_tags_filter_expression_helper(tags_ast, r#"tags @> ARRAY[$$my_field:titi$$]"#);
     }
 
     #[test]
     fn test_tags_filter_expression_not_tag() {
         test_tags_filter_expression_helper(
             no_tag("my_field:titi"),
-            r#"NOT ($$my_field:titi$$ = ANY(tags))"#,
+            r#"NOT (tags @> ARRAY[$$my_field:titi$$])"#,
         );
     }
 
@@ -894,7 +1442,8 @@ mod tests {
         let tags_ast = TagFilterAst::And(vec![tag("tag:val1"), tag("tag:val2"), tag("tag:val3")]);
         test_tags_filter_expression_helper(
             tags_ast,
-            "($$tag:val1$$ = ANY(tags) AND $$tag:val2$$ = ANY(tags) AND $$tag:val3$$ = ANY(tags))",
+            "(tags @> ARRAY[$$tag:val1$$] AND tags @> ARRAY[$$tag:val2$$] AND tags @> \
+             ARRAY[$$tag:val3$$])",
         );
     }
 
@@ -906,7 +1455,8 @@ mod tests {
         ]);
         test_tags_filter_expression_helper(
             tags_ast,
-            "(($$tag:val1$$ = ANY(tags) AND $$tag:val2$$ = ANY(tags)) OR $$tag:val3$$ = ANY(tags))",
+            "((tags @> ARRAY[$$tag:val1$$] AND tags @> ARRAY[$$tag:val2$$]) OR tags @> \
+             ARRAY[$$tag:val3$$])",
         );
     }
 
@@ -918,7 +1468,7 @@ mod tests {
         ]);
         test_tags_filter_expression_helper(
             tags_ast,
-            r#"(($$tag:val1$$ = ANY(tags) OR $$tag:val2$$ = ANY(tags)) AND $$tag:val3$$ = ANY(tags))"#,
+            r#"((tags @> ARRAY[$$tag:val1$$] OR tags @> ARRAY[$$tag:val2$$]) AND tags @> ARRAY[$$tag:val3$$])"#,
         );
     }
 
@@ -927,7 +1477,14 @@ mod tests {
         let tags_ast = tag("tag:$$;DELETE FROM something_evil");
         test_tags_filter_expression_helper(
             tags_ast,
-            "$Quickwit!$tag:$$;DELETE FROM something_evil$Quickwit!$ = ANY(tags)",
+            "tags @> ARRAY[$Quickwit!$tag:$$;DELETE FROM something_evil$Quickwit!$]",
         );
     }
+
+    #[test]
+    fn test_namespace_like_pattern() {
+        assert_eq!(namespace_like_pattern("team-a"), "team-a/%");
+        assert_eq!(namespace_like_pattern("team_a/prod"), r"team\_a/prod/%");
+        assert_eq!(namespace_like_pattern(r"100%\"), r"100\%\\/%");
+    }
 }
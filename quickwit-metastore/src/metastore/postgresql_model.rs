@@ -23,8 +23,8 @@ use std::str::FromStr;
 use tracing::error;
 
 use crate::{
-    IndexMetadata, MetastoreError, MetastoreResult, Split as QuickwitSplit, SplitMetadata,
-    SplitState,
+    DeleteQuery, DeleteTask as QuickwitDeleteTask, IndexMetadata, MetastoreError, MetastoreResult,
+    Split as QuickwitSplit, SplitMetadata, SplitState,
 };
 
 #[derive(sqlx::FromRow, Debug)]
@@ -143,3 +143,38 @@ impl TryInto<QuickwitSplit> for Split {
         })
     }
 }
+
+/// A model structure for handling delete tasks in a database.
+#[derive(sqlx::FromRow)]
+pub struct DeleteTask {
+    /// Operation stamp of the delete task.
+    pub opstamp: i64,
+    /// Timestamp for tracking when the delete task was created.
+    pub create_timestamp: sqlx::types::time::PrimitiveDateTime,
+    /// Index ID. It is used as a foreign key in the database.
+    pub index_id: String,
+    // The delete query serialized as a JSON string.
+    pub delete_query_json: String,
+}
+
+impl TryInto<QuickwitDeleteTask> for DeleteTask {
+    type Error = MetastoreError;
+
+    fn try_into(self) -> Result<QuickwitDeleteTask, Self::Error> {
+        let delete_query =
+            serde_json::from_str::<DeleteQuery>(&self.delete_query_json).map_err(|err| {
+                MetastoreError::InternalError {
+                    message: format!(
+                        "Failed to deserialize delete query. index_id=`{}`, opstamp=`{}`.",
+                        self.index_id, self.opstamp
+                    ),
+                    cause: err.to_string(),
+                }
+            })?;
+        Ok(QuickwitDeleteTask {
+            create_timestamp: self.create_timestamp.assume_utc().unix_timestamp(),
+            opstamp: self.opstamp as u64,
+            delete_query,
+        })
+    }
+}
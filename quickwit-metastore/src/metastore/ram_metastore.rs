@@ -0,0 +1,725 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use quickwit_common::uri::Uri;
+use quickwit_config::{is_index_in_namespace, DocMapping, SearchSettings, SourceConfig};
+use quickwit_doc_mapper::tag_pruning::TagFilterAst;
+use quickwit_storage::{quickwit_storage_uri_resolver, Storage, StorageUriResolver};
+use tokio::sync::{Mutex, RwLock};
+use tracing::{error, info};
+
+use crate::checkpoint::IndexCheckpointDelta;
+use crate::metastore::file_backed_metastore::file_backed_index::FileBackedIndex;
+use crate::metastore::file_backed_metastore::store_operations::{
+    delete_index, fetch_alive_indexes, put_alive_indexes_states, put_index,
+};
+use crate::{
+    DeleteQuery, DeleteTask, IndexMetadata, ListSplitsQuery, Metastore, MetastoreError,
+    MetastoreFactory, MetastoreResolverError, MetastoreResult, QuarantinedFile, Split,
+    SplitMetadata, SplitProducer, SplitState,
+};
+
+/// Default interval between two snapshots of a [`RamMetastore`].
+const DEFAULT_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// State of the last snapshot of a [`RamMetastore`].
+#[derive(Default)]
+struct SnapshotState {
+    /// Version of the metastore at the time of the last snapshot.
+    version: u64,
+    /// Indexes saved in the last snapshot.
+    index_ids: HashSet<String>,
+}
+
+/// Metastore that holds all of the metadata in memory, for embedded deployments where neither a
+/// file-backed metastore nor PostgreSQL are convenient.
+///
+/// The metastore can be saved to and restored from a storage. A snapshot has the layout of the
+/// file-backed metastore, so it can also be opened with a [`crate::FileBackedMetastore`].
+pub struct RamMetastore {
+    uri: Uri,
+    indexes: RwLock<HashMap<String, FileBackedIndex>>,
+    /// Incremented on every mutation, so that snapshots are skipped when nothing changed.
+    version: AtomicU64,
+    snapshot_storage_opt: Option<Arc<dyn Storage>>,
+    snapshot_state: Mutex<SnapshotState>,
+}
+
+impl RamMetastore {
+    /// Creates an empty [`RamMetastore`].
+    pub fn new(uri: Uri) -> Self {
+        Self {
+            uri,
+            indexes: Default::default(),
+            version: AtomicU64::new(0),
+            snapshot_storage_opt: None,
+            snapshot_state: Default::default(),
+        }
+    }
+
+    /// Creates a [`RamMetastore`] restored from the last snapshot saved on `snapshot_storage`,
+    /// if any. The subsequent snapshots are saved on the same storage.
+    pub async fn restore(uri: Uri, snapshot_storage: Arc<dyn Storage>) -> MetastoreResult<Self> {
+        let indexes: HashMap<String, FileBackedIndex> = fetch_alive_indexes(&*snapshot_storage)
+            .await?
+            .into_iter()
+            .map(|index| (index.index_id().to_string(), index))
+            .collect();
+        info!(
+            num_indexes = indexes.len(),
+            snapshot_uri = %snapshot_storage.uri(),
+            "restore-metastore"
+        );
+        let snapshot_state = SnapshotState {
+            version: 0,
+            index_ids: indexes.keys().cloned().collect(),
+        };
+        Ok(Self {
+            uri,
+            indexes: RwLock::new(indexes),
+            version: AtomicU64::new(0),
+            snapshot_storage_opt: Some(snapshot_storage),
+            snapshot_state: Mutex::new(snapshot_state),
+        })
+    }
+
+    /// Saves the indexes of the metastore on the snapshot storage. This is a no-op if the
+    /// metastore has no snapshot storage or has not changed since the last snapshot.
+    pub async fn snapshot(&self) -> MetastoreResult<()> {
+        let snapshot_storage = match &self.snapshot_storage_opt {
+            Some(snapshot_storage) => snapshot_storage,
+            None => return Ok(()),
+        };
+        let mut snapshot_state = self.snapshot_state.lock().await;
+        let (version, indexes) = {
+            let indexes_rlock = self.indexes.read().await;
+            let indexes: Vec<FileBackedIndex> = indexes_rlock.values().cloned().collect();
+            (self.version.load(Ordering::SeqCst), indexes)
+        };
+        if version == snapshot_state.version {
+            return Ok(());
+        }
+        for index in &indexes {
+            put_index(&**snapshot_storage, index).await?;
+        }
+        let index_ids: HashSet<String> = indexes
+            .iter()
+            .map(|index| index.index_id().to_string())
+            .collect();
+        put_alive_indexes_states(&**snapshot_storage, index_ids.iter()).await?;
+        // The indexes deleted since the last snapshot are removed once the indexes states file
+        // no longer lists them.
+        for deleted_index_id in snapshot_state.index_ids.difference(&index_ids) {
+            match delete_index(&**snapshot_storage, deleted_index_id).await {
+                Ok(()) | Err(MetastoreError::IndexDoesNotExist { .. }) => {}
+                Err(error) => return Err(error),
+            }
+        }
+        snapshot_state.version = version;
+        snapshot_state.index_ids = index_ids;
+        Ok(())
+    }
+
+    async fn mutate(
+        &self,
+        index_id: &str,
+        mutation: impl FnOnce(&mut FileBackedIndex) -> MetastoreResult<bool>,
+    ) -> MetastoreResult<()> {
+        let mut indexes_wlock = self.indexes.write().await;
+        let index =
+            indexes_wlock
+                .get_mut(index_id)
+                .ok_or_else(|| MetastoreError::IndexDoesNotExist {
+                    index_id: index_id.to_string(),
+                })?;
+        // The mutation is applied to a copy so that the index is left untouched on error.
+        let mut mutated_index = index.clone();
+        let has_changed = mutation(&mut mutated_index)?;
+        if has_changed {
+            *index = mutated_index;
+            self.version.fetch_add(1, Ordering::SeqCst);
+        }
+        Ok(())
+    }
+
+    async fn read<T, F>(&self, index_id: &str, view: F) -> MetastoreResult<T>
+    where F: FnOnce(&FileBackedIndex) -> MetastoreResult<T> {
+        let indexes_rlock = self.indexes.read().await;
+        let index =
+            indexes_rlock
+                .get(index_id)
+                .ok_or_else(|| MetastoreError::IndexDoesNotExist {
+                    index_id: index_id.to_string(),
+                })?;
+        view(index)
+    }
+}
+
+#[async_trait]
+impl Metastore for RamMetastore {
+    /// -------------------------------------------------------------------------------
+    /// Mutations over the high-level index.
+    async fn create_index(&self, index_metadata: IndexMetadata) -> MetastoreResult<()> {
+        let mut indexes_wlock = self.indexes.write().await;
+        if indexes_wlock.contains_key(&index_metadata.index_id) {
+            return Err(MetastoreError::IndexAlreadyExists {
+                index_id: index_metadata.index_id,
+            });
+        }
+        indexes_wlock.insert(
+            index_metadata.index_id.clone(),
+            FileBackedIndex::from(index_metadata),
+        );
+        self.version.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn delete_index(&self, index_id: &str) -> MetastoreResult<()> {
+        let mut indexes_wlock = self.indexes.write().await;
+        if indexes_wlock.remove(index_id).is_none() {
+            return Err(MetastoreError::IndexDoesNotExist {
+                index_id: index_id.to_string(),
+            });
+        }
+        self.version.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// -------------------------------------------------------------------------------
+    /// Mutations over a single index
+
+    async fn stage_split(
+        &self,
+        index_id: &str,
+        split_metadata: SplitMetadata,
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| {
+            index.stage_split(split_metadata)?;
+            Ok(true)
+        })
+        .await
+    }
+
+    async fn publish_splits<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+        replaced_split_ids: &[&'a str],
+        checkpoint_delta_opt: Option<IndexCheckpointDelta>,
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| {
+            index.publish_splits(split_ids, replaced_split_ids, checkpoint_delta_opt)?;
+            Ok(true)
+        })
+        .await
+    }
+
+    async fn mark_splits_for_deletion<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| {
+            index.mark_splits_for_deletion(
+                split_ids,
+                &[
+                    SplitState::Staged,
+                    SplitState::Published,
+                    SplitState::MarkedForDeletion,
+                ],
+            )
+        })
+        .await
+    }
+
+    async fn delete_splits<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| {
+            index.delete_splits(split_ids)?;
+            Ok(true)
+        })
+        .await
+    }
+
+    async fn add_source(&self, index_id: &str, source: SourceConfig) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| index.add_source(source))
+            .await
+    }
+
+    async fn delete_source(&self, index_id: &str, source_id: &str) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| index.delete_source(source_id))
+            .await
+    }
+
+    async fn reset_source_checkpoint(
+        &self,
+        index_id: &str,
+        source_id: &str,
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| index.reset_source_checkpoint(source_id))
+            .await
+    }
+
+    async fn create_delete_task(&self, delete_query: DeleteQuery) -> MetastoreResult<DeleteTask> {
+        let index_id = delete_query.index_id.clone();
+        let mut delete_task_opt = None;
+        self.mutate(&index_id, |index| {
+            delete_task_opt = Some(index.create_delete_task(delete_query)?);
+            Ok(true)
+        })
+        .await?;
+        Ok(delete_task_opt.expect("The delete task should have been created."))
+    }
+
+    async fn update_splits_delete_opstamp<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+        delete_opstamp: u64,
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| {
+            index.update_splits_delete_opstamp(split_ids, delete_opstamp)
+        })
+        .await
+    }
+
+    async fn amend_splits_tags(
+        &self,
+        index_id: &str,
+        splits_tags: BTreeMap<String, BTreeSet<String>>,
+        reason: &str,
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| {
+            index.amend_splits_tags(splits_tags, reason)
+        })
+        .await
+    }
+
+    async fn set_index_maintenance_mode(
+        &self,
+        index_id: &str,
+        enabled: bool,
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| Ok(index.set_maintenance_mode(enabled)))
+            .await
+    }
+
+    async fn set_index_feature_flag(
+        &self,
+        index_id: &str,
+        flag: &str,
+        enabled: bool,
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| Ok(index.set_feature_flag(flag, enabled)))
+            .await
+    }
+
+    async fn acquire_merge_planner_lease(
+        &self,
+        index_id: &str,
+        owner: &str,
+        lease_duration_secs: u64,
+    ) -> MetastoreResult<bool> {
+        let mut is_lease_holder = false;
+        self.mutate(index_id, |index| {
+            is_lease_holder = index.acquire_merge_planner_lease(owner, lease_duration_secs);
+            Ok(is_lease_holder)
+        })
+        .await?;
+        Ok(is_lease_holder)
+    }
+
+    async fn quarantine_source_file(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        quarantined_file: QuarantinedFile,
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| {
+            Ok(index.quarantine_source_file(source_id, quarantined_file))
+        })
+        .await
+    }
+
+    async fn update_index(
+        &self,
+        index_id: &str,
+        doc_mapping: DocMapping,
+        search_settings: SearchSettings,
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| {
+            Ok(index.update_doc_mapping(doc_mapping, search_settings))
+        })
+        .await
+    }
+
+    /// -------------------------------------------------------------------------------
+    /// Read-only accessors
+
+    async fn list_splits(
+        &self,
+        index_id: &str,
+        state: SplitState,
+        time_range_opt: Option<Range<i64>>,
+        tags: Option<TagFilterAst>,
+    ) -> MetastoreResult<Vec<Split>> {
+        self.read(index_id, |index| {
+            index.list_splits(state, time_range_opt, tags)
+        })
+        .await
+    }
+
+    async fn list_splits_page(
+        &self,
+        index_id: &str,
+        query: ListSplitsQuery,
+    ) -> MetastoreResult<Vec<Split>> {
+        self.read(index_id, |index| index.list_splits_page(&query))
+            .await
+    }
+
+    async fn list_all_splits(&self, index_id: &str) -> MetastoreResult<Vec<Split>> {
+        self.read(index_id, |index| index.list_all_splits()).await
+    }
+
+    async fn last_delete_opstamp(&self, index_id: &str) -> MetastoreResult<u64> {
+        self.read(index_id, |index| Ok(index.last_delete_opstamp()))
+            .await
+    }
+
+    async fn list_delete_tasks(
+        &self,
+        index_id: &str,
+        opstamp_start: u64,
+    ) -> MetastoreResult<Vec<DeleteTask>> {
+        self.read(index_id, |index| Ok(index.list_delete_tasks(opstamp_start)))
+            .await
+    }
+
+    async fn list_splits_for_delete_task(
+        &self,
+        index_id: &str,
+        delete_opstamp_threshold: u64,
+        producer_opt: Option<SplitProducer>,
+        limit: usize,
+    ) -> MetastoreResult<Vec<Split>> {
+        self.read(index_id, |index| {
+            Ok(index.list_splits_for_delete_task(
+                delete_opstamp_threshold,
+                producer_opt.as_ref(),
+                limit,
+            ))
+        })
+        .await
+    }
+
+    async fn index_metadata(&self, index_id: &str) -> MetastoreResult<IndexMetadata> {
+        self.read(index_id, |index| Ok(index.metadata().clone()))
+            .await
+    }
+
+    async fn list_indexes_metadatas(&self) -> MetastoreResult<Vec<IndexMetadata>> {
+        let indexes_rlock = self.indexes.read().await;
+        Ok(indexes_rlock
+            .values()
+            .map(|index| index.metadata().clone())
+            .collect())
+    }
+
+    async fn list_indexes_metadatas_in_namespace(
+        &self,
+        namespace: &str,
+    ) -> MetastoreResult<Vec<IndexMetadata>> {
+        let indexes_rlock = self.indexes.read().await;
+        Ok(indexes_rlock
+            .iter()
+            .filter(|(index_id, _)| is_index_in_namespace(index_id, namespace))
+            .map(|(_, index)| index.metadata().clone())
+            .collect())
+    }
+
+    fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    async fn check_connectivity(&self) -> anyhow::Result<()> {
+        if let Some(snapshot_storage) = &self.snapshot_storage_opt {
+            snapshot_storage.check_connectivity().await?;
+        }
+        Ok(())
+    }
+}
+
+/// Snapshot parameters passed in the fragment of a `ram://` metastore URI:
+/// `ram:///metastore#snapshot_uri=s3://bucket/metastore&snapshot_interval=60s`.
+#[derive(Debug, PartialEq)]
+struct SnapshotParams {
+    snapshot_uri: Uri,
+    snapshot_interval: Duration,
+}
+
+fn extract_snapshot_params_from_uri(
+    uri: &str,
+) -> Result<(String, Option<SnapshotParams>), MetastoreResolverError> {
+    let (uri_without_fragment, fragment) = match uri.split_once('#') {
+        Some((uri_without_fragment, fragment)) => (uri_without_fragment, fragment),
+        None => return Ok((uri.to_string(), None)),
+    };
+    let mut snapshot_uri_opt = None;
+    let mut snapshot_interval_opt = None;
+    for param in fragment.split('&') {
+        let invalid_param_error = || {
+            MetastoreResolverError::InvalidUri(format!(
+                "Invalid RAM metastore URI parameter `{}`. Expected `snapshot_uri=<uri>` or \
+                 `snapshot_interval=<secs>s`.",
+                param
+            ))
+        };
+        match param.split_once('=') {
+            Some(("snapshot_uri", snapshot_uri)) => {
+                let snapshot_uri = Uri::try_new(snapshot_uri)
+                    .map_err(|error| MetastoreResolverError::InvalidUri(error.to_string()))?;
+                snapshot_uri_opt = Some(snapshot_uri);
+            }
+            Some(("snapshot_interval", snapshot_interval)) => {
+                let snapshot_interval_secs = snapshot_interval
+                    .strip_suffix('s')
+                    .and_then(|secs| secs.parse::<u64>().ok())
+                    .filter(|secs| *secs > 0)
+                    .ok_or_else(invalid_param_error)?;
+                snapshot_interval_opt = Some(Duration::from_secs(snapshot_interval_secs));
+            }
+            _ => return Err(invalid_param_error()),
+        }
+    }
+    let snapshot_uri = snapshot_uri_opt.ok_or_else(|| {
+        MetastoreResolverError::InvalidUri(format!(
+            "RAM metastore URI `{}` sets snapshot parameters without `snapshot_uri`.",
+            uri
+        ))
+    })?;
+    let snapshot_params = SnapshotParams {
+        snapshot_uri,
+        snapshot_interval: snapshot_interval_opt.unwrap_or(DEFAULT_SNAPSHOT_INTERVAL),
+    };
+    Ok((uri_without_fragment.to_string(), Some(snapshot_params)))
+}
+
+fn spawn_snapshot_task(metastore_weak: Weak<RamMetastore>, snapshot_interval: Duration) {
+    tokio::task::spawn(async move {
+        let mut interval = tokio::time::interval(snapshot_interval);
+        interval.tick().await; //< this is to prevent a snapshot right after the restore.
+        loop {
+            interval.tick().await;
+            let metastore = match metastore_weak.upgrade() {
+                Some(metastore) => metastore,
+                None => break,
+            };
+            if let Err(snapshot_error) = metastore.snapshot().await {
+                error!(error=?snapshot_error, "snapshot-metastore-error");
+            }
+        }
+    });
+}
+
+/// A RAM metastore factory.
+///
+/// RAM metastores live as long as the process: the factory keeps the metastore of each URI so
+/// that resolving the same URI twice returns the same metastore.
+#[derive(Clone)]
+pub struct RamMetastoreFactory {
+    storage_uri_resolver: StorageUriResolver,
+    cache: Arc<Mutex<HashMap<Uri, Arc<RamMetastore>>>>,
+}
+
+impl Default for RamMetastoreFactory {
+    fn default() -> Self {
+        RamMetastoreFactory {
+            storage_uri_resolver: quickwit_storage_uri_resolver().clone(),
+            cache: Default::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl MetastoreFactory for RamMetastoreFactory {
+    async fn resolve(&self, uri: &Uri) -> Result<Arc<dyn Metastore>, MetastoreResolverError> {
+        let (uri_stripped, snapshot_params_opt) = extract_snapshot_params_from_uri(uri.as_str())?;
+        let uri = Uri::new(uri_stripped);
+        // The lock is held while restoring the metastore so that a single metastore is created
+        // per URI.
+        let mut cache_lock = self.cache.lock().await;
+        if let Some(metastore) = cache_lock.get(&uri) {
+            return Ok(metastore.clone());
+        }
+        let metastore = if let Some(snapshot_params) = snapshot_params_opt {
+            let snapshot_storage = self
+                .storage_uri_resolver
+                .resolve(&snapshot_params.snapshot_uri)
+                .map_err(|error| {
+                    MetastoreResolverError::FailedToOpenMetastore(MetastoreError::InternalError {
+                        message: format!(
+                            "Failed to open metastore snapshot storage `{}`.",
+                            snapshot_params.snapshot_uri
+                        ),
+                        cause: error.to_string(),
+                    })
+                })?;
+            let metastore = Arc::new(
+                RamMetastore::restore(uri.clone(), snapshot_storage)
+                    .await
+                    .map_err(MetastoreResolverError::FailedToOpenMetastore)?,
+            );
+            spawn_snapshot_task(
+                Arc::downgrade(&metastore),
+                snapshot_params.snapshot_interval,
+            );
+            metastore
+        } else {
+            Arc::new(RamMetastore::new(uri.clone()))
+        };
+        cache_lock.insert(uri, metastore.clone());
+        Ok(metastore)
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl crate::tests::test_suite::DefaultForTest for RamMetastore {
+    async fn default_for_test() -> Self {
+        RamMetastore::new(Uri::new("ram:///metastore".to_string()))
+    }
+}
+
+metastore_test_suite!(crate::RamMetastore);
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use quickwit_storage::RamStorage;
+
+    use super::*;
+
+    #[test]
+    fn test_extract_snapshot_params_from_uri() {
+        assert_eq!(
+            extract_snapshot_params_from_uri("ram:///metastore").unwrap(),
+            ("ram:///metastore".to_string(), None)
+        );
+        assert_eq!(
+            extract_snapshot_params_from_uri(
+                "ram:///metastore#snapshot_uri=s3://bucket/metastore&snapshot_interval=30s"
+            )
+            .unwrap(),
+            (
+                "ram:///metastore".to_string(),
+                Some(SnapshotParams {
+                    snapshot_uri: Uri::new("s3://bucket/metastore".to_string()),
+                    snapshot_interval: Duration::from_secs(30),
+                })
+            )
+        );
+        assert_eq!(
+            extract_snapshot_params_from_uri("ram:///metastore#snapshot_uri=ram:///snapshot")
+                .unwrap()
+                .1
+                .unwrap()
+                .snapshot_interval,
+            DEFAULT_SNAPSHOT_INTERVAL
+        );
+        assert!(
+            extract_snapshot_params_from_uri("ram:///metastore#snapshot_interval=30s").is_err()
+        );
+        assert!(extract_snapshot_params_from_uri(
+            "ram:///metastore#snapshot_uri=ram:///snapshot&snapshot_interval=0s"
+        )
+        .is_err());
+        assert!(extract_snapshot_params_from_uri("ram:///metastore#polling_interval=30s").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ram_metastore_snapshot_and_restore() {
+        let snapshot_storage: Arc<dyn Storage> = Arc::new(RamStorage::default());
+        let metastore_uri = Uri::new("ram:///metastore".to_string());
+        let metastore = RamMetastore::restore(metastore_uri.clone(), snapshot_storage.clone())
+            .await
+            .unwrap();
+        for index_id in ["test-index-1", "test-index-2"] {
+            let index_uri = format!("ram:///indexes/{index_id}");
+            let index_metadata = IndexMetadata::for_test(index_id, &index_uri);
+            metastore.create_index(index_metadata).await.unwrap();
+        }
+        let split_metadata = SplitMetadata {
+            split_id: "test-split".to_string(),
+            ..Default::default()
+        };
+        metastore
+            .stage_split("test-index-1", split_metadata)
+            .await
+            .unwrap();
+        metastore.snapshot().await.unwrap();
+        assert!(snapshot_storage
+            .exists(Path::new("test-index-2/metastore.json"))
+            .await
+            .unwrap());
+
+        metastore.delete_index("test-index-2").await.unwrap();
+        metastore.snapshot().await.unwrap();
+        assert!(!snapshot_storage
+            .exists(Path::new("test-index-2/metastore.json"))
+            .await
+            .unwrap());
+
+        let restored_metastore = RamMetastore::restore(metastore_uri, snapshot_storage)
+            .await
+            .unwrap();
+        let indexes_metadatas = restored_metastore.list_indexes_metadatas().await.unwrap();
+        assert_eq!(indexes_metadatas.len(), 1);
+        assert_eq!(indexes_metadatas[0].index_id, "test-index-1");
+        let splits = restored_metastore
+            .list_all_splits("test-index-1")
+            .await
+            .unwrap();
+        assert_eq!(splits.len(), 1);
+        assert_eq!(splits[0].split_metadata.split_id, "test-split");
+    }
+
+    #[tokio::test]
+    async fn test_ram_metastore_factory_returns_same_metastore() {
+        let factory = RamMetastoreFactory::default();
+        let metastore_uri = Uri::new("ram:///test-ram-metastore-factory".to_string());
+        let metastore = factory.resolve(&metastore_uri).await.unwrap();
+        let index_metadata = IndexMetadata::for_test("test-index", "ram:///indexes/test-index");
+        metastore.create_index(index_metadata).await.unwrap();
+
+        let same_metastore = factory.resolve(&metastore_uri).await.unwrap();
+        same_metastore.index_metadata("test-index").await.unwrap();
+    }
+}
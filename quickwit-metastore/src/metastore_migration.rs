@@ -0,0 +1,369 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+
+use itertools::Itertools;
+use tracing::info;
+
+use crate::{
+    DeleteTask, IndexMetadata, Metastore, MetastoreError, MetastoreResult, Split, SplitMetadata,
+    SplitState,
+};
+
+/// Index copied from a metastore to another by [`migrate_metastore`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MigratedIndex {
+    /// ID of the index.
+    pub index_id: String,
+    /// Number of splits copied.
+    pub num_splits: usize,
+    /// Number of delete tasks copied.
+    pub num_delete_tasks: usize,
+}
+
+/// Copies all the indexes of `source_metastore` to `target_metastore`, along with their sources,
+/// checkpoints, delete tasks, and splits, then verifies that the target metastore holds the same
+/// indexes and splits as the source metastore.
+///
+/// The target metastore must not hold any of the indexes of the source metastore. The delete
+/// tasks are assigned new opstamps by the target metastore: the delete opstamps of the splits are
+/// translated accordingly. The update timestamps of the indexes and splits, and the merge planner
+/// leases, are not preserved.
+///
+/// The indexes should not be written to while they are migrated.
+pub async fn migrate_metastore(
+    source_metastore: &dyn Metastore,
+    target_metastore: &dyn Metastore,
+) -> MetastoreResult<Vec<MigratedIndex>> {
+    let indexes_metadatas = source_metastore.list_indexes_metadatas().await?;
+    let target_index_ids: HashSet<String> = target_metastore
+        .list_indexes_metadatas()
+        .await?
+        .into_iter()
+        .map(|index_metadata| index_metadata.index_id)
+        .collect();
+    // Fails before copying anything rather than leaving a partially migrated metastore behind.
+    if let Some(index_metadata) = indexes_metadatas
+        .iter()
+        .find(|index_metadata| target_index_ids.contains(&index_metadata.index_id))
+    {
+        return Err(MetastoreError::IndexAlreadyExists {
+            index_id: index_metadata.index_id.clone(),
+        });
+    }
+    let mut migrated_indexes = Vec::with_capacity(indexes_metadatas.len());
+    for index_metadata in indexes_metadatas {
+        let migrated_index =
+            migrate_index(source_metastore, target_metastore, index_metadata).await?;
+        info!(
+            index_id = %migrated_index.index_id,
+            num_splits = migrated_index.num_splits,
+            num_delete_tasks = migrated_index.num_delete_tasks,
+            "migrate-index"
+        );
+        migrated_indexes.push(migrated_index);
+    }
+    Ok(migrated_indexes)
+}
+
+async fn migrate_index(
+    source_metastore: &dyn Metastore,
+    target_metastore: &dyn Metastore,
+    index_metadata: IndexMetadata,
+) -> MetastoreResult<MigratedIndex> {
+    let index_id = index_metadata.index_id.clone();
+    // The index is only put in maintenance mode once its splits are copied, since the metastore
+    // rejects the writes to an index in maintenance mode.
+    let mut target_index_metadata = index_metadata.clone();
+    target_index_metadata.maintenance_mode = false;
+    target_index_metadata.merge_planner_lease = None;
+    target_metastore.create_index(target_index_metadata).await?;
+
+    let delete_tasks = source_metastore.list_delete_tasks(&index_id, 0).await?;
+    let mut opstamp_mapping: Vec<(u64, u64)> = Vec::with_capacity(delete_tasks.len());
+    for delete_task in &delete_tasks {
+        let target_delete_task = target_metastore
+            .create_delete_task(delete_task.delete_query.clone())
+            .await?;
+        opstamp_mapping.push((delete_task.opstamp, target_delete_task.opstamp));
+    }
+
+    let splits: Vec<Split> = source_metastore
+        .list_all_splits(&index_id)
+        .await?
+        .into_iter()
+        .map(|mut split| {
+            split.split_metadata.delete_opstamp =
+                translate_opstamp(&opstamp_mapping, split.split_metadata.delete_opstamp);
+            split
+        })
+        .sorted_by(|left, right| left.split_id().cmp(right.split_id()))
+        .collect();
+    for split in &splits {
+        target_metastore
+            .stage_split(&index_id, split.split_metadata.clone())
+            .await?;
+    }
+    let split_ids_in_states = |split_states: &[SplitState]| -> Vec<&str> {
+        splits
+            .iter()
+            .filter(|split| split_states.contains(&split.split_state))
+            .map(|split| split.split_id())
+            .collect()
+    };
+    let published_split_ids =
+        split_ids_in_states(&[SplitState::Published, SplitState::MarkedForDeletion]);
+    if !published_split_ids.is_empty() {
+        target_metastore
+            .publish_splits(&index_id, &published_split_ids, &[], None)
+            .await?;
+    }
+    let marked_split_ids = split_ids_in_states(&[SplitState::MarkedForDeletion]);
+    if !marked_split_ids.is_empty() {
+        target_metastore
+            .mark_splits_for_deletion(&index_id, &marked_split_ids)
+            .await?;
+    }
+    if index_metadata.maintenance_mode {
+        target_metastore
+            .set_index_maintenance_mode(&index_id, true)
+            .await?;
+    }
+    verify_migrated_index(target_metastore, &index_metadata, &delete_tasks, &splits).await?;
+
+    Ok(MigratedIndex {
+        index_id,
+        num_splits: splits.len(),
+        num_delete_tasks: delete_tasks.len(),
+    })
+}
+
+/// Returns the opstamp of the target metastore of the last delete task with an opstamp lower
+/// than or equal to `opstamp` in the source metastore.
+fn translate_opstamp(opstamp_mapping: &[(u64, u64)], opstamp: u64) -> u64 {
+    opstamp_mapping
+        .iter()
+        .take_while(|(source_opstamp, _)| *source_opstamp <= opstamp)
+        .last()
+        .map(|(_, target_opstamp)| *target_opstamp)
+        .unwrap_or(0)
+}
+
+/// Checks that the target metastore holds the index, delete tasks, and splits copied from the
+/// source metastore.
+async fn verify_migrated_index(
+    target_metastore: &dyn Metastore,
+    index_metadata: &IndexMetadata,
+    delete_tasks: &[DeleteTask],
+    splits: &[Split],
+) -> MetastoreResult<()> {
+    let index_id = &index_metadata.index_id;
+    let verification_error = |cause: String| MetastoreError::InternalError {
+        message: format!("Failed to verify the migration of index `{index_id}`."),
+        cause,
+    };
+    let target_index_metadata = target_metastore.index_metadata(index_id).await?;
+    if comparable_index_metadata(&target_index_metadata)?
+        != comparable_index_metadata(index_metadata)?
+    {
+        return Err(verification_error(
+            "The index metadata differ between the source and target metastores.".to_string(),
+        ));
+    }
+    let target_delete_queries: Vec<_> = target_metastore
+        .list_delete_tasks(index_id, 0)
+        .await?
+        .into_iter()
+        .map(|delete_task| delete_task.delete_query)
+        .collect();
+    if !delete_tasks
+        .iter()
+        .map(|delete_task| &delete_task.delete_query)
+        .eq(target_delete_queries.iter())
+    {
+        return Err(verification_error(
+            "The delete tasks differ between the source and target metastores.".to_string(),
+        ));
+    }
+    let target_splits: Vec<(SplitState, SplitMetadata)> = target_metastore
+        .list_all_splits(index_id)
+        .await?
+        .into_iter()
+        .sorted_by(|left, right| left.split_id().cmp(right.split_id()))
+        .map(|split| (split.split_state, split.split_metadata))
+        .collect();
+    if target_splits.len() != splits.len() {
+        return Err(verification_error(format!(
+            "The source metastore holds {} splits, but the target metastore holds {} splits.",
+            splits.len(),
+            target_splits.len()
+        )));
+    }
+    for (split, (target_split_state, target_split_metadata)) in splits.iter().zip(&target_splits) {
+        if split.split_state != *target_split_state
+            || split.split_metadata != *target_split_metadata
+        {
+            return Err(verification_error(format!(
+                "Split `{}` differs between the source and target metastores.",
+                split.split_id()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the index metadata without the attributes that are not preserved by the migration.
+fn comparable_index_metadata(index_metadata: &IndexMetadata) -> MetastoreResult<serde_json::Value> {
+    let mut index_metadata = index_metadata.clone();
+    index_metadata.update_timestamp = 0;
+    index_metadata.merge_planner_lease = None;
+    serde_json::to_value(&index_metadata).map_err(|error| MetastoreError::JsonSerializeError {
+        name: "IndexMetadata".to_string(),
+        message: error.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use quickwit_config::{SourceConfig, SourceParams};
+    use quickwit_storage::RamStorage;
+
+    use super::*;
+    use crate::checkpoint::IndexCheckpointDelta;
+    use crate::{DeleteQuery, FileBackedMetastore};
+
+    #[test]
+    fn test_translate_opstamp() {
+        let opstamp_mapping = [(2, 10), (5, 11)];
+        assert_eq!(translate_opstamp(&opstamp_mapping, 0), 0);
+        assert_eq!(translate_opstamp(&opstamp_mapping, 1), 0);
+        assert_eq!(translate_opstamp(&opstamp_mapping, 2), 10);
+        assert_eq!(translate_opstamp(&opstamp_mapping, 4), 10);
+        assert_eq!(translate_opstamp(&opstamp_mapping, 7), 11);
+    }
+
+    #[tokio::test]
+    async fn test_migrate_metastore() {
+        let source_metastore = FileBackedMetastore::for_test(Arc::new(RamStorage::default()));
+        let target_metastore = FileBackedMetastore::for_test(Arc::new(RamStorage::default()));
+
+        let index_id = "migrate-metastore-index";
+        let index_metadata =
+            IndexMetadata::for_test(index_id, "ram:///indexes/migrate-metastore-index");
+        source_metastore.create_index(index_metadata).await.unwrap();
+        let source = SourceConfig {
+            source_id: "migrate-metastore-source".to_string(),
+            num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
+            source_params: SourceParams::void(),
+        };
+        source_metastore.add_source(index_id, source).await.unwrap();
+        let delete_query = DeleteQuery {
+            index_id: index_id.to_string(),
+            query: "body:delete".to_string(),
+            ..Default::default()
+        };
+        source_metastore
+            .create_delete_task(delete_query)
+            .await
+            .unwrap();
+
+        for split_id in ["split-1", "split-2", "split-3"] {
+            let split_metadata = SplitMetadata {
+                split_id: split_id.to_string(),
+                num_docs: 10,
+                create_timestamp: 1_000,
+                delete_opstamp: 1,
+                ..Default::default()
+            };
+            source_metastore
+                .stage_split(index_id, split_metadata)
+                .await
+                .unwrap();
+        }
+        source_metastore
+            .publish_splits(
+                index_id,
+                &["split-1", "split-2"],
+                &[],
+                Some(IndexCheckpointDelta::for_test(
+                    "migrate-metastore-source",
+                    0..10,
+                )),
+            )
+            .await
+            .unwrap();
+        source_metastore
+            .mark_splits_for_deletion(index_id, &["split-2"])
+            .await
+            .unwrap();
+
+        let migrated_indexes = migrate_metastore(&source_metastore, &target_metastore)
+            .await
+            .unwrap();
+        assert_eq!(
+            migrated_indexes,
+            [MigratedIndex {
+                index_id: index_id.to_string(),
+                num_splits: 3,
+                num_delete_tasks: 1,
+            }]
+        );
+        let target_index_metadata = target_metastore.index_metadata(index_id).await.unwrap();
+        assert!(target_index_metadata
+            .sources
+            .contains_key("migrate-metastore-source"));
+        assert_eq!(
+            target_index_metadata.checkpoint,
+            source_metastore
+                .index_metadata(index_id)
+                .await
+                .unwrap()
+                .checkpoint
+        );
+        let split_states: Vec<(String, SplitState)> = target_metastore
+            .list_all_splits(index_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|split| (split.split_id().to_string(), split.split_state))
+            .sorted_by(|left, right| left.0.cmp(&right.0))
+            .collect();
+        assert_eq!(
+            split_states,
+            [
+                ("split-1".to_string(), SplitState::Published),
+                ("split-2".to_string(), SplitState::MarkedForDeletion),
+                ("split-3".to_string(), SplitState::Staged),
+            ]
+        );
+
+        // The indexes of the source metastore are already in the target metastore.
+        let error = migrate_metastore(&source_metastore, &target_metastore)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::IndexAlreadyExists { .. }));
+    }
+}
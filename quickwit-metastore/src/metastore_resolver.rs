@@ -27,6 +27,7 @@ use quickwit_common::uri::{Protocol, Uri};
 use crate::metastore::file_backed_metastore::FileBackedMetastoreFactory;
 #[cfg(feature = "postgres")]
 use crate::metastore::postgresql_metastore::PostgresqlMetastoreFactory;
+use crate::metastore::ram_metastore::RamMetastoreFactory;
 use crate::{Metastore, MetastoreResolverError};
 
 /// A metastore factory builds a [`Metastore`] object from an URI.
@@ -70,7 +71,7 @@ pub fn quickwit_metastore_uri_resolver() -> &'static MetastoreUriResolver {
     METASTORE_URI_RESOLVER.get_or_init(|| {
         #[allow(unused_mut)]
         let mut builder = MetastoreUriResolver::builder()
-            .register(Protocol::Ram, FileBackedMetastoreFactory::default())
+            .register(Protocol::Ram, RamMetastoreFactory::default())
             .register(Protocol::File, FileBackedMetastoreFactory::default())
             .register(Protocol::S3, FileBackedMetastoreFactory::default());
 
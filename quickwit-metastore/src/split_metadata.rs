@@ -17,15 +17,16 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
-use std::ops::{Range, RangeInclusive};
+use std::ops::{Bound, Range, RangeInclusive};
 use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 
-use crate::VersionedSplitMetadata;
+use crate::{DedupDigest, VersionedSplitMetadata};
 
 /// Carries split metadata.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -77,6 +78,10 @@ pub struct SplitMetadata {
     /// Pipeline ordinal.
     pub pipeline_ord: usize,
 
+    /// Generation of the indexing pipeline that produced the split. The generation of a pipeline
+    /// is incremented each time it is respawned.
+    pub pipeline_generation: usize,
+
     /// Number of records (or documents) in the split.
     /// TODO make u64
     pub num_docs: usize,
@@ -112,6 +117,46 @@ pub struct SplitMetadata {
     /// The footer offsets
     /// make it possible to download the footer in a single call to `.get_slice(...)`.
     pub footer_offsets: Range<u64>,
+
+    /// Digest of the IDs of the documents in the split, recorded when deduplication is enabled
+    /// for the index.
+    pub dedup_digest: Option<DedupDigest>,
+
+    /// Opstamp of the last delete task applied to the split. The delete tasks with a greater
+    /// opstamp may still match documents of the split.
+    pub delete_opstamp: u64,
+
+    /// URI of the file holding a random sample of the documents of the split, exported when
+    /// split sampling is enabled for the index.
+    pub sample_uri: Option<String>,
+
+    /// If an expire timestamp field is declared in the doc mapping, the earliest expiry of the
+    /// documents of the split. The split holds expired documents once it is in the past, and is
+    /// rewritten to drop them.
+    pub min_expire_timestamp: Option<i64>,
+
+    /// Amendments of the tags of the split made after it was published, oldest first. Only the
+    /// last [`MAX_TAGS_AMENDMENTS_PER_SPLIT`] amendments are kept.
+    pub tags_amendments: Vec<SplitTagsAmendment>,
+
+    /// Version of the doc mapping of the index the split was produced with. Only splits sharing
+    /// the same version of the doc mapping can be merged together.
+    pub doc_mapping_version: u64,
+
+    /// Fingerprint of the doc mapping and the indexing settings of the index the split was built
+    /// with, see [`IndexMetadata::config_fingerprint`](crate::IndexMetadata::config_fingerprint).
+    /// `None` for the splits built before fingerprints were recorded, and for the merges of
+    /// splits built with different configs.
+    pub config_fingerprint: Option<String>,
+
+    /// IDs of the splits replaced by this split, if it is the result of a merge. While this split
+    /// is staged, the garbage collector does not delete the files of these splits.
+    pub replaced_split_ids: Vec<String>,
+
+    /// Statistics of the single-valued numeric fast fields of the split, keyed by field name,
+    /// computed when the split is packaged. The search planner prunes the splits whose statistics
+    /// rule out the range predicates of a query.
+    pub field_stats: BTreeMap<String, SplitFieldStats>,
 }
 
 impl SplitMetadata {
@@ -139,6 +184,29 @@ impl SplitMetadata {
         &self.split_id
     }
 
+    /// Replaces the tags of the split with `tags` and records the amendment in
+    /// [`SplitMetadata::tags_amendments`]. Returns whether the tags changed.
+    pub fn amend_tags(&mut self, tags: BTreeSet<String>, reason: &str) -> bool {
+        let added_tags: BTreeSet<String> = tags.difference(&self.tags).cloned().collect();
+        let removed_tags: BTreeSet<String> = self.tags.difference(&tags).cloned().collect();
+        if added_tags.is_empty() && removed_tags.is_empty() {
+            return false;
+        }
+        self.tags = tags;
+        self.tags_amendments.push(SplitTagsAmendment {
+            timestamp: utc_now_timestamp(),
+            reason: reason.to_string(),
+            added_tags,
+            removed_tags,
+        });
+        let num_amendments = self.tags_amendments.len();
+        if num_amendments > MAX_TAGS_AMENDMENTS_PER_SPLIT {
+            self.tags_amendments
+                .drain(..num_amendments - MAX_TAGS_AMENDMENTS_PER_SPLIT);
+        }
+        true
+    }
+
     #[cfg(any(test, feature = "testsuite"))]
     /// Returns an instance of `SplitMetadata` for testing.
     pub fn for_test(split_id: String) -> Self {
@@ -149,6 +217,90 @@ impl SplitMetadata {
     }
 }
 
+/// Maximum number of tags amendments kept in the metadata of a split.
+pub const MAX_TAGS_AMENDMENTS_PER_SPLIT: usize = 10;
+
+/// Amendment of the tags of a published split, recorded in its metadata as an audit trail.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SplitTagsAmendment {
+    /// Timestamp of the amendment.
+    pub timestamp: i64,
+    /// Why the tags were amended.
+    pub reason: String,
+    /// Tags added to the split.
+    pub added_tags: BTreeSet<String>,
+    /// Tags removed from the split.
+    pub removed_tags: BTreeSet<String>,
+}
+
+/// Statistics of a field of a split.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SplitFieldStats {
+    /// Range of the values of the field in the split. Documents without a value count as zero,
+    /// and deleted documents are accounted for, so the range may be wider than the values of the
+    /// live documents.
+    pub value_range: FieldValueRange,
+    /// Approximate number of distinct values of the field, summed over the segments of the
+    /// split: exact for single-segment splits, an upper bound otherwise. Only recorded for
+    /// indexed fields.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_distinct_values: Option<u64>,
+}
+
+/// Range of the values of a numeric field.
+#[allow(missing_docs)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FieldValueRange {
+    I64 { min: i64, max: i64 },
+    U64 { min: u64, max: u64 },
+    F64 { min: f64, max: f64 },
+}
+
+// The ranges holding a NaN are never recorded.
+impl Eq for FieldValueRange {}
+
+impl FieldValueRange {
+    /// Returns whether the field may hold values between the `lower` and `upper` bounds. Bounds
+    /// that are not values of the type of the field are assumed to overlap, so that splits are
+    /// never wrongly pruned.
+    pub fn overlaps(&self, lower: Bound<&str>, upper: Bound<&str>) -> bool {
+        match self {
+            FieldValueRange::I64 { min, max } => overlaps(min, max, lower, upper),
+            FieldValueRange::U64 { min, max } => overlaps(min, max, lower, upper),
+            FieldValueRange::F64 { min, max } => overlaps(min, max, lower, upper),
+        }
+    }
+}
+
+fn overlaps<T: FromStr + PartialOrd>(
+    min: &T,
+    max: &T,
+    lower: Bound<&str>,
+    upper: Bound<&str>,
+) -> bool {
+    // Comparisons with a NaN bound are inconclusive.
+    let compare = |value: &T, bound: &str, is_match: fn(Ordering) -> bool| {
+        bound
+            .parse::<T>()
+            .ok()
+            .and_then(|bound| value.partial_cmp(&bound))
+            .map(is_match)
+            .unwrap_or(true)
+    };
+    let above_lower = match lower {
+        Bound::Included(bound) => compare(max, bound, Ordering::is_ge),
+        Bound::Excluded(bound) => compare(max, bound, Ordering::is_gt),
+        Bound::Unbounded => true,
+    };
+    let below_upper = match upper {
+        Bound::Included(bound) => compare(min, bound, Ordering::is_le),
+        Bound::Excluded(bound) => compare(min, bound, Ordering::is_lt),
+        Bound::Unbounded => true,
+    };
+    above_lower && below_upper
+}
+
 /// A split state.
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub enum SplitState {
@@ -206,3 +358,67 @@ pub fn utc_now_timestamp() -> i64 {
         OffsetDateTime::now_utc().unix_timestamp()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+
+    use super::{FieldValueRange, SplitMetadata};
+
+    #[test]
+    fn test_field_value_range_overlaps() {
+        let value_range = FieldValueRange::I64 { min: -10, max: 10 };
+        assert!(value_range.overlaps(Bound::Included("10"), Bound::Unbounded));
+        assert!(!value_range.overlaps(Bound::Excluded("10"), Bound::Unbounded));
+        assert!(value_range.overlaps(Bound::Unbounded, Bound::Included("-10")));
+        assert!(!value_range.overlaps(Bound::Unbounded, Bound::Excluded("-10")));
+        assert!(value_range.overlaps(Bound::Included("0"), Bound::Included("0")));
+        assert!(!value_range.overlaps(Bound::Included("11"), Bound::Included("20")));
+        // Bounds that are not values of the field never prune.
+        assert!(value_range.overlaps(Bound::Included("10.5"), Bound::Unbounded));
+        assert!(value_range.overlaps(Bound::Included("foo"), Bound::Unbounded));
+
+        let value_range = FieldValueRange::U64 { min: 5, max: 10 };
+        assert!(!value_range.overlaps(Bound::Unbounded, Bound::Included("4")));
+        assert!(value_range.overlaps(Bound::Unbounded, Bound::Included("-4")));
+
+        let value_range = FieldValueRange::F64 { min: 0.5, max: 1.5 };
+        assert!(value_range.overlaps(Bound::Included("1"), Bound::Included("2")));
+        assert!(!value_range.overlaps(Bound::Excluded("1.5"), Bound::Unbounded));
+        assert!(value_range.overlaps(Bound::Included("NaN"), Bound::Unbounded));
+    }
+
+    #[test]
+    fn test_split_metadata_v1_without_config_fingerprint_nor_field_stats() {
+        let split_metadata_v1_json = r#"{
+            "version": "1",
+            "split_id": "split",
+            "partition_id": 7,
+            "source_id": "source",
+            "node_id": "node/1",
+            "num_docs": 12303,
+            "uncompressed_docs_size_in_bytes": 234234,
+            "time_range": {"start": 121000, "end": 130198},
+            "create_timestamp": 3,
+            "tags": ["234", "aaa"],
+            "footer_offsets": {"start": 1000, "end": 2000},
+            "doc_mapping_version": 2
+        }"#;
+        let split_metadata: SplitMetadata = serde_json::from_str(split_metadata_v1_json).unwrap();
+        assert_eq!(split_metadata.split_id, "split");
+        assert_eq!(split_metadata.source_id, "source");
+        assert_eq!(split_metadata.node_id, "node");
+        assert_eq!(split_metadata.pipeline_ord, 1);
+        assert_eq!(split_metadata.doc_mapping_version, 2);
+        assert!(split_metadata.config_fingerprint.is_none());
+        assert!(split_metadata.field_stats.is_empty());
+
+        let split_metadata_json = serde_json::to_value(&split_metadata).unwrap();
+        assert_eq!(split_metadata_json["version"], "1");
+        assert!(split_metadata_json.get("config_fingerprint").is_none());
+        assert!(split_metadata_json.get("field_stats").is_none());
+        let deserialized_split_metadata: SplitMetadata =
+            serde_json::from_value(split_metadata_json).unwrap();
+        assert_eq!(deserialized_split_metadata, split_metadata);
+    }
+}
@@ -17,13 +17,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::ops::{Range, RangeInclusive};
 
 use serde::{Deserialize, Serialize};
 
-use crate::split_metadata::utc_now_timestamp;
-use crate::{SplitMetadata, SplitState};
+use crate::split_metadata::{utc_now_timestamp, SplitFieldStats, SplitTagsAmendment};
+use crate::{DedupDigest, SplitMetadata, SplitState};
 
 #[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
 struct SplitMetadataV0 {
@@ -79,11 +79,21 @@ impl From<SplitMetadataAndFooterV0> for SplitMetadata {
             source_id: "unknown".to_string(),
             node_id: "unknown".to_string(),
             pipeline_ord: 0,
+            pipeline_generation: 0,
             num_docs: v0.split_metadata.num_docs,
             uncompressed_docs_size_in_bytes: v0.split_metadata.size_in_bytes,
             time_range: v0.split_metadata.time_range,
             create_timestamp: v0.split_metadata.create_timestamp,
             tags: v0.split_metadata.tags,
+            dedup_digest: None,
+            delete_opstamp: 0,
+            sample_uri: None,
+            min_expire_timestamp: None,
+            tags_amendments: Vec::new(),
+            doc_mapping_version: 0,
+            config_fingerprint: None,
+            replaced_split_ids: Vec::new(),
+            field_stats: BTreeMap::new(),
         }
     }
 }
@@ -105,6 +115,10 @@ pub(crate) struct SplitMetadataV1 {
     #[serde(default)]
     pub node_id: Option<String>,
 
+    /// Generation of the indexing pipeline that produced the split.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub pipeline_generation: usize,
+
     /// Number of records (or documents) in the split.
     pub num_docs: usize,
 
@@ -133,6 +147,46 @@ pub(crate) struct SplitMetadataV1 {
     /// The footer offsets
     /// make it possible to download the footer in a single call to `.get_slice(...)`.
     pub footer_offsets: Range<u64>,
+
+    /// Digest of the IDs of the documents in the split.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dedup_digest: Option<DedupDigest>,
+
+    /// Opstamp of the last delete task applied to the split.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub delete_opstamp: u64,
+
+    /// URI of the file holding the sample of the documents of the split.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_uri: Option<String>,
+
+    /// Earliest expiry of the documents of the split.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_expire_timestamp: Option<i64>,
+
+    /// Amendments of the tags of the split made after it was published.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags_amendments: Vec<SplitTagsAmendment>,
+
+    /// Version of the doc mapping the split was produced with.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub doc_mapping_version: u64,
+
+    /// Fingerprint of the doc mapping and the indexing settings the split was built with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_fingerprint: Option<String>,
+
+    /// IDs of the splits replaced by the split, if it is the result of a merge.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replaced_split_ids: Vec<String>,
+
+    /// Statistics of the numeric fast fields of the split, keyed by field name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub field_stats: BTreeMap<String, SplitFieldStats>,
+}
+
+fn is_zero<T: Default + PartialEq>(num: &T) -> bool {
+    *num == T::default()
 }
 
 impl From<SplitMetadataV1> for SplitMetadata {
@@ -158,12 +212,22 @@ impl From<SplitMetadataV1> for SplitMetadata {
             source_id,
             node_id,
             pipeline_ord,
+            pipeline_generation: v1.pipeline_generation,
             num_docs: v1.num_docs,
             uncompressed_docs_size_in_bytes: v1.uncompressed_docs_size_in_bytes,
             time_range: v1.time_range,
             create_timestamp: v1.create_timestamp,
             tags: v1.tags,
             footer_offsets: v1.footer_offsets,
+            dedup_digest: v1.dedup_digest,
+            delete_opstamp: v1.delete_opstamp,
+            sample_uri: v1.sample_uri,
+            min_expire_timestamp: v1.min_expire_timestamp,
+            tags_amendments: v1.tags_amendments,
+            doc_mapping_version: v1.doc_mapping_version,
+            config_fingerprint: v1.config_fingerprint,
+            replaced_split_ids: v1.replaced_split_ids,
+            field_stats: v1.field_stats,
         }
     }
 }
@@ -175,12 +239,22 @@ impl From<SplitMetadata> for SplitMetadataV1 {
             partition_id: split.partition_id,
             source_id: Some(split.source_id),
             node_id: Some(format!("{}/{}", split.node_id, split.pipeline_ord)),
+            pipeline_generation: split.pipeline_generation,
             num_docs: split.num_docs,
             uncompressed_docs_size_in_bytes: split.uncompressed_docs_size_in_bytes,
             time_range: split.time_range,
             create_timestamp: split.create_timestamp,
             tags: split.tags,
             footer_offsets: split.footer_offsets,
+            dedup_digest: split.dedup_digest,
+            delete_opstamp: split.delete_opstamp,
+            sample_uri: split.sample_uri,
+            min_expire_timestamp: split.min_expire_timestamp,
+            tags_amendments: split.tags_amendments,
+            doc_mapping_version: split.doc_mapping_version,
+            config_fingerprint: split.config_fingerprint,
+            replaced_split_ids: split.replaced_split_ids,
+            field_stats: split.field_stats,
         }
     }
 }
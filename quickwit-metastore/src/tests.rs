@@ -19,19 +19,23 @@
 
 #[cfg(test)]
 pub mod test_suite {
-    use std::collections::{BTreeSet, HashSet};
+    use std::collections::{BTreeMap, BTreeSet, HashSet};
 
     use async_trait::async_trait;
     use itertools::Itertools;
     use quickwit_common::rand::append_random_suffix;
     use quickwit_config::{SourceConfig, SourceParams};
     use quickwit_doc_mapper::tag_pruning::{no_tag, tag, TagFilterAst};
+    use quickwit_proto::ServiceError;
     use time::OffsetDateTime;
     use tokio::time::{sleep, Duration};
     use tracing::{error, info};
 
     use crate::checkpoint::{IndexCheckpointDelta, PartitionId, Position, SourceCheckpoint};
-    use crate::{IndexMetadata, Metastore, MetastoreError, SplitMetadata, SplitState};
+    use crate::{
+        DeleteQuery, IndexMetadata, ListSplitsQuery, Metastore, MetastoreError, QuarantinedFile,
+        SplitMetadata, SplitProducer, SplitState,
+    };
 
     #[async_trait]
     pub trait DefaultForTest {
@@ -92,6 +96,10 @@ pub mod test_suite {
             let source = SourceConfig {
                 source_id: source_id.clone(),
                 num_pipelines: 1,
+                profile: None,
+                rate_limit: None,
+                transforms: Vec::new(),
+                strict_ordering: false,
                 source_params: SourceParams::void(),
             };
             metastore
@@ -167,6 +175,10 @@ pub mod test_suite {
         let source = SourceConfig {
             source_id: source_id.to_string(),
             num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
             source_params: SourceParams::void(),
         };
 
@@ -226,6 +238,10 @@ pub mod test_suite {
         let source = SourceConfig {
             source_id: source_id.to_string(),
             num_pipelines: 1,
+            profile: None,
+            rate_limit: None,
+            transforms: Vec::new(),
+            strict_ordering: false,
             source_params: SourceParams::void(),
         };
 
@@ -259,6 +275,741 @@ pub mod test_suite {
         cleanup_index(&metastore, &index_metadata.index_id).await;
     }
 
+    pub async fn test_metastore_create_delete_task<MetastoreToTest: Metastore + DefaultForTest>() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let index_id = append_random_suffix("test-metastore-create-delete-task");
+        let index_uri = format!("ram://indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(&index_id, &index_uri);
+
+        let delete_query = DeleteQuery {
+            index_id: index_id.clone(),
+            query: "user_id:42".to_string(),
+            start_timestamp: Some(0),
+            end_timestamp: Some(100),
+            ..Default::default()
+        };
+
+        // Create a delete task on a non-existent index.
+        let error = metastore
+            .create_delete_task(delete_query.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::IndexDoesNotExist { .. }));
+
+        metastore
+            .create_index(index_metadata.clone())
+            .await
+            .unwrap();
+        assert_eq!(metastore.last_delete_opstamp(&index_id).await.unwrap(), 0);
+        assert!(metastore
+            .list_delete_tasks(&index_id, 0)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let delete_task_1 = metastore
+            .create_delete_task(delete_query.clone())
+            .await
+            .unwrap();
+        assert!(delete_task_1.opstamp > 0);
+        assert_eq!(delete_task_1.delete_query, delete_query);
+
+        let delete_query_2 = DeleteQuery {
+            query: "user_id:43".to_string(),
+            ..delete_query.clone()
+        };
+        let delete_task_2 = metastore
+            .create_delete_task(delete_query_2.clone())
+            .await
+            .unwrap();
+        assert!(delete_task_2.opstamp > delete_task_1.opstamp);
+        assert_eq!(delete_task_2.delete_query, delete_query_2);
+
+        assert_eq!(
+            metastore.last_delete_opstamp(&index_id).await.unwrap(),
+            delete_task_2.opstamp
+        );
+        assert_eq!(
+            metastore.list_delete_tasks(&index_id, 0).await.unwrap(),
+            vec![delete_task_1.clone(), delete_task_2.clone()]
+        );
+        assert_eq!(
+            metastore
+                .list_delete_tasks(&index_id, delete_task_1.opstamp)
+                .await
+                .unwrap(),
+            vec![delete_task_2.clone()]
+        );
+        assert!(metastore
+            .list_delete_tasks(&index_id, delete_task_2.opstamp)
+            .await
+            .unwrap()
+            .is_empty());
+
+        cleanup_index(&metastore, &index_id).await;
+    }
+
+    pub async fn test_metastore_update_splits_delete_opstamp<
+        MetastoreToTest: Metastore + DefaultForTest,
+    >() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let index_id = append_random_suffix("test-metastore-update-splits-delete-opstamp");
+        let index_uri = format!("ram://indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(&index_id, &index_uri);
+
+        metastore
+            .create_index(index_metadata.clone())
+            .await
+            .unwrap();
+
+        let split_id_1 = format!("{index_id}--split-1");
+        let split_id_2 = format!("{index_id}--split-2");
+        for split_id in [&split_id_1, &split_id_2] {
+            let split_metadata = SplitMetadata {
+                split_id: split_id.clone(),
+                ..Default::default()
+            };
+            metastore
+                .stage_split(&index_id, split_metadata)
+                .await
+                .unwrap();
+        }
+        metastore
+            .publish_splits(&index_id, &[&split_id_1, &split_id_2], &[], None)
+            .await
+            .unwrap();
+
+        metastore
+            .update_splits_delete_opstamp(&index_id, &[&split_id_1], 5)
+            .await
+            .unwrap();
+
+        let splits = metastore.list_all_splits(&index_id).await.unwrap();
+        for split in splits {
+            let expected_delete_opstamp = if split.split_id() == split_id_1 { 5 } else { 0 };
+            assert_eq!(split.split_state, SplitState::Published);
+            assert_eq!(split.split_metadata.delete_opstamp, expected_delete_opstamp);
+        }
+
+        // Update a non-existent split.
+        let error = metastore
+            .update_splits_delete_opstamp(&index_id, &[&split_id_2, "non-existent-split"], 5)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::SplitsDoNotExist { .. }));
+
+        // Update the splits of a non-existent index.
+        let error = metastore
+            .update_splits_delete_opstamp("non-existent-index", &[&split_id_1], 5)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::IndexDoesNotExist { .. }));
+
+        cleanup_index(&metastore, &index_id).await;
+    }
+
+    pub async fn test_metastore_amend_splits_tags<MetastoreToTest: Metastore + DefaultForTest>() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let index_id = append_random_suffix("test-metastore-amend-splits-tags");
+        let index_uri = format!("ram://indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(&index_id, &index_uri);
+
+        metastore
+            .create_index(index_metadata.clone())
+            .await
+            .unwrap();
+
+        let split_id_1 = format!("{index_id}--split-1");
+        let split_id_2 = format!("{index_id}--split-2");
+        for split_id in [&split_id_1, &split_id_2] {
+            let split_metadata = SplitMetadata {
+                split_id: split_id.clone(),
+                tags: to_set(&["tenant:foo"]),
+                ..Default::default()
+            };
+            metastore
+                .stage_split(&index_id, split_metadata)
+                .await
+                .unwrap();
+        }
+        metastore
+            .publish_splits(&index_id, &[&split_id_1], &[], None)
+            .await
+            .unwrap();
+
+        let splits_tags =
+            BTreeMap::from_iter([(split_id_1.clone(), to_set(&["tenant:foo", "region:eu"]))]);
+        metastore
+            .amend_splits_tags(&index_id, splits_tags, "Add region tag field.")
+            .await
+            .unwrap();
+
+        let splits = metastore.list_all_splits(&index_id).await.unwrap();
+        for split in splits {
+            if split.split_id() == split_id_1 {
+                assert_eq!(
+                    split.split_metadata.tags,
+                    to_set(&["tenant:foo", "region:eu"])
+                );
+                assert_eq!(split.split_metadata.tags_amendments.len(), 1);
+                let amendment = &split.split_metadata.tags_amendments[0];
+                assert_eq!(amendment.reason, "Add region tag field.");
+                assert_eq!(amendment.added_tags, to_set(&["region:eu"]));
+                assert!(amendment.removed_tags.is_empty());
+            } else {
+                assert_eq!(split.split_metadata.tags, to_set(&["tenant:foo"]));
+                assert!(split.split_metadata.tags_amendments.is_empty());
+            }
+        }
+        let tagged_splits = metastore
+            .list_splits(
+                &index_id,
+                SplitState::Published,
+                None,
+                Some(tag("region:eu")),
+            )
+            .await
+            .unwrap();
+        assert_eq!(tagged_splits.len(), 1);
+        assert_eq!(tagged_splits[0].split_id(), split_id_1);
+
+        // Amending a split with the same tags is a no-op.
+        let splits_tags =
+            BTreeMap::from_iter([(split_id_1.clone(), to_set(&["tenant:foo", "region:eu"]))]);
+        metastore
+            .amend_splits_tags(&index_id, splits_tags, "Add region tag field.")
+            .await
+            .unwrap();
+        let split = metastore
+            .list_all_splits(&index_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|split| split.split_id() == split_id_1)
+            .unwrap();
+        assert_eq!(split.split_metadata.tags_amendments.len(), 1);
+
+        // Amend a staged split.
+        let splits_tags = BTreeMap::from_iter([
+            (split_id_1.clone(), to_set(&["tenant:foo"])),
+            (split_id_2.clone(), to_set(&["tenant:foo", "region:eu"])),
+        ]);
+        let error = metastore
+            .amend_splits_tags(&index_id, splits_tags, "Add region tag field.")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::SplitsNotPublished { .. }));
+
+        // Amend a non-existent split.
+        let splits_tags =
+            BTreeMap::from_iter([("non-existent-split".to_string(), to_set(&["tenant:foo"]))]);
+        let error = metastore
+            .amend_splits_tags(&index_id, splits_tags, "Add region tag field.")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::SplitsDoNotExist { .. }));
+
+        // Amend the splits of a non-existent index.
+        let splits_tags = BTreeMap::from_iter([(split_id_1.clone(), to_set(&["tenant:foo"]))]);
+        let error = metastore
+            .amend_splits_tags("non-existent-index", splits_tags, "Add region tag field.")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::IndexDoesNotExist { .. }));
+
+        cleanup_index(&metastore, &index_id).await;
+    }
+
+    pub async fn test_metastore_list_splits_for_delete_task<
+        MetastoreToTest: Metastore + DefaultForTest,
+    >() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let index_id = append_random_suffix("test-metastore-list-splits-for-delete-task");
+        let index_uri = format!("ram://indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(&index_id, &index_uri);
+
+        metastore
+            .create_index(index_metadata.clone())
+            .await
+            .unwrap();
+
+        let delete_task_1 = metastore
+            .create_delete_task(DeleteQuery {
+                index_id: index_id.clone(),
+                query: "user_id:42".to_string(),
+                start_timestamp: Some(0),
+                end_timestamp: Some(100),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let delete_task_2 = metastore
+            .create_delete_task(DeleteQuery {
+                index_id: index_id.clone(),
+                query: "user_id:43".to_string(),
+                start_timestamp: Some(1000),
+                end_timestamp: Some(2000),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let split_id_1 = format!("{index_id}--split-1");
+        let split_id_2 = format!("{index_id}--split-2");
+        let split_id_3 = format!("{index_id}--split-3");
+        let split_id_4 = format!("{index_id}--split-4");
+        let split_id_5 = format!("{index_id}--split-5");
+        let split_id_6 = format!("{index_id}--split-6");
+        for (split_id, delete_opstamp, time_range, pipeline_ord) in [
+            // Matches the first delete task.
+            (&split_id_1, 0, Some(0..=10), 0),
+            // Does not match any delete task.
+            (&split_id_2, 0, Some(500..=600), 0),
+            // Matches the second delete task.
+            (&split_id_3, delete_task_1.opstamp, Some(1500..=1600), 0),
+            // Splits without a time range match all the delete tasks.
+            (&split_id_4, 0, None, 1),
+            // Staged splits are ignored.
+            (&split_id_5, 0, Some(0..=10), 0),
+            // Up to date.
+            (&split_id_6, delete_task_2.opstamp, Some(0..=10), 0),
+        ] {
+            let split_metadata = SplitMetadata {
+                split_id: split_id.clone(),
+                source_id: "test-source".to_string(),
+                node_id: "test-node".to_string(),
+                pipeline_ord,
+                delete_opstamp,
+                time_range,
+                ..Default::default()
+            };
+            metastore
+                .stage_split(&index_id, split_metadata)
+                .await
+                .unwrap();
+        }
+        metastore
+            .publish_splits(
+                &index_id,
+                &[
+                    &split_id_1,
+                    &split_id_2,
+                    &split_id_3,
+                    &split_id_4,
+                    &split_id_6,
+                ],
+                &[],
+                None,
+            )
+            .await
+            .unwrap();
+
+        let splits = metastore
+            .list_splits_for_delete_task(&index_id, delete_task_2.opstamp, None, 10)
+            .await
+            .unwrap();
+        assert_eq!(splits.len(), 3);
+        let mut lagging_split_ids = vec![splits[0].split_id(), splits[1].split_id()];
+        lagging_split_ids.sort_unstable();
+        assert_eq!(lagging_split_ids, vec![&split_id_1, &split_id_4]);
+        assert_eq!(splits[2].split_id(), split_id_3);
+
+        let splits = metastore
+            .list_splits_for_delete_task(&index_id, delete_task_1.opstamp, None, 10)
+            .await
+            .unwrap();
+        let mut split_ids: Vec<&str> = splits.iter().map(|split| split.split_id()).collect();
+        split_ids.sort_unstable();
+        assert_eq!(split_ids, vec![&split_id_1, &split_id_4]);
+
+        let splits = metastore
+            .list_splits_for_delete_task(&index_id, delete_task_2.opstamp, None, 1)
+            .await
+            .unwrap();
+        assert_eq!(splits.len(), 1);
+        assert_eq!(splits[0].split_metadata.delete_opstamp, 0);
+
+        // The splits of the other pipelines do not count towards the limit.
+        let producer = SplitProducer {
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let splits = metastore
+            .list_splits_for_delete_task(&index_id, delete_task_2.opstamp, Some(producer), 2)
+            .await
+            .unwrap();
+        let split_ids: Vec<&str> = splits.iter().map(|split| split.split_id()).collect();
+        assert_eq!(split_ids, vec![&split_id_1, &split_id_3]);
+
+        let error = metastore
+            .list_splits_for_delete_task("non-existent-index", delete_task_2.opstamp, None, 10)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::IndexDoesNotExist { .. }));
+
+        cleanup_index(&metastore, &index_id).await;
+    }
+
+    pub async fn test_metastore_merge_planner_lease<MetastoreToTest: Metastore + DefaultForTest>() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let index_id = append_random_suffix("test-metastore-merge-planner-lease");
+        let index_uri = format!("ram://indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(&index_id, &index_uri);
+
+        metastore.create_index(index_metadata).await.unwrap();
+
+        assert!(metastore
+            .acquire_merge_planner_lease(&index_id, "planner-1", 3_600)
+            .await
+            .unwrap());
+        let lease = metastore
+            .index_metadata(&index_id)
+            .await
+            .unwrap()
+            .merge_planner_lease
+            .unwrap();
+        assert_eq!(lease.owner, "planner-1");
+
+        // The lease is held by another planner.
+        assert!(!metastore
+            .acquire_merge_planner_lease(&index_id, "planner-2", 3_600)
+            .await
+            .unwrap());
+
+        // The owner renews its lease, this time for no time at all.
+        assert!(metastore
+            .acquire_merge_planner_lease(&index_id, "planner-1", 0)
+            .await
+            .unwrap());
+
+        // The lease expired.
+        assert!(metastore
+            .acquire_merge_planner_lease(&index_id, "planner-2", 3_600)
+            .await
+            .unwrap());
+        let lease = metastore
+            .index_metadata(&index_id)
+            .await
+            .unwrap()
+            .merge_planner_lease
+            .unwrap();
+        assert_eq!(lease.owner, "planner-2");
+
+        let error = metastore
+            .acquire_merge_planner_lease("non-existent-index", "planner-1", 3_600)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::IndexDoesNotExist { .. }));
+
+        cleanup_index(&metastore, &index_id).await;
+    }
+
+    pub async fn test_metastore_quarantine_source_file<
+        MetastoreToTest: Metastore + DefaultForTest,
+    >() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let index_id = append_random_suffix("test-metastore-quarantine-source-file");
+        let index_uri = format!("ram://indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(&index_id, &index_uri);
+
+        metastore.create_index(index_metadata).await.unwrap();
+
+        let quarantined_file = |filepath: &str| QuarantinedFile {
+            filepath: filepath.to_string(),
+            reason: "invalid gzip header".to_string(),
+            quarantine_timestamp: 1_662_000_000,
+        };
+        metastore
+            .quarantine_source_file(
+                &index_id,
+                "file-source",
+                quarantined_file("/logs/1.json.gz"),
+            )
+            .await
+            .unwrap();
+        // Quarantining a file twice is a no-op.
+        metastore
+            .quarantine_source_file(
+                &index_id,
+                "file-source",
+                quarantined_file("/logs/1.json.gz"),
+            )
+            .await
+            .unwrap();
+        metastore
+            .quarantine_source_file(
+                &index_id,
+                "file-source",
+                quarantined_file("/logs/2.json.gz"),
+            )
+            .await
+            .unwrap();
+        let quarantined_files = metastore
+            .index_metadata(&index_id)
+            .await
+            .unwrap()
+            .quarantined_files;
+        assert_eq!(
+            quarantined_files["file-source"],
+            [
+                quarantined_file("/logs/1.json.gz"),
+                quarantined_file("/logs/2.json.gz")
+            ]
+        );
+
+        // Resetting the checkpoint of the source releases its quarantined files.
+        metastore
+            .reset_source_checkpoint(&index_id, "file-source")
+            .await
+            .unwrap();
+        assert!(metastore
+            .index_metadata(&index_id)
+            .await
+            .unwrap()
+            .quarantined_files
+            .is_empty());
+
+        let error = metastore
+            .quarantine_source_file(
+                "non-existent-index",
+                "file-source",
+                quarantined_file("/logs/1.json.gz"),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::IndexDoesNotExist { .. }));
+
+        cleanup_index(&metastore, &index_id).await;
+    }
+
+    pub async fn test_metastore_update_index<MetastoreToTest: Metastore + DefaultForTest>() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let index_id = append_random_suffix("test-metastore-update-index");
+        let index_uri = format!("ram://indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(&index_id, &index_uri);
+        let num_field_mappings = index_metadata.doc_mapping.field_mappings.len();
+
+        metastore
+            .create_index(index_metadata.clone())
+            .await
+            .unwrap();
+
+        // The fingerprint of the config survives the round trip through the metastore.
+        assert_eq!(
+            metastore
+                .index_metadata(&index_id)
+                .await
+                .unwrap()
+                .config_fingerprint(),
+            index_metadata.config_fingerprint()
+        );
+
+        let mut doc_mapping = index_metadata.doc_mapping.clone();
+        doc_mapping.field_mappings.push(
+            serde_json::from_str(r#"{"name": "severity", "type": "text", "tokenizer": "raw"}"#)
+                .unwrap(),
+        );
+        let mut search_settings = index_metadata.search_settings.clone();
+        search_settings.default_search_fields = vec!["body".to_string(), "severity".to_string()];
+        metastore
+            .update_index(&index_id, doc_mapping, search_settings)
+            .await
+            .unwrap();
+
+        let updated_index_metadata = metastore.index_metadata(&index_id).await.unwrap();
+        assert_eq!(updated_index_metadata.doc_mapping_version, 1);
+        assert_ne!(
+            updated_index_metadata.config_fingerprint(),
+            index_metadata.config_fingerprint()
+        );
+        assert_eq!(
+            updated_index_metadata.doc_mapping.field_mappings.len(),
+            num_field_mappings + 1
+        );
+        assert_eq!(
+            updated_index_metadata.doc_mapping.field_mappings[num_field_mappings].name,
+            "severity"
+        );
+        assert_eq!(
+            updated_index_metadata.search_settings.default_search_fields,
+            ["body", "severity"]
+        );
+
+        let error = metastore
+            .update_index(
+                "non-existent-index",
+                index_metadata.doc_mapping,
+                index_metadata.search_settings,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::IndexDoesNotExist { .. }));
+
+        cleanup_index(&metastore, &index_id).await;
+    }
+
+    pub async fn test_metastore_maintenance_mode<MetastoreToTest: Metastore + DefaultForTest>() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let index_id = append_random_suffix("test-metastore-maintenance-mode");
+        let index_uri = format!("ram://indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(&index_id, &index_uri);
+
+        metastore
+            .create_index(index_metadata.clone())
+            .await
+            .unwrap();
+
+        let split_id_1 = format!("{index_id}--split-1");
+        let split_metadata_1 = SplitMetadata {
+            split_id: split_id_1.clone(),
+            ..Default::default()
+        };
+        metastore
+            .stage_split(&index_id, split_metadata_1)
+            .await
+            .unwrap();
+
+        metastore
+            .set_index_maintenance_mode(&index_id, true)
+            .await
+            .unwrap();
+        assert!(
+            metastore
+                .index_metadata(&index_id)
+                .await
+                .unwrap()
+                .maintenance_mode
+        );
+
+        let split_id_2 = format!("{index_id}--split-2");
+        let split_metadata_2 = SplitMetadata {
+            split_id: split_id_2.clone(),
+            ..Default::default()
+        };
+        let error = metastore
+            .stage_split(&index_id, split_metadata_2.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::MaintenanceMode { .. }));
+
+        let error = metastore
+            .publish_splits(&index_id, &[&split_id_1], &[], None)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::MaintenanceMode { .. }));
+
+        let error = metastore
+            .mark_splits_for_deletion(&index_id, &[&split_id_1])
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::MaintenanceMode { .. }));
+
+        let error = metastore
+            .delete_splits(&index_id, &[&split_id_1])
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::MaintenanceMode { .. }));
+
+        let delete_query = DeleteQuery {
+            index_id: index_id.clone(),
+            query: "user_id:42".to_string(),
+            ..Default::default()
+        };
+        let error = metastore
+            .create_delete_task(delete_query)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::MaintenanceMode { .. }));
+
+        // Reads keep working.
+        let splits = metastore.list_all_splits(&index_id).await.unwrap();
+        assert_eq!(splits.len(), 1);
+        assert_eq!(splits[0].split_state, SplitState::Staged);
+
+        metastore
+            .set_index_maintenance_mode(&index_id, false)
+            .await
+            .unwrap();
+        metastore
+            .stage_split(&index_id, split_metadata_2)
+            .await
+            .unwrap();
+        metastore
+            .publish_splits(&index_id, &[&split_id_1, &split_id_2], &[], None)
+            .await
+            .unwrap();
+
+        // Enable the maintenance mode of a non-existent index.
+        let error = metastore
+            .set_index_maintenance_mode("non-existent-index", true)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::IndexDoesNotExist { .. }));
+
+        cleanup_index(&metastore, &index_id).await;
+    }
+
+    pub async fn test_metastore_set_index_feature_flag<
+        MetastoreToTest: Metastore + DefaultForTest,
+    >() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let index_id = append_random_suffix("test-metastore-set-index-feature-flag");
+        let index_uri = format!("ram://indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(&index_id, &index_uri);
+
+        metastore
+            .create_index(index_metadata.clone())
+            .await
+            .unwrap();
+        assert!(!metastore
+            .index_metadata(&index_id)
+            .await
+            .unwrap()
+            .is_feature_enabled("shadow_indexing"));
+
+        metastore
+            .set_index_feature_flag(&index_id, "shadow_indexing", true)
+            .await
+            .unwrap();
+        metastore
+            .set_index_feature_flag(&index_id, "dictionary_compression", false)
+            .await
+            .unwrap();
+        let index_metadata = metastore.index_metadata(&index_id).await.unwrap();
+        assert!(index_metadata.is_feature_enabled("shadow_indexing"));
+        assert!(!index_metadata.is_feature_enabled("dictionary_compression"));
+        assert_eq!(index_metadata.feature_flags.len(), 2);
+
+        metastore
+            .set_index_feature_flag(&index_id, "shadow_indexing", false)
+            .await
+            .unwrap();
+        assert!(!metastore
+            .index_metadata(&index_id)
+            .await
+            .unwrap()
+            .is_feature_enabled("shadow_indexing"));
+
+        // Set a feature flag of a non-existent index.
+        let error = metastore
+            .set_index_feature_flag("non-existent-index", "shadow_indexing", true)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::IndexDoesNotExist { .. }));
+
+        cleanup_index(&metastore, &index_id).await;
+    }
+
     pub async fn test_metastore_create_index<MetastoreToTest: Metastore + DefaultForTest>() {
         let metastore = MetastoreToTest::default_for_test().await;
 
@@ -517,15 +1268,66 @@ pub mod test_suite {
                 .await
                 .unwrap();
 
-            metastore
-                .publish_splits(index_id, &[split_id_1], &[], None)
+            metastore
+                .publish_splits(index_id, &[split_id_1], &[], None)
+                .await
+                .unwrap();
+
+            cleanup_index(&metastore, index_id).await;
+        }
+
+        // Publish a published split on an index
+        {
+            metastore
+                .create_index(index_metadata.clone())
+                .await
+                .unwrap();
+
+            metastore
+                .stage_split(index_id, split_metadata_1.clone())
+                .await
+                .unwrap();
+
+            metastore
+                .publish_splits(
+                    index_id,
+                    &[split_id_1],
+                    &[],
+                    {
+                        let offsets = 1..12;
+                        IndexCheckpointDelta::for_test(source_id, offsets)
+                    }
+                    .into(),
+                )
+                .await
+                .unwrap();
+
+            let publish_error = metastore
+                .publish_splits(
+                    index_id,
+                    &[split_id_1],
+                    &[],
+                    {
+                        let offsets = 1..12;
+                        IndexCheckpointDelta::for_test(source_id, offsets)
+                    }
+                    .into(),
+                )
                 .await
-                .unwrap();
+                .unwrap_err();
+            assert!(matches!(
+                publish_error,
+                MetastoreError::CheckpointConflict(_)
+            ));
+            assert_eq!(
+                publish_error.status_code().to_http_status_code(),
+                http::StatusCode::CONFLICT
+            );
 
             cleanup_index(&metastore, index_id).await;
         }
 
-        // Publish a published split on an index
+        // Publish a checkpoint delta overlapping already acknowledged positions
         {
             metastore
                 .create_index(index_metadata.clone())
@@ -551,23 +1353,51 @@ pub mod test_suite {
                 .await
                 .unwrap();
 
+            metastore
+                .stage_split(index_id, split_metadata_2.clone())
+                .await
+                .unwrap();
+
             let publish_error = metastore
                 .publish_splits(
                     index_id,
-                    &[split_id_1],
+                    &[split_id_2],
                     &[],
                     {
-                        let offsets = 1..12;
+                        let offsets = 5..15;
                         IndexCheckpointDelta::for_test(source_id, offsets)
                     }
                     .into(),
                 )
                 .await
                 .unwrap_err();
-            assert!(matches!(
-                publish_error,
-                MetastoreError::IncompatibleCheckpointDelta(_)
-            ));
+            let incompatible_delta = match publish_error {
+                MetastoreError::CheckpointConflict(incompatible_delta) => incompatible_delta,
+                other_error => panic!("Expected incompatible checkpoint delta, got {other_error}."),
+            };
+            assert_eq!(incompatible_delta.partition_id, PartitionId::default());
+            assert_eq!(incompatible_delta.current_position, Position::from(11u64));
+            assert_eq!(incompatible_delta.delta_position_from, Position::from(4u64));
+
+            // The publish is rejected as a whole.
+            let staged_split_ids: Vec<String> = metastore
+                .list_splits(index_id, SplitState::Staged, None, None)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|split| split.split_id().to_string())
+                .collect();
+            assert_eq!(staged_split_ids, vec![split_id_2.to_string()]);
+
+            let index_checkpoint = metastore.index_metadata(index_id).await.unwrap().checkpoint;
+            assert_eq!(
+                index_checkpoint
+                    .source_checkpoint(source_id)
+                    .unwrap()
+                    .position_for_partition(&PartitionId::default())
+                    .unwrap(),
+                &Position::from(11u64)
+            );
 
             cleanup_index(&metastore, index_id).await;
         }
@@ -872,13 +1702,89 @@ pub mod test_suite {
                 .unwrap_err();
             assert!(matches!(
                 publish_error,
-                MetastoreError::IncompatibleCheckpointDelta(_)
+                MetastoreError::CheckpointConflict(_)
             ));
 
             cleanup_index(&metastore, index_id).await;
         }
     }
 
+    pub async fn test_metastore_publish_splits_concurrently<
+        MetastoreToTest: Metastore + DefaultForTest,
+    >() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let index_id = append_random_suffix("test-metastore-publish-splits-concurrently");
+        let index_uri = format!("ram://indexes/{index_id}");
+        let source_id = "publish-splits-concurrently-source";
+        let index_metadata = IndexMetadata::for_test(&index_id, &index_uri);
+        metastore.create_index(index_metadata).await.unwrap();
+
+        let split_ids: Vec<String> = (0..4)
+            .map(|split_ord| format!("{index_id}--split-{split_ord}"))
+            .collect();
+        for split_id in &split_ids {
+            let split_metadata = SplitMetadata {
+                split_id: split_id.clone(),
+                ..Default::default()
+            };
+            metastore
+                .stage_split(&index_id, split_metadata)
+                .await
+                .unwrap();
+        }
+        // All the checkpoint deltas start from the same position, so only one of the publishes
+        // can succeed.
+        let publish_results =
+            futures::future::join_all(split_ids.iter().enumerate().map(|(split_ord, split_id)| {
+                let checkpoint_delta =
+                    IndexCheckpointDelta::for_test(source_id, 0..10 + split_ord as u64);
+                let metastore = &metastore;
+                let index_id = &index_id;
+                async move {
+                    metastore
+                        .publish_splits(index_id, &[split_id.as_str()], &[], Some(checkpoint_delta))
+                        .await
+                }
+            }))
+            .await;
+
+        let published_split_ords: Vec<usize> = publish_results
+            .iter()
+            .positions(|publish_result| publish_result.is_ok())
+            .collect();
+        assert_eq!(published_split_ords.len(), 1);
+        for publish_result in &publish_results {
+            if let Err(error) = publish_result {
+                assert!(matches!(error, MetastoreError::CheckpointConflict(_)));
+            }
+        }
+        let published_split_ord = published_split_ords[0];
+        let index_metadata = metastore.index_metadata(&index_id).await.unwrap();
+        let mut expected_checkpoint = SourceCheckpoint::default();
+        expected_checkpoint
+            .try_apply_delta(
+                IndexCheckpointDelta::for_test(source_id, 0..10 + published_split_ord as u64)
+                    .source_delta,
+            )
+            .unwrap();
+        assert_eq!(
+            index_metadata.checkpoint.source_checkpoint(source_id),
+            Some(&expected_checkpoint)
+        );
+        let published_splits = metastore
+            .list_splits(&index_id, SplitState::Published, None, None)
+            .await
+            .unwrap();
+        assert_eq!(published_splits.len(), 1);
+        assert_eq!(
+            published_splits[0].split_id(),
+            split_ids[published_split_ord]
+        );
+
+        cleanup_index(&metastore, &index_id).await;
+    }
+
     pub async fn test_metastore_replace_splits<MetastoreToTest: Metastore + DefaultForTest>() {
         let metastore = MetastoreToTest::default_for_test().await;
 
@@ -1959,6 +2865,214 @@ pub mod test_suite {
         }
     }
 
+    pub async fn test_metastore_list_splits_page<MetastoreToTest: Metastore + DefaultForTest>() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let index_id = "list-splits-page-index";
+        let index_uri = format!("ram://indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(index_id, &index_uri);
+
+        let split_specs = [
+            ("list-splits-page-a", Some(0..=99), 1000, vec!["tag:foo"]),
+            ("list-splits-page-b", Some(100..=199), 2000, vec!["tag:bar"]),
+            ("list-splits-page-c", Some(200..=299), 3000, vec!["tag:foo"]),
+            ("list-splits-page-d", None, 4000, Vec::new()),
+        ];
+
+        let list_split_ids = |query: ListSplitsQuery| {
+            let metastore = &metastore;
+            async move {
+                metastore
+                    .list_splits_page(index_id, query)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|split| split.split_id().to_string())
+                    .collect::<Vec<String>>()
+            }
+        };
+
+        // List the splits of a non-existent index
+        {
+            let error = metastore
+                .list_splits_page("non-existent-index", ListSplitsQuery::default())
+                .await
+                .unwrap_err();
+            assert!(matches!(error, MetastoreError::IndexDoesNotExist { .. }));
+        }
+
+        metastore
+            .create_index(index_metadata.clone())
+            .await
+            .unwrap();
+
+        let config_fingerprint = index_metadata.config_fingerprint();
+        for (split_ord, (split_id, time_range, create_timestamp, tags)) in
+            split_specs.into_iter().enumerate()
+        {
+            let split_config_fingerprint = match split_ord {
+                0 | 2 => Some(config_fingerprint.clone()),
+                1 => Some("outdated-config-fingerprint".to_string()),
+                _ => None,
+            };
+            let split_metadata = SplitMetadata {
+                footer_offsets: 1000..2000,
+                split_id: split_id.to_string(),
+                num_docs: 1,
+                uncompressed_docs_size_in_bytes: 2,
+                time_range,
+                create_timestamp,
+                tags: to_set(&tags),
+                source_id: format!("source-{}", split_ord % 2),
+                node_id: format!("node-{}", split_ord / 2),
+                pipeline_ord: split_ord % 2,
+                pipeline_generation: split_ord / 3,
+                config_fingerprint: split_config_fingerprint,
+                ..Default::default()
+            };
+            metastore
+                .stage_split(index_id, split_metadata)
+                .await
+                .unwrap();
+        }
+        metastore
+            .publish_splits(
+                index_id,
+                &["list-splits-page-a", "list-splits-page-b"],
+                &[],
+                None,
+            )
+            .await
+            .unwrap();
+
+        // The splits are sorted by split ID
+        assert_eq!(
+            list_split_ids(ListSplitsQuery::default()).await,
+            [
+                "list-splits-page-a",
+                "list-splits-page-b",
+                "list-splits-page-c",
+                "list-splits-page-d"
+            ]
+        );
+
+        // Paginate
+        {
+            let query = ListSplitsQuery {
+                offset: 1,
+                limit_opt: Some(2),
+                ..Default::default()
+            };
+            assert_eq!(
+                list_split_ids(query).await,
+                ["list-splits-page-b", "list-splits-page-c"]
+            );
+
+            let query = ListSplitsQuery {
+                offset: 3,
+                limit_opt: Some(2),
+                ..Default::default()
+            };
+            assert_eq!(list_split_ids(query).await, ["list-splits-page-d"]);
+
+            let query = ListSplitsQuery {
+                offset: 4,
+                ..Default::default()
+            };
+            assert!(list_split_ids(query).await.is_empty());
+        }
+
+        // Filter by split state
+        {
+            let query = ListSplitsQuery {
+                split_state_opt: Some(SplitState::Staged),
+                ..Default::default()
+            };
+            assert_eq!(
+                list_split_ids(query).await,
+                ["list-splits-page-c", "list-splits-page-d"]
+            );
+        }
+
+        // Filter by time range, the splits without time range always match
+        {
+            let query = ListSplitsQuery {
+                time_range_opt: Some(150..250),
+                ..Default::default()
+            };
+            assert_eq!(
+                list_split_ids(query).await,
+                [
+                    "list-splits-page-b",
+                    "list-splits-page-c",
+                    "list-splits-page-d"
+                ]
+            );
+        }
+
+        // Filter by create timestamp
+        {
+            let query = ListSplitsQuery {
+                create_timestamp_range_opt: Some(2000..4000),
+                ..Default::default()
+            };
+            assert_eq!(
+                list_split_ids(query).await,
+                ["list-splits-page-b", "list-splits-page-c"]
+            );
+        }
+
+        // Filter by tags and paginate the matching splits
+        {
+            let query = ListSplitsQuery {
+                tags_opt: Some(tag("tag:foo")),
+                offset: 1,
+                ..Default::default()
+            };
+            assert_eq!(list_split_ids(query).await, ["list-splits-page-c"]);
+        }
+
+        // Filter by producer
+        {
+            let query = ListSplitsQuery {
+                source_id_opt: Some("source-1".to_string()),
+                ..Default::default()
+            };
+            assert_eq!(
+                list_split_ids(query).await,
+                ["list-splits-page-b", "list-splits-page-d"]
+            );
+
+            let query = ListSplitsQuery {
+                node_id_opt: Some("node-1".to_string()),
+                pipeline_ord_opt: Some(0),
+                ..Default::default()
+            };
+            assert_eq!(list_split_ids(query).await, ["list-splits-page-c"]);
+
+            let query = ListSplitsQuery {
+                pipeline_generation_opt: Some(1),
+                ..Default::default()
+            };
+            assert_eq!(list_split_ids(query).await, ["list-splits-page-d"]);
+        }
+
+        // Filter the splits built with an outdated config, the splits without config fingerprint
+        // always match
+        {
+            let query = ListSplitsQuery {
+                current_config_fingerprint_opt: Some(config_fingerprint.clone()),
+                ..Default::default()
+            };
+            assert_eq!(
+                list_split_ids(query).await,
+                ["list-splits-page-b", "list-splits-page-d"]
+            );
+        }
+
+        cleanup_index(&metastore, index_id).await;
+    }
+
     pub async fn test_metastore_split_update_timestamp<
         MetastoreToTest: Metastore + DefaultForTest,
     >() {
@@ -2125,6 +3239,51 @@ pub mod test_suite {
             .collect_vec();
         assert!(result.is_empty());
     }
+
+    pub async fn test_metastore_list_indexes_in_namespace<
+        MetastoreToTest: Metastore + DefaultForTest,
+    >() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let index_ids = [
+            "list-namespace_a/index-1",
+            "list-namespace_a/prod/index-2",
+            "list-namespaceXa/index-3",
+            "list-namespace_a-index-4",
+        ];
+        for index_id in index_ids {
+            let index_uri = format!("ram:///indexes/{index_id}");
+            let index_metadata = IndexMetadata::for_test(index_id, &index_uri);
+            metastore.create_index(index_metadata).await.unwrap();
+        }
+        let namespace_index_ids = |namespace: &'static str| {
+            let metastore = &metastore;
+            async move {
+                metastore
+                    .list_indexes_metadatas_in_namespace(namespace)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|index_metadata| index_metadata.index_id)
+                    .sorted()
+                    .collect_vec()
+            }
+        };
+        assert_eq!(
+            namespace_index_ids("list-namespace_a").await,
+            ["list-namespace_a/index-1", "list-namespace_a/prod/index-2"]
+        );
+        assert_eq!(
+            namespace_index_ids("list-namespace_a/prod").await,
+            ["list-namespace_a/prod/index-2"]
+        );
+        assert!(namespace_index_ids("list-namespace_b").await.is_empty());
+
+        for index_id in index_ids {
+            cleanup_index(&metastore, index_id).await;
+        }
+        assert!(namespace_index_ids("list-namespace_a").await.is_empty());
+    }
 }
 
 macro_rules! metastore_test_suite {
@@ -2155,6 +3314,14 @@ macro_rules! metastore_test_suite {
                 crate::tests::test_suite::test_metastore_list_indexes::<$metastore_type>().await;
             }
 
+            #[tokio::test]
+            async fn test_metastore_list_indexes_in_namespace() {
+                crate::tests::test_suite::test_metastore_list_indexes_in_namespace::<
+                    $metastore_type,
+                >()
+                .await;
+            }
+
             #[tokio::test]
             async fn test_metastore_stage_split() {
                 let _ = tracing_subscriber::fmt::try_init();
@@ -2172,6 +3339,12 @@ macro_rules! metastore_test_suite {
                 crate::tests::test_suite::test_metastore_publish_splits_empty_splits_array_is_allowed::<$metastore_type>().await;
             }
 
+            #[tokio::test]
+            async fn test_metastore_publish_splits_concurrently() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_publish_splits_concurrently::<$metastore_type>().await;
+            }
+
             #[tokio::test]
             async fn test_metastore_replace_splits() {
                 let _ = tracing_subscriber::fmt::try_init();
@@ -2203,6 +3376,12 @@ macro_rules! metastore_test_suite {
                 crate::tests::test_suite::test_metastore_list_splits::<$metastore_type>().await;
             }
 
+            #[tokio::test]
+            async fn test_metastore_list_splits_page() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_list_splits_page::<$metastore_type>().await;
+            }
+
             #[tokio::test]
             async fn test_metastore_split_update_timestamp() {
                 let _ = tracing_subscriber::fmt::try_init();
@@ -2228,6 +3407,62 @@ macro_rules! metastore_test_suite {
                 let _ = tracing_subscriber::fmt::try_init();
                 crate::tests::test_suite::test_metastore_reset_checkpoint::<$metastore_type>().await;
             }
+
+            #[tokio::test]
+            async fn test_metastore_create_delete_task() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_create_delete_task::<$metastore_type>().await;
+            }
+
+            #[tokio::test]
+            async fn test_metastore_update_splits_delete_opstamp() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_update_splits_delete_opstamp::<$metastore_type>()
+                .await;
+            }
+
+            #[tokio::test]
+            async fn test_metastore_amend_splits_tags() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_amend_splits_tags::<$metastore_type>().await;
+            }
+
+            #[tokio::test]
+            async fn test_metastore_list_splits_for_delete_task() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_list_splits_for_delete_task::<$metastore_type>()
+                .await;
+            }
+
+            #[tokio::test]
+            async fn test_metastore_maintenance_mode() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_maintenance_mode::<$metastore_type>().await;
+            }
+
+            #[tokio::test]
+            async fn test_metastore_set_index_feature_flag() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_set_index_feature_flag::<$metastore_type>().await;
+            }
+
+            #[tokio::test]
+            async fn test_metastore_merge_planner_lease() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_merge_planner_lease::<$metastore_type>().await;
+            }
+
+            #[tokio::test]
+            async fn test_metastore_quarantine_source_file() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_quarantine_source_file::<$metastore_type>().await;
+            }
+
+            #[tokio::test]
+            async fn test_metastore_update_index() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_update_index::<$metastore_type>().await;
+            }
         }
     }
 }
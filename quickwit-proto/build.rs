@@ -21,6 +21,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("cargo:rerun-if-changed=proto/search_api.proto");
     println!("cargo:rerun-if-changed=proto/ingest_api.proto");
     println!("cargo:rerun-if-changed=proto/metastore_api.proto");
+    println!("cargo:rerun-if-changed=proto/jaeger/model.proto");
+    println!("cargo:rerun-if-changed=proto/jaeger/query.proto");
 
     let mut prost_config = prost_build::Config::default();
     // prost_config.type_attribute("LeafSearchResponse", "#[derive(Default)]");
@@ -38,5 +40,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             ],
             &["./proto"],
         )?;
+
+    // The Jaeger protos rely on the well-known types of `prost-types`, which do not implement
+    // `Serialize` and `Deserialize`.
+    let mut prost_config = prost_build::Config::default();
+    prost_config.protoc_arg("--experimental_allow_proto3_optional");
+    tonic_build::configure()
+        .out_dir("src/")
+        .compile_with_config(prost_config, &["./proto/jaeger/query.proto"], &["./proto"])?;
     Ok(())
 }
@@ -19,8 +19,12 @@
 
 #![allow(clippy::derive_partial_eq_without_eq)]
 
+#[path = "jaeger.api_v2.rs"]
+mod jaeger_api_v2;
 mod quickwit;
 mod quickwit_ingest_api;
+// The response stream types of the snake case streaming RPCs are generated in snake case.
+#[allow(non_camel_case_types)]
 mod quickwit_metastore_api;
 
 pub mod ingest_api {
@@ -31,6 +35,12 @@ pub mod metastore_api {
     pub use crate::quickwit_metastore_api::*;
 }
 
+pub mod jaeger {
+    pub mod api_v2 {
+        pub use crate::jaeger_api_v2::*;
+    }
+}
+
 #[macro_use]
 extern crate serde;
 
@@ -52,6 +62,9 @@ pub enum ServiceErrorCode {
     MethodNotAllowed,
     UnsupportedMediaType,
     BadRequest,
+    Unauthorized,
+    Timeout,
+    Conflict,
 }
 
 impl ServiceErrorCode {
@@ -62,6 +75,9 @@ impl ServiceErrorCode {
             ServiceErrorCode::BadRequest => tonic::Code::InvalidArgument,
             ServiceErrorCode::MethodNotAllowed => tonic::Code::InvalidArgument,
             ServiceErrorCode::UnsupportedMediaType => tonic::Code::InvalidArgument,
+            ServiceErrorCode::Unauthorized => tonic::Code::Unauthenticated,
+            ServiceErrorCode::Timeout => tonic::Code::DeadlineExceeded,
+            ServiceErrorCode::Conflict => tonic::Code::Aborted,
         }
     }
     pub fn to_http_status_code(self) -> http::StatusCode {
@@ -71,6 +87,9 @@ impl ServiceErrorCode {
             ServiceErrorCode::BadRequest => http::StatusCode::BAD_REQUEST,
             ServiceErrorCode::MethodNotAllowed => http::StatusCode::METHOD_NOT_ALLOWED,
             ServiceErrorCode::UnsupportedMediaType => http::StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ServiceErrorCode::Unauthorized => http::StatusCode::UNAUTHORIZED,
+            ServiceErrorCode::Timeout => http::StatusCode::REQUEST_TIMEOUT,
+            ServiceErrorCode::Conflict => http::StatusCode::CONFLICT,
         }
     }
 }
@@ -112,6 +131,7 @@ impl From<SearchStreamRequest> for SearchRequest {
             sort_by_field: None,
             sort_order: None,
             aggregation_request: None,
+            search_after: None,
         }
     }
 }
@@ -39,6 +39,20 @@ pub struct SearchRequest {
     /// Fields to extract snippet on
     #[prost(string, repeated, tag="12")]
     pub snippet_fields: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// If set, only the hits ranked after this hit are returned. Together with max_hits,
+    /// this parameter can be used for cursor based pagination.
+    /// It cannot be combined with a non-zero start_offset.
+    #[prost(message, optional, tag="13")]
+    pub search_after: ::core::option::Option<PartialHit>,
+    /// Tag inserted before the highlighted terms of the snippets. Defaults to `<b>`.
+    #[prost(string, optional, tag="14")]
+    pub snippet_pre_tag: ::core::option::Option<::prost::alloc::string::String>,
+    /// Tag inserted after the highlighted terms of the snippets. Defaults to `</b>`.
+    #[prost(string, optional, tag="15")]
+    pub snippet_post_tag: ::core::option::Option<::prost::alloc::string::String>,
+    /// Maximum number of characters of the snippets. Defaults to 150.
+    #[prost(uint32, optional, tag="16")]
+    pub snippet_fragment_size: ::core::option::Option<u32>,
 }
 #[derive(Serialize, Deserialize)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -256,6 +270,10 @@ pub struct SearchStreamRequest {
     /// Fields to extract snippet on.
     #[prost(string, repeated, tag="10")]
     pub snippet_fields: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Names of the fast fields to export as columns. Cannot be combined with `fast_field`.
+    /// If neither `fast_field` nor `fast_fields` is set, the stored documents are exported.
+    #[prost(string, repeated, tag="11")]
+    pub fast_fields: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 #[derive(Serialize, Deserialize)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -324,6 +342,11 @@ pub enum OutputFormat {
     /// / Format data by row in ClickHouse binary format.
     /// / <https://clickhouse.tech/docs/en/interfaces/formats/#rowbinary>
     ClickHouseRowBinary = 1,
+    /// / Newline delimited JSON format (<http://ndjson.org>), one JSON object per line.
+    Ndjson = 2,
+    /// / Apache Arrow IPC streaming format.
+    /// / <https://arrow.apache.org/docs/format/Columnar.html#ipc-streaming-format>
+    ArrowIpc = 3,
 }
 impl OutputFormat {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -334,6 +357,8 @@ impl OutputFormat {
         match self {
             OutputFormat::Csv => "CSV",
             OutputFormat::ClickHouseRowBinary => "CLICK_HOUSE_ROW_BINARY",
+            OutputFormat::Ndjson => "NDJSON",
+            OutputFormat::ArrowIpc => "ARROW_IPC",
         }
     }
 }
@@ -11,12 +11,28 @@ pub struct CreateIndexResponse {
 #[derive(Serialize, Deserialize)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ListIndexesMetadatasRequest {
+    /// Highest version of the binary payload encoding supported by the client. The server responds
+    /// with a binary payload of this version or an older one if it supports any, and with a JSON
+    /// payload otherwise.
+    #[prost(uint32, optional, tag="1")]
+    pub binary_payload_version: ::core::option::Option<u32>,
 }
 #[derive(Serialize, Deserialize)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListIndexesMetadatasInNamespaceRequest {
+    #[prost(string, tag="1")]
+    pub namespace: ::prost::alloc::string::String,
+    #[prost(uint32, optional, tag="2")]
+    pub binary_payload_version: ::core::option::Option<u32>,
+}
+/// Holds either a JSON or a binary payload.
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ListIndexesMetadatasResponse {
     #[prost(string, tag="1")]
     pub indexes_metadatas_serialized_json: ::prost::alloc::string::String,
+    #[prost(bytes="vec", tag="2")]
+    pub indexes_metadatas_serialized_binary: ::prost::alloc::vec::Vec<u8>,
 }
 #[derive(Serialize, Deserialize)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -33,18 +49,25 @@ pub struct DeleteIndexResponse {
 pub struct IndexMetadataRequest {
     #[prost(string, tag="1")]
     pub index_id: ::prost::alloc::string::String,
+    #[prost(uint32, optional, tag="2")]
+    pub binary_payload_version: ::core::option::Option<u32>,
 }
+/// Holds either a JSON or a binary payload.
 #[derive(Serialize, Deserialize)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct IndexMetadataResponse {
     #[prost(string, tag="1")]
     pub index_metadata_serialized_json: ::prost::alloc::string::String,
+    #[prost(bytes="vec", tag="2")]
+    pub index_metadata_serialized_binary: ::prost::alloc::vec::Vec<u8>,
 }
 #[derive(Serialize, Deserialize)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ListAllSplitsRequest {
     #[prost(string, tag="1")]
     pub index_id: ::prost::alloc::string::String,
+    #[prost(uint32, optional, tag="2")]
+    pub binary_payload_version: ::core::option::Option<u32>,
 }
 #[derive(Serialize, Deserialize)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -59,12 +82,52 @@ pub struct ListSplitsRequest {
     pub time_range_end: ::core::option::Option<i64>,
     #[prost(string, optional, tag="5")]
     pub tags_serialized_json: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(uint32, optional, tag="6")]
+    pub binary_payload_version: ::core::option::Option<u32>,
 }
 #[derive(Serialize, Deserialize)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ListSplitsResponse {
+    /// JSON array of splits. When the response is streamed, each message holds one batch.
     #[prost(string, tag="1")]
     pub splits_serialized_json: ::prost::alloc::string::String,
+    /// Array of splits in the binary encoding, set instead of the JSON array if requested.
+    #[prost(bytes="vec", tag="2")]
+    pub splits_serialized_binary: ::prost::alloc::vec::Vec<u8>,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListSplitsPageRequest {
+    #[prost(string, tag="1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(string, optional, tag="2")]
+    pub split_state: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(int64, optional, tag="3")]
+    pub time_range_start: ::core::option::Option<i64>,
+    #[prost(int64, optional, tag="4")]
+    pub time_range_end: ::core::option::Option<i64>,
+    #[prost(int64, optional, tag="5")]
+    pub create_timestamp_start: ::core::option::Option<i64>,
+    #[prost(int64, optional, tag="6")]
+    pub create_timestamp_end: ::core::option::Option<i64>,
+    #[prost(string, optional, tag="7")]
+    pub tags_serialized_json: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(uint64, tag="8")]
+    pub offset: u64,
+    #[prost(uint64, optional, tag="9")]
+    pub limit: ::core::option::Option<u64>,
+    #[prost(uint32, optional, tag="10")]
+    pub binary_payload_version: ::core::option::Option<u32>,
+    #[prost(string, optional, tag="11")]
+    pub source_id: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag="12")]
+    pub node_id: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(uint64, optional, tag="13")]
+    pub pipeline_ord: ::core::option::Option<u64>,
+    #[prost(uint64, optional, tag="14")]
+    pub pipeline_generation: ::core::option::Option<u64>,
+    #[prost(string, optional, tag="15")]
+    pub current_config_fingerprint: ::core::option::Option<::prost::alloc::string::String>,
 }
 #[derive(Serialize, Deserialize)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -134,6 +197,149 @@ pub struct ResetSourceCheckpointRequest {
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SourceResponse {
 }
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CreateDeleteTaskRequest {
+    #[prost(string, tag="1")]
+    pub delete_query_serialized_json: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeleteTaskResponse {
+    #[prost(string, tag="1")]
+    pub delete_task_serialized_json: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LastDeleteOpstampRequest {
+    #[prost(string, tag="1")]
+    pub index_id: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LastDeleteOpstampResponse {
+    #[prost(uint64, tag="1")]
+    pub last_delete_opstamp: u64,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListDeleteTasksRequest {
+    #[prost(string, tag="1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(uint64, tag="2")]
+    pub opstamp_start: u64,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListDeleteTasksResponse {
+    #[prost(string, tag="1")]
+    pub delete_tasks_serialized_json: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListSplitsForDeleteTaskRequest {
+    #[prost(string, tag="1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(uint64, tag="2")]
+    pub delete_opstamp_threshold: u64,
+    #[prost(uint64, tag="3")]
+    pub limit: u64,
+    #[prost(uint32, optional, tag="4")]
+    pub binary_payload_version: ::core::option::Option<u32>,
+    #[prost(string, optional, tag="5")]
+    pub producer_source_id: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag="6")]
+    pub producer_node_id: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(uint64, optional, tag="7")]
+    pub producer_pipeline_ord: ::core::option::Option<u64>,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateSplitsDeleteOpstampRequest {
+    #[prost(string, tag="1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(string, repeated, tag="2")]
+    pub split_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(uint64, tag="3")]
+    pub delete_opstamp: u64,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AmendSplitsTagsRequest {
+    #[prost(string, tag="1")]
+    pub index_id: ::prost::alloc::string::String,
+    /// JSON map of the split IDs to their new tags.
+    #[prost(string, tag="2")]
+    pub splits_tags_serialized_json: ::prost::alloc::string::String,
+    #[prost(string, tag="3")]
+    pub reason: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SetIndexMaintenanceModeRequest {
+    #[prost(string, tag="1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(bool, tag="2")]
+    pub enabled: bool,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SetIndexMaintenanceModeResponse {
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SetIndexFeatureFlagRequest {
+    #[prost(string, tag="1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub flag: ::prost::alloc::string::String,
+    #[prost(bool, tag="3")]
+    pub enabled: bool,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SetIndexFeatureFlagResponse {
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AcquireMergePlannerLeaseRequest {
+    #[prost(string, tag="1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub owner: ::prost::alloc::string::String,
+    #[prost(uint64, tag="3")]
+    pub lease_duration_secs: u64,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AcquireMergePlannerLeaseResponse {
+    #[prost(bool, tag="1")]
+    pub is_lease_holder: bool,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct QuarantineSourceFileRequest {
+    #[prost(string, tag="1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub source_id: ::prost::alloc::string::String,
+    #[prost(string, tag="3")]
+    pub quarantined_file_serialized_json: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateIndexRequest {
+    #[prost(string, tag="1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub doc_mapping_serialized_json: ::prost::alloc::string::String,
+    #[prost(string, tag="3")]
+    pub search_settings_serialized_json: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateIndexResponse {
+}
 /// Generated client implementations.
 pub mod metastore_api_service_client {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
@@ -266,6 +472,29 @@ pub mod metastore_api_service_client {
             );
             self.inner.unary(request.into_request(), path, codec).await
         }
+        /// Get the metadatas of the indexes of a namespace.
+        pub async fn list_indexes_metadatas_in_namespace(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListIndexesMetadatasInNamespaceRequest>,
+        ) -> Result<
+            tonic::Response<super::ListIndexesMetadatasResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/list_indexes_metadatas_in_namespace",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
         /// Delete an index
         pub async fn delete_index(
             &mut self,
@@ -286,11 +515,14 @@ pub mod metastore_api_service_client {
             );
             self.inner.unary(request.into_request(), path, codec).await
         }
-        /// Get all splits from index.
+        /// Get all splits from index, streamed in batches.
         pub async fn list_all_splits(
             &mut self,
             request: impl tonic::IntoRequest<super::ListAllSplitsRequest>,
-        ) -> Result<tonic::Response<super::ListSplitsResponse>, tonic::Status> {
+        ) -> Result<
+            tonic::Response<tonic::codec::Streaming<super::ListSplitsResponse>>,
+            tonic::Status,
+        > {
             self.inner
                 .ready()
                 .await
@@ -304,13 +536,16 @@ pub mod metastore_api_service_client {
             let path = http::uri::PathAndQuery::from_static(
                 "/quickwit_metastore_api.MetastoreApiService/list_all_splits",
             );
-            self.inner.unary(request.into_request(), path, codec).await
+            self.inner.server_streaming(request.into_request(), path, codec).await
         }
-        /// Get splits from index.
+        /// Get splits from index, streamed in batches.
         pub async fn list_splits(
             &mut self,
             request: impl tonic::IntoRequest<super::ListSplitsRequest>,
-        ) -> Result<tonic::Response<super::ListSplitsResponse>, tonic::Status> {
+        ) -> Result<
+            tonic::Response<tonic::codec::Streaming<super::ListSplitsResponse>>,
+            tonic::Status,
+        > {
             self.inner
                 .ready()
                 .await
@@ -324,6 +559,26 @@ pub mod metastore_api_service_client {
             let path = http::uri::PathAndQuery::from_static(
                 "/quickwit_metastore_api.MetastoreApiService/list_splits",
             );
+            self.inner.server_streaming(request.into_request(), path, codec).await
+        }
+        /// Get a page of the splits from index matching a query, sorted by split ID.
+        pub async fn list_splits_page(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListSplitsPageRequest>,
+        ) -> Result<tonic::Response<super::ListSplitsResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/list_splits_page",
+            );
             self.inner.unary(request.into_request(), path, codec).await
         }
         /// Stage split.
@@ -466,159 +721,916 @@ pub mod metastore_api_service_client {
             );
             self.inner.unary(request.into_request(), path, codec).await
         }
-    }
-}
-/// Generated server implementations.
-pub mod metastore_api_service_server {
-    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
-    use tonic::codegen::*;
-    ///Generated trait containing gRPC methods that should be implemented for use with MetastoreApiServiceServer.
-    #[async_trait]
-    pub trait MetastoreApiService: Send + Sync + 'static {
-        /// Create an index.
-        async fn create_index(
-            &self,
-            request: tonic::Request<super::CreateIndexRequest>,
-        ) -> Result<tonic::Response<super::CreateIndexResponse>, tonic::Status>;
-        /// Get an index metadata.
-        async fn index_metadata(
-            &self,
-            request: tonic::Request<super::IndexMetadataRequest>,
-        ) -> Result<tonic::Response<super::IndexMetadataResponse>, tonic::Status>;
-        /// Get an indexes metadatas.
-        async fn list_indexes_metadatas(
-            &self,
-            request: tonic::Request<super::ListIndexesMetadatasRequest>,
-        ) -> Result<tonic::Response<super::ListIndexesMetadatasResponse>, tonic::Status>;
-        /// Delete an index
-        async fn delete_index(
-            &self,
-            request: tonic::Request<super::DeleteIndexRequest>,
-        ) -> Result<tonic::Response<super::DeleteIndexResponse>, tonic::Status>;
-        /// Get all splits from index.
-        async fn list_all_splits(
-            &self,
-            request: tonic::Request<super::ListAllSplitsRequest>,
-        ) -> Result<tonic::Response<super::ListSplitsResponse>, tonic::Status>;
-        /// Get splits from index.
-        async fn list_splits(
-            &self,
-            request: tonic::Request<super::ListSplitsRequest>,
-        ) -> Result<tonic::Response<super::ListSplitsResponse>, tonic::Status>;
-        /// Stage split.
-        async fn stage_split(
-            &self,
-            request: tonic::Request<super::StageSplitRequest>,
-        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
-        /// Publish split.
-        async fn publish_splits(
-            &self,
-            request: tonic::Request<super::PublishSplitsRequest>,
-        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
-        /// Mark splits for deletion.
-        async fn mark_splits_for_deletion(
-            &self,
-            request: tonic::Request<super::MarkSplitsForDeletionRequest>,
-        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
-        /// Delete splits.
-        async fn delete_splits(
-            &self,
-            request: tonic::Request<super::DeleteSplitsRequest>,
-        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
-        /// Add source.
-        async fn add_source(
-            &self,
-            request: tonic::Request<super::AddSourceRequest>,
-        ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status>;
-        /// Remove source.
-        async fn delete_source(
-            &self,
-            request: tonic::Request<super::DeleteSourceRequest>,
-        ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status>;
-        /// Resets source checkpoint.
-        async fn reset_source_checkpoint(
-            &self,
-            request: tonic::Request<super::ResetSourceCheckpointRequest>,
-        ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status>;
-    }
-    #[derive(Debug)]
-    pub struct MetastoreApiServiceServer<T: MetastoreApiService> {
-        inner: _Inner<T>,
-        accept_compression_encodings: EnabledCompressionEncodings,
-        send_compression_encodings: EnabledCompressionEncodings,
-    }
-    struct _Inner<T>(Arc<T>);
-    impl<T: MetastoreApiService> MetastoreApiServiceServer<T> {
-        pub fn new(inner: T) -> Self {
-            Self::from_arc(Arc::new(inner))
-        }
-        pub fn from_arc(inner: Arc<T>) -> Self {
-            let inner = _Inner(inner);
-            Self {
-                inner,
-                accept_compression_encodings: Default::default(),
-                send_compression_encodings: Default::default(),
-            }
+        /// Create a delete task.
+        pub async fn create_delete_task(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CreateDeleteTaskRequest>,
+        ) -> Result<tonic::Response<super::DeleteTaskResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/create_delete_task",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
         }
-        pub fn with_interceptor<F>(
-            inner: T,
-            interceptor: F,
-        ) -> InterceptedService<Self, F>
-        where
-            F: tonic::service::Interceptor,
-        {
-            InterceptedService::new(Self::new(inner), interceptor)
+        /// Get the opstamp of the last delete task of an index.
+        pub async fn last_delete_opstamp(
+            &mut self,
+            request: impl tonic::IntoRequest<super::LastDeleteOpstampRequest>,
+        ) -> Result<tonic::Response<super::LastDeleteOpstampResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/last_delete_opstamp",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
         }
-        /// Enable decompressing requests with the given encoding.
-        #[must_use]
-        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
-            self.accept_compression_encodings.enable(encoding);
-            self
+        /// Get the delete tasks of an index created after an opstamp.
+        pub async fn list_delete_tasks(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListDeleteTasksRequest>,
+        ) -> Result<tonic::Response<super::ListDeleteTasksResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/list_delete_tasks",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
         }
-        /// Compress responses with the given encoding, if the client supports it.
-        #[must_use]
-        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
-            self.send_compression_encodings.enable(encoding);
-            self
+        /// Get the published splits of an index lagging behind a delete opstamp, whose time range
+        /// matches one of their pending delete tasks.
+        pub async fn list_splits_for_delete_task(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListSplitsForDeleteTaskRequest>,
+        ) -> Result<tonic::Response<super::ListSplitsResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/list_splits_for_delete_task",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
         }
-    }
-    impl<T, B> tonic::codegen::Service<http::Request<B>> for MetastoreApiServiceServer<T>
-    where
-        T: MetastoreApiService,
-        B: Body + Send + 'static,
-        B::Error: Into<StdError> + Send + 'static,
-    {
-        type Response = http::Response<tonic::body::BoxBody>;
-        type Error = std::convert::Infallible;
-        type Future = BoxFuture<Self::Response, Self::Error>;
-        fn poll_ready(
+        /// Update the delete opstamp of splits.
+        pub async fn update_splits_delete_opstamp(
             &mut self,
-            _cx: &mut Context<'_>,
-        ) -> Poll<Result<(), Self::Error>> {
-            Poll::Ready(Ok(()))
+            request: impl tonic::IntoRequest<super::UpdateSplitsDeleteOpstampRequest>,
+        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/update_splits_delete_opstamp",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
         }
-        fn call(&mut self, req: http::Request<B>) -> Self::Future {
-            let inner = self.inner.clone();
-            match req.uri().path() {
-                "/quickwit_metastore_api.MetastoreApiService/create_index" => {
+        /// Amend the tags of published splits.
+        pub async fn amend_splits_tags(
+            &mut self,
+            request: impl tonic::IntoRequest<super::AmendSplitsTagsRequest>,
+        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/amend_splits_tags",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Enables or disables the maintenance mode of an index.
+        pub async fn set_index_maintenance_mode(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SetIndexMaintenanceModeRequest>,
+        ) -> Result<
+            tonic::Response<super::SetIndexMaintenanceModeResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/set_index_maintenance_mode",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Enables or disables a feature flag of an index.
+        pub async fn set_index_feature_flag(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SetIndexFeatureFlagRequest>,
+        ) -> Result<
+            tonic::Response<super::SetIndexFeatureFlagResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/set_index_feature_flag",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Acquires or renews the merge planner lease of an index.
+        pub async fn acquire_merge_planner_lease(
+            &mut self,
+            request: impl tonic::IntoRequest<super::AcquireMergePlannerLeaseRequest>,
+        ) -> Result<
+            tonic::Response<super::AcquireMergePlannerLeaseResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/acquire_merge_planner_lease",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Records a file that a source could not read in the quarantine of the source.
+        pub async fn quarantine_source_file(
+            &mut self,
+            request: impl tonic::IntoRequest<super::QuarantineSourceFileRequest>,
+        ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/quarantine_source_file",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Updates the doc mapping and the search settings of an index.
+        pub async fn update_index(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UpdateIndexRequest>,
+        ) -> Result<tonic::Response<super::UpdateIndexResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/update_index",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod metastore_api_service_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    ///Generated trait containing gRPC methods that should be implemented for use with MetastoreApiServiceServer.
+    #[async_trait]
+    pub trait MetastoreApiService: Send + Sync + 'static {
+        /// Create an index.
+        async fn create_index(
+            &self,
+            request: tonic::Request<super::CreateIndexRequest>,
+        ) -> Result<tonic::Response<super::CreateIndexResponse>, tonic::Status>;
+        /// Get an index metadata.
+        async fn index_metadata(
+            &self,
+            request: tonic::Request<super::IndexMetadataRequest>,
+        ) -> Result<tonic::Response<super::IndexMetadataResponse>, tonic::Status>;
+        /// Get an indexes metadatas.
+        async fn list_indexes_metadatas(
+            &self,
+            request: tonic::Request<super::ListIndexesMetadatasRequest>,
+        ) -> Result<tonic::Response<super::ListIndexesMetadatasResponse>, tonic::Status>;
+        /// Get the metadatas of the indexes of a namespace.
+        async fn list_indexes_metadatas_in_namespace(
+            &self,
+            request: tonic::Request<super::ListIndexesMetadatasInNamespaceRequest>,
+        ) -> Result<tonic::Response<super::ListIndexesMetadatasResponse>, tonic::Status>;
+        /// Delete an index
+        async fn delete_index(
+            &self,
+            request: tonic::Request<super::DeleteIndexRequest>,
+        ) -> Result<tonic::Response<super::DeleteIndexResponse>, tonic::Status>;
+        ///Server streaming response type for the list_all_splits method.
+        type list_all_splitsStream: futures_core::Stream<
+                Item = Result<super::ListSplitsResponse, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        /// Get all splits from index, streamed in batches.
+        async fn list_all_splits(
+            &self,
+            request: tonic::Request<super::ListAllSplitsRequest>,
+        ) -> Result<tonic::Response<Self::list_all_splitsStream>, tonic::Status>;
+        ///Server streaming response type for the list_splits method.
+        type list_splitsStream: futures_core::Stream<
+                Item = Result<super::ListSplitsResponse, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        /// Get splits from index, streamed in batches.
+        async fn list_splits(
+            &self,
+            request: tonic::Request<super::ListSplitsRequest>,
+        ) -> Result<tonic::Response<Self::list_splitsStream>, tonic::Status>;
+        /// Get a page of the splits from index matching a query, sorted by split ID.
+        async fn list_splits_page(
+            &self,
+            request: tonic::Request<super::ListSplitsPageRequest>,
+        ) -> Result<tonic::Response<super::ListSplitsResponse>, tonic::Status>;
+        /// Stage split.
+        async fn stage_split(
+            &self,
+            request: tonic::Request<super::StageSplitRequest>,
+        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
+        /// Publish split.
+        async fn publish_splits(
+            &self,
+            request: tonic::Request<super::PublishSplitsRequest>,
+        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
+        /// Mark splits for deletion.
+        async fn mark_splits_for_deletion(
+            &self,
+            request: tonic::Request<super::MarkSplitsForDeletionRequest>,
+        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
+        /// Delete splits.
+        async fn delete_splits(
+            &self,
+            request: tonic::Request<super::DeleteSplitsRequest>,
+        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
+        /// Add source.
+        async fn add_source(
+            &self,
+            request: tonic::Request<super::AddSourceRequest>,
+        ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status>;
+        /// Remove source.
+        async fn delete_source(
+            &self,
+            request: tonic::Request<super::DeleteSourceRequest>,
+        ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status>;
+        /// Resets source checkpoint.
+        async fn reset_source_checkpoint(
+            &self,
+            request: tonic::Request<super::ResetSourceCheckpointRequest>,
+        ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status>;
+        /// Create a delete task.
+        async fn create_delete_task(
+            &self,
+            request: tonic::Request<super::CreateDeleteTaskRequest>,
+        ) -> Result<tonic::Response<super::DeleteTaskResponse>, tonic::Status>;
+        /// Get the opstamp of the last delete task of an index.
+        async fn last_delete_opstamp(
+            &self,
+            request: tonic::Request<super::LastDeleteOpstampRequest>,
+        ) -> Result<tonic::Response<super::LastDeleteOpstampResponse>, tonic::Status>;
+        /// Get the delete tasks of an index created after an opstamp.
+        async fn list_delete_tasks(
+            &self,
+            request: tonic::Request<super::ListDeleteTasksRequest>,
+        ) -> Result<tonic::Response<super::ListDeleteTasksResponse>, tonic::Status>;
+        /// Get the published splits of an index lagging behind a delete opstamp, whose time range
+        /// matches one of their pending delete tasks.
+        async fn list_splits_for_delete_task(
+            &self,
+            request: tonic::Request<super::ListSplitsForDeleteTaskRequest>,
+        ) -> Result<tonic::Response<super::ListSplitsResponse>, tonic::Status>;
+        /// Update the delete opstamp of splits.
+        async fn update_splits_delete_opstamp(
+            &self,
+            request: tonic::Request<super::UpdateSplitsDeleteOpstampRequest>,
+        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
+        /// Amend the tags of published splits.
+        async fn amend_splits_tags(
+            &self,
+            request: tonic::Request<super::AmendSplitsTagsRequest>,
+        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
+        /// Enables or disables the maintenance mode of an index.
+        async fn set_index_maintenance_mode(
+            &self,
+            request: tonic::Request<super::SetIndexMaintenanceModeRequest>,
+        ) -> Result<
+            tonic::Response<super::SetIndexMaintenanceModeResponse>,
+            tonic::Status,
+        >;
+        /// Enables or disables a feature flag of an index.
+        async fn set_index_feature_flag(
+            &self,
+            request: tonic::Request<super::SetIndexFeatureFlagRequest>,
+        ) -> Result<
+            tonic::Response<super::SetIndexFeatureFlagResponse>,
+            tonic::Status,
+        >;
+        /// Acquires or renews the merge planner lease of an index.
+        async fn acquire_merge_planner_lease(
+            &self,
+            request: tonic::Request<super::AcquireMergePlannerLeaseRequest>,
+        ) -> Result<
+            tonic::Response<super::AcquireMergePlannerLeaseResponse>,
+            tonic::Status,
+        >;
+        /// Records a file that a source could not read in the quarantine of the source.
+        async fn quarantine_source_file(
+            &self,
+            request: tonic::Request<super::QuarantineSourceFileRequest>,
+        ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status>;
+        /// Updates the doc mapping and the search settings of an index.
+        async fn update_index(
+            &self,
+            request: tonic::Request<super::UpdateIndexRequest>,
+        ) -> Result<tonic::Response<super::UpdateIndexResponse>, tonic::Status>;
+    }
+    #[derive(Debug)]
+    pub struct MetastoreApiServiceServer<T: MetastoreApiService> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: MetastoreApiService> MetastoreApiServiceServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for MetastoreApiServiceServer<T>
+    where
+        T: MetastoreApiService,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/quickwit_metastore_api.MetastoreApiService/create_index" => {
+                    #[allow(non_camel_case_types)]
+                    struct create_indexSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::CreateIndexRequest>
+                    for create_indexSvc<T> {
+                        type Response = super::CreateIndexResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CreateIndexRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).create_index(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = create_indexSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/index_metadata" => {
+                    #[allow(non_camel_case_types)]
+                    struct index_metadataSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::IndexMetadataRequest>
+                    for index_metadataSvc<T> {
+                        type Response = super::IndexMetadataResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::IndexMetadataRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).index_metadata(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = index_metadataSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/list_indexes_metadatas" => {
+                    #[allow(non_camel_case_types)]
+                    struct list_indexes_metadatasSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::ListIndexesMetadatasRequest>
+                    for list_indexes_metadatasSvc<T> {
+                        type Response = super::ListIndexesMetadatasResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListIndexesMetadatasRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).list_indexes_metadatas(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = list_indexes_metadatasSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/list_indexes_metadatas_in_namespace" => {
+                    #[allow(non_camel_case_types)]
+                    struct list_indexes_metadatas_in_namespaceSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::ListIndexesMetadatasInNamespaceRequest>
+                    for list_indexes_metadatas_in_namespaceSvc<T> {
+                        type Response = super::ListIndexesMetadatasResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListIndexesMetadatasInNamespaceRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).list_indexes_metadatas_in_namespace(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = list_indexes_metadatas_in_namespaceSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/delete_index" => {
+                    #[allow(non_camel_case_types)]
+                    struct delete_indexSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::DeleteIndexRequest>
+                    for delete_indexSvc<T> {
+                        type Response = super::DeleteIndexResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DeleteIndexRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).delete_index(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = delete_indexSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/list_all_splits" => {
+                    #[allow(non_camel_case_types)]
+                    struct list_all_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::ServerStreamingService<super::ListAllSplitsRequest>
+                    for list_all_splitsSvc<T> {
+                        type Response = super::ListSplitsResponse;
+                        type ResponseStream = T::list_all_splitsStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListAllSplitsRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).list_all_splits(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = list_all_splitsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/list_splits" => {
+                    #[allow(non_camel_case_types)]
+                    struct list_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::ServerStreamingService<super::ListSplitsRequest>
+                    for list_splitsSvc<T> {
+                        type Response = super::ListSplitsResponse;
+                        type ResponseStream = T::list_splitsStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListSplitsRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).list_splits(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = list_splitsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/list_splits_page" => {
+                    #[allow(non_camel_case_types)]
+                    struct list_splits_pageSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::ListSplitsPageRequest>
+                    for list_splits_pageSvc<T> {
+                        type Response = super::ListSplitsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListSplitsPageRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).list_splits_page(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = list_splits_pageSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/stage_split" => {
+                    #[allow(non_camel_case_types)]
+                    struct stage_splitSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::StageSplitRequest>
+                    for stage_splitSvc<T> {
+                        type Response = super::SplitResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::StageSplitRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).stage_split(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = stage_splitSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/publish_splits" => {
+                    #[allow(non_camel_case_types)]
+                    struct publish_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::PublishSplitsRequest>
+                    for publish_splitsSvc<T> {
+                        type Response = super::SplitResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::PublishSplitsRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).publish_splits(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = publish_splitsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/mark_splits_for_deletion" => {
+                    #[allow(non_camel_case_types)]
+                    struct mark_splits_for_deletionSvc<T: MetastoreApiService>(
+                        pub Arc<T>,
+                    );
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::MarkSplitsForDeletionRequest>
+                    for mark_splits_for_deletionSvc<T> {
+                        type Response = super::SplitResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::MarkSplitsForDeletionRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).mark_splits_for_deletion(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = mark_splits_for_deletionSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/delete_splits" => {
                     #[allow(non_camel_case_types)]
-                    struct create_indexSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct delete_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::CreateIndexRequest>
-                    for create_indexSvc<T> {
-                        type Response = super::CreateIndexResponse;
+                    > tonic::server::UnaryService<super::DeleteSplitsRequest>
+                    for delete_splitsSvc<T> {
+                        type Response = super::SplitResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::CreateIndexRequest>,
+                            request: tonic::Request<super::DeleteSplitsRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).create_index(request).await
+                                (*inner).delete_splits(request).await
                             };
                             Box::pin(fut)
                         }
@@ -628,7 +1640,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = create_indexSvc(inner);
+                        let method = delete_splitsSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -640,25 +1652,63 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/index_metadata" => {
+                "/quickwit_metastore_api.MetastoreApiService/add_source" => {
                     #[allow(non_camel_case_types)]
-                    struct index_metadataSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct add_sourceSvc<T: MetastoreApiService>(pub Arc<T>);
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::IndexMetadataRequest>
-                    for index_metadataSvc<T> {
-                        type Response = super::IndexMetadataResponse;
+                    > tonic::server::UnaryService<super::AddSourceRequest>
+                    for add_sourceSvc<T> {
+                        type Response = super::SourceResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::IndexMetadataRequest>,
+                            request: tonic::Request<super::AddSourceRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).add_source(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = add_sourceSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/delete_source" => {
+                    #[allow(non_camel_case_types)]
+                    struct delete_sourceSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::DeleteSourceRequest>
+                    for delete_sourceSvc<T> {
+                        type Response = super::SourceResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DeleteSourceRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).index_metadata(request).await
+                                (*inner).delete_source(request).await
                             };
                             Box::pin(fut)
                         }
@@ -668,7 +1718,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = index_metadataSvc(inner);
+                        let method = delete_sourceSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -680,25 +1730,27 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/list_indexes_metadatas" => {
+                "/quickwit_metastore_api.MetastoreApiService/reset_source_checkpoint" => {
                     #[allow(non_camel_case_types)]
-                    struct list_indexes_metadatasSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct reset_source_checkpointSvc<T: MetastoreApiService>(
+                        pub Arc<T>,
+                    );
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::ListIndexesMetadatasRequest>
-                    for list_indexes_metadatasSvc<T> {
-                        type Response = super::ListIndexesMetadatasResponse;
+                    > tonic::server::UnaryService<super::ResetSourceCheckpointRequest>
+                    for reset_source_checkpointSvc<T> {
+                        type Response = super::SourceResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::ListIndexesMetadatasRequest>,
+                            request: tonic::Request<super::ResetSourceCheckpointRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).list_indexes_metadatas(request).await
+                                (*inner).reset_source_checkpoint(request).await
                             };
                             Box::pin(fut)
                         }
@@ -708,7 +1760,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = list_indexes_metadatasSvc(inner);
+                        let method = reset_source_checkpointSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -720,25 +1772,27 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/delete_index" => {
+                "/quickwit_metastore_api.MetastoreApiService/create_delete_task" => {
                     #[allow(non_camel_case_types)]
-                    struct delete_indexSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct create_delete_taskSvc<T: MetastoreApiService>(
+                        pub Arc<T>,
+                    );
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::DeleteIndexRequest>
-                    for delete_indexSvc<T> {
-                        type Response = super::DeleteIndexResponse;
+                    > tonic::server::UnaryService<super::CreateDeleteTaskRequest>
+                    for create_delete_taskSvc<T> {
+                        type Response = super::DeleteTaskResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::DeleteIndexRequest>,
+                            request: tonic::Request<super::CreateDeleteTaskRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).delete_index(request).await
+                                (*inner).create_delete_task(request).await
                             };
                             Box::pin(fut)
                         }
@@ -748,7 +1802,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = delete_indexSvc(inner);
+                        let method = create_delete_taskSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -760,25 +1814,27 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/list_all_splits" => {
+                "/quickwit_metastore_api.MetastoreApiService/last_delete_opstamp" => {
                     #[allow(non_camel_case_types)]
-                    struct list_all_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct last_delete_opstampSvc<T: MetastoreApiService>(
+                        pub Arc<T>,
+                    );
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::ListAllSplitsRequest>
-                    for list_all_splitsSvc<T> {
-                        type Response = super::ListSplitsResponse;
+                    > tonic::server::UnaryService<super::LastDeleteOpstampRequest>
+                    for last_delete_opstampSvc<T> {
+                        type Response = super::LastDeleteOpstampResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::ListAllSplitsRequest>,
+                            request: tonic::Request<super::LastDeleteOpstampRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).list_all_splits(request).await
+                                (*inner).last_delete_opstamp(request).await
                             };
                             Box::pin(fut)
                         }
@@ -788,7 +1844,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = list_all_splitsSvc(inner);
+                        let method = last_delete_opstampSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -800,13 +1856,57 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/list_splits" => {
+                "/quickwit_metastore_api.MetastoreApiService/list_delete_tasks" => {
                     #[allow(non_camel_case_types)]
-                    struct list_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct list_delete_tasksSvc<T: MetastoreApiService>(
+                        pub Arc<T>,
+                    );
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::ListSplitsRequest>
-                    for list_splitsSvc<T> {
+                    > tonic::server::UnaryService<super::ListDeleteTasksRequest>
+                    for list_delete_tasksSvc<T> {
+                        type Response = super::ListDeleteTasksResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListDeleteTasksRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).list_delete_tasks(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = list_delete_tasksSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/list_splits_for_delete_task" => {
+                    #[allow(non_camel_case_types)]
+                    struct list_splits_for_delete_taskSvc<T: MetastoreApiService>(
+                        pub Arc<T>,
+                    );
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::ListSplitsForDeleteTaskRequest>
+                    for list_splits_for_delete_taskSvc<T> {
                         type Response = super::ListSplitsResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
@@ -814,10 +1914,12 @@ pub mod metastore_api_service_server {
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::ListSplitsRequest>,
+                            request: tonic::Request<super::ListSplitsForDeleteTaskRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
-                            let fut = async move { (*inner).list_splits(request).await };
+                            let fut = async move {
+                                (*inner).list_splits_for_delete_task(request).await
+                            };
                             Box::pin(fut)
                         }
                     }
@@ -826,7 +1928,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = list_splitsSvc(inner);
+                        let method = list_splits_for_delete_taskSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -838,13 +1940,15 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/stage_split" => {
+                "/quickwit_metastore_api.MetastoreApiService/update_splits_delete_opstamp" => {
                     #[allow(non_camel_case_types)]
-                    struct stage_splitSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct update_splits_delete_opstampSvc<T: MetastoreApiService>(
+                        pub Arc<T>,
+                    );
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::StageSplitRequest>
-                    for stage_splitSvc<T> {
+                    > tonic::server::UnaryService<super::UpdateSplitsDeleteOpstampRequest>
+                    for update_splits_delete_opstampSvc<T> {
                         type Response = super::SplitResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
@@ -852,10 +1956,12 @@ pub mod metastore_api_service_server {
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::StageSplitRequest>,
+                            request: tonic::Request<super::UpdateSplitsDeleteOpstampRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
-                            let fut = async move { (*inner).stage_split(request).await };
+                            let fut = async move {
+                                (*inner).update_splits_delete_opstamp(request).await
+                            };
                             Box::pin(fut)
                         }
                     }
@@ -864,7 +1970,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = stage_splitSvc(inner);
+                        let method = update_splits_delete_opstampSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -876,13 +1982,15 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/publish_splits" => {
+                "/quickwit_metastore_api.MetastoreApiService/amend_splits_tags" => {
                     #[allow(non_camel_case_types)]
-                    struct publish_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct amend_splits_tagsSvc<T: MetastoreApiService>(
+                        pub Arc<T>,
+                    );
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::PublishSplitsRequest>
-                    for publish_splitsSvc<T> {
+                    > tonic::server::UnaryService<super::AmendSplitsTagsRequest>
+                    for amend_splits_tagsSvc<T> {
                         type Response = super::SplitResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
@@ -890,11 +1998,11 @@ pub mod metastore_api_service_server {
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::PublishSplitsRequest>,
+                            request: tonic::Request<super::AmendSplitsTagsRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).publish_splits(request).await
+                                (*inner).amend_splits_tags(request).await
                             };
                             Box::pin(fut)
                         }
@@ -904,7 +2012,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = publish_splitsSvc(inner);
+                        let method = amend_splits_tagsSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -916,27 +2024,27 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/mark_splits_for_deletion" => {
+                "/quickwit_metastore_api.MetastoreApiService/set_index_maintenance_mode" => {
                     #[allow(non_camel_case_types)]
-                    struct mark_splits_for_deletionSvc<T: MetastoreApiService>(
+                    struct set_index_maintenance_modeSvc<T: MetastoreApiService>(
                         pub Arc<T>,
                     );
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::MarkSplitsForDeletionRequest>
-                    for mark_splits_for_deletionSvc<T> {
-                        type Response = super::SplitResponse;
+                    > tonic::server::UnaryService<super::SetIndexMaintenanceModeRequest>
+                    for set_index_maintenance_modeSvc<T> {
+                        type Response = super::SetIndexMaintenanceModeResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::MarkSplitsForDeletionRequest>,
+                            request: tonic::Request<super::SetIndexMaintenanceModeRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).mark_splits_for_deletion(request).await
+                                (*inner).set_index_maintenance_mode(request).await
                             };
                             Box::pin(fut)
                         }
@@ -946,7 +2054,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = mark_splits_for_deletionSvc(inner);
+                        let method = set_index_maintenance_modeSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -958,25 +2066,27 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/delete_splits" => {
+                "/quickwit_metastore_api.MetastoreApiService/set_index_feature_flag" => {
                     #[allow(non_camel_case_types)]
-                    struct delete_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct set_index_feature_flagSvc<T: MetastoreApiService>(
+                        pub Arc<T>,
+                    );
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::DeleteSplitsRequest>
-                    for delete_splitsSvc<T> {
-                        type Response = super::SplitResponse;
+                    > tonic::server::UnaryService<super::SetIndexFeatureFlagRequest>
+                    for set_index_feature_flagSvc<T> {
+                        type Response = super::SetIndexFeatureFlagResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::DeleteSplitsRequest>,
+                            request: tonic::Request<super::SetIndexFeatureFlagRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).delete_splits(request).await
+                                (*inner).set_index_feature_flag(request).await
                             };
                             Box::pin(fut)
                         }
@@ -986,7 +2096,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = delete_splitsSvc(inner);
+                        let method = set_index_feature_flagSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -998,24 +2108,28 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/add_source" => {
+                "/quickwit_metastore_api.MetastoreApiService/acquire_merge_planner_lease" => {
                     #[allow(non_camel_case_types)]
-                    struct add_sourceSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct acquire_merge_planner_leaseSvc<T: MetastoreApiService>(
+                        pub Arc<T>,
+                    );
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::AddSourceRequest>
-                    for add_sourceSvc<T> {
-                        type Response = super::SourceResponse;
+                    > tonic::server::UnaryService<super::AcquireMergePlannerLeaseRequest>
+                    for acquire_merge_planner_leaseSvc<T> {
+                        type Response = super::AcquireMergePlannerLeaseResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::AddSourceRequest>,
+                            request: tonic::Request<super::AcquireMergePlannerLeaseRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
-                            let fut = async move { (*inner).add_source(request).await };
+                            let fut = async move {
+                                (*inner).acquire_merge_planner_lease(request).await
+                            };
                             Box::pin(fut)
                         }
                     }
@@ -1024,7 +2138,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = add_sourceSvc(inner);
+                        let method = acquire_merge_planner_leaseSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1036,13 +2150,15 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/delete_source" => {
+                "/quickwit_metastore_api.MetastoreApiService/quarantine_source_file" => {
                     #[allow(non_camel_case_types)]
-                    struct delete_sourceSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct quarantine_source_fileSvc<T: MetastoreApiService>(
+                        pub Arc<T>,
+                    );
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::DeleteSourceRequest>
-                    for delete_sourceSvc<T> {
+                    > tonic::server::UnaryService<super::QuarantineSourceFileRequest>
+                    for quarantine_source_fileSvc<T> {
                         type Response = super::SourceResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
@@ -1050,11 +2166,11 @@ pub mod metastore_api_service_server {
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::DeleteSourceRequest>,
+                            request: tonic::Request<super::QuarantineSourceFileRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).delete_source(request).await
+                                (*inner).quarantine_source_file(request).await
                             };
                             Box::pin(fut)
                         }
@@ -1064,7 +2180,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = delete_sourceSvc(inner);
+                        let method = quarantine_source_fileSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1076,27 +2192,27 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/reset_source_checkpoint" => {
+                "/quickwit_metastore_api.MetastoreApiService/update_index" => {
                     #[allow(non_camel_case_types)]
-                    struct reset_source_checkpointSvc<T: MetastoreApiService>(
+                    struct update_indexSvc<T: MetastoreApiService>(
                         pub Arc<T>,
                     );
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::ResetSourceCheckpointRequest>
-                    for reset_source_checkpointSvc<T> {
-                        type Response = super::SourceResponse;
+                    > tonic::server::UnaryService<super::UpdateIndexRequest>
+                    for update_indexSvc<T> {
+                        type Response = super::UpdateIndexResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::ResetSourceCheckpointRequest>,
+                            request: tonic::Request<super::UpdateIndexRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).reset_source_checkpoint(request).await
+                                (*inner).update_index(request).await
                             };
                             Box::pin(fut)
                         }
@@ -1106,7 +2222,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = reset_source_checkpointSvc(inner);
+                        let method = update_indexSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
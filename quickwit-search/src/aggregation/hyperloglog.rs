@@ -0,0 +1,182 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Number of bits of the hash used to pick a register. With 2^14 registers, the standard error of
+/// the estimate is about 0.8%.
+const PRECISION: u32 = 14;
+
+const NUM_REGISTERS: usize = 1 << PRECISION;
+
+/// HyperLogLog sketch, used to estimate the number of distinct values of a set of values.
+///
+/// The sketch is made of 16KB of registers, regardless of the number of values it summarizes, and
+/// two sketches are merged by taking the maximum of their registers, so merging is lossless.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HyperLogLog {
+    #[serde(
+        serialize_with = "serialize_registers",
+        deserialize_with = "deserialize_registers"
+    )]
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        HyperLogLog {
+            registers: vec![0; NUM_REGISTERS],
+        }
+    }
+}
+
+impl HyperLogLog {
+    /// Adds a hash, computed with [`hash_u64`] or [`hash_bytes`], to the sketch.
+    pub fn insert_hash(&mut self, hash: u64) {
+        let register_idx = (hash >> (64 - PRECISION)) as usize;
+        // The sentinel bit caps the rank at `64 - PRECISION + 1`.
+        let rank = ((hash << PRECISION) | (1 << (PRECISION - 1))).leading_zeros() + 1;
+        let register = &mut self.registers[register_idx];
+        *register = (*register).max(rank as u8);
+    }
+
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        for (register, other_register) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(*other_register);
+        }
+    }
+
+    pub fn estimate(&self) -> u64 {
+        let num_registers = NUM_REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / num_registers);
+        let mut sum = 0.0;
+        let mut num_zero_registers = 0;
+        for &register in &self.registers {
+            sum += 1.0 / (1u64 << register) as f64;
+            if register == 0 {
+                num_zero_registers += 1;
+            }
+        }
+        let raw_estimate = alpha * num_registers * num_registers / sum;
+        // Linear counting is more accurate for small cardinalities.
+        if raw_estimate <= 2.5 * num_registers && num_zero_registers > 0 {
+            let linear_estimate = num_registers * (num_registers / num_zero_registers as f64).ln();
+            return linear_estimate.round() as u64;
+        }
+        raw_estimate.round() as u64
+    }
+}
+
+/// Hashes a fast field value. The hash must not depend on the node computing it, since the
+/// sketches of different searchers are merged.
+pub fn hash_u64(value: u64) -> u64 {
+    // Finalizer of MurmurHash3.
+    let mut hash = value;
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51afd7ed558ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ceb9fe1a85ec53);
+    hash ^= hash >> 33;
+    hash
+}
+
+/// Hashes a term. See [`hash_u64`].
+pub fn hash_bytes(bytes: &[u8]) -> u64 {
+    // FNV-1a, whose bits are then mixed by the MurmurHash3 finalizer.
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash_u64(hash)
+}
+
+fn serialize_registers<S>(registers: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+where S: Serializer {
+    serializer.serialize_str(&base64::encode(registers))
+}
+
+fn deserialize_registers<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where D: Deserializer<'de> {
+    let registers_base64 = String::deserialize(deserializer)?;
+    let registers = base64::decode(registers_base64).map_err(serde::de::Error::custom)?;
+    if registers.len() != NUM_REGISTERS {
+        return Err(serde::de::Error::custom(format!(
+            "Expected {} HyperLogLog registers, got {}.",
+            NUM_REGISTERS,
+            registers.len()
+        )));
+    }
+    Ok(registers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_estimate_within(hll: &HyperLogLog, expected: u64, relative_error: f64) {
+        let estimate = hll.estimate();
+        let error = (estimate as f64 - expected as f64).abs() / expected as f64;
+        assert!(
+            error <= relative_error,
+            "expected={} estimate={}",
+            expected,
+            estimate
+        );
+    }
+
+    #[test]
+    fn test_hyperloglog_estimate() {
+        let mut hll = HyperLogLog::default();
+        assert_eq!(hll.estimate(), 0);
+        for value in 0..10u64 {
+            hll.insert_hash(hash_u64(value));
+            hll.insert_hash(hash_u64(value));
+        }
+        assert_eq!(hll.estimate(), 10);
+        for value in 10..100_000u64 {
+            hll.insert_hash(hash_u64(value));
+        }
+        assert_estimate_within(&hll, 100_000, 0.03);
+    }
+
+    #[test]
+    fn test_hyperloglog_merge() {
+        let mut left_hll = HyperLogLog::default();
+        let mut right_hll = HyperLogLog::default();
+        for value in 0..30_000u64 {
+            left_hll.insert_hash(hash_bytes(format!("term-{}", value).as_bytes()));
+        }
+        for value in 20_000..50_000u64 {
+            right_hll.insert_hash(hash_bytes(format!("term-{}", value).as_bytes()));
+        }
+        let right_hll_json = serde_json::to_string(&right_hll).unwrap();
+        let right_hll: HyperLogLog = serde_json::from_str(&right_hll_json).unwrap();
+        left_hll.merge(&right_hll);
+        assert_estimate_within(&left_hll, 50_000, 0.03);
+    }
+
+    #[test]
+    fn test_hyperloglog_deserialize_invalid_registers() {
+        let error = serde_json::from_str::<HyperLogLog>(r#"{"registers": "AAAA"}"#).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Expected 16384 HyperLogLog registers, got 3."));
+    }
+}
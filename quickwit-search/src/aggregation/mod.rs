@@ -0,0 +1,588 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Aggregations computed with sketches: percentiles, backed by a t-digest, and cardinality,
+//! backed by a HyperLogLog.
+//!
+//! Tantivy does not provide these aggregations, so the top-level `percentiles` and `cardinality`
+//! entries of an aggregation request are split from the entries handled by tantivy. Leaves
+//! serialize their sketches along with the tantivy intermediate results, so that the root merges
+//! them without losing accuracy, in bounded memory.
+
+mod hyperloglog;
+mod tdigest;
+
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+use serde::de::Error as _;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use tantivy::aggregation::agg_req::{
+    get_fast_field_names, get_term_dict_field_names, Aggregations,
+};
+use tantivy::aggregation::agg_result::AggregationResults;
+use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
+use tantivy::fastfield::{
+    DynamicFastFieldReader, FastFieldReader, FastValue, MultiValuedFastFieldReader,
+};
+use tantivy::schema::{FieldType, Schema};
+use tantivy::{DocId, InvertedIndexReader, SegmentReader, TantivyError};
+
+use self::hyperloglog::{hash_bytes, hash_u64, HyperLogLog};
+use self::tdigest::TDigest;
+
+const DEFAULT_PERCENTS: [f64; 7] = [1.0, 5.0, 25.0, 50.0, 75.0, 95.0, 99.0];
+
+const DEFAULT_COMPRESSION: f64 = 100.0;
+
+/// Number of values buffered by a segment collector before they are inserted into its t-digest.
+const PERCENTILES_BUFFER_LEN: usize = 4_096;
+
+fn default_percents() -> Vec<f64> {
+    DEFAULT_PERCENTS.to_vec()
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TDigestSettings {
+    #[serde(default = "TDigestSettings::default_compression")]
+    pub compression: f64,
+}
+
+impl TDigestSettings {
+    fn default_compression() -> f64 {
+        DEFAULT_COMPRESSION
+    }
+}
+
+impl Default for TDigestSettings {
+    fn default() -> Self {
+        TDigestSettings {
+            compression: DEFAULT_COMPRESSION,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PercentilesAggregation {
+    pub field: String,
+    #[serde(default = "default_percents")]
+    pub percents: Vec<f64>,
+    #[serde(default)]
+    pub tdigest: TDigestSettings,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CardinalityAggregation {
+    pub field: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SketchAggregation {
+    Percentiles(PercentilesAggregation),
+    Cardinality(CardinalityAggregation),
+}
+
+impl SketchAggregation {
+    fn field_name(&self) -> &str {
+        match self {
+            SketchAggregation::Percentiles(percentiles) => &percentiles.field,
+            SketchAggregation::Cardinality(cardinality) => &cardinality.field,
+        }
+    }
+
+    fn validate(&self) -> serde_json::Result<()> {
+        if let SketchAggregation::Percentiles(percentiles) = self {
+            if let Some(percent) = percentiles
+                .percents
+                .iter()
+                .find(|percent| !(0.0..=100.0).contains(*percent))
+            {
+                return Err(serde_json::Error::custom(format!(
+                    "percents must be between 0 and 100, got {}",
+                    percent
+                )));
+            }
+            if percentiles.tdigest.compression <= 0.0 {
+                return Err(serde_json::Error::custom(format!(
+                    "tdigest compression must be strictly positive, got {}",
+                    percentiles.tdigest.compression
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Aggregation request of a search request, made of the aggregations computed by tantivy and of
+/// the top-level sketch aggregations.
+#[derive(Clone, Debug)]
+pub struct QuickwitAggregations {
+    pub tantivy_aggs: Option<Aggregations>,
+    pub sketch_aggs: BTreeMap<String, SketchAggregation>,
+}
+
+impl QuickwitAggregations {
+    pub fn from_json_str(aggregation_request: &str) -> serde_json::Result<Self> {
+        let aggregations: JsonMap<String, JsonValue> = serde_json::from_str(aggregation_request)?;
+        let mut tantivy_aggs_json = JsonMap::new();
+        let mut sketch_aggs = BTreeMap::new();
+        for (aggregation_name, aggregation_json) in aggregations {
+            let is_sketch_aggregation = aggregation_json
+                .as_object()
+                .map(|aggregation| {
+                    aggregation.contains_key("percentiles")
+                        || aggregation.contains_key("cardinality")
+                })
+                .unwrap_or(false);
+            if is_sketch_aggregation {
+                let sketch_aggregation: SketchAggregation =
+                    serde_json::from_value(aggregation_json)?;
+                sketch_aggregation.validate()?;
+                sketch_aggs.insert(aggregation_name, sketch_aggregation);
+            } else {
+                tantivy_aggs_json.insert(aggregation_name, aggregation_json);
+            }
+        }
+        let tantivy_aggs = if sketch_aggs.is_empty() {
+            // Parsing the original request keeps the position of the errors in their message.
+            Some(serde_json::from_str(aggregation_request)?)
+        } else if tantivy_aggs_json.is_empty() {
+            None
+        } else {
+            Some(serde_json::from_value(JsonValue::Object(
+                tantivy_aggs_json,
+            ))?)
+        };
+        Ok(QuickwitAggregations {
+            tantivy_aggs,
+            sketch_aggs,
+        })
+    }
+
+    pub fn fast_field_names(&self) -> HashSet<String> {
+        let mut fast_field_names = HashSet::new();
+        if let Some(tantivy_aggs) = &self.tantivy_aggs {
+            fast_field_names.extend(get_fast_field_names(tantivy_aggs));
+        }
+        for sketch_aggregation in self.sketch_aggs.values() {
+            fast_field_names.insert(sketch_aggregation.field_name().to_string());
+        }
+        fast_field_names
+    }
+
+    /// Returns the fields whose term dictionary must be warmed up. `schema` is used to find the
+    /// text fields of the cardinality aggregations, whose terms are looked up from their ordinals.
+    pub fn term_dict_field_names(&self, schema: &Schema) -> HashSet<String> {
+        let mut term_dict_field_names = HashSet::new();
+        if let Some(tantivy_aggs) = &self.tantivy_aggs {
+            term_dict_field_names.extend(get_term_dict_field_names(tantivy_aggs));
+        }
+        for sketch_aggregation in self.sketch_aggs.values() {
+            if let SketchAggregation::Cardinality(cardinality) = sketch_aggregation {
+                let is_text_field = schema
+                    .get_field(&cardinality.field)
+                    .map(|field| {
+                        matches!(
+                            schema.get_field_entry(field).field_type(),
+                            FieldType::Str(_)
+                        )
+                    })
+                    .unwrap_or(false);
+                if is_text_field {
+                    term_dict_field_names.insert(cardinality.field.clone());
+                }
+            }
+        }
+        term_dict_field_names
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntermediateSketch {
+    Percentiles(TDigest),
+    Cardinality(HyperLogLog),
+}
+
+impl IntermediateSketch {
+    fn merge(&mut self, other: IntermediateSketch) {
+        match (self, other) {
+            (IntermediateSketch::Percentiles(digest), IntermediateSketch::Percentiles(other)) => {
+                digest.merge(other)
+            }
+            (IntermediateSketch::Cardinality(hll), IntermediateSketch::Cardinality(other)) => {
+                hll.merge(&other)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Intermediate aggregation results exchanged between the leaves and the root.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct QuickwitIntermediateAggregationResults {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tantivy: Option<IntermediateAggregationResults>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub sketches: BTreeMap<String, IntermediateSketch>,
+}
+
+impl QuickwitIntermediateAggregationResults {
+    pub fn merge_fruits(&mut self, other: QuickwitIntermediateAggregationResults) {
+        if let Some(other_tantivy) = other.tantivy {
+            if let Some(tantivy) = self.tantivy.as_mut() {
+                tantivy.merge_fruits(other_tantivy);
+            } else {
+                self.tantivy = Some(other_tantivy);
+            }
+        }
+        for (aggregation_name, other_sketch) in other.sketches {
+            if let Some(sketch) = self.sketches.get_mut(&aggregation_name) {
+                sketch.merge(other_sketch);
+            } else {
+                self.sketches.insert(aggregation_name, other_sketch);
+            }
+        }
+    }
+
+    /// Computes the final aggregation results, serialized in JSON.
+    pub fn into_final_result(
+        mut self,
+        aggregations: QuickwitAggregations,
+    ) -> crate::Result<String> {
+        let mut final_result = JsonMap::new();
+        if let (Some(tantivy), Some(tantivy_aggs)) = (self.tantivy, aggregations.tantivy_aggs) {
+            let tantivy_result: AggregationResults =
+                tantivy.into_final_bucket_result(tantivy_aggs)?;
+            if let JsonValue::Object(tantivy_result_json) = serde_json::to_value(&tantivy_result)? {
+                final_result.extend(tantivy_result_json);
+            }
+        }
+        for (aggregation_name, sketch_aggregation) in aggregations.sketch_aggs {
+            let sketch_opt = self.sketches.remove(&aggregation_name);
+            let sketch_result = match sketch_aggregation {
+                SketchAggregation::Percentiles(percentiles) => {
+                    let digest = match sketch_opt {
+                        Some(IntermediateSketch::Percentiles(digest)) => digest,
+                        _ => TDigest::new(percentiles.tdigest.compression),
+                    };
+                    let values: JsonMap<String, JsonValue> = percentiles
+                        .percents
+                        .iter()
+                        .map(|percent| {
+                            (
+                                format!("{:?}", percent),
+                                json!(digest.quantile(percent / 100.0)),
+                            )
+                        })
+                        .collect();
+                    json!({ "values": values })
+                }
+                SketchAggregation::Cardinality(_) => {
+                    let hll = match sketch_opt {
+                        Some(IntermediateSketch::Cardinality(hll)) => hll,
+                        _ => HyperLogLog::default(),
+                    };
+                    json!({ "value": hll.estimate() })
+                }
+            };
+            final_result.insert(aggregation_name, sketch_result);
+        }
+        Ok(serde_json::to_string(&final_result)?)
+    }
+}
+
+enum SketchFieldCollector {
+    Percentiles {
+        fast_field_reader: DynamicFastFieldReader<u64>,
+        to_f64: fn(u64) -> f64,
+        values: Vec<f64>,
+        digest: TDigest,
+    },
+    Cardinality {
+        fast_field_reader: DynamicFastFieldReader<u64>,
+        hll: HyperLogLog,
+    },
+    /// Collects the ordinals of the terms of a text field, which are resolved into terms when
+    /// the segment is harvested, since ordinals are specific to a segment.
+    TermCardinality {
+        term_ords_reader: MultiValuedFastFieldReader<u64>,
+        inverted_index: Arc<InvertedIndexReader>,
+        term_ords_bitset: Vec<u64>,
+        term_ords: Vec<u64>,
+    },
+}
+
+impl SketchFieldCollector {
+    fn for_segment(
+        sketch_aggregation: &SketchAggregation,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Self> {
+        let field_name = sketch_aggregation.field_name();
+        let schema = segment_reader.schema();
+        let field = schema
+            .get_field(field_name)
+            .ok_or_else(|| TantivyError::FieldNotFound(field_name.to_string()))?;
+        let field_type = schema.get_field_entry(field).field_type();
+        match sketch_aggregation {
+            SketchAggregation::Percentiles(percentiles) => {
+                let to_f64: fn(u64) -> f64 = match field_type {
+                    FieldType::U64(_) => |value| value as f64,
+                    FieldType::I64(_) => |value| i64::from_u64(value) as f64,
+                    FieldType::F64(_) => f64::from_u64,
+                    _ => {
+                        return Err(TantivyError::InvalidArgument(format!(
+                            "Percentiles aggregation is only supported on u64, i64, and f64 \
+                             fields, but field `{}` is of type {:?}.",
+                            field_name,
+                            field_type.value_type()
+                        )))
+                    }
+                };
+                Ok(SketchFieldCollector::Percentiles {
+                    fast_field_reader: segment_reader.fast_fields().u64_lenient(field)?,
+                    to_f64,
+                    values: Vec::with_capacity(PERCENTILES_BUFFER_LEN),
+                    digest: TDigest::new(percentiles.tdigest.compression),
+                })
+            }
+            SketchAggregation::Cardinality(_) => {
+                if let FieldType::Str(_) = field_type {
+                    let inverted_index = segment_reader.inverted_index(field)?;
+                    let num_terms = inverted_index.terms().num_terms();
+                    return Ok(SketchFieldCollector::TermCardinality {
+                        term_ords_reader: segment_reader.fast_fields().u64s(field)?,
+                        inverted_index,
+                        term_ords_bitset: vec![0; (num_terms + 63) / 64],
+                        term_ords: Vec::new(),
+                    });
+                }
+                Ok(SketchFieldCollector::Cardinality {
+                    fast_field_reader: segment_reader.fast_fields().u64_lenient(field)?,
+                    hll: HyperLogLog::default(),
+                })
+            }
+        }
+    }
+
+    fn collect(&mut self, doc_id: DocId) {
+        match self {
+            SketchFieldCollector::Percentiles {
+                fast_field_reader,
+                to_f64,
+                values,
+                digest,
+            } => {
+                values.push(to_f64(fast_field_reader.get(doc_id)));
+                if values.len() >= PERCENTILES_BUFFER_LEN {
+                    digest.insert_all(values);
+                    values.clear();
+                }
+            }
+            SketchFieldCollector::Cardinality {
+                fast_field_reader,
+                hll,
+            } => {
+                hll.insert_hash(hash_u64(fast_field_reader.get(doc_id)));
+            }
+            SketchFieldCollector::TermCardinality {
+                term_ords_reader,
+                term_ords_bitset,
+                term_ords,
+                ..
+            } => {
+                term_ords_reader.get_vals(doc_id, term_ords);
+                for &term_ord in term_ords.iter() {
+                    term_ords_bitset[(term_ord / 64) as usize] |= 1 << (term_ord % 64);
+                }
+            }
+        }
+    }
+
+    fn harvest(self) -> tantivy::Result<IntermediateSketch> {
+        match self {
+            SketchFieldCollector::Percentiles {
+                values, mut digest, ..
+            } => {
+                digest.insert_all(&values);
+                Ok(IntermediateSketch::Percentiles(digest))
+            }
+            SketchFieldCollector::Cardinality { hll, .. } => {
+                Ok(IntermediateSketch::Cardinality(hll))
+            }
+            SketchFieldCollector::TermCardinality {
+                inverted_index,
+                term_ords_bitset,
+                ..
+            } => {
+                let mut hll = HyperLogLog::default();
+                let mut term_bytes = Vec::new();
+                for (block_idx, &block) in term_ords_bitset.iter().enumerate() {
+                    let mut remaining_bits = block;
+                    while remaining_bits != 0 {
+                        let term_ord =
+                            block_idx as u64 * 64 + remaining_bits.trailing_zeros() as u64;
+                        remaining_bits &= remaining_bits - 1;
+                        if inverted_index
+                            .terms()
+                            .ord_to_term(term_ord, &mut term_bytes)?
+                        {
+                            hll.insert_hash(hash_bytes(&term_bytes));
+                        }
+                    }
+                }
+                Ok(IntermediateSketch::Cardinality(hll))
+            }
+        }
+    }
+}
+
+/// Collects the sketches of the sketch aggregations of a request at the scale of a segment.
+pub struct SketchSegmentCollector {
+    field_collectors: Vec<(String, SketchFieldCollector)>,
+}
+
+impl SketchSegmentCollector {
+    pub fn for_segment(
+        sketch_aggs: &BTreeMap<String, SketchAggregation>,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Self> {
+        let field_collectors = sketch_aggs
+            .iter()
+            .map(|(aggregation_name, sketch_aggregation)| {
+                let field_collector =
+                    SketchFieldCollector::for_segment(sketch_aggregation, segment_reader)?;
+                Ok((aggregation_name.clone(), field_collector))
+            })
+            .collect::<tantivy::Result<_>>()?;
+        Ok(SketchSegmentCollector { field_collectors })
+    }
+
+    pub fn collect(&mut self, doc_id: DocId) {
+        for (_, field_collector) in self.field_collectors.iter_mut() {
+            field_collector.collect(doc_id);
+        }
+    }
+
+    pub fn harvest(self) -> tantivy::Result<BTreeMap<String, IntermediateSketch>> {
+        self.field_collectors
+            .into_iter()
+            .map(|(aggregation_name, field_collector)| {
+                Ok((aggregation_name, field_collector.harvest()?))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quickwit_aggregations_from_json_str() {
+        let aggregations = QuickwitAggregations::from_json_str(
+            r#"{
+                "latency_percentiles": {"percentiles": {"field": "latency", "percents": [50, 99]}},
+                "num_services": {"cardinality": {"field": "service"}},
+                "latency_stats": {"stats": {"field": "latency"}}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(aggregations.tantivy_aggs.unwrap().len(), 1);
+        assert_eq!(
+            aggregations.sketch_aggs["latency_percentiles"],
+            SketchAggregation::Percentiles(PercentilesAggregation {
+                field: "latency".to_string(),
+                percents: vec![50.0, 99.0],
+                tdigest: TDigestSettings::default(),
+            })
+        );
+        assert_eq!(
+            aggregations.sketch_aggs["num_services"],
+            SketchAggregation::Cardinality(CardinalityAggregation {
+                field: "service".to_string(),
+            })
+        );
+        let aggregations = QuickwitAggregations::from_json_str(
+            r#"{"latency_percentiles": {"percentiles": {"field": "latency"}}}"#,
+        )
+        .unwrap();
+        assert!(aggregations.tantivy_aggs.is_none());
+        let error = QuickwitAggregations::from_json_str(
+            r#"{"latency_percentiles": {"percentiles": {"field": "latency", "percents": [101]}}}"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "percents must be between 0 and 100, got 101"
+        );
+    }
+
+    #[test]
+    fn test_quickwit_intermediate_aggregation_results_merge_and_finalize() {
+        let aggregations = QuickwitAggregations::from_json_str(
+            r#"{
+                "latency_percentiles": {"percentiles": {"field": "latency", "percents": [50]}},
+                "num_services": {"cardinality": {"field": "service"}},
+                "empty_percentiles": {"percentiles": {"field": "latency", "percents": [50]}}
+            }"#,
+        )
+        .unwrap();
+        let leaf_results = |values: &[f64], services: &[&str]| {
+            let mut digest = TDigest::new(100.0);
+            digest.insert_all(values);
+            let mut hll = HyperLogLog::default();
+            for service in services {
+                hll.insert_hash(hash_bytes(service.as_bytes()));
+            }
+            let results = QuickwitIntermediateAggregationResults {
+                tantivy: None,
+                sketches: BTreeMap::from_iter([
+                    (
+                        "latency_percentiles".to_string(),
+                        IntermediateSketch::Percentiles(digest),
+                    ),
+                    (
+                        "num_services".to_string(),
+                        IntermediateSketch::Cardinality(hll),
+                    ),
+                ]),
+            };
+            let results_json = serde_json::to_string(&results).unwrap();
+            serde_json::from_str::<QuickwitIntermediateAggregationResults>(&results_json).unwrap()
+        };
+        let mut results = leaf_results(&[1.0, 2.0], &["api", "db"]);
+        results.merge_fruits(leaf_results(&[3.0, 4.0], &["db", "cache"]));
+        let final_result_json = results.into_final_result(aggregations).unwrap();
+        let final_result: JsonValue = serde_json::from_str(&final_result_json).unwrap();
+        assert_eq!(
+            final_result,
+            json!({
+                "latency_percentiles": {"values": {"50.0": 2.5}},
+                "num_services": {"value": 3},
+                "empty_percentiles": {"values": {"50.0": null}},
+            })
+        );
+    }
+}
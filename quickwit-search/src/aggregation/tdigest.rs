@@ -0,0 +1,222 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+struct Centroid {
+    mean: f64,
+    weight: u64,
+}
+
+impl Centroid {
+    fn merge(&mut self, other: Centroid) {
+        self.weight += other.weight;
+        self.mean += (other.mean - self.mean) * other.weight as f64 / self.weight as f64;
+    }
+}
+
+/// Merging t-digest sketch, used to estimate the percentiles of a set of values.
+///
+/// The number of centroids of the digest is bounded by its compression, regardless of the number
+/// of values it summarizes. Centroids are small near the extremes of the distribution, so that
+/// the tail percentiles remain accurate, and two digests can be merged without losing more
+/// accuracy than compressing the union of their values.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TDigest {
+    compression: f64,
+    centroids: Vec<Centroid>,
+    count: u64,
+    min: f64,
+    max: f64,
+}
+
+impl TDigest {
+    pub fn new(compression: f64) -> Self {
+        TDigest {
+            compression,
+            centroids: Vec::new(),
+            count: 0,
+            min: f64::MAX,
+            max: f64::MIN,
+        }
+    }
+
+    /// Adds a batch of values to the digest. NaN values are ignored.
+    pub fn insert_all(&mut self, values: &[f64]) {
+        let mut centroids = Vec::with_capacity(self.centroids.len() + values.len());
+        for &value in values {
+            if value.is_nan() {
+                continue;
+            }
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+            centroids.push(Centroid {
+                mean: value,
+                weight: 1,
+            });
+        }
+        if centroids.is_empty() {
+            return;
+        }
+        self.count += centroids.len() as u64;
+        centroids.extend_from_slice(&self.centroids);
+        self.compress(centroids);
+    }
+
+    pub fn merge(&mut self, other: TDigest) {
+        if other.count == 0 {
+            return;
+        }
+        self.count += other.count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        let mut centroids = other.centroids;
+        centroids.extend_from_slice(&self.centroids);
+        self.compress(centroids);
+    }
+
+    /// Estimates the value below which the given fraction `quantile` (between 0 and 1) of the
+    /// values fall. Returns `None` if the digest is empty.
+    pub fn quantile(&self, quantile: f64) -> Option<f64> {
+        let first = self.centroids.first()?;
+        let last = self.centroids.last()?;
+        if quantile <= 0.0 {
+            return Some(self.min);
+        }
+        if quantile >= 1.0 {
+            return Some(self.max);
+        }
+        let rank = quantile * self.count as f64;
+        let first_half_weight = first.weight as f64 / 2.0;
+        if rank < first_half_weight {
+            return Some(interpolate(self.min, first.mean, rank / first_half_weight));
+        }
+        let mut weight_so_far = 0.0;
+        for window in self.centroids.windows(2) {
+            let (left, right) = (window[0], window[1]);
+            let left_center = weight_so_far + left.weight as f64 / 2.0;
+            let right_center = weight_so_far + left.weight as f64 + right.weight as f64 / 2.0;
+            if rank < right_center {
+                return Some(interpolate(
+                    left.mean,
+                    right.mean,
+                    (rank - left_center) / (right_center - left_center),
+                ));
+            }
+            weight_so_far += left.weight as f64;
+        }
+        let last_half_weight = last.weight as f64 / 2.0;
+        let last_center = self.count as f64 - last_half_weight;
+        Some(interpolate(
+            last.mean,
+            self.max,
+            (rank - last_center) / last_half_weight,
+        ))
+    }
+
+    /// Merges adjacent centroids as long as their weight stays below the size bound
+    /// `4 * count * q * (1 - q) / compression`, where `q` is the quantile of the merged centroid.
+    fn compress(&mut self, mut centroids: Vec<Centroid>) {
+        centroids.sort_unstable_by(|left, right| left.mean.total_cmp(&right.mean));
+        let total_weight = self.count as f64;
+        let mut compressed_centroids = Vec::new();
+        let mut centroids_iter = centroids.into_iter();
+        let mut current = match centroids_iter.next() {
+            Some(centroid) => centroid,
+            None => {
+                self.centroids = compressed_centroids;
+                return;
+            }
+        };
+        let mut weight_so_far = 0u64;
+        for centroid in centroids_iter {
+            let proposed_weight = (current.weight + centroid.weight) as f64;
+            let quantile = (weight_so_far as f64 + proposed_weight / 2.0) / total_weight;
+            let max_weight = 4.0 * total_weight * quantile * (1.0 - quantile) / self.compression;
+            if proposed_weight <= max_weight {
+                current.merge(centroid);
+            } else {
+                weight_so_far += current.weight;
+                compressed_centroids.push(current);
+                current = centroid;
+            }
+        }
+        compressed_centroids.push(current);
+        self.centroids = compressed_centroids;
+    }
+}
+
+fn interpolate(left: f64, right: f64, ratio: f64) -> f64 {
+    left + (right - left) * ratio.clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tdigest_empty() {
+        let digest = TDigest::new(100.0);
+        assert_eq!(digest.count, 0);
+        assert_eq!(digest.quantile(0.5), None);
+    }
+
+    #[test]
+    fn test_tdigest_small_set_is_exact() {
+        let mut digest = TDigest::new(100.0);
+        let values: Vec<f64> = (1..=100).map(|value| value as f64).collect();
+        digest.insert_all(&values);
+        digest.insert_all(&[f64::NAN]);
+        assert_eq!(digest.count, 100);
+        assert_eq!(digest.quantile(0.0), Some(1.0));
+        assert_eq!(digest.quantile(0.5), Some(50.5));
+        assert_eq!(digest.quantile(0.99), Some(99.5));
+        assert_eq!(digest.quantile(1.0), Some(100.0));
+    }
+
+    #[test]
+    fn test_tdigest_merge_is_accurate_and_bounded() {
+        let mut digest = TDigest::new(100.0);
+        for chunk in (0..100_000u64).collect::<Vec<_>>().chunks(1_000) {
+            let values: Vec<f64> = chunk
+                .iter()
+                .map(|value| ((value * 7_919) % 100_000) as f64)
+                .collect();
+            let mut chunk_digest = TDigest::new(100.0);
+            chunk_digest.insert_all(&values);
+            digest.merge(chunk_digest);
+        }
+        assert_eq!(digest.count, 100_000);
+        assert!(digest.centroids.len() < 1_000);
+        for quantile in [0.01, 0.25, 0.5, 0.75, 0.99] {
+            let expected = quantile * 100_000.0;
+            let estimate = digest.quantile(quantile).unwrap();
+            assert!(
+                (estimate - expected).abs() < 500.0,
+                "quantile={} estimate={}",
+                quantile,
+                estimate
+            );
+        }
+        let digest_json = serde_json::to_string(&digest).unwrap();
+        let deserialized_digest: TDigest = serde_json::from_str(&digest_json).unwrap();
+        assert_eq!(deserialized_digest, digest);
+    }
+}
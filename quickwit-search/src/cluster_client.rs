@@ -22,15 +22,16 @@ use quickwit_proto::{
     FetchDocsRequest, FetchDocsResponse, LeafSearchRequest, LeafSearchResponse,
     LeafSearchStreamRequest, LeafSearchStreamResponse,
 };
-use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
 use tokio::sync::mpsc::error::SendError;
-use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
-use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio::sync::mpsc::{channel, Sender};
+use tokio_stream::wrappers::ReceiverStream;
 use tracing::debug;
 
+use crate::aggregation::QuickwitIntermediateAggregationResults;
 use crate::retry::search::LeafSearchRetryPolicy;
 use crate::retry::search_stream::{LeafSearchStreamRetryPolicy, SuccessfullSplitIds};
 use crate::retry::{retry_client, DefaultRetryPolicy, RetryPolicy};
+use crate::search_stream::{is_export_request, LEAF_SEARCH_STREAM_CHANNEL_CAPACITY};
 use crate::{SearchClientPool, SearchError, SearchServiceClient};
 
 /// Client that executes placed requests (Request, `SearchServiceClient`) and provides
@@ -101,15 +102,27 @@ impl ClusterClient {
         &self,
         request: LeafSearchStreamRequest,
         mut client: SearchServiceClient,
-    ) -> UnboundedReceiverStream<crate::Result<LeafSearchStreamResponse>> {
+    ) -> ReceiverStream<crate::Result<LeafSearchStreamResponse>> {
         // We need a dedicated channel to send results with retry. First we send only the successful
         // responses and and ignore errors. If there are some errors, we make one retry and
         // in this case we send all results.
-        let (result_sender, result_receiver) = unbounded_channel();
+        let (result_sender, result_receiver) = channel(LEAF_SEARCH_STREAM_CHANNEL_CAPACITY);
         let client_pool = self.client_pool.clone();
         let retry_policy = LeafSearchStreamRetryPolicy {};
+        // Export requests stream several responses per split: a split failing after some of its
+        // responses were forwarded cannot be retried without duplicating them, so all results
+        // are forwarded as is.
+        let is_export = request
+            .request
+            .as_ref()
+            .map(is_export_request)
+            .unwrap_or(false);
         tokio::spawn(async move {
             let result_stream = client.leaf_search_stream(request.clone()).await;
+            if is_export {
+                let _ = forward_leaf_search_stream(result_stream, result_sender, true).await;
+                return;
+            }
             // Forward only responses and not errors to the sender as we will make one retry on
             // errors.
             let forward_result =
@@ -125,7 +138,7 @@ impl ClusterClient {
                     Ok(retry_client) => retry_client,
                     Err(error) => {
                         // Propagates the error if we cannot get a new client and stops the task.
-                        let _ = result_sender.send(Err(SearchError::from(error)));
+                        let _ = result_sender.send(Err(SearchError::from(error))).await;
                         return;
                     }
                 };
@@ -142,7 +155,7 @@ impl ClusterClient {
             }
         });
 
-        UnboundedReceiverStream::new(result_receiver)
+        ReceiverStream::new(result_receiver)
     }
 }
 
@@ -161,9 +174,10 @@ fn merge_leaf_search_results(
                 .map(|res1_str| {
                     if let Some(res2_str) = retry_response.intermediate_aggregation_result.as_ref()
                     {
-                        let mut res1: IntermediateAggregationResults =
+                        let mut res1: QuickwitIntermediateAggregationResults =
                             serde_json::from_str(&res1_str)?;
-                        let res2: IntermediateAggregationResults = serde_json::from_str(res2_str)?;
+                        let res2: QuickwitIntermediateAggregationResults =
+                            serde_json::from_str(res2_str)?;
                         res1.merge_fruits(res2);
                         serde_json::to_string(&res1)
                     } else {
@@ -193,8 +207,8 @@ fn merge_leaf_search_results(
 // If `send_error` is false, errors are ignored and not forwarded. This is
 // useful if you want to make a retry before propagating errors.
 async fn forward_leaf_search_stream(
-    mut stream: UnboundedReceiverStream<crate::Result<LeafSearchStreamResponse>>,
-    sender: UnboundedSender<crate::Result<LeafSearchStreamResponse>>,
+    mut stream: ReceiverStream<crate::Result<LeafSearchStreamResponse>>,
+    sender: Sender<crate::Result<LeafSearchStreamResponse>>,
     send_error: bool,
 ) -> Result<SuccessfullSplitIds, SendError<crate::Result<LeafSearchStreamResponse>>> {
     let mut successful_split_ids: Vec<String> = Vec::new();
@@ -202,11 +216,11 @@ async fn forward_leaf_search_stream(
         match result {
             Ok(response) => {
                 successful_split_ids.push(response.split_id.clone());
-                sender.send(Ok(response))?;
+                sender.send(Ok(response)).await?;
             }
             Err(error) => {
                 if send_error {
-                    sender.send(Err(error))?;
+                    sender.send(Err(error)).await?;
                 }
             }
         }
@@ -292,6 +306,7 @@ mod tests {
             fast_field: "fast".to_string(),
             output_format: 0,
             partition_by_field: None,
+            fast_fields: Vec::new(),
         };
         LeafSearchStreamRequest {
             request: Some(search_request),
@@ -522,20 +537,24 @@ mod tests {
             .expect_leaf_search_stream()
             .return_once(|_| Err(SearchError::InternalError("error".to_string())));
         let mut mock_service_2 = MockSearchService::new();
-        let (result_sender, result_receiver) = tokio::sync::mpsc::unbounded_channel();
+        let (result_sender, result_receiver) = tokio::sync::mpsc::channel(2);
         mock_service_2
             .expect_leaf_search_stream()
-            .return_once(|_| Ok(UnboundedReceiverStream::new(result_receiver)));
+            .return_once(|_| Ok(ReceiverStream::new(result_receiver)));
         let client_pool =
             SearchClientPool::from_mocks(vec![Arc::new(mock_service_1), Arc::new(mock_service_2)])
                 .await?;
-        result_sender.send(Ok(LeafSearchStreamResponse {
-            data: Vec::new(),
-            split_id: "split_1".to_string(),
-        }))?;
-        result_sender.send(Err(SearchError::InternalError(
-            "last split error".to_string(),
-        )))?;
+        result_sender
+            .send(Ok(LeafSearchStreamResponse {
+                data: Vec::new(),
+                split_id: "split_1".to_string(),
+            }))
+            .await?;
+        result_sender
+            .send(Err(SearchError::InternalError(
+                "last split error".to_string(),
+            )))
+            .await?;
         drop(result_sender);
         let client_hashmap = client_pool.clients();
         let first_grpc_addr: SocketAddr = "127.0.0.1:20000".parse()?;
@@ -23,16 +23,15 @@ use std::collections::{BinaryHeap, HashSet};
 use itertools::Itertools;
 use quickwit_doc_mapper::{DocMapper, SortBy, SortOrder};
 use quickwit_proto::{LeafSearchResponse, PartialHit, SearchRequest};
-use tantivy::aggregation::agg_req::{
-    get_fast_field_names, get_term_dict_field_names, Aggregations,
-};
-use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
 use tantivy::aggregation::AggregationSegmentCollector;
 use tantivy::collector::{Collector, SegmentCollector};
 use tantivy::fastfield::{DynamicFastFieldReader, FastFieldReader};
 use tantivy::schema::Schema;
 use tantivy::{DocId, Score, SegmentOrdinal, SegmentReader};
 
+use crate::aggregation::{
+    QuickwitAggregations, QuickwitIntermediateAggregationResults, SketchSegmentCollector,
+};
 use crate::filters::{TimestampFilter, TimestampFilterBuilder};
 use crate::partial_hit_sorting_key;
 
@@ -152,6 +151,44 @@ impl PartialEq for PartialHitHeapItem {
 
 impl Eq for PartialHitHeapItem {}
 
+/// Skips the documents of a segment ranked before the `search_after` hit of the request, or equal
+/// to it, following the order of `partial_hit_sorting_key`.
+struct SearchAfterFilter {
+    sorting_field_value: u64,
+    /// Documents tied with the `search_after` hit on the sorting field are ranked after it if
+    /// their `DocId` is greater than or equal to this value. `None` if they are all ranked before
+    /// it.
+    first_doc_id_on_tie: Option<DocId>,
+}
+
+impl SearchAfterFilter {
+    fn for_segment(search_after: &PartialHit, split_id: &str, segment_ord: SegmentOrdinal) -> Self {
+        let first_doc_id_on_tie = match (split_id, segment_ord)
+            .cmp(&(search_after.split_id.as_str(), search_after.segment_ord))
+        {
+            Ordering::Less => None,
+            Ordering::Equal => search_after.doc_id.checked_add(1),
+            Ordering::Greater => Some(0),
+        };
+        SearchAfterFilter {
+            sorting_field_value: search_after.sorting_field_value,
+            first_doc_id_on_tie,
+        }
+    }
+
+    fn is_after(&self, sorting_field_value: u64, doc_id: DocId) -> bool {
+        // Hits are ranked by decreasing sorting field value.
+        match sorting_field_value.cmp(&self.sorting_field_value) {
+            Ordering::Greater => false,
+            Ordering::Less => true,
+            Ordering::Equal => self
+                .first_doc_id_on_tie
+                .map(|first_doc_id| doc_id >= first_doc_id)
+                .unwrap_or(false),
+        }
+    }
+}
+
 /// Quickwit collector working at the scale of the segment.
 pub struct QuickwitSegmentCollector {
     num_hits: u64,
@@ -160,8 +197,10 @@ pub struct QuickwitSegmentCollector {
     hits: BinaryHeap<PartialHitHeapItem>,
     max_hits: usize,
     segment_ord: u32,
+    search_after_filter_opt: Option<SearchAfterFilter>,
     timestamp_filter_opt: Option<TimestampFilter>,
     aggregation: Option<AggregationSegmentCollector>,
+    sketch_aggregation: Option<SketchSegmentCollector>,
 }
 
 impl QuickwitSegmentCollector {
@@ -171,6 +210,11 @@ impl QuickwitSegmentCollector {
 
     fn collect_top_k(&mut self, doc_id: DocId, score: Score) {
         let sorting_field_value: u64 = self.sort_by.compute_sorting_field(doc_id, score);
+        if let Some(search_after_filter) = &self.search_after_filter_opt {
+            if !search_after_filter.is_after(sorting_field_value, doc_id) {
+                return;
+            }
+        }
         if self.at_capacity() {
             if let Some(limit_sorting_field) = self.hits.peek().map(|head| head.sorting_field_value)
             {
@@ -213,6 +257,9 @@ impl SegmentCollector for QuickwitSegmentCollector {
         if let Some(aggregation_collector) = self.aggregation.as_mut() {
             aggregation_collector.collect(doc_id, score);
         }
+        if let Some(sketch_aggregation_collector) = self.sketch_aggregation.as_mut() {
+            sketch_aggregation_collector.collect(doc_id);
+        }
     }
 
     fn harvest(self) -> Self::Fruit {
@@ -231,14 +278,26 @@ impl SegmentCollector for QuickwitSegmentCollector {
             })
             .collect();
 
-        let intermediate_aggregation_result = if let Some(collector) = self.aggregation {
-            Some(
-                serde_json::to_string(&collector.harvest()?)
-                    .expect("could not serialize aggregation to json"),
-            )
-        } else {
-            None
-        };
+        let intermediate_aggregation_result =
+            if self.aggregation.is_some() || self.sketch_aggregation.is_some() {
+                let intermediate_aggregation_results = QuickwitIntermediateAggregationResults {
+                    tantivy: self
+                        .aggregation
+                        .map(|collector| collector.harvest())
+                        .transpose()?,
+                    sketches: self
+                        .sketch_aggregation
+                        .map(|collector| collector.harvest())
+                        .transpose()?
+                        .unwrap_or_default(),
+                };
+                Some(
+                    serde_json::to_string(&intermediate_aggregation_results)
+                        .expect("could not serialize aggregation to json"),
+                )
+            } else {
+                None
+            };
 
         Ok(LeafSearchResponse {
             intermediate_aggregation_result,
@@ -260,8 +319,12 @@ pub struct QuickwitCollector {
     pub start_offset: usize,
     pub max_hits: usize,
     pub sort_by: SortBy,
+    /// Only the hits ranked after this hit are collected. The documents ranked before it are
+    /// still counted and aggregated.
+    pub search_after: Option<PartialHit>,
     timestamp_filter_builder_opt: Option<TimestampFilterBuilder>,
-    pub aggregation: Option<Aggregations>,
+    pub aggregation: Option<QuickwitAggregations>,
+    aggregation_term_dict_field_names: HashSet<String>,
 }
 
 impl QuickwitCollector {
@@ -274,7 +337,7 @@ impl QuickwitCollector {
             }
         }
         if let Some(aggregate) = self.aggregation.as_ref() {
-            fast_field_names.extend(get_fast_field_names(aggregate));
+            fast_field_names.extend(aggregate.fast_field_names());
         }
         if let Some(timestamp_filter_builder) = &self.timestamp_filter_builder_opt {
             fast_field_names.insert(timestamp_filter_builder.timestamp_field_name.clone());
@@ -282,11 +345,7 @@ impl QuickwitCollector {
         fast_field_names
     }
     pub fn term_dict_field_names(&self) -> HashSet<String> {
-        let mut term_dict_field_names = HashSet::default();
-        if let Some(aggregate) = self.aggregation.as_ref() {
-            term_dict_field_names.extend(get_term_dict_field_names(aggregate));
-        }
-        term_dict_field_names
+        self.aggregation_term_dict_field_names.clone()
     }
 }
 
@@ -313,6 +372,10 @@ impl Collector for QuickwitCollector {
                 None
             };
 
+        let search_after_filter_opt = self.search_after.as_ref().map(|search_after| {
+            SearchAfterFilter::for_segment(search_after, &self.split_id, segment_ord)
+        });
+
         Ok(QuickwitSegmentCollector {
             num_hits: 0u64,
             split_id: self.split_id.clone(),
@@ -320,18 +383,26 @@ impl Collector for QuickwitCollector {
             hits: BinaryHeap::with_capacity(leaf_max_hits),
             segment_ord,
             max_hits: leaf_max_hits,
+            search_after_filter_opt,
             timestamp_filter_opt,
             aggregation: self
                 .aggregation
                 .as_ref()
-                .map(|aggs| {
+                .and_then(|aggs| aggs.tantivy_aggs.as_ref())
+                .map(|tantivy_aggs| {
                     AggregationSegmentCollector::from_agg_req_and_reader(
-                        aggs,
+                        tantivy_aggs,
                         segment_reader,
                         AGGREGATION_BUCKET_LIMIT,
                     )
                 })
                 .transpose()?,
+            sketch_aggregation: self
+                .aggregation
+                .as_ref()
+                .filter(|aggs| !aggs.sketch_aggs.is_empty())
+                .map(|aggs| SketchSegmentCollector::for_segment(&aggs.sketch_aggs, segment_reader))
+                .transpose()?,
         })
     }
 
@@ -386,7 +457,7 @@ fn merge_leaf_responses(
                 .as_ref()
                 .map(|res| serde_json::from_str(res))
         })
-        .collect::<Result<Vec<IntermediateAggregationResults>, _>>()?;
+        .collect::<Result<Vec<QuickwitIntermediateAggregationResults>, _>>()?;
 
     let intermediate_aggregation_result =
         intermediate_aggregation_results
@@ -449,10 +520,14 @@ pub fn make_collector_for_split(
     split_schema: &Schema,
 ) -> crate::Result<QuickwitCollector> {
     let aggregation = if let Some(agg) = search_request.aggregation_request.as_ref() {
-        Some(serde_json::from_str(agg)?)
+        Some(QuickwitAggregations::from_json_str(agg)?)
     } else {
         None
     };
+    let aggregation_term_dict_field_names = aggregation
+        .as_ref()
+        .map(|aggs| aggs.term_dict_field_names(split_schema))
+        .unwrap_or_default();
 
     let timestamp_field_opt = doc_mapper.timestamp_field(split_schema);
     let timestamp_filter_builder_opt = TimestampFilterBuilder::new(
@@ -467,18 +542,21 @@ pub fn make_collector_for_split(
         start_offset: search_request.start_offset as usize,
         max_hits: search_request.max_hits as usize,
         sort_by: search_request.into(),
+        search_after: search_request.search_after.clone(),
         timestamp_filter_builder_opt,
         aggregation,
+        aggregation_term_dict_field_names,
     })
 }
 
 /// Builds a QuickwitCollector that's only useful for merging fruits.
 ///
 /// This collector only needs `start_offset` & `max_hit` so the other attributes
-/// can be set to default.
+/// can be set to default. In particular, the leaves already dropped the hits ranked before
+/// `search_after`.
 pub fn make_merge_collector(search_request: &SearchRequest) -> crate::Result<QuickwitCollector> {
     let aggregation = if let Some(agg) = search_request.aggregation_request.as_ref() {
-        Some(serde_json::from_str(agg)?)
+        Some(QuickwitAggregations::from_json_str(agg)?)
     } else {
         None
     };
@@ -487,8 +565,10 @@ pub fn make_merge_collector(search_request: &SearchRequest) -> crate::Result<Qui
         start_offset: search_request.start_offset as usize,
         max_hits: search_request.max_hits as usize,
         sort_by: SortBy::DocId,
+        search_after: None,
         timestamp_filter_builder_opt: None,
         aggregation,
+        aggregation_term_dict_field_names: HashSet::default(),
     })
 }
 
@@ -499,8 +579,9 @@ mod tests {
     use proptest::prelude::*;
     use quickwit_proto::PartialHit;
 
-    use super::PartialHitHeapItem;
+    use super::{PartialHitHeapItem, SearchAfterFilter};
     use crate::collector::{f32_to_u64, top_k_partial_hits};
+    use crate::partial_hit_sorting_key;
 
     #[test]
     fn test_partial_hit_ordered_by_sorting_field() {
@@ -550,6 +631,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_search_after_filter_follows_partial_hit_order() {
+        let search_after = PartialHit {
+            sorting_field_value: 10u64,
+            split_id: "split_2".to_string(),
+            segment_ord: 1u32,
+            doc_id: 5u32,
+        };
+        for split_id in ["split_1", "split_2", "split_3"] {
+            for segment_ord in 0u32..3 {
+                let search_after_filter =
+                    SearchAfterFilter::for_segment(&search_after, split_id, segment_ord);
+                for sorting_field_value in [9u64, 10, 11] {
+                    for doc_id in [0u32, 5, 6] {
+                        let partial_hit = PartialHit {
+                            sorting_field_value,
+                            split_id: split_id.to_string(),
+                            segment_ord,
+                            doc_id,
+                        };
+                        assert_eq!(
+                            search_after_filter.is_after(sorting_field_value, doc_id),
+                            partial_hit_sorting_key(&partial_hit)
+                                > partial_hit_sorting_key(&search_after),
+                            "{:?}",
+                            partial_hit
+                        );
+                    }
+                }
+            }
+        }
+    }
+
     prop_compose! {
         // Turns out, zero's and negative zero's u64 representation is not same.
         // It is not relevant for our use case. For simplicity we filter the negative
@@ -28,7 +28,7 @@ use quickwit_proto::{FetchDocsResponse, PartialHit, SearchRequest, SplitIdAndFoo
 use quickwit_storage::Storage;
 use tantivy::query::{Query, QueryParserError};
 use tantivy::schema::{Field, Value};
-use tantivy::{ReloadPolicy, Score, Searcher, SnippetGenerator};
+use tantivy::{ReloadPolicy, Score, Searcher, Snippet, SnippetGenerator};
 use tracing::error;
 
 use crate::leaf::open_index_with_caches;
@@ -36,6 +36,8 @@ use crate::service::SearcherContext;
 use crate::GlobalDocAddress;
 
 const SNIPPET_MAX_NUM_CHARS: usize = 150;
+const SNIPPET_PRE_TAG: &str = "<b>";
+const SNIPPET_POST_TAG: &str = "</b>";
 
 /// Given a list of global doc address, fetches all the documents and
 /// returns them as a hashmap.
@@ -170,9 +172,18 @@ async fn fetch_docs_in_split(
     global_doc_addrs.sort_by_key(|doc| doc.doc_addr);
     // Opens the index without the ephemeral unbounded cache, this cache is indeed not useful
     // when fetching docs as we will fetch them only once.
-    let index = open_index_with_caches(&searcher_context, index_storage, split, false)
-        .await
-        .with_context(|| "open-index-for-split")?;
+    let doc_mapper_schema_opt = doc_mapper_opt
+        .as_ref()
+        .map(|doc_mapper| doc_mapper.schema());
+    let index = open_index_with_caches(
+        &searcher_context,
+        index_storage,
+        split,
+        false,
+        doc_mapper_schema_opt,
+    )
+    .await
+    .with_context(|| "open-index-for-split")?;
     let index_reader = index
         .reader_builder()
         // the docs are presorted so a cache size of NUM_CONCURRENT_REQUESTS is fine
@@ -246,6 +257,8 @@ async fn fetch_docs_in_split(
 #[derive(Clone)]
 struct FieldsSnippetGenerator {
     field_generators: Arc<HashMap<String, SnippetGenerator>>,
+    pre_tag: Arc<str>,
+    post_tag: Arc<str>,
 }
 
 impl FieldsSnippetGenerator {
@@ -262,7 +275,7 @@ impl FieldsSnippetGenerator {
                     value.as_text().and_then(|text| {
                         let snippet = snippet_generator.snippet(text);
                         match snippet.is_empty() {
-                            false => Some(snippet.to_html()),
+                            false => Some(render_snippet(&snippet, &self.pre_tag, &self.post_tag)),
                             _ => None,
                         }
                     })
@@ -287,17 +300,32 @@ async fn create_fields_snippet_generator(
 ) -> anyhow::Result<FieldsSnippetGenerator> {
     let schema = searcher.schema();
     let query = doc_mapper.query(schema.clone(), search_request)?;
+    let max_num_chars = search_request
+        .snippet_fragment_size
+        .map(|fragment_size| fragment_size as usize)
+        .unwrap_or(SNIPPET_MAX_NUM_CHARS);
     let mut snippet_generators = HashMap::new();
     for field_name in &search_request.snippet_fields {
         let field = schema
             .get_field(field_name)
             .ok_or_else(|| QueryParserError::FieldDoesNotExist(field_name.clone()))?;
-        let snippet_generator = create_snippet_generator(searcher, &*query, field).await?;
+        let snippet_generator =
+            create_snippet_generator(searcher, &*query, field, max_num_chars).await?;
         snippet_generators.insert(field_name.clone(), snippet_generator);
     }
 
     Ok(FieldsSnippetGenerator {
         field_generators: Arc::new(snippet_generators),
+        pre_tag: search_request
+            .snippet_pre_tag
+            .as_deref()
+            .unwrap_or(SNIPPET_PRE_TAG)
+            .into(),
+        post_tag: search_request
+            .snippet_post_tag
+            .as_deref()
+            .unwrap_or(SNIPPET_POST_TAG)
+            .into(),
     })
 }
 
@@ -306,6 +334,7 @@ async fn create_snippet_generator(
     searcher: &Searcher,
     query: &dyn Query,
     field: Field,
+    max_num_chars: usize,
 ) -> anyhow::Result<SnippetGenerator> {
     let mut terms = BTreeMap::new();
     query.query_terms(&mut terms);
@@ -330,6 +359,38 @@ async fn create_snippet_generator(
         terms_text,
         tokenizer,
         field,
-        SNIPPET_MAX_NUM_CHARS,
+        max_num_chars,
     ))
 }
+
+// Renders the fragment of a snippet, surrounding its highlighted terms with `pre_tag` and
+// `post_tag`. As in `Snippet::to_html`, the text of the fragment is HTML-escaped while the tags
+// are inserted as is.
+fn render_snippet(snippet: &Snippet, pre_tag: &str, post_tag: &str) -> String {
+    let fragment = snippet.fragment();
+    let mut rendered = String::with_capacity(fragment.len());
+    let mut start_from = 0;
+    for highlighted_section in snippet.highlighted() {
+        let (start, stop) = highlighted_section.bounds();
+        push_html_escaped(&mut rendered, &fragment[start_from..start]);
+        rendered.push_str(pre_tag);
+        push_html_escaped(&mut rendered, &fragment[start..stop]);
+        rendered.push_str(post_tag);
+        start_from = stop;
+    }
+    push_html_escaped(&mut rendered, &fragment[start_from..]);
+    rendered
+}
+
+fn push_html_escaped(buffer: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '"' => buffer.push_str("&quot;"),
+            '&' => buffer.push_str("&amp;"),
+            '\'' => buffer.push_str("&#x27;"),
+            '<' => buffer.push_str("&lt;"),
+            '>' => buffer.push_str("&gt;"),
+            _ => buffer.push(c),
+        }
+    }
+}
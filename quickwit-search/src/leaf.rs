@@ -26,8 +26,10 @@ use anyhow::Context;
 use futures::future::try_join_all;
 use futures::Future;
 use itertools::{Either, Itertools};
-use quickwit_directories::{CachingDirectory, HotDirectory, StorageDirectory};
-use quickwit_doc_mapper::{DocMapper, QUICKWIT_TOKENIZER_MANAGER};
+use quickwit_directories::{
+    CachingDirectory, HotDirectory, SchemaReconcilingDirectory, StorageDirectory,
+};
+use quickwit_doc_mapper::{DocMapper, TermExpansion, QUICKWIT_TOKENIZER_MANAGER};
 use quickwit_proto::{
     LeafSearchResponse, SearchRequest, SplitIdAndFooterOffsets, SplitSearchError,
 };
@@ -38,7 +40,7 @@ use tantivy::collector::Collector;
 use tantivy::directory::FileSlice;
 use tantivy::error::AsyncIoError;
 use tantivy::query::Query;
-use tantivy::schema::{Cardinality, FieldType};
+use tantivy::schema::{Cardinality, FieldType, Schema};
 use tantivy::{Index, ReloadPolicy, Searcher, Term};
 use tokio::task::spawn_blocking;
 use tracing::*;
@@ -86,12 +88,22 @@ async fn get_split_footer_from_cache_or_fetch(
 /// - A split footer cache given by `SearcherContext.split_footer_cache`.
 /// - A fast fields cache given by `SearcherContext.storage_long_term_cache`.
 /// - An ephemeral unbounded cache directory whose lifetime is tied to the returned `Index`.
+///
+/// The splits packaged on the node and not published yet are opened from the local split registry
+/// given by `SearcherContext.local_split_registry` instead.
 pub(crate) async fn open_index_with_caches(
     searcher_context: &Arc<SearcherContext>,
     index_storage: Arc<dyn Storage>,
     split_and_footer_offsets: &SplitIdAndFooterOffsets,
     ephemeral_unbounded_cache: bool,
+    doc_mapper_schema_opt: Option<Schema>,
 ) -> anyhow::Result<Index> {
+    if let Some(local_split) = searcher_context
+        .local_split_registry
+        .get(&split_and_footer_offsets.split_id)
+    {
+        return Ok(local_split.index().clone());
+    }
     let split_file = PathBuf::from(format!("{}.split", split_and_footer_offsets.split_id));
     let footer_data = get_split_footer_from_cache_or_fetch(
         index_storage.clone(),
@@ -116,7 +128,15 @@ pub(crate) async fn open_index_with_caches(
     } else {
         HotDirectory::open(directory, hotcache_bytes.read_bytes()?)?
     };
-    let mut index = Index::open(hot_directory)?;
+    // The splits created with an older doc mapping lack the fields added since then.
+    let mut index = if let Some(doc_mapper_schema) = doc_mapper_schema_opt {
+        Index::open(SchemaReconcilingDirectory::wrap(
+            Arc::new(hot_directory),
+            doc_mapper_schema,
+        ))?
+    } else {
+        Index::open(hot_directory)?
+    };
     index.set_tokenizers(QUICKWIT_TOKENIZER_MANAGER.clone());
     Ok(index)
 }
@@ -133,32 +153,46 @@ pub(crate) async fn open_index_with_caches(
 /// * `query` - query is used to extract the terms and their fields which will be loaded from the
 /// inverted_index.
 ///
+/// * `term_expansions` - The fuzzy and prefix terms of the query. The dictionaries of their fields
+/// are loaded to look up the terms they match, which are then loaded like the terms of the query.
+///
 /// * `term_dict_field_names` - A list of fields, where the whole dictionary needs to be loaded.
 /// This is e.g. required for term aggregation, since we don't know in advance which terms are going
 /// to be hit.
-#[instrument(skip(searcher, query, fast_field_names))]
+#[instrument(skip(searcher, query, term_expansions, fast_field_names))]
 pub(crate) async fn warmup(
     searcher: &Searcher,
     query: &dyn Query,
+    term_expansions: &[TermExpansion],
     fast_field_names: &HashSet<String>,
     term_dict_field_names: &HashSet<String>,
     requires_scoring: bool,
 ) -> anyhow::Result<()> {
     let warm_up_terms_future =
         warm_up_terms(searcher, query).instrument(debug_span!("warm_up_terms"));
+    let warm_up_term_expansions_future = warm_up_term_expansions(searcher, term_expansions)
+        .instrument(debug_span!("warm_up_term_expansions"));
     let warm_up_term_dict_future = warm_up_term_dict_fields(searcher, term_dict_field_names)
         .instrument(debug_span!("warm_up_term_dicts"));
     let warm_up_fastfields_future = warm_up_fastfields(searcher, fast_field_names)
         .instrument(debug_span!("warm_up_fastfields"));
     let warm_up_fieldnorms_future = warm_up_fieldnorms(searcher, requires_scoring)
         .instrument(debug_span!("warm_up_fieldnorms"));
-    let (warm_up_terms_res, warm_up_fastfields_res, warm_up_term_dict_res, warm_up_fieldnorms_res) = tokio::join!(
+    let (
+        warm_up_terms_res,
+        warm_up_term_expansions_res,
+        warm_up_fastfields_res,
+        warm_up_term_dict_res,
+        warm_up_fieldnorms_res,
+    ) = tokio::join!(
         warm_up_terms_future,
+        warm_up_term_expansions_future,
         warm_up_fastfields_future,
         warm_up_term_dict_future,
         warm_up_fieldnorms_future,
     );
     warm_up_terms_res?;
+    warm_up_term_expansions_res?;
     warm_up_fastfields_res?;
     warm_up_term_dict_res?;
     warm_up_fieldnorms_res?;
@@ -293,6 +327,28 @@ async fn warm_up_terms(searcher: &Searcher, query: &dyn Query) -> anyhow::Result
     Ok(())
 }
 
+async fn warm_up_term_expansions(
+    searcher: &Searcher,
+    term_expansions: &[TermExpansion],
+) -> anyhow::Result<()> {
+    let mut warm_up_futures = Vec::new();
+    for term_expansion in term_expansions {
+        for segment_reader in searcher.segment_readers() {
+            let inv_idx = segment_reader.inverted_index(term_expansion.field())?;
+            warm_up_futures.push(async move {
+                inv_idx.terms().warm_up_dictionary().await?;
+                let terms = term_expansion.expand(inv_idx.terms())?;
+                let warm_up_postings_futures =
+                    terms.iter().map(|term| inv_idx.warm_postings(term, false));
+                try_join_all(warm_up_postings_futures).await?;
+                anyhow::Result::<()>::Ok(())
+            });
+        }
+    }
+    try_join_all(warm_up_futures).await?;
+    Ok(())
+}
+
 async fn warm_up_fieldnorms(searcher: &Searcher, requires_scoring: bool) -> anyhow::Result<()> {
     if !requires_scoring {
         return Ok(());
@@ -321,7 +377,14 @@ async fn leaf_search_single_split(
     doc_mapper: Arc<dyn DocMapper>,
 ) -> crate::Result<LeafSearchResponse> {
     let split_id = split.split_id.to_string();
-    let index = open_index_with_caches(searcher_context, storage, &split, true).await?;
+    let index = open_index_with_caches(
+        searcher_context,
+        storage,
+        &split,
+        true,
+        Some(doc_mapper.schema()),
+    )
+    .await?;
     let split_schema = index.schema();
     let quickwit_collector = make_collector_for_split(
         split_id.clone(),
@@ -329,7 +392,8 @@ async fn leaf_search_single_split(
         search_request,
         &split_schema,
     )?;
-    let query = doc_mapper.query(split_schema, search_request)?;
+    let (query, term_expansions) =
+        doc_mapper.query_with_term_expansions(split_schema, search_request)?;
     let reader = index
         .reader_builder()
         .reload_policy(ReloadPolicy::Manual)
@@ -338,6 +402,7 @@ async fn leaf_search_single_split(
     warmup(
         &searcher,
         &query,
+        &term_expansions,
         &quickwit_collector.fast_field_names(),
         &quickwit_collector.term_dict_field_names(),
         quickwit_collector.requires_scoring(),
@@ -21,6 +21,7 @@
 #![warn(missing_docs)]
 #![allow(clippy::bool_assert_comparison)]
 
+mod aggregation;
 mod client;
 mod cluster_client;
 mod collector;
@@ -31,6 +32,7 @@ mod leaf;
 mod rendezvous_hasher;
 mod retry;
 mod root;
+mod search_after;
 mod search_client_pool;
 mod search_response_rest;
 mod search_stream;
@@ -57,23 +59,24 @@ use anyhow::Context;
 use itertools::Itertools;
 use quickwit_cluster::Cluster;
 use quickwit_config::{build_doc_mapper, QuickwitConfig, SearcherConfig};
+use quickwit_directories::LocalSplitRegistry;
+use quickwit_doc_mapper::range_pruning::extract_ranges_from_query;
 use quickwit_doc_mapper::tag_pruning::extract_tags_from_query;
 use quickwit_doc_mapper::DocMapper;
 use quickwit_metastore::{Metastore, SplitMetadata, SplitState};
 use quickwit_proto::{PartialHit, SearchRequest, SearchResponse, SplitIdAndFooterOffsets};
 use quickwit_storage::StorageUriResolver;
 use serde_json::Value as JsonValue;
-use tantivy::aggregation::agg_req::Aggregations;
-use tantivy::aggregation::agg_result::AggregationResults;
-use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
 use tantivy::DocAddress;
 
+use crate::aggregation::{QuickwitAggregations, QuickwitIntermediateAggregationResults};
 pub use crate::client::SearchServiceClient;
 pub use crate::cluster_client::ClusterClient;
 pub use crate::error::{parse_grpc_error, SearchError};
 use crate::fetch_docs::fetch_docs;
 use crate::leaf::leaf_search;
 pub use crate::root::root_search;
+pub use crate::search_after::{decode_search_after_cursor, encode_search_after_cursor};
 pub use crate::search_client_pool::{create_search_service_client, SearchClientPool};
 pub use crate::search_response_rest::SearchResponseRest;
 pub use crate::search_stream::root_search_stream;
@@ -115,6 +118,8 @@ fn extract_split_and_footer_offsets(split_metadata: &SplitMetadata) -> SplitIdAn
 }
 
 /// Extract the list of relevant splits for a given search request.
+///
+/// Splits are pruned on their time range, their tags, and the statistics of their numeric fields.
 async fn list_relevant_splits(
     search_request: &SearchRequest,
     metastore: &dyn Metastore,
@@ -122,6 +127,7 @@ async fn list_relevant_splits(
     let time_range_opt =
         extract_time_range(search_request.start_timestamp, search_request.end_timestamp);
     let tags_filter = extract_tags_from_query(&search_request.query)?;
+    let ranges_filter_opt = extract_ranges_from_query(&search_request.query)?;
     let split_metas = metastore
         .list_splits(
             &search_request.index_id,
@@ -133,6 +139,20 @@ async fn list_relevant_splits(
     Ok(split_metas
         .into_iter()
         .map(|metadata| metadata.split_metadata)
+        .filter(|split_metadata| {
+            ranges_filter_opt
+                .as_ref()
+                .map(|ranges_filter| {
+                    ranges_filter.evaluate(&|field, lower, upper| {
+                        split_metadata
+                            .field_stats
+                            .get(field)
+                            .map(|field_stats| field_stats.value_range.overlaps(lower, upper))
+                            .unwrap_or(true)
+                    })
+                })
+                .unwrap_or(true)
+        })
         .collect::<Vec<_>>())
 }
 
@@ -233,11 +253,10 @@ pub async fn single_node_search(
     let aggregation = if let Some(intermediate_aggregation_result) =
         leaf_search_response.intermediate_aggregation_result
     {
-        let res: IntermediateAggregationResults =
+        let res: QuickwitIntermediateAggregationResults =
             serde_json::from_str(&intermediate_aggregation_result)?;
-        let req: Aggregations = serde_json::from_str(search_request.aggregation_request())?;
-        let res: AggregationResults = res.into_final_bucket_result(req)?;
-        Some(serde_json::to_string(&res)?)
+        let req = QuickwitAggregations::from_json_str(search_request.aggregation_request())?;
+        Some(res.into_final_result(req)?)
     } else {
         None
     };
@@ -260,6 +279,7 @@ pub async fn start_searcher_service(
     metastore: Arc<dyn Metastore>,
     storage_uri_resolver: StorageUriResolver,
     cluster: Arc<Cluster>,
+    local_split_registry: LocalSplitRegistry,
 ) -> anyhow::Result<Arc<dyn SearchService>> {
     let client_pool = SearchClientPool::create_and_keep_updated(
         &cluster.members(),
@@ -273,6 +293,7 @@ pub async fn start_searcher_service(
         cluster_client,
         client_pool,
         quickwit_config.searcher_config.clone(),
+        local_split_registry,
     ));
     Ok(search_service)
 }
@@ -19,29 +19,30 @@
 
 use std::cmp::Reverse;
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 use futures::future::try_join_all;
 use itertools::Itertools;
+use quickwit_common::extract_time_range;
 use quickwit_config::build_doc_mapper;
+use quickwit_directories::LocalSplit;
 use quickwit_metastore::{Metastore, SplitMetadata};
 use quickwit_proto::{
     FetchDocsRequest, FetchDocsResponse, LeafSearchRequest, LeafSearchResponse, PartialHit,
     SearchRequest, SearchResponse, SplitIdAndFooterOffsets,
 };
-use tantivy::aggregation::agg_req::Aggregations;
-use tantivy::aggregation::agg_result::AggregationResults;
-use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
 use tantivy::collector::Collector;
 use tantivy::TantivyError;
 use tokio::task::spawn_blocking;
 use tracing::{debug, error, instrument};
 
+use crate::aggregation::{QuickwitAggregations, QuickwitIntermediateAggregationResults};
 use crate::cluster_client::ClusterClient;
 use crate::collector::make_merge_collector;
 use crate::search_client_pool::Job;
 use crate::{
     extract_split_and_footer_offsets, list_relevant_splits, SearchClientPool, SearchError,
-    SearchServiceClient,
+    SearchService, SearchServiceClient,
 };
 
 #[derive(Debug, PartialEq)]
@@ -111,7 +112,7 @@ impl From<FetchDocsJob> for SplitIdAndFooterOffsets {
 
 pub(crate) fn validate_request(search_request: &SearchRequest) -> crate::Result<()> {
     if let Some(agg) = search_request.aggregation_request.as_ref() {
-        let _agg: Aggregations = serde_json::from_str(agg)
+        QuickwitAggregations::from_json_str(agg)
             .map_err(|err| SearchError::InvalidAggregationRequest(err.to_string()))?;
     };
 
@@ -129,20 +130,96 @@ pub(crate) fn validate_request(search_request: &SearchRequest) -> crate::Result<
         )));
     }
 
+    if search_request.search_after.is_some() && search_request.start_offset > 0 {
+        return Err(SearchError::InvalidArgument(format!(
+            "start_offset must be 0 when search_after is set, but got {}",
+            search_request.start_offset
+        )));
+    }
+
+    if search_request.snippet_fragment_size == Some(0) {
+        return Err(SearchError::InvalidArgument(
+            "snippet_fragment_size must be strictly positive".to_string(),
+        ));
+    }
+
     Ok(())
 }
 
+/// Splits packaged on the node executing the root search and not published yet, see
+/// [`LocalSplitRegistry`](quickwit_directories::LocalSplitRegistry). They are searched by the
+/// search service of the node along with the published splits.
+pub(crate) struct LocalSplits {
+    pub local_splits: Vec<Arc<LocalSplit>>,
+    pub search_service: Arc<dyn SearchService>,
+}
+
+impl LocalSplits {
+    /// Returns the jobs searching the local splits relevant to the request. The local splits
+    /// published since they were listed are skipped: they are searched as published splits.
+    fn search_jobs(
+        &self,
+        search_request: &SearchRequest,
+        published_split_offsets: &HashMap<String, SplitIdAndFooterOffsets>,
+    ) -> Vec<SearchJob> {
+        let time_range_opt =
+            extract_time_range(search_request.start_timestamp, search_request.end_timestamp);
+        self.local_splits
+            .iter()
+            .filter(|local_split| !published_split_offsets.contains_key(&local_split.split_id))
+            .filter(
+                |local_split| match (&time_range_opt, &local_split.time_range) {
+                    (Some(time_range), Some(split_time_range)) => {
+                        time_range.start <= *split_time_range.end()
+                            && *split_time_range.start() < time_range.end
+                    }
+                    _ => true,
+                },
+            )
+            .map(|local_split| SearchJob {
+                cost: 1,
+                // The local splits are opened from the local split registry, regardless of their
+                // footer offsets.
+                offsets: SplitIdAndFooterOffsets {
+                    split_id: local_split.split_id.clone(),
+                    split_footer_start: 0,
+                    split_footer_end: 0,
+                },
+            })
+            .collect()
+    }
+}
+
 /// Performs a distributed search.
 /// 1. Sends leaf request over gRPC to multiple leaf nodes.
 /// 2. Merges the search results.
 /// 3. Sends fetch docs requests to multiple leaf nodes.
 /// 4. Builds the response with docs and returns.
-#[instrument(skip(search_request, cluster_client, client_pool, metastore))]
 pub async fn root_search(
     search_request: &SearchRequest,
     metastore: &dyn Metastore,
     cluster_client: &ClusterClient,
     client_pool: &SearchClientPool,
+) -> crate::Result<SearchResponse> {
+    root_search_with_local_splits(search_request, metastore, cluster_client, client_pool, None)
+        .await
+}
+
+/// Performs a distributed search, searching the local splits with the search service of the
+/// node in addition to the published splits.
+#[instrument(skip(
+    search_request,
+    cluster_client,
+    client_pool,
+    metastore,
+    local_splits_opt
+))]
+pub(crate) async fn root_search_with_local_splits(
+    search_request: &SearchRequest,
+    metastore: &dyn Metastore,
+    cluster_client: &ClusterClient,
+    client_pool: &SearchClientPool,
+    local_splits_opt: Option<LocalSplits>,
 ) -> crate::Result<SearchResponse> {
     let start_instant = tokio::time::Instant::now();
 
@@ -179,23 +256,59 @@ pub async fn root_search(
         })
         .collect();
 
+    let local_jobs_opt: Option<(Arc<dyn SearchService>, Vec<SearchJob>)> = local_splits_opt
+        .and_then(|local_splits| {
+            let local_jobs = local_splits.search_jobs(search_request, &split_offsets_map);
+            if local_jobs.is_empty() {
+                return None;
+            }
+            Some((local_splits.search_service, local_jobs))
+        });
+    let local_split_offsets: Vec<SplitIdAndFooterOffsets> = local_jobs_opt
+        .iter()
+        .flat_map(|(_, local_jobs)| local_jobs.iter().map(|local_job| local_job.offsets.clone()))
+        .collect();
+
     let jobs: Vec<SearchJob> = split_metadatas.iter().map(SearchJob::from).collect();
     let assigned_leaf_search_jobs = client_pool.assign_jobs(jobs, &HashSet::default())?;
     debug!(assigned_leaf_search_jobs=?assigned_leaf_search_jobs, "Assigned leaf search jobs.");
-    let leaf_search_responses: Vec<LeafSearchResponse> = try_join_all(
-        assigned_leaf_search_jobs
-            .into_iter()
-            .map(|(client, client_jobs)| {
-                let leaf_request = jobs_to_leaf_request(
-                    search_request,
-                    &doc_mapper_str,
-                    index_metadata.index_uri.as_ref(),
-                    client_jobs,
-                );
-                cluster_client.leaf_search(leaf_request, client)
-            }),
-    )
-    .await?;
+    let leaf_search_responses_future = try_join_all(assigned_leaf_search_jobs.into_iter().map(
+        |(client, client_jobs)| {
+            let leaf_request = jobs_to_leaf_request(
+                search_request,
+                &doc_mapper_str,
+                index_metadata.index_uri.as_ref(),
+                client_jobs,
+            );
+            cluster_client.leaf_search(leaf_request, client)
+        },
+    ));
+    // The local splits are searched by the search service of the node only: the other nodes
+    // cannot read them before they are uploaded.
+    let local_leaf_search_response_future = async {
+        if let Some((local_search_service, local_jobs)) = local_jobs_opt {
+            let leaf_request = jobs_to_leaf_request(
+                search_request,
+                &doc_mapper_str,
+                index_metadata.index_uri.as_ref(),
+                local_jobs,
+            );
+            let leaf_search_response = local_search_service.leaf_search(leaf_request).await?;
+            return Ok(Some((local_search_service, leaf_search_response)));
+        }
+        Ok::<_, SearchError>(None)
+    };
+    let (mut leaf_search_responses, local_leaf_search_response_opt): (Vec<LeafSearchResponse>, _) =
+        tokio::try_join!(
+            leaf_search_responses_future,
+            local_leaf_search_response_future
+        )?;
+    let mut local_search_service_opt = None;
+    if let Some((local_search_service, local_leaf_search_response)) = local_leaf_search_response_opt
+    {
+        leaf_search_responses.push(local_leaf_search_response);
+        local_search_service_opt = Some(local_search_service);
+    }
 
     // Creates a collector which merges responses into one
     let merge_collector = make_merge_collector(search_request)?;
@@ -225,12 +338,41 @@ pub async fn root_search(
         return Err(SearchError::InternalError(errors));
     }
 
+    let local_split_ids: HashSet<&str> = local_split_offsets
+        .iter()
+        .map(|split_offsets| split_offsets.split_id.as_str())
+        .collect();
+    let (local_partial_hits, partial_hits): (Vec<PartialHit>, Vec<PartialHit>) =
+        leaf_search_response
+            .partial_hits
+            .iter()
+            .cloned()
+            .partition(|partial_hit| local_split_ids.contains(partial_hit.split_id.as_str()));
     let client_fetch_docs_task: Vec<(SearchServiceClient, Vec<FetchDocsJob>)> =
-        assign_client_fetch_doc_tasks(
-            &leaf_search_response.partial_hits,
-            &split_offsets_map,
-            client_pool,
-        )?;
+        assign_client_fetch_doc_tasks(&partial_hits, &split_offsets_map, client_pool)?;
+
+    let make_fetch_docs_request =
+        |partial_hits: Vec<PartialHit>, split_offsets: Vec<SplitIdAndFooterOffsets>| {
+            if search_request.snippet_fields.is_empty() {
+                FetchDocsRequest {
+                    partial_hits,
+                    index_id: search_request.index_id.to_string(),
+                    split_offsets,
+                    index_uri: index_metadata.index_uri.to_string(),
+                    search_request: None,
+                    doc_mapper: None,
+                }
+            } else {
+                FetchDocsRequest {
+                    partial_hits,
+                    index_id: search_request.index_id.to_string(),
+                    split_offsets,
+                    index_uri: index_metadata.index_uri.to_string(),
+                    search_request: Some(search_request.clone()),
+                    doc_mapper: Some(doc_mapper_str.clone()),
+                }
+            }
+        };
 
     let fetch_docs_resp_futures =
         client_fetch_docs_task
@@ -244,30 +386,25 @@ pub async fn root_search(
                     .into_iter()
                     .map(|fetch_doc_job| fetch_doc_job.into())
                     .collect();
-
-                let fetch_docs_req = if search_request.snippet_fields.is_empty() {
-                    FetchDocsRequest {
-                        partial_hits,
-                        index_id: search_request.index_id.to_string(),
-                        split_offsets,
-                        index_uri: index_metadata.index_uri.to_string(),
-                        search_request: None,
-                        doc_mapper: None,
-                    }
-                } else {
-                    FetchDocsRequest {
-                        partial_hits,
-                        index_id: search_request.index_id.to_string(),
-                        split_offsets,
-                        index_uri: index_metadata.index_uri.to_string(),
-                        search_request: Some(search_request.clone()),
-                        doc_mapper: Some(doc_mapper_str.clone()),
-                    }
-                };
+                let fetch_docs_req = make_fetch_docs_request(partial_hits, split_offsets);
                 cluster_client.fetch_docs(fetch_docs_req, client)
             });
-
-    let fetch_docs_resps: Vec<FetchDocsResponse> = try_join_all(fetch_docs_resp_futures).await?;
+    let local_fetch_docs_resp_future = async {
+        match local_search_service_opt {
+            Some(local_search_service) if !local_partial_hits.is_empty() => {
+                let fetch_docs_req =
+                    make_fetch_docs_request(local_partial_hits, local_split_offsets.clone());
+                let fetch_docs_resp = local_search_service.fetch_docs(fetch_docs_req).await?;
+                Ok(Some(fetch_docs_resp))
+            }
+            _ => Ok::<_, SearchError>(None),
+        }
+    };
+    let (mut fetch_docs_resps, local_fetch_docs_resp_opt): (Vec<FetchDocsResponse>, _) = tokio::try_join!(
+        try_join_all(fetch_docs_resp_futures),
+        local_fetch_docs_resp_future
+    )?;
+    fetch_docs_resps.extend(local_fetch_docs_resp_opt);
 
     // Merge the fetched docs.
     let leaf_hits = fetch_docs_resps
@@ -292,11 +429,10 @@ pub async fn root_search(
     let aggregation = if let Some(intermediate_aggregation_result) =
         leaf_search_response.intermediate_aggregation_result
     {
-        let res: IntermediateAggregationResults =
+        let res: QuickwitIntermediateAggregationResults =
             serde_json::from_str(&intermediate_aggregation_result)?;
-        let req: Aggregations = serde_json::from_str(search_request.aggregation_request())?;
-        let res: AggregationResults = res.into_final_bucket_result(req)?;
-        Some(serde_json::to_string(&res)?)
+        let req = QuickwitAggregations::from_json_str(search_request.aggregation_request())?;
+        Some(res.into_final_result(req)?)
     } else {
         None
     };
@@ -371,7 +507,7 @@ fn jobs_to_leaf_request(
 
 #[cfg(test)]
 mod tests {
-    use std::ops::Range;
+    use std::ops::{Range, RangeInclusive};
     use std::sync::Arc;
 
     use quickwit_indexing::mock_split;
@@ -635,6 +771,122 @@ mod tests {
         Ok(())
     }
 
+    fn mock_local_split(
+        split_id: &str,
+        time_range: Option<RangeInclusive<i64>>,
+    ) -> Arc<LocalSplit> {
+        let index = tantivy::Index::create_in_ram(tantivy::schema::Schema::builder().build());
+        let local_split = LocalSplit::new(
+            "test-index".to_string(),
+            split_id.to_string(),
+            time_range,
+            index,
+            || true,
+        )
+        .unwrap();
+        Arc::new(local_split)
+    }
+
+    #[tokio::test]
+    async fn test_root_search_with_local_splits() -> anyhow::Result<()> {
+        let search_request = quickwit_proto::SearchRequest {
+            index_id: "test-index".to_string(),
+            query: "test".to_string(),
+            search_fields: vec!["body".to_string()],
+            start_timestamp: Some(100),
+            max_hits: 10,
+            ..Default::default()
+        };
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_index_metadata()
+            .returning(|_index_id: &str| {
+                Ok(IndexMetadata::for_test(
+                    "test-index",
+                    "ram:///indexes/test-index",
+                ))
+            });
+        metastore.expect_list_splits().returning(
+            |_index_id: &str, _split_state: SplitState, _time_range: Option<Range<i64>>, _tags| {
+                Ok(vec![mock_split("split1")])
+            },
+        );
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_leaf_search()
+            .withf(|leaf_search_req| leaf_search_req.split_offsets[0].split_id == "split1")
+            .times(1)
+            .returning(|_leaf_search_req: quickwit_proto::LeafSearchRequest| {
+                Ok(quickwit_proto::LeafSearchResponse {
+                    num_hits: 1,
+                    partial_hits: vec![mock_partial_hit("split1", 2, 1)],
+                    num_attempted_splits: 1,
+                    ..Default::default()
+                })
+            });
+        mock_search_service.expect_fetch_docs().times(1).returning(
+            |fetch_docs_req: quickwit_proto::FetchDocsRequest| {
+                Ok(quickwit_proto::FetchDocsResponse {
+                    hits: get_doc_for_fetch_req(fetch_docs_req),
+                })
+            },
+        );
+        let mut local_search_service = MockSearchService::new();
+        local_search_service
+            .expect_leaf_search()
+            .times(1)
+            .returning(|leaf_search_req: quickwit_proto::LeafSearchRequest| {
+                // The published split and the split out of the time range are not searched.
+                let split_ids: Vec<&str> = leaf_search_req
+                    .split_offsets
+                    .iter()
+                    .map(|split_offsets| split_offsets.split_id.as_str())
+                    .collect();
+                assert_eq!(split_ids, ["split2"]);
+                Ok(quickwit_proto::LeafSearchResponse {
+                    num_hits: 1,
+                    partial_hits: vec![mock_partial_hit("split2", 3, 1)],
+                    num_attempted_splits: 1,
+                    ..Default::default()
+                })
+            });
+        local_search_service.expect_fetch_docs().times(1).returning(
+            |fetch_docs_req: quickwit_proto::FetchDocsRequest| {
+                assert_eq!(fetch_docs_req.partial_hits.len(), 1);
+                assert_eq!(fetch_docs_req.partial_hits[0].split_id, "split2");
+                Ok(quickwit_proto::FetchDocsResponse {
+                    hits: get_doc_for_fetch_req(fetch_docs_req),
+                })
+            },
+        );
+        let local_splits = LocalSplits {
+            local_splits: vec![
+                mock_local_split("split1", None),
+                mock_local_split("split2", Some(50..=150)),
+                mock_local_split("split3", Some(0..=10)),
+            ],
+            search_service: Arc::new(local_search_service),
+        };
+        let client_pool = SearchClientPool::from_mocks(vec![Arc::new(mock_search_service)]).await?;
+        let cluster_client = ClusterClient::new(client_pool.clone());
+        let search_response = root_search_with_local_splits(
+            &search_request,
+            &metastore,
+            &cluster_client,
+            &client_pool,
+            Some(local_splits),
+        )
+        .await?;
+        assert_eq!(search_response.num_hits, 2);
+        let split_ids: Vec<String> = search_response
+            .hits
+            .iter()
+            .map(|hit| hit.partial_hit.as_ref().unwrap().split_id.clone())
+            .collect();
+        assert_eq!(split_ids, ["split2", "split1"]);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_root_search_multiple_splits_retry_on_other_node() -> anyhow::Result<()> {
         let search_request = quickwit_proto::SearchRequest {
@@ -1329,6 +1581,24 @@ mod tests {
             "Invalid argument: max value for max_hits is 10_000, but got 20000",
         );
 
+        let search_request = quickwit_proto::SearchRequest {
+            index_id: "test-index".to_string(),
+            query: "test".to_string(),
+            search_fields: vec!["body".to_string()],
+            snippet_fields: vec!["body".to_string()],
+            max_hits: 10,
+            snippet_fragment_size: Some(0),
+            ..Default::default()
+        };
+
+        let search_response =
+            root_search(&search_request, &metastore, &cluster_client, &client_pool).await;
+        assert!(search_response.is_err());
+        assert_eq!(
+            search_response.unwrap_err().to_string(),
+            "Invalid argument: snippet_fragment_size must be strictly positive",
+        );
+
         Ok(())
     }
 }
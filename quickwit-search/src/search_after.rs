@@ -0,0 +1,97 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use quickwit_proto::PartialHit;
+
+use crate::error::SearchError;
+
+/// Encodes the position of a hit into an opaque cursor, to pass as the `search_after` parameter of
+/// a search request to get the hits ranked after it.
+///
+/// The cursor holds the sorting field value of the hit and its address, so it is only meaningful
+/// for requests with the same query and sort.
+pub fn encode_search_after_cursor(partial_hit: &PartialHit) -> String {
+    let cursor = format!(
+        "{}:{}:{}:{}",
+        partial_hit.sorting_field_value,
+        partial_hit.segment_ord,
+        partial_hit.doc_id,
+        partial_hit.split_id
+    );
+    base64::encode_config(cursor, base64::URL_SAFE_NO_PAD)
+}
+
+/// Decodes a cursor encoded with [`encode_search_after_cursor`].
+pub fn decode_search_after_cursor(cursor_base64: &str) -> crate::Result<PartialHit> {
+    let invalid_cursor_error = || {
+        SearchError::InvalidArgument(format!("Invalid search after cursor `{}`.", cursor_base64))
+    };
+    let cursor_bytes = base64::decode_config(cursor_base64, base64::URL_SAFE_NO_PAD)
+        .map_err(|_| invalid_cursor_error())?;
+    let cursor = String::from_utf8(cursor_bytes).map_err(|_| invalid_cursor_error())?;
+    let mut cursor_parts = cursor.splitn(4, ':');
+    let mut next_part = || cursor_parts.next().ok_or_else(invalid_cursor_error);
+    let sorting_field_value = next_part()?
+        .parse::<u64>()
+        .map_err(|_| invalid_cursor_error())?;
+    let segment_ord = next_part()?
+        .parse::<u32>()
+        .map_err(|_| invalid_cursor_error())?;
+    let doc_id = next_part()?
+        .parse::<u32>()
+        .map_err(|_| invalid_cursor_error())?;
+    let split_id = next_part()?.to_string();
+    if split_id.is_empty() {
+        return Err(invalid_cursor_error());
+    }
+    Ok(PartialHit {
+        sorting_field_value,
+        split_id,
+        segment_ord,
+        doc_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_after_cursor_roundtrip() {
+        let partial_hit = PartialHit {
+            sorting_field_value: u64::MAX - 42,
+            split_id: "01GDRNYPXHCFD3W1YR5V4EY7VM".to_string(),
+            segment_ord: 1,
+            doc_id: 1_337,
+        };
+        let cursor = encode_search_after_cursor(&partial_hit);
+        assert!(cursor
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || char == '-' || char == '_'));
+        assert_eq!(decode_search_after_cursor(&cursor).unwrap(), partial_hit);
+    }
+
+    #[test]
+    fn test_decode_invalid_search_after_cursor() {
+        for cursor in ["", "not base64!", "MTI6Mw", "YTowOjA6c3BsaXQ"] {
+            let error = decode_search_after_cursor(cursor).unwrap_err();
+            assert!(matches!(error, SearchError::InvalidArgument(_)));
+        }
+    }
+}
@@ -24,6 +24,7 @@ use serde::Serialize;
 use serde_json::{Map, Value};
 
 use crate::error::SearchError;
+use crate::search_after::encode_search_after_cursor;
 
 /// SearchResponseRest represents the response returned by the REST search API
 /// and is meant to be serialized into JSON.
@@ -40,12 +41,21 @@ pub struct SearchResponseRest {
     /// Aggregations.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub aggregations: Option<serde_json::Value>,
+    /// Cursor of the last hit returned. Passed as the `search_after` parameter of the same
+    /// request, it returns the next page of hits.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_after: Option<String>,
 }
 
 impl TryFrom<quickwit_proto::SearchResponse> for SearchResponseRest {
     type Error = SearchError;
 
     fn try_from(search_response: quickwit_proto::SearchResponse) -> Result<Self, Self::Error> {
+        let search_after = search_response
+            .hits
+            .last()
+            .and_then(|hit| hit.partial_hit.as_ref())
+            .map(encode_search_after_cursor);
         let hits = search_response
             .hits
             .into_iter()
@@ -83,6 +93,7 @@ impl TryFrom<quickwit_proto::SearchResponse> for SearchResponseRest {
                 .map(|agg| serde_json::from_str(&agg))
                 .transpose()
                 .map_err(|err| SearchError::InternalError(err.to_string()))?,
+            search_after,
         })
     }
 }
@@ -23,7 +23,7 @@ use std::marker::PhantomData;
 
 use tantivy::collector::{Collector, SegmentCollector};
 use tantivy::fastfield::{DynamicFastFieldReader, FastFieldReader, FastValue};
-use tantivy::{DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
+use tantivy::{DocAddress, DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
 
 use crate::filters::{TimestampFilter, TimestampFilterBuilder};
 
@@ -112,6 +112,70 @@ impl<Item: FastValue> Collector for FastFieldCollector<Item> {
     }
 }
 
+pub struct DocAddressSegmentCollector {
+    segment_ord: SegmentOrdinal,
+    doc_addresses: Vec<DocAddress>,
+    timestamp_filter_opt: Option<TimestampFilter>,
+}
+
+impl SegmentCollector for DocAddressSegmentCollector {
+    type Fruit = Vec<DocAddress>;
+
+    fn collect(&mut self, doc_id: DocId, _score: Score) {
+        if let Some(timestamp_filter) = &self.timestamp_filter_opt {
+            if !timestamp_filter.is_within_range(doc_id) {
+                return;
+            }
+        }
+        self.doc_addresses
+            .push(DocAddress::new(self.segment_ord, doc_id));
+    }
+
+    fn harvest(self) -> Vec<DocAddress> {
+        self.doc_addresses
+    }
+}
+
+/// Collects the addresses of the documents matching the query and the timestamp filter, in
+/// doc address order.
+#[derive(Clone)]
+pub struct DocAddressCollector {
+    pub timestamp_filter_builder_opt: Option<TimestampFilterBuilder>,
+}
+
+impl Collector for DocAddressCollector {
+    type Child = DocAddressSegmentCollector;
+    type Fruit = Vec<DocAddress>;
+
+    fn for_segment(
+        &self,
+        segment_ord: SegmentOrdinal,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        let timestamp_filter_opt =
+            if let Some(timestamp_filter_builder) = &self.timestamp_filter_builder_opt {
+                timestamp_filter_builder.build(segment_reader)?
+            } else {
+                None
+            };
+        Ok(DocAddressSegmentCollector {
+            segment_ord,
+            doc_addresses: Vec::new(),
+            timestamp_filter_opt,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_fruits: Vec<Vec<DocAddress>>) -> tantivy::Result<Self::Fruit> {
+        let mut doc_addresses: Vec<DocAddress> = segment_fruits.into_iter().flatten().collect();
+        doc_addresses.sort_unstable();
+        Ok(doc_addresses)
+    }
+}
+
 #[derive(Clone)]
 pub struct PartionnedFastFieldCollector<Item: FastValue, PartitionItem: FastValue> {
     pub fast_field_to_collect: String,
@@ -0,0 +1,675 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, BooleanArray, Float64Array, Int64Array, UInt64Array};
+use arrow::datatypes::{DataType, Field as ArrowField, Schema as ArrowSchema};
+use arrow::error::ArrowError;
+use arrow::ipc::writer::{write_message, DictionaryTracker, IpcDataGenerator, IpcWriteOptions};
+use arrow::record_batch::RecordBatch;
+use futures::{StreamExt, TryStreamExt};
+use quickwit_doc_mapper::DocMapper;
+use quickwit_proto::{
+    LeafSearchStreamResponse, OutputFormat, SearchRequest, SearchStreamRequest,
+    SplitIdAndFooterOffsets,
+};
+use quickwit_storage::Storage;
+use serde_json::Value as JsonValue;
+use tantivy::fastfield::{DynamicFastFieldReader, FastFieldReader, FastValue};
+use tantivy::schema::{Field, Schema, Type};
+use tantivy::{DocAddress, ReloadPolicy};
+use tokio::sync::mpsc::Sender;
+use tracing::*;
+
+use super::collector::DocAddressCollector;
+use crate::filters::TimestampFilterBuilder;
+use crate::leaf::{open_index_with_caches, warmup};
+use crate::service::SearcherContext;
+use crate::SearchError;
+
+/// Number of rows of fast field values sent in a single leaf response.
+const NUM_ROWS_PER_CHUNK: usize = 10_000;
+
+/// Number of documents sent in a single leaf response.
+const NUM_DOCS_PER_CHUNK: usize = 1_000;
+
+/// Number of documents fetched concurrently from the doc store.
+const NUM_CONCURRENT_DOC_FETCHES: usize = 10;
+
+/// End-of-stream marker of the Arrow IPC streaming format.
+pub const ARROW_IPC_END_OF_STREAM: [u8; 8] = [0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0];
+
+/// Returns true if the request is served by the export path rather than by the legacy single
+/// fast field path: the request selects several fast fields, the stored documents, or an output
+/// format only supported by the export path.
+pub fn is_export_request(stream_request: &SearchStreamRequest) -> bool {
+    stream_request.fast_field.is_empty()
+        || !stream_request.fast_fields.is_empty()
+        || matches!(
+            OutputFormat::from_i32(stream_request.output_format),
+            Some(OutputFormat::Ndjson | OutputFormat::ArrowIpc)
+        )
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ColumnType {
+    U64,
+    I64,
+    F64,
+    Bool,
+    /// Dates are exported as timestamps in microseconds.
+    Date,
+}
+
+impl ColumnType {
+    fn arrow_data_type(&self) -> DataType {
+        match self {
+            ColumnType::U64 => DataType::UInt64,
+            ColumnType::I64 | ColumnType::Date => DataType::Int64,
+            ColumnType::F64 => DataType::Float64,
+            ColumnType::Bool => DataType::Boolean,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ExportColumn {
+    name: String,
+    field: Field,
+    column_type: ColumnType,
+}
+
+/// What a search stream request exports: the values of a list of fast fields, or the stored
+/// documents.
+#[derive(Clone, Debug)]
+pub enum ExportSelection {
+    FastFields(Vec<ExportColumn>),
+    Documents,
+}
+
+impl ExportSelection {
+    pub fn from_request(
+        stream_request: &SearchStreamRequest,
+        schema: &Schema,
+    ) -> crate::Result<ExportSelection> {
+        let output_format = output_format(stream_request)?;
+        if stream_request.partition_by_field.is_some() {
+            return Err(SearchError::InvalidArgument(
+                "`partition_by_field` is only supported when exporting a single fast field in the \
+                 CSV or ClickHouse RowBinary format."
+                    .to_string(),
+            ));
+        }
+        if !stream_request.fast_field.is_empty() && !stream_request.fast_fields.is_empty() {
+            return Err(SearchError::InvalidArgument(
+                "`fast_field` and `fast_fields` cannot be set together.".to_string(),
+            ));
+        }
+        let field_names: &[String] = if stream_request.fast_fields.is_empty() {
+            std::slice::from_ref(&stream_request.fast_field)
+        } else {
+            &stream_request.fast_fields
+        };
+        if field_names.iter().all(|field_name| field_name.is_empty()) {
+            if output_format != OutputFormat::Ndjson {
+                return Err(SearchError::InvalidArgument(format!(
+                    "Documents can only be exported in the NDJSON format, got `{}`. Set \
+                     `fast_fields` to export fast field values in this format.",
+                    output_format.as_str_name()
+                )));
+            }
+            return Ok(ExportSelection::Documents);
+        }
+        let columns = field_names
+            .iter()
+            .map(|field_name| export_column(schema, field_name))
+            .collect::<crate::Result<Vec<_>>>()?;
+        Ok(ExportSelection::FastFields(columns))
+    }
+
+    fn fast_field_names(&self) -> HashSet<String> {
+        match self {
+            ExportSelection::FastFields(columns) => {
+                columns.iter().map(|column| column.name.clone()).collect()
+            }
+            ExportSelection::Documents => HashSet::new(),
+        }
+    }
+}
+
+fn output_format(stream_request: &SearchStreamRequest) -> crate::Result<OutputFormat> {
+    OutputFormat::from_i32(stream_request.output_format)
+        .ok_or_else(|| SearchError::InternalError("Invalid output format specified.".to_string()))
+}
+
+fn export_column(schema: &Schema, field_name: &str) -> crate::Result<ExportColumn> {
+    let field = schema.get_field(field_name).ok_or_else(|| {
+        SearchError::InvalidQuery(format!("Field `{}` does not exist in schema", field_name))
+    })?;
+    let field_entry = schema.get_field_entry(field);
+    if !field_entry.is_fast() {
+        return Err(SearchError::InvalidQuery(format!(
+            "Field `{}` is not a fast field",
+            field_name
+        )));
+    }
+    let column_type = match field_entry.field_type().value_type() {
+        Type::U64 => ColumnType::U64,
+        Type::I64 => ColumnType::I64,
+        Type::F64 => ColumnType::F64,
+        Type::Bool => ColumnType::Bool,
+        Type::Date => ColumnType::Date,
+        value_type => {
+            return Err(SearchError::InvalidQuery(format!(
+                "Search stream does not support exporting fast field `{}` of type `{:?}`.",
+                field_name, value_type
+            )))
+        }
+    };
+    Ok(ExportColumn {
+        name: field_name.to_string(),
+        field,
+        column_type,
+    })
+}
+
+fn arrow_schema(columns: &[ExportColumn]) -> ArrowSchema {
+    let arrow_fields = columns
+        .iter()
+        .map(|column| ArrowField::new(&column.name, column.column_type.arrow_data_type(), false))
+        .collect();
+    ArrowSchema::new(arrow_fields)
+}
+
+fn arrow_error(arrow_error: ArrowError) -> SearchError {
+    SearchError::InternalError(format!(
+        "Failed to encode Arrow IPC message. Cause: {}",
+        arrow_error
+    ))
+}
+
+/// Returns the schema message opening the Arrow IPC stream of the exported columns. The leaves
+/// only send record batch messages: the root prepends this message and appends
+/// [`ARROW_IPC_END_OF_STREAM`].
+pub fn arrow_ipc_stream_header(columns: &[ExportColumn]) -> crate::Result<Vec<u8>> {
+    let write_options = IpcWriteOptions::default();
+    let encoded_schema =
+        IpcDataGenerator::default().schema_to_bytes(&arrow_schema(columns), &write_options);
+    let mut buffer = Vec::new();
+    write_message(&mut buffer, encoded_schema, &write_options).map_err(arrow_error)?;
+    Ok(buffer)
+}
+
+/// Values of a chunk of rows, one vector per exported column.
+#[derive(Debug, PartialEq)]
+enum ColumnValues {
+    U64(Vec<u64>),
+    I64(Vec<i64>),
+    F64(Vec<f64>),
+    Bool(Vec<bool>),
+}
+
+impl ColumnValues {
+    fn with_capacity(column_type: ColumnType, capacity: usize) -> ColumnValues {
+        match column_type {
+            ColumnType::U64 => ColumnValues::U64(Vec::with_capacity(capacity)),
+            ColumnType::I64 | ColumnType::Date => ColumnValues::I64(Vec::with_capacity(capacity)),
+            ColumnType::F64 => ColumnValues::F64(Vec::with_capacity(capacity)),
+            ColumnType::Bool => ColumnValues::Bool(Vec::with_capacity(capacity)),
+        }
+    }
+
+    /// Pushes a value read with a lenient `u64` fast field reader.
+    fn push_u64_lenient(&mut self, value: u64) {
+        match self {
+            ColumnValues::U64(values) => values.push(value),
+            ColumnValues::I64(values) => values.push(i64::from_u64(value)),
+            ColumnValues::F64(values) => values.push(f64::from_u64(value)),
+            ColumnValues::Bool(values) => values.push(value != 0),
+        }
+    }
+
+    fn write_csv_value(&self, row: usize, buffer: &mut Vec<u8>) {
+        // Writing to a `Vec` never fails.
+        let _ = match self {
+            ColumnValues::U64(values) => write!(buffer, "{}", values[row]),
+            ColumnValues::I64(values) => write!(buffer, "{}", values[row]),
+            ColumnValues::F64(values) => write!(buffer, "{}", values[row]),
+            ColumnValues::Bool(values) => write!(buffer, "{}", values[row]),
+        };
+    }
+
+    fn json_value(&self, row: usize) -> JsonValue {
+        match self {
+            ColumnValues::U64(values) => JsonValue::from(values[row]),
+            ColumnValues::I64(values) => JsonValue::from(values[row]),
+            // NaN and infinite values are serialized as `null`.
+            ColumnValues::F64(values) => JsonValue::from(values[row]),
+            ColumnValues::Bool(values) => JsonValue::from(values[row]),
+        }
+    }
+
+    fn write_row_binary_value(&self, row: usize, buffer: &mut Vec<u8>) {
+        match self {
+            ColumnValues::U64(values) => buffer.extend(values[row].to_le_bytes()),
+            ColumnValues::I64(values) => buffer.extend(values[row].to_le_bytes()),
+            ColumnValues::F64(values) => buffer.extend(values[row].to_le_bytes()),
+            ColumnValues::Bool(values) => buffer.push(values[row] as u8),
+        }
+    }
+
+    fn into_arrow_array(self) -> ArrayRef {
+        match self {
+            ColumnValues::U64(values) => Arc::new(UInt64Array::from(values)),
+            ColumnValues::I64(values) => Arc::new(Int64Array::from(values)),
+            ColumnValues::F64(values) => Arc::new(Float64Array::from(values)),
+            ColumnValues::Bool(values) => Arc::new(BooleanArray::from(values)),
+        }
+    }
+}
+
+/// Reads the values of the exported columns for a chunk of documents.
+fn read_columns(
+    columns: &[ExportColumn],
+    segment_fast_field_readers: &[Vec<DynamicFastFieldReader<u64>>],
+    doc_addresses: &[DocAddress],
+) -> Vec<ColumnValues> {
+    let mut columns_values: Vec<ColumnValues> = columns
+        .iter()
+        .map(|column| ColumnValues::with_capacity(column.column_type, doc_addresses.len()))
+        .collect();
+    for doc_address in doc_addresses {
+        let fast_field_readers = &segment_fast_field_readers[doc_address.segment_ord as usize];
+        for (column_values, fast_field_reader) in
+            columns_values.iter_mut().zip(fast_field_readers.iter())
+        {
+            column_values.push_u64_lenient(fast_field_reader.get(doc_address.doc_id));
+        }
+    }
+    columns_values
+}
+
+/// Serializes a chunk of rows into the `buffer`. For the Arrow IPC format, the rows are encoded
+/// as a single record batch message.
+fn serialize_columns(
+    columns: &[ExportColumn],
+    columns_values: Vec<ColumnValues>,
+    num_rows: usize,
+    output_format: OutputFormat,
+    buffer: &mut Vec<u8>,
+) -> crate::Result<()> {
+    match output_format {
+        OutputFormat::Csv => {
+            for row in 0..num_rows {
+                for (column_ord, column_values) in columns_values.iter().enumerate() {
+                    if column_ord > 0 {
+                        buffer.push(b',');
+                    }
+                    column_values.write_csv_value(row, buffer);
+                }
+                buffer.push(b'\n');
+            }
+        }
+        OutputFormat::Ndjson => {
+            for row in 0..num_rows {
+                let json_obj: serde_json::Map<String, JsonValue> = columns
+                    .iter()
+                    .zip(columns_values.iter())
+                    .map(|(column, column_values)| {
+                        (column.name.clone(), column_values.json_value(row))
+                    })
+                    .collect();
+                serde_json::to_writer(&mut *buffer, &json_obj)?;
+                buffer.push(b'\n');
+            }
+        }
+        OutputFormat::ClickHouseRowBinary => {
+            for row in 0..num_rows {
+                for column_values in &columns_values {
+                    column_values.write_row_binary_value(row, buffer);
+                }
+            }
+        }
+        OutputFormat::ArrowIpc => {
+            let arrays: Vec<ArrayRef> = columns_values
+                .into_iter()
+                .map(ColumnValues::into_arrow_array)
+                .collect();
+            let record_batch = RecordBatch::try_new(Arc::new(arrow_schema(columns)), arrays)
+                .map_err(arrow_error)?;
+            let write_options = IpcWriteOptions::default();
+            let mut dictionary_tracker = DictionaryTracker::new(false);
+            let (_, encoded_batch) = IpcDataGenerator::default()
+                .encoded_batch(&record_batch, &mut dictionary_tracker, &write_options)
+                .map_err(arrow_error)?;
+            write_message(&mut *buffer, encoded_batch, &write_options).map_err(arrow_error)?;
+        }
+    }
+    Ok(())
+}
+
+/// Exports the documents of a split matching the request, sending a response into the
+/// `result_sender` for every chunk of documents.
+///
+/// The sender is bounded: the export of the next chunk only starts once the previous one has
+/// been accepted, so that a slow client slows the export down instead of piling up chunks in
+/// memory.
+#[instrument(fields(split_id = %split.split_id), skip(searcher_context, split, doc_mapper, stream_request, storage, result_sender))]
+pub async fn export_single_split(
+    searcher_context: Arc<SearcherContext>,
+    split: SplitIdAndFooterOffsets,
+    doc_mapper: Arc<dyn DocMapper>,
+    stream_request: SearchStreamRequest,
+    storage: Arc<dyn Storage>,
+    result_sender: Sender<crate::Result<LeafSearchStreamResponse>>,
+) -> crate::Result<()> {
+    let _leaf_split_stream_permit = searcher_context
+        .split_stream_semaphore
+        .acquire()
+        .await
+        .expect("Failed to acquire permit. This should never happen! Please, report on https://github.com/quickwit-oss/quickwit/issues.");
+
+    let index = open_index_with_caches(
+        &searcher_context,
+        storage,
+        &split,
+        true,
+        Some(doc_mapper.schema()),
+    )
+    .await?;
+    let split_schema = index.schema();
+    let output_format = output_format(&stream_request)?;
+    let export_selection = ExportSelection::from_request(&stream_request, &split_schema)?;
+
+    let search_request = SearchRequest::from(stream_request);
+    let (query, term_expansions) =
+        doc_mapper.query_with_term_expansions(split_schema.clone(), &search_request)?;
+    let reader = index
+        .reader_builder()
+        // The documents are fetched in doc address order.
+        .doc_store_cache_size(NUM_CONCURRENT_DOC_FETCHES)
+        .reload_policy(ReloadPolicy::Manual)
+        .try_into()?;
+    let searcher = Arc::new(reader.searcher());
+
+    let timestamp_field_opt = doc_mapper.timestamp_field(&split_schema);
+    let timestamp_filter_builder_opt = TimestampFilterBuilder::new(
+        timestamp_field_opt.map(|field| split_schema.get_field_name(field).to_string()),
+        timestamp_field_opt,
+        search_request.start_timestamp,
+        search_request.end_timestamp,
+    );
+    let mut fast_field_names = export_selection.fast_field_names();
+    if let Some(timestamp_filter_builder) = &timestamp_filter_builder_opt {
+        fast_field_names.insert(timestamp_filter_builder.timestamp_field_name.clone());
+    }
+    warmup(
+        &searcher,
+        query.as_ref(),
+        &term_expansions,
+        &fast_field_names,
+        &Default::default(),
+        false,
+    )
+    .await?;
+
+    let collector = DocAddressCollector {
+        timestamp_filter_builder_opt,
+    };
+    let searcher_clone = searcher.clone();
+    let doc_addresses =
+        crate::run_cpu_intensive(move || searcher_clone.search(query.as_ref(), &collector))
+            .await
+            .map_err(|_| {
+                SearchError::InternalError(format!(
+                    "Error when collecting the matching documents of split {}",
+                    split.split_id
+                ))
+            })??;
+
+    match export_selection {
+        ExportSelection::FastFields(columns) => {
+            let columns = Arc::new(columns);
+            let segment_fast_field_readers = searcher
+                .segment_readers()
+                .iter()
+                .map(|segment_reader| {
+                    columns
+                        .iter()
+                        .map(|column| segment_reader.fast_fields().u64_lenient(column.field))
+                        .collect::<tantivy::Result<Vec<_>>>()
+                })
+                .collect::<tantivy::Result<Vec<_>>>()?;
+            let segment_fast_field_readers = Arc::new(segment_fast_field_readers);
+
+            for chunk in doc_addresses.chunks(NUM_ROWS_PER_CHUNK) {
+                let columns = columns.clone();
+                let segment_fast_field_readers = segment_fast_field_readers.clone();
+                let chunk = chunk.to_vec();
+                let data = crate::run_cpu_intensive(move || {
+                    let columns_values =
+                        read_columns(&columns, &segment_fast_field_readers, &chunk);
+                    let mut buffer = Vec::new();
+                    serialize_columns(
+                        &columns,
+                        columns_values,
+                        chunk.len(),
+                        output_format,
+                        &mut buffer,
+                    )?;
+                    crate::Result::Ok(buffer)
+                })
+                .await
+                .map_err(|_| {
+                    SearchError::InternalError(format!(
+                        "Error when exporting fast field values of split {}",
+                        split.split_id
+                    ))
+                })??;
+                let response = LeafSearchStreamResponse {
+                    data,
+                    split_id: split.split_id.clone(),
+                };
+                if result_sender.send(Ok(response)).await.is_err() {
+                    debug!("Search stream receiver dropped. Stop exporting.");
+                    return Ok(());
+                }
+            }
+        }
+        ExportSelection::Documents => {
+            for chunk in doc_addresses.chunks(NUM_DOCS_PER_CHUNK) {
+                let docs: Vec<tantivy::Document> = futures::stream::iter(chunk.iter().copied())
+                    .map(|doc_address| searcher.doc_async(doc_address))
+                    .buffered(NUM_CONCURRENT_DOC_FETCHES)
+                    .try_collect()
+                    .await?;
+                let mut data = Vec::new();
+                for doc in docs {
+                    let named_doc: BTreeMap<String, Vec<JsonValue>> =
+                        serde_json::from_str(&split_schema.to_json(&doc))?;
+                    let json_doc = doc_mapper.doc_to_json(named_doc)?;
+                    serde_json::to_writer(&mut data, &json_doc)?;
+                    data.push(b'\n');
+                }
+                let response = LeafSearchStreamResponse {
+                    data,
+                    split_id: split.split_id.clone(),
+                };
+                if result_sender.send(Ok(response)).await.is_err() {
+                    debug!("Search stream receiver dropped. Stop exporting.");
+                    return Ok(());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use arrow::ipc::reader::StreamReader;
+
+    use super::*;
+
+    fn test_columns() -> Vec<ExportColumn> {
+        let mut schema_builder = Schema::builder();
+        let count = schema_builder.add_u64_field("count", tantivy::schema::FAST);
+        let delta = schema_builder.add_i64_field("delta", tantivy::schema::FAST);
+        let ratio = schema_builder.add_f64_field("ratio", tantivy::schema::FAST);
+        vec![
+            ExportColumn {
+                name: "count".to_string(),
+                field: count,
+                column_type: ColumnType::U64,
+            },
+            ExportColumn {
+                name: "delta".to_string(),
+                field: delta,
+                column_type: ColumnType::I64,
+            },
+            ExportColumn {
+                name: "ratio".to_string(),
+                field: ratio,
+                column_type: ColumnType::F64,
+            },
+        ]
+    }
+
+    fn test_columns_values() -> Vec<ColumnValues> {
+        vec![
+            ColumnValues::U64(vec![1, 2]),
+            ColumnValues::I64(vec![-3, 4]),
+            ColumnValues::F64(vec![0.5, f64::NAN]),
+        ]
+    }
+
+    fn serialize_test_columns(output_format: OutputFormat) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        serialize_columns(
+            &test_columns(),
+            test_columns_values(),
+            2,
+            output_format,
+            &mut buffer,
+        )
+        .unwrap();
+        buffer
+    }
+
+    #[test]
+    fn test_serialize_columns_csv() {
+        let buffer = serialize_test_columns(OutputFormat::Csv);
+        assert_eq!(String::from_utf8(buffer).unwrap(), "1,-3,0.5\n2,4,NaN\n");
+    }
+
+    #[test]
+    fn test_serialize_columns_ndjson() {
+        let buffer = serialize_test_columns(OutputFormat::Ndjson);
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "{\"count\":1,\"delta\":-3,\"ratio\":0.5}\n{\"count\":2,\"delta\":4,\"ratio\":null}\n"
+        );
+    }
+
+    #[test]
+    fn test_serialize_columns_row_binary() {
+        let buffer = serialize_test_columns(OutputFormat::ClickHouseRowBinary);
+        assert_eq!(buffer.len(), 2 * 3 * 8);
+        assert_eq!(buffer[..8], 1u64.to_le_bytes());
+        assert_eq!(buffer[8..16], (-3i64).to_le_bytes());
+        assert_eq!(buffer[16..24], 0.5f64.to_le_bytes());
+        assert_eq!(buffer[24..32], 2u64.to_le_bytes());
+    }
+
+    #[test]
+    fn test_serialize_columns_arrow_ipc() {
+        let columns = test_columns();
+        let mut ipc_stream = arrow_ipc_stream_header(&columns).unwrap();
+        ipc_stream.extend(serialize_test_columns(OutputFormat::ArrowIpc));
+        ipc_stream.extend(serialize_test_columns(OutputFormat::ArrowIpc));
+        ipc_stream.extend(ARROW_IPC_END_OF_STREAM);
+
+        let stream_reader = StreamReader::try_new(Cursor::new(ipc_stream), None).unwrap();
+        assert_eq!(stream_reader.schema().fields().len(), 3);
+        let record_batches = stream_reader
+            .collect::<Result<Vec<RecordBatch>, ArrowError>>()
+            .unwrap();
+        assert_eq!(record_batches.len(), 2);
+        let record_batch = &record_batches[1];
+        assert_eq!(record_batch.num_rows(), 2);
+        let deltas = record_batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(deltas.values(), &[-3, 4]);
+    }
+
+    #[test]
+    fn test_export_selection_from_request() {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("body", tantivy::schema::TEXT);
+        schema_builder.add_u64_field("count", tantivy::schema::FAST);
+        schema_builder.add_i64_field("delta", tantivy::schema::INDEXED);
+        let schema = schema_builder.build();
+
+        let mut stream_request = SearchStreamRequest {
+            index_id: "test-index".to_string(),
+            query: "*".to_string(),
+            output_format: OutputFormat::Ndjson as i32,
+            ..Default::default()
+        };
+        assert!(is_export_request(&stream_request));
+        assert!(matches!(
+            ExportSelection::from_request(&stream_request, &schema).unwrap(),
+            ExportSelection::Documents
+        ));
+
+        stream_request.output_format = OutputFormat::Csv as i32;
+        let error = ExportSelection::from_request(&stream_request, &schema).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Documents can only be exported in the NDJSON format, got `CSV`."));
+
+        stream_request.fast_fields = vec!["count".to_string()];
+        let export_selection = ExportSelection::from_request(&stream_request, &schema).unwrap();
+        assert_eq!(
+            export_selection.fast_field_names(),
+            HashSet::from(["count".to_string()])
+        );
+
+        stream_request.fast_fields = vec!["count".to_string(), "delta".to_string()];
+        let error = ExportSelection::from_request(&stream_request, &schema).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Field `delta` is not a fast field"));
+
+        stream_request.fast_field = "count".to_string();
+        let error = ExportSelection::from_request(&stream_request, &schema).unwrap_err();
+        assert!(matches!(error, SearchError::InvalidArgument(_)));
+
+        stream_request.fast_fields.clear();
+        assert!(!is_export_request(&stream_request));
+    }
+}
@@ -33,11 +33,13 @@ use tantivy::fastfield::FastValue;
 use tantivy::query::Query;
 use tantivy::schema::{Field, Schema, Type};
 use tantivy::{ReloadPolicy, Searcher};
-use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio::sync::mpsc::Sender;
+use tokio_stream::wrappers::ReceiverStream;
 use tracing::*;
 
 use super::collector::{PartionnedFastFieldCollector, PartitionValues};
-use super::FastFieldCollector;
+use super::export::{export_single_split, is_export_request};
+use super::{FastFieldCollector, LEAF_SEARCH_STREAM_CHANNEL_CAPACITY};
 use crate::filters::TimestampFilterBuilder;
 use crate::leaf::{open_index_with_caches, warmup};
 use crate::service::SearcherContext;
@@ -56,16 +58,31 @@ pub async fn leaf_search_stream(
     storage: Arc<dyn Storage>,
     splits: Vec<SplitIdAndFooterOffsets>,
     doc_mapper: Arc<dyn DocMapper>,
-) -> UnboundedReceiverStream<crate::Result<LeafSearchStreamResponse>> {
-    let (result_sender, result_receiver) = tokio::sync::mpsc::unbounded_channel();
+) -> ReceiverStream<crate::Result<LeafSearchStreamResponse>> {
+    let (result_sender, result_receiver) =
+        tokio::sync::mpsc::channel(LEAF_SEARCH_STREAM_CHANNEL_CAPACITY);
     let span = info_span!("leaf_search_stream",);
+    if is_export_request(&request) {
+        tokio::spawn(
+            export_splits(
+                searcher_context,
+                request,
+                storage,
+                splits,
+                doc_mapper,
+                result_sender,
+            )
+            .instrument(span),
+        );
+        return ReceiverStream::new(result_receiver);
+    }
     tokio::spawn(
         async move {
             let mut stream =
                 leaf_search_results_stream(searcher_context, request, storage, splits, doc_mapper)
                     .await;
             while let Some(item) = stream.next().await {
-                if let Err(error) = result_sender.send(item) {
+                if let Err(error) = result_sender.send(item).await {
                     error!(
                         "Failed to send leaf search stream result. Stop sending. Cause: {}",
                         error
@@ -76,7 +93,40 @@ pub async fn leaf_search_stream(
         }
         .instrument(span),
     );
-    UnboundedReceiverStream::new(result_receiver)
+    ReceiverStream::new(result_receiver)
+}
+
+/// Exports the splits concurrently, each split sending its responses chunk by chunk.
+async fn export_splits(
+    searcher_context: Arc<SearcherContext>,
+    request: SearchStreamRequest,
+    storage: Arc<dyn Storage>,
+    splits: Vec<SplitIdAndFooterOffsets>,
+    doc_mapper: Arc<dyn DocMapper>,
+    result_sender: Sender<crate::Result<LeafSearchStreamResponse>>,
+) {
+    let max_num_concurrent_split_streams = searcher_context
+        .searcher_config
+        .max_num_concurrent_split_streams;
+    futures::stream::iter(splits)
+        .for_each_concurrent(max_num_concurrent_split_streams, |split| {
+            let result_sender = result_sender.clone();
+            let export_future = export_single_split(
+                searcher_context.clone(),
+                split,
+                doc_mapper.clone(),
+                request.clone(),
+                storage.clone(),
+                result_sender.clone(),
+            );
+            async move {
+                if let Err(error) = export_future.await {
+                    // It is ok to ignore the send error, the receiver is gone.
+                    let _ = result_sender.send(Err(error)).await;
+                }
+            }
+        })
+        .await;
 }
 
 async fn leaf_search_results_stream(
@@ -118,7 +168,14 @@ async fn leaf_search_stream_single_split(
         .await
         .expect("Failed to acquire permit. This should never happen! Please, report on https://github.com/quickwit-oss/quickwit/issues.");
 
-    let index = open_index_with_caches(&searcher_context, storage, &split, true).await?;
+    let index = open_index_with_caches(
+        &searcher_context,
+        storage,
+        &split,
+        true,
+        Some(doc_mapper.schema()),
+    )
+    .await?;
     let split_schema = index.schema();
 
     let request_fields = Arc::new(SearchStreamRequestFields::from_request(
@@ -142,7 +199,8 @@ async fn leaf_search_stream_single_split(
     }
 
     let search_request = Arc::new(SearchRequest::from(stream_request.clone()));
-    let query = doc_mapper.query(split_schema.clone(), &search_request)?;
+    let (query, term_expansions) =
+        doc_mapper.query_with_term_expansions(split_schema.clone(), &search_request)?;
     let reader = index
         .reader_builder()
         .reload_policy(ReloadPolicy::Manual)
@@ -164,6 +222,7 @@ async fn leaf_search_stream_single_split(
     warmup(
         &searcher,
         query.as_ref(),
+        &term_expansions,
         &request_fields.fast_fields_for_request(timestamp_filter_builder_opt.as_ref()),
         &Default::default(),
         requires_scoring,
@@ -435,6 +494,7 @@ mod tests {
     use std::convert::TryInto;
     use std::str::from_utf8;
 
+    use futures::TryStreamExt;
     use quickwit_config::SearcherConfig;
     use quickwit_indexing::TestSandbox;
     use serde_json::json;
@@ -486,6 +546,7 @@ mod tests {
             fast_field: "ts".to_string(),
             output_format: 0,
             partition_by_field: None,
+            fast_fields: Vec::new(),
         };
         let splits = test_sandbox.metastore().list_all_splits(index_id).await?;
         let splits_offsets = splits
@@ -565,6 +626,7 @@ mod tests {
             fast_field: "ts".to_string(),
             output_format: 0,
             partition_by_field: None,
+            fast_fields: Vec::new(),
         };
         let splits = test_sandbox.metastore().list_all_splits(index_id).await?;
         let splits_offsets = splits
@@ -621,6 +683,7 @@ mod tests {
             fast_field: "app".to_string(),
             output_format: 0,
             partition_by_field: None,
+            fast_fields: Vec::new(),
         };
         let splits = test_sandbox.metastore().list_all_splits(index_id).await?;
         let splits_offsets = splits
@@ -719,6 +782,7 @@ mod tests {
             fast_field: "fast_field".to_string(),
             output_format: 1,
             partition_by_field: Some(String::from("partition_by_fast_field")),
+            fast_fields: Vec::new(),
         };
         let splits = test_sandbox.metastore().list_all_splits(index_id).await?;
         let splits_offsets = splits
@@ -746,6 +810,96 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_leaf_search_stream_export_fast_fields_and_documents() -> anyhow::Result<()> {
+        let index_id = "single-node-export";
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: ts
+                type: i64
+                fast: true
+              - name: count
+                type: u64
+                fast: true
+        "#;
+        let indexing_settings_yaml = r#"
+            timestamp_field: ts
+        "#;
+        let test_sandbox = TestSandbox::create(
+            index_id,
+            doc_mapping_yaml,
+            indexing_settings_yaml,
+            &["body"],
+        )
+        .await?;
+        let docs = (0..30)
+            .map(|i| json!({"body": format!("info @ t:{}", i + 1), "ts": i + 1, "count": i * 2}))
+            .collect();
+        test_sandbox.add_documents(docs).await?;
+
+        let splits = test_sandbox.metastore().list_all_splits(index_id).await?;
+        let splits_offsets: Vec<SplitIdAndFooterOffsets> = splits
+            .into_iter()
+            .map(|split_meta| SplitIdAndFooterOffsets {
+                split_id: split_meta.split_id().to_string(),
+                split_footer_start: split_meta.split_metadata.footer_offsets.start,
+                split_footer_end: split_meta.split_metadata.footer_offsets.end,
+            })
+            .collect();
+        let searcher_context = Arc::new(SearcherContext::new(SearcherConfig::default()));
+
+        let csv_request = SearchStreamRequest {
+            index_id: index_id.to_string(),
+            query: "info".to_string(),
+            end_timestamp: Some(4),
+            fast_fields: vec!["ts".to_string(), "count".to_string()],
+            output_format: OutputFormat::Csv as i32,
+            ..Default::default()
+        };
+        let csv_responses: Vec<LeafSearchStreamResponse> = leaf_search_stream(
+            searcher_context.clone(),
+            csv_request,
+            test_sandbox.storage(),
+            splits_offsets.clone(),
+            test_sandbox.doc_mapper(),
+        )
+        .await
+        .try_collect()
+        .await?;
+        assert_eq!(csv_responses.len(), 1);
+        assert_eq!(from_utf8(&csv_responses[0].data)?, "1,0\n2,2\n3,4\n");
+
+        let ndjson_request = SearchStreamRequest {
+            index_id: index_id.to_string(),
+            query: "info".to_string(),
+            start_timestamp: Some(10),
+            end_timestamp: Some(12),
+            output_format: OutputFormat::Ndjson as i32,
+            ..Default::default()
+        };
+        let ndjson_responses: Vec<LeafSearchStreamResponse> = leaf_search_stream(
+            searcher_context,
+            ndjson_request,
+            test_sandbox.storage(),
+            splits_offsets,
+            test_sandbox.doc_mapper(),
+        )
+        .await
+        .try_collect()
+        .await?;
+        assert_eq!(ndjson_responses.len(), 1);
+        let json_docs: Vec<serde_json::Value> = from_utf8(&ndjson_responses[0].data)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(json_docs.len(), 2);
+        assert_eq!(json_docs[0]["body"], json!("info @ t:10"));
+        assert_eq!(json_docs[1]["ts"], json!(11));
+        Ok(())
+    }
+
     fn deserialize_partitions(buffer: Vec<u8>) -> Vec<PartitionValues<u64, u64>> {
         // Note: this function is only meant to be used with valid payloads for testing purposes
         let mut cursor = 0;
@@ -18,6 +18,7 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 mod collector;
+mod export;
 mod leaf;
 mod root;
 
@@ -26,6 +27,7 @@ use std::io;
 use std::io::Write;
 
 pub use collector::FastFieldCollector;
+pub(crate) use export::is_export_request;
 pub use leaf::leaf_search_stream;
 use quickwit_proto::OutputFormat;
 pub use root::root_search_stream;
@@ -33,6 +35,10 @@ use tantivy::fastfield::FastValue;
 
 use self::collector::PartitionValues;
 
+/// Capacity of the channels forwarding the leaf search stream responses. Once a channel is full,
+/// the leaf stops producing responses until the consumer catches up.
+pub(crate) const LEAF_SEARCH_STREAM_CHANNEL_CAPACITY: usize = 4;
+
 /// Serialize the values into the `buffer` as bytes.
 ///
 /// Please note that the `buffer` is always cleared.
@@ -44,6 +50,13 @@ pub fn serialize<TFastValue: FastValue + Display>(
     match format {
         OutputFormat::Csv => serialize_csv(values, buffer),
         OutputFormat::ClickHouseRowBinary => serialize_click_house_row_binary(values, buffer),
+        OutputFormat::Ndjson | OutputFormat::ArrowIpc => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Output format `{}` is not supported for a single fast field.",
+                format.as_str_name()
+            ),
+        )),
     }
 }
 
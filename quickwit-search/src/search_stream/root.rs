@@ -23,10 +23,13 @@ use bytes::Bytes;
 use futures::{StreamExt, TryStreamExt};
 use quickwit_config::build_doc_mapper;
 use quickwit_metastore::Metastore;
-use quickwit_proto::{LeafSearchStreamRequest, SearchRequest, SearchStreamRequest};
+use quickwit_proto::{LeafSearchStreamRequest, OutputFormat, SearchRequest, SearchStreamRequest};
 use tokio_stream::StreamMap;
 use tracing::*;
 
+use super::export::{
+    arrow_ipc_stream_header, is_export_request, ExportSelection, ARROW_IPC_END_OF_STREAM,
+};
 use crate::cluster_client::ClusterClient;
 use crate::root::SearchJob;
 use crate::{list_relevant_splits, SearchClientPool, SearchError, SearchServiceClient};
@@ -57,6 +60,21 @@ pub async fn root_search_stream(
     // Validates the query by effectively building it against the current schema.
     doc_mapper.query(doc_mapper.schema(), &search_request)?;
 
+    // Validates the exported fields and, for the Arrow IPC format, builds the messages framing
+    // the record batches sent by the leaves.
+    let mut arrow_ipc_header_opt: Option<Bytes> = None;
+    let mut arrow_ipc_footer_opt: Option<Bytes> = None;
+    if is_export_request(&search_stream_request) {
+        let export_selection =
+            ExportSelection::from_request(&search_stream_request, &doc_mapper.schema())?;
+        if let ExportSelection::FastFields(columns) = export_selection {
+            if search_stream_request.output_format == OutputFormat::ArrowIpc as i32 {
+                arrow_ipc_header_opt = Some(Bytes::from(arrow_ipc_stream_header(&columns)?));
+                arrow_ipc_footer_opt = Some(Bytes::from_static(&ARROW_IPC_END_OF_STREAM));
+            }
+        }
+    }
+
     let doc_mapper_str = serde_json::to_string(&doc_mapper).map_err(|err| {
         SearchError::InternalError(format!("Failed to serialize doc mapper: Cause {}", err))
     })?;
@@ -80,9 +98,12 @@ pub async fn root_search_stream(
             .await;
         stream_map.insert(leaf_ord, leaf_stream);
     }
-    Ok(stream_map
+    let leaf_data_stream = stream_map
         .map(|(_leaf_ord, result)| result)
-        .map_ok(|leaf_response| Bytes::from(leaf_response.data)))
+        .map_ok(|leaf_response| Bytes::from(leaf_response.data));
+    Ok(futures::stream::iter(arrow_ipc_header_opt.map(Ok))
+        .chain(leaf_data_stream)
+        .chain(futures::stream::iter(arrow_ipc_footer_opt.map(Ok))))
 }
 
 fn jobs_to_leaf_request(
@@ -107,7 +128,7 @@ mod tests {
     use quickwit_indexing::mock_split;
     use quickwit_metastore::{IndexMetadata, MockMetastore, SplitState};
     use quickwit_proto::OutputFormat;
-    use tokio_stream::wrappers::UnboundedReceiverStream;
+    use tokio_stream::wrappers::ReceiverStream;
 
     use super::*;
     use crate::MockSearchService;
@@ -124,6 +145,7 @@ mod tests {
             fast_field: "timestamp".to_string(),
             output_format: OutputFormat::Csv as i32,
             partition_by_field: None,
+            fast_fields: Vec::new(),
         };
         let mut metastore = MockMetastore::new();
         metastore
@@ -140,18 +162,18 @@ mod tests {
             },
         );
         let mut mock_search_service = MockSearchService::new();
-        let (result_sender, result_receiver) = tokio::sync::mpsc::unbounded_channel();
-        result_sender.send(Ok(quickwit_proto::LeafSearchStreamResponse {
+        let (result_sender, result_receiver) = tokio::sync::mpsc::channel(2);
+        result_sender.try_send(Ok(quickwit_proto::LeafSearchStreamResponse {
             data: b"123".to_vec(),
             split_id: "split_1".to_string(),
         }))?;
-        result_sender.send(Ok(quickwit_proto::LeafSearchStreamResponse {
+        result_sender.try_send(Ok(quickwit_proto::LeafSearchStreamResponse {
             data: b"456".to_vec(),
             split_id: "split_1".to_string(),
         }))?;
         mock_search_service.expect_leaf_search_stream().return_once(
             |_leaf_search_req: quickwit_proto::LeafSearchStreamRequest| {
-                Ok(UnboundedReceiverStream::new(result_receiver))
+                Ok(ReceiverStream::new(result_receiver))
             },
         );
         // The test will hang on indefinitely if we don't drop the receiver.
@@ -182,6 +204,7 @@ mod tests {
             fast_field: "timestamp".to_string(),
             output_format: OutputFormat::Csv as i32,
             partition_by_field: Some("timestamp".to_string()),
+            fast_fields: Vec::new(),
         };
         let mut metastore = MockMetastore::new();
         metastore
@@ -198,18 +221,18 @@ mod tests {
             },
         );
         let mut mock_search_service = MockSearchService::new();
-        let (result_sender, result_receiver) = tokio::sync::mpsc::unbounded_channel();
-        result_sender.send(Ok(quickwit_proto::LeafSearchStreamResponse {
+        let (result_sender, result_receiver) = tokio::sync::mpsc::channel(2);
+        result_sender.try_send(Ok(quickwit_proto::LeafSearchStreamResponse {
             data: b"123".to_vec(),
             split_id: "1".to_string(),
         }))?;
-        result_sender.send(Ok(quickwit_proto::LeafSearchStreamResponse {
+        result_sender.try_send(Ok(quickwit_proto::LeafSearchStreamResponse {
             data: b"456".to_vec(),
             split_id: "2".to_string(),
         }))?;
         mock_search_service.expect_leaf_search_stream().return_once(
             |_leaf_search_req: quickwit_proto::LeafSearchStreamRequest| {
-                Ok(UnboundedReceiverStream::new(result_receiver))
+                Ok(ReceiverStream::new(result_receiver))
             },
         );
         // The test will hang on indefinitely if we don't drop the sender.
@@ -236,6 +259,7 @@ mod tests {
             fast_field: "timestamp".to_string(),
             output_format: OutputFormat::Csv as i32,
             partition_by_field: None,
+            fast_fields: Vec::new(),
         };
         let mut metastore = MockMetastore::new();
         metastore
@@ -252,18 +276,18 @@ mod tests {
             },
         );
         let mut mock_search_service = MockSearchService::new();
-        let (result_sender, result_receiver) = tokio::sync::mpsc::unbounded_channel();
-        result_sender.send(Ok(quickwit_proto::LeafSearchStreamResponse {
+        let (result_sender, result_receiver) = tokio::sync::mpsc::channel(2);
+        result_sender.try_send(Ok(quickwit_proto::LeafSearchStreamResponse {
             data: b"123".to_vec(),
             split_id: "split1".to_string(),
         }))?;
-        result_sender.send(Err(SearchError::InternalError("error".to_string())))?;
+        result_sender.try_send(Err(SearchError::InternalError("error".to_string())))?;
         mock_search_service
             .expect_leaf_search_stream()
             .withf(|request| request.split_offsets.len() == 2) // First request.
             .return_once(
                 |_leaf_search_req: quickwit_proto::LeafSearchStreamRequest| {
-                    Ok(UnboundedReceiverStream::new(result_receiver))
+                    Ok(ReceiverStream::new(result_receiver))
                 },
             );
         mock_search_service
@@ -316,6 +340,7 @@ mod tests {
                 fast_field: "timestamp".to_string(),
                 output_format: OutputFormat::Csv as i32,
                 partition_by_field: Some("timestamp".to_string()),
+                fast_fields: Vec::new(),
             },
             &metastore,
             ClusterClient::new(client_pool.clone()),
@@ -335,6 +360,7 @@ mod tests {
                 fast_field: "timestamp".to_string(),
                 output_format: OutputFormat::Csv as i32,
                 partition_by_field: Some("timestamp".to_string()),
+                fast_fields: Vec::new(),
             },
             &metastore,
             ClusterClient::new(client_pool.clone()),
@@ -345,4 +371,77 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_root_search_stream_arrow_ipc_framing() -> anyhow::Result<()> {
+        let mut request = quickwit_proto::SearchStreamRequest {
+            index_id: "test-index".to_string(),
+            query: "test".to_string(),
+            search_fields: vec!["body".to_string()],
+            snippet_fields: vec![],
+            start_timestamp: None,
+            end_timestamp: None,
+            fast_field: String::new(),
+            output_format: OutputFormat::ArrowIpc as i32,
+            partition_by_field: None,
+            fast_fields: vec!["timestamp".to_string(), "response_payload".to_string()],
+        };
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_index_metadata()
+            .returning(|_index_id: &str| {
+                Ok(IndexMetadata::for_test(
+                    "test-index",
+                    "ram:///indexes/test-index",
+                ))
+            });
+        metastore.expect_list_splits().returning(
+            |_index_id: &str, _split_state: SplitState, _time_range: Option<Range<i64>>, _tags| {
+                Ok(vec![mock_split("split1")])
+            },
+        );
+        let mut mock_search_service = MockSearchService::new();
+        let (result_sender, result_receiver) = tokio::sync::mpsc::channel(1);
+        result_sender.try_send(Ok(quickwit_proto::LeafSearchStreamResponse {
+            data: b"record-batch".to_vec(),
+            split_id: "split1".to_string(),
+        }))?;
+        mock_search_service.expect_leaf_search_stream().return_once(
+            |_leaf_search_req: quickwit_proto::LeafSearchStreamRequest| {
+                Ok(ReceiverStream::new(result_receiver))
+            },
+        );
+        drop(result_sender);
+        let client_pool = SearchClientPool::from_mocks(vec![Arc::new(mock_search_service)]).await?;
+
+        // Bytes fast fields cannot be exported, the request is rejected before reaching the
+        // leaves.
+        let error = root_search_stream(
+            request.clone(),
+            &metastore,
+            ClusterClient::new(client_pool.clone()),
+            &client_pool,
+        )
+        .await
+        .err()
+        .unwrap();
+        assert!(matches!(error, SearchError::InvalidQuery(_)));
+
+        request.fast_fields = vec!["timestamp".to_string(), "response_time".to_string()];
+        let result: Vec<Bytes> = root_search_stream(
+            request,
+            &metastore,
+            ClusterClient::new(client_pool.clone()),
+            &client_pool,
+        )
+        .await?
+        .try_collect()
+        .await?;
+        assert_eq!(result.len(), 3);
+        // The schema message starts with the continuation marker.
+        assert_eq!(&result[0][..4], &[0xFF; 4]);
+        assert_eq!(&result[1], &b"record-batch"[..]);
+        assert_eq!(&result[2], &ARROW_IPC_END_OF_STREAM[..]);
+        Ok(())
+    }
 }
@@ -24,6 +24,7 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use quickwit_common::uri::Uri;
 use quickwit_config::SearcherConfig;
+use quickwit_directories::LocalSplitRegistry;
 use quickwit_doc_mapper::DocMapper;
 use quickwit_metastore::Metastore;
 use quickwit_proto::{
@@ -33,11 +34,12 @@ use quickwit_proto::{
 };
 use quickwit_storage::{Cache, MemorySizedCache, QuickwitCache, StorageUriResolver};
 use tokio::sync::Semaphore;
-use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::wrappers::ReceiverStream;
 use tracing::info;
 
+use crate::root::{root_search_with_local_splits, LocalSplits};
 use crate::search_stream::{leaf_search_stream, root_search_stream};
-use crate::{fetch_docs, leaf_search, root_search, ClusterClient, SearchClientPool, SearchError};
+use crate::{fetch_docs, leaf_search, ClusterClient, SearchClientPool, SearchError};
 
 #[derive(Clone)]
 /// The search service implementation.
@@ -88,7 +90,7 @@ pub trait SearchService: 'static + Send + Sync {
     async fn leaf_search_stream(
         &self,
         request: LeafSearchStreamRequest,
-    ) -> crate::Result<UnboundedReceiverStream<crate::Result<LeafSearchStreamResponse>>>;
+    ) -> crate::Result<ReceiverStream<crate::Result<LeafSearchStreamResponse>>>;
 }
 
 impl SearchServiceImpl {
@@ -99,8 +101,11 @@ impl SearchServiceImpl {
         cluster_client: ClusterClient,
         client_pool: SearchClientPool,
         searcher_config: SearcherConfig,
+        local_split_registry: LocalSplitRegistry,
     ) -> Self {
-        let searcher_context = Arc::new(SearcherContext::new(searcher_config));
+        let searcher_context = Arc::new(
+            SearcherContext::new(searcher_config).with_local_split_registry(local_split_registry),
+        );
         SearchServiceImpl {
             metastore,
             storage_uri_resolver,
@@ -121,11 +126,26 @@ fn deserialize_doc_mapper(doc_mapper_str: &str) -> crate::Result<Arc<dyn DocMapp
 #[async_trait]
 impl SearchService for SearchServiceImpl {
     async fn root_search(&self, search_request: SearchRequest) -> crate::Result<SearchResponse> {
-        let search_result = root_search(
+        // The local splits are listed before the published splits, so that a split published in
+        // the meantime is listed at least once.
+        let local_splits = self
+            .searcher_context
+            .local_split_registry
+            .list(&search_request.index_id);
+        let local_splits_opt = if local_splits.is_empty() {
+            None
+        } else {
+            Some(LocalSplits {
+                local_splits,
+                search_service: Arc::new(self.clone()),
+            })
+        };
+        let search_result = root_search_with_local_splits(
             &search_request,
             self.metastore.as_ref(),
             &self.cluster_client,
             &self.client_pool,
+            local_splits_opt,
         )
         .await?;
 
@@ -201,7 +221,7 @@ impl SearchService for SearchServiceImpl {
     async fn leaf_search_stream(
         &self,
         leaf_stream_request: LeafSearchStreamRequest,
-    ) -> crate::Result<UnboundedReceiverStream<crate::Result<LeafSearchStreamResponse>>> {
+    ) -> crate::Result<ReceiverStream<crate::Result<LeafSearchStreamResponse>>> {
         let stream_request = leaf_stream_request
             .request
             .ok_or_else(|| SearchError::InternalError("No search request.".to_string()))?;
@@ -236,6 +256,8 @@ pub struct SearcherContext {
     pub split_footer_cache: MemorySizedCache<String>,
     /// Fast fields cache.
     pub fast_fields_cache: Arc<dyn Cache>,
+    /// Splits packaged by the indexing pipelines of the node and not published yet.
+    pub local_split_registry: LocalSplitRegistry,
 }
 
 impl SearcherContext {
@@ -258,6 +280,14 @@ impl SearcherContext {
             leaf_search_split_semaphore,
             split_stream_semaphore,
             fast_fields_cache: storage_long_term_cache,
+            local_split_registry: LocalSplitRegistry::default(),
         }
     }
+
+    /// Searches the splits registered in `local_split_registry` by the indexing pipelines of the
+    /// node before they are uploaded.
+    pub fn with_local_split_registry(mut self, local_split_registry: LocalSplitRegistry) -> Self {
+        self.local_split_registry = local_split_registry;
+        self
+    }
 }
@@ -125,6 +125,57 @@ async fn test_single_search_with_snippet() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_single_search_with_snippet_tags_and_fragment_size() -> anyhow::Result<()> {
+    let index_id = "single-node-with-snippet-tags";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: title
+                type: text
+              - name: body
+                type: text
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+    let docs = vec![
+        json!({"title": "snoopy", "body": "Snoopy is an anthropomorphic beagle in the comic strip."}),
+        json!({"title": "beagle", "body": "The beagle is a breed of small scent hound."}),
+    ];
+    test_sandbox.add_documents(docs.clone()).await?;
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "beagle".to_string(),
+        search_fields: vec!["title".to_string(), "body".to_string()],
+        snippet_fields: vec!["title".to_string(), "body".to_string()],
+        max_hits: 2,
+        snippet_pre_tag: Some("<em>".to_string()),
+        snippet_post_tag: Some("</em>".to_string()),
+        snippet_fragment_size: Some(20),
+        ..Default::default()
+    };
+    let single_node_result = single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await?;
+    assert_eq!(single_node_result.hits.len(), 2);
+
+    let highlight_json: serde_json::Value =
+        serde_json::from_str(single_node_result.hits[0].snippet.as_ref().unwrap())?;
+    let expected_json: serde_json::Value =
+        json!({"title": [], "body": ["<em>beagle</em> in the comic"]});
+    assert_json_eq!(highlight_json, expected_json);
+
+    let highlight_json: serde_json::Value =
+        serde_json::from_str(single_node_result.hits[1].snippet.as_ref().unwrap())?;
+    let expected_json: serde_json::Value = json!({
+        "title": ["<em>beagle</em>"],
+        "body": ["The <em>beagle</em> is a"]
+    });
+    assert_json_eq!(highlight_json, expected_json);
+    Ok(())
+}
+
 async fn slop_search_and_check(
     test_sandbox: &TestSandbox,
     index_id: &str,
@@ -195,6 +246,79 @@ async fn test_slop_queries() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_fuzzy_and_prefix_queries() -> anyhow::Result<()> {
+    let index_id = "fuzzy-and-prefix-query";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: title
+                type: text
+              - name: body
+                type: text
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+    let docs = vec![
+        json!({"title": "one", "body": "quickwit is fast"}),
+        json!({"title": "two", "body": "quickly indexed logs"}),
+        json!({"title": "three", "body": "a quokka"}),
+        json!({"title": "four", "body": "tantivy"}),
+    ];
+    test_sandbox.add_documents(docs).await?;
+
+    for (query, expected_titles) in [
+        ("quick*", &["one", "two"][..]),
+        ("body:quickwir~1", &["one"]),
+        ("quickwit~", &["one"]),
+        ("tantiv~2 OR quok*", &["three", "four"]),
+        ("quick* AND NOT title:tw*", &["one"]),
+        ("quick*^2 OR tantivy", &["one", "two", "four"]),
+    ] {
+        let search_request = SearchRequest {
+            index_id: index_id.to_string(),
+            query: query.to_string(),
+            search_fields: vec!["body".to_string()],
+            max_hits: 10,
+            ..Default::default()
+        };
+        let single_node_result = single_node_search(
+            &search_request,
+            &*test_sandbox.metastore(),
+            test_sandbox.storage_uri_resolver(),
+        )
+        .await?;
+        let mut titles: Vec<String> = single_node_result
+            .hits
+            .iter()
+            .map(|hit| {
+                let doc: serde_json::Value = serde_json::from_str(&hit.json).unwrap();
+                doc["title"].as_str().unwrap().to_string()
+            })
+            .collect();
+        titles.sort();
+        let mut expected_titles = expected_titles.to_vec();
+        expected_titles.sort_unstable();
+        assert_eq!(titles, expected_titles, "query: {}", query);
+    }
+
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "body:q*".to_string(),
+        max_hits: 10,
+        ..Default::default()
+    };
+    let search_error = single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await
+    .unwrap_err();
+    assert!(search_error
+        .to_string()
+        .contains("must have a prefix of at least 2 characters"));
+    Ok(())
+}
+
 // TODO remove me once `Iterator::is_sorted_by_key` is stabilized.
 fn is_sorted<E, I: Iterator<Item = E>>(mut it: I) -> bool
 where E: Ord {
@@ -609,6 +733,50 @@ async fn test_single_node_split_pruning_by_tags() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_single_node_split_pruning_by_field_stats() -> anyhow::Result<()> {
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: latency
+                type: u64
+                fast: true
+        "#;
+    let index_id = "single-node-pruning-by-field-stats";
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+    for latency_base in [0, 1_000] {
+        let docs: Vec<serde_json::Value> = (0..10)
+            .map(|i| json!({"body": format!("content #{}", i), "latency": latency_base + i}))
+            .collect();
+        test_sandbox.add_documents(docs).await?;
+    }
+
+    let num_selected_splits = |query: &str| {
+        let search_request = SearchRequest {
+            index_id: index_id.to_string(),
+            query: query.to_string(),
+            ..Default::default()
+        };
+        let metastore = test_sandbox.metastore();
+        async move {
+            list_relevant_splits(&search_request, &*metastore)
+                .await
+                .unwrap()
+                .len()
+        }
+    };
+    assert_eq!(num_selected_splits("latency:[0 TO 9]").await, 1);
+    assert_eq!(num_selected_splits("latency:>1009").await, 0);
+    assert_eq!(num_selected_splits("latency:1005").await, 1);
+    assert_eq!(num_selected_splits("latency:5 OR latency:1005").await, 2);
+    assert_eq!(num_selected_splits("latency:[10 TO 999]").await, 0);
+    // Negations and queries on fields without statistics do not prune splits.
+    assert_eq!(num_selected_splits("NOT latency:5").await, 2);
+    assert_eq!(num_selected_splits("body:content").await, 2);
+    Ok(())
+}
+
 const DYNAMIC_TEST_INDEX_ID: &str = "search_dynamic_mode";
 
 async fn test_search_dynamic_util(test_sandbox: &TestSandbox, query: &str) -> Vec<u32> {
@@ -898,6 +1066,148 @@ async fn test_single_node_aggregation() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_single_node_sketch_aggregations() -> anyhow::Result<()> {
+    let index_id = "single-node-agg-sketches";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: color
+                type: text
+                tokenizer: raw
+                fast: true
+              - name: price
+                type: f64
+                fast: true
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["color"]).await?;
+    let colors = ["blue", "green", "white"];
+    let docs: Vec<JsonValue> = (1..=100)
+        .map(|price| json!({"color": colors[price % 3], "price": price as f64}))
+        .collect();
+    test_sandbox.add_documents(docs[..50].to_vec()).await?;
+    test_sandbox.add_documents(docs[50..].to_vec()).await?;
+    let agg_req = r#"
+ {
+   "price_percentiles": {
+     "percentiles": {
+       "field": "price",
+       "percents": [50, 99]
+     }
+   },
+   "num_colors": {
+     "cardinality": {
+       "field": "color"
+     }
+   },
+   "price_stats": {
+     "stats": {
+       "field": "price"
+     }
+   }
+ }"#;
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "*".to_string(),
+        search_fields: vec!["color".to_string()],
+        max_hits: 0,
+        aggregation_request: Some(agg_req.to_string()),
+        ..Default::default()
+    };
+    let single_node_result = single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await?;
+    assert_eq!(single_node_result.num_hits, 100);
+    let agg_res_json: JsonValue = serde_json::from_str(&single_node_result.aggregation.unwrap())?;
+    assert_eq!(
+        agg_res_json["price_percentiles"],
+        json!({"values": {"50.0": 50.5, "99.0": 99.5}})
+    );
+    assert_eq!(agg_res_json["num_colors"], json!({"value": 3}));
+    assert_eq!(agg_res_json["price_stats"]["count"], 100);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_search_after() -> anyhow::Result<()> {
+    let index_id = "single-node-search-after";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: priority
+                type: u64
+                fast: true
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+    // Priorities are tied across splits and within splits.
+    for split_ord in 0..3u64 {
+        let docs: Vec<serde_json::Value> = (0..10u64)
+            .map(|doc_ord| json!({"body": "beagle", "priority": (split_ord + doc_ord) % 4}))
+            .collect();
+        test_sandbox.add_documents(docs).await?;
+    }
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "beagle".to_string(),
+        max_hits: 30,
+        sort_by_field: Some("priority".to_string()),
+        sort_order: Some(SortOrder::Asc as i32),
+        ..Default::default()
+    };
+    let expected_partial_hits: Vec<PartialHit> = single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await?
+    .hits
+    .into_iter()
+    .flat_map(|hit| hit.partial_hit)
+    .collect();
+    assert_eq!(expected_partial_hits.len(), 30);
+
+    let mut partial_hits = Vec::new();
+    let mut search_after = None;
+    loop {
+        let page_request = SearchRequest {
+            max_hits: 7,
+            search_after: search_after.take(),
+            ..search_request.clone()
+        };
+        let page = single_node_search(
+            &page_request,
+            &*test_sandbox.metastore(),
+            test_sandbox.storage_uri_resolver(),
+        )
+        .await?;
+        assert_eq!(page.num_hits, 30);
+        if page.hits.is_empty() {
+            break;
+        }
+        partial_hits.extend(page.hits.into_iter().flat_map(|hit| hit.partial_hit));
+        search_after = partial_hits.last().cloned();
+    }
+    assert_eq!(partial_hits, expected_partial_hits);
+
+    let invalid_request = SearchRequest {
+        start_offset: 7,
+        search_after: expected_partial_hits.first().cloned(),
+        ..search_request
+    };
+    let search_error = single_node_search(
+        &invalid_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(search_error, SearchError::InvalidArgument(_)));
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_single_node_aggregation_missing_fast_field() -> anyhow::Result<()> {
     let index_id = "single-node-agg-2";
@@ -0,0 +1,968 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures::future::join_all;
+use quickwit_metastore::Metastore;
+use quickwit_proto::{SearchRequest, SearchResponse, ServiceError, SortOrder};
+use quickwit_search::{SearchError, SearchService};
+use serde::Deserialize;
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use tracing::info;
+use warp::{Filter, Rejection};
+
+use crate::{decode_index_id, with_arg, Format};
+
+const CONTENT_LENGTH_LIMIT: u64 = 1024 * 1024; // 1MB
+
+fn default_size() -> u64 {
+    10
+}
+
+/// Body of an Elasticsearch search request. Only the subset of the query DSL used by the Grafana
+/// Elasticsearch datasource is supported: `query_string` queries, range filters, and
+/// `date_histogram` and `terms` aggregations. The other parameters are ignored.
+#[derive(Debug, Deserialize, PartialEq)]
+struct ElasticSearchRequest {
+    #[serde(default = "default_size")]
+    size: u64,
+    #[serde(default)]
+    from: u64,
+    #[serde(default)]
+    query: Option<JsonValue>,
+    #[serde(default)]
+    sort: Option<JsonValue>,
+    #[serde(default, alias = "aggregations")]
+    aggs: Option<JsonMap<String, JsonValue>>,
+}
+
+/// Header of a search request of a multi-search request.
+#[derive(Debug, Deserialize)]
+struct MultiSearchHeader {
+    index: Option<IndexIds>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum IndexIds {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl MultiSearchHeader {
+    fn index_id(self) -> Result<String, SearchError> {
+        match self.index {
+            Some(IndexIds::One(index_id)) => Ok(index_id),
+            Some(IndexIds::Many(mut index_ids)) if index_ids.len() == 1 => Ok(index_ids.remove(0)),
+            Some(IndexIds::Many(_)) => Err(SearchError::InvalidArgument(
+                "Searching several indexes at once is not supported.".to_string(),
+            )),
+            None => Err(SearchError::InvalidArgument(
+                "The multi-search header does not set the index to search.".to_string(),
+            )),
+        }
+    }
+}
+
+/// Query translated into the Quickwit query language. The range filters on the timestamp field
+/// of the index are turned into the time range of the search request.
+#[derive(Debug, Default)]
+struct QueryTranslation {
+    clauses: Vec<String>,
+    start_timestamp: Option<i64>,
+    end_timestamp: Option<i64>,
+}
+
+impl QueryTranslation {
+    fn narrow_start_timestamp(&mut self, start_timestamp: i64) {
+        self.start_timestamp = Some(
+            self.start_timestamp
+                .map_or(start_timestamp, |other| other.max(start_timestamp)),
+        );
+    }
+
+    fn narrow_end_timestamp(&mut self, end_timestamp: i64) {
+        self.end_timestamp = Some(
+            self.end_timestamp
+                .map_or(end_timestamp, |other| other.min(end_timestamp)),
+        );
+    }
+
+    fn query(&self) -> String {
+        if self.clauses.is_empty() {
+            return "*".to_string();
+        }
+        self.clauses.join(" AND ")
+    }
+}
+
+/// Remembers the date histograms of the aggregations of a request, as the keys of their buckets
+/// are converted back into milliseconds in the response.
+#[derive(Debug, Default, PartialEq)]
+struct AggregationShape {
+    is_date_histogram: bool,
+    sub_aggregations: BTreeMap<String, AggregationShape>,
+}
+
+fn invalid_query(message: String) -> SearchError {
+    SearchError::InvalidQuery(message)
+}
+
+fn invalid_aggregation(message: String) -> SearchError {
+    SearchError::InvalidAggregationRequest(message)
+}
+
+/// Returns the single key and value of a JSON object, such as a query or a range filter.
+fn single_entry<'a>(
+    json_value: &'a JsonValue,
+    what: &str,
+) -> Result<(&'a String, &'a JsonValue), SearchError> {
+    match json_value.as_object() {
+        Some(json_object) if json_object.len() == 1 => Ok(json_object
+            .iter()
+            .next()
+            .expect("The object should have one key.")),
+        _ => Err(invalid_query(format!(
+            "Expected {what} to be an object with a single key, got `{json_value}`."
+        ))),
+    }
+}
+
+fn translate_query(
+    query: &JsonValue,
+    timestamp_field_opt: Option<&str>,
+    translation: &mut QueryTranslation,
+) -> Result<(), SearchError> {
+    let (query_kind, params) = single_entry(query, "the query")?;
+    match query_kind.as_str() {
+        "match_all" => {}
+        "query_string" => {
+            let query_str = params
+                .get("query")
+                .and_then(JsonValue::as_str)
+                .ok_or_else(|| invalid_query("The query string is missing.".to_string()))?
+                .trim();
+            if !query_str.is_empty() && query_str != "*" {
+                translation.clauses.push(format!("({query_str})"));
+            }
+        }
+        "bool" => {
+            let clauses = params.as_object().ok_or_else(|| {
+                invalid_query(format!("Expected a boolean query, got `{params}`."))
+            })?;
+            for (occur, sub_queries) in clauses {
+                if occur != "must" && occur != "filter" {
+                    return Err(invalid_query(format!(
+                        "Unsupported boolean clause `{occur}`: only the `must` and `filter` \
+                         clauses are supported."
+                    )));
+                }
+                match sub_queries {
+                    JsonValue::Array(sub_queries) => {
+                        for sub_query in sub_queries {
+                            translate_query(sub_query, timestamp_field_opt, translation)?;
+                        }
+                    }
+                    sub_query => translate_query(sub_query, timestamp_field_opt, translation)?,
+                }
+            }
+        }
+        "range" => translate_range(params, timestamp_field_opt, translation)?,
+        _ => {
+            return Err(invalid_query(format!(
+                "Unsupported query `{query_kind}`: only the `match_all`, `query_string`, `bool`, \
+                 and `range` queries are supported."
+            )))
+        }
+    }
+    Ok(())
+}
+
+fn translate_range(
+    params: &JsonValue,
+    timestamp_field_opt: Option<&str>,
+    translation: &mut QueryTranslation,
+) -> Result<(), SearchError> {
+    let (field_name, bounds) = single_entry(params, "the range filter")?;
+    let bounds = bounds
+        .as_object()
+        .ok_or_else(|| invalid_query(format!("Expected range bounds, got `{bounds}`.")))?;
+    let mut lower_bound_opt: Option<(&JsonValue, bool)> = None;
+    let mut upper_bound_opt: Option<(&JsonValue, bool)> = None;
+    for (operator, bound) in bounds {
+        match operator.as_str() {
+            "gte" => lower_bound_opt = Some((bound, true)),
+            "gt" => lower_bound_opt = Some((bound, false)),
+            "lte" => upper_bound_opt = Some((bound, true)),
+            "lt" => upper_bound_opt = Some((bound, false)),
+            "format" | "time_zone" | "boost" => {}
+            _ => {
+                return Err(invalid_query(format!(
+                    "Unsupported range operator `{operator}`."
+                )))
+            }
+        }
+    }
+    if timestamp_field_opt == Some(field_name.as_str()) {
+        // The timestamps are stored in seconds.
+        let format = bounds
+            .get("format")
+            .and_then(JsonValue::as_str)
+            .unwrap_or("epoch_millis");
+        if let Some((lower_bound, inclusive)) = lower_bound_opt {
+            let millis = parse_timestamp_millis(lower_bound, format)?;
+            let start_timestamp = if inclusive {
+                ceil_secs(millis)
+            } else {
+                millis.div_euclid(1_000) + 1
+            };
+            translation.narrow_start_timestamp(start_timestamp);
+        }
+        if let Some((upper_bound, inclusive)) = upper_bound_opt {
+            let millis = parse_timestamp_millis(upper_bound, format)?;
+            // The end timestamp of a search request is exclusive.
+            let end_timestamp = if inclusive {
+                millis.div_euclid(1_000) + 1
+            } else {
+                ceil_secs(millis)
+            };
+            translation.narrow_end_timestamp(end_timestamp);
+        }
+        return Ok(());
+    }
+    let format_bound = |bound_opt: Option<(&JsonValue, bool)>| match bound_opt {
+        Some((JsonValue::String(bound), inclusive)) => (bound.clone(), inclusive),
+        Some((bound, inclusive)) => (bound.to_string(), inclusive),
+        None => ("*".to_string(), true),
+    };
+    let (lower_bound, lower_inclusive) = format_bound(lower_bound_opt);
+    let (upper_bound, upper_inclusive) = format_bound(upper_bound_opt);
+    translation.clauses.push(format!(
+        "{field_name}:{}{lower_bound} TO {upper_bound}{}",
+        if lower_inclusive { '[' } else { '{' },
+        if upper_inclusive { ']' } else { '}' },
+    ));
+    Ok(())
+}
+
+fn ceil_secs(millis: i64) -> i64 {
+    (millis + 999).div_euclid(1_000)
+}
+
+fn parse_timestamp_millis(bound: &JsonValue, format: &str) -> Result<i64, SearchError> {
+    let value = match bound {
+        JsonValue::Number(number) => number
+            .as_i64()
+            .or_else(|| number.as_f64().map(|value| value as i64)),
+        JsonValue::String(value_str) => value_str.parse::<i64>().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| {
+        invalid_query(format!(
+            "Timestamp `{bound}` is not a number: only the `epoch_millis` and `epoch_second` \
+             formats are supported."
+        ))
+    })?;
+    match format {
+        "epoch_millis" => Ok(value),
+        "epoch_second" => Ok(value * 1_000),
+        _ => Err(invalid_query(format!(
+            "Unsupported timestamp format `{format}`: only the `epoch_millis` and `epoch_second` \
+             formats are supported."
+        ))),
+    }
+}
+
+/// Returns the sort order and the field to sort by. The documents cannot be sorted by score.
+fn translate_sort(sort: &JsonValue) -> Result<(Option<i32>, Option<String>), SearchError> {
+    let sort_items: Vec<&JsonValue> = match sort {
+        JsonValue::Array(sort_items) => sort_items.iter().collect(),
+        sort_item => vec![sort_item],
+    };
+    for sort_item in sort_items {
+        let (field_name, order_opt) = match sort_item {
+            JsonValue::String(field_name) => (field_name, None),
+            JsonValue::Object(_) => {
+                let (field_name, order) = single_entry(sort_item, "the sort")?;
+                let order_opt = match order {
+                    JsonValue::String(order) => Some(order.as_str()),
+                    JsonValue::Object(order_object) => {
+                        order_object.get("order").and_then(JsonValue::as_str)
+                    }
+                    _ => None,
+                };
+                (field_name, order_opt)
+            }
+            _ => {
+                return Err(SearchError::InvalidArgument(format!(
+                    "Invalid sort `{sort_item}`."
+                )))
+            }
+        };
+        if field_name == "_score" || field_name == "_doc" {
+            continue;
+        }
+        let sort_order = match order_opt {
+            Some("asc") | None => SortOrder::Asc,
+            Some("desc") => SortOrder::Desc,
+            Some(order) => {
+                return Err(SearchError::InvalidArgument(format!(
+                    "Invalid sort order `{order}`."
+                )))
+            }
+        };
+        return Ok((Some(sort_order as i32), Some(field_name.clone())));
+    }
+    Ok((None, None))
+}
+
+/// Converts the numbers sent as strings by some clients into JSON numbers.
+fn as_number(value: &JsonValue) -> JsonValue {
+    match value {
+        JsonValue::String(value_str) => value_str
+            .parse::<u64>()
+            .map(JsonValue::from)
+            .unwrap_or_else(|_| value.clone()),
+        _ => value.clone(),
+    }
+}
+
+/// Parses a fixed interval of a date histogram, such as `30s` or `1h`, into seconds. The
+/// timestamps are stored in seconds, so the interval is rounded up to the second.
+fn parse_interval_secs(interval: &str) -> Result<u64, SearchError> {
+    let interval = match interval {
+        "minute" => "1m",
+        "hour" => "1h",
+        "day" => "1d",
+        "week" => "1w",
+        _ => interval,
+    };
+    let unit_start = interval
+        .find(|character: char| !character.is_ascii_digit())
+        .unwrap_or(interval.len());
+    let (value_str, unit) = interval.split_at(unit_start);
+    let unit_millis = match unit {
+        "ms" => 1,
+        "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        "d" => 86_400_000,
+        "w" => 604_800_000,
+        _ => 0,
+    };
+    let interval_millis = value_str.parse::<u64>().unwrap_or_default() * unit_millis;
+    if interval_millis == 0 {
+        return Err(invalid_aggregation(format!(
+            "Unsupported date histogram interval `{interval}`: only fixed intervals are supported."
+        )));
+    }
+    Ok((interval_millis + 999) / 1_000)
+}
+
+/// Translates a date histogram into a histogram on the timestamps, stored in seconds.
+fn translate_date_histogram(name: &str, params: &JsonValue) -> Result<JsonValue, SearchError> {
+    let field_name = params
+        .get("field")
+        .and_then(JsonValue::as_str)
+        .ok_or_else(|| {
+            invalid_aggregation(format!("Date histogram `{name}` does not set a field."))
+        })?;
+    let interval = ["fixed_interval", "interval", "calendar_interval"]
+        .iter()
+        .find_map(|key| params.get(*key))
+        .and_then(JsonValue::as_str)
+        .ok_or_else(|| {
+            invalid_aggregation(format!("Date histogram `{name}` does not set an interval."))
+        })?;
+    let mut histogram = json!({
+        "field": field_name,
+        "interval": parse_interval_secs(interval)?,
+    });
+    let min_doc_count = params
+        .get("min_doc_count")
+        .map(as_number)
+        .unwrap_or_else(|| json!(0));
+    // Extended bounds only make sense with empty buckets.
+    if min_doc_count == json!(0) {
+        if let Some(extended_bounds) = params.get("extended_bounds") {
+            let format = params
+                .get("format")
+                .and_then(JsonValue::as_str)
+                .unwrap_or("epoch_millis");
+            let parse_bound_secs = |key: &str| -> Result<i64, SearchError> {
+                let bound = extended_bounds.get(key).ok_or_else(|| {
+                    invalid_aggregation(format!(
+                        "The extended bounds of date histogram `{name}` do not set `{key}`."
+                    ))
+                })?;
+                parse_timestamp_millis(bound, format).map(|millis| millis.div_euclid(1_000))
+            };
+            histogram["extended_bounds"] = json!({
+                "min": parse_bound_secs("min")?,
+                "max": parse_bound_secs("max")?,
+            });
+        }
+    }
+    histogram["min_doc_count"] = min_doc_count;
+    Ok(histogram)
+}
+
+fn translate_terms(params: &JsonValue) -> JsonValue {
+    let mut terms = JsonMap::new();
+    for key in ["field", "size", "min_doc_count", "order"] {
+        if let Some(value) = params.get(key) {
+            terms.insert(key.to_string(), as_number(value));
+        }
+    }
+    // `_term` is the former name of `_key`.
+    if let Some(JsonValue::Object(order)) = terms.get_mut("order") {
+        if let Some(term_order) = order.remove("_term") {
+            order.insert("_key".to_string(), term_order);
+        }
+    }
+    JsonValue::Object(terms)
+}
+
+#[allow(clippy::type_complexity)]
+fn translate_aggregations(
+    aggregations: &JsonMap<String, JsonValue>,
+) -> Result<
+    (
+        JsonMap<String, JsonValue>,
+        BTreeMap<String, AggregationShape>,
+    ),
+    SearchError,
+> {
+    let mut translated_aggregations = JsonMap::new();
+    let mut aggregation_shapes = BTreeMap::new();
+    for (name, aggregation) in aggregations {
+        let aggregation = aggregation.as_object().ok_or_else(|| {
+            invalid_aggregation(format!("Expected aggregation `{name}` to be an object."))
+        })?;
+        let mut translated_aggregation = JsonMap::new();
+        let mut aggregation_shape = AggregationShape::default();
+        for (key, params) in aggregation {
+            match key.as_str() {
+                "aggs" | "aggregations" => {
+                    let sub_aggregations = params.as_object().ok_or_else(|| {
+                        invalid_aggregation(format!(
+                            "Expected the sub-aggregations of `{name}` to be an object."
+                        ))
+                    })?;
+                    let (translated_sub_aggregations, sub_aggregation_shapes) =
+                        translate_aggregations(sub_aggregations)?;
+                    translated_aggregation.insert(
+                        "aggs".to_string(),
+                        JsonValue::Object(translated_sub_aggregations),
+                    );
+                    aggregation_shape.sub_aggregations = sub_aggregation_shapes;
+                }
+                "date_histogram" => {
+                    translated_aggregation.insert(
+                        "histogram".to_string(),
+                        translate_date_histogram(name, params)?,
+                    );
+                    aggregation_shape.is_date_histogram = true;
+                }
+                "terms" => {
+                    translated_aggregation.insert("terms".to_string(), translate_terms(params));
+                }
+                _ => {
+                    translated_aggregation.insert(key.clone(), params.clone());
+                }
+            }
+        }
+        translated_aggregations.insert(name.clone(), JsonValue::Object(translated_aggregation));
+        aggregation_shapes.insert(name.clone(), aggregation_shape);
+    }
+    Ok((translated_aggregations, aggregation_shapes))
+}
+
+fn build_search_request(
+    index_id: String,
+    request: ElasticSearchRequest,
+    timestamp_field_opt: Option<&str>,
+) -> Result<(SearchRequest, BTreeMap<String, AggregationShape>), SearchError> {
+    let mut query_translation = QueryTranslation::default();
+    if let Some(query) = &request.query {
+        translate_query(query, timestamp_field_opt, &mut query_translation)?;
+    }
+    let (sort_order, sort_by_field) = match &request.sort {
+        Some(sort) => translate_sort(sort)?,
+        None => (None, None),
+    };
+    let (aggregation_request, aggregation_shapes) = match &request.aggs {
+        Some(aggregations) => {
+            let (translated_aggregations, aggregation_shapes) =
+                translate_aggregations(aggregations)?;
+            (
+                Some(JsonValue::Object(translated_aggregations).to_string()),
+                aggregation_shapes,
+            )
+        }
+        None => (None, BTreeMap::new()),
+    };
+    let search_request = SearchRequest {
+        index_id,
+        query: query_translation.query(),
+        start_timestamp: query_translation.start_timestamp,
+        end_timestamp: query_translation.end_timestamp,
+        max_hits: request.size,
+        start_offset: request.from,
+        sort_order,
+        sort_by_field,
+        aggregation_request,
+        ..Default::default()
+    };
+    Ok((search_request, aggregation_shapes))
+}
+
+/// Converts the keys of the buckets of the date histograms back into milliseconds.
+fn convert_aggregation_results(
+    aggregation_results: &mut JsonValue,
+    aggregation_shapes: &BTreeMap<String, AggregationShape>,
+) {
+    for (name, aggregation_shape) in aggregation_shapes {
+        let buckets = match aggregation_results
+            .get_mut(name)
+            .and_then(|aggregation_result| aggregation_result.get_mut("buckets"))
+            .and_then(JsonValue::as_array_mut)
+        {
+            Some(buckets) => buckets,
+            None => continue,
+        };
+        for bucket in buckets {
+            if aggregation_shape.is_date_histogram {
+                if let Some(key_secs) = bucket.get("key").and_then(JsonValue::as_f64) {
+                    let key_millis = (key_secs * 1_000.0) as i64;
+                    bucket["key"] = json!(key_millis);
+                    bucket["key_as_string"] = json!(key_millis.to_string());
+                }
+            }
+            convert_aggregation_results(bucket, &aggregation_shape.sub_aggregations);
+        }
+    }
+}
+
+fn into_elastic_search_response(
+    index_id: &str,
+    search_response: SearchResponse,
+    aggregation_shapes: &BTreeMap<String, AggregationShape>,
+) -> Result<JsonValue, SearchError> {
+    let hits = search_response
+        .hits
+        .into_iter()
+        .map(|hit| {
+            let source: JsonValue = serde_json::from_str(&hit.json).map_err(|error| {
+                SearchError::InternalError(format!("Failed to deserialize hit: {error}"))
+            })?;
+            let doc_id = hit
+                .partial_hit
+                .map(|partial_hit| {
+                    format!(
+                        "{}:{}:{}",
+                        partial_hit.split_id, partial_hit.segment_ord, partial_hit.doc_id
+                    )
+                })
+                .unwrap_or_default();
+            Ok(json!({
+                "_index": index_id,
+                "_id": doc_id,
+                "_score": null,
+                "_source": source,
+            }))
+        })
+        .collect::<Result<Vec<JsonValue>, SearchError>>()?;
+    let mut response = json!({
+        "took": search_response.elapsed_time_micros / 1_000,
+        "timed_out": false,
+        "_shards": {
+            "total": 1,
+            "successful": 1,
+            "skipped": 0,
+            "failed": search_response.errors.len(),
+        },
+        "hits": {
+            "total": {"value": search_response.num_hits, "relation": "eq"},
+            "max_score": null,
+            "hits": hits,
+        },
+    });
+    if let Some(aggregation_json) = search_response.aggregation {
+        let mut aggregation_results: JsonValue =
+            serde_json::from_str(&aggregation_json).map_err(|error| {
+                SearchError::InternalError(format!("Failed to deserialize aggregations: {error}"))
+            })?;
+        convert_aggregation_results(&mut aggregation_results, aggregation_shapes);
+        response["aggregations"] = aggregation_results;
+    }
+    Ok(response)
+}
+
+async fn elastic_search_endpoint(
+    index_id: String,
+    request: ElasticSearchRequest,
+    search_service: &dyn SearchService,
+    metastore: &dyn Metastore,
+) -> Result<JsonValue, SearchError> {
+    let index_metadata = metastore.index_metadata(&index_id).await?;
+    let timestamp_field_opt = index_metadata.indexing_settings.timestamp_field.as_deref();
+    let (search_request, aggregation_shapes) =
+        build_search_request(index_id.clone(), request, timestamp_field_opt)?;
+    let search_response = search_service.root_search(search_request).await?;
+    into_elastic_search_response(&index_id, search_response, &aggregation_shapes)
+}
+
+/// Runs the search requests of a multi-search request concurrently. The requests that fail are
+/// reported in the response, along with the results of the others.
+async fn elastic_msearch_endpoint(
+    payload: Bytes,
+    search_service: &dyn SearchService,
+    metastore: &dyn Metastore,
+) -> Result<JsonValue, SearchError> {
+    let payload = std::str::from_utf8(&payload)
+        .map_err(|_| SearchError::InvalidArgument("Body is not utf-8.".to_string()))?;
+    let mut payload_lines = payload
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+    let mut search_futures = Vec::new();
+    while let Some(header_line) = payload_lines.next() {
+        let header: MultiSearchHeader = serde_json::from_str(header_line).map_err(|error| {
+            SearchError::InvalidArgument(format!("Failed to parse multi-search header: {error}"))
+        })?;
+        let request_line = payload_lines.next().ok_or_else(|| {
+            SearchError::InvalidArgument(
+                "Expected a search request after the multi-search header.".to_string(),
+            )
+        })?;
+        let request: ElasticSearchRequest =
+            serde_json::from_str(request_line).map_err(|error| {
+                SearchError::InvalidArgument(format!("Failed to parse search request: {error}"))
+            })?;
+        search_futures.push(async move {
+            let index_id = header.index_id()?;
+            elastic_search_endpoint(index_id, request, search_service, metastore).await
+        });
+    }
+    let responses: Vec<JsonValue> = join_all(search_futures)
+        .await
+        .into_iter()
+        .map(|search_result| match search_result {
+            Ok(response) => response,
+            Err(error) => json!({
+                "error": {"reason": error.to_string()},
+                "status": error.status_code().to_http_status_code().as_u16(),
+            }),
+        })
+        .collect();
+    Ok(json!({ "responses": responses }))
+}
+
+fn elastic_search_filter(
+) -> impl Filter<Extract = (String, ElasticSearchRequest), Error = Rejection> + Clone {
+    warp::path!(String / "_search")
+        .map(decode_index_id)
+        .and(warp::post())
+        .and(warp::body::content_length_limit(CONTENT_LENGTH_LIMIT))
+        .and(warp::body::json())
+}
+
+fn elastic_msearch_filter() -> impl Filter<Extract = (Bytes,), Error = Rejection> + Clone {
+    warp::path!("_msearch")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(CONTENT_LENGTH_LIMIT))
+        .and(warp::body::bytes())
+}
+
+async fn elastic_search(
+    index_id: String,
+    request: ElasticSearchRequest,
+    search_service: Arc<dyn SearchService>,
+    metastore: Arc<dyn Metastore>,
+) -> Result<impl warp::Reply, Infallible> {
+    info!(index_id = %index_id, request =? request, "elastic-search");
+    Ok(Format::Json.make_rest_reply(
+        elastic_search_endpoint(index_id, request, &*search_service, &*metastore).await,
+    ))
+}
+
+async fn elastic_msearch(
+    payload: Bytes,
+    search_service: Arc<dyn SearchService>,
+    metastore: Arc<dyn Metastore>,
+) -> Result<impl warp::Reply, Infallible> {
+    info!("elastic-msearch");
+    Ok(Format::Json
+        .make_rest_reply(elastic_msearch_endpoint(payload, &*search_service, &*metastore).await))
+}
+
+/// Elasticsearch compatible `_search` handler, for the clients of Elasticsearch such as the
+/// Grafana Elasticsearch datasource.
+pub fn elastic_search_handler(
+    search_service: Arc<dyn SearchService>,
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    elastic_search_filter()
+        .and(with_arg(search_service))
+        .and(with_arg(metastore))
+        .and_then(elastic_search)
+}
+
+/// Elasticsearch compatible `_msearch` handler, used by the Grafana Elasticsearch datasource to
+/// send the queries of a panel at once.
+pub fn elastic_msearch_handler(
+    search_service: Arc<dyn SearchService>,
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    elastic_msearch_filter()
+        .and(with_arg(search_service))
+        .and(with_arg(metastore))
+        .and_then(elastic_msearch)
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_metastore::{IndexMetadata, MockMetastore};
+    use quickwit_proto::{Hit, PartialHit};
+    use quickwit_search::MockSearchService;
+
+    use super::*;
+    use crate::recover_fn;
+
+    fn grafana_search_request() -> ElasticSearchRequest {
+        serde_json::from_value(json!({
+            "size": 0,
+            "query": {
+                "bool": {
+                    "filter": [
+                        {
+                            "range": {
+                                "timestamp": {
+                                    "gte": 1660000000500u64,
+                                    "lte": 1660000060000u64,
+                                    "format": "epoch_millis"
+                                }
+                            }
+                        },
+                        {"range": {"response_time": {"gt": 100}}},
+                        {"query_string": {"analyze_wildcard": true, "query": "body:error"}}
+                    ]
+                }
+            },
+            "sort": [{"_score": "desc"}, {"timestamp": {"order": "desc", "unmapped_type": "boolean"}}],
+            "aggs": {
+                "2": {
+                    "date_histogram": {
+                        "field": "timestamp",
+                        "fixed_interval": "10s",
+                        "min_doc_count": 0,
+                        "extended_bounds": {"min": 1660000000500u64, "max": 1660000060000u64},
+                        "format": "epoch_millis"
+                    },
+                    "aggs": {
+                        "3": {
+                            "terms": {
+                                "field": "owner",
+                                "size": "10",
+                                "order": {"_term": "desc"},
+                                "min_doc_count": 1
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_build_search_request() {
+        let (search_request, aggregation_shapes) = build_search_request(
+            "test-index".to_string(),
+            grafana_search_request(),
+            Some("timestamp"),
+        )
+        .unwrap();
+        assert_eq!(search_request.index_id, "test-index");
+        assert_eq!(
+            search_request.query,
+            "response_time:{100 TO *] AND (body:error)"
+        );
+        assert_eq!(search_request.start_timestamp, Some(1_660_000_001));
+        assert_eq!(search_request.end_timestamp, Some(1_660_000_061));
+        assert_eq!(search_request.max_hits, 0);
+        assert_eq!(search_request.sort_order, Some(SortOrder::Desc as i32));
+        assert_eq!(search_request.sort_by_field.as_deref(), Some("timestamp"));
+        let aggregation_request: JsonValue =
+            serde_json::from_str(&search_request.aggregation_request.unwrap()).unwrap();
+        assert_eq!(
+            aggregation_request,
+            json!({
+                "2": {
+                    "histogram": {
+                        "field": "timestamp",
+                        "interval": 10,
+                        "min_doc_count": 0,
+                        "extended_bounds": {"min": 1_660_000_000, "max": 1_660_000_060}
+                    },
+                    "aggs": {
+                        "3": {
+                            "terms": {
+                                "field": "owner",
+                                "size": 10,
+                                "order": {"_key": "desc"},
+                                "min_doc_count": 1
+                            }
+                        }
+                    }
+                }
+            })
+        );
+        assert_eq!(
+            aggregation_shapes,
+            BTreeMap::from([(
+                "2".to_string(),
+                AggregationShape {
+                    is_date_histogram: true,
+                    sub_aggregations: BTreeMap::from([(
+                        "3".to_string(),
+                        AggregationShape::default()
+                    )]),
+                }
+            )])
+        );
+    }
+
+    #[test]
+    fn test_build_search_request_rejects_unsupported_queries() {
+        let request: ElasticSearchRequest = serde_json::from_value(json!({
+            "query": {"bool": {"should": [{"match_all": {}}]}}
+        }))
+        .unwrap();
+        let error = build_search_request("test-index".to_string(), request, None).unwrap_err();
+        assert!(matches!(error, SearchError::InvalidQuery(_)));
+
+        let request: ElasticSearchRequest = serde_json::from_value(json!({
+            "aggs": {"1": {"date_histogram": {"field": "timestamp", "calendar_interval": "1M"}}}
+        }))
+        .unwrap();
+        let error = build_search_request("test-index".to_string(), request, None).unwrap_err();
+        assert!(matches!(error, SearchError::InvalidAggregationRequest(_)));
+    }
+
+    #[test]
+    fn test_parse_interval_secs() {
+        assert_eq!(parse_interval_secs("30s").unwrap(), 30);
+        assert_eq!(parse_interval_secs("500ms").unwrap(), 1);
+        assert_eq!(parse_interval_secs("2h").unwrap(), 7_200);
+        assert_eq!(parse_interval_secs("day").unwrap(), 86_400);
+        assert!(parse_interval_secs("1M").is_err());
+        assert!(parse_interval_secs("auto").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_elastic_msearch_handler() {
+        let mut mock_metastore = MockMetastore::new();
+        mock_metastore
+            .expect_index_metadata()
+            .returning(|index_id| {
+                Ok(IndexMetadata::for_test(
+                    index_id,
+                    "ram:///indexes/test-index",
+                ))
+            });
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .times(1)
+            .returning(|search_request| {
+                assert_eq!(search_request.query, "*");
+                assert_eq!(search_request.start_timestamp, Some(1_660_000_000));
+                let aggregation = json!({
+                    "1": {"buckets": [{"key": 1_660_000_000.0, "doc_count": 1}]}
+                });
+                Ok(SearchResponse {
+                    num_hits: 1,
+                    hits: vec![Hit {
+                        json: r#"{"body": "error", "timestamp": 1660000000}"#.to_string(),
+                        partial_hit: Some(PartialHit {
+                            split_id: "split".to_string(),
+                            segment_ord: 0,
+                            doc_id: 1,
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }],
+                    elapsed_time_micros: 2_000,
+                    aggregation: Some(aggregation.to_string()),
+                    ..Default::default()
+                })
+            });
+        let handler =
+            elastic_msearch_handler(Arc::new(mock_search_service), Arc::new(mock_metastore))
+                .recover(recover_fn);
+        let payload = r#"
+            {"index": "test-index", "ignore_unavailable": true}
+            {"size": 1, "query": {"range": {"timestamp": {"gte": 1660000000000}}}, "aggs": {"1": {"date_histogram": {"field": "timestamp", "fixed_interval": "1m"}}}}
+            {"index": ["index-a", "index-b"]}
+            {"size": 1}
+        "#;
+        let response = warp::test::request()
+            .method("POST")
+            .path("/_msearch")
+            .body(payload)
+            .reply(&handler)
+            .await;
+        assert_eq!(response.status(), 200);
+        let response_json: JsonValue = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            response_json["responses"][0],
+            json!({
+                "took": 2,
+                "timed_out": false,
+                "_shards": {"total": 1, "successful": 1, "skipped": 0, "failed": 0},
+                "hits": {
+                    "total": {"value": 1, "relation": "eq"},
+                    "max_score": null,
+                    "hits": [{
+                        "_index": "test-index",
+                        "_id": "split:0:1",
+                        "_score": null,
+                        "_source": {"body": "error", "timestamp": 1_660_000_000},
+                    }],
+                },
+                "aggregations": {
+                    "1": {
+                        "buckets": [{
+                            "key": 1_660_000_000_000i64,
+                            "key_as_string": "1660000000000",
+                            "doc_count": 1,
+                        }]
+                    }
+                },
+            })
+        );
+        assert_eq!(response_json["responses"][1]["status"], 400);
+    }
+}
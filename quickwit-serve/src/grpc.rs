@@ -19,14 +19,19 @@
 
 use std::net::SocketAddr;
 
+use opentelemetry_proto::tonic::collector::logs::v1::logs_service_server::LogsServiceServer;
+use opentelemetry_proto::tonic::collector::trace::v1::trace_service_server::TraceServiceServer;
 use quickwit_cluster::QuickwitService;
 use quickwit_metastore::GrpcMetastoreAdapter;
+use quickwit_proto::jaeger::api_v2::query_service_server::QueryServiceServer;
 use quickwit_proto::metastore_api::metastore_api_service_server::MetastoreApiServiceServer;
 use quickwit_proto::search_service_server::SearchServiceServer;
 use quickwit_proto::tonic;
 use tonic::transport::Server;
 use tracing::*;
 
+use crate::jaeger_api::JaegerService;
+use crate::otlp_api::{OtlpLogsService, OtlpTracesService};
 use crate::search_api::GrpcSearchAdapter;
 use crate::QuickwitServices;
 
@@ -64,9 +69,59 @@ pub(crate) async fn start_grpc_server(
         None
     };
 
+    // We only mount the OTLP logs gRPC service if the ingest API is available on this node and an
+    // index is configured to receive the logs.
+    let otlp_logs_grpc = match (
+        &quickwit_services.ingest_api_service,
+        &quickwit_services.config.indexer_config.otlp_logs_index_id,
+    ) {
+        (Some(ingest_api_service), Some(otlp_logs_index_id)) => {
+            info!(index_id = %otlp_logs_index_id, "Mounting OTLP logs gRPC service.");
+            let otlp_logs_service =
+                OtlpLogsService::new(otlp_logs_index_id.clone(), ingest_api_service.clone());
+            Some(LogsServiceServer::new(otlp_logs_service))
+        }
+        _ => None,
+    };
+
+    // Same for the OTLP traces gRPC service.
+    let otlp_traces_grpc = match (
+        &quickwit_services.ingest_api_service,
+        &quickwit_services.config.indexer_config.otlp_traces_index_id,
+    ) {
+        (Some(ingest_api_service), Some(otlp_traces_index_id)) => {
+            info!(index_id = %otlp_traces_index_id, "Mounting OTLP traces gRPC service.");
+            let otlp_traces_service =
+                OtlpTracesService::new(otlp_traces_index_id.clone(), ingest_api_service.clone());
+            Some(TraceServiceServer::new(otlp_traces_service))
+        }
+        _ => None,
+    };
+
+    // We only mount the Jaeger query gRPC service if `QuickwitService::Searcher` is enabled on this
+    // node and an index is configured to receive the traces.
+    let jaeger_grpc = match &quickwit_services.config.indexer_config.otlp_traces_index_id {
+        Some(otlp_traces_index_id)
+            if quickwit_services
+                .services
+                .contains(&QuickwitService::Searcher) =>
+        {
+            info!(index_id = %otlp_traces_index_id, "Mounting Jaeger query gRPC service.");
+            let jaeger_service = JaegerService::new(
+                otlp_traces_index_id.clone(),
+                quickwit_services.search_service.clone(),
+            );
+            Some(QueryServiceServer::new(jaeger_service))
+        }
+        _ => None,
+    };
+
     let server_router = server
         .add_optional_service(search_grpc_service)
-        .add_optional_service(metastore_grpc);
+        .add_optional_service(metastore_grpc)
+        .add_optional_service(otlp_logs_grpc)
+        .add_optional_service(otlp_traces_grpc)
+        .add_optional_service(jaeger_grpc);
     server_router.serve(grpc_listen_addr).await?;
 
     Ok(())
@@ -22,18 +22,20 @@ use std::sync::Arc;
 
 use quickwit_core::IndexService;
 use quickwit_search::SearchError;
+use serde::Deserialize;
 use tracing::info;
 use warp::{Filter, Rejection};
 
 use crate::format::Format;
-use crate::with_arg;
+use crate::{decode_index_id, with_arg};
 
 pub fn index_management_handlers(
     index_service: Arc<IndexService>,
 ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
     get_index_metadata_handler(index_service.clone())
         .or(get_indexes_metadatas_handler(index_service.clone()))
-        .or(get_all_splits_handler(index_service))
+        .or(get_all_splits_handler(index_service.clone()))
+        .or(export_index_handler(index_service))
     // TODO: comment create/delete handlers and reactivate/update them once we implemented the logic
     // of routing these requests to the right node, see https://github.com/quickwit-oss/quickwit/issues/1481.
     //.or(create_index_handler(index_service.clone()))
@@ -44,6 +46,7 @@ fn get_index_metadata_handler(
     index_service: Arc<IndexService>,
 ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
     warp::path!("indexes" / String)
+        .map(decode_index_id)
         .and(warp::get())
         .and(with_arg(index_service))
         .and_then(get_index_metadata)
@@ -58,11 +61,21 @@ async fn get_index_metadata(
     Ok(Format::default().make_rest_reply_non_serializable_error(index_metadata))
 }
 
+/// Query string of the index listing endpoint.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ListIndexesQueryString {
+    /// Only lists the indexes of this namespace, including the indexes of its nested namespaces.
+    #[serde(default)]
+    namespace: Option<String>,
+}
+
 fn get_indexes_metadatas_handler(
     index_service: Arc<IndexService>,
 ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
     warp::path!("indexes")
         .and(warp::get())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
         .and(warp::path::end().map(move || index_service.clone()))
         .and_then(get_indexes_metadatas)
 }
@@ -80,19 +93,42 @@ fn get_all_splits_handler(
     index_service: Arc<IndexService>,
 ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
     warp::path!("indexes" / String / "splits")
+        .map(decode_index_id)
         .and(warp::get())
         .and(warp::path::end().map(move || index_service.clone()))
         .and_then(get_all_splits)
 }
 
+fn export_index_handler(
+    index_service: Arc<IndexService>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "export")
+        .map(decode_index_id)
+        .and(warp::get())
+        .and(warp::path::end().map(move || index_service.clone()))
+        .and_then(export_index)
+}
+
+async fn export_index(
+    index_id: String,
+    index_service: Arc<IndexService>,
+) -> Result<impl warp::Reply, Infallible> {
+    info!(index_id = %index_id, "export-index");
+    let index_config = index_service.export_index(&index_id).await;
+    Ok(Format::default().make_rest_reply_non_serializable_error(index_config))
+}
+
 async fn get_indexes_metadatas(
+    query_string: ListIndexesQueryString,
     index_service: Arc<IndexService>,
 ) -> Result<impl warp::Reply, Infallible> {
-    info!("get-indexes-metadatas");
-    let index_metadata = index_service
-        .list_indexes()
-        .await
-        .map_err(SearchError::from);
+    info!(namespace = ?query_string.namespace, "get-indexes-metadatas");
+    let index_metadata = if let Some(namespace) = &query_string.namespace {
+        index_service.list_indexes_in_namespace(namespace).await
+    } else {
+        index_service.list_indexes().await
+    }
+    .map_err(SearchError::from);
     Ok(Format::default().make_rest_reply_non_serializable_error(index_metadata))
 }
 
@@ -216,6 +252,43 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_rest_export_index() -> anyhow::Result<()> {
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_index_metadata()
+            .returning(|_index_id: &str| {
+                Ok(IndexMetadata::for_test(
+                    "test-index",
+                    "ram:///indexes/test-index",
+                ))
+            });
+        let index_service = IndexService::new(
+            Arc::new(metastore),
+            StorageUriResolver::for_test(),
+            Uri::new("ram:///indexes".to_string()),
+        );
+        let index_management_handler =
+            super::index_management_handlers(Arc::new(index_service)).recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/indexes/test-index/export")
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let actual_response_json: serde_json::Value = serde_json::from_slice(resp.body())?;
+        let expected_response_json = serde_json::json!({
+            "version": 0,
+            "index_id": "test-index",
+            "index_uri": "ram:///indexes/test-index",
+        });
+        assert_json_include!(
+            actual: actual_response_json,
+            expected: expected_response_json
+        );
+        assert!(actual_response_json.get("doc_mapping").is_some());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_rest_get_list_indexes() -> anyhow::Result<()> {
         let mut metastore = MockMetastore::new();
@@ -249,6 +322,64 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_rest_namespaced_indexes() -> anyhow::Result<()> {
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_list_indexes_metadatas_in_namespace()
+            .withf(|namespace| namespace == "team-a")
+            .returning(|_| {
+                Ok(vec![IndexMetadata::for_test(
+                    "team-a/logs",
+                    "ram:///indexes/team-a/logs",
+                )])
+            });
+        metastore
+            .expect_index_metadata()
+            .withf(|index_id| index_id == "team-a/logs")
+            .returning(|_| {
+                Ok(IndexMetadata::for_test(
+                    "team-a/logs",
+                    "ram:///indexes/team-a/logs",
+                ))
+            });
+        let index_service = IndexService::new(
+            Arc::new(metastore),
+            StorageUriResolver::for_test(),
+            Uri::new("ram:///indexes".to_string()),
+        );
+        let index_management_handler =
+            super::index_management_handlers(Arc::new(index_service)).recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/indexes?namespace=team-a")
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let actual_response_json: serde_json::Value = serde_json::from_slice(resp.body())?;
+        let expected_response_json = serde_json::json!([{
+            "index_id": "team-a/logs",
+        }]);
+        assert_json_include!(
+            actual: actual_response_json,
+            expected: expected_response_json
+        );
+
+        let resp = warp::test::request()
+            .path("/indexes/team-a%2Flogs")
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let actual_response_json: serde_json::Value = serde_json::from_slice(resp.body())?;
+        let expected_response_json = serde_json::json!({
+            "index_id": "team-a/logs",
+        });
+        assert_json_include!(
+            actual: actual_response_json,
+            expected: expected_response_json
+        );
+        Ok(())
+    }
+
     // TODO: comment create/delete handlers and reactivate/update them once we implemented the logic
     // of routing these requests to the right node, see https://github.com/quickwit-oss/quickwit/issues/1481.
     // #[tokio::test]
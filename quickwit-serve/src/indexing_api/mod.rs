@@ -17,6 +17,8 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+mod relocation_handoff;
 mod rest_handler;
 
-pub use rest_handler::indexing_get_handler;
+pub(crate) use relocation_handoff::relocation_handoff_task;
+pub use rest_handler::{indexing_get_handler, indexing_pipelines_handlers};
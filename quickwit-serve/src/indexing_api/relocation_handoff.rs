@@ -0,0 +1,98 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use quickwit_actors::Mailbox;
+use quickwit_cluster::Cluster;
+use quickwit_indexing::actors::IndexingService;
+use quickwit_indexing::models::{
+    AdoptPipeline, ObserveRelocations, PipelineRelocation, RelocationState,
+};
+use tracing::error;
+
+/// Key under which each node advertises the pipelines it drained for relocation.
+const PIPELINE_RELOCATIONS_KEY: &str = "pipeline_relocations";
+
+const RELOCATION_HANDOFF_INTERVAL: Duration = if cfg!(any(test, feature = "testsuite")) {
+    Duration::from_millis(25)
+} else {
+    Duration::from_secs(5)
+};
+
+/// Advertises the pipelines drained on this node in the cluster state, and adopts the pipelines
+/// drained on other nodes and relocated to this node.
+pub(crate) async fn relocation_handoff_task(
+    cluster: Arc<Cluster>,
+    node_id: String,
+    indexing_service_mailbox: Mailbox<IndexingService>,
+) {
+    let mut interval = tokio::time::interval(RELOCATION_HANDOFF_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(error) = handoff_relocations(&cluster, &node_id, &indexing_service_mailbox).await
+        {
+            error!(error=?error, "Failed to hand off pipeline relocations.");
+        }
+    }
+}
+
+async fn handoff_relocations(
+    cluster: &Cluster,
+    node_id: &str,
+    indexing_service_mailbox: &Mailbox<IndexingService>,
+) -> anyhow::Result<()> {
+    let drained_relocations: Vec<PipelineRelocation> = indexing_service_mailbox
+        .ask(ObserveRelocations)
+        .await?
+        .into_iter()
+        .filter(|relocation| relocation.state == RelocationState::Drained)
+        .collect();
+    let drained_relocations_json = serde_json::to_string(&drained_relocations)?;
+    cluster
+        .set_key_value(PIPELINE_RELOCATIONS_KEY, drained_relocations_json)
+        .await;
+
+    for (peer_node_id, relocations_json) in cluster.node_key_values(PIPELINE_RELOCATIONS_KEY).await
+    {
+        let relocations: Vec<PipelineRelocation> = match serde_json::from_str(&relocations_json) {
+            Ok(relocations) => relocations,
+            Err(error) => {
+                error!(peer_node_id=%peer_node_id, error=?error, "Failed to parse pipeline relocations.");
+                continue;
+            }
+        };
+        for relocation in relocations {
+            if relocation.target_node_id != node_id {
+                continue;
+            }
+            // Adopting a pipeline is idempotent.
+            if let Err(error) = indexing_service_mailbox
+                .ask_for_res(AdoptPipeline {
+                    relocated_pipeline_id: relocation.pipeline_id.clone(),
+                })
+                .await
+            {
+                error!(pipeline_id=?relocation.pipeline_id, error=?error, "Failed to adopt relocated pipeline.");
+            }
+        }
+    }
+    Ok(())
+}
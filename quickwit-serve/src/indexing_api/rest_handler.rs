@@ -18,14 +18,23 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::convert::Infallible;
+use std::sync::Arc;
 
 use quickwit_actors::Mailbox;
+use quickwit_cluster::{Cluster, QuickwitService};
 use quickwit_indexing::actors::IndexingService;
-use quickwit_indexing::models::Observe;
-use warp::{Filter, Rejection};
+use quickwit_indexing::models::{
+    ForceMergeIndex, IndexingPipelineId, ListPipelineWarnings, ListPipelines, Observe,
+    ObserveFieldProfile, ObservePipelineTopologies, ObserveRelocations, ObserveSourceHealth,
+    ObserveStorageOps, PipelineRelocation, RecompressIndex, RelocatePipeline,
+};
+use quickwit_proto::ServiceErrorCode;
+use serde::Deserialize;
+use warp::hyper::header::CONTENT_TYPE;
+use warp::{reply, Filter, Rejection, Reply};
 
-use crate::format::Format;
-use crate::require;
+use crate::format::{Format, FormatError};
+use crate::{require, with_arg};
 
 async fn indexing_endpoint(
     indexing_service_mailbox: Mailbox<IndexingService>,
@@ -45,3 +54,310 @@ pub fn indexing_get_handler(
         .and(require(indexing_service_mailbox_opt))
         .and_then(indexing_endpoint)
 }
+
+async fn list_pipelines_endpoint(
+    indexing_service_mailbox: Mailbox<IndexingService>,
+) -> Result<impl warp::Reply, Infallible> {
+    let pipeline_ids = indexing_service_mailbox.ask(ListPipelines).await;
+    Ok(Format::PrettyJson.make_rest_reply_non_serializable_error(pipeline_ids))
+}
+
+fn list_pipelines_filter() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::path!("indexing" / "pipelines").and(warp::get())
+}
+
+async fn list_warnings_endpoint(
+    indexing_service_mailbox: Mailbox<IndexingService>,
+) -> Result<impl warp::Reply, Infallible> {
+    let warnings = indexing_service_mailbox.ask(ListPipelineWarnings).await;
+    Ok(Format::PrettyJson.make_rest_reply_non_serializable_error(warnings))
+}
+
+fn list_warnings_filter() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::path!("indexing" / "warnings").and(warp::get())
+}
+
+async fn storage_ops_endpoint(
+    indexing_service_mailbox: Mailbox<IndexingService>,
+) -> Result<impl warp::Reply, Infallible> {
+    let storage_ops = indexing_service_mailbox.ask(ObserveStorageOps).await;
+    Ok(Format::PrettyJson.make_rest_reply_non_serializable_error(storage_ops))
+}
+
+fn storage_ops_filter() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::path!("indexing" / "storage-ops").and(warp::get())
+}
+
+async fn source_health_endpoint(
+    index_id: String,
+    source_id: String,
+    indexing_service_mailbox: Mailbox<IndexingService>,
+) -> Result<impl warp::Reply, Infallible> {
+    let source_health = indexing_service_mailbox
+        .ask_for_res(ObserveSourceHealth {
+            index_id,
+            source_id,
+        })
+        .await
+        .map_err(FormatError::wrap);
+    Ok(Format::PrettyJson.make_rest_reply_non_serializable_error(source_health))
+}
+
+fn source_health_filter() -> impl Filter<Extract = (String, String), Error = Rejection> + Clone {
+    warp::path!("indexing" / "sources" / String / String / "health").and(warp::get())
+}
+
+/// Output format of the topology of the pipelines.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum TopologyFormat {
+    Json,
+    /// DOT language of Graphviz, with one graph per pipeline.
+    Dot,
+}
+
+impl Default for TopologyFormat {
+    fn default() -> Self {
+        TopologyFormat::Json
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PipelineTopologyQueryString {
+    #[serde(default)]
+    format: TopologyFormat,
+}
+
+async fn pipeline_topology_endpoint(
+    index_id: String,
+    source_id: String,
+    query_string: PipelineTopologyQueryString,
+    indexing_service_mailbox: Mailbox<IndexingService>,
+) -> Result<warp::reply::Response, Infallible> {
+    let pipeline_topologies = indexing_service_mailbox
+        .ask_for_res(ObservePipelineTopologies {
+            index_id,
+            source_id,
+        })
+        .await
+        .map_err(FormatError::wrap);
+    match (query_string.format, pipeline_topologies) {
+        (TopologyFormat::Dot, Ok(pipeline_topologies)) => {
+            let dot: String = pipeline_topologies
+                .iter()
+                .map(|pipeline_topology| pipeline_topology.to_dot())
+                .collect();
+            Ok(reply::with_header(dot, CONTENT_TYPE, "text/vnd.graphviz").into_response())
+        }
+        (_, pipeline_topologies) => Ok(Format::PrettyJson
+            .make_rest_reply_non_serializable_error(pipeline_topologies)
+            .into_response()),
+    }
+}
+
+fn pipeline_topology_filter(
+) -> impl Filter<Extract = (String, String, PipelineTopologyQueryString), Error = Rejection> + Clone
+{
+    warp::path!("indexing" / "sources" / String / String / "topology")
+        .and(warp::get())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+}
+
+async fn field_profile_endpoint(
+    index_id: String,
+    source_id: String,
+    indexing_service_mailbox: Mailbox<IndexingService>,
+) -> Result<impl warp::Reply, Infallible> {
+    let field_profile_report = indexing_service_mailbox
+        .ask_for_res(ObserveFieldProfile {
+            index_id,
+            source_id,
+        })
+        .await
+        .map_err(FormatError::wrap);
+    Ok(Format::PrettyJson.make_rest_reply_non_serializable_error(field_profile_report))
+}
+
+fn field_profile_filter() -> impl Filter<Extract = (String, String), Error = Rejection> + Clone {
+    warp::path!("indexing" / "sources" / String / String / "field-profile").and(warp::get())
+}
+
+async fn list_relocations_endpoint(
+    indexing_service_mailbox: Mailbox<IndexingService>,
+) -> Result<impl warp::Reply, Infallible> {
+    let relocations = indexing_service_mailbox.ask(ObserveRelocations).await;
+    Ok(Format::PrettyJson.make_rest_reply_non_serializable_error(relocations))
+}
+
+fn list_relocations_filter() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::path!("indexing" / "relocations").and(warp::get())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RelocatePipelineRequest {
+    index_id: String,
+    source_id: String,
+    #[serde(default)]
+    pipeline_ord: usize,
+    target_node_id: String,
+}
+
+async fn relocate_pipeline(
+    request: RelocatePipelineRequest,
+    node_id: String,
+    cluster: Arc<Cluster>,
+    indexing_service_mailbox: Mailbox<IndexingService>,
+) -> Result<PipelineRelocation, FormatError> {
+    let is_target_node_indexer = cluster.members().iter().any(|member| {
+        member.node_unique_id == request.target_node_id
+            && member
+                .available_services
+                .contains(&QuickwitService::Indexer)
+    });
+    if !is_target_node_indexer {
+        return Err(FormatError {
+            code: ServiceErrorCode::BadRequest,
+            error: format!(
+                "Node `{}` is not an indexer of the cluster.",
+                request.target_node_id
+            ),
+        });
+    }
+    let relocate_pipeline = RelocatePipeline {
+        pipeline_id: IndexingPipelineId {
+            index_id: request.index_id,
+            source_id: request.source_id,
+            node_id,
+            pipeline_ord: request.pipeline_ord,
+        },
+        target_node_id: request.target_node_id,
+    };
+    indexing_service_mailbox
+        .ask_for_res(relocate_pipeline)
+        .await
+        .map_err(FormatError::wrap)
+}
+
+async fn relocate_pipeline_endpoint(
+    request: RelocatePipelineRequest,
+    node_id: String,
+    cluster: Arc<Cluster>,
+    indexing_service_mailbox: Mailbox<IndexingService>,
+) -> Result<impl warp::Reply, Infallible> {
+    let relocation = relocate_pipeline(request, node_id, cluster, indexing_service_mailbox).await;
+    Ok(Format::PrettyJson.make_rest_reply_non_serializable_error(relocation))
+}
+
+fn relocate_pipeline_filter(
+) -> impl Filter<Extract = (RelocatePipelineRequest,), Error = Rejection> + Clone {
+    warp::path!("indexing" / "pipelines" / "relocate")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(1024 * 1024))
+        .and(warp::body::json())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ForceMergeRequest {
+    index_id: String,
+    #[serde(default = "default_target_num_splits")]
+    target_num_splits: usize,
+}
+
+fn default_target_num_splits() -> usize {
+    1
+}
+
+async fn force_merge_endpoint(
+    request: ForceMergeRequest,
+    indexing_service_mailbox: Mailbox<IndexingService>,
+) -> Result<impl warp::Reply, Infallible> {
+    let force_merge_index = ForceMergeIndex {
+        index_id: request.index_id,
+        target_num_splits: request.target_num_splits,
+    };
+    let force_merged_pipeline_ids = indexing_service_mailbox
+        .ask_for_res(force_merge_index)
+        .await
+        .map_err(FormatError::wrap);
+    Ok(Format::PrettyJson.make_rest_reply_non_serializable_error(force_merged_pipeline_ids))
+}
+
+fn force_merge_filter() -> impl Filter<Extract = (ForceMergeRequest,), Error = Rejection> + Clone {
+    warp::path!("indexing" / "force-merge")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(1024 * 1024))
+        .and(warp::body::json())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RecompressRequest {
+    index_id: String,
+}
+
+async fn recompress_endpoint(
+    request: RecompressRequest,
+    indexing_service_mailbox: Mailbox<IndexingService>,
+) -> Result<impl warp::Reply, Infallible> {
+    let recompress_index = RecompressIndex {
+        index_id: request.index_id,
+    };
+    let recompressed_pipeline_ids = indexing_service_mailbox
+        .ask_for_res(recompress_index)
+        .await
+        .map_err(FormatError::wrap);
+    Ok(Format::PrettyJson.make_rest_reply_non_serializable_error(recompressed_pipeline_ids))
+}
+
+fn recompress_filter() -> impl Filter<Extract = (RecompressRequest,), Error = Rejection> + Clone {
+    warp::path!("indexing" / "recompress")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(1024 * 1024))
+        .and(warp::body::json())
+}
+
+/// Pipeline control handlers: list the pipelines running on the node, their warnings and storage
+/// requests, relocate one of them to another node, observe the relocations requested on the node,
+/// observe the health of a source, the actor topology of its pipelines, and the profile of the
+/// fields of its documents, and force merge or recompress an index.
+pub fn indexing_pipelines_handlers(
+    node_id: String,
+    cluster: Arc<Cluster>,
+    indexing_service_mailbox_opt: Option<Mailbox<IndexingService>>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    list_pipelines_filter()
+        .and(require(indexing_service_mailbox_opt.clone()))
+        .and_then(list_pipelines_endpoint)
+        .or(list_warnings_filter()
+            .and(require(indexing_service_mailbox_opt.clone()))
+            .and_then(list_warnings_endpoint))
+        .or(storage_ops_filter()
+            .and(require(indexing_service_mailbox_opt.clone()))
+            .and_then(storage_ops_endpoint))
+        .or(source_health_filter()
+            .and(require(indexing_service_mailbox_opt.clone()))
+            .and_then(source_health_endpoint))
+        .or(pipeline_topology_filter()
+            .and(require(indexing_service_mailbox_opt.clone()))
+            .and_then(pipeline_topology_endpoint))
+        .or(field_profile_filter()
+            .and(require(indexing_service_mailbox_opt.clone()))
+            .and_then(field_profile_endpoint))
+        .or(list_relocations_filter()
+            .and(require(indexing_service_mailbox_opt.clone()))
+            .and_then(list_relocations_endpoint))
+        .or(relocate_pipeline_filter()
+            .and(with_arg(node_id))
+            .and(with_arg(cluster))
+            .and(require(indexing_service_mailbox_opt.clone()))
+            .and_then(relocate_pipeline_endpoint))
+        .or(force_merge_filter()
+            .and(require(indexing_service_mailbox_opt.clone()))
+            .and_then(force_merge_endpoint))
+        .or(recompress_filter()
+            .and(require(indexing_service_mailbox_opt))
+            .and_then(recompress_endpoint))
+}
@@ -19,5 +19,7 @@
 
 mod grpc_adapter;
 mod rest_handler;
+mod webhook_handler;
 
 pub use rest_handler::{elastic_bulk_handler, ingest_handler, tail_handler, BulkApiError};
+pub use webhook_handler::webhook_handler;
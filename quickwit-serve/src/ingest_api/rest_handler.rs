@@ -19,18 +19,19 @@
 
 use std::collections::HashMap;
 use std::convert::Infallible;
+use std::time::Duration;
 
 use bytes::Bytes;
 use quickwit_actors::Mailbox;
-use quickwit_ingest_api::{add_doc, IngestApiService};
-use quickwit_proto::ingest_api::{DocBatch, IngestRequest, TailRequest};
+use quickwit_ingest_api::{add_doc, IngestApiService, IngestWaitForPublishRequest};
+use quickwit_proto::ingest_api::{DocBatch, IngestRequest, IngestResponse, TailRequest};
 use serde::Deserialize;
 use serde_json::Value;
 use thiserror::Error;
 use warp::{reject, Filter, Rejection};
 
 use crate::format::FormatError;
-use crate::{require, Format};
+use crate::{decode_index_id, require, Format};
 
 #[derive(Debug, Error)]
 #[error("Body is not utf-8.")]
@@ -46,6 +47,20 @@ impl warp::reject::Reject for IngestApiServiceUnavailable {}
 
 const CONTENT_LENGTH_LIMIT: u64 = 10_000_000; // 10M
 
+fn default_wait_for_publish_timeout_secs() -> u64 {
+    30
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IngestOptions {
+    /// Returns only once the documents are published, so that they are searchable.
+    #[serde(default)]
+    wait_for_publish: bool,
+    #[serde(default = "default_wait_for_publish_timeout_secs")]
+    timeout_secs: u64,
+}
+
 #[derive(Debug, Error)]
 pub enum BulkApiError {
     #[error("Could not parse action `{0}`.")]
@@ -88,9 +103,12 @@ pub fn ingest_handler(
         .and_then(ingest)
 }
 
-fn ingest_filter() -> impl Filter<Extract = (String, String), Error = Rejection> + Clone {
+fn ingest_filter(
+) -> impl Filter<Extract = (String, IngestOptions, String), Error = Rejection> + Clone {
     warp::path!(String / "ingest")
+        .map(decode_index_id)
         .and(warp::post())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
         .and(warp::body::content_length_limit(CONTENT_LENGTH_LIMIT))
         .and(warp::body::bytes().and_then(|body: Bytes| async move {
             if let Ok(body_str) = std::str::from_utf8(&*body) {
@@ -113,6 +131,7 @@ fn lines(body: &str) -> impl Iterator<Item = &str> {
 
 async fn ingest(
     index_id: String,
+    ingest_options: IngestOptions,
     payload: String,
     ingest_api_mailbox: Mailbox<IngestApiService>,
 ) -> Result<impl warp::Reply, Infallible> {
@@ -126,13 +145,34 @@ async fn ingest(
     let ingest_req = IngestRequest {
         doc_batches: vec![doc_batch],
     };
-    let ingest_resp = ingest_api_mailbox
-        .ask_for_res(ingest_req)
-        .await
-        .map_err(FormatError::wrap);
+    let ingest_resp = if ingest_options.wait_for_publish {
+        let timeout = Duration::from_secs(ingest_options.timeout_secs);
+        ingest_wait_for_publish(ingest_req, timeout, ingest_api_mailbox).await
+    } else {
+        ingest_api_mailbox
+            .ask_for_res(ingest_req)
+            .await
+            .map_err(FormatError::wrap)
+    };
     Ok(Format::PrettyJson.make_rest_reply(ingest_resp))
 }
 
+async fn ingest_wait_for_publish(
+    ingest_req: IngestRequest,
+    timeout: Duration,
+    ingest_api_mailbox: Mailbox<IngestApiService>,
+) -> Result<IngestResponse, FormatError> {
+    let (ingest_resp, publish_waiter) = ingest_api_mailbox
+        .ask_for_res(IngestWaitForPublishRequest(ingest_req))
+        .await
+        .map_err(FormatError::wrap)?;
+    publish_waiter
+        .wait(timeout)
+        .await
+        .map_err(FormatError::wrap)?;
+    Ok(ingest_resp)
+}
+
 pub fn tail_handler(
     ingest_api_mailbox_opt: Option<Mailbox<IngestApiService>>,
 ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
@@ -142,7 +182,9 @@ pub fn tail_handler(
 }
 
 fn tail_filter() -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
-    warp::path!(String / "fetch").and(warp::get())
+    warp::path!(String / "fetch")
+        .map(decode_index_id)
+        .and(warp::get())
 }
 
 async fn tail_endpoint(
@@ -0,0 +1,291 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::convert::Infallible;
+use std::env;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use hmac::{Hmac, Mac};
+use hyper::HeaderMap;
+use quickwit_actors::{AskError, Mailbox};
+use quickwit_config::{SourceParams, WebhookSourceParams};
+use quickwit_ingest_api::{add_doc, webhook_queue_id, IngestApiError, IngestApiService};
+use quickwit_metastore::{Metastore, MetastoreError};
+use quickwit_proto::ingest_api::{
+    CreateQueueIfNotExistsRequest, DocBatch, IngestRequest, IngestResponse,
+};
+use quickwit_proto::{ServiceError, ServiceErrorCode};
+use serde_json::Value;
+use sha2::Sha256;
+use thiserror::Error;
+use warp::{Filter, Rejection};
+
+use crate::{decode_index_id, require, Format};
+
+const CONTENT_LENGTH_LIMIT: u64 = 10_000_000; // 10M
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Error)]
+pub enum WebhookError {
+    #[error("Webhook source `{source_id}` of index `{index_id}` does not exist.")]
+    SourceDoesNotExist { index_id: String, source_id: String },
+    #[error("Environment variable `{0}` holding the webhook secret is not set.")]
+    MissingSecret(String),
+    #[error("Signature header `{0}` is missing or is not valid.")]
+    MissingSignature(String),
+    #[error("Signature does not match the request body.")]
+    InvalidSignature,
+    #[error("Could not parse events: {0}.")]
+    InvalidEvents(String),
+    #[error("Metastore error: `{0}`.")]
+    Metastore(#[from] MetastoreError),
+    #[error("Ingest API error: `{0}`.")]
+    IngestApi(#[from] AskError<IngestApiError>),
+}
+
+impl ServiceError for WebhookError {
+    fn status_code(&self) -> ServiceErrorCode {
+        match self {
+            WebhookError::SourceDoesNotExist { .. } => ServiceErrorCode::NotFound,
+            WebhookError::MissingSecret(_) => ServiceErrorCode::Internal,
+            WebhookError::MissingSignature(_) => ServiceErrorCode::Unauthorized,
+            WebhookError::InvalidSignature => ServiceErrorCode::Unauthorized,
+            WebhookError::InvalidEvents(_) => ServiceErrorCode::BadRequest,
+            WebhookError::Metastore(error) => error.status_code(),
+            WebhookError::IngestApi(error) => error.status_code(),
+        }
+    }
+}
+
+pub fn webhook_handler(
+    metastore: Arc<dyn Metastore>,
+    ingest_api_mailbox_opt: Option<Mailbox<IngestApiService>>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    webhook_filter()
+        .and(warp::any().map(move || metastore.clone()))
+        .and(require(ingest_api_mailbox_opt))
+        .and_then(webhook_endpoint)
+}
+
+fn webhook_filter(
+) -> impl Filter<Extract = (String, String, HeaderMap, Bytes), Error = Rejection> + Clone {
+    warp::path!(String / "webhooks" / String)
+        .map(|index_id, source_id| (decode_index_id(index_id), source_id))
+        .untuple_one()
+        .and(warp::post())
+        .and(warp::header::headers_cloned())
+        .and(warp::body::content_length_limit(CONTENT_LENGTH_LIMIT))
+        .and(warp::body::bytes())
+}
+
+async fn webhook_endpoint(
+    index_id: String,
+    source_id: String,
+    headers: HeaderMap,
+    body: Bytes,
+    metastore: Arc<dyn Metastore>,
+    ingest_api_mailbox: Mailbox<IngestApiService>,
+) -> Result<impl warp::Reply, Infallible> {
+    let ingest_resp = webhook_ingest(
+        &index_id,
+        &source_id,
+        &headers,
+        &body,
+        &*metastore,
+        &ingest_api_mailbox,
+    )
+    .await;
+    Ok(Format::PrettyJson.make_rest_reply_non_serializable_error(ingest_resp))
+}
+
+/// Verifies the signature of a webhook request and appends its events to the queue of the
+/// webhook source. The events are indexed by the pipeline of the source, which checkpoints its
+/// position in the queue.
+async fn webhook_ingest(
+    index_id: &str,
+    source_id: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+    metastore: &dyn Metastore,
+    ingest_api_mailbox: &Mailbox<IngestApiService>,
+) -> Result<IngestResponse, WebhookError> {
+    let index_metadata = metastore.index_metadata(index_id).await?;
+    let webhook_params = match index_metadata
+        .sources
+        .get(source_id)
+        .map(|source_config| &source_config.source_params)
+    {
+        Some(SourceParams::Webhook(webhook_params)) => webhook_params,
+        _ => {
+            return Err(WebhookError::SourceDoesNotExist {
+                index_id: index_id.to_string(),
+                source_id: source_id.to_string(),
+            })
+        }
+    };
+    verify_signature(webhook_params, headers, body)?;
+
+    let queue_id = webhook_queue_id(index_id, source_id);
+    let mut doc_batch = DocBatch {
+        index_id: queue_id.clone(),
+        ..Default::default()
+    };
+    for event in parse_events(body)? {
+        add_doc(event.to_string().as_bytes(), &mut doc_batch);
+    }
+    let create_queue_req = CreateQueueIfNotExistsRequest { queue_id };
+    ingest_api_mailbox.ask_for_res(create_queue_req).await?;
+    let ingest_req = IngestRequest {
+        doc_batches: vec![doc_batch],
+    };
+    let ingest_resp = ingest_api_mailbox.ask_for_res(ingest_req).await?;
+    Ok(ingest_resp)
+}
+
+/// Checks the HMAC-SHA256 signature of the request body against the hex-encoded signature
+/// carried by the signature header. The signature may be prefixed with `sha256=`. The secret is
+/// read from the environment variable named by the source params.
+fn verify_signature(
+    webhook_params: &WebhookSourceParams,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<(), WebhookError> {
+    let secret = env::var(&webhook_params.secret_env_var)
+        .map_err(|_| WebhookError::MissingSecret(webhook_params.secret_env_var.clone()))?;
+    let missing_signature_error =
+        || WebhookError::MissingSignature(webhook_params.signature_header.clone());
+    let signature_str = headers
+        .get(webhook_params.signature_header.as_str())
+        .and_then(|header_value| header_value.to_str().ok())
+        .ok_or_else(missing_signature_error)?;
+    let signature_hex = signature_str
+        .trim()
+        .strip_prefix("sha256=")
+        .unwrap_or(signature_str);
+    let signature = hex::decode(signature_hex).map_err(|_| missing_signature_error())?;
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC should accept keys of any size.");
+    mac.update(body);
+    // `verify_slice` compares the signatures in constant time.
+    mac.verify_slice(&signature)
+        .map_err(|_| WebhookError::InvalidSignature)
+}
+
+/// Parses the events of a webhook request body. The body may contain a single JSON event, a JSON
+/// array of events, or newline-delimited JSON events.
+fn parse_events(body: &[u8]) -> Result<Vec<Value>, WebhookError> {
+    let mut events = Vec::new();
+    for value_res in serde_json::Deserializer::from_slice(body).into_iter::<Value>() {
+        let value = value_res.map_err(|error| WebhookError::InvalidEvents(error.to_string()))?;
+        match value {
+            Value::Array(array_events) => events.extend(array_events),
+            event => events.push(event),
+        }
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn sign(secret: &str, body: &[u8]) -> String {
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let webhook_params = WebhookSourceParams {
+            secret_env_var: "QW_TEST_VERIFY_SIGNATURE_SECRET".to_string(),
+            signature_header: "x-hub-signature-256".to_string(),
+            batch_num_bytes_limit: None,
+        };
+        let body = br#"{"action": "opened"}"#;
+        {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                "x-hub-signature-256",
+                sign("my-secret", body).parse().unwrap(),
+            );
+            let error = verify_signature(&webhook_params, &headers, body).unwrap_err();
+            assert!(matches!(error, WebhookError::MissingSecret(_)));
+            assert!(matches!(error.status_code(), ServiceErrorCode::Internal));
+        }
+        env::set_var("QW_TEST_VERIFY_SIGNATURE_SECRET", "my-secret");
+        {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                "x-hub-signature-256",
+                sign("my-secret", body).parse().unwrap(),
+            );
+            verify_signature(&webhook_params, &headers, body).unwrap();
+        }
+        {
+            let mut headers = HeaderMap::new();
+            let signature = sign("my-secret", body);
+            headers.insert(
+                "x-hub-signature-256",
+                signature.trim_start_matches("sha256=").parse().unwrap(),
+            );
+            verify_signature(&webhook_params, &headers, body).unwrap();
+        }
+        {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                "x-hub-signature-256",
+                sign("other-secret", body).parse().unwrap(),
+            );
+            let error = verify_signature(&webhook_params, &headers, body).unwrap_err();
+            assert!(matches!(error, WebhookError::InvalidSignature));
+        }
+        {
+            let headers = HeaderMap::new();
+            let error = verify_signature(&webhook_params, &headers, body).unwrap_err();
+            assert!(matches!(error, WebhookError::MissingSignature(_)));
+            assert!(matches!(
+                error.status_code(),
+                ServiceErrorCode::Unauthorized
+            ));
+        }
+    }
+
+    #[test]
+    fn test_parse_events() {
+        assert_eq!(
+            parse_events(br#"{"id": 1}"#).unwrap(),
+            vec![json!({"id": 1})]
+        );
+        assert_eq!(
+            parse_events(br#"[{"id": 1}, {"id": 2}]"#).unwrap(),
+            vec![json!({"id": 1}), json!({"id": 2})]
+        );
+        assert_eq!(
+            parse_events(b"{\"id\": 1}\n{\"id\": 2}\n").unwrap(),
+            vec![json!({"id": 1}), json!({"id": 2})]
+        );
+        assert!(parse_events(b"").unwrap().is_empty());
+        parse_events(b"{\"id\": ").unwrap_err();
+    }
+}
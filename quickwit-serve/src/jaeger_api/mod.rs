@@ -0,0 +1,869 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use futures::stream;
+use quickwit_proto::jaeger::api_v2::query_service_server::QueryService;
+use quickwit_proto::jaeger::api_v2::{
+    FindTracesRequest, GetOperationsRequest, GetOperationsResponse, GetServicesRequest,
+    GetServicesResponse, GetTraceRequest, KeyValue, Log, Operation, Process, Span, SpanRef,
+    SpanRefType, SpansResponseChunk, TraceQueryParameters, ValueType,
+};
+use quickwit_proto::{tonic, SearchRequest, ServiceError, SortOrder};
+use quickwit_search::SearchService;
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use tracing::{debug, warn};
+
+/// Maximum number of spans returned for a trace.
+const MAX_NUM_SPANS_PER_TRACE: u64 = 10_000;
+
+/// Maximum number of spans returned by `FindTraces`, all traces included.
+const MAX_NUM_SPANS: u64 = 100_000;
+
+/// Maximum number of matching spans from which `FindTraces` collects the IDs of the traces to
+/// return.
+const MAX_NUM_MATCHING_SPANS: u64 = 1_000;
+
+/// Number of traces returned by `FindTraces` when the request does not set a search depth.
+const DEFAULT_SEARCH_DEPTH: usize = 20;
+
+/// Maximum number of services or operations returned.
+const MAX_NUM_TERMS: usize = 1_000;
+
+/// Names of the OTLP span kinds, indexed by their value.
+const SPAN_KINDS: [&str; 6] = ["", "internal", "server", "client", "producer", "consumer"];
+
+/// OTLP status code of the spans that failed.
+const ERROR_STATUS_CODE: u64 = 2;
+
+type SpansResponseStream =
+    stream::Iter<std::vec::IntoIter<Result<SpansResponseChunk, tonic::Status>>>;
+
+/// Jaeger query gRPC service, answering the queries of the Jaeger UI and of the Grafana Jaeger
+/// datasource with the spans of an index laid out as expected by the `otel-traces` index
+/// template.
+#[derive(Clone)]
+pub(crate) struct JaegerService {
+    index_id: String,
+    search_service: Arc<dyn SearchService>,
+}
+
+impl JaegerService {
+    pub fn new(index_id: String, search_service: Arc<dyn SearchService>) -> Self {
+        Self {
+            index_id,
+            search_service,
+        }
+    }
+
+    /// Returns the spans matching the query, most recent first.
+    async fn search_spans(
+        &self,
+        query: String,
+        time_range: (Option<i64>, Option<i64>),
+        max_hits: u64,
+    ) -> Result<Vec<JsonMap<String, JsonValue>>, tonic::Status> {
+        let search_request = SearchRequest {
+            index_id: self.index_id.clone(),
+            query,
+            start_timestamp: time_range.0,
+            end_timestamp: time_range.1,
+            max_hits,
+            sort_by_field: Some("span_start_timestamp_secs".to_string()),
+            sort_order: Some(SortOrder::Desc as i32),
+            ..Default::default()
+        };
+        let search_response = self
+            .search_service
+            .root_search(search_request)
+            .await
+            .map_err(|error| error.grpc_error())?;
+        search_response
+            .hits
+            .into_iter()
+            .map(|hit| {
+                serde_json::from_str(&hit.json).map_err(|error| {
+                    tonic::Status::internal(format!("Failed to deserialize span: {error}"))
+                })
+            })
+            .collect()
+    }
+
+    /// Runs a terms aggregation on the spans matching the query and returns the aggregation
+    /// result.
+    async fn aggregate_spans(
+        &self,
+        query: String,
+        aggregation: JsonValue,
+    ) -> Result<JsonValue, tonic::Status> {
+        let search_request = SearchRequest {
+            index_id: self.index_id.clone(),
+            query,
+            max_hits: 0,
+            aggregation_request: Some(aggregation.to_string()),
+            ..Default::default()
+        };
+        let search_response = self
+            .search_service
+            .root_search(search_request)
+            .await
+            .map_err(|error| error.grpc_error())?;
+        let aggregation_result = search_response
+            .aggregation
+            .map(|aggregation_json| serde_json::from_str(&aggregation_json))
+            .transpose()
+            .map_err(|error| {
+                tonic::Status::internal(format!("Failed to deserialize aggregation: {error}"))
+            })?
+            .unwrap_or(JsonValue::Null);
+        Ok(aggregation_result)
+    }
+}
+
+#[async_trait]
+impl QueryService for JaegerService {
+    type GetTraceStream = SpansResponseStream;
+    type FindTracesStream = SpansResponseStream;
+
+    async fn get_trace(
+        &self,
+        request: tonic::Request<GetTraceRequest>,
+    ) -> Result<tonic::Response<Self::GetTraceStream>, tonic::Status> {
+        let trace_id = hex::encode(request.into_inner().trace_id);
+        debug!(index_id = %self.index_id, trace_id = %trace_id, "jaeger-get-trace");
+        let docs = self
+            .search_spans(
+                format!("trace_id:{trace_id}"),
+                (None, None),
+                MAX_NUM_SPANS_PER_TRACE,
+            )
+            .await?;
+        if docs.is_empty() {
+            return Err(tonic::Status::not_found(format!(
+                "Trace `{trace_id}` not found."
+            )));
+        }
+        let chunk = SpansResponseChunk {
+            spans: docs_to_spans(docs),
+        };
+        Ok(tonic::Response::new(stream::iter(vec![Ok(chunk)])))
+    }
+
+    async fn find_traces(
+        &self,
+        request: tonic::Request<FindTracesRequest>,
+    ) -> Result<tonic::Response<Self::FindTracesStream>, tonic::Status> {
+        let trace_query = request
+            .into_inner()
+            .query
+            .ok_or_else(|| tonic::Status::invalid_argument("Trace query is missing."))?;
+        debug!(index_id = %self.index_id, trace_query = ?trace_query, "jaeger-find-traces");
+        let search_depth = if trace_query.search_depth > 0 {
+            trace_query.search_depth as usize
+        } else {
+            DEFAULT_SEARCH_DEPTH
+        };
+        let matching_docs = self
+            .search_spans(
+                build_find_traces_query(&trace_query),
+                find_traces_time_range(&trace_query),
+                MAX_NUM_MATCHING_SPANS,
+            )
+            .await?;
+        let mut trace_ids: Vec<String> = Vec::new();
+        for doc in &matching_docs {
+            if trace_ids.len() == search_depth {
+                break;
+            }
+            if let Some(trace_id) = doc.get("trace_id").and_then(JsonValue::as_str) {
+                if !trace_ids
+                    .iter()
+                    .any(|other_trace_id| other_trace_id == trace_id)
+                {
+                    trace_ids.push(trace_id.to_string());
+                }
+            }
+        }
+        if trace_ids.is_empty() {
+            return Ok(tonic::Response::new(stream::iter(Vec::new())));
+        }
+        // The traces are fetched as a whole, including the spans that do not match the query.
+        let query = trace_ids
+            .iter()
+            .map(|trace_id| format!("trace_id:{trace_id}"))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        let docs = self
+            .search_spans(query, (None, None), MAX_NUM_SPANS)
+            .await?;
+        let mut spans_per_trace: BTreeMap<String, Vec<JsonMap<String, JsonValue>>> =
+            BTreeMap::new();
+        for doc in docs {
+            if let Some(trace_id) = doc.get("trace_id").and_then(JsonValue::as_str) {
+                spans_per_trace
+                    .entry(trace_id.to_string())
+                    .or_default()
+                    .push(doc);
+            }
+        }
+        // One chunk per trace, in the order in which the traces matched.
+        let chunks: Vec<Result<SpansResponseChunk, tonic::Status>> = trace_ids
+            .iter()
+            .filter_map(|trace_id| spans_per_trace.remove(trace_id))
+            .map(|docs| {
+                Ok(SpansResponseChunk {
+                    spans: docs_to_spans(docs),
+                })
+            })
+            .collect();
+        Ok(tonic::Response::new(stream::iter(chunks)))
+    }
+
+    async fn get_services(
+        &self,
+        _request: tonic::Request<GetServicesRequest>,
+    ) -> Result<tonic::Response<GetServicesResponse>, tonic::Status> {
+        debug!(index_id = %self.index_id, "jaeger-get-services");
+        let aggregation = json!({
+            "services": {
+                "terms": {"field": "service_name", "size": MAX_NUM_TERMS}
+            }
+        });
+        let aggregation_result = self.aggregate_spans("*".to_string(), aggregation).await?;
+        let services: BTreeSet<String> = bucket_keys(&aggregation_result["services"])
+            .filter_map(|key| key.as_str().map(str::to_string))
+            .collect();
+        Ok(tonic::Response::new(GetServicesResponse {
+            services: services.into_iter().collect(),
+        }))
+    }
+
+    async fn get_operations(
+        &self,
+        request: tonic::Request<GetOperationsRequest>,
+    ) -> Result<tonic::Response<GetOperationsResponse>, tonic::Status> {
+        let request = request.into_inner();
+        debug!(index_id = %self.index_id, service = %request.service, "jaeger-get-operations");
+        let mut query = format!("service_name:{}", query_value(&request.service));
+        if !request.span_kind.is_empty() {
+            let span_kind = SPAN_KINDS
+                .iter()
+                .position(|span_kind| *span_kind == request.span_kind)
+                .ok_or_else(|| {
+                    tonic::Status::invalid_argument(format!(
+                        "Unknown span kind `{}`.",
+                        request.span_kind
+                    ))
+                })?;
+            query.push_str(&format!(" AND span_kind:{span_kind}"));
+        }
+        let aggregation = json!({
+            "operations": {
+                "terms": {"field": "span_name", "size": MAX_NUM_TERMS},
+                "aggs": {
+                    "span_kinds": {
+                        "terms": {"field": "span_kind"}
+                    }
+                }
+            }
+        });
+        let aggregation_result = self.aggregate_spans(query, aggregation).await?;
+        let mut operations: BTreeSet<(String, String)> = BTreeSet::new();
+        for bucket in buckets(&aggregation_result["operations"]) {
+            let operation_name = match bucket["key"].as_str() {
+                Some(operation_name) => operation_name,
+                None => continue,
+            };
+            let mut span_kinds: Vec<&str> = bucket_keys(&bucket["span_kinds"])
+                .map(|span_kind_key| {
+                    span_kind_key
+                        .as_f64()
+                        .and_then(|span_kind| SPAN_KINDS.get(span_kind as usize))
+                        .copied()
+                        .unwrap_or_default()
+                })
+                .collect();
+            // The spans of the operation may not have a kind.
+            if span_kinds.is_empty() {
+                span_kinds.push("");
+            }
+            for span_kind in span_kinds {
+                operations.insert((operation_name.to_string(), span_kind.to_string()));
+            }
+        }
+        let operation_names: BTreeSet<String> = operations
+            .iter()
+            .map(|(operation_name, _)| operation_name.clone())
+            .collect();
+        Ok(tonic::Response::new(GetOperationsResponse {
+            operation_names: operation_names.into_iter().collect(),
+            operations: operations
+                .into_iter()
+                .map(|(name, span_kind)| Operation { name, span_kind })
+                .collect(),
+        }))
+    }
+}
+
+fn buckets(aggregation_result: &JsonValue) -> impl Iterator<Item = &JsonValue> {
+    aggregation_result["buckets"]
+        .as_array()
+        .into_iter()
+        .flat_map(|buckets| buckets.iter())
+}
+
+fn bucket_keys(aggregation_result: &JsonValue) -> impl Iterator<Item = &JsonValue> {
+    buckets(aggregation_result).map(|bucket| &bucket["key"])
+}
+
+/// Formats a value for the query language, quoting it unless it is made of alphanumeric
+/// characters, dots, and underscores only. The query language does not allow escaping double
+/// quotes, so they are removed.
+fn query_value(value: &str) -> String {
+    if !value.is_empty()
+        && value
+            .chars()
+            .all(|character| character.is_alphanumeric() || character == '.' || character == '_')
+    {
+        return value.to_string();
+    }
+    format!("\"{}\"", value.replace('"', ""))
+}
+
+/// Builds the query matching the spans of the traces looked for by a `FindTraces` request. The
+/// `error=true` tag matches the spans that failed. The other tags match the span attributes.
+fn build_find_traces_query(trace_query: &TraceQueryParameters) -> String {
+    let mut clauses = Vec::new();
+    if !trace_query.service_name.is_empty() {
+        clauses.push(format!(
+            "service_name:{}",
+            query_value(&trace_query.service_name)
+        ));
+    }
+    if !trace_query.operation_name.is_empty() {
+        clauses.push(format!(
+            "span_name:{}",
+            query_value(&trace_query.operation_name)
+        ));
+    }
+    let tags: BTreeMap<&String, &String> = trace_query.tags.iter().collect();
+    for (key, value) in tags {
+        if key == "error" && value == "true" {
+            clauses.push(format!("span_status.code:{ERROR_STATUS_CODE}"));
+            continue;
+        }
+        clauses.push(format!(
+            "span_attributes.{}:{}",
+            key.replace('.', "\\."),
+            query_value(value)
+        ));
+    }
+    let duration_min_millis = trace_query.duration_min.as_ref().map(duration_millis);
+    let duration_max_millis = trace_query.duration_max.as_ref().map(duration_millis);
+    if duration_min_millis.is_some() || duration_max_millis.is_some() {
+        let format_bound = |bound_opt: Option<i64>| {
+            bound_opt
+                .map(|bound| bound.to_string())
+                .unwrap_or_else(|| "*".to_string())
+        };
+        clauses.push(format!(
+            "span_duration_millis:[{} TO {}]",
+            format_bound(duration_min_millis),
+            format_bound(duration_max_millis)
+        ));
+    }
+    if clauses.is_empty() {
+        return "*".to_string();
+    }
+    clauses.join(" AND ")
+}
+
+/// Returns the time range of a `FindTraces` request, in seconds. The end of the range is
+/// exclusive.
+fn find_traces_time_range(trace_query: &TraceQueryParameters) -> (Option<i64>, Option<i64>) {
+    let start_timestamp = trace_query
+        .start_time_min
+        .as_ref()
+        .map(|timestamp| timestamp.seconds);
+    let end_timestamp = trace_query
+        .start_time_max
+        .as_ref()
+        .map(|timestamp| timestamp.seconds + 1);
+    (start_timestamp, end_timestamp)
+}
+
+fn duration_millis(duration: &prost_types::Duration) -> i64 {
+    duration.seconds * 1_000 + duration.nanos as i64 / 1_000_000
+}
+
+fn docs_to_spans(docs: Vec<JsonMap<String, JsonValue>>) -> Vec<Span> {
+    docs.into_iter()
+        .filter_map(|doc| match doc_to_span(doc) {
+            Ok(span) => Some(span),
+            Err(error) => {
+                warn!(error = ?error, "Failed to convert document into Jaeger span.");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Converts a document of the `otel-traces` index template into a Jaeger span, following the
+/// OpenTelemetry to Jaeger translation conventions.
+fn doc_to_span(mut doc: JsonMap<String, JsonValue>) -> anyhow::Result<Span> {
+    let trace_id = decode_hex_field(&doc, "trace_id")?.context("Span has no trace ID.")?;
+    let span_id = decode_hex_field(&doc, "span_id")?.context("Span has no span ID.")?;
+    let mut references = Vec::new();
+    if let Some(parent_span_id) = decode_hex_field(&doc, "parent_span_id")? {
+        references.push(SpanRef {
+            trace_id: trace_id.clone(),
+            span_id: parent_span_id,
+            ref_type: SpanRefType::ChildOf as i32,
+        });
+    }
+    if let Some(JsonValue::Array(links)) = doc.remove("links") {
+        for link in links {
+            let link = match link {
+                JsonValue::Object(link) => link,
+                _ => continue,
+            };
+            if let (Some(link_trace_id), Some(link_span_id)) = (
+                decode_hex_field(&link, "trace_id")?,
+                decode_hex_field(&link, "span_id")?,
+            ) {
+                references.push(SpanRef {
+                    trace_id: link_trace_id,
+                    span_id: link_span_id,
+                    ref_type: SpanRefType::FollowsFrom as i32,
+                });
+            }
+        }
+    }
+    let start_timestamp_nanos = doc
+        .get("span_start_timestamp_nanos")
+        .and_then(JsonValue::as_u64)
+        .unwrap_or_default();
+    let end_timestamp_nanos = doc
+        .get("span_end_timestamp_nanos")
+        .and_then(JsonValue::as_u64)
+        .unwrap_or(start_timestamp_nanos);
+    let duration_nanos = end_timestamp_nanos.saturating_sub(start_timestamp_nanos);
+
+    let mut tags = Vec::new();
+    if let Some(JsonValue::Object(span_attributes)) = doc.remove("span_attributes") {
+        tags.extend(
+            span_attributes
+                .into_iter()
+                .map(|(key, value)| json_to_key_value(key, value)),
+        );
+    }
+    let span_kind = doc
+        .get("span_kind")
+        .and_then(JsonValue::as_u64)
+        .and_then(|span_kind| SPAN_KINDS.get(span_kind as usize))
+        .copied()
+        .unwrap_or_default();
+    if !span_kind.is_empty() {
+        tags.push(json_to_key_value("span.kind".to_string(), json!(span_kind)));
+    }
+    if let Some(span_status) = doc.get("span_status") {
+        let status_code = span_status["code"].as_u64().unwrap_or_default();
+        if status_code == ERROR_STATUS_CODE {
+            tags.push(json_to_key_value("error".to_string(), json!(true)));
+            tags.push(json_to_key_value(
+                "otel.status_code".to_string(),
+                json!("ERROR"),
+            ));
+        } else if status_code == 1 {
+            tags.push(json_to_key_value(
+                "otel.status_code".to_string(),
+                json!("OK"),
+            ));
+        }
+        if let Some(status_message) = span_status["message"].as_str() {
+            tags.push(json_to_key_value(
+                "otel.status_description".to_string(),
+                json!(status_message),
+            ));
+        }
+    }
+    for (field_name, tag_key) in [
+        ("scope_name", "otel.scope.name"),
+        ("scope_version", "otel.scope.version"),
+    ] {
+        if let Some(value) = doc.remove(field_name) {
+            tags.push(json_to_key_value(tag_key.to_string(), value));
+        }
+    }
+    let mut logs = Vec::new();
+    if let Some(JsonValue::Array(events)) = doc.remove("events") {
+        for event in events {
+            let mut event = match event {
+                JsonValue::Object(event) => event,
+                _ => continue,
+            };
+            let timestamp_nanos = event
+                .get("timestamp_nanos")
+                .and_then(JsonValue::as_u64)
+                .unwrap_or(start_timestamp_nanos);
+            let mut fields = Vec::new();
+            if let Some(name) = event.remove("name") {
+                fields.push(json_to_key_value("event".to_string(), name));
+            }
+            if let Some(JsonValue::Object(attributes)) = event.remove("attributes") {
+                fields.extend(
+                    attributes
+                        .into_iter()
+                        .map(|(key, value)| json_to_key_value(key, value)),
+                );
+            }
+            logs.push(Log {
+                timestamp: Some(to_timestamp(timestamp_nanos)),
+                fields,
+            });
+        }
+    }
+    let service_name = match doc.remove("service_name") {
+        Some(JsonValue::String(service_name)) => service_name,
+        _ => String::new(),
+    };
+    let mut process_tags = Vec::new();
+    if let Some(JsonValue::Object(resource_attributes)) = doc.remove("resource_attributes") {
+        process_tags.extend(
+            resource_attributes
+                .into_iter()
+                .filter(|(key, _)| key != "service.name")
+                .map(|(key, value)| json_to_key_value(key, value)),
+        );
+    }
+    let operation_name = match doc.remove("span_name") {
+        Some(JsonValue::String(span_name)) => span_name,
+        _ => String::new(),
+    };
+    Ok(Span {
+        trace_id,
+        span_id,
+        operation_name,
+        references,
+        start_time: Some(to_timestamp(start_timestamp_nanos)),
+        duration: Some(prost_types::Duration {
+            seconds: (duration_nanos / 1_000_000_000) as i64,
+            nanos: (duration_nanos % 1_000_000_000) as i32,
+        }),
+        tags,
+        logs,
+        process: Some(Process {
+            service_name,
+            tags: process_tags,
+        }),
+        ..Default::default()
+    })
+}
+
+fn decode_hex_field(
+    json_map: &JsonMap<String, JsonValue>,
+    field_name: &str,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    match json_map.get(field_name).and_then(JsonValue::as_str) {
+        Some(hex_value) if !hex_value.is_empty() => {
+            let value = hex::decode(hex_value)
+                .with_context(|| format!("Field `{field_name}` is not a valid hex string."))?;
+            Ok(Some(value))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn to_timestamp(timestamp_nanos: u64) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: (timestamp_nanos / 1_000_000_000) as i64,
+        nanos: (timestamp_nanos % 1_000_000_000) as i32,
+    }
+}
+
+/// Converts a JSON value into a Jaeger tag. Arrays and objects are serialized into JSON strings.
+fn json_to_key_value(key: String, value: JsonValue) -> KeyValue {
+    let mut key_value = KeyValue {
+        key,
+        ..Default::default()
+    };
+    match value {
+        JsonValue::String(value) => {
+            key_value.v_type = ValueType::String as i32;
+            key_value.v_str = value;
+        }
+        JsonValue::Bool(value) => {
+            key_value.v_type = ValueType::Bool as i32;
+            key_value.v_bool = value;
+        }
+        JsonValue::Number(value) => {
+            if let Some(value) = value.as_i64() {
+                key_value.v_type = ValueType::Int64 as i32;
+                key_value.v_int64 = value;
+            } else {
+                key_value.v_type = ValueType::Float64 as i32;
+                key_value.v_float64 = value.as_f64().unwrap_or_default();
+            }
+        }
+        value => {
+            key_value.v_type = ValueType::String as i32;
+            key_value.v_str = value.to_string();
+        }
+    }
+    key_value
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use futures::StreamExt;
+    use quickwit_proto::{Hit, SearchResponse};
+    use quickwit_search::MockSearchService;
+
+    use super::*;
+
+    fn span_doc() -> JsonValue {
+        json!({
+            "service_name": "api",
+            "resource_attributes": {"service.name": "api", "host.name": "node-1"},
+            "trace_id": "01010101010101010101010101010101",
+            "span_id": "0202020202020202",
+            "parent_span_id": "0303030303030303",
+            "span_kind": 2,
+            "span_name": "GET /users",
+            "span_start_timestamp_secs": 1_660_000_000u64,
+            "span_start_timestamp_nanos": 1_660_000_000_000_000_000u64,
+            "span_end_timestamp_nanos": 1_660_000_000_250_000_000u64,
+            "span_duration_millis": 250,
+            "span_attributes": {"http.method": "GET", "http.status_code": 500},
+            "span_status": {"code": 2, "message": "Internal error"},
+            "events": [{"name": "cache-miss", "timestamp_nanos": 1_660_000_000_100_000_000u64}],
+            "event_names": ["cache-miss"],
+            "links": [{
+                "trace_id": "04040404040404040404040404040404",
+                "span_id": "0505050505050505",
+            }],
+        })
+    }
+
+    fn string_tag(key: &str, value: &str) -> KeyValue {
+        json_to_key_value(key.to_string(), json!(value))
+    }
+
+    #[test]
+    fn test_doc_to_span() {
+        let doc = match span_doc() {
+            JsonValue::Object(doc) => doc,
+            _ => unreachable!(),
+        };
+        let span = doc_to_span(doc).unwrap();
+        assert_eq!(span.trace_id, vec![1; 16]);
+        assert_eq!(span.span_id, vec![2; 8]);
+        assert_eq!(span.operation_name, "GET /users");
+        assert_eq!(
+            span.references,
+            vec![
+                SpanRef {
+                    trace_id: vec![1; 16],
+                    span_id: vec![3; 8],
+                    ref_type: SpanRefType::ChildOf as i32,
+                },
+                SpanRef {
+                    trace_id: vec![4; 16],
+                    span_id: vec![5; 8],
+                    ref_type: SpanRefType::FollowsFrom as i32,
+                },
+            ]
+        );
+        assert_eq!(
+            span.start_time,
+            Some(prost_types::Timestamp {
+                seconds: 1_660_000_000,
+                nanos: 0,
+            })
+        );
+        assert_eq!(
+            span.duration,
+            Some(prost_types::Duration {
+                seconds: 0,
+                nanos: 250_000_000,
+            })
+        );
+        assert_eq!(
+            span.tags,
+            vec![
+                string_tag("http.method", "GET"),
+                json_to_key_value("http.status_code".to_string(), json!(500)),
+                string_tag("span.kind", "server"),
+                json_to_key_value("error".to_string(), json!(true)),
+                string_tag("otel.status_code", "ERROR"),
+                string_tag("otel.status_description", "Internal error"),
+            ]
+        );
+        assert_eq!(
+            span.logs,
+            vec![Log {
+                timestamp: Some(prost_types::Timestamp {
+                    seconds: 1_660_000_000,
+                    nanos: 100_000_000,
+                }),
+                fields: vec![string_tag("event", "cache-miss")],
+            }]
+        );
+        assert_eq!(
+            span.process,
+            Some(Process {
+                service_name: "api".to_string(),
+                tags: vec![string_tag("host.name", "node-1")],
+            })
+        );
+    }
+
+    #[test]
+    fn test_build_find_traces_query() {
+        assert_eq!(
+            build_find_traces_query(&TraceQueryParameters::default()),
+            "*"
+        );
+        let trace_query = TraceQueryParameters {
+            service_name: "api".to_string(),
+            operation_name: "GET /users".to_string(),
+            tags: HashMap::from([
+                ("error".to_string(), "true".to_string()),
+                ("http.method".to_string(), "GET".to_string()),
+            ]),
+            duration_min: Some(prost_types::Duration {
+                seconds: 1,
+                nanos: 500_000_000,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            build_find_traces_query(&trace_query),
+            "service_name:api AND span_name:\"GET /users\" AND span_status.code:2 AND \
+             span_attributes.http\\.method:GET AND span_duration_millis:[1500 TO *]"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_jaeger_service_find_traces() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .times(2)
+            .returning(|search_request| {
+                assert_eq!(search_request.index_id, "otel-traces");
+                let hit = Hit {
+                    json: span_doc().to_string(),
+                    ..Default::default()
+                };
+                if search_request.query == "service_name:api" {
+                    assert_eq!(search_request.start_timestamp, Some(1_660_000_000));
+                    assert_eq!(search_request.end_timestamp, Some(1_660_000_061));
+                } else {
+                    assert_eq!(
+                        search_request.query,
+                        "trace_id:01010101010101010101010101010101"
+                    );
+                }
+                Ok(SearchResponse {
+                    num_hits: 1,
+                    hits: vec![hit],
+                    ..Default::default()
+                })
+            });
+        let jaeger_service =
+            JaegerService::new("otel-traces".to_string(), Arc::new(mock_search_service));
+        let find_traces_request = FindTracesRequest {
+            query: Some(TraceQueryParameters {
+                service_name: "api".to_string(),
+                start_time_min: Some(prost_types::Timestamp {
+                    seconds: 1_660_000_000,
+                    nanos: 0,
+                }),
+                start_time_max: Some(prost_types::Timestamp {
+                    seconds: 1_660_000_060,
+                    nanos: 0,
+                }),
+                ..Default::default()
+            }),
+        };
+        let chunks: Vec<SpansResponseChunk> = jaeger_service
+            .find_traces(tonic::Request::new(find_traces_request))
+            .await
+            .unwrap()
+            .into_inner()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].spans.len(), 1);
+        assert_eq!(chunks[0].spans[0].operation_name, "GET /users");
+    }
+
+    #[tokio::test]
+    async fn test_jaeger_service_get_operations() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .times(1)
+            .returning(|search_request| {
+                assert_eq!(search_request.query, "service_name:api AND span_kind:2");
+                let aggregation = json!({
+                    "operations": {
+                        "buckets": [
+                            {
+                                "key": "GET /users",
+                                "doc_count": 3,
+                                "span_kinds": {"buckets": [{"key": 2.0, "doc_count": 3}]}
+                            },
+                        ]
+                    }
+                });
+                Ok(SearchResponse {
+                    aggregation: Some(aggregation.to_string()),
+                    ..Default::default()
+                })
+            });
+        let jaeger_service =
+            JaegerService::new("otel-traces".to_string(), Arc::new(mock_search_service));
+        let get_operations_request = GetOperationsRequest {
+            service: "api".to_string(),
+            span_kind: "server".to_string(),
+        };
+        let get_operations_response = jaeger_service
+            .get_operations(tonic::Request::new(get_operations_request))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(get_operations_response.operation_names, ["GET /users"]);
+        assert_eq!(
+            get_operations_response.operations,
+            [Operation {
+                name: "GET /users".to_string(),
+                span_kind: "server".to_string(),
+            }]
+        );
+    }
+}
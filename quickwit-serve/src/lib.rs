@@ -25,11 +25,14 @@ mod grpc;
 mod rest;
 
 mod cluster_api;
+mod elastic_search_api;
 mod health_check_api;
 mod index_api;
 mod indexing_api;
 mod ingest_api;
+mod jaeger_api;
 mod node_info_handler;
+mod otlp_api;
 mod search_api;
 #[cfg(test)]
 mod test_utils;
@@ -44,11 +47,13 @@ use std::time::Duration;
 
 use anyhow::anyhow;
 use format::Format;
+use percent_encoding::percent_decode_str;
 use quickwit_actors::{Mailbox, Universe};
 use quickwit_cluster::{Cluster, ClusterMember, QuickwitService};
 use quickwit_common::uri::Uri;
 use quickwit_config::QuickwitConfig;
 use quickwit_core::IndexService;
+use quickwit_directories::LocalSplitRegistry;
 use quickwit_indexing::actors::IndexingService;
 use quickwit_indexing::start_indexing_service;
 use quickwit_ingest_api::{start_ingest_api_service, IngestApiService};
@@ -63,6 +68,7 @@ use warp::{Filter, Rejection};
 
 pub use crate::args::ServeArgs;
 pub use crate::metrics::SERVE_METRICS;
+use crate::otlp_api::create_otlp_traces_index_if_not_exists;
 #[cfg(test)]
 use crate::rest::recover_fn;
 
@@ -146,8 +152,32 @@ pub async fn serve_quickwit(
 
     let universe = Universe::new();
 
+    // Always instantiate index management service.
+    let index_service = Arc::new(
+        IndexService::new(
+            metastore.clone(),
+            storage_resolver.clone(),
+            config.default_index_root_uri.clone(),
+        )
+        .with_namespace_configs(config.namespaces.clone()),
+    );
+
+    // Splits packaged by the indexer of the node, searchable by its searcher before they are
+    // uploaded, for the indexes enabling `search_before_upload`.
+    let local_split_registry = LocalSplitRegistry::default();
+
     let (ingest_api_service, indexer_service) = if services.contains(&QuickwitService::Indexer) {
-        let ingest_api_service = start_ingest_api_service(&universe, &config.data_dir_path).await?;
+        // The indexing service spawns the pipelines of the existing indexes on startup, so the
+        // OTLP traces index must be created beforehand.
+        if let Some(otlp_traces_index_id) = &config.indexer_config.otlp_traces_index_id {
+            create_otlp_traces_index_if_not_exists(&index_service, otlp_traces_index_id).await?;
+        }
+        let ingest_api_service = start_ingest_api_service(
+            &universe,
+            &config.data_dir_path,
+            config.indexer_config.sync_ingest_writes,
+        )
+        .await?;
         // TODO: Move to indexer config?
         let enable_ingest_api = true;
         let indexing_service = start_indexing_service(
@@ -156,8 +186,14 @@ pub async fn serve_quickwit(
             metastore.clone(),
             storage_resolver.clone(),
             enable_ingest_api,
+            local_split_registry.clone(),
         )
         .await?;
+        tokio::spawn(indexing_api::relocation_handoff_task(
+            cluster.clone(),
+            config.node_id.clone(),
+            indexing_service.clone(),
+        ));
         (Some(ingest_api_service), Some(indexing_service))
     } else {
         (None, None)
@@ -179,18 +215,12 @@ pub async fn serve_quickwit(
     let search_service: Arc<dyn SearchService> = start_searcher_service(
         &config,
         metastore.clone(),
-        storage_resolver.clone(),
+        storage_resolver,
         cluster.clone(),
+        local_split_registry,
     )
     .await?;
 
-    // Always instantiate index management service.
-    let index_service = Arc::new(IndexService::new(
-        metastore.clone(),
-        storage_resolver,
-        config.default_index_root_uri.clone(),
-    ));
-
     let grpc_listen_addr = config.grpc_listen_addr;
     let rest_listen_addr = config.rest_listen_addr;
 
@@ -231,6 +261,15 @@ fn with_arg<T: Clone + Send>(arg: T) -> impl Filter<Extract = (T,), Error = Infa
     warp::any().map(move || arg.clone())
 }
 
+/// Decodes an index ID extracted from a path segment, where the `/` separating the namespaces of
+/// the index from its name are percent-encoded: `team-a%2Flogs` for `team-a/logs`.
+fn decode_index_id(index_id: String) -> String {
+    match percent_decode_str(&index_id).decode_utf8() {
+        Ok(decoded_index_id) => decoded_index_id.into_owned(),
+        Err(_) => index_id,
+    }
+}
+
 /// Reports node readyness to chitchat cluster every 10 seconds (25 ms for tests).
 async fn node_readyness_reporting_task(cluster: Arc<Cluster>, metastore: Arc<dyn Metastore>) {
     let mut interval = tokio::time::interval(READYNESS_REPORTING_INTERVAL);
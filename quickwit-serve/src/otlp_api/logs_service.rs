@@ -0,0 +1,222 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use async_trait::async_trait;
+use opentelemetry_proto::tonic::collector::logs::v1::logs_service_server::LogsService;
+use opentelemetry_proto::tonic::collector::logs::v1::{
+    ExportLogsServiceRequest, ExportLogsServiceResponse,
+};
+use opentelemetry_proto::tonic::logs::v1::LogRecord;
+use quickwit_actors::Mailbox;
+use quickwit_ingest_api::IngestApiService;
+use quickwit_proto::tonic;
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use tracing::debug;
+
+use super::{any_value_to_json, ingest_docs, insert_attributes, insert_non_empty, insert_non_zero};
+
+/// OTLP logs gRPC service converting the log records it receives into JSON documents appended to
+/// the ingest API queue of an index.
+#[derive(Clone)]
+pub(crate) struct OtlpLogsService {
+    index_id: String,
+    ingest_api_mailbox: Mailbox<IngestApiService>,
+}
+
+impl OtlpLogsService {
+    pub fn new(index_id: String, ingest_api_mailbox: Mailbox<IngestApiService>) -> Self {
+        Self {
+            index_id,
+            ingest_api_mailbox,
+        }
+    }
+}
+
+#[async_trait]
+impl LogsService for OtlpLogsService {
+    async fn export(
+        &self,
+        request: tonic::Request<ExportLogsServiceRequest>,
+    ) -> Result<tonic::Response<ExportLogsServiceResponse>, tonic::Status> {
+        let docs = export_request_to_docs(request.into_inner());
+        debug!(index_id = %self.index_id, num_docs = docs.len(), "otlp-export-logs");
+        ingest_docs(&self.index_id, &self.ingest_api_mailbox, docs).await?;
+        Ok(tonic::Response::new(ExportLogsServiceResponse::default()))
+    }
+}
+
+/// Converts the log records of an export request into JSON documents. The resource and the
+/// instrumentation scope that emitted a log record are copied into its document under the
+/// `resource` and `scope` objects.
+fn export_request_to_docs(request: ExportLogsServiceRequest) -> Vec<JsonValue> {
+    let mut docs = Vec::new();
+    for resource_logs in request.resource_logs {
+        let mut resource = JsonMap::new();
+        if let Some(otlp_resource) = resource_logs.resource {
+            let mut resource_attributes = JsonMap::new();
+            insert_attributes(&mut resource_attributes, otlp_resource.attributes);
+            if !resource_attributes.is_empty() {
+                resource.insert(
+                    "attributes".to_string(),
+                    JsonValue::Object(resource_attributes),
+                );
+            }
+            insert_non_zero(
+                &mut resource,
+                "dropped_attributes_count",
+                otlp_resource.dropped_attributes_count as u64,
+            );
+        }
+        for scope_logs in resource_logs.scope_logs {
+            let mut scope = JsonMap::new();
+            if let Some(otlp_scope) = scope_logs.scope {
+                insert_non_empty(&mut scope, "name", otlp_scope.name);
+                insert_non_empty(&mut scope, "version", otlp_scope.version);
+            }
+            for log_record in scope_logs.log_records {
+                let mut doc = log_record_to_doc(log_record);
+                if !resource.is_empty() {
+                    doc.insert("resource".to_string(), JsonValue::Object(resource.clone()));
+                }
+                if !scope.is_empty() {
+                    doc.insert("scope".to_string(), JsonValue::Object(scope.clone()));
+                }
+                docs.push(JsonValue::Object(doc));
+            }
+        }
+    }
+    docs
+}
+
+/// Converts a log record into a JSON document. The timestamp of the log record, or its observed
+/// timestamp when it is not set, is also recorded in seconds in the `timestamp_secs` field, so
+/// that it can be used as the timestamp field of the index.
+fn log_record_to_doc(log_record: LogRecord) -> JsonMap<String, JsonValue> {
+    let mut doc = JsonMap::new();
+    let timestamp_nanos = if log_record.time_unix_nano != 0 {
+        log_record.time_unix_nano
+    } else {
+        log_record.observed_time_unix_nano
+    };
+    if timestamp_nanos != 0 {
+        doc.insert(
+            "timestamp_secs".to_string(),
+            JsonValue::from(timestamp_nanos / 1_000_000_000),
+        );
+    }
+    insert_non_zero(&mut doc, "timestamp_nanos", log_record.time_unix_nano);
+    insert_non_zero(
+        &mut doc,
+        "observed_timestamp_nanos",
+        log_record.observed_time_unix_nano,
+    );
+    insert_non_zero(
+        &mut doc,
+        "severity_number",
+        log_record.severity_number as u64,
+    );
+    insert_non_empty(&mut doc, "severity_text", log_record.severity_text);
+    if let Some(body) = log_record.body {
+        doc.insert("body".to_string(), any_value_to_json(body));
+    }
+    let mut attributes = JsonMap::new();
+    insert_attributes(&mut attributes, log_record.attributes);
+    if !attributes.is_empty() {
+        doc.insert("attributes".to_string(), JsonValue::Object(attributes));
+    }
+    insert_non_zero(
+        &mut doc,
+        "dropped_attributes_count",
+        log_record.dropped_attributes_count as u64,
+    );
+    insert_non_empty(&mut doc, "trace_id", hex::encode(log_record.trace_id));
+    insert_non_empty(&mut doc, "span_id", hex::encode(log_record.span_id));
+    insert_non_zero(&mut doc, "trace_flags", log_record.flags as u64);
+    doc
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry_proto::tonic::logs::v1::{ResourceLogs, ScopeLogs};
+    use opentelemetry_proto::tonic::resource::v1::Resource;
+    use serde_json::json;
+
+    use super::*;
+    use crate::otlp_api::tests::{key_value, string_value};
+
+    #[test]
+    fn test_export_request_to_docs() {
+        let log_record = LogRecord {
+            time_unix_nano: 1_660_000_000_123_456_789,
+            observed_time_unix_nano: 1_660_000_001_000_000_000,
+            severity_number: 17,
+            severity_text: "ERROR".to_string(),
+            body: string_value("Failed to connect to the database."),
+            attributes: vec![key_value("db.system", string_value("postgresql"))],
+            trace_id: vec![1; 16],
+            span_id: vec![2; 8],
+            flags: 1,
+            ..Default::default()
+        };
+        let untimed_log_record = LogRecord {
+            observed_time_unix_nano: 1_660_000_002_000_000_000,
+            body: string_value("Retrying."),
+            ..Default::default()
+        };
+        let request = ExportLogsServiceRequest {
+            resource_logs: vec![ResourceLogs {
+                resource: Some(Resource {
+                    attributes: vec![key_value("service.name", string_value("api"))],
+                    ..Default::default()
+                }),
+                scope_logs: vec![ScopeLogs {
+                    scope: None,
+                    log_records: vec![log_record, untimed_log_record],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        };
+        let docs = export_request_to_docs(request);
+        assert_eq!(
+            docs,
+            vec![
+                json!({
+                    "timestamp_secs": 1_660_000_000u64,
+                    "timestamp_nanos": 1_660_000_000_123_456_789u64,
+                    "observed_timestamp_nanos": 1_660_000_001_000_000_000u64,
+                    "severity_number": 17,
+                    "severity_text": "ERROR",
+                    "body": "Failed to connect to the database.",
+                    "attributes": {"db.system": "postgresql"},
+                    "trace_id": "01010101010101010101010101010101",
+                    "span_id": "0202020202020202",
+                    "trace_flags": 1,
+                    "resource": {"attributes": {"service.name": "api"}},
+                }),
+                json!({
+                    "timestamp_secs": 1_660_000_002u64,
+                    "observed_timestamp_nanos": 1_660_000_002_000_000_000u64,
+                    "body": "Retrying.",
+                    "resource": {"attributes": {"service.name": "api"}},
+                }),
+            ]
+        );
+    }
+}
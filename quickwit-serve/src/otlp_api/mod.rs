@@ -0,0 +1,205 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+mod logs_service;
+mod traces_service;
+
+pub(crate) use logs_service::OtlpLogsService;
+use opentelemetry_proto::tonic::common::v1::any_value::Value as OtlpValue;
+use opentelemetry_proto::tonic::common::v1::{AnyValue, KeyValue};
+use quickwit_actors::Mailbox;
+use quickwit_config::IndexTemplate;
+use quickwit_core::{IndexService, IndexServiceError};
+use quickwit_ingest_api::{add_doc, IngestApiService};
+use quickwit_metastore::MetastoreError;
+use quickwit_proto::ingest_api::{DocBatch, IngestRequest};
+use quickwit_proto::{tonic, ServiceError};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+pub(crate) use traces_service::OtlpTracesService;
+use tracing::info;
+
+/// Appends the documents converted from an OTLP export request to the ingest API queue of the
+/// index.
+async fn ingest_docs(
+    index_id: &str,
+    ingest_api_mailbox: &Mailbox<IngestApiService>,
+    docs: Vec<JsonValue>,
+) -> Result<(), tonic::Status> {
+    if docs.is_empty() {
+        return Ok(());
+    }
+    let mut doc_batch = DocBatch {
+        index_id: index_id.to_string(),
+        ..Default::default()
+    };
+    for doc in docs {
+        add_doc(doc.to_string().as_bytes(), &mut doc_batch);
+    }
+    let ingest_req = IngestRequest {
+        doc_batches: vec![doc_batch],
+    };
+    ingest_api_mailbox
+        .ask_for_res(ingest_req)
+        .await
+        .map_err(|error| error.grpc_error())?;
+    Ok(())
+}
+
+/// Creates the index receiving the spans sent to the OTLP traces gRPC service from the
+/// `otel-traces` index template, unless it already exists.
+pub(crate) async fn create_otlp_traces_index_if_not_exists(
+    index_service: &IndexService,
+    index_id: &str,
+) -> anyhow::Result<()> {
+    match index_service.get_index(index_id).await {
+        Ok(_) => return Ok(()),
+        Err(IndexServiceError::MetastoreError(MetastoreError::IndexDoesNotExist { .. })) => {}
+        Err(error) => return Err(error.into()),
+    }
+    let index_config = IndexTemplate::OtelTraces.index_config(index_id)?;
+    match index_service.create_index(index_config, false).await {
+        Ok(_) => {
+            info!(index_id = %index_id, "Created OTLP traces index from the `otel-traces` index template.");
+            Ok(())
+        }
+        // Another indexer created the index in the meantime.
+        Err(IndexServiceError::MetastoreError(MetastoreError::IndexAlreadyExists { .. })) => Ok(()),
+        Err(error) => Err(error.into()),
+    }
+}
+
+pub(crate) fn insert_attributes(
+    json_map: &mut JsonMap<String, JsonValue>,
+    attributes: Vec<KeyValue>,
+) {
+    for attribute in attributes {
+        let value = attribute
+            .value
+            .map(any_value_to_json)
+            .unwrap_or(JsonValue::Null);
+        json_map.insert(attribute.key, value);
+    }
+}
+
+pub(crate) fn insert_non_empty(
+    json_map: &mut JsonMap<String, JsonValue>,
+    key: &str,
+    value: String,
+) {
+    if !value.is_empty() {
+        json_map.insert(key.to_string(), JsonValue::String(value));
+    }
+}
+
+pub(crate) fn insert_non_zero(json_map: &mut JsonMap<String, JsonValue>, key: &str, value: u64) {
+    if value != 0 {
+        json_map.insert(key.to_string(), JsonValue::from(value));
+    }
+}
+
+/// Converts an OTLP value into a JSON value. Bytes values are hex-encoded and non-finite doubles
+/// are converted into `null`.
+pub(crate) fn any_value_to_json(any_value: AnyValue) -> JsonValue {
+    match any_value.value {
+        Some(OtlpValue::StringValue(value)) => JsonValue::String(value),
+        Some(OtlpValue::BoolValue(value)) => JsonValue::Bool(value),
+        Some(OtlpValue::IntValue(value)) => JsonValue::from(value),
+        Some(OtlpValue::DoubleValue(value)) => JsonValue::from(value),
+        Some(OtlpValue::ArrayValue(array_value)) => JsonValue::Array(
+            array_value
+                .values
+                .into_iter()
+                .map(any_value_to_json)
+                .collect(),
+        ),
+        Some(OtlpValue::KvlistValue(kvlist_value)) => {
+            let mut json_map = JsonMap::new();
+            insert_attributes(&mut json_map, kvlist_value.values);
+            JsonValue::Object(json_map)
+        }
+        Some(OtlpValue::BytesValue(value)) => JsonValue::String(hex::encode(value)),
+        None => JsonValue::Null,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use opentelemetry_proto::tonic::common::v1::{ArrayValue, KeyValueList};
+    use serde_json::json;
+
+    use super::*;
+
+    pub(crate) fn string_value(value: &str) -> Option<AnyValue> {
+        Some(AnyValue {
+            value: Some(OtlpValue::StringValue(value.to_string())),
+        })
+    }
+
+    pub(crate) fn key_value(key: &str, value: Option<AnyValue>) -> KeyValue {
+        KeyValue {
+            key: key.to_string(),
+            value,
+        }
+    }
+
+    #[test]
+    fn test_any_value_to_json() {
+        let any_value = AnyValue {
+            value: Some(OtlpValue::KvlistValue(KeyValueList {
+                values: vec![
+                    key_value("string", string_value("foo")),
+                    key_value(
+                        "array",
+                        Some(AnyValue {
+                            value: Some(OtlpValue::ArrayValue(ArrayValue {
+                                values: vec![
+                                    AnyValue {
+                                        value: Some(OtlpValue::IntValue(-1)),
+                                    },
+                                    AnyValue {
+                                        value: Some(OtlpValue::DoubleValue(1.5)),
+                                    },
+                                    AnyValue {
+                                        value: Some(OtlpValue::BoolValue(true)),
+                                    },
+                                ],
+                            })),
+                        }),
+                    ),
+                    key_value(
+                        "bytes",
+                        Some(AnyValue {
+                            value: Some(OtlpValue::BytesValue(vec![0xca, 0xfe])),
+                        }),
+                    ),
+                    key_value("unset", None),
+                ],
+            })),
+        };
+        assert_eq!(
+            any_value_to_json(any_value),
+            json!({
+                "string": "foo",
+                "array": [-1, 1.5, true],
+                "bytes": "cafe",
+                "unset": null,
+            })
+        );
+    }
+}
@@ -0,0 +1,315 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use async_trait::async_trait;
+use opentelemetry_proto::tonic::collector::trace::v1::trace_service_server::TraceService;
+use opentelemetry_proto::tonic::collector::trace::v1::{
+    ExportTraceServiceRequest, ExportTraceServiceResponse,
+};
+use opentelemetry_proto::tonic::common::v1::any_value::Value as OtlpValue;
+use opentelemetry_proto::tonic::trace::v1::span::{Event, Link};
+use opentelemetry_proto::tonic::trace::v1::Span;
+use quickwit_actors::Mailbox;
+use quickwit_ingest_api::IngestApiService;
+use quickwit_proto::tonic;
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use tracing::debug;
+
+use super::{ingest_docs, insert_attributes, insert_non_empty, insert_non_zero};
+
+/// Resource attribute holding the name of the service that emitted a span.
+const SERVICE_NAME_ATTRIBUTE_KEY: &str = "service.name";
+
+/// OTLP traces gRPC service converting the spans it receives into JSON documents appended to the
+/// ingest API queue of an index, laid out as expected by the `otel-traces` index template.
+#[derive(Clone)]
+pub(crate) struct OtlpTracesService {
+    index_id: String,
+    ingest_api_mailbox: Mailbox<IngestApiService>,
+}
+
+impl OtlpTracesService {
+    pub fn new(index_id: String, ingest_api_mailbox: Mailbox<IngestApiService>) -> Self {
+        Self {
+            index_id,
+            ingest_api_mailbox,
+        }
+    }
+}
+
+#[async_trait]
+impl TraceService for OtlpTracesService {
+    async fn export(
+        &self,
+        request: tonic::Request<ExportTraceServiceRequest>,
+    ) -> Result<tonic::Response<ExportTraceServiceResponse>, tonic::Status> {
+        let docs = export_request_to_docs(request.into_inner());
+        debug!(index_id = %self.index_id, num_docs = docs.len(), "otlp-export-traces");
+        ingest_docs(&self.index_id, &self.ingest_api_mailbox, docs).await?;
+        Ok(tonic::Response::new(ExportTraceServiceResponse::default()))
+    }
+}
+
+/// Converts the spans of an export request into JSON documents. The fields of the resource and
+/// the instrumentation scope that emitted a span are copied into its document.
+fn export_request_to_docs(request: ExportTraceServiceRequest) -> Vec<JsonValue> {
+    let mut docs = Vec::new();
+    for resource_spans in request.resource_spans {
+        let mut resource_fields = JsonMap::new();
+        if let Some(resource) = resource_spans.resource {
+            let service_name_opt = resource
+                .attributes
+                .iter()
+                .find(|attribute| attribute.key == SERVICE_NAME_ATTRIBUTE_KEY)
+                .and_then(|attribute| attribute.value.as_ref())
+                .and_then(|value| match &value.value {
+                    Some(OtlpValue::StringValue(service_name)) => Some(service_name.clone()),
+                    _ => None,
+                });
+            if let Some(service_name) = service_name_opt {
+                insert_non_empty(&mut resource_fields, "service_name", service_name);
+            }
+            let mut resource_attributes = JsonMap::new();
+            insert_attributes(&mut resource_attributes, resource.attributes);
+            if !resource_attributes.is_empty() {
+                resource_fields.insert(
+                    "resource_attributes".to_string(),
+                    JsonValue::Object(resource_attributes),
+                );
+            }
+            insert_non_zero(
+                &mut resource_fields,
+                "resource_dropped_attributes_count",
+                resource.dropped_attributes_count as u64,
+            );
+        }
+        for scope_spans in resource_spans.scope_spans {
+            let mut scope_fields = resource_fields.clone();
+            if let Some(scope) = scope_spans.scope {
+                insert_non_empty(&mut scope_fields, "scope_name", scope.name);
+                insert_non_empty(&mut scope_fields, "scope_version", scope.version);
+            }
+            for span in scope_spans.spans {
+                let mut doc = scope_fields.clone();
+                doc.extend(span_to_doc(span));
+                docs.push(JsonValue::Object(doc));
+            }
+        }
+    }
+    docs
+}
+
+/// Converts a span into a JSON document. The start timestamp of the span is also recorded in
+/// seconds in the `span_start_timestamp_secs` field, the timestamp field of the `otel-traces`
+/// index template.
+fn span_to_doc(span: Span) -> JsonMap<String, JsonValue> {
+    let mut doc = JsonMap::new();
+    insert_non_empty(&mut doc, "trace_id", hex::encode(span.trace_id));
+    insert_non_empty(&mut doc, "trace_state", span.trace_state);
+    insert_non_empty(&mut doc, "span_id", hex::encode(span.span_id));
+    insert_non_empty(&mut doc, "parent_span_id", hex::encode(span.parent_span_id));
+    insert_non_zero(&mut doc, "span_kind", span.kind.max(0) as u64);
+    insert_non_empty(&mut doc, "span_name", span.name);
+    if span.start_time_unix_nano != 0 {
+        doc.insert(
+            "span_start_timestamp_secs".to_string(),
+            JsonValue::from(span.start_time_unix_nano / 1_000_000_000),
+        );
+    }
+    insert_non_zero(
+        &mut doc,
+        "span_start_timestamp_nanos",
+        span.start_time_unix_nano,
+    );
+    insert_non_zero(
+        &mut doc,
+        "span_end_timestamp_nanos",
+        span.end_time_unix_nano,
+    );
+    if span.start_time_unix_nano != 0 && span.end_time_unix_nano != 0 {
+        let duration_millis = span
+            .end_time_unix_nano
+            .saturating_sub(span.start_time_unix_nano)
+            / 1_000_000;
+        doc.insert(
+            "span_duration_millis".to_string(),
+            JsonValue::from(duration_millis),
+        );
+    }
+    let mut span_attributes = JsonMap::new();
+    insert_attributes(&mut span_attributes, span.attributes);
+    if !span_attributes.is_empty() {
+        doc.insert(
+            "span_attributes".to_string(),
+            JsonValue::Object(span_attributes),
+        );
+    }
+    insert_non_zero(
+        &mut doc,
+        "span_dropped_attributes_count",
+        span.dropped_attributes_count as u64,
+    );
+    insert_non_zero(
+        &mut doc,
+        "span_dropped_events_count",
+        span.dropped_events_count as u64,
+    );
+    insert_non_zero(
+        &mut doc,
+        "span_dropped_links_count",
+        span.dropped_links_count as u64,
+    );
+    if let Some(status) = span.status {
+        let mut span_status = JsonMap::new();
+        insert_non_zero(&mut span_status, "code", status.code.max(0) as u64);
+        insert_non_empty(&mut span_status, "message", status.message);
+        if !span_status.is_empty() {
+            doc.insert("span_status".to_string(), JsonValue::Object(span_status));
+        }
+    }
+    if !span.events.is_empty() {
+        let event_names: Vec<JsonValue> = span
+            .events
+            .iter()
+            .filter(|event| !event.name.is_empty())
+            .map(|event| JsonValue::String(event.name.clone()))
+            .collect();
+        let events: Vec<JsonValue> = span.events.into_iter().map(event_to_json).collect();
+        doc.insert("events".to_string(), JsonValue::Array(events));
+        if !event_names.is_empty() {
+            doc.insert("event_names".to_string(), JsonValue::Array(event_names));
+        }
+    }
+    if !span.links.is_empty() {
+        let links: Vec<JsonValue> = span.links.into_iter().map(link_to_json).collect();
+        doc.insert("links".to_string(), JsonValue::Array(links));
+    }
+    doc
+}
+
+fn event_to_json(event: Event) -> JsonValue {
+    let mut json_event = JsonMap::new();
+    insert_non_empty(&mut json_event, "name", event.name);
+    insert_non_zero(&mut json_event, "timestamp_nanos", event.time_unix_nano);
+    let mut attributes = JsonMap::new();
+    insert_attributes(&mut attributes, event.attributes);
+    if !attributes.is_empty() {
+        json_event.insert("attributes".to_string(), JsonValue::Object(attributes));
+    }
+    insert_non_zero(
+        &mut json_event,
+        "dropped_attributes_count",
+        event.dropped_attributes_count as u64,
+    );
+    JsonValue::Object(json_event)
+}
+
+fn link_to_json(link: Link) -> JsonValue {
+    let mut json_link = JsonMap::new();
+    insert_non_empty(&mut json_link, "trace_id", hex::encode(link.trace_id));
+    insert_non_empty(&mut json_link, "span_id", hex::encode(link.span_id));
+    insert_non_empty(&mut json_link, "trace_state", link.trace_state);
+    let mut attributes = JsonMap::new();
+    insert_attributes(&mut attributes, link.attributes);
+    if !attributes.is_empty() {
+        json_link.insert("attributes".to_string(), JsonValue::Object(attributes));
+    }
+    insert_non_zero(
+        &mut json_link,
+        "dropped_attributes_count",
+        link.dropped_attributes_count as u64,
+    );
+    JsonValue::Object(json_link)
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry_proto::tonic::resource::v1::Resource;
+    use opentelemetry_proto::tonic::trace::v1::{ResourceSpans, ScopeSpans};
+    use serde_json::json;
+
+    use super::*;
+    use crate::otlp_api::tests::{key_value, string_value};
+
+    #[test]
+    fn test_export_request_to_docs() {
+        let span = Span {
+            trace_id: vec![1; 16],
+            span_id: vec![2; 8],
+            parent_span_id: vec![3; 8],
+            name: "GET /users".to_string(),
+            kind: 2,
+            start_time_unix_nano: 1_660_000_000_000_000_000,
+            end_time_unix_nano: 1_660_000_000_250_000_000,
+            attributes: vec![key_value("http.method", string_value("GET"))],
+            events: vec![Event {
+                time_unix_nano: 1_660_000_000_100_000_000,
+                name: "cache-miss".to_string(),
+                ..Default::default()
+            }],
+            links: vec![Link {
+                trace_id: vec![4; 16],
+                span_id: vec![5; 8],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let request = ExportTraceServiceRequest {
+            resource_spans: vec![ResourceSpans {
+                resource: Some(Resource {
+                    attributes: vec![
+                        key_value("service.name", string_value("api")),
+                        key_value("host.name", string_value("node-1")),
+                    ],
+                    ..Default::default()
+                }),
+                scope_spans: vec![ScopeSpans {
+                    scope: None,
+                    spans: vec![span],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        };
+        let docs = export_request_to_docs(request);
+        assert_eq!(
+            docs,
+            vec![json!({
+                "service_name": "api",
+                "resource_attributes": {"service.name": "api", "host.name": "node-1"},
+                "trace_id": "01010101010101010101010101010101",
+                "span_id": "0202020202020202",
+                "parent_span_id": "0303030303030303",
+                "span_kind": 2,
+                "span_name": "GET /users",
+                "span_start_timestamp_secs": 1_660_000_000u64,
+                "span_start_timestamp_nanos": 1_660_000_000_000_000_000u64,
+                "span_end_timestamp_nanos": 1_660_000_000_250_000_000u64,
+                "span_duration_millis": 250,
+                "span_attributes": {"http.method": "GET"},
+                "events": [{"name": "cache-miss", "timestamp_nanos": 1_660_000_000_100_000_000u64}],
+                "event_names": ["cache-miss"],
+                "links": [{
+                    "trace_id": "04040404040404040404040404040404",
+                    "span_id": "0505050505050505",
+                }],
+            })]
+        );
+    }
+}
@@ -26,11 +26,12 @@ use tracing::{error, info};
 use warp::{redirect, Filter, Rejection, Reply};
 
 use crate::cluster_api::cluster_handler;
+use crate::elastic_search_api::{elastic_msearch_handler, elastic_search_handler};
 use crate::format::FormatError;
 use crate::health_check_api::health_check_handlers;
 use crate::index_api::index_management_handlers;
-use crate::indexing_api::indexing_get_handler;
-use crate::ingest_api::{elastic_bulk_handler, ingest_handler, tail_handler};
+use crate::indexing_api::{indexing_get_handler, indexing_pipelines_handlers};
+use crate::ingest_api::{elastic_bulk_handler, ingest_handler, tail_handler, webhook_handler};
 use crate::node_info_handler::node_info_handler;
 use crate::search_api::{search_get_handler, search_post_handler, search_stream_handler};
 use crate::ui_handler::ui_handler;
@@ -57,6 +58,11 @@ pub(crate) async fn start_rest_server(
         .or(indexing_get_handler(
             quickwit_services.indexer_service.clone(),
         ))
+        .or(indexing_pipelines_handlers(
+            quickwit_services.config.node_id.clone(),
+            quickwit_services.cluster.clone(),
+            quickwit_services.indexer_service.clone(),
+        ))
         .or(search_get_handler(quickwit_services.search_service.clone()))
         .or(search_post_handler(
             quickwit_services.search_service.clone(),
@@ -64,11 +70,23 @@ pub(crate) async fn start_rest_server(
         .or(search_stream_handler(
             quickwit_services.search_service.clone(),
         ))
+        .or(elastic_search_handler(
+            quickwit_services.search_service.clone(),
+            quickwit_services.metastore.clone(),
+        ))
+        .or(elastic_msearch_handler(
+            quickwit_services.search_service.clone(),
+            quickwit_services.metastore.clone(),
+        ))
         .or(ingest_handler(quickwit_services.ingest_api_service.clone()))
         .or(tail_handler(quickwit_services.ingest_api_service.clone()))
         .or(elastic_bulk_handler(
             quickwit_services.ingest_api_service.clone(),
         ))
+        .or(webhook_handler(
+            quickwit_services.metastore.clone(),
+            quickwit_services.ingest_api_service.clone(),
+        ))
         .or(index_management_handlers(
             quickwit_services.index_service.clone(),
         ))
@@ -38,7 +38,7 @@ mod tests {
         root_search_stream, ClusterClient, MockSearchService, SearchClientPool, SearchError,
         SearchService,
     };
-    use tokio_stream::wrappers::UnboundedReceiverStream;
+    use tokio_stream::wrappers::ReceiverStream;
     use tonic::transport::Server;
 
     use crate::search_api::GrpcSearchAdapter;
@@ -71,6 +71,7 @@ mod tests {
             fast_field: "timestamp".to_string(),
             output_format: OutputFormat::Csv as i32,
             partition_by_field: None,
+            fast_fields: Vec::new(),
         };
         let mut metastore = MockMetastore::new();
         metastore
@@ -87,12 +88,12 @@ mod tests {
             },
         );
         let mut mock_search_service = MockSearchService::new();
-        let (result_sender, result_receiver) = tokio::sync::mpsc::unbounded_channel();
-        result_sender.send(Ok(quickwit_proto::LeafSearchStreamResponse {
+        let (result_sender, result_receiver) = tokio::sync::mpsc::channel(2);
+        result_sender.try_send(Ok(quickwit_proto::LeafSearchStreamResponse {
             data: b"123".to_vec(),
             split_id: "split_1".to_string(),
         }))?;
-        result_sender.send(Err(SearchError::InternalError(
+        result_sender.try_send(Err(SearchError::InternalError(
             "Error on `split2`".to_string(),
         )))?;
         mock_search_service
@@ -100,7 +101,7 @@ mod tests {
             .withf(|request| request.split_offsets.len() == 2) // First request.
             .return_once(
                 |_leaf_search_req: quickwit_proto::LeafSearchStreamRequest| {
-                    Ok(UnboundedReceiverStream::new(result_receiver))
+                    Ok(ReceiverStream::new(result_receiver))
                 },
             );
         mock_search_service
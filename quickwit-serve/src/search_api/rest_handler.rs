@@ -25,14 +25,14 @@ use hyper::header::HeaderValue;
 use hyper::HeaderMap;
 use quickwit_doc_mapper::{SortByField, SortOrder};
 use quickwit_proto::{OutputFormat, ServiceError, SortOrder as ProtoSortOrder};
-use quickwit_search::{SearchError, SearchResponseRest, SearchService};
+use quickwit_search::{decode_search_after_cursor, SearchError, SearchResponseRest, SearchService};
 use serde::{de, Deserialize, Deserializer};
 use tracing::info;
 use warp::hyper::header::CONTENT_TYPE;
 use warp::hyper::StatusCode;
 use warp::{reply, Filter, Rejection, Reply};
 
-use crate::{with_arg, Format};
+use crate::{decode_index_id, with_arg, Format};
 
 fn sort_by_field_mini_dsl<'de, D>(deserializer: D) -> Result<Option<SortByField>, D::Error>
 where D: Deserializer<'de> {
@@ -54,13 +54,13 @@ fn default_max_hits() -> u64 {
 //   I did not find a way to plug it to serde_qs.
 // Conclusion: the best way I found to reject a user query that contains an empty
 // string on an mandatory field is this serializer.
-fn deserialize_not_empty_string<'de, D>(deserializer: D) -> Result<String, D::Error>
+fn deserialize_not_empty_string<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
 where D: Deserializer<'de> {
     let value = String::deserialize(deserializer)?;
     if value.is_empty() {
         return Err(de::Error::custom("Expected a non empty string field."));
     }
-    Ok(value)
+    Ok(Some(value))
 }
 
 fn from_simple_list<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
@@ -93,6 +93,16 @@ pub struct SearchRequestQueryString {
     #[serde(default)]
     #[serde(deserialize_with = "from_simple_list")]
     pub snippet_fields: Option<Vec<String>>,
+    /// Tag inserted before the highlighted terms of the snippets (by default `<b>`).
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_not_empty_string")]
+    pub snippet_pre_tag: Option<String>,
+    /// Tag inserted after the highlighted terms of the snippets (by default `</b>`).
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_not_empty_string")]
+    pub snippet_post_tag: Option<String>,
+    /// Maximum number of characters of the snippets (by default 150).
+    pub snippet_fragment_size: Option<u32>,
     /// If set, restrict search to documents with a `timestamp >= start_timestamp`.
     pub start_timestamp: Option<i64>,
     /// If set, restrict search to documents with a `timestamp < end_timestamp``.
@@ -114,6 +124,10 @@ pub struct SearchRequestQueryString {
     #[serde(deserialize_with = "sort_by_field_mini_dsl")]
     #[serde(default)]
     sort_by_field: Option<SortByField>,
+    /// Cursor returned by a previous search, from which the next hits are returned. Unlike
+    /// `start_offset`, the cost of the search does not grow with the depth of the page.
+    #[serde(default)]
+    pub search_after: Option<String>,
 }
 
 fn get_proto_search_by(search_request: &SearchRequestQueryString) -> (Option<i32>, Option<String>) {
@@ -134,6 +148,11 @@ async fn search_endpoint(
     search_service: &dyn SearchService,
 ) -> Result<SearchResponseRest, SearchError> {
     let (sort_order, sort_by_field) = get_proto_search_by(&search_request);
+    let search_after = search_request
+        .search_after
+        .as_deref()
+        .map(decode_search_after_cursor)
+        .transpose()?;
     let search_request = quickwit_proto::SearchRequest {
         index_id,
         query: search_request.query,
@@ -148,6 +167,10 @@ async fn search_endpoint(
             .map(|agg| serde_json::to_string(&agg).expect("could not serialize serde_json::Value")),
         sort_order,
         sort_by_field,
+        search_after,
+        snippet_pre_tag: search_request.snippet_pre_tag,
+        snippet_post_tag: search_request.snippet_post_tag,
+        snippet_fragment_size: search_request.snippet_fragment_size,
     };
     let search_response = search_service.root_search(search_request).await?;
     let search_response_rest = SearchResponseRest::try_from(search_response)?;
@@ -157,6 +180,7 @@ async fn search_endpoint(
 fn search_get_filter(
 ) -> impl Filter<Extract = (String, SearchRequestQueryString), Error = Rejection> + Clone {
     warp::path!(String / "search")
+        .map(decode_index_id)
         .and(warp::get())
         .and(serde_qs::warp::query(serde_qs::Config::default()))
 }
@@ -164,6 +188,7 @@ fn search_get_filter(
 fn search_post_filter(
 ) -> impl Filter<Extract = (String, SearchRequestQueryString), Error = Rejection> + Clone {
     warp::path!(String / "search")
+        .map(decode_index_id)
         .and(warp::post())
         .and(warp::body::content_length_limit(1024 * 1024))
         .and(warp::body::json())
@@ -232,8 +257,14 @@ struct SearchStreamRequestQueryString {
     /// If set, restricts search to documents with a `timestamp < end_timestamp``.
     pub end_timestamp: Option<i64>,
     /// The fast field to extract.
+    #[serde(default)]
     #[serde(deserialize_with = "deserialize_not_empty_string")]
-    pub fast_field: String,
+    pub fast_field: Option<String>,
+    /// The fast fields to extract as columns. If neither `fast_field` nor `fast_fields` is set,
+    /// the stored documents are extracted.
+    #[serde(default)]
+    #[serde(deserialize_with = "from_simple_list")]
+    pub fast_fields: Option<Vec<String>>,
     /// The requested output format.
     #[serde(default)]
     pub output_format: OutputFormat,
@@ -253,9 +284,10 @@ async fn search_stream_endpoint(
         snippet_fields: search_request.snippet_fields.unwrap_or_default(),
         start_timestamp: search_request.start_timestamp,
         end_timestamp: search_request.end_timestamp,
-        fast_field: search_request.fast_field,
+        fast_field: search_request.fast_field.unwrap_or_default(),
         output_format: search_request.output_format as i32,
         partition_by_field: search_request.partition_by_field,
+        fast_fields: search_request.fast_fields.unwrap_or_default(),
     };
     let mut data = search_service.root_search_stream(request).await?;
     let (mut sender, body) = hyper::Body::channel();
@@ -316,6 +348,8 @@ async fn search_stream(
     let content_type = match request.output_format {
         OutputFormat::ClickHouseRowBinary => "application/octet-stream",
         OutputFormat::Csv => "text/csv",
+        OutputFormat::Ndjson => "application/x-ndjson",
+        OutputFormat::ArrowIpc => "application/vnd.apache.arrow.stream",
     };
     let reply =
         make_streaming_reply(search_stream_endpoint(index_id, request, &*search_service).await);
@@ -326,6 +360,7 @@ async fn search_stream(
 fn search_stream_filter(
 ) -> impl Filter<Extract = (String, SearchStreamRequestQueryString), Error = Rejection> + Clone {
     warp::path!(String / "search" / "stream")
+        .map(decode_index_id)
         .and(warp::get())
         .and(serde_qs::warp::query(serde_qs::Config::default()))
 }
@@ -359,6 +394,7 @@ mod tests {
             elapsed_time_micros: 0u64,
             errors: Vec::new(),
             aggregations: None,
+            search_after: None,
         };
         let search_response_json: serde_json::Value = serde_json::to_value(&search_response)?;
         let expected_search_response_json: serde_json::Value = json!({
@@ -565,7 +601,7 @@ mod tests {
         assert_eq!(resp.status(), 400);
         let resp_json: serde_json::Value = serde_json::from_slice(resp.body())?;
         let exp_resp_json = serde_json::json!({
-            "error": "unknown field `end_unix_timestamp`, expected one of `query`, `aggs`, `search_field`, `snippet_fields`, `start_timestamp`, `end_timestamp`, `max_hits`, `start_offset`, `format`, `sort_by_field`"
+            "error": "unknown field `end_unix_timestamp`, expected one of `query`, `aggs`, `search_field`, `snippet_fields`, `snippet_pre_tag`, `snippet_post_tag`, `snippet_fragment_size`, `start_timestamp`, `end_timestamp`, `max_hits`, `start_offset`, `format`, `sort_by_field`, `search_after`"
         });
         assert_eq!(resp_json, exp_resp_json);
         Ok(())
@@ -637,6 +673,35 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_rest_search_api_snippet_parameters() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .with(predicate::function(
+                |search_request: &quickwit_proto::SearchRequest| {
+                    search_request.snippet_fields == ["body"]
+                        && search_request.snippet_pre_tag.as_deref() == Some("<em>")
+                        && search_request.snippet_post_tag.as_deref() == Some("</em>")
+                        && search_request.snippet_fragment_size == Some(50)
+                },
+            ))
+            .returning(|_| Ok(Default::default()));
+        let rest_search_api_handler = search_handler(mock_search_service);
+        assert_eq!(
+            warp::test::request()
+                .path(
+                    "/quickwit-demo-index/search?query=bar&snippet_fields=body&snippet_pre_tag=%\
+                     3Cem%3E&snippet_post_tag=%3C%2Fem%3E&snippet_fragment_size=50"
+                )
+                .reply(&rest_search_api_handler)
+                .await
+                .status(),
+            200
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_rest_search_api_with_index_does_not_exist() -> anyhow::Result<()> {
         let mut mock_search_service = MockSearchService::new();
@@ -731,7 +796,8 @@ mod tests {
                 snippet_fields: None,
                 start_timestamp: None,
                 end_timestamp: None,
-                fast_field: "external_id".to_string(),
+                fast_field: Some("external_id".to_string()),
+                fast_fields: None,
                 output_format: OutputFormat::Csv,
                 partition_by_field: None,
             }
@@ -757,13 +823,41 @@ mod tests {
                 snippet_fields: None,
                 start_timestamp: None,
                 end_timestamp: None,
-                fast_field: "external_id".to_string(),
+                fast_field: Some("external_id".to_string()),
+                fast_fields: None,
                 output_format: OutputFormat::ClickHouseRowBinary,
                 partition_by_field: None,
             }
         );
     }
 
+    #[tokio::test]
+    async fn test_rest_search_stream_api_ndjson_fast_fields() {
+        let (index, req) = warp::test::request()
+            .path(
+                "/my-index/search/stream?query=obama&fast_fields=external_id,timestamp&\
+                 output_format=ndjson",
+            )
+            .filter(&super::search_stream_filter())
+            .await
+            .unwrap();
+        assert_eq!(&index, "my-index");
+        assert_eq!(
+            &req,
+            &super::SearchStreamRequestQueryString {
+                query: "obama".to_string(),
+                search_fields: None,
+                snippet_fields: None,
+                start_timestamp: None,
+                end_timestamp: None,
+                fast_field: None,
+                fast_fields: Some(vec!["external_id".to_string(), "timestamp".to_string()]),
+                output_format: OutputFormat::Ndjson,
+                partition_by_field: None,
+            }
+        );
+    }
+
     #[tokio::test]
     async fn test_rest_search_stream_api_error() {
         let rejection = warp::test::request()
@@ -777,7 +871,8 @@ mod tests {
         let parse_error = rejection.find::<serde_qs::Error>().unwrap();
         assert_eq!(
             parse_error.to_string(),
-            "unknown variant `ClickHouseRowBinary`, expected `csv` or `click_house_row_binary`"
+            "unknown variant `ClickHouseRowBinary`, expected one of `csv`, \
+             `click_house_row_binary`, `ndjson`, `arrow_ipc`"
         );
     }
 
@@ -62,6 +62,10 @@ async fn test_standalone_server() -> anyhow::Result<()> {
             sort_by_field: None,
             sort_order: None,
             start_offset: 0,
+            search_after: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            snippet_fragment_size: None,
         })
         .await;
     assert!(search_result.is_ok());
@@ -100,6 +104,10 @@ async fn test_multi_nodes_cluster() -> anyhow::Result<()> {
             sort_order: None,
             start_offset: 0,
             snippet_fields: Vec::new(),
+            search_after: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            snippet_fragment_size: None,
         })
         .await;
     assert!(search_result.is_ok());
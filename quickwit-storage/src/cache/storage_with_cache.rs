@@ -18,7 +18,7 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::ops::Range;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use async_trait::async_trait;
@@ -85,6 +85,10 @@ impl Storage for StorageWithCache {
         self.storage.file_num_bytes(path).await
     }
 
+    async fn list_prefix(&self, prefix: &Path) -> StorageResult<Vec<PathBuf>> {
+        self.storage.list_prefix(prefix).await
+    }
+
     fn uri(&self) -> &Uri {
         self.storage.uri()
     }
@@ -0,0 +1,269 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::io;
+use std::ops::{Add, AddAssign, Range};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use quickwit_common::uri::Uri;
+use serde::{Deserialize, Serialize};
+
+use crate::{OwnedBytes, PutPayload, ResumableUploadState, Storage, StorageResult};
+
+/// Counts of the requests issued against a storage and of the bytes they transferred.
+///
+/// `HEAD` requests, used to check whether a file exists or get its size, are counted as `GET`
+/// requests as object storages bill them alike.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StorageOpsCounts {
+    /// Number of `GET` requests.
+    pub num_get_requests: u64,
+    /// Number of bytes downloaded.
+    pub num_get_bytes: u64,
+    /// Number of `PUT` requests.
+    pub num_put_requests: u64,
+    /// Number of bytes uploaded.
+    pub num_put_bytes: u64,
+    /// Number of `DELETE` requests.
+    pub num_delete_requests: u64,
+}
+
+impl Add for StorageOpsCounts {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        self += other;
+        self
+    }
+}
+
+impl AddAssign for StorageOpsCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.num_get_requests += other.num_get_requests;
+        self.num_get_bytes += other.num_get_bytes;
+        self.num_put_requests += other.num_put_requests;
+        self.num_put_bytes += other.num_put_bytes;
+        self.num_delete_requests += other.num_delete_requests;
+    }
+}
+
+/// Shared counters updated by the storages wrapped with [`wrap_storage_with_ops_counters`].
+#[derive(Clone, Debug, Default)]
+pub struct StorageOpsCounters {
+    num_get_requests: Arc<AtomicU64>,
+    num_get_bytes: Arc<AtomicU64>,
+    num_put_requests: Arc<AtomicU64>,
+    num_put_bytes: Arc<AtomicU64>,
+    num_delete_requests: Arc<AtomicU64>,
+}
+
+impl StorageOpsCounters {
+    /// Returns the current value of the counters.
+    pub fn counts(&self) -> StorageOpsCounts {
+        StorageOpsCounts {
+            num_get_requests: self.num_get_requests.load(Ordering::Relaxed),
+            num_get_bytes: self.num_get_bytes.load(Ordering::Relaxed),
+            num_put_requests: self.num_put_requests.load(Ordering::Relaxed),
+            num_put_bytes: self.num_put_bytes.load(Ordering::Relaxed),
+            num_delete_requests: self.num_delete_requests.load(Ordering::Relaxed),
+        }
+    }
+
+    fn record_get(&self, num_bytes: u64) {
+        self.num_get_requests.fetch_add(1, Ordering::Relaxed);
+        self.num_get_bytes.fetch_add(num_bytes, Ordering::Relaxed);
+    }
+
+    fn record_put(&self, num_bytes: u64) {
+        self.num_put_requests.fetch_add(1, Ordering::Relaxed);
+        self.num_put_bytes.fetch_add(num_bytes, Ordering::Relaxed);
+    }
+
+    fn record_delete(&self) {
+        self.num_delete_requests.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// This storage acts as a proxy to another storage and counts the requests issued through it.
+///
+/// Failed requests are counted too, as object storages bill them.
+struct CountingStorage {
+    storage: Arc<dyn Storage>,
+    counters: StorageOpsCounters,
+}
+
+#[async_trait]
+impl Storage for CountingStorage {
+    async fn check_connectivity(&self) -> anyhow::Result<()> {
+        self.storage.check_connectivity().await
+    }
+
+    async fn put(&self, path: &Path, payload: Box<dyn PutPayload>) -> StorageResult<()> {
+        self.counters.record_put(payload.len());
+        self.storage.put(path, payload).await
+    }
+
+    async fn put_resumable(
+        &self,
+        path: &Path,
+        payload: Box<dyn PutPayload>,
+        upload_state: &mut ResumableUploadState,
+    ) -> StorageResult<()> {
+        self.counters.record_put(payload.len());
+        self.storage
+            .put_resumable(path, payload, upload_state)
+            .await
+    }
+
+    async fn abort_resumable_upload(
+        &self,
+        path: &Path,
+        upload_state: &ResumableUploadState,
+    ) -> StorageResult<()> {
+        self.storage
+            .abort_resumable_upload(path, upload_state)
+            .await
+    }
+
+    async fn put_streamed(
+        &self,
+        path: &Path,
+        payload_stream: BoxStream<'static, io::Result<Box<dyn PutPayload>>>,
+    ) -> StorageResult<()> {
+        // The number of bytes of a streamed file is only known once the stream is exhausted.
+        let num_put_bytes = Arc::new(AtomicU64::new(0));
+        let num_put_bytes_clone = num_put_bytes.clone();
+        let payload_stream = payload_stream
+            .inspect_ok(move |payload| {
+                num_put_bytes_clone.fetch_add(payload.len(), Ordering::Relaxed);
+            })
+            .boxed();
+        let put_result = self.storage.put_streamed(path, payload_stream).await;
+        self.counters
+            .record_put(num_put_bytes.load(Ordering::Relaxed));
+        put_result
+    }
+
+    async fn copy_to_file(&self, path: &Path, output_path: &Path) -> StorageResult<()> {
+        let copy_result = self.storage.copy_to_file(path, output_path).await;
+        let num_bytes = if copy_result.is_ok() {
+            tokio::fs::metadata(output_path)
+                .await
+                .map(|metadata| metadata.len())
+                .unwrap_or(0)
+        } else {
+            0
+        };
+        self.counters.record_get(num_bytes);
+        copy_result
+    }
+
+    async fn get_slice(&self, path: &Path, range: Range<usize>) -> StorageResult<OwnedBytes> {
+        let get_result = self.storage.get_slice(path, range).await;
+        self.counters
+            .record_get(get_result.as_ref().map_or(0, |bytes| bytes.len() as u64));
+        get_result
+    }
+
+    async fn get_all(&self, path: &Path) -> StorageResult<OwnedBytes> {
+        let get_result = self.storage.get_all(path).await;
+        self.counters
+            .record_get(get_result.as_ref().map_or(0, |bytes| bytes.len() as u64));
+        get_result
+    }
+
+    async fn delete(&self, path: &Path) -> StorageResult<()> {
+        self.counters.record_delete();
+        self.storage.delete(path).await
+    }
+
+    async fn exists(&self, path: &Path) -> StorageResult<bool> {
+        self.counters.record_get(0);
+        self.storage.exists(path).await
+    }
+
+    async fn file_num_bytes(&self, path: &Path) -> StorageResult<u64> {
+        self.counters.record_get(0);
+        self.storage.file_num_bytes(path).await
+    }
+
+    async fn list_prefix(&self, prefix: &Path) -> StorageResult<Vec<PathBuf>> {
+        self.counters.record_get(0);
+        self.storage.list_prefix(prefix).await
+    }
+
+    fn uri(&self) -> &Uri {
+        self.storage.uri()
+    }
+}
+
+/// Wraps a storage so that the requests issued through it are recorded in `counters`.
+pub fn wrap_storage_with_ops_counters(
+    storage: Arc<dyn Storage>,
+    counters: StorageOpsCounters,
+) -> Arc<dyn Storage> {
+    Arc::new(CountingStorage { storage, counters })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RamStorage;
+
+    #[tokio::test]
+    async fn test_counting_storage() -> anyhow::Result<()> {
+        let counters = StorageOpsCounters::default();
+        let storage =
+            wrap_storage_with_ops_counters(Arc::new(RamStorage::default()), counters.clone());
+        storage
+            .put(Path::new("foo"), Box::new(b"hello".to_vec()))
+            .await?;
+        assert_eq!(&storage.get_all(Path::new("foo")).await?[..], b"hello");
+        assert_eq!(&storage.get_slice(Path::new("foo"), 1..3).await?[..], b"el");
+        assert!(storage.get_all(Path::new("bar")).await.is_err());
+        assert!(storage.exists(Path::new("foo")).await?);
+        storage.delete(Path::new("foo")).await?;
+        assert_eq!(
+            counters.counts(),
+            StorageOpsCounts {
+                num_get_requests: 4,
+                num_get_bytes: 7,
+                num_put_requests: 1,
+                num_put_bytes: 5,
+                num_delete_requests: 1,
+            }
+        );
+        assert_eq!(
+            counters.counts() + counters.counts(),
+            StorageOpsCounts {
+                num_get_requests: 8,
+                num_get_bytes: 14,
+                num_put_requests: 2,
+                num_put_bytes: 10,
+                num_delete_requests: 2,
+            }
+        );
+        Ok(())
+    }
+}
@@ -18,6 +18,7 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::hash::Hash;
+use std::io;
 use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
@@ -25,11 +26,12 @@ use std::sync::{Arc, Mutex};
 use async_trait::async_trait;
 use fnv::FnvHashMap;
 use futures::future::{BoxFuture, WeakShared};
+use futures::stream::BoxStream;
 use futures::{Future, FutureExt};
 use quickwit_common::uri::Uri;
 use tantivy::directory::OwnedBytes;
 
-use crate::{Storage, StorageResult};
+use crate::{ResumableUploadState, Storage, StorageResult};
 
 /// The AsyncDebouncer debounces inflight Futures, so that concurrent async request to the same data
 /// source can be deduplicated.
@@ -141,6 +143,35 @@ impl<T: Storage> Storage for DebouncedStorage<T> {
         self.underlying.put(path, payload).await
     }
 
+    async fn put_resumable(
+        &self,
+        path: &Path,
+        payload: Box<dyn crate::PutPayload>,
+        upload_state: &mut ResumableUploadState,
+    ) -> StorageResult<()> {
+        self.underlying
+            .put_resumable(path, payload, upload_state)
+            .await
+    }
+
+    async fn abort_resumable_upload(
+        &self,
+        path: &Path,
+        upload_state: &ResumableUploadState,
+    ) -> StorageResult<()> {
+        self.underlying
+            .abort_resumable_upload(path, upload_state)
+            .await
+    }
+
+    async fn put_streamed(
+        &self,
+        path: &Path,
+        payload_stream: BoxStream<'static, io::Result<Box<dyn crate::PutPayload>>>,
+    ) -> StorageResult<()> {
+        self.underlying.put_streamed(path, payload_stream).await
+    }
+
     async fn copy_to_file(&self, path: &Path, output_path: &Path) -> StorageResult<()> {
         self.underlying.copy_to_file(path, output_path).await
     }
@@ -177,6 +208,10 @@ impl<T: Storage> Storage for DebouncedStorage<T> {
     async fn file_num_bytes(&self, path: &Path) -> StorageResult<u64> {
         self.underlying.file_num_bytes(path).await
     }
+
+    async fn list_prefix(&self, prefix: &Path) -> StorageResult<Vec<PathBuf>> {
+        self.underlying.list_prefix(prefix).await
+    }
 }
 
 #[cfg(test)]
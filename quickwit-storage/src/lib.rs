@@ -38,15 +38,17 @@ pub(crate) use debouncer::DebouncedStorage;
 
 pub use self::metrics::STORAGE_METRICS;
 pub use self::payload::PutPayload;
-pub use self::storage::Storage;
+pub use self::storage::{ResumableUploadState, Storage};
 
 mod bundle_storage;
+mod counting_storage;
 mod error;
 mod local_file_storage;
 mod object_storage;
 mod payload;
 mod prefix_storage;
 mod ram_storage;
+mod retry_storage;
 mod split;
 mod storage_resolver;
 
@@ -57,6 +59,9 @@ pub use self::bundle_storage::{BundleStorage, BundleStorageFileOffsets};
 #[cfg(any(test, feature = "testsuite"))]
 pub use self::cache::MockCache;
 pub use self::cache::{wrap_storage_with_long_term_cache, Cache, MemorySizedCache, QuickwitCache};
+pub use self::counting_storage::{
+    wrap_storage_with_ops_counters, StorageOpsCounters, StorageOpsCounts,
+};
 pub use self::local_file_storage::{LocalFileStorage, LocalFileStorageFactory};
 #[cfg(feature = "azure")]
 pub use self::object_storage::{AzureBlobStorage, AzureBlobStorageFactory};
@@ -64,6 +69,7 @@ pub use self::object_storage::{
     MultiPartPolicy, S3CompatibleObjectStorage, S3CompatibleObjectStorageFactory,
 };
 pub use self::ram_storage::{RamStorage, RamStorageBuilder};
+pub use self::retry_storage::{wrap_storage_with_retry_policy, StorageRetryPolicy};
 pub use self::split::{SplitPayload, SplitPayloadBuilder};
 #[cfg(any(test, feature = "testsuite"))]
 pub use self::storage::MockStorage;
@@ -114,8 +120,9 @@ pub(crate) mod test_suite {
     use std::path::Path;
 
     use anyhow::Context;
+    use futures::StreamExt;
 
-    use crate::{Storage, StorageErrorKind};
+    use crate::{PutPayload, Storage, StorageErrorKind};
 
     async fn test_get_inexistent_file(storage: &mut dyn Storage) -> anyhow::Result<()> {
         let err = storage
@@ -179,6 +186,32 @@ pub(crate) mod test_suite {
         Ok(())
     }
 
+    async fn test_write_streamed(storage: &mut dyn Storage) -> anyhow::Result<()> {
+        let test_path = Path::new("write_streamed");
+        let payloads: Vec<std::io::Result<Box<dyn PutPayload>>> = vec![
+            Ok(Box::new(b"abcdef".to_vec())),
+            Ok(Box::new(b"ghijklmnopqrstuvwxyz".to_vec())),
+        ];
+        storage
+            .put_streamed(test_path, futures::stream::iter(payloads).boxed())
+            .await?;
+        let payload = storage.get_all(test_path).await?;
+        assert_eq!(&payload[..], &b"abcdefghijklmnopqrstuvwxyz"[..]);
+        storage.delete(test_path).await?;
+
+        let aborted_test_path = Path::new("write_streamed_aborted");
+        let payloads: Vec<std::io::Result<Box<dyn PutPayload>>> = vec![
+            Ok(Box::new(b"abcdef".to_vec())),
+            Err(std::io::Error::new(std::io::ErrorKind::Other, "aborted")),
+        ];
+        assert!(storage
+            .put_streamed(aborted_test_path, futures::stream::iter(payloads).boxed())
+            .await
+            .is_err());
+        assert!(matches!(storage.exists(aborted_test_path).await, Ok(false)));
+        Ok(())
+    }
+
     async fn test_file_size(storage: &mut dyn Storage) -> anyhow::Result<()> {
         let test_path = Path::new("write_for_filesize");
         let payload_bytes = b"abcdefghijklmnopqrstuvwxyz".as_ref();
@@ -249,6 +282,9 @@ pub(crate) mod test_suite {
         test_write_and_delete_with_dir_separator(storage)
             .await
             .with_context(|| "write_and_delete_with_separator")?;
+        test_write_streamed(storage)
+            .await
+            .with_context(|| "write_streamed")?;
         test_file_size(storage).await.with_context(|| "file_size")?;
         test_delete_missing_file(storage)
             .await
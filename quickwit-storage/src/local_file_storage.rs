@@ -25,6 +25,7 @@ use std::{fmt, io};
 
 use async_trait::async_trait;
 use futures::future::{BoxFuture, FutureExt};
+use quickwit_common::fs::sync_dir;
 use quickwit_common::uri::{Protocol, Uri};
 use tokio::fs;
 use tokio::io::{AsyncReadExt, AsyncSeekExt};
@@ -123,6 +124,16 @@ fn delete_all_dirs(root: PathBuf, path: &Path) -> BoxFuture<'_, std::io::Result<
     .boxed()
 }
 
+/// Returns the path of a temporary file in the same directory as `path`, written before being
+/// renamed to `path`.
+fn tmp_file_path(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{file_name}.{:016x}.tmp", rand::random::<u64>()))
+}
+
 fn missing_file_is_ok(io_result: io::Result<()>) -> io::Result<()> {
     match io_result {
         Ok(()) => Ok(()),
@@ -151,10 +162,25 @@ impl Storage for LocalFileStorage {
             fs::create_dir_all(parent_dir).await?;
         }
 
+        // The payload is written to a temporary file renamed once complete, so that a crash
+        // never leaves a partially written file behind.
+        let tmp_path = tmp_file_path(&full_path);
         let mut reader = payload.byte_stream().await?.into_async_read();
-        let mut f = tokio::fs::File::create(full_path).await?;
-        tokio::io::copy(&mut reader, &mut f).await?;
-
+        let write_result: io::Result<()> = async {
+            let mut f = tokio::fs::File::create(&tmp_path).await?;
+            tokio::io::copy(&mut reader, &mut f).await?;
+            f.sync_all().await?;
+            fs::rename(&tmp_path, &full_path).await?;
+            if let Some(parent_dir) = full_path.parent() {
+                sync_dir(parent_dir).await?;
+            }
+            Ok(())
+        }
+        .await;
+        if write_result.is_err() {
+            let _ = fs::remove_file(&tmp_path).await;
+        }
+        write_result?;
         Ok(())
     }
 
@@ -224,6 +250,37 @@ impl Storage for LocalFileStorage {
             }
         }
     }
+
+    async fn list_prefix(&self, prefix: &Path) -> StorageResult<Vec<PathBuf>> {
+        let root = self.root.clone();
+        let dir_path = self.root.join(prefix);
+        let mut paths = tokio::task::spawn_blocking(move || {
+            let mut paths = Vec::new();
+            list_files_rec(&root, &dir_path, &mut paths).map(|_| paths)
+        })
+        .await
+        .map_err(|join_error| StorageErrorKind::InternalError.with_error(join_error))??;
+        paths.sort();
+        Ok(paths)
+    }
+}
+
+/// Appends the paths, relative to `root`, of the files stored under the directory `dir_path`.
+fn list_files_rec(root: &Path, dir_path: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    let dir_entries = match dir_path.read_dir() {
+        Ok(dir_entries) => dir_entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    for dir_entry_res in dir_entries {
+        let path = dir_entry_res?.path();
+        if path.is_dir() {
+            list_files_rec(root, &path, paths)?;
+        } else if let Ok(relative_path) = path.strip_prefix(root) {
+            paths.push(relative_path.to_path_buf());
+        }
+    }
+    Ok(())
 }
 
 /// A File storage resolver
@@ -278,6 +335,51 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_put_leaves_no_temporary_file() -> anyhow::Result<()> {
+        let tempdir = tempdir()?;
+        let uri = Uri::try_new(&format!("{}", tempdir.path().display())).unwrap();
+        let file_storage = LocalFileStorage::from_uri(&uri)?;
+        let path = Path::new("foo/bar.json");
+        file_storage.put(path, Box::new(b"v1".to_vec())).await?;
+        file_storage.put(path, Box::new(b"v2".to_vec())).await?;
+        assert_eq!(file_storage.get_all(path).await?.as_slice(), b"v2");
+        let file_names: Vec<_> = std::fs::read_dir(tempdir.path().join("foo"))?
+            .map(|dir_entry| dir_entry.map(|dir_entry| dir_entry.file_name()))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(file_names, vec!["bar.json"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_prefix() -> anyhow::Result<()> {
+        let tempdir = tempdir()?;
+        let uri = Uri::try_new(&format!("{}", tempdir.path().display())).unwrap();
+        let file_storage = LocalFileStorage::from_uri(&uri)?;
+        for path in [
+            "2022/01/events-0.parquet",
+            "2022/events-1.parquet",
+            "events.json",
+        ] {
+            file_storage
+                .put(Path::new(path), Box::new(b"payload".to_vec()))
+                .await?;
+        }
+        assert_eq!(
+            file_storage.list_prefix(Path::new("2022")).await?,
+            [
+                PathBuf::from("2022/01/events-0.parquet"),
+                PathBuf::from("2022/events-1.parquet")
+            ]
+        );
+        assert_eq!(file_storage.list_prefix(Path::new("")).await?.len(), 3);
+        assert!(file_storage
+            .list_prefix(Path::new("2021"))
+            .await?
+            .is_empty());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_try_delete_dir_all() -> anyhow::Result<()> {
         let path_root = tempdir()?.into_path();
@@ -35,6 +35,7 @@ use futures::stream::StreamExt;
 use md5::Digest;
 use once_cell::sync::OnceCell;
 use quickwit_aws::retry::{retry, RetryParams, Retryable};
+use quickwit_common::fs::to_slash_path;
 use quickwit_common::uri::{Protocol, Uri};
 use quickwit_common::{chunk_range, into_u64_range};
 use regex::Regex;
@@ -152,7 +153,7 @@ impl AzureBlobStorage {
     /// Returns the blob name (a.k.a blob key).
     fn blob_name(&self, relative_path: &Path) -> String {
         let key_path = self.prefix.join(relative_path);
-        key_path.to_string_lossy().to_string()
+        to_slash_path(&key_path)
     }
 
     /// Downloads a blob as vector of bytes.
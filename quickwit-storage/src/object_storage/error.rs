@@ -22,7 +22,8 @@ use quickwit_aws::retry::Retryable;
 use rusoto_core::RusotoError;
 use rusoto_s3::{
     AbortMultipartUploadError, CompleteMultipartUploadError, CreateMultipartUploadError,
-    DeleteObjectError, GetObjectError, HeadObjectError, PutObjectError, UploadPartError,
+    DeleteObjectError, GetObjectError, HeadObjectError, ListObjectsV2Error, ListPartsError,
+    PutObjectError, UploadPartError,
 };
 
 use crate::{StorageError, StorageErrorKind};
@@ -82,6 +83,12 @@ impl ToStorageErrorKind for AbortMultipartUploadError {
     }
 }
 
+impl ToStorageErrorKind for ListPartsError {
+    fn to_storage_error_kind(&self) -> StorageErrorKind {
+        StorageErrorKind::Service
+    }
+}
+
 impl ToStorageErrorKind for CreateMultipartUploadError {
     fn to_storage_error_kind(&self) -> StorageErrorKind {
         StorageErrorKind::Service
@@ -99,3 +106,11 @@ impl ToStorageErrorKind for HeadObjectError {
         StorageErrorKind::Service
     }
 }
+
+impl ToStorageErrorKind for ListObjectsV2Error {
+    fn to_storage_error_kind(&self) -> StorageErrorKind {
+        match self {
+            ListObjectsV2Error::NoSuchBucket(_) => StorageErrorKind::DoesNotExist,
+        }
+    }
+}
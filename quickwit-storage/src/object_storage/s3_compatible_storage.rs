@@ -17,18 +17,21 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashMap;
 use std::fmt::{self, Debug};
 use std::io;
 use std::ops::Range;
 use std::path::{Path, PathBuf};
 
 use async_trait::async_trait;
+use futures::stream::{BoxStream, FuturesUnordered};
 use futures::{stream, StreamExt};
 use once_cell::sync::OnceCell;
 use quickwit_aws::error::RusotoErrorWrapper;
-use quickwit_aws::get_http_client;
 use quickwit_aws::region::sniff_aws_region_and_cache;
 use quickwit_aws::retry::{retry, Retry, RetryParams, Retryable};
+use quickwit_aws::{get_http_client, get_http_client_with_ca_cert};
+use quickwit_common::fs::to_slash_path;
 use quickwit_common::uri::Uri;
 use quickwit_common::{chunk_range, into_u64_range};
 use regex::Regex;
@@ -36,16 +39,18 @@ use rusoto_core::{ByteStream, Region, RusotoError};
 use rusoto_s3::{
     AbortMultipartUploadRequest, CompleteMultipartUploadRequest, CompletedMultipartUpload,
     CompletedPart, CreateMultipartUploadError, CreateMultipartUploadRequest, DeleteObjectRequest,
-    GetObjectRequest, HeadObjectError, HeadObjectRequest, ListObjectsV2Request, PutObjectError,
-    PutObjectRequest, S3Client, UploadPartRequest, S3,
+    GetObjectRequest, HeadObjectError, HeadObjectRequest, ListObjectsV2Request, ListPartsRequest,
+    Part as UploadedPart, PutObjectError, PutObjectRequest, S3Client, UploadPartRequest, S3,
 };
 use tokio::fs::File;
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
 use tracing::{instrument, warn};
 
 use crate::object_storage::MultiPartPolicy;
+use crate::split::ConcatPayload;
 use crate::{
-    OwnedBytes, Storage, StorageError, StorageErrorKind, StorageResolverError, StorageResult,
+    OwnedBytes, PutPayload, ResumableUploadState, Storage, StorageError, StorageErrorKind,
+    StorageResolverError, StorageResult,
 };
 
 /// S3 Compatible object storage implementation.
@@ -68,8 +73,16 @@ impl fmt::Debug for S3CompatibleObjectStorage {
     }
 }
 
+/// Environment variable pointing to a PEM file containing additional certificate authorities
+/// to trust when connecting to the object storage, for instance a self-signed MinIO or Ceph RGW
+/// deployment.
+const QW_S3_CA_CERT_PATH_ENV_KEY: &str = "QW_S3_CA_CERT_PATH";
+
 fn create_s3_client(region: Region) -> anyhow::Result<S3Client> {
-    let http_client = get_http_client();
+    let http_client = match std::env::var_os(QW_S3_CA_CERT_PATH_ENV_KEY) {
+        Some(ca_cert_path) => get_http_client_with_ca_cert(Path::new(&ca_cert_path))?,
+        None => get_http_client(),
+    };
     let credentials_provider = quickwit_aws::get_credentials_provider()?;
     Ok(S3Client::new_with(
         http_client,
@@ -204,7 +217,7 @@ async fn compute_md5<T: AsyncRead + std::marker::Unpin>(mut read: T) -> io::Resu
 impl S3CompatibleObjectStorage {
     fn key(&self, relative_path: &Path) -> String {
         let key_path = self.prefix.join(relative_path);
-        key_path.to_string_lossy().to_string()
+        to_slash_path(&key_path)
     }
 
     async fn put_single_part_single_try<'a>(
@@ -384,6 +397,114 @@ impl S3CompatibleObjectStorage {
         }
     }
 
+    /// Computes the checksum of a part of a streamed object and uploads it.
+    async fn upload_streamed_part(
+        &self,
+        upload_id: MultipartUploadId,
+        key: &str,
+        part_number: usize,
+        range: Range<u64>,
+        payload: Box<dyn crate::PutPayload>,
+    ) -> StorageResult<CompletedPart> {
+        let read = payload
+            .range_byte_stream(range.clone())
+            .await?
+            .into_async_read();
+        let md5 = compute_md5(read).await?;
+        let part = Part {
+            part_number,
+            range,
+            md5,
+        };
+        retry(&self.retry_params, || {
+            self.upload_part(upload_id.clone(), key, part.clone(), payload.clone())
+        })
+        .await
+        .map_err(Retry::into_inner)
+    }
+
+    /// Uploads the parts of a streamed object as soon as enough bytes are received, and completes
+    /// the multipart upload once the stream is exhausted.
+    ///
+    /// The length of the object is not known upfront, so its parts cannot be sized by the
+    /// multipart policy: a part is cut every time the multipart threshold is reached. Objects
+    /// smaller than the threshold are uploaded in a single part once the stream is exhausted.
+    async fn put_streamed_multi_part(
+        &self,
+        key: &str,
+        mut payload_stream: BoxStream<'static, io::Result<Box<dyn PutPayload>>>,
+        upload_id_opt: &mut Option<MultipartUploadId>,
+    ) -> StorageResult<()> {
+        let part_len = self.multipart_policy.multipart_threshold_num_bytes;
+        let max_concurrent_upload = self.multipart_policy.max_concurrent_upload();
+        let mut object_payload = ConcatPayload::default();
+        let mut next_part_start = 0u64;
+        let mut num_parts = 0;
+        let mut part_uploads = FuturesUnordered::new();
+        let mut completed_parts = Vec::new();
+        loop {
+            let accepts_parts = part_uploads.len() < max_concurrent_upload;
+            tokio::select! {
+                Some(completed_part_res) = part_uploads.next() => {
+                    completed_parts.push(completed_part_res?);
+                }
+                payload_res_opt = payload_stream.next(), if accepts_parts => {
+                    let payload = match payload_res_opt {
+                        Some(payload_res) => payload_res?,
+                        None => break,
+                    };
+                    object_payload.push(payload);
+                    while object_payload.len() - next_part_start >= part_len {
+                        let upload_id = match upload_id_opt {
+                            Some(upload_id) => upload_id.clone(),
+                            None => {
+                                let upload_id = self
+                                    .create_multipart_upload(key)
+                                    .await
+                                    .map_err(RusotoErrorWrapper::from)?;
+                                upload_id_opt.insert(upload_id).clone()
+                            }
+                        };
+                        let part_range = next_part_start..next_part_start + part_len;
+                        next_part_start = part_range.end;
+                        num_parts += 1; // parts are 1-indexed
+                        part_uploads.push(self.upload_streamed_part(
+                            upload_id,
+                            key,
+                            num_parts,
+                            part_range,
+                            Box::new(object_payload.clone()),
+                        ));
+                    }
+                }
+            }
+        }
+        let total_len = object_payload.len();
+        let upload_id = if let Some(upload_id) = upload_id_opt.clone() {
+            upload_id
+        } else {
+            return self
+                .put_single_part(key, Box::new(object_payload), total_len)
+                .await;
+        };
+        if next_part_start < total_len {
+            num_parts += 1;
+            part_uploads.push(self.upload_streamed_part(
+                upload_id.clone(),
+                key,
+                num_parts,
+                next_part_start..total_len,
+                Box::new(object_payload),
+            ));
+        }
+        while let Some(completed_part_res) = part_uploads.next().await {
+            completed_parts.push(completed_part_res?);
+        }
+        completed_parts.sort_by_key(|completed_part| completed_part.part_number);
+        self.complete_multipart_upload(key, completed_parts, &upload_id.0)
+            .await
+    }
+
     async fn complete_multipart_upload(
         &self,
         key: &str,
@@ -427,6 +548,138 @@ impl S3CompatibleObjectStorage {
         Ok(())
     }
 
+    /// Lists the parts already uploaded for a multipart upload, indexed by part number.
+    async fn list_uploaded_parts(
+        &self,
+        key: &str,
+        upload_id: &str,
+    ) -> StorageResult<HashMap<i64, UploadedPart>> {
+        let mut uploaded_parts = HashMap::new();
+        let mut part_number_marker = None;
+        loop {
+            let list_parts_req = ListPartsRequest {
+                bucket: self.bucket.clone(),
+                key: key.to_string(),
+                upload_id: upload_id.to_string(),
+                part_number_marker,
+                ..Default::default()
+            };
+            let list_parts_output = retry(&self.retry_params, || async {
+                self.s3_client
+                    .list_parts(list_parts_req.clone())
+                    .await
+                    .map_err(RusotoErrorWrapper::from)
+            })
+            .await?;
+            for uploaded_part in list_parts_output.parts.unwrap_or_default() {
+                if let Some(part_number) = uploaded_part.part_number {
+                    uploaded_parts.insert(part_number, uploaded_part);
+                }
+            }
+            part_number_marker = list_parts_output.next_part_number_marker;
+            if !list_parts_output.is_truncated.unwrap_or(false) || part_number_marker.is_none() {
+                return Ok(uploaded_parts);
+            }
+        }
+    }
+
+    /// Uploads an object with a multipart upload, resuming the upload described by
+    /// `upload_state` if it was started with the same part size.
+    ///
+    /// The parts that are already uploaded with the expected size are not uploaded again. If a
+    /// part fails to upload, the upload is not aborted: the parts uploaded successfully are kept
+    /// and `upload_state` identifies the upload to resume.
+    async fn put_resumable_multi_part(
+        &self,
+        key: &str,
+        payload: Box<dyn crate::PutPayload>,
+        part_len: u64,
+        total_len: u64,
+        upload_state: &mut ResumableUploadState,
+    ) -> StorageResult<()> {
+        let mut uploaded_parts = HashMap::new();
+        if let Some(upload_id) = upload_state.upload_id.take() {
+            let list_parts_res = if upload_state.part_num_bytes == part_len {
+                self.list_uploaded_parts(key, &upload_id).await
+            } else {
+                Err(StorageErrorKind::InternalError.with_error(anyhow::anyhow!(
+                    "The part size changed from {} to {} bytes.",
+                    upload_state.part_num_bytes,
+                    part_len
+                )))
+            };
+            match list_parts_res {
+                Ok(parts) => {
+                    uploaded_parts = parts;
+                    upload_state.upload_id = Some(upload_id);
+                }
+                Err(error) => {
+                    warn!(
+                        key = %key,
+                        upload_id = %upload_id,
+                        error = ?error,
+                        "Failed to resume multipart upload, starting a new one."
+                    );
+                    if let Err(abort_error) = self.abort_multipart_upload(key, &upload_id).await {
+                        warn!(
+                            key = %key,
+                            error = ?abort_error,
+                            "Failed to abort multipart upload."
+                        );
+                    }
+                }
+            }
+        }
+        let upload_id = match &upload_state.upload_id {
+            Some(upload_id) => MultipartUploadId(upload_id.clone()),
+            None => {
+                let upload_id = self
+                    .create_multipart_upload(key)
+                    .await
+                    .map_err(RusotoErrorWrapper::from)?;
+                *upload_state = ResumableUploadState {
+                    upload_id: Some(upload_id.0.clone()),
+                    part_num_bytes: part_len,
+                };
+                upload_id
+            }
+        };
+        let parts = self
+            .create_multipart_requests(payload.clone(), total_len, part_len)
+            .await?;
+        let max_concurrent_upload = self.multipart_policy.max_concurrent_upload();
+        let completed_parts: Vec<CompletedPart> = stream::iter(parts.into_iter().map(|part| {
+            let uploaded_part_opt = uploaded_parts
+                .get(&(part.part_number as i64))
+                .filter(|uploaded_part| uploaded_part.size == Some(part.len() as i64))
+                .and_then(|uploaded_part| uploaded_part.e_tag.clone());
+            let payload = payload.clone();
+            let upload_id = upload_id.clone();
+            async move {
+                if let Some(e_tag) = uploaded_part_opt {
+                    return Ok(CompletedPart {
+                        e_tag: Some(e_tag),
+                        part_number: Some(part.part_number as i64),
+                    });
+                }
+                retry(&self.retry_params, || {
+                    self.upload_part(upload_id.clone(), key, part.clone(), payload.clone())
+                })
+                .await
+            }
+        }))
+        .buffered(max_concurrent_upload)
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .map(|res| res.map_err(|e| e.into_inner()))
+        .collect::<StorageResult<_>>()?;
+        self.complete_multipart_upload(key, completed_parts, &upload_id.0)
+            .await?;
+        *upload_state = ResumableUploadState::default();
+        Ok(())
+    }
+
     fn create_get_object_request(
         &self,
         path: &Path,
@@ -512,6 +765,60 @@ impl Storage for S3CompatibleObjectStorage {
         Ok(())
     }
 
+    async fn put_streamed(
+        &self,
+        path: &Path,
+        payload_stream: BoxStream<'static, io::Result<Box<dyn PutPayload>>>,
+    ) -> StorageResult<()> {
+        crate::STORAGE_METRICS.object_storage_put_total.inc();
+        let key = self.key(path);
+        let mut upload_id_opt = None;
+        let put_result = self
+            .put_streamed_multi_part(&key, payload_stream, &mut upload_id_opt)
+            .await;
+        if let (Err(_), Some(upload_id)) = (&put_result, upload_id_opt) {
+            if let Err(abort_error) = self.abort_multipart_upload(&key, &upload_id.0).await {
+                warn!(
+                    key = %key,
+                    error = ?abort_error,
+                    "Failed to abort multipart upload."
+                );
+            }
+        }
+        put_result
+    }
+
+    async fn put_resumable(
+        &self,
+        path: &Path,
+        payload: Box<dyn crate::PutPayload>,
+        upload_state: &mut ResumableUploadState,
+    ) -> StorageResult<()> {
+        crate::STORAGE_METRICS.object_storage_put_total.inc();
+        let key = self.key(path);
+        let total_len = payload.len();
+        let part_num_bytes = self.multipart_policy.part_num_bytes(total_len);
+        if part_num_bytes >= total_len {
+            self.put_single_part(&key, payload, total_len).await?;
+        } else {
+            self.put_resumable_multi_part(&key, payload, part_num_bytes, total_len, upload_state)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn abort_resumable_upload(
+        &self,
+        path: &Path,
+        upload_state: &ResumableUploadState,
+    ) -> StorageResult<()> {
+        if let Some(upload_id) = &upload_state.upload_id {
+            let key = self.key(path);
+            self.abort_multipart_upload(&key, upload_id).await?;
+        }
+        Ok(())
+    }
+
     // TODO implement multipart
     async fn copy_to_file(&self, path: &Path, output_path: &Path) -> StorageResult<()> {
         let get_object_req = self.create_get_object_request(path, None);
@@ -629,6 +936,44 @@ impl Storage for S3CompatibleObjectStorage {
         }
     }
 
+    async fn list_prefix(&self, prefix: &Path) -> StorageResult<Vec<PathBuf>> {
+        let mut key_prefix = self.key(prefix);
+        if !key_prefix.is_empty() && !key_prefix.ends_with('/') {
+            key_prefix.push('/');
+        }
+        let mut paths = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let list_objects_req = ListObjectsV2Request {
+                bucket: self.bucket.clone(),
+                prefix: Some(key_prefix.clone()),
+                continuation_token: continuation_token.take(),
+                ..Default::default()
+            };
+            let list_objects_output = retry(&self.retry_params, || async {
+                self.s3_client
+                    .list_objects_v2(list_objects_req.clone())
+                    .await
+                    .map_err(RusotoErrorWrapper::from)
+            })
+            .await?;
+            for object in list_objects_output.contents.unwrap_or_default() {
+                if let Some(path) = object.key.as_ref().and_then(|key| {
+                    Path::new(key)
+                        .strip_prefix(&self.prefix)
+                        .map(Path::to_path_buf)
+                        .ok()
+                }) {
+                    paths.push(path);
+                }
+            }
+            continuation_token = list_objects_output.next_continuation_token;
+            if continuation_token.is_none() {
+                return Ok(paths);
+            }
+        }
+    }
+
     fn uri(&self) -> &Uri {
         &self.uri
     }
@@ -17,14 +17,16 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::io;
 use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use futures::stream::BoxStream;
 use quickwit_common::uri::Uri;
 
-use crate::{OwnedBytes, Storage};
+use crate::{OwnedBytes, ResumableUploadState, Storage};
 
 /// This storage acts as a proxy to another storage that simply modifies each API call
 /// by preceding each path with a given a prefix.
@@ -48,6 +50,37 @@ impl Storage for PrefixStorage {
         self.storage.put(&self.prefix.join(path), payload).await
     }
 
+    async fn put_resumable(
+        &self,
+        path: &Path,
+        payload: Box<dyn crate::PutPayload>,
+        upload_state: &mut ResumableUploadState,
+    ) -> crate::StorageResult<()> {
+        self.storage
+            .put_resumable(&self.prefix.join(path), payload, upload_state)
+            .await
+    }
+
+    async fn abort_resumable_upload(
+        &self,
+        path: &Path,
+        upload_state: &ResumableUploadState,
+    ) -> crate::StorageResult<()> {
+        self.storage
+            .abort_resumable_upload(&self.prefix.join(path), upload_state)
+            .await
+    }
+
+    async fn put_streamed(
+        &self,
+        path: &Path,
+        payload_stream: BoxStream<'static, io::Result<Box<dyn crate::PutPayload>>>,
+    ) -> crate::StorageResult<()> {
+        self.storage
+            .put_streamed(&self.prefix.join(path), payload_stream)
+            .await
+    }
+
     async fn copy_to_file(&self, path: &Path, output_path: &Path) -> crate::StorageResult<()> {
         self.storage
             .copy_to_file(&self.prefix.join(path), output_path)
@@ -81,6 +114,14 @@ impl Storage for PrefixStorage {
     async fn file_num_bytes(&self, path: &Path) -> crate::StorageResult<u64> {
         self.storage.file_num_bytes(&self.prefix.join(path)).await
     }
+
+    async fn list_prefix(&self, prefix: &Path) -> crate::StorageResult<Vec<PathBuf>> {
+        let paths = self.storage.list_prefix(&self.prefix.join(prefix)).await?;
+        Ok(paths
+            .into_iter()
+            .flat_map(|path| path.strip_prefix(&self.prefix).map(Path::to_path_buf).ok())
+            .collect())
+    }
 }
 
 /// Creates a [`PrefixStorage`] using an underlying storage and a prefix.
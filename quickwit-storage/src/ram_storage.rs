@@ -141,6 +141,19 @@ impl Storage for RamStorage {
             Err(StorageErrorKind::DoesNotExist.with_error(err))
         }
     }
+
+    async fn list_prefix(&self, prefix: &Path) -> StorageResult<Vec<PathBuf>> {
+        let mut paths: Vec<PathBuf> = self
+            .files
+            .read()
+            .await
+            .keys()
+            .filter(|path| path.starts_with(prefix))
+            .cloned()
+            .collect();
+        paths.sort();
+        Ok(paths)
+    }
 }
 
 /// Builder to create a prepopulated [`RamStorage`]. This is mostly useful for tests.
@@ -247,4 +260,29 @@ mod tests {
         );
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_ram_storage_list_prefix() -> anyhow::Result<()> {
+        let ram_storage_factory = RamStorageFactory::default();
+        let storage = ram_storage_factory.resolve(&Uri::new("ram:///exports".to_string()))?;
+        storage
+            .put(Path::new("2022/events-0.parquet"), Box::new(b"0".to_vec()))
+            .await?;
+        storage
+            .put(Path::new("2022/events-1.parquet"), Box::new(b"1".to_vec()))
+            .await?;
+        storage
+            .put(Path::new("2021/events-0.parquet"), Box::new(b"2".to_vec()))
+            .await?;
+        assert_eq!(
+            storage.list_prefix(Path::new("2022")).await?,
+            [
+                PathBuf::from("2022/events-0.parquet"),
+                PathBuf::from("2022/events-1.parquet")
+            ]
+        );
+        assert_eq!(storage.list_prefix(Path::new("")).await?.len(), 3);
+        assert!(storage.list_prefix(Path::new("202")).await?.is_empty());
+        Ok(())
+    }
 }
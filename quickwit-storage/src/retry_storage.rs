@@ -0,0 +1,275 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::future::Future;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use quickwit_aws::retry::{retry, Retry, RetryParams};
+use quickwit_common::uri::Uri;
+
+use crate::{
+    OwnedBytes, PutPayload, ResumableUploadState, Storage, StorageError, StorageErrorKind,
+    StorageResult,
+};
+
+/// Timeout and retry policy applied to the requests issued against a storage.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StorageRetryPolicy {
+    /// Timeout of the requests transferring no payload or a small one.
+    pub request_timeout: Duration,
+    /// Throughput below which a request transferring a payload of known size times out. The
+    /// timeout of such a request is extended by the time required to transfer its payload at
+    /// this throughput.
+    pub min_throughput_bytes_per_sec: u64,
+    /// Number of times a request failing with a transient error is retried.
+    pub max_retries: usize,
+    /// Base delay of the exponential backoff between two attempts.
+    pub base_backoff: Duration,
+    /// Maximum delay between two attempts.
+    pub max_backoff: Duration,
+}
+
+impl StorageRetryPolicy {
+    fn timeout(&self, num_bytes: u64) -> Duration {
+        let transfer_secs = num_bytes / self.min_throughput_bytes_per_sec.max(1);
+        self.request_timeout + Duration::from_secs(transfer_secs)
+    }
+
+    fn retry_params(&self) -> RetryParams {
+        RetryParams {
+            base_delay: self.base_backoff,
+            max_delay: self.max_backoff,
+            max_attempts: self.max_retries + 1,
+        }
+    }
+}
+
+/// This storage acts as a proxy to another storage and times out and retries the requests
+/// issued through it according to a [`StorageRetryPolicy`].
+///
+/// Only I/O and internal errors are retried. `get_all` and `copy_to_file` requests are not
+/// timed out, as the size of the file they download is not known upfront.
+struct RetryStorage {
+    storage: Arc<dyn Storage>,
+    policy: StorageRetryPolicy,
+}
+
+impl RetryStorage {
+    async fn retry<T, F, Fut>(&self, timeout_opt: Option<Duration>, f: F) -> StorageResult<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = StorageResult<T>>,
+    {
+        retry(&self.policy.retry_params(), || async {
+            let result = if let Some(timeout) = timeout_opt {
+                tokio::time::timeout(timeout, f())
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(StorageErrorKind::Io.with_error(anyhow::anyhow!(
+                            "Storage request timed out after {:?}.",
+                            timeout
+                        )))
+                    })
+            } else {
+                f().await
+            };
+            result.map_err(|error: StorageError| match error.kind() {
+                StorageErrorKind::Io | StorageErrorKind::InternalError => Retry::Transient(error),
+                _ => Retry::Permanent(error),
+            })
+        })
+        .await
+        .map_err(Retry::into_inner)
+    }
+}
+
+#[async_trait]
+impl Storage for RetryStorage {
+    async fn check_connectivity(&self) -> anyhow::Result<()> {
+        self.storage.check_connectivity().await
+    }
+
+    async fn put(&self, path: &Path, payload: Box<dyn PutPayload>) -> StorageResult<()> {
+        let timeout = self.policy.timeout(payload.len());
+        self.retry(Some(timeout), || self.storage.put(path, payload.clone()))
+            .await
+    }
+
+    /// Streamed files are neither timed out nor retried, as their payloads are consumed by the
+    /// first attempt.
+    async fn put_streamed(
+        &self,
+        path: &Path,
+        payload_stream: BoxStream<'static, io::Result<Box<dyn PutPayload>>>,
+    ) -> StorageResult<()> {
+        self.storage.put_streamed(path, payload_stream).await
+    }
+
+    /// Resumable uploads are timed out but not retried: the caller is expected to retry them with
+    /// the upload state, so that only the missing parts are uploaded again.
+    async fn put_resumable(
+        &self,
+        path: &Path,
+        payload: Box<dyn PutPayload>,
+        upload_state: &mut ResumableUploadState,
+    ) -> StorageResult<()> {
+        let timeout = self.policy.timeout(payload.len());
+        tokio::time::timeout(
+            timeout,
+            self.storage.put_resumable(path, payload, upload_state),
+        )
+        .await
+        .unwrap_or_else(|_| {
+            Err(StorageErrorKind::Io.with_error(anyhow::anyhow!(
+                "Storage request timed out after {:?}.",
+                timeout
+            )))
+        })
+    }
+
+    async fn abort_resumable_upload(
+        &self,
+        path: &Path,
+        upload_state: &ResumableUploadState,
+    ) -> StorageResult<()> {
+        self.retry(None, || {
+            self.storage.abort_resumable_upload(path, upload_state)
+        })
+        .await
+    }
+
+    async fn copy_to_file(&self, path: &Path, output_path: &Path) -> StorageResult<()> {
+        self.retry(None, || self.storage.copy_to_file(path, output_path))
+            .await
+    }
+
+    async fn get_slice(&self, path: &Path, range: Range<usize>) -> StorageResult<OwnedBytes> {
+        let timeout = self.policy.timeout(range.len() as u64);
+        self.retry(Some(timeout), || {
+            self.storage.get_slice(path, range.clone())
+        })
+        .await
+    }
+
+    async fn get_all(&self, path: &Path) -> StorageResult<OwnedBytes> {
+        self.retry(None, || self.storage.get_all(path)).await
+    }
+
+    async fn delete(&self, path: &Path) -> StorageResult<()> {
+        let timeout = self.policy.request_timeout;
+        self.retry(Some(timeout), || self.storage.delete(path))
+            .await
+    }
+
+    async fn exists(&self, path: &Path) -> StorageResult<bool> {
+        let timeout = self.policy.request_timeout;
+        self.retry(Some(timeout), || self.storage.exists(path))
+            .await
+    }
+
+    async fn file_num_bytes(&self, path: &Path) -> StorageResult<u64> {
+        let timeout = self.policy.request_timeout;
+        self.retry(Some(timeout), || self.storage.file_num_bytes(path))
+            .await
+    }
+
+    async fn list_prefix(&self, prefix: &Path) -> StorageResult<Vec<PathBuf>> {
+        let timeout = self.policy.request_timeout;
+        self.retry(Some(timeout), || self.storage.list_prefix(prefix))
+            .await
+    }
+
+    fn uri(&self) -> &Uri {
+        self.storage.uri()
+    }
+}
+
+/// Wraps a storage so that the requests issued through it are timed out and retried according
+/// to `policy`.
+pub fn wrap_storage_with_retry_policy(
+    storage: Arc<dyn Storage>,
+    policy: StorageRetryPolicy,
+) -> Arc<dyn Storage> {
+    Arc::new(RetryStorage { storage, policy })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::MockStorage;
+
+    fn policy_for_test(max_retries: usize) -> StorageRetryPolicy {
+        StorageRetryPolicy {
+            request_timeout: Duration::from_millis(50),
+            min_throughput_bytes_per_sec: 1_000,
+            max_retries,
+            base_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+        }
+    }
+
+    #[test]
+    fn test_storage_retry_policy_timeout() {
+        let policy = policy_for_test(0);
+        assert_eq!(policy.timeout(0), Duration::from_millis(50));
+        assert_eq!(policy.timeout(5_500), Duration::from_millis(5_050));
+    }
+
+    #[tokio::test]
+    async fn test_retry_storage_retries_transient_errors() {
+        let num_attempts = Arc::new(AtomicUsize::new(0));
+        let num_attempts_clone = num_attempts.clone();
+        let mut mock_storage = MockStorage::default();
+        mock_storage.expect_get_all().times(3).returning(move |_| {
+            if num_attempts_clone.fetch_add(1, Ordering::SeqCst) < 2 {
+                Err(StorageErrorKind::Io.with_error(anyhow::anyhow!("Connection reset.")))
+            } else {
+                Ok(OwnedBytes::new(b"hello".to_vec()))
+            }
+        });
+        let storage = wrap_storage_with_retry_policy(Arc::new(mock_storage), policy_for_test(2));
+        let bytes = storage.get_all(Path::new("foo")).await.unwrap();
+        assert_eq!(bytes.as_slice(), b"hello");
+        assert_eq!(num_attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_storage_gives_up() {
+        let mut mock_storage = MockStorage::default();
+        mock_storage.expect_delete().times(3).returning(|_| {
+            Err(StorageErrorKind::InternalError.with_error(anyhow::anyhow!("Internal error.")))
+        });
+        mock_storage.expect_exists().times(1).returning(|_| {
+            Err(StorageErrorKind::Unauthorized.with_error(anyhow::anyhow!("Access denied.")))
+        });
+        let storage = wrap_storage_with_retry_policy(Arc::new(mock_storage), policy_for_test(2));
+        let delete_error = storage.delete(Path::new("foo")).await.unwrap_err();
+        assert_eq!(delete_error.kind(), StorageErrorKind::InternalError);
+        let exists_error = storage.exists(Path::new("foo")).await.unwrap_err();
+        assert_eq!(exists_error.kind(), StorageErrorKind::Unauthorized);
+    }
+}
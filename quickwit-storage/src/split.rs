@@ -61,6 +61,17 @@ async fn range_byte_stream_from_payloads(
     Ok(concat_stream)
 }
 
+impl SplitPayload {
+    /// Returns the payload of the footer (hotcache + bundle metadata), which comes last in the
+    /// split.
+    pub fn footer_payload(&self) -> Box<dyn PutPayload> {
+        self.payloads
+            .last()
+            .expect("The split payload should always end with its footer.")
+            .clone()
+    }
+}
+
 #[async_trait]
 impl PutPayload for SplitPayload {
     fn len(&self) -> u64 {
@@ -72,6 +83,33 @@ impl PutPayload for SplitPayload {
     }
 }
 
+/// Payload made of the concatenation of several payloads.
+#[derive(Clone, Default)]
+pub(crate) struct ConcatPayload {
+    payloads: Vec<Box<dyn PutPayload>>,
+}
+
+impl ConcatPayload {
+    pub fn new(payloads: Vec<Box<dyn PutPayload>>) -> Self {
+        Self { payloads }
+    }
+
+    pub fn push(&mut self, payload: Box<dyn PutPayload>) {
+        self.payloads.push(payload);
+    }
+}
+
+#[async_trait]
+impl PutPayload for ConcatPayload {
+    fn len(&self) -> u64 {
+        self.payloads.iter().map(|payload| payload.len()).sum()
+    }
+
+    async fn range_byte_stream(&self, range: Range<u64>) -> io::Result<ByteStream> {
+        range_byte_stream_from_payloads(&self.payloads, range).await
+    }
+}
+
 #[derive(Clone)]
 struct FilePayload {
     len: u64,
@@ -118,16 +156,20 @@ impl SplitPayloadBuilder {
         Ok(offsets)
     }
 
-    /// Adds the file to the bundle file.
+    /// Adds the file to the bundle file and returns its payload, which can be uploaded before the
+    /// bundle file is finalized.
     ///
     /// The hotcache needs to be the last file that is added, in order to be able to read
     /// the hotcache and the metadata in one continous read.
-    pub fn add_file(&mut self, path: &Path) -> io::Result<()> {
+    pub fn add_file(&mut self, path: &Path) -> io::Result<Box<dyn PutPayload>> {
         let file = std::fs::metadata(path)?;
         let file_range = self.current_offset as u64..self.current_offset as u64 + file.len() as u64;
         self.current_offset += file.len() as usize;
         self.metadata.files.insert(path.to_owned(), file_range);
-        Ok(())
+        Ok(Box::new(FilePayload {
+            path: path.to_owned(),
+            len: file.len(),
+        }))
     }
 
     /// Writes the bundle file offsets metadata at the end of the bundle file,
@@ -159,7 +201,7 @@ impl SplitPayloadBuilder {
         let metadata_json_len = metadata_json.len() as u64;
         footer_bytes.extend(&metadata_json_len.to_le_bytes());
         footer_bytes.extend(hotcache);
-        footer_bytes.extend(&hotcache.len().to_le_bytes());
+        footer_bytes.extend(&(hotcache.len() as u64).to_le_bytes());
 
         let mut payloads: Vec<Box<dyn PutPayload>> = Vec::new();
 
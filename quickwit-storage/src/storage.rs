@@ -17,14 +17,39 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::io;
 use std::ops::Range;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use async_trait::async_trait;
+use futures::stream::BoxStream;
+use futures::TryStreamExt;
 use quickwit_common::uri::Uri;
+use serde::{Deserialize, Serialize};
 
+use crate::split::ConcatPayload;
 use crate::{OwnedBytes, PutPayload, StorageErrorKind, StorageResult};
 
+/// State of an upload started by [`Storage::put_resumable`] that did not complete.
+///
+/// Persisting it allows a later attempt to resume the upload instead of starting over, for the
+/// storages that support it. An empty state means there is nothing to resume.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ResumableUploadState {
+    /// ID of the multipart upload in progress.
+    pub upload_id: Option<String>,
+    /// Size of the parts of the multipart upload in progress. An upload can only be resumed with
+    /// the same part size, since parts are identified by their number.
+    pub part_num_bytes: u64,
+}
+
+impl ResumableUploadState {
+    /// Returns true if there is no upload to resume.
+    pub fn is_empty(&self) -> bool {
+        self.upload_id.is_none()
+    }
+}
+
 /// Storage meant to receive and serve quickwit's split.
 ///
 /// Object storage are the primary target implementation of this trait,
@@ -45,6 +70,49 @@ pub trait Storage: Send + Sync + 'static {
     /// Saves a file into the storage.
     async fn put(&self, path: &Path, payload: Box<dyn PutPayload>) -> StorageResult<()>;
 
+    /// Saves a file whose content is streamed as a sequence of payloads, so that the upload can
+    /// start before the whole file is available.
+    ///
+    /// The file is only created once the stream is exhausted. If the stream yields an error, the
+    /// upload is aborted and nothing is written.
+    ///
+    /// The default implementation waits for the end of the stream and saves the concatenation of
+    /// the payloads with `put`.
+    async fn put_streamed(
+        &self,
+        path: &Path,
+        payload_stream: BoxStream<'static, io::Result<Box<dyn PutPayload>>>,
+    ) -> StorageResult<()> {
+        let payloads: Vec<Box<dyn PutPayload>> = payload_stream.try_collect().await?;
+        self.put(path, Box::new(ConcatPayload::new(payloads))).await
+    }
+
+    /// Saves a file into the storage, resuming the upload described by `upload_state` if any.
+    ///
+    /// On failure, `upload_state` is updated with what was uploaded so far, so that a later call
+    /// with the same payload only uploads the missing parts. On success, it is reset.
+    ///
+    /// The default implementation does not support resuming uploads and calls `put`.
+    async fn put_resumable(
+        &self,
+        path: &Path,
+        payload: Box<dyn PutPayload>,
+        _upload_state: &mut ResumableUploadState,
+    ) -> StorageResult<()> {
+        self.put(path, payload).await
+    }
+
+    /// Aborts the upload described by `upload_state`, releasing the parts already uploaded.
+    ///
+    /// The default implementation does nothing.
+    async fn abort_resumable_upload(
+        &self,
+        _path: &Path,
+        _upload_state: &ResumableUploadState,
+    ) -> StorageResult<()> {
+        Ok(())
+    }
+
     /// Downloads an entire file and writes it into a local file.
     /// `output_path` is expected to be a file path (not a directory path).
     /// TODO Change the API to support multipart download
@@ -74,6 +142,18 @@ pub trait Storage: Send + Sync + 'static {
     /// Returns a file size.
     async fn file_num_bytes(&self, path: &Path) -> StorageResult<u64>;
 
+    /// Lists the paths of the files stored under `prefix`, which is treated as a directory. An
+    /// empty prefix lists all the files of the storage.
+    ///
+    /// The default implementation fails, for the storages that do not support listing files.
+    async fn list_prefix(&self, prefix: &Path) -> StorageResult<Vec<PathBuf>> {
+        Err(StorageErrorKind::Service.with_error(anyhow::anyhow!(
+            "Storage `{}` does not support listing the files under `{}`.",
+            self.uri(),
+            prefix.display()
+        )))
+    }
+
     /// Returns an URI identifying the storage
     fn uri(&self) -> &Uri;
 }